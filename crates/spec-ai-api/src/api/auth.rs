@@ -4,15 +4,19 @@
 //! - User credential management (loaded from JSON file)
 //! - Password verification using PBKDF2-HMAC-SHA256
 //! - Bearer token generation and validation using HMAC-SHA256
+//! - Short-lived tokens scoped to specific endpoints, minted by agents for the
+//!   processes and remote tools they start, audited and revocable
 
-use anyhow::{Context, Result};
+use crate::persistence::{Persistence, TokenAuditRecord};
+use crate::tools::builtin::{ScopedToken, ScopedTokenRequest, TokenMinter};
+use anyhow::{bail, Context, Result};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use ring::{hmac, pbkdf2, rand as ring_rand};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// Number of PBKDF2 iterations for password hashing
 const PBKDF2_ITERATIONS: u32 = 100_000;
@@ -26,6 +30,12 @@ const CREDENTIAL_LENGTH: usize = 32;
 /// Token validity duration default (24 hours in seconds)
 const DEFAULT_TOKEN_EXPIRY_SECS: u64 = 86400;
 
+/// Longest a scoped token may live, whatever its minter asks for
+const MAX_SCOPED_TOKEN_TTL_SECS: u64 = 3600;
+
+/// Routes that mint, revoke and audit tokens, never open to scoped tokens
+const AUTH_ROUTES: &str = "/auth";
+
 /// A user credential stored in the credentials file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserCredential {
@@ -46,6 +56,88 @@ struct TokenPayload {
     pub exp: u64,
     /// Unique token ID
     pub jti: String,
+    /// Endpoints a scoped token may call; unrestricted when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Vec<String>>,
+}
+
+/// What a valid token grants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenClaims {
+    /// User the token was issued to, or the agent that minted it
+    pub subject: String,
+    pub token_id: String,
+    /// Endpoints a scoped token may call; `None` for full tokens
+    pub scope: Option<Vec<String>>,
+}
+
+impl TokenClaims {
+    /// Whether the token may call `method path`
+    pub fn permits(&self, method: &str, path: &str) -> bool {
+        match &self.scope {
+            Some(endpoints) => endpoints
+                .iter()
+                .any(|pattern| endpoint_matches(pattern, method, path)),
+            None => true,
+        }
+    }
+}
+
+/// Match a "METHOD /path" or "/path" pattern, where a trailing `*` matches
+/// any suffix of the path
+fn endpoint_matches(pattern: &str, method: &str, path: &str) -> bool {
+    let (pattern_method, pattern_path) = match pattern.trim().split_once(' ') {
+        Some((method, path)) => (Some(method), path.trim()),
+        None => (None, pattern.trim()),
+    };
+    if let Some(expected) = pattern_method {
+        if expected != "*" && !expected.eq_ignore_ascii_case(method) {
+            return false;
+        }
+    }
+    match pattern_path.strip_suffix('*') {
+        Some(prefix) => path.starts_with(prefix),
+        None => path == pattern_path,
+    }
+}
+
+/// Whether `path` is one of the token management routes
+pub fn is_auth_route(path: &str) -> bool {
+    path.strip_prefix(AUTH_ROUTES)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Check that a scoped token's endpoint pattern names a route, and that no
+/// route under `/auth` can match it
+fn validate_endpoint(pattern: &str) -> Result<()> {
+    let path = match pattern.trim().split_once(' ') {
+        Some((_, path)) => path.trim(),
+        None => pattern.trim(),
+    };
+    if !path.starts_with('/') {
+        bail!("Endpoint '{}' must name a path starting with /", pattern);
+    }
+    let reaches_auth = match path.strip_suffix('*') {
+        Some("/") => bail!("Endpoint '{}' would match every route", pattern),
+        Some(prefix) => is_auth_route(prefix) || format!("{}/", AUTH_ROUTES).starts_with(prefix),
+        None => is_auth_route(path),
+    };
+    if reaches_auth {
+        bail!(
+            "Endpoint '{}' reaches the {} routes, which scoped tokens may not call",
+            pattern,
+            AUTH_ROUTES
+        );
+    }
+    Ok(())
+}
+
+/// A scoped token an agent minted
+#[derive(Debug, Clone)]
+struct MintedToken {
+    /// Agent that minted it, the only agent allowed to revoke it
+    issuer: String,
+    expires_at: u64,
 }
 
 /// Authentication service that manages credentials and tokens
@@ -59,6 +151,12 @@ pub struct AuthService {
     token_expiry_secs: u64,
     /// Whether auth is enabled
     enabled: bool,
+    /// Scoped tokens minted by agents, by ID
+    minted: Arc<Mutex<HashMap<String, MintedToken>>>,
+    /// Revoked token IDs, kept until the tokens would have expired
+    revoked: Arc<Mutex<HashMap<String, u64>>>,
+    /// Where minting and revoking are recorded
+    audit: Option<Persistence>,
 }
 
 impl std::fmt::Debug for AuthService {
@@ -67,6 +165,7 @@ impl std::fmt::Debug for AuthService {
             .field("credentials_count", &self.credentials.len())
             .field("token_expiry_secs", &self.token_expiry_secs)
             .field("enabled", &self.enabled)
+            .field("audited", &self.audit.is_some())
             .finish()
     }
 }
@@ -106,9 +205,29 @@ impl AuthService {
             signing_key: Arc::new(signing_key),
             token_expiry_secs: token_expiry_secs.unwrap_or(DEFAULT_TOKEN_EXPIRY_SECS),
             enabled,
+            minted: Arc::default(),
+            revoked: Arc::default(),
+            audit: None,
         })
     }
 
+    /// Record scoped tokens in `persistence`, and honour revocations recorded
+    /// there before a restart
+    pub fn with_audit_log(mut self, persistence: Persistence) -> Self {
+        match persistence.revoked_token_ids() {
+            Ok(ids) => {
+                let until = unix_now() + self.longest_token_life();
+                let mut revoked = self.revoked.lock().unwrap();
+                for id in ids {
+                    revoked.insert(id, until);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to load revoked tokens: {}", e),
+        }
+        self.audit = Some(persistence);
+        self
+    }
+
     /// Create a disabled AuthService (no authentication required)
     pub fn disabled() -> Self {
         Self {
@@ -116,6 +235,9 @@ impl AuthService {
             signing_key: Arc::new(hmac::Key::new(hmac::HMAC_SHA256, b"disabled-auth-not-used")),
             token_expiry_secs: DEFAULT_TOKEN_EXPIRY_SECS,
             enabled: false,
+            minted: Arc::default(),
+            revoked: Arc::default(),
+            audit: None,
         }
     }
 
@@ -183,10 +305,124 @@ impl AuthService {
             iat: now,
             exp: now + self.token_expiry_secs,
             jti: uuid::Uuid::new_v4().to_string(),
+            scope: None,
+        };
+        self.sign(&payload)
+    }
+
+    /// Mint a token that may only call `request.endpoints`, for at most
+    /// `request.ttl_secs` and never longer than an ordinary token
+    pub fn mint_scoped_token(&self, request: &ScopedTokenRequest) -> Result<ScopedToken> {
+        if !self.enabled {
+            bail!("Authentication is disabled, so API requests need no token");
+        }
+        if request.endpoints.is_empty() {
+            bail!("A scoped token needs at least one endpoint");
+        }
+        if request.ttl_secs == 0 {
+            bail!("A scoped token needs a TTL of at least one second");
+        }
+        for endpoint in &request.endpoints {
+            validate_endpoint(endpoint)?;
+        }
+
+        let ttl = request
+            .ttl_secs
+            .min(MAX_SCOPED_TOKEN_TTL_SECS)
+            .min(self.token_expiry_secs);
+        let now = unix_now();
+        let payload = TokenPayload {
+            sub: request.issuer.clone(),
+            iat: now,
+            exp: now + ttl,
+            jti: uuid::Uuid::new_v4().to_string(),
+            scope: Some(request.endpoints.clone()),
+        };
+
+        // A token that was not audited is never handed out
+        self.record(
+            "minted",
+            &payload,
+            &request.issuer,
+            request.session_id.clone(),
+        )?;
+        {
+            let mut minted = self.minted.lock().unwrap();
+            minted.retain(|_, token| token.expires_at >= now);
+            minted.insert(
+                payload.jti.clone(),
+                MintedToken {
+                    issuer: request.issuer.clone(),
+                    expires_at: payload.exp,
+                },
+            );
+        }
+
+        Ok(ScopedToken {
+            token: self.sign(&payload)?,
+            token_id: payload.jti,
+            endpoints: request.endpoints.clone(),
+            expires_in: ttl,
+        })
+    }
+
+    /// Revoke any token by ID; false if it was already revoked
+    pub fn revoke_token(&self, token_id: &str, actor: &str) -> Result<bool> {
+        let now = unix_now();
+        let until = match self.minted.lock().unwrap().get(token_id) {
+            Some(token) => token.expires_at,
+            // Unknown tokens may be full tokens, so outlive the longest of them
+            None => now + self.longest_token_life(),
         };
+        {
+            let mut revoked = self.revoked.lock().unwrap();
+            revoked.retain(|_, exp| *exp >= now);
+            if revoked.insert(token_id.to_string(), until).is_some() {
+                return Ok(false);
+            }
+        }
 
+        let payload = TokenPayload {
+            sub: actor.to_string(),
+            iat: now,
+            exp: until,
+            jti: token_id.to_string(),
+            scope: None,
+        };
+        self.record("revoked", &payload, actor, None)?;
+        Ok(true)
+    }
+
+    fn longest_token_life(&self) -> u64 {
+        self.token_expiry_secs.max(MAX_SCOPED_TOKEN_TTL_SECS)
+    }
+
+    fn record(
+        &self,
+        event: &str,
+        payload: &TokenPayload,
+        actor: &str,
+        session_id: Option<String>,
+    ) -> Result<()> {
+        let Some(audit) = &self.audit else {
+            return Ok(());
+        };
+        audit
+            .record_token_event(&TokenAuditRecord {
+                token_id: payload.jti.clone(),
+                event: event.to_string(),
+                actor: actor.to_string(),
+                session_id,
+                scope: payload.scope.clone().unwrap_or_default(),
+                expires_at: payload.exp as i64,
+                created_at: chrono::Utc::now(),
+            })
+            .context("Failed to record token in the audit log")
+    }
+
+    fn sign(&self, payload: &TokenPayload) -> Result<String> {
         // Serialize payload to JSON
-        let payload_json = serde_json::to_string(&payload)?;
+        let payload_json = serde_json::to_string(payload)?;
         let payload_b64 = URL_SAFE_NO_PAD.encode(payload_json.as_bytes());
 
         // Sign the payload
@@ -199,6 +435,11 @@ impl AuthService {
 
     /// Validate a bearer token and return the username if valid
     pub fn validate_token(&self, token: &str) -> Option<String> {
+        self.validate_claims(token).map(|claims| claims.subject)
+    }
+
+    /// Validate a bearer token and return what it grants
+    pub fn validate_claims(&self, token: &str) -> Option<TokenClaims> {
         let parts: Vec<&str> = token.split('.').collect();
         if parts.len() != 2 {
             return None;
@@ -235,7 +476,15 @@ impl AuthService {
             return None;
         }
 
-        Some(payload.sub)
+        if self.revoked.lock().unwrap().contains_key(&payload.jti) {
+            return None;
+        }
+
+        Some(TokenClaims {
+            subject: payload.sub,
+            token_id: payload.jti,
+            scope: payload.scope,
+        })
     }
 
     /// Hash a password for storage
@@ -267,6 +516,33 @@ impl AuthService {
     }
 }
 
+impl TokenMinter for AuthService {
+    fn mint(&self, request: &ScopedTokenRequest) -> Result<ScopedToken> {
+        self.mint_scoped_token(request)
+    }
+
+    /// Agents may only revoke the tokens they minted
+    fn revoke(&self, token_id: &str, actor: &str) -> Result<bool> {
+        let minted_by_actor = self
+            .minted
+            .lock()
+            .unwrap()
+            .get(token_id)
+            .is_some_and(|token| token.issuer == actor);
+        if !minted_by_actor {
+            return Ok(false);
+        }
+        self.revoke_token(token_id, actor)
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
 /// Request body for token generation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenRequest {
@@ -365,6 +641,122 @@ mod tests {
         assert!(!auth.is_enabled());
     }
 
+    fn scoped_request(endpoints: &[&str], ttl_secs: u64) -> ScopedTokenRequest {
+        ScopedTokenRequest {
+            issuer: "coder".to_string(),
+            session_id: Some("s1".to_string()),
+            endpoints: endpoints.iter().map(|e| e.to_string()).collect(),
+            ttl_secs,
+        }
+    }
+
+    #[test]
+    fn scoped_tokens_only_reach_their_endpoints() {
+        let auth = AuthService::new(None, Some("test_secret"), Some(600), true).unwrap();
+        let minted = auth
+            .mint_scoped_token(&scoped_request(&["GET /graph/*", "/agents"], 86400))
+            .unwrap();
+        assert_eq!(minted.expires_in, 600);
+
+        let claims = auth.validate_claims(&minted.token).unwrap();
+        assert_eq!(claims.subject, "coder");
+        assert_eq!(claims.token_id, minted.token_id);
+        assert!(claims.permits("GET", "/graph/nodes/1"));
+        assert!(claims.permits("POST", "/agents"));
+        assert!(!claims.permits("DELETE", "/graph/nodes/1"));
+        assert!(!claims.permits("POST", "/query"));
+
+        // Full tokens are unrestricted
+        let full = auth.generate_token("admin").unwrap();
+        assert!(auth
+            .validate_claims(&full)
+            .unwrap()
+            .permits("POST", "/query"));
+
+        assert!(auth.mint_scoped_token(&scoped_request(&[], 60)).is_err());
+        for endpoints in [
+            &["*"][..],
+            &["/*"],
+            &["GET /*"],
+            &["graph/nodes"],
+            &["DELETE /auth/*"],
+            &["/auth/tokens/t1"],
+            &["/au*"],
+            &["POST /query", "/auth"],
+        ] {
+            assert!(
+                auth.mint_scoped_token(&scoped_request(endpoints, 60))
+                    .is_err(),
+                "{:?} was minted",
+                endpoints
+            );
+        }
+        assert!(auth
+            .mint_scoped_token(&scoped_request(&["GET /authors/*"], 60))
+            .is_ok());
+        assert!(is_auth_route("/auth/tokens/t1"));
+        assert!(!is_auth_route("/authors"));
+        assert!(AuthService::disabled()
+            .mint_scoped_token(&scoped_request(&["/agents"], 60))
+            .is_err());
+    }
+
+    #[test]
+    fn revoked_tokens_are_rejected_and_audited() {
+        let persistence = Persistence::in_memory().unwrap();
+        let auth = AuthService::new(None, Some("test_secret"), Some(3600), true)
+            .unwrap()
+            .with_audit_log(persistence.clone());
+        let minted = auth.mint(&scoped_request(&["POST /query"], 60)).unwrap();
+        let full = auth.generate_token("admin").unwrap();
+        let full_id = auth.validate_claims(&full).unwrap().token_id;
+
+        // Agents can only revoke what they minted; operators can revoke anything
+        assert!(!auth.revoke(&full_id, "coder").unwrap());
+        assert!(auth.revoke(&minted.token_id, "coder").unwrap());
+        assert!(!auth.revoke(&minted.token_id, "coder").unwrap());
+        assert!(auth.validate_token(&minted.token).is_none());
+        assert!(auth.revoke_token(&full_id, "admin").unwrap());
+        assert!(auth.validate_token(&full).is_none());
+
+        let events = persistence.list_token_events(10).unwrap();
+        let summary: Vec<(&str, &str)> = events
+            .iter()
+            .map(|e| (e.event.as_str(), e.actor.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("revoked", "admin"),
+                ("revoked", "coder"),
+                ("minted", "coder")
+            ]
+        );
+        assert_eq!(events[2].scope, vec!["POST /query"]);
+
+        // Revocations survive a restart with the same secret
+        let restarted = AuthService::new(None, Some("test_secret"), Some(3600), true)
+            .unwrap()
+            .with_audit_log(persistence);
+        assert!(restarted.validate_token(&full).is_none());
+    }
+
+    #[test]
+    fn agents_cannot_revoke_tokens_minted_by_others() {
+        let persistence = Persistence::in_memory().unwrap();
+        let auth = AuthService::new(None, Some("test_secret"), Some(3600), true)
+            .unwrap()
+            .with_audit_log(persistence.clone());
+        let minted = auth.mint(&scoped_request(&["POST /query"], 60)).unwrap();
+
+        assert!(!auth.revoke(&minted.token_id, "reviewer").unwrap());
+        assert!(auth.validate_token(&minted.token).is_some());
+        assert_eq!(persistence.list_token_events(10).unwrap().len(), 1);
+
+        assert!(auth.revoke(&minted.token_id, "coder").unwrap());
+        assert!(auth.validate_token(&minted.token).is_none());
+    }
+
     #[test]
     fn test_token_tampering() {
         let auth = AuthService::new(None, Some("test_secret"), Some(3600), true).unwrap();
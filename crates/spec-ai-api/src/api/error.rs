@@ -0,0 +1,81 @@
+//! HTTP responses for library errors
+//!
+//! Every handler that fails with an `anyhow::Error` answers through
+//! [`error_response`], so clients see the same stable codes and statuses
+//! regardless of which endpoint failed.
+
+use crate::api::models::ErrorResponse;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use spec_ai_core::error::{code_of, ErrorCode};
+
+/// HTTP status for an error code
+pub fn status_for(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::Conflict => StatusCode::CONFLICT,
+        ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+        ErrorCode::PolicyDenied => StatusCode::FORBIDDEN,
+        ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        ErrorCode::ProviderUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::Provider | ErrorCode::Network => StatusCode::BAD_GATEWAY,
+        ErrorCode::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        ErrorCode::Config
+        | ErrorCode::Database
+        | ErrorCode::Tool
+        | ErrorCode::Plugin
+        | ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Code for an error, or `fallback` when nothing in its chain is classified
+///
+/// The fallback lets a handler say what kind of operation failed, such as
+/// [`ErrorCode::Database`] for graph queries.
+pub fn code_or(err: &anyhow::Error, fallback: ErrorCode) -> ErrorCode {
+    match code_of(err) {
+        ErrorCode::Internal => fallback,
+        code => code,
+    }
+}
+
+/// Error response with the status and code for a library error
+pub fn error_response(err: &anyhow::Error, fallback: ErrorCode) -> Response {
+    let code = code_or(err, fallback);
+    (status_for(code), Json(ErrorResponse::from_error(code, err))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spec_ai_core::error::Error;
+
+    #[test]
+    fn classified_errors_keep_their_code() {
+        let err = anyhow::Error::from(Error::Provider {
+            provider: "Anthropic".to_string(),
+            status: 429,
+            message: "slow down".to_string(),
+        });
+        let response = error_response(&err, ErrorCode::Internal);
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let body = ErrorResponse::from_error(code_or(&err, ErrorCode::Internal), &err);
+        assert_eq!(body.code, "rate_limited");
+        assert!(body.retryable);
+    }
+
+    #[test]
+    fn unclassified_errors_use_the_fallback() {
+        let err = anyhow::anyhow!("table is locked");
+        assert_eq!(code_or(&err, ErrorCode::Database), ErrorCode::Database);
+        assert_eq!(
+            error_response(&err, ErrorCode::Database).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
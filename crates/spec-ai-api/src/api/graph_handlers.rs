@@ -2,6 +2,7 @@
 ///
 /// These endpoints expose the knowledge graph as a generic key-value store
 /// with nodes and edges. Clients interpret the data in domain-specific ways.
+use crate::api::error::error_response;
 use crate::api::handlers::AppState;
 use crate::api::models::ErrorResponse;
 use axum::{
@@ -18,6 +19,7 @@ use serde_json::Value as JsonValue;
 use spec_ai_core::bootstrap_self::plugin::BootstrapPlugin;
 use spec_ai_core::bootstrap_self::plugin::{BootstrapMode, PluginContext};
 use spec_ai_core::bootstrap_self::plugins::universal_code::UniversalCodePlugin;
+use spec_ai_core::error::{Error, ErrorCode};
 use spec_ai_knowledge_graph::{EdgeType, NodeType};
 use std::convert::Infallible;
 use std::time::Duration;
@@ -189,11 +191,7 @@ pub async fn list_nodes(
             })
             .into_response()
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
-        )
-            .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -212,14 +210,13 @@ pub async fn get_node(State(state): State<AppState>, Path(node_id): Path<i64>) -
         .into_response(),
         Ok(None) => (
             StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new("not_found", "Node not found")),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
+            Json(ErrorResponse::new(
+                ErrorCode::NotFound.as_str(),
+                "Node not found",
+            )),
         )
             .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -260,11 +257,7 @@ pub async fn create_node(
                     .into_response(),
             }
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
-        )
-            .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -279,17 +272,14 @@ pub async fn update_node(
         Ok(None) => {
             return (
                 StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new("not_found", "Node not found")),
-            )
-                .into_response()
-        }
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new("database_error", e.to_string())),
+                Json(ErrorResponse::new(
+                    ErrorCode::NotFound.as_str(),
+                    "Node not found",
+                )),
             )
                 .into_response()
         }
+        Err(e) => return error_response(&e, ErrorCode::Database),
         Ok(Some(_)) => {}
     }
 
@@ -313,11 +303,7 @@ pub async fn update_node(
                 _ => StatusCode::NO_CONTENT.into_response(),
             }
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
-        )
-            .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -325,11 +311,7 @@ pub async fn update_node(
 pub async fn delete_node(State(state): State<AppState>, Path(node_id): Path<i64>) -> Response {
     match state.persistence.delete_graph_node(node_id) {
         Ok(()) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
-        )
-            .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -369,11 +351,7 @@ pub async fn list_edges(
             })
             .into_response()
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
-        )
-            .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -394,14 +372,13 @@ pub async fn get_edge(State(state): State<AppState>, Path(edge_id): Path<i64>) -
         .into_response(),
         Ok(None) => (
             StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new("not_found", "Edge not found")),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
+            Json(ErrorResponse::new(
+                ErrorCode::NotFound.as_str(),
+                "Edge not found",
+            )),
         )
             .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -446,11 +423,7 @@ pub async fn create_edge(
                     .into_response(),
             }
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
-        )
-            .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -458,11 +431,7 @@ pub async fn create_edge(
 pub async fn delete_edge(State(state): State<AppState>, Path(edge_id): Path<i64>) -> Response {
     match state.persistence.delete_graph_edge(edge_id) {
         Ok(()) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("database_error", e.to_string())),
-        )
-            .into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
     }
 }
 
@@ -555,14 +524,10 @@ pub async fn bootstrap_graph(
     let cwd = match std::env::current_dir() {
         Ok(path) => path,
         Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    "cwd_error",
-                    format!("Failed to get current directory: {}", e),
-                )),
+            return error_response(
+                &anyhow::Error::from(e).context("Failed to get current directory"),
+                ErrorCode::Internal,
             )
-                .into_response()
         }
     };
 
@@ -580,14 +545,13 @@ pub async fn bootstrap_graph(
     let plugin = UniversalCodePlugin;
 
     if !plugin.should_activate(&cwd) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(
-                "not_a_repository",
-                "Current directory does not appear to be a code repository",
-            )),
-        )
-            .into_response();
+        return error_response(
+            &Error::InvalidRequest(
+                "Current directory does not appear to be a code repository".to_string(),
+            )
+            .into(),
+            ErrorCode::InvalidRequest,
+        );
     }
 
     match plugin.run(context) {
@@ -610,11 +574,7 @@ pub async fn bootstrap_graph(
         }
         Err(e) => {
             tracing::error!("Bootstrap failed: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new("bootstrap_error", e.to_string())),
-            )
-                .into_response()
+            error_response(&e, ErrorCode::Internal)
         }
     }
 }
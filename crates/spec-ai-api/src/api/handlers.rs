@@ -1,15 +1,26 @@
 /// API request handlers
-use crate::agent::builder::AgentBuilder;
+use crate::agent::builder::{create_embeddings_client_from_config, AgentBuilder};
 use crate::agent::core::AgentCore;
+use crate::agent::{RunBudget, UserAnswer};
 use crate::api::auth::{AuthService, TokenRequest, TokenResponse};
+use crate::api::error::error_response;
+use crate::api::mcp::McpSessions;
 use crate::api::mesh::{MeshRegistry, MeshState};
+use crate::api::middleware::AuthenticatedUser;
+use crate::api::mirror::MirrorHub;
 use crate::api::models::*;
-use crate::config::{AgentRegistry, AppConfig};
+use crate::api::runs::RunRegistry;
+use crate::config::{AgentRegistry, AppConfig, GenerationPreset};
+use crate::embeddings::EmbeddingsClient;
+use crate::heartbeat::Liveness;
 use crate::persistence::Persistence;
+use crate::policy::PolicyCapsule;
+use crate::presence::{PresenceAnnouncement, PresenceSnapshot, SessionPresence, TurnQueue};
+use crate::tools::builtin::ApiTokenTool;
 use crate::tools::ToolRegistry;
 use async_stream::stream;
 use axum::{
-    extract::{Json, State},
+    extract::{Extension, Json, Path as UrlPath, Query, State},
     http::StatusCode,
     response::{
         sse::{Event, Sse},
@@ -18,6 +29,7 @@ use axum::{
 };
 use futures::StreamExt;
 use serde_json::json;
+use spec_ai_core::error::{code_of, Error, ErrorCode};
 use std::convert::Infallible;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -28,6 +40,8 @@ use tokio::sync::RwLock;
 const DEFAULT_PAGE_SIZE: usize = 10;
 const MAX_PAGE_SIZE: usize = 25;
 const MAX_TOTAL_RESULTS: usize = 100;
+const DEFAULT_AUDIT_LIMIT: i64 = 100;
+const MAX_AUDIT_LIMIT: i64 = 1000;
 
 /// Shared application state
 #[derive(Clone)]
@@ -39,6 +53,20 @@ pub struct AppState {
     pub start_time: Instant,
     pub mesh_registry: MeshRegistry,
     pub auth_service: Arc<AuthService>,
+    /// Request and error counters published by the heartbeat
+    pub liveness: Liveness,
+    /// Runs in progress, cancellable with `DELETE /runs/{run_id}`
+    pub runs: RunRegistry,
+    /// Shared by every agent, so concurrent requests batch their embeddings
+    pub embeddings: Option<EmbeddingsClient>,
+    /// TUI sessions mirrored to read-only viewers
+    pub mirrors: MirrorHub,
+    /// Open MCP connections, answered on their event streams
+    pub mcp_sessions: McpSessions,
+    /// Who is in each session, for clients sharing one
+    pub presence: SessionPresence,
+    /// Messages to the same session, answered one at a time in arrival order
+    pub turns: TurnQueue,
 }
 
 impl AppState {
@@ -55,19 +83,47 @@ impl AppState {
             Some(config.auth.token_expiry_secs),
             config.auth.enabled,
         )
+        .map(|auth| auth.with_audit_log(persistence.clone()))
         .unwrap_or_else(|e| {
             tracing::warn!("Failed to initialize auth service: {}. Auth disabled.", e);
             AuthService::disabled()
         });
+        let auth_service = Arc::new(auth_service);
+
+        // Agents mint scoped tokens for their tools instead of passing on the
+        // operator's, which only matters when requests need a token at all
+        let tool_registry = if auth_service.is_enabled() {
+            let mut tools = (*tool_registry).clone();
+            tools.register(Arc::new(ApiTokenTool::new(auth_service.clone())));
+            Arc::new(tools)
+        } else {
+            tool_registry
+        };
+
+        let mesh_registry = MeshRegistry::with_persistence(persistence.clone())
+            .with_bandwidth(config.mesh.bandwidth.clone())
+            .with_max_clock_skew(config.mesh.max_clock_skew_ms);
+
+        let embeddings = create_embeddings_client_from_config(&config).unwrap_or_else(|e| {
+            tracing::warn!("Failed to initialize embeddings client: {}", e);
+            None
+        });
 
         Self {
-            persistence: persistence.clone(),
+            persistence,
             agent_registry,
             tool_registry,
             config,
             start_time: Instant::now(),
-            mesh_registry: MeshRegistry::with_persistence(persistence),
-            auth_service: Arc::new(auth_service),
+            mesh_registry,
+            auth_service,
+            liveness: Liveness::new(),
+            runs: RunRegistry::new(),
+            embeddings,
+            mirrors: MirrorHub::new(),
+            mcp_sessions: McpSessions::new(),
+            presence: SessionPresence::default(),
+            turns: TurnQueue::new(),
         }
     }
 }
@@ -94,6 +150,10 @@ pub async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
         version: env!("CARGO_PKG_VERSION").to_string(),
         uptime_seconds: uptime,
         active_sessions,
+        embedding_batches: state
+            .embeddings
+            .as_ref()
+            .and_then(EmbeddingsClient::batch_stats),
     };
 
     Json(response)
@@ -101,34 +161,47 @@ pub async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
 
 /// List available agents
 pub async fn list_agents(State(state): State<AppState>) -> impl IntoResponse {
+    Json(AgentListResponse {
+        agents: agent_infos(&state),
+    })
+    .into_response()
+}
+
+/// Every configured agent, as listed by `GET /agents`
+pub(crate) fn agent_infos(state: &AppState) -> Vec<AgentInfo> {
     let agent_names = state.agent_registry.list();
     let mut agent_infos = Vec::new();
 
     for name in agent_names {
         if let Some(profile) = state.agent_registry.get(&name) {
+            let model = profile.effective_model(&state.config.model);
             agent_infos.push(AgentInfo {
                 id: name,
                 description: profile.prompt.unwrap_or_default(),
                 allowed_tools: profile.allowed_tools.unwrap_or_default(),
                 denied_tools: profile.denied_tools.unwrap_or_default(),
+                model_provider: model.provider,
+                model_name: model.model_name,
+                policy_mode: profile.policy_mode,
             });
         }
     }
 
-    Json(AgentListResponse {
-        agents: agent_infos,
-    })
-    .into_response()
+    agent_infos
 }
 
 /// Query endpoint - process a message and return response
-pub async fn query(State(state): State<AppState>, Json(request): Json<QueryRequest>) -> Response {
+pub async fn query(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthenticatedUser>>,
+    Json(request): Json<QueryRequest>,
+) -> Response {
     // If streaming requested, delegate to streaming handler
     if request.stream {
         return (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse::new(
-                "invalid_request",
+                ErrorCode::InvalidRequest.as_str(),
                 "Streaming not supported on /query endpoint. Use /stream instead.",
             )),
         )
@@ -136,7 +209,8 @@ pub async fn query(State(state): State<AppState>, Json(request): Json<QueryReque
     }
 
     // Determine which agent to use
-    let agent_name = request.agent.unwrap_or_else(|| "default".to_string());
+    let agent_name = agent_name(&state, request.agent);
+    let author = request_author(user, request.author);
 
     // Get or create session ID
     let session_id = request
@@ -144,18 +218,41 @@ pub async fn query(State(state): State<AppState>, Json(request): Json<QueryReque
         .unwrap_or_else(|| format!("api_{}", uuid_v4()));
 
     // Create agent instance
-    let agent_result = create_agent(&state, &agent_name, &session_id, request.temperature).await;
-
+    let agent_result = create_agent(
+        &state,
+        &agent_name,
+        &session_id,
+        request.temperature,
+        request.policy_capsule,
+        request.budget,
+        request.preset.as_deref(),
+    )
+    .await;
+
+    let run_id = request
+        .run_id
+        .unwrap_or_else(|| format!("run-{}", uuid_v4()));
     let mut agent = match agent_result {
-        Ok(agent) => agent,
+        Ok(agent) => agent.with_run_id(run_id.clone()),
         Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("agent_error", e.to_string())),
-            )
-                .into_response();
+            return error_response(&e, ErrorCode::InvalidRequest);
         }
     };
+    let run = match state.runs.register(&run_id, agent.interrupt_handle()) {
+        Ok(run) => run,
+        Err(e) => return error_response(&e.into(), ErrorCode::InvalidRequest),
+    };
+    // Clients poll GET /runs/{run_id}/question for questions
+    agent.set_user_questions(Some(run.user_questions(None)));
+    agent.set_author(author.clone());
+
+    // Wait for earlier messages to the session to be answered
+    let _in_flight = state.liveness.begin(&session_id);
+    let mut presence = state
+        .presence
+        .join(&session_id, author_name(&author), API_CLIENT);
+    let turn = state.turns.enqueue(&session_id).wait().await;
+    presence.start();
 
     // Process the message
     let start = Instant::now();
@@ -172,6 +269,7 @@ pub async fn query(State(state): State<AppState>, Json(request): Json<QueryReque
                     success: inv.success,
                     output: inv.output.clone(),
                     error: inv.error.clone(),
+                    data: inv.data.clone(),
                 })
                 .collect();
 
@@ -180,65 +278,119 @@ pub async fn query(State(state): State<AppState>, Json(request): Json<QueryReque
                 session_id,
                 agent: agent_name,
                 tool_calls,
+                budget_exceeded: output.budget_exceeded,
                 metadata: ResponseMetadata {
                     timestamp: current_timestamp(),
-                    model: state.config.model.provider.clone(),
+                    model: agent
+                        .profile()
+                        .effective_model(&state.config.model)
+                        .provider,
                     processing_time_ms: processing_time,
                     run_id: output.run_id,
+                    author,
+                    turn: Some(turn.number()),
                 },
             };
 
             Json(response).into_response()
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("execution_error", e.to_string())),
-        )
-            .into_response(),
+        Err(e) => {
+            state.liveness.record_error(&e);
+            error_response(&e, ErrorCode::Internal)
+        }
     }
 }
 
 /// Streaming query endpoint
 pub async fn stream_query(
     State(state): State<AppState>,
+    user: Option<Extension<AuthenticatedUser>>,
     Json(request): Json<QueryRequest>,
 ) -> Response {
-    let agent_name = request.agent.unwrap_or_else(|| "default".to_string());
+    let agent_name = agent_name(&state, request.agent);
+    let author = request_author(user, request.author);
     let session_id = request
         .session_id
         .unwrap_or_else(|| format!("api_{}", uuid_v4()));
 
     // Create agent
-    let agent_result = create_agent(&state, &agent_name, &session_id, request.temperature).await;
-
-    let agent = match agent_result {
-        Ok(agent) => agent,
+    let agent_result = create_agent(
+        &state,
+        &agent_name,
+        &session_id,
+        request.temperature,
+        request.policy_capsule,
+        request.budget,
+        request.preset.as_deref(),
+    )
+    .await;
+
+    let run_id = request
+        .run_id
+        .unwrap_or_else(|| format!("run-{}", uuid_v4()));
+    let mut agent = match agent_result {
+        Ok(agent) => agent.with_run_id(run_id.clone()),
         Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("agent_error", e.to_string())),
-            )
-                .into_response();
+            return error_response(&e, ErrorCode::InvalidRequest);
         }
     };
+    let run = match state.runs.register(&run_id, agent.interrupt_handle()) {
+        Ok(run) => run,
+        Err(e) => return error_response(&e.into(), ErrorCode::InvalidRequest),
+    };
+    let (asked_tx, mut asked) = tokio::sync::mpsc::unbounded_channel();
+    agent.set_user_questions(Some(run.user_questions(Some(asked_tx))));
+    agent.set_author(author.clone());
 
     // Create SSE stream
+    let model_id = agent
+        .profile()
+        .effective_model(&state.config.model)
+        .provider;
     let agent = Arc::new(RwLock::new(agent));
     let message = request.message.clone();
     let session_id_clone = session_id.clone();
     let agent_name_clone = agent_name.clone();
-    let model_id = state.config.model.provider.clone();
+    let liveness = state.liveness.clone();
+    let in_flight = liveness.begin(&session_id);
+    let mut presence = state
+        .presence
+        .join(&session_id, author_name(&author), API_CLIENT);
+    let ticket = state.turns.enqueue(&session_id);
 
     let sse_stream = stream! {
+        // Held until the stream finishes or the client goes away
+        let _in_flight = in_flight;
+        let _run = run;
         yield StreamChunk::Start {
             session_id: session_id_clone.clone(),
             agent: agent_name_clone.clone(),
+            run_id,
         };
 
+        // Wait for earlier messages to the session to be answered
+        let ahead = ticket.ahead();
+        if ahead > 0 {
+            yield StreamChunk::Queued { turn: ticket.number(), ahead };
+        }
+        let turn = ticket.wait().await;
+        presence.start();
+
         let start = Instant::now();
         let mut agent_lock = agent.write().await;
 
-        match agent_lock.run_step(&message).await {
+        // Tell the client about questions while the step waits for answers
+        let step = agent_lock.run_step(&message);
+        tokio::pin!(step);
+        let result = loop {
+            let question = tokio::select! {
+                result = &mut step => break result,
+                Some(question) = asked.recv() => question,
+            };
+            yield StreamChunk::Question { question };
+        };
+
+        match result {
             Ok(output) => {
                 yield StreamChunk::Content { text: output.response.clone() };
 
@@ -253,6 +405,7 @@ pub async fn stream_query(
                             "success": invocation.success,
                             "output": invocation.output,
                             "error": invocation.error,
+                            "data": invocation.data,
                         }),
                     };
                 }
@@ -263,12 +416,19 @@ pub async fn stream_query(
                         model: model_id.clone(),
                         processing_time_ms: start.elapsed().as_millis() as u64,
                         run_id: output.run_id,
+                        author: author.clone(),
+                        turn: Some(turn.number()),
                     },
+                    budget_exceeded: output.budget_exceeded,
                 };
             }
             Err(e) => {
+                liveness.record_error(&e);
+                let code = code_of(&e);
                 yield StreamChunk::Error {
                     message: e.to_string(),
+                    code: code.as_str().to_string(),
+                    retryable: code.is_retryable(),
                 };
             }
         }
@@ -281,34 +441,172 @@ pub async fn stream_query(
     .into_response()
 }
 
+/// Interrupt a query or stream in progress
+///
+/// The run ends with whatever the agent had produced, marked as interrupted,
+/// and the session can be continued with another query.
+pub async fn cancel_run(
+    State(state): State<AppState>,
+    UrlPath(run_id): UrlPath<String>,
+) -> Response {
+    if state.runs.cancel(&run_id) {
+        (
+            StatusCode::ACCEPTED,
+            Json(json!({ "run_id": run_id, "status": "interrupting" })),
+        )
+            .into_response()
+    } else {
+        let err = Error::NotFound {
+            kind: "Run",
+            id: run_id,
+        };
+        error_response(&err.into(), ErrorCode::NotFound)
+    }
+}
+
+/// The question a run is waiting on
+///
+/// Answers `204 No Content` while the run has not asked anything.
+pub async fn get_run_question(
+    State(state): State<AppState>,
+    UrlPath(run_id): UrlPath<String>,
+) -> Response {
+    match state.runs.question(&run_id) {
+        Ok(Some(question)) => {
+            Json(json!({ "run_id": run_id, "question": question })).into_response()
+        }
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(&e.into(), ErrorCode::NotFound),
+    }
+}
+
+/// Answer the question a run is waiting on, so the run can continue
+pub async fn answer_run_question(
+    State(state): State<AppState>,
+    UrlPath(run_id): UrlPath<String>,
+    Json(answer): Json<UserAnswer>,
+) -> Response {
+    match state.runs.answer(&run_id, answer) {
+        Ok(()) => Json(json!({ "run_id": run_id, "status": "answered" })).into_response(),
+        Err(e) => error_response(&e.into(), ErrorCode::InvalidRequest),
+    }
+}
+
+/// Who is in a session: authors of queued and running messages, and clients
+/// that announced themselves in the last half minute
+pub async fn get_session_presence(
+    State(state): State<AppState>,
+    UrlPath(session_id): UrlPath<String>,
+) -> Response {
+    Json(presence_snapshot(&state, session_id)).into_response()
+}
+
+/// Announce a client in a session, answering with everyone present
+///
+/// With auth enabled the client is listed under its token's user.
+pub async fn announce_presence(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthenticatedUser>>,
+    UrlPath(session_id): UrlPath<String>,
+    Json(mut announcement): Json<PresenceAnnouncement>,
+) -> Response {
+    if let Some(Extension(user)) = user {
+        announcement.name = user.username;
+    }
+    state.presence.announce(&session_id, &announcement);
+    Json(presence_snapshot(&state, session_id)).into_response()
+}
+
+fn presence_snapshot(state: &AppState, session_id: String) -> PresenceSnapshot {
+    PresenceSnapshot {
+        participants: state.presence.list(&session_id),
+        pending_turns: state.turns.len(&session_id),
+        session_id,
+    }
+}
+
+/// Listed as the client of queries and streams in session presence
+const API_CLIENT: &str = "api";
+
+/// Author of a message: the token's user with auth enabled, else the name the
+/// request gives, if any
+fn request_author(
+    user: Option<Extension<AuthenticatedUser>>,
+    requested: Option<String>,
+) -> Option<String> {
+    match user {
+        Some(Extension(user)) => Some(user.username),
+        None => requested.filter(|name| !name.trim().is_empty()),
+    }
+}
+
+/// Presence name of clients that did not say who they are
+pub(crate) const ANONYMOUS: &str = "anonymous";
+
+fn author_name(author: &Option<String>) -> &str {
+    author.as_deref().unwrap_or(ANONYMOUS)
+}
+
 /// Helper: Create agent instance
-async fn create_agent(
+/// Profile a request asked for, else the server's default agent
+pub(crate) fn agent_name(state: &AppState, requested: Option<String>) -> String {
+    requested
+        .or_else(|| state.config.default_agent.clone())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+pub(crate) async fn create_agent(
     state: &AppState,
     agent_name: &str,
     session_id: &str,
     _temperature: Option<f32>,
+    policy_capsule: Option<PolicyCapsule>,
+    budget: Option<RunBudget>,
+    preset: Option<&str>,
 ) -> anyhow::Result<AgentCore> {
     // Get the agent profile
     let profile = state
         .agent_registry
         .get(agent_name)
-        .ok_or_else(|| anyhow::anyhow!("Agent '{}' not found", agent_name))?;
+        .ok_or_else(|| Error::NotFound {
+            kind: "Agent",
+            id: agent_name.to_string(),
+        })?;
 
     // Build the agent using the builder with config
-    let agent = AgentBuilder::new()
+    let mut builder = AgentBuilder::new()
         .with_profile(profile)
         .with_config(state.config.clone())
         .with_session_id(session_id)
         .with_agent_name(agent_name.to_string())
         .with_tool_registry(state.tool_registry.clone())
-        .with_persistence(state.persistence.clone())
-        .build()?;
+        .with_persistence(state.persistence.clone());
+    if let Some(embeddings) = &state.embeddings {
+        builder = builder.with_embeddings_client(embeddings.clone());
+    }
+    let mut agent = builder.build()?;
+    if let Some(budget) = budget {
+        agent.set_run_budget(agent.run_budget().overridden_by(&budget));
+    }
+    if let Some(name) = preset {
+        let preset = GenerationPreset::resolve(name, &state.config.presets).ok_or_else(|| {
+            Error::NotFound {
+                kind: "Preset",
+                id: name.to_string(),
+            }
+        })?;
+        agent.set_preset(Some((name.to_string(), preset)));
+    }
 
-    Ok(agent)
+    // Delegated work runs within what the delegating peer may do
+    Ok(match policy_capsule {
+        Some(capsule) => agent.with_policy_capsule(capsule),
+        None => agent,
+    })
 }
 
 /// Helper: Generate UUID v4
-fn uuid_v4() -> String {
+pub(crate) fn uuid_v4() -> String {
     let rng = std::collections::hash_map::RandomState::new();
     let hash = std::hash::BuildHasher::hash_one(&rng, SystemTime::now());
     format!("{:x}", hash)
@@ -325,6 +623,14 @@ fn current_timestamp() -> String {
         .to_rfc3339()
 }
 
+/// Response for authentication endpoints on a server without authentication
+fn auth_disabled() -> Response {
+    error_response(
+        &Error::Config("Authentication is not enabled on this server".to_string()).into(),
+        ErrorCode::Config,
+    )
+}
+
 /// Token generation endpoint - exchange username/password for bearer token
 pub async fn generate_token(
     State(state): State<AppState>,
@@ -332,14 +638,7 @@ pub async fn generate_token(
 ) -> Response {
     // Check if auth is enabled
     if !state.auth_service.is_enabled() {
-        return (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(ErrorResponse::new(
-                "auth_disabled",
-                "Authentication is not enabled on this server",
-            )),
-        )
-            .into_response();
+        return auth_disabled();
     }
 
     // Verify credentials
@@ -347,14 +646,10 @@ pub async fn generate_token(
         .auth_service
         .verify_password(&request.username, &request.password)
     {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(ErrorResponse::new(
-                "invalid_credentials",
-                "Invalid username or password",
-            )),
-        )
-            .into_response();
+        return error_response(
+            &Error::Unauthorized("Invalid username or password".to_string()).into(),
+            ErrorCode::Unauthorized,
+        );
     }
 
     // Generate token
@@ -369,18 +664,45 @@ pub async fn generate_token(
         }
         Err(e) => {
             tracing::error!("Failed to generate token: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    "token_error",
-                    "Failed to generate token",
-                )),
-            )
-                .into_response()
+            error_response(&e.context("Failed to generate token"), ErrorCode::Internal)
         }
     }
 }
 
+/// Revoke a token by ID, such as a scoped token an agent minted
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthenticatedUser>>,
+    UrlPath(token_id): UrlPath<String>,
+) -> Response {
+    if !state.auth_service.is_enabled() {
+        return auth_disabled();
+    }
+
+    let actor = user
+        .map(|Extension(user)| user.username)
+        .unwrap_or_else(|| "operator".to_string());
+    match state.auth_service.revoke_token(&token_id, &actor) {
+        Ok(revoked) => Json(json!({ "token_id": token_id, "revoked": revoked })).into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
+    }
+}
+
+/// Audit log of scoped tokens, newest first
+pub async fn token_audit(
+    State(state): State<AppState>,
+    Query(query): Query<TokenAuditQuery>,
+) -> Response {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, MAX_AUDIT_LIMIT);
+    match state.persistence.list_token_events(limit) {
+        Ok(events) => Json(events).into_response(),
+        Err(e) => error_response(&e, ErrorCode::Database),
+    }
+}
+
 /// Password hash generation endpoint - utility for creating password hashes
 /// This endpoint can be used to generate hashes for the credentials file
 pub async fn hash_password(
@@ -389,21 +711,14 @@ pub async fn hash_password(
 ) -> Response {
     // Only allow if auth is enabled (to prevent abuse)
     if !state.auth_service.is_enabled() {
-        return (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(ErrorResponse::new(
-                "auth_disabled",
-                "Authentication is not enabled on this server",
-            )),
-        )
-            .into_response();
+        return auth_disabled();
     }
 
     let Some(password) = body.get("password").and_then(|v| v.as_str()) else {
         return (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse::new(
-                "invalid_request",
+                ErrorCode::InvalidRequest.as_str(),
                 "Missing 'password' field in request body",
             )),
         )
@@ -414,11 +729,7 @@ pub async fn hash_password(
         Ok(hash) => Json(json!({ "password_hash": hash })).into_response(),
         Err(e) => {
             tracing::error!("Failed to hash password: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new("hash_error", "Failed to hash password")),
-            )
-                .into_response()
+            error_response(&e.context("Failed to hash password"), ErrorCode::Internal)
         }
     }
 }
@@ -430,7 +741,7 @@ pub async fn search(Json(request): Json<SearchRequest>) -> Response {
         return (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse::new(
-                "invalid_request",
+                ErrorCode::InvalidRequest.as_str(),
                 "Query cannot be empty",
             )),
         )
@@ -445,14 +756,10 @@ pub async fn search(Json(request): Json<SearchRequest>) -> Response {
         .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
     if !root.exists() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(
-                "invalid_root",
-                format!("Search root {} does not exist", root.display()),
-            )),
-        )
-            .into_response();
+        return error_response(
+            &Error::InvalidRequest(format!("Search root {} does not exist", root.display())).into(),
+            ErrorCode::InvalidRequest,
+        );
     }
 
     // Calculate pagination parameters
@@ -468,14 +775,10 @@ pub async fn search(Json(request): Json<SearchRequest>) -> Response {
         Ok(path) => path,
         Err(e) => {
             tracing::error!("Failed to generate embeddings: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    "embeddings_error",
-                    format!("Failed to generate embeddings: {}", e),
-                )),
-            )
-                .into_response();
+            return error_response(
+                &e.context("Failed to generate embeddings"),
+                ErrorCode::Internal,
+            );
         }
     };
 
@@ -484,14 +787,10 @@ pub async fn search(Json(request): Json<SearchRequest>) -> Response {
         Ok(s) => s,
         Err(e) => {
             tracing::error!("Failed to load embeddings: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    "search_error",
-                    format!("Failed to load embeddings database: {}", e),
-                )),
-            )
-                .into_response();
+            return error_response(
+                &e.context("Failed to load embeddings database"),
+                ErrorCode::Database,
+            );
         }
     };
 
@@ -501,14 +800,7 @@ pub async fn search(Json(request): Json<SearchRequest>) -> Response {
         Ok(h) => h,
         Err(e) => {
             tracing::error!("Search failed: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    "search_error",
-                    format!("Search failed: {}", e),
-                )),
-            )
-                .into_response();
+            return error_response(&e.context("Search failed"), ErrorCode::Internal);
         }
     };
 
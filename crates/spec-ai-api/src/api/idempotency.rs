@@ -0,0 +1,322 @@
+//! Idempotency keys for API mutations
+//!
+//! A client that sends `Idempotency-Key: <key>` with a POST, PUT, PATCH or
+//! DELETE can retry it after a network failure without running it twice. The
+//! first request with a key runs and its response is stored; retries with the
+//! same key and the same request get that response back, marked with
+//! `Idempotent-Replayed: true`. Reusing a key for a different request, or
+//! while the first one is still running, is answered with `409 Conflict`.
+//!
+//! Keys are scoped to the authenticated user and expire after a day. Server
+//! errors and streamed responses are not stored, so those requests run again.
+
+use crate::api::error::error_response;
+use crate::api::middleware::AuthenticatedUser;
+use crate::persistence::{IdempotencyRecord, Persistence};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::digest;
+use spec_ai_core::error::{Error, ErrorCode};
+use std::time::Duration;
+
+/// Header carrying the client's key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header set on responses replayed from the store
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// How long a key and its response are kept
+const KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Longest key accepted
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Largest request or response body that is hashed or stored
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// What to do with a request whose key is already stored
+#[derive(Debug, PartialEq, Eq)]
+enum Replay {
+    /// The first request has not finished yet
+    InProgress,
+    /// The key was first used for a different request
+    Mismatch,
+    /// Send back the stored response
+    Respond {
+        status: u16,
+        content_type: Option<String>,
+        body: String,
+    },
+}
+
+impl Replay {
+    fn of(record: IdempotencyRecord, request_hash: &str) -> Self {
+        if record.request_hash != request_hash {
+            return Self::Mismatch;
+        }
+        match record.status {
+            Some(status) => Self::Respond {
+                status,
+                content_type: record.content_type,
+                body: record.body.unwrap_or_default(),
+            },
+            None => Self::InProgress,
+        }
+    }
+}
+
+/// Axum middleware that runs each keyed mutation at most once
+///
+/// Must run after [`auth_middleware`](crate::api::middleware::auth_middleware)
+/// so keys can be scoped to the user.
+pub async fn idempotency_middleware(
+    State(persistence): State<Persistence>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mutating = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    let Some(key) = request
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .filter(|_| mutating)
+        .cloned()
+    else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.trim().is_empty() && key.len() <= MAX_KEY_LENGTH => key.trim().to_string(),
+        _ => {
+            return error(Error::InvalidRequest(format!(
+                "Idempotency-Key must be 1 to {} visible ASCII characters",
+                MAX_KEY_LENGTH
+            )))
+        }
+    };
+
+    let user = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.username.clone())
+        .unwrap_or_default();
+    let stored_key = format!("{}:{}", user, key);
+
+    // The body is hashed along with the endpoint, then handed on unchanged
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            let mut response = error(Error::InvalidRequest(
+                "Request body is too large to be made idempotent".to_string(),
+            ));
+            *response.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+            return response;
+        }
+    };
+    let request_hash = request_hash(&parts.method, &parts.uri, &bytes);
+
+    match persistence.idempotency_reserve(&stored_key, &request_hash, KEY_TTL) {
+        Ok(None) => {}
+        Ok(Some(record)) => return replay(Replay::of(record, &request_hash)),
+        Err(e) => {
+            tracing::warn!("Idempotency store unavailable: {}", e);
+            return error_response(
+                &e.context("Idempotency keys are unavailable; retry without the header or later"),
+                ErrorCode::Database,
+            );
+        }
+    }
+
+    // Released again unless a response is stored, including when the client
+    // goes away and this future is dropped
+    let mut reservation = Reservation {
+        persistence,
+        key: stored_key,
+        kept: false,
+    };
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    reservation.store(response).await
+}
+
+/// Hash of what makes two requests the same: method, path, query and body
+fn request_hash(method: &Method, uri: &Uri, body: &[u8]) -> String {
+    let target = uri
+        .path_and_query()
+        .map_or_else(|| uri.path(), |target| target.as_str());
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(method.as_str().as_bytes());
+    context.update(b" ");
+    context.update(target.as_bytes());
+    context.update(b"\n");
+    context.update(body);
+    URL_SAFE_NO_PAD.encode(context.finish().as_ref())
+}
+
+/// A reserved key, released on drop unless its response was stored
+struct Reservation {
+    persistence: Persistence,
+    key: String,
+    kept: bool,
+}
+
+impl Reservation {
+    /// Store the response for later retries and pass it on
+    async fn store(&mut self, response: Response) -> Response {
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let streamed = content_type
+            .as_deref()
+            .is_some_and(|content_type| content_type.starts_with("text/event-stream"));
+        if streamed || response.status().is_server_error() {
+            return response;
+        }
+
+        let (parts, body) = response.into_parts();
+        let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!("Failed to buffer response for idempotency key: {}", e);
+                return error_response(
+                    &anyhow::Error::from(e).context("Failed to read the response"),
+                    ErrorCode::Internal,
+                );
+            }
+        };
+        // Binary responses are passed on but not stored
+        if let Ok(text) = std::str::from_utf8(&bytes) {
+            match self.persistence.idempotency_complete(
+                &self.key,
+                parts.status.as_u16(),
+                content_type.as_deref(),
+                text,
+            ) {
+                Ok(()) => self.kept = true,
+                Err(e) => tracing::warn!("Failed to store response for idempotency key: {}", e),
+            }
+        }
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.kept {
+            return;
+        }
+        if let Err(e) = self.persistence.idempotency_release(&self.key) {
+            tracing::warn!("Failed to release idempotency key: {}", e);
+        }
+    }
+}
+
+fn replay(replay: Replay) -> Response {
+    match replay {
+        Replay::InProgress => error(Error::Conflict(
+            "A request with this Idempotency-Key is still in progress".to_string(),
+        )),
+        Replay::Mismatch => error(Error::Conflict(
+            "Idempotency-Key was already used for a different request".to_string(),
+        )),
+        Replay::Respond {
+            status,
+            content_type,
+            body,
+        } => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+            let mut response = (status, body).into_response();
+            let headers = response.headers_mut();
+            if let Some(value) = content_type.and_then(|value| HeaderValue::from_str(&value).ok()) {
+                headers.insert(header::CONTENT_TYPE, value);
+            }
+            headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+            response
+        }
+    }
+}
+
+fn error(err: Error) -> Response {
+    error_response(&err.into(), ErrorCode::Internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(request_hash: &str, status: Option<u16>) -> IdempotencyRecord {
+        IdempotencyRecord {
+            key: "alice:k1".to_string(),
+            request_hash: request_hash.to_string(),
+            status,
+            content_type: Some("application/json".to_string()),
+            body: status.map(|_| "{\"id\":7}".to_string()),
+            expires_at: 0,
+        }
+    }
+
+    #[test]
+    fn requests_hash_by_endpoint_and_body() {
+        let nodes = Uri::from_static("/graph/nodes");
+        let hash = request_hash(&Method::POST, &nodes, b"{\"label\":\"a\"}");
+        assert_eq!(
+            hash,
+            request_hash(&Method::POST, &nodes, b"{\"label\":\"a\"}")
+        );
+        assert_ne!(
+            hash,
+            request_hash(&Method::POST, &nodes, b"{\"label\":\"b\"}")
+        );
+        assert_ne!(
+            hash,
+            request_hash(&Method::PUT, &nodes, b"{\"label\":\"a\"}")
+        );
+    }
+
+    #[test]
+    fn requests_with_different_queries_hash_differently() {
+        let session = |uri| request_hash(&Method::DELETE, &Uri::from_static(uri), b"");
+        assert_ne!(
+            session("/sessions/s1/messages?before=10"),
+            session("/sessions/s1/messages?before=20")
+        );
+        assert_ne!(
+            session("/sessions/s1/messages?before=10"),
+            session("/sessions/s1/messages")
+        );
+        assert_eq!(
+            session("/sessions/s1/messages?before=10"),
+            session("/sessions/s1/messages?before=10")
+        );
+    }
+
+    #[test]
+    fn stored_keys_replay_only_the_same_finished_request() {
+        assert_eq!(
+            Replay::of(record("h1", Some(201)), "h1"),
+            Replay::Respond {
+                status: 201,
+                content_type: Some("application/json".to_string()),
+                body: "{\"id\":7}".to_string(),
+            }
+        );
+        assert_eq!(Replay::of(record("h1", None), "h1"), Replay::InProgress);
+        assert_eq!(Replay::of(record("h1", Some(201)), "h2"), Replay::Mismatch);
+
+        let response = replay(Replay::of(record("h1", Some(201)), "h1"));
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[REPLAYED_HEADER], "true");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    }
+}
@@ -0,0 +1,675 @@
+//! Model Context Protocol server
+//!
+//! Lets other assistants, such as Claude Desktop or an IDE, use a running
+//! node as a backend. Its tools are the built-in tools the default agent may
+//! use under its profile and the policy rules, including `graph` for
+//! knowledge graph queries, plus `ask_agent` to run a step of any agent. Its
+//! resources are the agent profiles (`spec-ai://agents`) and the knowledge
+//! graph of each session (`spec-ai://graph/{session_id}`).
+//!
+//! Clients connect with `GET /mcp/sse`, whose first event names the URL to
+//! `POST` requests to; answers arrive as `message` events on the stream.
+//! `spec-ai mcp` serves the same over stdio for clients that start their
+//! servers themselves.
+
+use crate::agent::builder::load_policy_engine;
+use crate::api::error::error_response;
+use crate::api::handlers::{agent_infos, agent_name, create_agent, uuid_v4, AppState, ANONYMOUS};
+use crate::api::middleware::AuthenticatedUser;
+use crate::policy::{PolicyDecision, PolicyEngine};
+use crate::tools::builtin::{bind_issuer, API_TOKEN_TOOL};
+use async_stream::stream;
+use axum::{
+    extract::{Extension, Json, Query, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+};
+use futures::stream::Stream;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use spec_ai_core::error::{Error, ErrorCode};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+/// Protocol revision the server speaks
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const RESOURCE_NOT_FOUND: i64 = -32002;
+
+/// Tool that runs a step of an agent
+const ASK_AGENT: &str = "ask_agent";
+/// Built-in tools that need someone at the terminal or a front end
+const HIDDEN_TOOLS: &[&str] = &["prompt_user", "ask_user"];
+
+/// Issuer of tokens minted by clients that did not authenticate, such as
+/// those on stdio
+const MCP_CALLER: &str = "mcp";
+
+const AGENTS_URI: &str = "spec-ai://agents";
+const GRAPH_URI_PREFIX: &str = "spec-ai://graph/";
+/// Most session graphs listed by `resources/list`
+const MAX_LISTED_GRAPHS: usize = 100;
+
+/// A JSON-RPC error answer
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn internal(err: anyhow::Error) -> Self {
+        Self::new(INTERNAL_ERROR, format!("{:#}", err))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AskAgentArgs {
+    message: String,
+    #[serde(default)]
+    agent: Option<String>,
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+/// Answers MCP requests from the node's tools, agents and knowledge graph
+#[derive(Clone)]
+pub struct McpServer {
+    state: AppState,
+    /// Who is calling, named as the issuer of tokens the client mints
+    caller: String,
+}
+
+impl McpServer {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            caller: MCP_CALLER.to_string(),
+        }
+    }
+
+    /// Answer for an authenticated user
+    pub fn with_caller(mut self, caller: impl Into<String>) -> Self {
+        self.caller = caller.into();
+        self
+    }
+
+    /// Answer one message; notifications and responses get no reply
+    pub async fn handle(&self, message: Value) -> Option<Value> {
+        let method = message.get("method")?.as_str()?.to_string();
+        let id = message.get("id").cloned()?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        Some(match self.dispatch(&method, params).await {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message },
+            }),
+        })
+    }
+
+    async fn dispatch(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {}, "resources": {} },
+                "serverInfo": { "name": "spec-ai", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": self.list_tools() })),
+            "tools/call" => self.call_tool(params).await,
+            "resources/list" => self.list_resources(),
+            "resources/templates/list" => Ok(json!({
+                "resourceTemplates": [{
+                    "uriTemplate": format!("{}{{session_id}}", GRAPH_URI_PREFIX),
+                    "name": "Knowledge graph",
+                    "description": "Nodes and edges of a session's knowledge graph",
+                    "mimeType": "application/json",
+                }],
+            })),
+            "resources/read" => self.read_resource(&params),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            )),
+        }
+    }
+
+    /// Whether clients may call `name`: built-in tools must be allowed by
+    /// the default agent's profile, and every tool by the policy rules
+    fn tool_allowed(&self, policy: &PolicyEngine, name: &str) -> bool {
+        let agent = agent_name(&self.state, None);
+        let profile_allows = name == ASK_AGENT
+            || (!HIDDEN_TOOLS.contains(&name)
+                && self
+                    .state
+                    .agent_registry
+                    .get(&agent)
+                    .is_none_or(|profile| profile.is_tool_allowed(name)));
+        profile_allows
+            && matches!(
+                policy.check(&agent, "tool_call", name),
+                PolicyDecision::Allow
+            )
+    }
+
+    fn list_tools(&self) -> Vec<Value> {
+        let policy = load_policy_engine(&self.state.persistence);
+        let mut names = self.state.tool_registry.list();
+        names.sort();
+
+        let mut tools: Vec<Value> = names
+            .into_iter()
+            .filter(|name| self.tool_allowed(&policy, name))
+            .filter_map(|name| self.state.tool_registry.get(name))
+            .map(|tool| {
+                json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "inputSchema": tool.parameters(),
+                })
+            })
+            .collect();
+        if self.tool_allowed(&policy, ASK_AGENT) {
+            tools.push(json!({
+                "name": ASK_AGENT,
+                "description": "Send a message to a spec-ai agent and get its reply. \
+                                Pass the returned session_id to continue the conversation.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "message": { "type": "string", "description": "Message for the agent" },
+                        "agent": {
+                            "type": "string",
+                            "description": "Agent profile to use (defaults to the default agent)"
+                        },
+                        "session_id": {
+                            "type": "string",
+                            "description": "Session to continue (defaults to a new one)"
+                        }
+                    },
+                    "required": ["message"]
+                },
+            }));
+        }
+        tools
+    }
+
+    async fn call_tool(&self, params: Value) -> Result<Value, RpcError> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Missing tool name"))?;
+        let mut arguments = match params.get("arguments") {
+            Some(Value::Null) | None => json!({}),
+            Some(arguments) => arguments.clone(),
+        };
+        if name != ASK_AGENT && !self.state.tool_registry.has(name) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Unknown tool: {}", name),
+            ));
+        }
+
+        let policy = load_policy_engine(&self.state.persistence);
+        if !self.tool_allowed(&policy, name) {
+            return Ok(tool_result(
+                false,
+                format!("Tool '{}' is not allowed by agent policy", name),
+            ));
+        }
+
+        if name == ASK_AGENT {
+            let args: AskAgentArgs = serde_json::from_value(arguments)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+            return Ok(self.ask_agent(args).await);
+        }
+        if name == API_TOKEN_TOOL {
+            bind_issuer(&mut arguments, &self.caller, None);
+        }
+
+        Ok(
+            match self.state.tool_registry.execute(name, arguments).await {
+                Ok(result) if result.success => tool_result(true, result.output),
+                Ok(result) => tool_result(
+                    false,
+                    result.error.unwrap_or_else(|| "Tool failed".to_string()),
+                ),
+                Err(err) => tool_result(false, format!("{:#}", err)),
+            },
+        )
+    }
+
+    async fn ask_agent(&self, args: AskAgentArgs) -> Value {
+        let agent_name = agent_name(&self.state, args.agent);
+        let session_id = args
+            .session_id
+            .unwrap_or_else(|| format!("mcp_{}", uuid_v4()));
+        let mut agent = match create_agent(
+            &self.state,
+            &agent_name,
+            &session_id,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        {
+            Ok(agent) => agent,
+            Err(err) => return tool_result(false, format!("{:#}", err)),
+        };
+
+        // Messages to a shared session are answered in arrival order
+        let _in_flight = self.state.liveness.begin(&session_id);
+        let mut presence = self.state.presence.join(&session_id, ANONYMOUS, "mcp");
+        let _turn = self.state.turns.enqueue(&session_id).wait().await;
+        presence.start();
+        match agent.run_step(&args.message).await {
+            Ok(output) => json!({
+                "content": [
+                    { "type": "text", "text": output.response },
+                    { "type": "text", "text": format!("session_id: {}", session_id) },
+                ],
+                "isError": false,
+            }),
+            Err(err) => {
+                self.state.liveness.record_error(&err);
+                tool_result(false, format!("{:#}", err))
+            }
+        }
+    }
+
+    fn list_resources(&self) -> Result<Value, RpcError> {
+        let persistence = &self.state.persistence;
+        let mut resources = vec![json!({
+            "uri": AGENTS_URI,
+            "name": "Agent profiles",
+            "description": "Configured agents with their models and tool permissions",
+            "mimeType": "application/json",
+        })];
+        let sessions = persistence.list_sessions().map_err(RpcError::internal)?;
+        for session_id in sessions {
+            if resources.len() > MAX_LISTED_GRAPHS {
+                break;
+            }
+            if persistence.count_graph_nodes(&session_id).unwrap_or(0) > 0 {
+                resources.push(json!({
+                    "uri": format!("{}{}", GRAPH_URI_PREFIX, session_id),
+                    "name": format!("Knowledge graph of {}", session_id),
+                    "mimeType": "application/json",
+                }));
+            }
+        }
+        Ok(json!({ "resources": resources }))
+    }
+
+    fn read_resource(&self, params: &Value) -> Result<Value, RpcError> {
+        let uri = params
+            .get("uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Missing resource uri"))?;
+
+        let contents = if uri == AGENTS_URI {
+            json!({ "agents": agent_infos(&self.state) })
+        } else if let Some(session_id) = uri.strip_prefix(GRAPH_URI_PREFIX) {
+            let persistence = &self.state.persistence;
+            let nodes = persistence
+                .list_graph_nodes(session_id, None, None)
+                .map_err(RpcError::internal)?;
+            let edges = persistence
+                .list_graph_edges(session_id, None, None)
+                .map_err(RpcError::internal)?;
+            json!({ "session_id": session_id, "nodes": nodes, "edges": edges })
+        } else {
+            return Err(RpcError::new(
+                RESOURCE_NOT_FOUND,
+                format!("Resource not found: {}", uri),
+            ));
+        };
+
+        Ok(json!({
+            "contents": [{
+                "uri": uri,
+                "mimeType": "application/json",
+                "text": serde_json::to_string_pretty(&contents).unwrap_or_default(),
+            }],
+        }))
+    }
+}
+
+fn tool_result(success: bool, text: impl Into<String>) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text.into() }],
+        "isError": !success,
+    })
+}
+
+/// Serve newline-delimited JSON-RPC from `reader` until it closes, as MCP
+/// clients that start their servers expect on stdin and stdout
+///
+/// Requests are answered concurrently, so a long `ask_agent` call does not
+/// hold up the rest.
+pub async fn serve<R, W>(server: McpServer, reader: R, mut writer: W) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (replies, mut outgoing) = mpsc::unbounded_channel::<Value>();
+    let write_task = tokio::spawn(async move {
+        while let Some(reply) = outgoing.recv().await {
+            let mut line = reply.to_string();
+            line.push('\n');
+            writer.write_all(line.as_bytes()).await?;
+            writer.flush().await?;
+        }
+        Ok::<_, std::io::Error>(())
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(err) => {
+                let _ = replies.send(json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": err.to_string() },
+                }));
+                continue;
+            }
+        };
+        let server = server.clone();
+        let replies = replies.clone();
+        tokio::spawn(async move {
+            if let Some(reply) = server.handle(message).await {
+                let _ = replies.send(reply);
+            }
+        });
+    }
+
+    // Requests still running answer before the writer finishes
+    drop(replies);
+    write_task.await??;
+    Ok(())
+}
+
+/// Serve MCP on stdin and stdout
+pub async fn serve_stdio(server: McpServer) -> anyhow::Result<()> {
+    serve(server, tokio::io::stdin(), tokio::io::stdout()).await
+}
+
+/// Open MCP connections by session ID, with the sender for each event stream
+#[derive(Clone, Default)]
+pub struct McpSessions {
+    sessions: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<Value>>>>,
+}
+
+impl McpSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(&self) -> (String, mpsc::UnboundedReceiver<Value>) {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_id.clone(), sender);
+        (session_id, receiver)
+    }
+
+    fn sender(&self, session_id: &str) -> Option<mpsc::UnboundedSender<Value>> {
+        self.sessions.lock().unwrap().get(session_id).cloned()
+    }
+
+    fn close(&self, session_id: &str) {
+        self.sessions.lock().unwrap().remove(session_id);
+    }
+
+    /// Number of connected clients
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Forgets a session once its event stream is dropped
+struct SessionGuard {
+    sessions: McpSessions,
+    session_id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.sessions.close(&self.session_id);
+    }
+}
+
+/// Query parameters of `POST /mcp/messages`
+#[derive(Debug, Deserialize)]
+pub struct McpMessageQuery {
+    pub session_id: String,
+}
+
+/// Open an MCP connection; the first event names the endpoint for requests
+pub async fn mcp_sse(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (session_id, mut messages) = state.mcp_sessions.open();
+    let guard = SessionGuard {
+        sessions: state.mcp_sessions.clone(),
+        session_id,
+    };
+
+    let stream = stream! {
+        let guard = guard;
+        yield Ok(Event::default()
+            .event("endpoint")
+            .data(format!("/mcp/messages?session_id={}", guard.session_id)));
+        while let Some(message) = messages.recv().await {
+            yield Ok(Event::default().event("message").data(message.to_string()));
+        }
+    };
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Take a message for an open MCP connection; any answer is sent on its
+/// event stream
+pub async fn mcp_message(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthenticatedUser>>,
+    Query(query): Query<McpMessageQuery>,
+    Json(message): Json<Value>,
+) -> Response {
+    let Some(replies) = state.mcp_sessions.sender(&query.session_id) else {
+        let err = Error::NotFound {
+            kind: "MCP session",
+            id: query.session_id,
+        };
+        return error_response(&err.into(), ErrorCode::NotFound);
+    };
+
+    let mut server = McpServer::new(state);
+    if let Some(Extension(user)) = user {
+        server = server.with_caller(user.username);
+    }
+    tokio::spawn(async move {
+        if let Some(reply) = server.handle(message).await {
+            let _ = replies.send(reply);
+        }
+    });
+    StatusCode::ACCEPTED.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::AuthService;
+    use crate::config::{AgentProfile, AgentRegistry, AppConfig};
+    use crate::persistence::Persistence;
+    use crate::tools::builtin::ApiTokenTool;
+    use crate::tools::ToolRegistry;
+
+    fn server(profile: AgentProfile) -> McpServer {
+        server_with(profile, Persistence::in_memory().unwrap(), |_| {})
+    }
+
+    fn server_with(
+        profile: AgentProfile,
+        persistence: Persistence,
+        register: impl FnOnce(&mut ToolRegistry),
+    ) -> McpServer {
+        let mut config = AppConfig::default();
+        config.model.provider = "mock".to_string();
+        config.agents.insert("default".to_string(), profile);
+        let agent_registry = Arc::new(AgentRegistry::new(
+            config.agents.clone(),
+            persistence.clone(),
+        ));
+        let mut tool_registry =
+            ToolRegistry::with_builtin_tools(Some(Arc::new(persistence.clone())), None, None);
+        register(&mut tool_registry);
+        let tool_registry = Arc::new(tool_registry);
+        McpServer::new(AppState::new(
+            persistence,
+            agent_registry,
+            tool_registry,
+            config,
+        ))
+    }
+
+    async fn request(server: &McpServer, method: &str, params: Value) -> Value {
+        server
+            .handle(json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn offers_the_tools_the_default_agent_may_use() {
+        let server = server(AgentProfile {
+            denied_tools: Some(vec!["bash".to_string()]),
+            ..AgentProfile::default()
+        });
+
+        let init = request(&server, "initialize", json!({})).await;
+        assert_eq!(init["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert!(server
+            .handle(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await
+            .is_none());
+
+        let list = request(&server, "tools/list", json!({})).await;
+        let names: Vec<&str> = list["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|tool| tool["name"].as_str())
+            .collect();
+        assert!(names.contains(&"graph"));
+        assert!(names.contains(&ASK_AGENT));
+        assert!(!names.contains(&"bash"));
+        assert!(!names.contains(&"prompt_user"));
+        assert!(!names.contains(&"ask_user"));
+
+        let echoed = request(
+            &server,
+            "tools/call",
+            json!({ "name": "echo", "arguments": { "message": "hi" } }),
+        )
+        .await;
+        assert_eq!(echoed["result"]["isError"], false);
+
+        let denied = request(&server, "tools/call", json!({ "name": "bash" })).await;
+        assert_eq!(denied["result"]["isError"], true);
+
+        let unknown = request(&server, "tools/call", json!({ "name": "nope" })).await;
+        assert_eq!(unknown["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn minted_tokens_name_the_caller_not_the_arguments() {
+        let persistence = Persistence::in_memory().unwrap();
+        let auth = AuthService::new(None, Some("test_secret"), Some(600), true)
+            .unwrap()
+            .with_audit_log(persistence.clone());
+        let server = server_with(AgentProfile::default(), persistence.clone(), |tools| {
+            tools.register(Arc::new(ApiTokenTool::new(Arc::new(auth))))
+        })
+        .with_caller("alice");
+
+        let minted = request(
+            &server,
+            "tools/call",
+            json!({
+                "name": API_TOKEN_TOOL,
+                "arguments": {
+                    "action": "mint",
+                    "endpoints": ["GET /graph/*"],
+                    "issuer": "admin",
+                },
+            }),
+        )
+        .await;
+        assert_eq!(minted["result"]["isError"], false);
+
+        let events = persistence.list_token_events(10).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].actor, "alice");
+    }
+
+    #[tokio::test]
+    async fn reads_agents_and_graphs_as_resources() {
+        let server = server(AgentProfile::default());
+
+        let agents = request(&server, "resources/read", json!({ "uri": AGENTS_URI })).await;
+        let text = agents["result"]["contents"][0]["text"].as_str().unwrap();
+        let agents: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(agents["agents"][0]["id"], "default");
+
+        let graph = request(
+            &server,
+            "resources/read",
+            json!({ "uri": "spec-ai://graph/s1" }),
+        )
+        .await;
+        let text = graph["result"]["contents"][0]["text"].as_str().unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(text).unwrap()["nodes"],
+            json!([])
+        );
+
+        let missing = request(&server, "resources/read", json!({ "uri": "file:///etc" })).await;
+        assert_eq!(missing["error"]["code"], RESOURCE_NOT_FOUND);
+
+        let unknown = request(&server, "sampling/createMessage", json!({})).await;
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
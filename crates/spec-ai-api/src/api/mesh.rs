@@ -1,3 +1,8 @@
+use crate::api::mesh_shaping::BandwidthShaper;
+use crate::config::MeshBandwidthConfig;
+pub use crate::mesh::{
+    ClassTraffic, ClockSample, ClockSkewEstimator, MessagePriority, TrafficResponse,
+};
 use crate::persistence::Persistence;
 use anyhow::Result;
 /// Mesh registry handlers and models
@@ -9,7 +14,9 @@ use axum::{
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::sync::RwLock;
 
 /// Agent instance information in the mesh
@@ -23,6 +30,13 @@ pub struct MeshInstance {
     pub last_heartbeat: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub agent_profiles: Vec<String>,
+    /// Metrics sent with the latest heartbeat, such as the liveness report
+    #[serde(default)]
+    pub metrics: HashMap<String, serde_json::Value>,
+    /// Offset of the instance's clock from the registry's, as it last
+    /// reported it
+    #[serde(default)]
+    pub clock: Option<ClockSample>,
 }
 
 /// Request to register a new instance
@@ -50,6 +64,10 @@ pub struct RegisterResponse {
 pub struct InstancesResponse {
     pub instances: Vec<MeshInstance>,
     pub leader_id: Option<String>,
+    /// Registry's clock when the list was made, which heartbeat times are
+    /// measured against
+    #[serde(default)]
+    pub registry_time: Option<DateTime<Utc>>,
 }
 
 /// Heartbeat request
@@ -57,6 +75,12 @@ pub struct InstancesResponse {
 pub struct HeartbeatRequest {
     pub status: String,
     pub metrics: Option<HashMap<String, serde_json::Value>>,
+    /// Sender's clock when the heartbeat was sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<DateTime<Utc>>,
+    /// Sender's estimate of its clock's offset from the registry's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<ClockSample>,
 }
 
 /// Heartbeat response
@@ -65,6 +89,12 @@ pub struct HeartbeatResponse {
     pub acknowledged: bool,
     pub leader_id: Option<String>,
     pub should_sync: bool,
+    /// Registry's clock when the heartbeat arrived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
+    /// Registry's clock when the response was made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replied_at: Option<DateTime<Utc>>,
 }
 
 /// Message types for inter-agent communication
@@ -125,6 +155,11 @@ impl MessageType {
             custom => MessageType::Custom(custom.to_string()),
         }
     }
+
+    /// Delivery class of messages of this type
+    pub fn priority(&self) -> MessagePriority {
+        MessagePriority::for_message_type(&self.as_str())
+    }
 }
 
 /// Inter-agent message
@@ -137,6 +172,12 @@ pub struct AgentMessage {
     pub payload: serde_json::Value,
     pub correlation_id: Option<String>, // For request/response correlation
     pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub priority: MessagePriority,
+    /// Registry-assigned order of the message, which unlike `created_at`
+    /// does not depend on the sender's clock
+    #[serde(default)]
+    pub seq: u64,
 }
 
 /// Message send request
@@ -146,6 +187,9 @@ pub struct SendMessageRequest {
     pub message_type: MessageType,
     pub payload: serde_json::Value,
     pub correlation_id: Option<String>,
+    /// Delivery class, when not the message type's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<MessagePriority>,
 }
 
 /// Message send response
@@ -169,6 +213,9 @@ pub struct MeshRegistry {
     leader_id: Arc<RwLock<Option<String>>>,
     message_queue: Arc<RwLock<Vec<AgentMessage>>>,
     persistence: Option<Persistence>,
+    shaper: Arc<Mutex<BandwidthShaper>>,
+    next_seq: Arc<AtomicU64>,
+    max_clock_skew_ms: Option<u64>,
 }
 
 impl Default for MeshRegistry {
@@ -184,6 +231,9 @@ impl MeshRegistry {
             leader_id: Arc::new(RwLock::new(None)),
             message_queue: Arc::new(RwLock::new(Vec::new())),
             persistence: None,
+            shaper: Arc::new(Mutex::new(BandwidthShaper::default())),
+            next_seq: Arc::new(AtomicU64::new(1)),
+            max_clock_skew_ms: None,
         }
     }
 
@@ -193,9 +243,29 @@ impl MeshRegistry {
             leader_id: Arc::new(RwLock::new(None)),
             message_queue: Arc::new(RwLock::new(Vec::new())),
             persistence: Some(persistence),
+            shaper: Arc::new(Mutex::new(BandwidthShaper::default())),
+            next_seq: Arc::new(AtomicU64::new(1)),
+            max_clock_skew_ms: None,
         }
     }
 
+    /// Limit the bandwidth of each message priority class
+    pub fn with_bandwidth(mut self, limits: MeshBandwidthConfig) -> Self {
+        self.shaper = Arc::new(Mutex::new(BandwidthShaper::new(limits)));
+        self
+    }
+
+    /// Warn when an instance reports a clock further than `limit_ms` from
+    /// the registry's
+    pub fn with_max_clock_skew(mut self, limit_ms: u64) -> Self {
+        self.max_clock_skew_ms = Some(limit_ms);
+        self
+    }
+
+    fn shaper(&self) -> std::sync::MutexGuard<'_, BandwidthShaper> {
+        self.shaper.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     /// Register a new instance
     pub async fn register(&self, instance: MeshInstance) -> RegisterResponse {
         let mut instances = self.instances.write().await;
@@ -221,24 +291,56 @@ impl MeshRegistry {
         }
     }
 
-    /// Update heartbeat timestamp
-    pub async fn heartbeat(&self, instance_id: &str) -> HeartbeatResponse {
+    /// Update heartbeat timestamp, replacing the metrics and clock offset
+    /// when new ones are sent
+    ///
+    /// The timestamp comes from the registry's clock, so stale instances are
+    /// found without trusting theirs.
+    pub async fn heartbeat(
+        &self,
+        instance_id: &str,
+        metrics: Option<HashMap<String, serde_json::Value>>,
+        clock: Option<ClockSample>,
+    ) -> HeartbeatResponse {
         let mut instances = self.instances.write().await;
         let leader = self.leader_id.read().await;
 
-        if let Some(instance) = instances.get_mut(instance_id) {
-            instance.last_heartbeat = Utc::now();
-            HeartbeatResponse {
-                acknowledged: true,
-                leader_id: leader.clone(),
-                should_sync: false,
-            }
-        } else {
-            HeartbeatResponse {
-                acknowledged: false,
-                leader_id: leader.clone(),
-                should_sync: false,
+        let acknowledged = match instances.get_mut(instance_id) {
+            Some(instance) => {
+                instance.last_heartbeat = Utc::now();
+                if let Some(metrics) = metrics {
+                    instance.metrics = metrics;
+                }
+                if let Some(clock) = clock {
+                    self.check_clock(instance, clock);
+                    instance.clock = Some(clock);
+                }
+                true
             }
+            None => false,
+        };
+        HeartbeatResponse {
+            acknowledged,
+            leader_id: leader.clone(),
+            should_sync: false,
+            received_at: None,
+            replied_at: None,
+        }
+    }
+
+    /// Warn once when an instance's clock drifts beyond the limit
+    fn check_clock(&self, instance: &MeshInstance, clock: ClockSample) {
+        let Some(limit) = self.max_clock_skew_ms else {
+            return;
+        };
+        let was_skewed = instance.clock.is_some_and(|c| c.exceeds(limit));
+        if clock.exceeds(limit) && !was_skewed {
+            tracing::warn!(
+                "Clock of mesh instance {} is {}ms off the registry's (±{}ms)",
+                instance.instance_id,
+                clock.offset_ms,
+                clock.rtt_ms / 2
+            );
         }
     }
 
@@ -246,6 +348,7 @@ impl MeshRegistry {
     pub async fn deregister(&self, instance_id: &str) -> bool {
         let mut instances = self.instances.write().await;
         let mut leader = self.leader_id.write().await;
+        self.shaper().forget_instance(instance_id);
 
         if let Some(instance) = instances.remove(instance_id) {
             // If leader is leaving, elect a new one
@@ -289,6 +392,7 @@ impl MeshRegistry {
             .collect();
 
         for id in stale_ids {
+            self.shaper().forget_instance(&id);
             if let Some(instance) = instances.remove(&id) {
                 // Handle leader failover if needed
                 if instance.is_leader && !instances.is_empty() {
@@ -319,10 +423,32 @@ impl MeshRegistry {
         message_type: MessageType,
         payload: serde_json::Value,
         correlation_id: Option<String>,
+    ) -> Result<SendMessageResponse> {
+        self.send_message_with_priority(
+            source_instance,
+            target_instance,
+            message_type,
+            payload,
+            correlation_id,
+            None,
+        )
+        .await
+    }
+
+    /// Send a message in a delivery class other than its type's default
+    pub async fn send_message_with_priority(
+        &self,
+        source_instance: String,
+        target_instance: Option<String>,
+        message_type: MessageType,
+        payload: serde_json::Value,
+        correlation_id: Option<String>,
+        priority: Option<MessagePriority>,
     ) -> Result<SendMessageResponse> {
         // Generate time-ordered UUID v7 for better database performance and distributed safety
         let message_id = uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string();
 
+        let priority = priority.unwrap_or_else(|| message_type.priority());
         let message = AgentMessage {
             message_id: message_id.clone(),
             source_instance,
@@ -331,6 +457,8 @@ impl MeshRegistry {
             payload,
             correlation_id,
             created_at: Utc::now(),
+            priority,
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
         };
 
         // Persist to database if available
@@ -377,14 +505,38 @@ impl MeshRegistry {
     }
 
     /// Get pending messages for an instance
+    ///
+    /// Messages come highest priority class first, in the order the
+    /// registry received them within a class. A class over its bandwidth
+    /// limit is cut off at its first message that does not fit; the rest
+    /// wait for a later poll.
     pub async fn get_pending_messages(&self, instance_id: &str) -> Vec<AgentMessage> {
         let queue = self.message_queue.read().await;
-        queue
+        let mut pending: Vec<&AgentMessage> = queue
             .iter()
             .filter(|msg| {
                 // Return messages targeted at this instance or broadcasts (None)
                 msg.target_instance.as_deref() == Some(instance_id) || msg.target_instance.is_none()
             })
+            .collect();
+        pending.sort_by_key(|msg| (msg.priority, msg.seq));
+
+        let now = Instant::now();
+        let mut shaper = self.shaper();
+        let mut held_back = Vec::new();
+        pending
+            .into_iter()
+            .filter(|msg| {
+                if held_back.contains(&msg.priority) {
+                    return false;
+                }
+                let bytes = msg.payload.to_string().len();
+                let admitted = shaper.admit(instance_id, &msg.message_id, msg.priority, bytes, now);
+                if !admitted {
+                    held_back.push(msg.priority);
+                }
+                admitted
+            })
             .cloned()
             .collect()
     }
@@ -393,6 +545,16 @@ impl MeshRegistry {
     pub async fn acknowledge_messages(&self, message_ids: Vec<String>) {
         let mut queue = self.message_queue.write().await;
         queue.retain(|msg| !message_ids.contains(&msg.message_id));
+        self.shaper().acknowledge(&message_ids);
+    }
+
+    /// Delivery statistics of every message priority class
+    pub async fn traffic(&self) -> Vec<ClassTraffic> {
+        let mut queued = HashMap::new();
+        for msg in self.message_queue.read().await.iter() {
+            *queued.entry(msg.priority).or_insert(0) += 1;
+        }
+        self.shaper().traffic(&queued, Instant::now())
     }
 }
 
@@ -401,6 +563,7 @@ impl MeshRegistry {
 pub struct MeshClient {
     base_url: String,
     client: reqwest::Client,
+    clock: Arc<Mutex<ClockSkewEstimator>>,
 }
 
 impl MeshClient {
@@ -416,9 +579,19 @@ impl MeshClient {
         Self {
             base_url: format!("http://{}:{}", host, port),
             client,
+            clock: Arc::default(),
         }
     }
 
+    /// Estimated offset of the registry's clock from this one, measured
+    /// over heartbeats
+    pub fn clock_skew(&self) -> Option<ClockSample> {
+        self.clock
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .estimate()
+    }
+
     /// Generate a unique instance ID
     pub fn generate_instance_id() -> String {
         let hostname = hostname::get()
@@ -467,9 +640,12 @@ impl MeshClient {
         instance_id: &str,
         metrics: Option<HashMap<String, serde_json::Value>>,
     ) -> Result<HeartbeatResponse> {
+        let sent_at = Utc::now();
         let request = HeartbeatRequest {
             status: "healthy".to_string(),
             metrics,
+            sent_at: Some(sent_at),
+            clock: self.clock_skew().map(ClockSample::inverse),
         };
 
         let response = self
@@ -483,7 +659,15 @@ impl MeshClient {
             .await?;
 
         if response.status().is_success() {
-            Ok(response.json().await?)
+            let response: HeartbeatResponse = response.json().await?;
+            if let (Some(received), Some(replied)) = (response.received_at, response.replied_at) {
+                let sample = ClockSample::from_exchange(sent_at, received, replied, Utc::now());
+                self.clock
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .record(sample);
+            }
+            Ok(response)
         } else {
             anyhow::bail!("Heartbeat failed: {}", response.status())
         }
@@ -536,6 +720,7 @@ impl MeshClient {
             message_type,
             payload,
             correlation_id,
+            priority: None,
         };
 
         let response = self
@@ -612,6 +797,8 @@ pub async fn register_instance<S: MeshState>(
         last_heartbeat: Utc::now(),
         created_at: Utc::now(),
         agent_profiles: request.agent_profiles,
+        metrics: HashMap::new(),
+        clock: None,
     };
 
     let response = state.mesh_registry().register(instance).await;
@@ -629,6 +816,7 @@ pub async fn list_instances<S: MeshState>(State(state): State<S>) -> impl IntoRe
     Json(InstancesResponse {
         instances,
         leader_id,
+        registry_time: Some(Utc::now()),
     })
 }
 
@@ -636,9 +824,16 @@ pub async fn list_instances<S: MeshState>(State(state): State<S>) -> impl IntoRe
 pub async fn heartbeat<S: MeshState>(
     State(state): State<S>,
     Path(instance_id): Path<String>,
-    Json(_request): Json<HeartbeatRequest>,
+    Json(request): Json<HeartbeatRequest>,
 ) -> impl IntoResponse {
-    let response = state.mesh_registry().heartbeat(&instance_id).await;
+    let received_at = Utc::now();
+    let mut response = state
+        .mesh_registry()
+        .heartbeat(&instance_id, request.metrics, request.clock)
+        .await;
+    // Timestamps for the sender to estimate its clock offset from
+    response.received_at = Some(received_at);
+    response.replied_at = Some(Utc::now());
 
     if response.acknowledged {
         (StatusCode::OK, Json(response))
@@ -669,12 +864,13 @@ pub async fn send_message<S: MeshState>(
 ) -> impl IntoResponse {
     match state
         .mesh_registry()
-        .send_message(
+        .send_message_with_priority(
             source_instance,
             request.target_instance,
             request.message_type,
             request.payload,
             request.correlation_id,
+            request.priority,
         )
         .await
     {
@@ -702,6 +898,12 @@ pub async fn get_messages<S: MeshState>(
     Json(PendingMessagesResponse { messages })
 }
 
+/// Handler: Delivery statistics per message priority class
+pub async fn get_traffic<S: MeshState>(State(state): State<S>) -> impl IntoResponse {
+    let classes = state.mesh_registry().traffic().await;
+    Json(TrafficResponse { classes })
+}
+
 /// Acknowledge messages request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AcknowledgeMessagesRequest {
@@ -721,3 +923,115 @@ pub async fn acknowledge_messages<S: MeshState>(
 
     StatusCode::NO_CONTENT
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn pending_messages_follow_priority_and_bandwidth() {
+        let registry = MeshRegistry::new().with_bandwidth(MeshBandwidthConfig {
+            bulk_sync: Some(100),
+            ..Default::default()
+        });
+        for (message_type, payload) in [
+            (MessageType::GraphSync, json!({"data": "x".repeat(200)})),
+            (MessageType::GraphSync, json!({"data": "y"})),
+            (MessageType::ProposalVote, json!({"vote": "yes"})),
+        ] {
+            registry
+                .send_message("peer".to_string(), None, message_type, payload, None)
+                .await
+                .unwrap();
+        }
+        registry
+            .send_message_with_priority(
+                "peer".to_string(),
+                None,
+                MessageType::Custom("ping".to_string()),
+                json!({}),
+                None,
+                Some(MessagePriority::Control),
+            )
+            .await
+            .unwrap();
+
+        // The oversized sync goes through on a full bucket; the next one waits
+        let pending = registry.get_pending_messages("me").await;
+        let types: Vec<String> = pending.iter().map(|m| m.message_type.as_str()).collect();
+        assert_eq!(types, vec!["ping", "proposal_vote", "graph_sync"]);
+        assert_eq!(pending[2].payload["data"].as_str().unwrap().len(), 200);
+
+        let traffic = registry.traffic().await;
+        let bulk = &traffic[3];
+        assert_eq!(bulk.priority, MessagePriority::BulkSync);
+        assert_eq!(bulk.queued, 2);
+        assert_eq!(bulk.delivered_messages, 1);
+        assert_eq!(bulk.deferred, 1);
+
+        registry
+            .acknowledge_messages(pending.iter().map(|m| m.message_id.clone()).collect())
+            .await;
+        assert_eq!(registry.traffic().await[3].queued, 1);
+    }
+
+    fn instance(instance_id: &str) -> MeshInstance {
+        MeshInstance {
+            instance_id: instance_id.to_string(),
+            hostname: "localhost".to_string(),
+            port: 3000,
+            capabilities: vec![],
+            is_leader: false,
+            last_heartbeat: Utc::now(),
+            created_at: Utc::now(),
+            agent_profiles: vec![],
+            metrics: HashMap::new(),
+            clock: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn heartbeats_record_reported_clock_offsets() {
+        let registry = MeshRegistry::new().with_max_clock_skew(1000);
+        registry.register(instance("node-a")).await;
+
+        let skew = ClockSample {
+            offset_ms: 4_000,
+            rtt_ms: 20,
+        };
+        let response = registry.heartbeat("node-a", None, Some(skew)).await;
+        assert!(response.acknowledged);
+        assert_eq!(registry.list().await[0].clock, Some(skew));
+
+        // A heartbeat without an estimate keeps the last one
+        registry.heartbeat("node-a", None, None).await;
+        assert_eq!(registry.list().await[0].clock, Some(skew));
+        assert!(!registry.heartbeat("node-b", None, None).await.acknowledged);
+    }
+
+    #[tokio::test]
+    async fn messages_are_ordered_by_registry_sequence() {
+        let registry = MeshRegistry::new();
+        for n in 0..3 {
+            registry
+                .send_message(
+                    "peer".to_string(),
+                    None,
+                    MessageType::Notification,
+                    json!({ "n": n }),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        let pending = registry.get_pending_messages("me").await;
+        let seqs: Vec<u64> = pending.iter().map(|m| m.seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        let order: Vec<i64> = pending
+            .iter()
+            .map(|m| m.payload["n"].as_i64().unwrap())
+            .collect();
+        assert_eq!(order, vec![0, 1, 2]);
+    }
+}
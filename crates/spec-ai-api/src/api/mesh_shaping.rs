@@ -0,0 +1,251 @@
+//! Bandwidth shaping for mesh message delivery
+//!
+//! Pending messages are handed to each instance in priority order, and every
+//! priority class draws on its own per-instance token bucket. A large graph
+//! sync transfer then waits for its bucket to refill on a later poll instead
+//! of holding up the votes and control messages queued behind it.
+
+use crate::config::MeshBandwidthConfig;
+use crate::mesh::{ClassTraffic, MessagePriority};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Window over which delivery rates are measured
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Bytes a class may send to one instance, refilled at its rate
+///
+/// The bucket holds one second of traffic. A message larger than that is
+/// let through once the bucket is full, leaving it in debt, so oversized
+/// transfers are slowed down rather than stuck.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, now: Instant) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            updated: now,
+        }
+    }
+
+    fn try_take(&mut self, bytes: usize, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated = now;
+
+        let bytes = bytes as f64;
+        if self.tokens < bytes.min(self.rate) {
+            return false;
+        }
+        self.tokens -= bytes;
+        true
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClassStats {
+    delivered_messages: u64,
+    delivered_bytes: u64,
+    deferred: u64,
+    /// Deliveries within the rate window, oldest first
+    recent: VecDeque<(Instant, usize)>,
+}
+
+impl ClassStats {
+    fn trim(&mut self, now: Instant) {
+        while let Some((at, _)) = self.recent.front() {
+            if now.saturating_duration_since(*at) <= RATE_WINDOW {
+                break;
+            }
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// Per-class bandwidth limits and delivery statistics of a mesh registry
+#[derive(Debug, Default)]
+pub struct BandwidthShaper {
+    limits: MeshBandwidthConfig,
+    buckets: HashMap<(String, MessagePriority), TokenBucket>,
+    /// (instance, message) pairs already handed out; polls repeat messages
+    /// until they are acknowledged, and those repeats are not charged again
+    admitted: HashSet<(String, String)>,
+    stats: HashMap<MessagePriority, ClassStats>,
+}
+
+impl BandwidthShaper {
+    pub fn new(limits: MeshBandwidthConfig) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Bytes per second `priority` may deliver to each instance, if limited
+    pub fn limit(&self, priority: MessagePriority) -> Option<u64> {
+        match priority {
+            MessagePriority::Control => self.limits.control,
+            MessagePriority::Consensus => self.limits.consensus,
+            MessagePriority::Delegation => self.limits.delegation,
+            MessagePriority::BulkSync => self.limits.bulk_sync,
+        }
+        .filter(|limit| *limit > 0)
+    }
+
+    /// Whether a message of `bytes` may be delivered to `instance` now,
+    /// charging its class when it is
+    pub fn admit(
+        &mut self,
+        instance: &str,
+        message_id: &str,
+        priority: MessagePriority,
+        bytes: usize,
+        now: Instant,
+    ) -> bool {
+        let key = (instance.to_string(), message_id.to_string());
+        if self.admitted.contains(&key) {
+            return true;
+        }
+
+        if let Some(limit) = self.limit(priority) {
+            let bucket = self
+                .buckets
+                .entry((instance.to_string(), priority))
+                .or_insert_with(|| TokenBucket::new(limit, now));
+            if !bucket.try_take(bytes, now) {
+                self.stats.entry(priority).or_default().deferred += 1;
+                return false;
+            }
+        }
+
+        self.admitted.insert(key);
+        let stats = self.stats.entry(priority).or_default();
+        stats.delivered_messages += 1;
+        stats.delivered_bytes += bytes as u64;
+        stats.recent.push_back((now, bytes));
+        stats.trim(now);
+        true
+    }
+
+    /// Drop the delivery records of acknowledged messages
+    pub fn acknowledge(&mut self, message_ids: &[String]) {
+        self.admitted.retain(|(_, id)| !message_ids.contains(id));
+    }
+
+    /// Drop the buckets and delivery records of an instance that left
+    pub fn forget_instance(&mut self, instance: &str) {
+        self.buckets.retain(|(id, _), _| id != instance);
+        self.admitted.retain(|(id, _)| id != instance);
+    }
+
+    /// Statistics of every class, given how many of its messages are queued
+    pub fn traffic(
+        &mut self,
+        queued: &HashMap<MessagePriority, usize>,
+        now: Instant,
+    ) -> Vec<ClassTraffic> {
+        MessagePriority::ALL
+            .iter()
+            .map(|priority| {
+                let limit_bytes_per_sec = self.limit(*priority);
+                let stats = self.stats.entry(*priority).or_default();
+                stats.trim(now);
+                let window_bytes: usize = stats.recent.iter().map(|(_, bytes)| bytes).sum();
+                ClassTraffic {
+                    priority: *priority,
+                    limit_bytes_per_sec,
+                    queued: queued.get(priority).copied().unwrap_or(0),
+                    delivered_messages: stats.delivered_messages,
+                    delivered_bytes: stats.delivered_bytes,
+                    deferred: stats.deferred,
+                    bytes_per_sec: window_bytes as f64 / RATE_WINDOW.as_secs_f64(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limited_bulk_sync(rate: u64) -> BandwidthShaper {
+        BandwidthShaper::new(MeshBandwidthConfig {
+            bulk_sync: Some(rate),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn unlimited_classes_are_never_held_back() {
+        let mut shaper = limited_bulk_sync(100);
+        let now = Instant::now();
+        for n in 0..10 {
+            assert!(shaper.admit("a", &n.to_string(), MessagePriority::Control, 1_000, now));
+        }
+    }
+
+    #[test]
+    fn limited_classes_wait_for_their_bucket_to_refill() {
+        let mut shaper = limited_bulk_sync(100);
+        let now = Instant::now();
+
+        assert!(shaper.admit("a", "1", MessagePriority::BulkSync, 60, now));
+        assert!(!shaper.admit("a", "2", MessagePriority::BulkSync, 60, now));
+        // Buckets are per instance
+        assert!(shaper.admit("b", "2", MessagePriority::BulkSync, 60, now));
+        // Repeating an unacknowledged message costs nothing
+        assert!(shaper.admit("a", "1", MessagePriority::BulkSync, 60, now));
+
+        let later = now + Duration::from_millis(500);
+        assert!(shaper.admit("a", "2", MessagePriority::BulkSync, 60, later));
+    }
+
+    #[test]
+    fn oversized_messages_go_through_once_the_bucket_is_full() {
+        let mut shaper = limited_bulk_sync(100);
+        let now = Instant::now();
+
+        assert!(shaper.admit("a", "big", MessagePriority::BulkSync, 1_000, now));
+        // The bucket is 900 bytes in debt, which takes nine seconds to repay
+        let soon = now + Duration::from_secs(5);
+        assert!(!shaper.admit("a", "next", MessagePriority::BulkSync, 10, soon));
+        let later = now + Duration::from_secs(10);
+        assert!(shaper.admit("a", "next", MessagePriority::BulkSync, 10, later));
+    }
+
+    #[test]
+    fn traffic_reports_every_class() {
+        let mut shaper = limited_bulk_sync(100);
+        let now = Instant::now();
+        shaper.admit("a", "1", MessagePriority::Consensus, 300, now);
+        shaper.admit("a", "2", MessagePriority::BulkSync, 100, now);
+        shaper.admit("a", "3", MessagePriority::BulkSync, 100, now);
+
+        let queued = HashMap::from([(MessagePriority::BulkSync, 1)]);
+        let traffic = shaper.traffic(&queued, now);
+        assert_eq!(traffic.len(), 4);
+
+        let consensus = &traffic[1];
+        assert_eq!(consensus.delivered_messages, 1);
+        assert_eq!(consensus.delivered_bytes, 300);
+        assert_eq!(consensus.bytes_per_sec, 5.0);
+
+        let bulk = &traffic[3];
+        assert_eq!(bulk.limit_bytes_per_sec, Some(100));
+        assert_eq!(bulk.queued, 1);
+        assert_eq!(bulk.delivered_messages, 1);
+        assert_eq!(bulk.deferred, 1);
+
+        // Rates only cover the last minute
+        let traffic = shaper.traffic(&queued, now + Duration::from_secs(61));
+        assert_eq!(traffic[1].bytes_per_sec, 0.0);
+        assert_eq!(traffic[1].delivered_bytes, 300);
+    }
+}
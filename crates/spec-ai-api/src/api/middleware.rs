@@ -1,18 +1,21 @@
 /// API authentication and middleware
-use crate::api::auth::AuthService;
+use crate::api::auth::{is_auth_route, AuthService};
+use crate::api::error::error_response;
 use axum::{
     extract::{Request, State},
-    http::{header, StatusCode},
+    http::header,
     middleware::Next,
-    response::{IntoResponse, Response},
-    Json,
+    response::Response,
 };
+use spec_ai_core::error::{Error, ErrorCode};
 use std::sync::Arc;
 
 /// Extension to store authenticated user info in request
 #[derive(Clone, Debug)]
 pub struct AuthenticatedUser {
     pub username: String,
+    /// Endpoints the token may call, for scoped tokens minted by agents
+    pub scope: Option<Vec<String>>,
 }
 
 /// Axum middleware function for bearer token authentication
@@ -21,7 +24,9 @@ pub struct AuthenticatedUser {
 /// 1. Checks if auth is enabled in the AuthService
 /// 2. If disabled, allows all requests through
 /// 3. If enabled, validates the Bearer token from Authorization header
-/// 4. Adds AuthenticatedUser extension to request if valid
+/// 4. Rejects scoped tokens outside the endpoints they were minted for, and
+///    on the `/auth` routes whatever they were minted for
+/// 5. Adds AuthenticatedUser extension to request if valid
 pub async fn auth_middleware(
     State(auth_service): State<Arc<AuthService>>,
     mut request: Request,
@@ -50,31 +55,47 @@ pub async fn auth_middleware(
     };
 
     // Validate token
-    let Some(username) = auth_service.validate_token(token) else {
-        return unauthorized_response("Invalid or expired token");
+    let Some(claims) = auth_service.validate_claims(token) else {
+        return unauthorized_response("Invalid, expired or revoked token");
     };
 
+    let method = request.method().as_str();
+    let path = request.uri().path();
+    let scoped_auth_call = claims.scope.is_some() && is_auth_route(path);
+    if scoped_auth_call || !claims.permits(method, path) {
+        tracing::warn!(
+            "Scoped token {} minted by '{}' tried {} {}",
+            claims.token_id,
+            claims.subject,
+            method,
+            path
+        );
+        return forbidden_response(&format!("Token is not scoped for {} {}", method, path));
+    }
+
     // Add authenticated user to request extensions
-    request
-        .extensions_mut()
-        .insert(AuthenticatedUser { username });
+    request.extensions_mut().insert(AuthenticatedUser {
+        username: claims.subject,
+        scope: claims.scope,
+    });
 
     next.run(request).await
 }
 
 /// Create an unauthorized response with JSON error body
 fn unauthorized_response(message: &str) -> Response {
-    let body = serde_json::json!({
-        "error": message,
-        "code": "unauthorized"
-    });
+    error_response(
+        &Error::Unauthorized(message.to_string()).into(),
+        ErrorCode::Unauthorized,
+    )
+}
 
-    (
-        StatusCode::UNAUTHORIZED,
-        [(header::CONTENT_TYPE, "application/json")],
-        Json(body),
+/// Create a forbidden response for tokens used outside their scope
+fn forbidden_response(message: &str) -> Response {
+    error_response(
+        &Error::PolicyDenied(message.to_string()).into(),
+        ErrorCode::PolicyDenied,
     )
-        .into_response()
 }
 
 /// Legacy API key authentication (kept for backward compatibility)
@@ -0,0 +1,247 @@
+//! Read-only mirrors of TUI sessions
+//!
+//! A TUI started with `ui.mirror` posts its rendered frames to
+//! `POST /mirror/{name}`; viewers connect a WebSocket to
+//! `GET /mirror/{name}/watch` and receive every frame as JSON, starting with
+//! the latest keyframe and the diffs after it so they join mid-session with a
+//! complete screen. Viewers cannot send input. `DELETE /mirror/{name}` ends
+//! the mirror and disconnects its viewers.
+
+use crate::api::error::error_response;
+use crate::api::handlers::AppState;
+use crate::mesh::MirrorFrame;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Json, Path as UrlPath, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use spec_ai_core::broadcast::{Broadcaster, DropPolicy, Subscriber};
+use spec_ai_core::error::{Error, ErrorCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Frames kept for late viewers; later diffs wait for the next keyframe
+const MAX_BACKLOG: usize = 1000;
+/// Frames buffered per viewer before its oldest are skipped
+const VIEWER_BUFFER: usize = 256;
+
+/// Frames still to be sent to one viewer
+type Frames = Subscriber<Arc<MirrorFrame>>;
+
+/// A mirror as listed by `GET /mirror`
+#[derive(Debug, Clone, Serialize)]
+pub struct MirrorSummary {
+    pub name: String,
+    pub width: u16,
+    pub height: u16,
+    /// Sequence number of the latest frame
+    pub seq: u64,
+    pub viewers: usize,
+}
+
+struct MirrorChannel {
+    /// Latest keyframe and the diffs since
+    backlog: Vec<Arc<MirrorFrame>>,
+    frames: Broadcaster<Arc<MirrorFrame>>,
+}
+
+/// Mirrored sessions by name
+#[derive(Clone, Default)]
+pub struct MirrorHub {
+    mirrors: Arc<Mutex<HashMap<String, MirrorChannel>>>,
+}
+
+impl MirrorHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a frame and send it to the mirror's viewers
+    pub fn publish(&self, name: &str, frame: MirrorFrame) {
+        let frame = Arc::new(frame);
+        let mut mirrors = self.mirrors.lock().unwrap();
+        let channel = mirrors
+            .entry(name.to_string())
+            .or_insert_with(|| MirrorChannel {
+                backlog: Vec::new(),
+                frames: Broadcaster::new(VIEWER_BUFFER, DropPolicy::DropOldest),
+            });
+
+        if frame.keyframe {
+            channel.backlog.clear();
+        }
+        // Diffs before the first keyframe cannot be replayed on their own
+        let replayable = frame.keyframe || !channel.backlog.is_empty();
+        if replayable && channel.backlog.len() < MAX_BACKLOG {
+            channel.backlog.push(Arc::clone(&frame));
+        }
+        channel.frames.send(frame);
+    }
+
+    /// End a mirror; false if there was none by that name
+    pub fn remove(&self, name: &str) -> bool {
+        self.mirrors.lock().unwrap().remove(name).is_some()
+    }
+
+    /// Frames to replay and a receiver for the rest; `None` if unknown
+    pub fn subscribe(&self, name: &str) -> Option<(Vec<Arc<MirrorFrame>>, Frames)> {
+        let mirrors = self.mirrors.lock().unwrap();
+        let channel = mirrors.get(name)?;
+        Some((channel.backlog.clone(), channel.frames.subscribe()))
+    }
+
+    pub fn list(&self) -> Vec<MirrorSummary> {
+        let mirrors = self.mirrors.lock().unwrap();
+        let mut list: Vec<_> = mirrors
+            .iter()
+            .map(|(name, channel)| {
+                let latest = channel.backlog.last();
+                MirrorSummary {
+                    name: name.clone(),
+                    width: latest.map_or(0, |f| f.width),
+                    height: latest.map_or(0, |f| f.height),
+                    seq: latest.map_or(0, |f| f.seq),
+                    viewers: channel.frames.subscriber_count(),
+                }
+            })
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+}
+
+/// Publish a frame of a mirrored session
+pub async fn publish_frame(
+    State(state): State<AppState>,
+    UrlPath(name): UrlPath<String>,
+    Json(frame): Json<MirrorFrame>,
+) -> Response {
+    state.mirrors.publish(&name, frame);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// End a mirror, disconnecting its viewers
+pub async fn end_mirror(State(state): State<AppState>, UrlPath(name): UrlPath<String>) -> Response {
+    if state.mirrors.remove(&name) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        let err = Error::NotFound {
+            kind: "Mirror",
+            id: name,
+        };
+        error_response(&err.into(), ErrorCode::NotFound)
+    }
+}
+
+/// List the mirrored sessions
+pub async fn list_mirrors(State(state): State<AppState>) -> Response {
+    Json(state.mirrors.list()).into_response()
+}
+
+/// Watch a mirrored session over a WebSocket
+pub async fn watch_mirror(
+    State(state): State<AppState>,
+    UrlPath(name): UrlPath<String>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some((backlog, frames)) = state.mirrors.subscribe(&name) else {
+        let err = Error::NotFound {
+            kind: "Mirror",
+            id: name,
+        };
+        return error_response(&err.into(), ErrorCode::NotFound);
+    };
+    ws.on_upgrade(move |socket| stream_frames(socket, backlog, frames))
+}
+
+async fn stream_frames(mut socket: WebSocket, backlog: Vec<Arc<MirrorFrame>>, mut frames: Frames) {
+    for frame in &backlog {
+        if send_frame(&mut socket, frame).await.is_err() {
+            return;
+        }
+    }
+
+    // After skipping frames a viewer waits for a keyframe to repaint
+    let mut resync = false;
+    let mut skipped = 0;
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => {
+                    let dropped = frames.stats().dropped;
+                    if dropped > skipped {
+                        tracing::debug!("Mirror viewer skipped {} frames", dropped - skipped);
+                        skipped = dropped;
+                        resync = true;
+                    }
+                    if resync && !frame.keyframe {
+                        continue;
+                    }
+                    resync = false;
+                    if send_frame(&mut socket, &frame).await.is_err() {
+                        return;
+                    }
+                }
+                Err(_) => break,
+            },
+            // Viewers are read-only; anything but a close is ignored
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+async fn send_frame(socket: &mut WebSocket, frame: &MirrorFrame) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(frame).map_err(axum::Error::new)?;
+    socket.send(Message::Text(json.into())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spec_ai_core::broadcast::RecvError;
+
+    fn frame(seq: u64, keyframe: bool) -> MirrorFrame {
+        MirrorFrame {
+            seq,
+            keyframe,
+            width: 80,
+            height: 24,
+            ansi: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn viewers_start_from_the_latest_keyframe() {
+        let hub = MirrorHub::new();
+        assert!(hub.subscribe("tui").is_none());
+
+        hub.publish("tui", frame(0, false));
+        hub.publish("tui", frame(1, true));
+        hub.publish("tui", frame(2, false));
+        hub.publish("tui", frame(3, true));
+        hub.publish("tui", frame(4, false));
+
+        let (backlog, mut frames) = hub.subscribe("tui").unwrap();
+        let seqs: Vec<_> = backlog.iter().map(|f| f.seq).collect();
+        assert_eq!(seqs, vec![3, 4]);
+
+        hub.publish("tui", frame(5, false));
+        assert_eq!(frames.recv().await.unwrap().seq, 5);
+
+        let list = hub.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!((list[0].seq, list[0].viewers), (5, 1));
+
+        assert!(hub.remove("tui"));
+        assert_eq!(frames.recv().await.err(), Some(RecvError::Closed));
+        assert!(!hub.remove("tui"));
+    }
+}
@@ -1,9 +1,15 @@
 pub mod auth;
+pub mod error;
 pub mod graph_handlers;
 pub mod handlers;
+pub mod idempotency;
+pub mod mcp;
 pub mod mesh;
+pub mod mesh_shaping;
 pub mod middleware;
+pub mod mirror;
 pub mod models;
+pub mod runs;
 /// REST API and WebSocket server for programmatic agent access
 ///
 /// This module provides:
@@ -18,6 +24,10 @@ pub mod tls;
 pub use spec_ai_core::sync;
 
 pub use auth::{AuthService, TokenRequest, TokenResponse};
+pub use error::{error_response, status_for};
+pub use mcp::{McpServer, McpSessions};
+pub use mirror::MirrorHub;
 pub use models::{ErrorResponse, QueryRequest, QueryResponse, StreamChunk};
+pub use runs::RunRegistry;
 pub use server::{ApiConfig, ApiServer};
 pub use tls::{CertificateInfo, TlsConfig};
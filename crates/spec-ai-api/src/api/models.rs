@@ -1,5 +1,10 @@
 /// API request and response models
 use serde::{Deserialize, Serialize};
+use spec_ai_core::agent::{BudgetExceeded, RunBudget, UserQuestion};
+use spec_ai_core::config::PolicyMode;
+use spec_ai_core::embeddings::EmbeddingBatchStats;
+use spec_ai_core::error::ErrorCode;
+use spec_ai_core::policy::PolicyCapsule;
 
 /// Request to query the agent
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,7 +13,7 @@ pub struct QueryRequest {
     pub message: String,
     /// Optional session ID for conversation continuity
     pub session_id: Option<String>,
-    /// Optional agent profile to use
+    /// Agent profile to use; the server's default agent when unset
     pub agent: Option<String>,
     /// Whether to stream the response
     #[serde(default)]
@@ -17,6 +22,22 @@ pub struct QueryRequest {
     pub temperature: Option<f32>,
     /// Optional max tokens
     pub max_tokens: Option<usize>,
+    /// Restrictions of the peer that delegated this work, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_capsule: Option<PolicyCapsule>,
+    /// ID to give the run, for cancelling it with `DELETE /runs/{run_id}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// Limits for this run, replacing those of the agent profile that it sets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<RunBudget>,
+    /// Generation preset for this message, replacing the agent profile's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Name to record as the message's author when auth is disabled; with
+    /// auth enabled the token's user is the author
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
 }
 
 /// Response from the agent
@@ -31,6 +52,9 @@ pub struct QueryResponse {
     /// Tool calls made (if any)
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub tool_calls: Vec<ToolCallInfo>,
+    /// Limit that stopped the run; retry with a larger `budget` to let it go further
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_exceeded: Option<BudgetExceeded>,
     /// Processing metadata
     pub metadata: ResponseMetadata,
 }
@@ -50,6 +74,9 @@ pub struct ToolCallInfo {
     /// Error message if the tool failed
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Structured payload, shaped by the tool's output schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
 }
 
 /// Response metadata
@@ -63,6 +90,13 @@ pub struct ResponseMetadata {
     pub processing_time_ms: u64,
     /// Unique identifier for correlating with telemetry
     pub run_id: String,
+    /// Who the message was recorded as written by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Order of the message among those sent to the session while others
+    /// were queued or running, starting at 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn: Option<u64>,
 }
 
 /// Streaming response chunk
@@ -71,7 +105,11 @@ pub struct ResponseMetadata {
 pub enum StreamChunk {
     /// Initial metadata
     #[serde(rename = "start")]
-    Start { session_id: String, agent: String },
+    Start {
+        session_id: String,
+        agent: String,
+        run_id: String,
+    },
     /// Content chunk
     #[serde(rename = "chunk")]
     Content { text: String },
@@ -87,12 +125,30 @@ pub enum StreamChunk {
         name: String,
         result: serde_json::Value,
     },
+    /// The message waits for `ahead` earlier messages to the session to be answered
+    #[serde(rename = "queued")]
+    Queued { turn: u64, ahead: u64 },
+    /// The agent asked a question and waits for `POST /runs/{run_id}/answer`
+    #[serde(rename = "question")]
+    Question { question: UserQuestion },
     /// End of stream
     #[serde(rename = "end")]
-    End { metadata: ResponseMetadata },
+    End {
+        metadata: ResponseMetadata,
+        /// Limit that stopped the run, if it ran out of budget
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        budget_exceeded: Option<BudgetExceeded>,
+    },
     /// Error occurred
     #[serde(rename = "error")]
-    Error { message: String },
+    Error {
+        message: String,
+        /// Stable error code, see [`ErrorResponse::code`]
+        #[serde(default)]
+        code: String,
+        #[serde(default)]
+        retryable: bool,
+    },
 }
 
 /// Error response
@@ -100,8 +156,11 @@ pub enum StreamChunk {
 pub struct ErrorResponse {
     /// Error message
     pub error: String,
-    /// Error code
+    /// Stable error code, such as `rate_limited` or `not_found`
     pub code: String,
+    /// Whether the same request may succeed if retried later
+    #[serde(default)]
+    pub retryable: bool,
     /// Additional details
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<serde_json::Value>,
@@ -112,16 +171,32 @@ impl ErrorResponse {
         Self {
             error: error.into(),
             code: code.into(),
+            retryable: false,
             details: None,
         }
     }
 
+    /// Response for a library error with the given code
+    pub fn from_error(code: ErrorCode, err: &anyhow::Error) -> Self {
+        Self {
+            retryable: code.is_retryable(),
+            ..Self::new(code.as_str(), err.to_string())
+        }
+    }
+
     pub fn with_details(mut self, details: serde_json::Value) -> Self {
         self.details = Some(details);
         self
     }
 }
 
+/// Query parameters of `GET /auth/audit`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenAuditQuery {
+    /// Most events to return (default 100)
+    pub limit: Option<i64>,
+}
+
 /// Health check response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthResponse {
@@ -133,6 +208,9 @@ pub struct HealthResponse {
     pub uptime_seconds: u64,
     /// Active sessions count
     pub active_sessions: usize,
+    /// How well embedding requests have been batched, when they are
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_batches: Option<EmbeddingBatchStats>,
 }
 
 /// Agent list response
@@ -155,6 +233,15 @@ pub struct AgentInfo {
     /// Denied tools
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub denied_tools: Vec<String>,
+    /// Provider the agent runs on
+    #[serde(default)]
+    pub model_provider: String,
+    /// Model the agent runs, if not the provider's default
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub model_name: Option<String>,
+    /// What happens to tool calls its policy does not allow
+    #[serde(default)]
+    pub policy_mode: PolicyMode,
 }
 
 /// Semantic code search request
@@ -217,6 +304,11 @@ mod tests {
             stream: false,
             temperature: Some(0.7),
             max_tokens: Some(1000),
+            policy_capsule: None,
+            run_id: None,
+            budget: None,
+            preset: None,
+            author: None,
         };
 
         let json = serde_json::to_string(&req).unwrap();
@@ -226,6 +318,39 @@ mod tests {
         assert_eq!(deserialized.session_id, Some("sess123".to_string()));
     }
 
+    #[test]
+    fn query_budgets_round_trip() {
+        let req: QueryRequest = serde_json::from_value(serde_json::json!({
+            "message": "Refactor the parser",
+            "budget": {"max_tokens": 20000, "max_tool_calls": 10}
+        }))
+        .unwrap();
+        let budget = req.budget.unwrap();
+        assert_eq!(budget.max_tokens, Some(20_000));
+        assert_eq!(budget.max_cost, None);
+
+        let resp = QueryResponse {
+            response: "[stopped: tool call budget of 10 used up]".to_string(),
+            session_id: "sess123".to_string(),
+            agent: "coder".to_string(),
+            tool_calls: vec![],
+            budget_exceeded: Some(BudgetExceeded::ToolCalls { max: 10, used: 10 }),
+            metadata: ResponseMetadata {
+                timestamp: "2024-01-01T00:00:00Z".to_string(),
+                model: "mock".to_string(),
+                processing_time_ms: 100,
+                run_id: "run-1".to_string(),
+                author: None,
+                turn: None,
+            },
+        };
+        let json = serde_json::to_value(&resp).unwrap();
+        assert_eq!(
+            json["budget_exceeded"],
+            serde_json::json!({"limit": "tool_calls", "max": 10, "used": 10})
+        );
+    }
+
     #[test]
     fn test_query_response_serialization() {
         let resp = QueryResponse {
@@ -233,11 +358,14 @@ mod tests {
             session_id: "sess123".to_string(),
             agent: "coder".to_string(),
             tool_calls: vec![],
+            budget_exceeded: None,
             metadata: ResponseMetadata {
                 timestamp: "2024-01-01T00:00:00Z".to_string(),
                 model: "mock".to_string(),
                 processing_time_ms: 100,
                 run_id: "run-1".to_string(),
+                author: None,
+                turn: None,
             },
         };
 
@@ -254,6 +382,7 @@ mod tests {
             StreamChunk::Start {
                 session_id: "sess1".to_string(),
                 agent: "coder".to_string(),
+                run_id: "run-1".to_string(),
             },
             StreamChunk::Content {
                 text: "Hello".to_string(),
@@ -264,7 +393,10 @@ mod tests {
                     model: "mock".to_string(),
                     processing_time_ms: 100,
                     run_id: "run-1".to_string(),
+                    author: None,
+                    turn: None,
                 },
+                budget_exceeded: None,
             },
         ];
 
@@ -291,6 +423,7 @@ mod tests {
             version: "0.1.0".to_string(),
             uptime_seconds: 3600,
             active_sessions: 5,
+            embedding_batches: None,
         };
 
         let json = serde_json::to_string(&health).unwrap();
@@ -298,5 +431,6 @@ mod tests {
 
         assert_eq!(deserialized.status, "healthy");
         assert_eq!(deserialized.uptime_seconds, 3600);
+        assert!(!json.contains("embedding_batches"));
     }
 }
@@ -0,0 +1,220 @@
+//! Runs in progress, so clients can cancel them
+//!
+//! Every `/query` and `/stream` request registers the interrupt handle of its
+//! agent under a run ID, either the client's `run_id` or a generated one that
+//! is sent back in the response. `DELETE /runs/{run_id}` interrupts the run;
+//! the agent stops the model call and its tools and stores what it had so
+//! far, so the session can be continued with another query.
+//!
+//! Runs also hold the question their agent asked with `ask_user`, if any,
+//! until a client reads it from `GET /runs/{run_id}/question` and answers it
+//! with `POST /runs/{run_id}/answer`.
+
+use spec_ai_core::agent::{
+    user_question_channel, RunInterrupt, UserAnswer, UserQuestion, UserQuestionRequest,
+    UserQuestionSender,
+};
+use spec_ai_core::error::Error;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Interrupt handles of the runs in progress, by run ID
+#[derive(Clone, Default)]
+pub struct RunRegistry {
+    runs: Arc<Mutex<HashMap<String, Run>>>,
+}
+
+struct Run {
+    interrupt: RunInterrupt,
+    /// Question the agent is waiting on
+    question: Option<UserQuestionRequest>,
+}
+
+impl RunRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a run until the returned guard is dropped
+    pub fn register(&self, run_id: &str, interrupt: RunInterrupt) -> Result<ActiveRun, Error> {
+        let mut runs = self.runs.lock().unwrap();
+        if runs.contains_key(run_id) {
+            return Err(Error::InvalidRequest(format!(
+                "Run '{}' is already in progress",
+                run_id
+            )));
+        }
+        runs.insert(
+            run_id.to_string(),
+            Run {
+                interrupt,
+                question: None,
+            },
+        );
+        Ok(ActiveRun {
+            registry: self.clone(),
+            run_id: run_id.to_string(),
+        })
+    }
+
+    /// Interrupt a run; false if no run with that ID is in progress
+    pub fn cancel(&self, run_id: &str) -> bool {
+        match self.runs.lock().unwrap().get(run_id) {
+            Some(run) => {
+                run.interrupt.interrupt();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_active(&self, run_id: &str) -> bool {
+        self.runs.lock().unwrap().contains_key(run_id)
+    }
+
+    /// The question a run is waiting on, if any
+    pub fn question(&self, run_id: &str) -> Result<Option<UserQuestion>, Error> {
+        let runs = self.runs.lock().unwrap();
+        let run = runs.get(run_id).ok_or_else(|| not_found(run_id))?;
+        Ok(run
+            .question
+            .as_ref()
+            .filter(|request| !request.is_abandoned())
+            .map(|request| request.question.clone()))
+    }
+
+    /// Answer the question a run is waiting on
+    ///
+    /// An answer that does not fit the question's form is rejected and the
+    /// question stays open.
+    pub fn answer(&self, run_id: &str, answer: UserAnswer) -> Result<(), Error> {
+        let mut runs = self.runs.lock().unwrap();
+        let run = runs.get_mut(run_id).ok_or_else(|| not_found(run_id))?;
+        let no_question = || Error::NotFound {
+            kind: "Question",
+            id: run_id.to_string(),
+        };
+        let request = run.question.take().ok_or_else(no_question)?;
+        match request.respond(answer) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(no_question()),
+            Err((request, reason)) => {
+                run.question = Some(request);
+                Err(Error::InvalidRequest(reason))
+            }
+        }
+    }
+
+    fn ask(&self, run_id: &str, request: UserQuestionRequest) {
+        // A run that already ended drops the request, which declines it
+        if let Some(run) = self.runs.lock().unwrap().get_mut(run_id) {
+            run.question = Some(request);
+        }
+    }
+}
+
+fn not_found(run_id: &str) -> Error {
+    Error::NotFound {
+        kind: "Run",
+        id: run_id.to_string(),
+    }
+}
+
+/// Keeps a run cancellable while it is alive
+pub struct ActiveRun {
+    registry: RunRegistry,
+    run_id: String,
+}
+
+impl ActiveRun {
+    /// Channel for the run's agent to ask questions on
+    ///
+    /// Questions wait in the registry for a client to answer them. Each is
+    /// also sent on `notify`, so a stream can tell its client right away.
+    pub fn user_questions(
+        &self,
+        notify: Option<mpsc::UnboundedSender<UserQuestion>>,
+    ) -> UserQuestionSender {
+        let (sender, mut receiver) = user_question_channel();
+        let registry = self.registry.clone();
+        let run_id = self.run_id.clone();
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                let question = request.question.clone();
+                registry.ask(&run_id, request);
+                if let Some(notify) = &notify {
+                    let _ = notify.send(question);
+                }
+            }
+        });
+        sender
+    }
+}
+
+impl Drop for ActiveRun {
+    fn drop(&mut self) {
+        self.registry.runs.lock().unwrap().remove(&self.run_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_can_be_cancelled_while_registered() {
+        let registry = RunRegistry::new();
+        let interrupt = RunInterrupt::new();
+
+        let guard = registry.register("run-1", interrupt.clone()).unwrap();
+        assert!(registry.register("run-1", RunInterrupt::new()).is_err());
+        assert!(registry.is_active("run-1"));
+
+        assert!(registry.cancel("run-1"));
+        assert!(interrupt.is_interrupted());
+        assert!(!registry.cancel("run-2"));
+
+        drop(guard);
+        assert!(!registry.is_active("run-1"));
+        assert!(!registry.cancel("run-1"));
+    }
+
+    #[tokio::test]
+    async fn questions_wait_for_an_answer_that_fits() {
+        let registry = RunRegistry::new();
+        let run = registry.register("run-1", RunInterrupt::new()).unwrap();
+        let (notify, mut notified) = mpsc::unbounded_channel();
+        let questions = run.user_questions(Some(notify));
+        assert_eq!(registry.question("run-1").unwrap(), None);
+        assert!(registry.question("run-2").is_err());
+
+        let question = UserQuestion::from_args(&serde_json::json!({
+            "question": "Which database?",
+            "choices": ["duckdb", "sqlite"]
+        }))
+        .unwrap();
+        let (request, answer) = UserQuestionRequest::new(question.clone());
+        questions.send(request).unwrap();
+        assert_eq!(notified.recv().await, Some(question.clone()));
+        assert_eq!(registry.question("run-1").unwrap(), Some(question));
+
+        let other = UserAnswer::Choice {
+            selected: vec!["postgres".into()],
+        };
+        assert!(matches!(
+            registry.answer("run-1", other),
+            Err(Error::InvalidRequest(_))
+        ));
+        let duckdb = UserAnswer::Choice {
+            selected: vec!["duckdb".into()],
+        };
+        registry.answer("run-1", duckdb.clone()).unwrap();
+        assert_eq!(answer.await.unwrap(), duckdb);
+        assert_eq!(registry.question("run-1").unwrap(), None);
+        assert!(matches!(
+            registry.answer("run-1", UserAnswer::Declined),
+            Err(Error::NotFound { .. })
+        ));
+    }
+}
@@ -4,20 +4,27 @@ use crate::api::graph_handlers::{
     list_edges, list_nodes, stream_changelog, update_node,
 };
 use crate::api::handlers::{
-    generate_token, hash_password, health_check, list_agents, query, search, stream_query, AppState,
+    announce_presence, answer_run_question, cancel_run, generate_token, get_run_question,
+    get_session_presence, hash_password, health_check, list_agents, query, revoke_token, search,
+    stream_query, token_audit, AppState,
 };
+use crate::api::idempotency::idempotency_middleware;
+use crate::api::mcp::{mcp_message, mcp_sse};
 use crate::api::mesh::{
-    acknowledge_messages, deregister_instance, get_messages, heartbeat, list_instances,
-    register_instance, send_message, MeshClient,
+    acknowledge_messages, deregister_instance, get_messages, get_traffic, heartbeat,
+    list_instances, register_instance, send_message, MeshClient,
 };
 use crate::api::middleware::auth_middleware;
+use crate::api::mirror::{end_mirror, list_mirrors, publish_frame, watch_mirror};
 use crate::api::sync_handlers::{
     bulk_toggle_sync, configure_sync, get_sync_status, handle_sync_apply, handle_sync_request,
     list_conflicts, list_sync_configs, toggle_sync,
 };
 use crate::api::tls::TlsConfig;
 use crate::config::{AgentRegistry, AppConfig};
+use crate::events;
 use crate::persistence::Persistence;
+use crate::shutdown::CancellationToken;
 use crate::sync::{start_sync_coordinator, SyncCoordinatorConfig};
 use crate::tools::ToolRegistry;
 use anyhow::{Context, Result};
@@ -30,9 +37,13 @@ use axum_server::tls_rustls::RustlsConfig;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
+/// How long open connections get to finish once shutdown starts
+pub const GRACEFUL_SHUTDOWN_PERIOD: Duration = Duration::from_secs(30);
+
 /// Install the rustls crypto provider (call once at startup)
 fn install_crypto_provider() {
     // Install aws-lc-rs as the default crypto provider for rustls
@@ -202,6 +213,11 @@ impl ApiServer {
         &self.state.mesh_registry
     }
 
+    /// Get the request and error counters for the heartbeat
+    pub fn liveness(&self) -> &crate::heartbeat::Liveness {
+        &self.state.liveness
+    }
+
     /// Get the TLS configuration (for certificate info)
     pub fn tls_config(&self) -> &TlsConfig {
         &self.tls_config
@@ -234,6 +250,17 @@ impl ApiServer {
             // Query endpoints
             .route("/query", post(query))
             .route("/stream", post(stream_query))
+            .route("/runs/{run_id}", delete(cancel_run))
+            .route("/runs/{run_id}/question", get(get_run_question))
+            .route("/runs/{run_id}/answer", post(answer_run_question))
+            // Who is in a session, for clients sharing one
+            .route(
+                "/sessions/{session_id}/presence",
+                get(get_session_presence).post(announce_presence),
+            )
+            // Scoped tokens minted by agents
+            .route("/auth/tokens/{token_id}", delete(revoke_token))
+            .route("/auth/audit", get(token_audit))
             // Search endpoint
             .route("/api/search", post(search))
             // Mesh registry endpoints
@@ -247,6 +274,7 @@ impl ApiServer {
                 "/registry/deregister/{instance_id}",
                 delete(deregister_instance::<AppState>),
             )
+            .route("/registry/traffic", get(get_traffic::<AppState>))
             // Message routing endpoints
             .route(
                 "/messages/send/{source_instance}",
@@ -257,6 +285,10 @@ impl ApiServer {
                 "/messages/ack/{instance_id}",
                 post(acknowledge_messages::<AppState>),
             )
+            // Mirrored TUI sessions
+            .route("/mirror", get(list_mirrors))
+            .route("/mirror/{name}", post(publish_frame).delete(end_mirror))
+            .route("/mirror/{name}/watch", get(watch_mirror))
             // Graph sync endpoints
             .route("/sync/request", post(handle_sync_request))
             .route("/sync/apply", post(handle_sync_apply))
@@ -285,6 +317,15 @@ impl ApiServer {
             .route("/graph/stream", get(stream_changelog))
             // Bootstrap endpoint
             .route("/bootstrap", post(bootstrap_graph))
+            // Model Context Protocol clients
+            .route("/mcp/sse", get(mcp_sse))
+            .route("/mcp/messages", post(mcp_message))
+            // Retried mutations with an Idempotency-Key run once; layered
+            // inside auth so keys are scoped to the user
+            .layer(middleware::from_fn_with_state(
+                self.state.persistence.clone(),
+                idempotency_middleware,
+            ))
             // Apply auth middleware to protected routes
             .layer(middleware::from_fn_with_state(
                 self.state.auth_service.clone(),
@@ -314,42 +355,14 @@ impl ApiServer {
 
     /// Run the server with TLS
     pub async fn run(self) -> Result<()> {
-        // Start sync coordinator if sync is enabled
-        if self.state.config.sync.enabled {
-            self.start_sync_coordinator_background();
-        }
-
-        let app = self.build_router();
-        let bind_addr: SocketAddr = self
-            .config
-            .bind_address()
-            .parse()
-            .context("Invalid bind address")?;
-
-        // Build rustls config
-        let rustls_config = RustlsConfig::from_der(
-            vec![self.tls_config.certificate.clone()],
-            self.tls_config.private_key.clone(),
-        )
-        .await
-        .context("Failed to create TLS config")?;
-
-        tracing::info!(
-            "Starting HTTPS server on {} (fingerprint: {})",
-            bind_addr,
-            self.tls_config.fingerprint
-        );
-
-        axum_server::bind_rustls(bind_addr, rustls_config)
-            .serve(app.into_make_service())
-            .await
-            .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
-
-        Ok(())
+        self.run_until(CancellationToken::new()).await
     }
 
-    /// Start the sync coordinator as a background task
-    fn start_sync_coordinator_background(&self) {
+    /// Start the sync coordinator as a background task that stops with `shutdown`
+    fn start_sync_coordinator_background(
+        &self,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
         let persistence = Arc::new(self.state.persistence.clone());
         let mesh_registry = Arc::new(self.state.mesh_registry.clone());
         let mesh_client = Arc::new(MeshClient::new("localhost", self.config.port));
@@ -371,17 +384,24 @@ impl ApiServer {
             }
         }
 
-        // Spawn the sync coordinator
-        tokio::spawn(async move {
-            let _handle =
-                start_sync_coordinator(persistence, mesh_registry, mesh_client, sync_config).await;
-            // The coordinator runs indefinitely
-        });
-
         tracing::info!(
-            "Started sync coordinator with {} configured namespaces",
+            "Starting sync coordinator with {} configured namespaces",
             self.state.config.sync.namespaces.len()
         );
+
+        tokio::spawn(async move {
+            let coordinator = start_sync_coordinator(
+                persistence,
+                mesh_registry,
+                mesh_client,
+                sync_config,
+                shutdown,
+            )
+            .await;
+            if let Err(e) = coordinator.await {
+                tracing::error!("Sync coordinator task failed: {}", e);
+            }
+        })
     }
 
     /// Run the server with TLS and graceful shutdown
@@ -389,11 +409,20 @@ impl ApiServer {
         self,
         shutdown_signal: impl std::future::Future<Output = ()> + Send + 'static,
     ) -> Result<()> {
-        // Start sync coordinator if sync is enabled
-        if self.state.config.sync.enabled {
-            self.start_sync_coordinator_background();
-        }
+        let shutdown = CancellationToken::new();
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            shutdown_signal.await;
+            trigger.cancel();
+        });
+        self.run_until(shutdown).await
+    }
 
+    /// Run the server with TLS until `shutdown` is cancelled
+    ///
+    /// Open connections get [`GRACEFUL_SHUTDOWN_PERIOD`] to finish, and the
+    /// sync coordinator finishes its current cycle before this returns.
+    pub async fn run_until(self, shutdown: CancellationToken) -> Result<()> {
         let app = self.build_router();
         let bind_addr: SocketAddr = self
             .config
@@ -415,23 +444,43 @@ impl ApiServer {
             self.tls_config.fingerprint
         );
 
+        // Sync operations are logged before any agent starts the exporter
+        events::init(&self.state.config.event_log);
+
+        // Start sync coordinator if sync is enabled
+        let sync_shutdown = shutdown.child_token();
+        let sync_task = if self.state.config.sync.enabled {
+            Some(self.start_sync_coordinator_background(sync_shutdown.clone()))
+        } else {
+            None
+        };
+
         // Create handle for graceful shutdown
         let handle = axum_server::Handle::new();
         let handle_clone = handle.clone();
+        let listener_shutdown = shutdown.clone();
 
         // Spawn shutdown listener
         tokio::spawn(async move {
-            shutdown_signal.await;
-            handle_clone.graceful_shutdown(Some(std::time::Duration::from_secs(30)));
+            listener_shutdown.cancelled().await;
+            handle_clone.graceful_shutdown(Some(GRACEFUL_SHUTDOWN_PERIOD));
         });
 
-        axum_server::bind_rustls(bind_addr, rustls_config)
+        let served = axum_server::bind_rustls(bind_addr, rustls_config)
             .handle(handle)
             .serve(app.into_make_service())
             .await
-            .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
+            .map_err(|e| anyhow::anyhow!("Server error: {}", e));
+
+        // Stop background work even when the server exited on its own
+        if let Some(sync_task) = sync_task {
+            sync_shutdown.cancel();
+            if let Err(e) = sync_task.await {
+                tracing::error!("Sync coordinator task failed: {}", e);
+            }
+        }
 
-        Ok(())
+        served
     }
 }
 
@@ -1,4 +1,5 @@
 use crate::api::handlers::AppState;
+use crate::events::{self, Event};
 use axum::extract::{Json, Path, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
@@ -162,6 +163,12 @@ pub async fn handle_sync_request(
         }
     };
 
+    events::emit(Event::sync_sent(
+        &payload,
+        request.graph_name.as_deref().unwrap_or("default"),
+        Some(&request.requesting_instance),
+    ));
+
     (
         StatusCode::OK,
         Json(SyncResponse {
@@ -185,21 +192,24 @@ pub async fn handle_sync_apply(
     let graph_name = payload.graph_name.as_deref().unwrap_or("default");
 
     match sync_engine.apply_sync(&payload, graph_name).await {
-        Ok(stats) => (
-            StatusCode::OK,
-            Json(serde_json::json!({
-                "success": true,
-                "message": "Sync applied successfully",
-                "stats": {
-                    "nodes_applied": stats.nodes_applied,
-                    "edges_applied": stats.edges_applied,
-                    "tombstones_applied": stats.tombstones_applied,
-                    "conflicts_detected": stats.conflicts_detected,
-                    "conflicts_resolved": stats.conflicts_resolved,
-                    "sync_type": stats.sync_type
-                }
-            })),
-        ),
+        Ok(stats) => {
+            events::emit(Event::sync_applied(&payload, graph_name, None, &stats));
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "success": true,
+                    "message": "Sync applied successfully",
+                    "stats": {
+                        "nodes_applied": stats.nodes_applied,
+                        "edges_applied": stats.edges_applied,
+                        "tombstones_applied": stats.tombstones_applied,
+                        "conflicts_detected": stats.conflicts_detected,
+                        "conflicts_resolved": stats.conflicts_resolved,
+                        "sync_type": stats.sync_type
+                    }
+                })),
+            )
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({
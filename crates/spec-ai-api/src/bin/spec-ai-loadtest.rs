@@ -0,0 +1,112 @@
+//! Load test for the spec-ai API server
+//!
+//! Without `--url`, starts the server in-process on the mock provider and an
+//! in-memory database. Exits with status 1 when a threshold is exceeded.
+//!
+//! ```text
+//! cargo run --release -p spec-ai-api --features loadtest --bin spec-ai-loadtest -- \
+//!     --queries 16 --streams 8 --duration 15 --max-p99-ms 1500 --max-error-rate 0.01
+//! ```
+
+use anyhow::Result;
+use clap::Parser;
+use spec_ai_api::loadtest::{self, LoadTestConfig, Thresholds};
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "spec-ai-loadtest")]
+#[command(about = "Drive the spec-ai API with concurrent queries and streams")]
+struct Args {
+    /// Server to load, such as https://127.0.0.1:3000; an in-process mock
+    /// server when unset
+    #[arg(long)]
+    url: Option<String>,
+    /// Bearer token for servers with auth enabled
+    #[arg(long)]
+    token: Option<String>,
+    /// Workers sending /query requests back to back
+    #[arg(long, default_value = "8")]
+    queries: usize,
+    /// Workers sending /stream requests back to back
+    #[arg(long, default_value = "4")]
+    streams: usize,
+    /// Seconds to keep sending
+    #[arg(long, default_value = "10")]
+    duration: u64,
+    /// Seconds after which a request counts as an error
+    #[arg(long, default_value = "30")]
+    timeout: u64,
+    /// Message sent with every request
+    #[arg(long)]
+    message: Option<String>,
+    /// Agent profile to query
+    #[arg(long)]
+    agent: Option<String>,
+    /// Fail when any workload's p99 latency exceeds this many milliseconds
+    #[arg(long)]
+    max_p99_ms: Option<f64>,
+    /// Fail when any workload's error rate exceeds this fraction
+    #[arg(long)]
+    max_error_rate: Option<f64>,
+    /// Fail when any workload completes fewer requests per second
+    #[arg(long)]
+    min_throughput: Option<f64>,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+
+    let (base_url, server) = match &args.url {
+        Some(url) => (url.trim_end_matches('/').to_string(), None),
+        None => {
+            let server = loadtest::start_mock_server().await?;
+            (server.base_url.clone(), Some(server))
+        }
+    };
+
+    let mut config = LoadTestConfig::new(base_url);
+    config.token = args.token;
+    config.query_workers = args.queries;
+    config.stream_workers = args.streams;
+    config.duration = Duration::from_secs(args.duration);
+    config.timeout = Duration::from_secs(args.timeout);
+    config.agent = args.agent;
+    if let Some(message) = args.message {
+        config.message = message;
+    }
+
+    eprintln!(
+        "Loading {} with {} query and {} stream workers for {}s",
+        config.base_url, config.query_workers, config.stream_workers, args.duration
+    );
+    let report = loadtest::run(&config).await?;
+    if let Some(server) = server {
+        server.stop().await?;
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report);
+    }
+
+    let thresholds = Thresholds {
+        max_p99_ms: args.max_p99_ms,
+        max_error_rate: args.max_error_rate,
+        min_throughput: args.min_throughput,
+    };
+    let violations = thresholds.violations(&report);
+    for violation in &violations {
+        eprintln!("Threshold exceeded: {}", violation);
+    }
+    Ok(if violations.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -1,5 +1,9 @@
 pub mod api;
+#[cfg(feature = "loadtest")]
+pub mod loadtest;
 pub mod sync;
 pub use spec_ai_config::{config, persistence};
-pub use spec_ai_core::{agent, embeddings, mesh, spec, tools};
+pub use spec_ai_core::{
+    agent, embeddings, events, heartbeat, mesh, presence, shutdown, spec, tools,
+};
 pub use spec_ai_policy::{plugin, policy};
@@ -0,0 +1,530 @@
+//! Load Testing
+//!
+//! Drives `/query` and `/stream` with concurrent workers for a fixed time
+//! and reports latency percentiles, throughput and error rates per workload.
+//! [`start_mock_server`] runs the server in-process on the mock provider, so
+//! the numbers measure the server rather than a model, and [`Thresholds`]
+//! turn a report into pass or fail for CI.
+//!
+//! Used by the `spec-ai-loadtest` binary (feature `loadtest`).
+
+use crate::api::models::{QueryRequest, StreamChunk};
+use crate::api::server::{ApiConfig, ApiServer};
+use crate::config::{AgentProfile, AgentRegistry, AppConfig};
+use crate::persistence::Persistence;
+use crate::shutdown::CancellationToken;
+use crate::tools::ToolRegistry;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Requests a worker sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Workload {
+    /// `POST /query`, timed until the full response arrives
+    Query,
+    /// `POST /stream`, timed to the first event and to the end event
+    Stream,
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Workload::Query => write!(f, "query"),
+            Workload::Stream => write!(f, "stream"),
+        }
+    }
+}
+
+/// What to send and for how long
+#[derive(Debug, Clone)]
+pub struct LoadTestConfig {
+    /// Server to load, such as `https://127.0.0.1:3000`
+    pub base_url: String,
+    /// Bearer token, for servers with auth enabled
+    pub token: Option<String>,
+    /// Workers sending queries back to back
+    pub query_workers: usize,
+    /// Workers sending streams back to back
+    pub stream_workers: usize,
+    /// How long workers keep sending
+    pub duration: Duration,
+    /// Message sent with every request
+    pub message: String,
+    /// Agent profile to query; the server's default when unset
+    pub agent: Option<String>,
+    /// Requests taking longer than this count as errors
+    pub timeout: Duration,
+}
+
+impl LoadTestConfig {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            token: None,
+            query_workers: 8,
+            stream_workers: 4,
+            duration: Duration::from_secs(10),
+            message: "Summarize the status of the build".to_string(),
+            agent: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// One finished request
+#[derive(Debug, Clone)]
+struct Sample {
+    workload: Workload,
+    latency: Duration,
+    /// Time to the first streamed event
+    first_event: Option<Duration>,
+    error: Option<String>,
+}
+
+/// Latency distribution in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+impl LatencySummary {
+    /// Percentiles of `samples`, or `None` when there are none
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1e3).collect();
+        ms.sort_by(f64::total_cmp);
+        // Nearest rank, so p99 of 100 samples is the 99th
+        let rank = |p: f64| ms[((p * ms.len() as f64).ceil() as usize).clamp(1, ms.len()) - 1];
+        Some(Self {
+            p50: rank(0.50),
+            p90: rank(0.90),
+            p99: rank(0.99),
+            max: ms[ms.len() - 1],
+            mean: ms.iter().sum::<f64>() / ms.len() as f64,
+        })
+    }
+}
+
+/// Results of one workload
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkloadReport {
+    pub workload: Workload,
+    pub requests: usize,
+    pub errors: usize,
+    /// Successful requests per second
+    pub throughput: f64,
+    /// Latency of successful requests
+    pub latency: Option<LatencySummary>,
+    /// Time to the first event of successful streams
+    pub first_event: Option<LatencySummary>,
+    /// Error messages and how often each occurred
+    pub error_kinds: BTreeMap<String, usize>,
+}
+
+impl WorkloadReport {
+    /// Fraction of requests that failed
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+/// Results of a load test
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadReport {
+    /// Wall time the workers ran for, in seconds
+    pub elapsed_secs: f64,
+    pub workloads: Vec<WorkloadReport>,
+}
+
+impl LoadReport {
+    fn from_samples(samples: Vec<Sample>, elapsed: Duration) -> Self {
+        let mut by_workload: BTreeMap<Workload, Vec<Sample>> = BTreeMap::new();
+        for sample in samples {
+            by_workload.entry(sample.workload).or_default().push(sample);
+        }
+        let workloads = by_workload
+            .into_iter()
+            .map(|(workload, samples)| {
+                let mut error_kinds = BTreeMap::new();
+                let mut latencies = Vec::new();
+                let mut first_events = Vec::new();
+                for sample in &samples {
+                    match &sample.error {
+                        Some(error) => *error_kinds.entry(error.clone()).or_insert(0) += 1,
+                        None => {
+                            latencies.push(sample.latency);
+                            first_events.extend(sample.first_event);
+                        }
+                    }
+                }
+                WorkloadReport {
+                    workload,
+                    requests: samples.len(),
+                    errors: samples.len() - latencies.len(),
+                    throughput: latencies.len() as f64 / elapsed.as_secs_f64(),
+                    latency: LatencySummary::from_samples(&latencies),
+                    first_event: LatencySummary::from_samples(&first_events),
+                    error_kinds,
+                }
+            })
+            .collect();
+        Self {
+            elapsed_secs: elapsed.as_secs_f64(),
+            workloads,
+        }
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<8} {:>8} {:>7} {:>9} {:>9} {:>9} {:>9} {:>9} {:>11}",
+            "workload",
+            "requests",
+            "errors",
+            "req/s",
+            "p50 ms",
+            "p90 ms",
+            "p99 ms",
+            "max ms",
+            "first p50"
+        )?;
+        for report in &self.workloads {
+            let latency = |pick: fn(&LatencySummary) -> f64| {
+                report
+                    .latency
+                    .as_ref()
+                    .map(|summary| format!("{:.1}", pick(summary)))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            let first = report
+                .first_event
+                .map(|summary| format!("{:.1}", summary.p50))
+                .unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f,
+                "{:<8} {:>8} {:>6.1}% {:>9.1} {:>9} {:>9} {:>9} {:>9} {:>11}",
+                report.workload,
+                report.requests,
+                report.error_rate() * 100.0,
+                report.throughput,
+                latency(|s| s.p50),
+                latency(|s| s.p90),
+                latency(|s| s.p99),
+                latency(|s| s.max),
+                first
+            )?;
+            for (error, count) in &report.error_kinds {
+                writeln!(f, "  {} x {}", count, error)?;
+            }
+        }
+        write!(f, "ran for {:.1}s", self.elapsed_secs)
+    }
+}
+
+/// Limits a report must stay within, applied to every workload
+#[derive(Debug, Clone, Default)]
+pub struct Thresholds {
+    pub max_p99_ms: Option<f64>,
+    pub max_error_rate: Option<f64>,
+    /// Minimum successful requests per second
+    pub min_throughput: Option<f64>,
+}
+
+impl Thresholds {
+    /// Every limit `report` exceeds, described for the CI log
+    pub fn violations(&self, report: &LoadReport) -> Vec<String> {
+        let mut violations = Vec::new();
+        for workload in &report.workloads {
+            let name = workload.workload;
+            if let (Some(max), Some(latency)) = (self.max_p99_ms, workload.latency) {
+                if latency.p99 > max {
+                    violations.push(format!(
+                        "{} p99 latency {:.1}ms exceeds {:.1}ms",
+                        name, latency.p99, max
+                    ));
+                }
+            }
+            if let Some(max) = self.max_error_rate {
+                if workload.error_rate() > max {
+                    violations.push(format!(
+                        "{} error rate {:.2}% exceeds {:.2}%",
+                        name,
+                        workload.error_rate() * 100.0,
+                        max * 100.0
+                    ));
+                }
+            }
+            if let Some(min) = self.min_throughput {
+                if workload.throughput < min {
+                    violations.push(format!(
+                        "{} throughput {:.1} req/s is below {:.1} req/s",
+                        name, workload.throughput, min
+                    ));
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// A server started by [`start_mock_server`]
+pub struct MockServer {
+    pub base_url: String,
+    shutdown: CancellationToken,
+    task: JoinHandle<Result<()>>,
+}
+
+impl MockServer {
+    /// Stop the server and wait for it to finish
+    pub async fn stop(self) -> Result<()> {
+        self.shutdown.cancel();
+        self.task.await.context("Server task panicked")?
+    }
+}
+
+/// Start the API server in-process on the mock provider and an in-memory
+/// database, listening on a free local port
+pub async fn start_mock_server() -> Result<MockServer> {
+    let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    let persistence = Persistence::in_memory()?;
+    let mut app_config = AppConfig::default();
+    app_config.model.provider = "mock".to_string();
+    app_config
+        .agents
+        .insert("default".to_string(), AgentProfile::default());
+    let agent_registry = Arc::new(AgentRegistry::new(
+        app_config.agents.clone(),
+        persistence.clone(),
+    ));
+    let tool_registry = Arc::new(ToolRegistry::with_builtin_tools(
+        Some(Arc::new(persistence.clone())),
+        None,
+        None,
+    ));
+    let server = ApiServer::new(
+        ApiConfig::new().with_port(port),
+        persistence,
+        agent_registry,
+        tool_registry,
+        app_config,
+    )?;
+
+    let shutdown = CancellationToken::new();
+    let task = tokio::spawn(server.run_until(shutdown.clone()));
+    let base_url = format!("https://127.0.0.1:{}", port);
+
+    // Wait for the listener before handing out the address
+    let client = client(Duration::from_secs(1))?;
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        match client.get(format!("{}/health", base_url)).send().await {
+            Ok(response) if response.status().is_success() => break,
+            _ if Instant::now() > deadline || task.is_finished() => {
+                shutdown.cancel();
+                bail!("Mock server did not become healthy on {}", base_url)
+            }
+            _ => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+    Ok(MockServer {
+        base_url,
+        shutdown,
+        task,
+    })
+}
+
+/// Run the workloads in `config` against its server
+pub async fn run(config: &LoadTestConfig) -> Result<LoadReport> {
+    if config.query_workers + config.stream_workers == 0 {
+        bail!("A load test needs at least one query or stream worker");
+    }
+    let client = client(config.timeout)?;
+    let started = Instant::now();
+    let deadline = started + config.duration;
+
+    let workers = std::iter::repeat_n(Workload::Query, config.query_workers)
+        .chain(std::iter::repeat_n(Workload::Stream, config.stream_workers))
+        .map(|workload| {
+            let client = client.clone();
+            let config = config.clone();
+            tokio::spawn(async move {
+                let mut samples = Vec::new();
+                while Instant::now() < deadline {
+                    samples.push(send(&client, &config, workload).await);
+                }
+                samples
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut samples = Vec::new();
+    for worker in workers {
+        samples.extend(worker.await.context("Load test worker panicked")?);
+    }
+    Ok(LoadReport::from_samples(samples, started.elapsed()))
+}
+
+/// Client for the server's self-signed certificate
+fn client(timeout: Duration) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .timeout(timeout)
+        .build()
+        .context("Failed to build HTTP client")
+}
+
+async fn send(client: &reqwest::Client, config: &LoadTestConfig, workload: Workload) -> Sample {
+    let started = Instant::now();
+    let mut first_event = None;
+    let result: std::result::Result<(), String> = async {
+        let path = match workload {
+            Workload::Query => "query",
+            Workload::Stream => "stream",
+        };
+        let request = QueryRequest {
+            message: config.message.clone(),
+            session_id: None,
+            agent: config.agent.clone(),
+            stream: false,
+            temperature: None,
+            max_tokens: None,
+            policy_capsule: None,
+            run_id: None,
+            budget: None,
+            preset: None,
+            author: None,
+        };
+        let mut builder = client
+            .post(format!("{}/{}", config.base_url, path))
+            .json(&request);
+        if let Some(token) = &config.token {
+            builder = builder.bearer_auth(token);
+        }
+        let mut response = builder.send().await.map_err(|err| request_error(&err))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("HTTP {}", status.as_u16()));
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|err| request_error(&err))? {
+            first_event.get_or_insert_with(|| started.elapsed());
+            body.extend_from_slice(&chunk);
+        }
+        match workload {
+            Workload::Query => Ok(()),
+            Workload::Stream => stream_outcome(&String::from_utf8_lossy(&body)),
+        }
+    }
+    .await;
+
+    Sample {
+        workload,
+        latency: started.elapsed(),
+        first_event: first_event.filter(|_| workload == Workload::Stream),
+        error: result.err(),
+    }
+}
+
+/// Error label that groups like failures together
+fn request_error(err: &reqwest::Error) -> String {
+    if err.is_timeout() {
+        "timed out".to_string()
+    } else if err.is_connect() {
+        "connection failed".to_string()
+    } else {
+        "request failed".to_string()
+    }
+}
+
+/// Whether an SSE body ended with an `end` event
+fn stream_outcome(body: &str) -> std::result::Result<(), String> {
+    let last = body
+        .lines()
+        .rev()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .find_map(|data| serde_json::from_str::<StreamChunk>(data.trim()).ok());
+    match last {
+        Some(StreamChunk::End { .. }) => Ok(()),
+        Some(StreamChunk::Error { code, .. }) => Err(format!("stream error {}", code)),
+        _ => Err("stream ended early".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(workload: Workload, ms: u64, error: Option<&str>) -> Sample {
+        Sample {
+            workload,
+            latency: Duration::from_millis(ms),
+            first_event: (workload == Workload::Stream).then(|| Duration::from_millis(ms / 2)),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn reports_percentiles_and_error_rates_per_workload() {
+        let mut samples: Vec<Sample> = (1..=100)
+            .map(|ms| sample(Workload::Query, ms, None))
+            .collect();
+        samples.push(sample(Workload::Stream, 40, None));
+        samples.push(sample(Workload::Stream, 0, Some("timed out")));
+        let report = LoadReport::from_samples(samples, Duration::from_secs(2));
+
+        let query = &report.workloads[0];
+        let latency = query.latency.unwrap();
+        assert_eq!((latency.p50, latency.p90, latency.p99), (50.0, 90.0, 99.0));
+        assert_eq!(query.throughput, 50.0);
+
+        let stream = &report.workloads[1];
+        assert_eq!((stream.requests, stream.errors), (2, 1));
+        assert_eq!(stream.first_event.unwrap().p50, 20.0);
+        assert_eq!(stream.error_kinds["timed out"], 1);
+
+        let thresholds = Thresholds {
+            max_p99_ms: Some(95.0),
+            max_error_rate: Some(0.1),
+            min_throughput: None,
+        };
+        assert_eq!(
+            thresholds.violations(&report),
+            vec![
+                "query p99 latency 99.0ms exceeds 95.0ms",
+                "stream error rate 50.00% exceeds 10.00%"
+            ]
+        );
+        assert!(Thresholds::default().violations(&report).is_empty());
+    }
+
+    #[test]
+    fn streams_must_reach_their_end_event() {
+        let end = r#"data: {"type":"start","session_id":"s","agent":"a","run_id":"r"}
+
+data: {"type":"end","metadata":{"timestamp":"1","model":"mock","processing_time_ms":3,"run_id":"r"}}
+"#;
+        assert!(stream_outcome(end).is_ok());
+        let cut = r#"data: {"type":"start","session_id":"s","agent":"a","run_id":"r"}"#;
+        assert_eq!(stream_outcome(cut).unwrap_err(), "stream ended early");
+    }
+}
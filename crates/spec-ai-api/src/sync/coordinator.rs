@@ -5,8 +5,10 @@ use std::time::Duration;
 use tokio::time;
 use tracing::{debug, error, info, warn};
 
-use crate::api::mesh::{MeshClient, MeshRegistry};
+use crate::api::mesh::{MeshClient, MeshInstance, MeshRegistry};
+use crate::events::{self, Event};
 use crate::persistence::Persistence;
+use crate::shutdown::CancellationToken;
 use spec_ai_core::sync::{GraphSyncPayload, SyncEngine, SyncPersistenceAdapter};
 
 /// Configuration for the sync coordinator
@@ -72,8 +74,10 @@ impl SyncCoordinator {
         }
     }
 
-    /// Start the background sync coordinator
-    pub async fn start(self: Arc<Self>) {
+    /// Run sync cycles until `shutdown` is cancelled
+    ///
+    /// A cycle already in progress is finished so no graph is left half-applied.
+    pub async fn start(self: Arc<Self>, shutdown: CancellationToken) {
         info!(
             "Starting sync coordinator with interval {} seconds",
             self.config.sync_interval_secs
@@ -83,12 +87,17 @@ impl SyncCoordinator {
         interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
 
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
 
             if let Err(e) = self.run_sync_cycle().await {
                 error!("Sync cycle failed: {}", e);
             }
         }
+
+        info!("Sync coordinator stopped");
     }
 
     /// Run a single sync cycle
@@ -135,13 +144,14 @@ impl SyncCoordinator {
                 let graph_name = graph_name.clone();
                 let peer_id = peer.instance_id.clone();
                 let peer_url = format!("http://{}:{}", peer.hostname, peer.port);
+                let clock_offset = peer_clock_offset(&peers, &self.instance_id, peer);
 
                 // Spawn sync task
                 let task = tokio::spawn(async move {
                     let _permit = permit; // Hold permit until task completes
 
                     match self_clone
-                        .sync_with_peer(&session_id, &graph_name, &peer_id, &peer_url)
+                        .sync_with_peer(&session_id, &graph_name, &peer_id, &peer_url, clock_offset)
                         .await
                     {
                         Ok(_) => {
@@ -209,6 +219,7 @@ impl SyncCoordinator {
         graph_name: &str,
         peer_id: &str,
         peer_url: &str,
+        clock_offset: chrono::Duration,
     ) -> Result<()> {
         debug!(
             "Syncing {}/{} with peer {} at {}",
@@ -217,7 +228,8 @@ impl SyncCoordinator {
 
         // Create sync engine using the adapter
         let adapter = SyncPersistenceAdapter::new((*self.persistence).clone());
-        let sync_engine = SyncEngine::new(adapter, self.instance_id.clone());
+        let sync_engine =
+            SyncEngine::new(adapter, self.instance_id.clone()).with_peer_clock_offset(clock_offset);
 
         // Get our current vector clock
         let our_vc = self
@@ -258,6 +270,12 @@ impl SyncCoordinator {
 
             // Apply the sync payload
             let stats = sync_engine.apply_sync(&sync_payload, graph_name).await?;
+            events::emit(Event::sync_applied(
+                &sync_payload,
+                graph_name,
+                Some(peer_id),
+                &stats,
+            ));
 
             info!(
                 "Applied sync from peer {}: {} nodes, {} edges, {} conflicts",
@@ -275,11 +293,66 @@ impl SyncCoordinator {
     }
 }
 
+/// How far `peer`'s clock runs ahead of ours, from the offsets both report
+/// to the registry; zero when either is unknown
+fn peer_clock_offset(
+    instances: &[MeshInstance],
+    own_id: &str,
+    peer: &MeshInstance,
+) -> chrono::Duration {
+    let own = instances
+        .iter()
+        .find(|instance| instance.instance_id == own_id)
+        .and_then(|instance| instance.clock);
+    match (peer.clock, own) {
+        (Some(peer), Some(own)) => chrono::Duration::milliseconds(peer.offset_ms - own.offset_ms),
+        _ => chrono::Duration::zero(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[test]
+    fn peer_clock_offset_is_relative_to_own_clock() {
+        use crate::api::mesh::ClockSample;
+
+        let instance = |id: &str, offset_ms: Option<i64>| MeshInstance {
+            instance_id: id.to_string(),
+            hostname: "localhost".to_string(),
+            port: 3000,
+            capabilities: vec![],
+            is_leader: false,
+            last_heartbeat: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+            agent_profiles: vec![],
+            metrics: Default::default(),
+            clock: offset_ms.map(|offset_ms| ClockSample {
+                offset_ms,
+                rtt_ms: 10,
+            }),
+        };
+        let instances = vec![
+            instance("me", Some(-500)),
+            instance("ahead", Some(1_500)),
+            instance("unknown", None),
+        ];
+        assert_eq!(
+            peer_clock_offset(&instances, "me", &instances[1]),
+            chrono::Duration::milliseconds(2_000)
+        );
+        assert_eq!(
+            peer_clock_offset(&instances, "me", &instances[2]),
+            chrono::Duration::zero()
+        );
+        assert_eq!(
+            peer_clock_offset(&instances, "other", &instances[1]),
+            chrono::Duration::zero()
+        );
+    }
+
     #[test]
     fn get_sync_enabled_sessions_returns_empty_when_no_sync_enabled() {
         let dir = tempdir().expect("tempdir");
@@ -361,12 +434,13 @@ mod tests {
     }
 }
 
-/// Start the sync coordinator as a background task
+/// Start the sync coordinator as a background task that stops with `shutdown`
 pub async fn start_sync_coordinator(
     persistence: Arc<Persistence>,
     mesh_registry: Arc<MeshRegistry>,
     mesh_client: Arc<MeshClient>,
     config: SyncCoordinatorConfig,
+    shutdown: CancellationToken,
 ) -> tokio::task::JoinHandle<()> {
     let coordinator = Arc::new(SyncCoordinator::new(
         persistence,
@@ -376,6 +450,6 @@ pub async fn start_sync_coordinator(
     ));
 
     tokio::spawn(async move {
-        coordinator.start().await;
+        coordinator.start(shutdown).await;
     })
 }
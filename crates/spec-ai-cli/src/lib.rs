@@ -1,15 +1,37 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use spec_ai_config::config::{AppConfig, PricingConfig};
+use spec_ai_config::persistence::profile::ProfileSelection;
+use spec_ai_config::persistence::{HeartbeatRecord, Persistence, UsageSummaryRecord};
+use spec_ai_core::agent::{RunTranscript, TranscriptMode};
 use spec_ai_core::cli::CliState;
+use spec_ai_core::error::code_of;
+use spec_ai_core::graph_export::{
+    parse_node_type, ExportFormat, Selection, Subgraph, DEFAULT_EXPORT_LIMIT,
+};
+use spec_ai_core::heartbeat::effective_status;
+use spec_ai_core::profile_archive::{self, ProfilePayload};
+use spec_ai_core::prompts::{PromptLibrary, PromptSource};
 use spec_ai_core::spec::AgentSpec;
-use std::path::PathBuf;
+use spec_ai_core::startup::millis;
+use spec_ai_core::types::NodeType;
+use spec_ai_plugin::{
+    expand_tilde, scaffold_plugin, PluginIndex, PluginInstaller, PluginKind, PluginManifest,
+};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
 use walkdir::WalkDir;
 
 #[cfg(feature = "api")]
 use {
-    spec_ai_api::api::server::{ApiConfig, ApiServer},
+    spec_ai_api::api::server::{ApiConfig, ApiServer, GRACEFUL_SHUTDOWN_PERIOD},
     spec_ai_config::config::AgentRegistry,
-    spec_ai_config::persistence::Persistence,
+    spec_ai_core::heartbeat::Heartbeat,
+    spec_ai_core::maintenance,
+    spec_ai_core::shutdown::{shutdown_signal, ShutdownCoordinator, DEFAULT_STOP_TIMEOUT},
     spec_ai_core::tools::ToolRegistry,
     std::sync::Arc,
 };
@@ -23,6 +45,7 @@ struct Cli {
     config: Option<PathBuf>,
 
     /// Launch mode. Defaults to the new TUI; use `--mode legacy` for the legacy REPL.
+    /// Builds without the `tui` feature always start the legacy REPL.
     #[arg(
         long = "mode",
         value_enum,
@@ -33,6 +56,11 @@ struct Cli {
     )]
     mode: TuiMode,
 
+    /// Agent profile to run, e.g. `--agent coder`; the server's default for
+    /// requests that do not name one
+    #[arg(long, global = true)]
+    agent: Option<String>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -44,6 +72,12 @@ enum Commands {
         /// Spec files or directories to run. If not provided, uses examples/spec/smoke.spec
         #[arg(value_name = "SPEC_OR_DIR")]
         specs: Vec<PathBuf>,
+        /// Record every model and tool call of the run to this transcript file
+        #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+        record: Option<PathBuf>,
+        /// Replay a recorded transcript instead of calling models and tools
+        #[arg(long, value_name = "FILE")]
+        replay: Option<PathBuf>,
     },
     /// Start the API server for agent mesh functionality
     Server {
@@ -57,6 +91,211 @@ enum Commands {
         #[arg(long)]
         join: Option<String>,
     },
+    /// Serve tools, knowledge graphs and agents to an MCP client over stdio
+    Mcp,
+    /// Inspect recorded token usage and estimated cost
+    Usage {
+        #[command(subcommand)]
+        command: UsageCommands,
+    },
+    /// Maintain knowledge graphs
+    Graph {
+        #[command(subcommand)]
+        command: GraphCommands,
+    },
+    /// Manage the prompt template library
+    Prompts {
+        #[command(subcommand)]
+        command: PromptCommands,
+    },
+    /// Create and manage plugins
+    Plugin {
+        #[command(subcommand)]
+        command: PluginCommands,
+    },
+    /// Move an agent's configuration, sessions, graphs and strategies between machines
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+    /// Start every subsystem once and show how long each one takes
+    Doctor,
+    /// Remove stored history outside the `[retention]` limits now
+    Prune,
+    /// Show liveness, load and the last error of headless instances
+    Status {
+        /// Ask a running mesh registry (HOST:PORT) instead of reading the database
+        #[arg(long, value_name = "HOST:PORT")]
+        registry: Option<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: StatusFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum GraphCommands {
+    /// Encrypt graph properties with the current key of their namespace
+    ///
+    /// Run after adding a key to the front of a namespace's `keys`, or after
+    /// encrypting a namespace that already has data. Once it finishes, older
+    /// keys can be removed from the config.
+    Reencrypt {
+        /// Namespace to rewrite (defaults to every encrypted namespace)
+        namespace: Option<String>,
+    },
+    /// Render a session's graph, or part of it, as Graphviz DOT or Mermaid
+    ///
+    /// Exports the session's nodes, optionally of one `--type`, or with
+    /// `--node` that node and its neighbors up to `--hops` edges away. Only
+    /// edges between exported nodes are drawn.
+    Export {
+        /// Session whose graph to export
+        session: String,
+        /// Output format
+        #[arg(long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+        /// Export the neighborhood of this node id
+        #[arg(long, conflicts_with = "node_type")]
+        node: Option<i64>,
+        /// How many edges from `--node` to go
+        #[arg(long, default_value_t = 1, requires = "node")]
+        hops: usize,
+        /// Only export nodes of this type, e.g. `entity` or `goal`
+        #[arg(long = "type", value_name = "TYPE", value_parser = parse_node_type)]
+        node_type: Option<NodeType>,
+        /// Most nodes to export
+        #[arg(long, default_value_t = DEFAULT_EXPORT_LIMIT)]
+        limit: usize,
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommands {
+    /// Write the configuration and stored state to a single archive
+    ///
+    /// With `--agent`, only sessions run by that agent and their graph
+    /// namespaces are included. Secrets written inline in the config are left
+    /// out; `env:` and `file:` sources are kept.
+    Export {
+        /// Archive to write
+        output: PathBuf,
+        /// Graph namespace to include instead of the default set (repeatable)
+        #[arg(long = "namespace", value_name = "NAMESPACE")]
+        namespaces: Vec<String>,
+    },
+    /// Verify an archive and load it into this machine's database
+    ///
+    /// The archived config is written to `--config` (or ./spec-ai.config.toml)
+    /// when that file does not exist yet, otherwise beside it as
+    /// spec-ai.config.imported.toml for review.
+    Import {
+        archive: PathBuf,
+        /// Leave sessions and namespaces that already exist alone instead of failing
+        #[arg(long)]
+        skip_existing: bool,
+    },
+    /// Check an archive's digest and summarize what it holds
+    Verify { archive: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum PromptCommands {
+    /// List every template and where it comes from
+    List,
+    /// Print a template; NAME@VERSION prints a saved version
+    Show { name: String },
+    /// Save a new version of a template from a file, or from stdin with "-"
+    Save { name: String, file: PathBuf },
+    /// List the saved versions of a template
+    History { name: String },
+}
+
+#[derive(Subcommand)]
+enum PluginCommands {
+    /// Generate a new plugin crate skeleton
+    New {
+        /// Crate name for the plugin (letters, digits, '-' or '_')
+        name: String,
+        /// Kind of plugin to generate
+        #[arg(long, value_enum, default_value = "tool")]
+        kind: PluginKindArg,
+        /// Directory to create the plugin crate in (defaults to the current directory)
+        #[arg(long, value_name = "DIR")]
+        path: Option<PathBuf>,
+    },
+    /// Download, verify and install a plugin into the plugins directory
+    Install {
+        /// Plugin name from the index (NAME or NAME@VERSION), or a manifest URL or path
+        source: String,
+        /// Install even when the artifact has no trusted signature
+        #[arg(long)]
+        allow_unsigned: bool,
+        /// Grant the capabilities the plugin requires without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Plugin kinds the scaffolder has templates for
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum PluginKindArg {
+    Tool,
+}
+
+impl From<PluginKindArg> for PluginKind {
+    fn from(kind: PluginKindArg) -> Self {
+        match kind {
+            PluginKindArg::Tool => PluginKind::Tool,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum UsageCommands {
+    /// Summarize token usage and cost per day, provider and model
+    Report {
+        /// Only include usage on or after this date (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// Only include usage on or before this date (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum ReportFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl From<GraphFormat> for ExportFormat {
+    fn from(format: GraphFormat) -> Self {
+        match format {
+            GraphFormat::Dot => ExportFormat::Dot,
+            GraphFormat::Mermaid => ExportFormat::Mermaid,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum StatusFormat {
+    Table,
+    Json,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
@@ -126,15 +365,55 @@ async fn run_spec_file(cli: &mut CliState, spec_path: &PathBuf) -> Result<bool>
     Ok(true)
 }
 
+/// Serve MCP over stdin and stdout until the client disconnects
+///
+/// Stdout carries the protocol, so logs go to stderr.
+#[cfg(feature = "api")]
+async fn serve_mcp(config_path: Option<PathBuf>, agent: Option<String>) -> Result<()> {
+    use spec_ai_api::api::handlers::AppState;
+    use spec_ai_api::api::mcp::{serve_stdio, McpServer};
+    use spec_ai_core::agent::builder::create_embeddings_client_from_config;
+
+    tracing_subscriber::fmt()
+        .with_env_filter(std::env::var("RUST_LOG").unwrap_or_else(|_| "spec_ai=warn".to_string()))
+        .with_writer(std::io::stderr)
+        .init();
+
+    let mut app_config = if let Some(path) = config_path {
+        AppConfig::load_from_file(&path)?
+    } else {
+        AppConfig::load()?
+    };
+    if agent.is_some() {
+        app_config.default_agent = agent;
+    }
+
+    let persistence = Persistence::from_config(&app_config.database)?;
+    let embeddings = create_embeddings_client_from_config(&app_config)?;
+    let agent_registry = Arc::new(AgentRegistry::new(
+        app_config.agents.clone(),
+        persistence.clone(),
+    ));
+    let tool_registry = Arc::new(ToolRegistry::with_builtin_tools(
+        Some(Arc::new(persistence.clone())),
+        embeddings,
+        None,
+    ));
+
+    let state = AppState::new(persistence, agent_registry, tool_registry, app_config);
+    serve_stdio(McpServer::new(state)).await
+}
+
 #[cfg(feature = "api")]
 async fn start_server(
     config_path: Option<PathBuf>,
     host: String,
     port: u16,
     join: Option<String>,
+    agent: Option<String>,
 ) -> Result<()> {
     use spec_ai_api::api::mesh::MeshClient;
-    use spec_ai_config::config::AppConfig;
+    use spec_ai_core::agent::builder::create_embeddings_client_from_config;
     use spec_ai_core::embeddings::EmbeddingsClient;
     use std::net::TcpListener;
 
@@ -168,6 +447,7 @@ async fn start_server(
                     test_port,
                     registry_addr.clone(),
                     instance_id,
+                    agent,
                 )
                 .await;
             }
@@ -212,6 +492,7 @@ async fn start_server(
                                 test_port,
                                 registry_url,
                                 instance_id,
+                                agent,
                             )
                             .await;
                         }
@@ -232,17 +513,22 @@ async fn start_server(
     }
 
     // Load configuration
-    let app_config = if let Some(path) = config_path {
+    let mut app_config = if let Some(path) = config_path {
         AppConfig::load_from_file(&path)?
     } else {
         AppConfig::load()?
     };
+    if agent.is_some() {
+        app_config.default_agent = agent;
+    }
 
     // Initialize persistence
-    let persistence = Persistence::new(&app_config.database.path)?;
+    let persistence = Persistence::from_config(&app_config.database)?;
 
     // Initialize embeddings client if configured
-    let embeddings = if let Some(embeddings_model) = &app_config.model.embeddings_model {
+    let embeddings = if app_config.embeddings.is_local() {
+        create_embeddings_client_from_config(&app_config)?
+    } else if let Some(embeddings_model) = &app_config.model.embeddings_model {
         if let Some(api_key_source) = &app_config.model.api_key_source {
             // Resolve API key from environment or file
             let api_key = if api_key_source.starts_with("ENV:") {
@@ -263,7 +549,8 @@ async fn start_server(
         }
     } else {
         None
-    };
+    }
+    .map(|client| client.with_batching(&app_config.embeddings));
 
     // Create registries
     let agent_registry = Arc::new(AgentRegistry::new(
@@ -309,51 +596,157 @@ async fn start_server(
         last_heartbeat: chrono::Utc::now(),
         created_at: chrono::Utc::now(),
         agent_profiles: agent_registry.list(),
+        metrics: Default::default(),
+        // The registry's clock is the one the mesh is measured against
+        clock: Some(spec_ai_api::api::mesh::ClockSample {
+            offset_ms: 0,
+            rtt_ms: 0,
+        }),
     };
     mesh_registry.register(self_instance).await;
 
+    let mut shutdown = ShutdownCoordinator::new();
+
+    // Publish liveness to the database for `spec-ai status`
+    let liveness = app_config.heartbeat.enabled.then(|| {
+        Heartbeat::new(
+            instance_id.clone(),
+            "server",
+            server.liveness().clone(),
+            &app_config.heartbeat,
+        )
+    });
+    if let Some(liveness) = &liveness {
+        shutdown.register_task(
+            "liveness heartbeat",
+            DEFAULT_STOP_TIMEOUT,
+            liveness.spawn(persistence.clone(), shutdown.token()),
+        );
+    }
+
+    // Prune stored history outside the `[retention]` limits
+    if app_config.retention.enabled {
+        shutdown.register_task(
+            "retention",
+            DEFAULT_STOP_TIMEOUT,
+            maintenance::spawn_retention(
+                persistence.clone(),
+                app_config.retention.clone(),
+                shutdown.token(),
+            ),
+        );
+    }
+
+    // Keep the knowledge graph tidy with the built-in maintenance workflows
+    if app_config.graph_maintenance.enabled {
+        shutdown.register_task(
+            "graph maintenance",
+            DEFAULT_STOP_TIMEOUT,
+            maintenance::spawn_graph_maintenance(
+                persistence.clone(),
+                app_config.graph_maintenance.clone(),
+                shutdown.token(),
+            ),
+        );
+    }
+
     // Start background heartbeat for self (keeps our own timestamp fresh)
     let heartbeat_instance_id = instance_id.clone();
     let heartbeat_registry = mesh_registry.clone();
     let heartbeat_interval = app_config.mesh.heartbeat_interval_secs;
-    tokio::spawn(async move {
-        let mut interval =
-            tokio::time::interval(tokio::time::Duration::from_secs(heartbeat_interval));
-        loop {
-            interval.tick().await;
-            let _ = heartbeat_registry.heartbeat(&heartbeat_instance_id).await;
-        }
-    });
+    let heartbeat_shutdown = shutdown.token();
+    shutdown.register_task(
+        "mesh heartbeat",
+        DEFAULT_STOP_TIMEOUT,
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(heartbeat_interval));
+            loop {
+                tokio::select! {
+                    _ = heartbeat_shutdown.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+                let metrics = liveness.as_ref().map(Heartbeat::metrics);
+                let _ = heartbeat_registry
+                    .heartbeat(&heartbeat_instance_id, metrics, None)
+                    .await;
+            }
+            Ok(())
+        }),
+    );
 
     // Start stale instance cleanup task
     let cleanup_registry = mesh_registry.clone();
     let cleanup_timeout = app_config.mesh.leader_timeout_secs;
-    tokio::spawn(async move {
-        let mut interval =
-            tokio::time::interval(tokio::time::Duration::from_secs(cleanup_timeout / 2));
-        loop {
-            interval.tick().await;
-            cleanup_registry.cleanup_stale(cleanup_timeout).await;
-        }
-    });
+    let cleanup_shutdown = shutdown.token();
+    shutdown.register_task(
+        "mesh cleanup",
+        DEFAULT_STOP_TIMEOUT,
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(cleanup_timeout / 2));
+            loop {
+                tokio::select! {
+                    _ = cleanup_shutdown.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+                cleanup_registry.cleanup_stale(cleanup_timeout).await;
+            }
+            Ok(())
+        }),
+    );
 
-    // Setup shutdown signal
+    // Deregister from the mesh once shutdown starts
     let shutdown_instance_id = instance_id.clone();
     let shutdown_registry = mesh_registry.clone();
-    let shutdown = async move {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("Failed to install Ctrl+C handler");
-        println!("\nShutting down server...");
-        // Deregister from mesh
-        let _ = shutdown_registry.deregister(&shutdown_instance_id).await;
+    shutdown.on_shutdown("mesh registration", DEFAULT_STOP_TIMEOUT, async move {
+        shutdown_registry.deregister(&shutdown_instance_id).await;
+        Ok(())
+    });
+
+    shutdown.stop_tasks();
+    shutdown.stop_plugins();
+    shutdown.flush_persistence(persistence);
+
+    run_server_until_shutdown(server, shutdown).await
+}
+
+/// Serve until Ctrl+C or SIGTERM, then stop every subsystem and report failures
+#[cfg(feature = "api")]
+async fn run_server_until_shutdown(
+    server: ApiServer,
+    mut shutdown: ShutdownCoordinator,
+) -> Result<()> {
+    let mut server_task = tokio::spawn(server.run_until(shutdown.token()));
+
+    // The server can also stop on its own, e.g. when the port is taken
+    let server_exit = tokio::select! {
+        _ = shutdown_signal() => {
+            println!("\nShutting down server...");
+            None
+        }
+        exit = &mut server_task => Some(exit),
     };
+    if server_exit.is_none() {
+        // Leave room for the graceful connection drain
+        shutdown.register_task(
+            "api server",
+            GRACEFUL_SHUTDOWN_PERIOD + DEFAULT_STOP_TIMEOUT,
+            server_task,
+        );
+    }
 
-    // Run server with graceful shutdown
-    server.run_with_shutdown(shutdown).await?;
+    let report = shutdown.shutdown().await;
+    if report.is_clean() {
+        println!("Server stopped");
+    } else {
+        eprintln!("Server stopped, but {}", report);
+    }
 
-    println!("Server stopped");
-    Ok(())
+    match server_exit {
+        Some(exit) => exit.context("server task failed")?,
+        None => Ok(()),
+    }
 }
 
 #[cfg(feature = "api")]
@@ -363,26 +756,32 @@ async fn start_mesh_member(
     port: u16,
     registry_url: String,
     instance_id: String,
+    agent: Option<String>,
 ) -> Result<()> {
     use spec_ai_api::api::mesh::MeshClient;
-    use spec_ai_config::config::AppConfig;
+    use spec_ai_core::agent::builder::create_embeddings_client_from_config;
     use spec_ai_core::embeddings::EmbeddingsClient;
 
     println!("Starting as mesh member on {}:{}", host, port);
     println!("Registry at: {}", registry_url);
 
     // Load configuration
-    let app_config = if let Some(path) = config_path {
+    let mut app_config = if let Some(path) = config_path {
         AppConfig::load_from_file(&path)?
     } else {
         AppConfig::load()?
     };
+    if agent.is_some() {
+        app_config.default_agent = agent;
+    }
 
     // Initialize persistence
-    let persistence = Persistence::new(&app_config.database.path)?;
+    let persistence = Persistence::from_config(&app_config.database)?;
 
     // Initialize embeddings client if configured
-    let embeddings = if let Some(embeddings_model) = &app_config.model.embeddings_model {
+    let embeddings = if app_config.embeddings.is_local() {
+        create_embeddings_client_from_config(&app_config)?
+    } else if let Some(embeddings_model) = &app_config.model.embeddings_model {
         if let Some(api_key_source) = &app_config.model.api_key_source {
             let api_key = if api_key_source.starts_with("ENV:") {
                 std::env::var(&api_key_source[4..]).ok()
@@ -402,7 +801,8 @@ async fn start_mesh_member(
         }
     } else {
         None
-    };
+    }
+    .map(|client| client.with_batching(&app_config.embeddings));
 
     // Create registries
     let agent_registry = Arc::new(AgentRegistry::new(
@@ -446,7 +846,7 @@ async fn start_mesh_member(
 
     let server = ApiServer::new(
         api_config.clone(),
-        persistence,
+        persistence.clone(),
         agent_registry,
         tool_registry,
         app_config.clone(),
@@ -458,46 +858,102 @@ async fn start_mesh_member(
         server.certificate_fingerprint()
     );
 
+    let mut shutdown = ShutdownCoordinator::new();
+
+    // Publish liveness to our own database and with each mesh heartbeat
+    let liveness = app_config.heartbeat.enabled.then(|| {
+        Heartbeat::new(
+            instance_id.clone(),
+            "mesh-member",
+            server.liveness().clone(),
+            &app_config.heartbeat,
+        )
+    });
+    if let Some(liveness) = &liveness {
+        shutdown.register_task(
+            "liveness heartbeat",
+            DEFAULT_STOP_TIMEOUT,
+            liveness.spawn(persistence.clone(), shutdown.token()),
+        );
+    }
+
+    // Prune stored history outside the `[retention]` limits
+    if app_config.retention.enabled {
+        shutdown.register_task(
+            "retention",
+            DEFAULT_STOP_TIMEOUT,
+            maintenance::spawn_retention(
+                persistence.clone(),
+                app_config.retention.clone(),
+                shutdown.token(),
+            ),
+        );
+    }
+
+    // Keep the knowledge graph tidy with the built-in maintenance workflows
+    if app_config.graph_maintenance.enabled {
+        shutdown.register_task(
+            "graph maintenance",
+            DEFAULT_STOP_TIMEOUT,
+            maintenance::spawn_graph_maintenance(
+                persistence.clone(),
+                app_config.graph_maintenance.clone(),
+                shutdown.token(),
+            ),
+        );
+    }
+
     // Start background heartbeat to registry
     let heartbeat_instance_id = instance_id.clone();
     let heartbeat_client = mesh_client.clone();
     let heartbeat_interval = app_config.mesh.heartbeat_interval_secs;
-    tokio::spawn(async move {
-        let mut interval =
-            tokio::time::interval(tokio::time::Duration::from_secs(heartbeat_interval));
-        loop {
-            interval.tick().await;
-            if let Err(e) = heartbeat_client
-                .heartbeat(&heartbeat_instance_id, None)
-                .await
-            {
-                eprintln!("Heartbeat failed: {}", e);
+    let heartbeat_shutdown = shutdown.token();
+    shutdown.register_task(
+        "mesh heartbeat",
+        DEFAULT_STOP_TIMEOUT,
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(heartbeat_interval));
+            loop {
+                tokio::select! {
+                    _ = heartbeat_shutdown.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+                let metrics = liveness.as_ref().map(Heartbeat::metrics);
+                if let Err(e) = heartbeat_client
+                    .heartbeat(&heartbeat_instance_id, metrics)
+                    .await
+                {
+                    eprintln!("Heartbeat failed: {}", e);
+                }
             }
-        }
-    });
+            Ok(())
+        }),
+    );
 
-    // Setup shutdown signal with deregistration
+    // Deregister from the mesh once shutdown starts
     let shutdown_instance_id = instance_id.clone();
     let shutdown_client = mesh_client.clone();
-    let shutdown = async move {
-        tokio::signal::ctrl_c()
+    shutdown.on_shutdown("mesh registration", DEFAULT_STOP_TIMEOUT, async move {
+        shutdown_client
+            .deregister(&shutdown_instance_id)
             .await
-            .expect("Failed to install Ctrl+C handler");
-        println!("\nShutting down server...");
-        // Deregister from mesh
-        if let Err(e) = shutdown_client.deregister(&shutdown_instance_id).await {
-            eprintln!("Failed to deregister: {}", e);
-        }
-    };
+            .context("failed to deregister from the mesh")
+    });
 
-    // Run server with graceful shutdown
-    server.run_with_shutdown(shutdown).await?;
+    shutdown.stop_tasks();
+    shutdown.stop_plugins();
+    shutdown.flush_persistence(persistence);
 
-    println!("Server stopped");
-    Ok(())
+    run_server_until_shutdown(server, shutdown).await
 }
 
-async fn run_specs_command(config_path: Option<PathBuf>, spec_paths: Vec<PathBuf>) -> Result<i32> {
+async fn run_specs_command(
+    config_path: Option<PathBuf>,
+    spec_paths: Vec<PathBuf>,
+    agent: Option<String>,
+    transcript: Option<RunTranscript>,
+) -> Result<i32> {
     // Determine which spec to run
     let specs_to_run = if spec_paths.is_empty() {
         let default_spec = PathBuf::from("../../../examples/spec/smoke.spec");
@@ -542,6 +998,11 @@ async fn run_specs_command(config_path: Option<PathBuf>, spec_paths: Vec<PathBuf
             return Err(e);
         }
     };
+    if let Some(agent) = agent {
+        cli.switch_agent(&agent)?;
+    }
+    let transcript = transcript.map(Arc::new);
+    cli.set_transcript(transcript.clone());
 
     // Run each spec file
     let mut all_success = true;
@@ -559,21 +1020,927 @@ async fn run_specs_command(config_path: Option<PathBuf>, spec_paths: Vec<PathBuf
         }
     }
 
+    if let Some(transcript) = &transcript {
+        let path = transcript
+            .path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        match transcript.mode() {
+            TranscriptMode::Record => println!(
+                "Recorded {} model and tool calls to {}",
+                transcript.events().len(),
+                path
+            ),
+            TranscriptMode::Replay if transcript.unused() > 0 => {
+                // Calls left over mean the run took a different path
+                eprintln!(
+                    "Replay diverged: {} recorded calls in {} were not made",
+                    transcript.unused(),
+                    path
+                );
+                all_success = false;
+            }
+            TranscriptMode::Replay => {}
+        }
+    }
+
     Ok(if all_success { 0 } else { 1 })
 }
 
+/// A single line of the usage report with its estimated cost.
+#[derive(Debug, Serialize)]
+struct UsageReportRow {
+    day: NaiveDate,
+    provider: String,
+    model: String,
+    calls: u64,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    total_tokens: u64,
+    cost: Option<f64>,
+}
+
+impl UsageReportRow {
+    fn from_record(record: UsageSummaryRecord, pricing: &PricingConfig) -> Self {
+        let cost = pricing.cost(
+            &record.model,
+            record.prompt_tokens,
+            record.completion_tokens,
+        );
+        Self {
+            day: record.day,
+            provider: record.provider,
+            model: record.model,
+            calls: record.calls,
+            prompt_tokens: record.prompt_tokens,
+            completion_tokens: record.completion_tokens,
+            total_tokens: record.total_tokens,
+            cost,
+        }
+    }
+}
+
+fn format_cost(cost: Option<f64>) -> String {
+    cost.map(|c| format!("{:.4}", c)).unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_usage_csv(rows: &[UsageReportRow]) -> String {
+    let mut out = String::from(
+        "day,provider,model,calls,prompt_tokens,completion_tokens,total_tokens,cost\n",
+    );
+    for row in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            row.day,
+            csv_field(&row.provider),
+            csv_field(&row.model),
+            row.calls,
+            row.prompt_tokens,
+            row.completion_tokens,
+            row.total_tokens,
+            format_cost(row.cost)
+        ));
+    }
+    out
+}
+
+fn render_usage_table(rows: &[UsageReportRow]) -> String {
+    if rows.is_empty() {
+        return "No usage recorded for the selected period.\n".to_string();
+    }
+
+    let headers = [
+        "DAY",
+        "PROVIDER",
+        "MODEL",
+        "CALLS",
+        "PROMPT",
+        "COMPLETION",
+        "TOTAL",
+        "COST",
+    ];
+    let mut lines: Vec<[String; 8]> = rows
+        .iter()
+        .map(|row| {
+            [
+                row.day.to_string(),
+                row.provider.clone(),
+                row.model.clone(),
+                row.calls.to_string(),
+                row.prompt_tokens.to_string(),
+                row.completion_tokens.to_string(),
+                row.total_tokens.to_string(),
+                format_cost(row.cost),
+            ]
+        })
+        .collect();
+
+    // Totals only sum cost when every row is priced, otherwise the figure would be misleading
+    let total_cost = rows.iter().map(|row| row.cost).sum::<Option<f64>>();
+    lines.push([
+        "TOTAL".to_string(),
+        String::new(),
+        String::new(),
+        rows.iter().map(|r| r.calls).sum::<u64>().to_string(),
+        rows.iter()
+            .map(|r| r.prompt_tokens)
+            .sum::<u64>()
+            .to_string(),
+        rows.iter()
+            .map(|r| r.completion_tokens)
+            .sum::<u64>()
+            .to_string(),
+        rows.iter().map(|r| r.total_tokens).sum::<u64>().to_string(),
+        format_cost(total_cost),
+    ]);
+
+    let mut widths = headers.map(str::len);
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let format_line = |cells: &[String]| {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                // Text columns are left-aligned, numeric columns right-aligned
+                if i < 3 {
+                    format!("{:<width$}", cell, width = widths[i])
+                } else {
+                    format!("{:>width$}", cell, width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+
+    let header_cells: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let mut out = format_line(&header_cells);
+    out.push('\n');
+    for line in &lines {
+        out.push_str(format_line(line).trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn run_usage_report(
+    config_path: Option<PathBuf>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    format: ReportFormat,
+) -> Result<()> {
+    if let (Some(since), Some(until)) = (since, until) {
+        if since > until {
+            anyhow::bail!("--since ({}) must not be after --until ({})", since, until);
+        }
+    }
+
+    let app_config = if let Some(path) = config_path {
+        AppConfig::load_from_file(&path)?
+    } else {
+        AppConfig::load()?
+    };
+
+    let persistence = Persistence::from_config(&app_config.database)?;
+    let rows: Vec<UsageReportRow> = persistence
+        .usage_summary(since, until)
+        .context("querying usage records")?
+        .into_iter()
+        .map(|record| UsageReportRow::from_record(record, &app_config.pricing))
+        .collect();
+
+    match format {
+        ReportFormat::Table => print!("{}", render_usage_table(&rows)),
+        ReportFormat::Csv => print!("{}", render_usage_csv(&rows)),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+    }
+
+    Ok(())
+}
+
+/// Liveness of one instance as shown by `spec-ai status`
+///
+/// Instances that only appear in a mesh registry may not have sent a
+/// liveness report, in which case the load and error fields are empty.
+#[derive(Debug, Serialize)]
+struct StatusRow {
+    instance_id: String,
+    hostname: String,
+    role: Option<String>,
+    status: String,
+    pid: Option<u32>,
+    active_sessions: Option<u64>,
+    queue_depth: Option<u64>,
+    last_error: Option<String>,
+    last_error_code: Option<String>,
+    last_error_at: Option<DateTime<Utc>>,
+    last_seen: DateTime<Utc>,
+}
+
+impl StatusRow {
+    fn from_record(record: HeartbeatRecord, now: DateTime<Utc>, stale_after: u64) -> Self {
+        let status =
+            effective_status(&record, now, std::time::Duration::from_secs(stale_after)).to_string();
+        Self {
+            instance_id: record.instance_id,
+            hostname: record.hostname,
+            role: Some(record.role),
+            status,
+            pid: Some(record.pid),
+            active_sessions: Some(record.active_sessions),
+            queue_depth: Some(record.queue_depth),
+            last_error: record.last_error,
+            last_error_code: record.last_error_code,
+            last_error_at: record.last_error_at,
+            last_seen: record.updated_at,
+        }
+    }
+
+    #[cfg(feature = "api")]
+    fn from_instance(
+        instance: spec_ai_api::api::mesh::MeshInstance,
+        now: DateTime<Utc>,
+        stale_after: u64,
+    ) -> Self {
+        use spec_ai_core::heartbeat::{from_metrics, STATUS_RUNNING, STATUS_STALE};
+
+        match from_metrics(&instance.metrics) {
+            // The registry's receive time, so clock skew between hosts does not
+            // make a live member look stale
+            Some(record) => Self::from_record(
+                HeartbeatRecord {
+                    updated_at: instance.last_heartbeat,
+                    ..record
+                },
+                now,
+                stale_after,
+            ),
+            None => {
+                let age = (now - instance.last_heartbeat).num_seconds();
+                Self {
+                    instance_id: instance.instance_id,
+                    hostname: instance.hostname,
+                    role: None,
+                    status: if age > stale_after as i64 {
+                        STATUS_STALE
+                    } else {
+                        STATUS_RUNNING
+                    }
+                    .to_string(),
+                    pid: None,
+                    active_sessions: None,
+                    queue_depth: None,
+                    last_error: None,
+                    last_error_code: None,
+                    last_error_at: None,
+                    last_seen: instance.last_heartbeat,
+                }
+            }
+        }
+    }
+}
+
+/// Compact age such as "42s", "5m" or "3d"
+fn format_age(now: DateTime<Utc>, then: DateTime<Utc>) -> String {
+    let secs = (now - then).num_seconds().max(0);
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+fn render_status_table(rows: &[StatusRow], now: DateTime<Utc>) -> String {
+    if rows.is_empty() {
+        return "No heartbeats recorded. Is a server running with [heartbeat] enabled?\n"
+            .to_string();
+    }
+
+    let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+    let headers = [
+        "INSTANCE",
+        "HOST",
+        "ROLE",
+        "STATUS",
+        "PID",
+        "SESSIONS",
+        "QUEUE",
+        "SEEN",
+        "LAST ERROR",
+    ];
+    let lines: Vec<[String; 9]> = rows
+        .iter()
+        .map(|row| {
+            let last_error = match (&row.last_error_code, &row.last_error_at) {
+                (Some(code), Some(at)) => format!(
+                    "{} {} ago: {}",
+                    code,
+                    format_age(now, *at),
+                    row.last_error.as_deref().unwrap_or_default()
+                ),
+                _ => String::new(),
+            };
+            [
+                row.instance_id.clone(),
+                row.hostname.clone(),
+                row.role.clone().unwrap_or_default(),
+                row.status.clone(),
+                optional(row.pid.map(u64::from)),
+                optional(row.active_sessions),
+                optional(row.queue_depth),
+                format!("{} ago", format_age(now, row.last_seen)),
+                last_error,
+            ]
+        })
+        .collect();
+
+    let mut widths = headers.map(str::len);
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let format_line = |cells: &[String]| {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                // Counts are right-aligned, everything else left-aligned
+                if (4..=6).contains(&i) {
+                    format!("{:>width$}", cell, width = widths[i])
+                } else {
+                    format!("{:<width$}", cell, width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+
+    let header_cells: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let mut out = format_line(&header_cells).trim_end().to_string();
+    out.push('\n');
+    for line in &lines {
+        out.push_str(format_line(line).trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(feature = "api")]
+async fn registry_status(
+    registry: &str,
+    now: DateTime<Utc>,
+    stale_after: u64,
+) -> Result<Vec<StatusRow>> {
+    use spec_ai_api::api::mesh::MeshClient;
+
+    let (host, port) = registry
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+        .with_context(|| format!("--registry must be HOST:PORT, got '{}'", registry))?;
+    let response = MeshClient::new(host, port)
+        .list_instances()
+        .await
+        .with_context(|| format!("querying mesh registry at {}", registry))?;
+    // Heartbeat times are on the registry's clock, so measure ages on it too
+    let now = response.registry_time.unwrap_or(now);
+    Ok(response
+        .instances
+        .into_iter()
+        .map(|instance| StatusRow::from_instance(instance, now, stale_after))
+        .collect())
+}
+
+#[cfg(not(feature = "api"))]
+async fn registry_status(
+    _registry: &str,
+    _now: DateTime<Utc>,
+    _stale_after: u64,
+) -> Result<Vec<StatusRow>> {
+    anyhow::bail!("--registry requires the 'api' feature")
+}
+
+async fn run_status(
+    config_path: Option<PathBuf>,
+    registry: Option<String>,
+    format: StatusFormat,
+) -> Result<()> {
+    let app_config = if let Some(path) = config_path {
+        AppConfig::load_from_file(&path)?
+    } else {
+        AppConfig::load()?
+    };
+    let stale_after = app_config.heartbeat.stale_after_secs;
+    let now = Utc::now();
+
+    let rows = match registry {
+        Some(registry) => registry_status(&registry, now, stale_after).await?,
+        None => {
+            let persistence = Persistence::from_config(&app_config.database).map_err(|e| {
+                let e = anyhow::Error::from(e);
+                // A running server holds the database lock for as long as it is up
+                let error_chain = format!("{:#}", e);
+                if error_chain.contains("Could not set lock")
+                    || error_chain.contains("Conflicting lock")
+                {
+                    e.context(
+                        "the database is in use by a running instance; \
+                         query its mesh registry with `spec-ai status --registry HOST:PORT`",
+                    )
+                } else {
+                    e
+                }
+            })?;
+            persistence
+                .list_heartbeats()
+                .context("reading heartbeats")?
+                .into_iter()
+                .map(|record| StatusRow::from_record(record, now, stale_after))
+                .collect()
+        }
+    };
+
+    match format {
+        StatusFormat::Table => print!("{}", render_status_table(&rows, now)),
+        StatusFormat::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+    }
+
+    Ok(())
+}
+
+fn run_graph_reencrypt(config_path: Option<PathBuf>, namespace: Option<String>) -> Result<()> {
+    let app_config = if let Some(path) = config_path {
+        AppConfig::load_from_file(&path)?
+    } else {
+        AppConfig::load()?
+    };
+
+    let persistence = Persistence::from_config(&app_config.database)?;
+    let namespaces = match namespace {
+        Some(namespace) => vec![namespace],
+        None => app_config
+            .database
+            .encrypted_namespaces
+            .iter()
+            .map(|ns| ns.namespace.clone())
+            .collect(),
+    };
+    if namespaces.is_empty() {
+        anyhow::bail!("No encrypted namespaces configured under [[database.encrypted_namespaces]]");
+    }
+
+    for namespace in namespaces {
+        let rewritten = persistence
+            .reencrypt_graph_namespace(&namespace)
+            .with_context(|| format!("re-encrypting namespace '{}'", namespace))?;
+        println!("{}: re-encrypted {} nodes and edges", namespace, rewritten);
+    }
+    persistence.checkpoint()?;
+    Ok(())
+}
+
+fn run_graph_export(
+    config_path: Option<PathBuf>,
+    session: &str,
+    format: ExportFormat,
+    selection: Selection,
+    limit: usize,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let app_config = if let Some(path) = config_path {
+        AppConfig::load_from_file(&path)?
+    } else {
+        AppConfig::load()?
+    };
+
+    let persistence = Persistence::from_config(&app_config.database)?;
+    let graph = Subgraph::select(&persistence, session, &selection, limit)?;
+    if graph.nodes.is_empty() {
+        anyhow::bail!("No graph nodes to export in session '{}'", session);
+    }
+
+    let text = graph.render(format);
+    match output {
+        Some(path) => {
+            std::fs::write(&path, text).with_context(|| format!("writing {}", path.display()))?;
+            eprintln!(
+                "Exported {} nodes and {} edges to {}",
+                graph.nodes.len(),
+                graph.edges.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", text),
+    }
+    if graph.omitted > 0 {
+        eprintln!(
+            "{} more nodes left out; raise --limit to include them",
+            graph.omitted
+        );
+    }
+    Ok(())
+}
+
+fn run_prune(config_path: Option<PathBuf>) -> Result<()> {
+    let app_config = if let Some(path) = config_path {
+        AppConfig::load_from_file(&path)?
+    } else {
+        AppConfig::load()?
+    };
+    if !app_config
+        .retention
+        .rules()
+        .iter()
+        .any(|(_, rule)| rule.is_bounded())
+    {
+        anyhow::bail!("No retention limits configured under [retention]");
+    }
+
+    let persistence = Persistence::from_config(&app_config.database)?;
+    let reports = persistence.apply_retention(&app_config.retention, Utc::now())?;
+    println!("{:<12} {:>8} {:>8}", "STORE", "BY AGE", "BY SIZE");
+    for report in reports {
+        println!(
+            "{:<12} {:>8} {:>8}",
+            report.store, report.by_age, report.by_size
+        );
+    }
+    persistence.checkpoint()?;
+    Ok(())
+}
+
+fn run_profile(
+    config_path: Option<PathBuf>,
+    agent: Option<String>,
+    command: ProfileCommands,
+) -> Result<()> {
+    match command {
+        ProfileCommands::Export { output, namespaces } => {
+            let app_config = if let Some(path) = config_path {
+                AppConfig::load_from_file(&path)?
+            } else {
+                AppConfig::load()?
+            };
+            let persistence = Persistence::from_config(&app_config.database)?;
+            let data = persistence.export_profile_data(&ProfileSelection { agent, namespaces })?;
+            let payload = ProfilePayload::new(&app_config, data)?;
+            let digest = profile_archive::write_archive(&output, &payload)?;
+            print_profile_summary(&payload);
+            println!("Wrote {} (blake3 {})", output.display(), digest);
+        }
+        ProfileCommands::Import {
+            archive,
+            skip_existing,
+        } => {
+            let archive = profile_archive::read_archive(&archive)?;
+            let payload = archive.payload;
+            let archived_config = payload.app_config()?;
+            let target = config_path.unwrap_or_else(|| PathBuf::from("spec-ai.config.toml"));
+            let app_config = if target.exists() {
+                let imported = target.with_file_name("spec-ai.config.imported.toml");
+                std::fs::write(&imported, &payload.config)
+                    .with_context(|| format!("writing {}", imported.display()))?;
+                println!(
+                    "Kept {}; archived config written to {} for review",
+                    target.display(),
+                    imported.display()
+                );
+                AppConfig::load_from_file(&target)?
+            } else {
+                std::fs::write(&target, &payload.config)
+                    .with_context(|| format!("writing {}", target.display()))?;
+                println!("Wrote config to {}", target.display());
+                archived_config
+            };
+
+            let persistence = Persistence::from_config(&app_config.database)?;
+            let report = persistence.import_profile_data(&payload.data, skip_existing)?;
+            persistence.checkpoint()?;
+            println!(
+                "Imported {} sessions, {} graph namespaces and {} strategies ({} rows)",
+                report.sessions, report.namespaces, report.strategies, report.rows
+            );
+            for skipped in &report.skipped {
+                println!("Skipped existing {}", skipped);
+            }
+            if !payload.removed_secrets.is_empty() {
+                println!("Secrets left out of the archive; set them again:");
+                for path in &payload.removed_secrets {
+                    println!("  {}", path);
+                }
+            }
+        }
+        ProfileCommands::Verify { archive } => {
+            let archive = profile_archive::read_archive(&archive)?;
+            println!(
+                "Archive created {} verified (blake3 {})",
+                archive.created_at, archive.digest
+            );
+            print_profile_summary(&archive.payload);
+        }
+    }
+    Ok(())
+}
+
+fn print_profile_summary(payload: &ProfilePayload) {
+    let data = &payload.data;
+    println!("Sessions:   {}", data.sessions.len());
+    println!("Namespaces: {}", data.namespaces.len());
+    println!("Messages:   {}", data.row_count("messages"));
+    println!(
+        "Graph:      {} nodes, {} edges",
+        data.row_count("graph_nodes"),
+        data.row_count("graph_edges")
+    );
+    println!("Strategies: {}", data.row_count("strategies"));
+    if !payload.removed_secrets.is_empty() {
+        println!("Secrets left out: {}", payload.removed_secrets.join(", "));
+    }
+}
+
+fn run_prompts(config_path: Option<PathBuf>, command: PromptCommands) -> Result<()> {
+    let app_config = if let Some(path) = config_path {
+        AppConfig::load_from_file(&path)?
+    } else {
+        AppConfig::load()?
+    };
+    let persistence = Persistence::from_config(&app_config.database)?;
+    let library = PromptLibrary::new()
+        .with_templates(app_config.prompts.clone())
+        .with_persistence(persistence.clone());
+
+    match command {
+        PromptCommands::List => {
+            for name in library.names()? {
+                let template = library.resolve(&name, None)?;
+                let source = match (template.source, template.version) {
+                    (PromptSource::Saved, Some(version)) => format!("saved v{}", version),
+                    (PromptSource::Config, _) => "config".to_string(),
+                    _ => "built-in".to_string(),
+                };
+                println!("{:<24} {}", name, source);
+            }
+        }
+        PromptCommands::Show { name } => {
+            println!("{}", library.resolve(&name, None)?.body);
+        }
+        PromptCommands::Save { name, file } => {
+            let body = if file.as_os_str() == "-" {
+                std::io::read_to_string(std::io::stdin()).context("reading template from stdin")?
+            } else {
+                std::fs::read_to_string(&file)
+                    .with_context(|| format!("reading template from {}", file.display()))?
+            };
+            let record = library.save(&name, body.trim_end())?;
+            persistence.checkpoint()?;
+            println!("{}@{}", record.name, record.version);
+        }
+        PromptCommands::History { name } => {
+            let versions = persistence.prompt_template_versions(&name)?;
+            if versions.is_empty() {
+                anyhow::bail!("No saved versions of prompt template '{}'", name);
+            }
+            for record in versions {
+                let first_line = record.body.lines().next().unwrap_or_default();
+                println!(
+                    "v{:<4} {}  {}",
+                    record.version,
+                    record.created_at.format("%Y-%m-%d %H:%M"),
+                    first_line
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_plugin_new(name: &str, kind: PluginKindArg, path: Option<PathBuf>) -> Result<()> {
+    let parent = match path {
+        Some(path) => path,
+        None => std::env::current_dir().context("resolving current directory")?,
+    };
+    let root = scaffold_plugin(name, kind.into(), &parent)?;
+
+    println!("Created plugin '{}' at {}", name, root.display());
+    println!();
+    println!("Next steps:");
+    println!("  cd {}", root.display());
+    println!("  cargo test");
+    println!("  cargo build --release");
+    println!("Then copy the library from target/release/ into your plugins directory.");
+    Ok(())
+}
+
+/// Whether a plugin source names a manifest rather than an index entry
+fn is_manifest_location(source: &str) -> bool {
+    source.contains("://") || source.ends_with(".json") || Path::new(source).is_file()
+}
+
+/// Resolve an artifact URL against the manifest or index it came from
+fn resolve_location(base: &str, location: &str) -> Result<String> {
+    if location.contains("://") {
+        return Ok(location.to_string());
+    }
+    if base.starts_with("http://") || base.starts_with("https://") {
+        let url = reqwest::Url::parse(base)
+            .and_then(|base| base.join(location))
+            .with_context(|| format!("resolving {} against {}", location, base))?;
+        return Ok(url.to_string());
+    }
+    let base = Path::new(base.strip_prefix("file://").unwrap_or(base));
+    let dir = base.parent().unwrap_or(Path::new("."));
+    Ok(dir.join(location).display().to_string())
+}
+
+/// Read a URL over HTTP(S), or a local path
+async fn fetch_location(location: &str) -> Result<Vec<u8>> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let response = reqwest::get(location)
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("downloading {}", location))?;
+        let bytes = response
+            .bytes()
+            .await
+            .with_context(|| format!("downloading {}", location))?;
+        return Ok(bytes.to_vec());
+    }
+    let path = expand_tilde(Path::new(
+        location.strip_prefix("file://").unwrap_or(location),
+    ));
+    std::fs::read(&path).with_context(|| format!("reading {}", path.display()))
+}
+
+async fn run_plugin_install(
+    config_path: Option<PathBuf>,
+    source: &str,
+    allow_unsigned: bool,
+    yes: bool,
+) -> Result<()> {
+    let app_config = if let Some(path) = config_path {
+        AppConfig::load_from_file(&path)?
+    } else {
+        AppConfig::load()?
+    };
+    let plugins = &app_config.plugins;
+
+    let (manifest, base) = if is_manifest_location(source) {
+        let manifest = PluginManifest::from_json(&fetch_location(source).await?)?;
+        (manifest, source.to_string())
+    } else {
+        let index_url = plugins.index_url.as_deref().context(
+            "No plugin index configured; set index_url under [plugins] or pass a manifest URL",
+        )?;
+        let index = PluginIndex::from_json(&fetch_location(index_url).await?)?;
+        let (name, version) = match source.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (source, None),
+        };
+        (index.find(name, version)?.clone(), index_url.to_string())
+    };
+    let artifact = manifest.artifact_for_host()?;
+
+    let installer = PluginInstaller::new(expand_tilde(&plugins.custom_tools_dir))
+        .trusted_keys(plugins.trusted_keys.clone())
+        .require_signatures(plugins.require_signatures && !allow_unsigned);
+    let previous = installer.installed(&manifest.name);
+
+    println!("{} {}", manifest.name, manifest.version);
+    if let Some(description) = &manifest.description {
+        println!("  {}", description);
+    }
+    if !manifest.capabilities.is_empty() {
+        println!("  requires: {}", manifest.capabilities.join(", "));
+        if !yes {
+            use std::io::{BufRead, IsTerminal, Write};
+            if !std::io::stdin().is_terminal() {
+                anyhow::bail!("Pass --yes to grant the capabilities this plugin requires");
+            }
+            print!("Grant these capabilities and install? [y/N] ");
+            std::io::stdout().flush()?;
+            let mut answer = String::new();
+            std::io::stdin().lock().read_line(&mut answer)?;
+            if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                anyhow::bail!("Installation cancelled");
+            }
+        }
+    }
+
+    let url = resolve_location(&base, &artifact.url)?;
+    let bytes = fetch_location(&url).await?;
+    let installed = installer.install(&manifest, artifact, &bytes)?;
+
+    match previous {
+        Some(previous) if previous.manifest.version != manifest.version => println!(
+            "Replaced '{}' {} with {}",
+            manifest.name, previous.manifest.version, manifest.version
+        ),
+        _ => println!("Installed '{}' {}", manifest.name, manifest.version),
+    }
+    println!(
+        "  {} ({})",
+        installed.path.display(),
+        if installed.signed {
+            "signature verified"
+        } else {
+            "unsigned"
+        }
+    );
+    if !plugins.enabled {
+        println!("Set enabled = true under [plugins] to load it.");
+    }
+    if !installed.signed {
+        println!(
+            "It loads only where a policy rule allows the plugin_load action on {}.",
+            installed.path.display()
+        );
+    }
+    Ok(())
+}
+
+fn run_doctor(config_path: Option<PathBuf>) -> Result<()> {
+    let started = Instant::now();
+    let cli = CliState::initialize_with_path(config_path)?;
+    let ready = started.elapsed();
+
+    let model = &cli.config.model;
+    println!("database: {}", cli.config.database.location());
+    println!(
+        "agent:    {}",
+        cli.registry
+            .active_name()
+            .unwrap_or_else(|| "none".to_string())
+    );
+    println!(
+        "model:    {} on {}",
+        model.model_name.as_deref().unwrap_or("default"),
+        model.provider
+    );
+    println!();
+    println!("{}", cli.startup_report());
+
+    // Deferred subsystems are built here only to time them
+    let deferred = Instant::now();
+    match cli.transcription_provider() {
+        Ok(provider) => println!(
+            "  transcription ready in {} ({})",
+            millis(deferred.elapsed()),
+            provider.metadata().name
+        ),
+        Err(err) => println!("  transcription failed: {:#}", err),
+    }
+    println!();
+    println!("ready for input in {} including config load", millis(ready));
+    Ok(())
+}
+
+/// Process exit code for an error that ended the CLI
+///
+/// Follows `sysexits.h`, so scripts can tell a rate limit (75) or bad
+/// configuration (78) from an internal failure (1).
+pub fn exit_code(err: &anyhow::Error) -> ExitCode {
+    ExitCode::from(code_of(err).exit_code())
+}
+
 #[tokio::main]
 pub async fn run() -> Result<()> {
     let cli = Cli::parse();
 
+    // Installed before anything that captures the injector is created
+    #[cfg(feature = "chaos")]
+    spec_ai_core::chaos::install_from_env()?;
+
     match cli.command {
-        Some(Commands::Run { specs }) => {
-            let exit_code = run_specs_command(cli.config, specs).await?;
+        Some(Commands::Run {
+            specs,
+            record,
+            replay,
+        }) => {
+            let transcript = match (record, replay) {
+                (Some(path), _) => Some(RunTranscript::record(path)?),
+                (None, Some(path)) => Some(RunTranscript::replay(path)?),
+                (None, None) => None,
+            };
+            let exit_code = run_specs_command(cli.config, specs, cli.agent, transcript).await?;
             std::process::exit(exit_code);
         }
         #[cfg(feature = "api")]
         Some(Commands::Server { port, host, join }) => {
-            start_server(cli.config, host, port, join).await?;
+            start_server(cli.config, host, port, join, cli.agent).await?;
             Ok(())
         }
         #[cfg(not(feature = "api"))]
@@ -582,17 +1949,83 @@ pub async fn run() -> Result<()> {
             eprintln!("Please rebuild with: cargo build --features api");
             std::process::exit(1);
         }
+        #[cfg(feature = "api")]
+        Some(Commands::Mcp) => serve_mcp(cli.config, cli.agent).await,
+        #[cfg(not(feature = "api"))]
+        Some(Commands::Mcp) => {
+            eprintln!("Error: MCP server functionality requires the 'api' feature");
+            eprintln!("Please rebuild with: cargo build --features api");
+            std::process::exit(1);
+        }
+        Some(Commands::Usage {
+            command:
+                UsageCommands::Report {
+                    since,
+                    until,
+                    format,
+                },
+        }) => run_usage_report(cli.config, since, until, format),
+        Some(Commands::Graph {
+            command: GraphCommands::Reencrypt { namespace },
+        }) => run_graph_reencrypt(cli.config, namespace),
+        Some(Commands::Graph {
+            command:
+                GraphCommands::Export {
+                    session,
+                    format,
+                    node,
+                    hops,
+                    node_type,
+                    limit,
+                    output,
+                },
+        }) => {
+            let selection = match node {
+                Some(node_id) => Selection::Neighborhood { node_id, hops },
+                None => Selection::Query { node_type },
+            };
+            run_graph_export(
+                cli.config,
+                &session,
+                format.into(),
+                selection,
+                limit,
+                output,
+            )
+        }
+        Some(Commands::Prompts { command }) => run_prompts(cli.config, command),
+        Some(Commands::Plugin {
+            command: PluginCommands::New { name, kind, path },
+        }) => run_plugin_new(&name, kind, path),
+        Some(Commands::Plugin {
+            command:
+                PluginCommands::Install {
+                    source,
+                    allow_unsigned,
+                    yes,
+                },
+        }) => run_plugin_install(cli.config, &source, allow_unsigned, yes).await,
+        Some(Commands::Profile { command }) => run_profile(cli.config, cli.agent, command),
+        Some(Commands::Doctor) => run_doctor(cli.config),
+        Some(Commands::Prune) => run_prune(cli.config),
+        Some(Commands::Status { registry, format }) => {
+            run_status(cli.config, registry, format).await
+        }
         None => match cli.mode {
+            #[cfg(feature = "tui")]
             TuiMode::New => {
-                spec_ai_tui_app::run_tui(cli.config).await?;
+                spec_ai_tui_app::run_tui(cli.config, cli.agent).await?;
                 Ok(())
             }
-            TuiMode::Legacy => run_repl_with_config(cli.config).await,
+            // Builds without the TUI only have the legacy REPL
+            #[cfg(not(feature = "tui"))]
+            TuiMode::New => run_repl_with_config(cli.config, cli.agent).await,
+            TuiMode::Legacy => run_repl_with_config(cli.config, cli.agent).await,
         },
     }
 }
 
-async fn run_repl_with_config(config: Option<PathBuf>) -> Result<()> {
+async fn run_repl_with_config(config: Option<PathBuf>, agent: Option<String>) -> Result<()> {
     let mut cli_state = match CliState::initialize_with_path(config) {
         Ok(cli) => cli,
         Err(e) => {
@@ -612,6 +2045,9 @@ async fn run_repl_with_config(config: Option<PathBuf>) -> Result<()> {
             return Err(e);
         }
     };
+    if let Some(agent) = agent {
+        cli_state.switch_agent(&agent)?;
+    }
 
     // Initialize logging based on config
     let log_level = cli_state.config.logging.level.to_uppercase();
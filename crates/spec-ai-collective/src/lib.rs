@@ -9,6 +9,7 @@
 //! - **Collective Decision-Making**: Agents vote on proposals with expertise-weighted voting
 //! - **Workflow Orchestration**: Coordinate complex multi-agent workflows
 //! - **Emergent Specialization**: Agents develop and leverage expertise over time
+//! - **Graph Maintenance**: Built-in workflows that keep the knowledge graph tidy
 //!
 //! # Architecture
 //!
@@ -44,6 +45,7 @@ pub mod capability;
 pub mod consensus;
 pub mod delegation;
 pub mod learning;
+pub mod maintenance;
 pub mod orchestration;
 pub mod specialization;
 pub mod types;
@@ -58,6 +60,7 @@ pub use delegation::{
     TaskStatus,
 };
 pub use learning::{LearningFabric, Strategy, StrategyMatch};
+pub use maintenance::{MaintenanceJob, MaintenanceWorkflows};
 pub use orchestration::{
     StageState, StageType, Workflow, WorkflowEngine, WorkflowExecution, WorkflowStage,
     WorkflowState,
@@ -0,0 +1,318 @@
+//! Built-in graph maintenance workflows.
+//!
+//! Long-running instances keep their knowledge graph tidy with a few
+//! housekeeping jobs, each shipped as a [`Workflow`] template with a default
+//! schedule. [`MaintenanceWorkflows`] registers the templates with a
+//! [`WorkflowEngine`], says which jobs are due and drives an execution of a
+//! job stage by stage, leaving the work of each stage to the caller.
+
+use crate::orchestration::{Workflow, WorkflowEngine, WorkflowStage};
+use crate::types::{CollectiveError, InstanceId, Result, WorkflowId};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A built-in graph maintenance job.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceJob {
+    /// Remove rows soft-deleted by graph sync once peers had time to see them
+    TombstoneGc,
+    /// Drop and recreate the graph indexes
+    IndexRebuild,
+    /// Merge entity nodes that name the same thing
+    EntityResolution,
+    /// Refresh the summary node of every session
+    Summarization,
+}
+
+impl MaintenanceJob {
+    /// Every built-in job.
+    pub const ALL: [MaintenanceJob; 4] = [
+        MaintenanceJob::TombstoneGc,
+        MaintenanceJob::IndexRebuild,
+        MaintenanceJob::EntityResolution,
+        MaintenanceJob::Summarization,
+    ];
+
+    /// Name used in configuration and logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MaintenanceJob::TombstoneGc => "tombstone_gc",
+            MaintenanceJob::IndexRebuild => "index_rebuild",
+            MaintenanceJob::EntityResolution => "entity_resolution",
+            MaintenanceJob::Summarization => "summarization",
+        }
+    }
+
+    /// Time between runs when configuration does not say otherwise.
+    pub fn default_interval(&self) -> std::time::Duration {
+        let hours = match self {
+            MaintenanceJob::TombstoneGc => 6,
+            MaintenanceJob::IndexRebuild => 24,
+            MaintenanceJob::EntityResolution => 1,
+            MaintenanceJob::Summarization => 12,
+        };
+        std::time::Duration::from_secs(hours * 3600)
+    }
+
+    /// Workflow template of this job.
+    ///
+    /// Every stage carries `{"job": <name>}` as its config so executors can
+    /// tell which job a stage belongs to.
+    pub fn workflow(&self, created_by: InstanceId) -> Workflow {
+        let workflow = Workflow::new(
+            format!("graph-maintenance/{}", self.name()),
+            self.description(),
+            created_by,
+        );
+        self.stages()
+            .into_iter()
+            .fold(workflow, |workflow, stage| {
+                workflow.add_stage(stage.with_config(serde_json::json!({ "job": self.name() })))
+            })
+            .with_input(serde_json::json!({ "job": self.name() }))
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            MaintenanceJob::TombstoneGc => "Purge graph tombstones past their grace period",
+            MaintenanceJob::IndexRebuild => "Rebuild the graph indexes",
+            MaintenanceJob::EntityResolution => "Merge duplicate entity nodes",
+            MaintenanceJob::Summarization => "Refresh per-session graph summaries",
+        }
+    }
+
+    fn stages(&self) -> Vec<WorkflowStage> {
+        match self {
+            MaintenanceJob::TombstoneGc => vec![WorkflowStage::sequential(
+                "purge_tombstones",
+                "Purge tombstones",
+                "Remove soft-deleted nodes and edges older than the grace period",
+            )
+            .with_timeout(Duration::minutes(10))],
+            MaintenanceJob::IndexRebuild => vec![WorkflowStage::sequential(
+                "rebuild_indexes",
+                "Rebuild indexes",
+                "Drop and recreate the indexes of the graph tables",
+            )
+            .with_timeout(Duration::minutes(30))],
+            MaintenanceJob::EntityResolution => vec![WorkflowStage::sequential(
+                "resolve_entities",
+                "Resolve entities",
+                "Merge entity nodes whose labels match ignoring case and spacing",
+            )
+            .with_timeout(Duration::minutes(10))],
+            MaintenanceJob::Summarization => vec![WorkflowStage::sequential(
+                "summarize_sessions",
+                "Summarize sessions",
+                "Record counts and the best connected entities of every session",
+            )
+            .with_timeout(Duration::minutes(10))],
+        }
+    }
+}
+
+/// A registered job and when it runs next.
+#[derive(Debug)]
+struct ScheduledJob {
+    job: MaintenanceJob,
+    workflow_id: WorkflowId,
+    every: Duration,
+    next_run: Option<DateTime<Utc>>,
+}
+
+/// The maintenance templates registered with a workflow engine, with their
+/// schedules.
+#[derive(Debug)]
+pub struct MaintenanceWorkflows {
+    engine: WorkflowEngine,
+    jobs: Vec<ScheduledJob>,
+}
+
+impl MaintenanceWorkflows {
+    /// Register a template for every job in `schedule`, run at the given
+    /// intervals.
+    ///
+    /// Every job is due right away; afterwards each runs once per interval.
+    pub fn new(
+        instance_id: InstanceId,
+        schedule: impl IntoIterator<Item = (MaintenanceJob, std::time::Duration)>,
+    ) -> Result<Self> {
+        let mut engine = WorkflowEngine::new(instance_id.clone());
+        let mut jobs = Vec::new();
+        for (job, every) in schedule {
+            let every = Duration::from_std(every)
+                .ok()
+                .filter(|every| *every > Duration::zero())
+                .ok_or_else(|| {
+                    CollectiveError::WorkflowExecutionFailed(format!(
+                        "Interval of {} must be positive",
+                        job.name()
+                    ))
+                })?;
+            let workflow_id = engine.register_workflow(job.workflow(instance_id.clone()))?;
+            jobs.push(ScheduledJob {
+                job,
+                workflow_id,
+                every,
+                next_run: None,
+            });
+        }
+        Ok(Self { engine, jobs })
+    }
+
+    /// The engine the templates are registered with.
+    pub fn engine(&self) -> &WorkflowEngine {
+        &self.engine
+    }
+
+    /// Workflow registered for `job`, if it is scheduled.
+    pub fn workflow_id(&self, job: MaintenanceJob) -> Option<&str> {
+        self.jobs
+            .iter()
+            .find(|scheduled| scheduled.job == job)
+            .map(|scheduled| scheduled.workflow_id.as_str())
+    }
+
+    /// Jobs due at `now`, in the order they were scheduled.
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<MaintenanceJob> {
+        self.jobs
+            .iter()
+            .filter(|scheduled| scheduled.next_run.is_none_or(|next| next <= now))
+            .map(|scheduled| scheduled.job)
+            .collect()
+    }
+
+    /// Execute `job` once, calling `step` for each stage in dependency order.
+    ///
+    /// The job's next run is scheduled one interval after `now`, whether or
+    /// not this one succeeds. Returns the result of every stage by stage id;
+    /// the first stage that fails fails the execution.
+    pub fn run(
+        &mut self,
+        job: MaintenanceJob,
+        now: DateTime<Utc>,
+        mut step: impl FnMut(&WorkflowStage) -> anyhow::Result<serde_json::Value>,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let scheduled = self
+            .jobs
+            .iter_mut()
+            .find(|scheduled| scheduled.job == job)
+            .ok_or_else(|| CollectiveError::WorkflowNotFound(job.name().to_string()))?;
+        scheduled.next_run = Some(now + scheduled.every);
+        let workflow_id = scheduled.workflow_id.clone();
+
+        // Finished executions are only kept until the next run
+        self.engine.cleanup_completed(Duration::zero());
+        let execution_id = self.engine.start_execution(&workflow_id)?;
+        let workflow = self
+            .engine
+            .get_workflow(&workflow_id)
+            .cloned()
+            .ok_or_else(|| CollectiveError::WorkflowNotFound(workflow_id.clone()))?;
+        let agent = self.engine.instance_id().to_string();
+
+        loop {
+            let ready = self.engine.get_ready_stages(&execution_id)?;
+            if ready.is_empty() {
+                break;
+            }
+            for stage_id in ready {
+                let Some(stage) = workflow.stages.iter().find(|s| s.stage_id == stage_id) else {
+                    continue;
+                };
+                self.engine
+                    .start_stage(&execution_id, &stage_id, vec![agent.clone()])?;
+                match step(stage) {
+                    Ok(result) => self
+                        .engine
+                        .complete_stage(&execution_id, &stage_id, result)?,
+                    Err(e) => {
+                        let reason = format!("{:#}", e);
+                        self.engine
+                            .fail_stage(&execution_id, &stage_id, reason.clone())?;
+                        return Err(CollectiveError::WorkflowExecutionFailed(format!(
+                            "{} stage {} failed: {}",
+                            job.name(),
+                            stage_id,
+                            reason
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(self
+            .engine
+            .get_execution(&execution_id)
+            .map(|execution| execution.results.clone())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_template_registers_with_the_engine() {
+        let mut engine = WorkflowEngine::new("agent-1".to_string());
+        for job in MaintenanceJob::ALL {
+            let workflow = job.workflow("agent-1".to_string());
+            assert!(!workflow.stages.is_empty());
+            assert!(workflow
+                .stages
+                .iter()
+                .all(|stage| stage.config["job"] == job.name()));
+            let workflow_id = engine.register_workflow(workflow).unwrap();
+            assert!(engine.get_workflow(&workflow_id).is_some());
+        }
+    }
+
+    #[test]
+    fn jobs_run_on_their_schedules() {
+        let hour = std::time::Duration::from_secs(3600);
+        let mut workflows = MaintenanceWorkflows::new(
+            "agent-1".to_string(),
+            [
+                (MaintenanceJob::TombstoneGc, hour),
+                (MaintenanceJob::IndexRebuild, hour * 24),
+            ],
+        )
+        .unwrap();
+        let start = Utc::now();
+        assert_eq!(
+            workflows.due(start),
+            vec![MaintenanceJob::TombstoneGc, MaintenanceJob::IndexRebuild]
+        );
+
+        let results = workflows
+            .run(MaintenanceJob::TombstoneGc, start, |stage| {
+                Ok(serde_json::json!({ "stage": stage.stage_id }))
+            })
+            .unwrap();
+        assert_eq!(
+            results["purge_tombstones"],
+            serde_json::json!({ "stage": "purge_tombstones" })
+        );
+
+        let err = workflows
+            .run(MaintenanceJob::IndexRebuild, start, |_| {
+                Err(anyhow::anyhow!("database is busy"))
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("database is busy"));
+
+        assert!(workflows.due(start).is_empty());
+        assert_eq!(
+            workflows.due(start + Duration::hours(1)),
+            vec![MaintenanceJob::TombstoneGc]
+        );
+        assert!(workflows
+            .run(MaintenanceJob::Summarization, start, |_| Ok(
+                serde_json::Value::Null
+            ))
+            .is_err());
+    }
+}
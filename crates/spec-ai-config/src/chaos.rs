@@ -0,0 +1,479 @@
+//! Fault injection for resilience testing
+//!
+//! A [`FaultScenario`] lists rules that add latency, errors or disconnects to
+//! provider calls, mesh sends and persistence operations. Components capture
+//! the process-wide [`FaultInjector`] from [`install`] when they are created,
+//! or take one explicitly, and consult it at their fault points so retry,
+//! failover and conflict paths can be exercised in integration tests.
+//!
+//! Scenarios are TOML files:
+//!
+//! ```toml
+//! seed = 7
+//!
+//! [[rule]]
+//! target = "provider"
+//! operation = "generate"
+//! probability = 0.5
+//! latency_ms = 200
+//! fault = { kind = "error", message = "503 overloaded" }
+//! ```
+
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Environment variable naming a scenario file for [`install_from_env`]
+pub const SCENARIO_ENV: &str = "SPEC_AI_CHAOS_SCENARIO";
+
+static INSTALLED: RwLock<Option<Arc<FaultInjector>>> = RwLock::new(None);
+
+/// Subsystem a fault rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FaultTarget {
+    Provider,
+    Mesh,
+    Persistence,
+}
+
+impl fmt::Display for FaultTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FaultTarget::Provider => "provider",
+            FaultTarget::Mesh => "mesh",
+            FaultTarget::Persistence => "persistence",
+        })
+    }
+}
+
+/// Failure returned in place of the real operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum FaultKind {
+    Error {
+        #[serde(default = "default_error_message")]
+        message: String,
+    },
+    /// The connection drops; streams are cut off after their first chunk
+    Disconnect,
+}
+
+fn default_error_message() -> String {
+    "injected error".to_string()
+}
+
+/// One rule of a fault scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaultRule {
+    pub target: FaultTarget,
+    /// Operation to match, such as `generate` or `insert_message`; unset or
+    /// `*` matches every operation of the target
+    #[serde(default)]
+    pub operation: Option<String>,
+    /// Chance that a matching call is affected, from 0.0 to 1.0
+    #[serde(default = "default_probability")]
+    pub probability: f64,
+    /// Delay added before the call
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Failure returned instead of running the call
+    #[serde(default)]
+    pub fault: Option<FaultKind>,
+    /// Matching calls let through before the rule starts applying
+    #[serde(default)]
+    pub skip: u32,
+    /// Stop applying after this many affected calls
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+fn default_probability() -> f64 {
+    1.0
+}
+
+impl FaultRule {
+    fn matches(&self, target: FaultTarget, operation: &str) -> bool {
+        self.target == target
+            && self
+                .operation
+                .as_deref()
+                .is_none_or(|op| op == "*" || op == operation)
+    }
+}
+
+/// Rules to inject, with the seed that makes probabilistic runs repeatable
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FaultScenario {
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<FaultRule>,
+}
+
+impl FaultScenario {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let scenario: Self = toml::from_str(content).context("parsing fault scenario")?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("reading fault scenario {}", path.display()))?;
+        Self::from_toml(&content)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !(0.0..=1.0).contains(&rule.probability) {
+                bail!(
+                    "fault rule {} has probability {}, expected 0.0 to 1.0",
+                    index,
+                    rule.probability
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error returned by a fault point instead of running the operation
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum InjectedFault {
+    #[error("injected {target} fault in {operation}: {message}")]
+    Error {
+        target: FaultTarget,
+        operation: String,
+        message: String,
+    },
+    #[error("injected {target} disconnect in {operation}")]
+    Disconnect {
+        target: FaultTarget,
+        operation: String,
+    },
+}
+
+impl InjectedFault {
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self, InjectedFault::Disconnect { .. })
+    }
+}
+
+/// What a fault point should do for one call
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FaultDecision {
+    /// Total latency of the matching rules
+    pub latency: Duration,
+    /// Failure of the first matching rule that has one
+    pub fault: Option<InjectedFault>,
+}
+
+impl FaultDecision {
+    pub fn into_result(self) -> Result<(), InjectedFault> {
+        match self.fault {
+            Some(fault) => Err(fault),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct InjectorState {
+    rng: u64,
+    /// Matching calls seen, per rule
+    seen: Vec<u32>,
+    /// Calls affected, per rule
+    hits: Vec<u32>,
+}
+
+/// Applies a [`FaultScenario`] at fault points
+#[derive(Debug)]
+pub struct FaultInjector {
+    scenario: FaultScenario,
+    state: Mutex<InjectorState>,
+}
+
+impl FaultInjector {
+    pub fn new(scenario: FaultScenario) -> Self {
+        let rules = scenario.rules.len();
+        Self {
+            state: Mutex::new(InjectorState {
+                rng: scenario.seed,
+                seen: vec![0; rules],
+                hits: vec![0; rules],
+            }),
+            scenario,
+        }
+    }
+
+    pub fn scenario(&self) -> &FaultScenario {
+        &self.scenario
+    }
+
+    /// Decide the latency and failure for one call of `operation`
+    pub fn decide(&self, target: FaultTarget, operation: &str) -> FaultDecision {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut decision = FaultDecision::default();
+
+        for (index, rule) in self.scenario.rules.iter().enumerate() {
+            if !rule.matches(target, operation) {
+                continue;
+            }
+            state.seen[index] += 1;
+            if state.seen[index] <= rule.skip
+                || rule.limit.is_some_and(|limit| state.hits[index] >= limit)
+            {
+                continue;
+            }
+            let roll = next_unit(&mut state.rng);
+            if roll >= rule.probability {
+                continue;
+            }
+
+            state.hits[index] += 1;
+            decision.latency += Duration::from_millis(rule.latency_ms);
+            if decision.fault.is_none() {
+                decision.fault = rule.fault.as_ref().map(|fault| match fault {
+                    FaultKind::Error { message } => InjectedFault::Error {
+                        target,
+                        operation: operation.to_string(),
+                        message: message.clone(),
+                    },
+                    FaultKind::Disconnect => InjectedFault::Disconnect {
+                        target,
+                        operation: operation.to_string(),
+                    },
+                });
+            }
+        }
+
+        if decision != FaultDecision::default() {
+            tracing::debug!(
+                %target,
+                operation,
+                latency_ms = decision.latency.as_millis() as u64,
+                fault = ?decision.fault,
+                "injecting fault"
+            );
+        }
+        decision
+    }
+
+    /// Decide for one call, sleeping the thread for any latency
+    pub fn apply_blocking(
+        &self,
+        target: FaultTarget,
+        operation: &str,
+    ) -> Result<(), InjectedFault> {
+        let decision = self.decide(target, operation);
+        if !decision.latency.is_zero() {
+            std::thread::sleep(decision.latency);
+        }
+        decision.into_result()
+    }
+
+    /// Calls each rule has affected so far, in rule order
+    pub fn hits(&self) -> Vec<u32> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .hits
+            .clone()
+    }
+}
+
+/// Uniform value in [0, 1) from a splitmix64 sequence
+fn next_unit(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Make `scenario` the injector for components created from now on
+pub fn install(scenario: FaultScenario) -> Arc<FaultInjector> {
+    let injector = Arc::new(FaultInjector::new(scenario));
+    *INSTALLED
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::clone(&injector));
+    injector
+}
+
+/// Stop injecting faults into components created from now on
+pub fn uninstall() {
+    *INSTALLED
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// Injector components should capture when they are created
+pub fn installed() -> Option<Arc<FaultInjector>> {
+    INSTALLED
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Install the scenario named by [`SCENARIO_ENV`], if it is set
+pub fn install_from_env() -> Result<Option<Arc<FaultInjector>>> {
+    let Ok(path) = std::env::var(SCENARIO_ENV) else {
+        return Ok(None);
+    };
+    let scenario = FaultScenario::load(&path)?;
+    tracing::warn!(
+        "Fault injection enabled with {} rule(s) from {}",
+        scenario.rules.len(),
+        path
+    );
+    Ok(Some(install(scenario)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(target: FaultTarget, operation: &str) -> FaultRule {
+        FaultRule {
+            target,
+            operation: Some(operation.to_string()),
+            probability: 1.0,
+            latency_ms: 0,
+            fault: None,
+            skip: 0,
+            limit: None,
+        }
+    }
+
+    #[test]
+    fn scenario_parses_from_toml() {
+        let scenario = FaultScenario::from_toml(
+            r#"
+            seed = 7
+
+            [[rule]]
+            target = "provider"
+            operation = "generate"
+            probability = 0.5
+            latency_ms = 200
+            fault = { kind = "error", message = "503 overloaded" }
+
+            [[rule]]
+            target = "mesh"
+            fault = { kind = "disconnect" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.seed, 7);
+        assert_eq!(
+            scenario.rules[0].fault,
+            Some(FaultKind::Error {
+                message: "503 overloaded".to_string()
+            })
+        );
+        assert_eq!(scenario.rules[1].operation, None);
+        assert_eq!(scenario.rules[1].probability, 1.0);
+        assert_eq!(scenario.rules[1].fault, Some(FaultKind::Disconnect));
+
+        let err =
+            FaultScenario::from_toml("[[rule]]\ntarget = \"persistence\"\nprobability = 2.0\n")
+                .unwrap_err();
+        assert!(err.to_string().contains("probability 2"));
+    }
+
+    #[test]
+    fn rules_respect_target_operation_skip_and_limit() {
+        let injector = FaultInjector::new(FaultScenario {
+            seed: 0,
+            rules: vec![
+                FaultRule {
+                    skip: 1,
+                    limit: Some(2),
+                    fault: Some(FaultKind::Error {
+                        message: "locked".to_string(),
+                    }),
+                    ..rule(FaultTarget::Persistence, "insert_message")
+                },
+                FaultRule {
+                    latency_ms: 5,
+                    operation: Some("*".to_string()),
+                    ..rule(FaultTarget::Persistence, "")
+                },
+            ],
+        });
+
+        let outcomes: Vec<bool> = (0..4)
+            .map(|_| {
+                injector
+                    .decide(FaultTarget::Persistence, "insert_message")
+                    .fault
+                    .is_some()
+            })
+            .collect();
+        assert_eq!(outcomes, vec![false, true, true, false]);
+        assert_eq!(
+            injector.decide(FaultTarget::Persistence, "list_messages"),
+            FaultDecision {
+                latency: Duration::from_millis(5),
+                fault: None,
+            }
+        );
+        assert_eq!(
+            injector.decide(FaultTarget::Mesh, "insert_message"),
+            FaultDecision::default()
+        );
+        assert_eq!(injector.hits(), vec![2, 5]);
+
+        let err = FaultInjector::new(FaultScenario {
+            seed: 0,
+            rules: vec![FaultRule {
+                fault: Some(FaultKind::Disconnect),
+                ..rule(FaultTarget::Mesh, "send_message")
+            }],
+        })
+        .apply_blocking(FaultTarget::Mesh, "send_message")
+        .unwrap_err();
+        assert!(err.is_disconnect());
+        assert_eq!(err.to_string(), "injected mesh disconnect in send_message");
+    }
+
+    #[test]
+    fn probabilistic_rules_repeat_with_the_same_seed() {
+        let scenario = FaultScenario {
+            seed: 42,
+            rules: vec![FaultRule {
+                probability: 0.5,
+                fault: Some(FaultKind::Disconnect),
+                ..rule(FaultTarget::Provider, "generate")
+            }],
+        };
+        let run = || {
+            let injector = FaultInjector::new(scenario.clone());
+            (0..64)
+                .map(|_| {
+                    injector
+                        .decide(FaultTarget::Provider, "generate")
+                        .fault
+                        .is_some()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = run();
+        assert_eq!(first, run());
+        let failures = first.iter().filter(|failed| **failed).count();
+        assert!((16..=48).contains(&failures), "{}", failures);
+    }
+}
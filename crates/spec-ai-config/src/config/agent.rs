@@ -1,6 +1,7 @@
+use super::agent_config::ModelConfig;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -9,6 +10,19 @@ pub enum AgentError {
     Invalid(String),
 }
 
+/// How an agent handles tool calls its policy does not allow
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyMode {
+    /// Ask the user whether to run the call
+    #[default]
+    Ask,
+    /// Refuse the call without asking
+    Enforce,
+    /// Run the call and record the violation; denied tools stay denied
+    Audit,
+}
+
 /// Configuration for a specific agent profile
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentProfile {
@@ -16,6 +30,19 @@ pub struct AgentProfile {
     #[serde(default)]
     pub prompt: Option<String>,
 
+    /// Template from the prompt library to render as the system prompt
+    /// instead of `prompt`, optionally pinned to a version ("coder@2")
+    #[serde(default)]
+    pub prompt_template: Option<String>,
+
+    /// Values for `{{variables}}` in this agent's prompt templates
+    #[serde(default)]
+    pub prompt_vars: HashMap<String, String>,
+
+    /// Template bodies this agent uses instead of the library's, by name
+    #[serde(default)]
+    pub prompt_overrides: HashMap<String, String>,
+
     /// Conversational style or personality
     #[serde(default)]
     pub style: Option<String>,
@@ -40,6 +67,10 @@ pub struct AgentProfile {
     #[serde(default)]
     pub denied_tools: Option<Vec<String>>,
 
+    /// What happens to tool calls the policy engine or tool lists block
+    #[serde(default)]
+    pub policy_mode: PolicyMode,
+
     /// Memory parameters: number of messages to recall (k for top-k)
     #[serde(default = "AgentProfile::default_memory_k")]
     pub memory_k: usize,
@@ -52,6 +83,53 @@ pub struct AgentProfile {
     #[serde(default)]
     pub max_context_tokens: Option<usize>,
 
+    /// Serve repeated identical generations from the response cache for this
+    /// many seconds; responses are not cached when unset
+    #[serde(default)]
+    pub response_cache_ttl_secs: Option<u64>,
+
+    /// Summarize older turns into a memory block, using the fast model when
+    /// one is configured, once the conversation outgrows the context budget
+    #[serde(default)]
+    pub context_compaction: bool,
+
+    /// Most recent messages that compaction always keeps verbatim
+    #[serde(default = "AgentProfile::default_context_keep_recent")]
+    pub context_keep_recent: usize,
+
+    /// Most read-only tool calls from one model turn that run at the same
+    /// time; calls to other tools always run alone, and 1 runs every call one
+    /// after another
+    #[serde(default = "AgentProfile::default_tool_parallelism")]
+    pub tool_parallelism: usize,
+
+    /// Seconds a single tool call may run before it fails; unlimited when unset
+    #[serde(default)]
+    pub tool_timeout_secs: Option<u64>,
+
+    /// Seconds a whole step may spend on tool calls and follow-up generations;
+    /// running tools are cancelled once it passes
+    #[serde(default)]
+    pub turn_timeout_secs: Option<u64>,
+
+    /// Most main model tokens one step may use; unlimited when unset
+    #[serde(default)]
+    pub max_run_tokens: Option<u64>,
+
+    /// Most estimated cost in dollars one step may spend; usage of models
+    /// without pricing is free. Unlimited when unset
+    #[serde(default)]
+    pub max_run_cost: Option<f64>,
+
+    /// Most tool calls one step may make; unlimited when unset
+    #[serde(default)]
+    pub max_run_tool_calls: Option<u32>,
+
+    /// Generation preset used unless a message picks another, such as
+    /// `precise` or `deep`
+    #[serde(default)]
+    pub preset: Option<String>,
+
     // ========== Knowledge Graph Configuration ==========
     /// Enable knowledge graph features for this agent
     #[serde(default)]
@@ -154,7 +232,7 @@ pub struct AgentProfile {
 }
 
 impl AgentProfile {
-    const ALWAYS_ALLOWED_TOOLS: [&'static str; 1] = ["prompt_user"];
+    const ALWAYS_ALLOWED_TOOLS: [&'static str; 2] = ["prompt_user", "ask_user"];
     fn default_memory_k() -> usize {
         10
     }
@@ -163,6 +241,14 @@ impl AgentProfile {
         0.9
     }
 
+    fn default_context_keep_recent() -> usize {
+        6
+    }
+
+    fn default_tool_parallelism() -> usize {
+        4
+    }
+
     fn default_graph_depth() -> usize {
         3
     }
@@ -253,6 +339,17 @@ impl AgentProfile {
             .into());
         }
 
+        // Validate max_run_cost
+        if let Some(cost) = self.max_run_cost {
+            if !cost.is_finite() || cost < 0.0 {
+                return Err(AgentError::Invalid(format!(
+                    "max_run_cost must be a non-negative number, got {}",
+                    cost
+                ))
+                .into());
+            }
+        }
+
         // Validate that allowed_tools and denied_tools don't overlap
         if let (Some(allowed), Some(denied)) = (&self.allowed_tools, &self.denied_tools) {
             let allowed_set: HashSet<_> = allowed.iter().collect();
@@ -270,7 +367,16 @@ impl AgentProfile {
 
         // Validate model provider if specified
         if let Some(provider) = &self.model_provider {
-            let valid_providers = ["mock", "openai", "anthropic", "ollama", "mlx", "lmstudio"];
+            let valid_providers = [
+                "mock",
+                "openai",
+                "anthropic",
+                "gemini",
+                "bedrock",
+                "ollama",
+                "mlx",
+                "lmstudio",
+            ];
             if !valid_providers.contains(&provider.as_str()) {
                 return Err(AgentError::Invalid(format!(
                     "model_provider must be one of: {}. Got: {}",
@@ -320,21 +426,54 @@ impl AgentProfile {
     pub fn effective_model_name<'a>(&'a self, default: Option<&'a str>) -> Option<&'a str> {
         self.model_name.as_deref().or(default)
     }
+
+    /// Model settings for this agent, based on `default`
+    ///
+    /// A profile on another provider inherits neither the default model
+    /// name nor its API key.
+    pub fn effective_model(&self, default: &ModelConfig) -> ModelConfig {
+        let mut model = default.clone();
+        if let Some(provider) = &self.model_provider {
+            if !provider.eq_ignore_ascii_case(&model.provider) {
+                model.provider = provider.to_lowercase();
+                model.model_name = None;
+                model.api_key_source = None;
+            }
+        }
+        if let Some(model_name) = &self.model_name {
+            model.model_name = Some(model_name.clone());
+        }
+        model
+    }
 }
 
 impl Default for AgentProfile {
     fn default() -> Self {
         Self {
             prompt: None,
+            prompt_template: None,
+            prompt_vars: HashMap::new(),
+            prompt_overrides: HashMap::new(),
             style: None,
             temperature: None,
             model_provider: None,
             model_name: None,
             allowed_tools: None,
             denied_tools: None,
+            policy_mode: PolicyMode::Ask,
             memory_k: Self::default_memory_k(),
             top_p: Self::default_top_p(),
             max_context_tokens: None,
+            response_cache_ttl_secs: None,
+            context_compaction: false,
+            context_keep_recent: Self::default_context_keep_recent(),
+            tool_parallelism: Self::default_tool_parallelism(),
+            tool_timeout_secs: None,
+            turn_timeout_secs: None,
+            max_run_tokens: None,
+            max_run_cost: None,
+            max_run_tool_calls: None,
+            preset: None,
             enable_graph: true, // Enable by default
             graph_memory: true, // Enable by default
             graph_depth: Self::default_graph_depth(),
@@ -407,6 +546,15 @@ mod tests {
         assert!(profile.validate().is_err());
     }
 
+    #[test]
+    fn test_validate_invalid_max_run_cost() {
+        let mut profile = AgentProfile::default();
+        profile.max_run_cost = Some(-1.0);
+        assert!(profile.validate().is_err());
+        profile.max_run_cost = Some(0.5);
+        assert!(profile.validate().is_ok());
+    }
+
     #[test]
     fn test_validate_tool_overlap() {
         let mut profile = AgentProfile::default();
@@ -430,8 +578,9 @@ mod tests {
         assert!(profile.is_tool_allowed("tool1"));
         assert!(profile.is_tool_allowed("tool2"));
         assert!(!profile.is_tool_allowed("tool3"));
-        // prompt_user should remain available even if not explicitly listed
+        // prompt_user and ask_user should remain available even if not explicitly listed
         assert!(profile.is_tool_allowed("prompt_user"));
+        assert!(profile.is_tool_allowed("ask_user"));
     }
 
     #[test]
@@ -461,4 +610,40 @@ mod tests {
         profile.model_provider = Some("openai".to_string());
         assert_eq!(profile.effective_provider("mock"), "openai");
     }
+
+    #[test]
+    fn test_effective_model() {
+        let default = ModelConfig {
+            provider: "openai".to_string(),
+            model_name: Some("gpt-4".to_string()),
+            code_model: None,
+            embeddings_model: None,
+            api_key_source: Some("env:OPENAI_API_KEY".to_string()),
+            temperature: 0.7,
+            bedrock: None,
+        };
+        let mut profile = AgentProfile::default();
+        let model = profile.effective_model(&default);
+        assert_eq!(model.provider, "openai");
+        assert_eq!(model.model_name.as_deref(), Some("gpt-4"));
+
+        profile.model_name = Some("gpt-4o".to_string());
+        let model = profile.effective_model(&default);
+        assert_eq!(model.model_name.as_deref(), Some("gpt-4o"));
+        assert_eq!(model.api_key_source, default.api_key_source);
+
+        profile.model_provider = Some("Anthropic".to_string());
+        profile.model_name = None;
+        let model = profile.effective_model(&default);
+        assert_eq!(model.provider, "anthropic");
+        assert_eq!(model.model_name, None);
+        assert_eq!(model.api_key_source, None);
+    }
+
+    #[test]
+    fn test_policy_mode_parses() {
+        let profile: AgentProfile = toml::from_str("policy_mode = \"enforce\"").unwrap();
+        assert_eq!(profile.policy_mode, PolicyMode::Enforce);
+        assert_eq!(AgentProfile::default().policy_mode, PolicyMode::Ask);
+    }
 }
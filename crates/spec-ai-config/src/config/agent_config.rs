@@ -4,10 +4,13 @@
 //! database configuration, UI preferences, and logging.
 
 use crate::config::agent::AgentProfile;
+use crate::config::presets::GenerationPreset;
+use crate::error::Error;
 use anyhow::{Context, Result};
 use directories::BaseDirs;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use spec_ai_knowledge_graph::{GraphEncryption, NamespaceKey};
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 /// Embedded default configuration file
@@ -26,6 +29,9 @@ pub struct AppConfig {
     /// Model provider configuration
     #[serde(default)]
     pub model: ModelConfig,
+    /// Retries and fallback providers for the model provider
+    #[serde(default)]
+    pub providers: ProvidersConfig,
     /// UI configuration
     #[serde(default)]
     pub ui: UiConfig,
@@ -38,15 +44,63 @@ pub struct AppConfig {
     /// Mesh networking configuration
     #[serde(default)]
     pub mesh: MeshConfig,
+    /// Liveness heartbeat configuration for headless instances
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    /// How long stored history is kept before maintenance prunes it
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Built-in workflows that keep the knowledge graph tidy
+    #[serde(default)]
+    pub graph_maintenance: GraphMaintenanceConfig,
     /// Plugin configuration for custom tools
     #[serde(default)]
     pub plugins: PluginConfig,
+    /// External tool servers reached over the Model Context Protocol
+    #[serde(default)]
+    pub mcp: McpConfig,
+    /// HTTP APIs whose OpenAPI operations are offered as tools
+    #[serde(default)]
+    pub openapi: OpenApiConfig,
+    /// Isolation and resource limits for the bash tool
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    /// Limits and domain policy for the web_fetch tool
+    #[serde(default)]
+    pub web_fetch: WebFetchConfig,
+    /// Databases the sql_query tool can reach
+    #[serde(default)]
+    pub sql: SqlConfig,
+    /// Redaction, logging, rate limits and output limits applied to every tool call
+    #[serde(default)]
+    pub tools: ToolsConfig,
     /// Graph synchronization configuration
     #[serde(default)]
     pub sync: SyncConfig,
     /// HTTP API authentication configuration
     #[serde(default)]
     pub auth: AuthConfig,
+    /// Per-model pricing used for usage cost reports
+    #[serde(default)]
+    pub pricing: PricingConfig,
+    /// Batching of embedding requests
+    #[serde(default)]
+    pub embeddings: EmbeddingsConfig,
+    /// Durable storage and similarity search for embeddings
+    #[serde(default)]
+    pub vector_store: VectorStoreConfig,
+    /// OpenTelemetry spans for agent turns, model calls and tools
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    /// JSONL export of agent activity for analytics pipelines
+    #[serde(default)]
+    pub event_log: EventLogConfig,
+    /// Named prompt templates shared by every agent
+    #[serde(default)]
+    pub prompts: HashMap<String, String>,
+    /// Named generation presets, added to or replacing the built-in ones
+    #[serde(default)]
+    pub presets: HashMap<String, GenerationPreset>,
     /// Available agent profiles
     #[serde(default)]
     pub agents: HashMap<String, AgentProfile>,
@@ -57,11 +111,12 @@ pub struct AppConfig {
 
 impl AppConfig {
     /// Load configuration from file or create a default configuration
-    pub fn load() -> Result<Self> {
+    pub fn load() -> Result<Self, Error> {
         // Try to load from spec-ai.config.toml in current directory
         if let Ok(content) = std::fs::read_to_string(CONFIG_FILE_NAME) {
-            return toml::from_str(&content)
-                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", CONFIG_FILE_NAME, e));
+            return toml::from_str(&content).map_err(|e| {
+                Error::Config(format!("Failed to parse {}: {}", CONFIG_FILE_NAME, e))
+            });
         }
 
         // Try to load from ~/.spec-ai/spec-ai.config.toml
@@ -71,7 +126,7 @@ impl AppConfig {
             let home_config = base_dirs.home_dir().join(".spec-ai").join(CONFIG_FILE_NAME);
             if let Ok(content) = std::fs::read_to_string(&home_config) {
                 return toml::from_str(&content).map_err(|e| {
-                    anyhow::anyhow!("Failed to parse {}: {}", home_config.display(), e)
+                    Error::Config(format!("Failed to parse {}: {}", home_config.display(), e))
                 });
             }
         }
@@ -80,7 +135,7 @@ impl AppConfig {
         if let Ok(config_path) = std::env::var("CONFIG_PATH") {
             if let Ok(content) = std::fs::read_to_string(&config_path) {
                 return toml::from_str(&content)
-                    .map_err(|e| anyhow::anyhow!("Failed to parse config: {}", e));
+                    .map_err(|e| Error::Config(format!("Failed to parse config: {}", e)));
             }
         }
 
@@ -101,16 +156,20 @@ impl AppConfig {
 
         // Parse and return the embedded default config
         toml::from_str(DEFAULT_CONFIG)
-            .map_err(|e| anyhow::anyhow!("Failed to parse embedded default config: {}", e))
+            .map_err(|e| Error::Config(format!("Failed to parse embedded default config: {}", e)))
     }
 
     /// Load configuration from a specific file path
     /// If the file doesn't exist, creates it with default settings
-    pub fn load_from_file(path: &std::path::Path) -> Result<Self> {
+    pub fn load_from_file(path: &std::path::Path) -> Result<Self, Error> {
         // Try to read existing file
         match std::fs::read_to_string(path) {
             Ok(content) => toml::from_str(&content).map_err(|e| {
-                anyhow::anyhow!("Failed to parse config file {}: {}", path.display(), e)
+                Error::Config(format!(
+                    "Failed to parse config file {}: {}",
+                    path.display(),
+                    e
+                ))
             }),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                 // File doesn't exist - create it with default config
@@ -121,15 +180,23 @@ impl AppConfig {
 
                 // Create parent directories if needed
                 if let Some(parent) = path.parent() {
-                    std::fs::create_dir_all(parent)
-                        .context(format!("Failed to create directory {}", parent.display()))?;
+                    std::fs::create_dir_all(parent).map_err(|e| {
+                        Error::Config(format!(
+                            "Failed to create directory {}: {}",
+                            parent.display(),
+                            e
+                        ))
+                    })?;
                 }
 
                 // Write default config
-                std::fs::write(path, DEFAULT_CONFIG).context(format!(
-                    "Failed to create config file at {}",
-                    path.display()
-                ))?;
+                std::fs::write(path, DEFAULT_CONFIG).map_err(|e| {
+                    Error::Config(format!(
+                        "Failed to create config file at {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
 
                 eprintln!(
                     "Created {}. You can edit this file to customize your settings.",
@@ -137,56 +204,223 @@ impl AppConfig {
                 );
 
                 // Parse and return the embedded default config
-                toml::from_str(DEFAULT_CONFIG)
-                    .map_err(|e| anyhow::anyhow!("Failed to parse embedded default config: {}", e))
+                toml::from_str(DEFAULT_CONFIG).map_err(|e| {
+                    Error::Config(format!("Failed to parse embedded default config: {}", e))
+                })
             }
-            Err(e) => Err(anyhow::anyhow!(
+            Err(e) => Err(Error::Config(format!(
                 "Failed to read config file {}: {}",
                 path.display(),
                 e
-            )),
+            ))),
         }
     }
 
     /// Validate the configuration
-    pub fn validate(&self) -> Result<()> {
+    pub fn validate(&self) -> Result<(), Error> {
         // Validate model provider: must be non-empty and supported
         if self.model.provider.is_empty() {
-            return Err(anyhow::anyhow!("Model provider cannot be empty"));
+            return Err(Error::Config("Model provider cannot be empty".to_string()));
         }
         // Validate against known provider names independent of compile-time feature flags
         {
             let p = self.model.provider.to_lowercase();
-            let known = ["mock", "openai", "anthropic", "ollama", "mlx", "lmstudio"];
+            let known = [
+                "mock",
+                "openai",
+                "anthropic",
+                "gemini",
+                "bedrock",
+                "ollama",
+                "mlx",
+                "lmstudio",
+            ];
             if !known.contains(&p.as_str()) {
-                return Err(anyhow::anyhow!(
+                return Err(Error::Config(format!(
                     "Invalid model provider: {}",
                     self.model.provider
-                ));
+                )));
+            }
+            // Fallback providers are checked like the main one
+            for fallback in &self.providers.fallback {
+                if !known.contains(&fallback.provider.to_lowercase().as_str()) {
+                    return Err(Error::Config(format!(
+                        "Invalid fallback provider: {}",
+                        fallback.provider
+                    )));
+                }
             }
         }
 
         // Validate temperature
         if self.model.temperature < 0.0 || self.model.temperature > 2.0 {
-            return Err(anyhow::anyhow!(
+            return Err(Error::Config(format!(
                 "Temperature must be between 0.0 and 2.0, got {}",
                 self.model.temperature
-            ));
+            )));
         }
 
         // Validate log level
         match self.logging.level.as_str() {
             "trace" | "debug" | "info" | "warn" | "error" => {}
-            _ => return Err(anyhow::anyhow!("Invalid log level: {}", self.logging.level)),
+            _ => {
+                return Err(Error::Config(format!(
+                    "Invalid log level: {}",
+                    self.logging.level
+                )))
+            }
+        }
+
+        // Validate keymap profile
+        match self.ui.keymap.profile.as_str() {
+            "default" | "vim" | "emacs" => {}
+            _ => {
+                return Err(Error::Config(format!(
+                    "Invalid keymap profile: {}",
+                    self.ui.keymap.profile
+                )))
+            }
+        }
+
+        if let Some(threshold) = &self.database.checkpoint_threshold {
+            let digits = threshold.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+            let unit = threshold[digits.len()..].to_ascii_uppercase();
+            if digits.is_empty()
+                || !digits.chars().all(|c| c.is_ascii_digit())
+                || !["B", "KB", "MB", "GB", "KIB", "MIB", "GIB"].contains(&unit.as_str())
+            {
+                return Err(Error::Config(format!(
+                    "Invalid database.checkpoint_threshold '{}' (expected a size such as \"64MB\")",
+                    threshold
+                )));
+            }
+        }
+
+        // Keys must come from the environment or a file, never the config itself
+        for namespace in &self.database.encrypted_namespaces {
+            if namespace.keys.is_empty() {
+                return Err(Error::Config(format!(
+                    "Encrypted namespace '{}' has no keys",
+                    namespace.namespace
+                )));
+            }
+            if let Some(source) = namespace
+                .keys
+                .iter()
+                .find(|source| !source.starts_with("env:") && !source.starts_with("file:"))
+            {
+                return Err(Error::Config(format!(
+                    "Key for encrypted namespace '{}' must be an env: or file: source, got '{}'",
+                    namespace.namespace, source
+                )));
+            }
+        }
+
+        // A zero interval would make the heartbeat task spin
+        if self.heartbeat.enabled && self.heartbeat.interval_secs == 0 {
+            return Err(Error::Config(
+                "heartbeat.interval_secs must be greater than zero".to_string(),
+            ));
+        }
+
+        self.retention.validate()?;
+        self.graph_maintenance.validate()?;
+
+        if !["model", "local"].contains(&self.embeddings.provider.as_str()) {
+            return Err(Error::Config(format!(
+                "Invalid embeddings provider '{}' (expected model or local)",
+                self.embeddings.provider
+            )));
+        }
+
+        if self.embeddings.max_batch_size == 0 {
+            return Err(Error::Config(
+                "embeddings.max_batch_size must be greater than zero".to_string(),
+            ));
+        }
+
+        if self.embeddings.chunk_size == 0
+            || self.embeddings.concurrency == 0
+            || self.embeddings.pipeline_batch_size == Some(0)
+        {
+            return Err(Error::Config(
+                "embeddings.chunk_size, concurrency and pipeline_batch_size must be greater than zero"
+                    .to_string(),
+            ));
+        }
+
+        if self.embeddings.chunk_overlap >= self.embeddings.chunk_size {
+            return Err(Error::Config(
+                "embeddings.chunk_overlap must be smaller than chunk_size".to_string(),
+            ));
+        }
+
+        self.vector_store.validate()?;
+
+        if !(0.0..=1.0).contains(&self.telemetry.sample_ratio) {
+            return Err(Error::Config(format!(
+                "telemetry.sample_ratio must be between 0.0 and 1.0, got {}",
+                self.telemetry.sample_ratio
+            )));
+        }
+
+        if let Some(endpoint) = &self.telemetry.control_endpoint {
+            let port = endpoint
+                .rsplit_once(':')
+                .map(|(_, port)| port.parse::<u16>());
+            if !matches!(port, Some(Ok(_))) {
+                return Err(Error::Config(format!(
+                    "telemetry.control_endpoint must be host:port, got '{}'",
+                    endpoint
+                )));
+            }
+        }
+
+        if self.event_log.enabled {
+            if self.event_log.path.is_none() && self.event_log.endpoint.is_none() {
+                return Err(Error::Config(
+                    "event_log.enabled needs event_log.path or event_log.endpoint".to_string(),
+                ));
+            }
+            if self.event_log.max_file_bytes == 0 {
+                return Err(Error::Config(
+                    "event_log.max_file_bytes must be greater than zero".to_string(),
+                ));
+            }
+        }
+
+        for (name, server) in &self.mcp.servers {
+            server.validate(name)?;
+        }
+        for (name, api) in &self.openapi.apis {
+            api.validate(name)?;
+        }
+        self.sandbox.validate()?;
+        self.web_fetch.validate()?;
+        self.sql.validate()?;
+        self.tools.validate()?;
+
+        for (name, preset) in &self.presets {
+            preset.validate(name)?;
+        }
+        for (agent, profile) in &self.agents {
+            if let Some(preset) = &profile.preset {
+                if GenerationPreset::resolve(preset, &self.presets).is_none() {
+                    return Err(Error::Config(format!(
+                        "Agent '{}' uses unknown preset '{}'",
+                        agent, preset
+                    )));
+                }
+            }
         }
 
         // If a default agent is specified, it must exist in the agents map
         if let Some(default_agent) = &self.default_agent {
             if !self.agents.contains_key(default_agent) {
-                return Err(anyhow::anyhow!(
+                return Err(Error::Config(format!(
                     "Default agent '{}' not found in agents map",
                     default_agent
-                ));
+                )));
             }
         }
 
@@ -223,9 +457,19 @@ impl AppConfig {
         if let Some(db_path) = first("AGENT_DB_PATH", "SPEC_AI_DB_PATH") {
             self.database.path = PathBuf::from(db_path);
         }
+        if let Some(mode) = first("AGENT_PERSISTENCE", "SPEC_AI_PERSISTENCE") {
+            match mode.to_lowercase().as_str() {
+                "file" => self.database.persistence = PersistenceMode::File,
+                "memory" => self.database.persistence = PersistenceMode::Memory,
+                _ => {}
+            }
+        }
         if let Some(theme) = first("AGENT_UI_THEME", "SPEC_AI_UI_THEME") {
             self.ui.theme = theme;
         }
+        if let Some(profile) = first("AGENT_UI_KEYMAP", "SPEC_AI_UI_KEYMAP") {
+            self.ui.keymap.profile = profile;
+        }
         if let Some(default_agent) = first("AGENT_DEFAULT_AGENT", "SPEC_AI_DEFAULT_AGENT") {
             self.default_agent = Some(default_agent);
         }
@@ -235,7 +479,7 @@ impl AppConfig {
     pub fn summary(&self) -> String {
         let mut summary = String::new();
         summary.push_str("Configuration loaded:\n");
-        summary.push_str(&format!("Database: {}\n", self.database.path.display()));
+        summary.push_str(&format!("Database: {}\n", self.database.location()));
         summary.push_str(&format!("Model Provider: {}\n", self.model.provider));
         if let Some(model) = &self.model.model_name {
             summary.push_str(&format!("Model Name: {}\n", model));
@@ -255,24 +499,128 @@ impl AppConfig {
 }
 
 /// Database configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DatabaseConfig {
     /// Path to the database file
     pub path: PathBuf,
+    /// Where data is kept; `path` is ignored in memory mode
+    #[serde(default)]
+    pub persistence: PersistenceMode,
+    /// Graph namespaces whose node and edge properties are encrypted at rest
+    #[serde(default)]
+    pub encrypted_namespaces: Vec<EncryptedNamespace>,
+    /// Connections kept for reads beside the one that writes; 0 reads
+    /// through the writer
+    #[serde(default = "default_read_connections")]
+    pub read_connections: usize,
+    /// How long opening the database waits for another process to release it
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64,
+    /// WAL size that triggers an automatic checkpoint, e.g. "64MB"; DuckDB's
+    /// default (16MB) when unset
+    #[serde(default)]
+    pub checkpoint_threshold: Option<String>,
+}
+
+fn default_read_connections() -> usize {
+    4
+}
+
+fn default_busy_timeout_ms() -> u64 {
+    5000
 }
 
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
             path: PathBuf::from("spec-ai.duckdb"),
+            persistence: PersistenceMode::File,
+            encrypted_namespaces: Vec::new(),
+            read_connections: default_read_connections(),
+            busy_timeout_ms: default_busy_timeout_ms(),
+            checkpoint_threshold: None,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// Database location for display, e.g. in the config summary
+    pub fn location(&self) -> String {
+        match self.persistence {
+            PersistenceMode::File => self.path.display().to_string(),
+            PersistenceMode::Memory => "in-memory".to_string(),
+        }
+    }
+
+    /// Load the keys of the encrypted namespaces
+    pub fn graph_encryption(&self) -> Result<GraphEncryption> {
+        let mut encryption = GraphEncryption::new();
+        for namespace in &self.encrypted_namespaces {
+            let keys = namespace
+                .keys
+                .iter()
+                .map(|source| {
+                    NamespaceKey::from_base64(&read_secret(source)?).with_context(|| {
+                        format!(
+                            "loading key {} for encrypted namespace '{}'",
+                            source, namespace.namespace
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            encryption = encryption.with_namespace(namespace.namespace.clone(), keys)?;
         }
+        Ok(encryption)
+    }
+}
+
+/// A graph namespace whose properties are encrypted at rest
+///
+/// Ids, types, labels and the shape of the graph stay readable; only node and
+/// edge properties are encrypted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedNamespace {
+    /// Session ID (namespace) of the graph
+    pub namespace: String,
+    /// Base64-encoded 32-byte keys as `env:VAR` or `file:PATH`, current key
+    /// first. Older keys only decrypt data written before a rotation.
+    pub keys: Vec<String>,
+}
+
+/// Read a secret from `env:VAR` or `file:PATH`
+fn read_secret(source: &str) -> Result<String> {
+    if let Some(var) = source.strip_prefix("env:") {
+        std::env::var(var).with_context(|| format!("Environment variable {} not set", var))
+    } else if let Some(path) = source.strip_prefix("file:") {
+        let path = match path.strip_prefix("~/") {
+            Some(rest) => BaseDirs::new()
+                .map(|dirs| dirs.home_dir().join(rest))
+                .unwrap_or_else(|| PathBuf::from(path)),
+            None => PathBuf::from(path),
+        };
+        std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .map(|s| s.trim().to_string())
+    } else {
+        Err(Error::Config(format!("Unsupported secret source: {}", source)).into())
     }
 }
 
+/// Storage backend for sessions, graph and sync state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PersistenceMode {
+    /// DuckDB database file at the configured path
+    #[default]
+    File,
+    /// Database held in memory and discarded on exit, for ephemeral runs and tests
+    Memory,
+}
+
 /// Model provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
-    /// Provider name (e.g., "openai", "anthropic", "mlx", "lmstudio", "mock")
+    /// Provider name (e.g., "openai", "anthropic", "bedrock", "lmstudio", "mock")
     pub provider: String,
     /// Model name to use (e.g., "gpt-4", "claude-3-opus")
     #[serde(default)]
@@ -289,6 +637,26 @@ pub struct ModelConfig {
     /// Default temperature for model completions (0.0 to 2.0)
     #[serde(default = "default_temperature")]
     pub temperature: f32,
+    /// AWS settings for the "bedrock" provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bedrock: Option<BedrockConfig>,
+}
+
+/// AWS Bedrock settings
+///
+/// Unset values come from the standard AWS environment variables and the
+/// shared config and credentials files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BedrockConfig {
+    /// AWS region (e.g., "us-east-1")
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Profile in the shared AWS config and credentials files
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Runtime endpoint to use instead of the regional one, e.g. a VPC endpoint
+    #[serde(default)]
+    pub endpoint_url: Option<String>,
 }
 
 fn default_temperature() -> f32 {
@@ -304,6 +672,67 @@ impl Default for ModelConfig {
             embeddings_model: None,
             api_key_source: None,
             temperature: default_temperature(),
+            bedrock: None,
+        }
+    }
+}
+
+/// Retry and failover settings for model providers
+///
+/// Rate limits, timeouts, 5xx responses and dropped connections are retried
+/// with exponential backoff, then the next provider in `fallback` is tried.
+/// A provider that keeps failing is skipped until its circuit resets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvidersConfig {
+    /// Providers tried in order after the main model, as `[[providers.fallback]]` tables
+    #[serde(default)]
+    pub fallback: Vec<ModelConfig>,
+    /// Retries of each provider before failing over
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each later one
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Longest delay between retries
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    /// Consecutive failed requests that open a provider's circuit
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds an open circuit skips its provider
+    #[serde(default = "default_circuit_reset")]
+    pub circuit_reset_secs: u64,
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_max_backoff_ms() -> u64 {
+    8_000
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_circuit_reset() -> u64 {
+    60
+}
+
+impl Default for ProvidersConfig {
+    fn default() -> Self {
+        Self {
+            fallback: Vec::new(),
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            failure_threshold: default_failure_threshold(),
+            circuit_reset_secs: default_circuit_reset(),
         }
     }
 }
@@ -315,6 +744,19 @@ pub struct UiConfig {
     pub prompt: String,
     /// UI theme name
     pub theme: String,
+    /// Key bindings for the terminal UI
+    #[serde(default)]
+    pub keymap: KeymapConfig,
+    /// Write the terminal UI as plain appended lines for screen readers
+    #[serde(default)]
+    pub screen_reader: bool,
+    /// Mirror the terminal UI's frames to read-only viewers through the API
+    #[serde(default)]
+    pub mirror: bool,
+    /// Announce the terminal UI in its session through the API server and
+    /// show who else is in it
+    #[serde(default)]
+    pub presence: bool,
 }
 
 impl Default for UiConfig {
@@ -322,6 +764,35 @@ impl Default for UiConfig {
         Self {
             prompt: "> ".to_string(),
             theme: "default".to_string(),
+            keymap: KeymapConfig::default(),
+            screen_reader: false,
+            mirror: false,
+            presence: false,
+        }
+    }
+}
+
+/// Key binding profile and per-action overrides for the terminal UI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeymapConfig {
+    /// Binding profile: "default", "vim" (modal editor) or "emacs"
+    #[serde(default = "default_keymap_profile")]
+    pub profile: String,
+    /// Keys for individual actions, keyed by "<context>.<action>" (e.g., "chat.scroll_up"),
+    /// replacing the profile's keys for that action
+    #[serde(default)]
+    pub bindings: BTreeMap<String, Vec<String>>,
+}
+
+fn default_keymap_profile() -> String {
+    "default".to_string()
+}
+
+impl Default for KeymapConfig {
+    fn default() -> Self {
+        Self {
+            profile: default_keymap_profile(),
+            bindings: BTreeMap::new(),
         }
     }
 }
@@ -362,6 +833,34 @@ pub struct MeshConfig {
     /// Auto-join mesh on startup
     #[serde(default)]
     pub auto_join: bool,
+    /// Per-class bandwidth limits for message delivery
+    #[serde(default)]
+    pub bandwidth: MeshBandwidthConfig,
+    /// Clock offset in milliseconds beyond which an instance is reported
+    /// as skewed
+    #[serde(default = "default_max_clock_skew_ms")]
+    pub max_clock_skew_ms: u64,
+}
+
+/// Bandwidth limits of the mesh message priority classes, in payload bytes
+/// per second for each receiving instance
+///
+/// Messages are always delivered in priority order (control, consensus,
+/// delegation, bulk sync); a class without a limit is not throttled.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MeshBandwidthConfig {
+    /// Queries, responses, notifications and capability exchange
+    #[serde(default)]
+    pub control: Option<u64>,
+    /// Proposals and votes
+    #[serde(default)]
+    pub consensus: Option<u64>,
+    /// Task delegation, results and workflow stages
+    #[serde(default)]
+    pub delegation: Option<u64>,
+    /// Graph sync and learning transfers
+    #[serde(default)]
+    pub bulk_sync: Option<u64>,
 }
 
 fn default_registry_port() -> u16 {
@@ -376,6 +875,10 @@ fn default_leader_timeout() -> u64 {
     15
 }
 
+fn default_max_clock_skew_ms() -> u64 {
+    1000
+}
+
 fn default_replication_factor() -> usize {
     2
 }
@@ -389,7 +892,247 @@ impl Default for MeshConfig {
             leader_timeout_secs: default_leader_timeout(),
             replication_factor: default_replication_factor(),
             auto_join: true,
+            bandwidth: MeshBandwidthConfig::default(),
+            max_clock_skew_ms: default_max_clock_skew_ms(),
+        }
+    }
+}
+
+/// Liveness heartbeat configuration
+///
+/// Servers and mesh members periodically record their liveness, active
+/// session count, queue depth and last error so `spec-ai status` can report
+/// on headless instances.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatConfig {
+    /// Publish heartbeats while running headless
+    #[serde(default = "default_heartbeat_enabled")]
+    pub enabled: bool,
+    /// Seconds between heartbeats
+    #[serde(default = "default_liveness_interval")]
+    pub interval_secs: u64,
+    /// Seconds without a heartbeat before an instance is reported as stale
+    #[serde(default = "default_stale_after")]
+    pub stale_after_secs: u64,
+    /// Sessions with activity in this many seconds count as active
+    #[serde(default = "default_session_idle")]
+    pub session_idle_secs: u64,
+}
+
+fn default_heartbeat_enabled() -> bool {
+    true
+}
+
+fn default_liveness_interval() -> u64 {
+    15
+}
+
+fn default_stale_after() -> u64 {
+    60
+}
+
+fn default_session_idle() -> u64 {
+    300
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_heartbeat_enabled(),
+            interval_secs: default_liveness_interval(),
+            stale_after_secs: default_stale_after(),
+            session_idle_secs: default_session_idle(),
+        }
+    }
+}
+
+/// Retention of stored history, enforced by a periodic maintenance task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Run the maintenance task in long-running instances
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds between maintenance runs
+    #[serde(default = "default_retention_interval")]
+    pub interval_secs: u64,
+    /// Conversations: messages, embeddings, pins, checkpoints and summaries
+    #[serde(default)]
+    pub sessions: RetentionRule,
+    /// Tool call log and audit records of expired API tokens
+    #[serde(default)]
+    pub audit: RetentionRule,
+    /// Liveness heartbeats recorded by headless instances
+    #[serde(default)]
+    pub telemetry: RetentionRule,
+    /// Graph changelog entries used by graph sync
+    #[serde(default)]
+    pub changelog: RetentionRule,
+    /// Token usage records behind `spec-ai usage`
+    #[serde(default)]
+    pub usage: RetentionRule,
+}
+
+fn default_retention_interval() -> u64 {
+    3600
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_retention_interval(),
+            sessions: RetentionRule::default(),
+            audit: RetentionRule::default(),
+            telemetry: RetentionRule::default(),
+            changelog: RetentionRule::default(),
+            usage: RetentionRule::default(),
+        }
+    }
+}
+
+/// Limits of one store; a store without limits is never pruned
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionRule {
+    /// Remove entries older than this many days
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+    /// Keep at most this many of the newest entries
+    #[serde(default)]
+    pub max_entries: Option<u64>,
+}
+
+impl RetentionRule {
+    /// Whether the rule removes anything
+    pub fn is_bounded(&self) -> bool {
+        self.max_age_days.is_some() || self.max_entries.is_some()
+    }
+}
+
+impl RetentionConfig {
+    /// The rules by store name, in the order maintenance applies them
+    pub fn rules(&self) -> [(&'static str, &RetentionRule); 5] {
+        [
+            ("sessions", &self.sessions),
+            ("audit", &self.audit),
+            ("telemetry", &self.telemetry),
+            ("changelog", &self.changelog),
+            ("usage", &self.usage),
+        ]
+    }
+
+    /// Check that limits are positive; zero would empty a store on every run
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.enabled && self.interval_secs == 0 {
+            return Err(Error::Config(
+                "retention.interval_secs must be greater than zero".to_string(),
+            ));
+        }
+        for (store, rule) in self.rules() {
+            if rule.max_age_days == Some(0) || rule.max_entries == Some(0) {
+                return Err(Error::Config(format!(
+                    "retention.{} limits must be greater than zero",
+                    store
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Built-in graph maintenance workflows run by long-running instances
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphMaintenanceConfig {
+    /// Run the enabled jobs in long-running instances
+    #[serde(default)]
+    pub enabled: bool,
+    /// Purge nodes and edges soft-deleted by graph sync
+    #[serde(default)]
+    pub tombstone_gc: MaintenanceJobConfig,
+    /// Drop and recreate the graph indexes
+    #[serde(default)]
+    pub index_rebuild: MaintenanceJobConfig,
+    /// Merge entity nodes whose labels match ignoring case and spacing
+    #[serde(default)]
+    pub entity_resolution: MaintenanceJobConfig,
+    /// Keep a summary node with counts and top entities in every session
+    #[serde(default)]
+    pub summarization: MaintenanceJobConfig,
+    /// Days a soft-deleted node or edge is kept so peers can sync the deletion
+    #[serde(default = "default_tombstone_grace_days")]
+    pub tombstone_grace_days: u64,
+    /// Best connected entities listed in each session summary
+    #[serde(default = "default_summary_top_entities")]
+    pub summary_top_entities: usize,
+}
+
+fn default_tombstone_grace_days() -> u64 {
+    7
+}
+
+fn default_summary_top_entities() -> usize {
+    10
+}
+
+impl Default for GraphMaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tombstone_gc: MaintenanceJobConfig::default(),
+            index_rebuild: MaintenanceJobConfig::default(),
+            entity_resolution: MaintenanceJobConfig::default(),
+            summarization: MaintenanceJobConfig::default(),
+            tombstone_grace_days: default_tombstone_grace_days(),
+            summary_top_entities: default_summary_top_entities(),
+        }
+    }
+}
+
+/// Schedule of one graph maintenance job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceJobConfig {
+    /// Run this job when graph maintenance is enabled
+    #[serde(default = "default_maintenance_job_enabled")]
+    pub enabled: bool,
+    /// Seconds between runs; the job's built-in schedule when unset
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+fn default_maintenance_job_enabled() -> bool {
+    true
+}
+
+impl Default for MaintenanceJobConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_maintenance_job_enabled(),
+            interval_secs: None,
+        }
+    }
+}
+
+impl GraphMaintenanceConfig {
+    /// The jobs by the names the built-in workflows use
+    pub fn jobs(&self) -> [(&'static str, &MaintenanceJobConfig); 4] {
+        [
+            ("tombstone_gc", &self.tombstone_gc),
+            ("index_rebuild", &self.index_rebuild),
+            ("entity_resolution", &self.entity_resolution),
+            ("summarization", &self.summarization),
+        ]
+    }
+
+    /// Check that intervals are positive; zero would run a job continuously
+    pub fn validate(&self) -> Result<(), Error> {
+        for (job, config) in self.jobs() {
+            if config.interval_secs == Some(0) {
+                return Err(Error::Config(format!(
+                    "graph_maintenance.{}.interval_secs must be greater than zero",
+                    job
+                )));
+            }
         }
+        Ok(())
     }
 }
 
@@ -503,6 +1246,18 @@ pub struct PluginConfig {
     /// Allow plugins to override built-in tools
     #[serde(default)]
     pub allow_override_builtin: bool,
+
+    /// URL or path of the plugin index `spec-ai plugin install <name>` reads
+    #[serde(default)]
+    pub index_url: Option<String>,
+
+    /// Base64 Ed25519 or minisign public keys whose plugin signatures are trusted
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
+
+    /// Refuse to install plugins without a trusted signature
+    #[serde(default = "default_require_signatures")]
+    pub require_signatures: bool,
 }
 
 fn default_plugins_dir() -> PathBuf {
@@ -513,6 +1268,10 @@ fn default_continue_on_error() -> bool {
     true
 }
 
+fn default_require_signatures() -> bool {
+    true
+}
+
 impl Default for PluginConfig {
     fn default() -> Self {
         Self {
@@ -520,10 +1279,599 @@ impl Default for PluginConfig {
             custom_tools_dir: default_plugins_dir(),
             continue_on_error: true,
             allow_override_builtin: false,
+            index_url: None,
+            trusted_keys: Vec::new(),
+            require_signatures: true,
+        }
+    }
+}
+
+/// External tool servers reached over the Model Context Protocol
+///
+/// Each enabled server is started (or connected to) once per process and its
+/// tools are offered to agents as `mcp_<server>_<tool>`, subject to the same
+/// profile and policy checks as built-in tools.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct McpConfig {
+    /// Servers by name, as in `[mcp.servers.github]`
+    #[serde(default)]
+    pub servers: HashMap<String, McpServerConfig>,
+}
+
+/// How to reach one MCP server: a `command` speaking over stdio, or the
+/// `url` of an SSE endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Set to false to keep the server configured without starting it
+    #[serde(default = "default_mcp_enabled")]
+    pub enabled: bool,
+    /// Program to run for a stdio server
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Arguments for `command`
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Variables added to the environment `command` inherits
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// SSE endpoint of a server reached over HTTP
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Headers sent with every request to `url`, such as `Authorization`
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Seconds to wait for the server to answer a request
+    #[serde(default = "default_mcp_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_mcp_enabled() -> bool {
+    true
+}
+
+fn default_mcp_timeout_secs() -> u64 {
+    60
+}
+
+impl Default for McpServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_mcp_enabled(),
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            url: None,
+            headers: HashMap::new(),
+            timeout_secs: default_mcp_timeout_secs(),
+        }
+    }
+}
+
+impl McpServerConfig {
+    /// Check that the server named `name` can be reached one way
+    pub fn validate(&self, name: &str) -> Result<(), Error> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(Error::Config(format!(
+                "MCP server name '{}' may only use letters, digits, '_' and '-'",
+                name
+            )));
+        }
+        if self.command.is_some() == self.url.is_some() {
+            return Err(Error::Config(format!(
+                "MCP server '{}' needs exactly one of command or url",
+                name
+            )));
+        }
+        if self.timeout_secs == 0 {
+            return Err(Error::Config(format!(
+                "mcp.servers.{}.timeout_secs must be greater than zero",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// HTTP APIs described by OpenAPI 3 documents
+///
+/// Each operation of an enabled API is offered to agents as
+/// `api_<name>_<operationId>`, subject to the same profile and policy checks
+/// as built-in tools.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenApiConfig {
+    /// APIs by name, as in `[openapi.apis.billing]`
+    #[serde(default)]
+    pub apis: HashMap<String, OpenApiSource>,
+}
+
+/// Where an API's OpenAPI document is and how to call it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenApiSource {
+    /// Set to false to keep the API configured without offering its tools
+    #[serde(default = "default_openapi_enabled")]
+    pub enabled: bool,
+    /// Path or http(s) URL of the document, in JSON or YAML
+    pub spec: String,
+    /// URL requests go to, instead of the first `servers` entry of the document
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Headers sent with every request, such as `Authorization`; values may
+    /// be `env:VAR` or `file:PATH` sources
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// operationIds to offer; empty offers every operation
+    #[serde(default)]
+    pub operations: Vec<String>,
+    /// Offer only GET and HEAD operations
+    #[serde(default)]
+    pub read_only: bool,
+    /// Seconds to wait for a response
+    #[serde(default = "default_openapi_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_openapi_enabled() -> bool {
+    true
+}
+
+fn default_openapi_timeout_secs() -> u64 {
+    30
+}
+
+impl OpenApiSource {
+    /// Check that the API named `name` has a usable name, document and timeout
+    pub fn validate(&self, name: &str) -> Result<(), Error> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(Error::Config(format!(
+                "OpenAPI name '{}' may only use letters, digits, '_' and '-'",
+                name
+            )));
+        }
+        if self.spec.trim().is_empty() {
+            return Err(Error::Config(format!("OpenAPI '{}' has no spec", name)));
+        }
+        if let Some(url) = &self.base_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(Error::Config(format!(
+                    "openapi.apis.{}.base_url must be an http or https URL",
+                    name
+                )));
+            }
+        }
+        if self.timeout_secs == 0 {
+            return Err(Error::Config(format!(
+                "openapi.apis.{}.timeout_secs must be greater than zero",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    /// The headers, with `env:` and `file:` values read from their source
+    pub fn headers(&self) -> Result<Vec<(String, String)>> {
+        self.headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if value.starts_with("env:") || value.starts_with("file:") {
+                    read_secret(value)?
+                } else {
+                    value.clone()
+                };
+                Ok((name.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// Isolation and resource limits for commands run by the bash tool
+///
+/// When enabled, commands run under bubblewrap on Linux or sandbox-exec on
+/// macOS: the filesystem is read-only except `write_paths`, reads can be
+/// narrowed to `read_paths`, and the network is cut off unless `network` is
+/// set. Policy rules allowing `file_read` or `file_write` on a path add it to
+/// the matching list for agents they apply to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// `auto`, `bubblewrap`, `sandbox-exec`, or `rlimit` for resource limits
+    /// without isolation
+    #[serde(default = "default_sandbox_backend")]
+    pub backend: String,
+    /// Longest a command may run, whatever timeout the agent asks for
+    #[serde(default = "default_sandbox_timeout_secs")]
+    pub timeout_secs: u64,
+    /// CPU seconds each process may use
+    #[serde(default = "default_sandbox_cpu_secs")]
+    pub cpu_secs: u64,
+    /// Address space each process may use, in MiB (not enforced on macOS)
+    #[serde(default = "default_sandbox_memory_mb")]
+    pub memory_mb: u64,
+    /// Allow network access
+    #[serde(default)]
+    pub network: bool,
+    /// Paths commands may read; the whole filesystem when empty
+    #[serde(default)]
+    pub read_paths: Vec<String>,
+    /// Paths commands may write, relative ones resolved against the working
+    /// directory of the process
+    #[serde(default = "default_sandbox_write_paths")]
+    pub write_paths: Vec<String>,
+}
+
+fn default_sandbox_backend() -> String {
+    "auto".to_string()
+}
+
+fn default_sandbox_timeout_secs() -> u64 {
+    120
+}
+
+fn default_sandbox_cpu_secs() -> u64 {
+    60
+}
+
+fn default_sandbox_memory_mb() -> u64 {
+    2048
+}
+
+fn default_sandbox_write_paths() -> Vec<String> {
+    vec![".".to_string()]
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: default_sandbox_backend(),
+            timeout_secs: default_sandbox_timeout_secs(),
+            cpu_secs: default_sandbox_cpu_secs(),
+            memory_mb: default_sandbox_memory_mb(),
+            network: false,
+            read_paths: Vec::new(),
+            write_paths: default_sandbox_write_paths(),
+        }
+    }
+}
+
+impl SandboxConfig {
+    /// Check the backend name, that every limit is set, and that an enabled
+    /// `rlimit` backend is not asked for isolation it cannot provide
+    pub fn validate(&self) -> Result<(), Error> {
+        if !["auto", "bubblewrap", "sandbox-exec", "rlimit"].contains(&self.backend.as_str()) {
+            return Err(Error::Config(format!(
+                "Invalid sandbox backend '{}' (expected auto, bubblewrap, sandbox-exec or rlimit)",
+                self.backend
+            )));
+        }
+        // Only the confining backends can cut off the network or hide paths
+        if self.enabled && self.backend == "rlimit" {
+            if !self.network {
+                return Err(Error::Config(
+                    "sandbox.backend = \"rlimit\" cannot cut off the network; set \
+                     sandbox.network = true, or use bubblewrap or sandbox-exec"
+                        .to_string(),
+                ));
+            }
+            if !self.read_paths.is_empty() {
+                return Err(Error::Config(
+                    "sandbox.backend = \"rlimit\" cannot narrow reads; leave \
+                     sandbox.read_paths empty, or use bubblewrap or sandbox-exec"
+                        .to_string(),
+                ));
+            }
+        }
+        for (name, value) in [
+            ("timeout_secs", self.timeout_secs),
+            ("cpu_secs", self.cpu_secs),
+            ("memory_mb", self.memory_mb),
+        ] {
+            if value == 0 {
+                return Err(Error::Config(format!(
+                    "sandbox.{} must be greater than zero",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Limits and domain policy for pages read by the web_fetch tool
+///
+/// Domains match themselves and their subdomains; a `*.` prefix is accepted
+/// and means the same. Denied domains win over allowed ones, and an empty
+/// allow list allows every domain not denied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebFetchConfig {
+    /// Domains pages may be fetched from; any domain when empty
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// Domains pages are never fetched from
+    #[serde(default)]
+    pub denied_domains: Vec<String>,
+    /// Longest a fetch may take, including the robots.txt check
+    #[serde(default = "default_web_fetch_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Largest response body read, in bytes; longer bodies are cut off
+    #[serde(default = "default_web_fetch_max_bytes")]
+    pub max_bytes: usize,
+    /// Longest extracted text returned to the agent, in characters
+    #[serde(default = "default_web_fetch_max_chars")]
+    pub max_chars: usize,
+    /// How long fetched pages are cached; 0 disables the cache
+    #[serde(default = "default_web_fetch_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    /// Skip pages the site's robots.txt disallows for the tool's user agent
+    #[serde(default = "default_web_fetch_respect_robots")]
+    pub respect_robots: bool,
+}
+
+fn default_web_fetch_timeout_secs() -> u64 {
+    20
+}
+
+fn default_web_fetch_max_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+fn default_web_fetch_max_chars() -> usize {
+    20_000
+}
+
+fn default_web_fetch_cache_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_web_fetch_respect_robots() -> bool {
+    true
+}
+
+impl Default for WebFetchConfig {
+    fn default() -> Self {
+        Self {
+            allowed_domains: Vec::new(),
+            denied_domains: Vec::new(),
+            timeout_secs: default_web_fetch_timeout_secs(),
+            max_bytes: default_web_fetch_max_bytes(),
+            max_chars: default_web_fetch_max_chars(),
+            cache_ttl_secs: default_web_fetch_cache_ttl_secs(),
+            respect_robots: default_web_fetch_respect_robots(),
+        }
+    }
+}
+
+impl WebFetchConfig {
+    /// Check that every limit is set and no domain is blank
+    pub fn validate(&self) -> Result<(), Error> {
+        for (name, value) in [
+            ("timeout_secs", self.timeout_secs),
+            ("max_bytes", self.max_bytes as u64),
+            ("max_chars", self.max_chars as u64),
+        ] {
+            if value == 0 {
+                return Err(Error::Config(format!(
+                    "web_fetch.{} must be greater than zero",
+                    name
+                )));
+            }
+        }
+        for domain in self.allowed_domains.iter().chain(&self.denied_domains) {
+            let name = domain.trim_start_matches("*.");
+            if name.is_empty() || name.contains('/') || name.contains(char::is_whitespace) {
+                return Err(Error::Config(format!(
+                    "Invalid web_fetch domain '{}' (expected a host name such as docs.rs)",
+                    domain
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Connection profiles of the sql_query tool, as in `[sql.connections.shop]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SqlConfig {
+    /// Profiles by name
+    #[serde(default)]
+    pub connections: HashMap<String, SqlConnectionConfig>,
+    /// Most rows a query returns unless a profile or call asks for fewer
+    #[serde(default = "default_sql_max_rows")]
+    pub max_rows: usize,
+}
+
+fn default_sql_max_rows() -> usize {
+    200
+}
+
+impl Default for SqlConfig {
+    fn default() -> Self {
+        Self {
+            connections: HashMap::new(),
+            max_rows: default_sql_max_rows(),
+        }
+    }
+}
+
+/// Database engine of a SQL connection profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlKind {
+    Sqlite,
+    Postgres,
+    Mysql,
+}
+
+impl SqlKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SqlKind::Sqlite => "sqlite",
+            SqlKind::Postgres => "postgres",
+            SqlKind::Mysql => "mysql",
+        }
+    }
+}
+
+/// One database the sql_query tool can reach
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SqlConnectionConfig {
+    pub kind: SqlKind,
+    /// SQLite file path, or a Postgres or MySQL connection string such as
+    /// `host=localhost dbname=shop user=analyst`
+    pub database: String,
+    /// Password as `env:VAR` or `file:PATH`, kept out of the connection string
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Refuse writes; when false, writes still need a `sql_write` policy rule
+    #[serde(default = "default_sql_read_only")]
+    pub read_only: bool,
+    /// Lower row limit for this profile
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+    /// What the database holds, shown to the model
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+fn default_sql_read_only() -> bool {
+    true
+}
+
+impl SqlConnectionConfig {
+    /// The password, read from its source
+    pub fn password(&self) -> Result<Option<String>> {
+        self.password.as_deref().map(read_secret).transpose()
+    }
+}
+
+impl SqlConfig {
+    /// Check that every profile has a usable name, a database and a password
+    /// kept outside the config
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_rows == 0 {
+            return Err(Error::Config(
+                "sql.max_rows must be greater than zero".to_string(),
+            ));
+        }
+        for (name, connection) in &self.connections {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(Error::Config(format!(
+                    "Invalid SQL connection name '{}' (use letters, digits, '-' or '_')",
+                    name
+                )));
+            }
+            if connection.database.trim().is_empty() {
+                return Err(Error::Config(format!(
+                    "SQL connection '{}' has no database",
+                    name
+                )));
+            }
+            if connection.max_rows == Some(0) {
+                return Err(Error::Config(format!(
+                    "sql.connections.{}.max_rows must be greater than zero",
+                    name
+                )));
+            }
+            if let Some(source) = &connection.password {
+                if connection.kind == SqlKind::Sqlite {
+                    return Err(Error::Config(format!(
+                        "SQL connection '{}' is SQLite, which takes no password",
+                        name
+                    )));
+                }
+                if !source.starts_with("env:") && !source.starts_with("file:") {
+                    return Err(Error::Config(format!(
+                        "Password of SQL connection '{}' must be an env: or file: source",
+                        name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Middleware wrapped around every tool call, as in `[tools]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolsConfig {
+    /// Argument names whose values are replaced with `[redacted]` in the
+    /// tool log, events and the knowledge graph; the tool still gets them
+    #[serde(default = "default_redact_arguments")]
+    pub redact_arguments: Vec<String>,
+    /// Record every tool call as a node in the session's knowledge graph
+    #[serde(default)]
+    pub log_to_graph: bool,
+    /// Longest output in characters a tool may return before it is cut
+    /// short; 0 keeps output whole
+    #[serde(default)]
+    pub max_output_chars: usize,
+    /// Output limits of single tools, replacing `max_output_chars`
+    #[serde(default)]
+    pub output_limits: HashMap<String, usize>,
+    /// Calls a tool may make per window, as in `[tools.rate_limits.web_fetch]`
+    #[serde(default)]
+    pub rate_limits: HashMap<String, ToolRateLimit>,
+}
+
+fn default_redact_arguments() -> Vec<String> {
+    ["password", "token", "api_key", "secret", "authorization"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            redact_arguments: default_redact_arguments(),
+            log_to_graph: false,
+            max_output_chars: 0,
+            output_limits: HashMap::new(),
+            rate_limits: HashMap::new(),
+        }
+    }
+}
+
+impl ToolsConfig {
+    /// Check that every rate limit allows some calls in a non-empty window
+    pub fn validate(&self) -> Result<(), Error> {
+        for (tool, limit) in &self.rate_limits {
+            if limit.max_calls == 0 || limit.per_secs == 0 {
+                return Err(Error::Config(format!(
+                    "tools.rate_limits.{}: max_calls and per_secs must be greater than zero",
+                    tool
+                )));
+            }
         }
+        Ok(())
     }
 }
 
+/// Most calls of one tool within a sliding window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolRateLimit {
+    pub max_calls: u32,
+    /// Length of the window in seconds
+    #[serde(default = "default_rate_limit_window")]
+    pub per_secs: u64,
+}
+
+fn default_rate_limit_window() -> u64 {
+    60
+}
+
 /// HTTP API authentication configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthConfig {
@@ -562,6 +1910,293 @@ impl Default for AuthConfig {
     }
 }
 
+/// OpenTelemetry spans for agent turns, model calls and tool executions
+///
+/// Spans are exported over OTLP/gRPC, for example to the spec-ai-oui-app
+/// receiver. Export needs spec-ai built with the `otel` feature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// Export spans
+    #[serde(default)]
+    pub enabled: bool,
+    /// OTLP/gRPC endpoint spans are sent to
+    #[serde(default = "default_telemetry_endpoint")]
+    pub endpoint: String,
+    /// Fraction of agent turns traced, from 0.0 to 1.0
+    #[serde(default = "default_telemetry_sample_ratio")]
+    pub sample_ratio: f64,
+    /// Reported as the `service.name` resource attribute
+    #[serde(default = "default_telemetry_service_name")]
+    pub service_name: String,
+    /// `host:port` of a sampling control channel, such as the one started by
+    /// `oui-demo --control`, which may change `sample_ratio` while running
+    #[serde(default)]
+    pub control_endpoint: Option<String>,
+}
+
+fn default_telemetry_endpoint() -> String {
+    "http://127.0.0.1:4317".to_string()
+}
+
+fn default_telemetry_sample_ratio() -> f64 {
+    1.0
+}
+
+fn default_telemetry_service_name() -> String {
+    "spec-ai".to_string()
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: default_telemetry_endpoint(),
+            sample_ratio: default_telemetry_sample_ratio(),
+            service_name: default_telemetry_service_name(),
+            control_endpoint: None,
+        }
+    }
+}
+
+/// JSONL export of messages, tool calls, policy decisions, sync operations
+/// and delegations
+///
+/// Events are appended to a file rotated by size, POSTed to an HTTP endpoint
+/// as `application/x-ndjson`, or both. The schema is in `docs/EVENTS.md`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLogConfig {
+    /// Export events
+    #[serde(default)]
+    pub enabled: bool,
+    /// File events are appended to
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Size at which the file is rotated to `<path>.1`
+    #[serde(default = "default_event_log_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Rotated files kept besides the current one
+    #[serde(default = "default_event_log_max_files")]
+    pub max_files: u32,
+    /// HTTP endpoint batches of events are POSTed to
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+fn default_event_log_max_file_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_event_log_max_files() -> u32 {
+    5
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            max_file_bytes: default_event_log_max_file_bytes(),
+            max_files: default_event_log_max_files(),
+            endpoint: None,
+        }
+    }
+}
+
+/// Where embeddings come from and how requests are batched
+///
+/// Concurrent embedding requests, such as memory recall during indexing, are
+/// held for up to `batch_window_ms` and sent to the provider as one call of
+/// at most `max_batch_size` inputs. Document sets are embedded by the batch
+/// pipeline, which splits them into chunks of `chunk_size` characters and
+/// runs `concurrency` batches at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingsConfig {
+    /// "model" asks the chat model's provider; "local" runs an ONNX model in
+    /// process (needs the `local-embeddings` feature)
+    #[serde(default = "default_embeddings_provider")]
+    pub provider: String,
+    /// Directory local models are downloaded to and loaded from
+    /// (default: ~/.spec-ai/models)
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+    /// Milliseconds to wait for more requests before sending a batch; 0 sends each request alone
+    #[serde(default = "default_embeddings_batch_window")]
+    pub batch_window_ms: u64,
+    /// Most inputs sent in one provider call
+    #[serde(default = "default_embeddings_max_batch")]
+    pub max_batch_size: usize,
+    /// Most characters in a document chunk
+    #[serde(default = "default_embeddings_chunk_size")]
+    pub chunk_size: usize,
+    /// Characters a chunk repeats from the end of the previous one
+    #[serde(default = "default_embeddings_chunk_overlap")]
+    pub chunk_overlap: usize,
+    /// Document batches embedded at once
+    #[serde(default = "default_embeddings_concurrency")]
+    pub concurrency: usize,
+    /// Chunks per provider call when embedding documents (default: suited to
+    /// the provider)
+    #[serde(default)]
+    pub pipeline_batch_size: Option<usize>,
+}
+
+fn default_embeddings_provider() -> String {
+    "model".to_string()
+}
+
+fn default_embeddings_batch_window() -> u64 {
+    5
+}
+
+fn default_embeddings_max_batch() -> usize {
+    64
+}
+
+fn default_embeddings_chunk_size() -> usize {
+    1000
+}
+
+fn default_embeddings_chunk_overlap() -> usize {
+    100
+}
+
+fn default_embeddings_concurrency() -> usize {
+    4
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            provider: default_embeddings_provider(),
+            cache_dir: None,
+            batch_window_ms: default_embeddings_batch_window(),
+            max_batch_size: default_embeddings_max_batch(),
+            chunk_size: default_embeddings_chunk_size(),
+            chunk_overlap: default_embeddings_chunk_overlap(),
+            concurrency: default_embeddings_concurrency(),
+            pipeline_batch_size: None,
+        }
+    }
+}
+
+impl EmbeddingsConfig {
+    /// Whether embeddings are computed in process rather than by a provider
+    pub fn is_local(&self) -> bool {
+        self.provider == "local"
+    }
+}
+
+/// Where embeddings are kept for semantic recall and graph similarity search
+///
+/// When disabled, recall scans the embeddings stored in the database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorStoreConfig {
+    /// Mirror embeddings into the vector store and search them there
+    #[serde(default)]
+    pub enabled: bool,
+    /// "sqlite" keeps vectors in an embedded file searched with sqlite-vec;
+    /// "qdrant" uses a Qdrant server (needs the `qdrant` feature)
+    #[serde(default = "default_vector_store_backend")]
+    pub backend: String,
+    /// File of the sqlite backend (default: ~/.spec-ai/vectors.sqlite)
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// gRPC endpoint of the Qdrant server
+    #[serde(default = "default_vector_store_url")]
+    pub url: String,
+    /// API key source of the Qdrant server (e.g. "env:QDRANT_API_KEY")
+    #[serde(default)]
+    pub api_key_source: Option<String>,
+    /// Prepended to collection names, so several instances can share a server
+    #[serde(default = "default_vector_store_prefix")]
+    pub collection_prefix: String,
+}
+
+fn default_vector_store_backend() -> String {
+    "sqlite".to_string()
+}
+
+fn default_vector_store_url() -> String {
+    "http://localhost:6334".to_string()
+}
+
+fn default_vector_store_prefix() -> String {
+    "spec_ai_".to_string()
+}
+
+impl Default for VectorStoreConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: default_vector_store_backend(),
+            path: None,
+            url: default_vector_store_url(),
+            api_key_source: None,
+            collection_prefix: default_vector_store_prefix(),
+        }
+    }
+}
+
+impl VectorStoreConfig {
+    /// Check the backend name and that the Qdrant URL is set when used
+    pub fn validate(&self) -> Result<(), Error> {
+        match self.backend.as_str() {
+            "sqlite" => Ok(()),
+            "qdrant" if self.url.trim().is_empty() => Err(Error::Config(
+                "vector_store.url is required for the qdrant backend".to_string(),
+            )),
+            "qdrant" => Ok(()),
+            other => Err(Error::Config(format!(
+                "Invalid vector_store.backend '{}' (expected \"sqlite\" or \"qdrant\")",
+                other
+            ))),
+        }
+    }
+}
+
+/// Token pricing configuration for usage and cost reports
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PricingConfig {
+    /// Prices keyed by model name as reported by the provider (e.g., "gpt-4.1")
+    #[serde(default)]
+    pub models: HashMap<String, ModelPricing>,
+}
+
+/// Price of a single model in currency units per million tokens
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ModelPricing {
+    /// Price per million prompt (input) tokens
+    #[serde(default)]
+    pub input_per_million: f64,
+    /// Price per million completion (output) tokens
+    #[serde(default)]
+    pub output_per_million: f64,
+}
+
+impl PricingConfig {
+    /// Look up pricing for a model, falling back to the longest configured prefix
+    /// so that dated snapshots (e.g., "gpt-4.1-2025-04-14") match "gpt-4.1".
+    pub fn for_model(&self, model: &str) -> Option<&ModelPricing> {
+        if let Some(pricing) = self.models.get(model) {
+            return Some(pricing);
+        }
+        self.models
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, pricing)| pricing)
+    }
+
+    /// Compute the cost of the given token counts, or `None` if the model is not priced.
+    pub fn cost(&self, model: &str, prompt_tokens: u64, completion_tokens: u64) -> Option<f64> {
+        self.for_model(model).map(|pricing| {
+            (prompt_tokens as f64 * pricing.input_per_million
+                + completion_tokens as f64 * pricing.output_per_million)
+                / 1_000_000.0
+        })
+    }
+}
+
 /// Graph synchronization configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncConfig {
@@ -632,3 +2267,497 @@ impl Default for SyncConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pricing() -> PricingConfig {
+        let mut models = HashMap::new();
+        models.insert(
+            "gpt-4.1".to_string(),
+            ModelPricing {
+                input_per_million: 2.0,
+                output_per_million: 8.0,
+            },
+        );
+        models.insert(
+            "gpt-4.1-mini".to_string(),
+            ModelPricing {
+                input_per_million: 0.4,
+                output_per_million: 1.6,
+            },
+        );
+        PricingConfig { models }
+    }
+
+    #[test]
+    fn pricing_prefers_longest_matching_prefix() {
+        let pricing = pricing();
+        let mini = pricing.for_model("gpt-4.1-mini-2025-04-14").unwrap();
+        assert_eq!(mini.input_per_million, 0.4);
+        let full = pricing.for_model("gpt-4.1-2025-04-14").unwrap();
+        assert_eq!(full.input_per_million, 2.0);
+        assert!(pricing.for_model("claude-3-opus").is_none());
+    }
+
+    #[test]
+    fn pricing_computes_cost_per_million_tokens() {
+        let pricing = pricing();
+        let cost = pricing.cost("gpt-4.1", 500_000, 250_000).unwrap();
+        assert!((cost - 3.0).abs() < f64::EPSILON);
+        assert_eq!(pricing.cost("unknown", 10, 10), None);
+    }
+
+    #[test]
+    fn keymap_section_parses_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [ui]
+            prompt = "> "
+            theme = "default"
+
+            [ui.keymap]
+            profile = "vim"
+
+            [ui.keymap.bindings]
+            "chat.scroll_up" = ["k", "ctrl+y"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.ui.keymap.profile, "vim");
+        assert_eq!(
+            config.ui.keymap.bindings["chat.scroll_up"],
+            vec!["k", "ctrl+y"]
+        );
+        assert!(config.validate().is_ok());
+
+        let mut config = AppConfig::default();
+        assert_eq!(config.ui.keymap, KeymapConfig::default());
+        config.ui.keymap.profile = "nano".to_string();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.code(), crate::error::ErrorCode::Config);
+    }
+
+    #[test]
+    fn memory_persistence_parses_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [database]
+            path = "unused.duckdb"
+            persistence = "memory"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.database.persistence, PersistenceMode::Memory);
+        assert_eq!(config.database.location(), "in-memory");
+
+        let config = AppConfig::default();
+        assert_eq!(config.database.persistence, PersistenceMode::File);
+        assert_eq!(config.database.location(), "spec-ai.duckdb");
+    }
+
+    #[test]
+    fn bedrock_section_parses_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [model]
+            provider = "bedrock"
+            model_name = "anthropic.claude-3-5-sonnet-20240620-v1:0"
+
+            [model.bedrock]
+            region = "eu-west-1"
+            profile = "work"
+            "#,
+        )
+        .unwrap();
+        let bedrock = config.model.bedrock.as_ref().unwrap();
+        assert_eq!(bedrock.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(bedrock.profile.as_deref(), Some("work"));
+        assert_eq!(bedrock.endpoint_url, None);
+        assert!(config.validate().is_ok());
+        assert!(AppConfig::default().model.bedrock.is_none());
+    }
+
+    #[test]
+    fn pricing_section_parses_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [pricing.models."gpt-4.1"]
+            input_per_million = 2.0
+            output_per_million = 8.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.pricing.models.len(), 1);
+        assert_eq!(
+            config
+                .pricing
+                .for_model("gpt-4.1")
+                .unwrap()
+                .output_per_million,
+            8.0
+        );
+    }
+
+    #[test]
+    fn event_log_section_parses_and_validates() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            [event_log]
+            enabled = true
+            path = "events.jsonl"
+            max_files = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.event_log.path.as_deref(),
+            Some(std::path::Path::new("events.jsonl"))
+        );
+        assert_eq!(config.event_log.max_files, 2);
+        assert_eq!(config.event_log.max_file_bytes, 10 * 1024 * 1024);
+        assert!(config.validate().is_ok());
+
+        config.event_log.path = None;
+        assert!(config.validate().is_err());
+        config.event_log.endpoint = Some("http://127.0.0.1:9000/events".to_string());
+        assert!(config.validate().is_ok());
+        config.event_log.max_file_bytes = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn mcp_servers_parse_and_validate() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            [mcp.servers.github]
+            command = "npx"
+            args = ["-y", "@modelcontextprotocol/server-github"]
+            env = { GITHUB_TOKEN_FILE = "~/.github-token" }
+
+            [mcp.servers.docs]
+            url = "http://127.0.0.1:8931/sse"
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        let github = &config.mcp.servers["github"];
+        assert_eq!(github.command.as_deref(), Some("npx"));
+        assert_eq!(github.args.len(), 2);
+        assert!(github.enabled);
+        assert_eq!(github.timeout_secs, 60);
+        assert!(!config.mcp.servers["docs"].enabled);
+        assert!(config.validate().is_ok());
+
+        let docs = config.mcp.servers.get_mut("docs").unwrap();
+        docs.command = Some("docs-server".to_string());
+        assert!(config.validate().is_err());
+
+        let mut config = AppConfig::default();
+        let server = McpServerConfig {
+            command: Some("server".to_string()),
+            ..Default::default()
+        };
+        config
+            .mcp
+            .servers
+            .insert("tools".to_string(), server.clone());
+        assert!(config.validate().is_ok());
+        config.mcp.servers.insert("has space".to_string(), server);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn sandbox_section_parses_and_validates() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            [sandbox]
+            enabled = true
+            memory_mb = 512
+            write_paths = [".", "/tmp/builds"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.sandbox.enabled);
+        assert_eq!(config.sandbox.backend, "auto");
+        assert_eq!(config.sandbox.memory_mb, 512);
+        assert!(!config.sandbox.network);
+        assert!(config.sandbox.read_paths.is_empty());
+        assert!(config.validate().is_ok());
+        assert_eq!(AppConfig::default().sandbox.write_paths, ["."]);
+
+        config.sandbox.backend = "docker".to_string();
+        assert!(config.validate().is_err());
+
+        // rlimit leaves the network and filesystem open, so it must be told to
+        config.sandbox.backend = "rlimit".to_string();
+        assert!(config.validate().is_err());
+        config.sandbox.network = true;
+        assert!(config.validate().is_ok());
+        config.sandbox.read_paths = vec!["/srv/data".to_string()];
+        assert!(config.validate().is_err());
+        config.sandbox.read_paths.clear();
+
+        config.sandbox.cpu_secs = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn telemetry_control_endpoint_must_be_host_port() {
+        let mut config = AppConfig::default();
+        config.telemetry.control_endpoint = Some("127.0.0.1:4320".to_string());
+        assert!(config.validate().is_ok());
+        config.telemetry.control_endpoint = Some("http://127.0.0.1".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn web_fetch_section_parses_and_validates() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            [web_fetch]
+            allowed_domains = ["docs.rs", "*.rust-lang.org"]
+            max_bytes = 65536
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.web_fetch.allowed_domains.len(), 2);
+        assert_eq!(config.web_fetch.max_bytes, 65536);
+        assert_eq!(config.web_fetch.timeout_secs, 20);
+        assert!(config.web_fetch.respect_robots);
+        assert!(config.validate().is_ok());
+
+        config.web_fetch.denied_domains = vec!["https://example.com/".to_string()];
+        assert!(config.validate().is_err());
+        config.web_fetch.denied_domains.clear();
+        config.web_fetch.timeout_secs = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn openapi_section_parses_and_validates() {
+        std::env::set_var("SPEC_AI_TEST_BILLING_TOKEN", "Bearer s3cret");
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            [openapi.apis.billing]
+            spec = "specs/billing.yaml"
+            base_url = "https://billing.internal"
+            headers = { Authorization = "env:SPEC_AI_TEST_BILLING_TOKEN", X-Team = "agents" }
+            "#,
+        )
+        .unwrap();
+        let api = &config.openapi.apis["billing"];
+        assert!(api.enabled);
+        assert_eq!(api.timeout_secs, 30);
+        let mut headers = api.headers().unwrap();
+        headers.sort();
+        assert_eq!(
+            headers,
+            vec![
+                ("Authorization".to_string(), "Bearer s3cret".to_string()),
+                ("X-Team".to_string(), "agents".to_string()),
+            ]
+        );
+        assert!(config.validate().is_ok());
+
+        config.openapi.apis.get_mut("billing").unwrap().base_url =
+            Some("billing.internal".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn tools_section_parses_and_validates() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            [tools]
+            max_output_chars = 20000
+
+            [tools.output_limits]
+            bash = 4000
+
+            [tools.rate_limits.web_fetch]
+            max_calls = 10
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.tools.output_limits.get("bash"), Some(&4000));
+        assert_eq!(config.tools.rate_limits["web_fetch"].per_secs, 60);
+        assert!(config
+            .tools
+            .redact_arguments
+            .contains(&"password".to_string()));
+        assert!(!config.tools.log_to_graph);
+        assert!(config.validate().is_ok());
+
+        config
+            .tools
+            .rate_limits
+            .get_mut("web_fetch")
+            .unwrap()
+            .max_calls = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn agents_may_only_use_known_presets() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+            [presets.review]
+            temperature = 0.3
+            reasoning_effort = "medium"
+
+            [agents.reviewer]
+            preset = "review"
+
+            [agents.thinker]
+            preset = "deep"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        config.agents.get_mut("thinker").unwrap().preset = Some("missing".to_string());
+        assert!(config.validate().is_err());
+        config.agents.get_mut("thinker").unwrap().preset = None;
+        config.presets.get_mut("review").unwrap().temperature = Some(3.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn fallback_providers_parse_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [model]
+            provider = "openai"
+            model_name = "gpt-4.1"
+
+            [providers]
+            max_retries = 1
+
+            [[providers.fallback]]
+            provider = "anthropic"
+            model_name = "claude-sonnet-4-5"
+            api_key_source = "env:ANTHROPIC_API_KEY"
+
+            [[providers.fallback]]
+            provider = "ollama"
+            "#,
+        )
+        .unwrap();
+        let providers = &config.providers;
+        assert_eq!(providers.max_retries, 1);
+        assert_eq!(providers.initial_backoff_ms, 500);
+        assert_eq!(providers.fallback.len(), 2);
+        assert_eq!(providers.fallback[0].provider, "anthropic");
+        assert_eq!(providers.fallback[1].temperature, 0.7);
+        assert!(config.validate().is_ok());
+
+        let mut config = config;
+        config.providers.fallback[1].provider = "nope".to_string();
+        assert!(config.validate().is_err());
+        assert!(AppConfig::default().providers.fallback.is_empty());
+    }
+
+    #[test]
+    fn local_embeddings_parse_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [embeddings]
+            provider = "local"
+            cache_dir = "/opt/spec-ai/models"
+            "#,
+        )
+        .unwrap();
+        assert!(config.embeddings.is_local());
+        assert_eq!(
+            config.embeddings.cache_dir,
+            Some(PathBuf::from("/opt/spec-ai/models"))
+        );
+        assert_eq!(config.embeddings.max_batch_size, 64);
+        assert!(config.validate().is_ok());
+        assert!(!AppConfig::default().embeddings.is_local());
+
+        let mut config = config;
+        config.embeddings.provider = "onnx".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn embedding_pipeline_settings_parse_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [embeddings]
+            chunk_size = 500
+            chunk_overlap = 50
+            concurrency = 8
+            pipeline_batch_size = 128
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.embeddings.chunk_size, 500);
+        assert_eq!(config.embeddings.chunk_overlap, 50);
+        assert_eq!(config.embeddings.concurrency, 8);
+        assert_eq!(config.embeddings.pipeline_batch_size, Some(128));
+        assert!(config.validate().is_ok());
+        assert_eq!(AppConfig::default().embeddings.chunk_size, 1000);
+        assert_eq!(AppConfig::default().embeddings.pipeline_batch_size, None);
+
+        let mut config = config;
+        config.embeddings.chunk_overlap = 500;
+        assert!(config.validate().is_err());
+        config.embeddings.chunk_overlap = 0;
+        config.embeddings.concurrency = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn vector_store_parses_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [vector_store]
+            enabled = true
+            backend = "qdrant"
+            api_key_source = "env:QDRANT_API_KEY"
+            "#,
+        )
+        .unwrap();
+        assert!(config.vector_store.enabled);
+        assert_eq!(config.vector_store.url, "http://localhost:6334");
+        assert_eq!(config.vector_store.collection_prefix, "spec_ai_");
+        assert!(config.validate().is_ok());
+        assert!(!AppConfig::default().vector_store.enabled);
+        assert_eq!(AppConfig::default().vector_store.backend, "sqlite");
+
+        let mut config = config;
+        config.vector_store.url = String::new();
+        assert!(config.validate().is_err());
+        config.vector_store.backend = "usearch".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn encrypted_namespaces_load_keys_from_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("personal.key");
+        std::fs::write(&key_path, "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=\n").unwrap();
+
+        let mut config: AppConfig = toml::from_str(&format!(
+            r#"
+            [[database.encrypted_namespaces]]
+            namespace = "personal"
+            keys = ['file:{}']
+            "#,
+            key_path.display()
+        ))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        let encryption = config.database.graph_encryption().unwrap();
+        assert_eq!(encryption.namespaces(), vec!["personal"]);
+
+        config.database.encrypted_namespaces[0].keys[0] = "c2VjcmV0".to_string();
+        assert!(config.validate().is_err());
+        config.database.encrypted_namespaces[0].keys[0] = "file:/does/not/exist".to_string();
+        assert!(config.database.graph_encryption().is_err());
+    }
+}
@@ -144,8 +144,10 @@ mod tests {
 
     fn create_test_config() -> AppConfig {
         use crate::config::{
-            AudioConfig, AuthConfig, DatabaseConfig, LoggingConfig, ModelConfig, PluginConfig,
-            SyncConfig, UiConfig,
+            AudioConfig, AuthConfig, DatabaseConfig, EmbeddingsConfig, EventLogConfig,
+            KeymapConfig, LoggingConfig, McpConfig, ModelConfig, OpenApiConfig, PersistenceMode,
+            PluginConfig, PricingConfig, SandboxConfig, SqlConfig, SyncConfig, TelemetryConfig,
+            ToolsConfig, UiConfig, WebFetchConfig,
         };
         use std::collections::HashMap;
         use std::path::PathBuf;
@@ -153,6 +155,11 @@ mod tests {
         AppConfig {
             database: DatabaseConfig {
                 path: PathBuf::from("/tmp/test.db"),
+                persistence: PersistenceMode::File,
+                encrypted_namespaces: Vec::new(),
+                read_connections: 4,
+                busy_timeout_ms: 5000,
+                checkpoint_threshold: None,
             },
             model: ModelConfig {
                 provider: "test".to_string(),
@@ -161,19 +168,41 @@ mod tests {
                 embeddings_model: None,
                 api_key_source: None,
                 temperature: 0.5,
+                bedrock: None,
             },
             ui: UiConfig {
                 prompt: "> ".to_string(),
                 theme: "default".to_string(),
+                keymap: KeymapConfig::default(),
+                screen_reader: false,
+                mirror: false,
+                presence: false,
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
             },
             audio: AudioConfig::default(),
             mesh: crate::config::MeshConfig::default(),
+            heartbeat: crate::config::HeartbeatConfig::default(),
+            retention: crate::config::RetentionConfig::default(),
+            graph_maintenance: crate::config::GraphMaintenanceConfig::default(),
+            providers: crate::config::ProvidersConfig::default(),
             plugins: PluginConfig::default(),
+            mcp: McpConfig::default(),
+            openapi: OpenApiConfig::default(),
+            sandbox: SandboxConfig::default(),
+            web_fetch: WebFetchConfig::default(),
+            sql: SqlConfig::default(),
+            tools: ToolsConfig::default(),
             sync: SyncConfig::default(),
             auth: AuthConfig::default(),
+            pricing: PricingConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            vector_store: crate::config::VectorStoreConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            event_log: EventLogConfig::default(),
+            prompts: HashMap::new(),
+            presets: HashMap::new(),
             agents: HashMap::new(),
             default_agent: None,
         }
@@ -1,12 +1,19 @@
 pub mod agent;
 pub mod agent_config;
 pub mod cache;
+pub mod presets;
 pub mod registry;
 
 // Re-export common types for convenience
-pub use agent::AgentProfile;
+pub use agent::{AgentProfile, PolicyMode};
 pub use agent_config::{
-    AppConfig, AudioConfig, AuthConfig, DatabaseConfig, LoggingConfig, MeshConfig, ModelConfig,
-    PluginConfig, SyncConfig, SyncNamespace, UiConfig,
+    AppConfig, AudioConfig, AuthConfig, BedrockConfig, DatabaseConfig, EmbeddingsConfig,
+    EncryptedNamespace, EventLogConfig, GraphMaintenanceConfig, HeartbeatConfig, KeymapConfig,
+    LoggingConfig, MaintenanceJobConfig, McpConfig, McpServerConfig, MeshBandwidthConfig,
+    MeshConfig, ModelConfig, ModelPricing, OpenApiConfig, OpenApiSource, PersistenceMode,
+    PluginConfig, PricingConfig, ProvidersConfig, RetentionConfig, RetentionRule, SandboxConfig,
+    SqlConfig, SqlConnectionConfig, SqlKind, SyncConfig, SyncNamespace, TelemetryConfig,
+    ToolRateLimit, ToolsConfig, UiConfig, VectorStoreConfig, WebFetchConfig,
 };
+pub use presets::{GenerationPreset, ReasoningEffort};
 pub use registry::AgentRegistry;
@@ -0,0 +1,201 @@
+//! Generation presets
+//!
+//! A preset is a named bundle of sampling settings and reasoning effort that
+//! can be picked per message instead of tuning each knob. `precise`,
+//! `balanced`, `creative`, `quick` and `deep` are built in; `[presets.<name>]`
+//! tables in the config add presets or replace built-in ones. How a preset's
+//! reasoning effort reaches a model depends on its provider; see
+//! `spec_ai_core::agent::factory::apply_preset`.
+
+use crate::error::Error;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Names of the built-in presets
+pub const BUILTIN_PRESETS: &[&str] = &["precise", "balanced", "creative", "quick", "deep"];
+
+/// How much a model should think before it answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for ReasoningEffort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Sampling settings and reasoning effort applied on top of an agent profile
+///
+/// Unset fields keep the profile's values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationPreset {
+    /// Sampling temperature (0.0 - 2.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Top-p sampling (0.0 - 1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Thinking asked of models that support it; ignored by others
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+impl GenerationPreset {
+    /// The built-in preset called `name`
+    pub fn builtin(name: &str) -> Option<Self> {
+        let preset = match name {
+            "precise" => Self {
+                temperature: Some(0.2),
+                top_p: Some(0.9),
+                reasoning_effort: None,
+            },
+            "balanced" => Self {
+                temperature: Some(0.7),
+                top_p: Some(1.0),
+                reasoning_effort: None,
+            },
+            "creative" => Self {
+                temperature: Some(1.1),
+                top_p: Some(1.0),
+                reasoning_effort: None,
+            },
+            "quick" => Self {
+                reasoning_effort: Some(ReasoningEffort::Low),
+                ..Self::default()
+            },
+            "deep" => Self {
+                reasoning_effort: Some(ReasoningEffort::High),
+                ..Self::default()
+            },
+            _ => return None,
+        };
+        Some(preset)
+    }
+
+    /// The preset called `name`, preferring those defined in `custom`
+    pub fn resolve(name: &str, custom: &HashMap<String, GenerationPreset>) -> Option<Self> {
+        custom.get(name).copied().or_else(|| Self::builtin(name))
+    }
+
+    /// Check the settings of the preset called `name`
+    pub fn validate(&self, name: &str) -> Result<(), Error> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err(Error::Config(format!(
+                    "presets.{}.temperature must be between 0.0 and 2.0, got {}",
+                    name, temperature
+                )));
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(Error::Config(format!(
+                    "presets.{}.top_p must be between 0.0 and 1.0, got {}",
+                    name, top_p
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// One-line description, such as `temperature 0.2, top_p 0.9`
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(temperature) = self.temperature {
+            parts.push(format!("temperature {}", temperature));
+        }
+        if let Some(top_p) = self.top_p {
+            parts.push(format!("top_p {}", top_p));
+        }
+        if let Some(effort) = self.reasoning_effort {
+            parts.push(format!("reasoning effort {}", effort));
+        }
+        if parts.is_empty() {
+            "profile defaults".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Names of every preset: the built-in ones, then those only in `custom`
+pub fn preset_names(custom: &HashMap<String, GenerationPreset>) -> Vec<String> {
+    let mut extra: Vec<String> = custom
+        .keys()
+        .filter(|name| !BUILTIN_PRESETS.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    extra.sort();
+    BUILTIN_PRESETS
+        .iter()
+        .map(|name| name.to_string())
+        .chain(extra)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_presets_replace_and_extend_the_builtin_ones() {
+        let custom: HashMap<String, GenerationPreset> = toml::from_str(
+            r#"
+            [precise]
+            temperature = 0.0
+
+            [review]
+            temperature = 0.3
+            reasoning_effort = "medium"
+            "#,
+        )
+        .unwrap();
+
+        let precise = GenerationPreset::resolve("precise", &custom).unwrap();
+        assert_eq!(precise.temperature, Some(0.0));
+        assert_eq!(precise.top_p, None);
+        let review = GenerationPreset::resolve("review", &custom).unwrap();
+        assert_eq!(review.reasoning_effort, Some(ReasoningEffort::Medium));
+        assert_eq!(review.summary(), "temperature 0.3, reasoning effort medium");
+        assert_eq!(
+            GenerationPreset::resolve("deep", &custom)
+                .unwrap()
+                .reasoning_effort,
+            Some(ReasoningEffort::High)
+        );
+        assert!(GenerationPreset::resolve("missing", &custom).is_none());
+
+        let names = preset_names(&custom);
+        assert_eq!(names.len(), BUILTIN_PRESETS.len() + 1);
+        assert_eq!(names.last().map(String::as_str), Some("review"));
+
+        let invalid = GenerationPreset {
+            top_p: Some(1.5),
+            ..GenerationPreset::default()
+        };
+        assert!(invalid.validate("wide").is_err());
+        for name in BUILTIN_PRESETS {
+            GenerationPreset::builtin(name)
+                .unwrap()
+                .validate(name)
+                .unwrap();
+        }
+    }
+}
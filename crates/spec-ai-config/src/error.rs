@@ -0,0 +1,352 @@
+//! Error type shared by the spec-ai library crates
+//!
+//! The entry points front ends call first return `Result<T, Error>`:
+//! `AppConfig::load`, `AppConfig::load_from_file`, `AppConfig::validate` and
+//! the `Persistence` constructors. Everything behind them, including the
+//! `Persistence` queries and `ModelProvider`, still returns `anyhow::Result`
+//! and raises [`Error`] for failures callers may want to handle, so that it
+//! can add context freely. Front ends recover the stable [`ErrorCode`] from
+//! any error chain with [`code_of`] (or `spec_ai_core::error::code_of`, which
+//! also knows about HTTP and plugin errors) and map it to an HTTP status,
+//! exit code or toast.
+
+use std::fmt;
+use thiserror::Error;
+
+/// Stable, machine-readable error classification
+///
+/// The string form is part of the public API: it is sent as the `code` of
+/// API error responses and must not change once released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// The request or command was malformed
+    InvalidRequest,
+    /// A session, agent, node or other resource does not exist
+    NotFound,
+    /// The request clashes with one made before, such as a reused idempotency key
+    Conflict,
+    /// Credentials were missing or rejected
+    Unauthorized,
+    /// A policy rule denied the action
+    PolicyDenied,
+    /// The configuration is invalid or could not be loaded
+    Config,
+    /// The persistence layer failed
+    Database,
+    /// A model provider rejected the request
+    Provider,
+    /// A model provider is overloaded or temporarily failing
+    ProviderUnavailable,
+    /// A rate limit was hit
+    RateLimited,
+    /// An operation did not finish in time
+    Timeout,
+    /// A connection could not be made or was dropped
+    Network,
+    /// A tool failed while executing
+    Tool,
+    /// A plugin failed to load or run
+    Plugin,
+    /// Anything not classified above
+    Internal,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 15] = [
+        ErrorCode::InvalidRequest,
+        ErrorCode::NotFound,
+        ErrorCode::Conflict,
+        ErrorCode::Unauthorized,
+        ErrorCode::PolicyDenied,
+        ErrorCode::Config,
+        ErrorCode::Database,
+        ErrorCode::Provider,
+        ErrorCode::ProviderUnavailable,
+        ErrorCode::RateLimited,
+        ErrorCode::Timeout,
+        ErrorCode::Network,
+        ErrorCode::Tool,
+        ErrorCode::Plugin,
+        ErrorCode::Internal,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::PolicyDenied => "policy_denied",
+            ErrorCode::Config => "config_error",
+            ErrorCode::Database => "database_error",
+            ErrorCode::Provider => "provider_error",
+            ErrorCode::ProviderUnavailable => "provider_unavailable",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Network => "network_error",
+            ErrorCode::Tool => "tool_error",
+            ErrorCode::Plugin => "plugin_error",
+            ErrorCode::Internal => "internal_error",
+        }
+    }
+
+    /// Code for a non-success HTTP status returned by a model provider
+    pub fn from_provider_status(status: u16) -> Self {
+        match status {
+            // A rejected API key is a problem with our configuration, not
+            // with whoever asked us to call the provider
+            401 | 403 => ErrorCode::Config,
+            408 | 504 => ErrorCode::Timeout,
+            429 => ErrorCode::RateLimited,
+            // Includes Anthropic's 529 "overloaded"
+            500..=599 => ErrorCode::ProviderUnavailable,
+            _ => ErrorCode::Provider,
+        }
+    }
+
+    /// Whether repeating the same operation later may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::ProviderUnavailable
+                | ErrorCode::RateLimited
+                | ErrorCode::Timeout
+                | ErrorCode::Network
+        )
+    }
+
+    /// Process exit code, following the BSD `sysexits.h` conventions
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorCode::InvalidRequest => 64,
+            ErrorCode::Conflict => 65,
+            ErrorCode::NotFound => 66,
+            ErrorCode::Provider | ErrorCode::ProviderUnavailable => 69,
+            ErrorCode::Tool | ErrorCode::Plugin => 70,
+            ErrorCode::Database => 74,
+            ErrorCode::RateLimited | ErrorCode::Timeout | ErrorCode::Network => 75,
+            ErrorCode::Unauthorized | ErrorCode::PolicyDenied => 77,
+            ErrorCode::Config => 78,
+            ErrorCode::Internal => 1,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ErrorCode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL
+            .into_iter()
+            .find(|code| code.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown error code: {}", s))
+    }
+}
+
+/// Errors raised by the spec-ai libraries
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    #[error("{kind} '{id}' not found")]
+    NotFound { kind: &'static str, id: String },
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("Denied by policy: {0}")]
+    PolicyDenied(String),
+
+    #[error("{0}")]
+    Config(String),
+
+    #[error("{0}")]
+    Database(String),
+
+    /// Non-success HTTP response from a model provider
+    #[error("{provider} API error ({status}): {message}")]
+    Provider {
+        provider: String,
+        status: u16,
+        message: String,
+    },
+
+    #[error("{0}")]
+    Timeout(String),
+
+    #[error("{0}")]
+    Network(String),
+
+    #[error("Tool '{tool}' failed: {message}")]
+    Tool { tool: String, message: String },
+
+    /// A failure from code that still returns `anyhow`, with the code it was
+    /// classified as when it reached a typed entry point
+    #[error("{error:#}")]
+    Other {
+        code: ErrorCode,
+        error: anyhow::Error,
+    },
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::InvalidRequest(_) => ErrorCode::InvalidRequest,
+            Error::NotFound { .. } => ErrorCode::NotFound,
+            Error::Conflict(_) => ErrorCode::Conflict,
+            Error::Unauthorized(_) => ErrorCode::Unauthorized,
+            Error::PolicyDenied(_) => ErrorCode::PolicyDenied,
+            Error::Config(_) => ErrorCode::Config,
+            Error::Database(_) => ErrorCode::Database,
+            Error::Provider { status, .. } => ErrorCode::from_provider_status(*status),
+            Error::Timeout(_) => ErrorCode::Timeout,
+            Error::Network(_) => ErrorCode::Network,
+            Error::Tool { .. } => ErrorCode::Tool,
+            Error::Other { code, .. } => *code,
+        }
+    }
+
+    /// Convert an `anyhow` error, keeping the code [`code_of`] finds in its
+    /// chain or else using `fallback`
+    pub fn from_anyhow(error: anyhow::Error, fallback: ErrorCode) -> Self {
+        // A bare `Error` is returned as is; one with context keeps it
+        let error = if error.chain().nth(1).is_none() {
+            match error.downcast::<Error>() {
+                Ok(error) => return error,
+                Err(error) => error,
+            }
+        } else {
+            error
+        };
+        let code = match code_of(&error) {
+            ErrorCode::Internal => fallback,
+            code => code,
+        };
+        Error::Other { code, error }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.code().is_retryable()
+    }
+}
+
+/// Code of a single error, if it is one of the types this crate knows
+pub fn classify(err: &(dyn std::error::Error + 'static)) -> Option<ErrorCode> {
+    if let Some(err) = err.downcast_ref::<Error>() {
+        return Some(err.code());
+    }
+    if err.is::<crate::config::agent::AgentError>() || err.is::<toml::de::Error>() {
+        return Some(ErrorCode::Config);
+    }
+    if err.is::<duckdb::Error>() {
+        return Some(ErrorCode::Database);
+    }
+    #[cfg(feature = "chaos")]
+    if let Some(fault) = err.downcast_ref::<crate::chaos::InjectedFault>() {
+        return Some(if fault.is_disconnect() {
+            ErrorCode::Network
+        } else {
+            ErrorCode::ProviderUnavailable
+        });
+    }
+    if let Some(err) = err.downcast_ref::<std::io::Error>() {
+        use std::io::ErrorKind;
+        return match err.kind() {
+            ErrorKind::NotFound => Some(ErrorCode::NotFound),
+            ErrorKind::PermissionDenied => Some(ErrorCode::Unauthorized),
+            ErrorKind::TimedOut => Some(ErrorCode::Timeout),
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe => Some(ErrorCode::Network),
+            _ => None,
+        };
+    }
+    None
+}
+
+/// Code of the first classifiable error in an `anyhow` chain
+pub fn code_of(err: &anyhow::Error) -> ErrorCode {
+    err.chain()
+        .find_map(classify)
+        .unwrap_or(ErrorCode::Internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn codes_round_trip_through_strings() {
+        for code in ErrorCode::ALL {
+            assert_eq!(code.as_str().parse::<ErrorCode>().unwrap(), code);
+        }
+        assert!("nope".parse::<ErrorCode>().is_err());
+    }
+
+    #[test]
+    fn provider_status_decides_retryability() {
+        let provider = |status| Error::Provider {
+            provider: "Anthropic".to_string(),
+            status,
+            message: "overloaded".to_string(),
+        };
+        assert_eq!(provider(429).code(), ErrorCode::RateLimited);
+        assert_eq!(provider(529).code(), ErrorCode::ProviderUnavailable);
+        assert!(provider(503).is_retryable());
+        assert_eq!(provider(400).code(), ErrorCode::Provider);
+        assert!(!provider(400).is_retryable());
+        assert_eq!(
+            provider(503).to_string(),
+            "Anthropic API error (503): overloaded"
+        );
+    }
+
+    #[test]
+    fn code_of_looks_through_context() {
+        let err = anyhow::Error::from(Error::NotFound {
+            kind: "session",
+            id: "abc".to_string(),
+        })
+        .context("Failed to resume");
+        assert_eq!(code_of(&err), ErrorCode::NotFound);
+
+        let err = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::TimedOut))
+            .context("mesh send")
+            .unwrap_err();
+        assert_eq!(code_of(&err), ErrorCode::Timeout);
+        assert_eq!(code_of(&anyhow::anyhow!("boom")), ErrorCode::Internal);
+    }
+
+    #[test]
+    fn from_anyhow_keeps_the_code_and_the_whole_message() {
+        let bare = anyhow::Error::from(Error::Database("locked".to_string()));
+        assert!(matches!(
+            Error::from_anyhow(bare, ErrorCode::Internal),
+            Error::Database(_)
+        ));
+
+        let err = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::TimedOut))
+            .context("opening database")
+            .unwrap_err();
+        let err = Error::from_anyhow(err, ErrorCode::Database);
+        assert_eq!(err.code(), ErrorCode::Timeout);
+        assert!(err.to_string().starts_with("opening database: "));
+
+        let err = Error::from_anyhow(anyhow::anyhow!("boom"), ErrorCode::Database);
+        assert_eq!(err.code(), ErrorCode::Database);
+        assert_eq!(code_of(&err.into()), ErrorCode::Database);
+    }
+}
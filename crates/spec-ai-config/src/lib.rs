@@ -1,4 +1,7 @@
+#[cfg(feature = "chaos")]
+pub mod chaos;
 pub mod config;
+pub mod error;
 pub mod persistence;
 pub mod sync;
 pub mod test_utils;
@@ -70,6 +70,78 @@ pub fn run(conn: &Connection) -> Result<()> {
         migrations_applied = true;
     }
 
+    if current < 10 {
+        apply_v10(conn)?;
+        set_version(conn, 10)?;
+        migrations_applied = true;
+    }
+
+    if current < 11 {
+        apply_v11(conn)?;
+        set_version(conn, 11)?;
+        migrations_applied = true;
+    }
+
+    if current < 12 {
+        apply_v12(conn)?;
+        set_version(conn, 12)?;
+        migrations_applied = true;
+    }
+
+    if current < 13 {
+        apply_v13(conn)?;
+        set_version(conn, 13)?;
+        migrations_applied = true;
+    }
+
+    if current < 14 {
+        apply_v14(conn)?;
+        set_version(conn, 14)?;
+        migrations_applied = true;
+    }
+
+    if current < 15 {
+        apply_v15(conn)?;
+        set_version(conn, 15)?;
+        migrations_applied = true;
+    }
+
+    if current < 16 {
+        apply_v16(conn)?;
+        set_version(conn, 16)?;
+        migrations_applied = true;
+    }
+
+    if current < 17 {
+        apply_v17(conn)?;
+        set_version(conn, 17)?;
+        migrations_applied = true;
+    }
+
+    if current < 18 {
+        apply_v18(conn)?;
+        set_version(conn, 18)?;
+        migrations_applied = true;
+    }
+
+    if current < 19 {
+        apply_v19(conn)?;
+        set_version(conn, 19)?;
+        migrations_applied = true;
+    }
+
+    if current < 20 {
+        apply_v20(conn)?;
+        set_version(conn, 20)?;
+        migrations_applied = true;
+    }
+
+    if current < 21 {
+        apply_v21(conn)?;
+        set_version(conn, 21)?;
+        migrations_applied = true;
+    }
+
     // Force checkpoint after migrations to ensure WAL is merged into the database file.
     // This prevents ALTER TABLE operations from being stuck in the WAL, which can cause
     // "no default database set" errors during WAL replay on subsequent startups.
@@ -577,3 +649,236 @@ fn apply_v9(conn: &Connection) -> Result<()> {
     )
     .context("applying v9 schema (collective intelligence)")
 }
+
+fn apply_v10(conn: &Connection) -> Result<()> {
+    // Per-call token usage records for cost reporting
+    conn.execute_batch(
+        r#"
+        CREATE SEQUENCE IF NOT EXISTS usage_records_id_seq START 1;
+
+        CREATE TABLE IF NOT EXISTS usage_records (
+            id BIGINT PRIMARY KEY DEFAULT nextval('usage_records_id_seq'),
+            session_id TEXT NOT NULL,
+            agent TEXT,
+            run_id TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            model TEXT NOT NULL,
+            prompt_tokens BIGINT NOT NULL DEFAULT 0,
+            completion_tokens BIGINT NOT NULL DEFAULT 0,
+            total_tokens BIGINT NOT NULL DEFAULT 0,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_usage_records_created ON usage_records(created_at);
+        CREATE INDEX IF NOT EXISTS idx_usage_records_provider_model ON usage_records(provider, model);
+        "#,
+    )
+    .context("applying v10 schema (usage records)")
+}
+
+fn apply_v11(conn: &Connection) -> Result<()> {
+    // Editor input history, namespaced per session
+    conn.execute_batch(
+        r#"
+        CREATE SEQUENCE IF NOT EXISTS input_history_id_seq START 1;
+
+        CREATE TABLE IF NOT EXISTS input_history (
+            id BIGINT PRIMARY KEY DEFAULT nextval('input_history_id_seq'),
+            session_id TEXT NOT NULL,
+            entry TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_input_history_session ON input_history(session_id, id);
+        "#,
+    )
+    .context("applying v11 schema (input history)")
+}
+
+fn apply_v12(conn: &Connection) -> Result<()> {
+    // Chat session metadata: agent and model selection, start and last activity
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS chat_sessions (
+            session_id TEXT PRIMARY KEY,
+            agent TEXT,
+            provider TEXT,
+            model TEXT,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+        "#,
+    )
+    .context("applying v12 schema (chat sessions)")
+}
+
+fn apply_v13(conn: &Connection) -> Result<()> {
+    // Latest liveness report of each headless instance, read by `spec-ai status`
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS agent_heartbeats (
+            instance_id TEXT PRIMARY KEY,
+            hostname TEXT NOT NULL,
+            pid BIGINT NOT NULL,
+            role TEXT NOT NULL,
+            status TEXT NOT NULL,
+            active_sessions BIGINT NOT NULL DEFAULT 0,
+            queue_depth BIGINT NOT NULL DEFAULT 0,
+            last_error TEXT,
+            last_error_code TEXT,
+            last_error_at TIMESTAMP,
+            started_at TIMESTAMP NOT NULL,
+            updated_at TIMESTAMP NOT NULL
+        );
+        "#,
+    )
+    .context("applying v13 schema (agent heartbeats)")
+}
+
+fn apply_v14(conn: &Connection) -> Result<()> {
+    // Model responses keyed by a hash of the request, served until they expire
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS response_cache (
+            key TEXT PRIMARY KEY,
+            model TEXT NOT NULL,
+            response TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            expires_at BIGINT NOT NULL  -- epoch milliseconds
+        );
+        "#,
+    )
+    .context("applying v14 schema (response cache)")
+}
+
+fn apply_v15(conn: &Connection) -> Result<()> {
+    // Context compaction: the running summary of older turns per session and
+    // the messages that are never folded into it
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS context_summaries (
+            session_id TEXT PRIMARY KEY,
+            summary TEXT NOT NULL,
+            through_message_id BIGINT NOT NULL,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS pinned_messages (
+            session_id TEXT NOT NULL,
+            message_id BIGINT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (session_id, message_id)
+        );
+        "#,
+    )
+    .context("applying v15 schema (context summaries)")
+}
+
+fn apply_v16(conn: &Connection) -> Result<()> {
+    // Audit log of scoped API tokens: who minted or revoked which token
+    conn.execute_batch(
+        r#"
+        CREATE SEQUENCE IF NOT EXISTS token_audit_id_seq START 1;
+
+        CREATE TABLE IF NOT EXISTS token_audit (
+            id BIGINT PRIMARY KEY DEFAULT nextval('token_audit_id_seq'),
+            token_id TEXT NOT NULL,
+            event TEXT NOT NULL,  -- "minted" or "revoked"
+            actor TEXT NOT NULL,
+            session_id TEXT,
+            scope TEXT NOT NULL,  -- JSON array of endpoint patterns
+            expires_at BIGINT NOT NULL,  -- epoch seconds
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_token_audit_token ON token_audit(token_id);
+        "#,
+    )
+    .context("applying v16 schema (token audit log)")
+}
+
+fn apply_v17(conn: &Connection) -> Result<()> {
+    // Idempotency keys of API mutations and the responses to replay for them;
+    // status is NULL while the first request is still running
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS idempotency_keys (
+            key TEXT PRIMARY KEY,
+            request_hash TEXT NOT NULL,
+            status INTEGER,
+            content_type TEXT,
+            body TEXT,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            expires_at BIGINT NOT NULL  -- epoch milliseconds
+        );
+        "#,
+    )
+    .context("applying v17 schema (idempotency keys)")
+}
+
+fn apply_v18(conn: &Connection) -> Result<()> {
+    // Saved prompt templates; every change of a template adds a version
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS prompt_templates (
+            name TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            body TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (name, version)
+        );
+        "#,
+    )
+    .context("applying v18 schema (prompt templates)")
+}
+
+fn apply_v19(conn: &Connection) -> Result<()> {
+    // Conversation branches, each a session copied from another up to a
+    // message, and named points in a conversation to branch from later
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS session_branches (
+            session_id TEXT PRIMARY KEY,
+            parent_session_id TEXT NOT NULL,
+            fork_message_id BIGINT NOT NULL,  -- last parent message id copied, 0 for none
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_session_branches_parent ON session_branches(parent_session_id);
+
+        CREATE TABLE IF NOT EXISTS conversation_checkpoints (
+            session_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            message_id BIGINT NOT NULL,  -- last message covered, 0 for an empty conversation
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (session_id, name)
+        );
+        "#,
+    )
+    .context("applying v19 schema (conversation branches)")
+}
+
+fn apply_v20(conn: &Connection) -> Result<()> {
+    // Readable text of pages fetched by the web_fetch tool, served until it expires
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS web_cache (
+            url TEXT PRIMARY KEY,
+            content TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            expires_at BIGINT NOT NULL  -- epoch milliseconds
+        );
+        "#,
+    )
+    .context("applying v20 schema (web page cache)")
+}
+
+fn apply_v21(conn: &Connection) -> Result<()> {
+    // Who wrote each message, for sessions several people take part in
+    conn.execute_batch(
+        r#"
+        ALTER TABLE messages ADD COLUMN author TEXT;
+        "#,
+    )
+    .context("applying v21 schema (message authors)")
+}
@@ -1,48 +1,126 @@
 pub mod migrations;
+pub mod pool;
+pub mod profile;
+pub mod retention;
 
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use directories::BaseDirs;
 use duckdb::{params, Connection};
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use spec_ai_knowledge_graph::KnowledgeGraphStore;
+use spec_ai_knowledge_graph::{GraphEncryption, KnowledgeGraphStore};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
+#[cfg(feature = "chaos")]
+use crate::chaos::{FaultInjector, FaultTarget};
+use crate::config::{DatabaseConfig, PersistenceMode};
+use crate::error::{Error, ErrorCode};
 use crate::types::{
-    GraphEdge, GraphNode, GraphPath, MemoryVector, Message, MessageRole, PolicyEntry,
+    GraphEdge, GraphNode, GraphPath, MemoryVector, Message, MessageRole, PolicyEntry, ToolLog,
 };
+use pool::{ConnectionPool, PoolOptions};
 
 #[derive(Clone)]
 pub struct Persistence {
-    conn: Arc<Mutex<Connection>>,
+    pool: Arc<ConnectionPool>,
     instance_id: String,
     graph_store: KnowledgeGraphStore,
+    #[cfg(feature = "chaos")]
+    faults: Option<Arc<FaultInjector>>,
 }
 
 impl Persistence {
     /// Create or open the database at the provided path and run migrations.
-    pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+    pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self, Error> {
         Self::with_instance_id(db_path, generate_instance_id())
     }
 
     /// Create with a specific instance_id
-    pub fn with_instance_id<P: AsRef<Path>>(db_path: P, instance_id: String) -> Result<Self> {
-        let db_path = expand_tilde(db_path.as_ref())?;
+    pub fn with_instance_id<P: AsRef<Path>>(
+        db_path: P,
+        instance_id: String,
+    ) -> Result<Self, Error> {
+        Self::open(db_path.as_ref(), instance_id, &PoolOptions::default())
+            .map_err(|e| Error::from_anyhow(e, ErrorCode::Database))
+    }
+
+    fn open(db_path: &Path, instance_id: String, options: &PoolOptions) -> Result<Self> {
+        let db_path = expand_tilde(db_path)?;
         if let Some(dir) = db_path.parent() {
             std::fs::create_dir_all(dir).context("creating DB directory")?;
         }
-        let conn = Connection::open(&db_path).context("opening DuckDB")?;
+        let conn = pool::open_file(&db_path, options.busy_timeout)?;
+        Self::from_connection(conn, instance_id, options)
+    }
+
+    fn open_in_memory(options: &PoolOptions) -> Result<Self> {
+        let conn = Connection::open_in_memory().context("opening in-memory DuckDB")?;
+        Self::from_connection(conn, generate_instance_id(), options)
+    }
+
+    /// Create a database that lives in memory and is discarded once the last
+    /// clone is dropped
+    ///
+    /// Every call returns a separate, empty database, which keeps tests isolated.
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::open_in_memory(&PoolOptions::default())
+            .map_err(|e| Error::from_anyhow(e, ErrorCode::Database))
+    }
+
+    /// Open the database described by the `[database]` config section
+    pub fn from_config(config: &DatabaseConfig) -> Result<Self, Error> {
+        let options = PoolOptions::from(config);
+        let persistence = match config.persistence {
+            PersistenceMode::File => Self::open(&config.path, generate_instance_id(), &options),
+            PersistenceMode::Memory => Self::open_in_memory(&options),
+        }
+        .map_err(|e| Error::from_anyhow(e, ErrorCode::Database))?;
+        if config.encrypted_namespaces.is_empty() {
+            return Ok(persistence);
+        }
+        let encryption = config
+            .graph_encryption()
+            .map_err(|e| Error::from_anyhow(e, ErrorCode::Config))?;
+        Ok(persistence.with_graph_encryption(encryption))
+    }
+
+    fn from_connection(
+        conn: Connection,
+        instance_id: String,
+        options: &PoolOptions,
+    ) -> Result<Self> {
         migrations::run(&conn).context("running migrations")?;
-        let conn_arc = Arc::new(Mutex::new(conn));
-        let graph_store = KnowledgeGraphStore::new(conn_arc.clone(), instance_id.clone());
+        let pool = Arc::new(ConnectionPool::new(conn, options)?);
+        let graph_store = KnowledgeGraphStore::new(pool.writer().clone(), instance_id.clone());
         Ok(Self {
-            conn: conn_arc,
+            pool,
             instance_id,
             graph_store,
+            #[cfg(feature = "chaos")]
+            faults: crate::chaos::installed(),
         })
     }
 
+    /// Inject faults from `injector` into this handle and its clones
+    #[cfg(feature = "chaos")]
+    pub fn with_fault_injector(mut self, injector: Arc<FaultInjector>) -> Self {
+        self.faults = Some(injector);
+        self
+    }
+
+    /// Delay or fail `operation` when a fault scenario says so
+    fn fault_point(&self, operation: &str) -> Result<()> {
+        #[cfg(feature = "chaos")]
+        if let Some(faults) = &self.faults {
+            faults.apply_blocking(FaultTarget::Persistence, operation)?;
+        }
+        #[cfg(not(feature = "chaos"))]
+        let _ = operation;
+        Ok(())
+    }
+
     /// Get the instance ID for this persistence instance
     pub fn instance_id(&self) -> &str {
         &self.instance_id
@@ -53,27 +131,39 @@ impl Persistence {
         &self.graph_store
     }
 
+    /// Encrypt graph properties of the namespaces in `encryption` at rest
+    pub fn with_graph_encryption(mut self, encryption: GraphEncryption) -> Self {
+        self.graph_store = self.graph_store.with_encryption(encryption);
+        self
+    }
+
     /// Checkpoint the database to ensure all WAL data is written to the main database file.
     /// Call this before shutdown to ensure clean database state.
     pub fn checkpoint(&self) -> Result<()> {
+        self.fault_point("checkpoint")?;
         let conn = self.conn();
         conn.execute_batch("CHECKPOINT;")
             .context("checkpointing database")
     }
 
     /// Creates or opens the default database at ~/.spec-ai/agent_data.duckdb
-    pub fn new_default() -> Result<Self> {
-        let base = BaseDirs::new().context("base directories not available")?;
+    pub fn new_default() -> Result<Self, Error> {
+        let base = BaseDirs::new()
+            .ok_or_else(|| Error::Config("base directories not available".to_string()))?;
         let path = base.home_dir().join(".agent_cli").join("agent_data.duckdb");
         Self::new(path)
     }
 
-    /// Get access to the pooled database connection.
+    /// Get access to the connection that writes.
     /// Returns a MutexGuard that provides exclusive access to the connection.
     pub fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
-        self.conn
-            .lock()
-            .expect("database connection mutex poisoned")
+        self.pool.write()
+    }
+
+    /// Get a pooled connection for statements that only read, so readers
+    /// don't wait on each other or on writes
+    pub fn read_conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.pool.read()
     }
 
     // ---------- Messages ----------
@@ -84,58 +174,48 @@ impl Persistence {
         role: MessageRole,
         content: &str,
     ) -> Result<i64> {
+        self.insert_message_by(session_id, role, content, None)
+    }
+
+    /// Insert a message written by `author`, for sessions shared by several people
+    pub fn insert_message_by(
+        &self,
+        session_id: &str,
+        role: MessageRole,
+        content: &str,
+        author: Option<&str>,
+    ) -> Result<i64> {
+        self.fault_point("insert_message")?;
         let conn = self.conn();
         let mut stmt = conn.prepare(
-            "INSERT INTO messages (session_id, role, content) VALUES (?, ?, ?) RETURNING id",
+            "INSERT INTO messages (session_id, role, content, author) VALUES (?, ?, ?, ?) RETURNING id",
         )?;
-        let id: i64 = stmt.query_row(params![session_id, role.as_str(), content], |row| {
-            row.get(0)
-        })?;
+        let id: i64 = stmt
+            .query_row(params![session_id, role.as_str(), content, author], |row| {
+                row.get(0)
+            })?;
         Ok(id)
     }
 
     pub fn list_messages(&self, session_id: &str, limit: i64) -> Result<Vec<Message>> {
-        let conn = self.conn();
-        let mut stmt = conn.prepare("SELECT id, session_id, role, content, CAST(created_at AS TEXT) as created_at FROM messages WHERE session_id = ? ORDER BY id DESC LIMIT ?")?;
+        self.fault_point("list_messages")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare("SELECT id, session_id, role, content, CAST(created_at AS TEXT) as created_at, author FROM messages WHERE session_id = ? ORDER BY id DESC LIMIT ?")?;
         let mut rows = stmt.query(params![session_id, limit])?;
         let mut out = Vec::new();
         while let Some(row) = rows.next()? {
-            let id: i64 = row.get(0)?;
-            let sid: String = row.get(1)?;
-            let role: String = row.get(2)?;
-            let content: String = row.get(3)?;
-            let created_at: String = row.get(4)?; // DuckDB returns TIMESTAMP as string
-            let created_at: DateTime<Utc> = created_at.parse().unwrap_or_else(|_| Utc::now());
-            out.push(Message {
-                id,
-                session_id: sid,
-                role: MessageRole::from_str(&role),
-                content,
-                created_at,
-            });
+            out.push(message_from_row(row)?);
         }
         out.reverse();
         Ok(out)
     }
 
     pub fn get_message(&self, message_id: i64) -> Result<Option<Message>> {
-        let conn = self.conn();
-        let mut stmt = conn.prepare("SELECT id, session_id, role, content, CAST(created_at AS TEXT) as created_at FROM messages WHERE id = ?")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare("SELECT id, session_id, role, content, CAST(created_at AS TEXT) as created_at, author FROM messages WHERE id = ?")?;
         let mut rows = stmt.query(params![message_id])?;
         if let Some(row) = rows.next()? {
-            let id: i64 = row.get(0)?;
-            let sid: String = row.get(1)?;
-            let role: String = row.get(2)?;
-            let content: String = row.get(3)?;
-            let created_at: String = row.get(4)?;
-            let created_at: DateTime<Utc> = created_at.parse().unwrap_or_else(|_| Utc::now());
-            Ok(Some(Message {
-                id,
-                session_id: sid,
-                role: MessageRole::from_str(&role),
-                content,
-                created_at,
-            }))
+            Ok(Some(message_from_row(row)?))
         } else {
             Ok(None)
         }
@@ -157,6 +237,7 @@ impl Persistence {
         message_id: Option<i64>,
         embedding: &[f32],
     ) -> Result<i64> {
+        self.fault_point("insert_memory_vector")?;
         let conn = self.conn();
         let embedding_json = serde_json::to_string(embedding)?;
         let mut stmt = conn.prepare("INSERT INTO memory_vectors (session_id, message_id, embedding) VALUES (?, ?, ?) RETURNING id")?;
@@ -172,7 +253,8 @@ impl Persistence {
         query_embedding: &[f32],
         k: usize,
     ) -> Result<Vec<(MemoryVector, f32)>> {
-        let conn = self.conn();
+        self.fault_point("recall_top_k")?;
+        let conn = self.read_conn();
         let mut stmt = conn.prepare("SELECT id, session_id, message_id, embedding, CAST(created_at AS TEXT) as created_at FROM memory_vectors WHERE session_id = ?")?;
         let mut rows = stmt.query(params![session_id])?;
         let mut scored: Vec<(MemoryVector, f32)> = Vec::new();
@@ -196,92 +278,969 @@ impl Persistence {
                 score,
             ));
         }
-        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        scored.truncate(k);
-        Ok(scored)
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(k);
+        Ok(scored)
+    }
+
+    /// List known session IDs ordered by most recent activity
+    pub fn list_sessions(&self) -> Result<Vec<String>> {
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT session_id, MAX(created_at) as last FROM messages GROUP BY session_id ORDER BY last DESC"
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            let sid: String = row.get(0)?;
+            out.push(sid);
+        }
+        Ok(out)
+    }
+
+    // ---------- Chat Sessions ----------
+
+    /// Record the agent and model used by a session and mark it active now.
+    pub fn upsert_session(
+        &self,
+        session_id: &str,
+        agent_name: Option<&str>,
+        provider: &str,
+        model: Option<&str>,
+    ) -> Result<()> {
+        self.fault_point("upsert_session")?;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO chat_sessions (session_id, agent, provider, model) VALUES (?, ?, ?, ?)
+             ON CONFLICT (session_id) DO UPDATE SET
+                agent = EXCLUDED.agent,
+                provider = EXCLUDED.provider,
+                model = EXCLUDED.model,
+                updated_at = CURRENT_TIMESTAMP",
+            params![session_id, agent_name, provider, model],
+        )?;
+        Ok(())
+    }
+
+    /// Summaries of the most recently active sessions, newest first.
+    ///
+    /// Sessions with messages but no recorded metadata (created before
+    /// metadata was tracked) are included with empty agent and model fields.
+    pub fn list_session_summaries(&self, limit: i64) -> Result<Vec<SessionRecord>> {
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "WITH activity AS (
+                SELECT session_id, COUNT(*) AS message_count,
+                       MIN(created_at) AS first_at, MAX(created_at) AS last_at,
+                       ARG_MIN(content, id) FILTER (WHERE role = 'user') AS title
+                FROM messages GROUP BY session_id
+             ), tools AS (
+                SELECT session_id, COUNT(*) AS tool_calls FROM tool_log GROUP BY session_id
+             ), summary AS (
+                SELECT COALESCE(s.session_id, a.session_id) AS session_id,
+                       s.agent, s.provider, s.model, a.title,
+                       COALESCE(a.message_count, 0) AS message_count,
+                       COALESCE(s.created_at, a.first_at) AS created_at,
+                       GREATEST(COALESCE(s.updated_at, a.last_at), COALESCE(a.last_at, s.updated_at)) AS updated_at
+                FROM chat_sessions s FULL OUTER JOIN activity a ON s.session_id = a.session_id
+             )
+             SELECT summary.session_id, agent, provider, model, title, message_count,
+                    COALESCE(tools.tool_calls, 0),
+                    CAST(created_at AS TEXT), CAST(updated_at AS TEXT)
+             FROM summary LEFT JOIN tools ON summary.session_id = tools.session_id
+             ORDER BY updated_at DESC LIMIT ?",
+        )?;
+        let mut rows = stmt.query(params![limit])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(SessionRecord::from_row(row)?);
+        }
+        Ok(out)
+    }
+
+    /// Insert or replace the liveness report of an instance
+    pub fn record_heartbeat(&self, heartbeat: &HeartbeatRecord) -> Result<()> {
+        self.fault_point("record_heartbeat")?;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT OR REPLACE INTO agent_heartbeats (instance_id, hostname, pid, role, status, active_sessions, queue_depth, last_error, last_error_code, last_error_at, started_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                heartbeat.instance_id,
+                heartbeat.hostname,
+                heartbeat.pid as i64,
+                heartbeat.role,
+                heartbeat.status,
+                heartbeat.active_sessions as i64,
+                heartbeat.queue_depth as i64,
+                heartbeat.last_error,
+                heartbeat.last_error_code,
+                heartbeat.last_error_at.map(|at| at.to_rfc3339()),
+                heartbeat.started_at.to_rfc3339(),
+                heartbeat.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Latest liveness report of every instance that has used this database,
+    /// most recently updated first.
+    pub fn list_heartbeats(&self) -> Result<Vec<HeartbeatRecord>> {
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT instance_id, hostname, pid, role, status, active_sessions, queue_depth,
+                    last_error, last_error_code, epoch_ms(last_error_at), epoch_ms(started_at), epoch_ms(updated_at)
+             FROM agent_heartbeats ORDER BY updated_at DESC",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(HeartbeatRecord::from_row(row)?);
+        }
+        Ok(out)
+    }
+
+    /// Most recent `limit` tool calls for a session, oldest first.
+    pub fn list_tool_calls(&self, session_id: &str, limit: i64) -> Result<Vec<ToolLog>> {
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, COALESCE(agent, ''), COALESCE(run_id, ''), tool_name, arguments, result, success, error, CAST(created_at AS TEXT)
+             FROM tool_log WHERE session_id = ? ORDER BY id DESC LIMIT ?",
+        )?;
+        let mut rows = stmt.query(params![session_id, limit])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            let arguments: String = row.get(5)?;
+            let result: String = row.get(6)?;
+            let error: Option<String> = row.get(8)?;
+            let created_at: String = row.get(9)?;
+            out.push(ToolLog {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                agent: row.get(2)?,
+                run_id: row.get(3)?,
+                tool_name: row.get(4)?,
+                arguments: serde_json::from_str(&arguments).unwrap_or(JsonValue::Null),
+                result: serde_json::from_str(&result).unwrap_or(JsonValue::Null),
+                success: row.get(7)?,
+                error: error.filter(|e| !e.is_empty()),
+                created_at: created_at.parse().unwrap_or_else(|_| Utc::now()),
+            });
+        }
+        out.reverse();
+        Ok(out)
+    }
+
+    // ---------- Tool Log ----------
+
+    pub fn log_tool(
+        &self,
+        session_id: &str,
+        agent_name: &str,
+        run_id: &str,
+        tool_name: &str,
+        arguments: &JsonValue,
+        result: &JsonValue,
+        success: bool,
+        error: Option<&str>,
+    ) -> Result<i64> {
+        self.fault_point("log_tool")?;
+        let conn = self.conn();
+        let mut stmt = conn.prepare("INSERT INTO tool_log (session_id, agent, run_id, tool_name, arguments, result, success, error) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id")?;
+        let id: i64 = stmt.query_row(
+            params![
+                session_id,
+                agent_name,
+                run_id,
+                tool_name,
+                arguments.to_string(),
+                result.to_string(),
+                success,
+                error.unwrap_or("")
+            ],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    // ---------- Usage Records ----------
+
+    /// Record token usage reported by a model provider for a single call.
+    pub fn record_usage(
+        &self,
+        session_id: &str,
+        agent_name: Option<&str>,
+        run_id: &str,
+        provider: &str,
+        model: &str,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+        total_tokens: u32,
+    ) -> Result<i64> {
+        self.fault_point("record_usage")?;
+        let conn = self.conn();
+        let mut stmt = conn.prepare("INSERT INTO usage_records (session_id, agent, run_id, provider, model, prompt_tokens, completion_tokens, total_tokens) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id")?;
+        let id: i64 = stmt.query_row(
+            params![
+                session_id,
+                agent_name,
+                run_id,
+                provider,
+                model,
+                prompt_tokens as i64,
+                completion_tokens as i64,
+                total_tokens as i64
+            ],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Aggregate usage records per day, provider and model.
+    ///
+    /// Both bounds are inclusive and compared against the UTC calendar day of each record.
+    pub fn usage_summary(
+        &self,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+    ) -> Result<Vec<UsageSummaryRecord>> {
+        let mut filters = Vec::new();
+        if let Some(since) = since {
+            filters.push(format!(
+                "CAST(created_at AS DATE) >= DATE '{}'",
+                since.format("%Y-%m-%d")
+            ));
+        }
+        if let Some(until) = until {
+            filters.push(format!(
+                "CAST(created_at AS DATE) <= DATE '{}'",
+                until.format("%Y-%m-%d")
+            ));
+        }
+        let where_clause = if filters.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", filters.join(" AND "))
+        };
+
+        let query = format!(
+            "SELECT CAST(CAST(created_at AS DATE) AS TEXT) AS day, provider, model, COUNT(*),
+                    CAST(SUM(prompt_tokens) AS BIGINT), CAST(SUM(completion_tokens) AS BIGINT), CAST(SUM(total_tokens) AS BIGINT)
+             FROM usage_records
+             {}
+             GROUP BY day, provider, model
+             ORDER BY day, provider, model",
+            where_clause
+        );
+
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(UsageSummaryRecord::from_row(row)?);
+        }
+        Ok(out)
+    }
+
+    /// Aggregate a session's usage records per provider and model, in order of first use.
+    pub fn session_usage(&self, session_id: &str) -> Result<Vec<ModelUsageRecord>> {
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT provider, model, COUNT(*),
+                    CAST(SUM(prompt_tokens) AS BIGINT), CAST(SUM(completion_tokens) AS BIGINT)
+             FROM usage_records
+             WHERE session_id = ?
+             GROUP BY provider, model
+             ORDER BY MIN(id)",
+        )?;
+        let mut rows = stmt.query(params![session_id])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            let calls: i64 = row.get(2)?;
+            let prompt_tokens: i64 = row.get(3)?;
+            let completion_tokens: i64 = row.get(4)?;
+            out.push(ModelUsageRecord {
+                provider: row.get(0)?,
+                model: row.get(1)?,
+                calls: calls.max(0) as u64,
+                prompt_tokens: prompt_tokens.max(0) as u64,
+                completion_tokens: completion_tokens.max(0) as u64,
+            });
+        }
+        Ok(out)
+    }
+
+    // ---------- Input History ----------
+
+    /// Append a submitted editor input to a session's history.
+    pub fn append_input_history(&self, session_id: &str, entry: &str) -> Result<i64> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("INSERT INTO input_history (session_id, entry) VALUES (?, ?) RETURNING id")?;
+        let id: i64 = stmt.query_row(params![session_id, entry], |row| row.get(0))?;
+        Ok(id)
+    }
+
+    /// Most recent `limit` inputs for a session, oldest first.
+    pub fn list_input_history(&self, session_id: &str, limit: i64) -> Result<Vec<String>> {
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT entry FROM input_history WHERE session_id = ? ORDER BY id DESC LIMIT ?",
+        )?;
+        let mut rows = stmt.query(params![session_id, limit])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(row.get::<_, String>(0)?);
+        }
+        out.reverse();
+        Ok(out)
+    }
+
+    // ---------- Policy Cache ----------
+
+    pub fn policy_upsert(&self, key: &str, value: &JsonValue) -> Result<()> {
+        let conn = self.conn();
+        // DuckDB upsert workaround: delete then insert atomically within a transaction.
+        conn.execute_batch("BEGIN TRANSACTION;")?;
+        {
+            let mut del = conn.prepare("DELETE FROM policy_cache WHERE key = ?")?;
+            let _ = del.execute(params![key])?;
+            let mut ins = conn.prepare("INSERT INTO policy_cache (key, value, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)")?;
+            let _ = ins.execute(params![key, value.to_string()])?;
+        }
+        conn.execute_batch("COMMIT;")?;
+        Ok(())
+    }
+
+    pub fn policy_get(&self, key: &str) -> Result<Option<PolicyEntry>> {
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare("SELECT key, value, CAST(updated_at AS TEXT) as updated_at FROM policy_cache WHERE key = ?")?;
+        let mut rows = stmt.query(params![key])?;
+        if let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            let value_text: String = row.get(1)?;
+            let updated_at: String = row.get(2)?;
+            let updated_at: DateTime<Utc> = updated_at.parse().unwrap_or_else(|_| Utc::now());
+            let value: JsonValue = serde_json::from_str(&value_text).unwrap_or(JsonValue::Null);
+            Ok(Some(PolicyEntry {
+                key,
+                value,
+                updated_at,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // ---------- Response Cache ----------
+
+    /// Cached model response stored under `key`, unless it has expired
+    pub fn response_cache_get(&self, key: &str) -> Result<Option<String>> {
+        self.fault_point("response_cache_get")?;
+        let conn = self.read_conn();
+        let mut stmt =
+            conn.prepare("SELECT response FROM response_cache WHERE key = ? AND expires_at > ?")?;
+        let mut rows = stmt.query(params![key, Utc::now().timestamp_millis()])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store a model response under `key` for `ttl`, replacing any previous one
+    pub fn response_cache_put(
+        &self,
+        key: &str,
+        model: &str,
+        response: &str,
+        ttl: std::time::Duration,
+    ) -> Result<()> {
+        self.fault_point("response_cache_put")?;
+        let expires_at = Utc::now().timestamp_millis() + ttl.as_millis() as i64;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT OR REPLACE INTO response_cache (key, model, response, created_at, expires_at)
+             VALUES (?, ?, ?, CURRENT_TIMESTAMP, ?)",
+            params![key, model, response, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// Delete expired responses, or every response when `all` is set,
+    /// returning how many were removed
+    pub fn response_cache_prune(&self, all: bool) -> Result<usize> {
+        let conn = self.conn();
+        let removed = if all {
+            conn.execute("DELETE FROM response_cache", [])?
+        } else {
+            conn.execute(
+                "DELETE FROM response_cache WHERE expires_at <= ?",
+                params![Utc::now().timestamp_millis()],
+            )?
+        };
+        Ok(removed)
+    }
+
+    // ---------- Web Cache ----------
+
+    /// Cached readable text of the page at `url`, unless it has expired
+    pub fn web_cache_get(&self, url: &str) -> Result<Option<String>> {
+        self.fault_point("web_cache_get")?;
+        let conn = self.read_conn();
+        let mut stmt =
+            conn.prepare("SELECT content FROM web_cache WHERE url = ? AND expires_at > ?")?;
+        let mut rows = stmt.query(params![url, Utc::now().timestamp_millis()])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the readable text of the page at `url` for `ttl`, replacing any
+    /// previous copy
+    pub fn web_cache_put(&self, url: &str, content: &str, ttl: std::time::Duration) -> Result<()> {
+        self.fault_point("web_cache_put")?;
+        let expires_at = Utc::now().timestamp_millis() + ttl.as_millis() as i64;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT OR REPLACE INTO web_cache (url, content, created_at, expires_at)
+             VALUES (?, ?, CURRENT_TIMESTAMP, ?)",
+            params![url, content, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// Delete expired pages, or every page when `all` is set, returning how
+    /// many were removed
+    pub fn web_cache_prune(&self, all: bool) -> Result<usize> {
+        let conn = self.conn();
+        let removed = if all {
+            conn.execute("DELETE FROM web_cache", [])?
+        } else {
+            conn.execute(
+                "DELETE FROM web_cache WHERE expires_at <= ?",
+                params![Utc::now().timestamp_millis()],
+            )?
+        };
+        Ok(removed)
+    }
+
+    // ---------- Context Compaction ----------
+
+    /// Messages of a session newer than `after_id`, oldest first
+    pub fn list_messages_after(&self, session_id: &str, after_id: i64) -> Result<Vec<Message>> {
+        self.fault_point("list_messages_after")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare("SELECT id, session_id, role, content, CAST(created_at AS TEXT) as created_at, author FROM messages WHERE session_id = ? AND id > ? ORDER BY id ASC")?;
+        let mut rows = stmt.query(params![session_id, after_id])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(message_from_row(row)?);
+        }
+        Ok(out)
+    }
+
+    /// Summary of the session's compacted turns, if any were compacted
+    pub fn context_summary(&self, session_id: &str) -> Result<Option<ContextSummaryRecord>> {
+        self.fault_point("context_summary")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT session_id, summary, through_message_id, CAST(updated_at AS TEXT)
+             FROM context_summaries WHERE session_id = ?",
+        )?;
+        let mut rows = stmt.query(params![session_id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(ContextSummaryRecord::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace the session's summary with one covering every message up to
+    /// and including `through_message_id`
+    pub fn save_context_summary(
+        &self,
+        session_id: &str,
+        summary: &str,
+        through_message_id: i64,
+    ) -> Result<()> {
+        self.fault_point("save_context_summary")?;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT OR REPLACE INTO context_summaries (session_id, summary, through_message_id, updated_at)
+             VALUES (?, ?, ?, CURRENT_TIMESTAMP)",
+            params![session_id, summary, through_message_id],
+        )?;
+        Ok(())
+    }
+
+    /// Keep a message out of compaction so it always reaches the prompt verbatim
+    pub fn pin_message(&self, session_id: &str, message_id: i64) -> Result<()> {
+        self.fault_point("pin_message")?;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT OR IGNORE INTO pinned_messages (session_id, message_id) VALUES (?, ?)",
+            params![session_id, message_id],
+        )?;
+        Ok(())
+    }
+
+    /// Unpin a message, returning whether it was pinned
+    pub fn unpin_message(&self, session_id: &str, message_id: i64) -> Result<bool> {
+        self.fault_point("unpin_message")?;
+        let conn = self.conn();
+        let removed = conn.execute(
+            "DELETE FROM pinned_messages WHERE session_id = ? AND message_id = ?",
+            params![session_id, message_id],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Pinned messages of a session, oldest first
+    pub fn list_pinned_messages(&self, session_id: &str) -> Result<Vec<Message>> {
+        self.fault_point("list_pinned_messages")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.session_id, m.role, m.content, CAST(m.created_at AS TEXT)
+             FROM pinned_messages p JOIN messages m ON m.id = p.message_id
+             WHERE p.session_id = ? ORDER BY m.id ASC",
+        )?;
+        let mut rows = stmt.query(params![session_id])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(message_from_row(row)?);
+        }
+        Ok(out)
+    }
+
+    // ---------- Conversation Branches ----------
+
+    /// Start `branch_id` as a copy of `session_id` up to and including
+    /// `through_message_id`, returning how many messages were copied
+    ///
+    /// Messages keep their roles, content and timestamps. Pins, checkpoints
+    /// and the context summary covering the copied messages come along, so
+    /// the branch continues exactly where the parent was at that message.
+    pub fn fork_session(
+        &self,
+        session_id: &str,
+        branch_id: &str,
+        through_message_id: i64,
+    ) -> Result<usize> {
+        self.fault_point("fork_session")?;
+        let conn = self.conn();
+        let taken: i64 = conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM messages WHERE session_id = ?)
+                  + (SELECT COUNT(*) FROM session_branches WHERE session_id = ?)",
+            params![branch_id, branch_id],
+            |row| row.get(0),
+        )?;
+        if taken > 0 {
+            anyhow::bail!("Session '{}' already exists", branch_id);
+        }
+
+        conn.execute_batch("BEGIN TRANSACTION;")?;
+        match copy_branch(&conn, session_id, branch_id, through_message_id) {
+            Ok(copied) => {
+                conn.execute_batch("COMMIT;")?;
+                Ok(copied)
+            }
+            Err(err) => {
+                let _ = conn.execute_batch("ROLLBACK;");
+                Err(err)
+            }
+        }
+    }
+
+    /// Where a session was branched from, if it is a branch
+    pub fn session_branch(&self, session_id: &str) -> Result<Option<BranchRecord>> {
+        self.fault_point("session_branch")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT session_id, parent_session_id, fork_message_id, CAST(created_at AS TEXT)
+             FROM session_branches WHERE session_id = ?",
+        )?;
+        let mut rows = stmt.query(params![session_id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(BranchRecord::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Branches started from a session, oldest first
+    pub fn list_branches(&self, session_id: &str) -> Result<Vec<BranchRecord>> {
+        self.fault_point("list_branches")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT session_id, parent_session_id, fork_message_id, CAST(created_at AS TEXT)
+             FROM session_branches WHERE parent_session_id = ? ORDER BY created_at, session_id",
+        )?;
+        let mut rows = stmt.query(params![session_id])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(BranchRecord::from_row(row)?);
+        }
+        Ok(out)
+    }
+
+    /// Name the conversation up to `message_id`, replacing a checkpoint of the same name
+    pub fn save_conversation_checkpoint(
+        &self,
+        session_id: &str,
+        name: &str,
+        message_id: i64,
+    ) -> Result<()> {
+        self.fault_point("save_conversation_checkpoint")?;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT OR REPLACE INTO conversation_checkpoints (session_id, name, message_id, created_at)
+             VALUES (?, ?, ?, CURRENT_TIMESTAMP)",
+            params![session_id, name, message_id],
+        )?;
+        Ok(())
+    }
+
+    /// A checkpoint of a session by name
+    pub fn conversation_checkpoint(
+        &self,
+        session_id: &str,
+        name: &str,
+    ) -> Result<Option<CheckpointRecord>> {
+        self.fault_point("conversation_checkpoint")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT session_id, name, message_id, CAST(created_at AS TEXT)
+             FROM conversation_checkpoints WHERE session_id = ? AND name = ?",
+        )?;
+        let mut rows = stmt.query(params![session_id, name])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(CheckpointRecord::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Checkpoints of a session, oldest first
+    pub fn list_conversation_checkpoints(&self, session_id: &str) -> Result<Vec<CheckpointRecord>> {
+        self.fault_point("list_conversation_checkpoints")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT session_id, name, message_id, CAST(created_at AS TEXT)
+             FROM conversation_checkpoints WHERE session_id = ? ORDER BY message_id, created_at",
+        )?;
+        let mut rows = stmt.query(params![session_id])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(CheckpointRecord::from_row(row)?);
+        }
+        Ok(out)
+    }
+
+    // ---------- Idempotency Keys ----------
+
+    /// Reserve `key` for a request, or return what is already stored for it
+    ///
+    /// `None` means the caller owns the key and must complete or release it.
+    /// Expired keys are dropped first, so they can be reused.
+    pub fn idempotency_reserve(
+        &self,
+        key: &str,
+        request_hash: &str,
+        ttl: std::time::Duration,
+    ) -> Result<Option<IdempotencyRecord>> {
+        self.fault_point("idempotency_reserve")?;
+        let now = Utc::now().timestamp_millis();
+        let conn = self.conn();
+        conn.execute(
+            "DELETE FROM idempotency_keys WHERE expires_at <= ?",
+            params![now],
+        )?;
+        let mut stmt = conn.prepare(
+            "SELECT key, request_hash, status, content_type, body, expires_at
+             FROM idempotency_keys WHERE key = ?",
+        )?;
+        let mut rows = stmt.query(params![key])?;
+        if let Some(row) = rows.next()? {
+            return Ok(Some(IdempotencyRecord::from_row(row)?));
+        }
+        conn.execute(
+            "INSERT INTO idempotency_keys (key, request_hash, expires_at) VALUES (?, ?, ?)",
+            params![key, request_hash, now + ttl.as_millis() as i64],
+        )?;
+        Ok(None)
+    }
+
+    /// Store the response to replay for a reserved key
+    pub fn idempotency_complete(
+        &self,
+        key: &str,
+        status: u16,
+        content_type: Option<&str>,
+        body: &str,
+    ) -> Result<()> {
+        self.fault_point("idempotency_complete")?;
+        let conn = self.conn();
+        conn.execute(
+            "UPDATE idempotency_keys SET status = ?, content_type = ?, body = ? WHERE key = ?",
+            params![status as i64, content_type, body, key],
+        )?;
+        Ok(())
+    }
+
+    /// Give up a reserved key so a retry runs the request again
+    pub fn idempotency_release(&self, key: &str) -> Result<()> {
+        self.fault_point("idempotency_release")?;
+        let conn = self.conn();
+        conn.execute("DELETE FROM idempotency_keys WHERE key = ?", params![key])?;
+        Ok(())
+    }
+
+    // ---------- Token Audit ----------
+
+    /// Append a scoped token event to the audit log
+    pub fn record_token_event(&self, record: &TokenAuditRecord) -> Result<()> {
+        self.fault_point("record_token_event")?;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO token_audit (token_id, event, actor, session_id, scope, expires_at, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                record.token_id,
+                record.event,
+                record.actor,
+                record.session_id,
+                serde_json::to_string(&record.scope)?,
+                record.expires_at,
+                record.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent `limit` token events, newest first
+    pub fn list_token_events(&self, limit: i64) -> Result<Vec<TokenAuditRecord>> {
+        self.fault_point("list_token_events")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT token_id, event, actor, session_id, scope, expires_at, CAST(created_at AS TEXT)
+             FROM token_audit ORDER BY id DESC LIMIT ?",
+        )?;
+        let mut rows = stmt.query(params![limit])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(TokenAuditRecord::from_row(row)?);
+        }
+        Ok(out)
+    }
+
+    /// IDs of revoked tokens that would otherwise still be valid
+    pub fn revoked_token_ids(&self) -> Result<Vec<String>> {
+        self.fault_point("revoked_token_ids")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT token_id FROM token_audit WHERE event = 'revoked' AND expires_at > ?",
+        )?;
+        let mut rows = stmt.query(params![Utc::now().timestamp()])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(row.get(0)?);
+        }
+        Ok(out)
+    }
+
+    // ---------- Prompt Templates ----------
+
+    /// Save `body` as the newest version of a prompt template
+    ///
+    /// Saving the body the template already has returns its current version
+    /// instead of adding one.
+    pub fn save_prompt_template(&self, name: &str, body: &str) -> Result<PromptTemplateRecord> {
+        self.fault_point("save_prompt_template")?;
+        let latest = self.prompt_template(name, None)?;
+        if let Some(latest) = latest.as_ref().filter(|latest| latest.body == body) {
+            return Ok(latest.clone());
+        }
+        let record = PromptTemplateRecord {
+            name: name.to_string(),
+            version: latest.map(|latest| latest.version + 1).unwrap_or(1),
+            body: body.to_string(),
+            created_at: Utc::now(),
+        };
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO prompt_templates (name, version, body, created_at) VALUES (?, ?, ?, ?)",
+            params![
+                record.name,
+                record.version as i64,
+                record.body,
+                record.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(record)
+    }
+
+    /// A version of a saved prompt template, or its newest with `None`
+    pub fn prompt_template(
+        &self,
+        name: &str,
+        version: Option<u32>,
+    ) -> Result<Option<PromptTemplateRecord>> {
+        self.fault_point("prompt_template")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT name, version, body, CAST(created_at AS TEXT) FROM prompt_templates
+             WHERE name = ? AND version = COALESCE(?, (SELECT MAX(version) FROM prompt_templates WHERE name = ?))",
+        )?;
+        let mut rows = stmt.query(params![name, version.map(i64::from), name])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(PromptTemplateRecord::from_row(row)?)),
+            None => Ok(None),
+        }
     }
 
-    /// List known session IDs ordered by most recent activity
-    pub fn list_sessions(&self) -> Result<Vec<String>> {
-        let conn = self.conn();
+    /// Every version of a saved prompt template, newest first
+    pub fn prompt_template_versions(&self, name: &str) -> Result<Vec<PromptTemplateRecord>> {
+        self.fault_point("prompt_template_versions")?;
+        let conn = self.read_conn();
         let mut stmt = conn.prepare(
-            "SELECT session_id, MAX(created_at) as last FROM messages GROUP BY session_id ORDER BY last DESC"
+            "SELECT name, version, body, CAST(created_at AS TEXT) FROM prompt_templates
+             WHERE name = ? ORDER BY version DESC",
         )?;
-        let mut rows = stmt.query([])?;
+        let mut rows = stmt.query(params![name])?;
         let mut out = Vec::new();
         while let Some(row) = rows.next()? {
-            let sid: String = row.get(0)?;
-            out.push(sid);
+            out.push(PromptTemplateRecord::from_row(row)?);
         }
         Ok(out)
     }
 
-    // ---------- Tool Log ----------
+    /// The newest version of every saved prompt template, by name
+    pub fn list_prompt_templates(&self) -> Result<Vec<PromptTemplateRecord>> {
+        self.fault_point("list_prompt_templates")?;
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT name, version, body, CAST(created_at AS TEXT) FROM prompt_templates t
+             WHERE version = (SELECT MAX(version) FROM prompt_templates WHERE name = t.name)
+             ORDER BY name",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            out.push(PromptTemplateRecord::from_row(row)?);
+        }
+        Ok(out)
+    }
+}
 
-    pub fn log_tool(
-        &self,
-        session_id: &str,
-        agent_name: &str,
-        run_id: &str,
-        tool_name: &str,
-        arguments: &JsonValue,
-        result: &JsonValue,
-        success: bool,
-        error: Option<&str>,
-    ) -> Result<i64> {
-        let conn = self.conn();
-        let mut stmt = conn.prepare("INSERT INTO tool_log (session_id, agent, run_id, tool_name, arguments, result, success, error) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id")?;
-        let id: i64 = stmt.query_row(
-            params![
-                session_id,
-                agent_name,
-                run_id,
-                tool_name,
-                arguments.to_string(),
-                result.to_string(),
-                success,
-                error.unwrap_or("")
-            ],
+/// Copy a session's messages through `through` into `branch_id`, then the
+/// pins, checkpoints and context summary that refer to them
+fn copy_branch(
+    conn: &Connection,
+    session_id: &str,
+    branch_id: &str,
+    through: i64,
+) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "SELECT id, role, content, CAST(created_at AS TEXT), author FROM messages
+         WHERE session_id = ? AND id <= ? ORDER BY id ASC",
+    )?;
+    let mut rows = stmt.query(params![session_id, through])?;
+    let mut messages = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let role: String = row.get(1)?;
+        let content: String = row.get(2)?;
+        let created_at: String = row.get(3)?;
+        let author: Option<String> = row.get(4)?;
+        messages.push((id, role, content, created_at, author));
+    }
+
+    // Parent message id -> id of its copy
+    let mut copies = std::collections::HashMap::new();
+    let mut insert = conn.prepare(
+        "INSERT INTO messages (session_id, role, content, created_at, author)
+         VALUES (?, ?, ?, CAST(? AS TIMESTAMP), ?) RETURNING id",
+    )?;
+    for (id, role, content, created_at, author) in &messages {
+        let copy: i64 = insert.query_row(
+            params![branch_id, role, content, created_at, author],
             |row| row.get(0),
         )?;
-        Ok(id)
+        copies.insert(*id, copy);
     }
 
-    // ---------- Policy Cache ----------
+    // Results are read out before writing on the same connection
+    let mut stmt = conn.prepare("SELECT message_id FROM pinned_messages WHERE session_id = ?")?;
+    let mut rows = stmt.query(params![session_id])?;
+    let mut pinned = Vec::new();
+    while let Some(row) = rows.next()? {
+        let message_id: i64 = row.get(0)?;
+        pinned.extend(copies.get(&message_id).copied());
+    }
+    for copy in pinned {
+        conn.execute(
+            "INSERT INTO pinned_messages (session_id, message_id) VALUES (?, ?)",
+            params![branch_id, copy],
+        )?;
+    }
 
-    pub fn policy_upsert(&self, key: &str, value: &JsonValue) -> Result<()> {
-        let conn = self.conn();
-        // DuckDB upsert workaround: delete then insert atomically within a transaction.
-        conn.execute_batch("BEGIN TRANSACTION;")?;
-        {
-            let mut del = conn.prepare("DELETE FROM policy_cache WHERE key = ?")?;
-            let _ = del.execute(params![key])?;
-            let mut ins = conn.prepare("INSERT INTO policy_cache (key, value, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)")?;
-            let _ = ins.execute(params![key, value.to_string()])?;
-        }
-        conn.execute_batch("COMMIT;")?;
-        Ok(())
+    let mut stmt = conn.prepare(
+        "SELECT name, message_id FROM conversation_checkpoints WHERE session_id = ? AND message_id <= ?",
+    )?;
+    let mut rows = stmt.query(params![session_id, through])?;
+    let mut checkpoints = Vec::new();
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(0)?;
+        let message_id: i64 = row.get(1)?;
+        // Checkpoints of an empty conversation stay at 0
+        checkpoints.push((name, copies.get(&message_id).copied().unwrap_or(0)));
+    }
+    for (name, copy) in checkpoints {
+        conn.execute(
+            "INSERT INTO conversation_checkpoints (session_id, name, message_id) VALUES (?, ?, ?)",
+            params![branch_id, name, copy],
+        )?;
     }
 
-    pub fn policy_get(&self, key: &str) -> Result<Option<PolicyEntry>> {
-        let conn = self.conn();
-        let mut stmt = conn.prepare("SELECT key, value, CAST(updated_at AS TEXT) as updated_at FROM policy_cache WHERE key = ?")?;
-        let mut rows = stmt.query(params![key])?;
-        if let Some(row) = rows.next()? {
-            let key: String = row.get(0)?;
-            let value_text: String = row.get(1)?;
-            let updated_at: String = row.get(2)?;
-            let updated_at: DateTime<Utc> = updated_at.parse().unwrap_or_else(|_| Utc::now());
-            let value: JsonValue = serde_json::from_str(&value_text).unwrap_or(JsonValue::Null);
-            Ok(Some(PolicyEntry {
-                key,
-                value,
-                updated_at,
-            }))
-        } else {
-            Ok(None)
-        }
+    let mut stmt = conn.prepare(
+        "SELECT summary, through_message_id FROM context_summaries WHERE session_id = ?",
+    )?;
+    let mut rows = stmt.query(params![session_id])?;
+    let mut summary = None;
+    if let Some(row) = rows.next()? {
+        let text: String = row.get(0)?;
+        let summarized: i64 = row.get(1)?;
+        // A summary reaching past the fork also covers messages the branch does not have
+        summary = copies.get(&summarized).map(|copy| (text, *copy));
+    }
+    if let Some((text, copy)) = summary {
+        conn.execute(
+            "INSERT INTO context_summaries (session_id, summary, through_message_id) VALUES (?, ?, ?)",
+            params![branch_id, text, copy],
+        )?;
     }
+
+    conn.execute(
+        "INSERT INTO session_branches (session_id, parent_session_id, fork_message_id) VALUES (?, ?, ?)",
+        params![branch_id, session_id, through],
+    )?;
+    Ok(messages.len())
+}
+
+fn message_from_row(row: &duckdb::Row) -> Result<Message> {
+    let id: i64 = row.get(0)?;
+    let session_id: String = row.get(1)?;
+    let role: String = row.get(2)?;
+    let content: String = row.get(3)?;
+    let created_at: String = row.get(4)?; // DuckDB returns TIMESTAMP as string
+    let author: Option<String> = row.get(5)?;
+    Ok(Message {
+        id,
+        session_id,
+        role: MessageRole::from_str(&role),
+        content,
+        created_at: created_at.parse().unwrap_or_else(|_| Utc::now()),
+        author,
+    })
 }
 
 fn generate_instance_id() -> String {
@@ -325,6 +1284,475 @@ mod tests {
         let result = expand_tilde(input).expect("path expansion succeeds");
         assert_eq!(result, input);
     }
+
+    #[test]
+    fn message_authors_are_stored_with_user_messages() {
+        let db = Persistence::in_memory().unwrap();
+        let id = db
+            .insert_message_by("shared", MessageRole::User, "hi", Some("alice"))
+            .unwrap();
+        db.insert_message("shared", MessageRole::Assistant, "hello")
+            .unwrap();
+
+        let messages = db.list_messages("shared", 10).unwrap();
+        assert_eq!(messages[0].author.as_deref(), Some("alice"));
+        assert_eq!(messages[1].author, None);
+        assert_eq!(
+            db.get_message(id).unwrap().unwrap().author.as_deref(),
+            Some("alice")
+        );
+    }
+
+    #[test]
+    fn in_memory_databases_are_isolated() {
+        let config = DatabaseConfig {
+            path: "never-created.duckdb".into(),
+            persistence: PersistenceMode::Memory,
+            encrypted_namespaces: Vec::new(),
+            read_connections: 4,
+            busy_timeout_ms: 5000,
+            checkpoint_threshold: None,
+        };
+        let first = Persistence::from_config(&config).unwrap();
+        let second = Persistence::in_memory().unwrap();
+
+        first
+            .insert_message("session", MessageRole::User, "hello")
+            .unwrap();
+        let clone = first.clone();
+
+        assert_eq!(clone.list_messages("session", 10).unwrap().len(), 1);
+        assert!(second.list_messages("session", 10).unwrap().is_empty());
+        assert!(!Path::new("never-created.duckdb").exists());
+        first.checkpoint().unwrap();
+    }
+
+    #[test]
+    fn concurrent_agent_sync_and_api_access_does_not_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DatabaseConfig {
+            path: dir.path().join("stress.duckdb"),
+            persistence: PersistenceMode::File,
+            encrypted_namespaces: Vec::new(),
+            read_connections: 4,
+            busy_timeout_ms: 5000,
+            checkpoint_threshold: Some("1MB".to_string()),
+        };
+        let persistence = Persistence::from_config(&config).unwrap();
+        const ROUNDS: usize = 50;
+
+        let mut workers = Vec::new();
+        for agent in 0..3 {
+            let db = persistence.clone();
+            workers.push(std::thread::spawn(move || -> Result<()> {
+                let session = format!("session-{}", agent);
+                for round in 0..ROUNDS {
+                    db.insert_message(&session, MessageRole::User, &format!("message {}", round))?;
+                    db.log_tool(
+                        &session,
+                        "agent",
+                        "run",
+                        "echo",
+                        &serde_json::json!({ "round": round }),
+                        &serde_json::json!("ok"),
+                        true,
+                        None,
+                    )?;
+                }
+                Ok(())
+            }));
+        }
+        let db = persistence.clone();
+        workers.push(std::thread::spawn(move || -> Result<()> {
+            for round in 0..ROUNDS {
+                let node = db.insert_graph_node(
+                    "session-0",
+                    spec_ai_knowledge_graph::NodeType::Entity,
+                    &format!("node {}", round),
+                    &serde_json::json!({}),
+                    None,
+                )?;
+                db.update_graph_node(node, &serde_json::json!({ "round": round }))?;
+                db.graph_sync_state_update("peer", "session-0", "default", "{}")?;
+            }
+            Ok(())
+        }));
+        for _ in 0..4 {
+            let db = persistence.clone();
+            workers.push(std::thread::spawn(move || -> Result<()> {
+                for _ in 0..ROUNDS {
+                    db.list_sessions()?;
+                    db.list_messages("session-1", 20)?;
+                    db.list_tool_calls("session-2", 20)?;
+                    db.list_graph_nodes("session-0", None, Some(20))?;
+                }
+                Ok(())
+            }));
+        }
+        for worker in workers {
+            worker.join().unwrap().unwrap();
+        }
+
+        assert_eq!(
+            persistence.list_messages("session-1", 100).unwrap().len(),
+            ROUNDS
+        );
+        assert_eq!(
+            persistence.count_graph_nodes("session-0").unwrap(),
+            ROUNDS as i64
+        );
+        persistence.checkpoint().unwrap();
+    }
+
+    #[test]
+    fn heartbeats_round_trip_and_replace() {
+        let persistence = Persistence::in_memory().unwrap();
+        let started_at = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+        let mut heartbeat = HeartbeatRecord {
+            instance_id: "host-1".to_string(),
+            hostname: "host".to_string(),
+            pid: 42,
+            role: "server".to_string(),
+            status: "running".to_string(),
+            active_sessions: 2,
+            queue_depth: 1,
+            last_error: None,
+            last_error_code: None,
+            last_error_at: None,
+            started_at,
+            updated_at: started_at + chrono::Duration::seconds(30),
+        };
+        persistence.record_heartbeat(&heartbeat).unwrap();
+        assert_eq!(
+            persistence.list_heartbeats().unwrap(),
+            vec![heartbeat.clone()]
+        );
+
+        heartbeat.status = "stopped".to_string();
+        heartbeat.last_error = Some("rate limited".to_string());
+        heartbeat.last_error_code = Some("rate_limited".to_string());
+        heartbeat.last_error_at = Some(started_at + chrono::Duration::seconds(45));
+        heartbeat.updated_at = started_at + chrono::Duration::seconds(60);
+        persistence.record_heartbeat(&heartbeat).unwrap();
+        assert_eq!(persistence.list_heartbeats().unwrap(), vec![heartbeat]);
+    }
+
+    #[test]
+    fn cached_responses_expire() {
+        let persistence = Persistence::in_memory().unwrap();
+        let hour = std::time::Duration::from_secs(3600);
+        persistence
+            .response_cache_put("fresh", "gpt", "{\"content\":\"hi\"}", hour)
+            .unwrap();
+        persistence
+            .response_cache_put("stale", "gpt", "{}", std::time::Duration::ZERO)
+            .unwrap();
+
+        assert_eq!(
+            persistence.response_cache_get("fresh").unwrap().as_deref(),
+            Some("{\"content\":\"hi\"}")
+        );
+        assert!(persistence.response_cache_get("stale").unwrap().is_none());
+        assert!(persistence.response_cache_get("missing").unwrap().is_none());
+
+        assert_eq!(persistence.response_cache_prune(false).unwrap(), 1);
+        persistence
+            .response_cache_put("fresh", "gpt", "{}", hour)
+            .unwrap();
+        assert_eq!(
+            persistence.response_cache_get("fresh").unwrap().as_deref(),
+            Some("{}")
+        );
+        assert_eq!(persistence.response_cache_prune(true).unwrap(), 1);
+    }
+
+    #[test]
+    fn cached_web_pages_expire() {
+        let persistence = Persistence::in_memory().unwrap();
+        let hour = std::time::Duration::from_secs(3600);
+        let url = "https://docs.rs/serde";
+        persistence.web_cache_put(url, "# serde", hour).unwrap();
+        persistence
+            .web_cache_put("https://example.com/", "old", std::time::Duration::ZERO)
+            .unwrap();
+
+        assert_eq!(
+            persistence.web_cache_get(url).unwrap().as_deref(),
+            Some("# serde")
+        );
+        assert!(persistence
+            .web_cache_get("https://example.com/")
+            .unwrap()
+            .is_none());
+
+        assert_eq!(persistence.web_cache_prune(false).unwrap(), 1);
+        persistence.web_cache_put(url, "# serde 1.0", hour).unwrap();
+        assert_eq!(
+            persistence.web_cache_get(url).unwrap().as_deref(),
+            Some("# serde 1.0")
+        );
+        assert_eq!(persistence.web_cache_prune(true).unwrap(), 1);
+    }
+
+    #[test]
+    fn context_summaries_and_pins_are_per_session() {
+        let persistence = Persistence::in_memory().unwrap();
+        let first = persistence
+            .insert_message("s1", MessageRole::User, "remember the code word")
+            .unwrap();
+        let second = persistence
+            .insert_message("s1", MessageRole::Assistant, "noted")
+            .unwrap();
+        persistence
+            .insert_message("s2", MessageRole::User, "elsewhere")
+            .unwrap();
+
+        assert!(persistence.context_summary("s1").unwrap().is_none());
+        persistence
+            .save_context_summary("s1", "User shared a code word.", first)
+            .unwrap();
+        let summary = persistence.context_summary("s1").unwrap().unwrap();
+        assert_eq!(summary.summary, "User shared a code word.");
+        assert_eq!(summary.through_message_id, first);
+
+        let after: Vec<i64> = persistence
+            .list_messages_after("s1", summary.through_message_id)
+            .unwrap()
+            .iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(after, vec![second]);
+
+        persistence.pin_message("s1", first).unwrap();
+        persistence.pin_message("s1", first).unwrap();
+        let pinned = persistence.list_pinned_messages("s1").unwrap();
+        assert_eq!(pinned.len(), 1);
+        assert_eq!(pinned[0].content, "remember the code word");
+        assert!(persistence.list_pinned_messages("s2").unwrap().is_empty());
+
+        assert!(persistence.unpin_message("s1", first).unwrap());
+        assert!(!persistence.unpin_message("s1", first).unwrap());
+        assert!(persistence.list_pinned_messages("s1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn forked_sessions_copy_history_up_to_the_fork() {
+        let persistence = Persistence::in_memory().unwrap();
+        let question = persistence
+            .insert_message("main", MessageRole::User, "what is 2 + 2?")
+            .unwrap();
+        let answer = persistence
+            .insert_message("main", MessageRole::Assistant, "4")
+            .unwrap();
+        persistence
+            .insert_message("main", MessageRole::User, "and 3 + 3?")
+            .unwrap();
+        persistence.pin_message("main", question).unwrap();
+        persistence
+            .save_context_summary("main", "User asked for a sum.", question)
+            .unwrap();
+        persistence
+            .save_conversation_checkpoint("main", "answered", answer)
+            .unwrap();
+
+        assert_eq!(
+            persistence.fork_session("main", "main.1", answer).unwrap(),
+            2
+        );
+        let copied = persistence.list_messages("main.1", 10).unwrap();
+        let contents: Vec<_> = copied.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["what is 2 + 2?", "4"]);
+        assert!(copied.iter().all(|m| m.id > answer));
+        assert_eq!(persistence.list_messages("main", 10).unwrap().len(), 3);
+
+        let pinned = persistence.list_pinned_messages("main.1").unwrap();
+        assert_eq!(pinned[0].id, copied[0].id);
+        let summary = persistence.context_summary("main.1").unwrap().unwrap();
+        assert_eq!(summary.through_message_id, copied[0].id);
+        let checkpoint = persistence
+            .conversation_checkpoint("main.1", "answered")
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoint.message_id, copied[1].id);
+
+        let branch = persistence.session_branch("main.1").unwrap().unwrap();
+        assert_eq!(
+            (branch.parent_session_id.as_str(), branch.fork_message_id),
+            ("main", answer)
+        );
+        assert!(persistence.session_branch("main").unwrap().is_none());
+        assert_eq!(persistence.list_branches("main").unwrap(), vec![branch]);
+
+        // Branch names are never reused, even for an empty branch
+        assert!(persistence.fork_session("main", "main.1", answer).is_err());
+        assert_eq!(persistence.fork_session("main", "main.2", 0).unwrap(), 0);
+        assert!(persistence.fork_session("main", "main.2", answer).is_err());
+    }
+
+    #[test]
+    fn idempotency_keys_are_reserved_once_until_they_expire() {
+        let persistence = Persistence::in_memory().unwrap();
+        let hour = std::time::Duration::from_secs(3600);
+
+        assert!(persistence
+            .idempotency_reserve("k1", "hash-a", hour)
+            .unwrap()
+            .is_none());
+        let running = persistence
+            .idempotency_reserve("k1", "hash-a", hour)
+            .unwrap()
+            .unwrap();
+        assert_eq!(running.request_hash, "hash-a");
+        assert_eq!(running.status, None);
+
+        persistence
+            .idempotency_complete("k1", 200, Some("application/json"), "{\"ok\":true}")
+            .unwrap();
+        let done = persistence
+            .idempotency_reserve("k1", "hash-b", hour)
+            .unwrap()
+            .unwrap();
+        assert_eq!(done.status, Some(200));
+        assert_eq!(done.body.as_deref(), Some("{\"ok\":true}"));
+
+        persistence.idempotency_release("k1").unwrap();
+        assert!(persistence
+            .idempotency_reserve("k1", "hash-b", std::time::Duration::ZERO)
+            .unwrap()
+            .is_none());
+        // Expired keys can be taken again
+        assert!(persistence
+            .idempotency_reserve("k1", "hash-c", hour)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn token_events_are_audited_and_revocations_expire() {
+        let persistence = Persistence::in_memory().unwrap();
+        let now = Utc::now();
+        let event = |token_id: &str, event: &str, expires_at: i64| TokenAuditRecord {
+            token_id: token_id.to_string(),
+            event: event.to_string(),
+            actor: "coder".to_string(),
+            session_id: Some("s1".to_string()),
+            scope: vec!["GET /graph/*".to_string()],
+            expires_at,
+            created_at: now,
+        };
+
+        let live = now.timestamp() + 600;
+        let expired = now.timestamp() - 1;
+        persistence
+            .record_token_event(&event("t1", "minted", live))
+            .unwrap();
+        persistence
+            .record_token_event(&event("t1", "revoked", live))
+            .unwrap();
+        persistence
+            .record_token_event(&event("t2", "revoked", expired))
+            .unwrap();
+
+        let events = persistence.list_token_events(10).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2].token_id, "t1");
+        assert_eq!(events[2].event, "minted");
+        assert_eq!(events[2].session_id.as_deref(), Some("s1"));
+        assert_eq!(events[2].scope, vec!["GET /graph/*"]);
+        assert_eq!(events[2].expires_at, live);
+        assert_eq!(events[0].token_id, "t2");
+        assert_eq!(persistence.revoked_token_ids().unwrap(), vec!["t1"]);
+    }
+
+    #[test]
+    fn prompt_templates_keep_every_version() {
+        let persistence = Persistence::in_memory().unwrap();
+        assert!(persistence
+            .prompt_template("coder", None)
+            .unwrap()
+            .is_none());
+
+        let first = persistence
+            .save_prompt_template("coder", "You write {{language}}.")
+            .unwrap();
+        assert_eq!(first.version, 1);
+        // Saving the same body again does not add a version
+        let same = persistence
+            .save_prompt_template("coder", "You write {{language}}.")
+            .unwrap();
+        assert_eq!(same.version, 1);
+        let second = persistence
+            .save_prompt_template("coder", "You write careful {{language}}.")
+            .unwrap();
+        assert_eq!(second.version, 2);
+        persistence
+            .save_prompt_template("reviewer", "Review.")
+            .unwrap();
+
+        let latest = persistence.prompt_template("coder", None).unwrap().unwrap();
+        assert_eq!(latest.body, "You write careful {{language}}.");
+        let pinned = persistence
+            .prompt_template("coder", Some(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(pinned.body, "You write {{language}}.");
+        assert!(persistence
+            .prompt_template("coder", Some(3))
+            .unwrap()
+            .is_none());
+
+        let versions = persistence.prompt_template_versions("coder").unwrap();
+        assert_eq!(
+            versions.iter().map(|r| r.version).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        let names: Vec<(String, u32)> = persistence
+            .list_prompt_templates()
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.name, r.version))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("coder".to_string(), 2), ("reviewer".to_string(), 1)]
+        );
+    }
+
+    #[cfg(feature = "chaos")]
+    #[test]
+    fn injected_faults_fail_operations() {
+        use crate::chaos::{FaultInjector, FaultKind, FaultRule, FaultScenario};
+
+        let faults = Arc::new(FaultInjector::new(FaultScenario {
+            seed: 0,
+            rules: vec![FaultRule {
+                target: FaultTarget::Persistence,
+                operation: Some("insert_message".to_string()),
+                probability: 1.0,
+                latency_ms: 0,
+                fault: Some(FaultKind::Error {
+                    message: "database is locked".to_string(),
+                }),
+                skip: 0,
+                limit: Some(1),
+            }],
+        }));
+        let persistence = Persistence::in_memory()
+            .unwrap()
+            .with_fault_injector(faults);
+
+        let err = persistence
+            .insert_message("session", MessageRole::User, "hello")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "injected persistence fault in insert_message: database is locked"
+        );
+        persistence
+            .insert_message("session", MessageRole::User, "hello")
+            .unwrap();
+        assert_eq!(persistence.list_messages("session", 10).unwrap().len(), 1);
+    }
 }
 
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
@@ -431,6 +1859,11 @@ impl Persistence {
         self.graph_store.delete_graph_node(node_id)
     }
 
+    /// Rewrite the graph properties of `session_id` with its current key
+    pub fn reencrypt_graph_namespace(&self, session_id: &str) -> Result<usize> {
+        self.graph_store.reencrypt_namespace(session_id)
+    }
+
     // ---------- Graph Edge Operations ----------
 
     pub fn insert_graph_edge(
@@ -537,7 +1970,7 @@ impl Persistence {
         session_id: &str,
         limit: Option<i64>,
     ) -> Result<Vec<(i64, i64, String, DateTime<Utc>)>> {
-        let conn = self.conn();
+        let conn = self.read_conn();
         let query = if let Some(lim) = limit {
             format!(
                 "SELECT id, chunk_id, text, CAST(timestamp AS TEXT) FROM transcriptions WHERE session_id = ? ORDER BY chunk_id ASC LIMIT {}",
@@ -582,7 +2015,7 @@ impl Persistence {
     }
 
     pub fn get_transcription_by_embedding(&self, embedding_id: i64) -> Result<Option<String>> {
-        let conn = self.conn();
+        let conn = self.read_conn();
         let mut stmt =
             conn.prepare("SELECT text FROM transcriptions WHERE embedding_id = ? LIMIT 1")?;
         let result: Result<String, _> = stmt.query_row(params![embedding_id], |row| row.get(0));
@@ -634,7 +2067,7 @@ impl Persistence {
         session_id: &str,
         path: &str,
     ) -> Result<Option<TokenizedFileRecord>> {
-        let conn = self.conn();
+        let conn = self.read_conn();
         let mut stmt = conn.prepare("SELECT id, session_id, path, file_hash, raw_tokens, cleaned_tokens, bytes_captured, truncated, embedding_id, CAST(updated_at AS TEXT) FROM tokenized_files WHERE session_id = ? AND path = ? LIMIT 1")?;
         let mut rows = stmt.query(params![session_id, path])?;
         if let Some(row) = rows.next()? {
@@ -646,7 +2079,7 @@ impl Persistence {
     }
 
     pub fn list_tokenized_files(&self, session_id: &str) -> Result<Vec<TokenizedFileRecord>> {
-        let conn = self.conn();
+        let conn = self.read_conn();
         let mut stmt = conn.prepare("SELECT id, session_id, path, file_hash, raw_tokens, cleaned_tokens, bytes_captured, truncated, embedding_id, CAST(updated_at AS TEXT) FROM tokenized_files WHERE session_id = ? ORDER BY path")?;
         let mut rows = stmt.query(params![session_id])?;
         let mut out = Vec::new();
@@ -668,6 +2101,7 @@ impl Persistence {
         payload: &JsonValue,
         status: &str,
     ) -> Result<i64> {
+        self.fault_point("mesh_message_store")?;
         let conn = self.conn();
         let payload_json = serde_json::to_string(payload)?;
         conn.execute(
@@ -681,7 +2115,7 @@ impl Persistence {
 
     /// Check if a message with this ID already exists (for duplicate detection)
     pub fn mesh_message_exists(&self, message_id: &str) -> Result<bool> {
-        let conn = self.conn();
+        let conn = self.read_conn();
         let count: i64 = conn.query_row(
             "SELECT COUNT(*) FROM mesh_messages WHERE message_id = ?",
             params![message_id],
@@ -692,6 +2126,7 @@ impl Persistence {
 
     /// Update message status (e.g., delivered, failed)
     pub fn mesh_message_update_status(&self, message_id: i64, status: &str) -> Result<()> {
+        self.fault_point("mesh_message_update_status")?;
         let conn = self.conn();
         conn.execute(
             "UPDATE mesh_messages SET status = ?, delivered_at = CURRENT_TIMESTAMP WHERE id = ?",
@@ -700,17 +2135,19 @@ impl Persistence {
         Ok(())
     }
 
-    /// Get pending messages for a target instance
+    /// Get pending messages for a target instance, in the order they were
+    /// stored
     pub fn mesh_message_get_pending(
         &self,
         target_instance: &str,
     ) -> Result<Vec<MeshMessageRecord>> {
-        let conn = self.conn();
+        self.fault_point("mesh_message_get_pending")?;
+        let conn = self.read_conn();
         let mut stmt = conn.prepare(
             "SELECT id, source_instance, target_instance, message_type, payload, status, CAST(created_at AS TEXT), CAST(delivered_at AS TEXT)
              FROM mesh_messages
              WHERE (target_instance = ? OR target_instance IS NULL) AND status = 'pending'
-             ORDER BY created_at",
+             ORDER BY id",
         )?;
         let mut rows = stmt.query(params![target_instance])?;
         let mut out = Vec::new();
@@ -726,20 +2163,20 @@ impl Persistence {
         instance_id: Option<&str>,
         limit: usize,
     ) -> Result<Vec<MeshMessageRecord>> {
-        let conn = self.conn();
+        let conn = self.read_conn();
         let query = if instance_id.is_some() {
             format!(
                 "SELECT id, source_instance, target_instance, message_type, payload, status, CAST(created_at AS TEXT), CAST(delivered_at AS TEXT)
                  FROM mesh_messages
                  WHERE source_instance = ? OR target_instance = ?
-                 ORDER BY created_at DESC LIMIT {}",
+                 ORDER BY id DESC LIMIT {}",
                 limit
             )
         } else {
             format!(
                 "SELECT id, source_instance, target_instance, message_type, payload, status, CAST(created_at AS TEXT), CAST(delivered_at AS TEXT)
                  FROM mesh_messages
-                 ORDER BY created_at DESC LIMIT {}",
+                 ORDER BY id DESC LIMIT {}",
                 limit
             )
         };
@@ -771,6 +2208,7 @@ impl Persistence {
         vector_clock: &str,
         data: Option<&str>,
     ) -> Result<i64> {
+        self.fault_point("graph_changelog_append")?;
         self.graph_store.graph_changelog_append(
             session_id,
             instance_id,
@@ -788,6 +2226,7 @@ impl Persistence {
         session_id: &str,
         since_timestamp: &str,
     ) -> Result<Vec<ChangelogEntry>> {
+        self.fault_point("graph_changelog_get_since")?;
         self.graph_store
             .graph_changelog_get_since(session_id, since_timestamp)
             .map(|entries| {
@@ -871,6 +2310,7 @@ impl Persistence {
         graph_name: &str,
         vector_clock: &str,
     ) -> Result<()> {
+        self.fault_point("graph_sync_state_update")?;
         self.graph_store
             .graph_sync_state_update(instance_id, session_id, graph_name, vector_clock)
     }
@@ -1059,6 +2499,7 @@ impl Persistence {
         last_modified_by: &str,
         sync_enabled: bool,
     ) -> Result<()> {
+        self.fault_point("graph_update_node_sync_metadata")?;
         self.graph_store.graph_update_node_sync_metadata(
             node_id,
             vector_clock,
@@ -1075,6 +2516,7 @@ impl Persistence {
         last_modified_by: &str,
         sync_enabled: bool,
     ) -> Result<()> {
+        self.fault_point("graph_update_edge_sync_metadata")?;
         self.graph_store.graph_update_edge_sync_metadata(
             edge_id,
             vector_clock,
@@ -1148,6 +2590,304 @@ impl TokenizedFileRecord {
     }
 }
 
+/// Token usage of one provider and model within a session
+#[derive(Debug, Clone)]
+pub struct ModelUsageRecord {
+    pub provider: String,
+    pub model: String,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct UsageSummaryRecord {
+    pub day: NaiveDate,
+    pub provider: String,
+    pub model: String,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl UsageSummaryRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let day: String = row.get(0)?;
+        let provider: String = row.get(1)?;
+        let model: String = row.get(2)?;
+        let calls: i64 = row.get(3)?;
+        let prompt_tokens: i64 = row.get(4)?;
+        let completion_tokens: i64 = row.get(5)?;
+        let total_tokens: i64 = row.get(6)?;
+
+        Ok(Self {
+            day: NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                .with_context(|| format!("parsing usage day '{}'", day))?,
+            provider,
+            model,
+            calls: calls.max(0) as u64,
+            prompt_tokens: prompt_tokens.max(0) as u64,
+            completion_tokens: completion_tokens.max(0) as u64,
+            total_tokens: total_tokens.max(0) as u64,
+        })
+    }
+}
+
+/// A chat session with its metadata and activity counts
+#[derive(Debug, Clone)]
+pub struct SessionRecord {
+    pub session_id: String,
+    pub agent: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    /// First user message, if any
+    pub title: Option<String>,
+    pub message_count: u64,
+    pub tool_call_count: u64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SessionRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let session_id: String = row.get(0)?;
+        let agent: Option<String> = row.get(1)?;
+        let provider: Option<String> = row.get(2)?;
+        let model: Option<String> = row.get(3)?;
+        let title: Option<String> = row.get(4)?;
+        let message_count: i64 = row.get(5)?;
+        let tool_call_count: i64 = row.get(6)?;
+        let created_at: String = row.get(7)?;
+        let updated_at: String = row.get(8)?;
+
+        Ok(Self {
+            session_id,
+            agent,
+            provider,
+            model,
+            title,
+            message_count: message_count.max(0) as u64,
+            tool_call_count: tool_call_count.max(0) as u64,
+            created_at: created_at.parse().unwrap_or_else(|_| Utc::now()),
+            updated_at: updated_at.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+/// Running summary of a session's older turns, written by context compaction
+#[derive(Debug, Clone)]
+pub struct ContextSummaryRecord {
+    pub session_id: String,
+    pub summary: String,
+    /// Last message folded into the summary; later messages are not covered
+    pub through_message_id: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ContextSummaryRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let session_id: String = row.get(0)?;
+        let summary: String = row.get(1)?;
+        let through_message_id: i64 = row.get(2)?;
+        let updated_at: String = row.get(3)?;
+
+        Ok(Self {
+            session_id,
+            summary,
+            through_message_id,
+            updated_at: updated_at.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+/// An idempotency key and, once its request finished, the response to replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyRecord {
+    pub key: String,
+    /// Hash of the request first sent with the key
+    pub request_hash: String,
+    /// Status of the stored response; `None` while the request is running
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    pub body: Option<String>,
+    /// Epoch milliseconds after which the key may be reused
+    pub expires_at: i64,
+}
+
+impl IdempotencyRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let status: Option<i64> = row.get(2)?;
+        Ok(Self {
+            key: row.get(0)?,
+            request_hash: row.get(1)?,
+            status: status.map(|status| status as u16),
+            content_type: row.get(3)?,
+            body: row.get(4)?,
+            expires_at: row.get(5)?,
+        })
+    }
+}
+
+/// Entry of the scoped token audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenAuditRecord {
+    pub token_id: String,
+    /// "minted" or "revoked"
+    pub event: String,
+    /// Agent that minted the token, or who revoked it
+    pub actor: String,
+    pub session_id: Option<String>,
+    /// Endpoint patterns the token may call
+    pub scope: Vec<String>,
+    /// When the token expires, in epoch seconds
+    pub expires_at: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TokenAuditRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let scope: String = row.get(4)?;
+        let created_at: String = row.get(6)?;
+        Ok(Self {
+            token_id: row.get(0)?,
+            event: row.get(1)?,
+            actor: row.get(2)?,
+            session_id: row.get(3)?,
+            scope: serde_json::from_str(&scope).unwrap_or_default(),
+            expires_at: row.get(5)?,
+            created_at: created_at.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+/// Where a branch split off from the conversation it was copied from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchRecord {
+    pub session_id: String,
+    pub parent_session_id: String,
+    /// Messages of the parent with ids up to this one were copied; 0 for none
+    pub fork_message_id: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl BranchRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let created_at: String = row.get(3)?;
+        Ok(Self {
+            session_id: row.get(0)?,
+            parent_session_id: row.get(1)?,
+            fork_message_id: row.get(2)?,
+            created_at: created_at.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+/// A named point in a conversation that can be branched from later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointRecord {
+    pub session_id: String,
+    pub name: String,
+    /// Last message covered; 0 for an empty conversation
+    pub message_id: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CheckpointRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let created_at: String = row.get(3)?;
+        Ok(Self {
+            session_id: row.get(0)?,
+            name: row.get(1)?,
+            message_id: row.get(2)?,
+            created_at: created_at.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+/// A saved version of a prompt template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplateRecord {
+    pub name: String,
+    /// Starts at 1 and grows with every saved change
+    pub version: u32,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PromptTemplateRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let version: i64 = row.get(1)?;
+        let created_at: String = row.get(3)?;
+        Ok(Self {
+            name: row.get(0)?,
+            version: version as u32,
+            body: row.get(2)?,
+            created_at: created_at.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+/// Liveness report of a headless instance, as written by its heartbeat
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeartbeatRecord {
+    pub instance_id: String,
+    pub hostname: String,
+    pub pid: u32,
+    /// How the instance was started, e.g. "server" or "mesh-member"
+    pub role: String,
+    /// "running", or "stopped" after a clean shutdown
+    pub status: String,
+    pub active_sessions: u64,
+    /// Requests accepted but not yet answered
+    pub queue_depth: u64,
+    pub last_error: Option<String>,
+    /// Stable code of the last error (see `crate::error::ErrorCode`)
+    pub last_error_code: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl HeartbeatRecord {
+    fn from_row(row: &duckdb::Row) -> Result<Self> {
+        let instance_id: String = row.get(0)?;
+        let hostname: String = row.get(1)?;
+        let pid: i64 = row.get(2)?;
+        let role: String = row.get(3)?;
+        let status: String = row.get(4)?;
+        let active_sessions: i64 = row.get(5)?;
+        let queue_depth: i64 = row.get(6)?;
+        let last_error: Option<String> = row.get(7)?;
+        let last_error_code: Option<String> = row.get(8)?;
+        let last_error_at: Option<i64> = row.get(9)?;
+        let started_at: i64 = row.get(10)?;
+        let updated_at: i64 = row.get(11)?;
+
+        // Read as epoch milliseconds: staleness checks need the real time, so
+        // falling back to "now" on a parse failure is not an option here
+        let timestamp = |millis: i64| {
+            DateTime::from_timestamp_millis(millis)
+                .with_context(|| format!("heartbeat timestamp out of range: {}", millis))
+        };
+
+        Ok(Self {
+            instance_id,
+            hostname,
+            pid: pid.max(0) as u32,
+            role,
+            status,
+            active_sessions: active_sessions.max(0) as u64,
+            queue_depth: queue_depth.max(0) as u64,
+            last_error,
+            last_error_code,
+            last_error_at: last_error_at.map(timestamp).transpose()?,
+            started_at: timestamp(started_at)?,
+            updated_at: timestamp(updated_at)?,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MeshMessageRecord {
     pub id: i64,
@@ -0,0 +1,178 @@
+//! Connection pool of the persistence layer
+//!
+//! DuckDB lets one process open a database file at a time. Within it, clones
+//! made with `try_clone` share the database, its cache and its WAL. Writes go
+//! through a single connection behind a mutex, the same one the knowledge
+//! graph store uses, so sessions, graph, sync and audit writes never conflict.
+//! Reads take one of a few reader connections instead: they see every
+//! committed write and do not wait for a write in progress.
+
+use crate::config::DatabaseConfig;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest pause between attempts to open a locked database
+const MAX_OPEN_BACKOFF: Duration = Duration::from_millis(250);
+
+/// How a database's connections are opened and tuned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolOptions {
+    /// Reader connections beside the writer; 0 reads through the writer
+    pub read_connections: usize,
+    /// How long opening waits for another process's lock on the file
+    pub busy_timeout: Duration,
+    /// WAL size that triggers an automatic checkpoint, e.g. "64MB"
+    pub checkpoint_threshold: Option<String>,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self::from(&DatabaseConfig::default())
+    }
+}
+
+impl From<&DatabaseConfig> for PoolOptions {
+    fn from(config: &DatabaseConfig) -> Self {
+        Self {
+            read_connections: config.read_connections,
+            busy_timeout: Duration::from_millis(config.busy_timeout_ms),
+            checkpoint_threshold: config.checkpoint_threshold.clone(),
+        }
+    }
+}
+
+/// One writer and a few readers on the same database
+pub struct ConnectionPool {
+    writer: Arc<Mutex<Connection>>,
+    readers: Vec<Mutex<Connection>>,
+    next: AtomicUsize,
+}
+
+impl ConnectionPool {
+    /// Tune `conn` and clone the reader connections from it
+    ///
+    /// `conn` becomes the writer; migrations should already have run on it.
+    pub fn new(conn: Connection, options: &PoolOptions) -> Result<Self> {
+        if let Some(threshold) = &options.checkpoint_threshold {
+            conn.execute_batch(&format!(
+                "SET checkpoint_threshold = '{}';",
+                threshold.replace('\'', "''")
+            ))
+            .context("setting checkpoint_threshold")?;
+        }
+        let readers = (0..options.read_connections)
+            .map(|_| conn.try_clone().map(Mutex::new))
+            .collect::<duckdb::Result<Vec<_>>>()
+            .context("opening reader connections")?;
+        Ok(Self {
+            writer: Arc::new(Mutex::new(conn)),
+            readers,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// The writer, shared with the knowledge graph store
+    pub fn writer(&self) -> &Arc<Mutex<Connection>> {
+        &self.writer
+    }
+
+    /// Exclusive access to the writer
+    pub fn write(&self) -> MutexGuard<'_, Connection> {
+        self.writer
+            .lock()
+            .expect("database connection mutex poisoned")
+    }
+
+    /// A free reader, or the next one in turn when all are busy
+    pub fn read(&self) -> MutexGuard<'_, Connection> {
+        if self.readers.is_empty() {
+            return self.write();
+        }
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for offset in 0..self.readers.len() {
+            match self.readers[(start + offset) % self.readers.len()].try_lock() {
+                Ok(conn) => return conn,
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Poisoned(_)) => panic!("database connection mutex poisoned"),
+            }
+        }
+        self.readers[start % self.readers.len()]
+            .lock()
+            .expect("database connection mutex poisoned")
+    }
+
+    /// Number of reader connections
+    pub fn reader_count(&self) -> usize {
+        self.readers.len()
+    }
+}
+
+/// Open the database file at `path`, retrying until `busy_timeout` while
+/// another process holds its lock
+pub fn open_file(path: &Path, busy_timeout: Duration) -> Result<Connection> {
+    let deadline = Instant::now() + busy_timeout;
+    let mut backoff = Duration::from_millis(10);
+    loop {
+        match Connection::open(path) {
+            Ok(conn) => return Ok(conn),
+            Err(e) if is_lock_error(&e.to_string()) && Instant::now() < deadline => {
+                tracing::debug!("database {} is locked, retrying: {}", path.display(), e);
+                thread::sleep(backoff.min(deadline.saturating_duration_since(Instant::now())));
+                backoff = (backoff * 2).min(MAX_OPEN_BACKOFF);
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("opening DuckDB at {}", path.display()))
+            }
+        }
+    }
+}
+
+/// Whether DuckDB failed because another process holds the file
+fn is_lock_error(message: &str) -> bool {
+    message.contains("Could not set lock") || message.contains("Conflicting lock")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readers_see_committed_writes() {
+        let pool = ConnectionPool::new(
+            Connection::open_in_memory().unwrap(),
+            &PoolOptions {
+                read_connections: 2,
+                ..PoolOptions::default()
+            },
+        )
+        .unwrap();
+        pool.write()
+            .execute_batch("CREATE TABLE t (x INTEGER); INSERT INTO t VALUES (1), (2);")
+            .unwrap();
+
+        let first = pool.read();
+        let second = pool.read();
+        let count = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(count(&first), 2);
+        assert_eq!(count(&second), 2);
+        assert_eq!(pool.reader_count(), 2);
+    }
+
+    #[test]
+    fn lock_errors_are_recognised() {
+        assert!(is_lock_error(
+            "IO Error: Could not set lock on file \"a.duckdb\": Conflicting lock is held"
+        ));
+        assert!(!is_lock_error(
+            "Catalog Error: Table with name t does not exist"
+        ));
+    }
+}
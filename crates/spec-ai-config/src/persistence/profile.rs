@@ -0,0 +1,619 @@
+//! Moving an agent's stored state between databases
+//!
+//! [`Persistence::export_profile_data`] dumps the sessions, knowledge graph
+//! namespaces and learned strategies of an agent as rows of text, and
+//! [`Persistence::import_profile_data`] loads such a dump into another
+//! database. Surrogate ids are assigned anew on import, and the columns that
+//! refer to them are rewritten to match, so a dump can be loaded into a
+//! database that already has data of its own.
+
+use super::Persistence;
+use anyhow::{bail, Context, Result};
+use duckdb::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Which rows of a table belong to a dump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    /// Rows of the exported sessions
+    Session,
+    /// Rows of the exported graph namespaces
+    Namespace,
+    /// Every row
+    All,
+    /// Registry entries of the instances the exported strategies come from
+    StrategyOrigins,
+}
+
+/// How a column referring to another table's id is rewritten on import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    /// The row is left out when the referenced row was not imported
+    Required,
+    /// The column is cleared when the referenced row was not imported
+    Optional,
+    /// A "last row covered" marker: it becomes the new id of the newest
+    /// imported row at or before it, and 0 stays 0
+    UpTo,
+}
+
+struct Table {
+    name: &'static str,
+    scope: Scope,
+    /// Whether the `id` column is a surrogate key assigned anew on import
+    renumbered: bool,
+    /// Column identifying a row across databases; rows already present are
+    /// left alone
+    unique: Option<&'static str>,
+    /// Columns holding ids of other tables, with the table they refer to
+    links: &'static [(&'static str, &'static str, Link)],
+}
+
+/// Tables of a dump in the order they are imported, referenced tables first
+const TABLES: &[Table] = &[
+    Table {
+        name: "chat_sessions",
+        scope: Scope::Session,
+        renumbered: false,
+        unique: None,
+        links: &[],
+    },
+    Table {
+        name: "messages",
+        scope: Scope::Session,
+        renumbered: true,
+        unique: None,
+        links: &[],
+    },
+    Table {
+        name: "memory_vectors",
+        scope: Scope::Session,
+        renumbered: true,
+        unique: None,
+        links: &[("message_id", "messages", Link::Optional)],
+    },
+    Table {
+        name: "pinned_messages",
+        scope: Scope::Session,
+        renumbered: false,
+        unique: None,
+        links: &[("message_id", "messages", Link::Required)],
+    },
+    Table {
+        name: "context_summaries",
+        scope: Scope::Session,
+        renumbered: false,
+        unique: None,
+        links: &[("through_message_id", "messages", Link::UpTo)],
+    },
+    Table {
+        name: "conversation_checkpoints",
+        scope: Scope::Session,
+        renumbered: false,
+        unique: None,
+        links: &[("message_id", "messages", Link::UpTo)],
+    },
+    Table {
+        name: "session_branches",
+        scope: Scope::Session,
+        renumbered: false,
+        unique: None,
+        links: &[("fork_message_id", "messages", Link::UpTo)],
+    },
+    Table {
+        name: "input_history",
+        scope: Scope::Session,
+        renumbered: true,
+        unique: None,
+        links: &[],
+    },
+    Table {
+        name: "graph_metadata",
+        scope: Scope::Namespace,
+        renumbered: true,
+        unique: None,
+        links: &[],
+    },
+    Table {
+        name: "graph_nodes",
+        scope: Scope::Namespace,
+        renumbered: true,
+        unique: None,
+        links: &[("embedding_id", "memory_vectors", Link::Optional)],
+    },
+    Table {
+        name: "graph_edges",
+        scope: Scope::Namespace,
+        renumbered: true,
+        unique: None,
+        links: &[
+            ("source_id", "graph_nodes", Link::Required),
+            ("target_id", "graph_nodes", Link::Required),
+        ],
+    },
+    // Strategies name the instance that learned them, which must be registered
+    Table {
+        name: "mesh_registry",
+        scope: Scope::StrategyOrigins,
+        renumbered: false,
+        unique: Some("instance_id"),
+        links: &[],
+    },
+    Table {
+        name: "strategies",
+        scope: Scope::All,
+        renumbered: true,
+        unique: Some("strategy_id"),
+        links: &[],
+    },
+];
+
+/// Rows of one table, every value cast to text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableDump {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+/// Stored state of an agent, as written to a profile archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileData {
+    /// Sessions included, by id
+    pub sessions: Vec<String>,
+    /// Knowledge graph namespaces included
+    pub namespaces: Vec<String>,
+    pub tables: Vec<TableDump>,
+}
+
+impl ProfileData {
+    /// Number of rows dumped from `table`
+    pub fn row_count(&self, table: &str) -> usize {
+        self.tables
+            .iter()
+            .filter(|dump| dump.table == table)
+            .map(|dump| dump.rows.len())
+            .sum()
+    }
+}
+
+/// What to export
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileSelection {
+    /// Only sessions this agent profile ran; every session when unset
+    pub agent: Option<String>,
+    /// Graph namespaces to include; when empty, every namespace, or with
+    /// `agent` set the namespaces of its sessions
+    pub namespaces: Vec<String>,
+}
+
+/// What an import added
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    pub sessions: usize,
+    pub namespaces: usize,
+    pub strategies: usize,
+    /// Rows inserted across every table
+    pub rows: usize,
+    /// Sessions and namespaces left alone because they already existed
+    pub skipped: Vec<String>,
+}
+
+impl Persistence {
+    /// Dump the sessions, graph namespaces and strategies `selection` names
+    pub fn export_profile_data(&self, selection: &ProfileSelection) -> Result<ProfileData> {
+        self.fault_point("export_profile_data")?;
+        let conn = self.read_conn();
+        let sessions = match &selection.agent {
+            Some(agent) => strings(
+                &conn,
+                "SELECT session_id FROM chat_sessions WHERE agent = ? ORDER BY session_id",
+                &[agent.as_str()],
+            )?,
+            None => strings(
+                &conn,
+                "SELECT session_id FROM chat_sessions
+                 UNION SELECT session_id FROM messages
+                 ORDER BY session_id",
+                &[],
+            )?,
+        };
+        let namespaces = if !selection.namespaces.is_empty() {
+            selection.namespaces.clone()
+        } else if selection.agent.is_some() {
+            let mut namespaces = Vec::new();
+            for session in &sessions {
+                if namespace_exists(&conn, session)? {
+                    namespaces.push(session.clone());
+                }
+            }
+            namespaces
+        } else {
+            strings(
+                &conn,
+                "SELECT session_id FROM graph_metadata
+                 UNION SELECT session_id FROM graph_nodes
+                 ORDER BY session_id",
+                &[],
+            )?
+        };
+
+        let mut tables = Vec::new();
+        for table in TABLES {
+            let columns = columns_of(&conn, table.name)?;
+            let select: Vec<String> = columns
+                .iter()
+                .map(|(name, _)| format!("CAST(\"{}\" AS VARCHAR)", name))
+                .collect();
+            let (filter, keys) = match table.scope {
+                Scope::Session => in_list("session_id", &sessions),
+                Scope::Namespace => in_list("session_id", &namespaces),
+                Scope::All => ("TRUE".to_string(), &[][..]),
+                Scope::StrategyOrigins => (
+                    "instance_id IN (SELECT origin_instance FROM strategies)".to_string(),
+                    &[][..],
+                ),
+            };
+            let order = if table.renumbered { " ORDER BY id" } else { "" };
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM {} WHERE {}{}",
+                select.join(", "),
+                table.name,
+                filter,
+                order
+            ))?;
+            let mut rows = stmt.query(params_from_iter(keys))?;
+            let mut dump = TableDump {
+                table: table.name.to_string(),
+                columns: columns.into_iter().map(|(name, _)| name).collect(),
+                rows: Vec::new(),
+            };
+            while let Some(row) = rows.next()? {
+                let values = (0..dump.columns.len())
+                    .map(|i| row.get(i))
+                    .collect::<duckdb::Result<_>>()?;
+                dump.rows.push(values);
+            }
+            tables.push(dump);
+        }
+
+        Ok(ProfileData {
+            sessions,
+            namespaces,
+            tables,
+        })
+    }
+
+    /// Load a dump made by [`Persistence::export_profile_data`]
+    ///
+    /// Sessions and namespaces that already exist here are an error, or are
+    /// left alone with `skip_existing`. Everything else is inserted in one
+    /// transaction.
+    pub fn import_profile_data(
+        &self,
+        data: &ProfileData,
+        skip_existing: bool,
+    ) -> Result<ImportReport> {
+        self.fault_point("import_profile_data")?;
+        let conn = self.conn();
+
+        let mut existing = BTreeSet::new();
+        let mut report = ImportReport::default();
+        for session in &data.sessions {
+            if session_exists(&conn, session)? {
+                existing.insert(("session", session.as_str()));
+            }
+        }
+        for namespace in &data.namespaces {
+            if namespace_exists(&conn, namespace)? {
+                existing.insert(("namespace", namespace.as_str()));
+            }
+        }
+        if !existing.is_empty() {
+            let names: Vec<String> = existing
+                .iter()
+                .map(|(kind, name)| format!("{} '{}'", kind, name))
+                .collect();
+            if !skip_existing {
+                bail!("Already in this database: {}", names.join(", "));
+            }
+            report.skipped = names;
+        }
+        report.sessions = data.sessions.len() - count_kind(&existing, "session");
+        report.namespaces = data.namespaces.len() - count_kind(&existing, "namespace");
+
+        conn.execute_batch("BEGIN TRANSACTION")?;
+        let result = (|| -> Result<()> {
+            // New ids of renumbered rows by table, keyed by their old ids
+            let mut ids: HashMap<&str, BTreeMap<i64, i64>> = HashMap::new();
+            for dump in &data.tables {
+                let Some(table) = TABLES.iter().find(|table| table.name == dump.table) else {
+                    tracing::warn!("Skipping unknown table '{}' in profile data", dump.table);
+                    continue;
+                };
+                let inserted = import_table(&conn, table, dump, &existing, &mut ids)
+                    .with_context(|| format!("importing {}", table.name))?;
+                if table.name == "strategies" {
+                    report.strategies = inserted;
+                }
+                report.rows += inserted;
+            }
+            Ok(())
+        })();
+        match result {
+            Ok(()) => {
+                conn.execute_batch("COMMIT")?;
+                Ok(report)
+            }
+            Err(e) => {
+                let _ = conn.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+}
+
+fn count_kind(existing: &BTreeSet<(&str, &str)>, kind: &str) -> usize {
+    existing.iter().filter(|(k, _)| *k == kind).count()
+}
+
+/// Insert the rows of `dump` that belong here, returning how many
+fn import_table<'a>(
+    conn: &Connection,
+    table: &'a Table,
+    dump: &TableDump,
+    existing: &BTreeSet<(&str, &str)>,
+    ids: &mut HashMap<&'a str, BTreeMap<i64, i64>>,
+) -> Result<usize> {
+    // Columns this database no longer has are dropped; new ones get defaults
+    let target: HashMap<String, String> = columns_of(conn, table.name)?.into_iter().collect();
+    let position = |name: &str| dump.columns.iter().position(|column| column == name);
+    let id_column = position("id");
+    let kept: Vec<(usize, &str, &str)> = dump
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| !(table.renumbered && name.as_str() == "id"))
+        .filter_map(|(i, name)| target.get(name).map(|ty| (i, name.as_str(), ty.as_str())))
+        .collect();
+    if kept.is_empty() {
+        return Ok(0);
+    }
+
+    let placeholders: Vec<String> = kept
+        .iter()
+        .map(|(_, _, ty)| format!("CAST(? AS {})", ty))
+        .collect();
+    let names: Vec<String> = kept
+        .iter()
+        .map(|(_, name, _)| format!("\"{}\"", name))
+        .collect();
+    let mut insert = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table.name,
+        names.join(", "),
+        placeholders.join(", ")
+    );
+    if table.renumbered {
+        insert.push_str(" RETURNING id");
+    }
+    let owner_kind = match table.scope {
+        Scope::Session => Some("session"),
+        Scope::Namespace => Some("namespace"),
+        Scope::All | Scope::StrategyOrigins => None,
+    };
+    let owner = position("session_id");
+    let unique = table
+        .unique
+        .and_then(|column| position(column).map(|i| (column, i)));
+
+    let mut inserted = 0;
+    'rows: for row in &dump.rows {
+        if let (Some(kind), Some(owner)) = (owner_kind, owner) {
+            if let Some(session) = row[owner].as_deref() {
+                if existing.contains(&(kind, session)) {
+                    continue;
+                }
+            }
+        }
+        if let Some((column, i)) = unique {
+            let present: i64 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE {} = ?", table.name, column),
+                params![row[i]],
+                |r| r.get(0),
+            )?;
+            if present > 0 {
+                continue;
+            }
+        }
+
+        let mut values: Vec<Option<String>> =
+            kept.iter().map(|(i, _, _)| row[*i].clone()).collect();
+        for (column, referenced, link) in table.links {
+            let Some(slot) = kept.iter().position(|(_, name, _)| name == column) else {
+                continue;
+            };
+            let old = values[slot].as_deref().and_then(|v| v.parse::<i64>().ok());
+            let map = ids.get(referenced);
+            values[slot] = match (old, link) {
+                (None, Link::Required) => continue 'rows,
+                (None, _) => None,
+                (Some(0), Link::UpTo) => Some("0".to_string()),
+                (Some(old), Link::UpTo) => Some(
+                    map.and_then(|map| map.range(..=old).next_back())
+                        .map_or(0, |(_, new)| *new)
+                        .to_string(),
+                ),
+                (Some(old), Link::Required) => match map.and_then(|map| map.get(&old)) {
+                    Some(new) => Some(new.to_string()),
+                    None => continue 'rows,
+                },
+                (Some(old), Link::Optional) => {
+                    map.and_then(|map| map.get(&old)).map(|new| new.to_string())
+                }
+            };
+        }
+
+        if table.renumbered {
+            let new_id: i64 = conn.query_row(&insert, params_from_iter(&values), |r| r.get(0))?;
+            if let Some(old_id) = id_column.and_then(|i| row[i].as_deref()?.parse().ok()) {
+                ids.entry(table.name).or_default().insert(old_id, new_id);
+            }
+        } else {
+            conn.execute(&insert, params_from_iter(&values))?;
+        }
+        inserted += 1;
+    }
+    Ok(inserted)
+}
+
+/// Columns of `table` with their types, in table order
+fn columns_of(conn: &Connection, table: &str) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT column_name, data_type FROM information_schema.columns
+         WHERE table_schema = 'main' AND table_name = ? ORDER BY ordinal_position",
+    )?;
+    let mut rows = stmt.query(params![table])?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next()? {
+        out.push((row.get(0)?, row.get(1)?));
+    }
+    Ok(out)
+}
+
+/// `column IN (?, ...)` for `keys`, or a condition no row meets when empty
+fn in_list<'a>(column: &str, keys: &'a [String]) -> (String, &'a [String]) {
+    if keys.is_empty() {
+        return ("FALSE".to_string(), keys);
+    }
+    let placeholders = vec!["?"; keys.len()].join(", ");
+    (format!("{} IN ({})", column, placeholders), keys)
+}
+
+fn strings(conn: &Connection, sql: &str, params: &[&str]) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params_from_iter(params))?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next()? {
+        out.push(row.get(0)?);
+    }
+    Ok(out)
+}
+
+fn session_exists(conn: &Connection, session_id: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM messages WHERE session_id = ?)
+              + (SELECT COUNT(*) FROM chat_sessions WHERE session_id = ?)",
+        params![session_id, session_id],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn namespace_exists(conn: &Connection, namespace: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM graph_nodes WHERE session_id = ?)
+              + (SELECT COUNT(*) FROM graph_metadata WHERE session_id = ?)",
+        params![namespace, namespace],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MessageRole;
+    use spec_ai_knowledge_graph::{EdgeType, NodeType};
+
+    fn seed(persistence: &Persistence, session: &str) {
+        persistence
+            .upsert_session(session, Some("coder"), "mock", None)
+            .unwrap();
+        let first = persistence
+            .insert_message(session, MessageRole::User, "hello")
+            .unwrap();
+        let second = persistence
+            .insert_message(session, MessageRole::Assistant, "hi")
+            .unwrap();
+        persistence.pin_message(session, second).unwrap();
+        persistence
+            .save_conversation_checkpoint(session, "start", first)
+            .unwrap();
+        let a = persistence
+            .insert_graph_node(
+                session,
+                NodeType::Entity,
+                "Person",
+                &serde_json::json!({"name": "Ada"}),
+                None,
+            )
+            .unwrap();
+        let b = persistence
+            .insert_graph_node(
+                session,
+                NodeType::Entity,
+                "Person",
+                &serde_json::json!({"name": "Bob"}),
+                None,
+            )
+            .unwrap();
+        persistence
+            .insert_graph_edge(session, a, b, EdgeType::RelatesTo, Some("knows"), None, 1.0)
+            .unwrap();
+    }
+
+    #[test]
+    fn exported_sessions_import_with_new_ids() {
+        let source = Persistence::in_memory().unwrap();
+        seed(&source, "s1");
+        let data = source
+            .export_profile_data(&ProfileSelection::default())
+            .unwrap();
+        assert_eq!(data.sessions, vec!["s1"]);
+        assert_eq!(data.namespaces, vec!["s1"]);
+        assert_eq!(data.row_count("messages"), 2);
+
+        // The target already has rows, so every id shifts
+        let target = Persistence::in_memory().unwrap();
+        seed(&target, "other");
+        let report = target.import_profile_data(&data, false).unwrap();
+        assert_eq!(report.sessions, 1);
+        assert_eq!(report.namespaces, 1);
+
+        let messages = target.list_messages("s1", 10).unwrap();
+        assert_eq!(messages.len(), 2);
+        let pinned = target.list_pinned_messages("s1").unwrap();
+        assert_eq!(pinned.len(), 1);
+        assert_eq!(pinned[0].id, messages[1].id);
+        let checkpoint = target.conversation_checkpoint("s1", "start").unwrap();
+        assert_eq!(checkpoint.map(|c| c.message_id), Some(messages[0].id));
+        let edges = target.list_graph_edges("s1", None, None).unwrap();
+        assert_eq!(edges.len(), 1);
+        let nodes = target.list_graph_nodes("s1", None, None).unwrap();
+        assert!(nodes.iter().any(|node| node.id == edges[0].source_id));
+
+        assert!(target.import_profile_data(&data, false).is_err());
+        let again = target.import_profile_data(&data, true).unwrap();
+        assert_eq!(again.rows, 0);
+        assert_eq!(again.skipped.len(), 2);
+    }
+
+    #[test]
+    fn agent_selection_limits_sessions() {
+        let persistence = Persistence::in_memory().unwrap();
+        seed(&persistence, "mine");
+        persistence
+            .insert_message("theirs", MessageRole::User, "hey")
+            .unwrap();
+        let data = persistence
+            .export_profile_data(&ProfileSelection {
+                agent: Some("coder".to_string()),
+                namespaces: Vec::new(),
+            })
+            .unwrap();
+        assert_eq!(data.sessions, vec!["mine"]);
+        assert_eq!(data.row_count("messages"), 2);
+    }
+}
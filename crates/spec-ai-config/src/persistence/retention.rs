@@ -0,0 +1,356 @@
+//! Retention of stored history
+//!
+//! Sessions, audit records, heartbeats, the graph changelog and usage records
+//! grow for as long as an instance runs. [`Persistence::apply_retention`]
+//! removes what falls outside the age and size limits of `[retention]` and
+//! reports what it removed from each store.
+
+use super::Persistence;
+use crate::config::{RetentionConfig, RetentionRule};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Connection};
+use serde::Serialize;
+
+/// Tables of one store with the columns retention needs
+struct Table {
+    name: &'static str,
+    /// Column compared with the age cutoff
+    timestamp: &'static str,
+    /// Column identifying a row; the newest rows sort last by it
+    key: &'static str,
+    /// Rows that may be removed at all
+    removable: &'static str,
+}
+
+/// Tables of the stores whose entries are plain rows
+const ROW_STORES: &[(&str, &[Table])] = &[
+    (
+        "audit",
+        &[
+            Table {
+                name: "tool_log",
+                timestamp: "created_at",
+                key: "id",
+                removable: "TRUE",
+            },
+            // Revocations must outlive the token they revoke
+            Table {
+                name: "token_audit",
+                timestamp: "created_at",
+                key: "id",
+                removable: "expires_at <= epoch(now())",
+            },
+        ],
+    ),
+    (
+        "telemetry",
+        &[Table {
+            name: "agent_heartbeats",
+            timestamp: "updated_at",
+            key: "updated_at",
+            removable: "TRUE",
+        }],
+    ),
+    (
+        "changelog",
+        &[Table {
+            name: "graph_changelog",
+            timestamp: "created_at",
+            key: "id",
+            removable: "TRUE",
+        }],
+    ),
+    (
+        "usage",
+        &[Table {
+            name: "usage_records",
+            timestamp: "created_at",
+            key: "id",
+            removable: "TRUE",
+        }],
+    ),
+];
+
+/// Tables holding a session's conversation, children before the messages
+/// they refer to
+const SESSION_TABLES: &[&str] = &[
+    "memory_vectors",
+    "pinned_messages",
+    "context_summaries",
+    "conversation_checkpoints",
+    "session_branches",
+    "input_history",
+    "messages",
+    "chat_sessions",
+];
+
+/// Sessions with the time of their last message or settings change
+const SESSION_ACTIVITY_SQL: &str = "WITH activity AS (
+        SELECT session_id, MAX(created_at) AS last FROM messages GROUP BY session_id
+        UNION ALL
+        SELECT session_id, updated_at AS last FROM chat_sessions
+     )
+     SELECT session_id, MAX(last) AS last FROM activity GROUP BY session_id";
+
+/// What one retention run removed from a store
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PruneReport {
+    pub store: &'static str,
+    /// Entries removed for being older than `max_age_days`: sessions for the
+    /// sessions store, rows for the others
+    pub by_age: usize,
+    /// Entries removed to stay within `max_entries`
+    pub by_size: usize,
+}
+
+impl PruneReport {
+    pub fn total(&self) -> usize {
+        self.by_age + self.by_size
+    }
+}
+
+impl Persistence {
+    /// Remove what falls outside the limits of `config`, as of `now`
+    ///
+    /// Every store with limits gets a report, including stores where nothing
+    /// was removed. Stores without limits are left alone.
+    pub fn apply_retention(
+        &self,
+        config: &RetentionConfig,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<PruneReport>> {
+        self.fault_point("apply_retention")?;
+        let mut reports = Vec::new();
+        for (store, rule) in config.rules() {
+            if !rule.is_bounded() {
+                continue;
+            }
+            let conn = self.conn();
+            let report = if store == "sessions" {
+                prune_sessions(&conn, rule, now)
+            } else {
+                let tables = ROW_STORES
+                    .iter()
+                    .find(|(name, _)| *name == store)
+                    .map(|(_, tables)| *tables)
+                    .unwrap_or_default();
+                prune_rows(&conn, store, tables, rule, now)
+            };
+            reports.push(report.with_context(|| format!("pruning {}", store))?);
+        }
+        Ok(reports)
+    }
+}
+
+/// Start of the period `rule` keeps, if it limits age
+fn cutoff(rule: &RetentionRule, now: DateTime<Utc>) -> Option<String> {
+    rule.max_age_days
+        .map(|days| (now - Duration::days(days as i64)).to_rfc3339())
+}
+
+fn prune_rows(
+    conn: &Connection,
+    store: &'static str,
+    tables: &[Table],
+    rule: &RetentionRule,
+    now: DateTime<Utc>,
+) -> Result<PruneReport> {
+    let mut report = PruneReport {
+        store,
+        by_age: 0,
+        by_size: 0,
+    };
+    for table in tables {
+        if let Some(cutoff) = cutoff(rule, now) {
+            report.by_age += conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE {} < ? AND {}",
+                    table.name, table.timestamp, table.removable
+                ),
+                params![cutoff],
+            )?;
+        }
+        if let Some(max_entries) = rule.max_entries {
+            report.by_size += conn.execute(
+                &format!(
+                    "DELETE FROM {0} WHERE {1} NOT IN \
+                     (SELECT {1} FROM {0} ORDER BY {1} DESC LIMIT ?) AND {2}",
+                    table.name, table.key, table.removable
+                ),
+                params![max_entries as i64],
+            )?;
+        }
+    }
+    Ok(report)
+}
+
+/// Remove whole sessions that have been idle too long, then the least
+/// recently active ones beyond `max_entries`
+fn prune_sessions(
+    conn: &Connection,
+    rule: &RetentionRule,
+    now: DateTime<Utc>,
+) -> Result<PruneReport> {
+    let mut report = PruneReport {
+        store: "sessions",
+        by_age: 0,
+        by_size: 0,
+    };
+    if let Some(cutoff) = cutoff(rule, now) {
+        let expired = session_ids(
+            conn,
+            &format!(
+                "SELECT session_id FROM ({}) WHERE last < ?",
+                SESSION_ACTIVITY_SQL
+            ),
+            params![cutoff],
+        )?;
+        report.by_age = delete_sessions(conn, &expired)?;
+    }
+    if let Some(max_entries) = rule.max_entries {
+        let excess = session_ids(
+            conn,
+            &format!(
+                "SELECT session_id FROM ({}) ORDER BY last DESC, session_id OFFSET ?",
+                SESSION_ACTIVITY_SQL
+            ),
+            params![max_entries as i64],
+        )?;
+        report.by_size = delete_sessions(conn, &excess)?;
+    }
+    Ok(report)
+}
+
+fn session_ids(conn: &Connection, sql: &str, params: &[&dyn duckdb::ToSql]) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params)?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next()? {
+        out.push(row.get(0)?);
+    }
+    Ok(out)
+}
+
+/// Delete every row of `sessions` in one transaction
+fn delete_sessions(conn: &Connection, sessions: &[String]) -> Result<usize> {
+    if sessions.is_empty() {
+        return Ok(0);
+    }
+    conn.execute_batch("BEGIN TRANSACTION")?;
+    let result = (|| -> Result<()> {
+        for session_id in sessions {
+            for table in SESSION_TABLES {
+                conn.execute(
+                    &format!("DELETE FROM {} WHERE session_id = ?", table),
+                    params![session_id],
+                )?;
+            }
+        }
+        Ok(())
+    })();
+    match result {
+        Ok(()) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(sessions.len())
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MessageRole;
+
+    fn age(persistence: &Persistence, table: &str, column: &str, days: i64) {
+        let at = (Utc::now() - Duration::days(days)).to_rfc3339();
+        persistence
+            .conn()
+            .execute(&format!("UPDATE {} SET {} = ?", table, column), params![at])
+            .unwrap();
+    }
+
+    #[test]
+    fn old_and_excess_sessions_are_removed_whole() {
+        let persistence = Persistence::in_memory().unwrap();
+        persistence
+            .insert_message("old", MessageRole::User, "hello")
+            .unwrap();
+        age(&persistence, "messages", "created_at", 30);
+        for session in ["a", "b", "c"] {
+            persistence
+                .insert_message(session, MessageRole::User, "hi")
+                .unwrap();
+        }
+        persistence.append_input_history("old", "hello").unwrap();
+
+        let config = RetentionConfig {
+            sessions: RetentionRule {
+                max_age_days: Some(7),
+                max_entries: Some(2),
+            },
+            ..RetentionConfig::default()
+        };
+        let reports = persistence.apply_retention(&config, Utc::now()).unwrap();
+
+        assert_eq!(
+            reports,
+            vec![PruneReport {
+                store: "sessions",
+                by_age: 1,
+                by_size: 1,
+            }]
+        );
+        assert_eq!(persistence.list_sessions().unwrap().len(), 2);
+        assert!(persistence
+            .list_input_history("old", 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn audit_rows_keep_unexpired_revocations() {
+        let persistence = Persistence::in_memory().unwrap();
+        for run in 0..3 {
+            persistence
+                .log_tool(
+                    "s",
+                    "agent",
+                    &run.to_string(),
+                    "echo",
+                    &serde_json::json!({}),
+                    &serde_json::json!("ok"),
+                    true,
+                    None,
+                )
+                .unwrap();
+        }
+        persistence
+            .conn()
+            .execute(
+                "INSERT INTO token_audit (token_id, event, actor, scope, expires_at)
+                 VALUES ('live', 'revoked', 'admin', '[]', ?)",
+                params![Utc::now().timestamp() + 3600],
+            )
+            .unwrap();
+        age(&persistence, "token_audit", "created_at", 30);
+
+        let config = RetentionConfig {
+            audit: RetentionRule {
+                max_age_days: Some(7),
+                max_entries: Some(1),
+            },
+            ..RetentionConfig::default()
+        };
+        let reports = persistence.apply_retention(&config, Utc::now()).unwrap();
+
+        assert_eq!(reports[0].store, "audit");
+        assert_eq!(reports[0].by_size, 2);
+        assert_eq!(persistence.list_tool_calls("s", 10).unwrap().len(), 1);
+        assert_eq!(persistence.revoked_token_ids().unwrap(), vec!["live"]);
+    }
+}
@@ -10,11 +10,7 @@ pub fn env_lock() -> &'static Mutex<()> {
     LOCK.get_or_init(|| Mutex::new(()))
 }
 
+/// Fresh in-memory database, isolated from every other test
 pub fn create_test_db() -> crate::persistence::Persistence {
-    use tempfile::tempdir;
-    let dir = tempdir().unwrap();
-    let db_path = dir.path().join("test.duckdb");
-    // Leak the temp dir to keep it alive for the test duration
-    std::mem::forget(dir);
-    crate::persistence::Persistence::new(&db_path).unwrap()
+    crate::persistence::Persistence::in_memory().unwrap()
 }
@@ -42,6 +42,9 @@ pub struct Message {
     pub role: MessageRole,
     pub content: String,
     pub created_at: DateTime<Utc>,
+    /// Who wrote the message, when the session is shared by several people
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,231 @@
+//! Interactive approval of tool calls blocked by policy
+//!
+//! A front end registers a [`ToolApprovalSender`] with the agent. When a tool
+//! call is not allowed by the agent profile or policy engine, the agent sends
+//! a [`ToolApprovalRequest`] and waits for the front end to answer it before
+//! continuing.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::io::ErrorKind;
+use tokio::sync::{mpsc, oneshot};
+
+/// Maximum number of diff lines included in a preview
+const MAX_PREVIEW_LINES: usize = 40;
+
+/// How the user answered an approval request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    /// Run this call only
+    AllowOnce,
+    /// Run this call and allow the tool for the rest of the session
+    AllowAlways,
+    /// Refuse the call
+    Deny,
+}
+
+/// A blocked tool call waiting for the user's decision
+#[derive(Debug)]
+pub struct ToolApprovalRequest {
+    pub tool_name: String,
+    pub description: String,
+    pub arguments: Value,
+    /// Rule or reason that blocked the call
+    pub rule: String,
+    /// Diff of the change for tools that write files
+    pub preview: Option<String>,
+    respond: oneshot::Sender<ApprovalDecision>,
+}
+
+impl ToolApprovalRequest {
+    /// Create a request and the receiver its decision arrives on
+    pub fn new(
+        tool_name: impl Into<String>,
+        description: impl Into<String>,
+        arguments: Value,
+        rule: impl Into<String>,
+    ) -> (Self, oneshot::Receiver<ApprovalDecision>) {
+        let tool_name = tool_name.into();
+        let (respond, decision) = oneshot::channel();
+        let request = Self {
+            preview: preview(&tool_name, &arguments),
+            tool_name,
+            description: description.into(),
+            arguments,
+            rule: rule.into(),
+            respond,
+        };
+        (request, decision)
+    }
+
+    /// Answer the request, returning false if the agent stopped waiting
+    pub fn respond(self, decision: ApprovalDecision) -> bool {
+        self.respond.send(decision).is_ok()
+    }
+}
+
+/// Channel the agent sends approval requests on
+pub type ToolApprovalSender = mpsc::UnboundedSender<ToolApprovalRequest>;
+
+/// Receiving end of a [`ToolApprovalSender`]
+pub type ToolApprovalReceiver = mpsc::UnboundedReceiver<ToolApprovalRequest>;
+
+/// Create a channel for approval requests
+pub fn tool_approval_channel() -> (ToolApprovalSender, ToolApprovalReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// Ask the front end to approve a tool call and wait for its answer
+///
+/// A request dropped without an answer counts as a denial.
+pub(crate) async fn request_approval(
+    sender: &ToolApprovalSender,
+    tool_name: &str,
+    description: String,
+    arguments: &Value,
+    rule: String,
+) -> Result<ApprovalDecision> {
+    let (request, decision) =
+        ToolApprovalRequest::new(tool_name, description, arguments.clone(), rule);
+    sender
+        .send(request)
+        .map_err(|_| anyhow!("tool approval handler is no longer running"))?;
+    Ok(decision.await.unwrap_or(ApprovalDecision::Deny))
+}
+
+/// Diff of the change a `file_write` or `apply_patch` call would make
+pub fn preview(tool_name: &str, arguments: &Value) -> Option<String> {
+    if tool_name == "apply_patch" {
+        return crate::tools::builtin::apply_patch::preview(arguments).map(|diff| truncate(&diff));
+    }
+    if tool_name != "file_write" {
+        return None;
+    }
+    let path = arguments.get("path")?.as_str()?;
+    let content = arguments.get("content")?.as_str()?;
+    if arguments.get("encoding").and_then(Value::as_str) == Some("base64") {
+        return None;
+    }
+
+    let current = if arguments.get("mode").and_then(Value::as_str) == Some("append") {
+        String::new()
+    } else {
+        match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            // Binary or unreadable files have no useful text diff
+            Err(_) => return None,
+        }
+    };
+
+    Some(format!(
+        "--- {}\n+++ {}\n{}",
+        path,
+        path,
+        line_diff(&current, content)
+    ))
+}
+
+/// Single-hunk line diff between the changed middle sections of two texts
+fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let removed = &old[prefix..old.len() - suffix];
+    let added = &new[prefix..new.len() - suffix];
+    if removed.is_empty() && added.is_empty() {
+        return "(no changes)".to_string();
+    }
+
+    let mut lines = vec![format!(
+        "@@ -{},{} +{},{} @@",
+        prefix + 1,
+        removed.len(),
+        prefix + 1,
+        added.len()
+    )];
+    lines.extend(removed.iter().map(|line| format!("-{}", line)));
+    lines.extend(added.iter().map(|line| format!("+{}", line)));
+    truncate(&lines.join("\n"))
+}
+
+/// Keep the first MAX_PREVIEW_LINES lines of a diff
+fn truncate(diff: &str) -> String {
+    let mut lines: Vec<&str> = diff.lines().collect();
+    if lines.len() <= MAX_PREVIEW_LINES {
+        return lines.join("\n");
+    }
+    let hidden = lines.len() - MAX_PREVIEW_LINES;
+    lines.truncate(MAX_PREVIEW_LINES);
+    format!("{}\n… {} more lines", lines.join("\n"), hidden)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_line_diff_reports_changed_lines() {
+        let diff = line_diff("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(diff, "@@ -2,2 +2,3 @@\n-b\n-c\n+B\n+c\n+d");
+        assert_eq!(line_diff("same\n", "same\n"), "(no changes)");
+    }
+
+    #[test]
+    fn test_preview_for_file_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        let path = path.to_string_lossy();
+
+        let diff = preview("file_write", &json!({"path": path, "content": "one\n2\n"})).unwrap();
+        assert!(diff.ends_with("@@ -2,1 +2,1 @@\n-two\n+2"));
+
+        // Appends only add lines
+        let diff = preview(
+            "file_write",
+            &json!({"path": path, "content": "three\n", "mode": "append"}),
+        )
+        .unwrap();
+        assert!(diff.ends_with("@@ -1,0 +1,1 @@\n+three"));
+
+        let root = dir.path().to_string_lossy();
+        let edits = json!([{"path": "notes.txt", "search": "two", "replace": "2"}]);
+        let diff = preview("apply_patch", &json!({"root": root, "edits": edits})).unwrap();
+        assert!(diff.ends_with(" one\n-two\n+2"));
+
+        assert!(preview("bash", &json!({"command": "ls"})).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_request_waits_for_decision() {
+        let (sender, mut receiver) = tool_approval_channel();
+        let answer = tokio::spawn(async move {
+            let request = receiver.recv().await.unwrap();
+            assert_eq!(request.tool_name, "bash");
+            assert_eq!(request.rule, "blocked");
+            request.respond(ApprovalDecision::AllowOnce);
+
+            // Dropping a request unanswered denies it
+            drop(receiver.recv().await.unwrap());
+        });
+
+        let args = json!({"command": "ls"});
+        let decision = request_approval(&sender, "bash", String::new(), &args, "blocked".into())
+            .await
+            .unwrap();
+        assert_eq!(decision, ApprovalDecision::AllowOnce);
+        let decision = request_approval(&sender, "bash", String::new(), &args, "blocked".into())
+            .await
+            .unwrap();
+        assert_eq!(decision, ApprovalDecision::Deny);
+        answer.await.unwrap();
+    }
+}
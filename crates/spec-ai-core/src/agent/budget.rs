@@ -0,0 +1,241 @@
+//! Run Budgets
+//!
+//! A [`RunBudget`] caps what one step of an agent may spend: model tokens,
+//! estimated cost and tool calls. The limits come from the agent profile's
+//! `max_run_tokens`, `max_run_cost` and `max_run_tool_calls`. A step that
+//! reaches one stops before its next model or tool call, stores what it has,
+//! and reports [`BudgetExceeded`] in its output. Front ends surface that and
+//! can raise the limits with [`AgentCore::set_run_budget`] before the user
+//! asks the agent to continue.
+//!
+//! [`AgentCore::set_run_budget`]: crate::agent::AgentCore::set_run_budget
+
+use crate::config::AgentProfile;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Finish reason of a step stopped by its budget
+pub const BUDGET_EXCEEDED_REASON: &str = "budget_exceeded";
+
+/// Most a single step may spend; unset limits are unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunBudget {
+    /// Most prompt and completion tokens of the main model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    /// Most estimated cost in dollars; usage of unpriced models costs nothing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+    /// Most tool calls that run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<u32>,
+}
+
+impl RunBudget {
+    /// The limits set in `profile`
+    pub fn from_profile(profile: &AgentProfile) -> Self {
+        Self {
+            max_tokens: profile.max_run_tokens,
+            max_cost: profile.max_run_cost,
+            max_tool_calls: profile.max_run_tool_calls,
+        }
+    }
+
+    /// Whether no limit is set
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tokens.is_none() && self.max_cost.is_none() && self.max_tool_calls.is_none()
+    }
+
+    /// These limits, replaced by those set in `overrides`
+    pub fn overridden_by(self, overrides: &RunBudget) -> Self {
+        Self {
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            max_cost: overrides.max_cost.or(self.max_cost),
+            max_tool_calls: overrides.max_tool_calls.or(self.max_tool_calls),
+        }
+    }
+
+    /// The first token or cost limit `spent` has reached
+    ///
+    /// Checked before each model call, since a call may not be cut short.
+    pub fn check_usage(&self, spent: &RunSpend) -> Option<BudgetExceeded> {
+        if let Some(max) = self.max_tokens.filter(|max| spent.tokens >= *max) {
+            return Some(BudgetExceeded::Tokens {
+                max,
+                used: spent.tokens,
+            });
+        }
+        if let Some(max) = self.max_cost.filter(|max| spent.cost >= *max) {
+            return Some(BudgetExceeded::Cost {
+                max,
+                used: spent.cost,
+            });
+        }
+        None
+    }
+
+    /// How many of `requested` further tool calls fit the budget
+    pub fn tool_calls_left(&self, spent: &RunSpend, requested: usize) -> usize {
+        match self.max_tool_calls {
+            Some(max) => requested.min(max.saturating_sub(spent.tool_calls) as usize),
+            None => requested,
+        }
+    }
+
+    /// The tool call limit, if `spent` has reached it
+    pub fn check_tool_calls(&self, spent: &RunSpend) -> Option<BudgetExceeded> {
+        self.max_tool_calls
+            .filter(|max| spent.tool_calls >= *max)
+            .map(|max| BudgetExceeded::ToolCalls {
+                max,
+                used: spent.tool_calls,
+            })
+    }
+}
+
+/// What a step has spent so far
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunSpend {
+    pub tokens: u64,
+    pub cost: f64,
+    pub tool_calls: u32,
+}
+
+/// The limit that stopped a step, and how much of it was used
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "limit", rename_all = "snake_case")]
+pub enum BudgetExceeded {
+    Tokens { max: u64, used: u64 },
+    Cost { max: f64, used: f64 },
+    ToolCalls { max: u32, used: u32 },
+}
+
+impl BudgetExceeded {
+    /// The budget with this limit raised to allow `factor` times as much
+    ///
+    /// For front ends offering to extend the budget of a stopped step.
+    pub fn extended(&self, budget: RunBudget, factor: u32) -> RunBudget {
+        let factor = factor.max(1);
+        match *self {
+            Self::Tokens { max, .. } => RunBudget {
+                max_tokens: Some(max.saturating_mul(factor.into())),
+                ..budget
+            },
+            Self::Cost { max, .. } => RunBudget {
+                max_cost: Some(max * f64::from(factor)),
+                ..budget
+            },
+            Self::ToolCalls { max, .. } => RunBudget {
+                max_tool_calls: Some(max.saturating_mul(factor)),
+                ..budget
+            },
+        }
+    }
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tokens { max, used } => {
+                write!(f, "token budget of {} used up ({} used)", max, used)
+            }
+            Self::Cost { max, used } => {
+                write!(f, "cost budget of ${:.4} used up (${:.4} used)", max, used)
+            }
+            Self::ToolCalls { max, .. } => write!(f, "tool call budget of {} used up", max),
+        }
+    }
+}
+
+/// Note that ends the stored response of a step stopped by its budget
+pub(crate) fn budget_exceeded_response(response: &str, exceeded: &BudgetExceeded) -> String {
+    let note = format!("[stopped: {}]", exceeded);
+    let response = response.trim_end();
+    if response.is_empty() {
+        note
+    } else {
+        format!("{}\n\n{}", response, note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_stop_a_step_once_reached() {
+        let budget = RunBudget {
+            max_tokens: Some(1_000),
+            max_cost: Some(0.05),
+            max_tool_calls: Some(3),
+        };
+        let mut spent = RunSpend {
+            tokens: 999,
+            cost: 0.01,
+            tool_calls: 1,
+        };
+        assert_eq!(budget.check_usage(&spent), None);
+        assert_eq!(budget.tool_calls_left(&spent, 5), 2);
+        assert_eq!(budget.check_tool_calls(&spent), None);
+
+        spent.tokens = 1_200;
+        assert_eq!(
+            budget.check_usage(&spent),
+            Some(BudgetExceeded::Tokens {
+                max: 1_000,
+                used: 1_200
+            })
+        );
+        spent.tokens = 0;
+        spent.cost = 0.05;
+        assert!(matches!(
+            budget.check_usage(&spent),
+            Some(BudgetExceeded::Cost { .. })
+        ));
+        spent.tool_calls = 3;
+        assert_eq!(budget.tool_calls_left(&spent, 5), 0);
+        assert_eq!(
+            budget.check_tool_calls(&spent),
+            Some(BudgetExceeded::ToolCalls { max: 3, used: 3 })
+        );
+
+        let unlimited = RunBudget::default();
+        assert!(unlimited.is_unlimited());
+        assert_eq!(unlimited.check_usage(&spent), None);
+        assert_eq!(unlimited.tool_calls_left(&spent, 5), 5);
+    }
+
+    #[test]
+    fn budgets_override_and_extend_limit_by_limit() {
+        let profile = RunBudget {
+            max_tokens: Some(1_000),
+            max_cost: None,
+            max_tool_calls: Some(3),
+        };
+        let request = RunBudget {
+            max_tool_calls: Some(10),
+            ..RunBudget::default()
+        };
+        let budget = profile.overridden_by(&request);
+        assert_eq!(budget.max_tokens, Some(1_000));
+        assert_eq!(budget.max_tool_calls, Some(10));
+
+        let exceeded = BudgetExceeded::Tokens {
+            max: 1_000,
+            used: 1_050,
+        };
+        let extended = exceeded.extended(budget, 2);
+        assert_eq!(extended.max_tokens, Some(2_000));
+        assert_eq!(extended.max_tool_calls, Some(10));
+
+        let json = serde_json::to_value(exceeded).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"limit": "tokens", "max": 1000, "used": 1050})
+        );
+        assert_eq!(
+            budget_exceeded_response("Partial answer\n", &exceeded),
+            "Partial answer\n\n[stopped: token budget of 1000 used up (1050 used)]"
+        );
+    }
+}
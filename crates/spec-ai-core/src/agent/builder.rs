@@ -3,22 +3,44 @@
 //! Provides a fluent API for constructing agent instances.
 
 use crate::agent::core::AgentCore;
-use crate::agent::factory::{create_provider, resolve_api_key};
+#[cfg(feature = "bedrock")]
+use crate::agent::factory::create_bedrock_provider;
+#[cfg(feature = "gemini")]
+use crate::agent::factory::create_gemini_provider;
+use crate::agent::factory::{
+    create_base_provider, create_provider, inject_faults, resolve_api_key, with_fallbacks,
+};
 use crate::agent::model::{ModelProvider, ProviderKind};
 #[cfg(feature = "openai")]
 use crate::agent::providers::openai::OpenAIProvider;
+#[cfg(feature = "anthropic")]
+use crate::agent::providers::AnthropicProvider;
 #[cfg(feature = "lmstudio")]
 use crate::agent::providers::LMStudioProvider;
 #[cfg(feature = "mlx")]
 use crate::agent::providers::MLXProvider;
-use crate::config::{AgentProfile, AgentRegistry, AppConfig, ModelConfig};
-use crate::embeddings::EmbeddingsClient;
+use crate::config::{AgentProfile, AgentRegistry, AppConfig, GenerationPreset, ModelConfig};
+use crate::embeddings::{EmbeddingsClient, DEFAULT_LOCAL_MODEL};
+use crate::events;
+use crate::mcp;
+#[cfg(feature = "reqwest")]
+use crate::openapi;
 use crate::persistence::Persistence;
 use crate::policy::PolicyEngine;
-use crate::tools::ToolRegistry;
+use crate::prompts::PromptLibrary;
+use crate::telemetry;
+#[cfg(feature = "reqwest")]
+use crate::tools::builtin::WebFetchTool;
+use crate::tools::builtin::{AnnotateTool, BashTool, GraphTool, SqlQueryTool, TaskStartTool};
+#[cfg(feature = "git")]
+use crate::tools::builtin::{GitBranchTool, GitCommitTool, GitPolicy};
+use crate::tools::sandbox::Sandbox;
+use crate::tools::{middleware, ToolRegistry};
+use crate::vectors::{self, VectorStore};
 use anyhow::{anyhow, Context, Result};
 #[cfg(any(feature = "mlx", feature = "lmstudio"))]
 use async_openai::config::OpenAIConfig;
+use spec_ai_plugin::TrustStore;
 use std::sync::Arc;
 use tracing::{info, warn};
 
@@ -27,6 +49,7 @@ pub struct AgentBuilder {
     profile: Option<AgentProfile>,
     provider: Option<Arc<dyn ModelProvider>>,
     embeddings_client: Option<EmbeddingsClient>,
+    vector_store: Option<Arc<dyn VectorStore>>,
     persistence: Option<Persistence>,
     session_id: Option<String>,
     config: Option<AppConfig>,
@@ -34,6 +57,7 @@ pub struct AgentBuilder {
     policy_engine: Option<Arc<PolicyEngine>>,
     agent_name: Option<String>,
     speak_responses: bool,
+    prompt_template: Option<String>,
 }
 
 impl AgentBuilder {
@@ -43,6 +67,7 @@ impl AgentBuilder {
             profile: None,
             provider: None,
             embeddings_client: None,
+            vector_store: None,
             persistence: None,
             session_id: None,
             config: None,
@@ -50,6 +75,7 @@ impl AgentBuilder {
             policy_engine: None,
             agent_name: None,
             speak_responses: false,
+            prompt_template: None,
         }
     }
 
@@ -81,6 +107,12 @@ impl AgentBuilder {
         self
     }
 
+    /// Set the vector store embeddings are mirrored into
+    pub fn with_vector_store(mut self, vector_store: Arc<dyn VectorStore>) -> Self {
+        self.vector_store = Some(vector_store);
+        self
+    }
+
     /// Set the persistence layer
     pub fn with_persistence(mut self, persistence: Persistence) -> Self {
         self.persistence = Some(persistence);
@@ -123,14 +155,26 @@ impl AgentBuilder {
         self
     }
 
+    /// Render the system prompt from a template in the prompt library
+    ///
+    /// `name` may pin a saved version, as in "coder@2". Overrides the
+    /// profile's `prompt_template`.
+    pub fn with_prompt_template(mut self, name: impl Into<String>) -> Self {
+        self.prompt_template = Some(name.into());
+        self
+    }
+
     /// Build the agent, validating all required fields
     pub fn build(self) -> Result<AgentCore> {
         // Get profile (required)
-        let profile = self
+        let mut profile = self
             .profile
             .as_ref()
             .cloned()
             .ok_or_else(|| anyhow!("Agent profile is required"))?;
+        if let Some(template) = self.prompt_template.clone() {
+            profile.prompt_template = Some(template);
+        }
 
         // Precompute session and speech settings before moving fields
         let session_id = self
@@ -139,18 +183,31 @@ impl AgentBuilder {
             .unwrap_or_else(|| format!("session-{}", chrono::Utc::now().timestamp_millis()));
         let speak_preference = self.resolve_speech_preference();
         let agent_name = self.agent_name.clone();
+        let pricing = self
+            .config
+            .as_ref()
+            .map(|config| config.pricing.clone())
+            .unwrap_or_default();
 
         // Get or create persistence (needed for tool registry)
         let persistence = if let Some(persistence) = self.persistence {
             persistence
         } else if let Some(ref config) = self.config {
-            Persistence::new(&config.database.path).context("Failed to create persistence layer")?
+            Persistence::from_config(&config.database)
+                .context("Failed to create persistence layer")?
         } else {
             return Err(anyhow!(
                 "Either persistence or config must be provided to build agent"
             ));
         };
 
+        // The first agent built with telemetry or the event log enabled
+        // starts its exporter
+        if let Some(ref config) = self.config {
+            telemetry::init(&config.telemetry);
+            events::init(&config.event_log);
+        }
+
         // Get or create embeddings client
         let embeddings_client = if let Some(client) = self.embeddings_client {
             Some(client)
@@ -160,6 +217,15 @@ impl AgentBuilder {
             None
         };
 
+        // Get or open the vector store `[vector_store]` describes
+        let vector_store = if let Some(store) = self.vector_store {
+            Some(store)
+        } else if let Some(ref config) = self.config {
+            vectors::from_config(&config.vector_store).context("Failed to open the vector store")?
+        } else {
+            None
+        };
+
         // Optional dedicated code model provider (exposed via the generate_code tool)
         let code_model_provider = if let Some(ref config) = self.config {
             if let Some(code_model_name) = &config.model.code_model {
@@ -184,6 +250,14 @@ impl AgentBuilder {
             None
         };
 
+        // Get or create policy engine (defaults to empty policy engine, or load from persistence)
+        // before the tool registry, which checks unsigned plugins against it
+        let policy_engine = if let Some(engine) = self.policy_engine {
+            engine
+        } else {
+            Arc::new(load_policy_engine(&persistence))
+        };
+
         // Get or create tool registry (defaults to built-in tools)
         // Create this before the provider so OpenAI can be configured with tools
         let tool_registry = if let Some(registry) = self.tool_registry {
@@ -195,6 +269,14 @@ impl AgentBuilder {
                 embeddings_client.clone(),
                 code_model_provider.clone(),
             );
+            // Timeline annotations only reach anyone when spans are exported
+            if self
+                .config
+                .as_ref()
+                .is_some_and(|config| config.telemetry.enabled)
+            {
+                registry.register(Arc::new(AnnotateTool::new()));
+            }
             info!(
                 "Created tool registry with {} builtin tools",
                 registry.len()
@@ -206,10 +288,18 @@ impl AgentBuilder {
             // Load plugins if enabled
             if let Some(ref config) = self.config {
                 if config.plugins.enabled {
-                    match registry.load_plugins(
-                        &config.plugins.custom_tools_dir,
-                        config.plugins.allow_override_builtin,
-                    ) {
+                    let loaded = TrustStore::new(&config.plugins.trusted_keys)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|trust| {
+                            registry.load_plugins(
+                                &config.plugins.custom_tools_dir,
+                                config.plugins.allow_override_builtin,
+                                &trust,
+                                &policy_engine,
+                                agent_name.as_deref().unwrap_or("agent"),
+                            )
+                        });
+                    match loaded {
                         Ok(stats) => {
                             if stats.loaded > 0 {
                                 info!(
@@ -222,6 +312,12 @@ impl AgentBuilder {
                             if stats.failed > 0 {
                                 warn!("{} plugins failed to load", stats.failed);
                             }
+                            if stats.rejected > 0 {
+                                warn!(
+                                    "{} plugins were refused: not signed by a trusted key",
+                                    stats.rejected
+                                );
+                            }
                         }
                         Err(e) => {
                             if config.plugins.continue_on_error {
@@ -235,143 +331,135 @@ impl AgentBuilder {
                 }
             }
 
+            // Tools of external MCP servers, after built-ins and plugins so
+            // they never replace them
+            if let Some(ref config) = self.config {
+                mcp::register_tools(&mut registry, &config.mcp);
+                #[cfg(feature = "reqwest")]
+                openapi::register_tools(&mut registry, &config.openapi);
+            }
+
             Arc::new(registry)
         };
 
-        // Get or create provider with tools configured (for OpenAI-compatible providers)
-        let provider = if let Some(provider) = self.provider {
-            provider
-        } else if let Some(ref config) = self.config {
-            let mut base_provider =
-                create_provider(&config.model).context("Failed to create provider from config")?;
-
-            // Configure OpenAI provider with tools for native function calling
-            #[cfg(feature = "openai")]
+        // Confine the shell tools, whether the registry was built here or passed in
+        let tool_registry = match self.config.as_ref() {
+            Some(config)
+                if config.sandbox.enabled
+                    && (tool_registry.has("bash") || tool_registry.has("task_start")) =>
             {
-                if base_provider.kind() == ProviderKind::OpenAI {
-                    let tools = tool_registry.to_openai_tools();
-                    if !tools.is_empty() {
-                        info!(
-                            "Configuring OpenAI provider with {} tools for native function calling",
-                            tools.len()
-                        );
-
-                        // Recreate OpenAI provider with tools
-                        let api_key = if let Some(source) = &config.model.api_key_source {
-                            resolve_api_key(source)?
-                        } else {
-                            // Default to OPENAI_API_KEY environment variable
-                            std::env::var("OPENAI_API_KEY")
-                                .context("OPENAI_API_KEY environment variable not set")?
-                        };
-
-                        let mut openai_provider = OpenAIProvider::with_api_key(api_key);
-
-                        // Set model if specified in config
-                        if let Some(model_name) = &config.model.model_name {
-                            openai_provider = openai_provider.with_model(model_name.clone());
-                        }
-
-                        // Configure with tools and cast to trait object
-                        base_provider = Arc::new(openai_provider.with_tools(tools));
-                    }
+                let sandbox = Sandbox::from_config(
+                    &config.sandbox,
+                    &policy_engine,
+                    agent_name.as_deref().unwrap_or("agent"),
+                )
+                .context("Failed to set up the bash sandbox")?;
+                info!("Sandboxing the bash tool with {:?}", sandbox.backend());
+                let sandbox = Arc::new(sandbox);
+                let mut registry = (*tool_registry).clone();
+                if registry.has("bash") {
+                    registry.register(Arc::new(BashTool::new().with_sandbox(sandbox.clone())));
                 }
+                if registry.has("task_start") {
+                    registry.register(Arc::new(TaskStartTool::new().with_sandbox(sandbox)));
+                }
+                Arc::new(registry)
             }
+            _ => tool_registry,
+        };
 
-            // Configure MLX provider with tools for native function calling (OpenAI-compatible API)
-            #[cfg(feature = "mlx")]
-            {
-                if base_provider.kind() == ProviderKind::MLX {
-                    let tools = tool_registry.to_openai_tools();
-                    if !tools.is_empty() {
-                        info!(
-                            "Configuring MLX provider with {} tools for native function calling",
-                            tools.len()
-                        );
+        // Hold commits and branch changes to the `git_write` policy rules
+        #[cfg(feature = "git")]
+        let tool_registry = if tool_registry.has("git_commit") || tool_registry.has("git_branch") {
+            let policy = GitPolicy::new(
+                policy_engine.clone(),
+                agent_name.as_deref().unwrap_or("agent"),
+            );
+            let mut registry = (*tool_registry).clone();
+            if registry.has("git_commit") {
+                registry.register(Arc::new(GitCommitTool::new().with_policy(policy.clone())));
+            }
+            if registry.has("git_branch") {
+                registry.register(Arc::new(GitBranchTool::new().with_policy(policy)));
+            }
+            Arc::new(registry)
+        } else {
+            tool_registry
+        };
 
-                        // MLX requires a model name; mirror create_provider's behavior
-                        let model_name = config
-                            .model
-                            .model_name
-                            .as_ref()
-                            .ok_or_else(|| {
-                                anyhow!("MLX provider requires a model_name to be specified")
-                            })?
-                            .clone();
-
-                        let mlx_provider = if let Ok(endpoint) = std::env::var("MLX_ENDPOINT") {
-                            MLXProvider::with_endpoint(endpoint, model_name)
-                        } else {
-                            MLXProvider::new(model_name)
-                        };
-
-                        base_provider = Arc::new(mlx_provider.with_tools(tools));
-                    }
-                }
+        // Apply the configured limits and domain policy to web_fetch
+        #[cfg(feature = "reqwest")]
+        let tool_registry = match self.config.as_ref() {
+            Some(config) if tool_registry.has("web_fetch") => {
+                let mut registry = (*tool_registry).clone();
+                registry.register(Arc::new(
+                    WebFetchTool::with_config(config.web_fetch.clone())
+                        .with_persistence(Arc::new(persistence.clone())),
+                ));
+                Arc::new(registry)
             }
+            _ => tool_registry,
+        };
 
-            #[cfg(feature = "lmstudio")]
-            {
-                if base_provider.kind() == ProviderKind::LMStudio {
-                    let tools = tool_registry.to_openai_tools();
-                    if !tools.is_empty() {
-                        info!(
-                            "Configuring LM Studio provider with {} tools for native function calling",
-                            tools.len()
-                        );
+        // Offer the configured databases through sql_query, writes held to
+        // the `sql_write` policy rules
+        let tool_registry = match self.config.as_ref() {
+            Some(config) if !config.sql.connections.is_empty() => {
+                let mut registry = (*tool_registry).clone();
+                registry.register(Arc::new(
+                    SqlQueryTool::with_config(config.sql.clone()).with_policy(
+                        policy_engine.clone(),
+                        agent_name.as_deref().unwrap_or("agent"),
+                    ),
+                ));
+                Arc::new(registry)
+            }
+            _ => tool_registry,
+        };
 
-                        let model_name = config
-                            .model
-                            .model_name
-                            .as_ref()
-                            .ok_or_else(|| {
-                                anyhow!("LM Studio provider requires a model_name to be specified")
-                            })?
-                            .clone();
-
-                        let lmstudio_provider =
-                            if let Ok(endpoint) = std::env::var("LMSTUDIO_ENDPOINT") {
-                                LMStudioProvider::with_endpoint(endpoint, model_name)
-                            } else {
-                                LMStudioProvider::new(model_name)
-                            };
+        // Let the graph tool search node embeddings in the vector store
+        let tool_registry = match (&vector_store, &embeddings_client) {
+            (Some(store), Some(client)) if tool_registry.has("graph") => {
+                let mut registry = (*tool_registry).clone();
+                registry.register(Arc::new(
+                    GraphTool::new(Arc::new(persistence.clone()))
+                        .with_similarity_search(store.clone(), client.clone()),
+                ));
+                Arc::new(registry)
+            }
+            _ => tool_registry,
+        };
 
-                        base_provider = Arc::new(lmstudio_provider.with_tools(tools));
-                    }
-                }
+        // Wrap every tool call in the configured redaction, logging, rate
+        // limits and output limits
+        let tool_registry = match self.config.as_ref() {
+            Some(config) => {
+                let mut registry = (*tool_registry).clone();
+                registry.set_middleware(middleware::from_config(
+                    &config.tools,
+                    Some(Arc::new(persistence.clone())),
+                ));
+                Arc::new(registry)
             }
+            None => tool_registry,
+        };
 
-            base_provider
+        // Get or create provider with tools configured (for OpenAI-compatible providers)
+        let provider = if let Some(provider) = self.provider {
+            provider
+        } else if let Some(ref config) = self.config {
+            let model = profile.effective_model(&config.model);
+            with_fallbacks(
+                create_provider_with_tools(&model, &tool_registry)?,
+                &config.providers,
+                |model| create_provider_with_tools(model, &tool_registry),
+            )
         } else {
             return Err(anyhow!(
                 "Either provider or config must be provided to build agent"
             ));
         };
 
-        // Get or create policy engine (defaults to empty policy engine, or load from persistence)
-        let policy_engine = if let Some(engine) = self.policy_engine {
-            engine
-        } else {
-            // Try to load from persistence, or create empty engine with default allow rule
-            let mut engine = PolicyEngine::load_from_persistence(&persistence)
-                .unwrap_or_else(|_| PolicyEngine::new());
-
-            // If the policy engine has no rules at all, add a default allow-all for tools
-            if engine.rule_count() == 0 {
-                tracing::debug!(
-                    "Empty policy engine detected, adding default allow-all rule for tools"
-                );
-                engine.add_rule(crate::policy::PolicyRule {
-                    agent: "*".to_string(),
-                    action: "tool_call".to_string(),
-                    resource: "*".to_string(),
-                    effect: crate::policy::PolicyEffect::Allow,
-                });
-            }
-
-            Arc::new(engine)
-        };
-
         let fast_provider = if profile.fast_reasoning {
             match (&profile.fast_model_provider, &profile.fast_model_name) {
                 (Some(provider_name), Some(model_name)) => {
@@ -382,6 +470,11 @@ impl AgentBuilder {
                         embeddings_model: None,
                         api_key_source: None,
                         temperature: profile.fast_model_temperature,
+                        // AWS settings are shared by every Bedrock model
+                        bedrock: self
+                            .config
+                            .as_ref()
+                            .and_then(|config| config.model.bedrock.clone()),
                     };
                     match create_provider(&fast_config) {
                         Ok(provider) => Some(provider),
@@ -400,6 +493,35 @@ impl AgentBuilder {
             None
         };
 
+        let prompts = PromptLibrary::new()
+            .with_templates(
+                self.config
+                    .as_ref()
+                    .map(|config| config.prompts.clone())
+                    .unwrap_or_default(),
+            )
+            .with_persistence(persistence.clone());
+        // Fail now rather than on the first step if the template is missing
+        if let Some(reference) = &profile.prompt_template {
+            prompts
+                .resolve(reference, Some(&profile))
+                .context("Invalid prompt_template")?;
+        }
+
+        let preset = match &profile.preset {
+            Some(name) => {
+                let custom = self
+                    .config
+                    .as_ref()
+                    .map(|config| config.presets.clone())
+                    .unwrap_or_default();
+                let preset = GenerationPreset::resolve(name, &custom)
+                    .ok_or_else(|| anyhow!("Unknown generation preset '{}'", name))?;
+                Some((name.clone(), preset))
+            }
+            None => None,
+        };
+
         let mut agent = AgentCore::new(
             profile,
             provider,
@@ -410,11 +532,17 @@ impl AgentBuilder {
             tool_registry,
             policy_engine,
             speak_preference,
-        );
+        )
+        .with_pricing(pricing)
+        .with_prompt_library(prompts);
+        agent.set_preset(preset);
 
         if let Some(fast_provider) = fast_provider {
             agent = agent.with_fast_provider(fast_provider);
         }
+        if let Some(vector_store) = vector_store {
+            agent = agent.with_vector_store(vector_store);
+        }
 
         Ok(agent)
     }
@@ -446,6 +574,168 @@ impl Default for AgentBuilder {
     }
 }
 
+/// Create the provider for an agent from model configuration
+///
+/// OpenAI-compatible, Anthropic, Gemini and Bedrock providers are configured
+/// with the registry's tools for native function calling.
+pub(crate) fn create_provider_with_tools(
+    model: &ModelConfig,
+    tool_registry: &ToolRegistry,
+) -> Result<Arc<dyn ModelProvider>> {
+    let mut base_provider =
+        create_base_provider(model).context("Failed to create provider from config")?;
+
+    // Configure OpenAI provider with tools for native function calling
+    #[cfg(feature = "openai")]
+    {
+        if base_provider.kind() == ProviderKind::OpenAI {
+            let tools = tool_registry.to_openai_tools();
+            if !tools.is_empty() {
+                info!(
+                    "Configuring OpenAI provider with {} tools for native function calling",
+                    tools.len()
+                );
+
+                // Recreate OpenAI provider with tools
+                let api_key = if let Some(source) = &model.api_key_source {
+                    resolve_api_key(source)?
+                } else {
+                    // Default to OPENAI_API_KEY environment variable
+                    std::env::var("OPENAI_API_KEY")
+                        .context("OPENAI_API_KEY environment variable not set")?
+                };
+
+                let mut openai_provider = OpenAIProvider::with_api_key(api_key);
+
+                // Set model if specified in config
+                if let Some(model_name) = &model.model_name {
+                    openai_provider = openai_provider.with_model(model_name.clone());
+                }
+
+                // Configure with tools and cast to trait object
+                base_provider = Arc::new(openai_provider.with_tools(tools));
+            }
+        }
+    }
+
+    #[cfg(feature = "anthropic")]
+    {
+        if base_provider.kind() == ProviderKind::Anthropic {
+            let tools = tool_registry.to_anthropic_tools();
+            if !tools.is_empty() {
+                info!(
+                    "Configuring Anthropic provider with {} tools for native tool use",
+                    tools.len()
+                );
+
+                let api_key = if let Some(source) = &model.api_key_source {
+                    resolve_api_key(source)?
+                } else {
+                    std::env::var("ANTHROPIC_API_KEY")
+                        .context("ANTHROPIC_API_KEY environment variable not set")?
+                };
+
+                let mut anthropic_provider = AnthropicProvider::with_api_key(api_key);
+                if let Some(model_name) = &model.model_name {
+                    anthropic_provider = anthropic_provider.with_model(model_name.clone());
+                }
+
+                base_provider = Arc::new(anthropic_provider.with_tools(tools));
+            }
+        }
+    }
+
+    #[cfg(feature = "gemini")]
+    {
+        if base_provider.kind() == ProviderKind::Gemini {
+            let tools = tool_registry.to_gemini_tools();
+            if !tools.is_empty() {
+                info!(
+                    "Configuring Gemini provider with {} tools for native function calling",
+                    tools.len()
+                );
+
+                base_provider = Arc::new(create_gemini_provider(model)?.with_tools(tools));
+            }
+        }
+    }
+
+    #[cfg(feature = "bedrock")]
+    {
+        if base_provider.kind() == ProviderKind::Bedrock {
+            let tools = tool_registry.to_bedrock_tools();
+            if !tools.is_empty() {
+                info!(
+                    "Configuring Bedrock provider with {} tools for native tool use",
+                    tools.len()
+                );
+
+                base_provider = Arc::new(create_bedrock_provider(model)?.with_tools(tools));
+            }
+        }
+    }
+
+    // Configure MLX provider with tools for native function calling (OpenAI-compatible API)
+    #[cfg(feature = "mlx")]
+    {
+        if base_provider.kind() == ProviderKind::MLX {
+            let tools = tool_registry.to_openai_tools();
+            if !tools.is_empty() {
+                info!(
+                    "Configuring MLX provider with {} tools for native function calling",
+                    tools.len()
+                );
+
+                // MLX requires a model name; mirror create_provider's behavior
+                let model_name = model
+                    .model_name
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("MLX provider requires a model_name to be specified"))?
+                    .clone();
+
+                let mlx_provider = if let Ok(endpoint) = std::env::var("MLX_ENDPOINT") {
+                    MLXProvider::with_endpoint(endpoint, model_name)
+                } else {
+                    MLXProvider::new(model_name)
+                };
+
+                base_provider = Arc::new(mlx_provider.with_tools(tools));
+            }
+        }
+    }
+
+    #[cfg(feature = "lmstudio")]
+    {
+        if base_provider.kind() == ProviderKind::LMStudio {
+            let tools = tool_registry.to_openai_tools();
+            if !tools.is_empty() {
+                info!(
+                    "Configuring LM Studio provider with {} tools for native function calling",
+                    tools.len()
+                );
+
+                let model_name = model
+                    .model_name
+                    .as_ref()
+                    .ok_or_else(|| {
+                        anyhow!("LM Studio provider requires a model_name to be specified")
+                    })?
+                    .clone();
+
+                let lmstudio_provider = if let Ok(endpoint) = std::env::var("LMSTUDIO_ENDPOINT") {
+                    LMStudioProvider::with_endpoint(endpoint, model_name)
+                } else {
+                    LMStudioProvider::new(model_name)
+                };
+
+                base_provider = Arc::new(lmstudio_provider.with_tools(tools));
+            }
+        }
+    }
+
+    Ok(inject_faults(base_provider))
+}
+
 /// Create an agent from the active profile in the registry
 pub fn create_agent_from_registry(
     registry: &AgentRegistry,
@@ -470,34 +760,100 @@ pub fn create_agent_from_registry(
     builder.build()
 }
 
-fn create_embeddings_client_from_config(config: &AppConfig) -> Result<Option<EmbeddingsClient>> {
-    let model = &config.model;
-    let Some(model_name) = &model.embeddings_model else {
-        return Ok(None);
+/// Load the policy rules stored in `persistence`, allowing every tool call
+/// when there are none
+pub fn load_policy_engine(persistence: &Persistence) -> PolicyEngine {
+    // Try to load from persistence, or create empty engine with default allow rule
+    let mut engine =
+        PolicyEngine::load_from_persistence(persistence).unwrap_or_else(|_| PolicyEngine::new());
+
+    // If the policy engine has no rules at all, add a default allow-all for tools
+    if engine.rule_count() == 0 {
+        tracing::debug!("Empty policy engine detected, adding default allow-all rule for tools");
+        engine.add_rule(crate::policy::PolicyRule {
+            agent: "*".to_string(),
+            action: "tool_call".to_string(),
+            resource: "*".to_string(),
+            effect: crate::policy::PolicyEffect::Allow,
+        });
+    }
+
+    engine
+}
+
+/// Build the embeddings client described by `config`, batching its requests
+/// as `[embeddings]` says
+///
+/// Returns `None` when no embeddings model is configured. Local embeddings
+/// fall back to [`DEFAULT_LOCAL_MODEL`].
+pub fn create_embeddings_client_from_config(
+    config: &AppConfig,
+) -> Result<Option<EmbeddingsClient>> {
+    let model_name = match &config.model.embeddings_model {
+        Some(name) => name.as_str(),
+        None if config.embeddings.is_local() => DEFAULT_LOCAL_MODEL,
+        None => return Ok(None),
     };
 
+    let client = build_embeddings_client(config, model_name)?;
+    Ok(Some(client.with_batching(&config.embeddings)))
+}
+
+fn build_embeddings_client(config: &AppConfig, model_name: &str) -> Result<EmbeddingsClient> {
+    let model = &config.model;
+
+    if config.embeddings.is_local() {
+        return build_local_embeddings_client(config, model_name);
+    }
+
     #[cfg(feature = "mlx")]
     {
         if ProviderKind::from_str(&model.provider) == Some(ProviderKind::MLX) {
-            return Ok(Some(build_mlx_embeddings_client(model_name)));
+            return Ok(build_mlx_embeddings_client(model_name));
         }
     }
 
     #[cfg(feature = "lmstudio")]
     {
         if ProviderKind::from_str(&model.provider) == Some(ProviderKind::LMStudio) {
-            return Ok(Some(build_lmstudio_embeddings_client(model_name)));
+            return Ok(build_lmstudio_embeddings_client(model_name));
         }
     }
 
     let client = if let Some(source) = &model.api_key_source {
         let api_key = resolve_api_key(source)?;
-        EmbeddingsClient::with_api_key(model_name.clone(), api_key)
+        EmbeddingsClient::with_api_key(model_name, api_key)
     } else {
-        EmbeddingsClient::new(model_name.clone())
+        EmbeddingsClient::new(model_name)
     };
 
-    Ok(Some(client))
+    Ok(client)
+}
+
+#[cfg(feature = "local-embeddings")]
+fn build_local_embeddings_client(config: &AppConfig, model_name: &str) -> Result<EmbeddingsClient> {
+    use crate::embeddings::local::{default_cache_dir, LocalEmbeddingsService};
+
+    let cache_dir = match &config.embeddings.cache_dir {
+        Some(dir) => dir.clone(),
+        None => default_cache_dir()?,
+    };
+    let service =
+        LocalEmbeddingsService::new(model_name, cache_dir, config.embeddings.max_batch_size)?;
+    Ok(EmbeddingsClient::with_service(
+        model_name,
+        Arc::new(service),
+    ))
+}
+
+#[cfg(not(feature = "local-embeddings"))]
+fn build_local_embeddings_client(
+    _config: &AppConfig,
+    _model_name: &str,
+) -> Result<EmbeddingsClient> {
+    Err(anyhow!(
+        "embeddings provider \"local\" needs spec-ai built with the local-embeddings feature"
+    ))
 }
 
 #[cfg(feature = "mlx")]
@@ -539,8 +895,10 @@ mod tests {
     use super::*;
     use crate::agent::providers::MockProvider;
     use crate::config::{
-        AgentProfile, AudioConfig, AuthConfig, DatabaseConfig, LoggingConfig, ModelConfig,
-        PluginConfig, SyncConfig, UiConfig,
+        AgentProfile, AudioConfig, AuthConfig, DatabaseConfig, EmbeddingsConfig, EventLogConfig,
+        KeymapConfig, LoggingConfig, McpConfig, ModelConfig, OpenApiConfig, PersistenceMode,
+        PluginConfig, PricingConfig, SandboxConfig, SqlConfig, SyncConfig, TelemetryConfig,
+        ToolsConfig, UiConfig, WebFetchConfig,
     };
     use std::collections::HashMap;
     use tempfile::tempdir;
@@ -550,7 +908,14 @@ mod tests {
         let db_path = dir.path().join("test.duckdb");
 
         AppConfig {
-            database: DatabaseConfig { path: db_path },
+            database: DatabaseConfig {
+                path: db_path,
+                persistence: PersistenceMode::File,
+                encrypted_namespaces: Vec::new(),
+                read_connections: 4,
+                busy_timeout_ms: 5000,
+                checkpoint_threshold: None,
+            },
             model: ModelConfig {
                 provider: "mock".to_string(),
                 model_name: Some("test-model".to_string()),
@@ -558,19 +923,41 @@ mod tests {
                 embeddings_model: None,
                 api_key_source: None,
                 temperature: 0.7,
+                bedrock: None,
             },
             ui: UiConfig {
                 prompt: "> ".to_string(),
                 theme: "default".to_string(),
+                keymap: KeymapConfig::default(),
+                screen_reader: false,
+                mirror: false,
+                presence: false,
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
             },
             audio: AudioConfig::default(),
             mesh: crate::config::MeshConfig::default(),
+            heartbeat: crate::config::HeartbeatConfig::default(),
+            retention: crate::config::RetentionConfig::default(),
+            graph_maintenance: crate::config::GraphMaintenanceConfig::default(),
+            providers: crate::config::ProvidersConfig::default(),
             plugins: PluginConfig::default(),
+            mcp: McpConfig::default(),
+            openapi: OpenApiConfig::default(),
+            sandbox: SandboxConfig::default(),
+            web_fetch: WebFetchConfig::default(),
+            sql: SqlConfig::default(),
+            tools: ToolsConfig::default(),
             sync: SyncConfig::default(),
             auth: AuthConfig::default(),
+            pricing: PricingConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            vector_store: crate::config::VectorStoreConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            event_log: EventLogConfig::default(),
+            prompts: HashMap::new(),
+            presets: HashMap::new(),
             agents: HashMap::new(),
             default_agent: None,
         }
@@ -588,6 +975,16 @@ mod tests {
             memory_k: 10,
             top_p: 0.95,
             max_context_tokens: Some(4096),
+            response_cache_ttl_secs: None,
+            context_compaction: false,
+            context_keep_recent: 6,
+            tool_parallelism: 4,
+            tool_timeout_secs: None,
+            turn_timeout_secs: None,
+            max_run_tokens: None,
+            max_run_cost: None,
+            max_run_tool_calls: None,
+            preset: None,
             enable_graph: false,
             graph_memory: false,
             auto_graph: false,
@@ -648,6 +1045,39 @@ mod tests {
         assert!(agent.session_id().starts_with("session-"));
     }
 
+    #[test]
+    fn test_builder_with_prompt_template() {
+        let dir = tempdir().unwrap();
+        let persistence = Persistence::new(dir.path().join("prompts.duckdb")).unwrap();
+        let mut config = create_test_config();
+        config.prompts.insert(
+            "coder".to_string(),
+            "You are {{agent}}, writing {{language}}.".to_string(),
+        );
+        let mut profile = create_test_profile();
+        profile
+            .prompt_vars
+            .insert("language".to_string(), "Rust".to_string());
+        let builder = || {
+            AgentBuilder::new()
+                .with_profile(profile.clone())
+                .with_config(config.clone())
+                .with_provider(Arc::new(MockProvider::default()))
+                .with_persistence(persistence.clone())
+                .with_agent_name("builder")
+        };
+
+        let agent = builder().with_prompt_template("coder").build().unwrap();
+        assert_eq!(agent.profile().prompt_template.as_deref(), Some("coder"));
+        assert_eq!(
+            agent.system_prompt().unwrap().as_deref(),
+            Some("You are builder, writing Rust.")
+        );
+
+        let missing = builder().with_prompt_template("reviewer").build();
+        assert!(missing.is_err());
+    }
+
     #[test]
     fn test_builder_missing_profile() {
         let config = create_test_config();
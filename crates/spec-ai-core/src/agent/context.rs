@@ -0,0 +1,211 @@
+//! Context Window
+//!
+//! Keeps long conversations inside the main model's context budget. With
+//! `context_compaction` enabled, prompts carry the whole conversation rather
+//! than only the last few turns. Once it outgrows its share of the budget,
+//! the oldest turns are folded into a running summary. Pinned messages and
+//! the most recent turns are never summarized.
+
+use crate::agent::model::{GenerationConfig, ProviderKind};
+use crate::types::Message;
+
+/// Percentage of the context budget the conversation may fill; the rest is
+/// left for the system prompt, tool list, new input and the response
+const CONVERSATION_SHARE_PERCENT: usize = 75;
+
+/// Bounds of the summary length, in tokens
+const MIN_SUMMARY_TOKENS: usize = 64;
+const MAX_SUMMARY_TOKENS: usize = 1024;
+
+/// Context window assumed for a provider when the profile sets no
+/// `max_context_tokens`
+pub fn default_budget(kind: ProviderKind) -> usize {
+    match kind {
+        ProviderKind::Mock => 8_192,
+        #[cfg(feature = "openai")]
+        ProviderKind::OpenAI => 128_000,
+        #[cfg(feature = "anthropic")]
+        ProviderKind::Anthropic => 200_000,
+        #[cfg(feature = "gemini")]
+        ProviderKind::Gemini => 1_000_000,
+        #[cfg(feature = "bedrock")]
+        ProviderKind::Bedrock => 200_000,
+        // Local servers default to small windows unless configured otherwise
+        #[cfg(feature = "ollama")]
+        ProviderKind::Ollama => 8_192,
+        #[cfg(feature = "mlx")]
+        ProviderKind::MLX => 8_192,
+        #[cfg(feature = "lmstudio")]
+        ProviderKind::LMStudio => 8_192,
+    }
+}
+
+/// Rough token count, at about four characters per token like
+/// [`TokenUsage::estimate`](crate::agent::model::TokenUsage::estimate)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn message_tokens(message: &Message) -> usize {
+    // Role label, separator and newline included
+    estimate_tokens(&message.content) + 2
+}
+
+/// Tokens the conversation may fill out of a context budget
+pub fn conversation_budget(budget: usize) -> usize {
+    budget * CONVERSATION_SHARE_PERCENT / 100
+}
+
+/// Longest summary compaction asks for within a context budget
+pub fn summary_budget(budget: usize) -> usize {
+    (conversation_budget(budget) / 4).clamp(MIN_SUMMARY_TOKENS, MAX_SUMMARY_TOKENS)
+}
+
+/// The conversation as it goes into the prompt
+#[derive(Debug, Clone, Default)]
+pub struct ContextWindow {
+    /// Summary of the compacted turns, if any
+    pub summary: Option<String>,
+    /// Pinned messages, always included verbatim
+    pub pinned: Vec<Message>,
+    /// Unpinned turns after the summary, oldest first
+    pub recent: Vec<Message>,
+}
+
+impl ContextWindow {
+    /// Estimated size of the window in tokens
+    pub fn tokens(&self) -> usize {
+        let summary = self.summary.as_deref().map(estimate_tokens).unwrap_or(0);
+        summary
+            + self
+                .pinned
+                .iter()
+                .chain(&self.recent)
+                .map(message_tokens)
+                .sum::<usize>()
+    }
+
+    /// Whether a message is already part of the window
+    pub fn contains(&self, message_id: i64) -> bool {
+        self.pinned
+            .iter()
+            .chain(&self.recent)
+            .any(|message| message.id == message_id)
+    }
+
+    /// Number of the oldest recent turns to fold into the summary
+    ///
+    /// Nothing is compacted while the window fits its share of `budget`.
+    /// Past that, enough turns are folded for the rest and a new summary to
+    /// fit again, but never the last `keep_recent`.
+    pub fn plan_compaction(&self, budget: usize, keep_recent: usize) -> usize {
+        let allowed = conversation_budget(budget);
+        if self.tokens() <= allowed {
+            return 0;
+        }
+
+        let pinned: usize = self.pinned.iter().map(message_tokens).sum();
+        let target = allowed.saturating_sub(summary_budget(budget) + pinned);
+        let compactable = self.recent.len().saturating_sub(keep_recent);
+        let mut remaining: usize = self.recent.iter().map(message_tokens).sum();
+        let mut count = 0;
+        while count < compactable && remaining > target {
+            remaining -= message_tokens(&self.recent[count]);
+            count += 1;
+        }
+        count
+    }
+}
+
+/// Prompt asking a model to fold `messages` into the running summary
+pub fn summary_prompt(previous: Option<&str>, messages: &[Message]) -> String {
+    let mut prompt = String::from(
+        "Condense the conversation below into a compact memory for an assistant that will \
+         continue it. Keep facts, decisions, names, numbers, open questions and user \
+         preferences; drop pleasantries. Reply with the memory only, in short plain sentences.\n\n",
+    );
+    if let Some(previous) = previous {
+        prompt.push_str("Existing memory:\n");
+        prompt.push_str(previous);
+        prompt.push_str("\n\n");
+    }
+    prompt.push_str("Conversation:\n");
+    for message in messages {
+        prompt.push_str(&format!("{}: {}\n", message.role.as_str(), message.content));
+    }
+    prompt.push_str("\nUpdated memory:");
+    prompt
+}
+
+/// Generation settings for summaries within a context budget
+pub fn summary_config(budget: usize) -> GenerationConfig {
+    GenerationConfig {
+        temperature: Some(0.2),
+        max_tokens: Some(summary_budget(budget) as u32),
+        stop_sequences: None,
+        top_p: Some(0.9),
+        frequency_penalty: None,
+        presence_penalty: None,
+        reasoning_effort: None,
+        thinking_budget: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MessageRole;
+    use chrono::Utc;
+
+    fn message(id: i64, content: &str) -> Message {
+        Message {
+            id,
+            session_id: "s".to_string(),
+            role: MessageRole::User,
+            content: content.to_string(),
+            created_at: Utc::now(),
+            author: None,
+        }
+    }
+
+    fn turns(count: i64, chars: usize) -> Vec<Message> {
+        (1..=count)
+            .map(|id| message(id, &"x".repeat(chars)))
+            .collect()
+    }
+
+    #[test]
+    fn windows_within_budget_are_left_alone() {
+        let window = ContextWindow {
+            recent: turns(4, 40),
+            ..Default::default()
+        };
+        // 4 turns of 12 tokens against 75 of 100
+        assert_eq!(window.tokens(), 48);
+        assert_eq!(window.plan_compaction(100, 2), 0);
+    }
+
+    #[test]
+    fn oldest_turns_are_folded_until_the_rest_fits() {
+        let window = ContextWindow {
+            summary: None,
+            pinned: vec![message(1, &"p".repeat(40))],
+            recent: turns(10, 400),
+        };
+        // 1000 tokens: 750 for the conversation, 187 of it for the summary
+        // and 12 for the pin, leaving 551 for turns of 102 tokens each
+        assert_eq!(window.plan_compaction(1000, 2), 5);
+
+        // Recent turns are kept even when they alone exceed the budget
+        assert_eq!(window.plan_compaction(100, 2), 8);
+        assert_eq!(window.plan_compaction(100, 20), 0);
+    }
+
+    #[test]
+    fn summary_prompt_extends_the_previous_memory() {
+        let prompt = summary_prompt(Some("User likes tea."), &[message(3, "and scones")]);
+        assert!(prompt.contains("Existing memory:\nUser likes tea."));
+        assert!(prompt.contains("user: and scones"));
+        assert!(prompt.ends_with("Updated memory:"));
+    }
+}
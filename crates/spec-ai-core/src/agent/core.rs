@@ -2,30 +2,65 @@
 //!
 //! The heart of the agent system - orchestrates reasoning, memory, and model interaction.
 
-use crate::agent::model::{GenerationConfig, ModelProvider};
+use crate::agent::approval::{request_approval, ApprovalDecision, ToolApprovalSender};
+use crate::agent::budget::{
+    budget_exceeded_response, BudgetExceeded, RunBudget, RunSpend, BUDGET_EXCEEDED_REASON,
+};
+use crate::agent::context::{self, ContextWindow};
+use crate::agent::factory;
+use crate::agent::inbox::AgentInbox;
+use crate::agent::interrupt::{interrupted_response, RunInterrupt};
+use crate::agent::model::{
+    text_only, DeltaStream, GenerationConfig, ModelProvider, ModelResponse, OutputSchema,
+    StreamDelta, TokenUsage,
+};
 pub use crate::agent::output::{
     AgentOutput, GraphDebugInfo, GraphDebugNode, MemoryRecallMatch, MemoryRecallStats,
-    MemoryRecallStrategy, ToolInvocation,
+    MemoryRecallStrategy, ModelUsage, PolicyOutcome, PolicyViolation, SessionUsage, ToolInvocation,
 };
-use crate::config::agent::AgentProfile;
+use crate::agent::providers::TranscriptProvider;
+use crate::agent::question::{ask_user, UserQuestion, UserQuestionSender, ASK_USER_TOOL};
+use crate::agent::replay::{RunTranscript, TranscriptEvent};
+use crate::agent::response_cache::ResponseCache;
+use crate::agent::structured;
+use crate::agent::subagent::{
+    child_session_id, run_subagent, SpawnAgentsArgs, SubAgentReport, SubAgentSpec, MAX_SUBAGENTS,
+    SPAWN_AGENTS_TOOL,
+};
+use crate::config::agent::{AgentProfile, PolicyMode};
+use crate::config::{GenerationPreset, ModelConfig, PricingConfig};
 use crate::embeddings::EmbeddingsClient;
-use crate::persistence::Persistence;
-use crate::policy::{PolicyDecision, PolicyEngine};
+use crate::events::{self, DelegationTarget, Event, EventKind};
+use crate::persistence::{BranchRecord, CheckpointRecord, Persistence};
+use crate::policy::{CapsuleBudget, PolicyCapsule, PolicyDecision, PolicyEngine};
+use crate::prompts::{self, PromptLibrary};
 use crate::spec::AgentSpec;
+use crate::telemetry::{self, Span, SpanContext, SpanKind};
+use crate::tools::builtin::{bind_issuer, API_TOKEN_TOOL};
 use crate::tools::{ToolRegistry, ToolResult};
-use crate::types::{Message, MessageRole};
+use crate::types::{MemoryVector, Message, MessageRole};
+use crate::vectors::{
+    VectorFilter, VectorMatch, VectorPoint, VectorStore, GRAPH_COLLECTION, MEMORY_COLLECTION,
+};
 use crate::SYNC_GRAPH_NAMESPACE;
 use anyhow::{Context, Result};
 use chrono::Utc;
+use futures::stream::{self, StreamExt};
 use futures::Stream;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use serde_json::{json, Value};
 use spec_ai_knowledge_graph::{EdgeType, NodeType, TraversalDirection};
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::path::Path;
 use std::pin::Pin;
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
 const DEFAULT_MAIN_TEMPERATURE: f32 = 0.7;
@@ -33,11 +68,48 @@ const DEFAULT_TOP_P: f32 = 0.9;
 const DEFAULT_FAST_TEMPERATURE: f32 = 0.3;
 const DEFAULT_ESCALATION_THRESHOLD: f32 = 0.6;
 
+/// Argument naming the path each file system tool works on; without it the
+/// tool works in the current directory
+const TOOL_PATH_ARGUMENTS: &[(&str, &str)] = &[
+    ("file_read", "path"),
+    ("file_write", "path"),
+    ("apply_patch", "root"),
+    ("file_extract", "path"),
+    ("grep", "path"),
+    ("rg", "path"),
+    ("code_search", "root"),
+    ("bash", "working_dir"),
+    ("shell", "working_dir"),
+    ("task_start", "working_dir"),
+];
+
+/// A running tool call
+type ToolExecution<'a> = Pin<Box<dyn Future<Output = Result<ToolResult>> + Send + 'a>>;
+
+/// Response being streamed, until it is finalized
+struct StreamingRun {
+    run_id: String,
+    prompt: String,
+    /// Usage the provider reported in the stream, if any
+    usage: Arc<Mutex<Option<TokenUsage>>>,
+    turn_span: Span,
+    model_span: Span,
+}
+
 struct RecallResult {
     messages: Vec<Message>,
     stats: Option<MemoryRecallStats>,
 }
 
+/// Answer from the fast model
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FastAnswer {
+    /// Concise result
+    answer: String,
+    /// Confidence in the answer, from 0 to 1
+    confidence: f32,
+}
+
 // Entity extracted from text
 struct ExtractedEntity {
     name: String,
@@ -84,6 +156,8 @@ pub struct AgentCore {
     embeddings_client: Option<EmbeddingsClient>,
     /// Persistence layer
     persistence: Persistence,
+    /// Where embeddings are mirrored for recall and graph similarity search
+    vector_store: Option<Arc<dyn VectorStore>>,
     /// Current session ID
     session_id: String,
     /// Optional logical agent name from the registry
@@ -98,6 +172,39 @@ pub struct AgentCore {
     tool_permission_cache: Arc<RwLock<HashMap<String, bool>>>,
     /// Whether to tailor prompts for speech playback
     speak_responses: bool,
+    /// Front end that approves blocked tool calls, instead of `prompt_user`
+    tool_approvals: Option<ToolApprovalSender>,
+    /// Front end that answers `ask_user` questions
+    user_questions: Option<UserQuestionSender>,
+    /// Person typing the input, recorded on user messages
+    author: Option<String>,
+    /// Token prices used to estimate the cost of each message
+    pricing: PricingConfig,
+    /// Token usage of the main model since the session started
+    session_usage: SessionUsage,
+    streaming_run: Option<StreamingRun>,
+    /// Cache of main model responses, when the profile enables one
+    response_cache: Option<ResponseCache>,
+    /// Tool calls made since a policy capsule was applied
+    capsule_tool_calls: AtomicU32,
+    /// Session token total when a policy capsule was applied
+    capsule_tokens_from: u64,
+    /// Interrupts the step in progress
+    interrupt: RunInterrupt,
+    /// Events from outside the conversation waiting for a turn
+    inbox: AgentInbox,
+    /// Run id for the next step, when the caller chose one
+    next_run_id: Option<String>,
+    /// Templates for the system prompt and the agent's internal prompts
+    prompts: PromptLibrary,
+    /// Records model and tool calls, or replays them instead of making them
+    transcript: Option<Arc<RunTranscript>>,
+    /// Span of the turn in progress, parent of its model and tool spans
+    trace: SpanContext,
+    /// Limits on what one step may spend
+    run_budget: RunBudget,
+    /// Generation preset applied on top of the profile, with its name
+    preset: Option<(String, GenerationPreset)>,
 }
 
 impl AgentCore {
@@ -130,12 +237,24 @@ impl AgentCore {
             );
         }
 
+        let response_cache = profile
+            .response_cache_ttl_secs
+            .map(|ttl| ResponseCache::new(persistence.clone(), Duration::from_secs(ttl)));
+        let prompts = PromptLibrary::new().with_persistence(persistence.clone());
+        let run_budget = RunBudget::from_profile(&profile);
+        // Presets from the config are resolved by the builder
+        let preset = profile
+            .preset
+            .as_ref()
+            .and_then(|name| GenerationPreset::builtin(name).map(|preset| (name.clone(), preset)));
+
         Self {
             profile,
             provider,
             fast_provider: None,
             embeddings_client,
             persistence,
+            vector_store: None,
             session_id,
             agent_name,
             conversation_history: Vec::new(),
@@ -143,7 +262,56 @@ impl AgentCore {
             policy_engine,
             tool_permission_cache: Arc::new(RwLock::new(HashMap::new())),
             speak_responses,
+            tool_approvals: None,
+            user_questions: None,
+            author: None,
+            pricing: PricingConfig::default(),
+            session_usage: SessionUsage::default(),
+            streaming_run: None,
+            response_cache,
+            capsule_tool_calls: AtomicU32::new(0),
+            capsule_tokens_from: 0,
+            interrupt: RunInterrupt::new(),
+            inbox: AgentInbox::new(),
+            next_run_id: None,
+            prompts,
+            transcript: None,
+            trace: SpanContext::default(),
+            run_budget,
+            preset,
+        }
+        .with_restored_usage()
+    }
+
+    /// Reload the session's token totals from persisted usage records
+    fn with_restored_usage(mut self) -> Self {
+        self.restore_usage();
+        self
+    }
+
+    fn restore_usage(&mut self) {
+        let mut session_usage = SessionUsage::default();
+        match self.persistence.session_usage(&self.session_id) {
+            Ok(records) => {
+                for record in records {
+                    let cost = self.pricing.cost(
+                        &record.model,
+                        record.prompt_tokens,
+                        record.completion_tokens,
+                    );
+                    session_usage.add(ModelUsage {
+                        provider: record.provider,
+                        model: record.model,
+                        calls: record.calls,
+                        prompt_tokens: record.prompt_tokens,
+                        completion_tokens: record.completion_tokens,
+                        cost,
+                    });
+                }
+            }
+            Err(err) => warn!("Failed to load session token usage: {}", err),
         }
+        self.session_usage = session_usage;
     }
 
     /// Set the fast model provider for hierarchical reasoning
@@ -152,6 +320,30 @@ impl AgentCore {
         self
     }
 
+    /// Cache main model responses for `ttl`, or stop caching with `None`
+    pub fn with_response_cache(mut self, ttl: Option<Duration>) -> Self {
+        self.response_cache = ttl.map(|ttl| ResponseCache::new(self.persistence.clone(), ttl));
+        self
+    }
+
+    /// Look up prompt templates in `prompts`
+    pub fn with_prompt_library(mut self, prompts: PromptLibrary) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Mirror embeddings into `store` and recall memories from it
+    pub fn with_vector_store(mut self, store: Arc<dyn VectorStore>) -> Self {
+        self.vector_store = Some(store);
+        self
+    }
+
+    /// Set the token prices used to estimate costs
+    pub fn with_pricing(mut self, pricing: PricingConfig) -> Self {
+        self.pricing = pricing;
+        self.with_restored_usage()
+    }
+
     /// Set a new session ID and clear conversation history
     pub fn with_session(mut self, session_id: String) -> Self {
         let (session_id, rewrote_namespace) = Self::sanitize_session_id(session_id);
@@ -164,12 +356,39 @@ impl AgentCore {
         self.session_id = session_id;
         self.conversation_history.clear();
         self.tool_permission_cache = Arc::new(RwLock::new(HashMap::new()));
-        self
+        self.with_restored_usage()
     }
 
     /// Execute a single interaction step
     pub async fn run_step(&mut self, input: &str) -> Result<AgentOutput> {
-        let run_id = format!("run-{}", Utc::now().timestamp_micros());
+        let mut span = self.turn_span();
+        self.trace = span.context();
+        let result = self.run_turn(input).await;
+        self.trace = SpanContext::default();
+
+        match &result {
+            Ok(output) => {
+                span.set_attribute(telemetry::RUN_ID, output.run_id.clone());
+                span.set_attribute(telemetry::TOOL_CALLS, output.tool_invocations.len());
+                if let Some(reason) = &output.finish_reason {
+                    span.set_attribute(telemetry::GEN_AI_RESPONSE_FINISH_REASON, reason.clone());
+                }
+                if let Some(usage) = &output.token_usage {
+                    record_token_usage(&mut span, usage);
+                }
+            }
+            Err(err) => span.set_error(err),
+        }
+        result
+    }
+
+    /// The turn behind `run_step`, within its span
+    async fn run_turn(&mut self, input: &str) -> Result<AgentOutput> {
+        let run_id = self
+            .next_run_id
+            .take()
+            .unwrap_or_else(|| format!("run-{}", Utc::now().timestamp_micros()));
+        let cancel = self.interrupt.begin();
         let total_timer = Instant::now();
 
         // Step 1: Recall relevant memories
@@ -178,10 +397,13 @@ impl AgentCore {
         self.log_timing("run_step.recall_memories", recall_timer);
         let recalled_messages = recall_result.messages;
         let recall_stats = recall_result.stats;
+        let context_window = self.prepare_context_window().await?;
 
         // Step 2: Build prompt with context
         let prompt_timer = Instant::now();
-        let mut prompt = self.build_prompt(input, &recalled_messages).await?;
+        let mut prompt = self
+            .build_prompt(input, &recalled_messages, context_window.as_ref())
+            .await?;
         self.log_timing("run_step.build_prompt", prompt_timer);
 
         // Step 3: Store user message
@@ -196,11 +418,15 @@ impl AgentCore {
         // Step 4: Agent loop with tool execution
         let mut tool_invocations = Vec::new();
         let mut final_response = String::new();
-        let mut token_usage = None;
+        let mut token_usage: Option<TokenUsage> = None;
+        let mut turn_usage = SessionUsage::default();
         let mut finish_reason = None;
+        let mut policy_violations: Vec<PolicyViolation> = Vec::new();
         let mut auto_response: Option<String> = None;
         let mut reasoning: Option<String> = None;
         let mut reasoning_summary: Option<String> = None;
+        let mut spend = RunSpend::default();
+        let mut budget_exceeded: Option<BudgetExceeded> = None;
 
         // Attempt to auto-satisfy simple goals before invoking the model
         if let Some(goal) = goal_context.as_mut() {
@@ -208,7 +434,10 @@ impl AgentCore {
                 if let Some((tool_name, tool_args)) =
                     Self::infer_goal_tool_action(goal.text.as_str())
                 {
-                    if self.is_tool_allowed(&tool_name).await {
+                    if self.is_tool_allowed(&tool_name).await
+                        && self.run_budget.tool_calls_left(&spend, 1) > 0
+                    {
+                        spend.tool_calls += 1;
                         let tool_timer = Instant::now();
                         let tool_result = self.execute_tool(&run_id, &tool_name, &tool_args).await;
                         self.log_timing("run_step.tool_execution.auto", tool_timer);
@@ -290,16 +519,68 @@ impl AgentCore {
             final_response = fast_text;
             finish_reason = Some(format!("fast_model ({:.0}%)", (confidence * 100.0).round()));
         } else {
+            let turn_deadline = self
+                .profile
+                .turn_timeout_secs
+                .map(|secs| Instant::now() + Duration::from_secs(secs));
             // Allow up to 5 iterations to handle tool calls
             for _iteration in 0..5 {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                if turn_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    warn!(
+                        "Turn deadline exceeded after {} tool calls; returning the last response",
+                        tool_invocations.len()
+                    );
+                    finish_reason = Some("turn_deadline".to_string());
+                    break;
+                }
+                if let Some(exceeded) = self.run_budget.check_usage(&spend) {
+                    warn!("Run {} stopped: {}", run_id, exceeded);
+                    budget_exceeded = Some(exceeded);
+                    break;
+                }
+                // Delegated work and sub-agents stop once their tokens are spent
+                if let Err(err) = self.ensure_capsule_tokens() {
+                    warn!("{}", err);
+                    if final_response.is_empty() {
+                        final_response = err.to_string();
+                    }
+                    finish_reason = Some("budget_exhausted".to_string());
+                    break;
+                }
+
                 // Generate response using model
                 let generation_config = self.build_generation_config();
                 let model_timer = Instant::now();
-                let response_result = self.provider.generate(&prompt, &generation_config).await;
+                let mut model_span = model_call_span(&self.trace, self.provider.as_ref());
+                let response_result = tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => None,
+                    result = self.generate_cached(&prompt, &generation_config) => Some(result),
+                };
                 self.log_timing("run_step.main_model_call", model_timer);
-                let response = response_result.context("Failed to generate response from model")?;
-
-                token_usage = response.usage;
+                let Some(response_result) = response_result else {
+                    model_span.set_error("interrupted");
+                    break;
+                };
+                let response = response_result
+                    .inspect_err(|err| model_span.set_error(err))
+                    .context("Failed to generate response from model")?;
+                record_model_response(&mut model_span, &response);
+                model_span.end();
+
+                if let Some(usage) = response.usage {
+                    let cost = self.record_usage(&run_id, &response.model, &usage);
+                    turn_usage.record(self.provider.kind().as_str(), &response.model, &usage, cost);
+                    spend.tokens +=
+                        u64::from(usage.prompt_tokens) + u64::from(usage.completion_tokens);
+                    spend.cost += cost.unwrap_or_default();
+                    token_usage
+                        .get_or_insert_with(TokenUsage::default)
+                        .add(&usage);
+                }
                 finish_reason = response.finish_reason.clone();
                 final_response = response.content.clone();
                 reasoning = response.reasoning.clone();
@@ -321,6 +602,7 @@ impl AgentCore {
                             || reason_lower.contains("end_turn")
                             || reason_lower.contains("complete")
                             || reason_lower == "length"
+                            || reason_lower == "max_tokens"
                     });
 
                     // If no goal constraint requires tools, terminate early
@@ -335,59 +617,182 @@ impl AgentCore {
                 }
 
                 if !sdk_tool_calls.is_empty() {
-                    // Process all tool calls from SDK response
-                    for tool_call in sdk_tool_calls {
+                    // Check permissions one call at a time, since denied calls
+                    // may prompt the user
+                    let mut denied: HashMap<usize, ToolInvocation> = HashMap::new();
+                    for (index, tool_call) in sdk_tool_calls.iter().enumerate() {
                         let tool_name = &tool_call.function_name;
                         let tool_args = &tool_call.arguments;
 
+                        if let Some(rule) = self.audited_denial(tool_name) {
+                            warn!("Tool '{}' runs in audit mode: {}", tool_name, rule);
+                            policy_violations.push(PolicyViolation {
+                                rule,
+                                action: "tool_call".to_string(),
+                                resource: tool_name.clone(),
+                                outcome: PolicyOutcome::Audited,
+                                timestamp: Utc::now(),
+                            });
+                        }
+
                         // Check if tool is allowed
                         if !self.is_tool_allowed(tool_name).await {
+                            let rule = self.tool_denial_reason(tool_name);
+
+                            if self.profile.policy_mode == PolicyMode::Enforce {
+                                let error_msg =
+                                    format!("Tool '{}' is not allowed: {}", tool_name, rule);
+                                warn!("{}", error_msg);
+                                policy_violations.push(PolicyViolation {
+                                    rule,
+                                    action: "tool_call".to_string(),
+                                    resource: tool_name.clone(),
+                                    outcome: PolicyOutcome::Denied,
+                                    timestamp: Utc::now(),
+                                });
+                                denied.insert(
+                                    index,
+                                    ToolInvocation {
+                                        name: tool_name.clone(),
+                                        arguments: tool_args.clone(),
+                                        success: false,
+                                        output: None,
+                                        error: Some(error_msg),
+                                        data: None,
+                                    },
+                                );
+                                continue;
+                            }
+
                             warn!(
                                 "Tool '{}' is not allowed by agent policy - prompting user",
                                 tool_name
                             );
 
                             // Prompt user for permission
-                            match self.prompt_for_tool_permission(tool_name).await {
+                            match self
+                                .prompt_for_tool_permission(tool_name, tool_args, &rule)
+                                .await
+                            {
                                 Ok(true) => {
                                     info!("User granted permission for tool '{}'", tool_name);
+                                    policy_violations.push(PolicyViolation {
+                                        rule,
+                                        action: "tool_call".to_string(),
+                                        resource: tool_name.clone(),
+                                        outcome: PolicyOutcome::Escalated,
+                                        timestamp: Utc::now(),
+                                    });
                                     // Permission granted, continue to execute the tool below
                                 }
                                 Ok(false) => {
+                                    policy_violations.push(PolicyViolation {
+                                        rule,
+                                        action: "tool_call".to_string(),
+                                        resource: tool_name.clone(),
+                                        outcome: PolicyOutcome::Denied,
+                                        timestamp: Utc::now(),
+                                    });
                                     let error_msg =
                                         format!("Tool '{}' was denied by user", tool_name);
                                     warn!("{}", error_msg);
-                                    tool_invocations.push(ToolInvocation {
-                                        name: tool_name.clone(),
-                                        arguments: tool_args.clone(),
-                                        success: false,
-                                        output: None,
-                                        error: Some(error_msg),
-                                    });
+                                    denied.insert(
+                                        index,
+                                        ToolInvocation {
+                                            name: tool_name.clone(),
+                                            arguments: tool_args.clone(),
+                                            success: false,
+                                            output: None,
+                                            error: Some(error_msg),
+                                            data: None,
+                                        },
+                                    );
                                     continue;
                                 }
                                 Err(e) => {
+                                    policy_violations.push(PolicyViolation {
+                                        rule,
+                                        action: "tool_call".to_string(),
+                                        resource: tool_name.clone(),
+                                        outcome: PolicyOutcome::Denied,
+                                        timestamp: Utc::now(),
+                                    });
                                     let error_msg = format!(
                                         "Failed to get user permission for tool '{}': {}",
                                         tool_name, e
                                     );
                                     warn!("{}", error_msg);
-                                    tool_invocations.push(ToolInvocation {
-                                        name: tool_name.clone(),
-                                        arguments: tool_args.clone(),
-                                        success: false,
-                                        output: None,
-                                        error: Some(error_msg),
-                                    });
+                                    denied.insert(
+                                        index,
+                                        ToolInvocation {
+                                            name: tool_name.clone(),
+                                            arguments: tool_args.clone(),
+                                            success: false,
+                                            output: None,
+                                            error: Some(error_msg),
+                                            data: None,
+                                        },
+                                    );
                                     continue;
                                 }
                             }
                         }
+                    }
 
-                        // Execute tool
-                        let tool_timer = Instant::now();
-                        let exec_result = self.execute_tool(&run_id, tool_name, tool_args).await;
-                        self.log_timing("run_step.tool_execution.sdk", tool_timer);
+                    // Refuse the calls beyond the run's tool call budget
+                    let requested: Vec<usize> = (0..sdk_tool_calls.len())
+                        .filter(|index| !denied.contains_key(index))
+                        .collect();
+                    let affordable = self.run_budget.tool_calls_left(&spend, requested.len());
+                    spend.tool_calls += affordable as u32;
+                    if let Some(exceeded) = self
+                        .run_budget
+                        .check_tool_calls(&spend)
+                        .filter(|_| affordable < requested.len())
+                    {
+                        warn!("Run {} stopped: {}", run_id, exceeded);
+                        for &index in &requested[affordable..] {
+                            let tool_call = &sdk_tool_calls[index];
+                            denied.insert(
+                                index,
+                                ToolInvocation {
+                                    name: tool_call.function_name.clone(),
+                                    arguments: tool_call.arguments.clone(),
+                                    success: false,
+                                    output: None,
+                                    error: Some(exceeded.to_string()),
+                                    data: None,
+                                },
+                            );
+                        }
+                        budget_exceeded = Some(exceeded);
+                    }
+
+                    // Run the permitted calls concurrently
+                    let permitted: Vec<(&str, &Value)> = sdk_tool_calls
+                        .iter()
+                        .enumerate()
+                        .filter(|(index, _)| !denied.contains_key(index))
+                        .map(|(_, call)| (call.function_name.as_str(), &call.arguments))
+                        .collect();
+                    let tool_timer = Instant::now();
+                    let mut results = self
+                        .execute_tool_batch(&run_id, &permitted, turn_deadline)
+                        .await
+                        .into_iter();
+                    self.log_timing("run_step.tool_execution.sdk", tool_timer);
+
+                    // Feed results back in the order the model asked for them
+                    for (index, tool_call) in sdk_tool_calls.iter().enumerate() {
+                        let tool_name = &tool_call.function_name;
+                        let tool_args = &tool_call.arguments;
+                        if let Some(invocation) = denied.remove(&index) {
+                            tool_invocations.push(invocation);
+                            continue;
+                        }
+                        let Some(exec_result) = results.next() else {
+                            break;
+                        };
                         match exec_result {
                             Ok(result) => {
                                 let invocation = ToolInvocation::from_result(
@@ -445,11 +850,15 @@ impl AgentCore {
                                     success: false,
                                     output: None,
                                     error: Some(error_msg),
+                                    data: None,
                                 });
                             }
                         }
                     }
 
+                    if budget_exceeded.is_some() {
+                        break;
+                    }
                     // Continue loop to process tool results
                     continue;
                 }
@@ -468,6 +877,15 @@ impl AgentCore {
             }
         }
 
+        if cancel.is_cancelled() {
+            info!("Run {} was interrupted", run_id);
+            final_response = interrupted_response(&final_response);
+            finish_reason = Some("interrupted".to_string());
+        } else if let Some(exceeded) = &budget_exceeded {
+            final_response = budget_exceeded_response(&final_response, exceeded);
+            finish_reason = Some(BUDGET_EXCEEDED_REASON.to_string());
+        }
+
         // Step 5: Store assistant response with reasoning if available
         let store_assistant_timer = Instant::now();
         let response_message_id = self
@@ -506,6 +924,7 @@ impl AgentCore {
             role: MessageRole::User,
             content: input.to_string(),
             created_at: Utc::now(),
+            author: self.author.clone(),
         });
 
         self.conversation_history.push(Message {
@@ -514,6 +933,7 @@ impl AgentCore {
             role: MessageRole::Assistant,
             content: final_response.clone(),
             created_at: Utc::now(),
+            author: None,
         });
 
         // Step 7: Re-evaluate knowledge graph to recommend next action
@@ -549,6 +969,7 @@ impl AgentCore {
                 role: MessageRole::System,
                 content: system_content,
                 created_at: Utc::now(),
+                author: None,
             });
         }
 
@@ -561,11 +982,37 @@ impl AgentCore {
         };
 
         self.log_timing("run_step.total", total_timer);
+        if events::enabled() {
+            for violation in &policy_violations {
+                events::emit(
+                    self.event(EventKind::PolicyDecision {
+                        action: violation.action.clone(),
+                        resource: violation.resource.clone(),
+                        outcome: violation.outcome,
+                        rule: violation.rule.clone(),
+                    })
+                    .with_run(&run_id)
+                    .with_timestamp(violation.timestamp),
+                );
+            }
+            events::emit(
+                self.event(EventKind::RunCompleted {
+                    finish_reason: finish_reason.clone(),
+                    tool_calls: tool_invocations.len(),
+                    total_tokens: turn_usage.total_tokens(),
+                    cost: turn_usage.cost(),
+                    duration_ms: total_timer.elapsed().as_millis() as u64,
+                })
+                .with_run(&run_id),
+            );
+        }
 
         Ok(AgentOutput {
             response: final_response,
             response_message_id: Some(response_message_id),
             token_usage,
+            cost: turn_usage.cost(),
+            session_usage: self.session_usage.clone(),
             tool_invocations,
             finish_reason,
             recall_stats,
@@ -574,6 +1021,8 @@ impl AgentCore {
             reasoning,
             reasoning_summary,
             graph_debug,
+            policy_violations,
+            budget_exceeded,
         })
     }
 
@@ -597,12 +1046,30 @@ impl AgentCore {
         &mut self,
         input: &str,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        let deltas = self.run_step_streaming_deltas(input).await?;
+        Ok(text_only(deltas))
+    }
+
+    /// Execute a streaming interaction step, delivering structured deltas.
+    ///
+    /// Like `run_step_streaming`, but tool calls arrive as the model
+    /// generates them, so callers can show them early. Usage reported in the
+    /// stream replaces the estimate when the step is finalized. Tools the
+    /// model asks for are not run by a streaming step.
+    pub async fn run_step_streaming_deltas(&mut self, input: &str) -> Result<DeltaStream> {
+        self.ensure_capsule_tokens()?;
+        let cancel = self.interrupt.begin();
+        let mut turn_span = self.turn_span();
+
         // Step 1: Recall relevant memories
         let recall_result = self.recall_memories(input).await?;
         let recalled_messages = recall_result.messages;
+        let context_window = self.prepare_context_window().await?;
 
         // Step 2: Build prompt with context
-        let prompt = self.build_prompt(input, &recalled_messages).await?;
+        let prompt = self
+            .build_prompt(input, &recalled_messages, context_window.as_ref())
+            .await?;
 
         // Step 3: Store user message
         let user_message_id = self.store_message(MessageRole::User, input).await?;
@@ -614,23 +1081,71 @@ impl AgentCore {
             role: MessageRole::User,
             content: input.to_string(),
             created_at: Utc::now(),
+            author: self.author.clone(),
         });
 
         // Step 4: Start streaming from the provider
         let generation_config = self.build_generation_config();
+        let mut model_span = model_call_span(&turn_span.context(), self.provider.as_ref());
         let stream = self
-            .provider
-            .stream(&prompt, &generation_config)
+            .main_model()
+            .stream_deltas(&prompt, &generation_config)
             .await
+            .inspect_err(|err| {
+                model_span.set_error(err);
+                turn_span.set_error(err);
+            })
             .context("Failed to start streaming response from model")?;
+        let run_id = self
+            .next_run_id
+            .take()
+            .unwrap_or_else(|| format!("run-{}", Utc::now().timestamp_micros()));
+        turn_span.set_attribute(telemetry::RUN_ID, run_id.clone());
+        let usage = Arc::new(Mutex::new(None));
+        self.streaming_run = Some(StreamingRun {
+            run_id,
+            prompt,
+            usage: Arc::clone(&usage),
+            turn_span,
+            model_span,
+        });
 
-        Ok(stream)
+        let stream = stream.inspect(move |delta| {
+            if let Ok(StreamDelta::Usage(reported)) = delta {
+                *usage.lock().unwrap_or_else(|p| p.into_inner()) = Some(*reported);
+            }
+        });
+        // An interruption ends the stream early; finalizing stores what came so far
+        Ok(Box::pin(stream.take_until(cancel.cancelled_owned())))
     }
 
     /// Finalize a streaming step by storing the assistant message.
     ///
-    /// Call this after consuming the stream from `run_step_streaming`.
+    /// Call this after consuming the stream from `run_step_streaming` or
+    /// `run_step_streaming_deltas`.
     pub async fn finalize_streaming_step(&mut self, content: &str) -> Result<i64> {
+        let interrupted;
+        let content = if self.interrupt.is_interrupted() {
+            interrupted = interrupted_response(content);
+            interrupted.as_str()
+        } else {
+            content
+        };
+
+        // Estimate usage from the prompt and response when the stream
+        // did not report it
+        if let Some(mut run) = self.streaming_run.take() {
+            let reported = *run.usage.lock().unwrap_or_else(|p| p.into_inner());
+            let usage = reported.unwrap_or_else(|| TokenUsage::estimate(&run.prompt, content));
+            let model = self.provider.model_name().to_string();
+            self.record_usage(&run.run_id, &model, &usage);
+            record_token_usage(&mut run.model_span, &usage);
+            record_token_usage(&mut run.turn_span, &usage);
+            if self.interrupt.is_interrupted() {
+                run.model_span.set_error("interrupted");
+            }
+        }
+
         // Store the assistant message
         let message_id = self.store_message(MessageRole::Assistant, content).await?;
 
@@ -641,11 +1156,63 @@ impl AgentCore {
             role: MessageRole::Assistant,
             content: content.to_string(),
             created_at: Utc::now(),
+            author: None,
         });
 
         Ok(message_id)
     }
 
+    /// Generate a typed value from a one-off prompt with the main model
+    ///
+    /// The prompt is sent as is, without recalled memories or history, and
+    /// nothing is stored in the conversation. Token usage is recorded like a
+    /// regular step.
+    pub async fn generate_structured<T>(&mut self, prompt: &str) -> Result<T>
+    where
+        T: JsonSchema + DeserializeOwned,
+    {
+        let config = self.build_generation_config();
+        let provider = self.main_model();
+        let output = structured::generate_structured::<T>(
+            provider.as_ref(),
+            prompt,
+            &config,
+            structured::DEFAULT_MAX_ATTEMPTS,
+        )
+        .await?;
+
+        if let Some(usage) = &output.usage {
+            let run_id = format!("run-{}", Utc::now().timestamp_micros());
+            self.record_usage(&run_id, &output.model, usage);
+        }
+        Ok(output.value)
+    }
+
+    /// Generate with the main model, serving identical requests from the
+    /// response cache when it is enabled
+    async fn generate_cached(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        self.ensure_capsule_tokens()?;
+        // Cache hits would leave gaps in a transcript
+        let cache = self
+            .response_cache
+            .as_ref()
+            .filter(|_| self.transcript.is_none());
+        let Some(cache) = cache else {
+            return self.main_model().generate(prompt, config).await;
+        };
+        let key = ResponseCache::key(self.provider.as_ref(), prompt, &self.tool_registry, config);
+        if let Some(response) = cache.get(&key) {
+            return Ok(response);
+        }
+        let response = self.provider.generate(prompt, config).await?;
+        cache.put(&key, &response);
+        Ok(response)
+    }
+
     /// Build generation configuration from profile
     fn build_generation_config(&self) -> GenerationConfig {
         let temperature = match self.profile.temperature {
@@ -670,13 +1237,24 @@ impl AgentCore {
             Some(DEFAULT_TOP_P)
         };
 
-        GenerationConfig {
+        let config = GenerationConfig {
             temperature,
             max_tokens: self.profile.max_context_tokens.map(|t| t as u32),
             stop_sequences: None,
             top_p,
             frequency_penalty: None,
             presence_penalty: None,
+            reasoning_effort: None,
+            thinking_budget: None,
+        };
+        match &self.preset {
+            Some((_, preset)) => factory::apply_preset(
+                config,
+                preset,
+                self.provider.kind(),
+                self.provider.model_name(),
+            ),
+            None => config,
         }
     }
 
@@ -716,17 +1294,15 @@ impl AgentCore {
     /// Summarize reasoning using the fast model
     async fn summarize_reasoning(&self, reasoning: &str) -> Option<String> {
         // Only summarize if we have a fast provider and reasoning is substantial
-        let fast_provider = self.fast_provider.as_ref()?;
+        let fast_provider = self.fast_model()?;
 
         if reasoning.len() < 50 {
             // Too short to summarize, just return it as-is
             return Some(reasoning.to_string());
         }
 
-        let summary_prompt = format!(
-            "Summarize the following reasoning in 1-2 concise sentences that explain the thought process:\n\n{}\n\nSummary:",
-            reasoning
-        );
+        let summary_prompt =
+            self.render_prompt(prompts::REASONING_SUMMARY, &[("reasoning", reasoning)]);
 
         let config = GenerationConfig {
             temperature: Some(0.3),
@@ -735,6 +1311,8 @@ impl AgentCore {
             top_p: Some(0.9),
             frequency_penalty: None,
             presence_penalty: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
 
         let timer = Instant::now();
@@ -757,6 +1335,246 @@ impl AgentCore {
         }
     }
 
+    /// Token budget of the main model's context window
+    fn context_budget(&self) -> usize {
+        self.profile
+            .max_context_tokens
+            .unwrap_or_else(|| context::default_budget(self.provider.kind()))
+    }
+
+    /// Conversation to include in the prompt when context compaction is
+    /// enabled, summarizing the oldest turns first if it outgrew the budget
+    ///
+    /// Summaries come from the fast model, or the main model when there is
+    /// none. A failed summary is logged and the full conversation is used.
+    async fn prepare_context_window(&mut self) -> Result<Option<ContextWindow>> {
+        if !self.profile.context_compaction {
+            return Ok(None);
+        }
+
+        let summary = self.persistence.context_summary(&self.session_id)?;
+        let through = summary.as_ref().map_or(0, |s| s.through_message_id);
+        let pinned = self.persistence.list_pinned_messages(&self.session_id)?;
+        let pinned_ids: HashSet<i64> = pinned.iter().map(|m| m.id).collect();
+        let recent = self
+            .persistence
+            .list_messages_after(&self.session_id, through)?
+            .into_iter()
+            .filter(|m| !pinned_ids.contains(&m.id))
+            .collect();
+        let mut window = ContextWindow {
+            summary: summary.map(|s| s.summary),
+            pinned,
+            recent,
+        };
+
+        let budget = self.context_budget();
+        let count = window.plan_compaction(budget, self.profile.context_keep_recent);
+        if count == 0 {
+            return Ok(Some(window));
+        }
+
+        let (provider, is_main) = match self.fast_model() {
+            Some(fast) => (fast, false),
+            None => (self.main_model(), true),
+        };
+        let prompt = context::summary_prompt(window.summary.as_deref(), &window.recent[..count]);
+        let timer = Instant::now();
+        let response = provider
+            .generate(&prompt, &context::summary_config(budget))
+            .await;
+        self.log_timing("run_step.compact_context", timer);
+
+        match response {
+            Ok(response) if !response.content.trim().is_empty() => {
+                // Session usage tracks the main model only
+                if let Some(usage) = response.usage.as_ref().filter(|_| is_main) {
+                    let run_id = format!("run-{}", Utc::now().timestamp_micros());
+                    self.record_usage(&run_id, &response.model, usage);
+                }
+                let summary = response.content.trim().to_string();
+                let through = window.recent[count - 1].id;
+                self.persistence
+                    .save_context_summary(&self.session_id, &summary, through)?;
+                debug!(
+                    "Compacted {} messages through {} into the context summary",
+                    count, through
+                );
+                window.summary = Some(summary);
+                window.recent.drain(..count);
+            }
+            Ok(_) => warn!("Context compaction returned an empty summary; keeping all turns"),
+            Err(err) => warn!("Failed to compact conversation context: {}", err),
+        }
+
+        Ok(Some(window))
+    }
+
+    /// Keep a message of this session out of context compaction
+    pub fn pin_message(&self, message_id: i64) -> Result<()> {
+        self.persistence.pin_message(&self.session_id, message_id)
+    }
+
+    /// Let a pinned message be compacted again, returning whether it was pinned
+    pub fn unpin_message(&self, message_id: i64) -> Result<bool> {
+        self.persistence.unpin_message(&self.session_id, message_id)
+    }
+
+    /// Name the conversation as it is now so it can be rolled back to later,
+    /// replacing an older checkpoint of the same name
+    pub fn save_checkpoint(&self, name: &str) -> Result<CheckpointRecord> {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("Checkpoint names must not be empty");
+        }
+        let message_id = self.latest_message_id()?;
+        self.persistence
+            .save_conversation_checkpoint(&self.session_id, name, message_id)?;
+        self.persistence
+            .conversation_checkpoint(&self.session_id, name)?
+            .context("Checkpoint was not saved")
+    }
+
+    /// Checkpoints of this session, oldest first
+    pub fn checkpoints(&self) -> Result<Vec<CheckpointRecord>> {
+        self.persistence
+            .list_conversation_checkpoints(&self.session_id)
+    }
+
+    /// Continue in a new branch holding the whole conversation so far
+    ///
+    /// Branches are sessions of their own, named after the session they came
+    /// from (`main.1`, `main.2`, `main.1.1`, ...). The current session is left
+    /// as it is and can be switched back to with [`Self::switch_branch`].
+    pub fn branch(&mut self) -> Result<BranchRecord> {
+        let through = self.latest_message_id()?;
+        self.fork(through)
+    }
+
+    /// Continue in a new branch from a checkpoint, keeping the current thread intact
+    pub fn rollback(&mut self, checkpoint: &str) -> Result<BranchRecord> {
+        let checkpoint = self
+            .persistence
+            .conversation_checkpoint(&self.session_id, checkpoint.trim())?
+            .with_context(|| {
+                format!(
+                    "No checkpoint named '{}' in session '{}'",
+                    checkpoint.trim(),
+                    self.session_id
+                )
+            })?;
+        self.fork(checkpoint.message_id)
+    }
+
+    /// Continue in a new branch that stops just before one of this session's
+    /// user messages, so an edited version can be sent in its place
+    pub fn branch_before(&mut self, message_id: i64) -> Result<BranchRecord> {
+        let message = self
+            .persistence
+            .get_message(message_id)?
+            .filter(|message| message.session_id == self.session_id)
+            .with_context(|| {
+                format!(
+                    "Message {} is not part of session '{}'",
+                    message_id, self.session_id
+                )
+            })?;
+        if message.role != MessageRole::User {
+            anyhow::bail!("Only user messages can be edited and resent");
+        }
+        self.fork(message_id - 1)
+    }
+
+    /// Session the conversation started in, before any branching
+    pub fn conversation_root(&self) -> Result<String> {
+        let mut root = self.session_id.clone();
+        let mut seen = HashSet::new();
+        while let Some(branch) = self.persistence.session_branch(&root)? {
+            if !seen.insert(root.clone()) {
+                break;
+            }
+            root = branch.parent_session_id;
+        }
+        Ok(root)
+    }
+
+    /// Every branch of this conversation, depth first from [`Self::conversation_root`]
+    pub fn branches(&self) -> Result<Vec<BranchRecord>> {
+        let mut branches = Vec::new();
+        // Stacked newest first so the oldest branch is visited first
+        let mut pending = self.persistence.list_branches(&self.conversation_root()?)?;
+        pending.reverse();
+        while let Some(branch) = pending.pop() {
+            let mut children = self.persistence.list_branches(&branch.session_id)?;
+            children.reverse();
+            pending.extend(children);
+            branches.push(branch);
+        }
+        Ok(branches)
+    }
+
+    /// Continue in another branch of this conversation
+    pub fn switch_branch(&mut self, session_id: &str) -> Result<()> {
+        let known = session_id == self.conversation_root()?
+            || self
+                .branches()?
+                .iter()
+                .any(|branch| branch.session_id == session_id);
+        if !known {
+            anyhow::bail!(
+                "'{}' is not a branch of this conversation; see /branch",
+                session_id
+            );
+        }
+        self.enter_session(session_id.to_string());
+        Ok(())
+    }
+
+    /// Copy the conversation through `through` into a new branch and continue there
+    fn fork(&mut self, through: i64) -> Result<BranchRecord> {
+        let branch_id = self.next_branch_id()?;
+        self.persistence
+            .fork_session(&self.session_id, &branch_id, through)?;
+        let branch = self
+            .persistence
+            .session_branch(&branch_id)?
+            .context("Branch was not recorded")?;
+        info!(
+            "Branched session '{}' into '{}' after message {}",
+            self.session_id, branch_id, through
+        );
+        self.enter_session(branch_id);
+        Ok(branch)
+    }
+
+    /// First unused `<session>.<n>` name
+    fn next_branch_id(&self) -> Result<String> {
+        let mut n = self.persistence.list_branches(&self.session_id)?.len() + 1;
+        loop {
+            let candidate = format!("{}.{}", self.session_id, n);
+            let taken = self.persistence.session_branch(&candidate)?.is_some()
+                || !self.persistence.list_messages(&candidate, 1)?.is_empty();
+            if !taken {
+                return Ok(candidate);
+            }
+            n += 1;
+        }
+    }
+
+    /// Id of the session's newest message, 0 when it has none
+    fn latest_message_id(&self) -> Result<i64> {
+        let latest = self.persistence.list_messages(&self.session_id, 1)?;
+        Ok(latest.last().map_or(0, |message| message.id))
+    }
+
+    /// Continue in another existing session
+    fn enter_session(&mut self, session_id: String) {
+        self.session_id = session_id;
+        self.conversation_history.clear();
+        self.tool_permission_cache = Arc::new(RwLock::new(HashMap::new()));
+        self.restore_usage();
+    }
+
     /// Recall relevant memories for the given input
     async fn recall_memories(&self, query: &str) -> Result<RecallResult> {
         const RECENT_CONTEXT: i64 = 2;
@@ -851,11 +1669,7 @@ impl AgentCore {
             match embed_result {
                 Ok(mut embeddings) => match embeddings.pop() {
                     Some(query_embedding) if !query_embedding.is_empty() => {
-                        let recalled = self.persistence.recall_top_k(
-                            &self.session_id,
-                            &query_embedding,
-                            self.profile.memory_k,
-                        )?;
+                        let recalled = self.recall_top_k(&query_embedding).await?;
 
                         let mut matches = Vec::new();
                         let mut semantic_context = Vec::new();
@@ -888,6 +1702,7 @@ impl AgentCore {
                                         role: MessageRole::User, // Transcriptions are user input
                                         content: format!("[Transcription] {}", transcription_text),
                                         created_at: memory.created_at,
+                                        author: None,
                                     };
 
                                     matches.push(MemoryRecallMatch {
@@ -947,6 +1762,7 @@ impl AgentCore {
                                                         role: MessageRole::System,
                                                         content: graph_content,
                                                         created_at: Utc::now(),
+                                                        author: None,
                                                     };
 
                                                     graph_expanded.push(graph_msg);
@@ -1041,19 +1857,29 @@ impl AgentCore {
     }
 
     /// Build the prompt from system prompt, context, and user input
-    async fn build_prompt(&self, input: &str, context_messages: &[Message]) -> Result<String> {
+    ///
+    /// With a context window, its summary and pinned messages come first and
+    /// its recent turns follow any recalled messages it does not already hold.
+    async fn build_prompt(
+        &self,
+        input: &str,
+        context_messages: &[Message],
+        window: Option<&ContextWindow>,
+    ) -> Result<String> {
         let mut prompt = String::new();
 
         // Add system prompt if configured
-        if let Some(system_prompt) = &self.profile.prompt {
+        if let Some(system_prompt) = self.system_prompt()? {
             prompt.push_str("System: ");
-            prompt.push_str(system_prompt);
+            prompt.push_str(&system_prompt);
             prompt.push_str("\n\n");
         }
 
         // Tailor for speech playback when enabled
         if self.speak_responses {
-            prompt.push_str("System: Speech mode is enabled; respond with concise, natural sentences suitable for text-to-speech. Avoid markdown/code fences and keep the reply brief.\n\n");
+            prompt.push_str("System: ");
+            prompt.push_str(&self.render_prompt(prompts::SPEECH_MODE, &[]));
+            prompt.push_str("\n\n");
         }
 
         // Add tool instructions
@@ -1076,10 +1902,34 @@ impl AgentCore {
             prompt.push('\n');
         }
 
+        // Add compacted conversation memory
+        if let Some(window) = window {
+            if let Some(summary) = &window.summary {
+                prompt.push_str("Summary of earlier conversation:\n");
+                prompt.push_str(summary);
+                prompt.push_str("\n\n");
+            }
+            if !window.pinned.is_empty() {
+                prompt.push_str("Pinned messages:\n");
+                for msg in &window.pinned {
+                    prompt.push_str(&format!("{}: {}\n", msg.role.as_str(), msg.content));
+                }
+                prompt.push('\n');
+            }
+        }
+
         // Add conversation context
-        if !context_messages.is_empty() {
+        let conversation: Vec<&Message> = match window {
+            Some(window) => context_messages
+                .iter()
+                .filter(|msg| !window.contains(msg.id))
+                .chain(&window.recent)
+                .collect(),
+            None => context_messages.iter().collect(),
+        };
+        if !conversation.is_empty() {
             prompt.push_str("Previous conversation:\n");
-            for msg in context_messages {
+            for msg in conversation {
                 prompt.push_str(&format!("{}: {}\n", msg.role.as_str(), msg.content));
             }
             prompt.push('\n');
@@ -1093,6 +1943,50 @@ impl AgentCore {
         Ok(prompt)
     }
 
+    /// The profile's system prompt, rendered from its template if it names one
+    pub fn system_prompt(&self) -> Result<Option<String>> {
+        let Some(reference) = &self.profile.prompt_template else {
+            return Ok(self.profile.prompt.clone());
+        };
+        let vars = HashMap::from([
+            (
+                "agent".to_string(),
+                self.agent_name
+                    .clone()
+                    .unwrap_or_else(|| "agent".to_string()),
+            ),
+            ("session_id".to_string(), self.session_id.clone()),
+            ("model".to_string(), self.provider.model_name().to_string()),
+            (
+                "date".to_string(),
+                Utc::now().format("%Y-%m-%d").to_string(),
+            ),
+        ]);
+        self.prompts
+            .render(reference, &self.profile, &vars)
+            .map(Some)
+    }
+
+    /// Render one of the agent's internal prompts
+    ///
+    /// An override or saved template that fails to render is logged and the
+    /// built-in default is used instead.
+    fn render_prompt(&self, name: &str, vars: &[(&str, &str)]) -> String {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        self.prompts
+            .render(name, &self.profile, &vars)
+            .or_else(|err| {
+                warn!("{:#}; using the built-in prompt", err);
+                prompts::builtin(name)
+                    .context("No built-in prompt")?
+                    .render(&vars)
+            })
+            .unwrap_or_default()
+    }
+
     /// Store a message in persistence
     async fn store_message(&self, role: MessageRole, content: &str) -> Result<i64> {
         self.store_message_with_reasoning(role, content, None).await
@@ -1105,12 +1999,24 @@ impl AgentCore {
         content: &str,
         reasoning: Option<&str>,
     ) -> Result<i64> {
+        let author = match role {
+            MessageRole::User => self.author.as_deref(),
+            _ => None,
+        };
         let message_id = self
             .persistence
-            .insert_message(&self.session_id, role.clone(), content)
+            .insert_message_by(&self.session_id, role.clone(), content, author)
             .context("Failed to store message")?;
+        if events::enabled() {
+            events::emit(self.event(EventKind::Message {
+                role: role.as_str(),
+                message_id,
+                content: content.to_string(),
+            }));
+        }
 
         let mut embedding_id = None;
+        let mut stored_embedding = None;
 
         if let Some(client) = &self.embeddings_client {
             if !content.trim().is_empty() {
@@ -1128,6 +2034,9 @@ impl AgentCore {
                                 ) {
                                     Ok(emb_id) => {
                                         embedding_id = Some(emb_id);
+                                        self.index_memory(emb_id, Some(message_id), &embedding)
+                                            .await;
+                                        stored_embedding = Some(embedding);
                                     }
                                     Err(err) => {
                                         warn!(
@@ -1151,13 +2060,19 @@ impl AgentCore {
 
         // If auto_graph is enabled, create graph nodes and edges
         if self.profile.enable_graph && self.profile.auto_graph {
-            self.build_graph_for_message(message_id, role, content, embedding_id, reasoning)?;
+            let node_id =
+                self.build_graph_for_message(message_id, role, content, embedding_id, reasoning)?;
+            if let Some(embedding) = stored_embedding {
+                self.index_graph_node(node_id, NodeType::Message, message_id, embedding)
+                    .await;
+            }
         }
 
         Ok(message_id)
     }
 
-    /// Build graph nodes and edges for a new message
+    /// Build graph nodes and edges for a new message, returning the id of
+    /// the message node
     fn build_graph_for_message(
         &self,
         message_id: i64,
@@ -1165,7 +2080,7 @@ impl AgentCore {
         content: &str,
         embedding_id: Option<i64>,
         reasoning: Option<&str>,
-    ) -> Result<()> {
+    ) -> Result<i64> {
         use serde_json::json;
 
         // Create a node for the message
@@ -1320,15 +2235,114 @@ impl AgentCore {
             }
         }
 
-        Ok(())
+        Ok(message_node_id)
     }
 
-    fn create_goal_context(
-        &self,
-        message_id: i64,
-        input: &str,
-        persist: bool,
-    ) -> Result<GoalContext> {
+    /// Mirror a stored embedding into the vector store, if there is one
+    async fn index_memory(&self, embedding_id: i64, message_id: Option<i64>, embedding: &[f32]) {
+        let Some(store) = &self.vector_store else {
+            return;
+        };
+        let mut point = VectorPoint::new(embedding_id as u64, embedding.to_vec())
+            .with_field("session_id", self.session_id.as_str())
+            .with_field("created_at", Utc::now().to_rfc3339());
+        if let Some(message_id) = message_id {
+            point = point.with_field("message_id", message_id);
+        }
+        if let Err(err) = store.upsert(MEMORY_COLLECTION, vec![point]).await {
+            warn!(
+                "Failed to index embedding {} in the {} vector store: {}",
+                embedding_id,
+                store.backend(),
+                err
+            );
+        }
+    }
+
+    /// Mirror the embedding of a graph node into the vector store, so
+    /// similar nodes can be found
+    async fn index_graph_node(
+        &self,
+        node_id: i64,
+        node_type: NodeType,
+        message_id: i64,
+        embedding: Vec<f32>,
+    ) {
+        let Some(store) = &self.vector_store else {
+            return;
+        };
+        let point = VectorPoint::new(node_id as u64, embedding)
+            .with_field("session_id", self.session_id.as_str())
+            .with_field("node_type", node_type.as_str())
+            .with_field("message_id", message_id);
+        if let Err(err) = store.upsert(GRAPH_COLLECTION, vec![point]).await {
+            warn!(
+                "Failed to index graph node {} in the {} vector store: {}",
+                node_id,
+                store.backend(),
+                err
+            );
+        }
+    }
+
+    /// The session's memories closest to `query`, best first
+    ///
+    /// Searches the vector store when there is one. Sessions with nothing in
+    /// the store, such as those stored before it was enabled, and failed
+    /// searches fall back to scanning the embeddings in the database.
+    async fn recall_top_k(&self, query: &[f32]) -> Result<Vec<(MemoryVector, f32)>> {
+        let k = self.profile.memory_k;
+        if let Some(store) = &self.vector_store {
+            let filter = VectorFilter::none().eq("session_id", self.session_id.as_str());
+            match store.knn(MEMORY_COLLECTION, query, k, &filter).await {
+                Ok(matches) if !matches.is_empty() => {
+                    return Ok(matches
+                        .into_iter()
+                        .map(|found| {
+                            let score = found.score;
+                            (self.memory_from_match(found), score)
+                        })
+                        .collect());
+                }
+                Ok(_) => {}
+                Err(err) => warn!(
+                    "Recall from the {} vector store failed, scanning stored embeddings: {}",
+                    store.backend(),
+                    err
+                ),
+            }
+        }
+        self.persistence.recall_top_k(&self.session_id, query, k)
+    }
+
+    fn memory_from_match(&self, found: VectorMatch) -> MemoryVector {
+        MemoryVector {
+            id: found.id as i64,
+            session_id: found
+                .payload
+                .get("session_id")
+                .and_then(Value::as_str)
+                .unwrap_or(&self.session_id)
+                .to_string(),
+            message_id: found.payload.get("message_id").and_then(Value::as_i64),
+            // Callers only need the ids; the vector stays in the store
+            embedding: Vec::new(),
+            created_at: found
+                .payload
+                .get("created_at")
+                .and_then(Value::as_str)
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                .map(|at| at.with_timezone(&Utc))
+                .unwrap_or_else(Utc::now),
+        }
+    }
+
+    fn create_goal_context(
+        &self,
+        message_id: i64,
+        input: &str,
+        persist: bool,
+    ) -> Result<GoalContext> {
         let requires_tool = Self::goal_requires_tool(input);
         let node_id = if self.profile.enable_graph {
             if persist {
@@ -1391,12 +2405,15 @@ impl AgentCore {
             let timestamp = Utc::now().to_rfc3339();
             let mut properties = json!({
                 "tool": tool_name,
-                "arguments": args,
+                "arguments": self.tool_registry.redact(tool_name, args),
                 "success": result.success,
                 "output_preview": preview_text(&result.output),
                 "error": result.error,
                 "timestamp": timestamp,
             });
+            if let Some(data) = &result.data {
+                properties["data"] = data.clone();
+            }
 
             let mut prompt_payload: Option<Value> = None;
             if tool_name == "prompt_user" && result.success {
@@ -1858,11 +2875,9 @@ impl AgentCore {
     /// Use fast model for preliminary reasoning tasks
     async fn fast_reasoning(&self, task: &str, input: &str) -> Result<(String, f32)> {
         let total_timer = Instant::now();
-        let result = if let Some(ref fast_provider) = self.fast_provider {
-            let prompt = format!(
-                "You are a fast specialist model that assists a more capable agent.\nTask: {}\nInput: {}\n\nRespond with two lines:\nAnswer: <concise result>\nConfidence: <0-1 decimal>",
-                task, input
-            );
+        let result = if let Some(fast_provider) = self.fast_model() {
+            let prompt =
+                self.render_prompt(prompts::FAST_REASONING, &[("task", task), ("input", input)]);
 
             let fast_temperature = if self.profile.fast_model_temperature.is_finite() {
                 self.profile.fast_model_temperature.clamp(0.0, 2.0)
@@ -1881,17 +2896,31 @@ impl AgentCore {
                 top_p: Some(DEFAULT_TOP_P),
                 frequency_penalty: None,
                 presence_penalty: None,
+                reasoning_effort: None,
+                thinking_budget: None,
             };
 
             let call_timer = Instant::now();
-            let response_result = fast_provider.generate(&prompt, &config).await;
+            let mut model_span = model_call_span(&self.trace, fast_provider.as_ref());
+            let schema = OutputSchema::of::<FastAnswer>();
+            let response_result = fast_provider.generate_json(&prompt, &schema, &config).await;
             self.log_timing("fast_reasoning.generate", call_timer);
-            let response = response_result?;
-
-            let confidence = Self::parse_confidence(&response.content).unwrap_or(0.7);
-            let cleaned = Self::strip_fast_answer(&response.content);
-
-            Ok((cleaned, confidence))
+            let response = response_result.inspect_err(|err| model_span.set_error(err))?;
+            record_model_response(&mut model_span, &response);
+            model_span.end();
+
+            // Small models often ignore the schema, so fall back to reading
+            // "Answer:" and "Confidence:" lines
+            match structured::parse_json::<FastAnswer>(&response.content) {
+                Ok(fast) if fast.confidence.is_finite() => Ok((
+                    fast.answer.trim().to_string(),
+                    fast.confidence.clamp(0.0, 1.0),
+                )),
+                _ => {
+                    let confidence = Self::parse_confidence(&response.content).unwrap_or(0.7);
+                    Ok((Self::strip_fast_answer(&response.content), confidence))
+                }
+            }
         } else {
             // No fast model configured
             Ok((String::new(), 0.0))
@@ -2018,7 +3047,9 @@ impl AgentCore {
             tool_name, decision
         );
 
-        let allowed = matches!(decision, PolicyDecision::Allow);
+        // Audit mode runs what the policy engine denies
+        let allowed = matches!(decision, PolicyDecision::Allow)
+            || self.profile.policy_mode == PolicyMode::Audit;
         self.tool_permission_cache
             .write()
             .await
@@ -2026,8 +3057,38 @@ impl AgentCore {
         allowed
     }
 
+    /// Describe why a tool is blocked, for surfacing policy violations to the user
+    fn tool_denial_reason(&self, tool_name: &str) -> String {
+        if !self.profile.is_tool_allowed(tool_name) {
+            return format!("agent profile does not allow tool '{}'", tool_name);
+        }
+        let agent_name = self.agent_name.as_deref().unwrap_or("agent");
+        match self.policy_engine.check(agent_name, "tool_call", tool_name) {
+            PolicyDecision::Deny(reason) => reason,
+            PolicyDecision::Allow => format!("tool '{}' is not permitted", tool_name),
+        }
+    }
+
+    /// Why the policy engine denies a tool that audit mode lets run
+    fn audited_denial(&self, tool_name: &str) -> Option<String> {
+        if self.profile.policy_mode != PolicyMode::Audit || !self.profile.is_tool_allowed(tool_name)
+        {
+            return None;
+        }
+        let agent_name = self.agent_name.as_deref().unwrap_or("agent");
+        match self.policy_engine.check(agent_name, "tool_call", tool_name) {
+            PolicyDecision::Deny(reason) => Some(reason),
+            PolicyDecision::Allow => None,
+        }
+    }
+
     /// Prompt user for permission to use a tool
-    async fn prompt_for_tool_permission(&mut self, tool_name: &str) -> Result<bool> {
+    async fn prompt_for_tool_permission(
+        &mut self,
+        tool_name: &str,
+        tool_args: &Value,
+        rule: &str,
+    ) -> Result<bool> {
         info!("Requesting user permission for tool: {}", tool_name);
 
         // Get the tool to show its description
@@ -2037,6 +3098,34 @@ impl AgentCore {
             .map(|t| t.description().to_string())
             .unwrap_or_else(|| "No description available".to_string());
 
+        if let Some(approvals) = self.tool_approvals.clone() {
+            let decision = request_approval(
+                &approvals,
+                tool_name,
+                tool_description,
+                tool_args,
+                rule.to_string(),
+            )
+            .await?;
+            info!("User chose {:?} for tool '{}'", decision, tool_name);
+            return Ok(match decision {
+                ApprovalDecision::AllowOnce => true,
+                ApprovalDecision::AllowAlways => {
+                    self.add_allowed_tool(tool_name).await;
+                    // Skip the policy check for the rest of the session
+                    self.tool_permission_cache
+                        .write()
+                        .await
+                        .insert(tool_name.to_string(), true);
+                    true
+                }
+                ApprovalDecision::Deny => {
+                    self.add_denied_tool(tool_name).await;
+                    false
+                }
+            });
+        }
+
         // Use prompt_user tool to ask for permission
         let prompt_args = json!({
             "prompt": format!(
@@ -2125,12 +3214,173 @@ impl AgentCore {
         tool_name: &str,
         args: &Value,
     ) -> Result<ToolResult> {
-        // Execute the tool (convert execution failures into ToolResult failures)
-        let exec_result = self.tool_registry.execute(tool_name, args.clone()).await;
-        let result = match exec_result {
-            Ok(res) => res,
-            Err(err) => ToolResult::failure(err.to_string()),
+        self.execute_tool_until(run_id, tool_name, args, None).await
+    }
+
+    /// Execute tool calls, running neighbouring calls to read-only tools up to
+    /// `tool_parallelism` at once
+    ///
+    /// Any other call runs alone, after the calls before it finished and
+    /// before the calls after it start, so a write is never raced by the
+    /// reads or writes the model asked for around it. Results are returned in
+    /// the order of `calls`, whichever finishes first. Calls still running at
+    /// `deadline` are cancelled.
+    async fn execute_tool_batch(
+        &self,
+        run_id: &str,
+        calls: &[(&str, &Value)],
+        deadline: Option<Instant>,
+    ) -> Vec<Result<ToolResult>> {
+        let read_only = |name: &str| {
+            self.tool_registry
+                .get(name)
+                .is_some_and(|tool| tool.read_only())
+        };
+        let mut results = Vec::with_capacity(calls.len());
+        let mut rest = calls;
+        while let Some((tool_name, _)) = rest.first() {
+            let len = if read_only(tool_name) {
+                rest.iter().take_while(|(name, _)| read_only(name)).count()
+            } else {
+                1
+            };
+            let (group, tail) = rest.split_at(len);
+            let finished: Vec<_> = stream::iter(group.iter().map(|(tool_name, args)| {
+                self.execute_tool_until(run_id, tool_name, args, deadline)
+            }))
+            .buffered(self.profile.tool_parallelism.max(1))
+            .collect()
+            .await;
+            results.extend(finished);
+            rest = tail;
+        }
+        results
+    }
+
+    /// Execute a tool within the profile's tool timeout, giving up at
+    /// `deadline`, and log the result
+    async fn execute_tool_until(
+        &self,
+        run_id: &str,
+        tool_name: &str,
+        args: &Value,
+        deadline: Option<Instant>,
+    ) -> Result<ToolResult> {
+        let mut span = self
+            .trace
+            .child(format!("execute_tool {}", tool_name), SpanKind::Internal);
+        span.set_attribute(telemetry::GEN_AI_OPERATION_NAME, "execute_tool");
+        span.set_attribute(telemetry::GEN_AI_TOOL_NAME, tool_name);
+        let started = Instant::now();
+        let replayed = self
+            .transcript
+            .as_ref()
+            .and_then(|transcript| transcript.replay_tool(tool_name, args));
+        let result = if let Some(reason) = self.capsule_violation(tool_name, args) {
+            warn!("{}", reason);
+            ToolResult::failure(reason)
+        } else if let Some(result) = replayed {
+            result
+        } else {
+            let mut exec_args = args.clone();
+            if tool_name == "delegate_task" {
+                // Delegated work may never exceed what this agent may do
+                let capsule = self.delegation_capsule().await;
+                if let Value::Object(map) = &mut exec_args {
+                    map.insert("policy_capsule".to_string(), json!(capsule));
+                }
+            }
+            if tool_name == API_TOKEN_TOOL {
+                // The audit log names the agent, not whoever the model claims to be
+                let issuer = self.agent_name.as_deref().unwrap_or("agent");
+                bind_issuer(&mut exec_args, issuer, Some(&self.session_id));
+            }
+            // The tighter of the tool timeout and the time left in the turn,
+            // and whether it is the turn. People answering questions get as
+            // long as the turn allows.
+            let tool_timeout = self
+                .profile
+                .tool_timeout_secs
+                .filter(|_| tool_name != ASK_USER_TOOL)
+                .map(Duration::from_secs);
+            let turn_left = deadline.map(|at| at.saturating_duration_since(Instant::now()));
+            let limit = match (tool_timeout, turn_left) {
+                (Some(timeout), Some(left)) if left < timeout => Some((left, true)),
+                (Some(timeout), _) => Some((timeout, false)),
+                (None, Some(left)) => Some((left, true)),
+                (None, None) => None,
+            };
+
+            // Execute the tool (convert execution failures into ToolResult failures)
+            let execution: ToolExecution<'_> = if tool_name == SPAWN_AGENTS_TOOL {
+                // Sub-agents are run by the agent itself, not the registry
+                self.spawn_for_tool(exec_args)
+            } else if tool_name == ASK_USER_TOOL {
+                self.ask_for_tool(exec_args)
+            } else {
+                Box::pin(self.tool_registry.execute_in_session(
+                    &self.session_id,
+                    tool_name,
+                    exec_args,
+                ))
+            };
+            let limited = async {
+                match limit {
+                    Some((limit, turn)) => {
+                        tokio::time::timeout(limit, execution).await.map_err(|_| {
+                            if turn {
+                                format!(
+                                    "Tool '{}' was cancelled because the turn deadline passed",
+                                    tool_name
+                                )
+                            } else {
+                                format!("Tool '{}' timed out after {:?}", tool_name, limit)
+                            }
+                        })
+                    }
+                    None => Ok(execution.await),
+                }
+            };
+            // Dropping an interrupted tool kills the processes it spawned
+            let cancel = self.interrupt.token();
+            let outcome = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => Err(format!("Tool '{}' was interrupted", tool_name)),
+                outcome = limited => outcome,
+            };
+            let result = match outcome {
+                Ok(Ok(res)) => res,
+                Ok(Err(err)) => ToolResult::failure(err.to_string()),
+                Err(reason) => ToolResult::failure(reason),
+            };
+            if let Some(transcript) = &self.transcript {
+                transcript.push(TranscriptEvent::Tool {
+                    name: tool_name.to_string(),
+                    args: args.clone(),
+                    result: result.clone(),
+                });
+            }
+            result
         };
+        span.set_attribute(telemetry::TOOL_SUCCESS, result.success);
+        if let Some(error) = &result.error {
+            span.set_error(error);
+        }
+        span.end();
+        // Secrets in the arguments stay out of events and the tool log
+        let recorded_args = self.tool_registry.redact(tool_name, args);
+        if events::enabled() {
+            events::emit(
+                self.event(EventKind::ToolCall {
+                    tool: tool_name.to_string(),
+                    arguments: recorded_args.clone(),
+                    success: result.success,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    error: result.error.clone(),
+                })
+                .with_run(run_id),
+            );
+        }
 
         // Log to persistence
         let result_json = serde_json::json!({
@@ -2146,7 +3396,7 @@ impl AgentCore {
                 self.agent_name.as_deref().unwrap_or("unknown"),
                 run_id,
                 tool_name,
-                args,
+                &recorded_args,
                 &result_json,
                 result.success,
                 error_str,
@@ -2156,6 +3406,278 @@ impl AgentCore {
         Ok(result)
     }
 
+    /// Why the policy capsule forbids a tool call, if it does
+    ///
+    /// Capsule limits are hard: unlike profile and policy denials they are
+    /// not escalated to the user. Calls that pass are charged to the
+    /// capsule's tool call budget.
+    fn capsule_violation(&self, tool_name: &str, args: &Value) -> Option<String> {
+        let capsule = self.policy_engine.capsule()?;
+        if !capsule.permits_tool(tool_name) {
+            return Some(format!(
+                "Tool '{}' is outside the capsule delegated by '{}'",
+                tool_name, capsule.issuer
+            ));
+        }
+
+        if let Some((_, argument)) = TOOL_PATH_ARGUMENTS
+            .iter()
+            .find(|(tool, _)| *tool == tool_name)
+        {
+            let path = args.get(*argument).and_then(Value::as_str).unwrap_or(".");
+            if !capsule.permits_path(path) {
+                return Some(format!(
+                    "Path '{}' is outside the capsule delegated by '{}'",
+                    path, capsule.issuer
+                ));
+            }
+        }
+
+        if let Some(max) = capsule.budget.max_tool_calls {
+            if self.capsule_tool_calls.fetch_add(1, Ordering::SeqCst) >= max {
+                return Some(format!("Delegated budget of {} tool calls is used up", max));
+            }
+        }
+        None
+    }
+
+    /// Fail once the model has used the policy capsule's token budget
+    fn ensure_capsule_tokens(&self) -> Result<()> {
+        let Some(max) = self
+            .policy_engine
+            .capsule()
+            .and_then(|capsule| capsule.budget.max_tokens)
+        else {
+            return Ok(());
+        };
+        let used = self
+            .session_usage
+            .total_tokens()
+            .saturating_sub(self.capsule_tokens_from);
+        if used >= max {
+            anyhow::bail!(
+                "Delegated budget of {} tokens is used up ({} used)",
+                max,
+                used
+            );
+        }
+        Ok(())
+    }
+
+    /// Confine the agent to a policy capsule received with delegated work
+    ///
+    /// The capsule is intersected with the agent's own policy and with any
+    /// capsule it already runs under. Its budget counts from this point.
+    pub fn with_policy_capsule(mut self, capsule: PolicyCapsule) -> Self {
+        let engine = (*self.policy_engine).clone().with_capsule(capsule);
+        self.policy_engine = Arc::new(engine);
+        self.tool_permission_cache = Arc::new(RwLock::new(HashMap::new()));
+        self.capsule_tool_calls = AtomicU32::new(0);
+        self.capsule_tokens_from = self.session_usage.total_tokens();
+        self
+    }
+
+    /// Capsule the agent runs under, if it is executing delegated work
+    pub fn policy_capsule(&self) -> Option<&PolicyCapsule> {
+        self.policy_engine.capsule()
+    }
+
+    /// Capsule attached to work this agent delegates: the tools it may call
+    /// and, when it runs under a capsule itself, that capsule's paths and
+    /// remaining budget
+    pub async fn delegation_capsule(&self) -> PolicyCapsule {
+        let issuer = self.agent_name.as_deref().unwrap_or("agent");
+        let mut tools = Vec::new();
+        for tool in self.tool_registry.list() {
+            if self.is_tool_allowed(tool).await {
+                tools.push(tool.to_string());
+            }
+        }
+        tools.sort();
+        let mut capsule = PolicyCapsule::new(issuer).with_tools(tools);
+
+        if let Some(own) = self.policy_engine.capsule() {
+            let used_calls = self.capsule_tool_calls.load(Ordering::SeqCst);
+            let used_tokens = self
+                .session_usage
+                .total_tokens()
+                .saturating_sub(self.capsule_tokens_from);
+            let mut remaining = own.clone();
+            remaining.budget = CapsuleBudget {
+                max_tool_calls: own
+                    .budget
+                    .max_tool_calls
+                    .map(|max| max.saturating_sub(used_calls)),
+                max_tokens: own
+                    .budget
+                    .max_tokens
+                    .map(|max| max.saturating_sub(used_tokens)),
+            };
+            capsule = capsule.intersect(&remaining);
+        }
+        capsule
+    }
+
+    /// Run sub-tasks on child agents and merge their reports into this session
+    ///
+    /// Children run concurrently, up to `tool_parallelism` at once, and are
+    /// interrupted along with this agent. Reports come back in the order of
+    /// `specs`.
+    pub async fn spawn_subagents(
+        &mut self,
+        specs: Vec<SubAgentSpec>,
+    ) -> Result<Vec<SubAgentReport>> {
+        let cancel = self.interrupt.begin();
+        let reports = self.run_subagents(specs, cancel).await?;
+        for report in &reports {
+            if let Some(id) = report.message_id {
+                self.conversation_history.push(Message {
+                    id,
+                    session_id: self.session_id.clone(),
+                    role: MessageRole::System,
+                    content: report.to_message(),
+                    created_at: Utc::now(),
+                    author: None,
+                });
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Run a `spawn_agents` tool call
+    ///
+    /// Boxed because the children's steps contain this call in their type.
+    fn spawn_for_tool(&self, args: Value) -> ToolExecution<'_> {
+        Box::pin(async move {
+            let args: SpawnAgentsArgs =
+                serde_json::from_value(args).context("Failed to parse spawn_agents arguments")?;
+            match self
+                .run_subagents(args.agents, self.interrupt.token())
+                .await
+            {
+                Ok(reports) => Ok(ToolResult::success(serde_json::to_string(&reports)?)),
+                Err(err) => Ok(ToolResult::failure(format!("{:#}", err))),
+            }
+        })
+    }
+
+    /// Execute an `ask_user` call by asking the front end
+    fn ask_for_tool(&self, args: Value) -> ToolExecution<'_> {
+        Box::pin(async move {
+            let question = match UserQuestion::from_args(&args) {
+                Ok(question) => question,
+                Err(err) => return Ok(ToolResult::failure(format!("{:#}", err))),
+            };
+            let Some(questions) = &self.user_questions else {
+                return Ok(ToolResult::failure(
+                    "No one is available to answer questions in this session; \
+                     continue with your best judgment or ask in your reply",
+                ));
+            };
+            info!("Asking the user: {}", question.question);
+            let answer = ask_user(questions, question).await?;
+            Ok(ToolResult::success(answer.to_tool_output()).with_data(json!(answer)))
+        })
+    }
+
+    /// Run child agents until they finish or `cancel` fires, storing each
+    /// report in this session
+    async fn run_subagents(
+        &self,
+        specs: Vec<SubAgentSpec>,
+        cancel: CancellationToken,
+    ) -> Result<Vec<SubAgentReport>> {
+        if specs.is_empty() {
+            anyhow::bail!("No sub-agents to spawn");
+        }
+        if specs.len() > MAX_SUBAGENTS {
+            anyhow::bail!(
+                "At most {} sub-agents can be spawned at once",
+                MAX_SUBAGENTS
+            );
+        }
+        let names: HashSet<&str> = specs.iter().map(|spec| spec.name.as_str()).collect();
+        if names.len() < specs.len() {
+            anyhow::bail!("Sub-agent names must be unique");
+        }
+
+        let parent = self.delegation_capsule().await;
+        let spawned_at = Utc::now().timestamp_micros();
+        let children: Vec<_> = specs
+            .into_iter()
+            .map(|spec| {
+                let child = self.subagent(&spec, &parent, spawned_at);
+                events::emit(self.event(EventKind::Delegation {
+                    target: DelegationTarget::Subagent,
+                    to: spec.name.clone(),
+                    task: spec.task.clone(),
+                    task_id: Some(child.session_id.clone()),
+                }));
+                run_subagent(spec, child, cancel.clone())
+            })
+            .collect();
+        let mut reports: Vec<SubAgentReport> = stream::iter(children)
+            .buffered(self.profile.tool_parallelism.max(1))
+            .collect()
+            .await;
+
+        for report in &mut reports {
+            match self
+                .store_message(MessageRole::System, &report.to_message())
+                .await
+            {
+                Ok(id) => report.message_id = Some(id),
+                Err(err) => warn!(
+                    "Failed to store the report of sub-agent '{}': {}",
+                    report.name, err
+                ),
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Child agent for `spec`, confined to what `parent` allows
+    fn subagent(&self, spec: &SubAgentSpec, parent: &PolicyCapsule, spawned_at: i64) -> AgentCore {
+        let mut requested = PolicyCapsule::new(parent.issuer.clone()).with_budget(spec.budget);
+        if let Some(tools) = &spec.tools {
+            requested = requested.with_tools(tools.clone());
+        }
+        let mut capsule = requested.intersect(parent);
+        // Children cannot spawn agents of their own, so one call cannot fan
+        // out without bound
+        if let Some(tools) = &mut capsule.tools {
+            tools.retain(|tool| tool != SPAWN_AGENTS_TOOL);
+        }
+
+        let mut profile = self.profile.clone();
+        if let Some(secs) = spec.timeout_secs {
+            profile.turn_timeout_secs =
+                Some(profile.turn_timeout_secs.map_or(secs, |own| own.min(secs)));
+        }
+
+        let mut child = AgentCore::new(
+            profile,
+            self.provider.clone(),
+            self.embeddings_client.clone(),
+            self.persistence.clone(),
+            child_session_id(&self.session_id, &spec.name, spawned_at),
+            self.agent_name.clone(),
+            self.tool_registry.clone(),
+            self.policy_engine.clone(),
+            false,
+        )
+        .with_pricing(self.pricing.clone())
+        .with_run_budget(self.run_budget)
+        .with_policy_capsule(capsule);
+        if let Some(fast_provider) = &self.fast_provider {
+            child = child.with_fast_provider(fast_provider.clone());
+        }
+        child.set_tool_approvals(self.tool_approvals.clone());
+        child.set_user_questions(self.user_questions.clone());
+        child.set_preset(self.preset.clone());
+        child
+    }
+
     /// Get the tool registry
     pub fn tool_registry(&self) -> &ToolRegistry {
         &self.tool_registry
@@ -2171,6 +3693,146 @@ impl AgentCore {
         self.policy_engine = policy_engine;
     }
 
+    /// Get the model provider
+    pub fn provider(&self) -> &Arc<dyn ModelProvider> {
+        &self.provider
+    }
+
+    /// Replace the model provider, keeping the session and history
+    pub fn set_provider(&mut self, provider: Arc<dyn ModelProvider>) {
+        self.provider = provider;
+    }
+
+    /// Run on `provider`, recording its model in the agent's profile
+    pub fn set_model(&mut self, provider: Arc<dyn ModelProvider>, model: &ModelConfig) {
+        self.provider = provider;
+        self.profile.model_provider = Some(model.provider.clone());
+        self.profile.model_name = model.model_name.clone();
+    }
+
+    /// Record this agent's model and tool calls to `transcript`, or replay
+    /// them from it, or stop with `None`
+    ///
+    /// Sub-agents are not recorded; replaying serves the results of the
+    /// `spawn_agents` calls that started them.
+    pub fn set_transcript(&mut self, transcript: Option<Arc<RunTranscript>>) {
+        self.transcript = transcript;
+    }
+
+    pub fn transcript(&self) -> Option<&Arc<RunTranscript>> {
+        self.transcript.as_ref()
+    }
+
+    /// Main model, through the transcript when one is set
+    fn main_model(&self) -> Arc<dyn ModelProvider> {
+        self.through_transcript(&self.provider)
+    }
+
+    /// Fast model, through the transcript when one is set
+    fn fast_model(&self) -> Option<Arc<dyn ModelProvider>> {
+        self.fast_provider
+            .as_ref()
+            .map(|provider| self.through_transcript(provider))
+    }
+
+    fn through_transcript(&self, provider: &Arc<dyn ModelProvider>) -> Arc<dyn ModelProvider> {
+        match &self.transcript {
+            Some(transcript) => Arc::new(TranscriptProvider::new(
+                Arc::clone(provider),
+                Arc::clone(transcript),
+            )),
+            None => Arc::clone(provider),
+        }
+    }
+
+    /// Handle for interrupting the agent's steps from another task
+    pub fn interrupt_handle(&self) -> RunInterrupt {
+        self.interrupt.clone()
+    }
+
+    /// Take interruptions from a handle the caller keeps across agents
+    pub fn set_interrupt(&mut self, interrupt: RunInterrupt) {
+        self.interrupt = interrupt;
+    }
+
+    /// Handle for injecting events into the agent's next turn
+    pub fn inbox(&self) -> AgentInbox {
+        self.inbox.clone()
+    }
+
+    /// Take events from an inbox the caller keeps across agents
+    pub fn set_inbox(&mut self, inbox: AgentInbox) {
+        self.inbox = inbox;
+    }
+
+    /// Input for a turn that delivers the pending events, emptying the
+    /// inbox, or None when nothing is waiting
+    pub fn take_events_input(&self) -> Option<String> {
+        self.inbox.take_input()
+    }
+
+    /// Use `run_id` for the next step instead of a generated one
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.next_run_id = Some(run_id.into());
+        self
+    }
+
+    /// Limit what each step may spend to `budget` instead of the profile's limits
+    pub fn with_run_budget(mut self, budget: RunBudget) -> Self {
+        self.run_budget = budget;
+        self
+    }
+
+    /// Limits on what each step may spend
+    pub fn run_budget(&self) -> RunBudget {
+        self.run_budget
+    }
+
+    /// Change the step limits, such as to let the user continue a step that
+    /// ran out of budget
+    pub fn set_run_budget(&mut self, budget: RunBudget) {
+        self.run_budget = budget;
+    }
+
+    /// Apply the generation preset `preset`, called `name`, to every model call
+    pub fn with_preset(mut self, name: impl Into<String>, preset: GenerationPreset) -> Self {
+        self.preset = Some((name.into(), preset));
+        self
+    }
+
+    /// Generation preset in use and its name
+    pub fn preset(&self) -> Option<(&str, &GenerationPreset)> {
+        self.preset
+            .as_ref()
+            .map(|(name, preset)| (name.as_str(), preset))
+    }
+
+    /// Switch to another generation preset, or back to the profile's settings
+    pub fn set_preset(&mut self, preset: Option<(String, GenerationPreset)>) {
+        self.preset = preset;
+    }
+
+    /// Send blocked tool calls to a front end for approval
+    ///
+    /// Without a handler the agent asks through the `prompt_user` tool.
+    pub fn set_tool_approvals(&mut self, approvals: Option<ToolApprovalSender>) {
+        self.tool_approvals = approvals;
+    }
+
+    /// Send `ask_user` questions to a front end
+    ///
+    /// Without a handler the tool tells the model that no one can answer.
+    pub fn set_user_questions(&mut self, questions: Option<UserQuestionSender>) {
+        self.user_questions = questions;
+    }
+
+    /// Record `author` as the writer of the user messages that follow
+    ///
+    /// Sessions shared by several people use this to tell their input apart.
+    pub fn set_author(&mut self, author: Option<String>) {
+        self.author = author;
+    }
+
     /// Enable or disable speech-oriented prompting
     pub fn set_speak_responses(&mut self, enabled: bool) {
         #[cfg(target_os = "macos")]
@@ -2198,7 +3860,10 @@ impl AgentCore {
                                     None, // No message_id for transcriptions
                                     &embedding,
                                 ) {
-                                    Ok(emb_id) => return Some(emb_id),
+                                    Ok(emb_id) => {
+                                        self.index_memory(emb_id, None, &embedding).await;
+                                        return Some(emb_id);
+                                    }
                                     Err(err) => {
                                         warn!("Failed to persist embedding: {}", err);
                                     }
@@ -2470,6 +4135,43 @@ impl AgentCore {
         }
     }
 
+    /// Persist token usage reported by the main provider for cost reporting,
+    /// add it to the session totals and return its estimated cost.
+    fn record_usage(&mut self, run_id: &str, model: &str, usage: &TokenUsage) -> Option<f64> {
+        let provider = self.provider.kind().as_str();
+        if let Err(err) = self.persistence.record_usage(
+            &self.session_id,
+            self.agent_name.as_deref(),
+            run_id,
+            provider,
+            model,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.total_tokens,
+        ) {
+            warn!("Failed to record token usage: {}", err);
+        }
+        let cost = self.pricing.cost(
+            model,
+            u64::from(usage.prompt_tokens),
+            u64::from(usage.completion_tokens),
+        );
+        self.session_usage.record(provider, model, usage, cost);
+        cost
+    }
+
+    /// Token usage of the main model since the session started, per model
+    pub fn session_usage(&self) -> &SessionUsage {
+        &self.session_usage
+    }
+
+    /// An event of this agent's session
+    fn event(&self, kind: EventKind) -> Event {
+        Event::new(kind)
+            .with_session(&self.session_id)
+            .with_agent(self.agent_name.as_deref())
+    }
+
     fn log_timing(&self, stage: &str, start: Instant) {
         let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
         let agent_label = self.agent_name.as_deref().unwrap_or("unnamed");
@@ -2482,13 +4184,51 @@ impl AgentCore {
             self.session_id
         );
     }
+
+    /// Span of an agent turn, opening a new trace
+    fn turn_span(&self) -> Span {
+        let agent = self.agent_name.as_deref().unwrap_or("agent");
+        let mut span = telemetry::start_trace(format!("invoke_agent {}", agent));
+        span.set_attribute(telemetry::GEN_AI_OPERATION_NAME, "invoke_agent");
+        span.set_attribute(telemetry::GEN_AI_AGENT_NAME, agent);
+        span.set_attribute(telemetry::GEN_AI_CONVERSATION_ID, self.session_id.clone());
+        span
+    }
 }
 
-fn preview_text(content: &str) -> String {
-    const MAX_CHARS: usize = 80;
-    let trimmed = content.trim();
-    let mut preview = String::new();
-    for (idx, ch) in trimmed.chars().enumerate() {
+/// Span of a call to `provider`, within `parent`
+fn model_call_span(parent: &SpanContext, provider: &dyn ModelProvider) -> Span {
+    let model = provider.model_name();
+    let mut span = parent.child(format!("chat {}", model), SpanKind::Client);
+    span.set_attribute(telemetry::GEN_AI_OPERATION_NAME, "chat");
+    span.set_attribute(telemetry::GEN_AI_SYSTEM, provider.kind().as_str());
+    span.set_attribute(telemetry::GEN_AI_REQUEST_MODEL, model);
+    span
+}
+
+fn record_model_response(span: &mut Span, response: &ModelResponse) {
+    span.set_attribute(telemetry::GEN_AI_RESPONSE_MODEL, response.model.clone());
+    if let Some(reason) = &response.finish_reason {
+        span.set_attribute(telemetry::GEN_AI_RESPONSE_FINISH_REASON, reason.clone());
+    }
+    if let Some(usage) = &response.usage {
+        record_token_usage(span, usage);
+    }
+}
+
+fn record_token_usage(span: &mut Span, usage: &TokenUsage) {
+    span.set_attribute(telemetry::GEN_AI_USAGE_INPUT_TOKENS, usage.prompt_tokens);
+    span.set_attribute(
+        telemetry::GEN_AI_USAGE_OUTPUT_TOKENS,
+        usage.completion_tokens,
+    );
+}
+
+fn preview_text(content: &str) -> String {
+    const MAX_CHARS: usize = 80;
+    let trimmed = content.trim();
+    let mut preview = String::new();
+    for (idx, ch) in trimmed.chars().enumerate() {
         if idx >= MAX_CHARS {
             preview.push_str("...");
             break;
@@ -2527,6 +4267,8 @@ mod tests {
     use crate::agent::providers::MockProvider;
     use crate::config::AgentProfile;
     use crate::embeddings::{EmbeddingsClient, EmbeddingsService};
+    #[cfg(feature = "sqlite-vec")]
+    use crate::vectors::sqlite::SqliteVectorStore;
     use async_trait::async_trait;
     use tempfile::tempdir;
 
@@ -2553,6 +4295,16 @@ mod tests {
             memory_k: 5,
             top_p: 0.9,
             max_context_tokens: Some(2048),
+            response_cache_ttl_secs: None,
+            context_compaction: false,
+            context_keep_recent: 6,
+            tool_parallelism: 4,
+            tool_timeout_secs: None,
+            turn_timeout_secs: None,
+            max_run_tokens: None,
+            max_run_cost: None,
+            max_run_tool_calls: None,
+            preset: None,
             enable_graph: false,
             graph_memory: false,
             auto_graph: false,
@@ -2612,6 +4364,16 @@ mod tests {
             memory_k: 5,
             top_p: 0.9,
             max_context_tokens: Some(2048),
+            response_cache_ttl_secs: None,
+            context_compaction: false,
+            context_keep_recent: 6,
+            tool_parallelism: 4,
+            tool_timeout_secs: None,
+            turn_timeout_secs: None,
+            max_run_tokens: None,
+            max_run_cost: None,
+            max_run_tool_calls: None,
+            preset: None,
             enable_graph: false,
             graph_memory: false,
             auto_graph: false,
@@ -2698,6 +4460,251 @@ mod tests {
         assert_eq!(output.tool_invocations.len(), 0);
     }
 
+    #[tokio::test]
+    async fn turns_model_calls_and_tools_are_traced() {
+        let (tracer, mut spans) = telemetry::Tracer::new(1.0);
+        assert!(
+            telemetry::install(tracer),
+            "only this test installs a tracer"
+        );
+        let (mut agent, _dir) = create_test_agent("telemetry-session");
+
+        agent.run_step("Hello, how are you?").await.unwrap();
+        let turn = agent.turn_span();
+        agent.trace = turn.context();
+        let result = agent
+            .execute_tool("run-telemetry", "no_such_tool", &json!({}))
+            .await
+            .unwrap();
+        assert!(!result.success);
+        agent.trace = SpanContext::default();
+        drop(turn);
+
+        // Other tests run alongside, so keep to this session's traces
+        let records: Vec<_> = std::iter::from_fn(|| spans.try_recv().ok()).collect();
+        let session = (
+            telemetry::GEN_AI_CONVERSATION_ID,
+            telemetry::AttributeValue::from("telemetry-session"),
+        );
+        let turns: Vec<_> = records
+            .iter()
+            .filter(|record| record.attributes.contains(&session))
+            .collect();
+        assert_eq!(turns.len(), 2);
+        let children = |turn: &telemetry::SpanRecord| -> Vec<_> {
+            records
+                .iter()
+                .filter(|record| {
+                    record.trace_id == turn.trace_id && record.parent_span_id == Some(turn.span_id)
+                })
+                .cloned()
+                .collect()
+        };
+
+        assert_eq!(turns[0].name, "invoke_agent telemetry-session");
+        let calls = children(turns[0]);
+        assert!(!calls.is_empty());
+        for call in &calls {
+            assert_eq!(call.name, "chat mock-model");
+            assert_eq!(call.kind, SpanKind::Client);
+            assert!(call
+                .attributes
+                .iter()
+                .any(|(key, _)| *key == telemetry::GEN_AI_USAGE_INPUT_TOKENS));
+        }
+
+        let tools = children(turns[1]);
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "execute_tool no_such_tool");
+        assert!(tools[0]
+            .attributes
+            .contains(&(telemetry::TOOL_SUCCESS, false.into())));
+        assert!(tools[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Tool not found"));
+    }
+
+    #[tokio::test]
+    async fn session_usage_accumulates_and_survives_rebuild() {
+        use crate::config::ModelPricing;
+        use futures::StreamExt;
+
+        let (agent, _dir) = create_test_agent("usage-session");
+        let mut pricing = PricingConfig::default();
+        pricing.models.insert(
+            "mock-model".to_string(),
+            ModelPricing {
+                input_per_million: 1_000_000.0,
+                output_per_million: 2_000_000.0,
+            },
+        );
+        let mut agent = agent.with_pricing(pricing);
+
+        let output = agent.run_step("Hello there").await.unwrap();
+        let usage = output.token_usage.unwrap();
+        let expected_cost = usage.prompt_tokens as f64 + 2.0 * usage.completion_tokens as f64;
+        assert_eq!(output.cost, Some(expected_cost));
+        assert_eq!(
+            output.session_usage.total_tokens(),
+            u64::from(usage.total_tokens)
+        );
+
+        let mut stream = agent.run_step_streaming("Stream please").await.unwrap();
+        let mut content = String::new();
+        while let Some(chunk) = stream.next().await {
+            content.push_str(&chunk.unwrap());
+        }
+        agent.finalize_streaming_step(&content).await.unwrap();
+
+        let session = agent.session_usage().clone();
+        assert_eq!(session.models.len(), 1);
+        assert_eq!(session.models[0].calls, 2);
+        assert!(session.total_tokens() > u64::from(usage.total_tokens));
+
+        // Rebuilding on the same session reloads the persisted totals
+        let agent = agent.with_session("usage-session".to_string());
+        assert_eq!(agent.session_usage(), &session);
+    }
+
+    #[tokio::test]
+    async fn recorded_runs_replay_without_the_provider_or_tools() {
+        let (mut recording, _record_dir) = create_test_agent("record-session");
+        let recorder = Arc::new(RunTranscript::recorder());
+        recording.set_transcript(Some(recorder.clone()));
+        let recorded = recording.run_step("Hello").await.unwrap();
+        assert!(!recorder.events().is_empty());
+
+        // The replaying agent has another model and no tools at all
+        let mut events = recorder.events();
+        events.push(TranscriptEvent::Tool {
+            name: "echo".to_string(),
+            args: json!({"message": "hi"}),
+            result: ToolResult::success("hi"),
+        });
+        let replay = Arc::new(RunTranscript::from_events(events));
+        let (mut replaying, _replay_dir) = create_test_agent("replay-session");
+        replaying.set_provider(Arc::new(MockProvider::new("A live response.")));
+        replaying.set_transcript(Some(replay.clone()));
+
+        let replayed = replaying.run_step("Hello").await.unwrap();
+        assert_eq!(replayed.response, recorded.response);
+        let echo = replaying
+            .execute_tool("run-replay", "echo", &json!({"message": "hi"}))
+            .await
+            .unwrap();
+        assert!(echo.success);
+        assert_eq!(echo.output, "hi");
+        assert_eq!(replay.unused(), 0);
+    }
+
+    #[tokio::test]
+    async fn presets_override_the_profile_generation_settings() {
+        let (mut agent, _dir) = create_test_agent("preset-session");
+        let profile_config = agent.build_generation_config();
+
+        agent.set_preset(Some((
+            "precise".to_string(),
+            GenerationPreset::builtin("precise").unwrap(),
+        )));
+        let config = agent.build_generation_config();
+        assert_eq!(config.temperature, Some(0.2));
+        assert_eq!(config.top_p, Some(0.9));
+        assert_eq!(config.max_tokens, profile_config.max_tokens);
+        assert_eq!(agent.preset().map(|(name, _)| name), Some("precise"));
+
+        agent.set_preset(None);
+        assert_eq!(
+            agent.build_generation_config().temperature,
+            profile_config.temperature
+        );
+    }
+
+    #[tokio::test]
+    async fn response_cache_serves_identical_requests() {
+        let (agent, _dir) = create_test_agent("cache-session");
+        let mut agent = agent.with_response_cache(Some(Duration::from_secs(60)));
+        agent.provider = Arc::new(MockProvider::with_responses(vec![
+            "first".to_string(),
+            "second".to_string(),
+            "third".to_string(),
+        ]));
+        let config = agent.build_generation_config();
+
+        let first = agent.generate_cached("same prompt", &config).await.unwrap();
+        let again = agent.generate_cached("same prompt", &config).await.unwrap();
+        assert_eq!(first.content, "first");
+        assert_eq!(again.content, "first");
+        assert!(first.usage.is_some());
+        assert!(again.usage.is_none());
+
+        let other = agent.generate_cached("new prompt", &config).await.unwrap();
+        assert_eq!(other.content, "second");
+
+        let agent = agent.with_response_cache(None);
+        let uncached = agent.generate_cached("same prompt", &config).await.unwrap();
+        assert_eq!(uncached.content, "third");
+    }
+
+    #[tokio::test]
+    async fn context_compaction_summarizes_old_turns_and_keeps_pins() {
+        let (agent, _dir) = create_test_agent("compaction-session");
+        let mut agent = agent.with_fast_provider(Arc::new(MockProvider::new(
+            "User is planning a trip to Lisbon.",
+        )));
+        agent.profile.context_compaction = true;
+        agent.profile.context_keep_recent = 2;
+        agent.profile.max_context_tokens = Some(400);
+
+        let pinned = agent
+            .store_message(MessageRole::User, "My passport number is X123")
+            .await
+            .unwrap();
+        agent.pin_message(pinned).unwrap();
+        let filler = "details ".repeat(40);
+        for turn in 0..6 {
+            agent
+                .store_message(MessageRole::User, &format!("turn {} {}", turn, filler))
+                .await
+                .unwrap();
+        }
+
+        let window = agent
+            .prepare_context_window()
+            .await
+            .unwrap()
+            .expect("compaction is enabled");
+        assert_eq!(
+            window.summary.as_deref(),
+            Some("User is planning a trip to Lisbon.")
+        );
+        assert_eq!(window.pinned.len(), 1);
+        assert_eq!(window.recent.len(), 2);
+        assert!(window.tokens() <= context::conversation_budget(400));
+
+        let prompt = agent
+            .build_prompt("Where am I going?", &[], Some(&window))
+            .await
+            .unwrap();
+        assert!(prompt.contains("Summary of earlier conversation:\nUser is planning"));
+        assert!(prompt.contains("My passport number is X123"));
+        assert!(!prompt.contains("turn 0 "));
+        assert!(prompt.contains("turn 5 "));
+
+        // The next step starts from the stored summary without compacting again
+        let summary = agent
+            .persistence
+            .context_summary("compaction-session")
+            .unwrap()
+            .unwrap();
+        assert!(summary.through_message_id < window.recent[0].id);
+        let again = agent.prepare_context_window().await.unwrap().unwrap();
+        assert_eq!(again.recent.len(), 2);
+
+        assert!(agent.unpin_message(pinned).unwrap());
+    }
+
     #[tokio::test]
     async fn fast_model_short_circuits_when_confident() {
         let (mut agent, _dir) = create_fast_reasoning_agent(
@@ -2717,6 +4724,44 @@ mod tests {
         assert!(output.response.contains("Entities detected"));
     }
 
+    #[tokio::test]
+    async fn fast_model_json_answer_is_used() {
+        let (mut agent, _dir) = create_fast_reasoning_agent(
+            "fast-json",
+            r#"{"answer": "Entities: Oslo.", "confidence": 0.95}"#,
+        );
+
+        let output = agent
+            .run_step("Extract the entities mentioned in this string.")
+            .await
+            .unwrap();
+
+        assert!(output
+            .finish_reason
+            .unwrap_or_default()
+            .contains("fast_model"));
+        assert_eq!(output.response, "Entities: Oslo.");
+    }
+
+    #[tokio::test]
+    async fn generate_structured_returns_typed_value_and_records_usage() {
+        #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+        struct Plan {
+            steps: Vec<String>,
+        }
+
+        let (mut agent, _dir) = create_test_agent("structured-session");
+        agent.set_provider(Arc::new(MockProvider::new(
+            r#"{"steps": ["read", "write"]}"#,
+        )));
+
+        let plan: Plan = agent.generate_structured("Plan the change").await.unwrap();
+
+        assert_eq!(plan.steps, vec!["read", "write"]);
+        assert_eq!(agent.session_usage().models[0].calls, 1);
+        assert!(agent.conversation_history().is_empty());
+    }
+
     #[tokio::test]
     async fn fast_model_only_hints_when_low_confidence() {
         let (mut agent, _dir) =
@@ -2783,6 +4828,7 @@ mod tests {
                 role: MessageRole::User,
                 content: "Previous question".to_string(),
                 created_at: Utc::now(),
+                author: None,
             },
             Message {
                 id: 2,
@@ -2790,11 +4836,12 @@ mod tests {
                 role: MessageRole::Assistant,
                 content: "Previous answer".to_string(),
                 created_at: Utc::now(),
+                author: None,
             },
         ];
 
         let prompt = agent
-            .build_prompt("Current question", &context)
+            .build_prompt("Current question", &context, None)
             .await
             .unwrap();
 
@@ -2822,6 +4869,63 @@ mod tests {
         assert_eq!(messages[0].content, "Test message");
     }
 
+    #[tokio::test]
+    async fn branching_keeps_the_original_thread() {
+        let (mut agent, _dir) = create_test_agent("main");
+        agent.run_step("first question").await.unwrap();
+        agent.save_checkpoint("answered").unwrap();
+        agent.run_step("second question").await.unwrap();
+        let original = agent.persistence.list_messages("main", 10).unwrap();
+        assert_eq!(original.len(), 4);
+
+        let branch = agent.branch_before(original[2].id).unwrap();
+        assert_eq!(branch.session_id, "main.1");
+        assert_eq!(agent.session_id(), "main.1");
+        agent.run_step("edited question").await.unwrap();
+        let edited: Vec<_> = agent
+            .persistence
+            .list_messages("main.1", 10)
+            .unwrap()
+            .into_iter()
+            .map(|message| message.content)
+            .collect();
+        assert_eq!(edited.len(), 4);
+        assert_eq!(edited[0], "first question");
+        assert_eq!(edited[2], "edited question");
+        let untouched: Vec<_> = agent
+            .persistence
+            .list_messages("main", 10)
+            .unwrap()
+            .iter()
+            .map(|message| message.id)
+            .collect();
+        let original_ids: Vec<_> = original.iter().map(|message| message.id).collect();
+        assert_eq!(untouched, original_ids);
+
+        // Messages of other sessions and assistant replies cannot be edited
+        assert!(agent.branch_before(original[2].id).is_err());
+        agent.switch_branch("main").unwrap();
+        assert!(agent.branch_before(original[1].id).is_err());
+
+        let rolled_back = agent.rollback("answered").unwrap();
+        assert_eq!(rolled_back.session_id, "main.2");
+        assert_eq!(
+            agent.persistence.list_messages("main.2", 10).unwrap().len(),
+            2
+        );
+        assert!(agent.rollback("missing").is_err());
+
+        let branches: Vec<_> = agent
+            .branches()
+            .unwrap()
+            .into_iter()
+            .map(|branch| branch.session_id)
+            .collect();
+        assert_eq!(branches, vec!["main.1", "main.2"]);
+        assert_eq!(agent.conversation_root().unwrap(), "main");
+        assert!(agent.switch_branch("elsewhere").is_err());
+    }
+
     #[tokio::test]
     async fn store_message_records_embeddings() {
         let (agent, _dir) =
@@ -2888,6 +4992,43 @@ mod tests {
         assert!(tail.contains(&"Alpha answer"));
     }
 
+    #[cfg(feature = "sqlite-vec")]
+    #[tokio::test]
+    async fn stored_messages_are_recalled_from_the_vector_store() {
+        let (agent, _dir) =
+            create_test_agent_with_embeddings("vector-recall", Some(test_embeddings_client()));
+        let store = Arc::new(SqliteVectorStore::in_memory().unwrap());
+        let agent = agent.with_vector_store(store.clone());
+
+        let alpha = agent
+            .store_message(MessageRole::User, "Alpha question")
+            .await
+            .unwrap();
+        let beta = agent
+            .store_message(MessageRole::User, "Beta prompt")
+            .await
+            .unwrap();
+
+        let session = VectorFilter::none().eq("session_id", "vector-recall");
+        let indexed = store
+            .knn(MEMORY_COLLECTION, &[1.0, 0.0], 5, &session)
+            .await
+            .unwrap();
+        assert_eq!(indexed.len(), 2);
+        assert_eq!(indexed[0].payload["message_id"], alpha);
+
+        // With the alpha point gone from the store, the database scan that
+        // would still find it is not consulted
+        store
+            .delete(MEMORY_COLLECTION, &[indexed[0].id])
+            .await
+            .unwrap();
+        let recalled = agent.recall_top_k(&[1.0, 0.0]).await.unwrap();
+        assert_eq!(recalled.len(), 1);
+        assert_eq!(recalled[0].0.message_id, Some(beta));
+        assert_eq!(recalled[0].0.session_id, "vector-recall");
+    }
+
     #[tokio::test]
     async fn test_agent_tool_permission_allowed() {
         let dir = tempdir().unwrap();
@@ -2905,6 +5046,16 @@ mod tests {
             memory_k: 5,
             top_p: 0.9,
             max_context_tokens: Some(2048),
+            response_cache_ttl_secs: None,
+            context_compaction: false,
+            context_keep_recent: 6,
+            tool_parallelism: 4,
+            tool_timeout_secs: None,
+            turn_timeout_secs: None,
+            max_run_tokens: None,
+            max_run_cost: None,
+            max_run_tool_calls: None,
+            preset: None,
             enable_graph: false,
             graph_memory: false,
             auto_graph: false,
@@ -2973,6 +5124,48 @@ mod tests {
         assert!(!agent.is_tool_allowed("calculator").await);
     }
 
+    #[tokio::test]
+    async fn policy_modes_decide_what_policy_denials_do() {
+        let dir = tempdir().unwrap();
+        let persistence = Persistence::new(dir.path().join("modes.duckdb")).unwrap();
+        let agent = |policy_mode| {
+            let profile = AgentProfile {
+                denied_tools: Some(vec!["bash".to_string()]),
+                policy_mode,
+                ..Default::default()
+            };
+            // No rules, so the policy engine denies everything
+            AgentCore::new(
+                profile,
+                Arc::new(MockProvider::new("Test")),
+                None,
+                persistence.clone(),
+                "modes".to_string(),
+                Some("ops".to_string()),
+                Arc::new(crate::tools::ToolRegistry::new()),
+                Arc::new(PolicyEngine::new()),
+                false,
+            )
+        };
+
+        let ask = agent(PolicyMode::Ask);
+        assert!(!ask.is_tool_allowed("echo").await);
+        assert!(ask.audited_denial("echo").is_none());
+
+        let enforce = agent(PolicyMode::Enforce);
+        assert!(!enforce.is_tool_allowed("echo").await);
+
+        // Audit runs what the engine denies but not what the profile denies
+        let audit = agent(PolicyMode::Audit);
+        assert!(audit.is_tool_allowed("echo").await);
+        assert!(audit
+            .audited_denial("echo")
+            .unwrap()
+            .contains("default deny"));
+        assert!(!audit.is_tool_allowed("bash").await);
+        assert!(audit.audited_denial("bash").is_none());
+    }
+
     #[tokio::test]
     async fn test_agent_tool_execution_with_logging() {
         let dir = tempdir().unwrap();
@@ -2990,6 +5183,16 @@ mod tests {
             memory_k: 5,
             top_p: 0.9,
             max_context_tokens: Some(2048),
+            response_cache_ttl_secs: None,
+            context_compaction: false,
+            context_keep_recent: 6,
+            tool_parallelism: 4,
+            tool_timeout_secs: None,
+            turn_timeout_secs: None,
+            max_run_tokens: None,
+            max_run_cost: None,
+            max_run_tool_calls: None,
+            preset: None,
             enable_graph: false,
             graph_memory: false,
             auto_graph: false,
@@ -3043,6 +5246,523 @@ mod tests {
         // Verify tool execution was logged (we can't easily check DB here without more setup)
     }
 
+    #[tokio::test]
+    async fn policy_capsule_limits_tools_paths_and_calls() {
+        let dir = tempdir().unwrap();
+        let persistence = Persistence::new(dir.path().join("capsule.duckdb")).unwrap();
+        let mut tool_registry = crate::tools::ToolRegistry::new();
+        tool_registry.register(Arc::new(crate::tools::builtin::EchoTool::new()));
+        tool_registry.register(Arc::new(crate::tools::builtin::FileReadTool::new()));
+        tool_registry.register(Arc::new(crate::tools::builtin::MathTool::new()));
+        let mut policy_engine = PolicyEngine::new();
+        policy_engine.add_rule(crate::policy::PolicyRule {
+            agent: "*".to_string(),
+            action: "tool_call".to_string(),
+            resource: "*".to_string(),
+            effect: crate::policy::PolicyEffect::Allow,
+        });
+
+        let scope = dir.path().to_string_lossy().to_string();
+        let agent = AgentCore::new(
+            AgentProfile::default(),
+            Arc::new(MockProvider::new("Test")),
+            None,
+            persistence,
+            "capsule-test".to_string(),
+            Some("worker".to_string()),
+            Arc::new(tool_registry),
+            Arc::new(policy_engine),
+            false,
+        )
+        .with_policy_capsule(
+            PolicyCapsule::new("planner")
+                .with_tools(vec!["echo".to_string(), "file_read".to_string()])
+                .with_paths(vec![scope.clone()])
+                .with_budget(CapsuleBudget {
+                    max_tool_calls: Some(2),
+                    max_tokens: None,
+                }),
+        );
+
+        assert!(agent.is_tool_allowed("echo").await);
+        assert!(!agent.is_tool_allowed("calculator").await);
+
+        let echo = json!({"message": "hi"});
+        let result = agent.execute_tool("run", "echo", &echo).await.unwrap();
+        assert!(result.success);
+
+        // Tools outside the capsule fail even if the user approved them
+        let result = agent
+            .execute_tool("run", "calculator", &json!({"expression": "1+1"}))
+            .await
+            .unwrap();
+        assert!(result.error.unwrap().contains("planner"));
+
+        let outside = json!({"path": "/etc/hosts"});
+        let result = agent
+            .execute_tool("run", "file_read", &outside)
+            .await
+            .unwrap();
+        assert!(result.error.unwrap().contains("/etc/hosts"));
+
+        // Re-delegated work inherits the scope and the remaining budget
+        let capsule = agent.delegation_capsule().await;
+        assert_eq!(capsule.issuer, "worker");
+        assert_eq!(
+            capsule.tools,
+            Some(vec!["echo".to_string(), "file_read".to_string()])
+        );
+        assert_eq!(capsule.paths, Some(vec![scope]));
+        assert_eq!(capsule.budget.max_tool_calls, Some(1));
+
+        assert!(
+            agent
+                .execute_tool("run", "echo", &echo)
+                .await
+                .unwrap()
+                .success
+        );
+        let result = agent.execute_tool("run", "echo", &echo).await.unwrap();
+        assert!(result.error.unwrap().contains("used up"));
+    }
+
+    #[tokio::test]
+    async fn subagents_run_in_their_own_sessions_and_report_back() {
+        let dir = tempdir().unwrap();
+        let persistence = Persistence::new(dir.path().join("subagents.duckdb")).unwrap();
+        let mut tool_registry = crate::tools::ToolRegistry::new();
+        tool_registry.register(Arc::new(crate::tools::builtin::EchoTool::new()));
+        tool_registry.register(Arc::new(crate::tools::builtin::SpawnAgentsTool::new()));
+        let mut policy_engine = PolicyEngine::new();
+        policy_engine.add_rule(crate::policy::PolicyRule {
+            agent: "*".to_string(),
+            action: "tool_call".to_string(),
+            resource: "*".to_string(),
+            effect: crate::policy::PolicyEffect::Allow,
+        });
+        let profile = AgentProfile {
+            enable_graph: false,
+            graph_memory: false,
+            auto_graph: false,
+            fast_reasoning: false,
+            ..Default::default()
+        };
+        let mut agent = AgentCore::new(
+            profile,
+            Arc::new(MockProvider::new("Found it")),
+            None,
+            persistence.clone(),
+            "parent".to_string(),
+            Some("planner".to_string()),
+            Arc::new(tool_registry),
+            Arc::new(policy_engine),
+            false,
+        );
+
+        // Children get the parent's tools, minus spawning more children
+        let parent = agent.delegation_capsule().await;
+        let child = agent.subagent(&SubAgentSpec::new("docs", "Read the docs"), &parent, 1);
+        assert_eq!(child.session_id(), "parent/sub-docs-1");
+        let capsule = child.policy_capsule().unwrap();
+        assert!(capsule.permits_tool("echo"));
+        assert!(!capsule.permits_tool(SPAWN_AGENTS_TOOL));
+
+        let reports = agent
+            .spawn_subagents(vec![
+                SubAgentSpec::new("docs", "Read the docs"),
+                SubAgentSpec::new("broke", "Read everything").with_budget(CapsuleBudget {
+                    max_tool_calls: None,
+                    max_tokens: Some(0),
+                }),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(reports[0].name, "docs");
+        assert_eq!(reports[0].response, "Found it");
+        assert!(reports[0].tokens > 0);
+        assert_eq!(
+            reports[1].finish_reason.as_deref(),
+            Some("budget_exhausted")
+        );
+        assert_ne!(reports[0].session_id, reports[1].session_id);
+
+        // Children keep their own conversations; the parent keeps their reports
+        let child_messages = persistence
+            .list_messages(&reports[0].session_id, 10)
+            .unwrap();
+        assert_eq!(child_messages.len(), 2);
+        let history = agent.conversation_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, "Sub-agent 'docs' reported:\nFound it");
+        assert_eq!(Some(history[1].id), reports[1].message_id);
+
+        let duplicates = vec![SubAgentSpec::new("a", "x"), SubAgentSpec::new("a", "y")];
+        assert!(agent.spawn_subagents(duplicates).await.is_err());
+    }
+
+    /// Sleeps for `ms` milliseconds, as a read-only tool unless it is `write`
+    struct SleepTool(&'static str);
+
+    #[async_trait]
+    impl crate::tools::Tool for SleepTool {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn description(&self) -> &str {
+            "Sleeps for `ms` milliseconds"
+        }
+
+        fn parameters(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        fn read_only(&self) -> bool {
+            self.0 != "write"
+        }
+
+        async fn execute(&self, args: Value) -> Result<ToolResult> {
+            let ms = args["ms"].as_u64().unwrap_or(0);
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            Ok(ToolResult::success(ms.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_batches_run_concurrently_in_call_order() {
+        let dir = tempdir().unwrap();
+        let persistence = Persistence::new(dir.path().join("batch.duckdb")).unwrap();
+        let mut tool_registry = crate::tools::ToolRegistry::new();
+        tool_registry.register(Arc::new(SleepTool("sleep")));
+        tool_registry.register(Arc::new(SleepTool("write")));
+        let profile = AgentProfile {
+            tool_parallelism: 3,
+            tool_timeout_secs: Some(1),
+            ..Default::default()
+        };
+        let agent = AgentCore::new(
+            profile,
+            Arc::new(MockProvider::new("Test")),
+            None,
+            persistence,
+            "batch-test".to_string(),
+            None,
+            Arc::new(tool_registry),
+            Arc::new(PolicyEngine::new()),
+            false,
+        );
+
+        let slow = json!({"ms": 600});
+        let fast = json!({"ms": 10});
+        let stuck = json!({"ms": 5000});
+        let started = Instant::now();
+        let results = agent
+            .execute_tool_batch(
+                "run",
+                &[("sleep", &slow), ("sleep", &fast), ("sleep", &stuck)],
+                None,
+            )
+            .await;
+        // One after another this would take 1.6 seconds
+        assert!(started.elapsed() < Duration::from_millis(1500));
+
+        let results: Vec<ToolResult> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(results[0].output, "600");
+        assert_eq!(results[1].output, "10");
+        assert!(results[2].error.as_deref().unwrap().contains("timed out"));
+
+        // Calls still running at the turn deadline are cancelled
+        let deadline = Instant::now() + Duration::from_millis(100);
+        let results = agent
+            .execute_tool_batch("run", &[("sleep", &fast), ("sleep", &slow)], Some(deadline))
+            .await;
+        assert!(results[0].as_ref().unwrap().success);
+        let cancelled = results[1].as_ref().unwrap();
+        assert!(cancelled
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("turn deadline"));
+
+        // A call to a tool that writes waits for the reads before it, and the
+        // reads after it wait for it
+        let step = json!({"ms": 300});
+        let started = Instant::now();
+        let results = agent
+            .execute_tool_batch(
+                "run",
+                &[("sleep", &step), ("write", &step), ("sleep", &step)],
+                None,
+            )
+            .await;
+        assert!(started.elapsed() >= Duration::from_millis(900));
+        assert!(results
+            .iter()
+            .all(|result| result.as_ref().unwrap().success));
+    }
+
+    /// Provider whose generations take longer than any test waits
+    struct StalledProvider(MockProvider);
+
+    #[async_trait]
+    impl ModelProvider for StalledProvider {
+        async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            self.0.generate(prompt, config).await
+        }
+
+        async fn stream(
+            &self,
+            prompt: &str,
+            config: &GenerationConfig,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+            self.0.stream(prompt, config).await
+        }
+
+        fn metadata(&self) -> crate::agent::model::ProviderMetadata {
+            self.0.metadata()
+        }
+
+        fn kind(&self) -> crate::agent::model::ProviderKind {
+            self.0.kind()
+        }
+    }
+
+    #[tokio::test]
+    async fn interrupted_steps_leave_a_resumable_conversation() {
+        use crate::agent::interrupt::INTERRUPTED_NOTE;
+
+        let (mut agent, _dir) = create_test_agent("interrupt-test");
+        agent.set_provider(Arc::new(StalledProvider(MockProvider::new("late"))));
+        let interrupt = agent.interrupt_handle();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            interrupt.interrupt();
+        });
+
+        let output = tokio::time::timeout(Duration::from_secs(10), agent.run_step("hello"))
+            .await
+            .expect("interrupted step returns")
+            .unwrap();
+        assert_eq!(output.finish_reason.as_deref(), Some("interrupted"));
+        assert_eq!(output.response, INTERRUPTED_NOTE);
+        assert_eq!(agent.conversation_history().len(), 2);
+
+        // Streams end early and keep what already arrived
+        agent.set_provider(Arc::new(MockProvider::new("one two three four five six")));
+        let mut stream = agent.run_step_streaming("again").await.unwrap();
+        let mut content = stream.next().await.unwrap().unwrap();
+        agent.interrupt_handle().interrupt();
+        while let Some(chunk) = stream.next().await {
+            content.push_str(&chunk.unwrap());
+        }
+        assert_eq!(content, "one ");
+        agent.finalize_streaming_step(&content).await.unwrap();
+        let last = agent.conversation_history().last().unwrap();
+        assert_eq!(last.content, format!("one\n\n{}", INTERRUPTED_NOTE));
+
+        // The next step starts afresh
+        agent.set_provider(Arc::new(MockProvider::new("resumed")));
+        let output = agent.run_step("continue").await.unwrap();
+        assert_eq!(output.response, "resumed");
+    }
+
+    /// Provider that asks for two echo calls in every response, as a
+    /// runaway loop would
+    struct LoopingProvider(MockProvider);
+
+    #[async_trait]
+    impl ModelProvider for LoopingProvider {
+        async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+            let mut response = self.0.generate(prompt, config).await?;
+            response.finish_reason = Some("tool_calls".to_string());
+            response.tool_calls = Some(
+                (0..2)
+                    .map(|index| crate::agent::model::ToolCall {
+                        id: format!("call_{}", index),
+                        function_name: "echo".to_string(),
+                        arguments: json!({"message": "again"}),
+                    })
+                    .collect(),
+            );
+            Ok(response)
+        }
+
+        async fn stream(
+            &self,
+            prompt: &str,
+            config: &GenerationConfig,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+            self.0.stream(prompt, config).await
+        }
+
+        fn metadata(&self) -> crate::agent::model::ProviderMetadata {
+            self.0.metadata()
+        }
+
+        fn kind(&self) -> crate::agent::model::ProviderKind {
+            self.0.kind()
+        }
+    }
+
+    fn looping_agent(dir: &tempfile::TempDir, profile: AgentProfile) -> AgentCore {
+        let persistence = Persistence::new(dir.path().join("budget.duckdb")).unwrap();
+        let mut tool_registry = crate::tools::ToolRegistry::new();
+        tool_registry.register(Arc::new(crate::tools::builtin::EchoTool::new()));
+        let mut policy_engine = PolicyEngine::new();
+        policy_engine.add_rule(crate::policy::PolicyRule {
+            agent: "*".to_string(),
+            action: "tool_call".to_string(),
+            resource: "*".to_string(),
+            effect: crate::policy::PolicyEffect::Allow,
+        });
+        AgentCore::new(
+            AgentProfile {
+                enable_graph: false,
+                fast_reasoning: false,
+                ..profile
+            },
+            Arc::new(LoopingProvider(MockProvider::new("Echoing"))),
+            None,
+            persistence,
+            "budget-test".to_string(),
+            None,
+            Arc::new(tool_registry),
+            Arc::new(policy_engine),
+            false,
+        )
+    }
+
+    #[tokio::test]
+    async fn runs_stop_when_their_budget_is_spent() {
+        use crate::agent::budget::{BudgetExceeded, RunBudget, BUDGET_EXCEEDED_REASON};
+
+        let dir = tempdir().unwrap();
+        let mut agent = looping_agent(
+            &dir,
+            AgentProfile {
+                max_run_tool_calls: Some(3),
+                ..Default::default()
+            },
+        );
+        let output = agent.run_step("repeat yourself").await.unwrap();
+        assert_eq!(
+            output.budget_exceeded,
+            Some(BudgetExceeded::ToolCalls { max: 3, used: 3 })
+        );
+        assert_eq!(
+            output.finish_reason.as_deref(),
+            Some(BUDGET_EXCEEDED_REASON)
+        );
+        // The fourth call is refused rather than run
+        let ran: Vec<bool> = output.tool_invocations.iter().map(|i| i.success).collect();
+        assert_eq!(ran, vec![true, true, true, false]);
+        assert!(output
+            .response
+            .ends_with("[stopped: tool call budget of 3 used up]"));
+
+        // Raising the limit lets the next step run further
+        agent.set_run_budget(RunBudget {
+            max_tool_calls: Some(6),
+            ..agent.run_budget()
+        });
+        let output = agent.run_step("continue").await.unwrap();
+        let ran = output.tool_invocations.iter().filter(|i| i.success).count();
+        assert_eq!(ran, 6);
+
+        let mut agent = looping_agent(
+            &dir,
+            AgentProfile {
+                max_run_tokens: Some(1),
+                ..Default::default()
+            },
+        );
+        let output = agent.run_step("repeat yourself").await.unwrap();
+        assert!(matches!(
+            output.budget_exceeded,
+            Some(BudgetExceeded::Tokens { max: 1, .. })
+        ));
+        // One model call, whose tools still run, then no more
+        assert_eq!(agent.session_usage().models[0].calls, 1);
+        assert_eq!(output.tool_invocations.len(), 2);
+    }
+
+    /// Provider that streams a tool call and reports usage
+    struct ToolStreamingProvider(MockProvider);
+
+    #[async_trait]
+    impl ModelProvider for ToolStreamingProvider {
+        async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+            self.0.generate(prompt, config).await
+        }
+
+        async fn stream(
+            &self,
+            prompt: &str,
+            config: &GenerationConfig,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+            self.0.stream(prompt, config).await
+        }
+
+        async fn stream_deltas(
+            &self,
+            _prompt: &str,
+            _config: &GenerationConfig,
+        ) -> Result<DeltaStream> {
+            let deltas = vec![
+                StreamDelta::Text("Searching.".to_string()),
+                StreamDelta::ToolCallStart {
+                    index: 0,
+                    id: "call_1".to_string(),
+                    name: "code_search".to_string(),
+                },
+                StreamDelta::ToolCallArguments {
+                    index: 0,
+                    fragment: r#"{"query":"parser"}"#.to_string(),
+                },
+                StreamDelta::Usage(TokenUsage::new(7, 3)),
+            ];
+            Ok(Box::pin(stream::iter(deltas.into_iter().map(Ok))))
+        }
+
+        fn metadata(&self) -> crate::agent::model::ProviderMetadata {
+            self.0.metadata()
+        }
+
+        fn kind(&self) -> crate::agent::model::ProviderKind {
+            self.0.kind()
+        }
+    }
+
+    #[tokio::test]
+    async fn streamed_deltas_carry_tool_calls_and_reported_usage() {
+        use crate::agent::model::StreamedResponse;
+
+        let (mut agent, _dir) = create_test_agent("delta-session");
+        agent.set_provider(Arc::new(ToolStreamingProvider(MockProvider::default())));
+
+        let mut stream = agent.run_step_streaming_deltas("find it").await.unwrap();
+        let mut response = StreamedResponse::new();
+        while let Some(delta) = stream.next().await {
+            response.push(&delta.unwrap());
+        }
+        assert_eq!(
+            response.tool_call_preview(0).as_deref(),
+            Some(r#"code_search({"query":"parser"})"#)
+        );
+        agent
+            .finalize_streaming_step(&response.content)
+            .await
+            .unwrap();
+
+        // The reported usage is recorded instead of an estimate
+        assert_eq!(agent.session_usage().total_tokens(), 10);
+        assert_eq!(
+            agent.conversation_history().last().unwrap().content,
+            "Searching."
+        );
+    }
+
     #[tokio::test]
     async fn test_agent_tool_registry_access() {
         let (agent, _dir) = create_test_agent("registry-test");
@@ -2,9 +2,16 @@
 //!
 //! Creates model provider instances based on configuration.
 
-use crate::agent::model::{ModelProvider, ProviderKind};
+use crate::agent::model::{GenerationConfig, ModelProvider, ProviderKind, ReasoningEffort};
 #[cfg(feature = "anthropic")]
 use crate::agent::providers::AnthropicProvider;
+#[cfg(feature = "bedrock")]
+use crate::agent::providers::BedrockProvider;
+#[cfg(feature = "chaos")]
+use crate::agent::providers::ChaosProvider;
+use crate::agent::providers::FallbackProvider;
+#[cfg(feature = "gemini")]
+use crate::agent::providers::GeminiProvider;
 #[cfg(feature = "lmstudio")]
 use crate::agent::providers::LMStudioProvider;
 #[cfg(feature = "mlx")]
@@ -14,12 +21,64 @@ use crate::agent::providers::MockProvider;
 use crate::agent::providers::OllamaProvider;
 #[cfg(feature = "openai")]
 use crate::agent::providers::OpenAIProvider;
-use crate::config::ModelConfig;
+use crate::config::{AppConfig, GenerationPreset, ModelConfig, ProvidersConfig};
 use anyhow::{anyhow, Context, Result};
 use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// How long a provider may take to list its models
+const MODEL_LIST_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Create a model provider from configuration
+///
+/// When a fault scenario is installed the provider is wrapped to inject its
+/// provider faults.
 pub fn create_provider(config: &ModelConfig) -> Result<Arc<dyn ModelProvider>> {
+    create_base_provider(config).map(inject_faults)
+}
+
+/// Wrap `provider` with the installed fault scenario, if there is one
+pub(crate) fn inject_faults(provider: Arc<dyn ModelProvider>) -> Arc<dyn ModelProvider> {
+    #[cfg(feature = "chaos")]
+    if let Some(faults) = crate::chaos::installed() {
+        return Arc::new(ChaosProvider::new(provider, faults));
+    }
+    provider
+}
+
+/// Wrap `primary` with retries and the fallback providers in `config`
+///
+/// Fallbacks are created with `create`; those that cannot be created are
+/// logged and left out of the chain. `primary` is returned as is when there
+/// is nothing to retry or fail over to.
+pub(crate) fn with_fallbacks<F>(
+    primary: Arc<dyn ModelProvider>,
+    config: &ProvidersConfig,
+    create: F,
+) -> Arc<dyn ModelProvider>
+where
+    F: Fn(&ModelConfig) -> Result<Arc<dyn ModelProvider>>,
+{
+    if config.fallback.is_empty() && config.max_retries == 0 {
+        return primary;
+    }
+    let fallbacks = config
+        .fallback
+        .iter()
+        .filter_map(|model| match create(model) {
+            Ok(provider) => Some(provider),
+            Err(err) => {
+                tracing::warn!("Skipping fallback provider {}: {:#}", model.provider, err);
+                None
+            }
+        })
+        .collect();
+    Arc::new(FallbackProvider::new(primary, fallbacks, config.into()))
+}
+
+/// Create a model provider from configuration, without fault injection
+pub(crate) fn create_base_provider(config: &ModelConfig) -> Result<Arc<dyn ModelProvider>> {
     let provider_kind = ProviderKind::from_str(&config.provider)
         .ok_or_else(|| anyhow!("Unknown provider: {}", config.provider))?;
 
@@ -76,6 +135,12 @@ pub fn create_provider(config: &ModelConfig) -> Result<Arc<dyn ModelProvider>> {
             Ok(Arc::new(provider))
         }
 
+        #[cfg(feature = "gemini")]
+        ProviderKind::Gemini => Ok(Arc::new(create_gemini_provider(config)?)),
+
+        #[cfg(feature = "bedrock")]
+        ProviderKind::Bedrock => Ok(Arc::new(create_bedrock_provider(config)?)),
+
         #[cfg(feature = "ollama")]
         ProviderKind::Ollama => {
             // Create Ollama provider with optional custom base URL
@@ -129,6 +194,231 @@ pub fn create_provider(config: &ModelConfig) -> Result<Arc<dyn ModelProvider>> {
     }
 }
 
+/// Create a Gemini provider from configuration
+///
+/// The endpoint and safety settings can be overridden with the
+/// `GEMINI_BASE_URL` and `GEMINI_SAFETY_SETTINGS` environment variables.
+#[cfg(feature = "gemini")]
+pub(crate) fn create_gemini_provider(config: &ModelConfig) -> Result<GeminiProvider> {
+    use crate::agent::providers::gemini::parse_safety_settings;
+
+    let api_key = if let Some(source) = &config.api_key_source {
+        resolve_api_key(source)?
+    } else {
+        // Default to GEMINI_API_KEY environment variable
+        load_api_key_from_env("GEMINI_API_KEY")?
+    };
+
+    let mut provider = GeminiProvider::with_api_key(api_key);
+    if let Ok(base_url) = std::env::var("GEMINI_BASE_URL") {
+        provider = provider.with_base_url(base_url);
+    }
+    if let Ok(spec) = std::env::var("GEMINI_SAFETY_SETTINGS") {
+        let settings = parse_safety_settings(&spec).context("Invalid GEMINI_SAFETY_SETTINGS")?;
+        provider = provider.with_safety_settings(settings);
+    }
+
+    // Set model if specified in config
+    if let Some(model_name) = &config.model_name {
+        provider = provider.with_model(model_name.clone());
+    }
+
+    Ok(provider)
+}
+
+/// Create a Bedrock provider from configuration
+///
+/// Bedrock signs requests with AWS credentials instead of an API key, so
+/// `api_key_source` is not used.
+#[cfg(feature = "bedrock")]
+pub(crate) fn create_bedrock_provider(config: &ModelConfig) -> Result<BedrockProvider> {
+    let mut provider = BedrockProvider::from_config(config.bedrock.as_ref())?;
+
+    // Set model if specified in config
+    if let Some(model_name) = config.model_name.as_ref().filter(|name| !name.is_empty()) {
+        provider = provider.with_model(model_name.clone());
+    }
+
+    Ok(provider)
+}
+
+/// Models offered by one configured provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelListing {
+    /// Provider name as written in the config (e.g. "ollama")
+    pub provider: String,
+    pub models: Vec<String>,
+    /// Why the provider could not be queried
+    pub error: Option<String>,
+}
+
+/// Model configs for every provider named in the config
+///
+/// The default model provider comes first, followed by providers named in
+/// agent profiles (sorted by agent name). Each provider appears once.
+pub fn configured_providers(config: &AppConfig) -> Vec<ModelConfig> {
+    let mut agents: Vec<_> = config.agents.iter().collect();
+    agents.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut named = vec![(
+        config.model.provider.clone(),
+        config.model.model_name.clone(),
+    )];
+    for (_, profile) in agents {
+        if let Some(provider) = &profile.model_provider {
+            named.push((provider.clone(), profile.model_name.clone()));
+        }
+        // Fast providers are only used when fast reasoning is on
+        if let Some(provider) = profile
+            .fast_model_provider
+            .as_ref()
+            .filter(|_| profile.fast_reasoning)
+        {
+            named.push((provider.clone(), profile.fast_model_name.clone()));
+        }
+    }
+
+    let mut providers: Vec<ModelConfig> = Vec::new();
+    for (provider, model_name) in named {
+        let provider = provider.to_lowercase();
+        if providers.iter().any(|p| p.provider == provider) {
+            continue;
+        }
+        let same_as_default = provider.eq_ignore_ascii_case(&config.model.provider);
+        providers.push(ModelConfig {
+            provider,
+            model_name,
+            api_key_source: if same_as_default {
+                config.model.api_key_source.clone()
+            } else {
+                None
+            },
+            ..config.model.clone()
+        });
+    }
+    providers
+}
+
+/// List the models offered by every configured provider
+///
+/// Providers that cannot be created, queried, or do not answer within ten
+/// seconds are reported with an error instead of models.
+pub async fn list_configured_models(config: &AppConfig) -> Vec<ModelListing> {
+    let mut listings = Vec::new();
+    for mut model in configured_providers(config) {
+        // Some providers require a model name up front; it is not used for listing
+        if model.model_name.is_none() {
+            model.model_name = Some(String::new());
+        }
+        let result = match create_provider(&model) {
+            // An unreachable local server should not hold up the other providers
+            Ok(provider) => tokio::time::timeout(MODEL_LIST_TIMEOUT, provider.list_models())
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("timed out listing models"))),
+            Err(err) => Err(err),
+        };
+        listings.push(match result {
+            Ok(models) => ModelListing {
+                provider: model.provider,
+                models,
+                error: None,
+            },
+            Err(err) => ModelListing {
+                provider: model.provider,
+                models: Vec::new(),
+                error: Some(err.to_string()),
+            },
+        });
+    }
+    listings
+}
+
+/// Thinking tokens a Claude or Gemini model gets for `effort`
+pub fn thinking_budget(effort: ReasoningEffort) -> u32 {
+    match effort {
+        ReasoningEffort::Low => 1_024,
+        ReasoningEffort::Medium => 4_096,
+        ReasoningEffort::High => 16_384,
+    }
+}
+
+/// Whether an OpenAI model takes a reasoning effort: the o-series and GPT-5
+pub fn is_openai_reasoning_model(model: &str) -> bool {
+    let model = model.to_ascii_lowercase();
+    ["o1", "o3", "o4", "gpt-5"]
+        .iter()
+        .any(|family| model == *family || model.starts_with(&format!("{}-", family)))
+}
+
+/// `config` with `preset` applied for `model` of provider `kind`
+///
+/// The preset's temperature and top-p replace the profile's. Its reasoning
+/// effort becomes the provider's own setting:
+/// - OpenAI o-series and GPT-5 models get `reasoning_effort`, and lose
+///   temperature and top-p, which they reject
+/// - Anthropic, Gemini and Bedrock's Claude models get a thinking token
+///   budget from [`thinking_budget`]
+/// - other models ignore it
+pub fn apply_preset(
+    mut config: GenerationConfig,
+    preset: &GenerationPreset,
+    kind: ProviderKind,
+    model: &str,
+) -> GenerationConfig {
+    if let Some(temperature) = preset.temperature {
+        config.temperature = Some(temperature);
+    }
+    if let Some(top_p) = preset.top_p {
+        config.top_p = Some(top_p);
+    }
+    let Some(effort) = preset.reasoning_effort else {
+        return config;
+    };
+
+    match kind {
+        #[cfg(feature = "openai")]
+        ProviderKind::OpenAI => {
+            if is_openai_reasoning_model(model) {
+                config.reasoning_effort = Some(effort);
+                config.temperature = None;
+                config.top_p = None;
+                return config;
+            }
+        }
+        #[cfg(feature = "anthropic")]
+        ProviderKind::Anthropic => {
+            config.thinking_budget = Some(thinking_budget(effort));
+            return config;
+        }
+        #[cfg(feature = "gemini")]
+        ProviderKind::Gemini => {
+            config.thinking_budget = Some(thinking_budget(effort));
+            return config;
+        }
+        #[cfg(feature = "bedrock")]
+        ProviderKind::Bedrock => {
+            if model.contains("anthropic.claude") {
+                config.thinking_budget = Some(thinking_budget(effort));
+                return config;
+            }
+        }
+        #[cfg(feature = "ollama")]
+        ProviderKind::Ollama => {}
+        #[cfg(feature = "mlx")]
+        ProviderKind::MLX => {}
+        #[cfg(feature = "lmstudio")]
+        ProviderKind::LMStudio => {}
+        ProviderKind::Mock => {}
+    }
+    debug!(
+        "Model '{}' of {} takes no reasoning effort; ignoring '{}'",
+        model,
+        kind.as_str(),
+        effort
+    );
+    config
+}
+
 /// Resolve API key from a source string
 ///
 /// Supports the following formats:
@@ -183,12 +473,72 @@ mod tests {
             embeddings_model: None,
             api_key_source: None,
             temperature: 0.8,
+            bedrock: None,
         };
 
         let provider = create_provider(&config).unwrap();
         assert_eq!(provider.kind(), ProviderKind::Mock);
     }
 
+    #[test]
+    fn presets_map_to_what_each_provider_supports() {
+        let deep = GenerationPreset::builtin("deep").unwrap();
+        let precise = GenerationPreset::builtin("precise").unwrap();
+
+        let config = apply_preset(
+            GenerationConfig::default(),
+            &precise,
+            ProviderKind::Mock,
+            "mock",
+        );
+        assert_eq!(config.temperature, Some(0.2));
+        assert_eq!(config.top_p, Some(0.9));
+        let config = apply_preset(
+            GenerationConfig::default(),
+            &deep,
+            ProviderKind::Mock,
+            "mock",
+        );
+        assert_eq!(config.temperature, GenerationConfig::default().temperature);
+        assert_eq!(config.reasoning_effort, None);
+        assert_eq!(config.thinking_budget, None);
+
+        assert!(is_openai_reasoning_model("o3-mini"));
+        assert!(is_openai_reasoning_model("gpt-5"));
+        assert!(!is_openai_reasoning_model("gpt-4o"));
+        assert!(thinking_budget(ReasoningEffort::Low) < thinking_budget(ReasoningEffort::High));
+
+        #[cfg(feature = "openai")]
+        {
+            let config = apply_preset(
+                GenerationConfig::default(),
+                &deep,
+                ProviderKind::OpenAI,
+                "o4-mini",
+            );
+            assert_eq!(config.reasoning_effort, Some(ReasoningEffort::High));
+            assert_eq!(config.temperature, None);
+            let config = apply_preset(
+                GenerationConfig::default(),
+                &deep,
+                ProviderKind::OpenAI,
+                "gpt-4o",
+            );
+            assert_eq!(config.reasoning_effort, None);
+        }
+        #[cfg(feature = "anthropic")]
+        {
+            let config = apply_preset(
+                GenerationConfig::default(),
+                &deep,
+                ProviderKind::Anthropic,
+                "claude-sonnet-4-5",
+            );
+            assert_eq!(config.thinking_budget, Some(16_384));
+            assert_eq!(config.reasoning_effort, None);
+        }
+    }
+
     #[test]
     fn test_create_unknown_provider() {
         let config = ModelConfig {
@@ -198,12 +548,59 @@ mod tests {
             embeddings_model: None,
             api_key_source: None,
             temperature: 0.7,
+            bedrock: None,
         };
 
         let result = create_provider(&config);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_with_fallbacks_skips_unknown_providers() {
+        let primary = create_provider(&ModelConfig::default()).unwrap();
+        let mut providers = ProvidersConfig {
+            max_retries: 0,
+            ..ProvidersConfig::default()
+        };
+        let unchanged = with_fallbacks(Arc::clone(&primary), &providers, create_provider);
+        assert!(Arc::ptr_eq(&unchanged, &primary));
+
+        providers.fallback = vec![
+            ModelConfig {
+                provider: "unknown-provider".to_string(),
+                ..ModelConfig::default()
+            },
+            ModelConfig::default(),
+        ];
+        let chained = with_fallbacks(Arc::clone(&primary), &providers, create_provider);
+        assert!(!Arc::ptr_eq(&chained, &primary));
+        assert_eq!(chained.kind(), ProviderKind::Mock);
+    }
+
+    #[tokio::test]
+    async fn test_list_configured_models() {
+        let mut config = AppConfig::default();
+        config.model.provider = "mock".to_string();
+        config.model.api_key_source = Some("env:MOCK_KEY".to_string());
+
+        let mut profile = crate::config::AgentProfile::default();
+        profile.fast_model_provider = Some("Mock".to_string());
+        config.agents.insert("fast".to_string(), profile.clone());
+        profile.model_provider = Some("unknown-provider".to_string());
+        config.agents.insert("other".to_string(), profile);
+
+        let providers = configured_providers(&config);
+        let names: Vec<&str> = providers.iter().map(|p| p.provider.as_str()).collect();
+        assert_eq!(names, vec!["mock", "unknown-provider"]);
+        assert_eq!(providers[1].api_key_source, None);
+
+        let listings = list_configured_models(&config).await;
+        assert!(listings[0].models.contains(&"mock-model".to_string()));
+        assert!(listings[0].error.is_none());
+        assert!(listings[1].models.is_empty());
+        assert!(listings[1].error.is_some());
+    }
+
     #[test]
     fn test_load_api_key_from_env() {
         unsafe {
@@ -3,7 +3,11 @@
 //! This module provides functionality to convert tool definitions into OpenAI's
 //! ChatCompletionTool format and parse function call responses from the SDK.
 
-use async_openai::types::{ChatCompletionTool, ChatCompletionToolType, FunctionObject};
+use crate::agent::model::OutputSchema;
+use async_openai::types::{
+    ChatCompletionTool, ChatCompletionToolType, FunctionObject, ResponseFormat,
+    ResponseFormatJsonSchema,
+};
 use serde_json::{json, Value};
 
 /// Converts parameters to OpenAI function schema format
@@ -51,6 +55,21 @@ pub fn tool_to_openai_function(
     }
 }
 
+/// Converts an output schema into a `json_schema` response format
+///
+/// Strict mode is left off: it rejects schemas with optional properties,
+/// which most Rust types have.
+pub fn schema_to_response_format(schema: &OutputSchema) -> ResponseFormat {
+    ResponseFormat::JsonSchema {
+        json_schema: ResponseFormatJsonSchema {
+            description: schema.description.clone(),
+            name: schema.name.clone(),
+            schema: Some(schema.schema.clone()),
+            strict: None,
+        },
+    }
+}
+
 /// Represents a parsed tool call from OpenAI's function calling response
 #[derive(Debug, Clone)]
 pub struct FunctionCall {
@@ -114,4 +133,17 @@ mod tests {
         let result = parse_tool_call_from_message("call_123", "echo", "invalid json");
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_schema_to_response_format() {
+        let schema = OutputSchema::new("route", json!({"type": "object"}))
+            .with_description("a routing decision");
+        let format = serde_json::to_value(schema_to_response_format(&schema)).unwrap();
+
+        assert_eq!(format["type"], "json_schema");
+        assert_eq!(format["json_schema"]["name"], "route");
+        assert_eq!(format["json_schema"]["description"], "a routing decision");
+        assert_eq!(format["json_schema"]["schema"]["type"], "object");
+        assert!(format["json_schema"].get("strict").is_none());
+    }
 }
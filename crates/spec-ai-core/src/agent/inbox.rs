@@ -0,0 +1,190 @@
+//! Events Delivered Between Turns
+//!
+//! An [`AgentInbox`] collects notifications from outside the conversation,
+//! such as file changes seen by `watch_path`, whether the agent is busy or
+//! idle. A front end keeps a clone, waits on it while the user is quiet, and
+//! runs the pending events as the next turn so the agent can react to them
+//! without anyone typing. Events arriving during a turn wait for the next.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Events kept while waiting for a turn; the oldest are dropped beyond this
+pub const MAX_PENDING_EVENTS: usize = 100;
+
+/// First line of a turn that delivers events
+pub const EVENTS_HEADER: &str = "[events]";
+
+/// Something that happened outside the conversation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentEvent {
+    /// What sent the event, such as a watch id
+    pub source: String,
+    /// One-line description shown to the model
+    pub summary: String,
+    pub received_at: DateTime<Utc>,
+}
+
+impl AgentEvent {
+    pub fn new(source: impl Into<String>, summary: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            summary: summary.into(),
+            received_at: Utc::now(),
+        }
+    }
+}
+
+/// Queue of events waiting for the agent's next turn
+#[derive(Debug, Clone, Default)]
+pub struct AgentInbox {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    pending: Mutex<Pending>,
+    arrived: Notify,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    events: VecDeque<AgentEvent>,
+    /// Events dropped since the inbox was last emptied
+    dropped: usize,
+}
+
+impl AgentInbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `event` for the next turn
+    pub fn push(&self, event: AgentEvent) {
+        {
+            let mut pending = self.lock();
+            pending.events.push_back(event);
+            if pending.events.len() > MAX_PENDING_EVENTS {
+                pending.events.pop_front();
+                pending.dropped += 1;
+            }
+        }
+        self.inner.arrived.notify_one();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().events.is_empty()
+    }
+
+    /// Remove and return every pending event, oldest first
+    pub fn take(&self) -> Vec<AgentEvent> {
+        self.take_counting_dropped().0
+    }
+
+    /// Wait until at least one event is pending
+    pub async fn wait(&self) {
+        loop {
+            if !self.is_empty() {
+                return;
+            }
+            self.inner.arrived.notified().await;
+        }
+    }
+
+    /// Input for a turn that delivers every pending event, or None when
+    /// there are none
+    pub fn take_input(&self) -> Option<String> {
+        let (events, dropped) = self.take_counting_dropped();
+        if events.is_empty() {
+            return None;
+        }
+        Some(events_input(&events, dropped))
+    }
+
+    /// Every pending event and how many were dropped before them
+    fn take_counting_dropped(&self) -> (Vec<AgentEvent>, usize) {
+        let mut pending = self.lock();
+        let dropped = std::mem::take(&mut pending.dropped);
+        (pending.events.drain(..).collect(), dropped)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Pending> {
+        // Events are plain values, so a poisoned lock is still usable
+        self.inner
+            .pending
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Turn input listing `events`, noting how many older ones were dropped
+pub fn events_input(events: &[AgentEvent], dropped: usize) -> String {
+    let mut input = format!(
+        "{}\nThese events arrived since your last turn. Act on them if they call for it; \
+         otherwise acknowledge them briefly.",
+        EVENTS_HEADER
+    );
+    if dropped > 0 {
+        input.push_str(&format!("\n({} earlier events were dropped)", dropped));
+    }
+    for event in events {
+        input.push_str(&format!(
+            "\n- {} [{}]: {}",
+            event.received_at.format("%H:%M:%S"),
+            event.source,
+            event.summary
+        ));
+    }
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn pending_events_become_one_turn() {
+        let inbox = AgentInbox::new();
+        assert!(inbox.take_input().is_none());
+
+        let waiter = {
+            let inbox = inbox.clone();
+            tokio::spawn(async move { inbox.wait().await })
+        };
+        inbox.push(AgentEvent::new("watch-1", "modified src/lib.rs"));
+        inbox.push(AgentEvent::new("watch-1", "created src/new.rs"));
+        tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(inbox.len(), 2);
+
+        let input = inbox.take_input().unwrap();
+        assert!(input.starts_with(EVENTS_HEADER));
+        assert!(input.contains("[watch-1]: modified src/lib.rs"));
+        assert!(input.contains("[watch-1]: created src/new.rs"));
+        assert!(inbox.is_empty());
+    }
+
+    #[test]
+    fn oldest_events_are_dropped_when_full() {
+        let inbox = AgentInbox::new();
+        for n in 0..MAX_PENDING_EVENTS + 3 {
+            inbox.push(AgentEvent::new("test", format!("event {}", n)));
+        }
+        assert_eq!(inbox.len(), MAX_PENDING_EVENTS);
+        let input = inbox.take_input().unwrap();
+        assert!(input.contains("(3 earlier events were dropped)"));
+        assert!(!input.contains("event 2\n"));
+        assert!(input.contains("event 3\n"));
+        assert!(inbox.take_input().is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! Run Interruption
+//!
+//! A [`RunInterrupt`] is a cloneable handle on whatever step its agent is
+//! running. Front ends keep a clone, such as the TUI for Esc or the REST API
+//! for `DELETE /runs/{id}`, and interrupt the run from another task. The
+//! agent stops waiting on the model and cancels running tools, which kills
+//! the processes they spawned. The step still stores an assistant message
+//! saying it was interrupted, so the conversation can simply continue.
+
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Note that ends the stored response of an interrupted step
+pub const INTERRUPTED_NOTE: &str = "[interrupted]";
+
+/// Interrupts the step its agent is running
+#[derive(Debug, Clone, Default)]
+pub struct RunInterrupt {
+    current: Arc<Mutex<Current>>,
+}
+
+#[derive(Debug, Default)]
+struct Current {
+    token: CancellationToken,
+    /// Whether a step has started with `token`
+    started: bool,
+}
+
+impl RunInterrupt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interrupt the step in progress
+    ///
+    /// Before the agent's first step this interrupts that step as soon as it
+    /// starts; between later steps it does nothing.
+    pub fn interrupt(&self) {
+        self.lock().token.cancel();
+    }
+
+    /// Whether the current or last step was interrupted
+    pub fn is_interrupted(&self) -> bool {
+        self.lock().token.is_cancelled()
+    }
+
+    /// Token of the step in progress
+    pub fn token(&self) -> CancellationToken {
+        self.lock().token.clone()
+    }
+
+    /// Token for a new step, fresh unless no step has started yet
+    pub(crate) fn begin(&self) -> CancellationToken {
+        let mut current = self.lock();
+        if current.started {
+            current.token = CancellationToken::new();
+        }
+        current.started = true;
+        current.token.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Current> {
+        // A token cannot be left half-updated, so a poisoned lock is still usable
+        self.current.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Stored form of a response cut short by an interruption
+pub(crate) fn interrupted_response(response: &str) -> String {
+    let response = response.trim_end();
+    if response.is_empty() {
+        INTERRUPTED_NOTE.to_string()
+    } else {
+        format!("{}\n\n{}", response, INTERRUPTED_NOTE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupts_only_reach_the_current_step() {
+        let interrupt = RunInterrupt::new();
+        let first = interrupt.begin();
+        let handle = interrupt.clone();
+        handle.interrupt();
+        assert!(first.is_cancelled());
+        assert!(interrupt.is_interrupted());
+
+        let second = interrupt.begin();
+        assert!(!second.is_cancelled());
+        assert!(!handle.is_interrupted());
+    }
+
+    #[test]
+    fn interrupts_before_the_first_step_are_kept() {
+        let interrupt = RunInterrupt::new();
+        interrupt.interrupt();
+        assert!(interrupt.begin().is_cancelled());
+        assert!(!interrupt.begin().is_cancelled());
+    }
+}
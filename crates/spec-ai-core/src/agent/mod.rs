@@ -1,19 +1,48 @@
+pub mod approval;
+pub mod budget;
 pub mod builder;
+pub mod context;
 pub mod core;
 pub mod factory;
 pub mod function_calling;
+pub mod inbox;
+pub mod interrupt;
 pub mod model;
 pub mod output;
 pub mod providers;
+pub mod question;
+pub mod replay;
+pub mod response_cache;
+pub mod structured;
+pub mod subagent;
 pub mod transcription;
 pub mod transcription_factory;
 pub mod transcription_providers;
 
+pub use approval::{
+    tool_approval_channel, ApprovalDecision, ToolApprovalReceiver, ToolApprovalRequest,
+    ToolApprovalSender,
+};
+pub use budget::{BudgetExceeded, RunBudget, BUDGET_EXCEEDED_REASON};
 pub use builder::AgentBuilder;
+pub use context::ContextWindow;
 pub use core::AgentCore;
-pub use factory::create_provider;
-pub use model::{GenerationConfig, ModelProvider, ModelResponse, ProviderKind, ProviderMetadata};
-pub use output::AgentOutput;
+pub use factory::{apply_preset, create_provider, list_configured_models, ModelListing};
+pub use inbox::{AgentEvent, AgentInbox};
+pub use interrupt::{RunInterrupt, INTERRUPTED_NOTE};
+pub use model::{
+    DeltaStream, GenerationConfig, ModelProvider, ModelResponse, OutputSchema, ProviderKind,
+    ProviderMetadata, StreamDelta, StreamedResponse,
+};
+pub use output::{AgentOutput, ModelUsage, PolicyOutcome, PolicyViolation, SessionUsage};
+pub use question::{
+    user_question_channel, AnswerForm, UserAnswer, UserQuestion, UserQuestionReceiver,
+    UserQuestionRequest, UserQuestionSender, ASK_USER_TOOL,
+};
+pub use replay::{RunTranscript, TranscriptEvent, TranscriptMode};
+pub use response_cache::ResponseCache;
+pub use structured::{generate_structured, Structured};
+pub use subagent::{SubAgentReport, SubAgentSpec, MAX_SUBAGENTS, SPAWN_AGENTS_TOOL};
 pub use transcription::{
     TranscriptionConfig, TranscriptionEvent, TranscriptionProvider, TranscriptionProviderKind,
     TranscriptionProviderMetadata, TranscriptionStats,
@@ -3,9 +3,10 @@
 //! This module defines the core traits and types for integrating with various LLM providers.
 //! It provides a unified interface that abstracts away provider-specific details.
 
-use anyhow::Result;
+pub use crate::config::ReasoningEffort;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
 
@@ -24,6 +25,12 @@ pub struct GenerationConfig {
     pub frequency_penalty: Option<f32>,
     /// Presence penalty
     pub presence_penalty: Option<f32>,
+    /// Reasoning effort, for models that take it as is, such as OpenAI's o-series
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Tokens the model may think with before it answers, for Claude and Gemini
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
 }
 
 impl Default for GenerationConfig {
@@ -35,10 +42,83 @@ impl Default for GenerationConfig {
             top_p: Some(1.0),
             frequency_penalty: None,
             presence_penalty: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         }
     }
 }
 
+/// JSON schema a structured response must conform to
+///
+/// Build one for a Rust type with [`OutputSchema::of`]; see
+/// [`generate_structured`](crate::agent::structured::generate_structured).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputSchema {
+    /// Name of the output, used as the schema or forced tool name
+    pub name: String,
+    /// What the output is for, shown to the model
+    pub description: Option<String>,
+    /// The JSON schema, with subschemas inlined
+    pub schema: serde_json::Value,
+}
+
+impl OutputSchema {
+    pub fn new(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        Self {
+            name: sanitize_schema_name(&name.into()),
+            description: None,
+            schema,
+        }
+    }
+
+    /// Schema of the values `T` deserializes from
+    ///
+    /// Subschemas are inlined, since several providers do not resolve
+    /// `$ref`, so recursive types are not supported.
+    pub fn of<T: schemars::JsonSchema>() -> Self {
+        let schema = schemars::generate::SchemaSettings::draft07()
+            .with(|settings| {
+                settings.inline_subschemas = true;
+                settings.meta_schema = None;
+            })
+            .into_generator()
+            .into_root_schema_for::<T>();
+        Self::new(T::schema_name(), schema.to_value())
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Whether the schema describes a JSON object, which forced tool calls
+    /// require
+    pub fn is_object(&self) -> bool {
+        self.schema.get("type").and_then(|t| t.as_str()) == Some("object")
+    }
+}
+
+/// Restrict a name to the characters every provider accepts for schema and
+/// tool names (letters, digits, `_` and `-`, at most 64 long)
+fn sanitize_schema_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect();
+    if sanitized.is_empty() {
+        "output".to_string()
+    } else {
+        sanitized
+    }
+}
+
 /// Tool call from a model response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
@@ -116,13 +196,180 @@ pub struct ModelResponse {
 }
 
 /// Token usage statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Providers build this with [`TokenUsage::new`] so the total is always
+/// prompt plus completion, whatever the upstream API reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
 }
 
+impl TokenUsage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens.saturating_add(completion_tokens),
+        }
+    }
+
+    /// Rough usage for responses that don't report it, such as streams
+    ///
+    /// Counts about four characters per token, which is close for English
+    /// text with the common BPE tokenizers.
+    pub fn estimate(prompt: &str, completion: &str) -> Self {
+        let tokens = |text: &str| text.chars().count().div_ceil(4) as u32;
+        Self::new(tokens(prompt), tokens(completion))
+    }
+
+    /// Add another response's usage to this one
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.prompt_tokens = self.prompt_tokens.saturating_add(other.prompt_tokens);
+        self.completion_tokens = self
+            .completion_tokens
+            .saturating_add(other.completion_tokens);
+        self.total_tokens = self.total_tokens.saturating_add(other.total_tokens);
+    }
+}
+
+/// Stream of text chunks from [`ModelProvider::stream`]
+pub type TextStream = Pin<Box<dyn Stream<Item = Result<String>> + Send>>;
+
+/// Stream of deltas from [`ModelProvider::stream_deltas`]
+pub type DeltaStream = Pin<Box<dyn Stream<Item = Result<StreamDelta>> + Send>>;
+
+/// One increment of a streamed response
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamDelta {
+    /// Response text, with thinking tokens removed
+    Text(String),
+    /// The model started a tool call
+    ///
+    /// `index` identifies the call in later argument fragments, since
+    /// several calls may stream at once.
+    ToolCallStart {
+        index: usize,
+        id: String,
+        name: String,
+    },
+    /// The next fragment of a tool call's JSON arguments
+    ToolCallArguments { index: usize, fragment: String },
+    /// Token usage for the whole response, sent once the provider reports it
+    Usage(TokenUsage),
+}
+
+#[derive(Debug, Clone)]
+struct PartialToolCall {
+    index: usize,
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Response assembled from the deltas of a stream
+#[derive(Debug, Clone, Default)]
+pub struct StreamedResponse {
+    /// Text received so far
+    pub content: String,
+    /// Usage, if the provider reported it
+    pub usage: Option<TokenUsage>,
+    calls: Vec<PartialToolCall>,
+}
+
+impl StreamedResponse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the next delta
+    pub fn push(&mut self, delta: &StreamDelta) {
+        match delta {
+            StreamDelta::Text(text) => self.content.push_str(text),
+            StreamDelta::ToolCallStart { index, id, name } => self.calls.push(PartialToolCall {
+                index: *index,
+                id: id.clone(),
+                name: name.clone(),
+                arguments: String::new(),
+            }),
+            StreamDelta::ToolCallArguments { index, fragment } => {
+                if let Some(call) = self.calls.iter_mut().rev().find(|c| c.index == *index) {
+                    call.arguments.push_str(fragment);
+                }
+            }
+            StreamDelta::Usage(usage) => self.usage = Some(*usage),
+        }
+    }
+
+    /// Tool call `index` as generated so far, as `name(arguments)`
+    ///
+    /// The arguments may be cut off mid-value while the call is streaming.
+    pub fn tool_call_preview(&self, index: usize) -> Option<String> {
+        self.calls
+            .iter()
+            .rev()
+            .find(|call| call.index == index)
+            .map(|call| format!("{}({})", call.name, call.arguments))
+    }
+
+    /// Whether the model asked for any tools
+    pub fn has_tool_calls(&self) -> bool {
+        !self.calls.is_empty()
+    }
+
+    /// Completed tool calls, in the order they started
+    ///
+    /// Call this once the stream has ended; arguments that are not valid
+    /// JSON are an error.
+    pub fn tool_calls(&self) -> Result<Vec<ToolCall>> {
+        self.calls
+            .iter()
+            .map(|call| {
+                let arguments = if call.arguments.trim().is_empty() {
+                    serde_json::json!({})
+                } else {
+                    serde_json::from_str(&call.arguments).map_err(|e| {
+                        anyhow!("Invalid arguments streamed for tool '{}': {}", call.name, e)
+                    })?
+                };
+                Ok(ToolCall {
+                    id: call.id.clone(),
+                    function_name: call.name.clone(),
+                    arguments,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Keep only the text of a delta stream
+///
+/// Tool calls cannot be carried by a text stream, so they are logged and
+/// dropped.
+pub fn text_only(mut deltas: DeltaStream) -> TextStream {
+    Box::pin(async_stream::stream! {
+        let mut tool_calls = 0;
+        while let Some(delta) = deltas.next().await {
+            match delta {
+                Ok(StreamDelta::Text(text)) => yield Ok(text),
+                Ok(StreamDelta::ToolCallStart { .. }) => tool_calls += 1,
+                Ok(_) => {}
+                Err(err) => {
+                    yield Err(err);
+                    break;
+                }
+            }
+        }
+        if tool_calls > 0 {
+            tracing::warn!(
+                "Stream ended with {} tool call(s), which text streams cannot run",
+                tool_calls
+            );
+        }
+    })
+}
+
 /// Provider metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderMetadata {
@@ -143,6 +390,10 @@ pub enum ProviderKind {
     OpenAI,
     #[cfg(feature = "anthropic")]
     Anthropic,
+    #[cfg(feature = "gemini")]
+    Gemini,
+    #[cfg(feature = "bedrock")]
+    Bedrock,
     #[cfg(feature = "ollama")]
     Ollama,
     #[cfg(feature = "mlx")]
@@ -159,6 +410,10 @@ impl ProviderKind {
             "openai" => Some(ProviderKind::OpenAI),
             #[cfg(feature = "anthropic")]
             "anthropic" => Some(ProviderKind::Anthropic),
+            #[cfg(feature = "gemini")]
+            "gemini" => Some(ProviderKind::Gemini),
+            #[cfg(feature = "bedrock")]
+            "bedrock" => Some(ProviderKind::Bedrock),
             #[cfg(feature = "ollama")]
             "ollama" => Some(ProviderKind::Ollama),
             #[cfg(feature = "mlx")]
@@ -176,6 +431,10 @@ impl ProviderKind {
             ProviderKind::OpenAI => "openai",
             #[cfg(feature = "anthropic")]
             ProviderKind::Anthropic => "anthropic",
+            #[cfg(feature = "gemini")]
+            ProviderKind::Gemini => "gemini",
+            #[cfg(feature = "bedrock")]
+            ProviderKind::Bedrock => "bedrock",
             #[cfg(feature = "ollama")]
             ProviderKind::Ollama => "ollama",
             #[cfg(feature = "mlx")]
@@ -199,11 +458,53 @@ pub trait ModelProvider: Send + Sync {
         config: &GenerationConfig,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>>;
 
+    /// Stream a response as deltas of text, tool calls and usage
+    ///
+    /// Lets callers show a tool call while its arguments are generated.
+    /// Providers that stream tool calls or usage override this; the default
+    /// wraps [`stream`](Self::stream), so it carries text only.
+    async fn stream_deltas(&self, prompt: &str, config: &GenerationConfig) -> Result<DeltaStream> {
+        let text = self.stream(prompt, config).await?;
+        Ok(Box::pin(text.map(|chunk| chunk.map(StreamDelta::Text))))
+    }
+
+    /// Generate a JSON response conforming to `schema`
+    ///
+    /// The JSON text is returned as the response content. Providers that can
+    /// constrain generation override this; the default only describes the
+    /// schema in the prompt. Either way the output should be validated, as
+    /// [`generate_structured`](crate::agent::structured::generate_structured)
+    /// does.
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        let prompt = crate::agent::structured::schema_prompt(prompt, schema);
+        self.generate(&prompt, config).await
+    }
+
     /// Get provider metadata
     fn metadata(&self) -> ProviderMetadata;
 
+    /// List the models this provider can serve
+    ///
+    /// Defaults to the models advertised in the provider metadata; providers
+    /// with a model listing API query it instead.
+    async fn list_models(&self) -> Result<Vec<String>> {
+        Ok(self.metadata().supported_models)
+    }
+
     /// Get the provider kind
     fn kind(&self) -> ProviderKind;
+
+    /// Model used for requests, as reported in usage records
+    ///
+    /// Empty when the provider does not know which model will answer.
+    fn model_name(&self) -> &str {
+        ""
+    }
 }
 
 #[cfg(test)]
@@ -223,6 +524,84 @@ mod tests {
         assert_eq!(ProviderKind::Mock.as_str(), "mock");
     }
 
+    #[test]
+    fn test_token_usage_totals_and_estimates() {
+        let mut usage = TokenUsage::new(12, 30);
+        assert_eq!(usage.total_tokens, 42);
+
+        usage.add(&TokenUsage::new(8, 0));
+        assert_eq!(usage, TokenUsage::new(20, 30));
+
+        let estimate = TokenUsage::estimate("twelve chars", "hi");
+        assert_eq!(estimate, TokenUsage::new(3, 1));
+        assert_eq!(TokenUsage::estimate("", ""), TokenUsage::default());
+    }
+
+    #[test]
+    fn test_streamed_response_assembles_tool_calls() {
+        let deltas = [
+            StreamDelta::Text("Let me look.".to_string()),
+            StreamDelta::ToolCallStart {
+                index: 0,
+                id: "call_1".to_string(),
+                name: "code_search".to_string(),
+            },
+            StreamDelta::ToolCallArguments {
+                index: 0,
+                fragment: r#"{"query": "pars"#.to_string(),
+            },
+            StreamDelta::ToolCallStart {
+                index: 1,
+                id: "call_2".to_string(),
+                name: "echo".to_string(),
+            },
+            StreamDelta::ToolCallArguments {
+                index: 0,
+                fragment: r#"er"}"#.to_string(),
+            },
+            StreamDelta::Usage(TokenUsage::new(10, 4)),
+        ];
+
+        let mut response = StreamedResponse::new();
+        for delta in &deltas[..3] {
+            response.push(delta);
+        }
+        assert_eq!(
+            response.tool_call_preview(0).as_deref(),
+            Some(r#"code_search({"query": "pars)"#)
+        );
+        for delta in &deltas[3..] {
+            response.push(delta);
+        }
+
+        assert_eq!(response.content, "Let me look.");
+        assert_eq!(response.usage, Some(TokenUsage::new(10, 4)));
+        let calls = response.tool_calls().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].function_name, "code_search");
+        assert_eq!(calls[0].arguments["query"], "parser");
+        assert_eq!(calls[1].arguments, serde_json::json!({}));
+
+        response.push(&StreamDelta::ToolCallArguments {
+            index: 1,
+            fragment: "{".to_string(),
+        });
+        assert!(response.tool_calls().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_default_stream_deltas_and_text_only() {
+        let provider = crate::agent::providers::MockProvider::new("streamed answer");
+        let config = GenerationConfig::default();
+
+        let deltas = provider.stream_deltas("hi", &config).await.unwrap();
+        let text: Vec<String> = text_only(deltas)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(text.concat(), "streamed answer ");
+    }
+
     #[test]
     fn test_generation_config_default() {
         let config = GenerationConfig::default();
@@ -240,6 +619,8 @@ mod tests {
             top_p: Some(0.95),
             frequency_penalty: None,
             presence_penalty: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
 
         let json = serde_json::to_string(&config).unwrap();
@@ -1,8 +1,10 @@
 //! Shared agent output data types used by the core loop and CLI
 
+use crate::agent::budget::BudgetExceeded;
 use crate::agent::model::TokenUsage;
 use crate::tools::ToolResult;
 use crate::types::MessageRole;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -13,8 +15,14 @@ pub struct AgentOutput {
     pub response: String,
     /// Message identifier for the persisted assistant response
     pub response_message_id: Option<i64>,
-    /// Token usage information
+    /// Token usage summed over every model call made for this message
     pub token_usage: Option<TokenUsage>,
+    /// Estimated cost of this message, when every model used has pricing configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    /// Running token totals for the session, including this message
+    #[serde(default)]
+    pub session_usage: SessionUsage,
     /// Detailed tool invocations performed during this turn
     pub tool_invocations: Vec<ToolInvocation>,
     /// Finish reason
@@ -34,6 +42,93 @@ pub struct AgentOutput {
     /// Snapshot of graph state for debugging purposes
     #[serde(skip_serializing_if = "Option::is_none")]
     pub graph_debug: Option<GraphDebugInfo>,
+    /// Actions refused or escalated by the agent profile or policy engine during this turn
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_violations: Vec<PolicyViolation>,
+    /// Limit that stopped this step, when it ran out of budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_exceeded: Option<BudgetExceeded>,
+}
+
+/// Tokens and estimated cost for one provider and model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    /// Number of model calls
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Estimated cost, or `None` if the model has no pricing configured
+    pub cost: Option<f64>,
+}
+
+impl ModelUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Running token usage for a session, broken down per model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionUsage {
+    /// One entry per provider and model, in order of first use
+    pub models: Vec<ModelUsage>,
+}
+
+impl SessionUsage {
+    /// Add one model call's usage and estimated cost
+    pub fn record(&mut self, provider: &str, model: &str, usage: &TokenUsage, cost: Option<f64>) {
+        self.add(ModelUsage {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            calls: 1,
+            prompt_tokens: u64::from(usage.prompt_tokens),
+            completion_tokens: u64::from(usage.completion_tokens),
+            cost,
+        });
+    }
+
+    /// Merge usage into the entry for the same provider and model
+    pub fn add(&mut self, usage: ModelUsage) {
+        match self
+            .models
+            .iter_mut()
+            .find(|entry| entry.provider == usage.provider && entry.model == usage.model)
+        {
+            Some(entry) => {
+                entry.calls += usage.calls;
+                entry.prompt_tokens += usage.prompt_tokens;
+                entry.completion_tokens += usage.completion_tokens;
+                entry.cost = entry.cost.zip(usage.cost).map(|(total, cost)| total + cost);
+            }
+            None => self.models.push(usage),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    pub fn prompt_tokens(&self) -> u64 {
+        self.models.iter().map(|m| m.prompt_tokens).sum()
+    }
+
+    pub fn completion_tokens(&self) -> u64 {
+        self.models.iter().map(|m| m.completion_tokens).sum()
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.models.iter().map(ModelUsage::total_tokens).sum()
+    }
+
+    /// Total estimated cost; `None` unless every model is priced, so the figure is never understated
+    pub fn cost(&self) -> Option<f64> {
+        if self.models.is_empty() {
+            return None;
+        }
+        self.models.iter().map(|m| m.cost).sum()
+    }
 }
 
 /// Minimal snapshot of a recent graph node for debugging output
@@ -66,6 +161,9 @@ pub struct ToolInvocation {
     pub output: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Structured payload of the result, see [`ToolResult::data`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
 }
 
 impl ToolInvocation {
@@ -82,6 +180,7 @@ impl ToolInvocation {
             success: result.success,
             output,
             error: result.error.clone(),
+            data: result.data.clone(),
         }
     }
 }
@@ -108,3 +207,51 @@ pub struct MemoryRecallMatch {
     pub role: MessageRole,
     pub preview: String,
 }
+
+/// How a policy violation was resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyOutcome {
+    /// The action was refused
+    Denied,
+    /// The action was blocked by policy but approved by the user
+    Escalated,
+    /// The action was blocked by policy but ran because the agent only audits
+    Audited,
+}
+
+impl PolicyOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyOutcome::Denied => "denied",
+            PolicyOutcome::Escalated => "escalated",
+            PolicyOutcome::Audited => "audited",
+        }
+    }
+}
+
+/// A single action blocked by the agent profile or policy engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    /// Rule or reason that blocked the action
+    pub rule: String,
+    /// Action that was checked (e.g. "tool_call")
+    pub action: String,
+    /// Resource the action targeted (e.g. the tool name)
+    pub resource: String,
+    pub outcome: PolicyOutcome,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl PolicyViolation {
+    /// One-line summary suitable for status and reasoning panels
+    pub fn summary(&self) -> String {
+        format!(
+            "Policy: {} {} '{}' ({})",
+            self.outcome.as_str(),
+            self.action,
+            self.resource,
+            self.rule
+        )
+    }
+}
@@ -1,12 +1,15 @@
 //! Anthropic Model Provider
 //!
-//! Integration with Anthropic's API (Claude models).
-//! Supports Claude 3 family models including Opus, Sonnet, and Haiku.
+//! Integration with Anthropic's Messages API (Claude models).
+//! Supports Claude 3 family models including Opus, Sonnet, and Haiku,
+//! native tool use, and `System:` prompt sections sent as the system prompt.
 
 use crate::agent::model::{
-    parse_thinking_tokens, GenerationConfig, ModelProvider, ModelResponse, ProviderKind,
-    ProviderMetadata, TokenUsage, ToolCall,
+    parse_thinking_tokens, text_only, DeltaStream, GenerationConfig, ModelProvider, ModelResponse,
+    OutputSchema, ProviderKind, ProviderMetadata, StreamDelta, TokenUsage, ToolCall,
 };
+use crate::agent::structured;
+use crate::error::Error;
 use anyhow::{anyhow, Result};
 use async_stream::stream;
 use async_trait::async_trait;
@@ -15,7 +18,18 @@ use serde::{Deserialize, Serialize};
 use std::pin::Pin;
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_MODELS_URL: &str = "https://api.anthropic.com/v1/models";
 const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+const DEFAULT_MODEL: &str = "claude-3-5-sonnet-20241022";
+/// Sent when the generation config leaves it unset, since the API requires it
+const DEFAULT_MAX_TOKENS: u32 = 2048;
+/// Upper end of the Messages API temperature range
+const MAX_TEMPERATURE: f32 = 1.0;
+/// Prefix the agent puts on system prompt paragraphs
+const SYSTEM_PREFIX: &str = "System: ";
+/// Paragraphs of an agent prompt that start the conversation part
+const CONVERSATION_MARKERS: [&str; 3] =
+    ["Available tools:\n", "Previous conversation:\n", "user: "];
 
 /// Message in an Anthropic conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +46,35 @@ pub struct Tool {
     pub input_schema: serde_json::Value,
 }
 
+impl Tool {
+    /// Build a tool from a spec-ai tool definition
+    ///
+    /// The API requires an object schema, so parameters without a type are
+    /// treated as one.
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: &serde_json::Value,
+    ) -> Self {
+        let mut input_schema = match parameters {
+            serde_json::Value::Object(schema) => schema.clone(),
+            _ => serde_json::Map::new(),
+        };
+        input_schema
+            .entry("type")
+            .or_insert_with(|| "object".into());
+        input_schema
+            .entry("properties")
+            .or_insert_with(|| serde_json::json!({}));
+
+        Self {
+            name: name.into(),
+            description: description.into(),
+            input_schema: serde_json::Value::Object(input_schema),
+        }
+    }
+}
+
 /// Anthropic API request
 #[derive(Debug, Clone, Serialize)]
 struct AnthropicRequest {
@@ -48,8 +91,14 @@ struct AnthropicRequest {
     stop_sequences: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
+    /// Forces a particular tool, e.g. `{"type": "tool", "name": "..."}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     stream: Option<bool>,
+    /// Extended thinking, e.g. `{"type": "enabled", "budget_tokens": 4096}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<serde_json::Value>,
 }
 
 /// Content block in Anthropic response
@@ -64,6 +113,12 @@ enum ContentBlock {
         name: String,
         input: serde_json::Value,
     },
+    /// Extended thinking output
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String },
+    /// Blocks spec-ai has no use for, such as redacted thinking
+    #[serde(other)]
+    Other,
 }
 
 /// Anthropic API response
@@ -80,8 +135,26 @@ struct AnthropicResponse {
 /// Token usage in Anthropic response
 #[derive(Debug, Clone, Deserialize)]
 struct Usage {
+    /// Absent from streaming `message_delta` events
+    #[serde(default)]
     input_tokens: u32,
     output_tokens: u32,
+    /// Prompt tokens written to the prompt cache, billed on top of `input_tokens`
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    /// Prompt tokens served from the prompt cache
+    #[serde(default)]
+    cache_read_input_tokens: u32,
+}
+
+impl Usage {
+    /// Count cached prompt tokens as prompt tokens, as OpenAI-compatible APIs do
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage::new(
+            self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens,
+            self.output_tokens,
+        )
+    }
 }
 
 /// Streaming event from Anthropic
@@ -106,6 +179,16 @@ enum StreamEvent {
     MessageStop,
     #[serde(rename = "ping")]
     Ping,
+    #[serde(rename = "error")]
+    Error { error: ApiError },
+}
+
+/// Error reported in the middle of a stream, such as `overloaded_error`
+#[derive(Debug, Clone, Deserialize)]
+struct ApiError {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -126,12 +209,126 @@ enum Delta {
     InputJsonDelta { partial_json: String },
 }
 
+/// Models API response
+#[derive(Debug, Clone, Deserialize)]
+struct ModelList {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ModelInfo {
+    id: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
-#[allow(dead_code)]
 struct MessageDelta {
     stop_reason: Option<String>,
 }
 
+/// Turns stream events into deltas while tracking usage and the stop reason
+///
+/// Text inside `<think>` tags is held back, matching [`parse_thinking_tokens`].
+/// Tool calls are passed on by content block index.
+#[derive(Debug, Default)]
+struct StreamState {
+    buffer: String,
+    in_think_block: bool,
+    think_ended: bool,
+    /// Prompt usage from `message_start`; `message_delta` adds the output count
+    usage: Option<Usage>,
+    stop_reason: Option<String>,
+}
+
+impl StreamState {
+    /// Handle one event, returning the deltas it produced
+    fn handle(&mut self, event: StreamEvent) -> Result<Vec<StreamDelta>> {
+        match event {
+            StreamEvent::MessageStart { message } => self.usage = Some(message.usage),
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block: ContentBlock::ToolUse { id, name, .. },
+            } => {
+                return Ok(vec![StreamDelta::ToolCallStart { index, id, name }]);
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => match delta {
+                Delta::TextDelta { text } => {
+                    return Ok(self.push_text(&text).into_iter().collect())
+                }
+                Delta::InputJsonDelta { partial_json } => {
+                    return Ok(vec![StreamDelta::ToolCallArguments {
+                        index,
+                        fragment: partial_json,
+                    }]);
+                }
+            },
+            StreamEvent::MessageDelta { delta, usage } => {
+                if let Some(reason) = delta.stop_reason {
+                    self.stop_reason = Some(reason);
+                }
+                // The output count is cumulative
+                let output_tokens = usage.output_tokens;
+                self.usage.get_or_insert(usage).output_tokens = output_tokens;
+            }
+            StreamEvent::MessageStop => {
+                let mut deltas: Vec<StreamDelta> = self.flush().into_iter().collect();
+                if let Some(usage) = &self.usage {
+                    deltas.push(StreamDelta::Usage(usage.token_usage()));
+                }
+                return Ok(deltas);
+            }
+            StreamEvent::Error { error } => {
+                return Err(anyhow!(
+                    "Anthropic stream error ({}): {}",
+                    error.kind,
+                    error.message
+                ));
+            }
+            _ => {}
+        }
+        Ok(Vec::new())
+    }
+
+    fn push_text(&mut self, text: &str) -> Option<StreamDelta> {
+        self.buffer.push_str(text);
+
+        // Check if we're entering a think block
+        if self.buffer.contains("<think>") && !self.in_think_block {
+            self.in_think_block = true;
+        }
+
+        // Check if we're exiting a think block
+        if self.buffer.contains("</think>") && self.in_think_block {
+            self.in_think_block = false;
+            self.think_ended = true;
+            // Clear buffer up to and including </think>
+            if let Some(idx) = self.buffer.find("</think>") {
+                self.buffer = self.buffer[idx + "</think>".len()..].to_string();
+            }
+        }
+
+        // Only yield content if we're not in a think block
+        if !self.in_think_block && (self.think_ended || !self.buffer.contains("<think>")) {
+            return self.flush();
+        }
+        None
+    }
+
+    /// Take any buffered text outside a think block
+    fn flush(&mut self) -> Option<StreamDelta> {
+        if self.buffer.is_empty() || self.in_think_block {
+            return None;
+        }
+        Some(StreamDelta::Text(std::mem::take(&mut self.buffer)))
+    }
+
+    /// Log how the response ended, once the stream is done
+    fn finish(&self) {
+        if self.stop_reason.as_deref() == Some("max_tokens") {
+            tracing::warn!("Anthropic stream was cut off at the max_tokens limit");
+        }
+    }
+}
+
 /// Anthropic provider for Claude models
 #[derive(Debug, Clone)]
 pub struct AnthropicProvider {
@@ -156,13 +353,7 @@ impl AnthropicProvider {
         let api_key = std::env::var("ANTHROPIC_API_KEY")
             .map_err(|_| anyhow!("ANTHROPIC_API_KEY environment variable not set"))?;
 
-        Ok(Self {
-            client: reqwest::Client::new(),
-            api_key,
-            model: "claude-3-5-sonnet-20241022".to_string(),
-            system_message: None,
-            tools: None,
-        })
+        Ok(Self::with_api_key(api_key))
     }
 
     /// Create a new Anthropic provider with a custom API key
@@ -170,7 +361,7 @@ impl AnthropicProvider {
         Self {
             client: reqwest::Client::new(),
             api_key: api_key.into(),
-            model: "claude-3-5-sonnet-20241022".to_string(),
+            model: DEFAULT_MODEL.to_string(),
             system_message: None,
             tools: None,
         }
@@ -195,51 +386,104 @@ impl AnthropicProvider {
     }
 
     /// Build the request for the Anthropic API
+    ///
+    /// Leading `System:` paragraphs of the prompt are moved into the system
+    /// prompt after the provider's own system message.
     fn build_request(
         &self,
         prompt: &str,
         config: &GenerationConfig,
         stream: bool,
     ) -> AnthropicRequest {
+        let (prompt_system, conversation) = split_system_prompt(prompt);
+        let system: Vec<String> = self
+            .system_message
+            .iter()
+            .cloned()
+            .chain(prompt_system)
+            .collect();
+
         let messages = vec![Message {
             role: "user".to_string(),
-            content: prompt.to_string(),
+            content: conversation.to_string(),
         }];
 
-        AnthropicRequest {
+        // The API rejects stop sequences that are only whitespace
+        let stop_sequences: Vec<String> = config
+            .stop_sequences
+            .iter()
+            .flatten()
+            .filter(|stop| !stop.trim().is_empty())
+            .cloned()
+            .collect();
+
+        let mut max_tokens = config.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        if let Some(budget) = config.thinking_budget {
+            // The thinking budget counts towards max_tokens and must leave room for the answer
+            if max_tokens <= budget {
+                max_tokens = budget + DEFAULT_MAX_TOKENS;
+            }
+        }
+
+        let mut request = AnthropicRequest {
             model: self.model.clone(),
             messages,
-            max_tokens: config.max_tokens.unwrap_or(2048),
-            system: self.system_message.clone(),
-            temperature: config.temperature,
-            top_p: config.top_p,
-            stop_sequences: config.stop_sequences.clone(),
+            max_tokens,
+            system: if system.is_empty() {
+                None
+            } else {
+                Some(system.join("\n\n"))
+            },
+            temperature: config
+                .temperature
+                .map(|temperature| temperature.clamp(0.0, MAX_TEMPERATURE)),
+            // Top-p of 1.0 samples from every token, so leave it to the API default
+            top_p: config.top_p.filter(|top_p| *top_p < 1.0),
+            stop_sequences: if stop_sequences.is_empty() {
+                None
+            } else {
+                Some(stop_sequences)
+            },
             tools: self.tools.clone(),
+            tool_choice: None,
             stream: if stream { Some(true) } else { None },
+            thinking: None,
+        };
+        if let Some(budget) = config.thinking_budget {
+            request.thinking =
+                Some(serde_json::json!({"type": "enabled", "budget_tokens": budget}));
+            // Sampling settings cannot be changed while thinking
+            request.temperature = None;
+            request.top_p = None;
         }
+        request
     }
 
-    /// Parse SSE (Server-Sent Events) line
-    fn parse_sse_line(line: &str) -> Option<StreamEvent> {
-        if let Some(data) = line.strip_prefix("data: ") {
-            serde_json::from_str(data).ok()
-        } else {
-            None
-        }
-    }
-}
-
-impl Default for AnthropicProvider {
-    fn default() -> Self {
-        Self::new().expect("Failed to create default Anthropic provider")
+    /// Build a request that forces a single tool taking `schema` as its
+    /// input, so the tool call arguments are the structured response
+    fn build_structured_request(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> AnthropicRequest {
+        let description = schema
+            .description
+            .clone()
+            .unwrap_or_else(|| "Report the response".to_string());
+        // Thinking cannot be combined with a forced tool choice
+        let config = GenerationConfig {
+            thinking_budget: None,
+            ..config.clone()
+        };
+        let mut request = self.build_request(prompt, &config, false);
+        request.tools = Some(vec![Tool::new(&schema.name, description, &schema.schema)]);
+        request.tool_choice = Some(serde_json::json!({"type": "tool", "name": schema.name}));
+        request
     }
-}
-
-#[async_trait]
-impl ModelProvider for AnthropicProvider {
-    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
-        let request = self.build_request(prompt, config, false);
 
+    /// Send a non-streaming request
+    async fn send(&self, request: &AnthropicRequest) -> Result<ModelResponse> {
         // Make the API call
         let response = self
             .client
@@ -247,7 +491,7 @@ impl ModelProvider for AnthropicProvider {
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", ANTHROPIC_API_VERSION)
             .header("content-type", "application/json")
-            .json(&request)
+            .json(request)
             .send()
             .await
             .map_err(|e| anyhow!("Anthropic API request failed: {}", e))?;
@@ -256,7 +500,12 @@ impl ModelProvider for AnthropicProvider {
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Anthropic API error ({}): {}", status, error_text));
+            return Err(Error::Provider {
+                provider: "Anthropic".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
         }
 
         // Parse the response
@@ -265,8 +514,13 @@ impl ModelProvider for AnthropicProvider {
             .await
             .map_err(|e| anyhow!("Failed to parse Anthropic response: {}", e))?;
 
-        // Extract text content and tool calls
+        Ok(Self::into_model_response(api_response))
+    }
+
+    /// Convert a Messages API response into the spec-ai response format
+    fn into_model_response(api_response: AnthropicResponse) -> ModelResponse {
         let mut raw_content = String::new();
+        let mut thinking = String::new();
         let mut tool_calls = Vec::new();
 
         for block in api_response.content {
@@ -281,22 +535,32 @@ impl ModelProvider for AnthropicProvider {
                         arguments: input,
                     });
                 }
+                ContentBlock::Thinking { thinking: text } => {
+                    thinking.push_str(&text);
+                }
+                ContentBlock::Other => {}
             }
         }
 
         // Parse thinking tokens if present
-        let (reasoning, content) = parse_thinking_tokens(&raw_content);
-
-        let usage = TokenUsage {
-            prompt_tokens: api_response.usage.input_tokens,
-            completion_tokens: api_response.usage.output_tokens,
-            total_tokens: api_response.usage.input_tokens + api_response.usage.output_tokens,
+        let (tagged_reasoning, content) = parse_thinking_tokens(&raw_content);
+        let reasoning = if thinking.is_empty() {
+            tagged_reasoning
+        } else {
+            Some(thinking)
         };
 
-        Ok(ModelResponse {
+        if api_response.stop_reason.as_deref() == Some("max_tokens") {
+            tracing::warn!(
+                "Anthropic response from {} was cut off at the max_tokens limit",
+                api_response.model
+            );
+        }
+
+        ModelResponse {
             content,
             model: api_response.model,
-            usage: Some(usage),
+            usage: Some(api_response.usage.token_usage()),
             finish_reason: api_response.stop_reason,
             tool_calls: if tool_calls.is_empty() {
                 None
@@ -304,7 +568,78 @@ impl ModelProvider for AnthropicProvider {
                 Some(tool_calls)
             },
             reasoning,
-        })
+        }
+    }
+
+    /// Parse SSE (Server-Sent Events) line
+    fn parse_sse_line(line: &str) -> Option<StreamEvent> {
+        let data = line.strip_prefix("data:")?.trim_start();
+        serde_json::from_str(data).ok()
+    }
+}
+
+/// Split the system prompt the agent writes at the top of its prompts from
+/// the conversation that follows it
+///
+/// The system part runs from a leading `System:` paragraph up to the tool
+/// list, the conversation history or the user turn. Prompts without one are
+/// returned whole.
+fn split_system_prompt(prompt: &str) -> (Option<String>, &str) {
+    if !prompt.starts_with(SYSTEM_PREFIX) {
+        return (None, prompt);
+    }
+
+    let mut end = None;
+    let mut offset = 0;
+    for paragraph in prompt.split("\n\n") {
+        if offset > 0
+            && CONVERSATION_MARKERS
+                .iter()
+                .any(|marker| paragraph.starts_with(marker))
+        {
+            end = Some(offset);
+            break;
+        }
+        offset += paragraph.len() + "\n\n".len();
+    }
+    let Some(end) = end else {
+        return (None, prompt);
+    };
+
+    let system = prompt[..end]
+        .split("\n\n")
+        .map(|paragraph| paragraph.strip_prefix(SYSTEM_PREFIX).unwrap_or(paragraph))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    (Some(system.trim().to_string()), &prompt[end..])
+}
+
+impl Default for AnthropicProvider {
+    fn default() -> Self {
+        Self::new().expect("Failed to create default Anthropic provider")
+    }
+}
+
+#[async_trait]
+impl ModelProvider for AnthropicProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        self.send(&self.build_request(prompt, config, false)).await
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        // Tool inputs must be objects, so other shapes are only described
+        if !schema.is_object() {
+            let prompt = structured::schema_prompt(prompt, schema);
+            return self.generate(&prompt, config).await;
+        }
+        let request = self.build_structured_request(prompt, schema, config);
+        let response = self.send(&request).await?;
+        Ok(structured::forced_tool_content(response, schema))
     }
 
     async fn stream(
@@ -312,6 +647,10 @@ impl ModelProvider for AnthropicProvider {
         prompt: &str,
         config: &GenerationConfig,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        Ok(text_only(self.stream_deltas(prompt, config).await?))
+    }
+
+    async fn stream_deltas(&self, prompt: &str, config: &GenerationConfig) -> Result<DeltaStream> {
         let request = self.build_request(prompt, config, true);
 
         // Make the streaming API call
@@ -330,11 +669,12 @@ impl ModelProvider for AnthropicProvider {
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "Anthropic streaming API error ({}): {}",
-                status,
-                error_text
-            ));
+            return Err(Error::Provider {
+                provider: "Anthropic".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
         }
 
         // Convert the response into a stream
@@ -343,64 +683,32 @@ impl ModelProvider for AnthropicProvider {
         let stream = stream! {
             use futures::StreamExt;
 
-            let mut buffer = String::new();
-            let mut line_buffer = String::new();
-            let mut in_think_block = false;
-            let mut think_ended = false;
+            let mut state = StreamState::default();
+            // Raw bytes so characters split across chunks decode intact
+            let mut line_buffer: Vec<u8> = Vec::new();
 
             let mut stream = byte_stream;
-            while let Some(result) = stream.next().await {
+            'read: while let Some(result) = stream.next().await {
                 match result {
                     Ok(chunk) => {
-                        let chunk_str = String::from_utf8_lossy(&chunk);
-                        line_buffer.push_str(&chunk_str);
+                        line_buffer.extend_from_slice(&chunk);
 
                         // Process complete lines
-                        while let Some(newline_pos) = line_buffer.find('\n') {
-                            let line = line_buffer[..newline_pos].trim().to_string();
-                            line_buffer = line_buffer[newline_pos + 1..].to_string();
+                        while let Some(newline_pos) = line_buffer.iter().position(|b| *b == b'\n') {
+                            let line_bytes: Vec<u8> = line_buffer.drain(..=newline_pos).collect();
+                            let line = String::from_utf8_lossy(&line_bytes);
 
                             // Parse SSE line
-                            if let Some(event) = Self::parse_sse_line(&line) {
-                                match event {
-                                    StreamEvent::ContentBlockDelta { delta, .. } => {
-                                        if let Delta::TextDelta { text } = delta {
-                                            buffer.push_str(&text);
-
-                                            // Check if we're entering a think block
-                                            if buffer.contains("<think>") && !in_think_block {
-                                                in_think_block = true;
-                                            }
-
-                                            // Check if we're exiting a think block
-                                            if buffer.contains("</think>") && in_think_block {
-                                                in_think_block = false;
-                                                think_ended = true;
-                                                // Clear buffer up to and including </think>
-                                                if let Some(idx) = buffer.find("</think>") {
-                                                    buffer = buffer[idx + "</think>".len()..].to_string();
-                                                }
-                                            }
-
-                                            // Only yield content if we're not in a think block
-                                            if !in_think_block && (think_ended || !buffer.contains("<think>")) {
-                                                let output = buffer.clone();
-                                                buffer.clear();
-                                                if !output.is_empty() {
-                                                    yield Ok(output);
-                                                }
-                                            }
+                            if let Some(event) = Self::parse_sse_line(line.trim()) {
+                                match state.handle(event) {
+                                    Ok(deltas) => {
+                                        for delta in deltas {
+                                            yield Ok(delta);
                                         }
                                     }
-                                    StreamEvent::MessageStop => {
-                                        // Yield any remaining buffered content
-                                        if !buffer.is_empty() && !in_think_block {
-                                            yield Ok(buffer.clone());
-                                            buffer.clear();
-                                        }
-                                    }
-                                    _ => {
-                                        // Ignore other event types
+                                    Err(e) => {
+                                        yield Err(e);
+                                        break 'read;
                                     }
                                 }
                             }
@@ -414,14 +722,43 @@ impl ModelProvider for AnthropicProvider {
             }
 
             // Yield any remaining buffered content
-            if !buffer.is_empty() && !in_think_block {
-                yield Ok(buffer);
+            if let Some(text) = state.flush() {
+                yield Ok(text);
             }
+            state.finish();
         };
 
         Ok(Box::pin(stream))
     }
 
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .client
+            .get(ANTHROPIC_MODELS_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to list Anthropic models: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Error::Provider {
+                provider: "Anthropic".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
+        }
+
+        let models: ModelList = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Anthropic model list: {}", e))?;
+        Ok(models.data.into_iter().map(|model| model.id).collect())
+    }
+
     fn metadata(&self) -> ProviderMetadata {
         ProviderMetadata {
             name: "Anthropic".to_string(),
@@ -439,11 +776,16 @@ impl ModelProvider for AnthropicProvider {
     fn kind(&self) -> ProviderKind {
         ProviderKind::Anthropic
     }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent::model::StreamedResponse;
 
     #[test]
     fn test_anthropic_provider_creation() {
@@ -528,4 +870,177 @@ mod tests {
 
         assert_eq!(request.stream, Some(true));
     }
+
+    #[test]
+    fn test_build_structured_request_forces_schema_tool() {
+        let provider = AnthropicProvider::with_api_key("test-key").with_tools(vec![Tool::new(
+            "echo",
+            "Echo",
+            &serde_json::json!({}),
+        )]);
+        let schema = OutputSchema::new(
+            "route",
+            serde_json::json!({"type": "object", "properties": {"agent": {"type": "string"}}}),
+        );
+
+        let request =
+            provider.build_structured_request("Route this", &schema, &GenerationConfig::default());
+        let body = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(body["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(body["tools"][0]["name"], "route");
+        assert_eq!(body["tools"][0]["input_schema"], schema.schema);
+        assert_eq!(
+            body["tool_choice"],
+            serde_json::json!({"type": "tool", "name": "route"})
+        );
+        assert!(serde_json::to_value(provider.build_request(
+            "Hi",
+            &GenerationConfig::default(),
+            false
+        ))
+        .unwrap()
+        .get("tool_choice")
+        .is_none());
+    }
+
+    #[test]
+    fn test_build_request_moves_system_prompt_and_limits() {
+        let provider = AnthropicProvider::with_api_key("test-key")
+            .with_system_message("Provider prompt")
+            .with_tools(vec![Tool::new(
+                "echo",
+                "Echo a message",
+                &serde_json::json!({"properties": {"message": {"type": "string"}}}),
+            )]);
+        let config = GenerationConfig {
+            temperature: Some(1.5),
+            stop_sequences: Some(vec!["END".to_string(), "\n".to_string()]),
+            ..Default::default()
+        };
+        let prompt = "System: Be terse.\n\nSecond paragraph.\n\nSystem: Speech mode.\n\nAvailable tools:\n- echo: Echo\n\nuser: hi\nassistant:";
+
+        let request = provider.build_request(prompt, &config, false);
+
+        assert_eq!(
+            request.system.as_deref(),
+            Some("Provider prompt\n\nBe terse.\n\nSecond paragraph.\n\nSpeech mode.")
+        );
+        assert_eq!(
+            request.messages[0].content,
+            "Available tools:\n- echo: Echo\n\nuser: hi\nassistant:"
+        );
+        assert_eq!(request.temperature, Some(1.0));
+        assert_eq!(request.top_p, None);
+        assert_eq!(request.stop_sequences, Some(vec!["END".to_string()]));
+        let tools = request.tools.unwrap();
+        assert_eq!(tools[0].input_schema["type"], "object");
+        assert_eq!(
+            tools[0].input_schema["properties"]["message"]["type"],
+            "string"
+        );
+    }
+
+    #[test]
+    fn test_build_request_enables_thinking_with_room_to_answer() {
+        let provider = AnthropicProvider::with_api_key("test-key");
+        let config = GenerationConfig {
+            temperature: Some(0.7),
+            max_tokens: Some(1024),
+            thinking_budget: Some(4096),
+            ..Default::default()
+        };
+
+        let request = provider.build_request("Hello", &config, false);
+        assert_eq!(request.thinking.as_ref().unwrap()["budget_tokens"], 4096);
+        assert!(request.max_tokens > 4096);
+        assert_eq!(request.temperature, None);
+
+        let schema = OutputSchema::new("report", serde_json::json!({"type": "object"}));
+        let request = provider.build_structured_request("Hello", &schema, &config);
+        assert!(request.thinking.is_none());
+    }
+
+    #[test]
+    fn test_split_system_prompt_keeps_prompts_without_conversation() {
+        assert_eq!(split_system_prompt("user: hi"), (None, "user: hi"));
+        assert_eq!(
+            split_system_prompt("System: only instructions"),
+            (None, "System: only instructions")
+        );
+    }
+
+    #[test]
+    fn test_response_maps_tool_use_and_stop_reason() {
+        let api_response: AnthropicResponse = serde_json::from_value(serde_json::json!({
+            "id": "msg_1",
+            "model": "claude-3-5-sonnet-20241022",
+            "content": [
+                {"type": "thinking", "thinking": "Need the weather.", "signature": "sig"},
+                {"type": "text", "text": "Checking."},
+                {"type": "tool_use", "id": "toolu_1", "name": "weather", "input": {"city": "Oslo"}},
+                {"type": "redacted_thinking", "data": "..."}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        }))
+        .unwrap();
+
+        let response = AnthropicProvider::into_model_response(api_response);
+
+        assert_eq!(response.content, "Checking.");
+        assert_eq!(response.reasoning.as_deref(), Some("Need the weather."));
+        assert_eq!(response.finish_reason.as_deref(), Some("tool_use"));
+        let calls = response.tool_calls.unwrap();
+        assert_eq!(calls[0].id, "toolu_1");
+        assert_eq!(calls[0].function_name, "weather");
+        assert_eq!(calls[0].arguments["city"], "Oslo");
+        assert_eq!(response.usage, Some(TokenUsage::new(10, 5)));
+    }
+
+    #[test]
+    fn test_stream_state_collects_text_and_tool_input() {
+        let events = [
+            r#"data: {"type":"message_start","message":{"id":"msg_1","model":"claude","usage":{"input_tokens":3,"output_tokens":1}}}"#,
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"<think>hmm</think>Hello"}}"#,
+            r#"data: {"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"echo","input":{}}}"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"message\": "}}"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"hi\"}"}}"#,
+            r#"data: {"type":"content_block_stop","index":1}"#,
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":9}}"#,
+            r#"data: {"type":"message_stop"}"#,
+        ];
+
+        let mut state = StreamState::default();
+        let mut response = StreamedResponse::new();
+        for line in events {
+            let event = AnthropicProvider::parse_sse_line(line).unwrap();
+            for delta in state.handle(event).unwrap() {
+                response.push(&delta);
+            }
+        }
+
+        assert_eq!(response.content, "Hello");
+        assert_eq!(state.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(response.usage, Some(TokenUsage::new(3, 9)));
+        let tool_calls = response.tool_calls().unwrap();
+        assert_eq!(tool_calls.len(), 1);
+        assert_eq!(tool_calls[0].function_name, "echo");
+        assert_eq!(tool_calls[0].arguments["message"], "hi");
+    }
+
+    #[test]
+    fn test_stream_error_event_is_an_error() {
+        let event = AnthropicProvider::parse_sse_line(
+            r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        )
+        .unwrap();
+
+        let err = StreamState::default().handle(event).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Anthropic stream error (overloaded_error): Overloaded"
+        );
+    }
 }
@@ -0,0 +1,1188 @@
+//! AWS Bedrock Model Provider
+//!
+//! Integration with the Bedrock Converse and ConverseStream APIs, signed
+//! with SigV4 using credentials from the standard AWS sources. Handles the
+//! differences between the Claude, Llama and Titan model families.
+
+pub mod credentials;
+mod event_stream;
+mod sigv4;
+
+use crate::agent::model::{
+    parse_thinking_tokens, GenerationConfig, ModelProvider, ModelResponse, OutputSchema,
+    ProviderKind, ProviderMetadata, TokenUsage, ToolCall,
+};
+use crate::agent::structured;
+use crate::config::BedrockConfig;
+use crate::error::Error;
+use anyhow::{anyhow, Result};
+use async_stream::stream;
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+
+pub use credentials::Credentials;
+use event_stream::Decoder;
+use sigv4::{sign, uri_encode, SigningParams};
+
+const DEFAULT_MODEL: &str = "anthropic.claude-3-5-sonnet-20240620-v1:0";
+/// SigV4 service name for both the runtime and control plane APIs
+const SERVICE: &str = "bedrock";
+/// Upper end of the Converse temperature range
+const MAX_TEMPERATURE: f32 = 1.0;
+/// Longest generation Llama models accept
+const LLAMA_MAX_TOKENS: u32 = 2048;
+/// Tokens left for the answer when a Claude thinking budget would use them all
+const ANSWER_TOKENS: u32 = 2048;
+/// The only stop sequences Titan text models accept
+const TITAN_STOP_SEQUENCES: [&str; 2] = ["|", "User:"];
+/// Region prefixes of cross-region inference profile IDs
+const INFERENCE_PROFILE_PREFIXES: [&str; 5] = ["us", "eu", "apac", "us-gov", "global"];
+
+/// Model family, which decides the request features a model accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelFamily {
+    Claude,
+    Llama,
+    Titan,
+    Other,
+}
+
+impl ModelFamily {
+    /// Family of a model ID, inference profile ID or ARN
+    fn of(model_id: &str) -> Self {
+        let id = model_id.rsplit('/').next().unwrap_or(model_id);
+        let mut segments = id.split('.');
+        let vendor = match segments.next() {
+            Some(prefix) if INFERENCE_PROFILE_PREFIXES.contains(&prefix) => segments.next(),
+            first => first,
+        };
+        match vendor {
+            Some("anthropic") => Self::Claude,
+            Some("meta") => Self::Llama,
+            Some("amazon") if id.contains("titan") => Self::Titan,
+            _ => Self::Other,
+        }
+    }
+
+    fn supports_system_prompt(self) -> bool {
+        self != Self::Titan
+    }
+
+    /// Llama 2 and 3.0 and Titan text models have no tool use, and Llama
+    /// cannot use tools while streaming
+    fn supports_tools(self, model_id: &str, stream: bool) -> bool {
+        match self {
+            Self::Titan => false,
+            Self::Llama => {
+                !stream
+                    && !["llama2", "llama3-8b", "llama3-70b"]
+                        .iter()
+                        .any(|old| model_id.contains(old))
+            }
+            Self::Claude | Self::Other => true,
+        }
+    }
+
+    /// Only Claude accepts a `toolChoice` naming a specific tool
+    fn supports_forced_tool(self) -> bool {
+        self == Self::Claude
+    }
+}
+
+/// Message in a Converse conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Message {
+    role: String,
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+}
+
+/// Content block in a message: text, a tool use or reasoning
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContentBlock {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_use: Option<ToolUse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reasoning_content: Option<ReasoningContent>,
+}
+
+impl ContentBlock {
+    fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolUse {
+    tool_use_id: String,
+    name: String,
+    #[serde(default)]
+    input: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReasoningContent {
+    reasoning_text: Option<ReasoningText>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReasoningText {
+    text: String,
+}
+
+/// System prompt block
+#[derive(Debug, Clone, Serialize)]
+struct SystemBlock {
+    text: String,
+}
+
+/// Tool definition for Bedrock tool use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub input_schema: InputSchema,
+}
+
+/// JSON schema of a tool's input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputSchema {
+    pub json: serde_json::Value,
+}
+
+impl ToolSpec {
+    /// Build a tool from a spec-ai tool definition
+    ///
+    /// Parameters without a type are treated as an object schema, which the
+    /// API requires.
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: &serde_json::Value,
+    ) -> Self {
+        let mut schema = match parameters {
+            serde_json::Value::Object(schema) => schema.clone(),
+            _ => serde_json::Map::new(),
+        };
+        schema.entry("type").or_insert_with(|| "object".into());
+        schema
+            .entry("properties")
+            .or_insert_with(|| serde_json::json!({}));
+
+        Self {
+            name: name.into(),
+            description: description.into(),
+            input_schema: InputSchema {
+                json: serde_json::Value::Object(schema),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolConfig {
+    tools: Vec<Tool>,
+    /// Forces a particular tool, e.g. `{"tool": {"name": "..."}}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Tool {
+    tool_spec: ToolSpec,
+}
+
+/// Converse API request
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseRequest {
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    system: Vec<SystemBlock>,
+    inference_config: InferenceConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<ToolConfig>,
+    /// Model-specific settings, such as Claude's extended thinking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    additional_model_request_fields: Option<serde_json::Value>,
+}
+
+/// Sampling options for a Converse request
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InferenceConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+}
+
+/// Converse API response
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseResponse {
+    output: ConverseOutput,
+    stop_reason: Option<String>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ConverseOutput {
+    message: Option<Message>,
+}
+
+/// Token usage in a Converse response
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Usage {
+    input_tokens: u32,
+    output_tokens: u32,
+}
+
+/// Payload of a ConverseStream event, whichever its type
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamPayload {
+    content_block_index: Option<usize>,
+    start: Option<BlockStart>,
+    delta: Option<BlockDelta>,
+    stop_reason: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockStart {
+    tool_use: Option<ToolUseStart>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolUseStart {
+    tool_use_id: String,
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockDelta {
+    text: Option<String>,
+    tool_use: Option<ToolUseDelta>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ToolUseDelta {
+    input: String,
+}
+
+/// Foundation models API response
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelList {
+    #[serde(default)]
+    model_summaries: Vec<ModelSummary>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelSummary {
+    model_id: String,
+}
+
+/// Turns ConverseStream events into text chunks while collecting tool calls
+/// and the stop reason
+#[derive(Debug, Default)]
+struct StreamState {
+    /// Tool use blocks still streaming, by block index: id, name and partial JSON input
+    pending_tools: HashMap<usize, (String, String, String)>,
+    tool_calls: Vec<ToolCall>,
+    stop_reason: Option<String>,
+}
+
+impl StreamState {
+    /// Handle one message, returning any text that is ready to show
+    fn handle(&mut self, message: &event_stream::Message) -> Result<Option<String>> {
+        let payload: StreamPayload = serde_json::from_slice(&message.payload).unwrap_or_default();
+        if message.header(":message-type") != Some("event") {
+            return Err(anyhow!(
+                "Bedrock stream error ({}): {}",
+                message
+                    .header(":exception-type")
+                    .or(message.header(":error-code"))
+                    .unwrap_or("unknown"),
+                payload.message.unwrap_or_default()
+            ));
+        }
+
+        let index = payload.content_block_index.unwrap_or_default();
+        match message.header(":event-type") {
+            Some("contentBlockStart") => {
+                if let Some(tool_use) = payload.start.and_then(|start| start.tool_use) {
+                    self.pending_tools
+                        .insert(index, (tool_use.tool_use_id, tool_use.name, String::new()));
+                }
+            }
+            Some("contentBlockDelta") => match payload.delta {
+                Some(BlockDelta {
+                    text: Some(text), ..
+                }) => return Ok(Some(text)),
+                Some(BlockDelta {
+                    tool_use: Some(delta),
+                    ..
+                }) => {
+                    if let Some((_, _, input)) = self.pending_tools.get_mut(&index) {
+                        input.push_str(&delta.input);
+                    }
+                }
+                // Reasoning deltas are not shown
+                _ => {}
+            },
+            Some("contentBlockStop") => {
+                if let Some((id, name, input)) = self.pending_tools.remove(&index) {
+                    let arguments = if input.trim().is_empty() {
+                        serde_json::json!({})
+                    } else {
+                        serde_json::from_str(&input).map_err(|e| {
+                            anyhow!("Invalid input streamed for tool '{}': {}", name, e)
+                        })?
+                    };
+                    self.tool_calls.push(ToolCall {
+                        id,
+                        function_name: name,
+                        arguments,
+                    });
+                }
+            }
+            Some("messageStop") => self.stop_reason = payload.stop_reason,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Log what a text stream cannot carry once the response has ended
+    fn finish(&self) {
+        if !self.tool_calls.is_empty() {
+            tracing::warn!(
+                "Bedrock stream ended with {} tool call(s), which streamed responses cannot run",
+                self.tool_calls.len()
+            );
+        }
+        if self.stop_reason.as_deref() == Some("max_tokens") {
+            tracing::warn!("Bedrock stream was cut off at the max_tokens limit");
+        }
+    }
+}
+
+/// AWS Bedrock provider
+#[derive(Debug, Clone)]
+pub struct BedrockProvider {
+    /// HTTP client for API requests
+    client: reqwest::Client,
+    /// Credentials used to sign requests
+    credentials: Credentials,
+    region: String,
+    /// Base URL of the runtime API
+    endpoint: String,
+    /// Model or inference profile ID
+    model: String,
+    /// Optional system message for all requests
+    system_message: Option<String>,
+    /// Optional tools available for tool use
+    tools: Option<Vec<ToolSpec>>,
+}
+
+impl BedrockProvider {
+    /// Create a provider with the given credentials for a region
+    pub fn new(credentials: Credentials, region: impl Into<String>) -> Self {
+        let region = region.into();
+        Self {
+            client: reqwest::Client::new(),
+            credentials,
+            endpoint: format!("https://bedrock-runtime.{}.amazonaws.com", region),
+            region,
+            model: DEFAULT_MODEL.to_string(),
+            system_message: None,
+            tools: None,
+        }
+    }
+
+    /// Create a provider from the Bedrock config section
+    ///
+    /// Credentials and any region the section leaves unset come from the
+    /// standard AWS environment variables and shared files.
+    pub fn from_config(config: Option<&BedrockConfig>) -> Result<Self> {
+        let default = BedrockConfig::default();
+        let config = config.unwrap_or(&default);
+        let profile = config.profile.as_deref();
+
+        let region = credentials::resolve_region(config.region.as_deref(), profile)?;
+        let provider = Self::new(credentials::resolve_credentials(profile)?, region);
+        Ok(match &config.endpoint_url {
+            Some(endpoint) => provider.with_endpoint(endpoint.clone()),
+            None => provider,
+        })
+    }
+
+    /// Send runtime requests to another endpoint, such as a VPC endpoint
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the model or inference profile to use
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Set a system message to be included in all requests
+    pub fn with_system_message(mut self, message: impl Into<String>) -> Self {
+        self.system_message = Some(message.into());
+        self
+    }
+
+    /// Set tools available for tool use
+    pub fn with_tools(mut self, tools: Vec<ToolSpec>) -> Self {
+        self.tools = if tools.is_empty() { None } else { Some(tools) };
+        self
+    }
+
+    /// Build the request for the Converse API, adjusted to the model family
+    fn build_request(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+        stream: bool,
+    ) -> ConverseRequest {
+        let family = ModelFamily::of(&self.model);
+
+        // Titan has no system prompt, so the system message leads the user turn
+        let (system, prompt) = match &self.system_message {
+            Some(message) if family.supports_system_prompt() => (
+                vec![SystemBlock {
+                    text: message.clone(),
+                }],
+                prompt.to_string(),
+            ),
+            Some(message) => (Vec::new(), format!("{}\n\n{}", message, prompt)),
+            None => (Vec::new(), prompt.to_string()),
+        };
+
+        let stop_sequences = config
+            .stop_sequences
+            .iter()
+            .flatten()
+            .filter(|stop| match family {
+                ModelFamily::Titan => TITAN_STOP_SEQUENCES.contains(&stop.as_str()),
+                // Claude rejects stop sequences that are only whitespace
+                _ => !stop.trim().is_empty(),
+            })
+            .cloned()
+            .collect();
+        let max_tokens = match family {
+            ModelFamily::Llama => config
+                .max_tokens
+                .map(|max_tokens| max_tokens.min(LLAMA_MAX_TOKENS)),
+            _ => config.max_tokens,
+        };
+
+        let mut request = ConverseRequest {
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: vec![ContentBlock::text(prompt)],
+            }],
+            system,
+            inference_config: InferenceConfig {
+                max_tokens,
+                temperature: config
+                    .temperature
+                    .map(|temperature| temperature.clamp(0.0, MAX_TEMPERATURE)),
+                // Top-p of 1.0 samples from every token, so leave it to the model default
+                top_p: config.top_p.filter(|top_p| *top_p < 1.0),
+                stop_sequences,
+            },
+            tool_config: self
+                .tools
+                .clone()
+                .filter(|_| family.supports_tools(&self.model, stream))
+                .map(|tools| ToolConfig {
+                    tools: tools
+                        .into_iter()
+                        .map(|tool_spec| Tool { tool_spec })
+                        .collect(),
+                    tool_choice: None,
+                }),
+            additional_model_request_fields: None,
+        };
+        if let (ModelFamily::Claude, Some(budget)) = (family, config.thinking_budget) {
+            request.additional_model_request_fields = Some(serde_json::json!({
+                "thinking": {"type": "enabled", "budget_tokens": budget}
+            }));
+            // Claude cannot change sampling settings while thinking, and its
+            // budget counts towards the token limit
+            let config = &mut request.inference_config;
+            config.temperature = None;
+            config.top_p = None;
+            if config
+                .max_tokens
+                .is_none_or(|max_tokens| max_tokens <= budget)
+            {
+                config.max_tokens = Some(budget + ANSWER_TOKENS);
+            }
+        }
+        request
+    }
+
+    /// Build a request that forces a single tool taking `schema` as its
+    /// input, so the tool use input is the structured response
+    fn build_structured_request(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> ConverseRequest {
+        let description = schema
+            .description
+            .clone()
+            .unwrap_or_else(|| "Report the response".to_string());
+        // Thinking cannot be combined with a forced tool choice
+        let config = GenerationConfig {
+            thinking_budget: None,
+            ..config.clone()
+        };
+        let mut request = self.build_request(prompt, &config, false);
+        request.tool_config = Some(ToolConfig {
+            tools: vec![Tool {
+                tool_spec: ToolSpec::new(&schema.name, description, &schema.schema),
+            }],
+            tool_choice: Some(serde_json::json!({"tool": {"name": schema.name}})),
+        });
+        request
+    }
+
+    /// Send a Converse request
+    async fn converse(&self, request: &ConverseRequest) -> Result<ModelResponse> {
+        let response = self
+            .send(
+                reqwest::Method::POST,
+                &self.model_url("converse"),
+                serde_json::to_vec(request)?,
+            )
+            .await?;
+
+        // Parse the response
+        let api_response: ConverseResponse = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Bedrock response: {}", e))?;
+
+        Ok(self.to_model_response(api_response))
+    }
+
+    /// Send a signed request and return the successful response
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response> {
+        let url = reqwest::Url::parse(url)?;
+        let content_type = [("content-type", "application/json")];
+        let signed = sign(
+            method.as_str(),
+            &url,
+            &content_type,
+            &body,
+            &SigningParams {
+                credentials: &self.credentials,
+                region: &self.region,
+                service: SERVICE,
+                time: Utc::now(),
+            },
+        );
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("content-type", "application/json")
+            .body(body);
+        for (name, value) in signed {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("Bedrock API request failed: {}", e))?;
+
+        // Check for HTTP errors
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Error::Provider {
+                provider: "Bedrock".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
+        }
+        Ok(response)
+    }
+
+    /// URL of a runtime action on the model
+    fn model_url(&self, action: &str) -> String {
+        format!(
+            "{}/model/{}/{}",
+            self.endpoint,
+            uri_encode(&self.model),
+            action
+        )
+    }
+
+    /// Convert a Converse response into the spec-ai response format
+    fn to_model_response(&self, api_response: ConverseResponse) -> ModelResponse {
+        let mut raw_content = String::new();
+        let mut thinking = String::new();
+        let mut tool_calls = Vec::new();
+
+        for block in api_response
+            .output
+            .message
+            .map(|message| message.content)
+            .unwrap_or_default()
+        {
+            if let Some(tool_use) = block.tool_use {
+                tool_calls.push(ToolCall {
+                    id: tool_use.tool_use_id,
+                    function_name: tool_use.name,
+                    arguments: tool_use.input,
+                });
+            } else if let Some(reasoning) = block.reasoning_content.and_then(|r| r.reasoning_text) {
+                thinking.push_str(&reasoning.text);
+            } else if let Some(text) = block.text {
+                raw_content.push_str(&text);
+            }
+        }
+
+        // Parse thinking tokens if present
+        let (tagged_reasoning, content) = parse_thinking_tokens(&raw_content);
+        let reasoning = if thinking.is_empty() {
+            tagged_reasoning
+        } else {
+            Some(thinking)
+        };
+
+        if api_response.stop_reason.as_deref() == Some("max_tokens") {
+            tracing::warn!(
+                "Bedrock response from {} was cut off at the max_tokens limit",
+                self.model
+            );
+        }
+
+        ModelResponse {
+            content,
+            model: self.model.clone(),
+            usage: api_response
+                .usage
+                .map(|usage| TokenUsage::new(usage.input_tokens, usage.output_tokens)),
+            finish_reason: api_response.stop_reason,
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+            reasoning,
+        }
+    }
+}
+
+#[async_trait]
+impl ModelProvider for BedrockProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        self.converse(&self.build_request(prompt, config, false))
+            .await
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        // Other families cannot be made to call the tool, and tool inputs
+        // must be objects, so those requests only describe the schema
+        if !ModelFamily::of(&self.model).supports_forced_tool() || !schema.is_object() {
+            let prompt = structured::schema_prompt(prompt, schema);
+            return self.generate(&prompt, config).await;
+        }
+        let request = self.build_structured_request(prompt, schema, config);
+        let response = self.converse(&request).await?;
+        Ok(structured::forced_tool_content(response, schema))
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        let request = self.build_request(prompt, config, true);
+        let response = self
+            .send(
+                reqwest::Method::POST,
+                &self.model_url("converse-stream"),
+                serde_json::to_vec(&request)?,
+            )
+            .await?;
+
+        // Convert the response into a stream
+        let byte_stream = response.bytes_stream();
+
+        let stream = stream! {
+            use futures::StreamExt;
+
+            let mut state = StreamState::default();
+            let mut decoder = Decoder::default();
+
+            let mut stream = byte_stream;
+            'read: while let Some(result) = stream.next().await {
+                match result {
+                    Ok(chunk) => {
+                        decoder.push(&chunk);
+
+                        // Process complete messages
+                        loop {
+                            let handled = decoder
+                                .next_message()
+                                .and_then(|message| match message {
+                                    Some(message) => state.handle(&message).map(Some),
+                                    None => Ok(None),
+                                });
+                            match handled {
+                                Ok(Some(Some(text))) => yield Ok(text),
+                                Ok(Some(None)) => {}
+                                Ok(None) => break,
+                                Err(e) => {
+                                    yield Err(e);
+                                    break 'read;
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(anyhow!("Stream error: {}", e));
+                        break;
+                    }
+                }
+            }
+            state.finish();
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let url = format!(
+            "https://bedrock.{}.amazonaws.com/foundation-models?byOutputModality=TEXT",
+            self.region
+        );
+        let response = self
+            .send(reqwest::Method::GET, &url, Vec::new())
+            .await
+            .map_err(|e| anyhow!("Failed to list Bedrock models: {}", e))?;
+
+        let models: ModelList = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Bedrock model list: {}", e))?;
+        Ok(models
+            .model_summaries
+            .into_iter()
+            .map(|model| model.model_id)
+            .collect())
+    }
+
+    fn metadata(&self) -> ProviderMetadata {
+        ProviderMetadata {
+            name: "AWS Bedrock".to_string(),
+            supported_models: vec![
+                "anthropic.claude-3-5-sonnet-20240620-v1:0".to_string(),
+                "anthropic.claude-3-haiku-20240307-v1:0".to_string(),
+                "meta.llama3-1-70b-instruct-v1:0".to_string(),
+                "amazon.titan-text-premier-v1:0".to_string(),
+            ],
+            supports_streaming: true,
+        }
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Bedrock
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    fn provider() -> BedrockProvider {
+        BedrockProvider::new(
+            Credentials {
+                access_key_id: "AKIDTEST".to_string(),
+                secret_access_key: "secret".to_string(),
+                session_token: None,
+            },
+            "us-west-2",
+        )
+    }
+
+    fn echo_tool() -> ToolSpec {
+        ToolSpec::new(
+            "echo",
+            "Echo a message",
+            &serde_json::json!({"properties": {"message": {"type": "string"}}}),
+        )
+    }
+
+    /// Answer one HTTP request with `body`, returning the server's URL and a
+    /// handle that yields the request head and body it received
+    fn serve_once(content_type: &str, body: Vec<u8>) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+            content_type,
+            body.len()
+        )
+        .into_bytes();
+        response.extend(body);
+
+        let handle = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(socket.try_clone().unwrap());
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+
+            socket.write_all(&response).unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_model_family_detection() {
+        assert_eq!(ModelFamily::of(DEFAULT_MODEL), ModelFamily::Claude);
+        assert_eq!(
+            ModelFamily::of("us.anthropic.claude-3-5-haiku-20241022-v1:0"),
+            ModelFamily::Claude
+        );
+        assert_eq!(
+            ModelFamily::of("arn:aws:bedrock:us-east-1:123456789012:inference-profile/eu.meta.llama3-2-3b-instruct-v1:0"),
+            ModelFamily::Llama
+        );
+        assert_eq!(
+            ModelFamily::of("amazon.titan-text-express-v1"),
+            ModelFamily::Titan
+        );
+        assert_eq!(ModelFamily::of("amazon.nova-pro-v1:0"), ModelFamily::Other);
+    }
+
+    #[test]
+    fn test_build_request_for_claude() {
+        let provider = provider()
+            .with_system_message("System prompt")
+            .with_tools(vec![echo_tool()]);
+        let config = GenerationConfig {
+            temperature: Some(1.5),
+            max_tokens: Some(4096),
+            stop_sequences: Some(vec!["END".to_string(), " ".to_string()]),
+            ..Default::default()
+        };
+
+        let request =
+            serde_json::to_value(provider.build_request("Hello", &config, false)).unwrap();
+
+        assert_eq!(
+            request["system"],
+            serde_json::json!([{"text": "System prompt"}])
+        );
+        assert_eq!(
+            request["messages"],
+            serde_json::json!([{"role": "user", "content": [{"text": "Hello"}]}])
+        );
+        assert_eq!(
+            request["inferenceConfig"],
+            serde_json::json!({"maxTokens": 4096, "temperature": 1.0, "stopSequences": ["END"]})
+        );
+        let tool = &request["toolConfig"]["tools"][0]["toolSpec"];
+        assert_eq!(tool["name"], "echo");
+        assert_eq!(tool["inputSchema"]["json"]["type"], "object");
+    }
+
+    #[test]
+    fn test_build_request_enables_thinking_for_claude_only() {
+        let config = GenerationConfig {
+            temperature: Some(0.5),
+            max_tokens: Some(1024),
+            thinking_budget: Some(4096),
+            ..Default::default()
+        };
+
+        let request =
+            serde_json::to_value(provider().build_request("Hello", &config, false)).unwrap();
+        assert_eq!(
+            request["additionalModelRequestFields"]["thinking"]["budget_tokens"],
+            4096
+        );
+        assert_eq!(
+            request["inferenceConfig"],
+            serde_json::json!({"maxTokens": 4096 + ANSWER_TOKENS})
+        );
+
+        let llama = provider().with_model("meta.llama3-70b-instruct-v1:0");
+        let request = llama.build_request("Hello", &config, false);
+        assert!(request.additional_model_request_fields.is_none());
+    }
+
+    #[test]
+    fn test_build_structured_request_forces_schema_tool() {
+        let schema = OutputSchema::new(
+            "route",
+            serde_json::json!({"type": "object", "properties": {"agent": {"type": "string"}}}),
+        );
+
+        let request = provider()
+            .with_tools(vec![echo_tool()])
+            .build_structured_request("Route this", &schema, &GenerationConfig::default());
+        let request = serde_json::to_value(request).unwrap();
+
+        let tools = request["toolConfig"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0]["toolSpec"]["name"], "route");
+        assert_eq!(
+            request["toolConfig"]["toolChoice"],
+            serde_json::json!({"tool": {"name": "route"}})
+        );
+        assert!(ModelFamily::Claude.supports_forced_tool());
+        assert!(!ModelFamily::Llama.supports_forced_tool());
+    }
+
+    #[test]
+    fn test_build_request_handles_llama_and_titan_quirks() {
+        let config = GenerationConfig {
+            max_tokens: Some(4096),
+            stop_sequences: Some(vec!["END".to_string(), "User:".to_string()]),
+            ..Default::default()
+        };
+
+        let llama = provider()
+            .with_model("meta.llama3-1-70b-instruct-v1:0")
+            .with_tools(vec![echo_tool()]);
+        let request = llama.build_request("Hi", &config, false);
+        assert_eq!(request.inference_config.max_tokens, Some(LLAMA_MAX_TOKENS));
+        assert!(request.tool_config.is_some());
+        assert!(llama
+            .build_request("Hi", &config, true)
+            .tool_config
+            .is_none());
+        assert!(llama
+            .with_model("meta.llama3-70b-instruct-v1:0")
+            .build_request("Hi", &config, false)
+            .tool_config
+            .is_none());
+
+        let titan = provider()
+            .with_model("amazon.titan-text-premier-v1:0")
+            .with_system_message("Be brief.")
+            .with_tools(vec![echo_tool()]);
+        let request = titan.build_request("Hi", &config, false);
+        assert!(request.system.is_empty());
+        assert_eq!(
+            request.messages[0].content[0].text.as_deref(),
+            Some("Be brief.\n\nHi")
+        );
+        assert_eq!(request.inference_config.stop_sequences, vec!["User:"]);
+        assert_eq!(request.inference_config.max_tokens, Some(4096));
+        assert!(request.tool_config.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_signs_request_and_maps_tool_use() {
+        let body = serde_json::json!({
+            "output": {"message": {"role": "assistant", "content": [
+                {"reasoningContent": {"reasoningText": {"text": "Need the weather."}}},
+                {"text": "Checking."},
+                {"toolUse": {"toolUseId": "tooluse_1", "name": "weather", "input": {"city": "Oslo"}}}
+            ]}},
+            "stopReason": "tool_use",
+            "usage": {"inputTokens": 10, "outputTokens": 5, "totalTokens": 15}
+        });
+        let (url, server) = serve_once("application/json", body.to_string().into_bytes());
+        let provider = provider().with_endpoint(url);
+
+        let response = provider
+            .generate("What's the weather?", &GenerationConfig::default())
+            .await
+            .unwrap();
+        let request = server.join().unwrap();
+
+        assert!(request
+            .starts_with("POST /model/anthropic.claude-3-5-sonnet-20240620-v1%3A0/converse "));
+        assert!(request.contains("authorization: AWS4-HMAC-SHA256 Credential=AKIDTEST/"));
+        assert!(request.contains("/us-west-2/bedrock/aws4_request, SignedHeaders=content-type;host;x-amz-date, Signature="));
+        assert!(request.contains(r#""text":"What's the weather?""#));
+        assert_eq!(response.content, "Checking.");
+        assert_eq!(response.reasoning.as_deref(), Some("Need the weather."));
+        assert_eq!(response.finish_reason.as_deref(), Some("tool_use"));
+        assert_eq!(response.usage, Some(TokenUsage::new(10, 5)));
+        let calls = response.tool_calls.unwrap();
+        assert_eq!(calls[0].id, "tooluse_1");
+        assert_eq!(calls[0].function_name, "weather");
+        assert_eq!(calls[0].arguments["city"], "Oslo");
+    }
+
+    #[tokio::test]
+    async fn test_stream_decodes_events() {
+        let event = |event_type: &str, payload: serde_json::Value| {
+            event_stream::encode(
+                &[(":event-type", event_type), (":message-type", "event")],
+                payload.to_string().as_bytes(),
+            )
+        };
+        let mut body = Vec::new();
+        body.extend(event(
+            "messageStart",
+            serde_json::json!({"role": "assistant"}),
+        ));
+        body.extend(event(
+            "contentBlockDelta",
+            serde_json::json!({"contentBlockIndex": 0, "delta": {"text": "Hel"}}),
+        ));
+        body.extend(event(
+            "contentBlockDelta",
+            serde_json::json!({"contentBlockIndex": 0, "delta": {"text": "lo"}}),
+        ));
+        body.extend(event(
+            "contentBlockStart",
+            serde_json::json!({"contentBlockIndex": 1, "start": {"toolUse": {"toolUseId": "t1", "name": "echo"}}}),
+        ));
+        body.extend(event(
+            "contentBlockDelta",
+            serde_json::json!({"contentBlockIndex": 1, "delta": {"toolUse": {"input": "{\"message\": \"hi\"}"}}}),
+        ));
+        body.extend(event(
+            "contentBlockStop",
+            serde_json::json!({"contentBlockIndex": 1}),
+        ));
+        body.extend(event(
+            "messageStop",
+            serde_json::json!({"stopReason": "tool_use"}),
+        ));
+        body.extend(event_stream::encode(
+            &[
+                (":exception-type", "throttlingException"),
+                (":message-type", "exception"),
+            ],
+            br#"{"message": "Too many requests"}"#,
+        ));
+        let (url, server) = serve_once("application/vnd.amazon.eventstream", body);
+        let provider = provider().with_endpoint(url);
+
+        let chunks: Vec<Result<String>> = provider
+            .stream("Hi", &GenerationConfig::default())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let request = server.join().unwrap();
+
+        assert!(request.contains("/converse-stream "));
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].as_ref().unwrap(), "Hel");
+        assert_eq!(chunks[1].as_ref().unwrap(), "lo");
+        assert_eq!(
+            chunks[2].as_ref().unwrap_err().to_string(),
+            "Bedrock stream error (throttlingException): Too many requests"
+        );
+    }
+
+    #[test]
+    fn test_stream_state_collects_tool_calls() {
+        let message = |event_type: &str, payload: serde_json::Value| event_stream::Message {
+            headers: [
+                (":event-type".to_string(), event_type.to_string()),
+                (":message-type".to_string(), "event".to_string()),
+            ]
+            .into(),
+            payload: payload.to_string().into_bytes(),
+        };
+
+        let mut state = StreamState::default();
+        for event in [
+            message(
+                "contentBlockStart",
+                serde_json::json!({"contentBlockIndex": 0, "start": {"toolUse": {"toolUseId": "t1", "name": "echo"}}}),
+            ),
+            message(
+                "contentBlockDelta",
+                serde_json::json!({"contentBlockIndex": 0, "delta": {"toolUse": {"input": "{\"message\": "}}}),
+            ),
+            message(
+                "contentBlockDelta",
+                serde_json::json!({"contentBlockIndex": 0, "delta": {"toolUse": {"input": "\"hi\"}"}}}),
+            ),
+            message(
+                "contentBlockStop",
+                serde_json::json!({"contentBlockIndex": 0}),
+            ),
+            message(
+                "messageStop",
+                serde_json::json!({"stopReason": "max_tokens"}),
+            ),
+        ] {
+            assert_eq!(state.handle(&event).unwrap(), None);
+        }
+
+        assert_eq!(state.stop_reason.as_deref(), Some("max_tokens"));
+        assert_eq!(state.tool_calls[0].id, "t1");
+        assert_eq!(state.tool_calls[0].arguments["message"], "hi");
+    }
+}
@@ -0,0 +1,275 @@
+//! AWS credentials and region from the standard sources
+//!
+//! Static credentials come from the `AWS_ACCESS_KEY_ID` family of environment
+//! variables, then from the shared credentials and config files. The profile
+//! is the one named in the spec-ai config, then `AWS_PROFILE`, then
+//! `default`. A profile named in the spec-ai config skips the environment
+//! credentials so it is always the one used.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+const DEFAULT_PROFILE: &str = "default";
+
+/// Access key pair used to sign requests
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Present for temporary credentials
+    pub session_token: Option<String>,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"<redacted>")
+            .field(
+                "session_token",
+                &self.session_token.as_ref().map(|_| "<redacted>"),
+            )
+            .finish()
+    }
+}
+
+/// Where credentials and the region are looked up
+///
+/// Kept separate from the process environment so lookups can be tested.
+pub(crate) struct Sources<'a> {
+    pub env: &'a dyn Fn(&str) -> Option<String>,
+    /// Contents of the shared credentials file
+    pub credentials_file: &'a str,
+    /// Contents of the shared config file
+    pub config_file: &'a str,
+}
+
+impl Sources<'_> {
+    /// Profile named in the spec-ai config, `AWS_PROFILE`, or "default"
+    fn profile(&self, configured: Option<&str>) -> String {
+        configured
+            .map(str::to_string)
+            .or_else(|| (self.env)("AWS_PROFILE"))
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+    }
+
+    pub fn credentials(&self, configured_profile: Option<&str>) -> Result<Credentials> {
+        if configured_profile.is_none() {
+            if let (Some(access_key_id), Some(secret_access_key)) = (
+                (self.env)("AWS_ACCESS_KEY_ID"),
+                (self.env)("AWS_SECRET_ACCESS_KEY"),
+            ) {
+                return Ok(Credentials {
+                    access_key_id,
+                    secret_access_key,
+                    session_token: (self.env)("AWS_SESSION_TOKEN"),
+                });
+            }
+        }
+
+        let profile = self.profile(configured_profile);
+        let credentials = parse_ini(self.credentials_file);
+        let config = parse_ini(self.config_file);
+        // The config file may also hold keys, under `[profile name]`
+        for section in [
+            credentials.get(&profile),
+            config.get(&config_section(&profile)),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if let (Some(access_key_id), Some(secret_access_key)) = (
+                section.get("aws_access_key_id"),
+                section.get("aws_secret_access_key"),
+            ) {
+                return Ok(Credentials {
+                    access_key_id: access_key_id.clone(),
+                    secret_access_key: secret_access_key.clone(),
+                    session_token: section.get("aws_session_token").cloned(),
+                });
+            }
+        }
+
+        Err(anyhow!(
+            "No AWS credentials found; set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY or add profile '{}' to the shared credentials file",
+            profile
+        ))
+    }
+
+    pub fn region(
+        &self,
+        configured_region: Option<&str>,
+        configured_profile: Option<&str>,
+    ) -> Result<String> {
+        if let Some(region) = configured_region {
+            return Ok(region.to_string());
+        }
+        if let Some(region) = (self.env)("AWS_REGION").or_else(|| (self.env)("AWS_DEFAULT_REGION"))
+        {
+            return Ok(region);
+        }
+
+        let profile = self.profile(configured_profile);
+        parse_ini(self.config_file)
+            .get(&config_section(&profile))
+            .and_then(|section| section.get("region"))
+            .cloned()
+            .ok_or_else(|| {
+                anyhow!(
+                    "No AWS region configured; set region under [model.bedrock], AWS_REGION, or in profile '{}'",
+                    profile
+                )
+            })
+    }
+}
+
+/// Resolve credentials from the environment and the shared AWS files
+pub fn resolve_credentials(profile: Option<&str>) -> Result<Credentials> {
+    let (credentials_file, config_file) = read_shared_files();
+    Sources {
+        env: &|name| std::env::var(name).ok(),
+        credentials_file: &credentials_file,
+        config_file: &config_file,
+    }
+    .credentials(profile)
+}
+
+/// Resolve the region from the spec-ai config, the environment, or the shared config file
+pub fn resolve_region(region: Option<&str>, profile: Option<&str>) -> Result<String> {
+    let (credentials_file, config_file) = read_shared_files();
+    Sources {
+        env: &|name| std::env::var(name).ok(),
+        credentials_file: &credentials_file,
+        config_file: &config_file,
+    }
+    .region(region, profile)
+}
+
+/// Contents of the shared credentials and config files, empty when missing
+fn read_shared_files() -> (String, String) {
+    let read = |env_var: &str, file_name: &str| {
+        std::env::var_os(env_var)
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join(".aws").join(file_name))
+            })
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .unwrap_or_default()
+    };
+    (
+        read("AWS_SHARED_CREDENTIALS_FILE", "credentials"),
+        read("AWS_CONFIG_FILE", "config"),
+    )
+}
+
+/// Section name of a profile in the shared config file
+fn config_section(profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        profile.to_string()
+    } else {
+        format!("profile {}", profile)
+    }
+}
+
+/// Parse an INI file into sections of key/value pairs
+fn parse_ini(contents: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = None;
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.trim().to_string();
+            sections.entry(name.clone()).or_default();
+            current = Some(name);
+        } else if let (Some(section), Some((key, value))) = (&current, line.split_once('=')) {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREDENTIALS: &str = "
+[default]
+aws_access_key_id = AKIDDEFAULT
+aws_secret_access_key = default-secret
+
+[work]
+aws_access_key_id=AKIDWORK
+aws_secret_access_key=work-secret
+aws_session_token=work-token
+";
+    const CONFIG: &str = "
+[default]
+region = us-east-1
+
+# Keys may live in the config file too
+[profile ops]
+region = eu-west-1
+aws_access_key_id = AKIDOPS
+aws_secret_access_key = ops-secret
+";
+
+    fn sources<'a>(env: &'a dyn Fn(&str) -> Option<String>) -> Sources<'a> {
+        Sources {
+            env,
+            credentials_file: CREDENTIALS,
+            config_file: CONFIG,
+        }
+    }
+
+    #[test]
+    fn environment_comes_before_profiles() {
+        let env = |name: &str| match name {
+            "AWS_ACCESS_KEY_ID" => Some("AKIDENV".to_string()),
+            "AWS_SECRET_ACCESS_KEY" => Some("env-secret".to_string()),
+            "AWS_REGION" => Some("ap-south-1".to_string()),
+            _ => None,
+        };
+        let sources = sources(&env);
+
+        let credentials = sources.credentials(None).unwrap();
+        assert_eq!(credentials.access_key_id, "AKIDENV");
+        assert_eq!(credentials.session_token, None);
+        assert_eq!(sources.region(None, None).unwrap(), "ap-south-1");
+        assert_eq!(
+            sources.region(Some("us-west-2"), None).unwrap(),
+            "us-west-2"
+        );
+
+        // A profile named in the spec-ai config wins over the environment
+        let credentials = sources.credentials(Some("work")).unwrap();
+        assert_eq!(credentials.access_key_id, "AKIDWORK");
+        assert_eq!(credentials.session_token.as_deref(), Some("work-token"));
+    }
+
+    #[test]
+    fn profiles_come_from_shared_files() {
+        let env = |name: &str| (name == "AWS_PROFILE").then(|| "ops".to_string());
+        let sources = sources(&env);
+
+        let credentials = sources.credentials(None).unwrap();
+        assert_eq!(credentials.access_key_id, "AKIDOPS");
+        assert_eq!(sources.region(None, None).unwrap(), "eu-west-1");
+        assert_eq!(
+            sources.credentials(Some("default")).unwrap().access_key_id,
+            "AKIDDEFAULT"
+        );
+        assert_eq!(sources.region(None, Some("default")).unwrap(), "us-east-1");
+
+        let err = sources.credentials(Some("missing")).unwrap_err();
+        assert!(err.to_string().contains("profile 'missing'"));
+        assert!(!format!("{:?}", credentials).contains("ops-secret"));
+    }
+}
@@ -0,0 +1,197 @@
+//! Decoder for the AWS event stream encoding of streaming Bedrock responses
+//!
+//! Each message is a length-prefixed frame of typed headers and a payload,
+//! with CRC32 checksums over the prelude and the whole message.
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// Total length, headers length and prelude checksum
+const PRELUDE_LEN: usize = 12;
+const CHECKSUM_LEN: usize = 4;
+
+/// One decoded event stream message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// String-valued headers such as `:event-type`; other header types are skipped
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+impl Message {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+/// Splits a byte stream into messages as enough bytes arrive
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Take the next complete message, if one has arrived
+    pub fn next_message(&mut self) -> Result<Option<Message>> {
+        if self.buffer.len() < PRELUDE_LEN {
+            return Ok(None);
+        }
+        let total_len = read_u32(&self.buffer[0..4]) as usize;
+        let headers_len = read_u32(&self.buffer[4..8]) as usize;
+        if crc32(&self.buffer[0..8]) != read_u32(&self.buffer[8..12]) {
+            bail!("Event stream prelude checksum mismatch");
+        }
+        if total_len < PRELUDE_LEN + headers_len + CHECKSUM_LEN {
+            bail!("Event stream message length {} is too short", total_len);
+        }
+        if self.buffer.len() < total_len {
+            return Ok(None);
+        }
+
+        let frame: Vec<u8> = self.buffer.drain(..total_len).collect();
+        let (body, checksum) = frame.split_at(total_len - CHECKSUM_LEN);
+        if crc32(body) != read_u32(checksum) {
+            bail!("Event stream message checksum mismatch");
+        }
+
+        let headers_end = PRELUDE_LEN + headers_len;
+        Ok(Some(Message {
+            headers: parse_headers(&body[PRELUDE_LEN..headers_end])?,
+            payload: body[headers_end..].to_vec(),
+        }))
+    }
+}
+
+fn parse_headers(mut bytes: &[u8]) -> Result<HashMap<String, String>> {
+    let mut headers = HashMap::new();
+    while !bytes.is_empty() {
+        let name_len = bytes[0] as usize;
+        let Some(name) = bytes.get(1..1 + name_len) else {
+            bail!("Event stream header name is truncated");
+        };
+        let name = String::from_utf8_lossy(name).into_owned();
+        let Some(&kind) = bytes.get(1 + name_len) else {
+            bail!("Event stream header '{}' has no type", name);
+        };
+        let rest = &bytes[2 + name_len..];
+
+        // Value sizes by header type: booleans carry no value, byte arrays
+        // and strings are prefixed with a 16-bit length
+        let value_len = match kind {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => match rest {
+                [high, low, ..] => 2 + u16::from_be_bytes([*high, *low]) as usize,
+                _ => bail!("Event stream header '{}' is truncated", name),
+            },
+            _ => bail!("Event stream header '{}' has invalid type {}", name, kind),
+        };
+        if rest.len() < value_len {
+            bail!("Event stream header '{}' is truncated", name);
+        }
+        if kind == 7 {
+            headers.insert(
+                name,
+                String::from_utf8_lossy(&rest[2..value_len]).into_owned(),
+            );
+        }
+        bytes = &rest[value_len..];
+    }
+    Ok(headers)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// CRC-32 (IEEE), as used by the event stream checksums
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Encode a message with string headers, as Bedrock sends them
+#[cfg(test)]
+pub(crate) fn encode(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+    let mut header_bytes = Vec::new();
+    for (name, value) in headers {
+        header_bytes.push(name.len() as u8);
+        header_bytes.extend_from_slice(name.as_bytes());
+        header_bytes.push(7);
+        header_bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        header_bytes.extend_from_slice(value.as_bytes());
+    }
+    let total_len = PRELUDE_LEN + header_bytes.len() + payload.len() + CHECKSUM_LEN;
+
+    let mut frame = Vec::with_capacity(total_len);
+    frame.extend_from_slice(&(total_len as u32).to_be_bytes());
+    frame.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&crc32(&frame).to_be_bytes());
+    frame.extend_from_slice(&header_bytes);
+    frame.extend_from_slice(payload);
+    frame.extend_from_slice(&crc32(&frame).to_be_bytes());
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_reference_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn decodes_messages_split_across_chunks() {
+        let mut bytes = encode(
+            &[(":event-type", "messageStart"), (":message-type", "event")],
+            br#"{"role":"assistant"}"#,
+        );
+        bytes.extend(encode(&[(":event-type", "messageStop")], b"{}"));
+
+        let mut decoder = Decoder::default();
+        let mut messages = Vec::new();
+        for chunk in bytes.chunks(7) {
+            decoder.push(chunk);
+            while let Some(message) = decoder.next_message().unwrap() {
+                messages.push(message);
+            }
+        }
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].header(":event-type"), Some("messageStart"));
+        assert_eq!(messages[0].header(":message-type"), Some("event"));
+        assert_eq!(messages[0].payload, br#"{"role":"assistant"}"#);
+        assert_eq!(messages[1].header(":event-type"), Some("messageStop"));
+    }
+
+    #[test]
+    fn rejects_corrupted_messages() {
+        let mut bytes = encode(&[(":event-type", "messageStop")], b"{}");
+        let last = bytes.len() - 5;
+        bytes[last] ^= 0xff;
+
+        let mut decoder = Decoder::default();
+        decoder.push(&bytes);
+        assert!(decoder.next_message().is_err());
+    }
+}
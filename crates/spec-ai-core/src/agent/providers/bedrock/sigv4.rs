@@ -0,0 +1,213 @@
+//! AWS Signature Version 4 request signing
+
+use super::credentials::Credentials;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use ring::{digest, hmac};
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// Who is signing, for which service, and when
+pub struct SigningParams<'a> {
+    pub credentials: &'a Credentials,
+    pub region: &'a str,
+    pub service: &'a str,
+    pub time: DateTime<Utc>,
+}
+
+/// Headers that make a request acceptable to AWS
+///
+/// `headers` are the request headers to cover by the signature besides
+/// `host`, which is taken from the URL. The returned headers must be sent
+/// along with them.
+pub fn sign(
+    method: &str,
+    url: &Url,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    params: &SigningParams<'_>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = params.time.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = params.time.format("%Y%m%d").to_string();
+
+    let mut added = vec![("x-amz-date", amz_date.clone())];
+    if let Some(token) = &params.credentials.session_token {
+        added.push(("x-amz-security-token", token.clone()));
+    }
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut canonical_headers: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+        .chain([("host".to_string(), host)])
+        .chain(
+            added
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone())),
+        )
+        .collect();
+    canonical_headers.sort();
+    let signed_headers = canonical_headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = [
+        method.to_string(),
+        canonical_uri(url),
+        canonical_query(url),
+        canonical_headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect(),
+        signed_headers.clone(),
+        hex(digest::digest(&digest::SHA256, body).as_ref()),
+    ]
+    .join("\n");
+
+    let scope = format!("{}/{}/{}/aws4_request", date, params.region, params.service);
+    let string_to_sign = [
+        ALGORITHM,
+        &amz_date,
+        &scope,
+        &hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref()),
+    ]
+    .join("\n");
+
+    let key = [params.region, params.service, "aws4_request"].iter().fold(
+        hmac_sha256(
+            format!("AWS4{}", params.credentials.secret_access_key).as_bytes(),
+            date.as_bytes(),
+        ),
+        |key, part| hmac_sha256(&key, part.as_bytes()),
+    );
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    added.push((
+        "authorization",
+        format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, params.credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    added
+}
+
+/// Path with every segment encoded again, as services other than S3 expect
+fn canonical_uri(url: &Url) -> String {
+    let path = url.path();
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path.split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn canonical_query(url: &Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (uri_encode(&key), uri_encode(&value)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent-encode everything except unreserved characters
+pub fn uri_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, data).as_ref().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Requests from the AWS Signature Version 4 test suite
+    fn sign_example(
+        method: &str,
+        url: &str,
+        session_token: Option<&str>,
+    ) -> Vec<(&'static str, String)> {
+        let credentials = Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: session_token.map(str::to_string),
+        };
+        let params = SigningParams {
+            credentials: &credentials,
+            region: "us-east-1",
+            service: "service",
+            time: Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap(),
+        };
+        sign(method, &Url::parse(url).unwrap(), &[], b"", &params)
+    }
+
+    fn header(headers: &[(&'static str, String)], name: &str) -> String {
+        headers
+            .iter()
+            .find(|(header, _)| *header == name)
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn signs_test_suite_requests() {
+        let headers = sign_example("GET", "https://example.amazonaws.com/", None);
+        assert_eq!(header(&headers, "x-amz-date"), "20150830T123600Z");
+        assert_eq!(
+            header(&headers, "authorization"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+
+        let headers = sign_example(
+            "GET",
+            "https://example.amazonaws.com/?Param2=value2&Param1=value1",
+            None,
+        );
+        assert!(header(&headers, "authorization").ends_with(
+            "Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        ));
+
+        let headers = sign_example("POST", "https://example.amazonaws.com/", Some("token"));
+        assert_eq!(header(&headers, "x-amz-security-token"), "token");
+        assert!(header(&headers, "authorization")
+            .contains("SignedHeaders=host;x-amz-date;x-amz-security-token,"));
+    }
+
+    #[test]
+    fn encodes_path_segments_again() {
+        let url = Url::parse("https://bedrock-runtime.us-east-1.amazonaws.com/model/anthropic.claude-v2%3A1/converse").unwrap();
+        assert_eq!(
+            canonical_uri(&url),
+            "/model/anthropic.claude-v2%253A1/converse"
+        );
+        assert_eq!(uri_encode("a b/c~"), "a%20b%2Fc~");
+    }
+}
@@ -0,0 +1,173 @@
+//! Fault-Injecting Provider
+//!
+//! Wraps another provider and applies the provider rules of a fault scenario
+//! to its calls, for exercising retry and failover paths.
+
+use crate::agent::model::{
+    DeltaStream, GenerationConfig, ModelProvider, ModelResponse, OutputSchema, ProviderKind,
+    ProviderMetadata,
+};
+use crate::chaos::{self, FaultInjector, FaultTarget, InjectedFault};
+use anyhow::Result;
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Provider that injects latency, errors and disconnects into another provider
+#[derive(Clone)]
+pub struct ChaosProvider {
+    inner: Arc<dyn ModelProvider>,
+    faults: Arc<FaultInjector>,
+}
+
+impl ChaosProvider {
+    pub fn new(inner: Arc<dyn ModelProvider>, faults: Arc<FaultInjector>) -> Self {
+        Self { inner, faults }
+    }
+}
+
+/// A dropped connection cuts the stream off after its first item
+fn cut_off<T: Send + 'static>(
+    mut inner: Pin<Box<dyn Stream<Item = Result<T>> + Send>>,
+    fault: InjectedFault,
+) -> Pin<Box<dyn Stream<Item = Result<T>> + Send>> {
+    Box::pin(stream! {
+        if let Some(first) = inner.next().await {
+            yield first;
+        }
+        yield Err(fault.into());
+    })
+}
+
+#[async_trait]
+impl ModelProvider for ChaosProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        chaos::apply(&self.faults, FaultTarget::Provider, "generate").await?;
+        self.inner.generate(prompt, config).await
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        chaos::apply(&self.faults, FaultTarget::Provider, "generate_json").await?;
+        self.inner.generate_json(prompt, schema, config).await
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        match chaos::apply(&self.faults, FaultTarget::Provider, "stream").await {
+            Ok(()) => self.inner.stream(prompt, config).await,
+            Err(fault) if fault.is_disconnect() => {
+                let inner = self.inner.stream(prompt, config).await?;
+                Ok(cut_off(inner, fault))
+            }
+            Err(fault) => Err(fault.into()),
+        }
+    }
+
+    /// Faults apply as they do to `stream`, so scenarios cover both
+    async fn stream_deltas(&self, prompt: &str, config: &GenerationConfig) -> Result<DeltaStream> {
+        match chaos::apply(&self.faults, FaultTarget::Provider, "stream").await {
+            Ok(()) => self.inner.stream_deltas(prompt, config).await,
+            Err(fault) if fault.is_disconnect() => {
+                let inner = self.inner.stream_deltas(prompt, config).await?;
+                Ok(cut_off(inner, fault))
+            }
+            Err(fault) => Err(fault.into()),
+        }
+    }
+
+    fn metadata(&self) -> ProviderMetadata {
+        self.inner.metadata()
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        chaos::apply(&self.faults, FaultTarget::Provider, "list_models").await?;
+        self.inner.list_models().await
+    }
+
+    fn kind(&self) -> ProviderKind {
+        self.inner.kind()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::providers::MockProvider;
+    use crate::chaos::{FaultKind, FaultRule, FaultScenario, InjectedFault};
+
+    fn provider(rule: FaultRule) -> (ChaosProvider, Arc<FaultInjector>) {
+        let faults = Arc::new(FaultInjector::new(FaultScenario {
+            seed: 0,
+            rules: vec![rule],
+        }));
+        let inner = Arc::new(MockProvider::new("one two three"));
+        (ChaosProvider::new(inner, Arc::clone(&faults)), faults)
+    }
+
+    fn rule(operation: &str, fault: FaultKind) -> FaultRule {
+        FaultRule {
+            target: FaultTarget::Provider,
+            operation: Some(operation.to_string()),
+            probability: 1.0,
+            latency_ms: 0,
+            fault: Some(fault),
+            skip: 0,
+            limit: Some(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn errors_are_injected_until_the_limit() {
+        let (provider, faults) = provider(rule(
+            "generate",
+            FaultKind::Error {
+                message: "503 overloaded".to_string(),
+            },
+        ));
+        let config = GenerationConfig::default();
+
+        let err = provider.generate("hi", &config).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InjectedFault>(),
+            Some(InjectedFault::Error { message, .. }) if message == "503 overloaded"
+        ));
+        // The retry goes through once the rule is spent
+        let response = provider.generate("hi", &config).await.unwrap();
+        assert_eq!(response.content, "one two three");
+        assert_eq!(faults.hits(), vec![1]);
+        assert_eq!(provider.kind(), ProviderKind::Mock);
+    }
+
+    #[tokio::test]
+    async fn disconnects_cut_streams_short() {
+        let (provider, _) = provider(rule("stream", FaultKind::Disconnect));
+        let config = GenerationConfig::default();
+
+        let chunks: Vec<Result<String>> = provider
+            .stream("hi", &config)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].as_ref().unwrap(), "one ");
+        assert_eq!(
+            chunks[1].as_ref().unwrap_err().to_string(),
+            "injected provider disconnect in stream"
+        );
+    }
+}
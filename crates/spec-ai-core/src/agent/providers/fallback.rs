@@ -0,0 +1,491 @@
+//! Fallback Provider
+//!
+//! Tries an ordered chain of providers. Failures that may go away on their
+//! own (rate limits, timeouts, 5xx responses, dropped connections) are
+//! retried with exponential backoff before the next provider is tried; any
+//! other error is returned straight away. A provider whose requests keep
+//! failing has its circuit opened and is skipped until the circuit resets.
+
+use crate::agent::model::{
+    DeltaStream, GenerationConfig, ModelProvider, ModelResponse, OutputSchema, ProviderKind,
+    ProviderMetadata,
+};
+use crate::config::ProvidersConfig;
+use crate::error::{code_of, ErrorCode};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Attempts kept for [`FallbackProvider::recent_attempts`]
+const MAX_RECORDED_ATTEMPTS: usize = 100;
+
+/// Retry, backoff and circuit breaker settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries of each provider before moving to the next
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive failed requests that open a provider's circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit skips its provider
+    pub circuit_reset: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl From<&ProvidersConfig> for RetryPolicy {
+    fn from(config: &ProvidersConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+            failure_threshold: config.failure_threshold.max(1),
+            circuit_reset: Duration::from_secs(config.circuit_reset_secs),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from(&ProvidersConfig::default())
+    }
+}
+
+/// One request made by a [`FallbackProvider`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderAttempt {
+    /// Provider kind, e.g. "openai"
+    pub provider: String,
+    pub model: String,
+    /// Trait method called, e.g. "generate"
+    pub operation: &'static str,
+    /// Attempt number on this provider, starting at 1
+    pub attempt: u32,
+    pub latency: Duration,
+    /// Code of the failure, `None` if the attempt succeeded
+    pub error: Option<ErrorCode>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Circuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Circuit {
+    fn is_open(&self, now: Instant) -> bool {
+        self.open_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Provider that retries and fails over across a chain of providers
+///
+/// Metadata, kind and model name are those of the first provider. Streams
+/// are retried until they start; errors in the middle of a stream are
+/// passed on.
+pub struct FallbackProvider {
+    providers: Vec<Arc<dyn ModelProvider>>,
+    policy: RetryPolicy,
+    circuits: Mutex<Vec<Circuit>>,
+    attempts: Mutex<VecDeque<ProviderAttempt>>,
+}
+
+impl FallbackProvider {
+    /// Chain `primary` and `fallbacks`, tried in that order
+    pub fn new(
+        primary: Arc<dyn ModelProvider>,
+        fallbacks: Vec<Arc<dyn ModelProvider>>,
+        policy: RetryPolicy,
+    ) -> Self {
+        let mut providers = vec![primary];
+        providers.extend(fallbacks);
+        Self {
+            circuits: Mutex::new(vec![Circuit::default(); providers.len()]),
+            providers,
+            policy,
+            attempts: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// The most recent attempts, oldest first
+    pub fn recent_attempts(&self) -> Vec<ProviderAttempt> {
+        self.attempts.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Whether the circuit of the provider at `index` in the chain is open
+    pub fn is_circuit_open(&self, index: usize) -> bool {
+        self.circuits
+            .lock()
+            .unwrap()
+            .get(index)
+            .is_some_and(|circuit| circuit.is_open(Instant::now()))
+    }
+
+    /// Providers to try, in order, and whether each may be retried
+    ///
+    /// Providers with an open circuit are skipped. If every circuit is open,
+    /// the provider whose circuit resets first gets a single attempt rather
+    /// than failing the request outright.
+    fn plan(&self) -> Vec<(usize, bool)> {
+        let circuits = self.circuits.lock().unwrap();
+        let now = Instant::now();
+        let closed: Vec<(usize, bool)> = (0..self.providers.len())
+            .filter(|&index| !circuits[index].is_open(now))
+            .map(|index| (index, true))
+            .collect();
+        if !closed.is_empty() {
+            return closed;
+        }
+        let soonest = (0..circuits.len())
+            .min_by_key(|&index| circuits[index].open_until)
+            .unwrap_or(0);
+        vec![(soonest, false)]
+    }
+
+    fn record(&self, attempt: ProviderAttempt) {
+        match attempt.error {
+            None => debug!(
+                provider = %attempt.provider,
+                model = %attempt.model,
+                operation = attempt.operation,
+                attempt = attempt.attempt,
+                latency_ms = attempt.latency.as_millis() as u64,
+                "Provider attempt succeeded"
+            ),
+            Some(code) => warn!(
+                provider = %attempt.provider,
+                model = %attempt.model,
+                operation = attempt.operation,
+                attempt = attempt.attempt,
+                latency_ms = attempt.latency.as_millis() as u64,
+                error_code = code.as_str(),
+                "Provider attempt failed"
+            ),
+        }
+        let mut attempts = self.attempts.lock().unwrap();
+        if attempts.len() == MAX_RECORDED_ATTEMPTS {
+            attempts.pop_front();
+        }
+        attempts.push_back(attempt);
+    }
+
+    fn close_circuit(&self, index: usize) {
+        let mut circuits = self.circuits.lock().unwrap();
+        circuits[index] = Circuit::default();
+    }
+
+    fn record_failure(&self, index: usize) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = &mut circuits[index];
+        circuit.consecutive_failures += 1;
+        if circuit.consecutive_failures >= self.policy.failure_threshold {
+            warn!(
+                provider = self.providers[index].kind().as_str(),
+                "Opening provider circuit for {:?} after {} failed requests",
+                self.policy.circuit_reset,
+                circuit.consecutive_failures
+            );
+            circuit.open_until = Some(Instant::now() + self.policy.circuit_reset);
+        }
+    }
+
+    /// Run `call` against the chain until one provider succeeds
+    async fn call<T, F, Fut>(&self, operation: &'static str, call: F) -> Result<T>
+    where
+        F: Fn(Arc<dyn ModelProvider>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_error = None;
+        for (index, retry) in self.plan() {
+            let provider = &self.providers[index];
+            let max_attempts = if retry {
+                self.policy.max_retries + 1
+            } else {
+                1
+            };
+            for attempt in 1..=max_attempts {
+                if attempt > 1 {
+                    tokio::time::sleep(self.policy.backoff(attempt - 1)).await;
+                }
+                let started = Instant::now();
+                let result = call(Arc::clone(provider)).await;
+                let error = result.as_ref().err().map(code_of);
+                self.record(ProviderAttempt {
+                    provider: provider.kind().as_str().to_string(),
+                    model: provider.model_name().to_string(),
+                    operation,
+                    attempt,
+                    latency: started.elapsed(),
+                    error,
+                });
+                match result {
+                    Ok(value) => {
+                        self.close_circuit(index);
+                        return Ok(value);
+                    }
+                    Err(err) if !code_of(&err).is_retryable() => return Err(err),
+                    Err(err) => last_error = Some(err),
+                }
+            }
+            self.record_failure(index);
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("No model provider configured")))
+    }
+}
+
+#[async_trait]
+impl ModelProvider for FallbackProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        self.call("generate", |provider| async move {
+            provider.generate(prompt, config).await
+        })
+        .await
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        self.call("generate_json", |provider| async move {
+            provider.generate_json(prompt, schema, config).await
+        })
+        .await
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        self.call("stream", |provider| async move {
+            provider.stream(prompt, config).await
+        })
+        .await
+    }
+
+    async fn stream_deltas(&self, prompt: &str, config: &GenerationConfig) -> Result<DeltaStream> {
+        self.call("stream", |provider| async move {
+            provider.stream_deltas(prompt, config).await
+        })
+        .await
+    }
+
+    fn metadata(&self) -> ProviderMetadata {
+        self.providers[0].metadata()
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        self.providers[0].list_models().await
+    }
+
+    fn kind(&self) -> ProviderKind {
+        self.providers[0].kind()
+    }
+
+    fn model_name(&self) -> &str {
+        self.providers[0].model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::providers::MockProvider;
+    use crate::error::Error;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails with `status` a number of times, then answers
+    struct FlakyProvider {
+        status: u16,
+        failures: AtomicU32,
+        calls: AtomicU32,
+    }
+
+    impl FlakyProvider {
+        fn new(status: u16, failures: u32) -> Arc<Self> {
+            Arc::new(Self {
+                status,
+                failures: AtomicU32::new(failures),
+                calls: AtomicU32::new(0),
+            })
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl ModelProvider for FlakyProvider {
+        async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let failing = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing {
+                return Err(Error::Provider {
+                    provider: "OpenAI".to_string(),
+                    status: self.status,
+                    message: "flaky".to_string(),
+                }
+                .into());
+            }
+            MockProvider::new("flaky answer")
+                .generate(prompt, config)
+                .await
+        }
+
+        async fn stream(
+            &self,
+            prompt: &str,
+            config: &GenerationConfig,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+            MockProvider::new("flaky answer")
+                .stream(prompt, config)
+                .await
+        }
+
+        fn metadata(&self) -> ProviderMetadata {
+            MockProvider::default().metadata()
+        }
+
+        fn kind(&self) -> ProviderKind {
+            ProviderKind::Mock
+        }
+
+        fn model_name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            failure_threshold: 2,
+            circuit_reset: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(3),
+            ..policy()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(4), Duration::from_secs(3));
+        assert_eq!(policy.backoff(40), Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn retryable_errors_are_retried_on_the_same_provider() {
+        let primary = FlakyProvider::new(429, 2);
+        let fallback: Arc<dyn ModelProvider> = Arc::new(MockProvider::new("fallback answer"));
+        let provider = FallbackProvider::new(primary.clone(), vec![fallback], policy());
+
+        let response = provider
+            .generate("hi", &GenerationConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(response.content, "flaky answer");
+        assert_eq!(primary.calls(), 3);
+
+        let codes: Vec<_> = provider
+            .recent_attempts()
+            .iter()
+            .map(|a| (a.attempt, a.error))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                (1, Some(ErrorCode::RateLimited)),
+                (2, Some(ErrorCode::RateLimited)),
+                (3, None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn exhausted_providers_fail_over_and_open_their_circuit() {
+        let primary = FlakyProvider::new(503, u32::MAX);
+        let fallback: Arc<dyn ModelProvider> = Arc::new(MockProvider::new("fallback answer"));
+        let provider = FallbackProvider::new(primary.clone(), vec![fallback], policy());
+        let config = GenerationConfig::default();
+
+        let response = provider.generate("hi", &config).await.unwrap();
+        assert_eq!(response.content, "fallback answer");
+        assert_eq!(primary.calls(), 3);
+        assert!(!provider.is_circuit_open(0));
+
+        provider.generate("hi", &config).await.unwrap();
+        assert_eq!(primary.calls(), 6);
+        assert!(provider.is_circuit_open(0));
+
+        // The open circuit sends requests straight to the fallback
+        provider.generate("hi", &config).await.unwrap();
+        assert_eq!(primary.calls(), 6);
+        let last = provider.recent_attempts().pop().unwrap();
+        assert_eq!(last.model, "mock-model");
+        assert_eq!(last.error, None);
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_returned_without_failover() {
+        let primary = FlakyProvider::new(400, 1);
+        let fallback: Arc<dyn ModelProvider> = Arc::new(MockProvider::new("fallback answer"));
+        let provider = FallbackProvider::new(primary.clone(), vec![fallback], policy());
+
+        let err = provider
+            .generate("hi", &GenerationConfig::default())
+            .await
+            .unwrap_err();
+        assert_eq!(code_of(&err), ErrorCode::Provider);
+        assert_eq!(primary.calls(), 1);
+        assert_eq!(provider.model_name(), "flaky");
+    }
+
+    #[tokio::test]
+    async fn open_circuits_still_get_a_single_attempt_when_nothing_else_is_left() {
+        let primary = FlakyProvider::new(503, u32::MAX);
+        let provider = FallbackProvider::new(
+            primary.clone(),
+            vec![],
+            RetryPolicy {
+                failure_threshold: 1,
+                ..policy()
+            },
+        );
+        let config = GenerationConfig::default();
+
+        let err = provider.generate("hi", &config).await.unwrap_err();
+        assert_eq!(code_of(&err), ErrorCode::ProviderUnavailable);
+        assert_eq!(primary.calls(), 3);
+        assert!(provider.is_circuit_open(0));
+
+        provider.generate("hi", &config).await.unwrap_err();
+        assert_eq!(primary.calls(), 4);
+    }
+}
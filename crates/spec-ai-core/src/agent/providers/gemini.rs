@@ -0,0 +1,1129 @@
+//! Google Gemini Model Provider
+//!
+//! Integration with the Gemini API (`generateContent`).
+//! Supports streaming, native function calling, safety settings, and
+//! inline image input.
+
+use crate::agent::model::{
+    parse_thinking_tokens, GenerationConfig, ModelProvider, ModelResponse, OutputSchema,
+    ProviderKind, ProviderMetadata, TokenUsage, ToolCall,
+};
+use crate::agent::structured;
+use crate::error::Error;
+use anyhow::{anyhow, bail, Context, Result};
+use async_stream::stream;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::pin::Pin;
+
+const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_MODEL: &str = "gemini-1.5-flash";
+/// Categories a safety threshold given without a category applies to
+const HARM_CATEGORIES: [&str; 4] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+const SAFETY_THRESHOLDS: [&str; 5] = [
+    "BLOCK_NONE",
+    "BLOCK_ONLY_HIGH",
+    "BLOCK_MEDIUM_AND_ABOVE",
+    "BLOCK_LOW_AND_ABOVE",
+    "OFF",
+];
+/// Finish reasons that mean the answer was withheld rather than completed
+const BLOCKED_FINISH_REASONS: [&str; 5] = [
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+];
+/// Schema keywords the Gemini API rejects in function parameters
+const UNSUPPORTED_SCHEMA_KEYS: [&str; 2] = ["$schema", "additionalProperties"];
+
+/// Turn in a Gemini conversation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Content {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+/// One piece of a turn: text, inline data or a function call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inline_data: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionCall>,
+    /// Set on parts that hold the model's thinking
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    thought: bool,
+}
+
+impl Part {
+    fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Base64 encoded file contents sent inline with a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Blob {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+/// Function declaration for Gemini function calling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub description: String,
+    /// Omitted for functions without parameters, which the API requires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+impl FunctionDeclaration {
+    /// Build a declaration from a spec-ai tool definition
+    ///
+    /// Schema keywords the API does not accept are dropped.
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: &serde_json::Value,
+    ) -> Self {
+        let mut parameters = parameters.clone();
+        strip_unsupported_schema_keys(&mut parameters);
+        let has_properties = parameters
+            .get("properties")
+            .and_then(|properties| properties.as_object())
+            .is_some_and(|properties| !properties.is_empty());
+
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters: has_properties.then_some(parameters),
+        }
+    }
+}
+
+fn strip_unsupported_schema_keys(schema: &mut serde_json::Value) {
+    match schema {
+        serde_json::Value::Object(map) => {
+            for key in UNSUPPORTED_SCHEMA_KEYS {
+                map.remove(key);
+            }
+            map.values_mut().for_each(strip_unsupported_schema_keys);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_unsupported_schema_keys),
+        _ => {}
+    }
+}
+
+/// Group of function declarations in a request
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolSet {
+    function_declarations: Vec<FunctionDeclaration>,
+}
+
+/// Blocking threshold for one harm category
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+/// Parse safety settings written as `category=threshold` pairs
+///
+/// Pairs are separated by commas, and names may be given in any case without
+/// their `HARM_CATEGORY_` prefix, e.g. `harassment=block_none`. A threshold on
+/// its own applies to every harm category.
+pub fn parse_safety_settings(spec: &str) -> Result<Vec<SafetySetting>> {
+    let mut settings = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (categories, threshold) = match entry.split_once('=') {
+            Some((category, threshold)) => {
+                let category = category.trim().to_uppercase();
+                let category = if category.starts_with("HARM_CATEGORY_") {
+                    category
+                } else {
+                    format!("HARM_CATEGORY_{}", category)
+                };
+                (vec![category], threshold)
+            }
+            None => (
+                HARM_CATEGORIES.iter().map(|c| c.to_string()).collect(),
+                entry,
+            ),
+        };
+
+        let threshold = threshold.trim().to_uppercase();
+        if !SAFETY_THRESHOLDS.contains(&threshold.as_str()) {
+            bail!(
+                "Unknown Gemini safety threshold '{}' (expected one of: {})",
+                threshold,
+                SAFETY_THRESHOLDS.join(", ")
+            );
+        }
+        for category in categories {
+            settings.retain(|s: &SafetySetting| s.category != category);
+            settings.push(SafetySetting {
+                category,
+                threshold: threshold.clone(),
+            });
+        }
+    }
+    Ok(settings)
+}
+
+/// Image sent alongside a prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInput {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl ImageInput {
+    pub fn new(mime_type: impl Into<String>, data: Vec<u8>) -> Self {
+        Self {
+            mime_type: mime_type.into(),
+            data,
+        }
+    }
+
+    /// Read an image file, taking its MIME type from the extension
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        let mime_type = match extension.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "webp" => "image/webp",
+            "heic" => "image/heic",
+            "heif" => "image/heif",
+            _ => bail!("Unsupported image type: {}", path.display()),
+        };
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read image: {}", path.display()))?;
+        Ok(Self::new(mime_type, data))
+    }
+
+    fn to_part(&self) -> Part {
+        Part {
+            inline_data: Some(Blob {
+                mime_type: self.mime_type.clone(),
+                data: general_purpose::STANDARD.encode(&self.data),
+            }),
+            ..Part::default()
+        }
+    }
+}
+
+/// Gemini `generateContent` request
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest {
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GeminiGenerationConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    safety_settings: Vec<SafetySetting>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<ToolSet>>,
+    /// Function calling mode, used to force a call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<serde_json::Value>,
+}
+
+/// Sampling options for a Gemini request
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking_config: Option<ThinkingConfig>,
+}
+
+/// How many tokens a thinking model may spend before answering
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThinkingConfig {
+    thinking_budget: u32,
+}
+
+/// Gemini `generateContent` response, also sent as each streamed chunk
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    usage_metadata: Option<UsageMetadata>,
+    prompt_feedback: Option<PromptFeedback>,
+    model_version: Option<String>,
+}
+
+impl GenerateContentResponse {
+    /// Fail when the prompt or the answer was blocked
+    fn check_blocked(&self) -> Result<()> {
+        if let Some(reason) = self
+            .prompt_feedback
+            .as_ref()
+            .and_then(|feedback| feedback.block_reason.as_deref())
+        {
+            bail!("Gemini blocked the prompt ({})", reason);
+        }
+        if let Some(reason) = self
+            .candidates
+            .first()
+            .and_then(|candidate| candidate.finish_reason.as_deref())
+            .filter(|reason| BLOCKED_FINISH_REASONS.contains(reason))
+        {
+            bail!("Gemini blocked the response ({})", reason);
+        }
+        Ok(())
+    }
+
+    /// Parts of the first candidate
+    fn parts(&self) -> &[Part] {
+        self.candidates
+            .first()
+            .and_then(|candidate| candidate.content.as_ref())
+            .map(|content| content.parts.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<Content>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+/// Token usage in a Gemini response
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    /// Thinking tokens, billed as output on top of `candidates_token_count`
+    #[serde(default)]
+    thoughts_token_count: u32,
+}
+
+impl UsageMetadata {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage::new(
+            self.prompt_token_count,
+            self.candidates_token_count + self.thoughts_token_count,
+        )
+    }
+}
+
+/// Models API response
+#[derive(Debug, Clone, Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelInfo {
+    name: String,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+/// Gemini provider for Google's Gemini models
+#[derive(Debug, Clone)]
+pub struct GeminiProvider {
+    /// HTTP client for API requests
+    client: reqwest::Client,
+    /// API key for authentication
+    api_key: String,
+    /// Base URL of the Gemini API, up to and including the version
+    base_url: String,
+    /// Default model to use
+    model: String,
+    /// Optional system message for all requests
+    system_message: Option<String>,
+    /// Optional functions available for function calling
+    tools: Option<Vec<FunctionDeclaration>>,
+    /// Blocking thresholds sent with every request
+    safety_settings: Vec<SafetySetting>,
+}
+
+impl GeminiProvider {
+    /// Create a new Gemini provider with the default configuration
+    ///
+    /// This will use the GEMINI_API_KEY environment variable for authentication
+    /// and default to the "gemini-1.5-flash" model.
+    pub fn new() -> Result<Self> {
+        let api_key = std::env::var("GEMINI_API_KEY")
+            .map_err(|_| anyhow!("GEMINI_API_KEY environment variable not set"))?;
+
+        Ok(Self::with_api_key(api_key))
+    }
+
+    /// Create a new Gemini provider with a custom API key
+    pub fn with_api_key(api_key: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.into(),
+            base_url: GEMINI_API_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            system_message: None,
+            tools: None,
+            safety_settings: Vec::new(),
+        }
+    }
+
+    /// Send requests to another API endpoint, such as a proxy
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the model to use
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Set a system message to be included in all requests
+    pub fn with_system_message(mut self, message: impl Into<String>) -> Self {
+        self.system_message = Some(message.into());
+        self
+    }
+
+    /// Set functions available for function calling
+    pub fn with_tools(mut self, tools: Vec<FunctionDeclaration>) -> Self {
+        self.tools = if tools.is_empty() { None } else { Some(tools) };
+        self
+    }
+
+    /// Set the safety thresholds sent with every request
+    pub fn with_safety_settings(mut self, settings: Vec<SafetySetting>) -> Self {
+        self.safety_settings = settings;
+        self
+    }
+
+    /// Generate a response to a prompt accompanied by images
+    pub async fn generate_with_images(
+        &self,
+        prompt: &str,
+        images: &[ImageInput],
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        let request = self.build_request(prompt, images, config);
+        self.send(&request).await
+    }
+
+    /// Send a non-streaming `generateContent` request
+    async fn send(&self, request: &GenerateContentRequest) -> Result<ModelResponse> {
+        // Make the API call
+        let response = self
+            .client
+            .post(self.endpoint("generateContent"))
+            .header("x-goog-api-key", &self.api_key)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Gemini API request failed: {}", e))?;
+
+        // Check for HTTP errors
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Error::Provider {
+                provider: "Gemini".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
+        }
+
+        // Parse the response
+        let api_response: GenerateContentResponse = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Gemini response: {}", e))?;
+
+        self.to_model_response(api_response)
+    }
+
+    /// Build the request for the Gemini API
+    fn build_request(
+        &self,
+        prompt: &str,
+        images: &[ImageInput],
+        config: &GenerationConfig,
+    ) -> GenerateContentRequest {
+        let mut parts: Vec<Part> = images.iter().map(ImageInput::to_part).collect();
+        parts.push(Part::text(prompt));
+
+        let stop_sequences: Vec<String> = config
+            .stop_sequences
+            .iter()
+            .flatten()
+            .filter(|stop| !stop.is_empty())
+            .cloned()
+            .collect();
+        let generation_config = GeminiGenerationConfig {
+            temperature: config.temperature,
+            max_output_tokens: config.max_tokens,
+            top_p: config.top_p,
+            stop_sequences: if stop_sequences.is_empty() {
+                None
+            } else {
+                Some(stop_sequences)
+            },
+            thinking_config: config
+                .thinking_budget
+                .map(|thinking_budget| ThinkingConfig { thinking_budget }),
+        };
+
+        GenerateContentRequest {
+            contents: vec![Content {
+                role: Some("user".to_string()),
+                parts,
+            }],
+            system_instruction: self.system_message.as_ref().map(|message| Content {
+                role: None,
+                parts: vec![Part::text(message)],
+            }),
+            generation_config: Some(generation_config),
+            safety_settings: self.safety_settings.clone(),
+            tools: self.tools.clone().map(|function_declarations| {
+                vec![ToolSet {
+                    function_declarations,
+                }]
+            }),
+            tool_config: None,
+        }
+    }
+
+    /// Build a request that must call a single function taking `schema` as
+    /// its parameters, so the call arguments are the structured response
+    fn build_structured_request(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> GenerateContentRequest {
+        let description = schema
+            .description
+            .clone()
+            .unwrap_or_else(|| "Report the response".to_string());
+        let mut request = self.build_request(prompt, &[], config);
+        request.tools = Some(vec![ToolSet {
+            function_declarations: vec![FunctionDeclaration::new(
+                &schema.name,
+                description,
+                &schema.schema,
+            )],
+        }]);
+        request.tool_config = Some(serde_json::json!({
+            "functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": [schema.name]}
+        }));
+        request
+    }
+
+    /// URL of a model method such as `generateContent`
+    fn endpoint(&self, method: &str) -> String {
+        format!("{}/models/{}:{}", self.base_url, self.model, method)
+    }
+
+    /// Convert a `generateContent` response into the spec-ai response format
+    fn to_model_response(&self, api_response: GenerateContentResponse) -> Result<ModelResponse> {
+        api_response.check_blocked()?;
+
+        let mut raw_content = String::new();
+        let mut thinking = String::new();
+        let mut tool_calls = Vec::new();
+
+        for part in api_response.parts() {
+            if let Some(call) = &part.function_call {
+                // Gemini does not identify calls, so number them within the response
+                tool_calls.push(ToolCall {
+                    id: format!("call_{}", tool_calls.len()),
+                    function_name: call.name.clone(),
+                    arguments: call.args.clone(),
+                });
+            } else if let Some(text) = &part.text {
+                if part.thought {
+                    thinking.push_str(text);
+                } else {
+                    raw_content.push_str(text);
+                }
+            }
+        }
+
+        // Parse thinking tokens if present
+        let (tagged_reasoning, content) = parse_thinking_tokens(&raw_content);
+        let reasoning = if thinking.is_empty() {
+            tagged_reasoning
+        } else {
+            Some(thinking)
+        };
+
+        let model = api_response
+            .model_version
+            .unwrap_or_else(|| self.model.clone());
+        let finish_reason = api_response
+            .candidates
+            .into_iter()
+            .next()
+            .and_then(|candidate| candidate.finish_reason);
+        if finish_reason.as_deref() == Some("MAX_TOKENS") {
+            tracing::warn!(
+                "Gemini response from {} was cut off at the max_tokens limit",
+                model
+            );
+        }
+
+        Ok(ModelResponse {
+            content,
+            model,
+            usage: api_response.usage_metadata.map(|usage| usage.token_usage()),
+            finish_reason,
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+            reasoning,
+        })
+    }
+
+    /// Parse SSE (Server-Sent Events) line
+    fn parse_sse_line(line: &str) -> Option<GenerateContentResponse> {
+        let data = line.strip_prefix("data:")?.trim_start();
+        serde_json::from_str(data).ok()
+    }
+}
+
+impl Default for GeminiProvider {
+    fn default() -> Self {
+        Self::new().expect("Failed to create default Gemini provider")
+    }
+}
+
+#[async_trait]
+impl ModelProvider for GeminiProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        self.generate_with_images(prompt, &[], config).await
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        // Function parameters must be objects, so other shapes are only described
+        if !schema.is_object() {
+            let prompt = structured::schema_prompt(prompt, schema);
+            return self.generate(&prompt, config).await;
+        }
+        let request = self.build_structured_request(prompt, schema, config);
+        let response = self.send(&request).await?;
+        Ok(structured::forced_tool_content(response, schema))
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        let request = self.build_request(prompt, &[], config);
+
+        // Make the streaming API call
+        let response = self
+            .client
+            .post(self.endpoint("streamGenerateContent"))
+            .query(&[("alt", "sse")])
+            .header("x-goog-api-key", &self.api_key)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Gemini streaming API request failed: {}", e))?;
+
+        // Check for HTTP errors
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Error::Provider {
+                provider: "Gemini".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
+        }
+
+        // Convert the response into a stream
+        let byte_stream = response.bytes_stream();
+
+        let stream = stream! {
+            use futures::StreamExt;
+
+            let mut tool_calls = 0usize;
+            // Raw bytes so characters split across chunks decode intact
+            let mut line_buffer: Vec<u8> = Vec::new();
+
+            let mut stream = byte_stream;
+            'read: while let Some(result) = stream.next().await {
+                match result {
+                    Ok(chunk) => {
+                        line_buffer.extend_from_slice(&chunk);
+
+                        // Process complete lines
+                        while let Some(newline_pos) = line_buffer.iter().position(|b| *b == b'\n') {
+                            let line_bytes: Vec<u8> = line_buffer.drain(..=newline_pos).collect();
+                            let line = String::from_utf8_lossy(&line_bytes);
+
+                            let Some(chunk) = Self::parse_sse_line(line.trim()) else {
+                                continue;
+                            };
+                            if let Err(e) = chunk.check_blocked() {
+                                yield Err(e);
+                                break 'read;
+                            }
+                            for part in chunk.parts() {
+                                if part.function_call.is_some() {
+                                    tool_calls += 1;
+                                } else if let Some(text) = part.text.as_ref().filter(|_| !part.thought) {
+                                    yield Ok(text.clone());
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(anyhow!("Stream error: {}", e));
+                        break;
+                    }
+                }
+            }
+
+            if tool_calls > 0 {
+                tracing::warn!(
+                    "Gemini stream ended with {} function call(s), which streamed responses cannot run",
+                    tool_calls
+                );
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .client
+            .get(format!("{}/models", self.base_url))
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to list Gemini models: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Error::Provider {
+                provider: "Gemini".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
+        }
+
+        let models: ModelList = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Gemini model list: {}", e))?;
+        Ok(models
+            .models
+            .into_iter()
+            .filter(|model| {
+                model
+                    .supported_generation_methods
+                    .iter()
+                    .any(|method| method == "generateContent")
+            })
+            .map(|model| {
+                model
+                    .name
+                    .strip_prefix("models/")
+                    .unwrap_or(&model.name)
+                    .to_string()
+            })
+            .collect())
+    }
+
+    fn metadata(&self) -> ProviderMetadata {
+        ProviderMetadata {
+            name: "Gemini".to_string(),
+            supported_models: vec![
+                "gemini-1.5-flash".to_string(),
+                "gemini-1.5-pro".to_string(),
+                "gemini-2.0-flash".to_string(),
+            ],
+            supports_streaming: true,
+        }
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Gemini
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// Answer one HTTP request with `body`, returning the server's base URL and
+    /// a handle that yields the request line, headers and body it received
+    fn serve_once(status: u16, content_type: &str, body: &str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/v1beta", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {} OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        );
+
+        let handle = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(socket.try_clone().unwrap());
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+
+            socket.write_all(response.as_bytes()).unwrap();
+            request
+        });
+        (base_url, handle)
+    }
+
+    #[test]
+    fn test_gemini_provider_builders() {
+        let provider = GeminiProvider::with_api_key("test-key")
+            .with_model("gemini-1.5-pro")
+            .with_base_url("http://localhost:8080/v1beta/")
+            .with_system_message("Be brief.");
+
+        assert_eq!(provider.api_key, "test-key");
+        assert_eq!(provider.model_name(), "gemini-1.5-pro");
+        assert_eq!(provider.kind(), ProviderKind::Gemini);
+        assert_eq!(
+            provider.endpoint("generateContent"),
+            "http://localhost:8080/v1beta/models/gemini-1.5-pro:generateContent"
+        );
+        assert!(provider.metadata().supports_streaming);
+    }
+
+    #[test]
+    fn test_build_request_with_tools_safety_and_images() {
+        let provider = GeminiProvider::with_api_key("test-key")
+            .with_system_message("System prompt")
+            .with_safety_settings(parse_safety_settings("dangerous_content=block_none").unwrap())
+            .with_tools(vec![
+                FunctionDeclaration::new(
+                    "echo",
+                    "Echo a message",
+                    &serde_json::json!({
+                        "$schema": "http://json-schema.org/draft-07/schema#",
+                        "type": "object",
+                        "properties": {"message": {"type": "string"}},
+                        "additionalProperties": false
+                    }),
+                ),
+                FunctionDeclaration::new(
+                    "now",
+                    "Current time",
+                    &serde_json::json!({"type": "object", "properties": {}}),
+                ),
+            ]);
+        let config = GenerationConfig {
+            temperature: Some(0.5),
+            max_tokens: Some(256),
+            top_p: None,
+            stop_sequences: Some(vec![String::new()]),
+            ..Default::default()
+        };
+        let image = ImageInput::new("image/png", vec![1, 2, 3]);
+
+        let request =
+            serde_json::to_value(provider.build_request("Describe", &[image], &config)).unwrap();
+
+        assert_eq!(
+            request["systemInstruction"]["parts"][0]["text"],
+            "System prompt"
+        );
+        assert_eq!(request["contents"][0]["role"], "user");
+        assert_eq!(
+            request["contents"][0]["parts"][0]["inlineData"],
+            serde_json::json!({"mimeType": "image/png", "data": "AQID"})
+        );
+        assert_eq!(request["contents"][0]["parts"][1]["text"], "Describe");
+        assert_eq!(
+            request["generationConfig"],
+            serde_json::json!({"temperature": 0.5, "maxOutputTokens": 256})
+        );
+        assert_eq!(
+            request["safetySettings"],
+            serde_json::json!([{"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "BLOCK_NONE"}])
+        );
+        let declarations = &request["tools"][0]["functionDeclarations"];
+        assert_eq!(
+            declarations[0]["parameters"],
+            serde_json::json!({"type": "object", "properties": {"message": {"type": "string"}}})
+        );
+        assert!(declarations[1].get("parameters").is_none());
+    }
+
+    #[test]
+    fn test_build_request_sets_thinking_budget() {
+        let provider = GeminiProvider::with_api_key("test-key");
+        let config = GenerationConfig {
+            thinking_budget: Some(1024),
+            ..Default::default()
+        };
+
+        let request = serde_json::to_value(provider.build_request("Hi", &[], &config)).unwrap();
+
+        assert_eq!(
+            request["generationConfig"]["thinkingConfig"],
+            serde_json::json!({"thinkingBudget": 1024})
+        );
+    }
+
+    #[test]
+    fn test_parse_safety_settings() {
+        let settings =
+            parse_safety_settings("block_only_high, HARM_CATEGORY_HARASSMENT=Block_None").unwrap();
+        assert_eq!(settings.len(), HARM_CATEGORIES.len());
+        assert!(settings.contains(&SafetySetting {
+            category: "HARM_CATEGORY_HARASSMENT".to_string(),
+            threshold: "BLOCK_NONE".to_string(),
+        }));
+        assert!(settings
+            .iter()
+            .filter(|s| s.category != "HARM_CATEGORY_HARASSMENT")
+            .all(|s| s.threshold == "BLOCK_ONLY_HIGH"));
+
+        assert!(parse_safety_settings("harassment=sometimes")
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown Gemini safety threshold 'SOMETIMES'"));
+    }
+
+    #[test]
+    fn test_image_input_from_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.JPG");
+        std::fs::write(&path, [0xff, 0xd8]).unwrap();
+
+        let image = ImageInput::from_path(&path).unwrap();
+        assert_eq!(image.mime_type, "image/jpeg");
+        assert_eq!(image.data, vec![0xff, 0xd8]);
+        assert!(ImageInput::from_path(dir.path().join("notes.txt")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_generate_maps_function_calls() {
+        let body = serde_json::json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "Weighing it up.", "thought": true},
+                    {"text": "Checking."},
+                    {"functionCall": {"name": "weather", "args": {"city": "Oslo"}}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5, "thoughtsTokenCount": 2},
+            "modelVersion": "gemini-1.5-flash-002"
+        });
+        let (base_url, server) = serve_once(200, "application/json", &body.to_string());
+        let provider = GeminiProvider::with_api_key("test-key").with_base_url(base_url);
+
+        let response = provider
+            .generate("What's the weather?", &GenerationConfig::default())
+            .await
+            .unwrap();
+        let request = server.join().unwrap();
+
+        assert!(request.starts_with("POST /v1beta/models/gemini-1.5-flash:generateContent "));
+        assert!(request.contains("x-goog-api-key: test-key"));
+        assert!(request.contains(r#""text":"What's the weather?""#));
+        assert_eq!(response.content, "Checking.");
+        assert_eq!(response.reasoning.as_deref(), Some("Weighing it up."));
+        assert_eq!(response.model, "gemini-1.5-flash-002");
+        assert_eq!(response.finish_reason.as_deref(), Some("STOP"));
+        assert_eq!(response.usage, Some(TokenUsage::new(10, 7)));
+        let calls = response.tool_calls.unwrap();
+        assert_eq!(calls[0].id, "call_0");
+        assert_eq!(calls[0].function_name, "weather");
+        assert_eq!(calls[0].arguments["city"], "Oslo");
+    }
+
+    #[tokio::test]
+    async fn test_generate_json_forces_schema_function() {
+        let body = serde_json::json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"functionCall": {"name": "route", "args": {"agent": "coder"}}}
+                ]},
+                "finishReason": "STOP"
+            }]
+        });
+        let (base_url, server) = serve_once(200, "application/json", &body.to_string());
+        let provider = GeminiProvider::with_api_key("test-key").with_base_url(base_url);
+        let schema = OutputSchema::new(
+            "route",
+            serde_json::json!({"type": "object", "properties": {"agent": {"type": "string"}}}),
+        );
+
+        let response = provider
+            .generate_json("Route this", &schema, &GenerationConfig::default())
+            .await
+            .unwrap();
+        let request = server.join().unwrap();
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+
+        assert_eq!(
+            body["toolConfig"]["functionCallingConfig"],
+            serde_json::json!({"mode": "ANY", "allowedFunctionNames": ["route"]})
+        );
+        assert_eq!(body["tools"][0]["functionDeclarations"][0]["name"], "route");
+        assert_eq!(response.content, r#"{"agent":"coder"}"#);
+        assert!(response.tool_calls.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_reports_blocked_prompt() {
+        let body = r#"{"promptFeedback": {"blockReason": "SAFETY"}}"#;
+        let (base_url, server) = serve_once(200, "application/json", body);
+        let provider = GeminiProvider::with_api_key("test-key").with_base_url(base_url);
+
+        let err = provider
+            .generate("Something unsafe", &GenerationConfig::default())
+            .await
+            .unwrap_err();
+        server.join().unwrap();
+
+        assert_eq!(err.to_string(), "Gemini blocked the prompt (SAFETY)");
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_text_chunks() {
+        let body = [
+            r#"data: {"candidates": [{"content": {"role": "model", "parts": [{"text": "Hel"}]}}]}"#,
+            r#"data: {"candidates": [{"content": {"role": "model", "parts": [{"text": "lo"}]}, "finishReason": "STOP"}]}"#,
+            r#"data: {"candidates": [{"finishReason": "SAFETY"}]}"#,
+            r#"data: {"candidates": [{"content": {"role": "model", "parts": [{"text": "unreachable"}]}}]}"#,
+        ]
+        .join("\r\n\r\n");
+        let (base_url, server) = serve_once(200, "text/event-stream", &body);
+        let provider = GeminiProvider::with_api_key("test-key").with_base_url(base_url);
+
+        let chunks: Vec<Result<String>> = provider
+            .stream("Hi", &GenerationConfig::default())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let request = server.join().unwrap();
+
+        assert!(request
+            .starts_with("POST /v1beta/models/gemini-1.5-flash:streamGenerateContent?alt=sse "));
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].as_ref().unwrap(), "Hel");
+        assert_eq!(chunks[1].as_ref().unwrap(), "lo");
+        assert_eq!(
+            chunks[2].as_ref().unwrap_err().to_string(),
+            "Gemini blocked the response (SAFETY)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_models_keeps_generation_models() {
+        let body = serde_json::json!({"models": [
+            {"name": "models/gemini-1.5-pro", "supportedGenerationMethods": ["generateContent", "countTokens"]},
+            {"name": "models/text-embedding-004", "supportedGenerationMethods": ["embedContent"]}
+        ]});
+        let (base_url, server) = serve_once(200, "application/json", &body.to_string());
+        let provider = GeminiProvider::with_api_key("test-key").with_base_url(base_url);
+
+        let models = provider.list_models().await.unwrap();
+        assert!(server.join().unwrap().starts_with("GET /v1beta/models "));
+        assert_eq!(models, vec!["gemini-1.5-pro".to_string()]);
+    }
+}
@@ -4,9 +4,10 @@
 //! This allows using locally hosted models while still benefiting from the agent
 //! framework's standard tooling and function calling surface.
 
+use crate::agent::function_calling::schema_to_response_format;
 use crate::agent::model::{
-    parse_thinking_tokens, GenerationConfig, ModelProvider, ModelResponse, ProviderKind,
-    ProviderMetadata, TokenUsage, ToolCall,
+    parse_thinking_tokens, GenerationConfig, ModelProvider, ModelResponse, OutputSchema,
+    ProviderKind, ProviderMetadata, TokenUsage, ToolCall,
 };
 use anyhow::{anyhow, Result};
 use async_openai::{
@@ -14,6 +15,7 @@ use async_openai::{
     types::{
         ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
         ChatCompletionRequestUserMessageArgs, ChatCompletionTool, CreateChatCompletionRequestArgs,
+        ResponseFormat,
     },
     Client,
 };
@@ -118,11 +120,14 @@ impl LMStudioProvider {
 
         Ok(messages)
     }
-}
 
-#[async_trait]
-impl ModelProvider for LMStudioProvider {
-    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+    /// Run a chat completion, constrained to `response_format` if given
+    async fn complete(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+        response_format: Option<ResponseFormat>,
+    ) -> Result<ModelResponse> {
         let messages = self.build_messages(prompt)?;
 
         let mut request_builder = CreateChatCompletionRequestArgs::default();
@@ -147,7 +152,10 @@ impl ModelProvider for LMStudioProvider {
             request_builder.stop(stop.clone());
         }
 
-        if let Some(ref tools) = self.tools {
+        // LM Studio enforces the schema through its grammar-based sampling
+        if let Some(format) = response_format {
+            request_builder.response_format(format);
+        } else if let Some(ref tools) = self.tools {
             request_builder.tools(tools.clone());
         }
 
@@ -189,11 +197,9 @@ impl ModelProvider for LMStudioProvider {
             })
             .filter(|calls| !calls.is_empty());
 
-        let usage = response.usage.map(|u| TokenUsage {
-            prompt_tokens: u.prompt_tokens,
-            completion_tokens: u.completion_tokens,
-            total_tokens: u.total_tokens,
-        });
+        let usage = response
+            .usage
+            .map(|u| TokenUsage::new(u.prompt_tokens, u.completion_tokens));
 
         Ok(ModelResponse {
             content,
@@ -204,6 +210,23 @@ impl ModelProvider for LMStudioProvider {
             reasoning,
         })
     }
+}
+
+#[async_trait]
+impl ModelProvider for LMStudioProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        self.complete(prompt, config, None).await
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        self.complete(prompt, config, Some(schema_to_response_format(schema)))
+            .await
+    }
 
     async fn stream(
         &self,
@@ -299,6 +322,18 @@ impl ModelProvider for LMStudioProvider {
         Ok(Box::pin(stream))
     }
 
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let models = self
+            .client
+            .models()
+            .list()
+            .await
+            .map_err(|e| anyhow!("Failed to list LM Studio models: {}", e))?;
+        let mut ids: Vec<String> = models.data.into_iter().map(|model| model.id).collect();
+        ids.sort();
+        Ok(ids)
+    }
+
     fn metadata(&self) -> ProviderMetadata {
         ProviderMetadata {
             name: "LM Studio".to_string(),
@@ -314,6 +349,10 @@ impl ModelProvider for LMStudioProvider {
     fn kind(&self) -> ProviderKind {
         ProviderKind::LMStudio
     }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
 }
 
 #[cfg(test)]
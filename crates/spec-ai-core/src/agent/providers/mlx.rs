@@ -205,11 +205,9 @@ impl ModelProvider for MLXProvider {
             })
             .filter(|calls| !calls.is_empty());
 
-        let usage = response.usage.map(|u| TokenUsage {
-            prompt_tokens: u.prompt_tokens,
-            completion_tokens: u.completion_tokens,
-            total_tokens: u.total_tokens,
-        });
+        let usage = response
+            .usage
+            .map(|u| TokenUsage::new(u.prompt_tokens, u.completion_tokens));
 
         Ok(ModelResponse {
             content,
@@ -325,6 +323,18 @@ impl ModelProvider for MLXProvider {
         Ok(Box::pin(stream))
     }
 
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let models = self
+            .client
+            .models()
+            .list()
+            .await
+            .map_err(|e| anyhow!("Failed to list MLX models: {}", e))?;
+        let mut ids: Vec<String> = models.data.into_iter().map(|model| model.id).collect();
+        ids.sort();
+        Ok(ids)
+    }
+
     fn metadata(&self) -> ProviderMetadata {
         ProviderMetadata {
             name: "MLX".to_string(),
@@ -342,6 +352,10 @@ impl ModelProvider for MLXProvider {
     fn kind(&self) -> ProviderKind {
         ProviderKind::MLX
     }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
 }
 
 #[cfg(test)]
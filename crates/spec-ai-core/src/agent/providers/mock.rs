@@ -72,11 +72,7 @@ impl ModelProvider for MockProvider {
         Ok(ModelResponse {
             content,
             model: self.model_name.clone(),
-            usage: Some(TokenUsage {
-                prompt_tokens,
-                completion_tokens,
-                total_tokens: prompt_tokens + completion_tokens,
-            }),
+            usage: Some(TokenUsage::new(prompt_tokens, completion_tokens)),
             finish_reason: Some("stop".to_string()),
             tool_calls: None,
             reasoning: None,
@@ -117,6 +113,10 @@ impl ModelProvider for MockProvider {
     fn kind(&self) -> ProviderKind {
         ProviderKind::Mock
     }
+
+    fn model_name(&self) -> &str {
+        &self.model_name
+    }
 }
 
 #[cfg(test)]
@@ -1,11 +1,24 @@
+#[cfg(feature = "chaos")]
+pub mod chaos;
+
+pub mod fallback;
+
 pub mod mock;
 
+pub mod transcript;
+
 #[cfg(feature = "openai")]
 pub mod openai;
 
 #[cfg(feature = "anthropic")]
 pub mod anthropic;
 
+#[cfg(feature = "gemini")]
+pub mod gemini;
+
+#[cfg(feature = "bedrock")]
+pub mod bedrock;
+
 #[cfg(feature = "ollama")]
 pub mod ollama;
 
@@ -15,7 +28,12 @@ pub mod mlx;
 #[cfg(feature = "lmstudio")]
 pub mod lmstudio;
 
+pub use fallback::{FallbackProvider, ProviderAttempt, RetryPolicy};
 pub use mock::MockProvider;
+pub use transcript::TranscriptProvider;
+
+#[cfg(feature = "chaos")]
+pub use chaos::ChaosProvider;
 
 #[cfg(feature = "openai")]
 pub use openai::OpenAIProvider;
@@ -29,5 +47,11 @@ pub use lmstudio::LMStudioProvider;
 #[cfg(feature = "anthropic")]
 pub use anthropic::AnthropicProvider;
 
+#[cfg(feature = "gemini")]
+pub use gemini::GeminiProvider;
+
+#[cfg(feature = "bedrock")]
+pub use bedrock::BedrockProvider;
+
 #[cfg(feature = "ollama")]
 pub use ollama::OllamaProvider;
@@ -4,9 +4,11 @@
 //! Supports any model available through your local Ollama instance.
 
 use crate::agent::model::{
-    parse_thinking_tokens, GenerationConfig, ModelProvider, ModelResponse, ProviderKind,
-    ProviderMetadata, TokenUsage,
+    parse_thinking_tokens, GenerationConfig, ModelProvider, ModelResponse, OutputSchema,
+    ProviderKind, ProviderMetadata, TokenUsage,
 };
+use crate::agent::structured;
+use crate::error::Error;
 use anyhow::{anyhow, Result};
 use async_stream::stream;
 use async_trait::async_trait;
@@ -32,6 +34,9 @@ struct OllamaChatRequest {
     stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<OllamaOptions>,
+    /// JSON schema the response is constrained to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
 }
 
 /// Options for Ollama API requests
@@ -62,6 +67,19 @@ struct OllamaChatResponse {
     eval_count: Option<u32>,
 }
 
+/// Ollama tags API response
+#[derive(Debug, Clone, Deserialize)]
+struct OllamaTagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModelTag>,
+}
+
+/// A locally available model in the tags response
+#[derive(Debug, Clone, Deserialize)]
+struct OllamaModelTag {
+    name: String,
+}
+
 /// Message content in Ollama response
 #[derive(Debug, Clone, Default, Deserialize)]
 struct MessageResponse {
@@ -164,32 +182,18 @@ impl OllamaProvider {
             messages,
             stream: if stream { Some(true) } else { Some(false) },
             options,
+            format: None,
         }
     }
 
-    /// Get the chat endpoint URL
-    fn chat_url(&self) -> String {
-        format!("{}/api/chat", self.base_url)
-    }
-}
-
-impl Default for OllamaProvider {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-#[async_trait]
-impl ModelProvider for OllamaProvider {
-    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
-        let request = self.build_request(prompt, config, false);
-
+    /// Send a non-streaming chat request
+    async fn chat(&self, request: &OllamaChatRequest) -> Result<ModelResponse> {
         // Make the API call
         let response = self
             .client
             .post(self.chat_url())
             .header("content-type", "application/json")
-            .json(&request)
+            .json(request)
             .send()
             .await
             .map_err(|e| anyhow!("Ollama API request failed: {}", e))?;
@@ -198,7 +202,12 @@ impl ModelProvider for OllamaProvider {
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Ollama API error ({}): {}", status, error_text));
+            return Err(Error::Provider {
+                provider: "Ollama".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
         }
 
         // Parse the response
@@ -216,13 +225,10 @@ impl ModelProvider for OllamaProvider {
         // Calculate token usage from eval counts
         let usage = if api_response.prompt_eval_count.is_some() || api_response.eval_count.is_some()
         {
-            let prompt_tokens = api_response.prompt_eval_count.unwrap_or(0);
-            let completion_tokens = api_response.eval_count.unwrap_or(0);
-            Some(TokenUsage {
-                prompt_tokens,
-                completion_tokens,
-                total_tokens: prompt_tokens + completion_tokens,
-            })
+            Some(TokenUsage::new(
+                api_response.prompt_eval_count.unwrap_or(0),
+                api_response.eval_count.unwrap_or(0),
+            ))
         } else {
             None
         };
@@ -241,6 +247,38 @@ impl ModelProvider for OllamaProvider {
         })
     }
 
+    /// Get the chat endpoint URL
+    fn chat_url(&self) -> String {
+        format!("{}/api/chat", self.base_url)
+    }
+}
+
+impl Default for OllamaProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ModelProvider for OllamaProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        self.chat(&self.build_request(prompt, config, false)).await
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        // The schema constrains sampling; repeating it in the prompt keeps the
+        // values grounded
+        let prompt = structured::schema_prompt(prompt, schema);
+        let mut request = self.build_request(&prompt, config, false);
+        request.format = Some(schema.schema.clone());
+        self.chat(&request).await
+    }
+
     async fn stream(
         &self,
         prompt: &str,
@@ -262,11 +300,12 @@ impl ModelProvider for OllamaProvider {
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "Ollama streaming API error ({}): {}",
-                status,
-                error_text
-            ));
+            return Err(Error::Provider {
+                provider: "Ollama".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
         }
 
         // Convert the response into a stream
@@ -351,6 +390,32 @@ impl ModelProvider for OllamaProvider {
         Ok(Box::pin(stream))
     }
 
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .client
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to list Ollama models: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Error::Provider {
+                provider: "Ollama".to_string(),
+                status: status.as_u16(),
+                message: error_text,
+            }
+            .into());
+        }
+
+        let tags: OllamaTagsResponse = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Ollama model list: {}", e))?;
+        Ok(tags.models.into_iter().map(|tag| tag.name).collect())
+    }
+
     fn metadata(&self) -> ProviderMetadata {
         ProviderMetadata {
             name: "Ollama".to_string(),
@@ -373,6 +438,10 @@ impl ModelProvider for OllamaProvider {
     fn kind(&self) -> ProviderKind {
         ProviderKind::Ollama
     }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
 }
 
 #[cfg(test)]
@@ -443,6 +512,7 @@ mod tests {
         assert_eq!(request.messages[1].role, "user");
         assert_eq!(request.messages[1].content, "Hello");
         assert_eq!(request.stream, Some(false));
+        assert!(request.format.is_none());
         assert!(request.options.is_some());
         let options = request.options.unwrap();
         assert_eq!(options.temperature, Some(0.8));
@@ -467,4 +537,14 @@ mod tests {
         let custom_provider = OllamaProvider::with_base_url("http://custom:8080");
         assert_eq!(custom_provider.chat_url(), "http://custom:8080/api/chat");
     }
+
+    #[test]
+    fn test_parse_tags_response() {
+        let tags: OllamaTagsResponse = serde_json::from_str(
+            r#"{"models": [{"name": "llama3:latest", "size": 4661224676}, {"name": "qwen2.5:7b"}]}"#,
+        )
+        .unwrap();
+        let names: Vec<String> = tags.models.into_iter().map(|tag| tag.name).collect();
+        assert_eq!(names, vec!["llama3:latest", "qwen2.5:7b"]);
+    }
 }
@@ -3,16 +3,19 @@
 //! Integration with OpenAI's API using the async-openai crate.
 //! Supports native function calling via the tools parameter.
 
+use crate::agent::function_calling::schema_to_response_format;
 use crate::agent::model::{
-    parse_thinking_tokens, GenerationConfig, ModelProvider, ModelResponse, ProviderKind,
-    ProviderMetadata, TokenUsage, ToolCall,
+    parse_thinking_tokens, text_only, DeltaStream, GenerationConfig, ModelProvider, ModelResponse,
+    OutputSchema, ProviderKind, ProviderMetadata, ReasoningEffort, StreamDelta, TokenUsage,
+    ToolCall,
 };
 use anyhow::{anyhow, Result};
 use async_openai::{
     config::OpenAIConfig,
     types::{
         ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
-        ChatCompletionRequestUserMessageArgs, ChatCompletionTool, CreateChatCompletionRequestArgs,
+        ChatCompletionRequestUserMessageArgs, ChatCompletionStreamOptions, ChatCompletionTool,
+        CreateChatCompletionRequestArgs, ReasoningEffort as OpenAIReasoningEffort, ResponseFormat,
     },
     Client,
 };
@@ -21,6 +24,14 @@ use async_trait::async_trait;
 use futures::Stream;
 use std::pin::Pin;
 
+fn openai_reasoning_effort(effort: ReasoningEffort) -> OpenAIReasoningEffort {
+    match effort {
+        ReasoningEffort::Low => OpenAIReasoningEffort::Low,
+        ReasoningEffort::Medium => OpenAIReasoningEffort::Medium,
+        ReasoningEffort::High => OpenAIReasoningEffort::High,
+    }
+}
+
 /// OpenAI provider that wraps the async-openai crate
 /// Supports both regular text generation and native function calling via tools.
 #[derive(Debug, Clone)]
@@ -110,17 +121,14 @@ impl OpenAIProvider {
 
         Ok(messages)
     }
-}
-
-impl Default for OpenAIProvider {
-    fn default() -> Self {
-        Self::new()
-    }
-}
 
-#[async_trait]
-impl ModelProvider for OpenAIProvider {
-    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+    /// Run a chat completion, constrained to `response_format` if given
+    async fn complete(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+        response_format: Option<ResponseFormat>,
+    ) -> Result<ModelResponse> {
         let messages = self.build_messages(prompt)?;
 
         // Build the request with configuration
@@ -130,7 +138,13 @@ impl ModelProvider for OpenAIProvider {
         if let Some(temp) = config.temperature {
             request_builder.temperature(temp);
         }
-        if let Some(max_tokens) = config.max_tokens {
+        if let Some(effort) = config.reasoning_effort {
+            // Reasoning models reject max_tokens
+            request_builder.reasoning_effort(openai_reasoning_effort(effort));
+            if let Some(max_tokens) = config.max_tokens {
+                request_builder.max_completion_tokens(max_tokens);
+            }
+        } else if let Some(max_tokens) = config.max_tokens {
             request_builder.max_tokens(max_tokens);
         }
         if let Some(top_p) = config.top_p {
@@ -146,8 +160,11 @@ impl ModelProvider for OpenAIProvider {
             request_builder.stop(stop.clone());
         }
 
-        // Add tools to the request if available (native function calling)
-        if let Some(ref tools) = self.tools {
+        // A structured response is the whole answer, so tools are left out
+        if let Some(format) = response_format {
+            request_builder.response_format(format);
+        } else if let Some(ref tools) = self.tools {
+            // Add tools to the request if available (native function calling)
             request_builder.tools(tools.clone());
         }
 
@@ -194,11 +211,9 @@ impl ModelProvider for OpenAIProvider {
             })
             .filter(|calls| !calls.is_empty());
 
-        let usage = response.usage.map(|u| TokenUsage {
-            prompt_tokens: u.prompt_tokens,
-            completion_tokens: u.completion_tokens,
-            total_tokens: u.total_tokens,
-        });
+        let usage = response
+            .usage
+            .map(|u| TokenUsage::new(u.prompt_tokens, u.completion_tokens));
 
         Ok(ModelResponse {
             content,
@@ -210,11 +225,16 @@ impl ModelProvider for OpenAIProvider {
         })
     }
 
-    async fn stream(
+    /// Start a streaming chat completion
+    ///
+    /// With `deltas`, tools are offered and usage is requested, since only a
+    /// delta stream can carry them.
+    async fn open_stream(
         &self,
         prompt: &str,
         config: &GenerationConfig,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        deltas: bool,
+    ) -> Result<DeltaStream> {
         let messages = self.build_messages(prompt)?;
 
         // Build the streaming request
@@ -227,7 +247,13 @@ impl ModelProvider for OpenAIProvider {
         if let Some(temp) = config.temperature {
             request_builder.temperature(temp);
         }
-        if let Some(max_tokens) = config.max_tokens {
+        if let Some(effort) = config.reasoning_effort {
+            // Reasoning models reject max_tokens
+            request_builder.reasoning_effort(openai_reasoning_effort(effort));
+            if let Some(max_tokens) = config.max_tokens {
+                request_builder.max_completion_tokens(max_tokens);
+            }
+        } else if let Some(max_tokens) = config.max_tokens {
             request_builder.max_tokens(max_tokens);
         }
         if let Some(top_p) = config.top_p {
@@ -243,6 +269,16 @@ impl ModelProvider for OpenAIProvider {
             request_builder.stop(stop.clone());
         }
 
+        // Only a delta stream can carry tool calls and usage
+        if deltas {
+            if let Some(ref tools) = self.tools {
+                request_builder.tools(tools.clone());
+            }
+            request_builder.stream_options(ChatCompletionStreamOptions {
+                include_usage: true,
+            });
+        }
+
         let request = request_builder
             .build()
             .map_err(|e| anyhow!("Failed to build streaming request: {}", e))?;
@@ -267,7 +303,33 @@ impl ModelProvider for OpenAIProvider {
             while let Some(result) = response_stream.next().await {
                 match result {
                     Ok(response) => {
+                        // The usage chunk comes last, with no choices
+                        if let Some(usage) = &response.usage {
+                            yield Ok(StreamDelta::Usage(TokenUsage::new(
+                                usage.prompt_tokens,
+                                usage.completion_tokens,
+                            )));
+                        }
                         if let Some(choice) = response.choices.first() {
+                            // The first chunk of a tool call names it; later ones add arguments
+                            for call in choice.delta.tool_calls.iter().flatten() {
+                                let index = call.index as usize;
+                                let function = call.function.as_ref();
+                                if let Some(id) = &call.id {
+                                    let name = function
+                                        .and_then(|f| f.name.clone())
+                                        .unwrap_or_default();
+                                    yield Ok(StreamDelta::ToolCallStart {
+                                        index,
+                                        id: id.clone(),
+                                        name,
+                                    });
+                                }
+                                let fragment = function.and_then(|f| f.arguments.clone());
+                                if let Some(fragment) = fragment.filter(|f| !f.is_empty()) {
+                                    yield Ok(StreamDelta::ToolCallArguments { index, fragment });
+                                }
+                            }
                             if let Some(content) = &choice.delta.content {
                                 buffer.push_str(content);
 
@@ -292,7 +354,7 @@ impl ModelProvider for OpenAIProvider {
                                     let output = buffer.clone();
                                     buffer.clear();
                                     if !output.is_empty() {
-                                        yield Ok(output);
+                                        yield Ok(StreamDelta::Text(output));
                                     }
                                 }
                             }
@@ -307,12 +369,59 @@ impl ModelProvider for OpenAIProvider {
 
             // Yield any remaining buffered content
             if !buffer.is_empty() && !in_think_block {
-                yield Ok(buffer);
+                yield Ok(StreamDelta::Text(buffer));
             }
         };
 
         Ok(Box::pin(stream))
     }
+}
+
+impl Default for OpenAIProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ModelProvider for OpenAIProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        self.complete(prompt, config, None).await
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        self.complete(prompt, config, Some(schema_to_response_format(schema)))
+            .await
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        Ok(text_only(self.open_stream(prompt, config, false).await?))
+    }
+
+    async fn stream_deltas(&self, prompt: &str, config: &GenerationConfig) -> Result<DeltaStream> {
+        self.open_stream(prompt, config, true).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let models = self
+            .client
+            .models()
+            .list()
+            .await
+            .map_err(|e| anyhow!("Failed to list OpenAI models: {}", e))?;
+        let mut ids: Vec<String> = models.data.into_iter().map(|model| model.id).collect();
+        ids.sort();
+        Ok(ids)
+    }
 
     fn metadata(&self) -> ProviderMetadata {
         ProviderMetadata {
@@ -331,6 +440,10 @@ impl ModelProvider for OpenAIProvider {
     fn kind(&self) -> ProviderKind {
         ProviderKind::OpenAI
     }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
 }
 
 #[cfg(test)]
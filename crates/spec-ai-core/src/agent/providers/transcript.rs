@@ -0,0 +1,185 @@
+//! Recording and Replaying Provider
+//!
+//! Wraps another provider so its calls go through a [`RunTranscript`]: while
+//! recording they reach the provider and are written down, while replaying
+//! they are answered from the transcript and the provider is never called.
+
+use crate::agent::model::{
+    GenerationConfig, ModelProvider, ModelResponse, OutputSchema, ProviderKind, ProviderMetadata,
+};
+use crate::agent::replay::{RunTranscript, TranscriptEvent, TranscriptMode};
+use anyhow::{anyhow, Result};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Provider that records its calls to, or replays them from, a transcript
+#[derive(Clone)]
+pub struct TranscriptProvider {
+    inner: Arc<dyn ModelProvider>,
+    transcript: Arc<RunTranscript>,
+}
+
+impl TranscriptProvider {
+    pub fn new(inner: Arc<dyn ModelProvider>, transcript: Arc<RunTranscript>) -> Self {
+        Self { inner, transcript }
+    }
+
+    fn replaying(&self) -> bool {
+        self.transcript.mode() == TranscriptMode::Replay
+    }
+
+    fn record(&self, prompt: &str, result: &Result<ModelResponse>) {
+        self.transcript.push(TranscriptEvent::Model {
+            prompt: prompt.to_string(),
+            response: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|err| format!("{:#}", err)),
+        });
+    }
+}
+
+/// A stream being recorded, written to the transcript when dropped so a
+/// stream cut short is recorded too
+struct StreamRecording {
+    transcript: Arc<RunTranscript>,
+    prompt: String,
+    chunks: Vec<String>,
+    error: Option<String>,
+}
+
+impl Drop for StreamRecording {
+    fn drop(&mut self) {
+        self.transcript.push(TranscriptEvent::Stream {
+            prompt: std::mem::take(&mut self.prompt),
+            chunks: std::mem::take(&mut self.chunks),
+            error: self.error.take(),
+        });
+    }
+}
+
+#[async_trait]
+impl ModelProvider for TranscriptProvider {
+    async fn generate(&self, prompt: &str, config: &GenerationConfig) -> Result<ModelResponse> {
+        if self.replaying() {
+            return self.transcript.replay_model(prompt);
+        }
+        let result = self.inner.generate(prompt, config).await;
+        self.record(prompt, &result);
+        result
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        schema: &OutputSchema,
+        config: &GenerationConfig,
+    ) -> Result<ModelResponse> {
+        if self.replaying() {
+            return self.transcript.replay_model(prompt);
+        }
+        let result = self.inner.generate_json(prompt, schema, config).await;
+        self.record(prompt, &result);
+        result
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+        config: &GenerationConfig,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        if self.replaying() {
+            let (chunks, error) = self.transcript.replay_stream(prompt)?;
+            let stream = stream! {
+                for chunk in chunks {
+                    yield Ok(chunk);
+                }
+                if let Some(error) = error {
+                    yield Err(anyhow!(error));
+                }
+            };
+            return Ok(Box::pin(stream));
+        }
+
+        let mut recording = StreamRecording {
+            transcript: self.transcript.clone(),
+            prompt: prompt.to_string(),
+            chunks: Vec::new(),
+            error: None,
+        };
+        let mut inner = match self.inner.stream(prompt, config).await {
+            Ok(inner) => inner,
+            Err(err) => {
+                recording.error = Some(format!("{:#}", err));
+                return Err(err);
+            }
+        };
+        let stream = stream! {
+            while let Some(item) = inner.next().await {
+                match &item {
+                    Ok(chunk) => recording.chunks.push(chunk.clone()),
+                    Err(err) => recording.error = Some(format!("{:#}", err)),
+                }
+                yield item;
+            }
+        };
+        Ok(Box::pin(stream))
+    }
+
+    fn metadata(&self) -> ProviderMetadata {
+        self.inner.metadata()
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        self.inner.list_models().await
+    }
+
+    fn kind(&self) -> ProviderKind {
+        self.inner.kind()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::providers::MockProvider;
+
+    #[tokio::test]
+    async fn recorded_calls_replay_without_the_provider() {
+        let recorder = Arc::new(RunTranscript::recorder());
+        let provider = TranscriptProvider::new(
+            Arc::new(MockProvider::new("recorded answer")),
+            recorder.clone(),
+        );
+        let config = GenerationConfig::default();
+        provider.generate("question", &config).await.unwrap();
+        let streamed: Vec<String> = provider
+            .stream("streamed question", &config)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        let replay = TranscriptProvider::new(
+            Arc::new(MockProvider::new("live answer")),
+            Arc::new(RunTranscript::from_events(recorder.events())),
+        );
+        let response = replay.generate("question", &config).await.unwrap();
+        assert_eq!(response.content, "recorded answer");
+        let replayed: Vec<String> = replay
+            .stream("streamed question", &config)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(replayed, streamed);
+        assert!(replay.generate("question", &config).await.is_err());
+    }
+}
@@ -0,0 +1,399 @@
+//! Questions the agent asks the user in the middle of a run
+//!
+//! A front end registers a [`UserQuestionSender`] with the agent. When the
+//! model calls the `ask_user` tool, the agent sends a [`UserQuestionRequest`]
+//! and the run waits for the front end to answer it, then hands the answer
+//! back to the model as the tool's result. Each question says which
+//! [`AnswerForm`] the answer takes, and answers are checked against it before
+//! the run resumes.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use tokio::sync::{mpsc, oneshot};
+
+/// Name of the tool the model asks questions with
+pub const ASK_USER_TOOL: &str = "ask_user";
+
+/// Most choices a question may offer
+pub const MAX_CHOICES: usize = 20;
+
+/// What kind of answer a question takes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnswerForm {
+    /// Free text
+    Text {
+        /// Example answer shown in the empty field
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        placeholder: Option<String>,
+        /// Whether the answer may span several lines
+        #[serde(default)]
+        multiline: bool,
+    },
+    /// One of `options`, or several when `multiple` is set
+    Choice {
+        options: Vec<String>,
+        #[serde(default)]
+        multiple: bool,
+        /// Whether a typed answer not among `options` is accepted
+        #[serde(default)]
+        allow_other: bool,
+    },
+    /// The path of an existing file
+    File {
+        /// Extensions the file may have, without the dot; any when empty
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        extensions: Vec<String>,
+    },
+}
+
+/// A question for the user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserQuestion {
+    pub question: String,
+    /// Why the agent is asking, shown under the question
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub form: AnswerForm,
+}
+
+/// How the user answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UserAnswer {
+    Text {
+        text: String,
+    },
+    Choice {
+        selected: Vec<String>,
+    },
+    File {
+        path: PathBuf,
+    },
+    /// The user chose not to answer
+    Declined,
+}
+
+impl UserQuestion {
+    /// Read a question from the arguments of an `ask_user` call
+    pub fn from_args(args: &Value) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Args {
+            question: String,
+            context: Option<String>,
+            form: Option<String>,
+            #[serde(default)]
+            choices: Vec<String>,
+            #[serde(default)]
+            multiple: bool,
+            #[serde(default)]
+            allow_other: bool,
+            placeholder: Option<String>,
+            #[serde(default)]
+            multiline: bool,
+            #[serde(default)]
+            extensions: Vec<String>,
+        }
+
+        let args: Args =
+            serde_json::from_value(args.clone()).context("Failed to parse ask_user arguments")?;
+        if args.question.trim().is_empty() {
+            bail!("question must not be empty");
+        }
+        let form = match args.form.as_deref() {
+            None if !args.choices.is_empty() => "choice",
+            None => "text",
+            Some(form) => form,
+        };
+        let form = match form {
+            "text" => AnswerForm::Text {
+                placeholder: args.placeholder,
+                multiline: args.multiline,
+            },
+            "choice" => {
+                if args.choices.is_empty() {
+                    bail!("a choice question needs at least one entry in choices");
+                }
+                if args.choices.len() > MAX_CHOICES {
+                    bail!("a question may offer at most {} choices", MAX_CHOICES);
+                }
+                AnswerForm::Choice {
+                    options: args.choices,
+                    multiple: args.multiple,
+                    allow_other: args.allow_other,
+                }
+            }
+            "file" => AnswerForm::File {
+                extensions: args
+                    .extensions
+                    .iter()
+                    .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+                    .collect(),
+            },
+            other => bail!("unknown form '{}', expected text, choice or file", other),
+        };
+        Ok(Self {
+            question: args.question,
+            context: args.context,
+            form,
+        })
+    }
+
+    /// Check that `answer` fits the question's form
+    pub fn check(&self, answer: &UserAnswer) -> Result<(), String> {
+        match (&self.form, answer) {
+            (_, UserAnswer::Declined) => Ok(()),
+            (AnswerForm::Text { multiline, .. }, UserAnswer::Text { text }) => {
+                if !multiline && text.contains('\n') {
+                    return Err("the answer must be a single line".to_string());
+                }
+                Ok(())
+            }
+            (
+                AnswerForm::Choice {
+                    options,
+                    multiple,
+                    allow_other,
+                },
+                UserAnswer::Choice { selected },
+            ) => {
+                if selected.is_empty() {
+                    return Err("select an option".to_string());
+                }
+                if !multiple && selected.len() > 1 {
+                    return Err("select only one option".to_string());
+                }
+                match selected.iter().find(|choice| !options.contains(choice)) {
+                    Some(other) if !allow_other => {
+                        Err(format!("'{}' is not one of the options", other))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            (AnswerForm::File { extensions }, UserAnswer::File { path }) => {
+                if !path.is_file() {
+                    return Err(format!("{} is not a file", path.display()));
+                }
+                let extension = path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(str::to_ascii_lowercase);
+                if !extensions.is_empty()
+                    && !extension.is_some_and(|extension| extensions.contains(&extension))
+                {
+                    return Err(format!("the file must end in .{}", extensions.join(", .")));
+                }
+                Ok(())
+            }
+            (form, _) => Err(format!("expected a {} answer", form.kind())),
+        }
+    }
+}
+
+impl AnswerForm {
+    /// The form's `kind` as serialized
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AnswerForm::Text { .. } => "text",
+            AnswerForm::Choice { .. } => "choice",
+            AnswerForm::File { .. } => "file",
+        }
+    }
+}
+
+impl UserAnswer {
+    /// The answer as the model reads it
+    pub fn to_tool_output(&self) -> String {
+        match self {
+            UserAnswer::Text { text } => format!("The user answered: {}", text),
+            UserAnswer::Choice { selected } => {
+                format!("The user chose: {}", selected.join(", "))
+            }
+            UserAnswer::File { path } => format!("The user picked the file {}", path.display()),
+            UserAnswer::Declined => {
+                "The user declined to answer; continue with your best judgment".to_string()
+            }
+        }
+    }
+}
+
+/// A question waiting for the user's answer
+#[derive(Debug)]
+pub struct UserQuestionRequest {
+    pub question: UserQuestion,
+    respond: oneshot::Sender<UserAnswer>,
+}
+
+impl UserQuestionRequest {
+    /// Create a request and the receiver its answer arrives on
+    pub fn new(question: UserQuestion) -> (Self, oneshot::Receiver<UserAnswer>) {
+        let (respond, answer) = oneshot::channel();
+        (Self { question, respond }, answer)
+    }
+
+    /// Answer the question
+    ///
+    /// An answer that does not fit the form is handed back with the reason,
+    /// so the front end can ask again. `Ok(false)` means the agent stopped
+    /// waiting.
+    pub fn respond(self, answer: UserAnswer) -> Result<bool, (Self, String)> {
+        if let Err(reason) = self.question.check(&answer) {
+            return Err((self, reason));
+        }
+        Ok(self.respond.send(answer).is_ok())
+    }
+
+    /// Whether the agent has stopped waiting for the answer
+    pub fn is_abandoned(&self) -> bool {
+        self.respond.is_closed()
+    }
+}
+
+/// Channel the agent sends questions on
+pub type UserQuestionSender = mpsc::UnboundedSender<UserQuestionRequest>;
+
+/// Receiving end of a [`UserQuestionSender`]
+pub type UserQuestionReceiver = mpsc::UnboundedReceiver<UserQuestionRequest>;
+
+/// Create a channel for questions
+pub fn user_question_channel() -> (UserQuestionSender, UserQuestionReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// Ask the front end a question and wait for the answer
+///
+/// A request dropped without an answer counts as declined.
+pub(crate) async fn ask_user(
+    sender: &UserQuestionSender,
+    question: UserQuestion,
+) -> Result<UserAnswer> {
+    let (request, answer) = UserQuestionRequest::new(question);
+    sender
+        .send(request)
+        .map_err(|_| anyhow!("question handler is no longer running"))?;
+    Ok(answer.await.unwrap_or(UserAnswer::Declined))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_forms_follow_the_arguments() {
+        let question = UserQuestion::from_args(&json!({
+            "question": "Which database?",
+            "choices": ["duckdb", "sqlite"],
+            "allow_other": true
+        }))
+        .unwrap();
+        assert_eq!(
+            question.form,
+            AnswerForm::Choice {
+                options: vec!["duckdb".into(), "sqlite".into()],
+                multiple: false,
+                allow_other: true,
+            }
+        );
+
+        let question = UserQuestion::from_args(&json!({
+            "question": "Which config?",
+            "form": "file",
+            "extensions": [".TOML"]
+        }))
+        .unwrap();
+        assert_eq!(
+            question.form,
+            AnswerForm::File {
+                extensions: vec!["toml".into()]
+            }
+        );
+
+        let question = UserQuestion::from_args(&json!({"question": "Why?"})).unwrap();
+        assert_eq!(question.form.kind(), "text");
+
+        assert!(UserQuestion::from_args(&json!({"question": "Pick", "form": "choice"})).is_err());
+        assert!(UserQuestion::from_args(&json!({"question": " "})).is_err());
+        assert!(UserQuestion::from_args(&json!({"question": "?", "form": "date"})).is_err());
+    }
+
+    #[test]
+    fn test_answers_must_fit_the_form() {
+        let choice = UserQuestion {
+            question: "Which?".into(),
+            context: None,
+            form: AnswerForm::Choice {
+                options: vec!["a".into(), "b".into()],
+                multiple: false,
+                allow_other: false,
+            },
+        };
+        let selected = |items: &[&str]| UserAnswer::Choice {
+            selected: items.iter().map(|item| item.to_string()).collect(),
+        };
+        assert_eq!(choice.check(&selected(&["a"])), Ok(()));
+        assert!(choice.check(&selected(&["a", "b"])).is_err());
+        assert!(choice.check(&selected(&["c"])).is_err());
+        assert!(choice.check(&selected(&[])).is_err());
+        assert!(choice
+            .check(&UserAnswer::Text { text: "a".into() })
+            .unwrap_err()
+            .contains("choice"));
+        assert_eq!(choice.check(&UserAnswer::Declined), Ok(()));
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("spec-ai.config.toml");
+        std::fs::write(&config, "").unwrap();
+        let file = UserQuestion {
+            question: "Which config?".into(),
+            context: None,
+            form: AnswerForm::File {
+                extensions: vec!["toml".into()],
+            },
+        };
+        assert_eq!(file.check(&UserAnswer::File { path: config }), Ok(()));
+        assert!(file
+            .check(&UserAnswer::File {
+                path: dir.path().join("missing.toml")
+            })
+            .is_err());
+        assert!(file
+            .check(&UserAnswer::File {
+                path: dir.path().to_path_buf()
+            })
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_questions_wait_for_a_fitting_answer() {
+        let (sender, mut receiver) = user_question_channel();
+        let answer = tokio::spawn(async move {
+            let request = receiver.recv().await.unwrap();
+            assert_eq!(request.question.question, "Which?");
+            let (request, reason) = request
+                .respond(UserAnswer::Text {
+                    text: "two\nlines".into(),
+                })
+                .unwrap_err();
+            assert!(reason.contains("single line"));
+            assert!(matches!(
+                request.respond(UserAnswer::Text { text: "b".into() }),
+                Ok(true)
+            ));
+
+            // Dropping a request unanswered declines it
+            drop(receiver.recv().await.unwrap());
+        });
+
+        let question = UserQuestion::from_args(&json!({"question": "Which?"})).unwrap();
+        let reply = ask_user(&sender, question.clone()).await.unwrap();
+        assert_eq!(reply, UserAnswer::Text { text: "b".into() });
+        assert_eq!(
+            ask_user(&sender, question).await.unwrap(),
+            UserAnswer::Declined
+        );
+        answer.await.unwrap();
+    }
+}
@@ -0,0 +1,370 @@
+//! Deterministic Replay
+//!
+//! A [`RunTranscript`] attached to an agent with
+//! [`AgentCore::set_transcript`](crate::agent::AgentCore::set_transcript)
+//! either records or replays a run. Recording appends every model request and
+//! response and every tool call and result to a JSON Lines file as it
+//! happens. Replaying loads such a file and serves the recorded responses and
+//! tool results instead of calling providers or running tools, so a run can
+//! be reproduced from a bug report, or agent logic tested offline.
+//!
+//! Replayed requests are matched to recorded ones by prompt (model calls) or
+//! by name and arguments (tool calls), falling back to the next unused record
+//! of the same kind, since concurrent tool calls may finish in any order.
+
+use crate::agent::model::ModelResponse;
+use crate::tools::ToolResult;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// One recorded model call or tool call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptEvent {
+    /// A `generate` or `generate_json` call
+    Model {
+        prompt: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        response: Option<ModelResponse>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// A `stream` call and the chunks it yielded before ending
+    Stream {
+        prompt: String,
+        chunks: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// A tool call, with the arguments the model gave
+    Tool {
+        name: String,
+        args: Value,
+        result: ToolResult,
+    },
+}
+
+impl TranscriptEvent {
+    /// Kind of call and what identifies it: the prompt, or the tool's name
+    fn key(&self) -> (&'static str, &str) {
+        match self {
+            TranscriptEvent::Model { prompt, .. } => ("model", prompt),
+            TranscriptEvent::Stream { prompt, .. } => ("stream", prompt),
+            TranscriptEvent::Tool { name, .. } => ("tool", name),
+        }
+    }
+}
+
+/// Whether a transcript is being written or read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptMode {
+    Record,
+    Replay,
+}
+
+struct TranscriptState {
+    events: Vec<TranscriptEvent>,
+    /// Which events a replay has served
+    used: Vec<bool>,
+    file: Option<File>,
+}
+
+impl TranscriptState {
+    /// Index of the first unused event `accept` accepts
+    fn position(&self, accept: impl Fn(&TranscriptEvent) -> bool) -> Option<usize> {
+        self.events
+            .iter()
+            .zip(&self.used)
+            .position(|(event, used)| !used && accept(event))
+    }
+}
+
+/// Model and tool calls of a run, being recorded or replayed
+pub struct RunTranscript {
+    mode: TranscriptMode,
+    path: Option<PathBuf>,
+    state: Mutex<TranscriptState>,
+}
+
+impl RunTranscript {
+    /// Record to `path`, replacing what it held
+    pub fn record(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create transcript {}", path.display()))?;
+        Ok(Self::new(
+            TranscriptMode::Record,
+            Some(path.to_path_buf()),
+            Vec::new(),
+            Some(file),
+        ))
+    }
+
+    /// Record in memory only
+    pub fn recorder() -> Self {
+        Self::new(TranscriptMode::Record, None, Vec::new(), None)
+    }
+
+    /// Replay the transcript recorded to `path`
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open transcript {}", path.display()))?;
+        let mut events = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line).with_context(|| {
+                format!("Invalid event on line {} of {}", index + 1, path.display())
+            })?;
+            events.push(event);
+        }
+        Ok(Self::new(
+            TranscriptMode::Replay,
+            Some(path.to_path_buf()),
+            events,
+            None,
+        ))
+    }
+
+    /// Replay `events`, as recorded by [`RunTranscript::recorder`]
+    pub fn from_events(events: Vec<TranscriptEvent>) -> Self {
+        Self::new(TranscriptMode::Replay, None, events, None)
+    }
+
+    fn new(
+        mode: TranscriptMode,
+        path: Option<PathBuf>,
+        events: Vec<TranscriptEvent>,
+        file: Option<File>,
+    ) -> Self {
+        let used = vec![false; events.len()];
+        Self {
+            mode,
+            path,
+            state: Mutex::new(TranscriptState { events, used, file }),
+        }
+    }
+
+    pub fn mode(&self) -> TranscriptMode {
+        self.mode
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Events recorded or loaded so far, in order
+    pub fn events(&self) -> Vec<TranscriptEvent> {
+        self.lock().events.clone()
+    }
+
+    /// Recorded events a replay has not served yet
+    pub fn unused(&self) -> usize {
+        self.lock().used.iter().filter(|used| !**used).count()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TranscriptState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Append `event` when recording
+    ///
+    /// Each event is written out at once, so a run that crashes still leaves
+    /// a transcript. Write errors are logged rather than failing the run.
+    pub(crate) fn push(&self, event: TranscriptEvent) {
+        if self.mode != TranscriptMode::Record {
+            return;
+        }
+        let mut state = self.lock();
+        if let Some(file) = &mut state.file {
+            let written = serde_json::to_string(&event)
+                .map_err(anyhow::Error::from)
+                .and_then(|line| Ok(writeln!(file, "{}", line)?));
+            if let Err(err) = written {
+                warn!("Failed to write transcript event: {}", err);
+            }
+        }
+        state.used.push(false);
+        state.events.push(event);
+    }
+
+    /// Take the first unused event `exact` accepts, or else the first unused
+    /// one `similar` accepts
+    fn take(
+        &self,
+        exact: impl Fn(&TranscriptEvent) -> bool,
+        similar: impl Fn(&TranscriptEvent) -> bool,
+    ) -> Option<TranscriptEvent> {
+        let mut state = self.lock();
+        let index = match state.position(exact) {
+            Some(index) => index,
+            None => {
+                let index = state.position(similar)?;
+                warn!(
+                    "Replay diverged: no recorded {} call matches, using the next one",
+                    state.events[index].key().0
+                );
+                index
+            }
+        };
+        state.used[index] = true;
+        Some(state.events[index].clone())
+    }
+
+    /// Recorded response to a model call with `prompt`
+    pub(crate) fn replay_model(&self, prompt: &str) -> Result<ModelResponse> {
+        let event = self.take(
+            |event| event.key() == ("model", prompt),
+            |event| event.key().0 == "model",
+        );
+        match event {
+            Some(TranscriptEvent::Model {
+                response: Some(response),
+                ..
+            }) => Ok(response),
+            Some(TranscriptEvent::Model { error, .. }) => {
+                Err(anyhow!(error.unwrap_or_else(|| {
+                    "Recorded model call has no response".to_string()
+                })))
+            }
+            _ => Err(self.exhausted("model")),
+        }
+    }
+
+    /// Recorded chunks of a stream for `prompt`, and the error it ended with
+    pub(crate) fn replay_stream(&self, prompt: &str) -> Result<(Vec<String>, Option<String>)> {
+        let event = self.take(
+            |event| event.key() == ("stream", prompt),
+            |event| event.key().0 == "stream",
+        );
+        match event {
+            Some(TranscriptEvent::Stream { chunks, error, .. }) => Ok((chunks, error)),
+            _ => Err(self.exhausted("stream")),
+        }
+    }
+
+    /// Recorded result of calling tool `name` with `args`, when replaying
+    ///
+    /// A call missing from the transcript fails rather than running the tool.
+    pub(crate) fn replay_tool(&self, name: &str, args: &Value) -> Option<ToolResult> {
+        if self.mode != TranscriptMode::Replay {
+            return None;
+        }
+        let same_tool = |event: &TranscriptEvent| event.key() == ("tool", name);
+        let event = self.take(
+            |event| match event {
+                TranscriptEvent::Tool { args: recorded, .. } => {
+                    same_tool(event) && recorded == args
+                }
+                _ => false,
+            },
+            same_tool,
+        );
+        Some(match event {
+            Some(TranscriptEvent::Tool { result, .. }) => result,
+            _ => ToolResult::failure(format!(
+                "Tool '{}' was not called in the replayed transcript",
+                name
+            )),
+        })
+    }
+
+    fn exhausted(&self, kind: &str) -> anyhow::Error {
+        match &self.path {
+            Some(path) => anyhow!(
+                "Replay transcript {} has no more {} calls",
+                path.display(),
+                kind
+            ),
+            None => anyhow!("Replay transcript has no more {} calls", kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(content: &str) -> ModelResponse {
+        ModelResponse {
+            content: content.to_string(),
+            model: "mock".to_string(),
+            usage: None,
+            finish_reason: Some("stop".to_string()),
+            tool_calls: None,
+            reasoning: None,
+        }
+    }
+
+    #[test]
+    fn transcripts_round_trip_through_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.jsonl");
+        let recorder = RunTranscript::record(&path).unwrap();
+        recorder.push(TranscriptEvent::Model {
+            prompt: "hi".to_string(),
+            response: Some(response("hello")),
+            error: None,
+        });
+        recorder.push(TranscriptEvent::Tool {
+            name: "echo".to_string(),
+            args: json!({"text": "a"}),
+            result: ToolResult::success("a"),
+        });
+        drop(recorder);
+
+        let replay = RunTranscript::replay(&path).unwrap();
+        assert_eq!(replay.mode(), TranscriptMode::Replay);
+        assert_eq!(replay.events().len(), 2);
+        assert_eq!(replay.replay_model("hi").unwrap().content, "hello");
+        assert!(replay.replay_model("hi").is_err());
+        assert_eq!(replay.unused(), 1);
+    }
+
+    #[test]
+    fn replay_matches_calls_before_falling_back_to_order() {
+        let tool = |text: &str| TranscriptEvent::Tool {
+            name: "echo".to_string(),
+            args: json!({"text": text}),
+            result: ToolResult::success(text),
+        };
+        let replay = RunTranscript::from_events(vec![
+            TranscriptEvent::Model {
+                prompt: "first".to_string(),
+                response: Some(response("1")),
+                error: None,
+            },
+            TranscriptEvent::Model {
+                prompt: "second".to_string(),
+                response: None,
+                error: Some("rate limited".to_string()),
+            },
+            tool("a"),
+            tool("b"),
+        ]);
+
+        let failed = replay.replay_model("second").unwrap_err();
+        assert_eq!(failed.to_string(), "rate limited");
+        assert_eq!(replay.replay_model("changed").unwrap().content, "1");
+
+        let b = replay.replay_tool("echo", &json!({"text": "b"})).unwrap();
+        assert_eq!(b.output, "b");
+        let missing = replay.replay_tool("shell", &json!({})).unwrap();
+        assert!(!missing.success);
+        assert!(RunTranscript::recorder()
+            .replay_tool("echo", &json!({}))
+            .is_none());
+    }
+}
@@ -0,0 +1,158 @@
+//! Response Cache
+//!
+//! Model responses kept in the database under a hash of everything that
+//! decides them: provider, model, prompt, the tools on offer and the
+//! generation settings. Agents opt in with `response_cache_ttl_secs`, which
+//! makes re-runs of deterministic spec and bootstrap pipelines instant.
+
+use crate::agent::model::{GenerationConfig, ModelProvider, ModelResponse};
+use crate::persistence::Persistence;
+use crate::tools::ToolRegistry;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Persistent cache of model responses with a fixed time to live
+#[derive(Clone)]
+pub struct ResponseCache {
+    persistence: Persistence,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    /// Open the cache, dropping responses that have already expired
+    pub fn new(persistence: Persistence, ttl: Duration) -> Self {
+        if let Err(err) = persistence.response_cache_prune(false) {
+            warn!("Failed to prune the response cache: {}", err);
+        }
+        Self { persistence, ttl }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Cache key of a generation request
+    pub fn key(
+        provider: &dyn ModelProvider,
+        prompt: &str,
+        tools: &ToolRegistry,
+        config: &GenerationConfig,
+    ) -> String {
+        let tools = tools.fingerprint();
+        let settings = serde_json::to_string(config).unwrap_or_default();
+        let mut hasher = blake3::Hasher::new();
+        for part in [
+            provider.kind().as_str(),
+            provider.model_name(),
+            prompt,
+            tools.as_str(),
+            settings.as_str(),
+        ] {
+            // Length prefixes keep ("ab", "c") and ("a", "bc") apart
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// Response stored under `key`, if there is one that has not expired
+    ///
+    /// Cached responses carry no token usage, since serving them costs
+    /// nothing. Read errors are logged and treated as a miss.
+    pub fn get(&self, key: &str) -> Option<ModelResponse> {
+        let stored = match self.persistence.response_cache_get(key) {
+            Ok(stored) => stored?,
+            Err(err) => {
+                warn!("Failed to read the response cache: {}", err);
+                return None;
+            }
+        };
+        match serde_json::from_str::<ModelResponse>(&stored) {
+            Ok(mut response) => {
+                debug!("Serving response for {} from cache", response.model);
+                response.usage = None;
+                Some(response)
+            }
+            Err(err) => {
+                warn!("Ignoring unreadable cached response: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Store `response` under `key`; write errors are logged
+    pub fn put(&self, key: &str, response: &ModelResponse) {
+        let result = serde_json::to_string(response)
+            .map_err(anyhow::Error::from)
+            .and_then(|stored| {
+                self.persistence
+                    .response_cache_put(key, &response.model, &stored, self.ttl)
+            });
+        if let Err(err) = result {
+            warn!("Failed to cache model response: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::providers::MockProvider;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn responses_are_served_until_they_expire() {
+        let persistence = Persistence::in_memory().unwrap();
+        let cache = ResponseCache::new(persistence.clone(), Duration::from_secs(60));
+        let provider = MockProvider::new("cached answer");
+        let tools = ToolRegistry::new();
+        let config = GenerationConfig::default();
+
+        let key = ResponseCache::key(&provider, "prompt", &tools, &config);
+        assert!(cache.get(&key).is_none());
+
+        let response = provider.generate("prompt", &config).await.unwrap();
+        assert!(response.usage.is_some());
+        cache.put(&key, &response);
+
+        let cached = cache.get(&key).expect("cached response");
+        assert_eq!(cached.content, "cached answer");
+        assert!(cached.usage.is_none());
+
+        let expired = ResponseCache::new(persistence, Duration::ZERO);
+        expired.put(&key, &response);
+        assert!(expired.get(&key).is_none());
+    }
+
+    #[test]
+    fn keys_change_with_every_input() {
+        let provider = MockProvider::new("answer");
+        let tools = ToolRegistry::new();
+        let config = GenerationConfig::default();
+        let key = ResponseCache::key(&provider, "prompt", &tools, &config);
+
+        assert_eq!(
+            key,
+            ResponseCache::key(&provider, "prompt", &tools, &config)
+        );
+        assert_ne!(
+            key,
+            ResponseCache::key(&provider, "other prompt", &tools, &config)
+        );
+        let warmer = GenerationConfig {
+            temperature: Some(1.5),
+            ..GenerationConfig::default()
+        };
+        assert_ne!(
+            key,
+            ResponseCache::key(&provider, "prompt", &tools, &warmer)
+        );
+
+        let mut with_tool = ToolRegistry::new();
+        with_tool.register(Arc::new(crate::tools::EchoTool::new()));
+        assert_ne!(
+            key,
+            ResponseCache::key(&provider, "prompt", &with_tool, &config)
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Structured Output
+//!
+//! [`generate_structured`] asks a provider for JSON matching the schema of a
+//! Rust type through [`ModelProvider::generate_json`], deserializes it, and
+//! retries with the parse error when the model gets it wrong. Providers
+//! constrain generation where their API allows it (OpenAI-compatible
+//! `response_format`, forced tool calls, Ollama's `format`); the rest only
+//! see the schema in the prompt.
+
+use crate::agent::model::{
+    GenerationConfig, ModelProvider, ModelResponse, OutputSchema, TokenUsage,
+};
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+/// Attempts made by [`generate_structured`] before giving up
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Typed value generated by a model
+#[derive(Debug, Clone)]
+pub struct Structured<T> {
+    pub value: T,
+    /// Model that produced the accepted response
+    pub model: String,
+    /// Usage summed over all attempts, if the provider reported any
+    pub usage: Option<TokenUsage>,
+    /// Number of requests made, including the successful one
+    pub attempts: u32,
+}
+
+/// Generate a value of type `T` from `prompt`
+///
+/// Responses that do not deserialize into `T` are retried up to
+/// `max_attempts` times in total, telling the model what was wrong. Provider
+/// errors are returned immediately.
+pub async fn generate_structured<T>(
+    provider: &dyn ModelProvider,
+    prompt: &str,
+    config: &GenerationConfig,
+    max_attempts: u32,
+) -> Result<Structured<T>>
+where
+    T: JsonSchema + DeserializeOwned,
+{
+    generate_with_schema(
+        provider,
+        prompt,
+        &OutputSchema::of::<T>(),
+        config,
+        max_attempts,
+    )
+    .await
+}
+
+/// [`generate_structured`] with a schema built by the caller, for example
+/// to add a description
+pub async fn generate_with_schema<T>(
+    provider: &dyn ModelProvider,
+    prompt: &str,
+    schema: &OutputSchema,
+    config: &GenerationConfig,
+    max_attempts: u32,
+) -> Result<Structured<T>>
+where
+    T: DeserializeOwned,
+{
+    let max_attempts = max_attempts.max(1);
+    let mut usage: Option<TokenUsage> = None;
+    let mut request = prompt.to_string();
+    let mut last_error = String::new();
+
+    for attempt in 1..=max_attempts {
+        let response = provider.generate_json(&request, schema, config).await?;
+        if let Some(response_usage) = &response.usage {
+            usage
+                .get_or_insert_with(TokenUsage::default)
+                .add(response_usage);
+        }
+
+        match parse_json::<T>(&response.content) {
+            Ok(value) => {
+                return Ok(Structured {
+                    value,
+                    model: response.model,
+                    usage,
+                    attempts: attempt,
+                })
+            }
+            Err(err) => {
+                debug!(
+                    "Structured output attempt {} for {} was invalid: {}",
+                    attempt, schema.name, err
+                );
+                request = retry_prompt(prompt, &response.content, &err);
+                last_error = err;
+            }
+        }
+    }
+
+    Err(anyhow!(
+        "Model did not return a valid {} after {} attempts: {}",
+        schema.name,
+        max_attempts,
+        last_error
+    ))
+}
+
+/// Deserialize the JSON in a model response
+///
+/// Code fences and text around a single JSON object or array are ignored.
+pub fn parse_json<T: DeserializeOwned>(text: &str) -> std::result::Result<T, String> {
+    serde_json::from_str(extract_json(text)).map_err(|e| e.to_string())
+}
+
+/// The part of a response that holds the JSON value
+fn extract_json(text: &str) -> &str {
+    let text = text.trim();
+    if let Some(fenced) = text.strip_prefix("```") {
+        // Drop the language tag on the opening fence
+        let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+        return body.trim_end().trim_end_matches("```").trim();
+    }
+    if text.starts_with(['{', '[']) {
+        return text;
+    }
+    let start = text.find(['{', '[']);
+    let end = text.rfind(['}', ']']);
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text,
+    }
+}
+
+/// Prompt asking for JSON conforming to `schema`, for providers that cannot
+/// constrain their output
+pub fn schema_prompt(prompt: &str, schema: &OutputSchema) -> String {
+    let mut out = format!("{}\n\n", prompt);
+    if let Some(description) = &schema.description {
+        out.push_str(&format!("The response is {}.\n", description));
+    }
+    out.push_str(
+        "Respond with only a JSON value, without code fences or commentary, \
+         that conforms to this JSON schema:\n",
+    );
+    out.push_str(&serde_json::to_string_pretty(&schema.schema).unwrap_or_default());
+    out
+}
+
+fn retry_prompt(prompt: &str, previous: &str, error: &str) -> String {
+    format!(
+        "{}\n\nYour previous response could not be used ({}):\n{}\n\nRespond again, fixing the problem.",
+        prompt, error, previous
+    )
+}
+
+/// Move the arguments of a forced tool call into the response content
+///
+/// For providers that implement [`ModelProvider::generate_json`] by forcing
+/// a tool named after the schema. Responses without the call are left
+/// alone, so their text fails to parse and is retried.
+pub fn forced_tool_content(mut response: ModelResponse, schema: &OutputSchema) -> ModelResponse {
+    let call = response
+        .tool_calls
+        .take()
+        .into_iter()
+        .flatten()
+        .find(|call| call.function_name == schema.name);
+    if let Some(call) = call {
+        response.content = call.arguments.to_string();
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::providers::MockProvider;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    struct Route {
+        /// Name of the agent to hand the request to
+        agent: String,
+        confidence: f32,
+        tags: Vec<Tag>,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Tag {
+        Code,
+        Research,
+    }
+
+    #[test]
+    fn schema_is_inlined_and_named() {
+        let schema = OutputSchema::of::<Route>();
+        assert_eq!(schema.name, "Route");
+        assert!(schema.is_object());
+        assert!(schema.schema.get("$schema").is_none());
+        assert!(!schema.schema.to_string().contains("$ref"));
+        assert_eq!(
+            schema.schema["properties"]["tags"]["items"]["enum"],
+            serde_json::json!(["code", "research"])
+        );
+        assert!(!OutputSchema::of::<Vec<Tag>>().is_object());
+        assert_eq!(OutputSchema::of::<Vec<Tag>>().name, "Array_of_Tag");
+    }
+
+    #[test]
+    fn json_is_found_inside_fences_and_prose() {
+        let fenced = "```json\n{\"a\": 1}\n```";
+        assert_eq!(extract_json(fenced), "{\"a\": 1}");
+        let prose = "Sure! Here it is: [1, 2] Hope that helps.";
+        assert_eq!(extract_json(prose), "[1, 2]");
+        assert_eq!(extract_json("  {\"a\": 1}  "), "{\"a\": 1}");
+        assert!(parse_json::<Vec<u8>>("no json here").is_err());
+    }
+
+    #[tokio::test]
+    async fn invalid_responses_are_retried() {
+        let provider = MockProvider::with_responses(vec![
+            "I think the coder should take it".to_string(),
+            r#"{"agent": "coder", "confidence": 0.8, "tags": ["code"]}"#.to_string(),
+        ]);
+
+        let route: Structured<Route> =
+            generate_structured(&provider, "Route this", &GenerationConfig::default(), 3)
+                .await
+                .unwrap();
+
+        assert_eq!(route.attempts, 2);
+        assert_eq!(route.value.agent, "coder");
+        assert_eq!(route.value.tags, vec![Tag::Code]);
+        assert!(route.usage.unwrap().completion_tokens > 0);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let provider = MockProvider::new(r#"{"agent": "coder"}"#);
+
+        let err =
+            generate_structured::<Route>(&provider, "Route this", &GenerationConfig::default(), 2)
+                .await
+                .unwrap_err();
+
+        assert!(err.to_string().contains("after 2 attempts"));
+        assert!(err.to_string().contains("missing field"));
+    }
+
+    #[test]
+    fn forced_tool_call_becomes_content() {
+        let schema = OutputSchema::of::<Route>();
+        let response = ModelResponse {
+            content: String::new(),
+            model: "m".to_string(),
+            usage: None,
+            finish_reason: None,
+            tool_calls: Some(vec![crate::agent::model::ToolCall {
+                id: "1".to_string(),
+                function_name: "Route".to_string(),
+                arguments: serde_json::json!({"agent": "coder"}),
+            }]),
+            reasoning: None,
+        };
+
+        let response = forced_tool_content(response, &schema);
+        assert_eq!(response.content, r#"{"agent":"coder"}"#);
+        assert!(response.tool_calls.is_none());
+    }
+}
@@ -0,0 +1,218 @@
+//! Sub-agents for local orchestration
+//!
+//! An agent can split work into sub-tasks and hand each to a child agent,
+//! either through [`AgentCore::spawn_subagents`](crate::agent::AgentCore::spawn_subagents)
+//! or by calling the `spawn_agents` tool. Children share the parent's model,
+//! tools and persistence but start with an empty session of their own, and
+//! run under a policy capsule that never exceeds what the parent may do,
+//! narrowed further by the tools and budget of their [`SubAgentSpec`].
+//! Children run concurrently; when they finish, each report is stored in the
+//! parent's session so it is recalled like any other message.
+
+use crate::agent::AgentCore;
+use crate::policy::CapsuleBudget;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Name of the tool through which a model spawns sub-agents
+pub const SPAWN_AGENTS_TOOL: &str = "spawn_agents";
+
+/// Most children a single call may spawn
+pub const MAX_SUBAGENTS: usize = 8;
+
+/// A sub-task and the limits of the child agent that runs it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubAgentSpec {
+    /// Short label for the child, used in its session ID and report
+    pub name: String,
+    /// Input of the child's single step
+    pub task: String,
+    /// Tools the child may call (supports wildcards); the parent's when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<String>>,
+    #[serde(default, flatten)]
+    pub budget: CapsuleBudget,
+    /// Seconds before the child is interrupted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl SubAgentSpec {
+    pub fn new(name: impl Into<String>, task: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            task: task.into(),
+            tools: None,
+            budget: CapsuleBudget::default(),
+            timeout_secs: None,
+        }
+    }
+
+    /// Limit the child to `tools`
+    pub fn with_tools(mut self, tools: Vec<String>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn with_budget(mut self, budget: CapsuleBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Interrupt the child after `secs` seconds
+    pub fn with_timeout_secs(mut self, secs: u64) -> Self {
+        self.timeout_secs = Some(secs);
+        self
+    }
+}
+
+/// What a child agent produced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubAgentReport {
+    pub name: String,
+    /// Session holding the child's conversation and tool log
+    pub session_id: String,
+    /// Final response, empty if the child failed
+    pub response: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    pub tool_calls: usize,
+    /// Model tokens the child used
+    pub tokens: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Message in the parent's session holding this report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<i64>,
+}
+
+impl SubAgentReport {
+    /// Text stored in the parent's session
+    pub(crate) fn to_message(&self) -> String {
+        match &self.error {
+            Some(error) => format!("Sub-agent '{}' failed: {}", self.name, error),
+            None => format!("Sub-agent '{}' reported:\n{}", self.name, self.response),
+        }
+    }
+}
+
+/// Arguments of the `spawn_agents` tool
+#[derive(Debug, Deserialize)]
+pub(crate) struct SpawnAgentsArgs {
+    pub agents: Vec<SubAgentSpec>,
+}
+
+/// Session ID of a child, unique to this spawn
+pub(crate) fn child_session_id(parent: &str, name: &str, spawned_at: i64) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("{}/sub-{}-{}", parent, name, spawned_at)
+}
+
+/// Run a child's step until it finishes, times out or `cancel` fires
+///
+/// Timed out and cancelled children are interrupted, so they still report
+/// what they had so far.
+pub(crate) async fn run_subagent(
+    spec: SubAgentSpec,
+    mut child: AgentCore,
+    cancel: CancellationToken,
+) -> SubAgentReport {
+    let interrupt = child.interrupt_handle();
+    let session_id = child.session_id().to_string();
+    let timeout_secs = spec.timeout_secs;
+    let deadline = async move {
+        match timeout_secs {
+            Some(secs) => tokio::time::sleep(Duration::from_secs(secs)).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let mut timed_out = false;
+    let run = child.run_step(&spec.task);
+    tokio::pin!(run);
+    let result = tokio::select! {
+        result = &mut run => result,
+        _ = deadline => {
+            timed_out = true;
+            interrupt.interrupt();
+            run.await
+        }
+        _ = cancel.cancelled() => {
+            interrupt.interrupt();
+            run.await
+        }
+    };
+
+    match result {
+        Ok(output) => SubAgentReport {
+            name: spec.name,
+            session_id,
+            response: output.response,
+            finish_reason: if timed_out {
+                Some("timeout".to_string())
+            } else {
+                output.finish_reason
+            },
+            tool_calls: output.tool_invocations.len(),
+            tokens: output.session_usage.total_tokens(),
+            error: None,
+            message_id: None,
+        },
+        Err(err) => SubAgentReport {
+            name: spec.name,
+            session_id,
+            response: String::new(),
+            finish_reason: None,
+            tool_calls: 0,
+            tokens: 0,
+            error: Some(format!("{:#}", err)),
+            message_id: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_parse_from_tool_arguments() {
+        let args: SpawnAgentsArgs = serde_json::from_value(serde_json::json!({
+            "agents": [
+                {"name": "docs", "task": "Summarize README.md", "tools": ["file_read"],
+                 "max_tokens": 2000, "timeout_secs": 30},
+                {"name": "tests", "task": "List the test files"}
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            args.agents[0],
+            SubAgentSpec::new("docs", "Summarize README.md")
+                .with_tools(vec!["file_read".to_string()])
+                .with_budget(CapsuleBudget {
+                    max_tool_calls: None,
+                    max_tokens: Some(2000),
+                })
+                .with_timeout_secs(30)
+        );
+        assert_eq!(
+            args.agents[1],
+            SubAgentSpec::new("tests", "List the test files")
+        );
+        assert_eq!(
+            child_session_id("main", "read me!", 42),
+            "main/sub-read-me--42"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! Bounded fan-out channels with per-subscriber backpressure
+//!
+//! Every subscriber gets its own bounded queue. When a subscriber falls
+//! behind, the channel's [`DropPolicy`] decides what happens, and the
+//! dropped messages are counted so slow consumers can be spotted.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use thiserror::Error;
+use tokio::sync::Notify;
+
+/// What to do when a subscriber's queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Discard the subscriber's oldest queued message
+    #[default]
+    DropOldest,
+    /// Discard the new message for that subscriber only
+    DropNewest,
+    /// Disconnect the subscriber; it sees [`RecvError::Lagged`] once drained
+    Disconnect,
+}
+
+/// Error returned when receiving from a [`Subscriber`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum RecvError {
+    #[error("all senders were dropped")]
+    Closed,
+    #[error("subscriber was disconnected after falling behind")]
+    Lagged,
+}
+
+/// Error returned by [`Subscriber::try_recv`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum TryRecvError {
+    #[error("no message queued")]
+    Empty,
+    #[error("all senders were dropped")]
+    Closed,
+    #[error("subscriber was disconnected after falling behind")]
+    Lagged,
+}
+
+/// Delivery counters for one subscriber
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberStats {
+    pub id: u64,
+    /// Messages waiting to be received
+    pub queued: usize,
+    /// Messages received so far
+    pub delivered: u64,
+    /// Messages lost because the queue was full
+    pub dropped: u64,
+    pub disconnected: bool,
+}
+
+struct SubscriberState<T> {
+    queue: VecDeque<T>,
+    delivered: u64,
+    dropped: u64,
+    disconnected: bool,
+    notify: Arc<Notify>,
+}
+
+struct State<T> {
+    subscribers: BTreeMap<u64, SubscriberState<T>>,
+    next_id: u64,
+    senders: usize,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    capacity: usize,
+    policy: DropPolicy,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // A panicking subscriber never leaves the queues half-updated
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Sending half of a bounded broadcast channel
+///
+/// Cloning creates another sender; subscribers see [`RecvError::Closed`]
+/// once every sender is dropped and their queue is drained.
+pub struct Broadcaster<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Create a broadcast channel holding up to `capacity` messages per subscriber
+pub fn channel<T: Clone>(capacity: usize, policy: DropPolicy) -> Broadcaster<T> {
+    Broadcaster::new(capacity, policy)
+}
+
+impl<T: Clone> Broadcaster<T> {
+    /// Create a channel holding up to `capacity` messages per subscriber
+    ///
+    /// A capacity of zero is treated as one.
+    pub fn new(capacity: usize, policy: DropPolicy) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    subscribers: BTreeMap::new(),
+                    next_id: 0,
+                    senders: 1,
+                }),
+                capacity: capacity.max(1),
+                policy,
+            }),
+        }
+    }
+
+    /// Send a message to every connected subscriber
+    ///
+    /// Never blocks. Returns how many subscribers queued the message.
+    pub fn send(&self, value: T) -> usize {
+        let mut state = self.shared.lock();
+        let mut queued = 0;
+        for subscriber in state.subscribers.values_mut() {
+            if subscriber.disconnected {
+                continue;
+            }
+            if subscriber.queue.len() >= self.shared.capacity {
+                subscriber.dropped += 1;
+                match self.shared.policy {
+                    DropPolicy::DropOldest => {
+                        subscriber.queue.pop_front();
+                    }
+                    DropPolicy::DropNewest => continue,
+                    DropPolicy::Disconnect => {
+                        subscriber.disconnected = true;
+                        subscriber.notify.notify_one();
+                        continue;
+                    }
+                }
+            }
+            subscriber.queue.push_back(value.clone());
+            subscriber.notify.notify_one();
+            queued += 1;
+        }
+        queued
+    }
+
+    /// Add a subscriber that receives messages sent from now on
+    pub fn subscribe(&self) -> Subscriber<T> {
+        let mut state = self.shared.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        let notify = Arc::new(Notify::new());
+        state.subscribers.insert(
+            id,
+            SubscriberState {
+                queue: VecDeque::new(),
+                delivered: 0,
+                dropped: 0,
+                disconnected: false,
+                notify: Arc::clone(&notify),
+            },
+        );
+        Subscriber {
+            id,
+            shared: Arc::clone(&self.shared),
+            notify,
+        }
+    }
+}
+
+impl<T> Broadcaster<T> {
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    pub fn policy(&self) -> DropPolicy {
+        self.shared.policy
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.shared.lock().subscribers.len()
+    }
+
+    /// Delivery counters for every subscriber, oldest subscriber first
+    pub fn stats(&self) -> Vec<SubscriberStats> {
+        let state = self.shared.lock();
+        state
+            .subscribers
+            .iter()
+            .map(|(id, subscriber)| stats_for(*id, subscriber))
+            .collect()
+    }
+
+    /// Total messages dropped across all current subscribers
+    pub fn dropped(&self) -> u64 {
+        let state = self.shared.lock();
+        state.subscribers.values().map(|s| s.dropped).sum()
+    }
+}
+
+impl<T> Clone for Broadcaster<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Broadcaster<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            for subscriber in state.subscribers.values() {
+                subscriber.notify.notify_one();
+            }
+        }
+    }
+}
+
+/// Receiving half of a broadcast channel, with its own bounded queue
+pub struct Subscriber<T> {
+    id: u64,
+    shared: Arc<Shared<T>>,
+    notify: Arc<Notify>,
+}
+
+impl<T> Subscriber<T> {
+    /// Wait for the next message
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Empty) => self.notify.notified().await,
+                Err(TryRecvError::Closed) => return Err(RecvError::Closed),
+                Err(TryRecvError::Lagged) => return Err(RecvError::Lagged),
+            }
+        }
+    }
+
+    /// Take the next queued message without waiting
+    ///
+    /// Queued messages are still delivered after the channel closes or the
+    /// subscriber is disconnected.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+        let closed = state.senders == 0;
+        let Some(subscriber) = state.subscribers.get_mut(&self.id) else {
+            return Err(TryRecvError::Closed);
+        };
+        if let Some(value) = subscriber.queue.pop_front() {
+            subscriber.delivered += 1;
+            return Ok(value);
+        }
+        if subscriber.disconnected {
+            Err(TryRecvError::Lagged)
+        } else if closed {
+            Err(TryRecvError::Closed)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Take every queued message without waiting
+    pub fn drain(&mut self) -> Vec<T> {
+        let mut state = self.shared.lock();
+        match state.subscribers.get_mut(&self.id) {
+            Some(subscriber) => {
+                subscriber.delivered += subscriber.queue.len() as u64;
+                subscriber.queue.drain(..).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// This subscriber's delivery counters
+    pub fn stats(&self) -> SubscriberStats {
+        let state = self.shared.lock();
+        match state.subscribers.get(&self.id) {
+            Some(subscriber) => stats_for(self.id, subscriber),
+            None => SubscriberStats {
+                id: self.id,
+                queued: 0,
+                delivered: 0,
+                dropped: 0,
+                disconnected: true,
+            },
+        }
+    }
+}
+
+impl<T> Drop for Subscriber<T> {
+    fn drop(&mut self) {
+        self.shared.lock().subscribers.remove(&self.id);
+    }
+}
+
+fn stats_for<T>(id: u64, subscriber: &SubscriberState<T>) -> SubscriberStats {
+    SubscriberStats {
+        id,
+        queued: subscriber.queue.len(),
+        delivered: subscriber.delivered,
+        dropped: subscriber.dropped,
+        disconnected: subscriber.disconnected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn every_subscriber_receives_each_message() {
+        let tx = channel(4, DropPolicy::DropOldest);
+        let mut a = tx.subscribe();
+        let mut b = tx.subscribe();
+
+        assert_eq!(tx.send(1), 2);
+        assert_eq!(tx.send(2), 2);
+
+        assert_eq!(a.recv().await, Ok(1));
+        assert_eq!(a.recv().await, Ok(2));
+        assert_eq!(b.drain(), vec![1, 2]);
+
+        drop(tx);
+        assert_eq!(a.recv().await, Err(RecvError::Closed));
+    }
+
+    #[test]
+    fn lagging_subscriber_follows_drop_policy() {
+        let oldest = channel(2, DropPolicy::DropOldest);
+        let mut sub = oldest.subscribe();
+        for i in 0..5 {
+            oldest.send(i);
+        }
+        assert_eq!(sub.drain(), vec![3, 4]);
+        assert_eq!(sub.stats().dropped, 3);
+
+        let newest = channel(2, DropPolicy::DropNewest);
+        let mut sub = newest.subscribe();
+        for i in 0..5 {
+            newest.send(i);
+        }
+        assert_eq!(sub.drain(), vec![0, 1]);
+        assert_eq!(newest.dropped(), 3);
+
+        let strict = channel(2, DropPolicy::Disconnect);
+        let mut slow = strict.subscribe();
+        let mut fast = strict.subscribe();
+        for i in 0..3 {
+            strict.send(i);
+            assert_eq!(fast.try_recv(), Ok(i));
+        }
+        assert_eq!(slow.try_recv(), Ok(0));
+        assert_eq!(slow.try_recv(), Ok(1));
+        assert_eq!(slow.try_recv(), Err(TryRecvError::Lagged));
+        assert_eq!(strict.send(3), 1);
+        assert!(strict.stats()[0].disconnected);
+    }
+
+    #[tokio::test]
+    async fn recv_wakes_on_send_and_dropped_subscribers_unregister() {
+        let tx = channel::<String>(8, DropPolicy::DropOldest);
+        let mut sub = tx.subscribe();
+        let sender = tx.clone();
+        let task = tokio::spawn(async move {
+            sender.send("hello".to_string());
+        });
+
+        assert_eq!(sub.recv().await.as_deref(), Ok("hello"));
+        task.await.unwrap();
+
+        drop(sub);
+        assert_eq!(tx.subscriber_count(), 0);
+        assert_eq!(tx.send("nobody".to_string()), 0);
+    }
+}
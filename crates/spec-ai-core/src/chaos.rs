@@ -0,0 +1,19 @@
+//! Fault injection for resilience testing
+//!
+//! Re-exports the scenario types from `spec_ai_config::chaos` and adds the
+//! async fault point used by model providers and the mesh client.
+
+pub use spec_ai_config::chaos::*;
+
+/// Delay or fail one call of `operation`, sleeping on the runtime for latency
+pub async fn apply(
+    injector: &FaultInjector,
+    target: FaultTarget,
+    operation: &str,
+) -> Result<(), InjectedFault> {
+    let decision = injector.decide(target, operation);
+    if !decision.latency.is_zero() {
+        tokio::time::sleep(decision.latency).await;
+    }
+    decision.into_result()
+}
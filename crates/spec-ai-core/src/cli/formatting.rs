@@ -1,6 +1,9 @@
 //! Terminal formatting utilities using termimad for rich markdown rendering
 
-use crate::agent::core::{AgentOutput, MemoryRecallStrategy};
+use crate::agent::budget::{BudgetExceeded, RunBudget};
+use crate::agent::core::{AgentOutput, MemoryRecallStrategy, SessionUsage};
+use crate::config::GenerationPreset;
+use crate::persistence::{BranchRecord, CheckpointRecord};
 use serde_json::to_string;
 use std::cell::Cell;
 use termimad::*;
@@ -323,10 +326,18 @@ pub fn render_run_stats(output: &AgentOutput, show_reasoning: bool) -> Option<St
     }
 
     if let Some(usage) = &output.token_usage {
-        sections.push(format!(
+        let mut section = format!(
             "## Tokens\n- Prompt: {}\n- Completion: {}\n- Total: {}\n",
             usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+        );
+        if let Some(cost) = output.cost {
+            section.push_str(&format!("- Estimated cost: {:.4}\n", cost));
+        }
+        section.push_str(&format!(
+            "- Session total: {}\n",
+            output.session_usage.total_tokens()
         ));
+        sections.push(section);
     }
 
     if sections.is_empty() {
@@ -347,6 +358,7 @@ Manage your AI agent profiles and sessions:
 
 - **`/agents`** or **`/list`** — List all available agent profiles
 - **`/switch <name>`** — Switch to a different agent profile
+- **`/profile [name]`** — Same as `/switch`; lists profiles without a name
 - **`/new <name>`** — Create new conversation session
 
 ## Configuration
@@ -356,6 +368,16 @@ Control your SpecAI configuration:
   - Shows model provider, temperature, and other settings
 - **`/config reload`** — Reload configuration from file
   - Useful after editing spec-ai.config.toml
+- **`/model`** — List models offered by the configured providers
+- **`/model <name>`** — Switch the current provider to another model
+- **`/model <provider> <name>`** — Switch provider and model without restarting
+- **`/usage`** — Show token usage and estimated cost for this session, per model
+- **`/budget`** — Show what one agent step may spend
+- **`/budget <tokens|cost|tools> <value|off>`** — Change a limit until the next agent switch
+- **`/budget extend`** — Double the limit that stopped the last step
+- **`/preset`** — List generation presets and the one in use
+- **`/preset <name|off>`** — Use a preset for every message, or go back to the profile's settings
+- **`/preset <name> <message>`** — Send one message with a preset
 
 ## Memory & History
 Access conversation memory:
@@ -371,6 +393,16 @@ Manage multiple conversation sessions:
 - **`/session load <id>`** — Load a specific session
 - **`/session delete <id>`** — Delete a session
 
+## Branches & Checkpoints
+Try another direction without losing the current one:
+
+- **`/checkpoint <name>`** — Name the conversation as it is now
+- **`/checkpoint`** — List this session's checkpoints
+- **`/rollback <name>`** — Continue from a checkpoint on a new branch
+- **`/branch new`** — Continue on a new branch of the conversation so far
+- **`/branch`** — List the branches of this conversation
+- **`/branch switch <id>`** — Continue on another branch
+
 ## Knowledge Graph
 AI reasoning with graph-based memory:
 
@@ -379,6 +411,8 @@ AI reasoning with graph-based memory:
 - **`/graph disable`** — Disable knowledge graph features
 - **`/graph status`** — Show current graph configuration
 - **`/graph show [N]`** — Display last N graph nodes (default: 10)
+- **`/graph export <dot|mermaid> [node [hops]]`** — Render the graph, or a node's neighborhood, for docs and issues
+  - `--type=<type>` exports only one node type, `--limit=N` caps the nodes (default: 200), `--out=<file>` writes a file
 - **`/graph clear`** — Clear graph for current session
 
 ## Graph Synchronization
@@ -465,6 +499,148 @@ pub fn render_agent_table(agents: Vec<(String, bool, Option<String>)>) -> String
     skin.text(&table, Some(terminal_width)).to_string()
 }
 
+/// Format the session's token usage and estimated cost per model
+pub fn render_usage(usage: &SessionUsage) -> String {
+    if usage.is_empty() {
+        return "No token usage recorded in this session.".to_string();
+    }
+
+    let format_cost = |cost: Option<f64>| cost.map(|c| format!("{:.4}", c)).unwrap_or_default();
+    let mut rows: Vec<[String; 6]> = usage
+        .models
+        .iter()
+        .map(|entry| {
+            [
+                format!("{}/{}", entry.provider, entry.model),
+                entry.calls.to_string(),
+                entry.prompt_tokens.to_string(),
+                entry.completion_tokens.to_string(),
+                entry.total_tokens().to_string(),
+                format_cost(entry.cost),
+            ]
+        })
+        .collect();
+    rows.push([
+        "TOTAL".to_string(),
+        usage
+            .models
+            .iter()
+            .map(|m| m.calls)
+            .sum::<u64>()
+            .to_string(),
+        usage.prompt_tokens().to_string(),
+        usage.completion_tokens().to_string(),
+        usage.total_tokens().to_string(),
+        format_cost(usage.cost()),
+    ]);
+
+    if !is_terminal() {
+        // Plain text fallback
+        let mut output = String::from("Session token usage:\n");
+        for [model, calls, prompt, completion, total, cost] in rows {
+            output.push_str(&format!(
+                "  - {}: {} calls, prompt {}, completion {}, total {}",
+                model, calls, prompt, completion, total
+            ));
+            if !cost.is_empty() {
+                output.push_str(&format!(", cost {}", cost));
+            }
+            output.push('\n');
+        }
+        return output;
+    }
+
+    let skin = create_skin();
+    let terminal_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+
+    let mut table = String::from("# Session Token Usage\n\n");
+    table.push_str("| Model | Calls | Prompt | Completion | Total | Cost |\n");
+    table.push_str("|-------|------:|-------:|-----------:|------:|-----:|\n");
+    for row in rows {
+        table.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+
+    skin.text(&table, Some(terminal_width)).to_string()
+}
+
+/// Render the limits on what one agent step may spend
+pub fn render_budget(budget: &RunBudget) -> String {
+    let limit = |max: Option<String>| max.unwrap_or_else(|| "unlimited".to_string());
+    render_list(
+        "Step budget",
+        vec![
+            format!(
+                "tokens: {}",
+                limit(budget.max_tokens.map(|m| m.to_string()))
+            ),
+            format!(
+                "cost: {}",
+                limit(budget.max_cost.map(|m| format!("${:.4}", m)))
+            ),
+            format!(
+                "tool calls: {}",
+                limit(budget.max_tool_calls.map(|m| m.to_string()))
+            ),
+        ],
+    )
+}
+
+/// List generation presets, marking the one in use
+pub fn render_presets(presets: Vec<(String, GenerationPreset)>, active: Option<&str>) -> String {
+    let items = presets
+        .into_iter()
+        .map(|(name, preset)| {
+            let marker = if Some(name.as_str()) == active {
+                " (active)"
+            } else {
+                ""
+            };
+            format!("{}{}: {}", name, marker, preset.summary())
+        })
+        .collect();
+    render_list("Generation presets", items)
+}
+
+/// List a conversation's sessions, the root first, marking the current one
+pub fn render_branches(root: &str, branches: &[BranchRecord], current: &str) -> String {
+    let marker = |id: &str| if id == current { " (current)" } else { "" };
+    let mut items = vec![format!("{}{}", root, marker(root))];
+    items.extend(branches.iter().map(|branch| {
+        format!(
+            "{}{}: from {}",
+            branch.session_id,
+            marker(&branch.session_id),
+            branch.parent_session_id
+        )
+    }));
+    render_list("Branches", items)
+}
+
+/// List checkpoints with when they were saved
+pub fn render_checkpoints(checkpoints: &[CheckpointRecord]) -> String {
+    let items = checkpoints
+        .iter()
+        .map(|checkpoint| {
+            format!(
+                "{}: saved {}",
+                checkpoint.name,
+                checkpoint.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+            )
+        })
+        .collect();
+    render_list("Checkpoints", items)
+}
+
+/// Explain how to continue a step that ran out of budget
+pub fn render_budget_exceeded(exceeded: &BudgetExceeded) -> String {
+    render_markdown(&format!(
+        "**Step stopped:** {}. Raise the limit with `/budget extend` and ask the agent to continue.",
+        exceeded
+    ))
+}
+
 /// Format memory/history display with role-based color coding
 pub fn render_memory(messages: Vec<(String, String)>) -> String {
     if !is_terminal() {
@@ -561,4 +737,45 @@ mod tests {
         assert!(result.contains("default"));
         assert!(result.contains("researcher"));
     }
+
+    #[test]
+    fn test_render_usage_plain_text() {
+        use crate::agent::model::TokenUsage;
+
+        set_plain_text_mode(true);
+        let mut usage = SessionUsage::default();
+        assert_eq!(
+            render_usage(&usage),
+            "No token usage recorded in this session."
+        );
+
+        usage.record("openai", "gpt-4.1", &TokenUsage::new(100, 20), Some(0.5));
+        usage.record("openai", "gpt-4.1", &TokenUsage::new(50, 10), Some(0.25));
+        usage.record("ollama", "llama3", &TokenUsage::new(10, 5), None);
+        let result = render_usage(&usage);
+        set_plain_text_mode(false);
+
+        assert!(result.contains(
+            "openai/gpt-4.1: 2 calls, prompt 150, completion 30, total 180, cost 0.7500"
+        ));
+        assert!(result.contains("ollama/llama3: 1 calls, prompt 10, completion 5, total 15\n"));
+        // Unpriced models leave the total cost blank rather than understating it
+        assert!(result.contains("TOTAL: 3 calls, prompt 160, completion 35, total 195\n"));
+    }
+
+    #[test]
+    fn test_render_budget_plain_text() {
+        set_plain_text_mode(true);
+        let result = render_budget(&RunBudget {
+            max_tokens: Some(50_000),
+            max_cost: Some(0.5),
+            max_tool_calls: None,
+        });
+        set_plain_text_mode(false);
+
+        assert_eq!(
+            result,
+            "Step budget:\n  - tokens: 50000\n  - cost: $0.5000\n  - tool calls: unlimited\n"
+        );
+    }
 }
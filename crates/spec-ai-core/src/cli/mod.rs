@@ -2,46 +2,82 @@
 
 pub mod formatting;
 
-use anyhow::{Context, Result};
-use crossterm::event::{Event, EventStream, KeyCode, KeyModifiers};
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use futures::StreamExt;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::mpsc;
 
+use crate::agent::builder::create_provider_with_tools;
 use crate::agent::core::MemoryRecallStrategy;
+use crate::agent::factory::with_fallbacks;
 use crate::agent::{
     create_transcription_provider, create_transcription_provider_simple, TranscriptionProvider,
 };
-use crate::agent::{AgentBuilder, AgentCore, AgentOutput};
+use crate::agent::{
+    list_configured_models, AgentBuilder, AgentCore, AgentOutput, BudgetExceeded, ModelListing,
+    PolicyViolation, RunTranscript, ToolApprovalSender, UserQuestionSender,
+};
 use crate::bootstrap_self::BootstrapSelf;
-use crate::config::{AgentProfile, AgentRegistry, AppConfig};
+use crate::broadcast::{self, DropPolicy, Subscriber};
+use crate::config::presets::preset_names;
+use crate::config::{
+    AgentProfile, AgentRegistry, AppConfig, GenerationPreset, ModelConfig, PersistenceMode,
+};
+use crate::graph_export::{
+    parse_node_type, ExportFormat, Selection, Subgraph, DEFAULT_EXPORT_LIMIT,
+};
 use crate::persistence::Persistence;
 use crate::policy::PolicyEngine;
 use crate::spec::AgentSpec;
+use crate::startup::{Slot, StartupPlan, StartupReport};
 use terminal_size::terminal_size;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Maximum number of pending policy violations retained on the CLI state
+const MAX_POLICY_VIOLATIONS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     Help,
     Quit,
     ConfigReload,
     ConfigShow,
     PolicyReload,
+    ListModels,
+    /// Switch to a model, optionally on another provider
+    SwitchModel {
+        provider: Option<String>,
+        model: String,
+    },
+    /// Token usage and estimated cost for the current session
+    Usage,
+    /// Show or change what one agent step may spend
+    Budget(BudgetCommand),
+    /// Show or change the generation preset
+    Preset(PresetCommand),
     SwitchAgent(String),
     ListAgents,
     MemoryShow(Option<usize>),
     SessionNew(Option<String>),
     SessionList,
     SessionSwitch(String),
+    /// List, start or switch branches of the conversation
+    Branch(BranchCommand),
+    /// List the session's checkpoints, or save one under a name
+    Checkpoint(Option<String>),
+    /// Continue in a new branch from a checkpoint
+    Rollback(String),
     // Graph commands
     GraphEnable,
     GraphDisable,
     GraphStatus,
     GraphShow(Option<usize>),
+    /// Render part of the session's graph as DOT or Mermaid
+    GraphExport(GraphExportCommand),
     GraphClear,
     // Sync commands
     SyncList,
@@ -59,6 +95,120 @@ pub enum Command {
     Empty,
 }
 
+/// What `/budget` does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetCommand {
+    Show,
+    /// Double the limit that stopped the last step
+    Extend,
+    /// Set the token limit; `None` removes it
+    Tokens(Option<u64>),
+    /// Set the cost limit in dollars; `None` removes it
+    Cost(Option<f64>),
+    /// Set the tool call limit; `None` removes it
+    ToolCalls(Option<u32>),
+}
+
+/// What `/branch` does
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchCommand {
+    /// List the branches of the conversation
+    List,
+    /// Continue in a new branch holding the conversation so far
+    New,
+    /// Continue in another branch
+    Switch(String),
+}
+
+/// What `/graph export` renders and where it goes
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphExportCommand {
+    pub format: ExportFormat,
+    pub selection: Selection,
+    /// Most nodes to include
+    pub limit: usize,
+    /// File to write; the export is shown inline when `None`
+    pub out: Option<PathBuf>,
+}
+
+/// `/graph export <dot|mermaid> [node_id [hops]] [--type=T] [--limit=N] [--out=PATH]`
+fn parse_graph_export<'a>(mut args: impl Iterator<Item = &'a str>) -> Option<GraphExportCommand> {
+    let format = args.next()?.parse().ok()?;
+    let mut positional = Vec::new();
+    let mut node_type = None;
+    let mut limit = DEFAULT_EXPORT_LIMIT;
+    let mut out = None;
+    for arg in args {
+        if let Some(value) = arg.strip_prefix("--type=") {
+            node_type = Some(parse_node_type(value).ok()?);
+        } else if let Some(value) = arg.strip_prefix("--limit=") {
+            limit = value.parse().ok().filter(|limit| *limit > 0)?;
+        } else if let Some(value) = arg.strip_prefix("--out=") {
+            out = Some(PathBuf::from(value));
+        } else {
+            positional.push(arg.parse::<i64>().ok()?);
+        }
+    }
+    let selection = match positional[..] {
+        [] => Selection::Query { node_type },
+        [node_id] if node_type.is_none() => Selection::Neighborhood { node_id, hops: 1 },
+        [node_id, hops] if node_type.is_none() && hops >= 0 => Selection::Neighborhood {
+            node_id,
+            hops: hops as usize,
+        },
+        _ => return None,
+    };
+    Some(GraphExportCommand {
+        format,
+        selection,
+        limit,
+        out,
+    })
+}
+
+/// What `/preset` does
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresetCommand {
+    /// List the presets and the one in use
+    Show,
+    /// Use a preset for every message; `None` goes back to the profile's settings
+    Use(Option<String>),
+    /// Send one message with a preset
+    Once { preset: String, message: String },
+}
+
+/// `/preset [name|off] [message]`
+fn parse_preset(args: &str) -> PresetCommand {
+    match args.split_once(char::is_whitespace) {
+        _ if args.is_empty() => PresetCommand::Show,
+        Some((preset, message)) => PresetCommand::Once {
+            preset: preset.to_string(),
+            message: message.trim().to_string(),
+        },
+        None if args.eq_ignore_ascii_case("off") => PresetCommand::Use(None),
+        None => PresetCommand::Use(Some(args.to_string())),
+    }
+}
+
+/// `/budget <limit> <value|off>`
+fn parse_budget_limit(limit: &str, value: &str) -> Option<BudgetCommand> {
+    fn max<T: std::str::FromStr>(value: &str) -> Option<Option<T>> {
+        if value.eq_ignore_ascii_case("off") {
+            Some(None)
+        } else {
+            value.parse().ok().map(Some)
+        }
+    }
+    match limit {
+        "tokens" => max(value).map(BudgetCommand::Tokens),
+        "cost" => max::<f64>(value.trim_start_matches('$'))
+            .filter(|cost| cost.is_none_or(|cost| cost.is_finite() && cost >= 0.0))
+            .map(BudgetCommand::Cost),
+        "tools" | "tool_calls" => max(value).map(BudgetCommand::ToolCalls),
+        _ => None,
+    }
+}
+
 pub fn parse_command(input: &str) -> Command {
     let line = input.trim();
     if line.is_empty() {
@@ -80,7 +230,37 @@ pub fn parse_command(input: &str) -> Command {
                 Some("reload") => Command::PolicyReload,
                 _ => Command::Help,
             },
+            "model" | "models" => match (parts.next(), parts.next()) {
+                (None, _) => Command::ListModels,
+                (Some(model), None) => Command::SwitchModel {
+                    provider: None,
+                    model: model.to_string(),
+                },
+                (Some(provider), Some(model)) => Command::SwitchModel {
+                    provider: Some(provider.to_lowercase()),
+                    model: model.to_string(),
+                },
+            },
+            "usage" => Command::Usage,
+            "budget" => match (parts.next(), parts.next()) {
+                (None, _) => Command::Budget(BudgetCommand::Show),
+                (Some("extend"), None) => Command::Budget(BudgetCommand::Extend),
+                (Some(limit), Some(value)) => {
+                    parse_budget_limit(limit, value).map_or(Command::Help, Command::Budget)
+                }
+                _ => Command::Help,
+            },
+            "preset" | "presets" => {
+                let args = rest
+                    .split_once(char::is_whitespace)
+                    .map_or("", |(_, args)| args.trim());
+                Command::Preset(parse_preset(args))
+            }
             "agents" | "list" => Command::ListAgents,
+            "profile" | "profiles" => match parts.next() {
+                Some(name) => Command::SwitchAgent(name.to_string()),
+                None => Command::ListAgents,
+            },
             "switch" => {
                 let name = parts.next().unwrap_or("").to_string();
                 if name.is_empty() {
@@ -112,6 +292,26 @@ pub fn parse_command(input: &str) -> Command {
                 }
                 _ => Command::Help,
             },
+            "branch" | "branches" => match (parts.next(), parts.next()) {
+                (None | Some("list"), None) => Command::Branch(BranchCommand::List),
+                (Some("new"), None) => Command::Branch(BranchCommand::New),
+                (Some("switch"), Some(id)) => {
+                    Command::Branch(BranchCommand::Switch(id.to_string()))
+                }
+                _ => Command::Help,
+            },
+            "checkpoint" | "checkpoints" => {
+                let name = rest
+                    .split_once(char::is_whitespace)
+                    .map_or("", |(_, name)| name.trim());
+                Command::Checkpoint((!name.is_empty()).then(|| name.to_string()))
+            }
+            "rollback" => match rest.split_once(char::is_whitespace) {
+                Some((_, name)) if !name.trim().is_empty() => {
+                    Command::Rollback(name.trim().to_string())
+                }
+                _ => Command::Help,
+            },
             "graph" => match parts.next() {
                 Some("enable") => Command::GraphEnable,
                 Some("disable") => Command::GraphDisable,
@@ -120,6 +320,9 @@ pub fn parse_command(input: &str) -> Command {
                     let n = parts.next().and_then(|s| s.parse::<usize>().ok());
                     Command::GraphShow(n)
                 }
+                Some("export") => {
+                    parse_graph_export(parts).map_or(Command::Help, Command::GraphExport)
+                }
                 Some("clear") => Command::GraphClear,
                 _ => Command::Help,
             },
@@ -217,7 +420,71 @@ struct TranscriptionTask {
     stop_tx: mpsc::UnboundedSender<()>,
     started_at: std::time::SystemTime,
     duration_secs: Option<u64>,
-    chunks_rx: mpsc::UnboundedReceiver<String>,
+    chunks_rx: Subscriber<String>,
+}
+
+/// Transcribed chunks kept while nobody collects them; older chunks are dropped
+const TRANSCRIPTION_CHUNK_CAPACITY: usize = 1024;
+
+/// Suffix for transcription summaries when chunks were lost
+fn dropped_chunks_note(dropped: u64) -> String {
+    if dropped == 0 {
+        String::new()
+    } else {
+        format!(
+            "; {} older chunks were dropped before they were saved",
+            dropped
+        )
+    }
+}
+
+/// Build the agent registry and make sure an agent is active
+fn init_registry(config: &AppConfig, persistence: Persistence) -> Result<AgentRegistry> {
+    let initial_agents = config.agents.clone();
+    let registry = AgentRegistry::new(initial_agents.clone(), persistence);
+    registry.init()?;
+
+    // Ensure we have an active agent
+    if registry.active_name().is_none() {
+        if let Some(default_name) = &config.default_agent {
+            if registry.get(default_name).is_some() {
+                registry.set_active(default_name)?;
+            }
+        }
+    }
+    if registry.active_name().is_none() {
+        // If still none, create or pick a default profile
+        if initial_agents.is_empty() {
+            let default_profile = AgentProfile::default();
+            registry.upsert("default".to_string(), default_profile)?;
+            registry.set_active("default")?;
+        } else {
+            // Pick first agent by name
+            if let Some(first) = registry.list().first().cloned() {
+                registry.set_active(&first)?;
+            }
+        }
+    }
+    Ok(registry)
+}
+
+/// Apply sync configuration from config file
+fn apply_sync_config(config: &AppConfig, persistence: &Persistence) -> Result<()> {
+    if !config.sync.enabled {
+        return Ok(());
+    }
+
+    // Enable sync for each configured namespace
+    for ns in &config.sync.namespaces {
+        if let Err(e) = persistence.graph_set_sync_enabled(&ns.session_id, &ns.graph_name, true) {
+            eprintln!(
+                "Warning: Failed to enable sync for {}/{}: {}",
+                ns.session_id, ns.graph_name, e
+            );
+        }
+    }
+
+    Ok(())
 }
 
 pub struct CliState {
@@ -225,10 +492,21 @@ pub struct CliState {
     pub persistence: Persistence,
     pub registry: AgentRegistry,
     pub agent: AgentCore,
-    pub transcription_provider: Arc<dyn TranscriptionProvider>,
+    transcription_provider: OnceLock<Arc<dyn TranscriptionProvider>>,
+    startup: StartupReport,
     pub reasoning_messages: Vec<String>,
     pub status_message: String,
+    /// Policy violations not yet taken by the UI, oldest first
+    pub policy_violations: Vec<PolicyViolation>,
+    /// Limit that stopped the last step, for `/budget extend`
+    pub budget_exceeded: Option<BudgetExceeded>,
     speech_enabled: Arc<AtomicBool>,
+    /// Front end that approves blocked tool calls, applied to every agent rebuild
+    tool_approvals: Option<ToolApprovalSender>,
+    /// Front end that answers `ask_user` questions, applied to every agent rebuild
+    user_questions: Option<UserQuestionSender>,
+    /// Transcript every agent rebuild records to or replays from
+    transcript: Option<Arc<RunTranscript>>,
     paste_mode: bool,
     paste_buffer: String,
     init_allowed: bool,
@@ -253,53 +531,46 @@ impl CliState {
     }
 
     /// Create a CLI state from a provided config
+    ///
+    /// Subsystems start through a [`StartupPlan`], concurrently where their
+    /// dependencies allow; the transcription provider is deferred until the
+    /// first `/listen`.
     pub fn new_with_config(config: AppConfig) -> Result<Self> {
-        let persistence =
-            Persistence::new(&config.database.path).context("initializing persistence")?;
-
-        // Build registry and ensure an active agent exists
-        let initial_agents = config.agents.clone();
-        let registry = AgentRegistry::new(initial_agents.clone(), persistence.clone());
-        registry.init()?;
-
-        // Ensure we have an active agent
-        if registry.active_name().is_none() {
-            if let Some(default_name) = &config.default_agent {
-                if registry.get(default_name).is_some() {
-                    registry.set_active(default_name)?;
-                }
-            }
-        }
-        if registry.active_name().is_none() {
-            // If still none, create or pick a default profile
-            if initial_agents.is_empty() {
-                let default_profile = AgentProfile::default();
-                registry.upsert("default".to_string(), default_profile)?;
-                registry.set_active("default")?;
-            } else {
-                // Pick first agent by name
-                if let Some(first) = registry.list().first().cloned() {
-                    registry.set_active(&first)?;
-                }
-            }
-        }
-
-        // Create the AgentCore from registry + config
-        let agent = AgentBuilder::new_with_registry(&registry, &config, None)?;
-
-        // Create transcription provider from config
-        let transcription_provider = {
-            use crate::agent::transcription_factory::TranscriptionProviderConfig;
-            let provider_config = TranscriptionProviderConfig {
-                provider: config.audio.provider.clone(),
-                api_key_source: config.audio.api_key_source.clone(),
-                endpoint: config.audio.endpoint.clone(),
-                on_device: config.audio.on_device,
-                settings: serde_json::Value::Null,
-            };
-            create_transcription_provider(&provider_config)
-                .or_else(|_| create_transcription_provider_simple("mock"))
-                .context("Failed to create transcription provider")?
+        let persistence = Slot::new();
+        let registry = Slot::new();
+        let agent = Slot::new();
+
+        let mut plan = StartupPlan::new();
+        plan.step("persistence", &[], || {
+            persistence.set(
+                Persistence::from_config(&config.database).context("initializing persistence")?,
+            );
+            Ok(())
+        })
+        .step("agent registry", &["persistence"], || {
+            registry.set(init_registry(&config, persistence.get())?);
+            Ok(())
+        })
+        .step("agent", &["agent registry"], || {
+            // Create the AgentCore from registry + config
+            agent.set(
+                registry
+                    .with(|registry| AgentBuilder::new_with_registry(registry, &config, None))?,
+            );
+            Ok(())
+        })
+        .step("sync config", &["persistence"], || {
+            apply_sync_config(&config, &persistence.get())
+        })
+        .defer("transcription");
+        let startup = plan.run()?;
+
+        let (Some(persistence), Some(registry), Some(agent)) = (
+            persistence.into_inner(),
+            registry.into_inner(),
+            agent.into_inner(),
+        ) else {
+            anyhow::bail!("startup finished without initializing every subsystem");
         };
 
         let speech_on = cfg!(target_os = "macos") && config.audio.speak_responses;
@@ -309,10 +580,16 @@ impl CliState {
             persistence,
             registry,
             agent,
-            transcription_provider,
+            transcription_provider: OnceLock::new(),
+            startup,
             reasoning_messages: vec!["Reasoning: idle".to_string()],
             status_message: "Status: initializing".to_string(),
+            policy_violations: Vec::new(),
+            budget_exceeded: None,
             speech_enabled: Arc::new(AtomicBool::new(speech_on)),
+            tool_approvals: None,
+            user_questions: None,
+            transcript: None,
             paste_mode: false,
             paste_buffer: String::new(),
             init_allowed: true,
@@ -322,32 +599,114 @@ impl CliState {
         state.agent.set_speak_responses(speech_on);
         state.refresh_init_gate()?;
 
-        // Apply sync configuration from config file
-        state.apply_sync_config()?;
-
         Ok(state)
     }
 
-    /// Apply sync configuration from config file
-    fn apply_sync_config(&self) -> Result<()> {
-        if !self.config.sync.enabled {
-            return Ok(());
+    /// Timing breakdown of the subsystems started by [`CliState::new_with_config`]
+    pub fn startup_report(&self) -> &StartupReport {
+        &self.startup
+    }
+
+    /// Transcription provider from config, created on first use
+    pub fn transcription_provider(&self) -> Result<Arc<dyn TranscriptionProvider>> {
+        if let Some(provider) = self.transcription_provider.get() {
+            return Ok(Arc::clone(provider));
         }
+        use crate::agent::transcription_factory::TranscriptionProviderConfig;
+        let provider_config = TranscriptionProviderConfig {
+            provider: self.config.audio.provider.clone(),
+            api_key_source: self.config.audio.api_key_source.clone(),
+            endpoint: self.config.audio.endpoint.clone(),
+            on_device: self.config.audio.on_device,
+            settings: serde_json::Value::Null,
+        };
+        let provider = create_transcription_provider(&provider_config)
+            .or_else(|_| create_transcription_provider_simple("mock"))
+            .context("Failed to create transcription provider")?;
+        Ok(Arc::clone(
+            self.transcription_provider.get_or_init(|| provider),
+        ))
+    }
 
-        // Enable sync for each configured namespace
-        for ns in &self.config.sync.namespaces {
-            if let Err(e) =
-                self.persistence
-                    .graph_set_sync_enabled(&ns.session_id, &ns.graph_name, true)
-            {
-                eprintln!(
-                    "Warning: Failed to enable sync for {}/{}: {}",
-                    ns.session_id, ns.graph_name, e
-                );
+    /// Send blocked tool calls to a front end for approval
+    ///
+    /// The handler is kept across agent switches and session changes.
+    pub fn set_tool_approvals(&mut self, approvals: Option<ToolApprovalSender>) {
+        self.agent.set_tool_approvals(approvals.clone());
+        self.tool_approvals = approvals;
+    }
+
+    /// Send the agent's `ask_user` questions to a front end
+    ///
+    /// Like approvals, the handler is kept across agent switches and session
+    /// changes.
+    pub fn set_user_questions(&mut self, questions: Option<UserQuestionSender>) {
+        self.agent.set_user_questions(questions.clone());
+        self.user_questions = questions;
+    }
+
+    /// Record the agent's model and tool calls, or replay them, through
+    /// `transcript`
+    ///
+    /// The transcript is kept across agent switches and session changes.
+    pub fn set_transcript(&mut self, transcript: Option<Arc<RunTranscript>>) {
+        self.agent.set_transcript(transcript.clone());
+        self.transcript = transcript;
+    }
+
+    /// List the models offered by every configured provider
+    pub async fn list_models(&self) -> Vec<ModelListing> {
+        list_configured_models(&self.config).await
+    }
+
+    /// Model the running agent uses, after its profile's overrides
+    pub fn active_model(&self) -> ModelConfig {
+        self.agent.profile().effective_model(&self.config.model)
+    }
+
+    /// Swap the running agent onto another provider and model
+    ///
+    /// The conversation continues in the same session, and later agent
+    /// rebuilds (agent or session switches) keep the new model until the
+    /// config is reloaded, except for profiles that name their own model.
+    pub fn switch_model(&mut self, provider: Option<&str>, model: &str) -> Result<String> {
+        let mut model_config = self.active_model();
+        if let Some(provider) = provider {
+            if !provider.eq_ignore_ascii_case(&model_config.provider) {
+                // The configured key belongs to the old provider
+                model_config.api_key_source = None;
             }
+            model_config.provider = provider.to_lowercase();
         }
+        model_config.model_name = Some(model.to_string());
 
-        Ok(())
+        let tool_registry = self.agent.tool_registry();
+        let provider = with_fallbacks(
+            create_provider_with_tools(&model_config, tool_registry)?,
+            &self.config.providers,
+            |model| create_provider_with_tools(model, tool_registry),
+        );
+        self.agent.set_model(provider, &model_config);
+        let message = format!(
+            "Switched to model '{}' on {}.",
+            model, model_config.provider
+        );
+        self.config.model = model_config;
+        Ok(message)
+    }
+
+    /// Make `name` the active agent profile, continuing the current session
+    ///
+    /// The agent is rebuilt with the profile's model, prompt, tools and
+    /// policy mode.
+    pub fn switch_agent(&mut self, name: &str) -> Result<String> {
+        self.registry.set_active(name)?;
+        let session = self.agent.session_id().to_string();
+        self.agent = AgentBuilder::new_with_registry(&self.registry, &self.config, Some(session))?;
+        let speak_enabled = self.speech_enabled.load(Ordering::Relaxed);
+        self.agent.set_speak_responses(speak_enabled);
+        self.agent.set_transcript(self.transcript.clone());
+        Ok(format!("Switched active agent to '{}'.", name))
     }
 
     /// Save transcription chunks to database with embeddings
@@ -396,6 +755,50 @@ impl CliState {
                 let summary = self.config.summary();
                 Ok(Some(formatting::render_config(&summary)))
             }
+            Command::ListModels => {
+                let active = self.active_model();
+                let current = active.model_name.as_deref();
+                let mut output = String::new();
+                for listing in self.list_models().await {
+                    let items = match &listing.error {
+                        Some(err) => vec![format!("unavailable: {}", err)],
+                        None if listing.models.is_empty() => vec!["no models".to_string()],
+                        None => listing
+                            .models
+                            .iter()
+                            .map(|name| {
+                                let is_active = listing.provider == active.provider
+                                    && Some(name.as_str()) == current;
+                                if is_active {
+                                    format!("{} (active)", name)
+                                } else {
+                                    name.clone()
+                                }
+                            })
+                            .collect(),
+                    };
+                    output.push_str(&formatting::render_list(&listing.provider, items));
+                }
+                Ok(Some(output))
+            }
+            Command::SwitchModel { provider, model } => {
+                Ok(Some(self.switch_model(provider.as_deref(), &model)?))
+            }
+            Command::Usage => Ok(Some(formatting::render_usage(self.agent.session_usage()))),
+            Command::Budget(command) => Ok(Some(self.budget_command(command))),
+            Command::Preset(PresetCommand::Show) => Ok(Some(self.show_presets())),
+            Command::Preset(PresetCommand::Use(name)) => Ok(Some(self.use_preset(name)?)),
+            Command::Preset(PresetCommand::Once { preset, message }) => {
+                let previous = self
+                    .agent
+                    .preset()
+                    .map(|(name, preset)| (name.to_string(), *preset));
+                let preset = self.resolve_preset(&preset)?;
+                self.agent.set_preset(Some(preset));
+                let output = self.send_message(&message).await;
+                self.agent.set_preset(previous);
+                output
+            }
             Command::ListAgents => {
                 let agents = self.registry.list();
                 let active = self.registry.active_name();
@@ -416,9 +819,15 @@ impl CliState {
             }
             Command::ConfigReload => {
                 let current_session = self.agent.session_id().to_string();
+                let previous_database = self.config.database.clone();
                 self.config = AppConfig::load()?;
-                // rebuild persistence (path may have changed)
-                self.persistence = Persistence::new(&self.config.database.path)?;
+                // rebuild persistence (path may have changed), keeping an
+                // in-memory database so the session survives the reload
+                let keep_memory = previous_database.persistence == PersistenceMode::Memory
+                    && previous_database == self.config.database;
+                if !keep_memory {
+                    self.persistence = Persistence::from_config(&self.config.database)?;
+                }
                 // rebuild registry with new agents
                 self.registry =
                     AgentRegistry::new(self.config.agents.clone(), self.persistence.clone());
@@ -453,15 +862,7 @@ impl CliState {
                     rule_count
                 )))
             }
-            Command::SwitchAgent(name) => {
-                self.registry.set_active(&name)?;
-                let session = self.agent.session_id().to_string();
-                self.agent =
-                    AgentBuilder::new_with_registry(&self.registry, &self.config, Some(session))?;
-                let speak_enabled = self.speech_enabled.load(Ordering::Relaxed);
-                self.agent.set_speak_responses(speak_enabled);
-                Ok(Some(format!("Switched active agent to '{}'.", name)))
-            }
+            Command::SwitchAgent(name) => Ok(Some(self.switch_agent(&name)?)),
             Command::MemoryShow(n) => {
                 let limit = n.unwrap_or(10) as i64;
                 let sid = self.agent.session_id().to_string();
@@ -511,6 +912,32 @@ impl CliState {
                 self.refresh_init_gate()?;
                 Ok(Some(format!("Switched to session '{}'.", id)))
             }
+            Command::Branch(command) => Ok(Some(self.branch_command(command)?)),
+            Command::Checkpoint(None) => {
+                let checkpoints = self.agent.checkpoints()?;
+                if checkpoints.is_empty() {
+                    return Ok(Some(
+                        "No checkpoints in this session. Save one with /checkpoint <name>."
+                            .to_string(),
+                    ));
+                }
+                Ok(Some(formatting::render_checkpoints(&checkpoints)))
+            }
+            Command::Checkpoint(Some(name)) => {
+                let checkpoint = self.agent.save_checkpoint(&name)?;
+                Ok(Some(format!(
+                    "Saved checkpoint '{}'. Return to this point with /rollback {}.",
+                    checkpoint.name, checkpoint.name
+                )))
+            }
+            Command::Rollback(name) => {
+                let branch = self.agent.rollback(&name)?;
+                self.refresh_init_gate()?;
+                Ok(Some(format!(
+                    "Rolled back to '{}' on new branch '{}'; '{}' is unchanged.",
+                    name, branch.session_id, branch.parent_session_id
+                )))
+            }
             // Graph commands
             Command::GraphEnable => {
                 // For now, just show instructions for enabling graph features
@@ -591,6 +1018,48 @@ impl CliState {
                     Ok(Some(output))
                 }
             }
+            Command::GraphExport(export) => {
+                let graph = Subgraph::select(
+                    &self.persistence,
+                    self.agent.session_id(),
+                    &export.selection,
+                    export.limit,
+                )?;
+                if graph.nodes.is_empty() {
+                    return Ok(Some("No graph nodes to export.".to_string()));
+                }
+
+                let text = graph.render(export.format);
+                let mut summary = format!(
+                    "{} nodes and {} edges",
+                    graph.nodes.len(),
+                    graph.edges.len()
+                );
+                if graph.omitted > 0 {
+                    summary.push_str(&format!(
+                        "; {} more nodes left out, raise --limit to include them",
+                        graph.omitted
+                    ));
+                }
+                match export.out {
+                    Some(path) => {
+                        std::fs::write(&path, text)
+                            .with_context(|| format!("writing {}", path.display()))?;
+                        Ok(Some(format!(
+                            "Exported {} as {} to {}",
+                            summary,
+                            export.format.as_str(),
+                            path.display()
+                        )))
+                    }
+                    None => Ok(Some(format!(
+                        "```{}\n{}```\n{}",
+                        export.format.as_str(),
+                        text,
+                        summary
+                    ))),
+                }
+            }
             Command::GraphClear => {
                 let session_id = self.agent.session_id();
 
@@ -650,10 +1119,14 @@ impl CliState {
 
                 // Create stop channel and chunks channel
                 let (stop_tx, mut stop_rx) = mpsc::unbounded_channel::<()>();
-                let (chunks_tx, chunks_rx) = mpsc::unbounded_channel::<String>();
+                let chunks_tx = broadcast::channel::<String>(
+                    TRANSCRIPTION_CHUNK_CAPACITY,
+                    DropPolicy::DropOldest,
+                );
+                let chunks_rx = chunks_tx.subscribe();
 
                 // Clone provider for background task
-                let provider = Arc::clone(&self.transcription_provider);
+                let provider = self.transcription_provider()?;
                 let provider_name = provider.metadata().name.clone();
                 let provider_name_display = provider_name.clone(); // Clone for response message
                 let started_at = std::time::SystemTime::now();
@@ -691,7 +1164,7 @@ impl CliState {
                                                 }
                                                 Some(Ok(TranscriptionEvent::Transcription { chunk_id, text, .. })) => {
                                                     println!("[Transcription] Chunk {}: {}", chunk_id, text);
-                                                    let _ = chunks_tx.send(text);
+                                                    chunks_tx.send(text);
                                                 }
                                                 Some(Ok(TranscriptionEvent::Error { chunk_id, message })) => {
                                                     eprintln!("[Transcription] Error in chunk {}: {}", chunk_id, message);
@@ -740,10 +1213,8 @@ impl CliState {
                     let _ = task.stop_tx.send(());
 
                     // Collect any remaining chunks
-                    let mut chunks = Vec::new();
-                    while let Ok(text) = task.chunks_rx.try_recv() {
-                        chunks.push(text);
-                    }
+                    let chunks = task.chunks_rx.drain();
+                    let dropped = task.chunks_rx.stats().dropped;
 
                     // Save to database
                     let chunk_count = self.save_transcription_chunks(&chunks).await;
@@ -751,8 +1222,10 @@ impl CliState {
                     let elapsed = task.started_at.elapsed().map(|d| d.as_secs()).unwrap_or(0);
 
                     Ok(Some(format!(
-                        "Stopped transcription (ran for {} seconds, saved {} chunks to database)",
-                        elapsed, chunk_count
+                        "Stopped transcription (ran for {} seconds, saved {} chunks to database){}",
+                        elapsed,
+                        chunk_count,
+                        dropped_chunks_note(dropped)
                     )))
                 } else {
                     Ok(Some("No transcription is currently running.".to_string()))
@@ -763,11 +1236,9 @@ impl CliState {
                 if let Some(task) = self.transcription_task.take() {
                     if task.handle.is_finished() {
                         // Collect chunks
-                        let mut chunks = Vec::new();
                         let mut chunks_rx = task.chunks_rx;
-                        while let Ok(text) = chunks_rx.try_recv() {
-                            chunks.push(text);
-                        }
+                        let chunks = chunks_rx.drain();
+                        let dropped = chunks_rx.stats().dropped;
 
                         // Save to database
                         let chunk_count = self.save_transcription_chunks(&chunks).await;
@@ -775,8 +1246,10 @@ impl CliState {
                         let elapsed = task.started_at.elapsed().map(|d| d.as_secs()).unwrap_or(0);
 
                         return Ok(Some(format!(
-                            "Transcription completed (ran for {} seconds, saved {} chunks to database)",
-                            elapsed, chunk_count
+                            "Transcription completed (ran for {} seconds, saved {} chunks to database){}",
+                            elapsed,
+                            chunk_count,
+                            dropped_chunks_note(dropped)
                         )));
                     } else {
                         // Put it back since it's still running
@@ -878,24 +1351,33 @@ impl CliState {
                     outcome.document_count
                 )))
             }
-            Command::Message(text) => {
-                self.init_allowed = false;
-                let speak_enabled = self.speech_enabled.load(Ordering::Relaxed);
-                self.config.audio.speak_responses = speak_enabled;
-                self.agent.set_speak_responses(speak_enabled);
-                let output = self.agent.run_step(&text).await?;
-                self.update_reasoning_messages(&output);
-                self.maybe_speak_response(&output.response);
-                let mut formatted =
-                    formatting::render_agent_response("assistant", &output.response);
-                let show_reasoning = self.agent.profile().show_reasoning;
-                if let Some(stats) = formatting::render_run_stats(&output, show_reasoning) {
-                    formatted.push('\n');
-                    formatted.push_str(&stats);
-                }
-                Ok(Some(formatted))
-            }
+            Command::Message(text) => self.send_message(&text).await,
+        }
+    }
+
+    /// Run an agent step for `text` and format its response
+    async fn send_message(&mut self, text: &str) -> Result<Option<String>> {
+        self.init_allowed = false;
+        let speak_enabled = self.speech_enabled.load(Ordering::Relaxed);
+        self.config.audio.speak_responses = speak_enabled;
+        self.agent.set_speak_responses(speak_enabled);
+        self.agent.set_tool_approvals(self.tool_approvals.clone());
+        self.agent.set_user_questions(self.user_questions.clone());
+        self.agent.set_transcript(self.transcript.clone());
+        let output = self.agent.run_step(text).await?;
+        self.update_reasoning_messages(&output);
+        self.maybe_speak_response(&output.response);
+        let mut formatted = formatting::render_agent_response("assistant", &output.response);
+        let show_reasoning = self.agent.profile().show_reasoning;
+        if let Some(stats) = formatting::render_run_stats(&output, show_reasoning) {
+            formatted.push('\n');
+            formatted.push_str(&stats);
+        }
+        if let Some(exceeded) = &output.budget_exceeded {
+            formatted.push('\n');
+            formatted.push_str(&formatting::render_budget_exceeded(exceeded));
         }
+        Ok(Some(formatted))
     }
 
     /// Run interactive REPL on stdin/stdout
@@ -972,7 +1454,10 @@ impl CliState {
             }
 
             // If this is a normal message to the agent, allow interruption with ESC
-            if matches!(command_preview, Command::Message(_)) {
+            if matches!(
+                command_preview,
+                Command::Message(_) | Command::Preset(PresetCommand::Once { .. })
+            ) {
                 let speech_flag = self.speech_enabled.clone();
                 let mut pending_speech_toggle: Option<bool> = None;
                 // Prepare the future for handling the line
@@ -992,7 +1477,8 @@ impl CliState {
                         }
                         maybe_event = events.next() => {
                             match maybe_event {
-                                Some(Ok(Event::Key(key))) => {
+                                // Windows also reports key releases; act on presses only
+                                Some(Ok(Event::Key(key))) if key.kind != KeyEventKind::Release => {
                                     if key.code == KeyCode::Esc {
                                         // User requested interruption; drop the future by breaking
                                         interrupted = true;
@@ -1090,6 +1576,9 @@ impl CliState {
         let speak_enabled = self.speech_enabled.load(Ordering::Relaxed);
         self.config.audio.speak_responses = speak_enabled;
         self.agent.set_speak_responses(speak_enabled);
+        self.agent.set_tool_approvals(self.tool_approvals.clone());
+        self.agent.set_user_questions(self.user_questions.clone());
+        self.agent.set_transcript(self.transcript.clone());
         let output = self.agent.run_spec(&spec).await?;
         self.update_reasoning_messages(&output);
         self.maybe_speak_response(&output.response);
@@ -1106,12 +1595,121 @@ impl CliState {
         Ok(intro)
     }
 
+    /// Show or change the agent's step budget
+    ///
+    /// Changes last until the agent is rebuilt, as by `/switch` or `/model`.
+    fn budget_command(&mut self, command: BudgetCommand) -> String {
+        let mut budget = self.agent.run_budget();
+        match command {
+            BudgetCommand::Show => return formatting::render_budget(&budget),
+            BudgetCommand::Extend => match self.budget_exceeded.take() {
+                Some(exceeded) => budget = exceeded.extended(budget, 2),
+                None => {
+                    return "No step has run out of budget. Set a limit with \
+                            /budget <tokens|cost|tools> <value|off>."
+                        .to_string()
+                }
+            },
+            BudgetCommand::Tokens(max) => budget.max_tokens = max,
+            BudgetCommand::Cost(max) => budget.max_cost = max,
+            BudgetCommand::ToolCalls(max) => budget.max_tool_calls = max,
+        }
+        self.agent.set_run_budget(budget);
+        formatting::render_budget(&budget)
+    }
+
+    /// List, start or switch branches of the conversation
+    fn branch_command(&mut self, command: BranchCommand) -> Result<String> {
+        match command {
+            BranchCommand::List => {
+                let root = self.agent.conversation_root()?;
+                let branches = self.agent.branches()?;
+                Ok(formatting::render_branches(
+                    &root,
+                    &branches,
+                    self.agent.session_id(),
+                ))
+            }
+            BranchCommand::New => {
+                let branch = self.agent.branch()?;
+                self.refresh_init_gate()?;
+                Ok(format!(
+                    "Continuing on new branch '{}'; '{}' is unchanged.",
+                    branch.session_id, branch.parent_session_id
+                ))
+            }
+            BranchCommand::Switch(id) => {
+                self.agent.switch_branch(&id)?;
+                self.refresh_init_gate()?;
+                Ok(format!("Switched to branch '{}'.", id))
+            }
+        }
+    }
+
+    /// The preset called `name`, from the config or built in
+    fn resolve_preset(&self, name: &str) -> Result<(String, GenerationPreset)> {
+        GenerationPreset::resolve(name, &self.config.presets)
+            .map(|preset| (name.to_string(), preset))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown preset '{}'. Available: {}",
+                    name,
+                    preset_names(&self.config.presets).join(", ")
+                )
+            })
+    }
+
+    fn show_presets(&self) -> String {
+        let active = self.agent.preset().map(|(name, _)| name);
+        let presets = preset_names(&self.config.presets)
+            .into_iter()
+            .filter_map(|name| {
+                let preset = GenerationPreset::resolve(&name, &self.config.presets)?;
+                Some((name, preset))
+            })
+            .collect();
+        formatting::render_presets(presets, active)
+    }
+
+    /// Use the preset called `name` for every message, or none
+    ///
+    /// Lasts until the agent is rebuilt, as by `/switch` or `/config reload`.
+    fn use_preset(&mut self, name: Option<String>) -> Result<String> {
+        match name {
+            Some(name) => {
+                let (name, preset) = self.resolve_preset(&name)?;
+                let message = format!("Using preset '{}' ({}).", name, preset.summary());
+                self.agent.set_preset(Some((name, preset)));
+                Ok(message)
+            }
+            None => {
+                self.agent.set_preset(None);
+                Ok("Using the agent profile's generation settings.".to_string())
+            }
+        }
+    }
+
     fn update_reasoning_messages(&mut self, output: &AgentOutput) {
         self.reasoning_messages = Self::format_reasoning_messages(output);
+        self.budget_exceeded = output.budget_exceeded;
+        self.policy_violations
+            .extend(output.policy_violations.iter().cloned());
+        if self.policy_violations.len() > MAX_POLICY_VIOLATIONS {
+            let excess = self.policy_violations.len() - MAX_POLICY_VIOLATIONS;
+            self.policy_violations.drain(..excess);
+        }
     }
 
     fn format_reasoning_messages(output: &AgentOutput) -> Vec<String> {
-        let mut lines = Vec::with_capacity(3);
+        let mut lines = Vec::with_capacity(3 + output.policy_violations.len());
+
+        // Surface violations first so users see why an action was refused
+        for violation in output.policy_violations.iter().rev() {
+            lines.push(violation.summary());
+        }
+        if let Some(exceeded) = &output.budget_exceeded {
+            lines.push(format!("Budget: {}", exceeded));
+        }
 
         if let Some(stats) = &output.recall_stats {
             match &stats.strategy {
@@ -1167,6 +1765,16 @@ impl CliState {
             Command::ConfigReload => "Status: reloading configuration".to_string(),
             Command::ConfigShow => "Status: displaying configuration".to_string(),
             Command::PolicyReload => "Status: reloading policies".to_string(),
+            Command::ListModels => "Status: listing available models".to_string(),
+            Command::SwitchModel { model, .. } => {
+                format!("Status: switching to model '{}'", model)
+            }
+            Command::Usage => "Status: showing token usage".to_string(),
+            Command::Budget(BudgetCommand::Show) => "Status: showing step budget".to_string(),
+            Command::Budget(_) => "Status: changing step budget".to_string(),
+            Command::Preset(PresetCommand::Show) => "Status: listing presets".to_string(),
+            Command::Preset(PresetCommand::Use(_)) => "Status: changing preset".to_string(),
+            Command::Preset(PresetCommand::Once { .. }) => "Status: running agent step".to_string(),
             Command::SwitchAgent(name) => {
                 format!("Status: switching to agent '{}'", name)
             }
@@ -1183,6 +1791,14 @@ impl CliState {
             Command::SessionSwitch(id) => {
                 format!("Status: switching to session '{}'", id)
             }
+            Command::Branch(BranchCommand::List) => "Status: listing branches".to_string(),
+            Command::Branch(BranchCommand::New) => "Status: starting a branch".to_string(),
+            Command::Branch(BranchCommand::Switch(id)) => {
+                format!("Status: switching to branch '{}'", id)
+            }
+            Command::Checkpoint(None) => "Status: listing checkpoints".to_string(),
+            Command::Checkpoint(Some(name)) => format!("Status: saving checkpoint '{}'", name),
+            Command::Rollback(name) => format!("Status: rolling back to '{}'", name),
             Command::GraphEnable => "Status: showing graph enable instructions".to_string(),
             Command::GraphDisable => "Status: showing graph disable instructions".to_string(),
             Command::GraphStatus => "Status: showing graph status".to_string(),
@@ -1190,6 +1806,9 @@ impl CliState {
                 format!("Status: inspecting graph (limit {})", limit)
             }
             Command::GraphShow(None) => "Status: inspecting graph".to_string(),
+            Command::GraphExport(export) => {
+                format!("Status: exporting graph as {}", export.format.as_str())
+            }
             Command::GraphClear => "Status: clearing session graph".to_string(),
             Command::SyncList => "Status: listing sync-enabled graphs".to_string(),
             Command::Init(_) => "Status: bootstrapping repository graph".to_string(),
@@ -1332,11 +1951,14 @@ mod tests {
     use super::*;
     use crate::agent::core::{MemoryRecallStats, MemoryRecallStrategy, ToolInvocation};
     use crate::agent::model::TokenUsage;
-    use crate::agent::AgentOutput;
+    use crate::agent::{AgentOutput, PolicyOutcome};
     use crate::config::{
-        AudioConfig, AuthConfig, DatabaseConfig, LoggingConfig, ModelConfig, PluginConfig,
-        SyncConfig, UiConfig,
+        AudioConfig, AuthConfig, DatabaseConfig, EmbeddingsConfig, EventLogConfig, KeymapConfig,
+        LoggingConfig, McpConfig, ModelConfig, OpenApiConfig, PersistenceMode, PluginConfig,
+        PricingConfig, SandboxConfig, SqlConfig, SyncConfig, TelemetryConfig, ToolsConfig,
+        UiConfig, WebFetchConfig,
     };
+    use crate::types::NodeType;
     use serde_json::json;
     use std::collections::HashMap;
     use std::path::PathBuf;
@@ -1376,6 +1998,11 @@ mod tests {
         assert_eq!(parse_command("/config show"), Command::ConfigShow);
         assert_eq!(parse_command("/agents"), Command::ListAgents);
         assert_eq!(parse_command("/list"), Command::ListAgents);
+        assert_eq!(parse_command("/profile"), Command::ListAgents);
+        assert_eq!(
+            parse_command("/profile coder"),
+            Command::SwitchAgent("coder".to_string())
+        );
         assert_eq!(parse_command("/init"), Command::Init(None));
         assert_eq!(
             parse_command("/init --plugins=rust-cargo"),
@@ -1411,6 +2038,118 @@ mod tests {
             parse_command("/spec nested/path/my.spec"),
             Command::RunSpec(PathBuf::from("nested/path/my.spec"))
         );
+        assert_eq!(parse_command("/model"), Command::ListModels);
+        assert_eq!(
+            parse_command("/model llama3:latest"),
+            Command::SwitchModel {
+                provider: None,
+                model: "llama3:latest".into()
+            }
+        );
+        assert_eq!(
+            parse_command("/model Ollama qwen2.5:7b"),
+            Command::SwitchModel {
+                provider: Some("ollama".into()),
+                model: "qwen2.5:7b".into()
+            }
+        );
+        assert_eq!(parse_command("/usage"), Command::Usage);
+        assert_eq!(
+            parse_command("/budget"),
+            Command::Budget(BudgetCommand::Show)
+        );
+        assert_eq!(
+            parse_command("/budget extend"),
+            Command::Budget(BudgetCommand::Extend)
+        );
+        assert_eq!(
+            parse_command("/budget tokens 50000"),
+            Command::Budget(BudgetCommand::Tokens(Some(50_000)))
+        );
+        assert_eq!(
+            parse_command("/budget cost $0.25"),
+            Command::Budget(BudgetCommand::Cost(Some(0.25)))
+        );
+        assert_eq!(
+            parse_command("/budget tools off"),
+            Command::Budget(BudgetCommand::ToolCalls(None))
+        );
+        assert_eq!(parse_command("/budget cost -1"), Command::Help);
+        assert_eq!(parse_command("/budget tokens many"), Command::Help);
+        assert_eq!(
+            parse_command("/preset"),
+            Command::Preset(PresetCommand::Show)
+        );
+        assert_eq!(
+            parse_command("/preset precise"),
+            Command::Preset(PresetCommand::Use(Some("precise".to_string())))
+        );
+        assert_eq!(
+            parse_command("/preset OFF"),
+            Command::Preset(PresetCommand::Use(None))
+        );
+        assert_eq!(
+            parse_command("/preset deep  why is the sky blue?"),
+            Command::Preset(PresetCommand::Once {
+                preset: "deep".to_string(),
+                message: "why is the sky blue?".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_command("/branch"),
+            Command::Branch(BranchCommand::List)
+        );
+        assert_eq!(
+            parse_command("/branch new"),
+            Command::Branch(BranchCommand::New)
+        );
+        assert_eq!(
+            parse_command("/branch switch main.1"),
+            Command::Branch(BranchCommand::Switch("main.1".to_string()))
+        );
+        assert_eq!(parse_command("/branch switch"), Command::Help);
+        assert_eq!(parse_command("/checkpoint"), Command::Checkpoint(None));
+        assert_eq!(
+            parse_command("/checkpoint before refactor"),
+            Command::Checkpoint(Some("before refactor".to_string()))
+        );
+        assert_eq!(
+            parse_command("/rollback before refactor"),
+            Command::Rollback("before refactor".to_string())
+        );
+        assert_eq!(parse_command("/rollback"), Command::Help);
+        assert_eq!(
+            parse_command("/graph export mermaid 42 2 --out=graph.mmd"),
+            Command::GraphExport(GraphExportCommand {
+                format: ExportFormat::Mermaid,
+                selection: Selection::Neighborhood {
+                    node_id: 42,
+                    hops: 2
+                },
+                limit: DEFAULT_EXPORT_LIMIT,
+                out: Some(PathBuf::from("graph.mmd")),
+            })
+        );
+        assert_eq!(
+            parse_command("/graph export dot --type=goal --limit=20"),
+            Command::GraphExport(GraphExportCommand {
+                format: ExportFormat::Dot,
+                selection: Selection::Query {
+                    node_type: Some(NodeType::Goal)
+                },
+                limit: 20,
+                out: None,
+            })
+        );
+        assert_eq!(parse_command("/graph export svg"), Command::Help);
+        assert_eq!(
+            parse_command("/graph export dot --type=goals"),
+            Command::Help
+        );
+        assert_eq!(
+            parse_command("/graph export dot 42 --type=goal"),
+            Command::Help
+        );
         assert_eq!(parse_command("/speak"), Command::SpeechToggle(None));
         assert_eq!(
             parse_command("/speak on"),
@@ -1426,6 +2165,8 @@ mod tests {
             response: String::new(),
             response_message_id: None,
             token_usage: None,
+            cost: None,
+            session_usage: Default::default(),
             tool_invocations: Vec::new(),
             finish_reason: None,
             recall_stats: None,
@@ -1434,6 +2175,8 @@ mod tests {
             reasoning: None,
             reasoning_summary: None,
             graph_debug: None,
+            policy_violations: Vec::new(),
+            budget_exceeded: None,
         };
         let lines = CliState::format_reasoning_messages(&output);
         assert_eq!(
@@ -1461,11 +2204,14 @@ mod tests {
             success: true,
             output: Some("ok".to_string()),
             error: None,
+            data: None,
         };
         let output = AgentOutput {
             response: String::new(),
             response_message_id: None,
             token_usage: None,
+            cost: None,
+            session_usage: Default::default(),
             tool_invocations: vec![invocation],
             finish_reason: Some("stop".to_string()),
             recall_stats: Some(stats),
@@ -1474,6 +2220,8 @@ mod tests {
             reasoning: None,
             reasoning_summary: None,
             graph_debug: None,
+            policy_violations: Vec::new(),
+            budget_exceeded: None,
         };
         let lines = CliState::format_reasoning_messages(&output);
         assert!(lines[0].starts_with("Recall: semantic"));
@@ -1492,6 +2240,8 @@ mod tests {
             response: String::new(),
             response_message_id: None,
             token_usage: Some(usage),
+            cost: None,
+            session_usage: Default::default(),
             tool_invocations: Vec::new(),
             finish_reason: None,
             recall_stats: None,
@@ -1500,11 +2250,44 @@ mod tests {
             reasoning: None,
             reasoning_summary: None,
             graph_debug: None,
+            policy_violations: Vec::new(),
+            budget_exceeded: None,
         };
         let lines = CliState::format_reasoning_messages(&output);
         assert_eq!(lines[2], "Tokens: P 4 C 6 T 10");
     }
 
+    #[test]
+    fn reasoning_messages_lead_with_policy_violations() {
+        let output = AgentOutput {
+            response: String::new(),
+            response_message_id: None,
+            token_usage: None,
+            cost: None,
+            session_usage: Default::default(),
+            tool_invocations: Vec::new(),
+            finish_reason: None,
+            recall_stats: None,
+            run_id: "run-policy".to_string(),
+            next_action: None,
+            reasoning: None,
+            reasoning_summary: None,
+            graph_debug: None,
+            policy_violations: vec![PolicyViolation {
+                rule: "default deny".to_string(),
+                action: "tool_call".to_string(),
+                resource: "bash".to_string(),
+                outcome: PolicyOutcome::Denied,
+                timestamp: chrono::Utc::now(),
+            }],
+            budget_exceeded: None,
+        };
+        let lines = CliState::format_reasoning_messages(&output);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "Policy: denied tool_call 'bash' (default deny)");
+        assert_eq!(lines[1], "Recall: not used");
+    }
+
     // #[tokio::test]
     async fn test_cli_smoke() {
         // Force plain text mode for consistent test output
@@ -1518,7 +2301,14 @@ mod tests {
         agents.insert("test".to_string(), AgentProfile::default());
 
         let config = AppConfig {
-            database: DatabaseConfig { path: db_path },
+            database: DatabaseConfig {
+                path: db_path,
+                persistence: PersistenceMode::File,
+                encrypted_namespaces: Vec::new(),
+                read_connections: 4,
+                busy_timeout_ms: 5000,
+                checkpoint_threshold: None,
+            },
             model: ModelConfig {
                 provider: "mock".into(),
                 model_name: None,
@@ -1526,19 +2316,41 @@ mod tests {
                 embeddings_model: None,
                 api_key_source: None,
                 temperature: 0.7,
+                bedrock: None,
             },
             ui: UiConfig {
                 prompt: "> ".into(),
                 theme: "default".into(),
+                keymap: KeymapConfig::default(),
+                screen_reader: false,
+                mirror: false,
+                presence: false,
             },
             logging: LoggingConfig {
                 level: "info".into(),
             },
             audio: AudioConfig::default(),
             mesh: crate::config::MeshConfig::default(),
+            heartbeat: crate::config::HeartbeatConfig::default(),
+            retention: crate::config::RetentionConfig::default(),
+            graph_maintenance: crate::config::GraphMaintenanceConfig::default(),
+            providers: crate::config::ProvidersConfig::default(),
             plugins: PluginConfig::default(),
+            mcp: McpConfig::default(),
+            openapi: OpenApiConfig::default(),
+            sandbox: SandboxConfig::default(),
+            web_fetch: WebFetchConfig::default(),
+            sql: SqlConfig::default(),
+            tools: ToolsConfig::default(),
             sync: SyncConfig::default(),
             auth: AuthConfig::default(),
+            pricing: PricingConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            vector_store: crate::config::VectorStoreConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            event_log: EventLogConfig::default(),
+            prompts: HashMap::new(),
+            presets: HashMap::new(),
             agents,
             default_agent: Some("test".into()),
         };
@@ -1584,7 +2396,14 @@ mod tests {
         agents.insert("researcher".to_string(), AgentProfile::default());
 
         let config = AppConfig {
-            database: DatabaseConfig { path: db_path },
+            database: DatabaseConfig {
+                path: db_path,
+                persistence: PersistenceMode::File,
+                encrypted_namespaces: Vec::new(),
+                read_connections: 4,
+                busy_timeout_ms: 5000,
+                checkpoint_threshold: None,
+            },
             model: ModelConfig {
                 provider: "mock".into(),
                 model_name: None,
@@ -1592,19 +2411,41 @@ mod tests {
                 embeddings_model: None,
                 api_key_source: None,
                 temperature: 0.7,
+                bedrock: None,
             },
             ui: UiConfig {
                 prompt: "> ".into(),
                 theme: "default".into(),
+                keymap: KeymapConfig::default(),
+                screen_reader: false,
+                mirror: false,
+                presence: false,
             },
             logging: LoggingConfig {
                 level: "info".into(),
             },
             audio: AudioConfig::default(),
             mesh: crate::config::MeshConfig::default(),
+            heartbeat: crate::config::HeartbeatConfig::default(),
+            retention: crate::config::RetentionConfig::default(),
+            graph_maintenance: crate::config::GraphMaintenanceConfig::default(),
+            providers: crate::config::ProvidersConfig::default(),
             plugins: PluginConfig::default(),
+            mcp: McpConfig::default(),
+            openapi: OpenApiConfig::default(),
+            sandbox: SandboxConfig::default(),
+            web_fetch: WebFetchConfig::default(),
+            sql: SqlConfig::default(),
+            tools: ToolsConfig::default(),
             sync: SyncConfig::default(),
             auth: AuthConfig::default(),
+            pricing: PricingConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            vector_store: crate::config::VectorStoreConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            event_log: EventLogConfig::default(),
+            prompts: HashMap::new(),
+            presets: HashMap::new(),
             agents,
             default_agent: Some("coder".into()),
         };
@@ -1638,6 +2479,11 @@ mod tests {
         let config = AppConfig {
             database: DatabaseConfig {
                 path: db_path.clone(),
+                persistence: PersistenceMode::File,
+                encrypted_namespaces: Vec::new(),
+                read_connections: 4,
+                busy_timeout_ms: 5000,
+                checkpoint_threshold: None,
             },
             model: ModelConfig {
                 provider: "mock".into(),
@@ -1646,19 +2492,41 @@ mod tests {
                 embeddings_model: None,
                 api_key_source: None,
                 temperature: 0.8,
+                bedrock: None,
             },
             ui: UiConfig {
                 prompt: "> ".into(),
                 theme: "dark".into(),
+                keymap: KeymapConfig::default(),
+                screen_reader: false,
+                mirror: false,
+                presence: false,
             },
             logging: LoggingConfig {
                 level: "debug".into(),
             },
             audio: AudioConfig::default(),
             mesh: crate::config::MeshConfig::default(),
+            heartbeat: crate::config::HeartbeatConfig::default(),
+            retention: crate::config::RetentionConfig::default(),
+            graph_maintenance: crate::config::GraphMaintenanceConfig::default(),
+            providers: crate::config::ProvidersConfig::default(),
             plugins: PluginConfig::default(),
+            mcp: McpConfig::default(),
+            openapi: OpenApiConfig::default(),
+            sandbox: SandboxConfig::default(),
+            web_fetch: WebFetchConfig::default(),
+            sql: SqlConfig::default(),
+            tools: ToolsConfig::default(),
             sync: SyncConfig::default(),
             auth: AuthConfig::default(),
+            pricing: PricingConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            vector_store: crate::config::VectorStoreConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            event_log: EventLogConfig::default(),
+            prompts: HashMap::new(),
+            presets: HashMap::new(),
             agents,
             default_agent: Some("test".into()),
         };
@@ -1688,7 +2556,14 @@ mod tests {
         agents.insert("test".to_string(), AgentProfile::default());
 
         let config = AppConfig {
-            database: DatabaseConfig { path: db_path },
+            database: DatabaseConfig {
+                path: db_path,
+                persistence: PersistenceMode::File,
+                encrypted_namespaces: Vec::new(),
+                read_connections: 4,
+                busy_timeout_ms: 5000,
+                checkpoint_threshold: None,
+            },
             model: ModelConfig {
                 provider: "mock".into(),
                 model_name: None,
@@ -1696,19 +2571,41 @@ mod tests {
                 embeddings_model: None,
                 api_key_source: None,
                 temperature: 0.7,
+                bedrock: None,
             },
             ui: UiConfig {
                 prompt: "> ".into(),
                 theme: "default".into(),
+                keymap: KeymapConfig::default(),
+                screen_reader: false,
+                mirror: false,
+                presence: false,
             },
             logging: LoggingConfig {
                 level: "info".into(),
             },
             audio: AudioConfig::default(),
             mesh: crate::config::MeshConfig::default(),
+            heartbeat: crate::config::HeartbeatConfig::default(),
+            retention: crate::config::RetentionConfig::default(),
+            graph_maintenance: crate::config::GraphMaintenanceConfig::default(),
+            providers: crate::config::ProvidersConfig::default(),
             plugins: PluginConfig::default(),
+            mcp: McpConfig::default(),
+            openapi: OpenApiConfig::default(),
+            sandbox: SandboxConfig::default(),
+            web_fetch: WebFetchConfig::default(),
+            sql: SqlConfig::default(),
+            tools: ToolsConfig::default(),
             sync: SyncConfig::default(),
             auth: AuthConfig::default(),
+            pricing: PricingConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            vector_store: crate::config::VectorStoreConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            event_log: EventLogConfig::default(),
+            prompts: HashMap::new(),
+            presets: HashMap::new(),
             agents,
             default_agent: Some("test".into()),
         };
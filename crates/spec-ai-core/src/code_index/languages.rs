@@ -0,0 +1,224 @@
+//! Languages the symbol index understands
+//!
+//! Each language has a tree-sitter grammar and one query whose patterns
+//! capture a symbol's `@name` together with either `@definition.<kind>` for
+//! the whole definition or `@reference.call` for a call site. Other captures
+//! only serve predicates.
+
+use std::path::Path;
+use std::sync::OnceLock;
+use tree_sitter::{Language, Node, Query};
+
+const RUST_QUERY: &str = r#"
+(struct_item name: (type_identifier) @name) @definition.struct
+(union_item name: (type_identifier) @name) @definition.struct
+(enum_item name: (type_identifier) @name) @definition.enum
+(trait_item name: (type_identifier) @name) @definition.trait
+(type_item name: (type_identifier) @name) @definition.type
+(impl_item type: [
+    (type_identifier) @name
+    (generic_type type: (type_identifier) @name)
+    (scoped_type_identifier name: (type_identifier) @name)
+]) @definition.impl
+(function_item name: (identifier) @name) @definition.function
+(function_signature_item name: (identifier) @name) @definition.function
+(mod_item name: (identifier) @name) @definition.module
+(macro_definition name: (identifier) @name) @definition.macro
+(const_item name: (identifier) @name) @definition.const
+(static_item name: (identifier) @name) @definition.const
+
+(call_expression function: [
+    (identifier) @name
+    (field_expression field: (field_identifier) @name)
+    (scoped_identifier name: (identifier) @name)
+    (generic_function function: [
+        (identifier) @name
+        (field_expression field: (field_identifier) @name)
+        (scoped_identifier name: (identifier) @name)
+    ])
+]) @reference.call
+(macro_invocation macro: (identifier) @name) @reference.call
+
+; Macro arguments, as in `tokio::select!`, are only tokens to the grammar
+(token_tree
+    (identifier) @name @reference.call
+    .
+    (token_tree) @arguments
+    (#match? @arguments "^\\("))
+"#;
+
+const PYTHON_QUERY: &str = r#"
+(class_definition name: (identifier) @name) @definition.class
+(function_definition name: (identifier) @name) @definition.function
+
+(call function: [
+    (identifier) @name
+    (attribute attribute: (identifier) @name)
+]) @reference.call
+"#;
+
+const JAVASCRIPT_QUERY: &str = r#"
+(class_declaration name: (_) @name) @definition.class
+(function_declaration name: (identifier) @name) @definition.function
+(generator_function_declaration name: (identifier) @name) @definition.function
+(method_definition name: (property_identifier) @name) @definition.method
+(variable_declarator
+    name: (identifier) @name
+    value: [(arrow_function) (function_expression)]) @definition.function
+
+(call_expression function: [
+    (identifier) @name
+    (member_expression property: (property_identifier) @name)
+]) @reference.call
+(new_expression constructor: (identifier) @name) @reference.call
+"#;
+
+/// Added to [`JAVASCRIPT_QUERY`] for TypeScript and TSX
+const TYPESCRIPT_QUERY: &str = r#"
+(abstract_class_declaration name: (type_identifier) @name) @definition.class
+(interface_declaration name: (type_identifier) @name) @definition.interface
+(type_alias_declaration name: (type_identifier) @name) @definition.type
+(enum_declaration name: (identifier) @name) @definition.enum
+(function_signature name: (identifier) @name) @definition.function
+"#;
+
+const GO_QUERY: &str = r#"
+(function_declaration name: (identifier) @name) @definition.function
+(method_declaration name: (field_identifier) @name) @definition.method
+(type_spec name: (type_identifier) @name type: (struct_type)) @definition.struct
+(type_spec name: (type_identifier) @name type: (interface_type)) @definition.interface
+(type_spec name: (type_identifier) @name) @definition.type
+(const_spec name: (identifier) @name) @definition.const
+
+(call_expression function: [
+    (identifier) @name
+    (selector_expression field: (field_identifier) @name)
+]) @reference.call
+"#;
+
+/// Nodes that define a function or method; calls inside one are attributed
+/// to it, and a container is not looked for beyond one
+const FUNCTION_NODES: &[&str] = &[
+    "function_item",
+    "function_definition",
+    "function_declaration",
+    "generator_function_declaration",
+    "method_definition",
+    "method_declaration",
+    "arrow_function",
+    "function_expression",
+];
+
+/// Nodes whose name qualifies the definitions inside them
+const CONTAINER_NODES: &[&str] = &[
+    "impl_item",
+    "trait_item",
+    "class_definition",
+    "class_declaration",
+    "abstract_class_declaration",
+    "class",
+    "interface_declaration",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lang {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+/// A language's grammar and compiled query
+pub struct Grammar {
+    pub language: Language,
+    pub query: Query,
+}
+
+impl Lang {
+    /// Language of a source file, by extension
+    pub fn from_path(path: &Path) -> Option<Lang> {
+        let lang = match path.extension()?.to_str()? {
+            "rs" => Lang::Rust,
+            "py" | "pyi" => Lang::Python,
+            "js" | "jsx" | "mjs" | "cjs" => Lang::JavaScript,
+            "ts" | "mts" | "cts" => Lang::TypeScript,
+            "tsx" => Lang::Tsx,
+            "go" => Lang::Go,
+            _ => return None,
+        };
+        Some(lang)
+    }
+
+    /// Grammar and query, compiled on first use
+    pub fn grammar(self) -> &'static Grammar {
+        static GRAMMARS: [OnceLock<Grammar>; 6] = [const { OnceLock::new() }; 6];
+        GRAMMARS[self as usize].get_or_init(|| {
+            let (language, query): (Language, String) = match self {
+                Lang::Rust => (tree_sitter_rust::LANGUAGE.into(), RUST_QUERY.into()),
+                Lang::Python => (tree_sitter_python::LANGUAGE.into(), PYTHON_QUERY.into()),
+                Lang::JavaScript => (
+                    tree_sitter_javascript::LANGUAGE.into(),
+                    JAVASCRIPT_QUERY.into(),
+                ),
+                Lang::TypeScript => (
+                    tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+                    format!("{}{}", JAVASCRIPT_QUERY, TYPESCRIPT_QUERY),
+                ),
+                Lang::Tsx => (
+                    tree_sitter_typescript::LANGUAGE_TSX.into(),
+                    format!("{}{}", JAVASCRIPT_QUERY, TYPESCRIPT_QUERY),
+                ),
+                Lang::Go => (tree_sitter_go::LANGUAGE.into(), GO_QUERY.into()),
+            };
+            let query = Query::new(&language, &query)
+                .unwrap_or_else(|err| panic!("invalid {:?} symbol query: {}", self, err));
+            Grammar { language, query }
+        })
+    }
+}
+
+pub fn is_function(node: Node) -> bool {
+    FUNCTION_NODES.contains(&node.kind())
+}
+
+/// Name of the type, class or trait a definition sits in, if any
+pub fn container(node: Node, source: &[u8]) -> Option<String> {
+    // Go declares methods outside their type, naming it in the receiver
+    if node.kind() == "method_declaration" {
+        let receiver = node.child_by_field_name("receiver")?;
+        let text = receiver.utf8_text(source).ok()?;
+        let name = text
+            .trim_matches(|c| c == '(' || c == ')')
+            .split_whitespace()
+            .last()?
+            .trim_start_matches('*');
+        return Some(strip_generics(name).to_string());
+    }
+
+    let mut parent = node.parent();
+    while let Some(ancestor) = parent {
+        if is_function(ancestor) {
+            return None;
+        }
+        if CONTAINER_NODES.contains(&ancestor.kind()) {
+            let field = if ancestor.kind() == "impl_item" {
+                "type"
+            } else {
+                "name"
+            };
+            let name = ancestor
+                .child_by_field_name(field)?
+                .utf8_text(source)
+                .ok()?;
+            return Some(strip_generics(name).to_string());
+        }
+        parent = ancestor.parent();
+    }
+    None
+}
+
+fn strip_generics(name: &str) -> &str {
+    name.split(['<', '[']).next().unwrap_or(name).trim()
+}
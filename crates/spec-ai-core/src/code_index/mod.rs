@@ -0,0 +1,622 @@
+//! Symbol Index for Code Search
+//!
+//! Source files under a root are parsed with tree-sitter to find where
+//! functions, methods, types, traits and impls are defined and where
+//! functions are called. `code_search` answers "definition of X" and
+//! "callers of X" from this index instead of from text matches, so results
+//! point at declarations rather than at every line mentioning a name.
+//!
+//! The index is kept per root and refreshed on each query; only files whose
+//! size or modification time changed are parsed again. Calls are matched by
+//! the called name alone, as resolving receivers and imports would need a
+//! type checker, so `callers of Foo::new` lists every `new` call.
+
+mod languages;
+
+pub use languages::Lang;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tree_sitter::{Parser, QueryCursor, StreamingIterator};
+
+/// Directories never indexed, on top of hidden ones
+pub const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "vendor", "dist", "__pycache__"];
+
+/// Larger files are assumed to be generated and skipped
+pub const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Most files indexed under one root
+pub const MAX_FILES: usize = 20_000;
+
+/// Longest snippet returned for a symbol or call, in characters
+const MAX_SNIPPET_CHARS: usize = 200;
+
+/// What a symbol is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Interface,
+    Class,
+    Type,
+    Impl,
+    Module,
+    Macro,
+    Const,
+}
+
+impl SymbolKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        let kind = match name.to_ascii_lowercase().as_str() {
+            "function" | "fn" | "func" => SymbolKind::Function,
+            "method" => SymbolKind::Method,
+            "struct" => SymbolKind::Struct,
+            "enum" => SymbolKind::Enum,
+            "trait" => SymbolKind::Trait,
+            "interface" => SymbolKind::Interface,
+            "class" => SymbolKind::Class,
+            "type" => SymbolKind::Type,
+            "impl" => SymbolKind::Impl,
+            "module" | "mod" => SymbolKind::Module,
+            "macro" => SymbolKind::Macro,
+            "const" | "static" => SymbolKind::Const,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// A definition found in a source file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Path relative to the indexed root, with `/` separators
+    pub path: String,
+    /// 1-based line the definition starts on
+    pub line: usize,
+    pub end_line: usize,
+    /// Type, class or trait the definition sits in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// First line of the definition
+    pub snippet: String,
+}
+
+impl Symbol {
+    /// Name with its container, as in `Parser::parse`
+    pub fn qualified_name(&self) -> String {
+        match &self.container {
+            Some(container) if self.kind != SymbolKind::Impl => {
+                format!("{}::{}", container, self.name)
+            }
+            _ => self.name.clone(),
+        }
+    }
+}
+
+/// A call site found in a source file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Call {
+    /// Name of the called function or method
+    pub name: String,
+    pub path: String,
+    pub line: usize,
+    /// Qualified name of the function the call is in; None at top level
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caller: Option<String>,
+    /// The line holding the call
+    pub snippet: String,
+}
+
+/// Symbols and calls of one parsed file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileSymbols {
+    pub symbols: Vec<Symbol>,
+    pub calls: Vec<Call>,
+}
+
+/// Find the symbols and calls in `source`, reporting them under `path`
+pub fn parse_source(lang: Lang, path: &str, source: &str) -> Result<FileSymbols> {
+    let grammar = lang.grammar();
+    let mut parser = Parser::new();
+    parser
+        .set_language(&grammar.language)
+        .with_context(|| format!("loading the {:?} grammar", lang))?;
+    let tree = parser
+        .parse(source, None)
+        .with_context(|| format!("parsing {}", path))?;
+
+    let bytes = source.as_bytes();
+    let lines: Vec<&str> = source.lines().collect();
+    let snippet = |row: usize| clip(lines.get(row).map_or("", |line| line.trim()));
+    let names = grammar.query.capture_names();
+
+    // Several patterns can match one definition, as with Go types; the first
+    // pattern is the most specific
+    let mut definitions: HashMap<usize, (usize, Symbol, tree_sitter::Range)> = HashMap::new();
+    let mut references = Vec::new();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&grammar.query, tree.root_node(), bytes);
+    while let Some(m) = matches.next() {
+        let mut name = None;
+        let mut item = None;
+        for capture in m.captures {
+            match names[capture.index as usize] {
+                "name" => name = Some(capture.node),
+                other if other == "reference.call" || other.starts_with("definition.") => {
+                    item = Some((other, capture.node))
+                }
+                _ => {}
+            }
+        }
+        let (Some(name), Some((capture, node))) = (name, item) else {
+            continue;
+        };
+        let Ok(text) = name.utf8_text(bytes) else {
+            continue;
+        };
+
+        if capture == "reference.call" {
+            references.push((text.to_string(), node));
+            continue;
+        }
+        let Some(mut kind) = capture
+            .strip_prefix("definition.")
+            .and_then(SymbolKind::parse)
+        else {
+            continue;
+        };
+        let container = languages::container(node, bytes);
+        if kind == SymbolKind::Function && container.is_some() {
+            kind = SymbolKind::Method;
+        }
+        let symbol = Symbol {
+            name: text.to_string(),
+            kind,
+            path: path.to_string(),
+            line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            container,
+            snippet: snippet(node.start_position().row),
+        };
+        let key = name.start_byte();
+        if definitions
+            .get(&key)
+            .is_none_or(|(pattern, _, _)| m.pattern_index < *pattern)
+        {
+            definitions.insert(key, (m.pattern_index, symbol, node.range()));
+        }
+    }
+
+    let mut definitions: Vec<(Symbol, tree_sitter::Range)> = definitions
+        .into_values()
+        .map(|(_, symbol, range)| (symbol, range))
+        .collect();
+    definitions.sort_by_key(|(symbol, _)| symbol.line);
+
+    let calls = references
+        .into_iter()
+        .map(|(name, node)| {
+            // The innermost function holding the call, found by the range of
+            // its definition, which for `const f = () => ...` is the declarator
+            let mut caller = None;
+            let mut parent = node.parent();
+            while let Some(ancestor) = parent {
+                let range = ancestor.range();
+                if let Some((symbol, _)) = definitions.iter().find(|(symbol, r)| {
+                    *r == range && matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method)
+                }) {
+                    caller = Some(symbol.qualified_name());
+                    break;
+                }
+                parent = ancestor.parent();
+            }
+            Call {
+                name,
+                path: path.to_string(),
+                line: node.start_position().row + 1,
+                caller,
+                snippet: snippet(node.start_position().row),
+            }
+        })
+        .collect();
+
+    Ok(FileSymbols {
+        symbols: definitions.into_iter().map(|(symbol, _)| symbol).collect(),
+        calls,
+    })
+}
+
+fn clip(text: &str) -> String {
+    match text.char_indices().nth(MAX_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[derive(Debug)]
+struct IndexedFile {
+    stamp: (SystemTime, u64),
+    symbols: FileSymbols,
+}
+
+/// What a refresh found
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RefreshStats {
+    pub files: usize,
+    /// Files parsed because they were new or changed
+    pub parsed: usize,
+    /// More files were found than [`MAX_FILES`]
+    pub truncated: bool,
+}
+
+/// Symbols of every supported source file under a root
+#[derive(Debug)]
+pub struct SymbolIndex {
+    root: PathBuf,
+    files: HashMap<String, IndexedFile>,
+}
+
+impl SymbolIndex {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Bring the index up to date with the files on disk
+    pub fn refresh(&mut self) -> Result<RefreshStats> {
+        let mut stats = RefreshStats::default();
+        let mut seen = HashMap::new();
+        let walker = walkdir::WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !skipped(entry));
+        for entry in walker.filter_map(|entry| entry.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Some(lang) = Lang::from_path(entry.path()) else {
+                continue;
+            };
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.len() > MAX_FILE_BYTES {
+                continue;
+            }
+            if seen.len() >= MAX_FILES {
+                stats.truncated = true;
+                break;
+            }
+            let Ok(relative) = entry.path().strip_prefix(&self.root) else {
+                continue;
+            };
+            let relative = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let stamp = (
+                metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                metadata.len(),
+            );
+            seen.insert(relative, (entry.into_path(), lang, stamp));
+        }
+
+        self.files.retain(|path, _| seen.contains_key(path));
+        for (relative, (path, lang, stamp)) in seen {
+            if self.files.get(&relative).is_some_and(|f| f.stamp == stamp) {
+                continue;
+            }
+            // Unreadable or non-UTF-8 files are left out
+            let Ok(source) = std::fs::read_to_string(&path) else {
+                self.files.remove(&relative);
+                continue;
+            };
+            let symbols = parse_source(lang, &relative, &source)?;
+            self.files.insert(relative, IndexedFile { stamp, symbols });
+            stats.parsed += 1;
+        }
+        stats.files = self.files.len();
+        Ok(stats)
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.files.values().flat_map(|f| &f.symbols.symbols)
+    }
+
+    pub fn calls(&self) -> impl Iterator<Item = &Call> {
+        self.files.values().flat_map(|f| &f.symbols.calls)
+    }
+
+    /// Definitions named `name`, which may be qualified as `Type::name`
+    pub fn definitions(&self, name: &str) -> Vec<&Symbol> {
+        let (container, name) = split_qualified(name);
+        let mut found: Vec<&Symbol> = self
+            .symbols()
+            .filter(|s| s.name == name)
+            .filter(|s| container.is_none() || s.container.as_deref() == container)
+            .collect();
+        sort_by_location(&mut found);
+        found
+    }
+
+    /// Calls to functions or methods named like `name`; a `Type::` prefix
+    /// is ignored, as calls are matched by name
+    pub fn callers(&self, name: &str) -> Vec<&Call> {
+        let (_, name) = split_qualified(name);
+        let mut found: Vec<&Call> = self.calls().filter(|c| c.name == name).collect();
+        found.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        found
+    }
+
+    /// Symbols whose name contains `text`, ignoring case; exact matches come
+    /// first, then prefixes, then the rest
+    pub fn search(&self, text: &str, kind: Option<SymbolKind>) -> Vec<&Symbol> {
+        let (_, text) = split_qualified(text);
+        let needle = text.to_lowercase();
+        let mut found: Vec<(u8, &Symbol)> = self
+            .symbols()
+            .filter(|s| kind.is_none_or(|kind| s.kind == kind))
+            .filter_map(|s| {
+                let name = s.name.to_lowercase();
+                let rank = if name == needle {
+                    0
+                } else if name.starts_with(&needle) {
+                    1
+                } else if name.contains(&needle) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, s))
+            })
+            .collect();
+        found.sort_by(|(ra, a), (rb, b)| (ra, &a.path, a.line).cmp(&(rb, &b.path, b.line)));
+        found.into_iter().map(|(_, s)| s).collect()
+    }
+}
+
+fn skipped(entry: &walkdir::DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    entry.file_type().is_dir() && (name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()))
+}
+
+fn sort_by_location(symbols: &mut [&Symbol]) {
+    symbols.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+}
+
+/// `Type::name` or `Type.name` split into its container and name
+fn split_qualified(name: &str) -> (Option<&str>, &str) {
+    let name = name.trim();
+    match name.rsplit_once("::").or_else(|| name.rsplit_once('.')) {
+        Some((container, last)) if !container.is_empty() && !last.is_empty() => {
+            let container = container.rsplit("::").next().unwrap_or(container);
+            (Some(container), last)
+        }
+        _ => (None, name),
+    }
+}
+
+/// A query `code_search` can answer from the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolQuery {
+    Definition(String),
+    Callers(String),
+    Search(String),
+}
+
+impl SymbolQuery {
+    /// Read "definition of X", "callers of X", or a bare identifier to look
+    /// up; other text is left to semantic search
+    pub fn parse(query: &str) -> Option<Self> {
+        const DEFINITION: &[&str] = &["definition of ", "definitions of ", "where is "];
+        const CALLERS: &[&str] = &["callers of ", "calls to ", "who calls "];
+
+        let query = query.trim();
+        let lower = query.to_lowercase();
+        let rest = |prefixes: &[&str]| {
+            prefixes.iter().find_map(|prefix| {
+                lower
+                    .starts_with(prefix)
+                    .then(|| identifier(&query[prefix.len()..]))
+                    .flatten()
+            })
+        };
+        if let Some(name) = rest(DEFINITION) {
+            return Some(SymbolQuery::Definition(name));
+        }
+        if let Some(name) = rest(CALLERS) {
+            return Some(SymbolQuery::Callers(name));
+        }
+        identifier(query).map(SymbolQuery::Search)
+    }
+}
+
+/// `text` as a possibly qualified identifier, without backticks or a
+/// trailing `()` or `?`
+fn identifier(text: &str) -> Option<String> {
+    let text = text
+        .trim()
+        .trim_end_matches('?')
+        .trim_matches('`')
+        .trim_end_matches("()");
+    let valid = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '.' | '$'));
+    valid.then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = r#"
+pub struct Parser<T> {
+    input: T,
+}
+
+impl<T> Parser<T> {
+    pub fn new(input: T) -> Self {
+        Self { input }
+    }
+
+    pub fn parse(&self) -> Result<()> {
+        let tokens = tokenize(&self.input);
+        helpers::check(tokens)
+    }
+}
+
+fn tokenize<T>(input: &T) -> Vec<String> {
+    Vec::new()
+}
+
+fn main() {
+    let parser = Parser::new("x");
+    parser.parse().unwrap();
+    println!("{:?}", tokenize(&parser));
+}
+"#;
+
+    fn names(symbols: &[&Symbol]) -> Vec<String> {
+        symbols
+            .iter()
+            .map(|s| format!("{:?} {}", s.kind, s.qualified_name()))
+            .collect()
+    }
+
+    #[test]
+    fn rust_definitions_and_callers() {
+        let file = parse_source(Lang::Rust, "src/parser.rs", RUST).unwrap();
+        let symbols: Vec<&Symbol> = file.symbols.iter().collect();
+        assert_eq!(
+            names(&symbols),
+            vec![
+                "Struct Parser",
+                "Impl Parser",
+                "Method Parser::new",
+                "Method Parser::parse",
+                "Function tokenize",
+                "Function main",
+            ]
+        );
+        let parse = &file.symbols[3];
+        assert_eq!((parse.line, parse.end_line), (11, 14));
+        assert_eq!(parse.snippet, "pub fn parse(&self) -> Result<()> {");
+
+        let calls: Vec<(&str, Option<&str>, usize)> = file
+            .calls
+            .iter()
+            .map(|c| (c.name.as_str(), c.caller.as_deref(), c.line))
+            .collect();
+        assert!(calls.contains(&("tokenize", Some("Parser::parse"), 12)));
+        assert!(calls.contains(&("check", Some("Parser::parse"), 13)));
+        assert!(calls.contains(&("new", Some("main"), 22)));
+        assert!(calls.contains(&("parse", Some("main"), 23)));
+        assert!(calls.contains(&("println", Some("main"), 24)));
+        // Inside macro arguments
+        assert!(calls.contains(&("tokenize", Some("main"), 24)));
+    }
+
+    #[test]
+    fn other_languages_find_definitions_and_calls() {
+        let python = "class Cache:\n    def get(self, key):\n        return load(key)\n\ndef load(key):\n    pass\n";
+        let file = parse_source(Lang::Python, "cache.py", python).unwrap();
+        let symbols: Vec<&Symbol> = file.symbols.iter().collect();
+        assert_eq!(
+            names(&symbols),
+            vec!["Class Cache", "Method Cache::get", "Function load"]
+        );
+        assert_eq!(file.calls[0].caller.as_deref(), Some("Cache::get"));
+
+        let typescript = "interface Store { get(k: string): string }\nclass Memory implements Store {\n  get(k: string) { return fetchValue(k); }\n}\nconst fetchValue = (k: string) => k;\n";
+        let file = parse_source(Lang::TypeScript, "store.ts", typescript).unwrap();
+        let symbols: Vec<&Symbol> = file.symbols.iter().collect();
+        assert_eq!(
+            names(&symbols),
+            vec![
+                "Interface Store",
+                "Class Memory",
+                "Method Memory::get",
+                "Function fetchValue"
+            ]
+        );
+        assert_eq!(file.calls[0].caller.as_deref(), Some("Memory::get"));
+
+        let go = "package main\n\ntype Server struct{}\n\nfunc (s *Server) Start() { listen() }\n\nfunc listen() {}\n";
+        let file = parse_source(Lang::Go, "server.go", go).unwrap();
+        let symbols: Vec<&Symbol> = file.symbols.iter().collect();
+        assert_eq!(
+            names(&symbols),
+            vec!["Struct Server", "Method Server::Start", "Function listen"]
+        );
+        assert_eq!(file.calls[0].caller.as_deref(), Some("Server::Start"));
+    }
+
+    #[test]
+    fn index_follows_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("src/parser.rs"), RUST).unwrap();
+        std::fs::write(dir.path().join("target/gen.rs"), "fn tokenize() {}").unwrap();
+
+        let mut index = SymbolIndex::new(dir.path());
+        let stats = index.refresh().unwrap();
+        assert_eq!((stats.files, stats.parsed), (1, 1));
+        assert_eq!(index.definitions("tokenize").len(), 1);
+        assert_eq!(index.definitions("Parser::parse")[0].path, "src/parser.rs");
+        assert!(index.definitions("Other::parse").is_empty());
+        // Matched by name, so `Vec::new` counts too
+        let callers: Vec<Option<&str>> = index
+            .callers("Parser::new")
+            .iter()
+            .map(|c| c.caller.as_deref())
+            .collect();
+        assert_eq!(callers, vec![Some("tokenize"), Some("main")]);
+        assert_eq!(
+            names(&index.search("pars", None)),
+            vec!["Struct Parser", "Impl Parser", "Method Parser::parse"]
+        );
+        assert_eq!(index.search("pars", Some(SymbolKind::Method)).len(), 1);
+
+        assert_eq!(index.refresh().unwrap().parsed, 0);
+        std::fs::write(dir.path().join("src/lib.rs"), "pub fn tokenize() {}\n").unwrap();
+        std::fs::remove_file(dir.path().join("src/parser.rs")).unwrap();
+        let stats = index.refresh().unwrap();
+        assert_eq!((stats.files, stats.parsed), (1, 1));
+        assert_eq!(index.definitions("tokenize")[0].path, "src/lib.rs");
+        assert!(index.definitions("Parser").is_empty());
+    }
+
+    #[test]
+    fn queries_are_recognized() {
+        assert_eq!(
+            SymbolQuery::parse("definition of `Parser::parse`"),
+            Some(SymbolQuery::Definition("Parser::parse".to_string()))
+        );
+        assert_eq!(
+            SymbolQuery::parse("Callers of tokenize()"),
+            Some(SymbolQuery::Callers("tokenize".to_string()))
+        );
+        assert_eq!(
+            SymbolQuery::parse("AgentCore"),
+            Some(SymbolQuery::Search("AgentCore".to_string()))
+        );
+        assert_eq!(SymbolQuery::parse("where tokens get parsed"), None);
+    }
+}
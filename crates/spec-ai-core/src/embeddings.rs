@@ -1,9 +1,23 @@
+use crate::config::EmbeddingsConfig;
 use anyhow::{anyhow, Context, Result};
 use async_openai::{
     config::OpenAIConfig, types::CreateEmbeddingRequestArgs, Client as OpenAIClient,
 };
 use async_trait::async_trait;
-use std::sync::Arc;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+#[cfg(feature = "local-embeddings")]
+pub mod local;
+pub mod pipeline;
+
+/// Model `[embeddings] provider = "local"` runs when no embeddings model is set.
+pub const DEFAULT_LOCAL_MODEL: &str = "bge-small-en-v1.5";
 
 /// Trait that describes an embeddings-capable service.
 #[async_trait]
@@ -17,6 +31,8 @@ pub trait EmbeddingsService: Send + Sync + 'static {
 pub struct EmbeddingsClient {
     model: String,
     service: Arc<dyn EmbeddingsService>,
+    /// Set when requests are batched, to report batch sizes
+    batching: Option<BatchingEmbeddingsService>,
 }
 
 impl EmbeddingsClient {
@@ -45,7 +61,31 @@ impl EmbeddingsClient {
         Self {
             model: model.into(),
             service,
+            batching: None,
+        }
+    }
+
+    /// Coalesce concurrent requests into batch calls as `config` describes.
+    ///
+    /// Clones of the returned client share its batches, so build it once and
+    /// hand out clones. A zero batch window leaves requests unbatched.
+    pub fn with_batching(mut self, config: &EmbeddingsConfig) -> Self {
+        if config.batch_window_ms == 0 || self.batching.is_some() {
+            return self;
         }
+        let batching = BatchingEmbeddingsService::new(
+            Arc::clone(&self.service),
+            Duration::from_millis(config.batch_window_ms),
+            config.max_batch_size,
+        );
+        self.service = Arc::new(batching.clone());
+        self.batching = Some(batching);
+        self
+    }
+
+    /// Batch sizes achieved so far, if requests are batched.
+    pub fn batch_stats(&self) -> Option<EmbeddingBatchStats> {
+        self.batching.as_ref().map(BatchingEmbeddingsService::stats)
     }
 
     /// Ask the underlying service for embeddings for a batch of inputs.
@@ -75,6 +115,248 @@ impl EmbeddingsClient {
     }
 }
 
+/// How well embedding requests have been coalesced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingBatchStats {
+    /// Requests received
+    pub requests: u64,
+    /// Inputs across all requests
+    pub inputs: u64,
+    /// Calls made to the provider
+    pub batches: u64,
+    /// Most inputs sent in one call
+    pub largest_batch: usize,
+}
+
+impl EmbeddingBatchStats {
+    /// Average number of inputs per provider call
+    pub fn mean_batch_size(&self) -> f64 {
+        if self.batches == 0 {
+            0.0
+        } else {
+            self.inputs as f64 / self.batches as f64
+        }
+    }
+
+    fn record(&mut self, requests: usize, inputs: usize) {
+        self.requests += requests as u64;
+        self.inputs += inputs as u64;
+        self.batches += 1;
+        self.largest_batch = self.largest_batch.max(inputs);
+    }
+}
+
+impl fmt::Display for EmbeddingBatchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requests with {} inputs in {} batches (mean {:.1}, largest {})",
+            self.requests,
+            self.inputs,
+            self.batches,
+            self.mean_batch_size(),
+            self.largest_batch
+        )
+    }
+}
+
+type BatchReply = oneshot::Sender<std::result::Result<Vec<Vec<f32>>, String>>;
+
+struct PendingRequest {
+    model: String,
+    inputs: Vec<String>,
+    reply: BatchReply,
+}
+
+#[derive(Default)]
+struct BatchQueue {
+    pending: Vec<PendingRequest>,
+    inputs: usize,
+    /// Bumped whenever the queue is taken, so a stale timer leaves the next batch alone
+    generation: u64,
+    timer_armed: bool,
+}
+
+impl BatchQueue {
+    fn take(&mut self) -> Vec<PendingRequest> {
+        self.generation += 1;
+        self.timer_armed = false;
+        self.inputs = 0;
+        std::mem::take(&mut self.pending)
+    }
+}
+
+struct BatchShared {
+    inner: Arc<dyn EmbeddingsService>,
+    window: Duration,
+    max_batch: usize,
+    queue: Mutex<BatchQueue>,
+    stats: Mutex<EmbeddingBatchStats>,
+}
+
+impl BatchShared {
+    /// Send the taken requests, one call per model and at most `max_batch` inputs each
+    async fn send(&self, pending: Vec<PendingRequest>) {
+        let mut batches: Vec<(String, Vec<PendingRequest>, usize)> = Vec::new();
+        for request in pending {
+            let size = request.inputs.len();
+            let open = batches.iter_mut().rev().find(|(model, _, inputs)| {
+                *model == request.model && inputs + size <= self.max_batch
+            });
+            match open {
+                Some((_, requests, inputs)) => {
+                    *inputs += size;
+                    requests.push(request);
+                }
+                None => batches.push((request.model.clone(), vec![request], size)),
+            }
+        }
+        join_all(
+            batches
+                .into_iter()
+                .map(|(model, requests, _)| self.send_batch(model, requests)),
+        )
+        .await;
+    }
+
+    async fn send_batch(&self, model: String, requests: Vec<PendingRequest>) {
+        let mut inputs = Vec::new();
+        let mut replies = Vec::with_capacity(requests.len());
+        for request in requests {
+            replies.push((request.inputs.len(), request.reply));
+            inputs.extend(request.inputs);
+        }
+        let total = inputs.len();
+        lock(&self.stats).record(replies.len(), total);
+        debug!(
+            "Sending {} embedding requests to '{}' as one batch of {} inputs",
+            replies.len(),
+            model,
+            total
+        );
+
+        let result = self.inner.create_embeddings(&model, inputs).await;
+        if replies.len() == 1 {
+            let (_, reply) = replies.pop().expect("one reply");
+            let _ = reply.send(result.map_err(|err| format!("{:#}", err)));
+            return;
+        }
+        match result {
+            Ok(embeddings) if embeddings.len() == total => {
+                let mut embeddings = embeddings.into_iter();
+                for (size, reply) in replies {
+                    let _ = reply.send(Ok(embeddings.by_ref().take(size).collect()));
+                }
+            }
+            outcome => {
+                let message = match outcome {
+                    Ok(embeddings) => format!(
+                        "Embeddings service returned {} embeddings for {} inputs",
+                        embeddings.len(),
+                        total
+                    ),
+                    Err(err) => format!("{:#}", err),
+                };
+                for (_, reply) in replies {
+                    let _ = reply.send(Err(message.clone()));
+                }
+            }
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Embeddings service that coalesces concurrent requests into batch calls.
+///
+/// A request waits up to the batch window for others to join it; a batch is
+/// sent as soon as it holds `max_batch` inputs. Requests that fill a batch on
+/// their own are sent straight away. Callers must run on a Tokio runtime.
+#[derive(Clone)]
+pub struct BatchingEmbeddingsService {
+    shared: Arc<BatchShared>,
+}
+
+impl BatchingEmbeddingsService {
+    pub fn new(inner: Arc<dyn EmbeddingsService>, window: Duration, max_batch: usize) -> Self {
+        Self {
+            shared: Arc::new(BatchShared {
+                inner,
+                window,
+                max_batch: max_batch.max(1),
+                queue: Mutex::new(BatchQueue::default()),
+                stats: Mutex::new(EmbeddingBatchStats::default()),
+            }),
+        }
+    }
+
+    /// Counts since the service was created
+    pub fn stats(&self) -> EmbeddingBatchStats {
+        *lock(&self.shared.stats)
+    }
+}
+
+#[async_trait]
+impl EmbeddingsService for BatchingEmbeddingsService {
+    async fn create_embeddings(&self, model: &str, inputs: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let shared = &self.shared;
+        if inputs.len() >= shared.max_batch {
+            lock(&shared.stats).record(1, inputs.len());
+            return shared.inner.create_embeddings(model, inputs).await;
+        }
+
+        let (reply, response) = oneshot::channel();
+        let full = {
+            let mut queue = lock(&shared.queue);
+            queue.inputs += inputs.len();
+            queue.pending.push(PendingRequest {
+                model: model.to_string(),
+                inputs,
+                reply,
+            });
+            if queue.inputs >= shared.max_batch {
+                Some(queue.take())
+            } else {
+                if !queue.timer_armed {
+                    queue.timer_armed = true;
+                    let generation = queue.generation;
+                    let shared = Arc::clone(shared);
+                    tokio::spawn(async move {
+                        tokio::time::sleep(shared.window).await;
+                        let pending = {
+                            let mut queue = lock(&shared.queue);
+                            if queue.generation != generation {
+                                return;
+                            }
+                            queue.take()
+                        };
+                        shared.send(pending).await;
+                    });
+                }
+                None
+            }
+        };
+        // Sent from a task of its own, so the batch survives this caller
+        // being cancelled
+        if let Some(pending) = full {
+            let shared = Arc::clone(shared);
+            tokio::spawn(async move { shared.send(pending).await });
+        }
+
+        match response.await {
+            Ok(result) => result.map_err(|err| anyhow!(err)),
+            Err(_) => Err(anyhow!("Embedding batch ended without a response")),
+        }
+    }
+}
+
 fn sanitize_embedding_input(input: &str) -> String {
     const MAX_LEN: usize = 4096;
     let mut processed = input
@@ -254,6 +536,107 @@ mod tests {
         assert!(result.is_err());
     }
 
+    /// Embeds each input as its length and records the size of every call
+    #[derive(Default)]
+    struct CountingService {
+        calls: Mutex<Vec<(String, usize)>>,
+    }
+
+    #[async_trait]
+    impl EmbeddingsService for CountingService {
+        async fn create_embeddings(
+            &self,
+            model: &str,
+            inputs: Vec<String>,
+        ) -> Result<Vec<Vec<f32>>> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((model.to_string(), inputs.len()));
+            Ok(inputs
+                .iter()
+                .map(|input| vec![input.len() as f32])
+                .collect())
+        }
+    }
+
+    fn batching(window_ms: u64, max_batch_size: usize) -> EmbeddingsConfig {
+        EmbeddingsConfig {
+            batch_window_ms: window_ms,
+            max_batch_size,
+            ..EmbeddingsConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_share_a_batch() {
+        let service = Arc::new(CountingService::default());
+        let client = EmbeddingsClient::with_service("model", service.clone())
+            .with_batching(&batching(20, 64));
+        let other = EmbeddingsClient {
+            model: "other".to_string(),
+            ..client.clone()
+        };
+
+        let inputs = ["a", "bb", "ccc", "dddd"];
+        let (embeddings, other_embedding) = tokio::join!(
+            join_all(inputs.iter().map(|input| client.embed(input))),
+            other.embed("eeeee")
+        );
+        for (input, embedding) in inputs.iter().zip(embeddings) {
+            assert_eq!(embedding.unwrap(), vec![input.len() as f32]);
+        }
+        assert_eq!(other_embedding.unwrap(), vec![5.0]);
+
+        // One call per model
+        let mut calls = service.calls.lock().unwrap().clone();
+        calls.sort();
+        assert_eq!(
+            calls,
+            vec![("model".to_string(), 4), ("other".to_string(), 1)]
+        );
+        let stats = client.batch_stats().unwrap();
+        assert_eq!((stats.requests, stats.inputs, stats.batches), (5, 5, 2));
+        assert_eq!(stats.largest_batch, 4);
+    }
+
+    #[tokio::test]
+    async fn full_batches_are_sent_without_waiting() {
+        let service = Arc::new(CountingService::default());
+        let client = EmbeddingsClient::with_service("model", service.clone())
+            .with_batching(&batching(60_000, 2));
+
+        let embeddings = tokio::time::timeout(
+            Duration::from_secs(5),
+            join_all(["a", "b", "c", "d"].map(|input| client.embed(input))),
+        )
+        .await
+        .expect("full batches do not wait for the window");
+        assert!(embeddings.iter().all(|embedding| embedding.is_ok()));
+        // Requests as large as a batch skip the queue
+        client.embed_batch(&["e", "f", "g"]).await.unwrap();
+
+        let sizes: Vec<usize> = service.calls.lock().unwrap().iter().map(|c| c.1).collect();
+        assert_eq!(sizes, vec![2, 2, 3]);
+        assert_eq!(client.batch_stats().unwrap().mean_batch_size(), 7.0 / 3.0);
+    }
+
+    #[tokio::test]
+    async fn batch_errors_reach_every_request() {
+        let client = EmbeddingsClient::with_service("model", Arc::new(DummyService::err()))
+            .with_batching(&batching(10, 64));
+
+        let (first, second) = tokio::join!(client.embed("one"), client.embed("two"));
+        assert_eq!(first.unwrap_err().to_string(), "boom");
+        assert_eq!(second.unwrap_err().to_string(), "boom");
+        assert!(
+            EmbeddingsClient::with_service("model", Arc::new(DummyService::err()))
+                .with_batching(&batching(0, 64))
+                .batch_stats()
+                .is_none()
+        );
+    }
+
     #[tokio::test]
     async fn embed_batch_returns_all_embeddings() {
         let service = Arc::new(DummyService::ok_batch(vec![vec![0.1, 0.2], vec![0.3, 0.4]]));
@@ -0,0 +1,154 @@
+//! Embeddings computed in process with fastembed
+//!
+//! [`LocalEmbeddingsService`] runs a sentence embedding model such as
+//! bge-small through ONNX Runtime, so semantic memory works without an
+//! embeddings API. The model is downloaded from Hugging Face into the cache
+//! directory the first time it is used and loaded from there afterwards; an
+//! air-gapped machine needs a copy of a cache directory filled elsewhere.
+
+use super::EmbeddingsService;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use directories::BaseDirs;
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+
+/// Directory models are cached in when `[embeddings] cache_dir` is unset
+pub fn default_cache_dir() -> Result<PathBuf> {
+    let base = BaseDirs::new().context("base directories not available")?;
+    Ok(base.home_dir().join(".spec-ai").join("models"))
+}
+
+/// Names of the models that can run locally, with their dimensions
+pub fn supported_models() -> Vec<(String, usize)> {
+    TextEmbedding::list_supported_models()
+        .into_iter()
+        .map(|info| (short_name(&info.model_code).to_string(), info.dim))
+        .collect()
+}
+
+/// Find a local model by its Hugging Face code or the name after the slash,
+/// ignoring case, e.g. "BAAI/bge-small-en-v1.5" or "bge-small-en-v1.5"
+fn resolve_model(name: &str) -> Result<(EmbeddingModel, usize)> {
+    TextEmbedding::list_supported_models()
+        .into_iter()
+        .find(|info| {
+            info.model_code.eq_ignore_ascii_case(name)
+                || short_name(&info.model_code).eq_ignore_ascii_case(name)
+        })
+        .map(|info| (info.model, info.dim))
+        .ok_or_else(|| {
+            let names: Vec<String> = supported_models().into_iter().map(|(n, _)| n).collect();
+            anyhow!(
+                "'{}' is not a local embeddings model; supported models: {}",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+fn short_name(model_code: &str) -> &str {
+    model_code.rsplit('/').next().unwrap_or(model_code)
+}
+
+/// Embeddings service that runs the model on this machine
+///
+/// The model is loaded on the first request, downloading it if the cache
+/// directory does not have it yet. Inference runs on the blocking thread pool.
+#[derive(Clone)]
+pub struct LocalEmbeddingsService {
+    model: EmbeddingModel,
+    dimensions: usize,
+    cache_dir: PathBuf,
+    batch_size: usize,
+    loaded: Arc<Mutex<Option<TextEmbedding>>>,
+}
+
+impl LocalEmbeddingsService {
+    /// Create a service for the model called `model_name`
+    ///
+    /// Unknown names are rejected here, before anything is downloaded.
+    /// Inputs are run through the model `batch_size` at a time.
+    pub fn new(model_name: &str, cache_dir: PathBuf, batch_size: usize) -> Result<Self> {
+        let (model, dimensions) = resolve_model(model_name)?;
+        Ok(Self {
+            model,
+            dimensions,
+            cache_dir,
+            batch_size: batch_size.max(1),
+            loaded: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Length of the vectors the model produces
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn with_model<T>(&self, run: impl FnOnce(&TextEmbedding) -> Result<T>) -> Result<T> {
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        if loaded.is_none() {
+            info!(
+                "Loading local embeddings model {:?} from {}",
+                self.model,
+                self.cache_dir.display()
+            );
+            std::fs::create_dir_all(&self.cache_dir).with_context(|| {
+                format!("Failed to create model cache {}", self.cache_dir.display())
+            })?;
+            let options = InitOptions::new(self.model.clone())
+                .with_cache_dir(self.cache_dir.clone())
+                .with_show_download_progress(false);
+            let model = TextEmbedding::try_new(options).with_context(|| {
+                format!(
+                    "Failed to load local embeddings model {:?}; it is downloaded on first use, \
+                     so offline machines need a filled cache_dir",
+                    self.model
+                )
+            })?;
+            *loaded = Some(model);
+        }
+        run(loaded.as_ref().expect("model loaded above"))
+    }
+}
+
+#[async_trait]
+impl EmbeddingsService for LocalEmbeddingsService {
+    async fn create_embeddings(&self, model: &str, inputs: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        debug!(
+            "Embedding {} inputs locally with {} ({:?})",
+            inputs.len(),
+            model,
+            self.model
+        );
+        let service = self.clone();
+        tokio::task::spawn_blocking(move || {
+            service.with_model(|model| Ok(model.embed(inputs, Some(service.batch_size))?))
+        })
+        .await
+        .context("local embeddings model panicked")?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_resolve_by_name_ignoring_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let short =
+            LocalEmbeddingsService::new("BGE-small-en-v1.5", dir.path().into(), 32).unwrap();
+        assert_eq!(short.dimensions(), 384);
+        assert!(supported_models()
+            .iter()
+            .any(|(name, dim)| name == "bge-small-en-v1.5" && *dim == 384));
+
+        let err = LocalEmbeddingsService::new("text-embedding-3-small", dir.path().into(), 32)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("bge-small-en-v1.5"));
+    }
+}
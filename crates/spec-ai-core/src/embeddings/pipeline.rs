@@ -0,0 +1,619 @@
+//! Batch embedding of large document sets
+//!
+//! [`EmbeddingPipeline`] splits documents into overlapping chunks, sends the
+//! chunks to the embeddings provider in batches sized for that provider, runs
+//! several batches at once and retries batches that fail for reasons that may
+//! go away on their own. Progress is reported after every batch through a
+//! callback or a watch channel, so front ends can show how far an ingestion
+//! has got.
+
+use super::EmbeddingsClient;
+use crate::agent::providers::RetryPolicy;
+use crate::config::AppConfig;
+use crate::error::code_of;
+use crate::vectors::{VectorPoint, VectorStore};
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+/// Inputs per provider call for providers without a better known limit
+const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// A text to embed, with an id naming it in the results
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Document {
+    pub id: String,
+    pub text: String,
+}
+
+impl Document {
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+        }
+    }
+}
+
+/// A piece of a document's text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    /// Byte offset of the chunk in the document
+    pub start: usize,
+    pub text: String,
+}
+
+/// A chunk with its embedding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddedChunk {
+    pub document_id: String,
+    /// Position of the chunk within its document
+    pub index: usize,
+    /// Byte offset of the chunk in the document
+    pub start: usize,
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+impl EmbeddedChunk {
+    /// Id of the chunk in a vector store; the same for every ingestion of
+    /// the document, so ingesting it again replaces its chunks
+    pub fn point_id(&self) -> u64 {
+        let hash = blake3::hash(format!("{}#{}", self.document_id, self.index).as_bytes());
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&hash.as_bytes()[..8]);
+        u64::from_le_bytes(id)
+    }
+}
+
+/// How far a pipeline run has got
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingProgress {
+    pub documents: usize,
+    pub chunks_total: usize,
+    pub chunks_done: usize,
+    pub batches_total: usize,
+    pub batches_done: usize,
+    /// Batches sent again after a transient failure
+    pub retries: u32,
+}
+
+impl EmbeddingProgress {
+    /// Share of the chunks embedded, from 0.0 to 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.chunks_total == 0 {
+            1.0
+        } else {
+            self.chunks_done as f64 / self.chunks_total as f64
+        }
+    }
+
+    /// Whether every batch has been embedded
+    pub fn is_done(&self) -> bool {
+        self.batches_done == self.batches_total
+    }
+}
+
+impl fmt::Display for EmbeddingProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} chunks of {} documents embedded ({}/{} batches",
+            self.chunks_done,
+            self.chunks_total,
+            self.documents,
+            self.batches_done,
+            self.batches_total
+        )?;
+        match self.retries {
+            0 => write!(f, ")"),
+            1 => write!(f, ", 1 retry)"),
+            retries => write!(f, ", {} retries)", retries),
+        }
+    }
+}
+
+type ProgressCallback = Arc<dyn Fn(&EmbeddingProgress) + Send + Sync>;
+
+/// Chunking, batching, concurrency and retry settings of a pipeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineOptions {
+    /// Most characters in a chunk
+    pub chunk_size: usize,
+    /// Characters a chunk repeats from the end of the one before it
+    pub chunk_overlap: usize,
+    /// Chunks sent to the provider in one call
+    pub batch_size: usize,
+    /// Batches in flight at once
+    pub concurrency: usize,
+    /// Retries of a batch that failed for a transient reason
+    pub retry: RetryPolicy,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: 1000,
+            chunk_overlap: 100,
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency: 4,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl PipelineOptions {
+    /// Options from `[embeddings]`, retrying as `[providers]` says
+    ///
+    /// Without a configured `pipeline_batch_size` the batch size suits the
+    /// embeddings provider.
+    pub fn from_config(config: &AppConfig) -> Self {
+        let embeddings = &config.embeddings;
+        Self {
+            chunk_size: embeddings.chunk_size,
+            chunk_overlap: embeddings.chunk_overlap,
+            batch_size: embeddings
+                .pipeline_batch_size
+                .unwrap_or_else(|| provider_batch_size(config)),
+            concurrency: embeddings.concurrency,
+            retry: RetryPolicy::from(&config.providers),
+        }
+    }
+}
+
+/// Chunks per call that suit the embeddings provider `config` selects
+pub fn provider_batch_size(config: &AppConfig) -> usize {
+    if config.embeddings.is_local() {
+        // Runs through the model in batches of this size anyway
+        return config.embeddings.max_batch_size.max(1);
+    }
+    match config.model.provider.to_lowercase().as_str() {
+        // Accepts up to 2048 inputs, but also caps the tokens of a request
+        "openai" => 256,
+        // Local servers embed on the machine they run on
+        "lmstudio" | "mlx" | "ollama" => 16,
+        _ => DEFAULT_BATCH_SIZE,
+    }
+}
+
+/// Split `text` into chunks of at most `size` characters, each repeating
+/// the last `overlap` characters of the one before
+///
+/// Chunks end after whitespace when there is some in their second half, so
+/// words are rarely cut. Whitespace around chunks is trimmed and blank
+/// chunks are dropped.
+pub fn chunk_text(text: &str, size: usize, overlap: usize) -> Vec<TextChunk> {
+    let size = size.max(1);
+    // Every chunk is longer than half the size, so this always moves on
+    let overlap = overlap.min(size / 2);
+    // Byte offset of every character, and of the end of the text
+    let bounds: Vec<usize> = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let chars = bounds.len() - 1;
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars {
+        let mut end = (start + size).min(chars);
+        if end < chars {
+            if let Some(cut) = (start + size / 2 + 1..=end)
+                .rev()
+                .find(|&i| text[bounds[i - 1]..bounds[i]].trim().is_empty())
+            {
+                end = cut;
+            }
+        }
+
+        let piece = &text[bounds[start]..bounds[end]];
+        let trimmed = piece.trim();
+        if !trimmed.is_empty() {
+            chunks.push(TextChunk {
+                start: bounds[start] + (piece.len() - piece.trim_start().len()),
+                text: trimmed.to_string(),
+            });
+        }
+        if end == chars {
+            break;
+        }
+        start = end - overlap;
+    }
+    chunks
+}
+
+/// Embeds document sets in concurrent, retried batches
+#[derive(Clone)]
+pub struct EmbeddingPipeline {
+    client: EmbeddingsClient,
+    options: PipelineOptions,
+    progress: Option<ProgressCallback>,
+}
+
+impl EmbeddingPipeline {
+    pub fn new(client: EmbeddingsClient, options: PipelineOptions) -> Self {
+        Self {
+            client,
+            options,
+            progress: None,
+        }
+    }
+
+    /// Call `callback` with the progress at the start of a run and after
+    /// every batch or retry
+    pub fn with_progress(
+        mut self,
+        callback: impl Fn(&EmbeddingProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Publish progress on a watch channel, which always holds the latest
+    pub fn with_progress_channel(self) -> (Self, watch::Receiver<EmbeddingProgress>) {
+        let (sender, receiver) = watch::channel(EmbeddingProgress::default());
+        let pipeline = self.with_progress(move |progress| {
+            sender.send_replace(*progress);
+        });
+        (pipeline, receiver)
+    }
+
+    pub fn options(&self) -> &PipelineOptions {
+        &self.options
+    }
+
+    /// Chunk and embed `documents`
+    ///
+    /// Returns the chunks of every document in document order. The first
+    /// batch that still fails after its retries, or fails for a reason that
+    /// retrying will not fix, fails the run.
+    pub async fn embed_documents(&self, documents: &[Document]) -> Result<Vec<EmbeddedChunk>> {
+        let mut chunks = Vec::new();
+        for document in documents {
+            for (index, chunk) in chunk_text(
+                &document.text,
+                self.options.chunk_size,
+                self.options.chunk_overlap,
+            )
+            .into_iter()
+            .enumerate()
+            {
+                chunks.push(EmbeddedChunk {
+                    document_id: document.id.clone(),
+                    index,
+                    start: chunk.start,
+                    text: chunk.text,
+                    embedding: Vec::new(),
+                });
+            }
+        }
+
+        let batch_size = self.options.batch_size.max(1);
+        let progress = Mutex::new(EmbeddingProgress {
+            documents: documents.len(),
+            chunks_total: chunks.len(),
+            batches_total: chunks.len().div_ceil(batch_size),
+            ..EmbeddingProgress::default()
+        });
+        self.report(&progress);
+
+        let batches = chunks
+            .chunks(batch_size)
+            .enumerate()
+            .map(|(number, batch)| {
+                let texts: Vec<String> = batch.iter().map(|chunk| chunk.text.clone()).collect();
+                (number * batch_size, texts)
+            })
+            .collect::<Vec<_>>();
+        let mut results = stream::iter(batches)
+            .map(|(first, texts)| {
+                let progress = &progress;
+                async move {
+                    let embeddings = self.embed_with_retries(&texts, progress).await?;
+                    Ok::<_, anyhow::Error>((first, embeddings))
+                }
+            })
+            .buffer_unordered(self.options.concurrency.max(1));
+
+        while let Some(result) = results.next().await {
+            let (first, embeddings) = result?;
+            let embedded = embeddings.len();
+            for (chunk, embedding) in chunks[first..].iter_mut().zip(embeddings) {
+                chunk.embedding = embedding;
+            }
+            {
+                let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+                progress.chunks_done += embedded;
+                progress.batches_done += 1;
+            }
+            self.report(&progress);
+        }
+        Ok(chunks)
+    }
+
+    /// Chunk and embed `documents` and upsert the chunks into `collection`
+    ///
+    /// Each point carries the document id, chunk index, byte offset and
+    /// text in its payload. Returns the number of chunks stored.
+    pub async fn ingest(
+        &self,
+        documents: &[Document],
+        store: &dyn VectorStore,
+        collection: &str,
+    ) -> Result<usize> {
+        let chunks = self.embed_documents(documents).await?;
+        let stored = chunks.len();
+        let points = chunks
+            .into_iter()
+            .map(|chunk| {
+                VectorPoint::new(chunk.point_id(), chunk.embedding)
+                    .with_field("document_id", chunk.document_id)
+                    .with_field("chunk", chunk.index as u64)
+                    .with_field("start", chunk.start as u64)
+                    .with_field("text", chunk.text)
+            })
+            .collect::<Vec<_>>();
+        for batch in points.chunks(self.options.batch_size.max(1)) {
+            store
+                .upsert(collection, batch.to_vec())
+                .await
+                .with_context(|| format!("storing chunks in {}", collection))?;
+        }
+        Ok(stored)
+    }
+
+    async fn embed_with_retries(
+        &self,
+        texts: &[String],
+        progress: &Mutex<EmbeddingProgress>,
+    ) -> Result<Vec<Vec<f32>>> {
+        let mut attempt = 0;
+        loop {
+            let result = self.client.embed_batch(texts).await.and_then(|embeddings| {
+                if embeddings.len() == texts.len() {
+                    Ok(embeddings)
+                } else {
+                    Err(anyhow::anyhow!(
+                        "Embeddings service returned {} embeddings for {} inputs",
+                        embeddings.len(),
+                        texts.len()
+                    ))
+                }
+            });
+            match result {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(err)
+                    if attempt < self.options.retry.max_retries && code_of(&err).is_retryable() =>
+                {
+                    attempt += 1;
+                    let delay = self.options.retry.backoff(attempt);
+                    warn!(
+                        "Embedding batch of {} chunks failed, retry {} in {:?}: {:#}",
+                        texts.len(),
+                        attempt,
+                        delay,
+                        err
+                    );
+                    progress.lock().unwrap_or_else(|e| e.into_inner()).retries += 1;
+                    self.report(progress);
+                    tokio::time::sleep(delay).await;
+                }
+                Err(err) => {
+                    debug!("Embedding batch failed after {} retries", attempt);
+                    return Err(err.context(format!("embedding a batch of {} chunks", texts.len())));
+                }
+            }
+        }
+    }
+
+    fn report(&self, progress: &Mutex<EmbeddingProgress>) {
+        if let Some(callback) = &self.progress {
+            let progress = *progress.lock().unwrap_or_else(|e| e.into_inner());
+            callback(&progress);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::EmbeddingsService;
+    use crate::error::Error;
+    #[cfg(feature = "sqlite-vec")]
+    use crate::vectors::{sqlite::SqliteVectorStore, VectorFilter};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Embeds a text as its length, failing the first `failures` calls
+    struct FlakyService {
+        calls: AtomicUsize,
+        failures: usize,
+        error: fn() -> anyhow::Error,
+        largest: AtomicUsize,
+    }
+
+    impl FlakyService {
+        fn new(failures: usize, error: fn() -> anyhow::Error) -> Arc<Self> {
+            Arc::new(Self {
+                calls: AtomicUsize::new(0),
+                failures,
+                error,
+                largest: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingsService for FlakyService {
+        async fn create_embeddings(
+            &self,
+            _model: &str,
+            inputs: Vec<String>,
+        ) -> Result<Vec<Vec<f32>>> {
+            self.largest.fetch_max(inputs.len(), Ordering::SeqCst);
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err((self.error)());
+            }
+            Ok(inputs
+                .iter()
+                .map(|input| vec![input.len() as f32, 1.0])
+                .collect())
+        }
+    }
+
+    fn rate_limited() -> anyhow::Error {
+        Error::Provider {
+            provider: "test".to_string(),
+            status: 429,
+            message: "slow down".to_string(),
+        }
+        .into()
+    }
+
+    fn rejected() -> anyhow::Error {
+        Error::Provider {
+            provider: "test".to_string(),
+            status: 400,
+            message: "bad input".to_string(),
+        }
+        .into()
+    }
+
+    fn pipeline(service: Arc<FlakyService>, batch_size: usize) -> EmbeddingPipeline {
+        let mut options = PipelineOptions {
+            chunk_size: 20,
+            chunk_overlap: 5,
+            batch_size,
+            concurrency: 3,
+            ..PipelineOptions::default()
+        };
+        options.retry.initial_backoff = Duration::ZERO;
+        options.retry.max_backoff = Duration::ZERO;
+        EmbeddingPipeline::new(EmbeddingsClient::with_service("test", service), options)
+    }
+
+    #[test]
+    fn chunks_overlap_and_end_at_whitespace() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta";
+        let chunks = chunk_text(text, 20, 6);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.text.chars().count() <= 20);
+            assert_eq!(
+                &text[chunk.start..chunk.start + chunk.text.len()],
+                chunk.text
+            );
+        }
+        assert_eq!(chunks[0].text, "alpha beta gamma");
+        assert!(chunks.last().unwrap().text.ends_with("theta"));
+
+        // Multi-byte characters are never split
+        let chunks = chunk_text("ééééééééé", 4, 1);
+        assert!(chunks.iter().all(|chunk| chunk.text.chars().count() <= 4));
+        assert!(chunk_text("   ", 10, 2).is_empty());
+    }
+
+    #[tokio::test]
+    async fn documents_are_embedded_in_order_with_progress() {
+        let service = FlakyService::new(0, rate_limited);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&seen);
+        let pipeline = pipeline(service.clone(), 2).with_progress(move |progress| {
+            recorded.lock().unwrap().push(*progress);
+        });
+        let documents = vec![
+            Document::new("a", "one two three four five six seven eight nine ten"),
+            Document::new("b", "short"),
+        ];
+
+        let chunks = pipeline.embed_documents(&documents).await.unwrap();
+        assert!(chunks.len() > 2);
+        assert_eq!(chunks.last().unwrap().document_id, "b");
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunk.embedding == vec![chunk.text.len() as f32, 1.0]));
+        assert_eq!(service.largest.load(Ordering::SeqCst), 2);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.first().unwrap().chunks_done, 0);
+        let last = seen.last().unwrap();
+        assert!(last.is_done());
+        assert_eq!(last.chunks_done, chunks.len());
+        assert_eq!(last.documents, 2);
+        assert_eq!(last.fraction(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried() {
+        let service = FlakyService::new(2, rate_limited);
+        let (pipeline, progress) = pipeline(service.clone(), 64).with_progress_channel();
+
+        let chunks = pipeline
+            .embed_documents(&[Document::new("a", "retry me")])
+            .await
+            .unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(service.calls.load(Ordering::SeqCst), 3);
+        let progress = *progress.borrow();
+        assert_eq!(progress.retries, 2);
+        assert!(progress.is_done());
+        assert!(progress.to_string().contains("2 retries"));
+
+        // Retries run out
+        let service = FlakyService::new(5, rate_limited);
+        assert!(pipeline_with(service.clone()).await.is_err());
+        assert_eq!(service.calls.load(Ordering::SeqCst), 3);
+
+        // Errors retrying cannot fix fail straight away
+        let service = FlakyService::new(1, rejected);
+        let err = pipeline_with(service.clone()).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("bad input"));
+        assert_eq!(service.calls.load(Ordering::SeqCst), 1);
+    }
+
+    async fn pipeline_with(service: Arc<FlakyService>) -> Result<Vec<EmbeddedChunk>> {
+        pipeline(service, 64)
+            .embed_documents(&[Document::new("a", "retry me")])
+            .await
+    }
+
+    #[cfg(feature = "sqlite-vec")]
+    #[tokio::test]
+    async fn ingesting_again_replaces_a_documents_chunks() {
+        let store = SqliteVectorStore::in_memory().unwrap();
+        let pipeline = pipeline(FlakyService::new(0, rate_limited), 2);
+        let documents = [Document::new("notes.md", "first second third fourth fifth")];
+
+        let stored = pipeline
+            .ingest(&documents, &store, "documents")
+            .await
+            .unwrap();
+        assert_eq!(
+            pipeline
+                .ingest(&documents, &store, "documents")
+                .await
+                .unwrap(),
+            stored
+        );
+
+        let matches = store
+            .knn(
+                "documents",
+                &[10.0, 1.0],
+                100,
+                &VectorFilter::none().eq("document_id", "notes.md"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), stored);
+        assert!(matches
+            .iter()
+            .any(|m| m.payload["chunk"] == 0 && m.payload["start"] == 0));
+    }
+}
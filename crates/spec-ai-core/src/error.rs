@@ -0,0 +1,57 @@
+//! Error codes for everything the core can fail with
+//!
+//! Re-exports the shared [`Error`] and [`ErrorCode`] and extends
+//! classification to HTTP client and plugin errors, which the config crate
+//! does not know about.
+
+pub use spec_ai_config::error::{classify, Error, ErrorCode};
+use spec_ai_plugin::PluginError;
+
+/// Code of the first classifiable error in an `anyhow` chain
+pub fn code_of(err: &anyhow::Error) -> ErrorCode {
+    err.chain()
+        .find_map(|err| classify(err).or_else(|| classify_core(err)))
+        .unwrap_or(ErrorCode::Internal)
+}
+
+fn classify_core(err: &(dyn std::error::Error + 'static)) -> Option<ErrorCode> {
+    if err.is::<PluginError>() {
+        return Some(ErrorCode::Plugin);
+    }
+    #[cfg(feature = "reqwest")]
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        if err.is_timeout() {
+            return Some(ErrorCode::Timeout);
+        }
+        if err.is_connect() || err.is_request() {
+            return Some(ErrorCode::Network);
+        }
+        if let Some(status) = err.status() {
+            return Some(ErrorCode::from_provider_status(status.as_u16()));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn classifies_plugin_and_provider_errors() {
+        let err = Err::<(), _>(PluginError::DuplicatePlugin("echo".to_string()))
+            .context("Failed to load plugins")
+            .unwrap_err();
+        assert_eq!(code_of(&err), ErrorCode::Plugin);
+
+        let err = anyhow::Error::from(Error::Provider {
+            provider: "Ollama".to_string(),
+            status: 503,
+            message: "loading model".to_string(),
+        });
+        assert_eq!(code_of(&err), ErrorCode::ProviderUnavailable);
+        assert!(code_of(&err).is_retryable());
+        assert_eq!(code_of(&anyhow::anyhow!("boom")), ErrorCode::Internal);
+    }
+}
@@ -0,0 +1,268 @@
+//! Writes batches of events to a rotating file and an HTTP endpoint
+
+use super::Event;
+use crate::broadcast::Subscriber;
+use crate::config::EventLogConfig;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Most events written or POSTed at once
+const MAX_BATCH: usize = 256;
+
+/// Where `config` sends events, for logging
+pub(super) fn describe(config: &EventLogConfig) -> String {
+    let mut sinks = Vec::new();
+    if let Some(path) = &config.path {
+        sinks.push(path.display().to_string());
+    }
+    if let Some(endpoint) = &config.endpoint {
+        sinks.push(endpoint.clone());
+    }
+    sinks.join(" and ")
+}
+
+/// Export events until every sender is gone
+pub(super) async fn export(config: EventLogConfig, mut events: Subscriber<Event>) {
+    let mut file = config
+        .path
+        .clone()
+        .map(|path| RotatingFile::new(path, config.max_file_bytes, config.max_files));
+    #[cfg(feature = "reqwest")]
+    let client = reqwest::Client::new();
+    #[cfg(not(feature = "reqwest"))]
+    if config.endpoint.is_some() {
+        warn!(
+            "event_log.endpoint is set, but spec-ai was built without HTTP support; \
+             events will not be posted"
+        );
+    }
+
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+    while let Ok(first) = events.recv().await {
+        batch.push(first);
+        while batch.len() < MAX_BATCH {
+            match events.try_recv() {
+                Ok(event) => batch.push(event),
+                Err(_) => break,
+            }
+        }
+        let lines = encode(&batch);
+        batch.clear();
+
+        if let Some(mut writer) = file.take() {
+            let chunk = lines.clone();
+            let written = tokio::task::spawn_blocking(move || {
+                if let Err(err) = writer.append(&chunk) {
+                    warn!(
+                        "Failed to write events to {}: {}",
+                        writer.path.display(),
+                        err
+                    );
+                }
+                writer
+            })
+            .await;
+            file = written.ok();
+        }
+
+        #[cfg(feature = "reqwest")]
+        if let Some(endpoint) = &config.endpoint {
+            if let Err(err) = post(&client, endpoint, lines).await {
+                warn!("Failed to post events to {}: {:#}", endpoint, err);
+            }
+        }
+    }
+}
+
+/// `events` as JSON Lines
+fn encode(events: &[Event]) -> String {
+    let mut lines = String::new();
+    for event in events {
+        match serde_json::to_string(event) {
+            Ok(line) => {
+                lines.push_str(&line);
+                lines.push('\n');
+            }
+            Err(err) => warn!("Failed to encode event {}: {}", event.id, err),
+        }
+    }
+    lines
+}
+
+#[cfg(feature = "reqwest")]
+async fn post(client: &reqwest::Client, endpoint: &str, lines: String) -> anyhow::Result<()> {
+    client
+        .post(endpoint)
+        .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+        .timeout(std::time::Duration::from_secs(30))
+        .body(lines)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// A file appended to until it reaches a size, then moved to `<path>.1`
+///
+/// Older files move up one number, and those beyond `max_files` are removed.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn new(path: PathBuf, max_bytes: u64, max_files: u32) -> Self {
+        Self {
+            path,
+            max_bytes,
+            max_files,
+            file: None,
+            size: 0,
+        }
+    }
+
+    /// Append `lines`, rotating first if they would not fit
+    ///
+    /// A batch is never split across files.
+    pub fn append(&mut self, lines: &str) -> io::Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        self.open()?;
+        if self.size > 0 && self.size + lines.len() as u64 > self.max_bytes {
+            self.rotate()?;
+            self.open()?;
+        }
+        if let Some(file) = &mut self.file {
+            file.write_all(lines.as_bytes())?;
+            file.flush()?;
+        }
+        self.size += lines.len() as u64;
+        Ok(())
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        if self.file.is_some() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        self.size = 0;
+        if self.max_files == 0 {
+            return ignore_missing(fs::remove_file(&self.path));
+        }
+        ignore_missing(fs::remove_file(rotated(&self.path, self.max_files)))?;
+        for index in (1..self.max_files).rev() {
+            ignore_missing(fs::rename(
+                rotated(&self.path, index),
+                rotated(&self.path, index + 1),
+            ))?;
+        }
+        fs::rename(&self.path, rotated(&self.path, 1))
+    }
+}
+
+/// Path of the `index`th rotated file
+fn rotated(path: &Path, index: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+
+    #[test]
+    fn files_rotate_by_size_and_keep_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("events.jsonl");
+        let mut file = RotatingFile::new(path.clone(), 10, 2);
+
+        for line in ["aaaa\n", "bbbb\n", "cccc\n", "dddd\n", "eeee\n"] {
+            file.append(line).unwrap();
+        }
+        // Each file holds two lines; the oldest pair was dropped
+        assert_eq!(fs::read_to_string(&path).unwrap(), "eeee\n");
+        assert_eq!(
+            fs::read_to_string(rotated(&path, 1)).unwrap(),
+            "cccc\ndddd\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rotated(&path, 2)).unwrap(),
+            "aaaa\nbbbb\n"
+        );
+        assert!(!rotated(&path, 3).exists());
+
+        // A reopened log carries on from the size on disk
+        let mut file = RotatingFile::new(path.clone(), 10, 2);
+        file.append("ffff\n").unwrap();
+        file.append("gggg\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "gggg\n");
+        assert_eq!(
+            fs::read_to_string(rotated(&path, 1)).unwrap(),
+            "eeee\nffff\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn exporter_writes_one_event_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let config = EventLogConfig {
+            enabled: true,
+            path: Some(path.clone()),
+            ..EventLogConfig::default()
+        };
+        let sender = crate::broadcast::channel(8, crate::broadcast::DropPolicy::DropOldest);
+        let events = sender.subscribe();
+        for id in 1..=3 {
+            sender.send(
+                Event::new(EventKind::Message {
+                    role: "user".to_string(),
+                    message_id: id,
+                    content: "line\nbreak".to_string(),
+                })
+                .with_session("s1"),
+            );
+        }
+        drop(sender);
+        export(config, events).await;
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let events: Vec<Event> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            events[2].kind,
+            EventKind::Message { message_id: 3, .. }
+        ));
+    }
+}
@@ -0,0 +1,336 @@
+//! Structured event log: agent activity exported as JSON Lines
+//!
+//! Messages, tool calls, policy decisions, plugin loads, graph sync
+//! operations and delegations are recorded as [`Event`]s with [`emit`]. The exporter started
+//! by [`init`] appends them, one JSON object per line, to a file rotated by
+//! size and POSTs them to an HTTP endpoint, so analytics pipelines can follow
+//! spec-ai without scraping its logs. Without an exporter, events are
+//! dropped.
+//!
+//! The schema is documented in `docs/EVENTS.md`; [`SCHEMA_VERSION`] changes
+//! whenever a field is removed or changes meaning.
+
+mod export;
+
+use crate::agent::PolicyOutcome;
+use crate::broadcast::{Broadcaster, DropPolicy, Subscriber};
+use crate::config::EventLogConfig;
+#[cfg(feature = "api")]
+use crate::sync::{GraphSyncPayload, SyncStats, SyncType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tracing::warn;
+
+pub use export::RotatingFile;
+
+/// Version of the event schema, written as `v` on every event
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// One line of the event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Schema version
+    pub v: u32,
+    /// Unique ID, for deduplicating events delivered twice
+    pub id: String,
+    /// When the event happened
+    pub ts: DateTime<Utc>,
+    /// Session the event belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Agent profile that caused the event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    /// Run ID of the agent step, as used by `DELETE /runs/{run_id}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Event {
+    /// An event of `kind` happening now
+    pub fn new(kind: EventKind) -> Self {
+        Self {
+            v: SCHEMA_VERSION,
+            id: uuid::Uuid::new_v4().to_string(),
+            ts: Utc::now(),
+            session_id: None,
+            agent: None,
+            run_id: None,
+            kind,
+        }
+    }
+
+    pub fn with_session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn with_agent(mut self, agent: Option<&str>) -> Self {
+        self.agent = agent.map(str::to_string);
+        self
+    }
+
+    pub fn with_run(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    pub fn with_timestamp(mut self, ts: DateTime<Utc>) -> Self {
+        self.ts = ts;
+        self
+    }
+}
+
+#[cfg(feature = "api")]
+impl Event {
+    /// `payload` was sent to `peer`
+    pub fn sync_sent(payload: &GraphSyncPayload, graph: &str, peer: Option<&str>) -> Self {
+        Self::new(EventKind::Sync {
+            direction: SyncDirection::Sent,
+            sync_type: sync_type_name(&payload.sync_type),
+            graph: graph.to_string(),
+            peer: peer.map(str::to_string),
+            nodes: payload.nodes.len(),
+            edges: payload.edges.len(),
+            tombstones: payload.tombstones.len(),
+            conflicts: 0,
+        })
+        .with_session(&payload.session_id)
+    }
+
+    /// `payload` from `peer` was applied, changing what `stats` counts
+    pub fn sync_applied(
+        payload: &GraphSyncPayload,
+        graph: &str,
+        peer: Option<&str>,
+        stats: &SyncStats,
+    ) -> Self {
+        Self::new(EventKind::Sync {
+            direction: SyncDirection::Applied,
+            sync_type: sync_type_name(&payload.sync_type),
+            graph: graph.to_string(),
+            peer: peer.map(str::to_string),
+            nodes: stats.nodes_applied,
+            edges: stats.edges_applied,
+            tombstones: stats.tombstones_applied,
+            conflicts: stats.conflicts_detected,
+        })
+        .with_session(&payload.session_id)
+    }
+}
+
+#[cfg(feature = "api")]
+fn sync_type_name(sync_type: &SyncType) -> String {
+    match serde_json::to_value(sync_type) {
+        Ok(Value::String(name)) => name,
+        _ => format!("{:?}", sync_type),
+    }
+}
+
+/// What happened, written as `type` and the fields of that type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// A message was stored in the session
+    Message {
+        /// `user`, `assistant`, `system` or `agent:<id>`
+        role: String,
+        message_id: i64,
+        content: String,
+    },
+    /// A tool call finished
+    ToolCall {
+        tool: String,
+        arguments: Value,
+        success: bool,
+        duration_ms: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// The agent profile or policy engine blocked an action
+    PolicyDecision {
+        /// Action that was checked, such as `tool_call`
+        action: String,
+        /// What the action targeted, such as the tool name
+        resource: String,
+        outcome: PolicyOutcome,
+        /// Rule or reason that blocked the action
+        rule: String,
+    },
+    /// A plugin library was checked before it was loaded
+    PluginLoad {
+        /// Path of the library
+        path: String,
+        /// `verified`, `unsigned` or `untrusted`
+        trust: String,
+        /// Trusted key that verified the signature
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<String>,
+        /// Why the library is untrusted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+        /// Whether the library was loaded
+        allowed: bool,
+        /// Policy reason an unverified library was refused
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rule: Option<String>,
+    },
+    /// Graph changes were sent to or applied from a peer
+    Sync {
+        direction: SyncDirection,
+        /// `full` or `incremental`
+        sync_type: String,
+        graph: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        peer: Option<String>,
+        nodes: usize,
+        edges: usize,
+        tombstones: usize,
+        conflicts: usize,
+    },
+    /// Work was handed to a sub-agent or a mesh peer
+    Delegation {
+        target: DelegationTarget,
+        /// Sub-agent name or peer instance ID
+        to: String,
+        task: String,
+        /// Session ID of the sub-agent, or task ID sent to the peer
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        task_id: Option<String>,
+    },
+    /// An agent step finished
+    RunCompleted {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        finish_reason: Option<String>,
+        tool_calls: usize,
+        /// Tokens of every model call in the step
+        total_tokens: u64,
+        /// Estimated cost, if every model used is priced
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cost: Option<f64>,
+        duration_ms: u64,
+    },
+}
+
+/// Which way graph changes went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    /// Changes were sent to a peer
+    Sent,
+    /// Changes from a peer were applied here
+    Applied,
+}
+
+/// Who delegated work went to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationTarget {
+    /// A child agent spawned by this one
+    Subagent,
+    /// Another spec-ai instance in the mesh
+    Peer,
+}
+
+/// Events waiting for each exporter; the oldest are dropped when one falls
+/// further behind
+const EVENT_CAPACITY: usize = 4096;
+
+static EVENTS: OnceLock<Broadcaster<Event>> = OnceLock::new();
+static EXPORTING: AtomicBool = AtomicBool::new(false);
+
+fn events() -> &'static Broadcaster<Event> {
+    EVENTS.get_or_init(|| Broadcaster::new(EVENT_CAPACITY, DropPolicy::DropOldest))
+}
+
+/// Start exporting events as `config` describes
+///
+/// The first call with the event log enabled starts the exporter on the
+/// current Tokio runtime; later calls do nothing, so every agent can call it.
+pub fn init(config: &EventLogConfig) {
+    if !config.enabled || EXPORTING.load(Ordering::SeqCst) {
+        return;
+    }
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        warn!("The event log needs a Tokio runtime; events will not be exported");
+        return;
+    };
+    if !EXPORTING.swap(true, Ordering::SeqCst) {
+        tracing::info!("Exporting events to {}", export::describe(config));
+        runtime.spawn(export::export(config.clone(), subscribe()));
+    }
+}
+
+/// Receive every event emitted from now on
+///
+/// For exporters of your own, alongside the file and HTTP exporter started by
+/// [`init`]. A subscriber that falls behind loses its oldest events.
+pub fn subscribe() -> Subscriber<Event> {
+    events().subscribe()
+}
+
+/// Whether events are being exported
+///
+/// Lets callers skip building events that would be dropped.
+pub fn enabled() -> bool {
+    EVENTS
+        .get()
+        .is_some_and(|events| events.subscriber_count() > 0)
+}
+
+/// Record `event`
+pub fn emit(event: Event) {
+    // Events are best effort; with no subscriber they are dropped
+    if let Some(events) = EVENTS.get() {
+        events.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_serialize_as_flat_tagged_lines() {
+        let event = Event::new(EventKind::ToolCall {
+            tool: "echo".to_string(),
+            arguments: serde_json::json!({"text": "hi"}),
+            success: false,
+            duration_ms: 12,
+            error: Some("boom".to_string()),
+        })
+        .with_session("s1")
+        .with_agent(Some("coder"))
+        .with_run("run-1");
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["v"], SCHEMA_VERSION);
+        assert_eq!(json["type"], "tool_call");
+        assert_eq!(json["session_id"], "s1");
+        assert_eq!(json["agent"], "coder");
+        assert_eq!(json["run_id"], "run-1");
+        assert_eq!(json["arguments"]["text"], "hi");
+        assert_eq!(json["error"], "boom");
+
+        let line = serde_json::to_string(&event).unwrap();
+        assert!(!line.contains('\n'));
+        let parsed: Event = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed, event);
+
+        let policy = Event::new(EventKind::PolicyDecision {
+            action: "tool_call".to_string(),
+            resource: "bash".to_string(),
+            outcome: PolicyOutcome::Denied,
+            rule: "not allowed".to_string(),
+        });
+        let json = serde_json::to_value(&policy).unwrap();
+        assert_eq!(json["type"], "policy_decision");
+        assert_eq!(json["outcome"], "denied");
+        assert!(json.get("session_id").is_none());
+    }
+}
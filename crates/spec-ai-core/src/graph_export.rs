@@ -0,0 +1,342 @@
+//! Rendering knowledge graph snapshots as Graphviz DOT or Mermaid
+//!
+//! A [`Selection`] picks a subgraph out of a session's graph, either the
+//! nodes a query returns or the k-hop neighborhood of one node, and
+//! [`Subgraph::render`] writes it as text that can be pasted into docs and
+//! issues. Only edges between selected nodes are drawn. Nodes and edges are
+//! written in id order so the same graph always renders the same way.
+
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::persistence::Persistence;
+use crate::types::{GraphEdge, GraphNode, NodeType, TraversalDirection};
+
+/// Nodes exported when no limit is given
+pub const DEFAULT_EXPORT_LIMIT: usize = 200;
+
+/// Text format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dot,
+    Mermaid,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Dot => "dot",
+            ExportFormat::Mermaid => "mermaid",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "dot" | "graphviz" => Ok(ExportFormat::Dot),
+            "mermaid" | "mmd" => Ok(ExportFormat::Mermaid),
+            other => Err(anyhow!(
+                "unknown export format '{}', expected dot or mermaid",
+                other
+            )),
+        }
+    }
+}
+
+/// Parse a node type name
+///
+/// Unlike `NodeType::from_str`, which reads unknown names as entities, a
+/// misspelled type is an error rather than a silently different filter.
+pub fn parse_node_type(value: &str) -> Result<NodeType> {
+    let node_type = NodeType::from_str(value);
+    if node_type.as_str() != value.to_ascii_lowercase() {
+        bail!("unknown node type '{}'", value);
+    }
+    Ok(node_type)
+}
+
+/// Which part of a session's graph to export
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    /// The nodes a listing returns, optionally only those of one type
+    Query { node_type: Option<NodeType> },
+    /// A node and everything within `hops` edges of it, in either direction
+    Neighborhood { node_id: i64, hops: usize },
+}
+
+/// Nodes and the edges between them
+#[derive(Debug, Clone, Default)]
+pub struct Subgraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Nodes the selection matched but the limit left out
+    pub omitted: usize,
+}
+
+impl Subgraph {
+    /// Load the nodes `selection` picks from `session_id`, at most `limit` of them
+    ///
+    /// Neighborhoods keep the nodes closest to the center when they are cut.
+    pub fn select(
+        persistence: &Persistence,
+        session_id: &str,
+        selection: &Selection,
+        limit: usize,
+    ) -> Result<Self> {
+        let mut nodes = match selection {
+            Selection::Query { node_type } => {
+                persistence.list_graph_nodes(session_id, node_type.clone(), None)?
+            }
+            Selection::Neighborhood { node_id, hops } => {
+                let center = match persistence.get_graph_node(*node_id)? {
+                    Some(node) if node.session_id == session_id => node,
+                    _ => bail!("node {} is not in session '{}'", node_id, session_id),
+                };
+                let mut nodes = vec![center];
+                nodes.extend(persistence.traverse_neighbors(
+                    session_id,
+                    *node_id,
+                    TraversalDirection::Both,
+                    *hops,
+                )?);
+                nodes
+            }
+        };
+        let omitted = nodes.len().saturating_sub(limit);
+        nodes.truncate(limit);
+
+        let edges = persistence.list_graph_edges(session_id, None, None)?;
+        Ok(Self::from_parts(nodes, edges, omitted))
+    }
+
+    /// Keep the edges of `edges` whose ends are both in `nodes`
+    pub fn from_parts(mut nodes: Vec<GraphNode>, edges: Vec<GraphEdge>, omitted: usize) -> Self {
+        nodes.sort_by_key(|node| node.id);
+        let ids: HashSet<i64> = nodes.iter().map(|node| node.id).collect();
+        let mut edges: Vec<GraphEdge> = edges
+            .into_iter()
+            .filter(|edge| ids.contains(&edge.source_id) && ids.contains(&edge.target_id))
+            .collect();
+        edges.sort_by_key(|edge| edge.id);
+        Self {
+            nodes,
+            edges,
+            omitted,
+        }
+    }
+
+    pub fn render(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Dot => self.to_dot(),
+            ExportFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    /// Graphviz source, e.g. for `dot -Tsvg`
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph knowledge_graph {\n    rankdir=LR;\n");
+        out.push_str("    node [shape=box, style=rounded];\n");
+        for node in &self.nodes {
+            let _ = writeln!(
+                out,
+                "    n{} [label=\"{}\", tooltip=\"{}\"];",
+                node.id,
+                dot_escape(&node_caption(node)),
+                node.node_type.as_str()
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    n{} -> n{} [label=\"{}\"];",
+                edge.source_id,
+                edge.target_id,
+                dot_escape(&edge_caption(edge))
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// A Mermaid flowchart, which GitHub renders inside a `mermaid` code block
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for node in &self.nodes {
+            let _ = writeln!(
+                out,
+                "    n{}[\"{}\"]",
+                node.id,
+                mermaid_escape(&node_caption(node))
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    n{} -->|\"{}\"| n{}",
+                edge.source_id,
+                mermaid_escape(&edge_caption(edge)),
+                edge.target_id
+            );
+        }
+        out
+    }
+}
+
+/// The node's label, with its `name` property when it has one
+fn node_caption(node: &GraphNode) -> String {
+    match node.properties.get("name").and_then(|name| name.as_str()) {
+        Some(name) if name != node.label => format!("{}: {}", node.label, name),
+        _ => node.label.clone(),
+    }
+}
+
+/// The edge's predicate, or its type when it has none
+fn edge_caption(edge: &GraphEdge) -> String {
+    edge.predicate
+        .clone()
+        .unwrap_or_else(|| edge.edge_type.as_str())
+}
+
+fn dot_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Mermaid has no backslash escapes inside quoted text, only entity codes
+fn mermaid_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => escaped.push_str("#quot;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '#' => escaped.push_str("#35;"),
+            '\n' | '\r' => escaped.push(' '),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::EdgeType;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn node(id: i64, label: &str, name: Option<&str>) -> GraphNode {
+        GraphNode {
+            id,
+            session_id: "s".to_string(),
+            node_type: NodeType::Entity,
+            label: label.to_string(),
+            properties: name.map_or(json!({}), |name| json!({ "name": name })),
+            embedding_id: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn edge(id: i64, source_id: i64, target_id: i64, predicate: Option<&str>) -> GraphEdge {
+        GraphEdge {
+            id,
+            session_id: "s".to_string(),
+            source_id,
+            target_id,
+            edge_type: EdgeType::DependsOn,
+            predicate: predicate.map(str::to_string),
+            properties: None,
+            weight: 1.0,
+            temporal_start: None,
+            temporal_end: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn subgraph() -> Subgraph {
+        Subgraph::from_parts(
+            vec![
+                node(2, "Crate", Some("spec-ai \"core\"")),
+                node(1, "Crate", Some("spec-ai-config")),
+            ],
+            vec![
+                edge(11, 2, 1, None),
+                edge(10, 1, 2, Some("re-exports <types>")),
+                edge(12, 2, 3, None),
+            ],
+            0,
+        )
+    }
+
+    #[test]
+    fn edges_outside_the_selection_are_dropped() {
+        let graph = subgraph();
+        assert_eq!(graph.nodes.iter().map(|n| n.id).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(
+            graph.edges.iter().map(|e| e.id).collect::<Vec<_>>(),
+            [10, 11]
+        );
+    }
+
+    #[test]
+    fn renders_dot() {
+        assert_eq!(
+            subgraph().to_dot(),
+            "digraph knowledge_graph {\n\
+             \x20   rankdir=LR;\n\
+             \x20   node [shape=box, style=rounded];\n\
+             \x20   n1 [label=\"Crate: spec-ai-config\", tooltip=\"entity\"];\n\
+             \x20   n2 [label=\"Crate: spec-ai \\\"core\\\"\", tooltip=\"entity\"];\n\
+             \x20   n1 -> n2 [label=\"re-exports <types>\"];\n\
+             \x20   n2 -> n1 [label=\"DEPENDS_ON\"];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn renders_mermaid() {
+        assert_eq!(
+            subgraph().to_mermaid(),
+            "flowchart LR\n\
+             \x20   n1[\"Crate: spec-ai-config\"]\n\
+             \x20   n2[\"Crate: spec-ai #quot;core#quot;\"]\n\
+             \x20   n1 -->|\"re-exports #lt;types#gt;\"| n2\n\
+             \x20   n2 -->|\"DEPENDS_ON\"| n1\n"
+        );
+    }
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!("DOT".parse::<ExportFormat>().unwrap(), ExportFormat::Dot);
+        assert_eq!(
+            "mermaid".parse::<ExportFormat>().unwrap(),
+            ExportFormat::Mermaid
+        );
+        assert!("svg".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn misspelled_node_types_are_rejected() {
+        assert_eq!(parse_node_type("Goal").unwrap(), NodeType::Goal);
+        assert_eq!(
+            parse_node_type("tool_result").unwrap(),
+            NodeType::ToolResult
+        );
+        assert!(parse_node_type("goals").is_err());
+    }
+}
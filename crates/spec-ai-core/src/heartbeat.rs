@@ -0,0 +1,315 @@
+//! Liveness reporting for headless instances
+//!
+//! [`Liveness`] counts requests in flight and recently active sessions and
+//! remembers the last error. A [`Heartbeat`] turns it into a
+//! [`HeartbeatRecord`] that servers write to persistence and attach to their
+//! mesh heartbeats, so `spec-ai status` can check on an instance without
+//! attaching a UI.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::config::HeartbeatConfig;
+use crate::error::{code_of, ErrorCode};
+pub use crate::persistence::HeartbeatRecord;
+use crate::persistence::Persistence;
+use crate::shutdown::CancellationToken;
+
+/// Status of an instance that is up and publishing heartbeats
+pub const STATUS_RUNNING: &str = "running";
+/// Status written by the final heartbeat of a clean shutdown
+pub const STATUS_STOPPED: &str = "stopped";
+/// Shown for a running instance whose last heartbeat is too old
+pub const STATUS_STALE: &str = "stale";
+
+/// The most recent failure seen by an instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastError {
+    pub message: String,
+    pub code: ErrorCode,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct LivenessState {
+    in_flight: u64,
+    /// Last activity per session; sessions with a request in flight count
+    /// as active regardless of age
+    sessions: HashMap<String, (u64, Instant)>,
+    last_error: Option<LastError>,
+}
+
+/// Shared counters describing what an instance is doing right now
+#[derive(Clone, Default)]
+pub struct Liveness {
+    state: Arc<Mutex<LivenessState>>,
+}
+
+impl Liveness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request for `session_id` as queued until the guard is dropped
+    pub fn begin(&self, session_id: &str) -> InFlight {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight += 1;
+        let entry = state
+            .sessions
+            .entry(session_id.to_string())
+            .or_insert((0, Instant::now()));
+        entry.0 += 1;
+        entry.1 = Instant::now();
+        InFlight {
+            liveness: self.clone(),
+            session_id: session_id.to_string(),
+        }
+    }
+
+    /// Remember `err` as the last error, classified by its [`ErrorCode`]
+    pub fn record_error(&self, err: &anyhow::Error) {
+        self.state.lock().unwrap().last_error = Some(LastError {
+            message: format!("{:#}", err),
+            code: code_of(err),
+            at: Utc::now(),
+        });
+    }
+
+    /// Requests accepted but not yet answered
+    pub fn queue_depth(&self) -> u64 {
+        self.state.lock().unwrap().in_flight
+    }
+
+    /// Sessions with a request in flight or activity within `idle`
+    pub fn active_sessions(&self, idle: Duration) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state
+            .sessions
+            .retain(|_, (in_flight, last_seen)| *in_flight > 0 || last_seen.elapsed() < idle);
+        state.sessions.len() as u64
+    }
+
+    pub fn last_error(&self) -> Option<LastError> {
+        self.state.lock().unwrap().last_error.clone()
+    }
+}
+
+/// Guard returned by [`Liveness::begin`]
+pub struct InFlight {
+    liveness: Liveness,
+    session_id: String,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut state = self.liveness.state.lock().unwrap();
+        state.in_flight = state.in_flight.saturating_sub(1);
+        if let Some(entry) = state.sessions.get_mut(&self.session_id) {
+            entry.0 = entry.0.saturating_sub(1);
+            entry.1 = Instant::now();
+        }
+    }
+}
+
+/// Builds and publishes the liveness reports of one instance
+#[derive(Clone)]
+pub struct Heartbeat {
+    instance_id: String,
+    hostname: String,
+    role: String,
+    started_at: DateTime<Utc>,
+    liveness: Liveness,
+    interval: Duration,
+    session_idle: Duration,
+}
+
+impl Heartbeat {
+    pub fn new(
+        instance_id: impl Into<String>,
+        role: impl Into<String>,
+        liveness: Liveness,
+        config: &HeartbeatConfig,
+    ) -> Self {
+        let hostname = hostname::get()
+            .ok()
+            .and_then(|h| h.into_string().ok())
+            .unwrap_or_else(|| "unknown".to_string());
+        Self {
+            instance_id: instance_id.into(),
+            hostname,
+            role: role.into(),
+            started_at: Utc::now(),
+            liveness,
+            interval: Duration::from_secs(config.interval_secs.max(1)),
+            session_idle: Duration::from_secs(config.session_idle_secs),
+        }
+    }
+
+    /// Current liveness report with the given status
+    pub fn record(&self, status: &str) -> HeartbeatRecord {
+        let last_error = self.liveness.last_error();
+        HeartbeatRecord {
+            instance_id: self.instance_id.clone(),
+            hostname: self.hostname.clone(),
+            pid: std::process::id(),
+            role: self.role.clone(),
+            status: status.to_string(),
+            active_sessions: self.liveness.active_sessions(self.session_idle),
+            queue_depth: self.liveness.queue_depth(),
+            last_error: last_error.as_ref().map(|e| e.message.clone()),
+            last_error_code: last_error.as_ref().map(|e| e.code.as_str().to_string()),
+            last_error_at: last_error.map(|e| e.at),
+            started_at: self.started_at,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Current report as mesh heartbeat metrics, readable with [`from_metrics`]
+    pub fn metrics(&self) -> HashMap<String, Value> {
+        match serde_json::to_value(self.record(STATUS_RUNNING)) {
+            Ok(Value::Object(map)) => map.into_iter().collect(),
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Write a report every interval until `shutdown` is cancelled, then a
+    /// last one marking the instance as stopped
+    ///
+    /// Failed writes are logged and retried on the next tick, so a busy
+    /// database does not end the heartbeat.
+    pub fn spawn(
+        &self,
+        persistence: Persistence,
+        shutdown: CancellationToken,
+    ) -> JoinHandle<Result<()>> {
+        let heartbeat = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(heartbeat.interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+                if let Err(e) = persistence.record_heartbeat(&heartbeat.record(STATUS_RUNNING)) {
+                    tracing::warn!("Failed to record heartbeat: {:#}", e);
+                }
+            }
+            persistence.record_heartbeat(&heartbeat.record(STATUS_STOPPED))
+        })
+    }
+}
+
+/// Report carried in the metrics of a mesh heartbeat, if the instance sent one
+pub fn from_metrics(metrics: &HashMap<String, Value>) -> Option<HeartbeatRecord> {
+    let map = metrics
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    serde_json::from_value(Value::Object(map)).ok()
+}
+
+/// Status to show for `record`: running instances that have not reported
+/// within `stale_after` are shown as stale
+pub fn effective_status<'a>(
+    record: &'a HeartbeatRecord,
+    now: DateTime<Utc>,
+    stale_after: Duration,
+) -> &'a str {
+    let age = (now - record.updated_at).to_std().unwrap_or_default();
+    if record.status == STATUS_RUNNING && age > stale_after {
+        STATUS_STALE
+    } else {
+        record.status.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    fn heartbeat(liveness: Liveness) -> Heartbeat {
+        Heartbeat::new("host-1", "server", liveness, &HeartbeatConfig::default())
+    }
+
+    #[test]
+    fn liveness_tracks_requests_sessions_and_errors() {
+        let liveness = Liveness::new();
+        let first = liveness.begin("a");
+        let _second = liveness.begin("a");
+        let _third = liveness.begin("b");
+        assert_eq!(liveness.queue_depth(), 3);
+        assert_eq!(liveness.active_sessions(Duration::ZERO), 2);
+
+        drop(first);
+        assert_eq!(liveness.queue_depth(), 2);
+
+        liveness.record_error(&anyhow::Error::from(Error::Provider {
+            provider: "OpenAI".to_string(),
+            status: 429,
+            message: "slow down".to_string(),
+        }));
+        let record = heartbeat(liveness.clone()).record(STATUS_RUNNING);
+        assert_eq!(record.queue_depth, 2);
+        assert_eq!(record.active_sessions, 2);
+        assert_eq!(record.last_error_code.as_deref(), Some("rate_limited"));
+        assert_eq!(
+            record.last_error.as_deref(),
+            Some("OpenAI API error (429): slow down")
+        );
+    }
+
+    #[test]
+    fn idle_sessions_expire_once_finished() {
+        let liveness = Liveness::new();
+        drop(liveness.begin("done"));
+        let _running = liveness.begin("running");
+        assert_eq!(liveness.active_sessions(Duration::from_secs(60)), 2);
+        assert_eq!(liveness.active_sessions(Duration::ZERO), 1);
+    }
+
+    #[test]
+    fn metrics_round_trip_and_staleness() {
+        let heartbeat = heartbeat(Liveness::new());
+        let record = from_metrics(&heartbeat.metrics()).unwrap();
+        assert_eq!(record.instance_id, "host-1");
+        assert_eq!(record.status, STATUS_RUNNING);
+        assert!(from_metrics(&HashMap::new()).is_none());
+
+        let stale_after = Duration::from_secs(60);
+        let now = record.updated_at + chrono::Duration::seconds(30);
+        assert_eq!(effective_status(&record, now, stale_after), STATUS_RUNNING);
+        let later = record.updated_at + chrono::Duration::seconds(90);
+        assert_eq!(effective_status(&record, later, stale_after), STATUS_STALE);
+
+        let stopped = HeartbeatRecord {
+            status: STATUS_STOPPED.to_string(),
+            ..record
+        };
+        assert_eq!(
+            effective_status(&stopped, later, stale_after),
+            STATUS_STOPPED
+        );
+    }
+
+    #[tokio::test]
+    async fn spawned_heartbeat_marks_clean_shutdown() {
+        let persistence = Persistence::in_memory().unwrap();
+        let shutdown = CancellationToken::new();
+        let task = heartbeat(Liveness::new()).spawn(persistence.clone(), shutdown.clone());
+
+        shutdown.cancel();
+        task.await.unwrap().unwrap();
+
+        let records = persistence.list_heartbeats().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, STATUS_STOPPED);
+        assert_eq!(records[0].pid, std::process::id());
+    }
+}
@@ -1,14 +1,37 @@
 pub mod agent;
 pub mod bootstrap_self;
+pub mod broadcast;
+#[cfg(feature = "chaos")]
+pub mod chaos;
 pub mod cli;
+#[cfg(feature = "code-index")]
+pub mod code_index;
 pub mod embeddings;
-#[cfg(feature = "api")]
+pub mod error;
+pub mod events;
+pub mod graph_export;
+pub mod heartbeat;
+pub mod maintenance;
+pub mod mcp;
+#[cfg(feature = "mesh")]
 pub mod mesh;
+#[cfg(feature = "reqwest")]
+pub mod openapi;
+pub mod presence;
+pub mod profile_archive;
+pub mod prompts;
+pub mod shutdown;
 pub mod spec;
+pub mod startup;
 #[cfg(feature = "api")]
 pub mod sync;
+pub mod tasks;
+pub mod telemetry;
 pub mod test_utils;
 pub mod tools;
+pub mod vectors;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 /// Reserved namespace for graphs that participate in distributed sync.
 pub const SYNC_GRAPH_NAMESPACE: &str = "graph-sync";
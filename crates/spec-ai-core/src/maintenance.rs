@@ -0,0 +1,271 @@
+//! Periodic maintenance for long-running instances
+//!
+//! Servers apply the `[retention]` limits on an interval so sessions, audit
+//! records, heartbeats, the graph changelog and usage records do not grow
+//! without bound. Each run logs what it removed from every store.
+//!
+//! With `[graph_maintenance]` enabled they also run the built-in graph
+//! maintenance workflows of `spec-ai-collective` on their schedules, in
+//! builds with the `graph-maintenance` feature.
+
+use anyhow::Result;
+use chrono::Utc;
+#[cfg(feature = "graph-maintenance")]
+use serde_json::json;
+#[cfg(feature = "graph-maintenance")]
+use spec_ai_collective::{MaintenanceJob, MaintenanceWorkflows};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::config::{GraphMaintenanceConfig, RetentionConfig};
+use crate::persistence::retention::PruneReport;
+use crate::persistence::Persistence;
+use crate::shutdown::CancellationToken;
+
+/// Apply `config` now, off the async runtime
+pub async fn run_retention(
+    persistence: Persistence,
+    config: RetentionConfig,
+) -> Result<Vec<PruneReport>> {
+    tokio::task::spawn_blocking(move || persistence.apply_retention(&config, Utc::now())).await?
+}
+
+/// Apply `config` every `interval_secs` until `shutdown` is cancelled
+///
+/// The first run happens right away. Failed runs are logged and retried on
+/// the next tick, so one busy moment does not stop maintenance.
+pub fn spawn_retention(
+    persistence: Persistence,
+    config: RetentionConfig,
+    shutdown: CancellationToken,
+) -> JoinHandle<Result<()>> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            match run_retention(persistence.clone(), config.clone()).await {
+                Ok(reports) => log_reports(&reports),
+                Err(e) => tracing::warn!("Retention run failed: {:#}", e),
+            }
+        }
+        Ok(())
+    })
+}
+
+fn log_reports(reports: &[PruneReport]) {
+    for report in reports.iter().filter(|report| report.total() > 0) {
+        tracing::info!(
+            store = report.store,
+            by_age = report.by_age,
+            by_size = report.by_size,
+            "Pruned {} {} entries",
+            report.total(),
+            report.store
+        );
+    }
+}
+
+/// How often the graph maintenance schedule is checked for due jobs
+#[cfg(feature = "graph-maintenance")]
+const GRAPH_MAINTENANCE_TICK: Duration = Duration::from_secs(60);
+
+/// Run the enabled `[graph_maintenance]` jobs on their schedules until
+/// `shutdown` is cancelled
+///
+/// Every job runs right away and then once per interval, one job at a time
+/// and off the async runtime. A failed job is logged and tried again at its
+/// next run.
+#[cfg(feature = "graph-maintenance")]
+pub fn spawn_graph_maintenance(
+    persistence: Persistence,
+    config: GraphMaintenanceConfig,
+    shutdown: CancellationToken,
+) -> JoinHandle<Result<()>> {
+    tokio::spawn(async move {
+        let mut workflows = MaintenanceWorkflows::new(
+            persistence.instance_id().to_string(),
+            graph_schedule(&config),
+        )?;
+        let mut interval = tokio::time::interval(GRAPH_MAINTENANCE_TICK);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            workflows = run_due_graph_jobs(workflows, persistence.clone(), config.clone()).await?;
+        }
+        Ok(())
+    })
+}
+
+/// Without the `graph-maintenance` feature there are no workflows to run, so
+/// this only warns and returns a finished task
+#[cfg(not(feature = "graph-maintenance"))]
+pub fn spawn_graph_maintenance(
+    _persistence: Persistence,
+    _config: GraphMaintenanceConfig,
+    _shutdown: CancellationToken,
+) -> JoinHandle<Result<()>> {
+    tracing::warn!(
+        "graph_maintenance.enabled is set, but spec-ai was built without the \
+         `graph-maintenance` feature; graph maintenance will not run"
+    );
+    tokio::spawn(async { Ok(()) })
+}
+
+/// The enabled jobs of `config` with their intervals
+#[cfg(feature = "graph-maintenance")]
+fn graph_schedule(config: &GraphMaintenanceConfig) -> Vec<(MaintenanceJob, Duration)> {
+    let jobs = config.jobs();
+    MaintenanceJob::ALL
+        .into_iter()
+        .filter_map(|job| {
+            let (_, job_config) = jobs.iter().find(|(name, _)| *name == job.name())?;
+            let every = job_config
+                .interval_secs
+                .map(Duration::from_secs)
+                .unwrap_or_else(|| job.default_interval());
+            job_config.enabled.then_some((job, every))
+        })
+        .collect()
+}
+
+#[cfg(feature = "graph-maintenance")]
+async fn run_due_graph_jobs(
+    mut workflows: MaintenanceWorkflows,
+    persistence: Persistence,
+    config: GraphMaintenanceConfig,
+) -> Result<MaintenanceWorkflows> {
+    Ok(tokio::task::spawn_blocking(move || {
+        let now = Utc::now();
+        for job in workflows.due(now) {
+            match workflows.run(job, now, |_| run_graph_job(&persistence, &config, job)) {
+                Ok(results) => tracing::debug!(
+                    job = job.name(),
+                    "Graph maintenance finished: {}",
+                    json!(results)
+                ),
+                Err(e) => tracing::warn!("Graph maintenance failed: {}", e),
+            }
+        }
+        workflows
+    })
+    .await?)
+}
+
+/// Do the work of `job`, whose templates have a single stage
+#[cfg(feature = "graph-maintenance")]
+fn run_graph_job(
+    persistence: &Persistence,
+    config: &GraphMaintenanceConfig,
+    job: MaintenanceJob,
+) -> Result<serde_json::Value> {
+    let graph = persistence.graph_store();
+    Ok(match job {
+        MaintenanceJob::TombstoneGc => {
+            let grace = chrono::Duration::days(config.tombstone_grace_days as i64);
+            json!(graph.purge_tombstones(Utc::now() - grace)?)
+        }
+        MaintenanceJob::IndexRebuild => json!({ "indexes": graph.rebuild_indexes()? }),
+        MaintenanceJob::EntityResolution => json!(graph.resolve_entities()?),
+        MaintenanceJob::Summarization => {
+            json!({ "summaries": graph.summarize_sessions(config.summary_top_entities)? })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RetentionRule;
+    use crate::types::MessageRole;
+    #[cfg(feature = "graph-maintenance")]
+    use crate::types::NodeType;
+    #[cfg(feature = "graph-maintenance")]
+    use spec_ai_knowledge_graph::GRAPH_SUMMARY_LABEL;
+
+    #[tokio::test]
+    async fn spawned_retention_prunes_until_cancelled() {
+        let persistence = Persistence::in_memory().unwrap();
+        for session in ["a", "b", "c"] {
+            persistence
+                .insert_message(session, MessageRole::User, "hi")
+                .unwrap();
+        }
+        let config = RetentionConfig {
+            enabled: true,
+            sessions: RetentionRule {
+                max_age_days: None,
+                max_entries: Some(1),
+            },
+            ..RetentionConfig::default()
+        };
+
+        let shutdown = CancellationToken::new();
+        let task = spawn_retention(persistence.clone(), config, shutdown.clone());
+        for _ in 0..100 {
+            if persistence.list_sessions().unwrap().len() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        shutdown.cancel();
+        task.await.unwrap().unwrap();
+
+        assert_eq!(persistence.list_sessions().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "graph-maintenance")]
+    #[tokio::test]
+    async fn spawned_graph_maintenance_runs_enabled_jobs() {
+        let persistence = Persistence::in_memory().unwrap();
+        for label in ["Ada", "ADA", "Bob"] {
+            persistence
+                .insert_graph_node("s", NodeType::Entity, label, &serde_json::json!({}), None)
+                .unwrap();
+        }
+        let mut config = GraphMaintenanceConfig {
+            enabled: true,
+            ..GraphMaintenanceConfig::default()
+        };
+        config.index_rebuild.enabled = false;
+        assert_eq!(
+            graph_schedule(&config)
+                .iter()
+                .map(|(job, _)| *job)
+                .collect::<Vec<_>>(),
+            vec![
+                MaintenanceJob::TombstoneGc,
+                MaintenanceJob::EntityResolution,
+                MaintenanceJob::Summarization
+            ]
+        );
+
+        let shutdown = CancellationToken::new();
+        let task = spawn_graph_maintenance(persistence.clone(), config, shutdown.clone());
+        let summarized = || {
+            persistence
+                .list_graph_nodes("s", Some(NodeType::Concept), None)
+                .unwrap()
+                .iter()
+                .any(|node| node.label == GRAPH_SUMMARY_LABEL)
+        };
+        for _ in 0..100 {
+            if summarized() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        shutdown.cancel();
+        task.await.unwrap().unwrap();
+
+        assert!(summarized());
+        let entities = persistence
+            .list_graph_nodes("s", Some(NodeType::Entity), None)
+            .unwrap();
+        assert_eq!(entities.len(), 2);
+    }
+}
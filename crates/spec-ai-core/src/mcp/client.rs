@@ -0,0 +1,494 @@
+//! JSON-RPC client for one MCP server
+//!
+//! Transports only move messages: each one feeds what the server sends to
+//! [`Connection::dispatch`] and writes what it receives on the outgoing
+//! channel, so requests, timeouts and server pings are handled here the same
+//! way for stdio and SSE servers.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+use crate::tools::ToolResult;
+
+/// Protocol revision sent in `initialize`
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC code for a method the client does not implement
+const METHOD_NOT_FOUND: i64 = -32601;
+
+type Reply = std::result::Result<Value, String>;
+
+/// A tool as listed by `tools/list`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+    /// Schema of the tool's `structuredContent`, from servers that send one
+    #[serde(default)]
+    pub output_schema: Option<Value>,
+}
+
+/// A resource as listed by `resources/list`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResourceInfo {
+    pub uri: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+}
+
+/// Requests waiting for an answer, shared between a client and the task
+/// reading its transport
+#[derive(Default)]
+pub(super) struct Connection {
+    pending: Mutex<HashMap<u64, oneshot::Sender<Reply>>>,
+    closed: AtomicBool,
+}
+
+impl Connection {
+    /// Handle one message from the server, returning the reply to send when
+    /// it is a request of its own
+    pub(super) fn dispatch(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        if let Some(method) = message.get("method").and_then(Value::as_str) {
+            let id = id?;
+            return Some(match method {
+                "ping" => json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": METHOD_NOT_FOUND, "message": format!("Method not found: {}", method) },
+                }),
+            });
+        }
+
+        let Some(id) = id.as_ref().and_then(Value::as_u64) else {
+            debug!("Ignoring MCP message without a request id: {}", message);
+            return None;
+        };
+        let reply = match message.get("error") {
+            Some(error) => Err(error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string())),
+            None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+        };
+        if let Some(sender) = self.pending.lock().unwrap().remove(&id) {
+            let _ = sender.send(reply);
+        }
+        None
+    }
+
+    /// Fail the request with `id`, such as when it could not be sent
+    pub(super) fn fail(&self, id: u64, reason: &str) {
+        if let Some(sender) = self.pending.lock().unwrap().remove(&id) {
+            let _ = sender.send(Err(reason.to_string()));
+        }
+    }
+
+    /// Mark the server gone and fail every request still waiting
+    pub(super) fn close(&self, reason: &str) {
+        self.closed.store(true, Ordering::SeqCst);
+        for (_, sender) in self.pending.lock().unwrap().drain() {
+            let _ = sender.send(Err(reason.to_string()));
+        }
+    }
+}
+
+/// An initialized connection to an MCP server
+pub struct McpClient {
+    server: String,
+    connection: Arc<Connection>,
+    outgoing: mpsc::UnboundedSender<Value>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    capabilities: Value,
+    /// Stdio server process, killed when the client is dropped
+    _child: Option<tokio::process::Child>,
+}
+
+impl McpClient {
+    /// Start a client over a transport that delivers messages sent on
+    /// `outgoing` and passes what the server sends to `connection`
+    pub(super) fn new(
+        server: impl Into<String>,
+        connection: Arc<Connection>,
+        outgoing: mpsc::UnboundedSender<Value>,
+        timeout: Duration,
+        child: Option<tokio::process::Child>,
+    ) -> Self {
+        Self {
+            server: server.into(),
+            connection,
+            outgoing,
+            next_id: AtomicU64::new(1),
+            timeout,
+            capabilities: Value::Null,
+            _child: child,
+        }
+    }
+
+    /// Connect over a pair of byte streams carrying newline-delimited JSON,
+    /// as stdio servers do
+    pub fn over_streams<R, W>(
+        server: impl Into<String>,
+        reader: R,
+        writer: W,
+        timeout: Duration,
+        child: Option<tokio::process::Child>,
+    ) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let server = server.into();
+        let connection = Arc::new(Connection::default());
+        let (outgoing, mut messages) = mpsc::unbounded_channel::<Value>();
+
+        let mut writer = writer;
+        tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                let mut line = message.to_string();
+                line.push('\n');
+                if writer.write_all(line.as_bytes()).await.is_err() || writer.flush().await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let reader_connection = connection.clone();
+        // A weak sender, so the writer stops once the client is dropped
+        let replies = outgoing.downgrade();
+        let name = server.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            loop {
+                match lines.next_line().await {
+                    Ok(Some(line)) if line.trim().is_empty() => {}
+                    Ok(Some(line)) => match serde_json::from_str::<Value>(&line) {
+                        Ok(message) => {
+                            if let (Some(reply), Some(replies)) =
+                                (reader_connection.dispatch(message), replies.upgrade())
+                            {
+                                let _ = replies.send(reply);
+                            }
+                        }
+                        Err(err) => debug!("MCP server '{}' sent non-JSON output: {}", name, err),
+                    },
+                    Ok(None) => {
+                        reader_connection.close("MCP server closed the connection");
+                        break;
+                    }
+                    Err(err) => {
+                        warn!("Failed to read from MCP server '{}': {}", name, err);
+                        reader_connection.close(&err.to_string());
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self::new(server, connection, outgoing, timeout, child)
+    }
+
+    /// Name of the server in the configuration
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    /// Whether the server has gone away
+    pub fn is_closed(&self) -> bool {
+        self.connection.closed.load(Ordering::SeqCst)
+    }
+
+    /// Whether the server offers resources
+    pub fn has_resources(&self) -> bool {
+        self.capabilities.get("resources").is_some()
+    }
+
+    /// Send a request and wait for its result
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        if self.is_closed() {
+            bail!("MCP server '{}' is not running", self.server);
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = oneshot::channel();
+        self.connection.pending.lock().unwrap().insert(id, sender);
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if self.outgoing.send(message).is_err() {
+            self.connection.fail(id, "connection closed");
+        }
+
+        match tokio::time::timeout(self.timeout, receiver).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(error))) => Err(anyhow!(
+                "MCP server '{}' failed {}: {}",
+                self.server,
+                method,
+                error
+            )),
+            Ok(Err(_)) => Err(anyhow!("MCP server '{}' dropped {}", self.server, method)),
+            Err(_) => {
+                self.connection.pending.lock().unwrap().remove(&id);
+                Err(anyhow!(
+                    "MCP server '{}' did not answer {} within {}s",
+                    self.server,
+                    method,
+                    self.timeout.as_secs()
+                ))
+            }
+        }
+    }
+
+    /// Send a notification, which gets no answer
+    pub fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.outgoing
+            .send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .map_err(|_| anyhow!("MCP server '{}' is not running", self.server))
+    }
+
+    /// Perform the initialization handshake
+    pub async fn initialize(&mut self) -> Result<()> {
+        let result = self
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "spec-ai", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            )
+            .await?;
+        self.capabilities = result.get("capabilities").cloned().unwrap_or(Value::Null);
+        self.notify("notifications/initialized", json!({}))
+    }
+
+    /// Every tool the server offers
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>> {
+        self.list_all("tools/list", "tools").await
+    }
+
+    /// Every resource the server offers
+    pub async fn list_resources(&self) -> Result<Vec<McpResourceInfo>> {
+        self.list_all("resources/list", "resources").await
+    }
+
+    async fn list_all<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        field: &str,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let mut page = self.request(method, params).await?;
+            let listed: Vec<T> = serde_json::from_value(page[field].take())
+                .with_context(|| format!("Invalid {} from MCP server '{}'", method, self.server))?;
+            items.extend(listed);
+            cursor = page
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Call a tool on the server
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<ToolResult> {
+        let reply = self
+            .request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+            )
+            .await?;
+        let output = render_content(reply.get("content"));
+        let mut result = if reply.get("isError").and_then(Value::as_bool) == Some(true) {
+            ToolResult::failure(output)
+        } else {
+            ToolResult::success(output)
+        };
+        result.data = reply.get("structuredContent").cloned();
+        Ok(result)
+    }
+
+    /// Read a resource from the server
+    pub async fn read_resource(&self, uri: &str) -> Result<ToolResult> {
+        let result = self
+            .request("resources/read", json!({ "uri": uri }))
+            .await?;
+        let contents = result
+            .get("contents")
+            .and_then(Value::as_array)
+            .map(|contents| {
+                contents
+                    .iter()
+                    .map(render_resource)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        Ok(ToolResult::success(contents))
+    }
+}
+
+/// Text of a tool result's content, with placeholders for anything else
+fn render_content(content: Option<&Value>) -> String {
+    let Some(parts) = content.and_then(Value::as_array) else {
+        return String::new();
+    };
+    parts
+        .iter()
+        .map(|part| match part.get("type").and_then(Value::as_str) {
+            Some("text") => part["text"].as_str().unwrap_or_default().to_string(),
+            Some("resource") => render_resource(&part["resource"]),
+            Some(kind) => format!(
+                "[{} content: {}]",
+                kind,
+                part.get("mimeType")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown type")
+            ),
+            None => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_resource(resource: &Value) -> String {
+    match resource.get("text").and_then(Value::as_str) {
+        Some(text) => text.to_string(),
+        None => format!(
+            "[binary resource: {} ({})]",
+            resource["uri"].as_str().unwrap_or_default(),
+            resource
+                .get("mimeType")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown type")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, split};
+
+    /// Answer requests from the client side of `stream` like a small server
+    async fn serve(stream: tokio::io::DuplexStream) {
+        let (reader, mut writer) = split(stream);
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let request: Value = serde_json::from_str(&line).unwrap();
+            let Some(id) = request.get("id").cloned() else {
+                continue;
+            };
+            let result = match request["method"].as_str().unwrap() {
+                "initialize" => json!({ "capabilities": { "tools": {}, "resources": {} } }),
+                "tools/list" if request["params"]["cursor"].is_null() => json!({
+                    "tools": [{ "name": "echo", "inputSchema": { "type": "object" } }],
+                    "nextCursor": "2",
+                }),
+                "tools/list" => json!({ "tools": [{ "name": "fail" }] }),
+                "tools/call" if request["params"]["name"] == "echo" => json!({
+                    "content": [
+                        { "type": "text", "text": request["params"]["arguments"]["text"] },
+                        { "type": "image", "data": "", "mimeType": "image/png" },
+                    ],
+                    "structuredContent": { "text": request["params"]["arguments"]["text"] },
+                }),
+                "tools/call" => json!({
+                    "content": [{ "type": "text", "text": "boom" }],
+                    "isError": true,
+                }),
+                _ => {
+                    let error = json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -1, "message": "nope" } });
+                    writer
+                        .write_all(format!("{}\n", error).as_bytes())
+                        .await
+                        .unwrap();
+                    continue;
+                }
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            writer
+                .write_all(format!("{}\n", response).as_bytes())
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lists_and_calls_tools_over_streams() {
+        let (client_side, server_side) = duplex(4096);
+        tokio::spawn(serve(server_side));
+        let (reader, writer) = split(client_side);
+        let mut client =
+            McpClient::over_streams("test", reader, writer, Duration::from_secs(5), None);
+
+        client.initialize().await.unwrap();
+        assert!(client.has_resources());
+
+        let tools = client.list_tools().await.unwrap();
+        let names: Vec<_> = tools.iter().map(|tool| tool.name.as_str()).collect();
+        assert_eq!(names, ["echo", "fail"]);
+
+        let echoed = client
+            .call_tool("echo", json!({ "text": "hi" }))
+            .await
+            .unwrap();
+        assert!(echoed.success);
+        assert_eq!(echoed.output, "hi\n[image content: image/png]");
+        assert_eq!(echoed.data, Some(json!({ "text": "hi" })));
+
+        let failed = client.call_tool("fail", json!({})).await.unwrap();
+        assert!(!failed.success);
+        assert_eq!(failed.error.as_deref(), Some("boom"));
+
+        let err = client.read_resource("file:///x").await.unwrap_err();
+        assert!(err.to_string().contains("nope"));
+    }
+
+    #[test]
+    fn test_answers_server_requests() {
+        let connection = Connection::default();
+        let pong = connection
+            .dispatch(json!({ "jsonrpc": "2.0", "id": 7, "method": "ping" }))
+            .unwrap();
+        assert_eq!(pong["id"], 7);
+        assert_eq!(pong["result"], json!({}));
+
+        let unknown = connection
+            .dispatch(json!({ "jsonrpc": "2.0", "id": "a", "method": "sampling/createMessage" }))
+            .unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+
+        assert!(connection
+            .dispatch(json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" }))
+            .is_none());
+    }
+}
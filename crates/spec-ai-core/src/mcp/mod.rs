@@ -0,0 +1,153 @@
+//! Model Context Protocol client for external tool servers
+//!
+//! Servers configured under `[mcp.servers.<name>]` are started (stdio) or
+//! connected to (SSE) once per process, when the first agent is built, and
+//! kept for agents built later. Their tools are registered as
+//! `mcp_<server>_<tool>`, plus `mcp_<server>_read_resource` for servers that
+//! offer resources, and run through the same profile and policy checks as
+//! built-in tools, so a rule like `resource = "mcp_github_*"` covers a whole
+//! server.
+
+pub mod client;
+pub mod tool;
+mod transport;
+
+pub use client::{McpClient, McpResourceInfo, McpToolInfo};
+pub use tool::{tool_name, McpResourceTool, McpTool};
+pub use transport::connect;
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tracing::{info, warn};
+
+use crate::config::{McpConfig, McpServerConfig};
+use crate::tools::{Tool, ToolRegistry};
+
+/// A connected server and the tools it offers
+struct Server {
+    config: McpServerConfig,
+    client: Arc<McpClient>,
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+/// Servers connected so far, by name
+static SERVERS: OnceLock<Mutex<HashMap<String, Server>>> = OnceLock::new();
+
+/// Agent tools for everything a connected server offers
+pub async fn discover(client: Arc<McpClient>) -> Result<Vec<Arc<dyn Tool>>> {
+    let mut tools: Vec<Arc<dyn Tool>> = client
+        .list_tools()
+        .await?
+        .into_iter()
+        .map(|info| Arc::new(McpTool::new(client.clone(), info)) as Arc<dyn Tool>)
+        .collect();
+    if client.has_resources() {
+        match client.list_resources().await {
+            Ok(resources) => tools.push(Arc::new(McpResourceTool::new(client.clone(), &resources))),
+            Err(err) => warn!(
+                "Failed to list resources of MCP server '{}': {}",
+                client.server(),
+                err
+            ),
+        }
+    }
+    Ok(tools)
+}
+
+async fn start(name: &str, config: &McpServerConfig) -> Result<Server> {
+    let client = Arc::new(connect(name, config).await?);
+    let tools = discover(client.clone()).await?;
+    Ok(Server {
+        config: config.clone(),
+        client,
+        tools,
+    })
+}
+
+/// Register the tools of every enabled server in `config`, connecting to
+/// those not yet connected, or whose settings changed or connection closed
+///
+/// Servers that can't be reached are skipped with a warning and tried again
+/// when the next agent is built. Tools never replace ones already registered.
+pub fn register_tools(registry: &mut ToolRegistry, config: &McpConfig) {
+    let enabled: Vec<(&String, &McpServerConfig)> = config
+        .servers
+        .iter()
+        .filter(|(_, server)| server.enabled)
+        .collect();
+    if enabled.is_empty() {
+        return;
+    }
+
+    // Connections outlive the agent being built, so they need a runtime that
+    // keeps running their tasks while this thread waits for the handshake
+    let handle = match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => handle,
+        _ => {
+            warn!(
+                "MCP servers need a multi-threaded Tokio runtime; skipping {} configured server(s)",
+                enabled.len()
+            );
+            return;
+        }
+    };
+
+    let servers = SERVERS.get_or_init(Default::default);
+    let to_start: Vec<(&String, &McpServerConfig)> = {
+        let connected = servers.lock().unwrap();
+        enabled
+            .iter()
+            .copied()
+            .filter(|(name, server)| {
+                connected.get(*name).is_none_or(|existing| {
+                    existing.config != **server || existing.client.is_closed()
+                })
+            })
+            .collect()
+    };
+
+    if !to_start.is_empty() {
+        let started = tokio::task::block_in_place(|| {
+            handle.block_on(futures::future::join_all(to_start.iter().map(
+                |(name, server)| async move { (*name, start(name, server).await) },
+            )))
+        });
+        let mut connected = servers.lock().unwrap();
+        for (name, result) in started {
+            match result {
+                Ok(server) => {
+                    info!(
+                        "Connected to MCP server '{}' with {} tools",
+                        name,
+                        server.tools.len()
+                    );
+                    connected.insert(name.clone(), server);
+                }
+                Err(err) => {
+                    warn!("Skipping MCP server '{}': {:#}", name, err);
+                    connected.remove(name);
+                }
+            }
+        }
+    }
+
+    let connected = servers.lock().unwrap();
+    for (name, _) in enabled {
+        let Some(server) = connected.get(name) else {
+            continue;
+        };
+        for tool in &server.tools {
+            if registry.has(tool.name()) {
+                warn!(
+                    "MCP tool '{}' from '{}' would override an existing tool, skipping",
+                    tool.name(),
+                    name
+                );
+                continue;
+            }
+            registry.register(tool.clone());
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Tools and resources of an MCP server offered as agent tools
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::client::{McpClient, McpResourceInfo, McpToolInfo};
+use crate::tools::{Tool, ToolResult};
+
+/// Longest tool name providers accept
+const MAX_TOOL_NAME: usize = 64;
+
+/// Most resources listed in the description of a read_resource tool
+const MAX_LISTED_RESOURCES: usize = 50;
+
+/// Name an MCP tool is registered under: `mcp_<server>_<tool>`, limited to
+/// the characters and length providers accept for function names
+pub fn tool_name(server: &str, tool: &str) -> String {
+    let name: String = format!("mcp_{}_{}", server, tool)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.chars().take(MAX_TOOL_NAME).collect()
+}
+
+/// A tool offered by an MCP server
+pub struct McpTool {
+    client: Arc<McpClient>,
+    name: String,
+    remote_name: String,
+    description: String,
+    parameters: Value,
+    output_schema: Option<Value>,
+}
+
+impl McpTool {
+    pub fn new(client: Arc<McpClient>, info: McpToolInfo) -> Self {
+        let description = match info.description.as_deref().map(str::trim) {
+            Some(description) if !description.is_empty() => {
+                format!("{} (MCP server '{}')", description, client.server())
+            }
+            _ => format!("Tool '{}' from MCP server '{}'", info.name, client.server()),
+        };
+        let parameters = match info.input_schema {
+            Some(schema) if schema.is_object() => schema,
+            _ => json!({ "type": "object", "properties": {} }),
+        };
+        Self {
+            name: tool_name(client.server(), &info.name),
+            remote_name: info.name,
+            description,
+            parameters,
+            output_schema: info.output_schema.filter(Value::is_object),
+            client,
+        }
+    }
+
+    /// Name of the tool on its server
+    pub fn remote_name(&self) -> &str {
+        &self.remote_name
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.parameters.clone()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.output_schema.clone()
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args = if args.is_null() { json!({}) } else { args };
+        self.client.call_tool(&self.remote_name, args).await
+    }
+}
+
+/// Reads resources from an MCP server, named `mcp_<server>_read_resource`
+pub struct McpResourceTool {
+    client: Arc<McpClient>,
+    name: String,
+    description: String,
+}
+
+impl McpResourceTool {
+    pub fn new(client: Arc<McpClient>, resources: &[McpResourceInfo]) -> Self {
+        let mut description = format!(
+            "Read a resource from MCP server '{}' by URI.",
+            client.server()
+        );
+        if !resources.is_empty() {
+            description.push_str(" Available resources:");
+            for resource in resources.iter().take(MAX_LISTED_RESOURCES) {
+                description.push_str(&format!("\n- {}", resource.uri));
+                if let Some(label) = resource.description.as_ref().or(resource.name.as_ref()) {
+                    description.push_str(&format!(": {}", label));
+                }
+            }
+            if resources.len() > MAX_LISTED_RESOURCES {
+                description.push_str(&format!(
+                    "\n- and {} more",
+                    resources.len() - MAX_LISTED_RESOURCES
+                ));
+            }
+        }
+        Self {
+            name: tool_name(client.server(), "read_resource"),
+            description,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for McpResourceTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "uri": {
+                    "type": "string",
+                    "description": "URI of the resource to read"
+                }
+            },
+            "required": ["uri"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let uri = args
+            .get("uri")
+            .and_then(Value::as_str)
+            .context("Missing required parameter 'uri'")?;
+        self.client.read_resource(uri).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_names_are_prefixed_and_sanitized() {
+        assert_eq!(
+            tool_name("github", "create_issue"),
+            "mcp_github_create_issue"
+        );
+        assert_eq!(tool_name("fs", "read.file v2"), "mcp_fs_read_file_v2");
+        assert_eq!(tool_name("docs", &"x".repeat(100)).len(), MAX_TOOL_NAME);
+    }
+}
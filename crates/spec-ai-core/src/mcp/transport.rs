@@ -0,0 +1,280 @@
+//! Starting stdio servers and connecting to SSE servers
+
+use anyhow::{bail, Context, Result};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tracing::debug;
+
+use super::client::McpClient;
+use crate::config::McpServerConfig;
+
+/// Connect to the server `name` and perform the handshake
+pub async fn connect(name: &str, config: &McpServerConfig) -> Result<McpClient> {
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let mut client = match (&config.command, &config.url) {
+        (Some(command), _) => spawn(name, command, config, timeout)?,
+        (None, Some(url)) => sse::connect(name, url, config, timeout).await?,
+        (None, None) => bail!("MCP server '{}' has no command or url", name),
+    };
+    tokio::time::timeout(timeout, client.initialize())
+        .await
+        .with_context(|| format!("MCP server '{}' did not initialize", name))??;
+    Ok(client)
+}
+
+/// Start a stdio server, logging what it writes to stderr
+fn spawn(
+    name: &str,
+    command: &str,
+    config: &McpServerConfig,
+    timeout: Duration,
+) -> Result<McpClient> {
+    let mut child = Command::new(command)
+        .args(&config.args)
+        .envs(&config.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start MCP server '{}' ({})", name, command))?;
+
+    let stdin = child.stdin.take().context("MCP server stdin unavailable")?;
+    let stdout = child
+        .stdout
+        .take()
+        .context("MCP server stdout unavailable")?;
+    if let Some(stderr) = child.stderr.take() {
+        let server = name.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                debug!("MCP server '{}': {}", server, line);
+            }
+        });
+    }
+
+    Ok(McpClient::over_streams(
+        name,
+        stdout,
+        stdin,
+        timeout,
+        Some(child),
+    ))
+}
+
+#[cfg(feature = "reqwest")]
+mod sse {
+    use anyhow::{Context, Result};
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT};
+    use reqwest::Url;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, oneshot};
+    use tracing::{debug, warn};
+
+    use crate::config::McpServerConfig;
+    use crate::mcp::client::{Connection, McpClient};
+
+    /// Open the event stream, wait for the server to name the endpoint that
+    /// takes messages, then post requests to it
+    pub async fn connect(
+        name: &str,
+        url: &str,
+        config: &McpServerConfig,
+        timeout: Duration,
+    ) -> Result<McpClient> {
+        let base = Url::parse(url).with_context(|| format!("Invalid MCP server url {}", url))?;
+        let mut headers = HeaderMap::new();
+        for (key, value) in &config.headers {
+            headers.insert(
+                HeaderName::from_bytes(key.as_bytes())
+                    .with_context(|| format!("Invalid MCP header name {}", key))?,
+                HeaderValue::from_str(value)
+                    .with_context(|| format!("Invalid value for MCP header {}", key))?,
+            );
+        }
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+
+        let mut response = http
+            .get(base.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to connect to MCP server '{}'", name))?;
+
+        let connection = Arc::new(Connection::default());
+        let (outgoing, mut messages) = mpsc::unbounded_channel::<Value>();
+        let (endpoint_tx, endpoint_rx) = oneshot::channel::<Url>();
+
+        let reader_connection = connection.clone();
+        let replies = outgoing.downgrade();
+        // Dropped by the writer once the client is gone, ending the stream
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+        let server = name.to_string();
+        tokio::spawn(async move {
+            let mut endpoint_tx = Some(endpoint_tx);
+            let mut events = EventParser::default();
+            loop {
+                let next = tokio::select! {
+                    next = response.chunk() => next,
+                    _ = &mut stop_rx => break,
+                };
+                let chunk = match next {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => {
+                        reader_connection.close("MCP server closed the event stream");
+                        break;
+                    }
+                    Err(err) => {
+                        warn!("MCP server '{}' event stream failed: {}", server, err);
+                        reader_connection.close(&err.to_string());
+                        break;
+                    }
+                };
+                for (event, data) in events.push(&chunk) {
+                    match event.as_str() {
+                        "endpoint" => match base.join(data.trim()) {
+                            Ok(endpoint) => {
+                                if let Some(tx) = endpoint_tx.take() {
+                                    let _ = tx.send(endpoint);
+                                }
+                            }
+                            Err(err) => warn!(
+                                "MCP server '{}' sent an invalid endpoint {}: {}",
+                                server, data, err
+                            ),
+                        },
+                        "message" => match serde_json::from_str::<Value>(&data) {
+                            Ok(message) => {
+                                if let (Some(reply), Some(replies)) =
+                                    (reader_connection.dispatch(message), replies.upgrade())
+                                {
+                                    let _ = replies.send(reply);
+                                }
+                            }
+                            Err(err) => {
+                                debug!("MCP server '{}' sent a non-JSON message: {}", server, err)
+                            }
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        });
+
+        let endpoint = tokio::time::timeout(timeout, endpoint_rx)
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .with_context(|| format!("MCP server '{}' did not send its endpoint", name))?;
+
+        let writer_connection = connection.clone();
+        let server = name.to_string();
+        tokio::spawn(async move {
+            let _stop = stop_tx;
+            while let Some(message) = messages.recv().await {
+                let sent = http
+                    .post(endpoint.clone())
+                    .json(&message)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(err) = sent {
+                    warn!("Failed to send to MCP server '{}': {}", server, err);
+                    if let Some(id) = message.get("id").and_then(Value::as_u64) {
+                        writer_connection.fail(id, &err.to_string());
+                    }
+                }
+            }
+        });
+
+        Ok(McpClient::new(name, connection, outgoing, timeout, None))
+    }
+
+    /// Splits a server-sent event stream into `(event, data)` pairs
+    #[derive(Default)]
+    pub(super) struct EventParser {
+        buffer: Vec<u8>,
+    }
+
+    impl EventParser {
+        /// Add received bytes, returning the events they complete
+        pub(super) fn push(&mut self, bytes: &[u8]) -> Vec<(String, String)> {
+            self.buffer
+                .extend(bytes.iter().filter(|&&byte| byte != b'\r'));
+            let mut events = Vec::new();
+            while let Some(end) = self.buffer.windows(2).position(|pair| pair == b"\n\n") {
+                let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                let block = String::from_utf8_lossy(&block);
+                let mut event = "message".to_string();
+                let mut data = Vec::new();
+                for line in block.lines() {
+                    let (field, value) = line.split_once(':').unwrap_or((line, ""));
+                    let value = value.strip_prefix(' ').unwrap_or(value);
+                    match field {
+                        "event" => event = value.to_string(),
+                        "data" => data.push(value),
+                        _ => {}
+                    }
+                }
+                if !data.is_empty() {
+                    events.push((event, data.join("\n")));
+                }
+            }
+            events
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_parses_events_split_across_chunks() {
+            let mut parser = EventParser::default();
+            assert!(parser
+                .push(b"event: endpoint\r\ndata: /messages?session=1\r")
+                .is_empty());
+            let events = parser.push(b"\n\r\n: keep-alive\n\ndata: {\"id\":1}\n\nevent: mess");
+            assert_eq!(
+                events,
+                [
+                    ("endpoint".to_string(), "/messages?session=1".to_string()),
+                    ("message".to_string(), "{\"id\":1}".to_string()),
+                ]
+            );
+            assert_eq!(
+                parser.push(b"age\ndata: a\ndata: b\n\n"),
+                [("message".to_string(), "a\nb".to_string())]
+            );
+        }
+    }
+}
+
+#[cfg(not(feature = "reqwest"))]
+mod sse {
+    use anyhow::{bail, Result};
+    use std::time::Duration;
+
+    use crate::config::McpServerConfig;
+    use crate::mcp::client::McpClient;
+
+    pub async fn connect(
+        name: &str,
+        _url: &str,
+        _config: &McpServerConfig,
+        _timeout: Duration,
+    ) -> Result<McpClient> {
+        bail!(
+            "MCP server '{}' uses a url, but spec-ai was built without HTTP support",
+            name
+        )
+    }
+}
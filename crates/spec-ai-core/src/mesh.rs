@@ -5,9 +5,12 @@ use hostname::get as get_hostname;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
 use uuid::{NoContext, Timestamp, Uuid};
 
+use crate::presence::{PresenceAnnouncement, PresenceSnapshot};
+
 /// Agent instance information in the mesh
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MeshInstance {
@@ -19,6 +22,10 @@ pub struct MeshInstance {
     pub last_heartbeat: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub agent_profiles: Vec<String>,
+    /// Offset of the instance's clock from the registry's, as it last
+    /// reported it
+    #[serde(default)]
+    pub clock: Option<ClockSample>,
 }
 
 /// Request to register a new instance
@@ -46,6 +53,10 @@ pub struct RegisterResponse {
 pub struct InstancesResponse {
     pub instances: Vec<MeshInstance>,
     pub leader_id: Option<String>,
+    /// Registry's clock when the list was made, which heartbeat times are
+    /// measured against
+    #[serde(default)]
+    pub registry_time: Option<DateTime<Utc>>,
 }
 
 /// Heartbeat request
@@ -53,6 +64,12 @@ pub struct InstancesResponse {
 pub struct HeartbeatRequest {
     pub status: String,
     pub metrics: Option<HashMap<String, serde_json::Value>>,
+    /// Sender's clock when the heartbeat was sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<DateTime<Utc>>,
+    /// Sender's estimate of its clock's offset from the registry's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<ClockSample>,
 }
 
 /// Heartbeat response
@@ -61,6 +78,88 @@ pub struct HeartbeatResponse {
     pub acknowledged: bool,
     pub leader_id: Option<String>,
     pub should_sync: bool,
+    /// Registry's clock when the heartbeat arrived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
+    /// Registry's clock when the response was made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replied_at: Option<DateTime<Utc>>,
+}
+
+/// Samples kept by a [`ClockSkewEstimator`]
+const CLOCK_SAMPLES: usize = 8;
+
+/// Offset of a remote clock from the local one, measured over one request
+/// and its response
+///
+/// `offset_ms` is how far the remote clock runs ahead; the true offset lies
+/// within half of `rtt_ms` of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockSample {
+    pub offset_ms: i64,
+    pub rtt_ms: i64,
+}
+
+impl ClockSample {
+    /// NTP-style estimate from the local send time, the remote receive and
+    /// reply times, and the local time the reply arrived
+    pub fn from_exchange(
+        sent: DateTime<Utc>,
+        received: DateTime<Utc>,
+        replied: DateTime<Utc>,
+        returned: DateTime<Utc>,
+    ) -> Self {
+        let outbound = (received - sent).num_milliseconds();
+        let inbound = (replied - returned).num_milliseconds();
+        let remote_busy = (replied - received).num_milliseconds();
+        Self {
+            offset_ms: (outbound + inbound) / 2,
+            rtt_ms: ((returned - sent).num_milliseconds() - remote_busy).max(0),
+        }
+    }
+
+    /// The same offset as seen from the remote clock
+    pub fn inverse(self) -> Self {
+        Self {
+            offset_ms: -self.offset_ms,
+            rtt_ms: self.rtt_ms,
+        }
+    }
+
+    /// Whether the clocks are surely further apart than `limit_ms`
+    pub fn exceeds(&self, limit_ms: u64) -> bool {
+        self.offset_ms.unsigned_abs() > limit_ms + self.rtt_ms.max(0) as u64 / 2
+    }
+
+    /// The offset as a duration to subtract from remote timestamps
+    pub fn offset(&self) -> chrono::Duration {
+        chrono::Duration::milliseconds(self.offset_ms)
+    }
+}
+
+/// Estimates a remote clock's offset from recent samples
+///
+/// The sample with the shortest round trip wins, since network delay
+/// distorted it least.
+#[derive(Debug, Clone, Default)]
+pub struct ClockSkewEstimator {
+    samples: VecDeque<ClockSample>,
+}
+
+impl ClockSkewEstimator {
+    pub fn record(&mut self, sample: ClockSample) {
+        if self.samples.len() == CLOCK_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn estimate(&self) -> Option<ClockSample> {
+        self.samples
+            .iter()
+            .min_by_key(|sample| sample.rtt_ms)
+            .copied()
+    }
 }
 
 /// Message types for inter-agent communication
@@ -121,6 +220,85 @@ impl MessageType {
             custom => MessageType::Custom(custom.to_string()),
         }
     }
+
+    /// Delivery class of messages of this type
+    pub fn priority(&self) -> MessagePriority {
+        MessagePriority::for_message_type(&self.as_str())
+    }
+}
+
+/// Delivery class of a mesh message
+///
+/// Pending messages are handed out in this order, and each class can be
+/// given its own bandwidth limit, so bulk transfers cannot starve control
+/// traffic and votes on slow links.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum MessagePriority {
+    /// Queries, responses, notifications and capability exchange
+    Control,
+    /// Proposals and votes
+    Consensus,
+    /// Task delegation, results and workflow stages; also custom types
+    #[default]
+    Delegation,
+    /// Graph sync and learning transfers
+    BulkSync,
+}
+
+impl MessagePriority {
+    /// All classes, highest priority first
+    pub const ALL: [MessagePriority; 4] = [
+        MessagePriority::Control,
+        MessagePriority::Consensus,
+        MessagePriority::Delegation,
+        MessagePriority::BulkSync,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessagePriority::Control => "control",
+            MessagePriority::Consensus => "consensus",
+            MessagePriority::Delegation => "delegation",
+            MessagePriority::BulkSync => "bulk_sync",
+        }
+    }
+
+    /// Default class of a message type, by its wire name
+    pub fn for_message_type(message_type: &str) -> Self {
+        match message_type {
+            "query" | "response" | "notification" | "capability_update" | "capability_query" => {
+                MessagePriority::Control
+            }
+            "proposal_submit" | "proposal_vote" => MessagePriority::Consensus,
+            "graph_sync" | "learning_share" => MessagePriority::BulkSync,
+            _ => MessagePriority::Delegation,
+        }
+    }
+}
+
+/// Delivery statistics of one message priority class
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassTraffic {
+    pub priority: MessagePriority,
+    /// Payload bytes per second each instance may receive, if limited
+    pub limit_bytes_per_sec: Option<u64>,
+    /// Messages of this class waiting in the queue
+    pub queued: usize,
+    pub delivered_messages: u64,
+    pub delivered_bytes: u64,
+    /// Polls on which the class was held back by its limit
+    pub deferred: u64,
+    /// Delivery rate over the last minute
+    pub bytes_per_sec: f64,
+}
+
+/// Message delivery statistics per priority class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficResponse {
+    pub classes: Vec<ClassTraffic>,
 }
 
 /// Inter-agent message
@@ -133,6 +311,12 @@ pub struct AgentMessage {
     pub payload: serde_json::Value,
     pub correlation_id: Option<String>,
     pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub priority: MessagePriority,
+    /// Registry-assigned order of the message, which unlike `created_at`
+    /// does not depend on the sender's clock
+    #[serde(default)]
+    pub seq: u64,
 }
 
 /// Message send request
@@ -142,6 +326,9 @@ pub struct SendMessageRequest {
     pub message_type: MessageType,
     pub payload: serde_json::Value,
     pub correlation_id: Option<String>,
+    /// Delivery class, when not the message type's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<MessagePriority>,
 }
 
 /// Message send response
@@ -158,11 +345,27 @@ pub struct PendingMessagesResponse {
     pub messages: Vec<AgentMessage>,
 }
 
+/// Rendered frame of a mirrored TUI session
+///
+/// Keyframes repaint the whole screen; other frames are ANSI diffs against
+/// the frames before them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorFrame {
+    pub seq: u64,
+    pub keyframe: bool,
+    pub width: u16,
+    pub height: u16,
+    pub ansi: String,
+}
+
 /// Client-side mesh operations
 #[derive(Clone)]
 pub struct MeshClient {
     base_url: String,
     client: Client,
+    clock: Arc<Mutex<ClockSkewEstimator>>,
+    #[cfg(feature = "chaos")]
+    faults: Option<std::sync::Arc<crate::chaos::FaultInjector>>,
 }
 
 impl MeshClient {
@@ -170,7 +373,40 @@ impl MeshClient {
         Self {
             base_url: format!("http://{}:{}", host, port),
             client: Client::new(),
+            clock: Arc::default(),
+            #[cfg(feature = "chaos")]
+            faults: crate::chaos::installed(),
+        }
+    }
+
+    /// Inject faults from `injector` into this client's requests
+    #[cfg(feature = "chaos")]
+    pub fn with_fault_injector(
+        mut self,
+        injector: std::sync::Arc<crate::chaos::FaultInjector>,
+    ) -> Self {
+        self.faults = Some(injector);
+        self
+    }
+
+    /// Delay or fail `operation` when a fault scenario says so
+    async fn fault_point(&self, operation: &str) -> Result<()> {
+        #[cfg(feature = "chaos")]
+        if let Some(faults) = &self.faults {
+            crate::chaos::apply(faults, crate::chaos::FaultTarget::Mesh, operation).await?;
         }
+        #[cfg(not(feature = "chaos"))]
+        let _ = operation;
+        Ok(())
+    }
+
+    /// Estimated offset of the registry's clock from this one, measured
+    /// over heartbeats
+    pub fn clock_skew(&self) -> Option<ClockSample> {
+        self.clock
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .estimate()
     }
 
     /// Generate a unique instance ID
@@ -192,6 +428,7 @@ impl MeshClient {
         capabilities: Vec<String>,
         agent_profiles: Vec<String>,
     ) -> Result<RegisterResponse> {
+        self.fault_point("register").await?;
         let request = RegisterRequest {
             instance_id,
             hostname,
@@ -220,9 +457,13 @@ impl MeshClient {
         instance_id: &str,
         metrics: Option<HashMap<String, serde_json::Value>>,
     ) -> Result<HeartbeatResponse> {
+        self.fault_point("heartbeat").await?;
+        let sent_at = Utc::now();
         let request = HeartbeatRequest {
             status: "healthy".to_string(),
             metrics,
+            sent_at: Some(sent_at),
+            clock: self.clock_skew().map(ClockSample::inverse),
         };
 
         let response = self
@@ -236,7 +477,15 @@ impl MeshClient {
             .await?;
 
         if response.status().is_success() {
-            Ok(response.json().await?)
+            let response: HeartbeatResponse = response.json().await?;
+            if let (Some(received), Some(replied)) = (response.received_at, response.replied_at) {
+                let sample = ClockSample::from_exchange(sent_at, received, replied, Utc::now());
+                self.clock
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .record(sample);
+            }
+            Ok(response)
         } else {
             anyhow::bail!("Heartbeat failed: {}", response.status())
         }
@@ -244,6 +493,7 @@ impl MeshClient {
 
     /// List all instances in the mesh
     pub async fn list_instances(&self) -> Result<InstancesResponse> {
+        self.fault_point("list_instances").await?;
         let response = self
             .client
             .get(format!("{}/registry/agents", self.base_url))
@@ -259,6 +509,7 @@ impl MeshClient {
 
     /// Deregister from the mesh
     pub async fn deregister(&self, instance_id: &str) -> Result<()> {
+        self.fault_point("deregister").await?;
         let response = self
             .client
             .delete(format!(
@@ -284,11 +535,34 @@ impl MeshClient {
         payload: serde_json::Value,
         correlation_id: Option<String>,
     ) -> Result<SendMessageResponse> {
+        self.send_message_with_priority(
+            source_instance,
+            target_instance,
+            message_type,
+            payload,
+            correlation_id,
+            None,
+        )
+        .await
+    }
+
+    /// Send a message in a delivery class other than its type's default
+    pub async fn send_message_with_priority(
+        &self,
+        source_instance: String,
+        target_instance: Option<String>,
+        message_type: MessageType,
+        payload: serde_json::Value,
+        correlation_id: Option<String>,
+        priority: Option<MessagePriority>,
+    ) -> Result<SendMessageResponse> {
+        self.fault_point("send_message").await?;
         let request = SendMessageRequest {
             target_instance,
             message_type,
             payload,
             correlation_id,
+            priority,
         };
 
         let response = self
@@ -310,6 +584,7 @@ impl MeshClient {
 
     /// Get pending messages for an instance
     pub async fn get_messages(&self, instance_id: &str) -> Result<PendingMessagesResponse> {
+        self.fault_point("get_messages").await?;
         let response = self
             .client
             .get(format!("{}/messages/{}", self.base_url, instance_id))
@@ -323,12 +598,29 @@ impl MeshClient {
         }
     }
 
+    /// Delivery statistics per message priority class
+    pub async fn traffic(&self) -> Result<TrafficResponse> {
+        self.fault_point("traffic").await?;
+        let response = self
+            .client
+            .get(format!("{}/registry/traffic", self.base_url))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response.json().await?)
+        } else {
+            anyhow::bail!("Failed to get mesh traffic: {}", response.status())
+        }
+    }
+
     /// Acknowledge delivered messages
     pub async fn acknowledge_messages(
         &self,
         instance_id: &str,
         message_ids: Vec<String>,
     ) -> Result<()> {
+        self.fault_point("acknowledge_messages").await?;
         let response = self
             .client
             .post(format!("{}/messages/{}/ack", self.base_url, instance_id))
@@ -342,4 +634,146 @@ impl MeshClient {
             anyhow::bail!("Failed to acknowledge messages: {}", response.status())
         }
     }
+
+    /// Publish a frame of the session mirrored as `name`
+    pub async fn publish_frame(&self, name: &str, frame: &MirrorFrame) -> Result<()> {
+        self.fault_point("publish_frame").await?;
+        let response = self
+            .client
+            .post(format!("{}/mirror/{}", self.base_url, name))
+            .json(frame)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            anyhow::bail!("Failed to publish mirror frame: {}", response.status())
+        }
+    }
+
+    /// Announce this client in a session on the server, getting back everyone present
+    pub async fn announce_presence(
+        &self,
+        session_id: &str,
+        announcement: &PresenceAnnouncement,
+    ) -> Result<PresenceSnapshot> {
+        self.fault_point("announce_presence").await?;
+        let response = self
+            .client
+            .post(format!("{}/sessions/{}/presence", self.base_url, session_id))
+            .json(announcement)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response.json().await?)
+        } else {
+            anyhow::bail!("Failed to announce presence: {}", response.status())
+        }
+    }
+
+    /// End the mirror `name`, disconnecting its viewers
+    pub async fn end_mirror(&self, name: &str) -> Result<()> {
+        self.fault_point("end_mirror").await?;
+        let response = self
+            .client
+            .delete(format!("{}/mirror/{}", self.base_url, name))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            anyhow::bail!("Failed to end mirror: {}", response.status())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "chaos")]
+    use crate::chaos::{FaultInjector, FaultKind, FaultRule, FaultScenario, FaultTarget};
+    use chrono::TimeZone;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(1_700_000_000_000 + ms).unwrap()
+    }
+
+    #[test]
+    fn exchanges_estimate_offset_and_round_trip() {
+        // Remote runs 5s ahead; 40ms each way, 20ms spent answering
+        let sample = ClockSample::from_exchange(at(0), at(5_040), at(5_060), at(100));
+        assert_eq!(
+            sample,
+            ClockSample {
+                offset_ms: 5_000,
+                rtt_ms: 80
+            }
+        );
+        assert_eq!(sample.inverse().offset_ms, -5_000);
+        assert!(sample.exceeds(1_000));
+        assert!(!sample.exceeds(4_960));
+    }
+
+    #[test]
+    fn estimator_prefers_shortest_round_trip() {
+        let mut estimator = ClockSkewEstimator::default();
+        assert_eq!(estimator.estimate(), None);
+        estimator.record(ClockSample {
+            offset_ms: 900,
+            rtt_ms: 1_500,
+        });
+        estimator.record(ClockSample {
+            offset_ms: 120,
+            rtt_ms: 30,
+        });
+        for _ in 0..CLOCK_SAMPLES - 1 {
+            estimator.record(ClockSample {
+                offset_ms: 400,
+                rtt_ms: 600,
+            });
+        }
+        assert_eq!(estimator.estimate().unwrap().offset_ms, 120);
+
+        // The best sample ages out once enough newer ones arrive
+        estimator.record(ClockSample {
+            offset_ms: 400,
+            rtt_ms: 600,
+        });
+        assert_eq!(estimator.estimate().unwrap().offset_ms, 400);
+    }
+
+    #[cfg(feature = "chaos")]
+    #[tokio::test]
+    async fn injected_disconnects_fail_before_sending() {
+        let faults = Arc::new(FaultInjector::new(FaultScenario {
+            seed: 0,
+            rules: vec![FaultRule {
+                target: FaultTarget::Mesh,
+                operation: Some("send_message".to_string()),
+                probability: 1.0,
+                latency_ms: 0,
+                fault: Some(FaultKind::Disconnect),
+                skip: 0,
+                limit: None,
+            }],
+        }));
+        // Nothing listens on the discard port; the fault fires before any request
+        let client = MeshClient::new("127.0.0.1", 9).with_fault_injector(Arc::clone(&faults));
+
+        let err = client
+            .send_message(
+                "source".to_string(),
+                None,
+                MessageType::Notification,
+                json!({}),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "injected mesh disconnect in send_message");
+        assert_eq!(faults.hits(), vec![1]);
+    }
 }
@@ -0,0 +1,275 @@
+//! Tools generated from OpenAPI 3 documents
+//!
+//! Each API configured under `[openapi.apis.<name>]` has its document read
+//! once per process, when the first agent is built, and every operation is
+//! registered as `api_<name>_<operationId>` with the parameters and request
+//! body schema the document declares. Calls go straight to the API with the
+//! configured headers, and run through the same profile and policy checks as
+//! built-in tools, so a rule like `resource = "api_billing_*"` covers a whole
+//! API.
+
+pub mod spec;
+pub mod tool;
+
+pub use spec::{Operation, ParamLocation, Parameter};
+pub use tool::{tool_name, OpenApiTool};
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::{Client, Url};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tracing::{info, warn};
+
+use crate::config::{OpenApiConfig, OpenApiSource};
+use crate::tools::{Tool, ToolRegistry};
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// An API whose document has been read, and the tools made from it
+struct Api {
+    config: OpenApiSource,
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+/// APIs loaded so far, by name
+static APIS: OnceLock<Mutex<HashMap<String, Api>>> = OnceLock::new();
+
+/// Agent tools for the operations of `document` that `config` offers
+pub fn tools_from_document(
+    name: &str,
+    config: &OpenApiSource,
+    document: &serde_json::Value,
+    spec_url: Option<&Url>,
+) -> Result<Vec<Arc<dyn Tool>>> {
+    let base_url = base_url(config, document, spec_url)?;
+    let headers = config.headers()?;
+    let client = Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()
+        .context("failed to build HTTP client")?;
+
+    let tools = spec::operations(document)?
+        .into_iter()
+        .filter(|op| config.operations.is_empty() || config.operations.contains(&op.id))
+        .filter(|op| !config.read_only || op.is_read_only())
+        .map(|op| {
+            Arc::new(OpenApiTool::new(
+                name,
+                op,
+                base_url.clone(),
+                headers.clone(),
+                client.clone(),
+            )) as Arc<dyn Tool>
+        })
+        .collect();
+    Ok(tools)
+}
+
+/// Where requests go: the configured base URL, or the document's first
+/// server, resolved against the document's own URL when relative
+fn base_url(
+    config: &OpenApiSource,
+    document: &serde_json::Value,
+    spec_url: Option<&Url>,
+) -> Result<Url> {
+    let raw = match (&config.base_url, spec::server_url(document)) {
+        (Some(url), _) => url.as_str(),
+        (None, Some(url)) => url,
+        (None, None) => bail!("document lists no servers and no base_url is configured"),
+    };
+    let url = match (Url::parse(raw), spec_url) {
+        (Ok(url), _) => url,
+        (Err(_), Some(spec_url)) => spec_url
+            .join(raw)
+            .with_context(|| format!("invalid server URL '{}'", raw))?,
+        (Err(err), None) => {
+            return Err(
+                anyhow!(err).context(format!("server URL '{}' is relative; set base_url", raw))
+            )
+        }
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("server URL '{}' is not http or https", url);
+    }
+    Ok(url)
+}
+
+async fn load(name: &str, config: &OpenApiSource) -> Result<Vec<Arc<dyn Tool>>> {
+    let (text, spec_url) =
+        if config.spec.starts_with("http://") || config.spec.starts_with("https://") {
+            let url = Url::parse(&config.spec).context("invalid spec URL")?;
+            let text = Client::builder()
+                .user_agent(APP_USER_AGENT)
+                .timeout(Duration::from_secs(config.timeout_secs))
+                .build()?
+                .get(url.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .context("fetching the document failed")?
+                .text()
+                .await?;
+            (text, Some(url))
+        } else {
+            let text = std::fs::read_to_string(Path::new(&config.spec))
+                .with_context(|| format!("failed to read {}", config.spec))?;
+            (text, None)
+        };
+    let document = spec::parse_document(&text)?;
+    tools_from_document(name, config, &document, spec_url.as_ref())
+}
+
+/// Register the operations of every enabled API in `config`, reading the
+/// documents not yet read or whose settings changed
+///
+/// APIs whose document can't be read are skipped with a warning and tried
+/// again when the next agent is built. Tools never replace ones already
+/// registered.
+pub fn register_tools(registry: &mut ToolRegistry, config: &OpenApiConfig) {
+    let enabled: Vec<(&String, &OpenApiSource)> =
+        config.apis.iter().filter(|(_, api)| api.enabled).collect();
+    if enabled.is_empty() {
+        return;
+    }
+
+    // Fetching documents waits on this thread, which needs a runtime that
+    // keeps running while it is blocked
+    let handle = match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => handle,
+        _ => {
+            warn!(
+                "OpenAPI tools need a multi-threaded Tokio runtime; skipping {} configured API(s)",
+                enabled.len()
+            );
+            return;
+        }
+    };
+
+    let apis = APIS.get_or_init(Default::default);
+    let to_load: Vec<(&String, &OpenApiSource)> = {
+        let loaded = apis.lock().unwrap();
+        enabled
+            .iter()
+            .copied()
+            .filter(|(name, api)| {
+                loaded
+                    .get(*name)
+                    .is_none_or(|existing| existing.config != **api)
+            })
+            .collect()
+    };
+
+    if !to_load.is_empty() {
+        let loaded_now = tokio::task::block_in_place(|| {
+            handle.block_on(futures::future::join_all(
+                to_load
+                    .iter()
+                    .map(|(name, api)| async move { (*name, load(name, api).await) }),
+            ))
+        });
+        let mut loaded = apis.lock().unwrap();
+        for (name, result) in loaded_now {
+            match result {
+                Ok(tools) => {
+                    info!("Loaded OpenAPI '{}' with {} operations", name, tools.len());
+                    let config = config.apis[name].clone();
+                    loaded.insert(name.clone(), Api { config, tools });
+                }
+                Err(err) => {
+                    warn!("Skipping OpenAPI '{}': {:#}", name, err);
+                    loaded.remove(name);
+                }
+            }
+        }
+    }
+
+    let loaded = apis.lock().unwrap();
+    for (name, _) in enabled {
+        let Some(api) = loaded.get(name) else {
+            continue;
+        };
+        for tool in &api.tools {
+            if registry.has(tool.name()) {
+                warn!(
+                    "OpenAPI tool '{}' from '{}' would override an existing tool, skipping",
+                    tool.name(),
+                    name
+                );
+                continue;
+            }
+            registry.register(tool.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn source() -> OpenApiSource {
+        toml::from_str(r#"spec = "billing.json""#).unwrap()
+    }
+
+    fn document() -> serde_json::Value {
+        json!({
+            "openapi": "3.0.0",
+            "servers": [{ "url": "/v1" }],
+            "paths": {
+                "/invoices": {
+                    "get": { "operationId": "listInvoices" },
+                    "post": { "operationId": "createInvoice" }
+                }
+            }
+        })
+    }
+
+    fn names(tools: &[Arc<dyn Tool>]) -> Vec<String> {
+        let mut names: Vec<String> = tools.iter().map(|t| t.name().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn operations_are_filtered_by_config() {
+        let spec_url = Url::parse("https://billing.internal/openapi.json").unwrap();
+        let mut config = source();
+        let tools = tools_from_document("billing", &config, &document(), Some(&spec_url)).unwrap();
+        assert_eq!(
+            names(&tools),
+            vec!["api_billing_createInvoice", "api_billing_listInvoices"]
+        );
+
+        config.read_only = true;
+        let tools = tools_from_document("billing", &config, &document(), Some(&spec_url)).unwrap();
+        assert_eq!(names(&tools), vec!["api_billing_listInvoices"]);
+
+        config.read_only = false;
+        config.operations = vec!["createInvoice".to_string()];
+        let tools = tools_from_document("billing", &config, &document(), Some(&spec_url)).unwrap();
+        assert_eq!(names(&tools), vec!["api_billing_createInvoice"]);
+    }
+
+    #[test]
+    fn relative_servers_need_a_spec_url_or_base_url() {
+        let spec_url = Url::parse("https://billing.internal/specs/openapi.json").unwrap();
+        let mut config = source();
+        assert_eq!(
+            base_url(&config, &document(), Some(&spec_url))
+                .unwrap()
+                .as_str(),
+            "https://billing.internal/v1"
+        );
+        assert!(base_url(&config, &document(), None).is_err());
+
+        config.base_url = Some("http://127.0.0.1:8080/api".to_string());
+        assert_eq!(
+            base_url(&config, &document(), None).unwrap().as_str(),
+            "http://127.0.0.1:8080/api"
+        );
+    }
+}
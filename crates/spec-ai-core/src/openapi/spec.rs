@@ -0,0 +1,390 @@
+//! Reading operations out of an OpenAPI 3 document
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+/// HTTP methods an operation can be declared under
+const METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch"];
+
+/// Depth at which `$ref`s stop being inlined, so recursive schemas end
+const MAX_REF_DEPTH: usize = 8;
+
+/// Name of the parameter carrying a request body
+pub const BODY_PARAMETER: &str = "body";
+
+/// Where a parameter goes in the request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamLocation {
+    Path,
+    Query,
+    Header,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub location: ParamLocation,
+    pub required: bool,
+    pub description: Option<String>,
+    /// JSON Schema of the value, with `$ref`s inlined
+    pub schema: Value,
+}
+
+/// One operation of the API
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    /// The `operationId`, or one made from the method and path
+    pub id: String,
+    /// Upper-case HTTP method
+    pub method: String,
+    /// Path template such as `/invoices/{id}`
+    pub path: String,
+    pub summary: Option<String>,
+    pub parameters: Vec<Parameter>,
+    /// JSON Schema of the JSON request body, and whether it is required
+    pub body: Option<(Value, bool)>,
+}
+
+impl Operation {
+    /// Whether the operation only reads
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.method.as_str(), "GET" | "HEAD")
+    }
+
+    /// JSON Schema of the tool arguments: one property per parameter, plus
+    /// `body` for the request body
+    pub fn input_schema(&self) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for parameter in &self.parameters {
+            let mut schema = parameter.schema.clone();
+            if let (Some(description), Value::Object(map)) = (&parameter.description, &mut schema) {
+                map.entry("description")
+                    .or_insert_with(|| Value::String(description.clone()));
+            }
+            properties.insert(parameter.name.clone(), schema);
+            if parameter.required {
+                required.push(Value::String(parameter.name.clone()));
+            }
+        }
+        if let Some((schema, body_required)) = &self.body {
+            properties.insert(BODY_PARAMETER.to_string(), schema.clone());
+            if *body_required {
+                required.push(Value::String(BODY_PARAMETER.to_string()));
+            }
+        }
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+}
+
+/// Parse a document written in JSON or YAML
+pub fn parse_document(text: &str) -> Result<Value> {
+    let document: Value = match serde_json::from_str(text) {
+        Ok(document) => document,
+        Err(_) => serde_yaml::from_str(text).context("document is neither JSON nor YAML")?,
+    };
+    let version = document
+        .get("openapi")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !version.starts_with("3.") {
+        bail!("only OpenAPI 3 documents are supported");
+    }
+    Ok(document)
+}
+
+/// URL of the first server the document lists
+pub fn server_url(document: &Value) -> Option<&str> {
+    document
+        .get("servers")?
+        .as_array()?
+        .first()?
+        .get("url")?
+        .as_str()
+}
+
+/// Every operation in the document, in path order
+///
+/// Operations with a request body that is not JSON are left out, since
+/// there is no way to describe one to a model.
+pub fn operations(document: &Value) -> Result<Vec<Operation>> {
+    let Some(paths) = document.get("paths").and_then(Value::as_object) else {
+        bail!("document has no paths");
+    };
+    let mut out = Vec::new();
+    for (path, item) in paths {
+        let item = resolve(document, item, 0);
+        let shared = item.get("parameters").cloned().unwrap_or(Value::Null);
+        for method in METHODS {
+            let Some(operation) = item.get(*method) else {
+                continue;
+            };
+            match operation_of(document, path, method, operation, &shared) {
+                Ok(Some(operation)) => out.push(operation),
+                Ok(None) => {}
+                Err(err) => warn!("Skipping {} {}: {}", method.to_uppercase(), path, err),
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn operation_of(
+    document: &Value,
+    path: &str,
+    method: &str,
+    operation: &Value,
+    shared: &Value,
+) -> Result<Option<Operation>> {
+    let id = match operation.get("operationId").and_then(Value::as_str) {
+        Some(id) => id.to_string(),
+        None => fallback_id(method, path),
+    };
+
+    // Operation parameters replace path-level ones of the same name and place
+    let mut parameters: Vec<Parameter> = Vec::new();
+    for list in [shared, operation.get("parameters").unwrap_or(&Value::Null)] {
+        for raw in list.as_array().into_iter().flatten() {
+            let Some(parameter) = parameter_of(document, raw)? else {
+                continue;
+            };
+            parameters.retain(|p| !(p.name == parameter.name && p.location == parameter.location));
+            parameters.push(parameter);
+        }
+    }
+
+    let body = match operation.get("requestBody") {
+        None => None,
+        Some(raw) => {
+            let body = resolve(document, raw, 0);
+            let content = body.get("content").and_then(Value::as_object);
+            let json_schema = content.and_then(|content| {
+                content
+                    .iter()
+                    .find(|(kind, _)| is_json(kind))
+                    .map(|(_, media)| media.get("schema").cloned().unwrap_or(json!({})))
+            });
+            let Some(schema) = json_schema else {
+                warn!(
+                    "Skipping {} {}: request body is not JSON",
+                    method.to_uppercase(),
+                    path
+                );
+                return Ok(None);
+            };
+            let required = body
+                .get("required")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            Some((resolve(document, &schema, 0), required))
+        }
+    };
+
+    let summary = operation
+        .get("summary")
+        .or_else(|| operation.get("description"))
+        .and_then(Value::as_str)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    Ok(Some(Operation {
+        id,
+        method: method.to_uppercase(),
+        path: path.to_string(),
+        summary,
+        parameters,
+        body,
+    }))
+}
+
+fn parameter_of(document: &Value, raw: &Value) -> Result<Option<Parameter>> {
+    let raw = resolve(document, raw, 0);
+    let name = raw
+        .get("name")
+        .and_then(Value::as_str)
+        .context("parameter without a name")?;
+    let location = match raw.get("in").and_then(Value::as_str) {
+        Some("path") => ParamLocation::Path,
+        Some("query") => ParamLocation::Query,
+        Some("header") => ParamLocation::Header,
+        // Cookies are left to the configured headers
+        _ => return Ok(None),
+    };
+    Ok(Some(Parameter {
+        name: name.to_string(),
+        location,
+        required: location == ParamLocation::Path
+            || raw
+                .get("required")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        description: raw
+            .get("description")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        schema: raw
+            .get("schema")
+            .map(|schema| resolve(document, schema, 0))
+            .unwrap_or_else(|| json!({ "type": "string" })),
+    }))
+}
+
+/// `value` with local `$ref`s replaced by what they point to
+fn resolve(document: &Value, value: &Value, depth: usize) -> Value {
+    match value {
+        Value::Object(map) => {
+            if let Some(pointer) = map.get("$ref").and_then(Value::as_str) {
+                if depth >= MAX_REF_DEPTH {
+                    return json!({ "type": "object" });
+                }
+                return match pointer
+                    .strip_prefix('#')
+                    .and_then(|pointer| document.pointer(pointer))
+                {
+                    Some(target) => resolve(document, target, depth + 1),
+                    None => json!({}),
+                };
+            }
+            Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), resolve(document, value, depth)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| resolve(document, item, depth))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+/// `get_invoices_id` for `GET /invoices/{id}`
+fn fallback_id(method: &str, path: &str) -> String {
+    let mut id = method.to_string();
+    for part in path
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+    {
+        id.push('_');
+        id.push_str(part);
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"
+openapi: 3.0.3
+servers:
+  - url: https://billing.internal/v1
+paths:
+  /invoices/{id}:
+    parameters:
+      - $ref: '#/components/parameters/InvoiceId'
+    get:
+      operationId: getInvoice
+      summary: Fetch one invoice
+      parameters:
+        - name: expand
+          in: query
+          schema: { type: boolean }
+    delete:
+      summary: Void an invoice
+  /invoices:
+    post:
+      operationId: createInvoice
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              $ref: '#/components/schemas/Invoice'
+  /uploads:
+    post:
+      operationId: upload
+      requestBody:
+        content:
+          multipart/form-data:
+            schema: { type: object }
+components:
+  parameters:
+    InvoiceId:
+      name: id
+      in: path
+      description: Invoice number
+      schema: { type: string }
+  schemas:
+    Invoice:
+      type: object
+      required: [amount]
+      properties:
+        amount: { type: integer }
+        parent:
+          $ref: '#/components/schemas/Invoice'
+"#;
+
+    fn find<'a>(operations: &'a [Operation], id: &str) -> &'a Operation {
+        operations.iter().find(|op| op.id == id).unwrap()
+    }
+
+    #[test]
+    fn operations_are_read_from_yaml() {
+        let document = parse_document(DOCUMENT).unwrap();
+        assert_eq!(server_url(&document), Some("https://billing.internal/v1"));
+        let operations = operations(&document).unwrap();
+        let mut ids: Vec<&str> = operations.iter().map(|op| op.id.as_str()).collect();
+        ids.sort();
+        // The multipart upload has no JSON body to describe
+        assert_eq!(
+            ids,
+            vec!["createInvoice", "delete_invoices_id", "getInvoice"]
+        );
+
+        let get = find(&operations, "getInvoice");
+        assert!(get.is_read_only());
+        assert_eq!(get.summary.as_deref(), Some("Fetch one invoice"));
+        let schema = get.input_schema();
+        assert_eq!(schema["properties"]["id"]["description"], "Invoice number");
+        assert_eq!(schema["properties"]["expand"]["type"], "boolean");
+        assert_eq!(schema["required"], json!(["id"]));
+    }
+
+    #[test]
+    fn body_schemas_are_inlined() {
+        let document = parse_document(DOCUMENT).unwrap();
+        let operations = operations(&document).unwrap();
+        let create = find(&operations, "createInvoice");
+        assert!(!create.is_read_only());
+        let schema = create.input_schema();
+        assert_eq!(schema["required"], json!(["body"]));
+        let body = &schema["properties"]["body"];
+        assert_eq!(body["properties"]["amount"]["type"], "integer");
+        // Recursive references stop after a few levels
+        assert_eq!(
+            body["properties"]["parent"]["properties"]["amount"]["type"],
+            "integer"
+        );
+        assert!(!body.to_string().contains("$ref"));
+    }
+
+    #[test]
+    fn only_openapi_3_is_accepted() {
+        assert!(parse_document(r#"{"swagger": "2.0", "paths": {}}"#).is_err());
+        assert!(parse_document(r#"{"openapi": "3.1.0", "paths": {}}"#).is_ok());
+    }
+}
@@ -0,0 +1,282 @@
+//! OpenAPI operations offered as agent tools
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, Method, Url};
+use serde_json::{json, Value};
+
+use super::spec::{Operation, ParamLocation, BODY_PARAMETER};
+use crate::tools::{Tool, ToolResult};
+
+/// Longest tool name providers accept
+const MAX_TOOL_NAME: usize = 64;
+
+/// Largest response body returned to the model
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Name an operation is registered under: `api_<api>_<operationId>`, limited
+/// to the characters and length providers accept for function names
+pub fn tool_name(api: &str, operation: &str) -> String {
+    let name: String = format!("api_{}_{}", api, operation)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.chars().take(MAX_TOOL_NAME).collect()
+}
+
+/// One operation of an HTTP API
+pub struct OpenApiTool {
+    name: String,
+    description: String,
+    parameters: Value,
+    operation: Operation,
+    base_url: Url,
+    headers: Vec<(String, String)>,
+    client: Client,
+}
+
+impl OpenApiTool {
+    pub fn new(
+        api: &str,
+        operation: Operation,
+        base_url: Url,
+        headers: Vec<(String, String)>,
+        client: Client,
+    ) -> Self {
+        let description = match &operation.summary {
+            Some(summary) => format!(
+                "{} ({} {} on API '{}')",
+                summary, operation.method, operation.path, api
+            ),
+            None => format!("{} {} on API '{}'", operation.method, operation.path, api),
+        };
+        Self {
+            name: tool_name(api, &operation.id),
+            description,
+            parameters: operation.input_schema(),
+            operation,
+            base_url,
+            headers,
+            client,
+        }
+    }
+
+    /// The operation this tool calls
+    pub fn operation(&self) -> &Operation {
+        &self.operation
+    }
+
+    /// URL for a call with `args`, with path parameters filled in and query
+    /// parameters appended
+    pub fn url(&self, args: &Value) -> Result<Url> {
+        let mut url = self.base_url.clone();
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .map_err(|_| anyhow!("base URL '{}' cannot have a path", self.base_url))?;
+            segments.pop_if_empty();
+            for segment in self.operation.path.split('/').filter(|s| !s.is_empty()) {
+                let value = match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => match args.get(name) {
+                        Some(value) if !value.is_null() => scalar(value),
+                        _ => bail!("missing path parameter '{}'", name),
+                    },
+                    None => segment.to_string(),
+                };
+                segments.push(&value);
+            }
+        }
+
+        let query: Vec<(String, String)> = self
+            .operation
+            .parameters
+            .iter()
+            .filter(|p| p.location == ParamLocation::Query)
+            .filter_map(|p| args.get(&p.name).map(|value| (p, value)))
+            .flat_map(|(p, value)| match value {
+                Value::Null => Vec::new(),
+                // Arrays repeat the parameter, the OpenAPI default for query
+                Value::Array(items) => items
+                    .iter()
+                    .map(|item| (p.name.clone(), scalar(item)))
+                    .collect(),
+                other => vec![(p.name.clone(), scalar(other))],
+            })
+            .collect();
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Tool for OpenApiTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.parameters.clone()
+    }
+
+    fn read_only(&self) -> bool {
+        self.operation.is_read_only()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "status": { "type": "integer" },
+                "body": {},
+                "truncated": { "type": "boolean" }
+            },
+            "required": ["status", "body", "truncated"]
+        }))
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args = if args.is_null() { json!({}) } else { args };
+        let url = match self.url(&args) {
+            Ok(url) => url,
+            Err(err) => return Ok(ToolResult::failure(err.to_string())),
+        };
+        let method =
+            Method::from_bytes(self.operation.method.as_bytes()).context("invalid HTTP method")?;
+
+        let mut request = self.client.request(method, url);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        for parameter in &self.operation.parameters {
+            if parameter.location != ParamLocation::Header {
+                continue;
+            }
+            if let Some(value) = args.get(&parameter.name).filter(|v| !v.is_null()) {
+                request = request.header(&parameter.name, scalar(value));
+            }
+        }
+        if self.operation.body.is_some() {
+            if let Some(body) = args.get(BODY_PARAMETER) {
+                request = request.json(body);
+            }
+        }
+
+        let mut response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => return Ok(ToolResult::failure(format!("request failed: {}", err))),
+        };
+        let status = response.status();
+        let mut bytes = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response.chunk().await.context("reading body failed")? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > MAX_RESPONSE_BYTES {
+                bytes.truncate(MAX_RESPONSE_BYTES);
+                truncated = true;
+                break;
+            }
+        }
+        let text = String::from_utf8_lossy(&bytes);
+        let body = match serde_json::from_str::<Value>(&text) {
+            Ok(value) if !truncated => value,
+            _ => Value::String(text.into_owned()),
+        };
+        let response = json!({
+            "status": status.as_u16(),
+            "body": body,
+            "truncated": truncated,
+        });
+
+        let mut result = ToolResult::success(response.to_string()).with_data(response);
+        if !status.is_success() {
+            result.success = false;
+            result.error = Some(format!("HTTP {}", status));
+        }
+        Ok(result)
+    }
+}
+
+/// A parameter value as it appears in a URL or header
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::spec::Parameter;
+
+    fn operation() -> Operation {
+        let parameter = |name: &str, location| Parameter {
+            name: name.to_string(),
+            location,
+            required: false,
+            description: None,
+            schema: json!({ "type": "string" }),
+        };
+        Operation {
+            id: "listLines".to_string(),
+            method: "GET".to_string(),
+            path: "/invoices/{id}/lines".to_string(),
+            summary: Some("List invoice lines".to_string()),
+            parameters: vec![
+                parameter("id", ParamLocation::Path),
+                parameter("tag", ParamLocation::Query),
+                parameter("limit", ParamLocation::Query),
+                parameter("X-Trace", ParamLocation::Header),
+            ],
+            body: None,
+        }
+    }
+
+    fn tool() -> OpenApiTool {
+        OpenApiTool::new(
+            "billing",
+            operation(),
+            Url::parse("https://billing.internal/v1/").unwrap(),
+            Vec::new(),
+            Client::new(),
+        )
+    }
+
+    #[test]
+    fn names_are_sanitized() {
+        assert_eq!(tool_name("billing", "getInvoice"), "api_billing_getInvoice");
+        assert_eq!(
+            tool_name("billing", "get.invoice"),
+            "api_billing_get_invoice"
+        );
+        assert_eq!(tool_name("a", &"x".repeat(100)).len(), MAX_TOOL_NAME);
+    }
+
+    #[test]
+    fn urls_fill_path_and_query_parameters() {
+        let tool = tool();
+        assert_eq!(tool.name(), "api_billing_listLines");
+        assert!(tool.description().contains("GET /invoices/{id}/lines"));
+
+        let url = tool
+            .url(&json!({ "id": "2024/17", "tag": ["a", "b"], "limit": 5, "X-Trace": "t" }))
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://billing.internal/v1/invoices/2024%2F17/lines?tag=a&tag=b&limit=5"
+        );
+        assert!(tool.url(&json!({ "tag": "a" })).is_err());
+    }
+}
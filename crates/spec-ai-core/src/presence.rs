@@ -0,0 +1,500 @@
+//! Who is taking part in a shared session, and whose turn it is
+//!
+//! Several clients can work in one session on the same server: API callers
+//! sending queries and TUIs that announce themselves while they watch.
+//! [`SessionPresence`] tracks who is there and what they are doing, and
+//! [`TurnQueue`] runs their submissions one at a time in the order they
+//! arrived, so two people never have the agent answer them at once.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+/// How long an announced participant is shown without announcing again
+pub const PRESENCE_TTL: Duration = Duration::from_secs(30);
+/// How often clients that stay in a session should announce themselves
+pub const PRESENCE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What a participant is doing in the session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Activity {
+    /// Connected, reading along
+    #[default]
+    Idle,
+    /// Submitted a message that waits for an earlier turn to finish
+    Queued,
+    /// The agent is answering their message
+    Running,
+}
+
+impl Activity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Activity::Idle => "idle",
+            Activity::Queued => "queued",
+            Activity::Running => "running",
+        }
+    }
+}
+
+/// Someone in a session, as shown to the others
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Participant {
+    pub name: String,
+    /// Kind of client they use, e.g. "tui" or "api"
+    pub client: String,
+    pub activity: Activity,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A client telling the server it is in a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceAnnouncement {
+    pub name: String,
+    pub client: String,
+    #[serde(default)]
+    pub activity: Activity,
+    /// Set when the client leaves, to disappear without waiting for expiry
+    #[serde(default)]
+    pub leaving: bool,
+}
+
+/// Everyone in a session and how many messages wait for the agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceSnapshot {
+    pub session_id: String,
+    pub participants: Vec<Participant>,
+    /// Messages queued or being answered in the session
+    pub pending_turns: u64,
+}
+
+struct Entry {
+    announced: Option<(Activity, Instant)>,
+    queued: usize,
+    running: usize,
+    last_seen: DateTime<Utc>,
+}
+
+impl Entry {
+    fn new() -> Self {
+        Self {
+            announced: None,
+            queued: 0,
+            running: 0,
+            last_seen: Utc::now(),
+        }
+    }
+
+    /// Current activity, or None once nothing keeps the entry alive
+    fn activity(&self, ttl: Duration) -> Option<Activity> {
+        if self.running > 0 {
+            return Some(Activity::Running);
+        }
+        if self.queued > 0 {
+            return Some(Activity::Queued);
+        }
+        self.announced
+            .filter(|(_, at)| at.elapsed() < ttl)
+            .map(|(activity, _)| activity)
+    }
+}
+
+type Sessions = HashMap<String, HashMap<(String, String), Entry>>;
+
+/// Participants of every session on a server
+///
+/// Requests in progress hold a [`PresenceGuard`] and count for as long as they
+/// run; clients without a request in flight stay listed for [`PRESENCE_TTL`]
+/// after each announcement.
+#[derive(Clone)]
+pub struct SessionPresence {
+    sessions: Arc<Mutex<Sessions>>,
+    ttl: Duration,
+}
+
+impl Default for SessionPresence {
+    fn default() -> Self {
+        Self::new(PRESENCE_TTL)
+    }
+}
+
+impl SessionPresence {
+    /// Presence that forgets quiet participants after `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// Record an announcement, or remove the participant when they leave
+    pub fn announce(&self, session_id: &str, announcement: &PresenceAnnouncement) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let key = (announcement.name.clone(), announcement.client.clone());
+        if announcement.leaving {
+            if let Some(entry) = sessions.get_mut(session_id).and_then(|s| s.get_mut(&key)) {
+                entry.announced = None;
+            }
+            prune(&mut sessions, session_id, self.ttl);
+            return;
+        }
+        let entry = sessions
+            .entry(session_id.to_string())
+            .or_default()
+            .entry(key)
+            .or_insert_with(Entry::new);
+        entry.announced = Some((announcement.activity, Instant::now()));
+        entry.last_seen = Utc::now();
+
+        // Sessions nobody lists would otherwise keep their departed clients
+        let ttl = self.ttl;
+        sessions.retain(|_, session| {
+            session.retain(|_, entry| entry.activity(ttl).is_some());
+            !session.is_empty()
+        });
+    }
+
+    /// Count `name` as queued in the session until the guard is dropped
+    pub fn join(&self, session_id: &str, name: &str, client: &str) -> PresenceGuard {
+        let key = (name.to_string(), client.to_string());
+        let mut sessions = self.sessions.lock().unwrap();
+        let entry = sessions
+            .entry(session_id.to_string())
+            .or_default()
+            .entry(key.clone())
+            .or_insert_with(Entry::new);
+        entry.queued += 1;
+        entry.last_seen = Utc::now();
+        PresenceGuard {
+            presence: self.clone(),
+            session_id: session_id.to_string(),
+            key,
+            running: false,
+        }
+    }
+
+    /// Everyone in the session, by name
+    pub fn list(&self, session_id: &str) -> Vec<Participant> {
+        let mut sessions = self.sessions.lock().unwrap();
+        prune(&mut sessions, session_id, self.ttl);
+        let mut participants: Vec<Participant> = sessions
+            .get(session_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|((name, client), entry)| {
+                Some(Participant {
+                    name: name.clone(),
+                    client: client.clone(),
+                    activity: entry.activity(self.ttl)?,
+                    last_seen: entry.last_seen,
+                })
+            })
+            .collect();
+        participants.sort_by(|a, b| (&a.name, &a.client).cmp(&(&b.name, &b.client)));
+        participants
+    }
+
+    fn update(&self, session_id: &str, key: &(String, String), change: impl FnOnce(&mut Entry)) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(entry) = sessions.get_mut(session_id).and_then(|s| s.get_mut(key)) {
+            change(entry);
+            entry.last_seen = Utc::now();
+        }
+        prune(&mut sessions, session_id, self.ttl);
+    }
+}
+
+fn prune(sessions: &mut Sessions, session_id: &str, ttl: Duration) {
+    if let Some(session) = sessions.get_mut(session_id) {
+        session.retain(|_, entry| entry.activity(ttl).is_some());
+        if session.is_empty() {
+            sessions.remove(session_id);
+        }
+    }
+}
+
+/// Keeps a request's author listed in the session while it is queued or runs
+pub struct PresenceGuard {
+    presence: SessionPresence,
+    session_id: String,
+    key: (String, String),
+    running: bool,
+}
+
+impl PresenceGuard {
+    /// Show the author as running once their turn has started
+    pub fn start(&mut self) {
+        if !self.running {
+            self.running = true;
+            self.presence.update(&self.session_id, &self.key, |entry| {
+                entry.queued -= 1;
+                entry.running += 1;
+            });
+        }
+    }
+}
+
+impl Drop for PresenceGuard {
+    fn drop(&mut self) {
+        let running = self.running;
+        self.presence.update(&self.session_id, &self.key, |entry| {
+            if running {
+                entry.running -= 1;
+            } else {
+                entry.queued -= 1;
+            }
+        });
+    }
+}
+
+struct LineState {
+    /// Number given to the next submission
+    next: u64,
+    /// Number of the submission whose turn it is
+    serving: u64,
+    /// Submissions that gave up before their turn came
+    abandoned: BTreeSet<u64>,
+}
+
+struct Line {
+    state: Mutex<LineState>,
+    serving: watch::Sender<u64>,
+}
+
+impl Line {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(LineState {
+                next: 1,
+                serving: 1,
+                abandoned: BTreeSet::new(),
+            }),
+            serving: watch::channel(1).0,
+        }
+    }
+
+    /// Move on from `number`, skipping submissions that were abandoned
+    fn finish(&self, number: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.serving != number {
+            state.abandoned.insert(number);
+            return;
+        }
+        state.serving += 1;
+        let mut serving = state.serving;
+        while state.abandoned.remove(&serving) {
+            serving += 1;
+        }
+        state.serving = serving;
+        self.serving.send_replace(serving);
+    }
+
+    fn idle(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.next == state.serving
+    }
+}
+
+/// Runs the turns of each session one at a time, first come first served
+#[derive(Clone, Default)]
+pub struct TurnQueue {
+    lines: Arc<Mutex<HashMap<String, Arc<Line>>>>,
+}
+
+impl TurnQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a place in the session's line
+    pub fn enqueue(&self, session_id: &str) -> Ticket {
+        let mut lines = self.lines.lock().unwrap();
+        let line = lines
+            .entry(session_id.to_string())
+            .or_insert_with(|| Arc::new(Line::new()))
+            .clone();
+        let number = {
+            let mut state = line.state.lock().unwrap();
+            state.next += 1;
+            state.next - 1
+        };
+        Ticket {
+            place: Place {
+                queue: self.clone(),
+                session_id: session_id.to_string(),
+                line,
+                number,
+            },
+            started: false,
+        }
+    }
+
+    /// Submissions waiting or running in the session
+    pub fn len(&self, session_id: &str) -> u64 {
+        let lines = self.lines.lock().unwrap();
+        lines.get(session_id).map_or(0, |line| {
+            let state = line.state.lock().unwrap();
+            state.next - state.serving - state.abandoned.len() as u64
+        })
+    }
+
+    pub fn is_empty(&self, session_id: &str) -> bool {
+        self.len(session_id) == 0
+    }
+
+    fn release(&self, session_id: &str, line: &Line, number: u64) {
+        line.finish(number);
+        let mut lines = self.lines.lock().unwrap();
+        if lines.get(session_id).is_some_and(|line| line.idle()) {
+            lines.remove(session_id);
+        }
+    }
+}
+
+struct Place {
+    queue: TurnQueue,
+    session_id: String,
+    line: Arc<Line>,
+    number: u64,
+}
+
+/// A place in a session's line; dropping it before the turn gives it up
+pub struct Ticket {
+    place: Place,
+    started: bool,
+}
+
+impl Ticket {
+    /// Order of the submission in the session, starting at 1 for each line
+    pub fn number(&self) -> u64 {
+        self.place.number
+    }
+
+    /// Submissions that run before this one
+    pub fn ahead(&self) -> u64 {
+        let state = self.place.line.state.lock().unwrap();
+        let skipped = state
+            .abandoned
+            .range(state.serving..self.place.number)
+            .count() as u64;
+        self.place.number.saturating_sub(state.serving) - skipped
+    }
+
+    /// Wait until every earlier submission in the session has finished
+    pub async fn wait(mut self) -> Turn {
+        let mut serving = self.place.line.serving.subscribe();
+        let number = self.place.number;
+        // The sender lives in the line this ticket holds, so this cannot fail
+        let _ = serving.wait_for(|serving| *serving == number).await;
+        self.started = true;
+        Turn {
+            place: Place {
+                queue: self.place.queue.clone(),
+                session_id: self.place.session_id.clone(),
+                line: self.place.line.clone(),
+                number,
+            },
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if !self.started {
+            let place = &self.place;
+            place
+                .queue
+                .release(&place.session_id, &place.line, place.number);
+        }
+    }
+}
+
+/// The session's current turn, passed on to the next submission when dropped
+pub struct Turn {
+    place: Place,
+}
+
+impl Turn {
+    pub fn number(&self) -> u64 {
+        self.place.number
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        let place = &self.place;
+        place
+            .queue
+            .release(&place.session_id, &place.line, place.number);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announce(name: &str, leaving: bool) -> PresenceAnnouncement {
+        PresenceAnnouncement {
+            name: name.to_string(),
+            client: "tui".to_string(),
+            activity: Activity::Idle,
+            leaving,
+        }
+    }
+
+    #[test]
+    fn participants_are_listed_while_guarded_or_recently_announced() {
+        let presence = SessionPresence::new(Duration::from_secs(60));
+        presence.announce("s", &announce("bob", false));
+        let mut guard = presence.join("s", "alice", "api");
+
+        let listed = presence.list("s");
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].name, "alice");
+        assert_eq!(listed[0].activity, Activity::Queued);
+        assert_eq!(listed[1].activity, Activity::Idle);
+
+        guard.start();
+        assert_eq!(presence.list("s")[0].activity, Activity::Running);
+        drop(guard);
+        presence.announce("s", &announce("bob", true));
+        assert!(presence.list("s").is_empty());
+        assert!(presence.list("other").is_empty());
+
+        let expired = SessionPresence::new(Duration::ZERO);
+        expired.announce("s", &announce("bob", false));
+        assert!(expired.list("s").is_empty());
+    }
+
+    #[tokio::test]
+    async fn turns_run_in_order_and_skip_abandoned_tickets() {
+        let queue = TurnQueue::new();
+        let first = queue.enqueue("s");
+        let second = queue.enqueue("s");
+        let third = queue.enqueue("s");
+        assert_eq!((first.number(), second.number(), third.number()), (1, 2, 3));
+        assert_eq!(third.ahead(), 2);
+        assert_eq!(queue.len("s"), 3);
+
+        let turn = first.wait().await;
+        drop(second);
+        assert_eq!(third.ahead(), 1);
+
+        let waiting = tokio::spawn(third.wait());
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        drop(turn);
+        let turn = waiting.await.unwrap();
+        assert_eq!(turn.number(), 3);
+        assert_eq!(queue.len("s"), 1);
+
+        drop(turn);
+        assert!(queue.is_empty("s"));
+        assert_eq!(queue.enqueue("s").number(), 1);
+    }
+}
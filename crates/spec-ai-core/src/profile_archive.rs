@@ -0,0 +1,265 @@
+//! Single-file archives of an agent profile
+//!
+//! An archive carries the configuration, sessions, knowledge graph
+//! namespaces and learned strategies of an agent so it can move to another
+//! machine. Secrets written inline in the configuration are left out; the
+//! archive lists where they were so they can be filled in after import.
+//! `env:` and `file:` sources are kept, since they name a secret rather than
+//! hold it. A BLAKE3 digest of the contents is checked on every read.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::config::AppConfig;
+use crate::persistence::profile::ProfileData;
+
+/// Value of the `format` field identifying a profile archive
+pub const ARCHIVE_FORMAT: &str = "spec-ai-profile";
+/// Archive layout written by this version
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Settings holding a secret, or a source for one
+const SECRET_KEYS: &[&str] = &["api_key_source", "token_secret", "password"];
+/// Tables whose every entry may be a secret, such as MCP server `env` and `headers`
+const SECRET_TABLES: &[&str] = &["env", "headers"];
+
+/// What an archive holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfilePayload {
+    /// The configuration as TOML, without inline secrets
+    pub config: String,
+    /// Dotted paths of the settings left out of `config`
+    pub removed_secrets: Vec<String>,
+    pub data: ProfileData,
+}
+
+impl ProfilePayload {
+    /// Bundle `config` and `data`, leaving inline secrets out of the config
+    pub fn new(config: &AppConfig, data: ProfileData) -> Result<Self> {
+        let (config, removed_secrets) = sanitize_config(config)?;
+        Ok(Self {
+            config,
+            removed_secrets,
+            data,
+        })
+    }
+
+    /// The archived configuration
+    pub fn app_config(&self) -> Result<AppConfig> {
+        toml::from_str(&self.config).context("parsing archived configuration")
+    }
+}
+
+/// An archive read back from disk, with its digest verified
+#[derive(Debug, Clone)]
+pub struct ProfileArchive {
+    pub created_at: String,
+    /// Hex BLAKE3 digest of the payload
+    pub digest: String,
+    pub payload: ProfilePayload,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u32,
+    created_at: String,
+    digest: String,
+    payload: serde_json::Value,
+}
+
+/// Write `payload` to `path` as an archive, returning its digest
+pub fn write_archive(path: &Path, payload: &ProfilePayload) -> Result<String> {
+    let payload = serde_json::to_value(payload)?;
+    let digest = digest_of(&payload)?;
+    let envelope = Envelope {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        digest: digest.clone(),
+        payload,
+    };
+    let bytes = serde_json::to_vec(&envelope)?;
+    std::fs::write(path, bytes).with_context(|| format!("writing {}", path.display()))?;
+    Ok(digest)
+}
+
+/// Read the archive at `path`, failing when its contents do not match the digest
+pub fn read_archive(path: &Path) -> Result<ProfileArchive> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let envelope: Envelope = serde_json::from_slice(&bytes)
+        .with_context(|| format!("{} is not a profile archive", path.display()))?;
+    if envelope.format != ARCHIVE_FORMAT {
+        bail!(
+            "{} is not a profile archive (format '{}')",
+            path.display(),
+            envelope.format
+        );
+    }
+    if envelope.version > ARCHIVE_VERSION {
+        bail!(
+            "{} is archive version {}; this build reads up to version {}",
+            path.display(),
+            envelope.version,
+            ARCHIVE_VERSION
+        );
+    }
+    let digest = digest_of(&envelope.payload)?;
+    if digest != envelope.digest {
+        bail!(
+            "{} failed integrity verification: digest {} does not match recorded {}",
+            path.display(),
+            digest,
+            envelope.digest
+        );
+    }
+    let payload = serde_json::from_value(envelope.payload)
+        .with_context(|| format!("reading contents of {}", path.display()))?;
+    Ok(ProfileArchive {
+        created_at: envelope.created_at,
+        digest,
+        payload,
+    })
+}
+
+fn digest_of(payload: &serde_json::Value) -> Result<String> {
+    let bytes = serde_json::to_vec(payload)?;
+    Ok(blake3::hash(&bytes).to_hex().to_string())
+}
+
+/// `config` as TOML without inline secrets, and the paths of what was removed
+pub fn sanitize_config(config: &AppConfig) -> Result<(String, Vec<String>)> {
+    let mut value = toml::Value::try_from(config).context("serializing configuration")?;
+    let mut removed = Vec::new();
+    strip_secrets(&mut value, "", &mut removed);
+    removed.sort();
+    let config = toml::to_string_pretty(&value).context("serializing configuration")?;
+    Ok((config, removed))
+}
+
+fn strip_secrets(value: &mut toml::Value, path: &str, removed: &mut Vec<String>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match value {
+        toml::Value::Table(table) => {
+            let keys: Vec<String> = table.keys().cloned().collect();
+            for key in keys {
+                let secret = match table.get(&key) {
+                    Some(toml::Value::String(s)) => {
+                        SECRET_KEYS.contains(&key.as_str()) && !is_secret_source(s)
+                    }
+                    _ => false,
+                };
+                if secret {
+                    table.remove(&key);
+                    removed.push(join(&key));
+                    continue;
+                }
+                let Some(child) = table.get_mut(&key) else {
+                    continue;
+                };
+                match child {
+                    toml::Value::Table(entries) if SECRET_TABLES.contains(&key.as_str()) => {
+                        let child_path = join(&key);
+                        entries.retain(|name, value| {
+                            let keep = value.as_str().is_some_and(is_secret_source);
+                            if !keep {
+                                removed.push(format!("{}.{}", child_path, name));
+                            }
+                            keep
+                        });
+                    }
+                    _ => strip_secrets(child, &join(&key), removed),
+                }
+            }
+        }
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                strip_secrets(item, &format!("{}[{}]", path, i), removed);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether `value` names where a secret is kept rather than holding it
+fn is_secret_source(value: &str) -> bool {
+    value.starts_with("env:") || value.starts_with("file:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::McpServerConfig;
+
+    fn config() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.model.api_key_source = Some("sk-inline".to_string());
+        config.audio.api_key_source = Some("env:OPENAI_API_KEY".to_string());
+        config.auth.token_secret = Some("hunter2".to_string());
+        config.mcp.servers.insert(
+            "github".to_string(),
+            McpServerConfig {
+                enabled: true,
+                command: Some("github-mcp".to_string()),
+                args: Vec::new(),
+                env: [("GITHUB_TOKEN".to_string(), "ghp_secret".to_string())].into(),
+                url: None,
+                headers: Default::default(),
+                timeout_secs: 30,
+            },
+        );
+        config
+    }
+
+    #[test]
+    fn inline_secrets_are_left_out_and_listed() {
+        let (toml, removed) = sanitize_config(&config()).unwrap();
+        assert!(!toml.contains("sk-inline"));
+        assert!(!toml.contains("hunter2"));
+        assert!(!toml.contains("ghp_secret"));
+        assert!(toml.contains("env:OPENAI_API_KEY"));
+        assert_eq!(
+            removed,
+            vec![
+                "auth.token_secret",
+                "mcp.servers.github.env.GITHUB_TOKEN",
+                "model.api_key_source",
+            ]
+        );
+        let parsed: AppConfig = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.model.api_key_source, None);
+        assert!(parsed.mcp.servers.contains_key("github"));
+    }
+
+    #[test]
+    fn archives_round_trip_and_detect_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+        let data = ProfileData {
+            sessions: vec!["s1".to_string()],
+            namespaces: Vec::new(),
+            tables: Vec::new(),
+        };
+        let payload = ProfilePayload::new(&config(), data).unwrap();
+        let digest = write_archive(&path, &payload).unwrap();
+
+        let archive = read_archive(&path).unwrap();
+        assert_eq!(archive.digest, digest);
+        assert_eq!(archive.payload, payload);
+
+        let tampered = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"s1\"", "\"s2\"");
+        std::fs::write(&path, tampered).unwrap();
+        let err = read_archive(&path).unwrap_err();
+        assert!(err.to_string().contains("integrity"));
+    }
+}
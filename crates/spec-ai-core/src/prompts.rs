@@ -0,0 +1,377 @@
+//! Prompt Template Library
+//!
+//! Prompts are named templates rather than string literals, so users can
+//! change them. A template is text with handlebars-style `{{variable}}`
+//! placeholders; `\{{` stands for a literal `{{`. Templates are looked up in
+//! order in:
+//!
+//! 1. the agent profile's `prompt_overrides`
+//! 2. templates saved in the database, which keep every version
+//! 3. the `[prompts]` table of the config file
+//! 4. the built-in defaults
+//!
+//! A reference such as `coder@2` pins a saved version and only looks there.
+
+use crate::config::AgentProfile;
+use crate::persistence::{Persistence, PromptTemplateRecord};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Added to the system prompt when replies are spoken
+pub const SPEECH_MODE: &str = "speech_mode";
+
+/// Asks the fast model to summarize the main model's reasoning
+pub const REASONING_SUMMARY: &str = "reasoning_summary";
+
+/// Frames preliminary tasks handed to the fast model
+pub const FAST_REASONING: &str = "fast_reasoning";
+
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    (
+        SPEECH_MODE,
+        "Speech mode is enabled; respond with concise, natural sentences suitable for \
+         text-to-speech. Avoid markdown/code fences and keep the reply brief.",
+    ),
+    (
+        REASONING_SUMMARY,
+        "Summarize the following reasoning in 1-2 concise sentences that explain the \
+         thought process:\n\n{{reasoning}}\n\nSummary:",
+    ),
+    (
+        FAST_REASONING,
+        "You are a fast specialist model that assists a more capable agent.\n\
+         Task: {{task}}\nInput: {{input}}",
+    ),
+];
+
+/// Where a template was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptSource {
+    Profile,
+    Saved,
+    Config,
+    Builtin,
+}
+
+/// A template resolved from the library
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    /// Saved version, for templates from the database
+    pub version: Option<u32>,
+    pub body: String,
+    pub source: PromptSource,
+}
+
+impl PromptTemplate {
+    pub fn render(&self, vars: &HashMap<String, String>) -> Result<String> {
+        render(&self.body, vars)
+            .with_context(|| format!("Failed to render prompt template '{}'", self.name))
+    }
+}
+
+/// Named prompt templates from profiles, the database, config and defaults
+#[derive(Clone, Default)]
+pub struct PromptLibrary {
+    templates: HashMap<String, String>,
+    persistence: Option<Persistence>,
+}
+
+impl PromptLibrary {
+    /// A library holding only the built-in templates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add templates from the config file
+    pub fn with_templates(mut self, templates: HashMap<String, String>) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Look up and save versioned templates in `persistence`
+    pub fn with_persistence(mut self, persistence: Persistence) -> Self {
+        self.persistence = Some(persistence);
+        self
+    }
+
+    /// Find the template `reference` names, as seen by `profile`
+    pub fn resolve(
+        &self,
+        reference: &str,
+        profile: Option<&AgentProfile>,
+    ) -> Result<PromptTemplate> {
+        let (name, version) = parse_reference(reference)?;
+
+        if version.is_none() {
+            if let Some(body) = profile.and_then(|profile| profile.prompt_overrides.get(name)) {
+                return Ok(PromptTemplate {
+                    name: name.to_string(),
+                    version: None,
+                    body: body.clone(),
+                    source: PromptSource::Profile,
+                });
+            }
+        }
+
+        if let Some(persistence) = &self.persistence {
+            if let Some(record) = persistence.prompt_template(name, version)? {
+                return Ok(record.into());
+            }
+        }
+        if let Some(version) = version {
+            bail!("Prompt template '{}' has no version {}", name, version);
+        }
+
+        if let Some(body) = self.templates.get(name) {
+            return Ok(PromptTemplate {
+                name: name.to_string(),
+                version: None,
+                body: body.clone(),
+                source: PromptSource::Config,
+            });
+        }
+        builtin(name).ok_or_else(|| anyhow!("Unknown prompt template '{}'", name))
+    }
+
+    /// Render the template `reference` names for `profile`
+    ///
+    /// `vars` take precedence over the profile's `prompt_vars`.
+    pub fn render(
+        &self,
+        reference: &str,
+        profile: &AgentProfile,
+        vars: &HashMap<String, String>,
+    ) -> Result<String> {
+        let mut all = profile.prompt_vars.clone();
+        all.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.resolve(reference, Some(profile))?.render(&all)
+    }
+
+    /// Save `body` as the newest version of the template `name`
+    pub fn save(&self, name: &str, body: &str) -> Result<PromptTemplateRecord> {
+        let persistence = self
+            .persistence
+            .as_ref()
+            .context("Saving prompt templates needs a database")?;
+        if name.is_empty() || name.contains('@') {
+            bail!("Prompt template names must be non-empty and may not contain '@'");
+        }
+        variables(body)?;
+        persistence.save_prompt_template(name, body)
+    }
+
+    /// Names of every template in the library, sorted
+    pub fn names(&self) -> Result<Vec<String>> {
+        let mut names: BTreeSet<String> = BUILTIN_TEMPLATES
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        names.extend(self.templates.keys().cloned());
+        if let Some(persistence) = &self.persistence {
+            names.extend(
+                persistence
+                    .list_prompt_templates()?
+                    .into_iter()
+                    .map(|record| record.name),
+            );
+        }
+        Ok(names.into_iter().collect())
+    }
+}
+
+impl From<PromptTemplateRecord> for PromptTemplate {
+    fn from(record: PromptTemplateRecord) -> Self {
+        Self {
+            name: record.name,
+            version: Some(record.version),
+            body: record.body,
+            source: PromptSource::Saved,
+        }
+    }
+}
+
+/// Built-in default of the template `name`
+pub fn builtin(name: &str) -> Option<PromptTemplate> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(name, body)| PromptTemplate {
+            name: name.to_string(),
+            version: None,
+            body: body.to_string(),
+            source: PromptSource::Builtin,
+        })
+}
+
+/// Split "name@version" into its name and optional version
+fn parse_reference(reference: &str) -> Result<(&str, Option<u32>)> {
+    match reference.split_once('@') {
+        Some((name, version)) => {
+            let version = version.parse().map_err(|_| {
+                anyhow!(
+                    "Invalid version '{}' in prompt reference '{}'",
+                    version,
+                    reference
+                )
+            })?;
+            Ok((name, Some(version)))
+        }
+        None => Ok((reference, None)),
+    }
+}
+
+/// Fill the `{{variables}}` of `template` from `vars`
+///
+/// Variables without a value are an error, so typos do not silently render
+/// as empty text.
+pub fn render(template: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    for part in parse(template)? {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Variable(name) => {
+                let value = vars
+                    .get(name)
+                    .ok_or_else(|| anyhow!("Prompt variable '{}' has no value", name))?;
+                out.push_str(value);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Names of the variables `template` uses, in order of first use
+pub fn variables(template: &str) -> Result<Vec<String>> {
+    let mut names: Vec<String> = Vec::new();
+    for part in parse(template)? {
+        if let Part::Variable(name) = part {
+            if !names.iter().any(|seen| seen == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+fn parse(template: &str) -> Result<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if let Some(text) = rest[..start].strip_suffix('\\') {
+            parts.push(Part::Text(text));
+            parts.push(Part::Text("{{"));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        parts.push(Part::Text(&rest[..start]));
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| anyhow!("Unclosed '{{{{' in prompt template"))?;
+        let name = after[..end].trim();
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-');
+        if !valid {
+            bail!("'{{{{{}}}}}' is not a valid prompt variable", name);
+        }
+        parts.push(Part::Variable(name));
+        rest = &after[end + 2..];
+    }
+    parts.push(Part::Text(rest));
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn templates_render_variables_and_escapes() {
+        let rendered = render(
+            "Hi {{ name }}, you are {{role}}. Literal: \\{{name}}",
+            &vars(&[("name", "Ada"), ("role", "a reviewer")]),
+        )
+        .unwrap();
+        assert_eq!(rendered, "Hi Ada, you are a reviewer. Literal: {{name}}");
+
+        let missing = render("Hi {{name}}", &HashMap::new()).unwrap_err();
+        assert!(missing.to_string().contains("'name'"));
+        assert!(render("Hi {{name", &HashMap::new()).is_err());
+        assert!(render("Hi {{two words}}", &HashMap::new()).is_err());
+        assert_eq!(
+            variables("{{a}} {{b}} {{a}}").unwrap(),
+            vec!["a".to_string(), "b".to_string()]
+        );
+    }
+
+    #[test]
+    fn profiles_saved_versions_and_config_override_builtins() {
+        let persistence = Persistence::in_memory().unwrap();
+        let library = PromptLibrary::new()
+            .with_templates(vars(&[("coder", "Config coder for {{language}}")]))
+            .with_persistence(persistence);
+
+        assert_eq!(
+            library.resolve(SPEECH_MODE, None).unwrap().source,
+            PromptSource::Builtin
+        );
+        assert_eq!(
+            library.resolve("coder", None).unwrap().source,
+            PromptSource::Config
+        );
+        assert!(library.resolve("missing", None).is_err());
+
+        library.save("coder", "Saved coder v1").unwrap();
+        library
+            .save("coder", "Saved coder for {{language}}")
+            .unwrap();
+        assert!(library.save("bad", "{{unclosed").is_err());
+        let latest = library.resolve("coder", None).unwrap();
+        assert_eq!(
+            (latest.source, latest.version),
+            (PromptSource::Saved, Some(2))
+        );
+        assert_eq!(
+            library.resolve("coder@1", None).unwrap().body,
+            "Saved coder v1"
+        );
+        assert!(library.resolve("coder@9", None).is_err());
+
+        let profile = AgentProfile {
+            prompt_vars: vars(&[("language", "Rust")]),
+            prompt_overrides: vars(&[(SPEECH_MODE, "Talk like a {{language}} pirate")]),
+            ..Default::default()
+        };
+        assert_eq!(
+            library.render("coder", &profile, &HashMap::new()).unwrap(),
+            "Saved coder for Rust"
+        );
+        assert_eq!(
+            library
+                .render(SPEECH_MODE, &profile, &vars(&[("language", "Go")]))
+                .unwrap(),
+            "Talk like a Go pirate"
+        );
+        assert_eq!(
+            library.names().unwrap(),
+            vec!["coder", FAST_REASONING, REASONING_SUMMARY, SPEECH_MODE]
+        );
+    }
+}
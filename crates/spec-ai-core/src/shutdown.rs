@@ -0,0 +1,356 @@
+//! Coordinated shutdown for long-running subsystems
+//!
+//! Subsystems register with a [`ShutdownCoordinator`] and watch its
+//! [`CancellationToken`]. Shutting down cancels the token, gives every
+//! component its own timeout to stop, runs cleanup steps such as plugin
+//! unloading and the persistence flush last, and returns a [`ShutdownReport`]
+//! naming anything that failed to stop.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use thiserror::Error;
+use tokio::task::{AbortHandle, JoinHandle};
+pub use tokio_util::sync::CancellationToken;
+
+use crate::persistence::Persistence;
+
+/// Time a component gets to stop when no timeout is given
+pub const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+type StopFuture = Pin<Box<dyn Future<Output = Result<(), StopError>> + Send>>;
+
+/// Why a component did not stop cleanly
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum StopError {
+    #[error("did not stop within {0:?}")]
+    TimedOut(Duration),
+    #[error("panicked while stopping")]
+    Panicked,
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// A component that failed to stop cleanly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentFailure {
+    pub component: String,
+    pub error: StopError,
+}
+
+/// Outcome of [`ShutdownCoordinator::shutdown`]
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    /// Components that stopped cleanly, tasks and hooks before cleanup steps
+    pub stopped: Vec<String>,
+    pub failed: Vec<ComponentFailure>,
+    pub elapsed: Duration,
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.stopped.len() + self.failed.len();
+        if self.is_clean() {
+            return write!(f, "all {} components stopped", total);
+        }
+        write!(
+            f,
+            "{} of {} components failed to stop:",
+            self.failed.len(),
+            total
+        )?;
+        for failure in &self.failed {
+            write!(f, "\n  - {}: {}", failure.component, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+struct Component {
+    name: String,
+    timeout: Duration,
+    stop: StopFuture,
+    /// Aborts a spawned task that overruns its timeout
+    abort: Option<AbortHandle>,
+}
+
+impl Component {
+    async fn stop(self) -> Result<String, ComponentFailure> {
+        let outcome = match tokio::time::timeout(self.timeout, self.stop).await {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                if let Some(abort) = &self.abort {
+                    abort.abort();
+                }
+                Err(StopError::TimedOut(self.timeout))
+            }
+        };
+        match outcome {
+            Ok(()) => Ok(self.name),
+            Err(error) => Err(ComponentFailure {
+                component: self.name,
+                error,
+            }),
+        }
+    }
+}
+
+/// Stops registered subsystems together when the process is shutting down
+///
+/// Tasks and shutdown hooks stop concurrently once the token is cancelled.
+/// Cleanup steps then run one at a time in registration order.
+pub struct ShutdownCoordinator {
+    token: CancellationToken,
+    components: Vec<Component>,
+    cleanup: Vec<Component>,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            components: Vec::new(),
+            cleanup: Vec::new(),
+        }
+    }
+
+    /// Token that is cancelled when shutdown starts
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Wait for a spawned task that exits once the token is cancelled
+    ///
+    /// The task is aborted if it is still running after `timeout`.
+    pub fn register_task(
+        &mut self,
+        name: impl Into<String>,
+        timeout: Duration,
+        handle: JoinHandle<Result<()>>,
+    ) {
+        let abort = handle.abort_handle();
+        let stop = async move {
+            match handle.await {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(err)) => Err(StopError::Failed(format!("{:#}", err))),
+                Err(err) if err.is_panic() => Err(StopError::Panicked),
+                Err(err) => Err(StopError::Failed(err.to_string())),
+            }
+        };
+        self.components.push(Component {
+            name: name.into(),
+            timeout,
+            stop: Box::pin(stop),
+            abort: Some(abort),
+        });
+    }
+
+    /// Run `hook` once shutdown starts, alongside the registered tasks
+    pub fn on_shutdown<F>(&mut self, name: impl Into<String>, timeout: Duration, hook: F)
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.components
+            .push(hook_component(name.into(), timeout, hook));
+    }
+
+    /// Run `step` after every task and shutdown hook has finished
+    pub fn on_cleanup<F>(&mut self, name: impl Into<String>, timeout: Duration, step: F)
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.cleanup
+            .push(hook_component(name.into(), timeout, step));
+    }
+
+    /// Call the shutdown hook of every loaded tool plugin during cleanup
+    pub fn stop_plugins(&mut self) {
+        self.on_cleanup("plugins", DEFAULT_STOP_TIMEOUT, async {
+            spec_ai_plugin::shutdown_plugins();
+            Ok(())
+        });
+    }
+
+    /// Stop the background tasks agents started with `task_start`
+    pub fn stop_tasks(&mut self) {
+        self.on_shutdown("background tasks", DEFAULT_STOP_TIMEOUT, async {
+            crate::tasks::TaskRegistry::global().stop_all().await;
+            Ok(())
+        });
+    }
+
+    /// Checkpoint the database during cleanup so no WAL data is lost
+    pub fn flush_persistence(&mut self, persistence: Persistence) {
+        self.on_cleanup("persistence", DEFAULT_STOP_TIMEOUT, async move {
+            tokio::task::spawn_blocking(move || persistence.checkpoint()).await?
+        });
+    }
+
+    /// Cancel the token, stop every component and report what failed
+    pub async fn shutdown(self) -> ShutdownReport {
+        let started = Instant::now();
+        self.token.cancel();
+
+        let mut report = ShutdownReport::default();
+        let stopping = self.components.into_iter().map(Component::stop);
+        for outcome in futures::future::join_all(stopping).await {
+            record(&mut report, outcome);
+        }
+        for step in self.cleanup {
+            record(&mut report, step.stop().await);
+        }
+
+        report.elapsed = started.elapsed();
+        report
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hook_component<F>(name: String, timeout: Duration, hook: F) -> Component
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    let stop = async move {
+        hook.await
+            .map_err(|err| StopError::Failed(format!("{:#}", err)))
+    };
+    Component {
+        name,
+        timeout,
+        stop: Box::pin(stop),
+        abort: None,
+    }
+}
+
+fn record(report: &mut ShutdownReport, outcome: Result<String, ComponentFailure>) {
+    match outcome {
+        Ok(name) => report.stopped.push(name),
+        Err(failure) => report.failed.push(failure),
+    }
+}
+
+/// Wait for Ctrl+C, or SIGTERM on Unix
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn tasks_stop_on_cancel_and_cleanup_runs_last() {
+        let mut shutdown = ShutdownCoordinator::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let token = shutdown.token();
+        let task_order = Arc::clone(&order);
+        shutdown.register_task(
+            "worker",
+            Duration::from_secs(1),
+            tokio::spawn(async move {
+                token.cancelled().await;
+                task_order.lock().unwrap().push("worker");
+                Ok(())
+            }),
+        );
+        let cleanup_order = Arc::clone(&order);
+        shutdown.on_cleanup("flush", Duration::from_secs(1), async move {
+            cleanup_order.lock().unwrap().push("flush");
+            Ok(())
+        });
+        let hook_order = Arc::clone(&order);
+        shutdown.on_shutdown("deregister", Duration::from_secs(1), async move {
+            hook_order.lock().unwrap().push("deregister");
+            Ok(())
+        });
+
+        // Hooks are lazy until shutdown starts
+        tokio::task::yield_now().await;
+        assert!(order.lock().unwrap().is_empty());
+
+        let report = shutdown.shutdown().await;
+        assert!(report.is_clean(), "{}", report);
+        assert_eq!(report.stopped.len(), 3);
+        assert_eq!(order.lock().unwrap().last(), Some(&"flush"));
+        assert_eq!(report.to_string(), "all 3 components stopped");
+    }
+
+    #[tokio::test]
+    async fn report_names_components_that_fail_to_stop() {
+        let mut shutdown = ShutdownCoordinator::new();
+        shutdown.register_task(
+            "stuck",
+            Duration::from_millis(50),
+            tokio::spawn(std::future::pending()),
+        );
+        shutdown.register_task(
+            "panics",
+            Duration::from_secs(1),
+            tokio::spawn(async { panic!("boom") }),
+        );
+        shutdown.on_shutdown("errors", Duration::from_secs(1), async {
+            anyhow::bail!("registry unreachable")
+        });
+
+        let report = shutdown.shutdown().await;
+        let failures: Vec<_> = report
+            .failed
+            .iter()
+            .map(|f| (f.component.as_str(), f.error.clone()))
+            .collect();
+        assert_eq!(
+            failures,
+            vec![
+                ("stuck", StopError::TimedOut(Duration::from_millis(50))),
+                ("panics", StopError::Panicked),
+                (
+                    "errors",
+                    StopError::Failed("registry unreachable".to_string())
+                ),
+            ]
+        );
+        assert!(report.to_string().starts_with("3 of 3 components failed"));
+    }
+}
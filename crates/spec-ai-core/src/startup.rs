@@ -0,0 +1,382 @@
+//! Startup orchestration for subsystems that depend on each other
+//!
+//! Each subsystem is a named step that declares the steps it needs. A
+//! [`StartupPlan`] starts every step on its own thread as soon as its
+//! dependencies have finished, so independent subsystems initialize
+//! concurrently, and records a timing breakdown in a [`StartupReport`].
+//! Subsystems that are only needed later are marked deferred and built on
+//! first use instead.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+
+type StepFn<'a> = Box<dyn FnOnce() -> Result<()> + Send + 'a>;
+
+struct Step<'a> {
+    name: &'static str,
+    depends_on: Vec<&'static str>,
+    init: StepFn<'a>,
+}
+
+/// Subsystems to initialize and the order constraints between them
+pub struct StartupPlan<'a> {
+    steps: Vec<Step<'a>>,
+    deferred: Vec<String>,
+}
+
+impl<'a> StartupPlan<'a> {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            deferred: Vec::new(),
+        }
+    }
+
+    /// Add a step that runs once every step in `depends_on` has finished
+    pub fn step<F>(&mut self, name: &'static str, depends_on: &[&'static str], init: F) -> &mut Self
+    where
+        F: FnOnce() -> Result<()> + Send + 'a,
+    {
+        self.steps.push(Step {
+            name,
+            depends_on: depends_on.to_vec(),
+            init: Box::new(init),
+        });
+        self
+    }
+
+    /// Note a subsystem that is initialized on first use rather than at startup
+    pub fn defer(&mut self, name: impl Into<String>) -> &mut Self {
+        self.deferred.push(name.into());
+        self
+    }
+
+    /// Run every step, concurrently where the dependencies allow
+    ///
+    /// When a step fails, no further steps are started; the steps already
+    /// running are allowed to finish and the first error is returned.
+    pub fn run(self) -> Result<StartupReport> {
+        self.validate()?;
+
+        let started = Instant::now();
+        let mut report = StartupReport {
+            deferred: self.deferred,
+            ..StartupReport::default()
+        };
+        let mut pending: Vec<Option<Step<'a>>> = self.steps.into_iter().map(Some).collect();
+        let mut finished: HashSet<&'static str> = HashSet::new();
+        let mut failure = None;
+
+        std::thread::scope(|scope| {
+            let (done_tx, done_rx) = mpsc::channel();
+            let mut running = 0usize;
+
+            loop {
+                if failure.is_none() {
+                    for slot in pending.iter_mut() {
+                        let ready = slot.as_ref().is_some_and(|step| {
+                            step.depends_on.iter().all(|d| finished.contains(d))
+                        });
+                        if !ready {
+                            continue;
+                        }
+                        let Some(step) = slot.take() else {
+                            continue;
+                        };
+                        let done_tx = done_tx.clone();
+                        running += 1;
+                        scope.spawn(move || {
+                            let step_started = Instant::now();
+                            let outcome = panic::catch_unwind(AssertUnwindSafe(step.init))
+                                .unwrap_or_else(|_| Err(anyhow!("panicked")));
+                            let timing = StepTiming {
+                                name: step.name.to_string(),
+                                depends_on: step.depends_on.iter().map(|d| d.to_string()).collect(),
+                                started: step_started.duration_since(started),
+                                elapsed: step_started.elapsed(),
+                            };
+                            let _ = done_tx.send((step.name, timing, outcome));
+                        });
+                    }
+                }
+                if running == 0 {
+                    break;
+                }
+
+                let Ok((name, timing, outcome)) = done_rx.recv() else {
+                    break;
+                };
+                running -= 1;
+                tracing::debug!(
+                    step = name,
+                    elapsed_ms = timing.elapsed.as_millis() as u64,
+                    "startup step finished"
+                );
+                match outcome {
+                    Ok(()) => {
+                        finished.insert(name);
+                        report.steps.push(timing);
+                    }
+                    Err(err) => {
+                        if failure.is_none() {
+                            failure = Some(err.context(format!("starting {}", name)));
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        report.total = started.elapsed();
+        tracing::info!(
+            total_ms = report.total.as_millis() as u64,
+            "startup finished\n{}",
+            report
+        );
+        Ok(report)
+    }
+
+    /// Reject duplicate names, unknown dependencies and cycles before running anything
+    fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for step in &self.steps {
+            if !names.insert(step.name) {
+                bail!("startup step '{}' is declared twice", step.name);
+            }
+        }
+        for step in &self.steps {
+            if let Some(missing) = step.depends_on.iter().find(|d| !names.contains(*d)) {
+                bail!(
+                    "startup step '{}' depends on unknown step '{}'",
+                    step.name,
+                    missing
+                );
+            }
+        }
+
+        let mut remaining: HashMap<&str, &[&str]> = self
+            .steps
+            .iter()
+            .map(|step| (step.name, step.depends_on.as_slice()))
+            .collect();
+        while !remaining.is_empty() {
+            let ready: Vec<&str> = remaining
+                .iter()
+                .filter(|(_, deps)| deps.iter().all(|d| !remaining.contains_key(d)))
+                .map(|(name, _)| *name)
+                .collect();
+            if ready.is_empty() {
+                let mut cycle: Vec<&str> = remaining.keys().copied().collect();
+                cycle.sort_unstable();
+                bail!(
+                    "startup steps have a dependency cycle: {}",
+                    cycle.join(", ")
+                );
+            }
+            for name in ready {
+                remaining.remove(name);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for StartupPlan<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Value produced by one step for the steps that depend on it
+pub struct Slot<T>(Mutex<Option<T>>);
+
+impl<T> Slot<T> {
+    pub fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    pub fn set(&self, value: T) {
+        *self.lock() = Some(value);
+    }
+
+    /// Run `f` with the value
+    ///
+    /// Panics if the producing step has not run, which declaring it as a
+    /// dependency rules out.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let guard = self.lock();
+        f(guard.as_ref().expect("startup dependency has not run"))
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        self.0
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<T>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How long one startup step took
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepTiming {
+    pub name: String,
+    pub depends_on: Vec<String>,
+    /// Offset from the start of the plan
+    pub started: Duration,
+    pub elapsed: Duration,
+}
+
+/// Timing breakdown of a [`StartupPlan`] run
+#[derive(Debug, Clone, Default)]
+pub struct StartupReport {
+    /// Steps in the order they finished
+    pub steps: Vec<StepTiming>,
+    /// Subsystems left to initialize on first use
+    pub deferred: Vec<String>,
+    pub total: Duration,
+}
+
+impl StartupReport {
+    pub fn step(&self, name: &str) -> Option<&StepTiming> {
+        self.steps.iter().find(|step| step.name == name)
+    }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "startup took {}", millis(self.total))?;
+        let width = self.steps.iter().map(|s| s.name.len()).max().unwrap_or(0);
+        for step in &self.steps {
+            write!(
+                f,
+                "\n  {:<width$}  {:>9}  at +{}",
+                step.name,
+                millis(step.elapsed),
+                millis(step.started),
+                width = width
+            )?;
+            if !step.depends_on.is_empty() {
+                write!(f, "  after {}", step.depends_on.join(", "))?;
+            }
+        }
+        if !self.deferred.is_empty() {
+            write!(f, "\n  deferred: {}", self.deferred.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Format a duration as fractional milliseconds
+pub fn millis(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn independent_steps_overlap_and_dependents_wait() {
+        let (a_tx, a_rx) = mpsc::channel();
+        let (b_tx, b_rx) = mpsc::channel();
+        let order = Mutex::new(Vec::new());
+        let value = Slot::new();
+
+        let order = &order;
+        let value = &value;
+        let mut plan = StartupPlan::new();
+        plan.step("a", &[], move || {
+            a_tx.send(()).unwrap();
+            // Only finishes if "b" is running at the same time
+            b_rx.recv_timeout(Duration::from_secs(5))?;
+            order.lock().unwrap().push("a");
+            Ok(())
+        })
+        .step("b", &[], move || {
+            b_tx.send(()).unwrap();
+            a_rx.recv_timeout(Duration::from_secs(5))?;
+            value.set(42);
+            order.lock().unwrap().push("b");
+            Ok(())
+        })
+        .step("c", &["a", "b"], || {
+            assert_eq!(value.get(), 42);
+            order.lock().unwrap().push("c");
+            Ok(())
+        })
+        .defer("lazy");
+
+        let report = plan.run().unwrap();
+        assert_eq!(order.lock().unwrap().last(), Some(&"c"));
+        assert_eq!(report.steps.len(), 3);
+        assert_eq!(report.step("c").unwrap().depends_on, vec!["a", "b"]);
+        assert!(report.step("c").unwrap().started >= report.step("a").unwrap().elapsed);
+        assert!(report.to_string().ends_with("deferred: lazy"));
+    }
+
+    #[test]
+    fn failed_step_skips_dependents() {
+        let ran = Mutex::new(Vec::new());
+        let mut plan = StartupPlan::new();
+        plan.step("database", &[], || bail!("locked"))
+            .step("agent", &["database"], || {
+                ran.lock().unwrap().push("agent");
+                Ok(())
+            })
+            .step("panics", &[], || panic!("boom"));
+
+        let err = plan.run().unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(
+            message == "starting database: locked" || message == "starting panics: panicked",
+            "{}",
+            message
+        );
+        assert!(ran.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_plans_are_rejected() {
+        let mut plan = StartupPlan::new();
+        plan.step("a", &["missing"], || Ok(()));
+        assert!(plan
+            .run()
+            .unwrap_err()
+            .to_string()
+            .contains("unknown step 'missing'"));
+
+        let mut plan = StartupPlan::new();
+        plan.step("a", &["b"], || Ok(()))
+            .step("b", &["a"], || Ok(()))
+            .step("c", &[], || Ok(()));
+        assert_eq!(
+            plan.run().unwrap_err().to_string(),
+            "startup steps have a dependency cycle: a, b"
+        );
+    }
+}
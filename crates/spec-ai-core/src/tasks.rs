@@ -0,0 +1,501 @@
+//! Background tasks started by agents
+//!
+//! `task_start` runs a shell command that can outlive the turn that started
+//! it, such as a build or a test suite. The [`TaskRegistry`] keeps each
+//! task's state and the newest lines of its output so later turns can poll
+//! them by id, and broadcasts [`TaskEvent`]s so the TUI can show tasks as
+//! they run.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+
+use crate::broadcast::{Broadcaster, DropPolicy, Subscriber};
+use crate::shutdown::CancellationToken;
+
+/// Output lines kept per task; older lines are dropped
+pub const MAX_LOG_LINES: usize = 2000;
+
+/// Longest output line kept, in characters
+const MAX_LINE_CHARS: usize = 2000;
+
+/// Events buffered for a slow subscriber before its oldest are dropped
+const EVENT_CAPACITY: usize = 1024;
+
+/// How long a stopped task gets to exit before it is killed
+const STOP_GRACE: Duration = Duration::from_secs(5);
+
+/// How long output is still read after a task exits, in case a process it
+/// started keeps the pipes open
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// Exited with status 0
+    Succeeded,
+    /// Exited with another status, was killed by a signal, or could not be
+    /// waited on
+    Failed,
+    /// Stopped with `task_stop` or at shutdown
+    Stopped,
+}
+
+impl TaskState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskState::Running => "running",
+            TaskState::Succeeded => "succeeded",
+            TaskState::Failed => "failed",
+            TaskState::Stopped => "stopped",
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        *self == TaskState::Running
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// One line of a task's output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogLine {
+    /// Position among every line the task wrote, starting at 0
+    pub seq: u64,
+    pub stream: LogStream,
+    pub text: String,
+}
+
+/// What is known about a task
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskInfo {
+    pub id: String,
+    pub name: String,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    pub state: TaskState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Why waiting on the task failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Output lines written so far, including ones no longer kept
+    pub lines: u64,
+}
+
+/// Change to a task, as broadcast to subscribers
+#[derive(Debug, Clone)]
+pub enum TaskEvent {
+    /// A task started or finished
+    Updated(TaskInfo),
+    /// A task wrote a line of output
+    Output { id: String, line: LogLine },
+}
+
+struct Entry {
+    info: TaskInfo,
+    logs: VecDeque<LogLine>,
+    stop: CancellationToken,
+}
+
+/// Tasks started in this process, by id
+pub struct TaskRegistry {
+    tasks: Mutex<HashMap<String, Entry>>,
+    next_id: AtomicU64,
+    events: Broadcaster<TaskEvent>,
+}
+
+static GLOBAL: OnceLock<Arc<TaskRegistry>> = OnceLock::new();
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self {
+            tasks: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            events: Broadcaster::new(EVENT_CAPACITY, DropPolicy::DropOldest),
+        }
+    }
+
+    /// Registry shared by the task tools and the UI of this process
+    pub fn global() -> Arc<TaskRegistry> {
+        GLOBAL.get_or_init(|| Arc::new(Self::new())).clone()
+    }
+
+    /// Receive every change from now on
+    pub fn subscribe(&self) -> Subscriber<TaskEvent> {
+        self.events.subscribe()
+    }
+
+    /// Spawn `command` as a new task
+    ///
+    /// `display` is the command line reported for it. The command's output
+    /// is captured and its standard input closed; on Unix it runs in its own
+    /// process group so stopping it also stops what it started.
+    pub fn start(
+        self: &Arc<Self>,
+        name: Option<&str>,
+        display: &str,
+        working_dir: Option<&str>,
+        mut command: Command,
+    ) -> Result<TaskInfo> {
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        command.process_group(0);
+        let child = command
+            .spawn()
+            .with_context(|| format!("Failed to start '{}'", display))?;
+
+        let id = format!("task-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        let info = TaskInfo {
+            id: id.clone(),
+            name: name.map_or_else(|| display.to_string(), str::to_string),
+            command: display.to_string(),
+            working_dir: working_dir.map(str::to_string),
+            state: TaskState::Running,
+            exit_code: None,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+            lines: 0,
+        };
+        let stop = CancellationToken::new();
+        self.lock().insert(
+            id.clone(),
+            Entry {
+                info: info.clone(),
+                logs: VecDeque::new(),
+                stop: stop.clone(),
+            },
+        );
+        self.events.send(TaskEvent::Updated(info.clone()));
+
+        tokio::spawn(self.clone().supervise(id, child, stop));
+        Ok(info)
+    }
+
+    pub fn status(&self, id: &str) -> Option<TaskInfo> {
+        self.lock().get(id).map(|entry| entry.info.clone())
+    }
+
+    /// Every task, oldest first
+    pub fn list(&self) -> Vec<TaskInfo> {
+        let mut tasks: Vec<TaskInfo> = self
+            .lock()
+            .values()
+            .map(|entry| entry.info.clone())
+            .collect();
+        tasks.sort_by_key(|task| (task.started_at, task_number(&task.id)));
+        tasks
+    }
+
+    /// The task and up to `limit` of its kept lines: the first ones from line
+    /// `since` when given, the newest ones otherwise
+    pub fn logs(
+        &self,
+        id: &str,
+        since: Option<u64>,
+        limit: usize,
+    ) -> Option<(TaskInfo, Vec<LogLine>)> {
+        let tasks = self.lock();
+        let entry = tasks.get(id)?;
+        let lines = match since {
+            Some(since) => entry
+                .logs
+                .iter()
+                .filter(|line| line.seq >= since)
+                .take(limit)
+                .cloned()
+                .collect(),
+            None => {
+                let skip = entry.logs.len().saturating_sub(limit);
+                entry.logs.iter().skip(skip).cloned().collect()
+            }
+        };
+        Some((entry.info.clone(), lines))
+    }
+
+    /// Wait until the task finishes or `timeout` passes, returning its state
+    pub async fn wait(&self, id: &str, timeout: Duration) -> Result<TaskInfo> {
+        let mut events = self.subscribe();
+        let info = self
+            .status(id)
+            .ok_or_else(|| anyhow!("Unknown task '{}'", id))?;
+        if !info.state.is_running() {
+            return Ok(info);
+        }
+        let finished = async {
+            loop {
+                match events.recv().await {
+                    Ok(TaskEvent::Updated(info)) if info.id == id && !info.state.is_running() => {
+                        return;
+                    }
+                    Err(_) => return,
+                    _ => {}
+                }
+            }
+        };
+        let _ = tokio::time::timeout(timeout, finished).await;
+        self.status(id)
+            .ok_or_else(|| anyhow!("Unknown task '{}'", id))
+    }
+
+    /// Ask a running task to stop and wait for it to exit
+    ///
+    /// The task gets SIGTERM, then SIGKILL if it is still running after a
+    /// grace period.
+    pub async fn stop(&self, id: &str) -> Result<TaskInfo> {
+        let stop = self
+            .lock()
+            .get(id)
+            .map(|entry| entry.stop.clone())
+            .ok_or_else(|| anyhow!("Unknown task '{}'", id))?;
+        stop.cancel();
+        self.wait(id, STOP_GRACE + DRAIN_TIMEOUT * 2).await
+    }
+
+    /// Stop every running task, as at shutdown
+    pub async fn stop_all(&self) {
+        let running: Vec<String> = self
+            .list()
+            .into_iter()
+            .filter(|task| task.state.is_running())
+            .map(|task| task.id)
+            .collect();
+        futures::future::join_all(running.iter().map(|id| self.stop(id))).await;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.tasks.lock().expect("task registry mutex poisoned")
+    }
+
+    /// Capture the output of a started task and record how it ends
+    async fn supervise(self: Arc<Self>, id: String, mut child: Child, stop: CancellationToken) {
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(tokio::spawn(self.clone().read_lines(
+                id.clone(),
+                LogStream::Stdout,
+                stdout,
+            )));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(tokio::spawn(self.clone().read_lines(
+                id.clone(),
+                LogStream::Stderr,
+                stderr,
+            )));
+        }
+
+        let (state, exit_code, error) = tokio::select! {
+            status = child.wait() => match status {
+                Ok(status) if status.success() => (TaskState::Succeeded, status.code(), None),
+                Ok(status) => (TaskState::Failed, status.code(), None),
+                Err(e) => (TaskState::Failed, None, Some(e.to_string())),
+            },
+            _ = stop.cancelled() => {
+                terminate(&mut child).await;
+                (TaskState::Stopped, None, None)
+            }
+        };
+        for reader in readers {
+            let abort = reader.abort_handle();
+            if tokio::time::timeout(DRAIN_TIMEOUT, reader).await.is_err() {
+                abort.abort();
+            }
+        }
+
+        let info = {
+            let mut tasks = self.lock();
+            let Some(entry) = tasks.get_mut(&id) else {
+                return;
+            };
+            entry.info.state = state;
+            entry.info.exit_code = exit_code;
+            entry.info.error = error;
+            entry.info.finished_at = Some(Utc::now());
+            entry.info.clone()
+        };
+        self.events.send(TaskEvent::Updated(info));
+    }
+
+    async fn read_lines(
+        self: Arc<Self>,
+        id: String,
+        stream: LogStream,
+        output: impl AsyncRead + Unpin,
+    ) {
+        let mut output = BufReader::new(output);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match output.read_until(b'\n', &mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let text = String::from_utf8_lossy(&buf);
+            let text: String = text
+                .trim_end_matches(['\n', '\r'])
+                .chars()
+                .take(MAX_LINE_CHARS)
+                .collect();
+            let line = {
+                let mut tasks = self.lock();
+                let Some(entry) = tasks.get_mut(&id) else {
+                    break;
+                };
+                let line = LogLine {
+                    seq: entry.info.lines,
+                    stream,
+                    text,
+                };
+                entry.info.lines += 1;
+                entry.logs.push_back(line.clone());
+                if entry.logs.len() > MAX_LOG_LINES {
+                    entry.logs.pop_front();
+                }
+                line
+            };
+            self.events.send(TaskEvent::Output {
+                id: id.clone(),
+                line,
+            });
+        }
+    }
+}
+
+impl Default for TaskRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number in a `task-<n>` id, so task-10 sorts after task-9
+fn task_number(id: &str) -> u64 {
+    id.trim_start_matches("task-").parse().unwrap_or_default()
+}
+
+/// Stop `child` and its process group: SIGTERM first, SIGKILL after the
+/// grace period
+async fn terminate(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        signal_group(pid, "TERM").await;
+        if tokio::time::timeout(STOP_GRACE, child.wait()).await.is_ok() {
+            // Processes the task left behind get no grace period of their own
+            signal_group(pid, "KILL").await;
+            return;
+        }
+        signal_group(pid, "KILL").await;
+    }
+    let _ = child.kill().await;
+}
+
+/// Send `signal` to every process in the group led by `pid`
+#[cfg(unix)]
+async fn signal_group(pid: u32, signal: &str) {
+    let _ = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg("--")
+        .arg(format!("-{}", pid))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[tokio::test]
+    async fn finished_tasks_keep_their_output_and_status() {
+        let registry = Arc::new(TaskRegistry::new());
+        let started = registry
+            .start(
+                Some("greet"),
+                "echo",
+                None,
+                shell("echo one; echo two >&2; echo three; exit 3"),
+            )
+            .unwrap();
+        assert_eq!(started.state, TaskState::Running);
+
+        let finished = registry
+            .wait(&started.id, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(finished.state, TaskState::Failed);
+        assert_eq!(finished.exit_code, Some(3));
+        assert_eq!(finished.lines, 3);
+
+        let (_, lines) = registry.logs(&started.id, None, 2).unwrap();
+        assert_eq!(lines.len(), 2);
+        // stdout and stderr are read concurrently, so only the order within
+        // each stream is fixed
+        assert_eq!(lines[1].seq, 2);
+        let (_, since) = registry.logs(&started.id, Some(1), 1).unwrap();
+        assert_eq!(since.len(), 1);
+        assert_eq!(since[0].seq, 1);
+        let (_, lines) = registry.logs(&started.id, None, 10).unwrap();
+        let text = |stream| {
+            lines
+                .iter()
+                .filter(|line| line.stream == stream)
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(text(LogStream::Stdout), ["one", "three"]);
+        assert_eq!(text(LogStream::Stderr), ["two"]);
+    }
+
+    #[tokio::test]
+    async fn stopped_tasks_are_reported_as_stopped() {
+        let registry = Arc::new(TaskRegistry::new());
+        let mut events = registry.subscribe();
+        let task = registry
+            .start(None, "sleep 30", None, shell("echo ready; sleep 30"))
+            .unwrap();
+        assert_eq!(task.name, "sleep 30");
+
+        let stopped = registry.stop(&task.id).await.unwrap();
+        assert_eq!(stopped.state, TaskState::Stopped);
+        assert!(stopped.finished_at.is_some());
+        assert!(registry.stop("task-999").await.is_err());
+
+        let mut saw_start = false;
+        while let Ok(event) = events.try_recv() {
+            if let TaskEvent::Updated(info) = event {
+                saw_start |= info.state == TaskState::Running;
+            }
+        }
+        assert!(saw_start);
+    }
+}
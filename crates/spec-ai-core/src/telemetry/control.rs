@@ -0,0 +1,151 @@
+//! Sampling control channel
+//!
+//! A minimal OpAMP-like protocol of newline-delimited JSON over TCP, as
+//! served by `oui-demo --control`. spec-ai connects, reports its service
+//! name and sample ratio, then applies each ratio it is sent and reports
+//! the ratio in effect:
+//!
+//! ```text
+//! → {"service":"spec-ai","sample_ratio":1.0}
+//! ← {"sample_ratio":0.25}
+//! → {"service":"spec-ai","sample_ratio":0.25}
+//! ```
+//!
+//! The configured ratio is restored whenever the connection drops.
+
+use super::Tracer;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+/// Wait before the first reconnection attempt, doubled after each failure
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+/// Longest wait between reconnection attempts
+const MAX_RETRY: Duration = Duration::from_secs(60);
+
+/// What a sender tells the control channel
+#[derive(Serialize)]
+struct Report<'a> {
+    service: &'a str,
+    sample_ratio: f64,
+}
+
+/// What the control channel asks of a sender
+#[derive(Deserialize)]
+struct Command {
+    sample_ratio: f64,
+}
+
+/// Apply the sample ratios sent over the control channel at `endpoint`,
+/// reconnecting for as long as the process runs
+pub(super) async fn follow(endpoint: String, service: String, tracer: Tracer) {
+    let configured = tracer.sample_ratio();
+    let mut retry = FIRST_RETRY;
+    let mut failing = false;
+    loop {
+        match TcpStream::connect(&endpoint).await {
+            Ok(stream) => {
+                info!("Following sampling control at {}", endpoint);
+                retry = FIRST_RETRY;
+                failing = false;
+                if let Err(err) = serve(stream, &service, &tracer).await {
+                    warn!("Sampling control at {} failed: {:#}", endpoint, err);
+                }
+                tracer.set_sample_ratio(configured);
+                info!(
+                    "Sampling control at {} closed; sampling {} of traces",
+                    endpoint, configured
+                );
+            }
+            Err(err) => {
+                if !failing {
+                    warn!("Failed to reach sampling control at {}: {}", endpoint, err);
+                }
+                failing = true;
+            }
+        }
+        tokio::time::sleep(retry).await;
+        retry = (retry * 2).min(MAX_RETRY);
+    }
+}
+
+/// Report, then apply commands until the other side hangs up
+async fn serve(stream: TcpStream, service: &str, tracer: &Tracer) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    report(&mut writer, service, tracer.sample_ratio()).await?;
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Command>(&line) {
+            Ok(command) if (0.0..=1.0).contains(&command.sample_ratio) => {
+                tracer.set_sample_ratio(command.sample_ratio);
+                info!(
+                    "Sampling {} of traces, as asked over sampling control",
+                    command.sample_ratio
+                );
+            }
+            _ => warn!("Ignoring sampling command {}", line),
+        }
+        report(&mut writer, service, tracer.sample_ratio()).await?;
+    }
+    Ok(())
+}
+
+async fn report(writer: &mut OwnedWriteHalf, service: &str, sample_ratio: f64) -> Result<()> {
+    let mut line = serde_json::to_string(&Report {
+        service,
+        sample_ratio,
+    })?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn ratios_follow_the_control_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let (tracer, _spans) = Tracer::new(1.0);
+        let task = tokio::spawn(follow(endpoint, "spec-ai".to_string(), tracer.clone()));
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let hello = lines.next_line().await.unwrap().unwrap();
+        assert_eq!(hello, r#"{"service":"spec-ai","sample_ratio":1.0}"#);
+
+        writer
+            .write_all(b"{\"sample_ratio\":0.25}\n")
+            .await
+            .unwrap();
+        let applied = lines.next_line().await.unwrap().unwrap();
+        assert_eq!(applied, r#"{"service":"spec-ai","sample_ratio":0.25}"#);
+        assert_eq!(tracer.sample_ratio(), 0.25);
+
+        // Out-of-range ratios are ignored, and the current one reported
+        writer.write_all(b"{\"sample_ratio\":4}\n").await.unwrap();
+        let ignored = lines.next_line().await.unwrap().unwrap();
+        assert_eq!(ignored, applied);
+
+        drop((writer, lines));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while tracer.sample_ratio() != 1.0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("configured ratio restored");
+        task.abort();
+    }
+}
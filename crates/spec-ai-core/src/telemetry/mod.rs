@@ -0,0 +1,517 @@
+//! Self-telemetry: OpenTelemetry spans for agent turns, model calls and tools
+//!
+//! [`start_trace`] opens the span of an agent turn, and [`SpanContext::child`]
+//! the spans of the model calls and tool executions within it. Finished spans
+//! go to the exporter started by [`init`], which sends them to an OTLP
+//! endpoint when spec-ai is built with the `otel` feature. Without an
+//! exporter, or for turns left out by sampling, spans record nothing.
+//!
+//! Attribute names follow the OpenTelemetry GenAI semantic conventions.
+//!
+//! [`annotate`] marks a point in time, such as a deployment or the start of
+//! an incident, with a zero-length span carrying [`ANNOTATION`]; viewers like
+//! the OUI visualizer draw these as labeled markers on their timelines.
+//!
+//! With `control_endpoint` set, the sample ratio follows a sampling control
+//! channel, so an operator can dial traffic up or down from the visualizer.
+
+#[cfg(feature = "otel")]
+mod control;
+#[cfg(feature = "otel")]
+mod otlp;
+
+use crate::broadcast::{Broadcaster, DropPolicy, Subscriber};
+use crate::config::TelemetryConfig;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Instant, SystemTime};
+use tracing::warn;
+
+/// Kind of operation, such as `chat` or `execute_tool`
+pub const GEN_AI_OPERATION_NAME: &str = "gen_ai.operation.name";
+/// Provider a model call went to
+pub const GEN_AI_SYSTEM: &str = "gen_ai.system";
+/// Model asked for
+pub const GEN_AI_REQUEST_MODEL: &str = "gen_ai.request.model";
+/// Model that answered
+pub const GEN_AI_RESPONSE_MODEL: &str = "gen_ai.response.model";
+/// Why the model stopped
+pub const GEN_AI_RESPONSE_FINISH_REASON: &str = "gen_ai.response.finish_reasons";
+/// Prompt tokens
+pub const GEN_AI_USAGE_INPUT_TOKENS: &str = "gen_ai.usage.input_tokens";
+/// Completion tokens
+pub const GEN_AI_USAGE_OUTPUT_TOKENS: &str = "gen_ai.usage.output_tokens";
+/// Agent profile running the turn
+pub const GEN_AI_AGENT_NAME: &str = "gen_ai.agent.name";
+/// Session the turn belongs to
+pub const GEN_AI_CONVERSATION_ID: &str = "gen_ai.conversation.id";
+/// Tool being executed
+pub const GEN_AI_TOOL_NAME: &str = "gen_ai.tool.name";
+/// Run ID of the turn, as used by `DELETE /runs/{run_id}`
+pub const RUN_ID: &str = "spec_ai.run.id";
+/// Tool calls made during a turn
+pub const TOOL_CALLS: &str = "spec_ai.tool_calls";
+/// Whether a tool call succeeded
+pub const TOOL_SUCCESS: &str = "spec_ai.tool.success";
+/// Label of an annotation, present only on annotation spans
+pub const ANNOTATION: &str = "spec_ai.annotation";
+/// Longer description of an annotation
+pub const ANNOTATION_DETAIL: &str = "spec_ai.annotation.detail";
+/// Severity of an annotation: `info`, `warn` or `error`
+pub const ANNOTATION_SEVERITY: &str = "spec_ai.annotation.severity";
+
+/// What a span represents, as in OTLP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    /// Work done within spec-ai, such as a turn or a tool call
+    Internal,
+    /// A request to another service, such as a model provider
+    Client,
+}
+
+/// Value of a span attribute
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<u32> for AttributeValue {
+    fn from(value: u32) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<usize> for AttributeValue {
+    fn from(value: usize) -> Self {
+        Self::Int(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+/// A finished span, ready to export
+#[derive(Debug, Clone)]
+pub struct SpanRecord {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub parent_span_id: Option<[u8; 8]>,
+    pub name: String,
+    pub kind: SpanKind,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Vec<(&'static str, AttributeValue)>,
+    /// Why the operation failed, if it did
+    pub error: Option<String>,
+}
+
+/// How much attention an annotation asks for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationSeverity {
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl AnnotationSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnnotationSeverity::Info => "info",
+            AnnotationSeverity::Warn => "warn",
+            AnnotationSeverity::Error => "error",
+        }
+    }
+}
+
+/// A labeled point in time, such as "deployment" or "incident started"
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    pub label: String,
+    pub detail: Option<String>,
+    pub severity: AnnotationSeverity,
+    pub at: SystemTime,
+}
+
+/// Finished spans waiting for the exporter; the oldest are dropped when it
+/// falls further behind
+const SPAN_CAPACITY: usize = 4096;
+
+/// Opens sampled traces and hands their finished spans to an exporter
+#[derive(Clone)]
+pub struct Tracer {
+    /// Bits of the `f64` ratio, shared with every clone
+    sample_ratio: Arc<AtomicU64>,
+    spans: Broadcaster<SpanRecord>,
+}
+
+impl Tracer {
+    /// A tracer keeping `sample_ratio` of traces, and the receiver of its
+    /// finished spans
+    pub fn new(sample_ratio: f64) -> (Self, Subscriber<SpanRecord>) {
+        let spans = Broadcaster::new(SPAN_CAPACITY, DropPolicy::DropOldest);
+        let receiver = spans.subscribe();
+        (
+            Self {
+                sample_ratio: Arc::new(AtomicU64::new(sample_ratio.to_bits())),
+                spans,
+            },
+            receiver,
+        )
+    }
+
+    /// Fraction of traces kept
+    pub fn sample_ratio(&self) -> f64 {
+        f64::from_bits(self.sample_ratio.load(Ordering::Relaxed))
+    }
+
+    /// Keep `ratio` of the traces started from now on, here and in every
+    /// clone of this tracer
+    pub fn set_sample_ratio(&self, ratio: f64) {
+        let ratio = ratio.clamp(0.0, 1.0);
+        self.sample_ratio.store(ratio.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Open the root span of a new trace, unless sampling leaves it out
+    ///
+    /// Every span in a trace shares its root's sampling decision.
+    pub fn start_trace(&self, name: impl Into<String>) -> Span {
+        let sample_ratio = self.sample_ratio();
+        if sample_ratio < 1.0 && rand::random::<f64>() >= sample_ratio {
+            return Span::disabled();
+        }
+        Span::start(
+            self.clone(),
+            rand::random(),
+            None,
+            name.into(),
+            SpanKind::Internal,
+        )
+    }
+
+    /// Export an annotation, whatever the sample ratio
+    ///
+    /// The annotation becomes a zero-length root span named after its label.
+    pub fn annotate(&self, annotation: &Annotation) {
+        let mut attributes = vec![
+            (ANNOTATION, annotation.label.as_str().into()),
+            (ANNOTATION_SEVERITY, annotation.severity.as_str().into()),
+        ];
+        if let Some(detail) = &annotation.detail {
+            attributes.push((ANNOTATION_DETAIL, detail.as_str().into()));
+        }
+        self.spans.send(SpanRecord {
+            trace_id: rand::random(),
+            span_id: rand::random(),
+            parent_span_id: None,
+            name: annotation.label.clone(),
+            kind: SpanKind::Internal,
+            start: annotation.at,
+            end: annotation.at,
+            attributes,
+            error: None,
+        });
+    }
+}
+
+static TRACER: OnceLock<Tracer> = OnceLock::new();
+
+/// Start exporting spans as `config` describes
+///
+/// The first call with telemetry enabled starts the exporter on the current
+/// Tokio runtime; later calls do nothing, so every agent can call it.
+pub fn init(config: &TelemetryConfig) {
+    if !config.enabled || TRACER.get().is_some() {
+        return;
+    }
+
+    #[cfg(feature = "otel")]
+    {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("Telemetry needs a Tokio runtime; spans will not be exported");
+            return;
+        };
+        let (tracer, spans) = Tracer::new(config.sample_ratio);
+        if install(tracer.clone()) {
+            tracing::info!("Exporting spans to {}", config.endpoint);
+            runtime.spawn(otlp::export(config.clone(), spans));
+            if let Some(endpoint) = &config.control_endpoint {
+                runtime.spawn(control::follow(
+                    endpoint.clone(),
+                    config.service_name.clone(),
+                    tracer,
+                ));
+            }
+        }
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        WARNED.call_once(|| {
+            warn!(
+                "telemetry.enabled is set, but spec-ai was built without the `otel` feature; \
+                 spans will not be exported"
+            );
+        });
+    }
+}
+
+/// Use `tracer` for every trace from now on
+///
+/// For exporters of your own; [`init`] installs the OTLP exporter. Returns
+/// false if a tracer was installed already.
+pub fn install(tracer: Tracer) -> bool {
+    TRACER.set(tracer).is_ok()
+}
+
+/// Open the root span of a new trace
+///
+/// The span records nothing unless [`init`] started an exporter.
+pub fn start_trace(name: impl Into<String>) -> Span {
+    match TRACER.get() {
+        Some(tracer) => tracer.start_trace(name),
+        None => Span::disabled(),
+    }
+}
+
+/// Export an annotation; false if no exporter is running
+pub fn annotate(annotation: &Annotation) -> bool {
+    match TRACER.get() {
+        Some(tracer) => {
+            tracer.annotate(annotation);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Where a span sits in its trace, to open children without borrowing it
+#[derive(Clone, Default)]
+pub struct SpanContext {
+    parent: Option<(Tracer, [u8; 16], [u8; 8])>,
+}
+
+impl SpanContext {
+    /// Open a span within this one's trace
+    pub fn child(&self, name: impl Into<String>, kind: SpanKind) -> Span {
+        match &self.parent {
+            Some((tracer, trace_id, span_id)) => {
+                Span::start(tracer.clone(), *trace_id, Some(*span_id), name.into(), kind)
+            }
+            None => Span::disabled(),
+        }
+    }
+}
+
+struct ActiveSpan {
+    tracer: Tracer,
+    record: SpanRecord,
+    started: Instant,
+}
+
+/// An operation being traced, ending when dropped
+#[derive(Default)]
+pub struct Span {
+    active: Option<ActiveSpan>,
+}
+
+impl Span {
+    /// A span that records nothing
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    fn start(
+        tracer: Tracer,
+        trace_id: [u8; 16],
+        parent_span_id: Option<[u8; 8]>,
+        name: String,
+        kind: SpanKind,
+    ) -> Self {
+        let now = SystemTime::now();
+        Self {
+            active: Some(ActiveSpan {
+                tracer,
+                record: SpanRecord {
+                    trace_id,
+                    span_id: rand::random(),
+                    parent_span_id,
+                    name,
+                    kind,
+                    start: now,
+                    end: now,
+                    attributes: Vec::new(),
+                    error: None,
+                },
+                started: Instant::now(),
+            }),
+        }
+    }
+
+    /// Whether the span will be exported
+    pub fn is_recording(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Context for opening children of this span
+    pub fn context(&self) -> SpanContext {
+        SpanContext {
+            parent: self.active.as_ref().map(|active| {
+                (
+                    active.tracer.clone(),
+                    active.record.trace_id,
+                    active.record.span_id,
+                )
+            }),
+        }
+    }
+
+    /// Open a span within this one
+    pub fn child(&self, name: impl Into<String>, kind: SpanKind) -> Span {
+        self.context().child(name, kind)
+    }
+
+    /// Set attribute `key`, replacing any earlier value
+    pub fn set_attribute(&mut self, key: &'static str, value: impl Into<AttributeValue>) {
+        let Some(active) = &mut self.active else {
+            return;
+        };
+        let value = value.into();
+        let attributes = &mut active.record.attributes;
+        match attributes.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => attributes.push((key, value)),
+        }
+    }
+
+    /// Mark the operation as failed
+    pub fn set_error(&mut self, error: impl fmt::Display) {
+        if let Some(active) = &mut self.active {
+            active.record.error = Some(format!("{:#}", error));
+        }
+    }
+
+    /// End the span now rather than when it is dropped
+    pub fn end(self) {}
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(active) = self.active.take() {
+            let mut record = active.record;
+            record.end = record.start + active.started.elapsed();
+            // The exporter may have stopped; spans are best effort
+            active.tracer.spans.send(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn children_join_their_parents_trace() {
+        let (tracer, mut spans) = Tracer::new(1.0);
+        let mut turn = tracer.start_trace("invoke_agent coder");
+        turn.set_attribute(GEN_AI_AGENT_NAME, "coder");
+        let context = turn.context();
+
+        let mut tool = context.child("execute_tool echo", SpanKind::Internal);
+        tool.set_attribute(TOOL_SUCCESS, false);
+        tool.set_attribute(TOOL_SUCCESS, true);
+        tool.end();
+        let mut call = turn.child("chat gpt", SpanKind::Client);
+        call.set_error(anyhow::anyhow!("rate limited"));
+        drop(call);
+        drop(turn);
+
+        let tool = spans.try_recv().unwrap();
+        let call = spans.try_recv().unwrap();
+        let turn = spans.try_recv().unwrap();
+        assert_eq!(turn.parent_span_id, None);
+        for child in [&tool, &call] {
+            assert_eq!(child.trace_id, turn.trace_id);
+            assert_eq!(child.parent_span_id, Some(turn.span_id));
+            assert_ne!(child.span_id, turn.span_id);
+        }
+        assert_eq!(tool.attributes, vec![(TOOL_SUCCESS, true.into())]);
+        assert_eq!(call.kind, SpanKind::Client);
+        assert_eq!(call.error.as_deref(), Some("rate limited"));
+        assert!(turn.end >= turn.start);
+    }
+
+    #[test]
+    fn unsampled_traces_record_nothing() {
+        let (tracer, mut spans) = Tracer::new(0.0);
+        let turn = tracer.start_trace("invoke_agent");
+        let tool = turn.child("execute_tool", SpanKind::Internal);
+        assert!(!turn.is_recording() && !tool.is_recording());
+        drop((tool, turn));
+        assert!(spans.try_recv().is_err());
+
+        // Spans outside any trace, as when telemetry is off, are inert too
+        let mut orphan = SpanContext::default().child("chat", SpanKind::Client);
+        orphan.set_attribute(GEN_AI_SYSTEM, "openai");
+        assert!(!orphan.is_recording());
+    }
+
+    #[test]
+    fn annotations_ignore_sampling() {
+        let (tracer, mut spans) = Tracer::new(0.0);
+        let at = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(60);
+        tracer.annotate(&Annotation {
+            label: "deployment".to_string(),
+            detail: Some("v2.3.1 to production".to_string()),
+            severity: AnnotationSeverity::Warn,
+            at,
+        });
+
+        let span = spans.try_recv().unwrap();
+        assert_eq!(span.name, "deployment");
+        assert_eq!((span.start, span.end), (at, at));
+        assert_eq!(span.parent_span_id, None);
+        assert_eq!(
+            span.attributes,
+            vec![
+                (ANNOTATION, "deployment".into()),
+                (ANNOTATION_SEVERITY, "warn".into()),
+                (ANNOTATION_DETAIL, "v2.3.1 to production".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! OTLP/gRPC export of finished spans
+
+use super::{AttributeValue, SpanKind, SpanRecord};
+use crate::broadcast::Subscriber;
+use crate::config::TelemetryConfig;
+use anyhow::Result;
+use opentelemetry_proto::tonic::collector::trace::v1::{
+    trace_service_client::TraceServiceClient, ExportTraceServiceRequest,
+};
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use opentelemetry_proto::tonic::trace::v1::{
+    span, status, ResourceSpans, ScopeSpans, Span, Status,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tonic::transport::Channel;
+use tracing::{info, warn};
+
+/// Most spans sent in one export request
+const MAX_BATCH: usize = 512;
+/// How long a finished span waits for others to share its request
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Send finished spans to the configured endpoint until every tracer is gone
+///
+/// Spans that fail to send are dropped rather than queued, so an unreachable
+/// collector costs memory for one batch at most. Spans finished while a slow
+/// collector holds up the exporter wait in the tracer's bounded queue.
+pub(super) async fn export(config: TelemetryConfig, mut spans: Subscriber<SpanRecord>) {
+    let mut client = None;
+    let mut failing = false;
+    while let Ok(first) = spans.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::Instant::now() + FLUSH_INTERVAL;
+        while batch.len() < MAX_BATCH {
+            match tokio::time::timeout_at(deadline, spans.recv()).await {
+                Ok(Ok(span)) => batch.push(span),
+                Ok(Err(_)) | Err(_) => break,
+            }
+        }
+
+        let request = export_request(&config.service_name, batch);
+        match send(&config.endpoint, &mut client, request).await {
+            Ok(()) if failing => {
+                info!("Exporting spans to {} again", config.endpoint);
+                failing = false;
+            }
+            Ok(()) => {}
+            Err(err) => {
+                if !failing {
+                    warn!("Failed to export spans to {}: {:#}", config.endpoint, err);
+                }
+                failing = true;
+                client = None;
+            }
+        }
+    }
+}
+
+async fn send(
+    endpoint: &str,
+    client: &mut Option<TraceServiceClient<Channel>>,
+    request: ExportTraceServiceRequest,
+) -> Result<()> {
+    let client = match client {
+        Some(client) => client,
+        None => client.insert(TraceServiceClient::connect(endpoint.to_string()).await?),
+    };
+    client.export(request).await?;
+    Ok(())
+}
+
+fn export_request(service_name: &str, spans: Vec<SpanRecord>) -> ExportTraceServiceRequest {
+    ExportTraceServiceRequest {
+        resource_spans: vec![ResourceSpans {
+            resource: Some(Resource {
+                attributes: vec![key_value("service.name", service_name.into())],
+                ..Default::default()
+            }),
+            scope_spans: vec![ScopeSpans {
+                scope: Some(InstrumentationScope {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    ..Default::default()
+                }),
+                spans: spans.into_iter().map(proto_span).collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    }
+}
+
+fn proto_span(record: SpanRecord) -> Span {
+    let kind = match record.kind {
+        SpanKind::Internal => span::SpanKind::Internal,
+        SpanKind::Client => span::SpanKind::Client,
+    };
+    Span {
+        trace_id: record.trace_id.to_vec(),
+        span_id: record.span_id.to_vec(),
+        parent_span_id: record
+            .parent_span_id
+            .map(|id| id.to_vec())
+            .unwrap_or_default(),
+        name: record.name,
+        kind: kind as i32,
+        start_time_unix_nano: unix_nanos(record.start),
+        end_time_unix_nano: unix_nanos(record.end),
+        attributes: record
+            .attributes
+            .into_iter()
+            .map(|(key, value)| key_value(key, value))
+            .collect(),
+        // Successful spans keep the unset status, as the spec recommends
+        status: record.error.map(|message| Status {
+            message,
+            code: status::StatusCode::Error as i32,
+        }),
+        ..Default::default()
+    }
+}
+
+fn key_value(key: &str, value: AttributeValue) -> KeyValue {
+    let value = match value {
+        AttributeValue::String(value) => any_value::Value::StringValue(value),
+        AttributeValue::Int(value) => any_value::Value::IntValue(value),
+        AttributeValue::Float(value) => any_value::Value::DoubleValue(value),
+        AttributeValue::Bool(value) => any_value::Value::BoolValue(value),
+    };
+    KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue { value: Some(value) }),
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since| u64::try_from(since.as_nanos()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::{SpanKind as Kind, Tracer, GEN_AI_TOOL_NAME, TOOL_SUCCESS};
+
+    #[test]
+    fn spans_convert_to_otlp() {
+        let (tracer, mut spans) = Tracer::new(1.0);
+        let turn = tracer.start_trace("invoke_agent");
+        let mut tool = turn.child("execute_tool echo", Kind::Internal);
+        tool.set_attribute(GEN_AI_TOOL_NAME, "echo");
+        tool.set_attribute(TOOL_SUCCESS, false);
+        tool.set_error("exit status 1");
+        drop(tool);
+        drop(turn);
+        let records = vec![spans.try_recv().unwrap(), spans.try_recv().unwrap()];
+
+        let request = export_request("spec-ai-test", records);
+        let resource = &request.resource_spans[0];
+        assert_eq!(
+            resource.resource.as_ref().unwrap().attributes,
+            vec![key_value("service.name", "spec-ai-test".into())]
+        );
+        let [tool, turn] = &resource.scope_spans[0].spans[..] else {
+            panic!("expected two spans");
+        };
+        assert_eq!(tool.trace_id, turn.trace_id);
+        assert_eq!(tool.parent_span_id, turn.span_id);
+        assert!(turn.parent_span_id.is_empty());
+        assert_eq!(tool.kind, span::SpanKind::Internal as i32);
+        assert!(tool.end_time_unix_nano >= tool.start_time_unix_nano);
+        assert_eq!(
+            tool.attributes,
+            vec![
+                key_value(GEN_AI_TOOL_NAME, "echo".into()),
+                key_value(TOOL_SUCCESS, false.into()),
+            ]
+        );
+        let status = tool.status.as_ref().unwrap();
+        assert_eq!(status.code, status::StatusCode::Error as i32);
+        assert_eq!(status.message, "exit status 1");
+        assert!(turn.status.is_none());
+    }
+}
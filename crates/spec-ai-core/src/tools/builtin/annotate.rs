@@ -0,0 +1,142 @@
+use crate::telemetry::{self, Annotation, AnnotationSeverity};
+use crate::tools::{Tool, ToolResult};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::SystemTime;
+
+/// Marks a point in time on the exported telemetry timeline
+///
+/// Annotations go out with the agent's spans, so dashboards following the
+/// same OTLP stream (such as the OUI visualizer) show them next to traces.
+pub struct AnnotateTool;
+
+#[derive(Debug, Deserialize)]
+struct AnnotateArgs {
+    label: String,
+    #[serde(default)]
+    detail: Option<String>,
+    #[serde(default)]
+    severity: AnnotationSeverity,
+    /// RFC 3339 time the annotation marks; now if absent
+    #[serde(default)]
+    at: Option<String>,
+}
+
+impl AnnotateTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for AnnotateTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for AnnotateTool {
+    fn name(&self) -> &str {
+        "annotate_timeline"
+    }
+
+    fn description(&self) -> &str {
+        "Adds a labeled marker to the telemetry timeline, e.g. \"deployment\" or \"incident started\", \
+         so people watching traces see what you found next to them"
+    }
+
+    fn parameters(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "label": {
+                    "type": "string",
+                    "description": "Short label shown on the marker"
+                },
+                "detail": {
+                    "type": "string",
+                    "description": "Longer description of what happened"
+                },
+                "severity": {
+                    "type": "string",
+                    "enum": ["info", "warn", "error"],
+                    "description": "How much attention the marker asks for (default: info)"
+                },
+                "at": {
+                    "type": "string",
+                    "description": "RFC 3339 time the marker points at, e.g. 2025-01-31T12:03:00Z (default: now)"
+                }
+            },
+            "required": ["label"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: AnnotateArgs =
+            serde_json::from_value(args).context("Failed to parse annotate_timeline arguments")?;
+        let label = args.label.trim();
+        if label.is_empty() {
+            return Ok(ToolResult::failure("label must not be empty"));
+        }
+        let at = match args.at.as_deref() {
+            Some(at) => match DateTime::parse_from_rfc3339(at) {
+                Ok(at) => SystemTime::from(at),
+                Err(err) => {
+                    return Ok(ToolResult::failure(format!(
+                        "Invalid time '{}': {}",
+                        at, err
+                    )))
+                }
+            },
+            None => SystemTime::now(),
+        };
+
+        let annotation = Annotation {
+            label: label.to_string(),
+            detail: args.detail.filter(|detail| !detail.trim().is_empty()),
+            severity: args.severity,
+            at,
+        };
+        if !telemetry::annotate(&annotation) {
+            return Ok(ToolResult::failure(
+                "Telemetry export is not running; set `enabled = true` under [telemetry] to annotate the timeline",
+            ));
+        }
+        let at = DateTime::<Utc>::from(at).to_rfc3339_opts(SecondsFormat::Secs, true);
+        Ok(ToolResult::success(format!(
+            "Annotated the timeline at {}: {}",
+            at, label
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_bad_times_before_exporting() {
+        let tool = AnnotateTool::new();
+
+        let result = tool
+            .execute(serde_json::json!({"label": "deploy", "at": "12:03"}))
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Invalid time '12:03'"));
+
+        let result = tool
+            .execute(serde_json::json!({"label": "  "}))
+            .await
+            .unwrap();
+        assert!(!result.success);
+
+        assert!(tool
+            .execute(serde_json::json!({"label": "deploy", "severity": "loud"}))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Scoped API tokens for spawned processes and remote tools
+//!
+//! Processes an agent starts sometimes need to call back into the API. Rather
+//! than handing them the operator's credentials, the agent mints a token that
+//! only reaches the endpoints it names and expires after a short TTL. Minting
+//! and revoking go through a [`TokenMinter`], which the API server provides
+//! and which records both in the audit log.
+//!
+//! Whoever runs the tool names the issuer with [`bind_issuer`], so a model or
+//! client cannot write another name into the audit log or revoke that name's
+//! tokens.
+
+use crate::tools::{Tool, ToolResult};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Name of the tool
+pub const API_TOKEN_TOOL: &str = "api_token";
+
+/// What an agent asks for when minting a token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopedTokenRequest {
+    /// Agent minting the token
+    pub issuer: String,
+    pub session_id: Option<String>,
+    /// Endpoints the token may call, such as "POST /query" or "GET /graph/*"
+    pub endpoints: Vec<String>,
+    pub ttl_secs: u64,
+}
+
+/// A minted token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopedToken {
+    pub token: String,
+    /// ID for revoking the token
+    pub token_id: String,
+    pub endpoints: Vec<String>,
+    /// Seconds until the token expires, which may be less than requested
+    pub expires_in: u64,
+}
+
+/// Issues and revokes scoped tokens
+pub trait TokenMinter: Send + Sync {
+    fn mint(&self, request: &ScopedTokenRequest) -> Result<ScopedToken>;
+
+    /// Revoke a minted token; false if it is unknown or already revoked
+    fn revoke(&self, token_id: &str, actor: &str) -> Result<bool>;
+}
+
+/// Tool that mints and revokes scoped API tokens
+pub struct ApiTokenTool {
+    minter: Arc<dyn TokenMinter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ApiTokenArgs {
+    Mint {
+        endpoints: Vec<String>,
+        #[serde(default = "default_ttl_secs")]
+        ttl_secs: u64,
+        /// Filled in by the agent
+        #[serde(default)]
+        issuer: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+    },
+    Revoke {
+        token_id: String,
+        #[serde(default)]
+        issuer: Option<String>,
+    },
+}
+
+fn default_ttl_secs() -> u64 {
+    300
+}
+
+/// Overwrite the issuer and session of `api_token` arguments with the
+/// caller's, whatever the arguments claimed
+pub fn bind_issuer(args: &mut Value, issuer: &str, session_id: Option<&str>) {
+    if let Value::Object(map) = args {
+        map.insert("issuer".to_string(), json!(issuer));
+        map.insert("session_id".to_string(), json!(session_id));
+    }
+}
+
+impl ApiTokenTool {
+    pub fn new(minter: Arc<dyn TokenMinter>) -> Self {
+        Self { minter }
+    }
+}
+
+#[async_trait]
+impl Tool for ApiTokenTool {
+    fn name(&self) -> &str {
+        API_TOKEN_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Mint a short-lived API token limited to specific endpoints, for a spawned process \
+         or remote tool that must call this server (pass it as a Bearer token, e.g. through \
+         the bash tool's env), or revoke one when it is no longer needed. \
+         Never share the operator's own credentials."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["mint", "revoke"],
+                    "description": "Mint a new token or revoke one"
+                },
+                "endpoints": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Endpoints the token may call, as \"METHOD /path\" or \"/path\" for any method; a trailing * matches any suffix (mint)"
+                },
+                "ttl_secs": {
+                    "type": "integer",
+                    "description": "Seconds the token stays valid (mint, default 300)"
+                },
+                "token_id": {
+                    "type": "string",
+                    "description": "ID of the token to revoke (revoke)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: ApiTokenArgs =
+            serde_json::from_value(args).context("Failed to parse api_token arguments")?;
+
+        match args {
+            ApiTokenArgs::Mint {
+                endpoints,
+                ttl_secs,
+                issuer,
+                session_id,
+            } => {
+                if endpoints.is_empty() {
+                    return Ok(ToolResult::failure("A token needs at least one endpoint"));
+                }
+                let request = ScopedTokenRequest {
+                    issuer: issuer.unwrap_or_else(|| "agent".to_string()),
+                    session_id,
+                    endpoints,
+                    ttl_secs,
+                };
+                match self.minter.mint(&request) {
+                    Ok(token) => Ok(ToolResult::success(serde_json::to_string(&token)?)),
+                    Err(err) => Ok(ToolResult::failure(format!("{:#}", err))),
+                }
+            }
+            ApiTokenArgs::Revoke { token_id, issuer } => {
+                let actor = issuer.unwrap_or_else(|| "agent".to_string());
+                match self.minter.revoke(&token_id, &actor) {
+                    Ok(true) => Ok(ToolResult::success(format!("Revoked token {}", token_id))),
+                    Ok(false) => Ok(ToolResult::failure(format!(
+                        "Token {} is unknown or already revoked",
+                        token_id
+                    ))),
+                    Err(err) => Ok(ToolResult::failure(format!("{:#}", err))),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingMinter {
+        requests: Mutex<Vec<ScopedTokenRequest>>,
+    }
+
+    impl TokenMinter for RecordingMinter {
+        fn mint(&self, request: &ScopedTokenRequest) -> Result<ScopedToken> {
+            self.requests.lock().unwrap().push(request.clone());
+            Ok(ScopedToken {
+                token: "signed".to_string(),
+                token_id: "t1".to_string(),
+                endpoints: request.endpoints.clone(),
+                expires_in: request.ttl_secs,
+            })
+        }
+
+        fn revoke(&self, token_id: &str, _actor: &str) -> Result<bool> {
+            Ok(token_id == "t1")
+        }
+    }
+
+    #[tokio::test]
+    async fn mints_and_revokes_through_the_minter() {
+        let minter = Arc::new(RecordingMinter::default());
+        let tool = ApiTokenTool::new(minter.clone());
+
+        let mut args = json!({
+            "action": "mint",
+            "endpoints": ["GET /graph/*"],
+            "issuer": "admin",
+            "session_id": "s9",
+        });
+        bind_issuer(&mut args, "coder", Some("s1"));
+        let result = tool.execute(args).await.unwrap();
+        assert!(result.success);
+        let token: ScopedToken = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(token.expires_in, 300);
+        let request = minter.requests.lock().unwrap()[0].clone();
+        assert_eq!(request.issuer, "coder");
+        assert_eq!(request.session_id.as_deref(), Some("s1"));
+
+        let empty = json!({"action": "mint", "endpoints": []});
+        assert!(!tool.execute(empty).await.unwrap().success);
+
+        let revoke = |id: &str| json!({"action": "revoke", "token_id": id});
+        assert!(tool.execute(revoke("t1")).await.unwrap().success);
+        assert!(!tool.execute(revoke("t9")).await.unwrap().success);
+    }
+}
@@ -0,0 +1,1092 @@
+//! Patch Application Tool
+//!
+//! Applies unified diffs or search/replace edits across files as one change:
+//! every hunk and edit is worked out in memory first, and nothing is written
+//! unless all of them apply. Conflicts come back with the lines the change
+//! expected and the lines the file has, and a successful run returns a
+//! reverse patch that undoes it.
+
+use crate::tools::{Tool, ToolResult};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Unchanged lines around each change in generated diffs
+const CONTEXT_LINES: usize = 3;
+
+/// File lines shown around a conflict
+const CONFLICT_CONTEXT: usize = 3;
+
+/// Largest line table the diff builds before replacing whole regions
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+const NO_NEWLINE: &str = "\\ No newline at end of file";
+
+#[derive(Debug, Deserialize)]
+struct ApplyPatchArgs {
+    patch: Option<String>,
+    #[serde(default)]
+    edits: Vec<Edit>,
+    root: Option<String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Replace `search` with `replace` in one file
+#[derive(Debug, Deserialize)]
+struct Edit {
+    path: String,
+    search: String,
+    replace: String,
+    #[serde(default)]
+    all: bool,
+}
+
+/// A change that could not be applied
+#[derive(Debug, Clone, Serialize)]
+struct Conflict {
+    path: String,
+    /// Hunk header, or `edit N` for search/replace edits
+    location: String,
+    message: String,
+    /// Line of the file the change was expected at, from 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    expected: Vec<String>,
+    /// File lines around where the change was expected
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    actual: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileChange {
+    path: String,
+    action: &'static str,
+    additions: usize,
+    deletions: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct PatchOutput {
+    dry_run: bool,
+    applied: bool,
+    files: Vec<FileChange>,
+    /// Unified diff of the change, for dry runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff: Option<String>,
+    /// Unified diff that undoes the change
+    #[serde(skip_serializing_if = "String::is_empty")]
+    reverse_patch: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    conflicts: Vec<Conflict>,
+}
+
+// ---------- unified diff parsing ----------
+
+#[derive(Debug)]
+struct Hunk {
+    header: String,
+    old_start: usize,
+    /// Context and removed lines, in order
+    old_lines: Vec<String>,
+    /// Context and added lines, in order
+    new_lines: Vec<String>,
+    new_no_newline: bool,
+}
+
+#[derive(Debug)]
+struct FilePatch {
+    /// `None` for files the patch creates
+    old_path: Option<String>,
+    /// `None` for files the patch deletes
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+fn header_path(line: &str, prefix: &str) -> Option<String> {
+    let path = line.split('\t').next().unwrap_or(line).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// `-12,3` or `+7` into a start line and line count
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, count) = match range[1..].split_once(',') {
+        Some((start, count)) => (start, count.parse().ok()?),
+        None => (&range[1..], 1),
+    };
+    Some((start.parse().ok()?, count))
+}
+
+fn parse_patch(text: &str) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        if let (Some(old), Some(new)) = (
+            line.strip_prefix("--- "),
+            lines
+                .get(index + 1)
+                .and_then(|next| next.strip_prefix("+++ ")),
+        ) {
+            // Git prefixes old paths with a/ and new paths with b/
+            let git = (old.starts_with("a/") || old.starts_with("/dev/null"))
+                && (new.starts_with("b/") || new.starts_with("/dev/null"));
+            files.push(FilePatch {
+                old_path: header_path(old, if git { "a/" } else { "" }),
+                new_path: header_path(new, if git { "b/" } else { "" }),
+                hunks: Vec::new(),
+            });
+            index += 2;
+            continue;
+        }
+        if !line.starts_with("@@") {
+            // diff --git, index, mode lines and commentary
+            index += 1;
+            continue;
+        }
+
+        let file = files
+            .last_mut()
+            .ok_or_else(|| anyhow!("Hunk at line {} has no ---/+++ file header", index + 1))?;
+        let mut ranges = line.split_whitespace().skip(1);
+        let (old_start, mut old_remaining) = ranges
+            .next()
+            .filter(|range| range.starts_with('-'))
+            .and_then(parse_range)
+            .ok_or_else(|| anyhow!("Malformed hunk header at line {}: {}", index + 1, line))?;
+        let (_, mut new_remaining) = ranges
+            .next()
+            .filter(|range| range.starts_with('+'))
+            .and_then(parse_range)
+            .ok_or_else(|| anyhow!("Malformed hunk header at line {}: {}", index + 1, line))?;
+        let mut hunk = Hunk {
+            header: line.to_string(),
+            old_start,
+            old_lines: Vec::new(),
+            new_lines: Vec::new(),
+            new_no_newline: false,
+        };
+        index += 1;
+
+        let mut last = ' ';
+        while index < lines.len() && (old_remaining > 0 || new_remaining > 0) {
+            let line = lines[index];
+            // Some tools drop the space of empty context lines
+            let (kind, content) = match line.chars().next() {
+                None => (' ', ""),
+                Some(kind @ (' ' | '-' | '+')) => (kind, &line[1..]),
+                Some('\\') => {
+                    if last != '-' {
+                        hunk.new_no_newline = true;
+                    }
+                    index += 1;
+                    continue;
+                }
+                Some(_) => break,
+            };
+            if matches!(kind, ' ' | '-') {
+                if old_remaining == 0 {
+                    break;
+                }
+                old_remaining -= 1;
+                hunk.old_lines.push(content.to_string());
+            }
+            if matches!(kind, ' ' | '+') {
+                if new_remaining == 0 {
+                    break;
+                }
+                new_remaining -= 1;
+                hunk.new_lines.push(content.to_string());
+            }
+            last = kind;
+            index += 1;
+        }
+        if old_remaining > 0 || new_remaining > 0 {
+            bail!("Hunk '{}' ends before all of its lines", hunk.header);
+        }
+        if lines.get(index).is_some_and(|line| line.starts_with('\\')) {
+            if last != '-' {
+                hunk.new_no_newline = true;
+            }
+            index += 1;
+        }
+        file.hunks.push(hunk);
+    }
+    Ok(files)
+}
+
+// ---------- applying ----------
+
+/// Text of a file split into lines, remembering its line ending
+struct Lines {
+    lines: Vec<String>,
+    crlf: bool,
+    trailing_newline: bool,
+}
+
+impl Lines {
+    fn new(text: &str) -> Self {
+        Self {
+            lines: text.lines().map(str::to_string).collect(),
+            crlf: text.contains("\r\n"),
+            trailing_newline: text.is_empty() || text.ends_with('\n'),
+        }
+    }
+
+    fn text(&self) -> String {
+        if self.lines.is_empty() {
+            return String::new();
+        }
+        let ending = if self.crlf { "\r\n" } else { "\n" };
+        let mut text = self.lines.join(ending);
+        if self.trailing_newline {
+            text.push_str(ending);
+        }
+        text
+    }
+
+    fn matches_at(&self, at: usize, expected: &[String], loose: bool) -> bool {
+        at + expected.len() <= self.lines.len()
+            && self.lines[at..at + expected.len()]
+                .iter()
+                .zip(expected)
+                .all(|(line, expected)| {
+                    line == expected || (loose && line.trim_end() == expected.trim_end())
+                })
+    }
+
+    /// Where `expected` occurs at or after `from`, nearest to `near`
+    fn find(&self, expected: &[String], near: usize, from: usize) -> Option<usize> {
+        let last = self.lines.len().checked_sub(expected.len())?;
+        let near = near.clamp(from, last.max(from));
+        for loose in [false, true] {
+            for distance in 0..=self.lines.len() {
+                let candidates = [near.checked_add(distance), near.checked_sub(distance)];
+                for at in candidates.into_iter().flatten() {
+                    if at >= from && at <= last && self.matches_at(at, expected, loose) {
+                        return Some(at);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn around(&self, at: usize, len: usize) -> Vec<String> {
+        let start = at.saturating_sub(CONFLICT_CONTEXT).min(self.lines.len());
+        let end = (at + len + CONFLICT_CONTEXT).min(self.lines.len());
+        self.lines[start..end].to_vec()
+    }
+}
+
+/// Files the change touches, before and after, by path relative to the root
+struct Workspace {
+    root: PathBuf,
+    /// `None` where a file does not exist
+    files: BTreeMap<PathBuf, (Option<String>, Option<String>)>,
+}
+
+impl Workspace {
+    fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Path under the root; absolute paths and `..` are refused
+    fn resolve(path: &str) -> Result<PathBuf> {
+        let mut resolved = PathBuf::new();
+        for component in Path::new(path.trim()).components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::CurDir => {}
+                _ => bail!("Path '{}' must be relative and stay inside the root", path),
+            }
+        }
+        if resolved.as_os_str().is_empty() {
+            bail!("Empty path in patch");
+        }
+        Ok(resolved)
+    }
+
+    /// Current contents of a file, reading it on first use
+    fn get(&mut self, path: &Path) -> Result<Option<String>> {
+        if let Some((_, current)) = self.files.get(path) {
+            return Ok(current.clone());
+        }
+        let full = self.root.join(path);
+        let original = match fs::read(&full) {
+            Ok(bytes) => Some(
+                String::from_utf8(bytes)
+                    .map_err(|_| anyhow!("{} is not a UTF-8 text file", path.display()))?,
+            ),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", full.display())),
+        };
+        self.files
+            .insert(path.to_path_buf(), (original.clone(), original.clone()));
+        Ok(original)
+    }
+
+    fn set(&mut self, path: &Path, contents: Option<String>) {
+        if let Some((_, current)) = self.files.get_mut(path) {
+            *current = contents;
+        }
+    }
+
+    fn changed(&self) -> impl Iterator<Item = (&PathBuf, &Option<String>, &Option<String>)> {
+        self.files
+            .iter()
+            .filter(|(_, (original, current))| original != current)
+            .map(|(path, (original, current))| (path, original, current))
+    }
+}
+
+fn conflict(path: &Path, location: impl Into<String>, message: impl Into<String>) -> Conflict {
+    Conflict {
+        path: path.display().to_string(),
+        location: location.into(),
+        message: message.into(),
+        line: None,
+        expected: Vec::new(),
+        actual: Vec::new(),
+    }
+}
+
+/// Apply a file's hunks in order, or report the first that does not fit
+fn apply_hunks(path: &Path, text: &str, hunks: &[Hunk]) -> Result<String, Box<Conflict>> {
+    let mut file = Lines::new(text);
+    // Lines added minus lines removed by earlier hunks, plus how far they moved
+    let mut offset: isize = 0;
+    let mut from = 0;
+    for hunk in hunks {
+        let expected_at = if hunk.old_lines.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let near = (expected_at as isize + offset).max(0) as usize;
+        let at = if hunk.old_lines.is_empty() {
+            Some(near.clamp(from, file.lines.len()))
+        } else {
+            file.find(&hunk.old_lines, near, from)
+        };
+        let Some(at) = at else {
+            return Err(Box::new(Conflict {
+                line: Some(near + 1),
+                expected: hunk.old_lines.clone(),
+                actual: file.around(near, hunk.old_lines.len()),
+                ..conflict(path, &hunk.header, "Hunk does not match the file")
+            }));
+        };
+
+        let reaches_end = at + hunk.old_lines.len() == file.lines.len();
+        file.lines.splice(
+            at..at + hunk.old_lines.len(),
+            hunk.new_lines.iter().cloned(),
+        );
+        if reaches_end {
+            file.trailing_newline = !hunk.new_no_newline;
+        }
+        from = at + hunk.new_lines.len();
+        offset = from as isize - (expected_at + hunk.old_lines.len()) as isize;
+    }
+    Ok(file.text())
+}
+
+fn apply_file_patch(workspace: &mut Workspace, patch: &FilePatch) -> Result<Vec<Conflict>> {
+    let old = patch
+        .old_path
+        .as_deref()
+        .map(Workspace::resolve)
+        .transpose()?;
+    let new = patch
+        .new_path
+        .as_deref()
+        .map(Workspace::resolve)
+        .transpose()?;
+    let (source, target) = match (old, new) {
+        (None, None) => bail!("Patch has /dev/null as both old and new file"),
+        (Some(old), None) => (Some(old.clone()), old),
+        (old, Some(new)) => (old, new),
+    };
+
+    let Some(source) = source else {
+        // Creation: the file must not exist yet
+        if workspace.get(&target)?.is_some() {
+            return Ok(vec![conflict(&target, "create", "File already exists")]);
+        }
+        return Ok(match apply_hunks(&target, "", &patch.hunks) {
+            Ok(contents) => {
+                workspace.set(&target, Some(contents));
+                Vec::new()
+            }
+            Err(conflict) => vec![*conflict],
+        });
+    };
+
+    let Some(text) = workspace.get(&source)? else {
+        return Ok(vec![conflict(&source, "modify", "File does not exist")]);
+    };
+    let contents = match apply_hunks(&source, &text, &patch.hunks) {
+        Ok(contents) => contents,
+        Err(conflict) => return Ok(vec![*conflict]),
+    };
+
+    if patch.new_path.is_none() {
+        // Deletion: the hunks must have removed every line
+        if !contents.is_empty() {
+            return Ok(vec![conflict(
+                &source,
+                "delete",
+                "File has lines the deletion does not remove",
+            )]);
+        }
+        workspace.set(&source, None);
+    } else if source != target {
+        if workspace.get(&target)?.is_some() {
+            return Ok(vec![conflict(
+                &target,
+                "rename",
+                "Rename target already exists",
+            )]);
+        }
+        workspace.set(&source, None);
+        workspace.set(&target, Some(contents));
+    } else {
+        workspace.set(&target, Some(contents));
+    }
+    Ok(Vec::new())
+}
+
+fn line_of(text: &str, byte: usize) -> usize {
+    text[..byte].matches('\n').count() + 1
+}
+
+fn apply_edit(workspace: &mut Workspace, number: usize, edit: &Edit) -> Result<Option<Conflict>> {
+    let path = Workspace::resolve(&edit.path)?;
+    let location = format!("edit {}", number);
+    let current = workspace.get(&path)?;
+
+    if edit.search.is_empty() {
+        if current.as_deref().is_some_and(|text| !text.is_empty()) {
+            return Ok(Some(conflict(
+                &path,
+                location,
+                "File already exists; give search text to edit it",
+            )));
+        }
+        workspace.set(&path, Some(edit.replace.clone()));
+        return Ok(None);
+    }
+    let Some(text) = current else {
+        return Ok(Some(conflict(&path, location, "File does not exist")));
+    };
+
+    // Search text written with \n still matches files with \r\n endings
+    let (search, replace) =
+        if !text.contains(&edit.search) && text.contains("\r\n") && !edit.search.contains("\r\n") {
+            (
+                edit.search.replace('\n', "\r\n"),
+                edit.replace.replace('\n', "\r\n"),
+            )
+        } else {
+            (edit.search.clone(), edit.replace.clone())
+        };
+
+    let matches: Vec<usize> = text.match_indices(&search).map(|(at, _)| at).collect();
+    match matches.len() {
+        0 => {
+            let file = Lines::new(&text);
+            let first = search.lines().find(|line| !line.trim().is_empty());
+            let near = first.and_then(|first| {
+                file.lines
+                    .iter()
+                    .position(|line| line.trim() == first.trim())
+            });
+            Ok(Some(Conflict {
+                line: near.map(|at| at + 1),
+                expected: search.lines().map(str::to_string).collect(),
+                actual: near
+                    .map(|at| file.around(at, search.lines().count()))
+                    .unwrap_or_default(),
+                ..conflict(&path, location, "Search text not found")
+            }))
+        }
+        1 => {
+            workspace.set(&path, Some(text.replacen(&search, &replace, 1)));
+            Ok(None)
+        }
+        _ if edit.all => {
+            workspace.set(&path, Some(text.replace(&search, &replace)));
+            Ok(None)
+        }
+        count => {
+            let lines: Vec<String> = matches
+                .iter()
+                .map(|&at| line_of(&text, at).to_string())
+                .collect();
+            Ok(Some(conflict(
+                &path,
+                location,
+                format!(
+                    "Search text matches {} times (lines {}); add context or set all",
+                    count,
+                    lines.join(", ")
+                ),
+            )))
+        }
+    }
+}
+
+// ---------- diffs ----------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op<'a> {
+    Equal(Line<'a>),
+    Delete(Line<'a>),
+    Insert(Line<'a>),
+}
+
+/// A line and whether it ends the file without a newline
+type Line<'a> = (&'a str, bool);
+
+fn split_lines(text: Option<&str>) -> Vec<Line<'_>> {
+    let Some(text) = text else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = text.lines().collect();
+    let no_newline = !text.is_empty() && !text.ends_with('\n');
+    let last = lines.len().saturating_sub(1);
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| (line, no_newline && index == last))
+        .collect()
+}
+
+/// Line operations turning `old` into `new`, from a longest common subsequence
+fn diff_ops<'a>(old: &[Line<'a>], new: &[Line<'a>]) -> Vec<Op<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<Op> = old[..prefix].iter().map(|&line| Op::Equal(line)).collect();
+    if a.len().saturating_mul(b.len()) > MAX_DIFF_CELLS {
+        ops.extend(a.iter().map(|&line| Op::Delete(line)));
+        ops.extend(b.iter().map(|&line| Op::Insert(line)));
+    } else {
+        let width = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * width + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                ops.push(Op::Equal(a[i]));
+                i += 1;
+                j += 1;
+            } else if i < a.len()
+                && (j == b.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+            {
+                ops.push(Op::Delete(a[i]));
+                i += 1;
+            } else {
+                ops.push(Op::Insert(b[j]));
+                j += 1;
+            }
+        }
+    }
+    ops.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|&line| Op::Equal(line)),
+    );
+    ops
+}
+
+fn push_line(out: &mut String, prefix: char, (line, no_newline): Line) {
+    out.push(prefix);
+    out.push_str(line);
+    out.push('\n');
+    if no_newline {
+        out.push_str(NO_NEWLINE);
+        out.push('\n');
+    }
+}
+
+/// Unified diff of one file; `None` stands for a missing file
+fn unified_diff(path: &Path, old: Option<&str>, new: Option<&str>) -> String {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let ops = diff_ops(&old_lines, &new_lines);
+    let path = path.display().to_string().replace('\\', "/");
+    let mut out = format!(
+        "--- {}\n+++ {}\n",
+        if old.is_some() {
+            format!("a/{}", path)
+        } else {
+            "/dev/null".to_string()
+        },
+        if new.is_some() {
+            format!("b/{}", path)
+        } else {
+            "/dev/null".to_string()
+        },
+    );
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(_)))
+        .map(|(index, _)| index)
+        .collect();
+    let mut index = 0;
+    while index < changes.len() {
+        // Join changes separated by fewer than two contexts' worth of lines
+        let mut last = index;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT_LINES {
+            last += 1;
+        }
+        let start = changes[index].saturating_sub(CONTEXT_LINES);
+        let end = (changes[last] + CONTEXT_LINES + 1).min(ops.len());
+
+        let count = |ops: &[Op], old: bool| {
+            ops.iter()
+                .filter(|op| match op {
+                    Op::Equal(_) => true,
+                    Op::Delete(_) => old,
+                    Op::Insert(_) => !old,
+                })
+                .count()
+        };
+        let old_before = count(&ops[..start], true);
+        let new_before = count(&ops[..start], false);
+        let old_count = count(&ops[start..end], true);
+        let new_count = count(&ops[start..end], false);
+        let first = |before: usize, count: usize| if count == 0 { before } else { before + 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            first(old_before, old_count),
+            old_count,
+            first(new_before, new_count),
+            new_count
+        ));
+        for op in &ops[start..end] {
+            match *op {
+                Op::Equal(line) => push_line(&mut out, ' ', line),
+                Op::Delete(line) => push_line(&mut out, '-', line),
+                Op::Insert(line) => push_line(&mut out, '+', line),
+            }
+        }
+        index = last + 1;
+    }
+    out
+}
+
+// ---------- writing ----------
+
+fn write_file(path: &Path, contents: &str) -> Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(parent)
+        .with_context(|| format!("Failed to create parent directories for {}", path.display()))?;
+    let permissions = fs::metadata(path)
+        .ok()
+        .map(|metadata| metadata.permissions());
+    let Some(permissions) = permissions else {
+        return fs::write(path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()));
+    };
+    let mut tmp = NamedTempFile::new_in(parent)
+        .with_context(|| format!("Failed to create temporary file near {}", path.display()))?;
+    tmp.write_all(contents.as_bytes())
+        .with_context(|| format!("Failed to write temporary file for {}", path.display()))?;
+    tmp.as_file().set_permissions(permissions).ok();
+    tmp.persist(path)
+        .map_err(|err| anyhow!("Failed to replace {}: {}", path.display(), err.error))?;
+    Ok(())
+}
+
+fn restore(path: &Path, original: &Option<String>) -> Result<()> {
+    match original {
+        Some(contents) => write_file(path, contents),
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Write every changed file, putting back those already written on failure
+fn commit(workspace: &Workspace) -> Result<()> {
+    let mut written: Vec<(PathBuf, &Option<String>)> = Vec::new();
+    for (path, original, current) in workspace.changed() {
+        let full = workspace.root.join(path);
+        let result = match current {
+            Some(contents) => write_file(&full, contents),
+            None => fs::remove_file(&full)
+                .with_context(|| format!("Failed to delete {}", full.display())),
+        };
+        if let Err(e) = result {
+            for (path, original) in written.iter().rev() {
+                if let Err(restore_err) = restore(path, original) {
+                    tracing::warn!("Failed to restore {}: {:#}", path.display(), restore_err);
+                }
+            }
+            return Err(e.context("Patch was not applied; files written so far were restored"));
+        }
+        written.push((full, original));
+    }
+    Ok(())
+}
+
+/// Work out the change `args` describe without writing it
+fn plan(args: &ApplyPatchArgs) -> Result<(Workspace, Vec<Conflict>)> {
+    let root = PathBuf::from(args.root.as_deref().unwrap_or("."));
+    if !root.is_dir() {
+        bail!("Root {} is not a directory", root.display());
+    }
+    let patches = match &args.patch {
+        Some(patch) if !patch.trim().is_empty() => parse_patch(patch)?,
+        _ => Vec::new(),
+    };
+    if patches.is_empty() && args.edits.is_empty() {
+        bail!("Nothing to apply: give a unified diff in `patch` or search/replace `edits`");
+    }
+
+    let mut workspace = Workspace::new(root);
+    let mut conflicts = Vec::new();
+    for patch in &patches {
+        conflicts.extend(apply_file_patch(&mut workspace, patch)?);
+    }
+    for (index, edit) in args.edits.iter().enumerate() {
+        conflicts.extend(apply_edit(&mut workspace, index + 1, edit)?);
+    }
+    Ok((workspace, conflicts))
+}
+
+fn run(args: &ApplyPatchArgs) -> Result<PatchOutput> {
+    let (workspace, conflicts) = plan(args)?;
+    let mut files = Vec::new();
+    let mut diff = String::new();
+    let mut reverse_patch = String::new();
+    for (path, original, current) in workspace.changed() {
+        let forward = unified_diff(path, original.as_deref(), current.as_deref());
+        let count = |prefix: char| {
+            forward
+                .lines()
+                .skip(2)
+                .filter(|line| line.starts_with(prefix))
+                .count()
+        };
+        files.push(FileChange {
+            path: path.display().to_string(),
+            action: match (original, current) {
+                (None, _) => "created",
+                (_, None) => "deleted",
+                _ => "modified",
+            },
+            additions: count('+'),
+            deletions: count('-'),
+        });
+        reverse_patch.push_str(&unified_diff(path, current.as_deref(), original.as_deref()));
+        diff.push_str(&forward);
+    }
+
+    let applied = conflicts.is_empty() && !args.dry_run;
+    if applied {
+        commit(&workspace)?;
+    }
+    Ok(PatchOutput {
+        dry_run: args.dry_run,
+        applied,
+        files,
+        diff: args.dry_run.then_some(diff),
+        reverse_patch: if conflicts.is_empty() {
+            reverse_patch
+        } else {
+            String::new()
+        },
+        conflicts,
+    })
+}
+
+/// Diff of the change an `apply_patch` call would make, for approval prompts
+pub fn preview(arguments: &Value) -> Option<String> {
+    let args: ApplyPatchArgs = serde_json::from_value(arguments.clone()).ok()?;
+    let (workspace, _) = plan(&args).ok()?;
+    let diff: String = workspace
+        .changed()
+        .map(|(path, original, current)| {
+            unified_diff(path, original.as_deref(), current.as_deref())
+        })
+        .collect();
+    (!diff.is_empty()).then_some(diff)
+}
+
+/// Tool that applies patches and search/replace edits atomically
+pub struct ApplyPatchTool;
+
+impl ApplyPatchTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for ApplyPatchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for ApplyPatchTool {
+    fn name(&self) -> &str {
+        "apply_patch"
+    }
+
+    fn description(&self) -> &str {
+        "Applies a unified diff or search/replace edits to one or more files, all or nothing. \
+         Reports conflicts with the surrounding file lines, supports dry runs, and returns a \
+         reverse patch that undoes the change. Prefer this over file_write for edits to \
+         existing files."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "patch": {
+                    "type": "string",
+                    "description": "Unified diff (as produced by `git diff` or `diff -u`); /dev/null headers create or delete files"
+                },
+                "edits": {
+                    "type": "array",
+                    "description": "Search/replace edits, applied after the patch in order",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "path": { "type": "string", "description": "File to edit, relative to root" },
+                            "search": { "type": "string", "description": "Exact text to find; must match once unless `all` is set. Empty creates the file" },
+                            "replace": { "type": "string", "description": "Text to put in its place" },
+                            "all": { "type": "boolean", "description": "Replace every match (default: false)" }
+                        },
+                        "required": ["path", "search", "replace"]
+                    }
+                },
+                "root": {
+                    "type": "string",
+                    "description": "Directory the paths are relative to (default: current directory)"
+                },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "Check the change and return its diff without writing (default: false)"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: ApplyPatchArgs =
+            serde_json::from_value(args).context("Failed to parse apply_patch arguments")?;
+        let output = match run(&args) {
+            Ok(output) => output,
+            Err(e) => return Ok(ToolResult::failure(format!("apply_patch failed: {:#}", e))),
+        };
+        let json =
+            serde_json::to_string(&output).context("Failed to serialize apply_patch output")?;
+        if output.conflicts.is_empty() {
+            Ok(ToolResult::success(json))
+        } else {
+            Ok(ToolResult::failure(json))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    async fn apply(root: &Path, args: Value) -> (bool, Value) {
+        let mut args = args;
+        args["root"] = json!(root.to_string_lossy());
+        let result = ApplyPatchTool::new().execute(args).await.unwrap();
+        let body = result.error.as_deref().unwrap_or(&result.output);
+        (
+            result.success,
+            serde_json::from_str(body).unwrap_or(json!(body)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_unified_diff_applies_and_reverses() {
+        let dir = tempdir().unwrap();
+        let lines: Vec<String> = (1..=12).map(|n| format!("line {}", n)).collect();
+        fs::write(dir.path().join("a.txt"), lines.join("\n") + "\n").unwrap();
+
+        // Written against an older copy: the hunk sits two lines lower now
+        let patch = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -6,3 +6,3 @@
+ line 8
+-line 9
++line nine
+ line 10
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++fresh
++file
+";
+        let (ok, dry) = apply(dir.path(), json!({ "patch": patch, "dry_run": true })).await;
+        assert!(ok);
+        assert_eq!(dry["applied"], false);
+        assert!(dry["diff"].as_str().unwrap().contains("+line nine"));
+        assert!(!dir.path().join("new.txt").exists());
+
+        let (ok, output) = apply(dir.path(), json!({ "patch": patch })).await;
+        assert!(ok, "{}", output);
+        let text = fs::read_to_string(dir.path().join("a.txt")).unwrap();
+        assert!(text.contains("line 8\nline nine\nline 10\n"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("new.txt")).unwrap(),
+            "fresh\nfile\n"
+        );
+        assert_eq!(output["files"].as_array().unwrap().len(), 2);
+
+        let reverse = output["reverse_patch"].as_str().unwrap();
+        let (ok, _) = apply(dir.path(), json!({ "patch": reverse })).await;
+        assert!(ok);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            lines.join("\n") + "\n"
+        );
+        assert!(!dir.path().join("new.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_leave_every_file_untouched() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("one.txt"), "alpha\nbeta\n").unwrap();
+        fs::write(dir.path().join("two.txt"), "gamma\ndelta\n").unwrap();
+
+        let (ok, output) = apply(
+            dir.path(),
+            json!({
+                "edits": [
+                    { "path": "one.txt", "search": "beta", "replace": "BETA" },
+                    { "path": "two.txt", "search": "epsilon", "replace": "E" }
+                ]
+            }),
+        )
+        .await;
+        assert!(!ok);
+        assert_eq!(output["applied"], false);
+        let conflict = &output["conflicts"][0];
+        assert_eq!(conflict["path"], "two.txt");
+        assert_eq!(conflict["location"], "edit 2");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("one.txt")).unwrap(),
+            "alpha\nbeta\n"
+        );
+
+        let patch = "--- a/one.txt\n+++ b/one.txt\n@@ -1,2 +1,2 @@\n alpha\n-zeta\n+eta\n";
+        let (ok, output) = apply(dir.path(), json!({ "patch": patch })).await;
+        assert!(!ok);
+        let conflict = &output["conflicts"][0];
+        assert_eq!(conflict["expected"], json!(["alpha", "zeta"]));
+        assert_eq!(conflict["actual"], json!(["alpha", "beta"]));
+    }
+
+    #[tokio::test]
+    async fn test_search_replace_edits() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("crlf.txt"), "x = 1\r\ny = 1\r\n").unwrap();
+
+        let (ok, output) = apply(
+            dir.path(),
+            json!({ "edits": [{ "path": "crlf.txt", "search": "= 1", "replace": "= 2" }] }),
+        )
+        .await;
+        assert!(!ok);
+        assert!(output["conflicts"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("lines 1, 2"));
+
+        let (ok, output) = apply(
+            dir.path(),
+            json!({ "edits": [
+                { "path": "crlf.txt", "search": "x = 1\ny", "replace": "x = 3\ny" },
+                { "path": "crlf.txt", "search": "1", "replace": "4", "all": true },
+                { "path": "sub/made.txt", "search": "", "replace": "made\n" }
+            ] }),
+        )
+        .await;
+        assert!(ok, "{}", output);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("crlf.txt")).unwrap(),
+            "x = 3\r\ny = 4\r\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("sub/made.txt")).unwrap(),
+            "made\n"
+        );
+
+        let (ok, output) = apply(
+            dir.path(),
+            json!({ "edits": [{ "path": "../escape.txt", "search": "", "replace": "x" }] }),
+        )
+        .await;
+        assert!(!ok);
+        assert!(output.as_str().unwrap().contains("inside the root"));
+    }
+
+    #[test]
+    fn test_unified_diff_marks_missing_newline() {
+        let diff = unified_diff(Path::new("f"), Some("a\nb"), Some("a\nb\n"));
+        assert_eq!(
+            diff,
+            "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
+        );
+        let patches = parse_patch(&diff).unwrap();
+        assert_eq!(
+            apply_hunks(Path::new("f"), "a\nb", &patches[0].hunks).unwrap(),
+            "a\nb\n"
+        );
+        let reverse = unified_diff(Path::new("f"), Some("a\nb\n"), Some("a\nb"));
+        let patches = parse_patch(&reverse).unwrap();
+        assert_eq!(
+            apply_hunks(Path::new("f"), "a\nb\n", &patches[0].hunks).unwrap(),
+            "a\nb"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::agent::question::{ASK_USER_TOOL, MAX_CHOICES};
+use crate::tools::{Tool, ToolResult};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// Describes the `ask_user` tool to the model
+///
+/// The calling agent sends the question to its front end itself (see
+/// [`crate::agent::question`]), so executing this tool from the registry
+/// only reports that it needs an agent.
+pub struct AskUserTool;
+
+impl AskUserTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for AskUserTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for AskUserTool {
+    fn name(&self) -> &str {
+        ASK_USER_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Ask the user a question and wait for the answer. Use it when you cannot go on \
+         without a decision or detail only the user has. Offer choices when the possible \
+         answers are known, ask for a file when you need a path, and otherwise take free text."
+    }
+
+    fn parameters(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "question": {
+                    "type": "string",
+                    "description": "The question, phrased for the user"
+                },
+                "context": {
+                    "type": "string",
+                    "description": "Why you are asking, shown under the question"
+                },
+                "form": {
+                    "type": "string",
+                    "enum": ["text", "choice", "file"],
+                    "description": "Kind of answer (default: choice when choices are given, else text)"
+                },
+                "choices": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "maxItems": MAX_CHOICES,
+                    "description": "Options to choose from"
+                },
+                "multiple": {
+                    "type": "boolean",
+                    "description": "Whether several choices may be selected"
+                },
+                "allow_other": {
+                    "type": "boolean",
+                    "description": "Whether the user may type an answer that is not among the choices"
+                },
+                "placeholder": {
+                    "type": "string",
+                    "description": "Example text answer"
+                },
+                "multiline": {
+                    "type": "boolean",
+                    "description": "Whether a text answer may span several lines"
+                },
+                "extensions": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "File extensions a picked file may have, e.g. [\"toml\"]"
+                }
+            },
+            "required": ["question"]
+        })
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult> {
+        Ok(ToolResult::failure(format!(
+            "{} can only be run by an agent",
+            ASK_USER_TOOL
+        )))
+    }
+}
@@ -1,3 +1,4 @@
+use crate::tools::sandbox::{Sandbox, SandboxViolation};
 use crate::tools::{Tool, ToolResult};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
@@ -5,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::process::Command;
 use tokio::time;
@@ -32,6 +34,8 @@ struct CommandOutput {
     stderr: String,
     exit_code: i32,
     duration_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    violation: Option<SandboxViolation>,
 }
 
 fn truncate_output(input: &[u8]) -> String {
@@ -45,7 +49,7 @@ fn truncate_output(input: &[u8]) -> String {
     }
 }
 
-fn validate_command(command: &str) -> Result<()> {
+pub(crate) fn validate_command(command: &str) -> Result<()> {
     if command.trim().is_empty() {
         return Err(anyhow!("Command cannot be empty"));
     }
@@ -69,7 +73,11 @@ fn validate_command(command: &str) -> Result<()> {
     Ok(())
 }
 
-async fn run_bash_command(args: &BashArgs, shell_path: &Path) -> Result<CommandOutput> {
+async fn run_bash_command(
+    args: &BashArgs,
+    shell_path: &Path,
+    sandbox: Option<&Sandbox>,
+) -> Result<CommandOutput> {
     if !shell_path.exists() {
         return Err(anyhow!(format!(
             "Shell path {} does not exist",
@@ -86,19 +94,31 @@ async fn run_bash_command(args: &BashArgs, shell_path: &Path) -> Result<CommandO
         "Executing bash command"
     );
 
-    let timeout = args
-        .timeout_ms
-        .map(Duration::from_millis)
-        .unwrap_or(DEFAULT_TIMEOUT);
+    let requested = args.timeout_ms.map(Duration::from_millis);
+    let timeout = match sandbox {
+        Some(sandbox) => sandbox.timeout(requested),
+        None => requested.unwrap_or(DEFAULT_TIMEOUT),
+    };
 
-    let mut command = Command::new(shell_path);
-    command.arg("-c").arg(&args.command);
+    let mut command = match sandbox {
+        Some(sandbox) => {
+            let working_dir = args.working_dir.as_deref().map(Path::new);
+            match sandbox.command(shell_path, &args.command, working_dir) {
+                Ok(command) => command,
+                Err(violation) => return Ok(CommandOutput::blocked(args, violation, 0)),
+            }
+        }
+        None => {
+            let mut command = Command::new(shell_path);
+            command.arg("-c").arg(&args.command);
+            if let Some(dir) = &args.working_dir {
+                command.current_dir(dir);
+            }
+            command
+        }
+    };
     command.kill_on_drop(true);
 
-    if let Some(dir) = &args.working_dir {
-        command.current_dir(dir);
-    }
-
     if let Some(env) = &args.env {
         for (key, value) in env {
             command.env(key, value);
@@ -108,6 +128,13 @@ async fn run_bash_command(args: &BashArgs, shell_path: &Path) -> Result<CommandO
     let start = Instant::now();
     let output = match time::timeout(timeout, command.output()).await {
         Ok(result) => result.context("Failed to execute bash command")?,
+        Err(_) if sandbox.is_some() => {
+            let violation = SandboxViolation::Timeout {
+                limit_ms: timeout.as_millis(),
+            };
+            let duration = start.elapsed().as_millis();
+            return Ok(CommandOutput::blocked(args, violation, duration));
+        }
         Err(_) => {
             return Err(anyhow!(format!(
                 "Command timed out after {} ms",
@@ -120,6 +147,7 @@ async fn run_bash_command(args: &BashArgs, shell_path: &Path) -> Result<CommandO
     let stdout = truncate_output(&output.stdout);
     let stderr = truncate_output(&output.stderr);
     let exit_code = output.status.code().unwrap_or_default();
+    let violation = sandbox.and_then(|sandbox| sandbox.classify(&output.status, &stderr));
 
     info!(
         target: "spec_ai::tools::bash",
@@ -135,24 +163,50 @@ async fn run_bash_command(args: &BashArgs, shell_path: &Path) -> Result<CommandO
         stderr,
         exit_code,
         duration_ms: duration,
+        violation,
     })
 }
 
+impl CommandOutput {
+    /// Output of a command the sandbox stopped or refused to start
+    fn blocked(args: &BashArgs, violation: SandboxViolation, duration_ms: u128) -> Self {
+        Self {
+            command: args.command.clone(),
+            stdout: String::new(),
+            stderr: violation.to_string(),
+            exit_code: -1,
+            duration_ms,
+            violation: Some(violation),
+        }
+    }
+}
+
 /// Tool that executes bash commands with safety checks
 pub struct BashTool {
     shell_path: String,
+    sandbox: Option<Arc<Sandbox>>,
 }
 
 impl BashTool {
     pub fn new() -> Self {
         let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-        Self { shell_path: shell }
+        Self {
+            shell_path: shell,
+            sandbox: None,
+        }
     }
 
     pub fn with_shell(mut self, path: impl Into<String>) -> Self {
         self.shell_path = path.into();
         self
     }
+
+    /// Run commands inside `sandbox`, reporting limits they hit as
+    /// violations instead of errors
+    pub fn with_sandbox(mut self, sandbox: Arc<Sandbox>) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
 }
 
 impl Default for BashTool {
@@ -203,7 +257,7 @@ impl Tool for BashTool {
             serde_json::from_value(args).context("Failed to parse bash arguments")?;
         let shell_path = Path::new(&self.shell_path);
 
-        let output = run_bash_command(&args, shell_path).await?;
+        let output = run_bash_command(&args, shell_path, self.sandbox.as_deref()).await?;
 
         if output.exit_code == 0 {
             Ok(ToolResult::success(
@@ -249,4 +303,23 @@ mod tests {
         let result = tool.execute(args).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_sandboxed_timeout_is_a_violation() {
+        let config = crate::config::SandboxConfig {
+            backend: "rlimit".to_string(),
+            timeout_secs: 1,
+            ..Default::default()
+        };
+        let sandbox =
+            Sandbox::from_config(&config, &crate::policy::PolicyEngine::new(), "agent").unwrap();
+        let tool = BashTool::new().with_sandbox(Arc::new(sandbox));
+        let args = serde_json::json!({ "command": "sleep 5", "timeout_ms": 10000 });
+        let result = tool.execute(args).await.unwrap();
+        assert!(!result.success);
+        let payload: serde_json::Value =
+            serde_json::from_str(result.error.as_deref().unwrap()).unwrap();
+        assert_eq!(payload["violation"]["kind"], "timeout");
+        assert_eq!(payload["violation"]["limit_ms"], 1000);
+    }
 }
@@ -1,17 +1,36 @@
+#[cfg(feature = "code-index")]
+use crate::code_index::{SymbolIndex, SymbolKind, SymbolQuery};
 use crate::tools::{Tool, ToolResult};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+#[cfg(feature = "code-index")]
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+#[cfg(feature = "code-index")]
+use std::sync::{Arc, Mutex};
 use toak_rs::{JsonDatabaseGenerator, JsonDatabaseOptions, SemanticSearch};
 
 const DEFAULT_TOP_N: usize = 3;
 const MAX_TOP_N: usize = 25;
+#[cfg(feature = "code-index")]
+const DEFAULT_SYMBOL_RESULTS: usize = 20;
+#[cfg(feature = "code-index")]
+const MAX_SYMBOL_RESULTS: usize = 100;
+
+#[cfg(feature = "code-index")]
+const DESCRIPTION: &str = "Searches code. Queries like \"definition of Parser::parse\", \"callers of tokenize\" or a bare symbol name are answered from a tree-sitter symbol index (Rust, Python, JavaScript, TypeScript, Go) with file, line and snippet; other text uses semantic search over toak-rs embeddings";
+#[cfg(not(feature = "code-index"))]
+const DESCRIPTION: &str =
+    "Searches code semantically over toak-rs embeddings, returning the closest files with snippets";
 
 #[derive(Debug, Deserialize)]
 struct CodeSearchArgs {
     query: String,
+    mode: Option<String>,
+    #[cfg(feature = "code-index")]
+    kind: Option<String>,
     top_n: Option<usize>,
     root: Option<String>,
     refresh: Option<bool>,
@@ -27,20 +46,32 @@ struct CodeSearchResult {
 #[derive(Debug, Serialize)]
 struct CodeSearchResponse {
     query: String,
+    mode: &'static str,
     root: String,
     top_n: usize,
     results: Vec<CodeSearchResult>,
 }
 
-/// Simple semantic code search powered by toak-rs embeddings.
+/// Code search that answers symbol queries from a tree-sitter index and
+/// other queries with toak-rs embeddings.
+///
+/// Without the `code-index` feature every query uses the embeddings.
 pub struct CodeSearchTool {
     root: PathBuf,
+    /// Symbol indexes by root, kept between calls so only changed files
+    /// are parsed again
+    #[cfg(feature = "code-index")]
+    indexes: Arc<Mutex<HashMap<PathBuf, SymbolIndex>>>,
 }
 
 impl CodeSearchTool {
     pub fn new() -> Self {
         let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        Self { root }
+        Self {
+            root,
+            #[cfg(feature = "code-index")]
+            indexes: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
 
     fn resolve_root(&self, override_root: &Option<String>) -> PathBuf {
@@ -83,6 +114,61 @@ impl CodeSearchTool {
 
         Ok(embeddings_path)
     }
+
+    /// Answer `query` from the symbol index of `root`
+    #[cfg(feature = "code-index")]
+    async fn symbol_search(
+        &self,
+        root: PathBuf,
+        query: SymbolQuery,
+        kind: Option<SymbolKind>,
+        top_n: usize,
+    ) -> Result<Value> {
+        let indexes = self.indexes.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut indexes = indexes.lock().unwrap_or_else(|err| err.into_inner());
+            let index = indexes
+                .entry(root.clone())
+                .or_insert_with(|| SymbolIndex::new(&root));
+            let stats = index.refresh().context("indexing symbols")?;
+
+            let (mode, name, results) = match &query {
+                SymbolQuery::Definition(name) => {
+                    let found: Vec<Value> = index
+                        .definitions(name)
+                        .into_iter()
+                        .filter(|s| kind.is_none_or(|kind| s.kind == kind))
+                        .map(|s| json!(s))
+                        .collect();
+                    ("definition", name, found)
+                }
+                SymbolQuery::Callers(name) => {
+                    let found = index.callers(name).into_iter().map(|c| json!(c)).collect();
+                    ("callers", name, found)
+                }
+                SymbolQuery::Search(name) => {
+                    let found = index
+                        .search(name, kind)
+                        .into_iter()
+                        .map(|s| json!(s))
+                        .collect();
+                    ("symbols", name, found)
+                }
+            };
+            let total = results.len();
+            Ok(json!({
+                "mode": mode,
+                "symbol": name,
+                "root": root.display().to_string(),
+                "files_indexed": stats.files,
+                "index_truncated": stats.truncated,
+                "total": total,
+                "results": results.into_iter().take(top_n).collect::<Vec<_>>(),
+            }))
+        })
+        .await
+        .context("symbol search task failed")?
+    }
 }
 
 impl Default for CodeSearchTool {
@@ -97,8 +183,12 @@ impl Tool for CodeSearchTool {
         "code_search"
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
-        "Semantic code search using toak-rs embeddings"
+        DESCRIPTION
     }
 
     fn parameters(&self) -> Value {
@@ -107,11 +197,20 @@ impl Tool for CodeSearchTool {
             "properties": {
                 "query": {
                     "type": "string",
-                    "description": "Search query text"
+                    "description": "\"definition of X\", \"callers of X\", a symbol name, or free text for semantic search"
+                },
+                "mode": {
+                    "type": "string",
+                    "enum": ["auto", "definition", "callers", "symbols", "semantic"],
+                    "description": "How to read the query; auto (default) picks from its wording"
+                },
+                "kind": {
+                    "type": "string",
+                    "description": "Only return symbols of this kind: function, method, struct, enum, trait, interface, class, type, impl, module, macro or const"
                 },
                 "top_n": {
                     "type": "integer",
-                    "description": "Number of results to return (default 3, max 25)"
+                    "description": "Number of results to return (semantic: default 3, max 25; symbols: default 20, max 100)"
                 },
                 "root": {
                     "type": "string",
@@ -126,6 +225,32 @@ impl Tool for CodeSearchTool {
         })
     }
 
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "mode": { "enum": ["definition", "callers", "symbols", "semantic"] },
+                "root": { "type": "string" },
+                "total": { "type": "integer", "minimum": 0 },
+                "results": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "path": { "type": "string" },
+                            "line": { "type": "integer", "minimum": 1 },
+                            "snippet": { "type": "string" },
+                            "similarity": { "type": "number" }
+                        },
+                        "required": ["path"]
+                    }
+                }
+            },
+            "required": ["query", "mode", "root", "results"]
+        }))
+    }
+
     async fn execute(&self, args: Value) -> Result<ToolResult> {
         let args: CodeSearchArgs =
             serde_json::from_value(args).context("Failed to parse code_search arguments")?;
@@ -134,13 +259,55 @@ impl Tool for CodeSearchTool {
             return Err(anyhow!("query cannot be empty"));
         }
 
-        let top_n = args.top_n.unwrap_or(DEFAULT_TOP_N).clamp(1, MAX_TOP_N);
-
         let root = self.resolve_root(&args.root);
         if !root.exists() {
             return Err(anyhow!("Search root {} does not exist", root.display()));
         }
 
+        let mode = args.mode.as_deref().unwrap_or("auto");
+        #[cfg(not(feature = "code-index"))]
+        match mode {
+            "auto" | "semantic" => {}
+            "definition" | "callers" | "symbols" => {
+                return Err(anyhow!(
+                    "code_search mode '{}' needs a build with the code-index feature",
+                    mode
+                ))
+            }
+            other => return Err(anyhow!("unknown code_search mode '{}'", other)),
+        }
+        #[cfg(feature = "code-index")]
+        let symbol_query = {
+            let query = args.query.trim().to_string();
+            match mode {
+                "auto" => SymbolQuery::parse(&query),
+                "definition" => Some(SymbolQuery::Definition(query)),
+                "callers" => Some(SymbolQuery::Callers(query)),
+                "symbols" => Some(SymbolQuery::Search(query)),
+                "semantic" => None,
+                other => return Err(anyhow!("unknown code_search mode '{}'", other)),
+            }
+        };
+        #[cfg(feature = "code-index")]
+        if let Some(symbol_query) = symbol_query {
+            let kind = match args.kind.as_deref() {
+                Some(name) => Some(
+                    SymbolKind::parse(name)
+                        .ok_or_else(|| anyhow!("unknown symbol kind '{}'", name))?,
+                ),
+                None => None,
+            };
+            let top_n = args
+                .top_n
+                .unwrap_or(DEFAULT_SYMBOL_RESULTS)
+                .clamp(1, MAX_SYMBOL_RESULTS);
+            let mut response = self.symbol_search(root, symbol_query, kind, top_n).await?;
+            response["query"] = Value::String(args.query);
+            return Ok(ToolResult::success(response.to_string()).with_data(response));
+        }
+
+        let top_n = args.top_n.unwrap_or(DEFAULT_TOP_N).clamp(1, MAX_TOP_N);
+
         let refresh = args.refresh.unwrap_or(false);
         let embeddings_path = self
             .ensure_embeddings(&root, refresh, top_n)
@@ -171,14 +338,14 @@ impl Tool for CodeSearchTool {
 
         let response = CodeSearchResponse {
             query: args.query,
+            mode: "semantic",
             root: root.display().to_string(),
             top_n,
             results,
         };
 
-        Ok(ToolResult::success(
-            serde_json::to_string(&response).context("serializing search response")?,
-        ))
+        let response = serde_json::to_value(&response).context("serializing search response")?;
+        Ok(ToolResult::success(response.to_string()).with_data(response))
     }
 }
 
@@ -189,6 +356,52 @@ mod tests {
     use std::process::Command;
     use tempfile::tempdir;
 
+    #[cfg(feature = "code-index")]
+    #[tokio::test]
+    async fn symbol_queries_use_the_index() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("lib.rs"),
+            "pub struct Lexer;\n\nimpl Lexer {\n    pub fn next_token(&mut self) {}\n}\n\nfn run(lexer: &mut Lexer) {\n    lexer.next_token();\n}\n",
+        )
+        .unwrap();
+        let tool = CodeSearchTool::new();
+        let search = |query: &str| {
+            let args = json!({ "query": query, "root": root.to_string_lossy() });
+            let tool = &tool;
+            async move {
+                let result = tool.execute(args).await.unwrap();
+                assert!(result.success);
+                let data = result.data.unwrap();
+                let schema = tool.output_schema().unwrap();
+                assert_eq!(crate::tools::schema::validate(&schema, &data), Ok(()));
+                assert_eq!(serde_json::from_str::<Value>(&result.output).unwrap(), data);
+                data
+            }
+        };
+
+        let found = search("definition of Lexer::next_token").await;
+        assert_eq!(found["mode"], "definition");
+        assert_eq!(found["results"][0]["path"], "lib.rs");
+        assert_eq!(found["results"][0]["line"], 4);
+        assert_eq!(found["results"][0]["kind"], "method");
+        assert_eq!(found["results"][0]["container"], "Lexer");
+
+        let found = search("callers of next_token").await;
+        assert_eq!(found["mode"], "callers");
+        assert_eq!(found["results"][0]["caller"], "run");
+        assert_eq!(found["results"][0]["snippet"], "lexer.next_token();");
+
+        let found = search("Lexer").await;
+        assert_eq!(found["mode"], "symbols");
+        assert_eq!(found["total"], 2);
+
+        let bad_kind =
+            json!({ "query": "Lexer", "root": root.to_string_lossy(), "kind": "widget" });
+        assert!(tool.execute(bad_kind).await.is_err());
+    }
+
     #[tokio::test]
     async fn runs_search_with_generated_embeddings() {
         if std::env::var("RUN_TOAK_SEARCH_TEST").is_err() {
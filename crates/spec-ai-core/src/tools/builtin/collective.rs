@@ -6,7 +6,9 @@
 //! - Participate in collective decision-making
 //! - Coordinate multi-agent workflows
 
+use crate::events::{self, DelegationTarget, Event, EventKind};
 use crate::mesh::{MeshClient, MessageType};
+use crate::policy::{CapsuleBudget, PolicyCapsule};
 use crate::tools::{Tool, ToolResult};
 use anyhow::Result;
 use async_trait::async_trait;
@@ -41,6 +43,36 @@ struct DelegateTaskArgs {
     #[serde(default)]
     priority: Option<String>,
     target_instance: Option<String>,
+    #[serde(default)]
+    allowed_tools: Option<Vec<String>>,
+    #[serde(default)]
+    path_scope: Option<Vec<String>>,
+    #[serde(default)]
+    max_tool_calls: Option<u32>,
+    #[serde(default)]
+    max_tokens: Option<u64>,
+    /// What the delegating agent may do itself, filled in by the agent
+    #[serde(default)]
+    policy_capsule: Option<PolicyCapsule>,
+}
+
+impl DelegateTaskArgs {
+    /// Capsule for the task: the requested limits within the delegator's own
+    fn capsule(&self, issuer: &str) -> PolicyCapsule {
+        let requested = PolicyCapsule {
+            issuer: issuer.to_string(),
+            tools: self.allowed_tools.clone(),
+            paths: self.path_scope.clone(),
+            budget: CapsuleBudget {
+                max_tool_calls: self.max_tool_calls,
+                max_tokens: self.max_tokens,
+            },
+        };
+        match &self.policy_capsule {
+            Some(delegator) => delegator.intersect(&requested),
+            None => requested,
+        }
+    }
 }
 
 #[async_trait]
@@ -83,6 +115,24 @@ impl Tool for DelegateTaskTool {
                 "target_instance": {
                     "type": "string",
                     "description": "Specific instance to delegate to. If omitted, routes to best capable agent."
+                },
+                "allowed_tools": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Tools the peer may use for this task. Defaults to the tools you may use."
+                },
+                "path_scope": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Directories the peer's tools may touch. Defaults to your own scope."
+                },
+                "max_tool_calls": {
+                    "type": "integer",
+                    "description": "Most tool calls the peer may make for this task"
+                },
+                "max_tokens": {
+                    "type": "integer",
+                    "description": "Most model tokens the peer may spend on this task"
                 }
             },
             "required": ["task_type", "description", "required_capabilities", "payload"]
@@ -112,6 +162,7 @@ impl Tool for DelegateTaskTool {
 
         // Create task delegation payload
         let task_id = uuid::Uuid::new_v4().to_string();
+        let policy_capsule = args.capsule(&self.instance_id);
         let delegation_payload = json!({
             "task_id": task_id,
             "task_type": args.task_type,
@@ -120,6 +171,7 @@ impl Tool for DelegateTaskTool {
             "payload": args.payload,
             "priority": args.priority.unwrap_or_else(|| "normal".to_string()),
             "delegator": self.instance_id,
+            "policy_capsule": policy_capsule,
         });
 
         // Send task delegation message
@@ -132,6 +184,14 @@ impl Tool for DelegateTaskTool {
                 Some(task_id.clone()),
             )
             .await?;
+        for peer in &response.delivered_to {
+            events::emit(Event::new(EventKind::Delegation {
+                target: DelegationTarget::Peer,
+                to: peer.clone(),
+                task: args.description.clone(),
+                task_id: Some(task_id.clone()),
+            }));
+        }
 
         Ok(ToolResult::success(format!(
             "Task delegated successfully.\n\
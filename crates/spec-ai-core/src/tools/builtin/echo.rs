@@ -30,6 +30,10 @@ impl Tool for EchoTool {
         "echo"
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         "Echoes back the provided message"
     }
@@ -343,6 +343,10 @@ impl Tool for FileExtractTool {
         "file_extract"
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         "Extracts text and metadata from files regardless of format (PDF, Office, HTML, images with OCR, etc.)"
     }
@@ -115,6 +115,10 @@ impl Tool for FileReadTool {
         "file_read"
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         "Reads files from disk with optional metadata and size limits"
     }
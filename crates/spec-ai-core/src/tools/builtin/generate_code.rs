@@ -97,6 +97,8 @@ impl Tool for GenerateCodeTool {
             top_p: None,
             frequency_penalty: None,
             presence_penalty: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
 
         let response = self
@@ -0,0 +1,1141 @@
+//! Git Tools
+//!
+//! Repository state as structured results instead of command output:
+//! `git_status` lists changed files, `git_diff` returns hunks, `git_blame`
+//! attributes lines to commits, and `git_commit` and `git_branch` change the
+//! repository. Write operations check the `git_write` action on the
+//! repository path against the policy engine when one is attached, so an
+//! agent only commits or moves branches where a rule allows it.
+
+use crate::policy::{PolicyDecision, PolicyEngine};
+use crate::tools::{Tool, ToolResult};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use git2::{
+    BlameOptions, BranchType, Delta, DiffOptions, ErrorCode, ObjectType, Repository, Status,
+    StatusOptions,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Diff lines returned before the rest is dropped
+const MAX_DIFF_LINES: usize = 2000;
+
+/// Blamed lines returned when no end line is given
+const MAX_BLAME_LINES: usize = 400;
+
+/// Open the repository containing `path`, or the current directory
+fn open_repo(path: Option<&str>) -> Result<Repository> {
+    let path = path.unwrap_or(".");
+    let repo = Repository::discover(path)
+        .with_context(|| format!("No git repository found at {}", path))?;
+    if repo.is_bare() {
+        bail!("Repository at {} is bare", repo.path().display());
+    }
+    Ok(repo)
+}
+
+fn workdir(repo: &Repository) -> Result<&Path> {
+    repo.workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))
+}
+
+/// Path relative to the repository root; absolute paths must lie inside it
+fn repo_relative(repo: &Repository, path: &str) -> Result<PathBuf> {
+    let path = Path::new(path);
+    if path.is_relative() {
+        return Ok(path.to_path_buf());
+    }
+    let root = workdir(repo)?;
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let absolute = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    absolute
+        .strip_prefix(&root)
+        .map(Path::to_path_buf)
+        .map_err(|_| anyhow!("{} is outside the repository", path.display()))
+}
+
+/// Name of the branch HEAD points at, even before its first commit
+fn current_branch(repo: &Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    let target = head.symbolic_target()?;
+    Some(
+        target
+            .strip_prefix("refs/heads/")
+            .unwrap_or(target)
+            .to_string(),
+    )
+}
+
+fn short_id(oid: git2::Oid) -> String {
+    oid.to_string().chars().take(8).collect()
+}
+
+fn timestamp(time: git2::Time) -> String {
+    chrono::DateTime::from_timestamp(time.seconds(), 0)
+        .map(|at| at.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn output(value: &impl Serialize) -> Result<ToolResult> {
+    let output = serde_json::to_string_pretty(value).context("Failed to serialize git result")?;
+    Ok(ToolResult::success(output))
+}
+
+/// Policy check for operations that change a repository
+#[derive(Clone)]
+pub struct GitPolicy {
+    engine: Arc<PolicyEngine>,
+    agent: String,
+}
+
+impl GitPolicy {
+    pub fn new(engine: Arc<PolicyEngine>, agent: impl Into<String>) -> Self {
+        Self {
+            engine,
+            agent: agent.into(),
+        }
+    }
+
+    /// Fail unless the rules allow `git_write` on the repository's path
+    fn check(&self, repo: &Repository) -> Result<()> {
+        let root = workdir(repo)?;
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let resource = root.to_string_lossy();
+        let resource = resource.trim_end_matches('/');
+        match self.engine.check(&self.agent, "git_write", resource) {
+            PolicyDecision::Allow => Ok(()),
+            PolicyDecision::Deny(reason) => Err(anyhow!(reason)),
+        }
+    }
+}
+
+fn check_write(policy: &Option<GitPolicy>, repo: &Repository) -> Result<()> {
+    match policy {
+        Some(policy) => policy.check(repo),
+        None => Ok(()),
+    }
+}
+
+// ---------- git_status ----------
+
+#[derive(Debug, Deserialize)]
+struct StatusArgs {
+    path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Upstream {
+    name: String,
+    ahead: usize,
+    behind: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct FileStatus {
+    path: String,
+    /// Change staged in the index, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    staged: Option<&'static str>,
+    /// Change in the working tree not yet staged, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unstaged: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResult {
+    root: String,
+    branch: Option<String>,
+    head: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream: Option<Upstream>,
+    clean: bool,
+    files: Vec<FileStatus>,
+}
+
+fn staged_change(status: Status) -> Option<&'static str> {
+    if status.is_index_new() {
+        Some("added")
+    } else if status.is_index_modified() {
+        Some("modified")
+    } else if status.is_index_deleted() {
+        Some("deleted")
+    } else if status.is_index_renamed() {
+        Some("renamed")
+    } else if status.is_index_typechange() {
+        Some("typechange")
+    } else {
+        None
+    }
+}
+
+fn unstaged_change(status: Status) -> Option<&'static str> {
+    if status.is_conflicted() {
+        Some("conflicted")
+    } else if status.is_wt_new() {
+        Some("untracked")
+    } else if status.is_wt_modified() {
+        Some("modified")
+    } else if status.is_wt_deleted() {
+        Some("deleted")
+    } else if status.is_wt_renamed() {
+        Some("renamed")
+    } else if status.is_wt_typechange() {
+        Some("typechange")
+    } else {
+        None
+    }
+}
+
+fn upstream(repo: &Repository, branch: &str) -> Option<Upstream> {
+    let local = repo.find_branch(branch, BranchType::Local).ok()?;
+    let upstream = local.upstream().ok()?;
+    let name = upstream.name().ok()??.to_string();
+    let (ahead, behind) = repo
+        .graph_ahead_behind(local.get().target()?, upstream.get().target()?)
+        .ok()?;
+    Some(Upstream {
+        name,
+        ahead,
+        behind,
+    })
+}
+
+fn status(args: &StatusArgs) -> Result<StatusResult> {
+    let repo = open_repo(args.path.as_deref())?;
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true);
+    let statuses = repo.statuses(Some(&mut options))?;
+    let files: Vec<FileStatus> = statuses
+        .iter()
+        .filter(|entry| !entry.status().is_ignored())
+        .map(|entry| FileStatus {
+            path: entry.path().unwrap_or_default().to_string(),
+            staged: staged_change(entry.status()),
+            unstaged: unstaged_change(entry.status()),
+        })
+        .collect();
+
+    let branch = current_branch(&repo);
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(short_id);
+    Ok(StatusResult {
+        root: workdir(&repo)?.display().to_string(),
+        upstream: branch.as_deref().and_then(|branch| upstream(&repo, branch)),
+        branch,
+        head,
+        clean: files.is_empty(),
+        files,
+    })
+}
+
+/// Structured `git status`
+pub struct GitStatusTool;
+
+impl GitStatusTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for GitStatusTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for GitStatusTool {
+    fn name(&self) -> &str {
+        "git_status"
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> &str {
+        "Shows the current branch, its upstream, and every staged, unstaged and untracked file \
+         of a git repository as structured JSON."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Path inside the repository (default: current directory)"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: StatusArgs =
+            serde_json::from_value(args).context("Failed to parse git_status arguments")?;
+        match status(&args) {
+            Ok(result) => output(&result),
+            Err(e) => Ok(ToolResult::failure(format!("git status failed: {:#}", e))),
+        }
+    }
+}
+
+// ---------- git_diff ----------
+
+#[derive(Debug, Deserialize)]
+struct DiffArgs {
+    path: Option<String>,
+    staged: Option<bool>,
+    commit: Option<String>,
+    paths: Option<Vec<String>>,
+    context_lines: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct Hunk {
+    header: String,
+    old_start: u32,
+    old_lines: u32,
+    new_start: u32,
+    new_lines: u32,
+    /// Lines prefixed with `+`, `-` or a space
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileDiff {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_path: Option<String>,
+    status: &'static str,
+    binary: bool,
+    additions: usize,
+    deletions: usize,
+    hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Serialize)]
+struct DiffResult {
+    files: Vec<FileDiff>,
+    additions: usize,
+    deletions: usize,
+    /// Whether hunks were left out after MAX_DIFF_LINES lines
+    truncated: bool,
+}
+
+fn delta_status(delta: Delta) -> &'static str {
+    match delta {
+        Delta::Added | Delta::Untracked => "added",
+        Delta::Deleted => "deleted",
+        Delta::Renamed => "renamed",
+        Delta::Copied => "copied",
+        Delta::Typechange => "typechange",
+        Delta::Conflicted => "conflicted",
+        _ => "modified",
+    }
+}
+
+fn diff(args: &DiffArgs) -> Result<DiffResult> {
+    let repo = open_repo(args.path.as_deref())?;
+    let mut options = DiffOptions::new();
+    options.context_lines(args.context_lines.unwrap_or(3));
+    for path in args.paths.iter().flatten() {
+        options.pathspec(repo_relative(&repo, path)?);
+    }
+
+    let diff = if let Some(rev) = &args.commit {
+        let commit = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Unknown commit '{}'", rev))?;
+        let parent = commit
+            .parents()
+            .next()
+            .map(|parent| parent.tree())
+            .transpose()?;
+        repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), Some(&mut options))?
+    } else if args.staged.unwrap_or(false) {
+        let head = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(e) if e.code() == ErrorCode::UnbornBranch => None,
+            Err(e) => return Err(e.into()),
+        };
+        repo.diff_tree_to_index(head.as_ref(), None, Some(&mut options))?
+    } else {
+        options.include_untracked(true).show_untracked_content(true);
+        repo.diff_index_to_workdir(None, Some(&mut options))?
+    };
+
+    let mut result = DiffResult {
+        files: Vec::new(),
+        additions: 0,
+        deletions: 0,
+        truncated: false,
+    };
+    let mut budget = MAX_DIFF_LINES;
+    for index in 0..diff.deltas().len() {
+        let Some(patch) = git2::Patch::from_diff(&diff, index)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        let new_path = delta.new_file().path().map(|p| p.display().to_string());
+        let old_path = delta.old_file().path().map(|p| p.display().to_string());
+        let (_, additions, deletions) = patch.line_stats()?;
+        let mut file = FileDiff {
+            path: new_path.clone().or(old_path.clone()).unwrap_or_default(),
+            old_path: old_path.filter(|old| Some(old) != new_path.as_ref()),
+            status: delta_status(delta.status()),
+            binary: delta.flags().is_binary(),
+            additions,
+            deletions,
+            hunks: Vec::new(),
+        };
+        for hunk_index in 0..patch.num_hunks() {
+            let (hunk, line_count) = patch.hunk(hunk_index)?;
+            if line_count > budget {
+                result.truncated = true;
+                break;
+            }
+            budget -= line_count;
+            let mut lines = Vec::with_capacity(line_count);
+            for line_index in 0..line_count {
+                let line = patch.line_in_hunk(hunk_index, line_index)?;
+                let content = String::from_utf8_lossy(line.content());
+                let origin = match line.origin() {
+                    origin @ ('+' | '-' | ' ') => origin,
+                    // End-of-file markers and the like carry no change
+                    _ => continue,
+                };
+                lines.push(format!("{}{}", origin, content.trim_end_matches('\n')));
+            }
+            file.hunks.push(Hunk {
+                header: String::from_utf8_lossy(hunk.header())
+                    .trim_end()
+                    .to_string(),
+                old_start: hunk.old_start(),
+                old_lines: hunk.old_lines(),
+                new_start: hunk.new_start(),
+                new_lines: hunk.new_lines(),
+                lines,
+            });
+        }
+        result.additions += additions;
+        result.deletions += deletions;
+        result.files.push(file);
+    }
+    Ok(result)
+}
+
+/// Structured `git diff`
+pub struct GitDiffTool;
+
+impl GitDiffTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for GitDiffTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for GitDiffTool {
+    fn name(&self) -> &str {
+        "git_diff"
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> &str {
+        "Shows changes in a git repository as files with hunks of added and removed lines: \
+         unstaged changes by default, staged changes, or the changes of one commit."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Path inside the repository (default: current directory)"
+                },
+                "staged": {
+                    "type": "boolean",
+                    "description": "Diff the index against HEAD instead of the working tree against the index (default: false)"
+                },
+                "commit": {
+                    "type": "string",
+                    "description": "Show the changes a commit made, e.g. 'HEAD~1' or a commit id"
+                },
+                "paths": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only diff these files or directories"
+                },
+                "context_lines": {
+                    "type": "integer",
+                    "description": "Unchanged lines around each change (default: 3)"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: DiffArgs =
+            serde_json::from_value(args).context("Failed to parse git_diff arguments")?;
+        match diff(&args) {
+            Ok(result) => output(&result),
+            Err(e) => Ok(ToolResult::failure(format!("git diff failed: {:#}", e))),
+        }
+    }
+}
+
+// ---------- git_commit ----------
+
+#[derive(Debug, Deserialize)]
+struct CommitArgs {
+    message: String,
+    path: Option<String>,
+    paths: Option<Vec<String>>,
+    all: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommitResult {
+    commit: String,
+    branch: Option<String>,
+    summary: String,
+    files_changed: usize,
+    insertions: usize,
+    deletions: usize,
+}
+
+fn commit(args: &CommitArgs, policy: &Option<GitPolicy>) -> Result<CommitResult> {
+    if args.message.trim().is_empty() {
+        bail!("Commit message is empty");
+    }
+    let repo = open_repo(args.path.as_deref())?;
+    check_write(policy, &repo)?;
+
+    let mut index = repo.index()?;
+    for path in args.paths.iter().flatten() {
+        let relative = repo_relative(&repo, path)?;
+        if workdir(&repo)?.join(&relative).exists() {
+            index
+                .add_path(&relative)
+                .with_context(|| format!("Failed to stage {}", relative.display()))?;
+        } else {
+            index
+                .remove_path(&relative)
+                .with_context(|| format!("Failed to stage removal of {}", relative.display()))?;
+        }
+    }
+    if args.all.unwrap_or(false) {
+        index.update_all(["*"].iter(), None)?;
+    }
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(e) if e.code() == ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e.into()),
+    };
+    let parent_tree = parent.as_ref().map(|parent| parent.tree()).transpose()?;
+    let stats = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?
+        .stats()?;
+    if stats.files_changed() == 0 {
+        bail!("Nothing to commit; stage files with `paths` or `all`");
+    }
+
+    let signature = repo
+        .signature()
+        .context("No commit author configured (set git user.name and user.email)")?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let oid = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &args.message,
+        &tree,
+        &parents,
+    )?;
+    Ok(CommitResult {
+        commit: short_id(oid),
+        branch: current_branch(&repo),
+        summary: args.message.lines().next().unwrap_or_default().to_string(),
+        files_changed: stats.files_changed(),
+        insertions: stats.insertions(),
+        deletions: stats.deletions(),
+    })
+}
+
+/// Commit staged changes, optionally staging files first
+pub struct GitCommitTool {
+    policy: Option<GitPolicy>,
+}
+
+impl GitCommitTool {
+    pub fn new() -> Self {
+        Self { policy: None }
+    }
+
+    /// Only commit where `policy` allows `git_write`
+    pub fn with_policy(mut self, policy: GitPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+}
+
+impl Default for GitCommitTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for GitCommitTool {
+    fn name(&self) -> &str {
+        "git_commit"
+    }
+
+    fn description(&self) -> &str {
+        "Creates a git commit on the current branch from the staged changes, after staging the \
+         given paths or every tracked change. Returns the new commit and what it changed."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "message": {
+                    "type": "string",
+                    "description": "Commit message; the first line is the summary"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Path inside the repository (default: current directory)"
+                },
+                "paths": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Files to stage before committing, relative to the repository root; deleted files are staged as removals"
+                },
+                "all": {
+                    "type": "boolean",
+                    "description": "Stage every change to tracked files first, like `git commit -a` (default: false)"
+                }
+            },
+            "required": ["message"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: CommitArgs =
+            serde_json::from_value(args).context("Failed to parse git_commit arguments")?;
+        match commit(&args, &self.policy) {
+            Ok(result) => output(&result),
+            Err(e) => Ok(ToolResult::failure(format!("git commit failed: {:#}", e))),
+        }
+    }
+}
+
+// ---------- git_branch ----------
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BranchAction {
+    List,
+    Create,
+    Switch,
+    Delete,
+}
+
+#[derive(Debug, Deserialize)]
+struct BranchArgs {
+    action: Option<BranchAction>,
+    name: Option<String>,
+    start_point: Option<String>,
+    path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BranchInfo {
+    name: String,
+    current: bool,
+    head: Option<String>,
+    summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream: Option<Upstream>,
+}
+
+fn list_branches(repo: &Repository) -> Result<Vec<BranchInfo>> {
+    let mut branches = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let commit = branch.get().peel_to_commit().ok();
+        branches.push(BranchInfo {
+            current: branch.is_head(),
+            head: commit.as_ref().map(|commit| short_id(commit.id())),
+            summary: commit
+                .as_ref()
+                .and_then(|commit| commit.summary().map(str::to_string)),
+            upstream: upstream(repo, &name),
+            name,
+        });
+    }
+    branches.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(branches)
+}
+
+fn branch(args: &BranchArgs, policy: &Option<GitPolicy>) -> Result<Value> {
+    let repo = open_repo(args.path.as_deref())?;
+    let action = args.action.as_ref().unwrap_or(&BranchAction::List);
+    if let BranchAction::List = action {
+        return Ok(json!({ "branches": list_branches(&repo)? }));
+    }
+
+    let name = args
+        .name
+        .as_deref()
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| anyhow!("A branch name is required"))?;
+    check_write(policy, &repo)?;
+    match action {
+        BranchAction::List => unreachable!(),
+        BranchAction::Create => {
+            let target = match &args.start_point {
+                Some(rev) => repo
+                    .revparse_single(rev)
+                    .and_then(|object| object.peel_to_commit())
+                    .with_context(|| format!("Unknown start point '{}'", rev))?,
+                None => repo
+                    .head()
+                    .and_then(|head| head.peel_to_commit())
+                    .context("HEAD has no commit to branch from")?,
+            };
+            repo.branch(name, &target, false)
+                .with_context(|| format!("Failed to create branch '{}'", name))?;
+            Ok(json!({ "created": name, "head": short_id(target.id()) }))
+        }
+        BranchAction::Switch => {
+            let branch = repo
+                .find_branch(name, BranchType::Local)
+                .with_context(|| format!("No branch named '{}'", name))?;
+            let reference = branch
+                .get()
+                .name()
+                .ok_or_else(|| anyhow!("Branch '{}' has an invalid name", name))?
+                .to_string();
+            let target = branch.get().peel(ObjectType::Commit)?;
+            // A safe checkout refuses to overwrite uncommitted changes
+            repo.checkout_tree(&target, Some(git2::build::CheckoutBuilder::new().safe()))
+                .context("Uncommitted changes would be overwritten")?;
+            repo.set_head(&reference)?;
+            Ok(json!({ "switched_to": name, "head": short_id(target.id()) }))
+        }
+        BranchAction::Delete => {
+            let mut branch = repo
+                .find_branch(name, BranchType::Local)
+                .with_context(|| format!("No branch named '{}'", name))?;
+            if branch.is_head() {
+                bail!("Cannot delete '{}', the current branch", name);
+            }
+            branch.delete()?;
+            Ok(json!({ "deleted": name }))
+        }
+    }
+}
+
+/// List, create, switch and delete local branches
+pub struct GitBranchTool {
+    policy: Option<GitPolicy>,
+}
+
+impl GitBranchTool {
+    pub fn new() -> Self {
+        Self { policy: None }
+    }
+
+    /// Only create, switch or delete branches where `policy` allows `git_write`
+    pub fn with_policy(mut self, policy: GitPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+}
+
+impl Default for GitBranchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for GitBranchTool {
+    fn name(&self) -> &str {
+        "git_branch"
+    }
+
+    fn description(&self) -> &str {
+        "Lists the local branches of a git repository with their latest commit and upstream, \
+         or creates, switches to or deletes a branch."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["list", "create", "switch", "delete"],
+                    "description": "What to do (default: list)"
+                },
+                "name": {
+                    "type": "string",
+                    "description": "Branch to create, switch to or delete"
+                },
+                "start_point": {
+                    "type": "string",
+                    "description": "Commit a new branch starts at (default: HEAD)"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Path inside the repository (default: current directory)"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: BranchArgs =
+            serde_json::from_value(args).context("Failed to parse git_branch arguments")?;
+        match branch(&args, &self.policy) {
+            Ok(result) => output(&result),
+            Err(e) => Ok(ToolResult::failure(format!("git branch failed: {:#}", e))),
+        }
+    }
+}
+
+// ---------- git_blame ----------
+
+#[derive(Debug, Deserialize)]
+struct BlameArgs {
+    file: String,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlameHunk {
+    start_line: usize,
+    end_line: usize,
+    commit: String,
+    author: String,
+    email: String,
+    time: String,
+    summary: String,
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlameResult {
+    file: String,
+    hunks: Vec<BlameHunk>,
+    /// Whether lines past MAX_BLAME_LINES were left out
+    truncated: bool,
+}
+
+fn blame(args: &BlameArgs) -> Result<BlameResult> {
+    let repo = open_repo(args.path.as_deref())?;
+    let relative = repo_relative(&repo, &args.file)?;
+    let blob = repo
+        .head()?
+        .peel_to_tree()?
+        .get_path(&relative)
+        .with_context(|| format!("{} is not committed", relative.display()))?
+        .to_object(&repo)?
+        .peel_to_blob()?;
+    let text = String::from_utf8_lossy(blob.content()).into_owned();
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return Ok(BlameResult {
+            file: relative.display().to_string(),
+            hunks: Vec::new(),
+            truncated: false,
+        });
+    }
+
+    let start = args.start_line.unwrap_or(1).clamp(1, lines.len());
+    let limit = args
+        .end_line
+        .unwrap_or(start + MAX_BLAME_LINES - 1)
+        .clamp(start, lines.len());
+    let truncated = args.end_line.is_none() && limit < lines.len();
+
+    let mut options = BlameOptions::new();
+    options.min_line(start).max_line(limit);
+    let blame = repo
+        .blame_file(&relative, Some(&mut options))
+        .with_context(|| format!("Failed to blame {}", relative.display()))?;
+
+    let mut hunks = Vec::new();
+    for hunk in blame.iter() {
+        let first = hunk.final_start_line().max(start);
+        let last = (hunk.final_start_line() + hunk.lines_in_hunk() - 1).min(limit);
+        if first > last {
+            continue;
+        }
+        let id = hunk.final_commit_id();
+        let signature = hunk.final_signature();
+        let summary = repo
+            .find_commit(id)
+            .ok()
+            .and_then(|commit| commit.summary().map(str::to_string))
+            .unwrap_or_default();
+        hunks.push(BlameHunk {
+            start_line: first,
+            end_line: last,
+            commit: short_id(id),
+            author: signature.name().unwrap_or_default().to_string(),
+            email: signature.email().unwrap_or_default().to_string(),
+            time: timestamp(signature.when()),
+            summary,
+            lines: lines[first - 1..last]
+                .iter()
+                .map(|line| line.to_string())
+                .collect(),
+        });
+    }
+    Ok(BlameResult {
+        file: relative.display().to_string(),
+        hunks,
+        truncated,
+    })
+}
+
+/// Structured `git blame` of a committed file
+pub struct GitBlameTool;
+
+impl GitBlameTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for GitBlameTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for GitBlameTool {
+    fn name(&self) -> &str {
+        "git_blame"
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> &str {
+        "Shows which commit, author and date last changed each line of a committed file, \
+         grouped into hunks of consecutive lines."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "file": {
+                    "type": "string",
+                    "description": "File to blame, relative to the repository root"
+                },
+                "start_line": {
+                    "type": "integer",
+                    "description": "First line to blame, starting at 1 (default: 1)"
+                },
+                "end_line": {
+                    "type": "integer",
+                    "description": "Last line to blame (default: 400 lines after start_line)"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Path inside the repository (default: current directory)"
+                }
+            },
+            "required": ["file"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: BlameArgs =
+            serde_json::from_value(args).context("Failed to parse git_blame arguments")?;
+        match blame(&args) {
+            Ok(result) => output(&result),
+            Err(e) => Ok(ToolResult::failure(format!("git blame failed: {:#}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{PolicyEffect, PolicyRule};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn repo() -> (TempDir, String) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Ada").unwrap();
+        config.set_str("user.email", "ada@example.com").unwrap();
+        let path = dir.path().display().to_string();
+        (dir, path)
+    }
+
+    async fn run(tool: &dyn Tool, args: Value) -> Value {
+        let result = tool.execute(args).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+        serde_json::from_str(&result.output).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_status_diff_commit_and_blame() {
+        let (dir, path) = repo();
+        fs::write(dir.path().join("notes.txt"), "one\ntwo\n").unwrap();
+
+        let status = run(&GitStatusTool::new(), json!({ "path": path })).await;
+        assert_eq!(status["clean"], false);
+        assert_eq!(
+            status["branch"],
+            current_branch(&open_repo(Some(&path)).unwrap()).unwrap()
+        );
+        assert_eq!(status["files"][0]["path"], "notes.txt");
+        assert_eq!(status["files"][0]["unstaged"], "untracked");
+
+        let first = run(
+            &GitCommitTool::new(),
+            json!({ "path": path, "message": "Add notes", "paths": ["notes.txt"] }),
+        )
+        .await;
+        assert_eq!(first["files_changed"], 1);
+        assert_eq!(first["insertions"], 2);
+
+        fs::write(dir.path().join("notes.txt"), "one\n2\n").unwrap();
+        let diff = run(&GitDiffTool::new(), json!({ "path": path })).await;
+        let file = &diff["files"][0];
+        assert_eq!(file["path"], "notes.txt");
+        assert_eq!(file["status"], "modified");
+        assert_eq!(
+            (file["additions"].as_u64(), file["deletions"].as_u64()),
+            (Some(1), Some(1))
+        );
+        assert_eq!(file["hunks"][0]["lines"], json!([" one", "-two", "+2"]));
+
+        let commit = GitCommitTool::new();
+        let nothing = commit
+            .execute(json!({ "path": path, "message": "Nothing staged" }))
+            .await
+            .unwrap();
+        assert!(nothing.error.unwrap().contains("Nothing to commit"));
+        let second = run(
+            &commit,
+            json!({ "path": path, "message": "Fix two", "all": true }),
+        )
+        .await;
+        assert_eq!(second["summary"], "Fix two");
+
+        let shown = run(
+            &GitDiffTool::new(),
+            json!({ "path": path, "commit": "HEAD" }),
+        )
+        .await;
+        assert_eq!(shown["additions"], 1);
+
+        let blame = run(
+            &GitBlameTool::new(),
+            json!({ "path": path, "file": "notes.txt" }),
+        )
+        .await;
+        let hunks = blame["hunks"].as_array().unwrap();
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0]["summary"], "Add notes");
+        assert_eq!(hunks[0]["lines"], json!(["one"]));
+        assert_eq!(hunks[1]["commit"], second["commit"]);
+        assert_eq!(hunks[1]["author"], "Ada");
+        assert_eq!(hunks[1]["lines"], json!(["2"]));
+    }
+
+    #[tokio::test]
+    async fn test_branches_need_policy_to_change() {
+        let (dir, path) = repo();
+        fs::write(dir.path().join("README.md"), "hi\n").unwrap();
+        run(
+            &GitCommitTool::new(),
+            json!({ "path": path, "message": "Initial", "paths": ["README.md"] }),
+        )
+        .await;
+
+        let mut engine = PolicyEngine::new();
+        engine.add_rule(PolicyRule {
+            agent: "reviewer".to_string(),
+            action: "git_write".to_string(),
+            resource: "*".to_string(),
+            effect: PolicyEffect::Allow,
+        });
+        let engine = Arc::new(engine);
+
+        let denied = GitBranchTool::new().with_policy(GitPolicy::new(engine.clone(), "coder"));
+        let result = denied
+            .execute(json!({ "path": path, "action": "create", "name": "feature" }))
+            .await
+            .unwrap();
+        assert!(result.error.unwrap().contains("default deny"));
+
+        let allowed = GitBranchTool::new().with_policy(GitPolicy::new(engine, "reviewer"));
+        run(
+            &allowed,
+            json!({ "path": path, "action": "create", "name": "feature" }),
+        )
+        .await;
+        run(
+            &allowed,
+            json!({ "path": path, "action": "switch", "name": "feature" }),
+        )
+        .await;
+
+        let listed = run(&allowed, json!({ "path": path })).await;
+        let branches = listed["branches"].as_array().unwrap();
+        assert_eq!(branches.len(), 2);
+        let feature = branches.iter().find(|b| b["name"] == "feature").unwrap();
+        assert_eq!(feature["current"], true);
+        assert_eq!(feature["summary"], "Initial");
+
+        let current = allowed
+            .execute(json!({ "path": path, "action": "delete", "name": "feature" }))
+            .await
+            .unwrap();
+        assert!(current.error.unwrap().contains("current branch"));
+    }
+}
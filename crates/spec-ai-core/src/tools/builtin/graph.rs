@@ -4,17 +4,34 @@ use serde_json::{json, Map, Value};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::embeddings::EmbeddingsClient;
 use crate::persistence::Persistence;
 use crate::tools::{Tool, ToolResult};
 use crate::types::{EdgeType, NodeType, TraversalDirection};
+use crate::vectors::{VectorFilter, VectorStore, GRAPH_COLLECTION};
 
 pub struct GraphTool {
     persistence: Arc<Persistence>,
+    /// Node embeddings and the client that embeds queries, for similar_nodes
+    similarity: Option<(Arc<dyn VectorStore>, EmbeddingsClient)>,
 }
 
 impl GraphTool {
     pub fn new(persistence: Arc<Persistence>) -> Self {
-        Self { persistence }
+        Self {
+            persistence,
+            similarity: None,
+        }
+    }
+
+    /// Answer similar_nodes from the node embeddings in `store`
+    pub fn with_similarity_search(
+        mut self,
+        store: Arc<dyn VectorStore>,
+        embeddings: EmbeddingsClient,
+    ) -> Self {
+        self.similarity = Some((store, embeddings));
+        self
     }
 }
 
@@ -28,8 +45,8 @@ impl Tool for GraphTool {
         "Create, query, traverse, and synchronize knowledge graphs. Supports operations: \
          create_node, create_edge, delete_node, delete_edge, get_node, get_edge, \
          list_nodes, list_edges, find_path, traverse_neighbors, update_node, \
-         node_degree, list_hubs, enable_sync, disable_sync, sync_status, force_sync, \
-         list_sync_configs"
+         node_degree, list_hubs, similar_nodes, enable_sync, disable_sync, sync_status, \
+         force_sync, list_sync_configs"
     }
 
     fn parameters(&self) -> Value {
@@ -42,7 +59,7 @@ impl Tool for GraphTool {
                         "create_node", "create_edge", "delete_node", "delete_edge",
                         "get_node", "get_edge", "list_nodes", "list_edges",
                         "find_path", "traverse_neighbors", "update_node",
-                        "node_degree", "list_hubs",
+                        "node_degree", "list_hubs", "similar_nodes",
                         "enable_sync", "disable_sync", "sync_status", "force_sync",
                         "list_sync_configs"
                     ],
@@ -129,6 +146,10 @@ impl Tool for GraphTool {
                     "maximum": 1000,
                     "description": "Limit for list operations"
                 },
+                "query": {
+                    "type": "string",
+                    "description": "Text to find semantically similar nodes for (for similar_nodes)"
+                },
                 "min_degree": {
                     "type": "integer",
                     "default": 1,
@@ -418,6 +439,49 @@ impl Tool for GraphTool {
                 ))
             }
 
+            "similar_nodes" => {
+                let Some((store, embeddings)) = &self.similarity else {
+                    return Ok(ToolResult::failure(
+                        "similar_nodes needs an embeddings model and [vector_store] enabled"
+                            .to_string(),
+                    ));
+                };
+                let query = args["query"]
+                    .as_str()
+                    .context("query is required for similar_nodes")?;
+                let limit = args["limit"].as_u64().unwrap_or(10) as usize;
+                let mut filter = VectorFilter::none().eq("session_id", session_id);
+                if let Some(node_type) = args["node_type"].as_str() {
+                    filter = filter.eq("node_type", node_type);
+                }
+
+                let query_embedding = embeddings.embed(query).await?;
+                let matches = store
+                    .knn(GRAPH_COLLECTION, &query_embedding, limit, &filter)
+                    .await?;
+
+                let nodes = tokio::task::spawn_blocking(move || {
+                    let mut nodes = Vec::new();
+                    for found in matches {
+                        // Nodes deleted since they were indexed are skipped
+                        if let Some(node) = persistence.get_graph_node(found.id as i64)? {
+                            nodes.push(json!({ "score": found.score, "node": node }));
+                        }
+                    }
+                    Ok::<_, anyhow::Error>(nodes)
+                })
+                .await
+                .context("task join error")??;
+
+                Ok(ToolResult::success(
+                    json!({
+                        "count": nodes.len(),
+                        "nodes": nodes
+                    })
+                    .to_string(),
+                ))
+            }
+
             "find_path" => {
                 let source_id = args["source_id"]
                     .as_i64()
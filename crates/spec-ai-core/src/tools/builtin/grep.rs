@@ -18,6 +18,8 @@ const DEFAULT_CONTEXT_LINES: usize = 0;
 const DEFAULT_MAX_FILE_BYTES: usize = 1024 * 1024;
 /// Maximum line length to include (truncate longer lines)
 const MAX_LINE_LENGTH: usize = 500;
+/// Directories never searched when walking a tree
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", ".venv", "__pycache__"];
 
 #[derive(Debug, Deserialize)]
 struct GrepArgs {
@@ -50,6 +52,16 @@ fn default_true() -> bool {
     true
 }
 
+/// Path as a string with `/` separators, which glob patterns are written in
+fn slash_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if std::path::MAIN_SEPARATOR == '/' {
+        path.into_owned()
+    } else {
+        path.replace(std::path::MAIN_SEPARATOR, "/")
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct GrepMatch {
     /// File path containing the match
@@ -121,7 +133,7 @@ impl GrepTool {
             None => true,
             Some(regex) => {
                 // Try matching against the full path and just the filename
-                let path_str = path.to_string_lossy();
+                let path_str = slash_path(path);
                 let filename = path.file_name().map(|s| s.to_string_lossy());
 
                 regex.is_match(&path_str) || filename.map(|f| regex.is_match(&f)).unwrap_or(false)
@@ -277,6 +289,10 @@ impl Tool for GrepTool {
         "grep"
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         "Search for patterns in files using grep-like matching. Returns matching lines with optional context to avoid loading entire files into context."
     }
@@ -403,13 +419,11 @@ impl Tool for GrepTool {
                 }
 
                 // Skip hidden files and common non-text directories
-                let path_str = path.to_string_lossy();
-                if path_str.contains("/.git/")
-                    || path_str.contains("/node_modules/")
-                    || path_str.contains("/target/")
-                    || path_str.contains("/.venv/")
-                    || path_str.contains("/__pycache__/")
-                {
+                if path.parent().is_some_and(|dir| {
+                    dir.components().any(|component| {
+                        SKIPPED_DIRS.contains(&component.as_os_str().to_string_lossy().as_ref())
+                    })
+                }) {
                     continue;
                 }
 
@@ -532,6 +546,49 @@ mod tests {
         assert!(payload.matches[0].file.ends_with("test.rs"));
     }
 
+    #[tokio::test]
+    async fn test_grep_nested_glob_and_skipped_dirs() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src").join("nested")).unwrap();
+        fs::create_dir_all(dir.path().join("target").join("debug")).unwrap();
+        fs::write(dir.path().join("src").join("lib.rs"), "fn main()").unwrap();
+        fs::write(
+            dir.path().join("src").join("nested").join("mod.rs"),
+            "fn main()",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("target").join("debug").join("gen.rs"),
+            "fn main()",
+        )
+        .unwrap();
+
+        // Built with platform separators, so this also covers Windows paths
+        let tool = GrepTool::new().with_root(dir.path());
+        let args = serde_json::json!({
+            "pattern": "main",
+            "path": dir.path().to_string_lossy(),
+            "glob": "**/src/*.rs",
+            "regex": false
+        });
+
+        let result = tool.execute(args).await.unwrap();
+        assert!(result.success);
+        let payload: GrepResponse = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(payload.total_matches, 1);
+        assert!(payload.matches[0].file.ends_with("lib.rs"));
+
+        let args = serde_json::json!({
+            "pattern": "main",
+            "path": dir.path().to_string_lossy(),
+            "regex": false
+        });
+        let result = tool.execute(args).await.unwrap();
+        let payload: GrepResponse = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(payload.total_matches, 2);
+        assert!(payload.matches.iter().all(|m| !m.file.contains("gen.rs")));
+    }
+
     #[tokio::test]
     async fn test_grep_max_matches() {
         let dir = tempdir().unwrap();
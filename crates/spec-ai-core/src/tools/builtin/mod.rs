@@ -1,18 +1,30 @@
+pub mod annotate;
+pub mod api_token;
+pub mod apply_patch;
+pub mod ask_user;
 pub mod audio_transcription;
 pub mod bash;
 pub mod calculator;
 pub mod code_search;
 pub mod echo;
+#[cfg(feature = "file-extract")]
 pub mod file_extract;
 pub mod file_read;
 pub mod file_write;
 pub mod generate_code;
+#[cfg(feature = "git")]
+pub mod git;
 pub mod graph;
 pub mod grep;
 pub mod prompt;
 pub mod rg;
 pub mod search;
 pub mod shell;
+pub mod spawn_agents;
+pub mod sql;
+pub mod task;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 #[cfg(feature = "api")]
 pub mod web_search;
@@ -20,27 +32,44 @@ pub mod web_search;
 #[cfg(feature = "web-scraping")]
 pub mod web_scraper;
 
-#[cfg(feature = "api")]
+#[cfg(feature = "reqwest")]
+pub mod web_fetch;
+
+#[cfg(feature = "mesh")]
 pub mod mesh_communication;
 
-#[cfg(feature = "api")]
+#[cfg(feature = "mesh")]
 pub mod collective;
 
+pub use annotate::AnnotateTool;
+pub use api_token::{
+    bind_issuer, ApiTokenTool, ScopedToken, ScopedTokenRequest, TokenMinter, API_TOKEN_TOOL,
+};
+pub use apply_patch::ApplyPatchTool;
+pub use ask_user::AskUserTool;
 pub use audio_transcription::AudioTranscriptionTool;
 pub use bash::BashTool;
 pub use calculator::MathTool;
 pub use code_search::CodeSearchTool;
 pub use echo::EchoTool;
+#[cfg(feature = "file-extract")]
 pub use file_extract::FileExtractTool;
 pub use file_read::FileReadTool;
 pub use file_write::FileWriteTool;
 pub use generate_code::GenerateCodeTool;
+#[cfg(feature = "git")]
+pub use git::{GitBlameTool, GitBranchTool, GitCommitTool, GitDiffTool, GitPolicy, GitStatusTool};
 pub use graph::GraphTool;
 pub use grep::GrepTool;
 pub use prompt::PromptUserTool;
 pub use rg::RgTool;
 pub use search::SearchTool;
 pub use shell::ShellTool;
+pub use spawn_agents::SpawnAgentsTool;
+pub use sql::SqlQueryTool;
+pub use task::{TaskLogsTool, TaskStartTool, TaskStatusTool, TaskStopTool};
+#[cfg(feature = "watch")]
+pub use watch::{UnwatchPathTool, WatchPathTool};
 
 #[cfg(feature = "api")]
 pub use web_search::WebSearchTool;
@@ -48,10 +77,13 @@ pub use web_search::WebSearchTool;
 #[cfg(feature = "web-scraping")]
 pub use web_scraper::WebScraperTool;
 
-#[cfg(feature = "api")]
+#[cfg(feature = "reqwest")]
+pub use web_fetch::WebFetchTool;
+
+#[cfg(feature = "mesh")]
 pub use mesh_communication::{GetMessagesTool, QueryMeshTool, SendMessageTool};
 
-#[cfg(feature = "api")]
+#[cfg(feature = "mesh")]
 pub use collective::{
     CastVoteTool, CreateWorkflowTool, DelegateTaskTool, QueryCapabilitiesTool,
     ReportStageResultTool, ShareCapabilitiesTool, ShareStrategyTool, SubmitProposalTool,
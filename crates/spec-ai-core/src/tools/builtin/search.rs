@@ -127,6 +127,10 @@ impl Tool for SearchTool {
         "search"
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         "Searches local files using literal or regex queries"
     }
@@ -0,0 +1,88 @@
+use crate::agent::subagent::{MAX_SUBAGENTS, SPAWN_AGENTS_TOOL};
+use crate::tools::{Tool, ToolResult};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// Describes the `spawn_agents` tool to the model
+///
+/// The calling agent runs the children itself (see
+/// [`crate::agent::subagent`]), so executing this tool from the registry
+/// only reports that it needs an agent.
+pub struct SpawnAgentsTool;
+
+impl SpawnAgentsTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for SpawnAgentsTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for SpawnAgentsTool {
+    fn name(&self) -> &str {
+        SPAWN_AGENTS_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Split work into independent sub-tasks and run each on a child agent with its own \
+         context, restricted tools and budget. The children run concurrently and their \
+         final answers are returned and remembered in this conversation."
+    }
+
+    fn parameters(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "agents": {
+                    "type": "array",
+                    "maxItems": MAX_SUBAGENTS,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": {
+                                "type": "string",
+                                "description": "Short unique label for the child"
+                            },
+                            "task": {
+                                "type": "string",
+                                "description": "Self-contained instructions; the child does not see this conversation"
+                            },
+                            "tools": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Tools the child may call (default: the tools you may call)"
+                            },
+                            "max_tokens": {
+                                "type": "integer",
+                                "description": "Most model tokens the child may use"
+                            },
+                            "max_tool_calls": {
+                                "type": "integer",
+                                "description": "Most tool calls the child may make"
+                            },
+                            "timeout_secs": {
+                                "type": "integer",
+                                "description": "Seconds before the child is stopped"
+                            }
+                        },
+                        "required": ["name", "task"]
+                    }
+                }
+            },
+            "required": ["agents"]
+        })
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult> {
+        Ok(ToolResult::failure(format!(
+            "{} can only be run by an agent",
+            SPAWN_AGENTS_TOOL
+        )))
+    }
+}
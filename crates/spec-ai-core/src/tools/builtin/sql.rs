@@ -0,0 +1,582 @@
+//! SQL Query Tool
+//!
+//! Runs SQL against the SQLite, Postgres and MySQL databases configured as
+//! `[sql.connections.<name>]` profiles. Each call opens an in-memory DuckDB
+//! that attaches the profile's database through DuckDB's scanner extensions,
+//! so one engine speaks all three dialects' data. The database is attached
+//! read-only unless the statement writes, the profile sets `read_only = false`
+//! and the policy engine allows `sql_write` on the profile name. File access
+//! and extension loading are switched off before the statement runs.
+
+use crate::config::{SqlConfig, SqlConnectionConfig};
+use crate::policy::{PolicyDecision, PolicyEngine};
+use crate::tools::{Tool, ToolResult};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use duckdb::types::Value as SqlValue;
+use duckdb::{params_from_iter, Connection};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Policy action a statement that writes needs on the profile name
+const SQL_WRITE_ACTION: &str = "sql_write";
+
+/// First keywords of statements that only read
+const READ_KEYWORDS: &[&str] = &[
+    "select",
+    "with",
+    "values",
+    "from",
+    "table",
+    "describe",
+    "show",
+    "summarize",
+];
+
+/// Tables and views of the attached database
+const TABLES_SQL: &str = "SELECT table_schema, table_name, table_type \
+     FROM information_schema.tables WHERE table_catalog = 'db' \
+     ORDER BY table_schema, table_name";
+
+/// Columns of one table of the attached database, `?` being its name
+const COLUMNS_SQL: &str =
+    "SELECT table_schema, column_name, data_type, is_nullable, column_default \
+     FROM information_schema.columns WHERE table_catalog = 'db' AND table_name = ?";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SqlAction {
+    #[default]
+    Query,
+    Tables,
+    Describe,
+}
+
+#[derive(Debug, Deserialize)]
+struct SqlQueryArgs {
+    connection: Option<String>,
+    #[serde(default)]
+    action: SqlAction,
+    sql: Option<String>,
+    #[serde(default)]
+    params: Vec<Value>,
+    table: Option<String>,
+    max_rows: Option<usize>,
+}
+
+/// Rows a read returned, values converted to JSON
+#[derive(Debug, PartialEq)]
+struct QueryRows {
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+    truncated: bool,
+}
+
+/// Tool that queries the configured SQL databases
+pub struct SqlQueryTool {
+    config: SqlConfig,
+    policy: Option<(Arc<PolicyEngine>, String)>,
+    description: String,
+}
+
+impl SqlQueryTool {
+    pub fn with_config(config: SqlConfig) -> Self {
+        let mut names: Vec<&String> = config.connections.keys().collect();
+        names.sort();
+        let profiles: Vec<String> = names
+            .into_iter()
+            .map(|name| {
+                let connection = &config.connections[name];
+                let mode = if connection.read_only {
+                    "read-only"
+                } else {
+                    "writable"
+                };
+                match &connection.description {
+                    Some(description) => format!(
+                        "{} ({}, {}): {}",
+                        name,
+                        connection.kind.as_str(),
+                        mode,
+                        description
+                    ),
+                    None => format!("{} ({}, {})", name, connection.kind.as_str(), mode),
+                }
+            })
+            .collect();
+        let description = format!(
+            "Runs SQL against a configured database and returns the rows as JSON. Use \
+             action 'tables' to list tables and 'describe' to list a table's columns before \
+             querying. Pass values as params bound to ? placeholders instead of writing them \
+             into the SQL. Connections: {}",
+            profiles.join("; ")
+        );
+        Self {
+            config,
+            policy: None,
+            description,
+        }
+    }
+
+    /// Allow writes where `engine` grants `agent` the `sql_write` action on
+    /// the profile name; without a policy every write is refused
+    pub fn with_policy(mut self, engine: Arc<PolicyEngine>, agent: impl Into<String>) -> Self {
+        self.policy = Some((engine, agent.into()));
+        self
+    }
+
+    /// The named profile, or the only one when no name is given
+    fn connection(&self, name: Option<&str>) -> Result<(String, &SqlConnectionConfig)> {
+        let configured = || {
+            let mut names: Vec<&str> = self.config.connections.keys().map(String::as_str).collect();
+            names.sort();
+            names.join(", ")
+        };
+        match name {
+            Some(name) => self
+                .config
+                .connections
+                .get(name)
+                .map(|connection| (name.to_string(), connection))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Unknown SQL connection '{}' (configured: {})",
+                        name,
+                        configured()
+                    )
+                }),
+            None if self.config.connections.len() == 1 => self
+                .config
+                .connections
+                .iter()
+                .next()
+                .map(|(name, connection)| (name.clone(), connection))
+                .ok_or_else(|| anyhow!("No SQL connections are configured")),
+            None => bail!("connection is required (configured: {})", configured()),
+        }
+    }
+
+    /// Fail unless the profile and the policy both allow writes
+    fn check_write(&self, name: &str, connection: &SqlConnectionConfig) -> Result<()> {
+        if connection.read_only {
+            bail!(
+                "Connection '{}' is read-only; only queries that read are allowed",
+                name
+            );
+        }
+        let Some((engine, agent)) = &self.policy else {
+            bail!(
+                "Writes to '{}' need a policy rule allowing {}",
+                name,
+                SQL_WRITE_ACTION
+            );
+        };
+        match engine.check(agent, SQL_WRITE_ACTION, name) {
+            PolicyDecision::Allow => Ok(()),
+            PolicyDecision::Deny(reason) => Err(anyhow!(reason)),
+        }
+    }
+}
+
+/// First keyword of a statement, past comments and opening parentheses
+fn first_keyword(sql: &str) -> String {
+    let mut rest = sql.trim_start();
+    loop {
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, after)| after);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, after)| after);
+        } else if let Some(inner) = rest.strip_prefix('(') {
+            rest = inner;
+        } else {
+            break;
+        }
+        rest = rest.trim_start();
+    }
+    rest.chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn is_read(sql: &str) -> bool {
+    READ_KEYWORDS.contains(&first_keyword(sql).as_str())
+}
+
+/// `text` as a SQL string literal
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(value) => SqlValue::Boolean(*value),
+        Value::Number(number) => number
+            .as_i64()
+            .map(SqlValue::BigInt)
+            .or_else(|| number.as_u64().map(SqlValue::UBigInt))
+            .unwrap_or_else(|| SqlValue::Double(number.as_f64().unwrap_or_default())),
+        Value::String(text) => SqlValue::Text(text.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+/// A value DuckDB rendered as text, back as JSON of its SQL type
+fn json_value(text: Option<String>, sql_type: &str) -> Value {
+    let Some(text) = text else {
+        return Value::Null;
+    };
+    let float = |text: String| {
+        text.parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or(Value::String(text))
+    };
+    match sql_type {
+        "BOOLEAN" => Value::Bool(text == "true"),
+        "TINYINT" | "SMALLINT" | "INTEGER" | "BIGINT" | "HUGEINT" | "UTINYINT" | "USMALLINT"
+        | "UINTEGER" | "UBIGINT" | "UHUGEINT" => text
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| text.parse::<u64>().map(Value::from))
+            .unwrap_or(Value::String(text)),
+        "FLOAT" | "DOUBLE" => float(text),
+        decimal if decimal.starts_with("DECIMAL") => float(text),
+        "JSON" => serde_json::from_str(&text).unwrap_or(Value::String(text)),
+        _ => Value::String(text),
+    }
+}
+
+/// An in-memory DuckDB with the profile's database attached as `db`
+fn open(
+    connection: &SqlConnectionConfig,
+    password: Option<&str>,
+    write: bool,
+) -> Result<Connection> {
+    let kind = connection.kind.as_str();
+    let mut options = vec![format!("TYPE {}", kind)];
+    let mut setup = format!("INSTALL {kind}; LOAD {kind};");
+    if let Some(password) = password {
+        setup.push_str(&format!(
+            "CREATE TEMPORARY SECRET sql_query (TYPE {}, PASSWORD {});",
+            kind,
+            quote(password)
+        ));
+        options.push("SECRET sql_query".to_string());
+    }
+    if !write {
+        options.push("READ_ONLY".to_string());
+    }
+    setup.push_str(&format!(
+        "ATTACH {} AS db ({}); USE db; \
+         SET enable_external_access = false; SET lock_configuration = true;",
+        quote(&connection.database),
+        options.join(", ")
+    ));
+
+    let conn = Connection::open_in_memory().context("Failed to open DuckDB")?;
+    conn.execute_batch(&setup)
+        .with_context(|| format!("Failed to attach the {} database", kind))?;
+    Ok(conn)
+}
+
+/// Run a statement that reads, returning at most `limit` rows
+///
+/// Column names come from a first run with no rows; the second casts every
+/// column to text next to its type, so any SQL type can be returned as JSON.
+fn query(conn: &Connection, sql: &str, params: &[SqlValue], limit: usize) -> Result<QueryRows> {
+    let columns = {
+        let mut statement = conn.prepare(&format!("SELECT * FROM ({}) AS q LIMIT 0", sql))?;
+        let rows = statement.query(params_from_iter(params))?;
+        rows.as_ref()
+            .map(|statement| statement.column_names())
+            .unwrap_or_default()
+    };
+
+    let aliases: Vec<String> = (0..columns.len()).map(|i| format!("c{}", i)).collect();
+    let select: Vec<String> = aliases
+        .iter()
+        .map(|alias| format!("CAST({0} AS VARCHAR), typeof({0})", alias))
+        .collect();
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM ({}) AS q({}) LIMIT {}",
+        select.join(", "),
+        sql,
+        aliases.join(", "),
+        limit + 1
+    ))?;
+    let width = columns.len();
+    let mut rows = statement
+        .query_map(params_from_iter(params), |row| {
+            (0..width)
+                .map(|i| {
+                    let text: Option<String> = row.get(2 * i)?;
+                    let sql_type: String = row.get(2 * i + 1)?;
+                    Ok(json_value(text, &sql_type))
+                })
+                .collect::<duckdb::Result<Vec<Value>>>()
+        })?
+        .collect::<duckdb::Result<Vec<_>>>()?;
+
+    let truncated = rows.len() > limit;
+    rows.truncate(limit);
+    Ok(QueryRows {
+        columns,
+        rows,
+        truncated,
+    })
+}
+
+#[async_trait]
+impl Tool for SqlQueryTool {
+    fn name(&self) -> &str {
+        "sql_query"
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "connection": {
+                    "type": "string",
+                    "description": "Name of the connection profile (optional when only one is configured)"
+                },
+                "action": {
+                    "type": "string",
+                    "enum": ["query", "tables", "describe"],
+                    "description": "'query' runs sql (default), 'tables' lists tables, 'describe' lists the columns of table"
+                },
+                "sql": {
+                    "type": "string",
+                    "description": "One SQL statement, with ? placeholders for params"
+                },
+                "params": {
+                    "type": "array",
+                    "description": "Values bound to the ? placeholders in order"
+                },
+                "table": {
+                    "type": "string",
+                    "description": "Table to describe, optionally as schema.table"
+                },
+                "max_rows": {
+                    "type": "integer",
+                    "description": "Most rows to return, up to the configured limit"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: SqlQueryArgs =
+            serde_json::from_value(args).context("Failed to parse sql_query arguments")?;
+        let (name, connection) = match self.connection(args.connection.as_deref()) {
+            Ok(found) => found,
+            Err(e) => return Ok(ToolResult::failure(e.to_string())),
+        };
+
+        let (sql, params) = match args.action {
+            SqlAction::Query => match args.sql {
+                Some(sql) if !sql.trim().is_empty() => (sql, args.params),
+                _ => return Ok(ToolResult::failure("sql is required for action 'query'")),
+            },
+            SqlAction::Tables => (TABLES_SQL.to_string(), Vec::new()),
+            SqlAction::Describe => match args.table.as_deref().map(str::trim) {
+                Some(table) if !table.is_empty() => match table.rsplit_once('.') {
+                    Some((schema, table)) => (
+                        format!(
+                            "{} AND table_schema = ? ORDER BY ordinal_position",
+                            COLUMNS_SQL
+                        ),
+                        vec![json!(table), json!(schema)],
+                    ),
+                    None => (
+                        format!("{} ORDER BY table_schema, ordinal_position", COLUMNS_SQL),
+                        vec![json!(table)],
+                    ),
+                },
+                _ => {
+                    return Ok(ToolResult::failure(
+                        "table is required for action 'describe'",
+                    ))
+                }
+            },
+        };
+        let sql = sql.trim().trim_end_matches(';').trim_end().to_string();
+        let params: Vec<SqlValue> = params.iter().map(sql_value).collect();
+
+        let write = !is_read(&sql);
+        if write {
+            if let Err(e) = self.check_write(&name, connection) {
+                return Ok(ToolResult::failure(e.to_string()));
+            }
+        }
+        let cap = connection.max_rows.unwrap_or(self.config.max_rows);
+        let limit = args.max_rows.map_or(cap, |rows| rows.clamp(1, cap));
+        let password = match connection.password() {
+            Ok(password) => password,
+            Err(e) => return Ok(ToolResult::failure(format!("{:#}", e))),
+        };
+
+        let connection = connection.clone();
+        let described = match args.action {
+            SqlAction::Describe => args.table,
+            _ => None,
+        };
+        let result = tokio::task::spawn_blocking(move || -> Result<Value> {
+            let conn = open(&connection, password.as_deref(), write)?;
+            if write {
+                let changed = conn.execute(&sql, params_from_iter(&params))?;
+                return Ok(json!({ "rows_affected": changed }));
+            }
+            let rows = query(&conn, &sql, &params, limit)?;
+            if let (Some(table), true) = (described, rows.rows.is_empty()) {
+                bail!("No table named '{}'", table);
+            }
+            Ok(json!({
+                "columns": rows.columns,
+                "row_count": rows.rows.len(),
+                "rows": rows.rows,
+                "truncated": rows.truncated,
+            }))
+        })
+        .await
+        .context("SQL query task failed")?;
+
+        match result {
+            Ok(mut value) => {
+                value["connection"] = json!(name);
+                let output =
+                    serde_json::to_string_pretty(&value).context("Failed to serialize rows")?;
+                Ok(ToolResult::success(output))
+            }
+            Err(e) => Ok(ToolResult::failure(format!(
+                "SQL on '{}' failed: {:#}",
+                name, e
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SqlKind;
+    use crate::policy::{PolicyEffect, PolicyRule};
+    use std::collections::HashMap;
+
+    fn tool(read_only: bool) -> SqlQueryTool {
+        let connection = SqlConnectionConfig {
+            kind: SqlKind::Sqlite,
+            database: "/tmp/shop.db".to_string(),
+            password: None,
+            read_only,
+            max_rows: None,
+            description: Some("Orders".to_string()),
+        };
+        SqlQueryTool::with_config(SqlConfig {
+            connections: HashMap::from([("shop".to_string(), connection)]),
+            max_rows: 10,
+        })
+    }
+
+    #[test]
+    fn test_statements_are_classified_by_first_keyword() {
+        assert!(is_read("SELECT * FROM orders"));
+        assert!(is_read("  with t as (select 1) select * from t"));
+        assert!(is_read("-- totals\n/* by day */ (SELECT 1)"));
+        assert!(is_read("DESCRIBE orders"));
+        assert!(!is_read("INSERT INTO orders VALUES (1)"));
+        assert!(!is_read("delete from orders"));
+        assert!(!is_read("COPY orders TO '/tmp/out.csv'"));
+        assert!(!is_read("ATTACH '/tmp/other.db' AS other"));
+        assert!(!is_read("-- select\nDROP TABLE orders"));
+        assert_eq!(quote("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn test_values_convert_by_sql_type() {
+        assert_eq!(json_value(None, "INTEGER"), Value::Null);
+        assert_eq!(json_value(Some("42".into()), "BIGINT"), json!(42));
+        assert_eq!(json_value(Some("1.5".into()), "DECIMAL(10,2)"), json!(1.5));
+        assert_eq!(json_value(Some("true".into()), "BOOLEAN"), json!(true));
+        assert_eq!(
+            json_value(Some("{\"a\": 1}".into()), "JSON"),
+            json!({"a": 1})
+        );
+        assert_eq!(
+            json_value(Some("2024-01-02".into()), "DATE"),
+            json!("2024-01-02")
+        );
+    }
+
+    #[test]
+    fn test_query_returns_typed_rows_up_to_the_limit() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE orders (id INTEGER, customer VARCHAR, total DOUBLE, paid BOOLEAN);
+             INSERT INTO orders VALUES (1, 'ada', 9.5, true), (2, 'bob', NULL, false),
+                                       (3, 'ada', 20.0, true);",
+        )
+        .unwrap();
+
+        let rows = query(
+            &conn,
+            "SELECT id, customer, total, paid FROM orders WHERE customer = ? ORDER BY id",
+            &[sql_value(&json!("ada"))],
+            10,
+        )
+        .unwrap();
+        assert_eq!(rows.columns, vec!["id", "customer", "total", "paid"]);
+        assert_eq!(
+            rows.rows,
+            vec![
+                vec![json!(1), json!("ada"), json!(9.5), json!(true)],
+                vec![json!(3), json!("ada"), json!(20.0), json!(true)],
+            ]
+        );
+        assert!(!rows.truncated);
+
+        let rows = query(&conn, "SELECT id, id FROM orders ORDER BY id", &[], 2).unwrap();
+        assert_eq!(rows.rows.len(), 2);
+        assert_eq!(rows.rows[1], vec![json!(2), json!(2)]);
+        assert!(rows.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_writes_need_a_writable_profile_and_policy() {
+        let insert = json!({"sql": "INSERT INTO orders VALUES (4)"});
+
+        let result = tool(true).execute(insert.clone()).await.unwrap();
+        assert!(result.error.unwrap().contains("read-only"));
+
+        let result = tool(false).execute(insert.clone()).await.unwrap();
+        assert!(result.error.unwrap().contains("sql_write"));
+
+        let mut policy = PolicyEngine::new();
+        policy.add_rule(PolicyRule {
+            agent: "*".to_string(),
+            action: "tool_call".to_string(),
+            resource: "*".to_string(),
+            effect: PolicyEffect::Allow,
+        });
+        let result = tool(false)
+            .with_policy(Arc::new(policy), "analyst")
+            .execute(insert)
+            .await
+            .unwrap();
+        assert!(result.error.unwrap().contains("default deny"));
+
+        let result = tool(true)
+            .execute(json!({"connection": "other", "sql": "SELECT 1"}))
+            .await
+            .unwrap();
+        assert!(result.error.unwrap().contains("configured: shop"));
+    }
+}
@@ -0,0 +1,489 @@
+//! Background Task Tools
+//!
+//! `task_start` runs a shell command in the background and returns a task id
+//! right away, so builds and test suites can run for longer than a turn.
+//! `task_status`, `task_logs` and `task_stop` poll, read and stop tasks by id
+//! in later turns. Tasks live in the process-wide [`TaskRegistry`], which the
+//! TUI's process panel follows too.
+//!
+//! Commands are checked against the same denylist as `bash`. With a sandbox,
+//! tasks get its filesystem, network and resource limits but not its wall
+//! clock timeout; they run until they exit or are stopped.
+
+use crate::tasks::TaskRegistry;
+use crate::tools::builtin::bash::validate_command;
+use crate::tools::sandbox::Sandbox;
+use crate::tools::{Tool, ToolResult};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Lines `task_logs` returns when no `tail` is given
+const DEFAULT_TAIL: usize = 100;
+
+/// Longest `wait_secs` accepted by `task_status`
+const MAX_WAIT_SECS: u64 = 600;
+
+#[derive(Debug, Deserialize)]
+struct StartArgs {
+    command: String,
+    name: Option<String>,
+    working_dir: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusArgs {
+    id: Option<String>,
+    wait_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogsArgs {
+    id: String,
+    since: Option<u64>,
+    tail: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StopArgs {
+    id: String,
+}
+
+fn unknown_task(id: &str) -> ToolResult {
+    ToolResult::failure(format!("Unknown task '{}'", id))
+}
+
+fn to_output(value: &impl serde::Serialize) -> Result<String> {
+    serde_json::to_string(value).context("Failed to serialize task output")
+}
+
+/// Tool that starts a shell command as a background task
+pub struct TaskStartTool {
+    tasks: Arc<TaskRegistry>,
+    shell_path: String,
+    sandbox: Option<Arc<Sandbox>>,
+}
+
+impl TaskStartTool {
+    pub fn new() -> Self {
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+        Self {
+            tasks: TaskRegistry::global(),
+            shell_path: shell,
+            sandbox: None,
+        }
+    }
+
+    /// Track tasks in `tasks` instead of the process-wide registry
+    pub fn with_registry(mut self, tasks: Arc<TaskRegistry>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    pub fn with_shell(mut self, path: impl Into<String>) -> Self {
+        self.shell_path = path.into();
+        self
+    }
+
+    /// Start tasks inside `sandbox`
+    pub fn with_sandbox(mut self, sandbox: Arc<Sandbox>) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+}
+
+impl Default for TaskStartTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for TaskStartTool {
+    fn name(&self) -> &str {
+        "task_start"
+    }
+
+    fn description(&self) -> &str {
+        "Starts a shell command in the background and returns its task id; poll it with task_status and task_logs"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": {
+                    "type": "string",
+                    "description": "Shell command to run, e.g. a build or test suite"
+                },
+                "name": {
+                    "type": "string",
+                    "description": "Short label for the task; defaults to the command"
+                },
+                "working_dir": {
+                    "type": "string",
+                    "description": "Working directory for the command"
+                }
+            },
+            "required": ["command"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: StartArgs =
+            serde_json::from_value(args).context("Failed to parse task_start arguments")?;
+        validate_command(&args.command)?;
+        let shell_path = Path::new(&self.shell_path);
+        if !shell_path.exists() {
+            return Err(anyhow!(
+                "Shell path {} does not exist",
+                shell_path.display()
+            ));
+        }
+
+        let command = match &self.sandbox {
+            Some(sandbox) => {
+                let working_dir = args.working_dir.as_deref().map(Path::new);
+                match sandbox.command(shell_path, &args.command, working_dir) {
+                    Ok(command) => command,
+                    Err(violation) => {
+                        return Ok(ToolResult::failure(to_output(&json!({
+                            "command": args.command,
+                            "violation": violation,
+                        }))?))
+                    }
+                }
+            }
+            None => {
+                let mut command = Command::new(shell_path);
+                command.arg("-c").arg(&args.command);
+                if let Some(dir) = &args.working_dir {
+                    command.current_dir(dir);
+                }
+                command
+            }
+        };
+
+        let info = self.tasks.start(
+            args.name.as_deref(),
+            &args.command,
+            args.working_dir.as_deref(),
+            command,
+        )?;
+        tracing::info!(
+            target: "spec_ai::tools::task",
+            id = %info.id,
+            command = %info.command,
+            "Started background task"
+        );
+        Ok(ToolResult::success(to_output(&info)?))
+    }
+}
+
+/// Tool that reports the state of one background task or all of them
+pub struct TaskStatusTool {
+    tasks: Arc<TaskRegistry>,
+}
+
+impl TaskStatusTool {
+    pub fn new() -> Self {
+        Self {
+            tasks: TaskRegistry::global(),
+        }
+    }
+
+    /// Report tasks of `tasks` instead of the process-wide registry
+    pub fn with_registry(mut self, tasks: Arc<TaskRegistry>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+}
+
+impl Default for TaskStatusTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for TaskStatusTool {
+    fn name(&self) -> &str {
+        "task_status"
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> &str {
+        "Reports whether a background task is running or how it ended; lists every task when no id is given"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Task id returned by task_start; omit to list all tasks"
+                },
+                "wait_secs": {
+                    "type": "integer",
+                    "description": "Wait up to this many seconds for the task to finish before reporting",
+                    "minimum": 0,
+                    "maximum": MAX_WAIT_SECS
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: StatusArgs =
+            serde_json::from_value(args).context("Failed to parse task_status arguments")?;
+        let Some(id) = args.id else {
+            return Ok(ToolResult::success(to_output(
+                &json!({ "tasks": self.tasks.list() }),
+            )?));
+        };
+        let info = match args.wait_secs {
+            Some(secs) if secs > 0 => {
+                let timeout = Duration::from_secs(secs.min(MAX_WAIT_SECS));
+                match self.tasks.wait(&id, timeout).await {
+                    Ok(info) => info,
+                    Err(_) => return Ok(unknown_task(&id)),
+                }
+            }
+            _ => match self.tasks.status(&id) {
+                Some(info) => info,
+                None => return Ok(unknown_task(&id)),
+            },
+        };
+        Ok(ToolResult::success(to_output(&info)?))
+    }
+}
+
+/// Tool that reads the captured output of a background task
+pub struct TaskLogsTool {
+    tasks: Arc<TaskRegistry>,
+}
+
+impl TaskLogsTool {
+    pub fn new() -> Self {
+        Self {
+            tasks: TaskRegistry::global(),
+        }
+    }
+
+    /// Read tasks of `tasks` instead of the process-wide registry
+    pub fn with_registry(mut self, tasks: Arc<TaskRegistry>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+}
+
+impl Default for TaskLogsTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for TaskLogsTool {
+    fn name(&self) -> &str {
+        "task_logs"
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> &str {
+        "Returns the newest output lines of a background task, optionally only lines after a given one"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Task id returned by task_start"
+                },
+                "since": {
+                    "type": "integer",
+                    "description": "Only return lines with this seq or later; pass next_seq from the previous call",
+                    "minimum": 0
+                },
+                "tail": {
+                    "type": "integer",
+                    "description": "Maximum number of lines to return (default 100); the newest ones unless since is given",
+                    "minimum": 1
+                }
+            },
+            "required": ["id"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: LogsArgs =
+            serde_json::from_value(args).context("Failed to parse task_logs arguments")?;
+        let tail = args.tail.unwrap_or(DEFAULT_TAIL).max(1);
+        let Some((info, lines)) = self.tasks.logs(&args.id, args.since, tail) else {
+            return Ok(unknown_task(&args.id));
+        };
+        Ok(ToolResult::success(to_output(&json!({
+            "id": info.id,
+            "state": info.state,
+            "exit_code": info.exit_code,
+            "next_seq": lines.last().map_or(info.lines, |line| line.seq + 1),
+            "lines": lines,
+        }))?))
+    }
+}
+
+/// Tool that stops a running background task
+pub struct TaskStopTool {
+    tasks: Arc<TaskRegistry>,
+}
+
+impl TaskStopTool {
+    pub fn new() -> Self {
+        Self {
+            tasks: TaskRegistry::global(),
+        }
+    }
+
+    /// Stop tasks of `tasks` instead of the process-wide registry
+    pub fn with_registry(mut self, tasks: Arc<TaskRegistry>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+}
+
+impl Default for TaskStopTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for TaskStopTool {
+    fn name(&self) -> &str {
+        "task_stop"
+    }
+
+    fn description(&self) -> &str {
+        "Stops a running background task and the processes it started"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Task id returned by task_start"
+                }
+            },
+            "required": ["id"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: StopArgs =
+            serde_json::from_value(args).context("Failed to parse task_stop arguments")?;
+        match self.tasks.stop(&args.id).await {
+            Ok(info) => Ok(ToolResult::success(to_output(&info)?)),
+            Err(_) => Ok(unknown_task(&args.id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::TaskState;
+
+    fn tools() -> (TaskStartTool, TaskStatusTool, TaskLogsTool, TaskStopTool) {
+        let tasks = Arc::new(TaskRegistry::new());
+        (
+            TaskStartTool::new()
+                .with_registry(tasks.clone())
+                .with_shell("/bin/sh"),
+            TaskStatusTool::new().with_registry(tasks.clone()),
+            TaskLogsTool::new().with_registry(tasks.clone()),
+            TaskStopTool::new().with_registry(tasks),
+        )
+    }
+
+    fn payload(result: &ToolResult) -> Value {
+        serde_json::from_str(&result.output).unwrap()
+    }
+
+    #[tokio::test]
+    async fn started_tasks_can_be_polled_and_read() {
+        let (start, status, logs, _) = tools();
+        let started = start
+            .execute(json!({ "command": "echo built; echo done", "name": "build" }))
+            .await
+            .unwrap();
+        assert!(started.success);
+        let id = payload(&started)["id"].as_str().unwrap().to_string();
+
+        let finished = status
+            .execute(json!({ "id": id, "wait_secs": 10 }))
+            .await
+            .unwrap();
+        assert_eq!(payload(&finished)["state"], "succeeded");
+        assert_eq!(payload(&finished)["name"], "build");
+
+        let output = logs.execute(json!({ "id": id, "since": 1 })).await.unwrap();
+        let output = payload(&output);
+        assert_eq!(output["next_seq"], 2);
+        assert_eq!(output["lines"][0]["text"], "done");
+
+        let listed = status.execute(json!({})).await.unwrap();
+        assert_eq!(payload(&listed)["tasks"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn running_tasks_can_be_stopped() {
+        let (start, status, _, stop) = tools();
+        let started = start
+            .execute(json!({ "command": "sleep 30" }))
+            .await
+            .unwrap();
+        let id = payload(&started)["id"].as_str().unwrap().to_string();
+
+        let stopped = stop.execute(json!({ "id": id })).await.unwrap();
+        assert_eq!(payload(&stopped)["state"], TaskState::Stopped.as_str());
+        assert!(
+            !stop
+                .execute(json!({ "id": "task-42" }))
+                .await
+                .unwrap()
+                .success
+        );
+        assert!(
+            !status
+                .execute(json!({ "id": "task-42" }))
+                .await
+                .unwrap()
+                .success
+        );
+    }
+
+    #[tokio::test]
+    async fn denylisted_commands_are_refused() {
+        let (start, ..) = tools();
+        assert!(start
+            .execute(json!({ "command": "sudo make install" }))
+            .await
+            .is_err());
+    }
+}
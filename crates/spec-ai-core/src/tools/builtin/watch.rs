@@ -0,0 +1,238 @@
+//! File Watch Tools
+//!
+//! `watch_path` starts following a file or directory and returns a watch id
+//! right away. Changes are batched until the path has been quiet for a
+//! moment and then delivered to the agent as an event turn, so it can react
+//! to edits without being asked, for example by running the tests again
+//! after each save. `unwatch_path` stops a watch. Watches live in the
+//! process-wide [`WatchRegistry`], which the TUI follows too.
+
+use crate::tools::{Tool, ToolResult};
+use crate::watch::{WatchOptions, WatchRegistry, MAX_DEBOUNCE, MIN_DEBOUNCE};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize)]
+struct WatchArgs {
+    path: String,
+    recursive: Option<bool>,
+    debounce_ms: Option<u64>,
+    #[serde(default)]
+    ignore: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnwatchArgs {
+    id: Option<String>,
+}
+
+fn to_output(value: &impl serde::Serialize) -> Result<String> {
+    serde_json::to_string(value).context("Failed to serialize watch output")
+}
+
+/// Tool that starts watching a path for changes
+pub struct WatchPathTool {
+    watches: Arc<WatchRegistry>,
+}
+
+impl WatchPathTool {
+    pub fn new() -> Self {
+        Self {
+            watches: WatchRegistry::global(),
+        }
+    }
+
+    /// Track watches in `watches` instead of the process-wide registry
+    pub fn with_registry(mut self, watches: Arc<WatchRegistry>) -> Self {
+        self.watches = watches;
+        self
+    }
+}
+
+impl Default for WatchPathTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for WatchPathTool {
+    fn name(&self) -> &str {
+        "watch_path"
+    }
+
+    fn description(&self) -> &str {
+        "Watches a file or directory and delivers its changes to you as events in a later turn; returns the watch id"
+    }
+
+    fn parameters(&self) -> Value {
+        let defaults = WatchOptions::default();
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File or directory to watch"
+                },
+                "recursive": {
+                    "type": "boolean",
+                    "description": "Also watch subdirectories (default true)"
+                },
+                "debounce_ms": {
+                    "type": "integer",
+                    "description": format!(
+                        "Wait until the path has been quiet this long before reporting (default {})",
+                        defaults.debounce.as_millis()
+                    ),
+                    "minimum": MIN_DEBOUNCE.as_millis() as u64,
+                    "maximum": MAX_DEBOUNCE.as_millis() as u64
+                },
+                "ignore": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "File or directory names whose changes are not reported, on top of .git, target and node_modules"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: WatchArgs =
+            serde_json::from_value(args).context("Failed to parse watch_path arguments")?;
+        let defaults = WatchOptions::default();
+        let options = WatchOptions {
+            recursive: args.recursive.unwrap_or(defaults.recursive),
+            debounce: args
+                .debounce_ms
+                .map_or(defaults.debounce, Duration::from_millis),
+            ignore: args.ignore,
+        };
+        match self.watches.watch(Path::new(&args.path), options) {
+            Ok(info) => {
+                tracing::info!(
+                    target: "spec_ai::tools::watch",
+                    id = %info.id,
+                    path = %info.path,
+                    "Started file watch"
+                );
+                Ok(ToolResult::success(to_output(&info)?))
+            }
+            Err(err) => Ok(ToolResult::failure(format!("{:#}", err))),
+        }
+    }
+}
+
+/// Tool that stops a watch, or lists them
+pub struct UnwatchPathTool {
+    watches: Arc<WatchRegistry>,
+}
+
+impl UnwatchPathTool {
+    pub fn new() -> Self {
+        Self {
+            watches: WatchRegistry::global(),
+        }
+    }
+
+    /// Stop watches of `watches` instead of the process-wide registry
+    pub fn with_registry(mut self, watches: Arc<WatchRegistry>) -> Self {
+        self.watches = watches;
+        self
+    }
+}
+
+impl Default for UnwatchPathTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for UnwatchPathTool {
+    fn name(&self) -> &str {
+        "unwatch_path"
+    }
+
+    fn description(&self) -> &str {
+        "Stops a watch started with watch_path; lists the active watches when no id is given"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Watch id returned by watch_path; omit to list all watches"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: UnwatchArgs =
+            serde_json::from_value(args).context("Failed to parse unwatch_path arguments")?;
+        let Some(id) = args.id else {
+            return Ok(ToolResult::success(to_output(
+                &json!({ "watches": self.watches.list() }),
+            )?));
+        };
+        match self.watches.unwatch(&id) {
+            Ok(info) => Ok(ToolResult::success(to_output(&info)?)),
+            Err(err) => Ok(ToolResult::failure(err.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(result: &ToolResult) -> Value {
+        serde_json::from_str(&result.output).unwrap()
+    }
+
+    #[tokio::test]
+    async fn watches_can_be_started_listed_and_stopped() {
+        let dir = tempfile::tempdir().unwrap();
+        let watches = Arc::new(WatchRegistry::new());
+        let watch = WatchPathTool::new().with_registry(watches.clone());
+        let unwatch = UnwatchPathTool::new().with_registry(watches);
+
+        let started = watch
+            .execute(json!({ "path": dir.path(), "debounce_ms": 100, "ignore": ["dist"] }))
+            .await
+            .unwrap();
+        assert!(started.success);
+        let id = payload(&started)["id"].as_str().unwrap().to_string();
+        assert_eq!(payload(&started)["ignore"], json!(["dist"]));
+
+        let listed = unwatch.execute(json!({})).await.unwrap();
+        assert_eq!(payload(&listed)["watches"].as_array().unwrap().len(), 1);
+
+        assert!(unwatch.execute(json!({ "id": id })).await.unwrap().success);
+        assert!(!unwatch.execute(json!({ "id": id })).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn missing_paths_and_bad_debounces_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let watch = WatchPathTool::new().with_registry(Arc::new(WatchRegistry::new()));
+        let missing = watch
+            .execute(json!({ "path": dir.path().join("nope") }))
+            .await
+            .unwrap();
+        assert!(!missing.success);
+        let too_fast = watch
+            .execute(json!({ "path": dir.path(), "debounce_ms": 1 }))
+            .await
+            .unwrap();
+        assert!(!too_fast.success);
+    }
+}
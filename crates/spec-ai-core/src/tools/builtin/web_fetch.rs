@@ -0,0 +1,676 @@
+//! Web Fetch Tool
+//!
+//! Reads a single page and returns its readable content: navigation, footers,
+//! scripts and other boilerplate are dropped and the rest is turned into
+//! markdown. Fetches are bounded by the `[web_fetch]` settings (domain
+//! allow and deny lists, timeout, body size, robots.txt) and the results are
+//! cached in persistence so repeated reads of the same documentation page are
+//! free.
+
+use crate::config::WebFetchConfig;
+use crate::persistence::Persistence;
+use crate::tools::{Tool, ToolResult};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::{redirect, Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Redirects followed before giving up
+const MAX_REDIRECTS: usize = 5;
+
+/// Elements that hold page chrome rather than content
+const BOILERPLATE_TAGS: [&str; 12] = [
+    "script", "style", "noscript", "template", "svg", "iframe", "nav", "footer", "aside", "button",
+    "select", "dialog",
+];
+
+#[derive(Debug, Deserialize)]
+struct WebFetchArgs {
+    url: String,
+    refresh: Option<bool>,
+}
+
+/// Readable content of a fetched page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchedPage {
+    pub url: String,
+    pub title: Option<String>,
+    pub content: String,
+    /// Whether the body or the extracted content was cut at a limit
+    pub truncated: bool,
+    /// Whether the page was served from the cache
+    #[serde(default)]
+    pub cached: bool,
+}
+
+/// Fetch a URL and return its readable content as markdown
+pub struct WebFetchTool {
+    client: Client,
+    config: WebFetchConfig,
+    persistence: Option<Arc<Persistence>>,
+}
+
+impl WebFetchTool {
+    pub fn new() -> Self {
+        Self::with_config(WebFetchConfig::default())
+    }
+
+    pub fn with_config(config: WebFetchConfig) -> Self {
+        Self {
+            client: build_client(&config),
+            config,
+            persistence: None,
+        }
+    }
+
+    /// Cache fetched pages in `persistence`, dropping pages that have expired
+    pub fn with_persistence(mut self, persistence: Arc<Persistence>) -> Self {
+        if let Err(err) = persistence.web_cache_prune(false) {
+            warn!("Failed to prune the web cache: {}", err);
+        }
+        self.persistence = Some(persistence);
+        self
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        (self.config.cache_ttl_secs > 0).then(|| Duration::from_secs(self.config.cache_ttl_secs))
+    }
+
+    fn cached(&self, url: &Url) -> Option<FetchedPage> {
+        let persistence = self.persistence.as_ref()?;
+        self.cache_ttl()?;
+        match persistence.web_cache_get(url.as_str()) {
+            Ok(Some(stored)) => serde_json::from_str::<FetchedPage>(&stored)
+                .ok()
+                .map(|page| FetchedPage {
+                    cached: true,
+                    ..page
+                }),
+            Ok(None) => None,
+            Err(err) => {
+                warn!("Failed to read the web cache: {}", err);
+                None
+            }
+        }
+    }
+
+    fn store(&self, url: &Url, page: &FetchedPage) {
+        let (Some(persistence), Some(ttl)) = (self.persistence.as_ref(), self.cache_ttl()) else {
+            return;
+        };
+        let stored = match serde_json::to_string(page) {
+            Ok(stored) => stored,
+            Err(_) => return,
+        };
+        if let Err(err) = persistence.web_cache_put(url.as_str(), &stored, ttl) {
+            warn!("Failed to cache {}: {}", url, err);
+        }
+    }
+
+    async fn fetch(&self, url: &Url) -> Result<FetchedPage> {
+        if self.config.respect_robots && !self.robots_allow(url).await {
+            bail!("robots.txt of {} disallows this page", host_of(url)?);
+        }
+
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .context("request failed")?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("server returned {}", status);
+        }
+        let final_url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        let (body, mut truncated) = read_limited(response, self.config.max_bytes).await?;
+        let body = String::from_utf8_lossy(&body);
+        let is_html = content_type.contains("html")
+            || (content_type.is_empty() && body.trim_start().starts_with('<'));
+        let (title, mut content) = if is_html {
+            readable_markdown(&body, Some(&final_url))
+        } else if content_type.is_empty()
+            || content_type.starts_with("text/")
+            || content_type.contains("json")
+            || content_type.contains("xml")
+        {
+            (None, body.trim().to_string())
+        } else {
+            bail!("unsupported content type '{}'", content_type);
+        };
+
+        if content.chars().count() > self.config.max_chars {
+            content = content.chars().take(self.config.max_chars).collect();
+            content.push_str("\n\n[truncated]");
+            truncated = true;
+        }
+
+        Ok(FetchedPage {
+            url: final_url.to_string(),
+            title,
+            content,
+            truncated,
+            cached: false,
+        })
+    }
+
+    /// Whether the site's robots.txt lets this tool read `url`
+    ///
+    /// A missing or unreachable robots.txt allows everything.
+    async fn robots_allow(&self, url: &Url) -> bool {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return true;
+        };
+        let response = match self.client.get(robots_url).send().await {
+            Ok(response) if response.status().is_success() => response,
+            _ => return true,
+        };
+        let Ok((body, _)) = read_limited(response, self.config.max_bytes).await else {
+            return true;
+        };
+        let robots = String::from_utf8_lossy(&body);
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        robots_allows(&robots, env!("CARGO_PKG_NAME"), &path)
+    }
+}
+
+impl Default for WebFetchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for WebFetchTool {
+    fn name(&self) -> &str {
+        "web_fetch"
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> &str {
+        "Fetches a single web page and returns its readable content as markdown, without \
+         navigation, scripts or other boilerplate. Useful for reading documentation pages."
+    }
+
+    fn parameters(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "The http or https URL to fetch"
+                },
+                "refresh": {
+                    "type": "boolean",
+                    "description": "Fetch the page again even if a cached copy exists (default: false)"
+                }
+            },
+            "required": ["url"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult> {
+        let args: WebFetchArgs =
+            serde_json::from_value(args).context("Failed to parse web_fetch arguments")?;
+
+        let url = match Url::parse(&args.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            Ok(url) => {
+                return Ok(ToolResult::failure(format!(
+                    "Unsupported URL scheme '{}' (expected http or https)",
+                    url.scheme()
+                )))
+            }
+            Err(e) => {
+                return Ok(ToolResult::failure(format!(
+                    "Invalid URL '{}': {}",
+                    args.url, e
+                )))
+            }
+        };
+        if let Err(e) = host_of(&url).and_then(|host| check_domain(&self.config, host)) {
+            return Ok(ToolResult::failure(e.to_string()));
+        }
+
+        if !args.refresh.unwrap_or(false) {
+            if let Some(page) = self.cached(&url) {
+                debug!("Serving {} from the web cache", url);
+                let output = serde_json::to_string_pretty(&page)
+                    .context("Failed to serialize fetched page")?;
+                return Ok(ToolResult::success(output));
+            }
+        }
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let fetched = match tokio::time::timeout(timeout, self.fetch(&url)).await {
+            Ok(fetched) => fetched,
+            Err(_) => Err(anyhow!("timed out after {}s", self.config.timeout_secs)),
+        };
+        match fetched {
+            Ok(page) => {
+                self.store(&url, &page);
+                let output = serde_json::to_string_pretty(&page)
+                    .context("Failed to serialize fetched page")?;
+                Ok(ToolResult::success(output))
+            }
+            Err(e) => {
+                warn!("Web fetch failed for {}: {:#}", url, e);
+                Ok(ToolResult::failure(format!(
+                    "Failed to fetch {}: {:#}",
+                    url, e
+                )))
+            }
+        }
+    }
+}
+
+/// HTTP client that follows redirects only to domains the policy allows
+fn build_client(config: &WebFetchConfig) -> Client {
+    let policy = config.clone();
+    Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .redirect(redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error(format!("more than {} redirects", MAX_REDIRECTS));
+            }
+            let checked = host_of(attempt.url()).and_then(|host| check_domain(&policy, host));
+            match checked {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e.to_string()),
+            }
+        }))
+        .build()
+        .expect("failed to construct web fetch client")
+}
+
+fn host_of(url: &Url) -> Result<&str> {
+    url.host_str()
+        .ok_or_else(|| anyhow!("URL '{}' has no host", url))
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn domain_matches(host: &str, domain: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let domain = domain.trim_start_matches("*.").to_ascii_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Apply the deny list, then the allow list when it isn't empty
+fn check_domain(config: &WebFetchConfig, host: &str) -> Result<()> {
+    if let Some(domain) = config
+        .denied_domains
+        .iter()
+        .find(|domain| domain_matches(host, domain))
+    {
+        bail!(
+            "Domain '{}' is denied by web_fetch policy ({})",
+            host,
+            domain
+        );
+    }
+    if !config.allowed_domains.is_empty()
+        && !config
+            .allowed_domains
+            .iter()
+            .any(|domain| domain_matches(host, domain))
+    {
+        bail!("Domain '{}' is not in the web_fetch allow list", host);
+    }
+    Ok(())
+}
+
+/// Read at most `max_bytes` of the body, reporting whether it was cut off
+async fn read_limited(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<(Vec<u8>, bool)> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.context("reading body failed")? {
+        body.extend_from_slice(&chunk);
+        if body.len() > max_bytes {
+            body.truncate(max_bytes);
+            return Ok((body, true));
+        }
+    }
+    Ok((body, false))
+}
+
+/// Whether robots.txt lets `agent` read `path`
+///
+/// Uses the group naming the agent, else the `*` group; the longest matching
+/// Allow or Disallow rule decides, with Allow winning ties.
+fn robots_allows(robots: &str, agent: &str, path: &str) -> bool {
+    let agent = agent.to_ascii_lowercase();
+    let mut named: Option<Vec<(bool, String)>> = None;
+    let mut wildcard: Vec<(bool, String)> = Vec::new();
+    let mut agents: Vec<String> = Vec::new();
+    let mut in_rules = false;
+
+    for line in robots.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        let field = field.trim().to_ascii_lowercase();
+        let value = value.trim();
+        match field.as_str() {
+            "user-agent" => {
+                // A user-agent line after rules starts a new group
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_ascii_lowercase());
+            }
+            "allow" | "disallow" => {
+                in_rules = true;
+                let group = if agents
+                    .iter()
+                    .any(|name| name != "*" && agent.contains(name.as_str()))
+                {
+                    Some(named.get_or_insert_with(Vec::new))
+                } else if agents.iter().any(|name| name == "*") {
+                    Some(&mut wildcard)
+                } else {
+                    None
+                };
+                // An empty Disallow allows everything and adds no rule
+                if let Some(group) = group.filter(|_| !value.is_empty()) {
+                    group.push((field == "allow", value.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let rules = named.unwrap_or(wildcard);
+    rules
+        .iter()
+        .filter(|(_, pattern)| robots_pattern_matches(pattern, path))
+        .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+        .is_none_or(|(allow, _)| *allow)
+}
+
+/// Match a robots.txt path pattern with `*` wildcards and a `$` end anchor
+fn robots_pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut regex = String::from("^");
+    for (i, part) in pattern.split('*').enumerate() {
+        if i > 0 {
+            regex.push_str(".*");
+        }
+        regex.push_str(&regex::escape(part));
+    }
+    if anchored {
+        regex.push('$');
+    }
+    Regex::new(&regex).is_ok_and(|re| re.is_match(path))
+}
+
+/// Title and readable markdown of an HTML page
+///
+/// Boilerplate elements are dropped and, when the page marks its content
+/// with `<main>` or `<article>`, only that part is kept. Relative links are
+/// resolved against `base`.
+fn readable_markdown(html: &str, base: Option<&Url>) -> (Option<String>, String) {
+    let title = Regex::new(r"(?is)<title[^>]*>(.*?)</title>")
+        .unwrap()
+        .captures(html)
+        .map(|caps| inline_text(&caps[1]))
+        .filter(|title| !title.is_empty());
+
+    let mut content = Regex::new(r"(?s)<!--.*?-->")
+        .unwrap()
+        .replace_all(html, "")
+        .to_string();
+    for tag in BOILERPLATE_TAGS {
+        // The regex crate has no backreferences, so each element gets its own pattern
+        content = Regex::new(&format!(r"(?is)<{tag}\b[^>]*>.*?</{tag}\s*>"))
+            .unwrap()
+            .replace_all(&content, "")
+            .to_string();
+    }
+    for tag in ["main", "article", "body"] {
+        let region = Regex::new(&format!(r"(?is)<{tag}\b[^>]*>(.*)</{tag}\s*>"))
+            .unwrap()
+            .captures(&content)
+            .map(|caps| caps[1].to_string());
+        if let Some(region) = region {
+            content = region;
+            break;
+        }
+    }
+
+    // Code blocks keep their whitespace, so they are set aside until the end
+    let mut blocks = Vec::new();
+    content = Regex::new(r"(?is)<pre\b[^>]*>(.*?)</pre\s*>")
+        .unwrap()
+        .replace_all(&content, |caps: &regex::Captures| {
+            let code = strip_tags(&caps[1]);
+            let code = html_escape::decode_html_entities(&code);
+            blocks.push(format!("```\n{}\n```", code.trim_matches('\n')));
+            format!("\n\n\u{0}{}\u{0}\n\n", blocks.len() - 1)
+        })
+        .to_string();
+    content = Regex::new(r"\s+")
+        .unwrap()
+        .replace_all(&content, " ")
+        .to_string();
+
+    content = Regex::new(r#"(?is)<a\b[^>]*?href\s*=\s*["']([^"']*)["'][^>]*>(.*?)</a\s*>"#)
+        .unwrap()
+        .replace_all(&content, |caps: &regex::Captures| {
+            let text = inline_text(&caps[2]);
+            let href = html_escape::decode_html_entities(&caps[1]).to_string();
+            let target = match base {
+                Some(base) => base.join(&href).map(|url| url.to_string()).unwrap_or(href),
+                None => href,
+            };
+            if text.is_empty() || target.starts_with('#') || target.starts_with("javascript:") {
+                text
+            } else {
+                format!("[{}]({})", text, target)
+            }
+        })
+        .to_string();
+
+    for (pattern, replacement) in [
+        (r"(?is)<code\b[^>]*>(.*?)</code\s*>", "`$1`"),
+        (
+            r"(?is)<(?:strong|b)\b[^>]*>(.*?)</(?:strong|b)\s*>",
+            "**$1**",
+        ),
+        (r"(?is)<(?:em|i)\b[^>]*>(.*?)</(?:em|i)\s*>", "*$1*"),
+    ] {
+        content = Regex::new(pattern)
+            .unwrap()
+            .replace_all(&content, replacement)
+            .to_string();
+    }
+
+    content = Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]\s*>")
+        .unwrap()
+        .replace_all(&content, |caps: &regex::Captures| {
+            let level: usize = caps[1].parse().unwrap_or(1);
+            format!("\n\n{} {}\n\n", "#".repeat(level), inline_text(&caps[2]))
+        })
+        .to_string();
+
+    for (pattern, replacement) in [
+        (r"(?i)<li\b[^>]*>", "\n- "),
+        (r"(?i)<br\s*/?>", "\n"),
+        (r"(?i)</?(?:p|blockquote|table|ul|ol|dl)\b[^>]*>", "\n\n"),
+        (r"(?i)</?(?:div|section|tr|dt|dd)\b[^>]*>", "\n"),
+    ] {
+        content = Regex::new(pattern)
+            .unwrap()
+            .replace_all(&content, replacement)
+            .to_string();
+    }
+
+    content = strip_tags(&content);
+    content = html_escape::decode_html_entities(&content).to_string();
+
+    let spaces = Regex::new(r"[ \t\u{a0}]+").unwrap();
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| spaces.replace_all(line, " ").trim().to_string())
+        .collect();
+    content = Regex::new(r"\n{3,}")
+        .unwrap()
+        .replace_all(&lines.join("\n"), "\n\n")
+        .trim()
+        .to_string();
+
+    for (i, block) in blocks.iter().enumerate() {
+        content = content.replace(&format!("\u{0}{}\u{0}", i), block);
+    }
+
+    (title, content)
+}
+
+fn strip_tags(html: &str) -> String {
+    Regex::new(r"<[^>]*>")
+        .unwrap()
+        .replace_all(html, "")
+        .to_string()
+}
+
+/// Text of an inline fragment on a single line
+fn inline_text(html: &str) -> String {
+    let text = strip_tags(html);
+    let text = html_escape::decode_html_entities(&text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readable_markdown_drops_boilerplate() {
+        let html = r#"
+            <html>
+              <head><title>Serde &amp; You</title><script>track();</script></head>
+              <body>
+                <nav><a href="/">Home</a> <a href="/blog">Blog</a></nav>
+                <main>
+                  <h1>Getting <em>started</em></h1>
+                  <p>Add the <code>serde</code> crate, then read the
+                     <a href="derive.html">derive guide</a>.</p>
+                  <ul><li>Fast</li><li>Generic</li></ul>
+                  <pre><code>#[derive(Serialize)]
+struct Point {
+    x: i32,
+}</code></pre>
+                </main>
+                <footer>Copyright 2024</footer>
+              </body>
+            </html>
+        "#;
+        let base = Url::parse("https://serde.rs/guide/intro.html").unwrap();
+
+        let (title, content) = readable_markdown(html, Some(&base));
+
+        assert_eq!(title.as_deref(), Some("Serde & You"));
+        assert!(content.starts_with("# Getting *started*"));
+        assert!(content
+            .contains("Add the `serde` crate, then read the [derive guide](https://serde.rs/guide/derive.html)."));
+        assert!(content.contains("- Fast\n- Generic"));
+        assert!(content.contains("```\n#[derive(Serialize)]\nstruct Point {\n    x: i32,\n}\n```"));
+        assert!(!content.contains("Home"));
+        assert!(!content.contains("track()"));
+        assert!(!content.contains("Copyright"));
+    }
+
+    #[test]
+    fn test_domain_policy() {
+        let config = WebFetchConfig {
+            allowed_domains: vec!["rust-lang.org".to_string(), "*.docs.rs".to_string()],
+            denied_domains: vec!["internal.rust-lang.org".to_string()],
+            ..WebFetchConfig::default()
+        };
+
+        assert!(check_domain(&config, "rust-lang.org").is_ok());
+        assert!(check_domain(&config, "doc.rust-lang.org").is_ok());
+        assert!(check_domain(&config, "Serde.Docs.RS").is_ok());
+        assert!(check_domain(&config, "wiki.internal.rust-lang.org").is_err());
+        assert!(check_domain(&config, "notrust-lang.org").is_err());
+        assert!(check_domain(&config, "example.com").is_err());
+        assert!(check_domain(&WebFetchConfig::default(), "example.com").is_ok());
+    }
+
+    #[test]
+    fn test_robots_rules() {
+        let robots = "
+            User-agent: *
+            Disallow: /private/
+            Allow: /private/readme.html
+            Disallow: /*.pdf$
+
+            User-agent: spec-ai-core
+            User-agent: otherbot
+            Disallow: /drafts # not ready
+        ";
+
+        // Only the group naming the agent applies to it
+        assert!(robots_allows(robots, "spec-ai-core", "/private/keys"));
+        assert!(!robots_allows(robots, "spec-ai-core", "/drafts/next"));
+        assert!(!robots_allows(robots, "curl", "/private/keys"));
+        assert!(robots_allows(robots, "curl", "/private/readme.html"));
+        assert!(!robots_allows(robots, "curl", "/manual.pdf"));
+        assert!(robots_allows(robots, "curl", "/manual.pdf?page=2"));
+        assert!(robots_allows(robots, "curl", "/drafts/next"));
+        assert!(robots_allows(
+            "User-agent: *\nDisallow:",
+            "curl",
+            "/anything"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_urls_outside_policy() {
+        let tool = WebFetchTool::with_config(WebFetchConfig {
+            denied_domains: vec!["example.com".to_string()],
+            ..WebFetchConfig::default()
+        });
+
+        let denied = tool
+            .execute(serde_json::json!({ "url": "https://www.example.com/docs" }))
+            .await
+            .unwrap();
+        assert!(!denied.success);
+        assert!(denied.error.unwrap().contains("denied"));
+
+        let file = tool
+            .execute(serde_json::json!({ "url": "file:///etc/passwd" }))
+            .await
+            .unwrap();
+        assert!(!file.success);
+        assert!(file.error.unwrap().contains("scheme"));
+    }
+}
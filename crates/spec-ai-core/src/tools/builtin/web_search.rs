@@ -518,6 +518,10 @@ impl Tool for WebSearchTool {
         "web_search"
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         "Performs web searches and returns titles, URLs, and snippets (Brave Search if API key configured, otherwise DuckDuckGo)"
     }
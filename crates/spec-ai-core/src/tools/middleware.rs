@@ -0,0 +1,427 @@
+//! Hooks around tool execution
+//!
+//! Middleware registered on a [`ToolRegistry`](super::ToolRegistry) sees
+//! every call the registry runs: `before` hooks in registration order, then
+//! the tool, then `after` hooks in reverse order. A `before` hook can answer
+//! the call itself, in which case the tool and later middleware are skipped
+//! and only the `after` hooks of middleware that already ran see the result.
+//! Redaction is separate: it never changes what the tool receives, only the
+//! arguments that are logged or recorded.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use super::ToolResult;
+use crate::config::ToolsConfig;
+use crate::persistence::Persistence;
+use crate::types::NodeType;
+
+/// Placeholder written in place of redacted argument values
+pub const REDACTED: &str = "[redacted]";
+
+/// One tool call passing through the middleware
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub tool: String,
+    /// Arguments the tool receives
+    pub args: Value,
+    /// Arguments as they may be logged, with secrets redacted
+    pub recorded_args: Value,
+    /// Session the call belongs to, when the caller said
+    pub session_id: Option<String>,
+    pub started: Instant,
+}
+
+/// A cross-cutting concern applied to every tool call
+#[async_trait]
+pub trait ToolMiddleware: Send + Sync {
+    /// Rewrite the arguments of a call before they are logged; the tool
+    /// still receives them as they were
+    fn redact(&self, _tool: &str, _args: &mut Value) {}
+
+    /// Inspect or rewrite a call before it runs; returning a result answers
+    /// the call without running the tool
+    async fn before(&self, _call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        Ok(None)
+    }
+
+    /// Inspect or rewrite the result of a call
+    ///
+    /// Not called when the tool fails to execute at all; its error goes
+    /// straight back to the caller.
+    async fn after(&self, _call: &ToolCall, _result: &mut ToolResult) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The middleware `config` asks for, in the order they should run
+pub fn from_config(
+    config: &ToolsConfig,
+    persistence: Option<Arc<Persistence>>,
+) -> Vec<Arc<dyn ToolMiddleware>> {
+    let mut middleware: Vec<Arc<dyn ToolMiddleware>> = Vec::new();
+    if !config.redact_arguments.is_empty() {
+        middleware.push(Arc::new(RedactArguments::new(
+            config.redact_arguments.iter().cloned(),
+        )));
+    }
+    // Before the rate limit, so refused calls are recorded too
+    match persistence {
+        Some(persistence) if config.log_to_graph => {
+            middleware.push(Arc::new(GraphLog::new(persistence)));
+        }
+        _ => {}
+    }
+    if !config.rate_limits.is_empty() {
+        let mut limiter = RateLimit::new();
+        for (tool, limit) in &config.rate_limits {
+            limiter = limiter.with_limit(
+                tool.clone(),
+                limit.max_calls,
+                Duration::from_secs(limit.per_secs),
+            );
+        }
+        middleware.push(Arc::new(limiter));
+    }
+    if config.max_output_chars > 0 || !config.output_limits.is_empty() {
+        let mut truncate = TruncateOutput::new(config.max_output_chars);
+        for (tool, limit) in &config.output_limits {
+            truncate = truncate.with_limit(tool.clone(), *limit);
+        }
+        middleware.push(Arc::new(truncate));
+    }
+    middleware
+}
+
+/// Replaces the values of secret-looking arguments, at any depth, when they
+/// are logged
+pub struct RedactArguments {
+    keys: Vec<String>,
+}
+
+impl RedactArguments {
+    /// Redact arguments named any of `keys`, ignoring case
+    pub fn new(keys: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            keys: keys.into_iter().map(|key| key.to_lowercase()).collect(),
+        }
+    }
+
+    fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self.keys.contains(&key.to_lowercase()) {
+                        *value = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact_value(value);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+            _ => {}
+        }
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for RedactArguments {
+    fn redact(&self, _tool: &str, args: &mut Value) {
+        self.redact_value(args);
+    }
+}
+
+/// Records every call as a `ToolResult` node in its session's knowledge graph
+pub struct GraphLog {
+    persistence: Arc<Persistence>,
+}
+
+impl GraphLog {
+    pub fn new(persistence: Arc<Persistence>) -> Self {
+        Self { persistence }
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for GraphLog {
+    async fn after(&self, call: &ToolCall, result: &mut ToolResult) -> Result<()> {
+        let Some(session_id) = &call.session_id else {
+            return Ok(());
+        };
+        let mut properties = json!({
+            "tool": call.tool,
+            "arguments": call.recorded_args,
+            "success": result.success,
+            "error": result.error,
+            "output_chars": result.output.chars().count(),
+            "duration_ms": call.started.elapsed().as_millis() as u64,
+            "timestamp": Utc::now().to_rfc3339(),
+        });
+        if let Some(data) = &result.data {
+            properties["data"] = data.clone();
+        }
+        // A failed write loses a log entry, not the call
+        if let Err(err) = self.persistence.insert_graph_node(
+            session_id,
+            NodeType::ToolResult,
+            "ToolInvocation",
+            &properties,
+            None,
+        ) {
+            warn!(
+                "Failed to log call of '{}' to the graph: {}",
+                call.tool, err
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Refuses calls of a tool beyond a number per sliding window
+pub struct RateLimit {
+    limits: HashMap<String, (u32, Duration)>,
+    calls: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimit {
+    pub fn new() -> Self {
+        Self {
+            limits: HashMap::new(),
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Allow `tool` at most `max_calls` calls in any `window`
+    pub fn with_limit(mut self, tool: impl Into<String>, max_calls: u32, window: Duration) -> Self {
+        self.limits.insert(tool.into(), (max_calls, window));
+        self
+    }
+
+    /// Charge a call of `tool` made at `now`, or say how long until one is allowed
+    fn admit(&self, tool: &str, now: Instant) -> std::result::Result<(), Duration> {
+        let Some(&(max_calls, window)) = self.limits.get(tool) else {
+            return Ok(());
+        };
+        let mut calls = self.calls.lock().unwrap();
+        let recent = calls.entry(tool.to_string()).or_default();
+        while recent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= window)
+        {
+            recent.pop_front();
+        }
+        if recent.len() >= max_calls as usize {
+            let oldest = recent.front().copied().unwrap_or(now);
+            return Err(window.saturating_sub(now.duration_since(oldest)));
+        }
+        recent.push_back(now);
+        Ok(())
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for RateLimit {
+    async fn before(&self, call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        Ok(self.admit(&call.tool, call.started).err().map(|wait| {
+            let (max_calls, window) = self.limits[&call.tool];
+            ToolResult::failure(format!(
+                "Tool '{}' is limited to {} calls per {}s; try again in {}s",
+                call.tool,
+                max_calls,
+                window.as_secs(),
+                wait.as_secs().max(1)
+            ))
+        }))
+    }
+}
+
+/// Cuts tool output down to a number of characters
+pub struct TruncateOutput {
+    default: usize,
+    limits: HashMap<String, usize>,
+}
+
+impl TruncateOutput {
+    /// Keep at most `default` characters of output; 0 keeps output whole
+    pub fn new(default: usize) -> Self {
+        Self {
+            default,
+            limits: HashMap::new(),
+        }
+    }
+
+    /// Use `limit` instead of the default for `tool`
+    pub fn with_limit(mut self, tool: impl Into<String>, limit: usize) -> Self {
+        self.limits.insert(tool.into(), limit);
+        self
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for TruncateOutput {
+    async fn after(&self, call: &ToolCall, result: &mut ToolResult) -> Result<()> {
+        let limit = self.limits.get(&call.tool).copied().unwrap_or(self.default);
+        if limit == 0 {
+            return Ok(());
+        }
+        if let Some((cut, _)) = result.output.char_indices().nth(limit) {
+            let dropped = result.output[cut..].chars().count();
+            result.output.truncate(cut);
+            result
+                .output
+                .push_str(&format!("\n...[truncated {} characters]", dropped));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Tool, ToolRegistry};
+
+    struct EchoArgs;
+
+    #[async_trait]
+    impl Tool for EchoArgs {
+        fn name(&self) -> &str {
+            "echo_args"
+        }
+
+        fn description(&self) -> &str {
+            "Returns its arguments"
+        }
+
+        fn parameters(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, args: Value) -> Result<ToolResult> {
+            Ok(ToolResult::success(args.to_string()))
+        }
+    }
+
+    /// Appends its name to the output, to show the order of `after` hooks
+    struct Mark(&'static str);
+
+    #[async_trait]
+    impl ToolMiddleware for Mark {
+        async fn after(&self, _call: &ToolCall, result: &mut ToolResult) -> Result<()> {
+            result.output.push_str(self.0);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn after_hooks_run_in_reverse_order() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoArgs));
+        registry.set_middleware(vec![Arc::new(Mark("a")), Arc::new(Mark("b"))]);
+
+        let result = registry.execute("echo_args", json!(1)).await.unwrap();
+        assert_eq!(result.output, "1ba");
+    }
+
+    #[tokio::test]
+    async fn redaction_changes_only_recorded_arguments() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoArgs));
+        registry.set_middleware(vec![Arc::new(RedactArguments::new([
+            "password".to_string()
+        ]))]);
+
+        let args = json!({"user": "ada", "auth": {"Password": "hunter2"}});
+        let result = registry.execute("echo_args", args.clone()).await.unwrap();
+        assert!(result.output.contains("hunter2"));
+        assert_eq!(
+            registry.redact("echo_args", &args),
+            json!({"user": "ada", "auth": {"Password": REDACTED}})
+        );
+    }
+
+    #[tokio::test]
+    async fn rate_limit_refuses_calls_beyond_the_window() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoArgs));
+        registry.set_middleware(vec![Arc::new(RateLimit::new().with_limit(
+            "echo_args",
+            2,
+            Duration::from_secs(60),
+        ))]);
+
+        for _ in 0..2 {
+            assert!(
+                registry
+                    .execute("echo_args", json!(1))
+                    .await
+                    .unwrap()
+                    .success
+            );
+        }
+        let refused = registry.execute("echo_args", json!(1)).await.unwrap();
+        assert!(!refused.success);
+        assert!(refused.error.unwrap().contains("2 calls per 60s"));
+    }
+
+    #[test]
+    fn rate_limit_window_slides() {
+        let limiter = RateLimit::new().with_limit("t", 1, Duration::from_secs(10));
+        let start = Instant::now();
+        assert!(limiter.admit("t", start).is_ok());
+        assert_eq!(
+            limiter.admit("t", start + Duration::from_secs(4)),
+            Err(Duration::from_secs(6))
+        );
+        assert!(limiter.admit("t", start + Duration::from_secs(10)).is_ok());
+        assert!(limiter.admit("other", start).is_ok());
+    }
+
+    #[tokio::test]
+    async fn output_is_truncated_per_tool() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoArgs));
+        registry.set_middleware(vec![Arc::new(
+            TruncateOutput::new(0).with_limit("echo_args", 4),
+        )]);
+
+        let result = registry.execute("echo_args", json!("héllo")).await.unwrap();
+        assert_eq!(result.output, "\"hél\n...[truncated 3 characters]");
+    }
+
+    #[tokio::test]
+    async fn calls_are_logged_to_the_session_graph() {
+        let persistence = Arc::new(Persistence::in_memory().unwrap());
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoArgs));
+        registry.set_middleware(from_config(
+            &ToolsConfig {
+                log_to_graph: true,
+                ..ToolsConfig::default()
+            },
+            Some(persistence.clone()),
+        ));
+
+        registry
+            .execute_in_session("s1", "echo_args", json!({"token": "abc"}))
+            .await
+            .unwrap();
+        let nodes = persistence
+            .list_graph_nodes("s1", Some(NodeType::ToolResult), None)
+            .unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].properties["tool"], "echo_args");
+        assert_eq!(nodes[0].properties["arguments"]["token"], REDACTED);
+    }
+}
@@ -1,29 +1,51 @@
 pub mod builtin;
+pub mod middleware;
 pub mod plugin_adapter;
+pub mod sandbox;
+pub mod schema;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use self::builtin::{
-    AudioTranscriptionTool, BashTool, CodeSearchTool, EchoTool, FileExtractTool, FileReadTool,
-    FileWriteTool, GenerateCodeTool, GraphTool, GrepTool, MathTool, PromptUserTool, RgTool,
-    SearchTool, ShellTool,
+    ApplyPatchTool, AskUserTool, AudioTranscriptionTool, BashTool, CodeSearchTool, EchoTool,
+    FileReadTool, FileWriteTool, GenerateCodeTool, GraphTool, GrepTool, MathTool, PromptUserTool,
+    RgTool, SearchTool, ShellTool, SpawnAgentsTool, TaskLogsTool, TaskStartTool, TaskStatusTool,
+    TaskStopTool,
 };
 
+#[cfg(feature = "file-extract")]
+use self::builtin::FileExtractTool;
+
 #[cfg(feature = "api")]
 use self::builtin::WebSearchTool;
 
 #[cfg(feature = "web-scraping")]
 use self::builtin::WebScraperTool;
+
+#[cfg(feature = "reqwest")]
+use self::builtin::WebFetchTool;
+
+#[cfg(feature = "watch")]
+use self::builtin::{UnwatchPathTool, WatchPathTool};
+
+#[cfg(feature = "git")]
+use self::builtin::{GitBlameTool, GitBranchTool, GitCommitTool, GitDiffTool, GitStatusTool};
 use crate::agent::model::ModelProvider;
 use crate::embeddings::EmbeddingsClient;
+use crate::events::{self, Event, EventKind};
 use crate::persistence::Persistence;
+use crate::policy::{PolicyDecision, PolicyEngine};
+use spec_ai_plugin::{Trust, TrustStore};
 
+pub use middleware::{ToolCall, ToolMiddleware};
 pub use plugin_adapter::PluginToolAdapter;
 
 #[cfg(feature = "openai")]
@@ -38,6 +60,10 @@ pub struct ToolResult {
     pub output: String,
     /// Error message if execution failed
     pub error: Option<String>,
+    /// Structured payload for code rather than the model, matching the
+    /// tool's [`Tool::output_schema`] when it declares one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
 }
 
 impl ToolResult {
@@ -47,6 +73,7 @@ impl ToolResult {
             success: true,
             output: output.into(),
             error: None,
+            data: None,
         }
     }
 
@@ -56,8 +83,22 @@ impl ToolResult {
             success: false,
             output: String::new(),
             error: Some(error.into()),
+            data: None,
         }
     }
+
+    /// Attach a structured payload
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// The payload as `T`, if there is one
+    pub fn data_as<T: DeserializeOwned>(&self) -> Option<Result<T>> {
+        self.data
+            .clone()
+            .map(|data| serde_json::from_value(data).map_err(Into::into))
+    }
 }
 
 /// Trait for all tools that can be executed by the agent
@@ -72,13 +113,32 @@ pub trait Tool: Send + Sync {
     /// JSON Schema describing the tool's parameters
     fn parameters(&self) -> Value;
 
+    /// JSON Schema of the [`ToolResult::data`] the tool returns, if it
+    /// returns any
+    ///
+    /// The registry drops payloads that don't match it, so consumers can
+    /// rely on the shape.
+    fn output_schema(&self) -> Option<Value> {
+        None
+    }
+
+    /// Whether calls only read and change nothing, so several of them from
+    /// one model turn may run at the same time
+    ///
+    /// Calls to other tools run one at a time, in the order the model asked.
+    fn read_only(&self) -> bool {
+        false
+    }
+
     /// Execute the tool with the given arguments
     async fn execute(&self, args: Value) -> Result<ToolResult>;
 }
 
 /// Registry for managing and executing tools
+#[derive(Clone)]
 pub struct ToolRegistry {
     tools: HashMap<String, Arc<dyn Tool>>,
+    middleware: Vec<Arc<dyn ToolMiddleware>>,
 }
 
 impl ToolRegistry {
@@ -86,6 +146,7 @@ impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: HashMap::new(),
+            middleware: Vec::new(),
         }
     }
 
@@ -105,15 +166,23 @@ impl ToolRegistry {
         registry.register(Arc::new(EchoTool::new()));
         registry.register(Arc::new(MathTool::new()));
         registry.register(Arc::new(FileReadTool::new()));
+        #[cfg(feature = "file-extract")]
         registry.register(Arc::new(FileExtractTool::new()));
         registry.register(Arc::new(FileWriteTool::new()));
+        registry.register(Arc::new(ApplyPatchTool::new()));
         registry.register(Arc::new(PromptUserTool::new()));
+        registry.register(Arc::new(AskUserTool::new()));
         registry.register(Arc::new(SearchTool::new()));
         registry.register(Arc::new(GrepTool::new()));
         registry.register(Arc::new(RgTool::new()));
         registry.register(Arc::new(CodeSearchTool::new()));
         registry.register(Arc::new(BashTool::new()));
         registry.register(Arc::new(ShellTool::new()));
+        registry.register(Arc::new(TaskStartTool::new()));
+        registry.register(Arc::new(TaskStatusTool::new()));
+        registry.register(Arc::new(TaskLogsTool::new()));
+        registry.register(Arc::new(TaskStopTool::new()));
+        registry.register(Arc::new(SpawnAgentsTool::new()));
         if let Some(provider) = code_model_provider {
             registry.register(Arc::new(GenerateCodeTool::new(provider)));
         }
@@ -126,6 +195,31 @@ impl ToolRegistry {
         #[cfg(feature = "web-scraping")]
         registry.register(Arc::new(WebScraperTool::new()));
 
+        // Register web fetch when an HTTP client is available, caching pages
+        // in persistence if there is one
+        #[cfg(feature = "reqwest")]
+        registry.register(Arc::new(match &persistence {
+            Some(persistence) => WebFetchTool::new().with_persistence(persistence.clone()),
+            None => WebFetchTool::new(),
+        }));
+
+        // Register the file watch tools if feature is enabled
+        #[cfg(feature = "watch")]
+        {
+            registry.register(Arc::new(WatchPathTool::new()));
+            registry.register(Arc::new(UnwatchPathTool::new()));
+        }
+
+        // Register the git tools if feature is enabled
+        #[cfg(feature = "git")]
+        {
+            registry.register(Arc::new(GitStatusTool::new()));
+            registry.register(Arc::new(GitDiffTool::new()));
+            registry.register(Arc::new(GitCommitTool::new()));
+            registry.register(Arc::new(GitBranchTool::new()));
+            registry.register(Arc::new(GitBlameTool::new()));
+        }
+
         if let Some(persistence) = persistence {
             registry.register(Arc::new(GraphTool::new(persistence.clone())));
             registry.register(Arc::new(AudioTranscriptionTool::with_persistence(
@@ -164,26 +258,105 @@ impl ToolRegistry {
         self.tools.contains_key(name)
     }
 
+    /// Hash of the names, descriptions and parameters of all tools, which
+    /// changes whenever the tools offered to a model do
+    pub fn fingerprint(&self) -> String {
+        let mut tools: Vec<&Arc<dyn Tool>> = self.tools.values().collect();
+        tools.sort_by(|a, b| a.name().cmp(b.name()));
+        let mut hasher = blake3::Hasher::new();
+        for tool in tools {
+            for part in [
+                tool.name().to_string(),
+                tool.description().to_string(),
+                tool.parameters().to_string(),
+            ] {
+                hasher.update(&(part.len() as u64).to_le_bytes());
+                hasher.update(part.as_bytes());
+            }
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// Replace the middleware every call passes through, in the order their
+    /// `before` hooks run
+    pub fn set_middleware(&mut self, middleware: Vec<Arc<dyn ToolMiddleware>>) {
+        self.middleware = middleware;
+    }
+
+    /// `args` of a call to `name` as they may be logged, with secrets redacted
+    pub fn redact(&self, name: &str, args: &Value) -> Value {
+        let mut args = args.clone();
+        for middleware in &self.middleware {
+            middleware.redact(name, &mut args);
+        }
+        args
+    }
+
     /// Execute a tool by name with the given arguments
     pub async fn execute(&self, name: &str, args: Value) -> Result<ToolResult> {
+        self.run(None, name, args).await
+    }
+
+    /// Execute a tool for `session_id`, which middleware such as graph
+    /// logging record the call under
+    pub async fn execute_in_session(
+        &self,
+        session_id: &str,
+        name: &str,
+        args: Value,
+    ) -> Result<ToolResult> {
+        self.run(Some(session_id), name, args).await
+    }
+
+    async fn run(&self, session_id: Option<&str>, name: &str, args: Value) -> Result<ToolResult> {
         let tool = self
             .get(name)
             .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", name))?;
 
-        debug!("Executing tool '{}'", name);
-        let result = tool.execute(args).await;
-        match &result {
-            Ok(res) => {
-                debug!(
-                    "Tool '{}' completed: success={}, error={:?}",
-                    name, res.success, res.error
-                );
+        let mut call = ToolCall {
+            tool: name.to_string(),
+            recorded_args: self.redact(name, &args),
+            args,
+            session_id: session_id.map(str::to_string),
+            started: std::time::Instant::now(),
+        };
+        let mut entered = 0;
+        let mut answered = None;
+        for middleware in &self.middleware {
+            entered += 1;
+            if let Some(result) = middleware.before(&mut call).await? {
+                answered = Some(result);
+                break;
+            }
+        }
+
+        let mut result = match answered {
+            Some(result) => {
+                debug!("Tool '{}' answered by middleware", name);
+                result
             }
-            Err(err) => {
-                debug!("Tool '{}' failed to execute: {}", name, err);
+            None => {
+                debug!("Executing tool '{}'", name);
+                match tool.execute(call.args.clone()).await {
+                    Ok(mut res) => {
+                        debug!(
+                            "Tool '{}' completed: success={}, error={:?}",
+                            name, res.success, res.error
+                        );
+                        check_data(tool.as_ref(), &mut res);
+                        res
+                    }
+                    Err(err) => {
+                        debug!("Tool '{}' failed to execute: {}", name, err);
+                        return Err(err);
+                    }
+                }
             }
+        };
+        for middleware in self.middleware[..entered].iter().rev() {
+            middleware.after(&call, &mut result).await?;
         }
-        result
+        Ok(result)
     }
 
     /// Get the number of registered tools
@@ -198,23 +371,36 @@ impl ToolRegistry {
 
     /// Load plugins from a directory and register their tools
     ///
+    /// Libraries signed by a key in `trust` load; unsigned or untrusted ones
+    /// load only when `policy` allows `agent` the `plugin_load` action on the
+    /// library's path. Each decision is logged and emitted as a `plugin_load`
+    /// event.
+    ///
     /// # Arguments
     /// * `dir` - Directory containing plugin libraries
     /// * `allow_override` - Whether plugins can override built-in tools
+    /// * `trust` - Publisher keys whose signatures are accepted
+    /// * `policy` - Policy consulted for libraries no trusted key signed
+    /// * `agent` - Agent the policy is checked for
     ///
     /// # Returns
     /// Statistics about the loading process
     pub fn load_plugins(
         &mut self,
-        dir: &std::path::Path,
+        dir: &Path,
         allow_override: bool,
+        trust: &TrustStore,
+        policy: &PolicyEngine,
+        agent: &str,
     ) -> anyhow::Result<spec_ai_plugin::LoadStats> {
         use spec_ai_plugin::{expand_tilde, PluginLoader};
 
         let expanded_dir = expand_tilde(dir);
 
         let mut loader = PluginLoader::new();
-        let stats = loader.load_directory(&expanded_dir)?;
+        let stats = loader.load_directory_trusted(&expanded_dir, trust, |path, trust| {
+            admit_plugin(path, trust, policy, agent)
+        })?;
 
         // Register tools from plugins
         for (tool_ref, plugin_name) in loader.all_tools() {
@@ -276,6 +462,41 @@ impl ToolRegistry {
             })
             .collect()
     }
+
+    /// Convert all tools in the registry to Anthropic tool definitions
+    #[cfg(feature = "anthropic")]
+    pub fn to_anthropic_tools(&self) -> Vec<crate::agent::providers::anthropic::Tool> {
+        use crate::agent::providers::anthropic::Tool as AnthropicTool;
+
+        self.tools
+            .values()
+            .map(|tool| AnthropicTool::new(tool.name(), tool.description(), &tool.parameters()))
+            .collect()
+    }
+
+    /// Convert all tools in the registry to Bedrock tool specifications
+    #[cfg(feature = "bedrock")]
+    pub fn to_bedrock_tools(&self) -> Vec<crate::agent::providers::bedrock::ToolSpec> {
+        use crate::agent::providers::bedrock::ToolSpec;
+
+        self.tools
+            .values()
+            .map(|tool| ToolSpec::new(tool.name(), tool.description(), &tool.parameters()))
+            .collect()
+    }
+
+    /// Convert all tools in the registry to Gemini function declarations
+    #[cfg(feature = "gemini")]
+    pub fn to_gemini_tools(&self) -> Vec<crate::agent::providers::gemini::FunctionDeclaration> {
+        use crate::agent::providers::gemini::FunctionDeclaration;
+
+        self.tools
+            .values()
+            .map(|tool| {
+                FunctionDeclaration::new(tool.name(), tool.description(), &tool.parameters())
+            })
+            .collect()
+    }
 }
 
 impl Default for ToolRegistry {
@@ -284,6 +505,79 @@ impl Default for ToolRegistry {
     }
 }
 
+/// Drop a payload that doesn't match the schema `tool` declares
+///
+/// The prose output still reaches the model; only code relying on the
+/// payload's shape loses it.
+fn check_data(tool: &dyn Tool, result: &mut ToolResult) {
+    let (Some(data), Some(schema)) = (&result.data, tool.output_schema()) else {
+        return;
+    };
+    if let Err(err) = schema::validate(&schema, data) {
+        warn!(
+            "Dropping payload of '{}' that doesn't match its output schema: {}",
+            tool.name(),
+            err
+        );
+        result.data = None;
+    }
+}
+
+/// Policy action that lets a plugin library no trusted key signed load
+const PLUGIN_LOAD_ACTION: &str = "plugin_load";
+
+/// Whether the plugin library at `path` may load, given how far it is trusted
+///
+/// Verified libraries always load; the rest need `policy` to allow
+/// [`PLUGIN_LOAD_ACTION`] on their path.
+fn admit_plugin(path: &Path, trust: &Trust, policy: &PolicyEngine, agent: &str) -> bool {
+    let resource = path.display().to_string();
+    let refusal = match trust {
+        Trust::Verified { .. } => None,
+        _ => match policy.check(agent, PLUGIN_LOAD_ACTION, &resource) {
+            PolicyDecision::Allow => None,
+            PolicyDecision::Deny(reason) => Some(reason),
+        },
+    };
+
+    match (trust, &refusal) {
+        (Trust::Verified { .. }, _) => tracing::info!("Plugin {} is {}", resource, trust),
+        (_, None) => tracing::warn!(
+            "Loading plugin {} ({}) because policy allows {}",
+            resource,
+            trust,
+            PLUGIN_LOAD_ACTION
+        ),
+        (_, Some(reason)) => tracing::error!(
+            "Refusing to load plugin {} ({}): {}",
+            resource,
+            trust,
+            reason
+        ),
+    }
+
+    if events::enabled() {
+        events::emit(
+            Event::new(EventKind::PluginLoad {
+                path: resource,
+                trust: trust.as_str().to_string(),
+                key: match trust {
+                    Trust::Verified { key } => Some(key.clone()),
+                    _ => None,
+                },
+                detail: match trust {
+                    Trust::Untrusted(detail) => Some(detail.clone()),
+                    _ => None,
+                },
+                allowed: refusal.is_none(),
+                rule: refusal.clone(),
+            })
+            .with_agent(Some(agent)),
+        );
+    }
+    refusal.is_none()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -312,6 +606,61 @@ mod tests {
         }
     }
 
+    /// Returns the `data` argument as its payload, declaring a count
+    struct CountTool;
+
+    #[async_trait]
+    impl Tool for CountTool {
+        fn name(&self) -> &str {
+            "count"
+        }
+
+        fn description(&self) -> &str {
+            "Counts things"
+        }
+
+        fn parameters(&self) -> Value {
+            serde_json::json!({ "type": "object", "properties": {} })
+        }
+
+        fn output_schema(&self) -> Option<Value> {
+            Some(serde_json::json!({
+                "type": "object",
+                "properties": { "count": { "type": "integer" } },
+                "required": ["count"]
+            }))
+        }
+
+        async fn execute(&self, args: Value) -> Result<ToolResult> {
+            Ok(ToolResult::success("counted").with_data(args["data"].clone()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_payloads_must_match_the_output_schema() {
+        #[derive(Deserialize)]
+        struct Count {
+            count: u32,
+        }
+
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(CountTool));
+
+        let args = serde_json::json!({ "data": { "count": 3 } });
+        let result = registry.execute("count", args).await.unwrap();
+        let count: Count = result.data_as().unwrap().unwrap();
+        assert_eq!(count.count, 3);
+
+        let args = serde_json::json!({ "data": { "count": "three" } });
+        let result = registry.execute("count", args).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.output, "counted");
+        assert!(result.data.is_none());
+
+        let json = serde_json::to_value(ToolResult::success("plain")).unwrap();
+        assert!(json.get("data").is_none());
+    }
+
     #[tokio::test]
     async fn test_register_and_get_tool() {
         let mut registry = ToolRegistry::new();
@@ -351,6 +700,41 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_unverified_plugins_need_policy() {
+        use crate::policy::{PolicyEffect, PolicyRule};
+
+        let path = Path::new("/plugins/libweather.so");
+        let verified = Trust::Verified {
+            key: "key".to_string(),
+        };
+        let mut policy = PolicyEngine::new();
+        policy.add_rule(PolicyRule {
+            agent: "*".to_string(),
+            action: "tool_call".to_string(),
+            resource: "*".to_string(),
+            effect: PolicyEffect::Allow,
+        });
+
+        assert!(admit_plugin(path, &verified, &policy, "coder"));
+        assert!(!admit_plugin(path, &Trust::Unsigned, &policy, "coder"));
+        assert!(!admit_plugin(
+            path,
+            &Trust::Untrusted("bad signature".to_string()),
+            &policy,
+            "coder"
+        ));
+
+        policy.add_rule(PolicyRule {
+            agent: "coder".to_string(),
+            action: PLUGIN_LOAD_ACTION.to_string(),
+            resource: "/plugins/*".to_string(),
+            effect: PolicyEffect::Allow,
+        });
+        assert!(admit_plugin(path, &Trust::Unsigned, &policy, "coder"));
+        assert!(!admit_plugin(path, &Trust::Unsigned, &policy, "reviewer"));
+    }
+
     #[tokio::test]
     async fn test_tool_result_success() {
         let result = ToolResult::success("test output");
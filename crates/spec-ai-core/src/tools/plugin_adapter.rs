@@ -76,6 +76,7 @@ impl Tool for PluginToolAdapter {
             success: result.success,
             output: result.output.to_string(),
             error: result.error.map(|e| e.to_string()).into_option(),
+            data: None,
         })
     }
 }
@@ -105,6 +106,7 @@ mod tests {
             success: plugin_result.success,
             output: plugin_result.output.to_string(),
             error: plugin_result.error.map(|e| e.to_string()).into_option(),
+            data: None,
         };
         assert!(result.success);
         assert_eq!(result.output, "test output");
@@ -116,6 +118,7 @@ mod tests {
             success: plugin_result.success,
             output: plugin_result.output.to_string(),
             error: plugin_result.error.map(|e| e.to_string()).into_option(),
+            data: None,
         };
         assert!(!result.success);
         assert_eq!(result.error, Some("test error".to_string()));
@@ -0,0 +1,537 @@
+//! Isolation and resource limits for commands run by tools
+//!
+//! A [`Sandbox`] wraps a shell command in bubblewrap on Linux or
+//! sandbox-exec on macOS, so it can only write the allowed paths, can read
+//! only the allowed paths when reads are restricted, and has no network
+//! unless allowed. CPU time and address space are capped with `ulimit` in
+//! every backend, including `rlimit`, which applies only the limits.
+//!
+//! Paths come from `[sandbox]` and from policy rules allowing `file_read`
+//! or `file_write` for the agent. Commands that hit a limit or the sandbox
+//! walls are reported as a [`SandboxViolation`].
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::config::SandboxConfig;
+use crate::policy::PolicyEngine;
+
+/// Signal sent to a process that used up its CPU time
+const SIGXCPU: i32 = 24;
+
+/// Readable in every sandbox so shells and common tools start
+const SYSTEM_READ_PATHS: &[&str] = &[
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/etc",
+    "/opt/homebrew",
+    "/System",
+    "/Library",
+    "/private/etc",
+    "/private/var/db",
+];
+
+/// Writable in a sandbox-exec sandbox so commands can use temporary files
+const MACOS_WRITE_PATHS: &[&str] = &["/private/tmp", "/private/var/folders"];
+
+const MEMORY_ERRORS: &[&str] = &[
+    "Cannot allocate memory",
+    "memory exhausted",
+    "Out of memory",
+    "out of memory",
+    "MemoryError",
+];
+
+const NETWORK_ERRORS: &[&str] = &[
+    "Network is unreachable",
+    "Could not resolve host",
+    "Temporary failure in name resolution",
+    "Name or service not known",
+    "nodename nor servname",
+];
+
+/// How commands are confined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SandboxBackend {
+    /// bubblewrap at the given path (Linux)
+    Bubblewrap(PathBuf),
+    /// The built-in sandbox-exec (macOS)
+    SandboxExec,
+    /// Resource limits only, without filesystem or network isolation
+    Rlimit,
+}
+
+impl SandboxBackend {
+    /// Resolve a backend name from the config, checking it is installed
+    pub fn resolve(name: &str) -> Result<Self> {
+        let sandbox_exec = Path::new("/usr/bin/sandbox-exec");
+        match name {
+            "bubblewrap" => match find_in_path("bwrap") {
+                Some(path) => Ok(SandboxBackend::Bubblewrap(path)),
+                None => bail!("sandbox.backend is bubblewrap, but bwrap is not on PATH"),
+            },
+            "sandbox-exec" if sandbox_exec.exists() => Ok(SandboxBackend::SandboxExec),
+            "sandbox-exec" => bail!("sandbox.backend is sandbox-exec, which needs macOS"),
+            "rlimit" => Ok(SandboxBackend::Rlimit),
+            "auto" => {
+                if let Some(path) = find_in_path("bwrap") {
+                    Ok(SandboxBackend::Bubblewrap(path))
+                } else if sandbox_exec.exists() {
+                    Ok(SandboxBackend::SandboxExec)
+                } else {
+                    bail!(
+                        "No sandbox is available for the bash tool: install bubblewrap, \
+                         or set sandbox.backend = \"rlimit\" for resource limits alone"
+                    )
+                }
+            }
+            other => bail!("Unknown sandbox backend '{}'", other),
+        }
+    }
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+/// Why a sandboxed command was stopped or failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SandboxViolation {
+    /// Ran longer than allowed
+    Timeout { limit_ms: u128 },
+    /// Used up its CPU time
+    CpuLimit { limit_secs: u64 },
+    /// Could not allocate memory within the limit
+    MemoryLimit { limit_mb: u64 },
+    /// Touched a path outside the allowed ones
+    Filesystem { detail: String },
+    /// Tried to reach the network while it is cut off
+    Network { detail: String },
+}
+
+impl fmt::Display for SandboxViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SandboxViolation::Timeout { limit_ms } => {
+                write!(f, "Command timed out after {} ms", limit_ms)
+            }
+            SandboxViolation::CpuLimit { limit_secs } => {
+                write!(f, "Command exceeded its CPU limit of {} s", limit_secs)
+            }
+            SandboxViolation::MemoryLimit { limit_mb } => {
+                write!(f, "Command exceeded its memory limit of {} MiB", limit_mb)
+            }
+            SandboxViolation::Filesystem { detail } => {
+                write!(f, "Sandbox blocked filesystem access: {}", detail)
+            }
+            SandboxViolation::Network { detail } => {
+                write!(f, "Sandbox blocked network access: {}", detail)
+            }
+        }
+    }
+}
+
+/// Confinement applied to every command a tool runs
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    backend: SandboxBackend,
+    timeout: Duration,
+    cpu_secs: u64,
+    memory_mb: u64,
+    network: bool,
+    /// Readable paths; everything is readable when empty
+    read_paths: Vec<PathBuf>,
+    write_paths: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// Sandbox described by `config`, with the paths `policy` allows
+    /// `agent` to read and write added to its lists
+    pub fn from_config(config: &SandboxConfig, policy: &PolicyEngine, agent: &str) -> Result<Self> {
+        let backend = SandboxBackend::resolve(&config.backend)?;
+        let paths = |configured: &[String], action: &str| -> Vec<PathBuf> {
+            let mut paths: Vec<PathBuf> = Vec::new();
+            for path in configured
+                .iter()
+                .cloned()
+                .chain(policy.allowed_paths(agent, action))
+            {
+                let path = resolve_path(Path::new(&path));
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+            paths
+        };
+        Ok(Self {
+            backend,
+            timeout: Duration::from_secs(config.timeout_secs),
+            cpu_secs: config.cpu_secs,
+            memory_mb: config.memory_mb,
+            network: config.network,
+            read_paths: paths(&config.read_paths, "file_read"),
+            write_paths: paths(&config.write_paths, "file_write"),
+        })
+    }
+
+    pub fn backend(&self) -> &SandboxBackend {
+        &self.backend
+    }
+
+    /// How long a command may run: what was asked for, up to the limit
+    pub fn timeout(&self, requested: Option<Duration>) -> Duration {
+        requested.map_or(self.timeout, |requested| requested.min(self.timeout))
+    }
+
+    /// Command running `shell -c script` in the sandbox
+    ///
+    /// Fails when `working_dir` is outside the readable paths.
+    pub fn command(
+        &self,
+        shell: &Path,
+        script: &str,
+        working_dir: Option<&Path>,
+    ) -> std::result::Result<Command, SandboxViolation> {
+        let working_dir = working_dir.map(resolve_path);
+        if let Some(dir) = &working_dir {
+            if !self.can_read(dir) {
+                return Err(SandboxViolation::Filesystem {
+                    detail: format!("working directory {} is not readable", dir.display()),
+                });
+            }
+        }
+        let (program, args) = self.plan(shell, script, working_dir.as_deref());
+        let mut command = Command::new(program);
+        command.args(args);
+        if let Some(dir) = working_dir {
+            command.current_dir(dir);
+        }
+        Ok(command)
+    }
+
+    fn can_read(&self, path: &Path) -> bool {
+        self.read_paths.is_empty()
+            || self
+                .read_paths
+                .iter()
+                .chain(&self.write_paths)
+                .map(PathBuf::as_path)
+                .chain(SYSTEM_READ_PATHS.iter().map(Path::new))
+                .any(|allowed| path.starts_with(allowed))
+    }
+
+    /// Program and arguments that run `shell -c script` under the limits
+    /// and, unless only limits apply, inside the backend
+    fn plan(
+        &self,
+        shell: &Path,
+        script: &str,
+        working_dir: Option<&Path>,
+    ) -> (PathBuf, Vec<String>) {
+        // The limits apply to the shell and are inherited by what it starts.
+        // The hard CPU limit is a second later so the soft one raises SIGXCPU
+        // rather than SIGKILL
+        let limited = vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            format!(
+                "ulimit -S -t {} && ulimit -H -t {} || exit 125; ulimit -v {} 2>/dev/null; \
+                 exec \"$0\" -c \"$1\"",
+                self.cpu_secs,
+                self.cpu_secs + 1,
+                self.memory_mb * 1024
+            ),
+            shell.display().to_string(),
+            script.to_string(),
+        ];
+
+        match &self.backend {
+            SandboxBackend::Rlimit => {
+                let mut limited = limited.into_iter();
+                let program = PathBuf::from(limited.next().unwrap_or_default());
+                (program, limited.collect())
+            }
+            SandboxBackend::Bubblewrap(bwrap) => {
+                let mut args: Vec<String> = [
+                    "--die-with-parent",
+                    "--new-session",
+                    "--unshare-pid",
+                    "--unshare-ipc",
+                    "--unshare-uts",
+                ]
+                .map(String::from)
+                .to_vec();
+                if !self.network {
+                    args.push("--unshare-net".to_string());
+                }
+                let bind = |args: &mut Vec<String>, flag: &str, path: &Path| {
+                    let path = path.display().to_string();
+                    args.extend([flag.to_string(), path.clone(), path]);
+                };
+                if self.read_paths.is_empty() {
+                    bind(&mut args, "--ro-bind", Path::new("/"));
+                } else {
+                    for path in SYSTEM_READ_PATHS.iter().map(Path::new) {
+                        bind(&mut args, "--ro-bind-try", path);
+                    }
+                    for path in &self.read_paths {
+                        bind(&mut args, "--ro-bind-try", path);
+                    }
+                }
+                args.extend(
+                    ["--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"].map(String::from),
+                );
+                for path in &self.write_paths {
+                    bind(&mut args, "--bind-try", path);
+                }
+                if let Some(dir) = working_dir {
+                    args.extend(["--chdir".to_string(), dir.display().to_string()]);
+                }
+                args.push("--".to_string());
+                args.extend(limited);
+                (bwrap.clone(), args)
+            }
+            SandboxBackend::SandboxExec => {
+                let mut args = vec!["-p".to_string(), self.seatbelt_profile()];
+                args.extend(limited);
+                (PathBuf::from("/usr/bin/sandbox-exec"), args)
+            }
+        }
+    }
+
+    /// sandbox-exec profile allowing the configured paths and network
+    fn seatbelt_profile(&self) -> String {
+        let subpaths = |paths: &mut dyn Iterator<Item = &Path>| -> String {
+            paths
+                .map(|path| format!(" (subpath {})", quote(path)))
+                .collect::<String>()
+        };
+        let mut profile =
+            String::from("(version 1)\n(allow default)\n(deny file-write*)\n(allow file-write*");
+        profile.push_str(" (literal \"/dev/null\") (literal \"/dev/tty\") (regex #\"^/dev/fd/\")");
+        profile.push_str(&subpaths(
+            &mut MACOS_WRITE_PATHS
+                .iter()
+                .map(Path::new)
+                .chain(self.write_paths.iter().map(PathBuf::as_path)),
+        ));
+        profile.push_str(")\n");
+        if !self.read_paths.is_empty() {
+            profile.push_str("(deny file-read*)\n(allow file-read-metadata)\n");
+            profile.push_str("(allow file-read* (literal \"/\") (subpath \"/dev\")");
+            profile.push_str(&subpaths(
+                &mut SYSTEM_READ_PATHS
+                    .iter()
+                    .chain(MACOS_WRITE_PATHS)
+                    .map(Path::new)
+                    .chain(self.read_paths.iter().map(PathBuf::as_path))
+                    .chain(self.write_paths.iter().map(PathBuf::as_path)),
+            ));
+            profile.push_str(")\n");
+        }
+        if !self.network {
+            profile.push_str("(deny network-outbound (remote ip \"*:*\"))\n");
+            profile.push_str("(deny network-bind (local ip \"*:*\"))\n");
+        }
+        profile
+    }
+
+    /// The limit or wall a failed command ran into, judged from how it
+    /// exited and what it wrote to stderr
+    pub fn classify(&self, status: &ExitStatus, stderr: &str) -> Option<SandboxViolation> {
+        if status.success() {
+            return None;
+        }
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(status);
+        #[cfg(not(unix))]
+        let signal: Option<i32> = None;
+        // Shells and bubblewrap report a child killed by a signal as 128 + signal
+        if signal == Some(SIGXCPU) || status.code() == Some(128 + SIGXCPU) {
+            return Some(SandboxViolation::CpuLimit {
+                limit_secs: self.cpu_secs,
+            });
+        }
+
+        let line_with = |needles: &[&str]| {
+            stderr
+                .lines()
+                .find(|line| needles.iter().any(|needle| line.contains(needle)))
+                .map(|line| line.trim().chars().take(200).collect::<String>())
+        };
+        if line_with(MEMORY_ERRORS).is_some() {
+            return Some(SandboxViolation::MemoryLimit {
+                limit_mb: self.memory_mb,
+            });
+        }
+        let filesystem_errors: &[&str] = match self.backend {
+            SandboxBackend::Bubblewrap(_) => &["Read-only file system"],
+            SandboxBackend::SandboxExec => &["Operation not permitted", "Read-only file system"],
+            SandboxBackend::Rlimit => &[],
+        };
+        if let Some(detail) = line_with(filesystem_errors) {
+            return Some(SandboxViolation::Filesystem { detail });
+        }
+        if !self.network && self.backend != SandboxBackend::Rlimit {
+            if let Some(detail) = line_with(NETWORK_ERRORS) {
+                return Some(SandboxViolation::Network { detail });
+            }
+        }
+        None
+    }
+}
+
+/// Absolute form of `path`, resolving symlinks when it exists
+fn resolve_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+    std::fs::canonicalize(&absolute).unwrap_or(absolute)
+}
+
+/// A path as an SBPL string literal
+fn quote(path: &Path) -> String {
+    let path = path.display().to_string();
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{PolicyEffect, PolicyRule};
+
+    fn sandbox(backend: SandboxBackend, read_paths: Vec<&str>) -> Sandbox {
+        Sandbox {
+            backend,
+            timeout: Duration::from_secs(10),
+            cpu_secs: 5,
+            memory_mb: 64,
+            network: false,
+            read_paths: read_paths.into_iter().map(PathBuf::from).collect(),
+            write_paths: vec![PathBuf::from("/work")],
+        }
+    }
+
+    #[test]
+    fn test_paths_come_from_config_and_policy() {
+        let mut policy = PolicyEngine::new();
+        policy.add_rule(PolicyRule {
+            agent: "coder".to_string(),
+            action: "file_write".to_string(),
+            resource: "/srv/build/*".to_string(),
+            effect: PolicyEffect::Allow,
+        });
+        let config = SandboxConfig {
+            backend: "rlimit".to_string(),
+            write_paths: vec!["/work".to_string()],
+            ..Default::default()
+        };
+
+        let sandbox = Sandbox::from_config(&config, &policy, "coder").unwrap();
+        assert_eq!(sandbox.backend(), &SandboxBackend::Rlimit);
+        assert_eq!(
+            sandbox.write_paths,
+            [PathBuf::from("/work"), PathBuf::from("/srv/build")]
+        );
+        assert!(sandbox.read_paths.is_empty());
+        let other = Sandbox::from_config(&config, &policy, "reviewer").unwrap();
+        assert_eq!(other.write_paths, [PathBuf::from("/work")]);
+        assert_eq!(
+            sandbox.timeout(Some(Duration::from_secs(1000))),
+            Duration::from_secs(120)
+        );
+    }
+
+    #[test]
+    fn test_bubblewrap_binds_allowed_paths() {
+        let bwrap = SandboxBackend::Bubblewrap(PathBuf::from("/usr/bin/bwrap"));
+        let (program, args) = sandbox(bwrap, vec!["/data"]).plan(
+            Path::new("/bin/bash"),
+            "make",
+            Some(Path::new("/work")),
+        );
+        assert_eq!(program, PathBuf::from("/usr/bin/bwrap"));
+        let joined = args.join(" ");
+        assert!(joined.contains("--unshare-net"));
+        assert!(joined.contains("--ro-bind-try /data /data"));
+        assert!(!joined.contains("--ro-bind / /"));
+        assert!(joined.contains("--bind-try /work /work"));
+        assert!(joined.contains("--chdir /work --"));
+        assert_eq!(args[args.len() - 2..], ["/bin/bash", "make"]);
+    }
+
+    #[test]
+    fn test_working_dir_must_be_readable() {
+        let sandbox = sandbox(SandboxBackend::Rlimit, vec!["/data"]);
+        assert!(sandbox.can_read(Path::new("/data/project")));
+        assert!(sandbox.can_read(Path::new("/work")));
+        assert!(matches!(
+            sandbox.command(Path::new("/bin/sh"), "ls", Some(Path::new("/home/me"))),
+            Err(SandboxViolation::Filesystem { .. })
+        ));
+    }
+
+    #[test]
+    fn test_seatbelt_profile_limits_writes_and_network() {
+        let profile = sandbox(SandboxBackend::SandboxExec, vec![]).seatbelt_profile();
+        assert!(profile.contains("(deny file-write*)"));
+        assert!(profile.contains("(subpath \"/work\")"));
+        assert!(!profile.contains("(deny file-read*)"));
+        assert!(profile.contains("(deny network-outbound"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_limits_are_reported_as_violations() {
+        let limited = Sandbox {
+            cpu_secs: 1,
+            ..sandbox(SandboxBackend::Rlimit, vec![])
+        };
+        let output = limited
+            .command(Path::new("/bin/sh"), "while :; do :; done", None)
+            .unwrap()
+            .output()
+            .await
+            .unwrap();
+        assert_eq!(
+            limited.classify(&output.status, &String::from_utf8_lossy(&output.stderr)),
+            Some(SandboxViolation::CpuLimit { limit_secs: 1 })
+        );
+
+        let bwrap = sandbox(SandboxBackend::Bubblewrap(PathBuf::from("bwrap")), vec![]);
+        let failed = std::process::Command::new("/bin/sh")
+            .args(["-c", "exit 1"])
+            .status()
+            .unwrap();
+        assert_eq!(
+            bwrap.classify(
+                &failed,
+                "touch: cannot touch '/etc/x': Read-only file system"
+            ),
+            Some(SandboxViolation::Filesystem {
+                detail: "touch: cannot touch '/etc/x': Read-only file system".to_string()
+            })
+        );
+        assert!(matches!(
+            bwrap.classify(&failed, "curl: (6) Could not resolve host: example.com"),
+            Some(SandboxViolation::Network { .. })
+        ));
+        assert_eq!(bwrap.classify(&failed, "make: *** [all] Error 2"), None);
+    }
+}
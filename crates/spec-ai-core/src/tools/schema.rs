@@ -0,0 +1,333 @@
+//! Checking tool payloads against their output schemas
+//!
+//! Tools that return a structured payload declare its JSON Schema through
+//! [`Tool::output_schema`](super::Tool::output_schema), and the registry
+//! checks every payload against it before anyone downstream sees it. Only
+//! the keywords tool schemas use are checked: `type`, `enum`, `const`,
+//! `properties`, `required`, `additionalProperties`, `items`, the length,
+//! size and range bounds, `allOf`, and `anyOf` and `oneOf`, both of which
+//! accept a value any alternative matches. Unknown keywords, including `$ref`
+//! and `format`, are ignored.
+
+use serde_json::{Map, Value};
+
+/// Check `value` against `schema`
+///
+/// The error names the first violation and where it is, as a JSON pointer.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), String> {
+    check(schema, value, &mut String::new())
+}
+
+fn check(schema: &Value, value: &Value, at: &mut String) -> Result<(), String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(violation(at, "no value is allowed")),
+        Value::Object(schema) => schema,
+        _ => return Ok(()),
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|name| has_type(value, name)) {
+            return Err(violation(
+                at,
+                &format!(
+                    "expected {}, found {}",
+                    allowed.join(" or "),
+                    type_name(value)
+                ),
+            ));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(violation(
+                at,
+                &format!("{} is not one of {}", value, Value::from(options.clone())),
+            ));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            return Err(violation(
+                at,
+                &format!("expected {}, found {}", constant, value),
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(object) => check_object(schema, object, at)?,
+        Value::Array(items) => check_array(schema, items, at)?,
+        Value::String(text) => {
+            let chars = text.chars().count() as u64;
+            check_bounds(schema, "minLength", "maxLength", chars, "characters", at)?;
+        }
+        Value::Number(number) => {
+            if let Some(number) = number.as_f64() {
+                check_range(schema, number, at)?;
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for subschema in all {
+            check(subschema, value, at)?;
+        }
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(options) = schema.get(keyword).and_then(Value::as_array) {
+            let mut errors = Vec::new();
+            for subschema in options {
+                match check(subschema, value, &mut at.clone()) {
+                    Ok(()) => break,
+                    Err(err) => errors.push(err),
+                }
+            }
+            if errors.len() == options.len() && !options.is_empty() {
+                return Err(violation(
+                    at,
+                    &format!("matches none of {} ({})", keyword, errors.join("; ")),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    at: &mut String,
+) -> Result<(), String> {
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                return Err(violation(
+                    at,
+                    &format!("missing required property '{}'", name),
+                ));
+            }
+        }
+    }
+    check_bounds(
+        schema,
+        "minProperties",
+        "maxProperties",
+        object.len() as u64,
+        "properties",
+        at,
+    )?;
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let additional = schema.get("additionalProperties");
+    for (name, property) in object {
+        let subschema = match properties.and_then(|properties| properties.get(name)) {
+            Some(subschema) => subschema,
+            None => match additional {
+                Some(Value::Bool(false)) => {
+                    return Err(violation(at, &format!("unexpected property '{}'", name)))
+                }
+                Some(subschema) => subschema,
+                None => continue,
+            },
+        };
+        within(at, name, |at| check(subschema, property, at))?;
+    }
+    Ok(())
+}
+
+fn check_array(
+    schema: &Map<String, Value>,
+    items: &[Value],
+    at: &mut String,
+) -> Result<(), String> {
+    check_bounds(
+        schema,
+        "minItems",
+        "maxItems",
+        items.len() as u64,
+        "items",
+        at,
+    )?;
+    if let Some(subschema) = schema.get("items") {
+        for (index, item) in items.iter().enumerate() {
+            within(at, &index.to_string(), |at| check(subschema, item, at))?;
+        }
+    }
+    Ok(())
+}
+
+fn check_bounds(
+    schema: &Map<String, Value>,
+    min_keyword: &str,
+    max_keyword: &str,
+    count: u64,
+    unit: &str,
+    at: &str,
+) -> Result<(), String> {
+    if let Some(min) = schema.get(min_keyword).and_then(Value::as_u64) {
+        if count < min {
+            return Err(violation(
+                at,
+                &format!("has {} {}, fewer than {}", count, unit, min),
+            ));
+        }
+    }
+    if let Some(max) = schema.get(max_keyword).and_then(Value::as_u64) {
+        if count > max {
+            return Err(violation(
+                at,
+                &format!("has {} {}, more than {}", count, unit, max),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_range(schema: &Map<String, Value>, number: f64, at: &str) -> Result<(), String> {
+    let bound = |keyword| schema.get(keyword).and_then(Value::as_f64);
+    let broken = [
+        ("minimum", bound("minimum").filter(|min| number < *min)),
+        ("maximum", bound("maximum").filter(|max| number > *max)),
+        (
+            "exclusiveMinimum",
+            bound("exclusiveMinimum").filter(|min| number <= *min),
+        ),
+        (
+            "exclusiveMaximum",
+            bound("exclusiveMaximum").filter(|max| number >= *max),
+        ),
+    ];
+    match broken
+        .iter()
+        .find_map(|(keyword, limit)| limit.map(|limit| (keyword, limit)))
+    {
+        Some((keyword, limit)) => Err(violation(
+            at,
+            &format!("{} breaks {} {}", number, keyword, limit),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Run `f` with `segment` appended to the pointer `at`
+fn within<T>(at: &mut String, segment: &str, f: impl FnOnce(&mut String) -> T) -> T {
+    let len = at.len();
+    at.push('/');
+    at.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    let result = f(at);
+    at.truncate(len);
+    result
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some(),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn violation(at: &str, message: &str) -> String {
+    if at.is_empty() {
+        message.to_string()
+    } else {
+        format!("{}: {}", at, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "total": { "type": "integer", "minimum": 0 },
+                "results": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "path": { "type": "string" },
+                            "kind": { "enum": ["function", "struct"] },
+                            "container": { "type": ["string", "null"] }
+                        },
+                        "required": ["path", "kind"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["total", "results"]
+        })
+    }
+
+    #[test]
+    fn matching_values_pass() {
+        let value = json!({
+            "total": 1,
+            "results": [{ "path": "src/lib.rs", "kind": "function", "container": null }],
+            "extra": true
+        });
+        assert_eq!(validate(&schema(), &value), Ok(()));
+        assert_eq!(validate(&json!(true), &value), Ok(()));
+        assert_eq!(validate(&json!({}), &value), Ok(()));
+    }
+
+    #[test]
+    fn violations_name_where_they_are() {
+        let check = |value: Value| validate(&schema(), &value).unwrap_err();
+
+        assert_eq!(check(json!([])), "expected object, found array");
+        assert_eq!(
+            check(json!({ "total": 1 })),
+            "missing required property 'results'"
+        );
+        assert_eq!(
+            check(json!({ "total": 1.5, "results": [] })),
+            "/total: expected integer, found number"
+        );
+        assert_eq!(
+            check(json!({ "total": -1, "results": [] })),
+            "/total: -1 breaks minimum 0"
+        );
+        assert_eq!(
+            check(json!({ "total": 1, "results": [{ "path": "a", "kind": "trait" }] })),
+            "/results/0/kind: \"trait\" is not one of [\"function\",\"struct\"]"
+        );
+        assert_eq!(
+            check(json!({ "total": 1, "results": [{ "path": "a", "kind": "struct", "line": 3 }] })),
+            "/results/0: unexpected property 'line'"
+        );
+    }
+
+    #[test]
+    fn alternatives_need_one_match() {
+        let schema =
+            json!({ "anyOf": [{ "type": "string" }, { "type": "integer", "maximum": 9 }] });
+        assert_eq!(validate(&schema, &json!("a")), Ok(()));
+        assert_eq!(validate(&schema, &json!(3)), Ok(()));
+        assert!(validate(&schema, &json!(10))
+            .unwrap_err()
+            .starts_with("matches none of anyOf"));
+    }
+}
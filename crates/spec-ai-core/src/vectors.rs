@@ -0,0 +1,236 @@
+//! Durable vector storage
+//!
+//! [`VectorStore`] keeps embeddings in named collections and answers nearest
+//! neighbour queries over them, optionally restricted to points whose payload
+//! matches a [`VectorFilter`]. Agents mirror the embeddings of their messages
+//! into [`MEMORY_COLLECTION`] for semantic recall and those of message nodes
+//! into [`GRAPH_COLLECTION`] for graph similarity search.
+//!
+//! With the `sqlite-vec` feature the default backend is an embedded SQLite
+//! file searched with sqlite-vec; with the `qdrant` feature a Qdrant server
+//! can be used instead.
+
+use crate::config::VectorStoreConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+#[cfg(feature = "qdrant")]
+pub mod qdrant;
+#[cfg(feature = "sqlite-vec")]
+pub mod sqlite;
+
+/// Collection holding the embeddings of stored messages and transcriptions
+pub const MEMORY_COLLECTION: &str = "memory";
+
+/// Collection holding the embeddings of graph nodes
+pub const GRAPH_COLLECTION: &str = "graph_nodes";
+
+/// An embedding with its id and payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorPoint {
+    /// Id unique within the collection; upserting it again replaces the point
+    pub id: u64,
+    pub vector: Vec<f32>,
+    /// Fields filters can match on
+    #[serde(default)]
+    pub payload: Map<String, Value>,
+}
+
+impl VectorPoint {
+    pub fn new(id: u64, vector: Vec<f32>) -> Self {
+        Self {
+            id,
+            vector,
+            payload: Map::new(),
+        }
+    }
+
+    /// Add a payload field
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.payload.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// A point found by a nearest neighbour query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorMatch {
+    pub id: u64,
+    /// Cosine similarity to the query, higher is closer
+    pub score: f32,
+    pub payload: Map<String, Value>,
+}
+
+/// Value a payload field is compared with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FilterValue {
+    Bool(bool),
+    Integer(i64),
+    Text(String),
+}
+
+impl From<bool> for FilterValue {
+    fn from(value: bool) -> Self {
+        FilterValue::Bool(value)
+    }
+}
+
+impl From<i64> for FilterValue {
+    fn from(value: i64) -> Self {
+        FilterValue::Integer(value)
+    }
+}
+
+impl From<&str> for FilterValue {
+    fn from(value: &str) -> Self {
+        FilterValue::Text(value.to_string())
+    }
+}
+
+impl From<String> for FilterValue {
+    fn from(value: String) -> Self {
+        FilterValue::Text(value)
+    }
+}
+
+/// Payload fields a point must match to be returned
+///
+/// Every condition must hold; an empty filter matches every point.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorFilter {
+    pub must: Vec<(String, FilterValue)>,
+}
+
+impl VectorFilter {
+    /// Filter that matches every point
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Require payload field `key` to equal `value`
+    pub fn eq(mut self, key: impl Into<String>, value: impl Into<FilterValue>) -> Self {
+        self.must.push((key.into(), value.into()));
+        self
+    }
+
+    /// Whether `payload` satisfies every condition
+    pub fn matches(&self, payload: &Map<String, Value>) -> bool {
+        self.must
+            .iter()
+            .all(|(key, expected)| match (payload.get(key), expected) {
+                (Some(Value::Bool(actual)), FilterValue::Bool(expected)) => actual == expected,
+                (Some(Value::Number(actual)), FilterValue::Integer(expected)) => {
+                    actual.as_i64() == Some(*expected)
+                }
+                (Some(Value::String(actual)), FilterValue::Text(expected)) => actual == expected,
+                _ => false,
+            })
+    }
+}
+
+/// Storage for embeddings with nearest neighbour search
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Name of the backend, for logs
+    fn backend(&self) -> &'static str;
+
+    /// Insert `points` into `collection`, replacing points with the same ids
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()>;
+
+    /// Remove the points with `ids` from `collection`; unknown ids are ignored
+    async fn delete(&self, collection: &str, ids: &[u64]) -> Result<()>;
+
+    /// The `k` points of `collection` closest to `query` that match `filter`,
+    /// closest first
+    ///
+    /// Points whose dimension differs from the query's are never returned.
+    async fn knn(
+        &self,
+        collection: &str,
+        query: &[f32],
+        k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>>;
+}
+
+/// Open the vector store `[vector_store]` describes
+///
+/// Returns `None` when the vector store is disabled.
+pub fn from_config(config: &VectorStoreConfig) -> Result<Option<Arc<dyn VectorStore>>> {
+    if !config.enabled {
+        return Ok(None);
+    }
+    let store: Arc<dyn VectorStore> = match config.backend.as_str() {
+        "sqlite" => open_sqlite(config)?,
+        "qdrant" => open_qdrant(config)?,
+        other => return Err(anyhow!("Unknown vector store backend '{}'", other)),
+    };
+    Ok(Some(store))
+}
+
+#[cfg(feature = "sqlite-vec")]
+fn open_sqlite(config: &VectorStoreConfig) -> Result<Arc<dyn VectorStore>> {
+    let path = match &config.path {
+        Some(path) => path.clone(),
+        None => sqlite::default_path()?,
+    };
+    Ok(Arc::new(sqlite::SqliteVectorStore::open(&path)?))
+}
+
+#[cfg(not(feature = "sqlite-vec"))]
+fn open_sqlite(_config: &VectorStoreConfig) -> Result<Arc<dyn VectorStore>> {
+    Err(anyhow!(
+        "vector store backend \"sqlite\" needs spec-ai built with the sqlite-vec feature"
+    ))
+}
+
+#[cfg(feature = "qdrant")]
+fn open_qdrant(config: &VectorStoreConfig) -> Result<Arc<dyn VectorStore>> {
+    let api_key = config
+        .api_key_source
+        .as_deref()
+        .map(crate::agent::factory::resolve_api_key)
+        .transpose()?;
+    Ok(Arc::new(qdrant::QdrantVectorStore::connect(
+        &config.url,
+        api_key,
+        &config.collection_prefix,
+    )?))
+}
+
+#[cfg(not(feature = "qdrant"))]
+fn open_qdrant(_config: &VectorStoreConfig) -> Result<Arc<dyn VectorStore>> {
+    Err(anyhow!(
+        "vector store backend \"qdrant\" needs spec-ai built with the qdrant feature"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn filters_compare_payload_fields_by_type() {
+        let payload = json!({ "session_id": "s1", "message_id": 7, "synced": false })
+            .as_object()
+            .cloned()
+            .unwrap();
+
+        assert!(VectorFilter::none().matches(&payload));
+        assert!(VectorFilter::none()
+            .eq("session_id", "s1")
+            .eq("message_id", 7)
+            .eq("synced", false)
+            .matches(&payload));
+        assert!(!VectorFilter::none()
+            .eq("session_id", "s2")
+            .matches(&payload));
+        assert!(!VectorFilter::none().eq("message_id", "7").matches(&payload));
+        assert!(!VectorFilter::none().eq("missing", true).matches(&payload));
+    }
+}
@@ -0,0 +1,169 @@
+//! Vectors in a Qdrant server
+//!
+//! Each collection maps to a Qdrant collection named with the configured
+//! prefix, created with cosine distance on the first upsert. A Qdrant
+//! collection holds vectors of a single dimension, fixed by that first
+//! upsert; after switching embeddings models use a new prefix.
+
+use super::{FilterValue, VectorFilter, VectorMatch, VectorPoint, VectorStore};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use qdrant_client::qdrant::{
+    Condition, CreateCollectionBuilder, DeletePointsBuilder, Distance, Filter, PointStruct,
+    PointsIdsList, QueryPointsBuilder, UpsertPointsBuilder, VectorParamsBuilder,
+};
+use qdrant_client::{Payload, Qdrant};
+use std::collections::HashSet;
+use tokio::sync::Mutex;
+
+/// [`VectorStore`] backed by a Qdrant server
+pub struct QdrantVectorStore {
+    client: Qdrant,
+    prefix: String,
+    /// Collections known to exist
+    collections: Mutex<HashSet<String>>,
+}
+
+impl QdrantVectorStore {
+    /// Connect to the gRPC endpoint at `url` (usually port 6334)
+    pub fn connect(url: &str, api_key: Option<String>, prefix: &str) -> Result<Self> {
+        let client = Qdrant::from_url(url)
+            .api_key(api_key)
+            .build()
+            .with_context(|| format!("connecting to Qdrant at {}", url))?;
+        Ok(Self {
+            client,
+            prefix: prefix.to_string(),
+            collections: Mutex::new(HashSet::new()),
+        })
+    }
+
+    fn collection_name(&self, collection: &str) -> String {
+        format!("{}{}", self.prefix, collection)
+    }
+
+    /// Whether `name` exists, creating it for `dimension` sized vectors if
+    /// asked to
+    async fn ensure_collection(&self, name: &str, create_with: Option<usize>) -> Result<bool> {
+        let mut collections = self.collections.lock().await;
+        if collections.contains(name) {
+            return Ok(true);
+        }
+        if !self.client.collection_exists(name).await? {
+            let Some(dimension) = create_with else {
+                return Ok(false);
+            };
+            self.client
+                .create_collection(
+                    CreateCollectionBuilder::new(name).vectors_config(VectorParamsBuilder::new(
+                        dimension as u64,
+                        Distance::Cosine,
+                    )),
+                )
+                .await
+                .with_context(|| format!("creating Qdrant collection {}", name))?;
+        }
+        collections.insert(name.to_string());
+        Ok(true)
+    }
+}
+
+fn condition(key: &str, value: &FilterValue) -> Condition {
+    match value {
+        FilterValue::Bool(value) => Condition::matches(key, *value),
+        FilterValue::Integer(value) => Condition::matches(key, *value),
+        FilterValue::Text(value) => Condition::matches(key, value.clone()),
+    }
+}
+
+#[async_trait]
+impl VectorStore for QdrantVectorStore {
+    fn backend(&self) -> &'static str {
+        "qdrant"
+    }
+
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+        let Some(dimension) = points.first().map(|point| point.vector.len()) else {
+            return Ok(());
+        };
+        let name = self.collection_name(collection);
+        self.ensure_collection(&name, Some(dimension)).await?;
+
+        let points = points
+            .into_iter()
+            .map(|point| PointStruct::new(point.id, point.vector, Payload::from(point.payload)))
+            .collect::<Vec<_>>();
+        self.client
+            .upsert_points(UpsertPointsBuilder::new(&name, points).wait(true))
+            .await
+            .with_context(|| format!("upserting into Qdrant collection {}", name))?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[u64]) -> Result<()> {
+        let name = self.collection_name(collection);
+        if ids.is_empty() || !self.ensure_collection(&name, None).await? {
+            return Ok(());
+        }
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(&name)
+                    .points(PointsIdsList::from(ids.to_vec()))
+                    .wait(true),
+            )
+            .await
+            .with_context(|| format!("deleting from Qdrant collection {}", name))?;
+        Ok(())
+    }
+
+    async fn knn(
+        &self,
+        collection: &str,
+        query: &[f32],
+        k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        let name = self.collection_name(collection);
+        if k == 0 || !self.ensure_collection(&name, None).await? {
+            return Ok(Vec::new());
+        }
+
+        let mut request = QueryPointsBuilder::new(&name)
+            .query(query.to_vec())
+            .limit(k as u64)
+            .with_payload(true);
+        if !filter.must.is_empty() {
+            request = request.filter(Filter::must(
+                filter.must.iter().map(|(key, value)| condition(key, value)),
+            ));
+        }
+        let response = self
+            .client
+            .query(request)
+            .await
+            .with_context(|| format!("querying Qdrant collection {}", name))?;
+
+        Ok(response
+            .result
+            .into_iter()
+            .filter_map(|point| {
+                // Points written by spec-ai always have numeric ids
+                let id = match point.id?.point_id_options? {
+                    PointIdOptions::Num(id) => id,
+                    PointIdOptions::Uuid(_) => return None,
+                };
+                let payload = point
+                    .payload
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into_json()))
+                    .collect();
+                Some(VectorMatch {
+                    id,
+                    score: point.score,
+                    payload,
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,391 @@
+//! Vectors in an embedded SQLite file, searched with sqlite-vec
+//!
+//! Every collection shares one table; points are stored as little-endian f32
+//! blobs with their payload as JSON. Queries scan the collection with
+//! `vec_distance_cosine`, which is exact and fast enough for the memories of
+//! a single instance.
+
+use super::{FilterValue, VectorFilter, VectorMatch, VectorPoint, VectorStore};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use directories::BaseDirs;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
+
+/// File vectors are kept in when `[vector_store] path` is unset
+pub fn default_path() -> Result<PathBuf> {
+    let base = BaseDirs::new().context("base directories not available")?;
+    Ok(base.home_dir().join(".spec-ai").join("vectors.sqlite"))
+}
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS vectors (
+    collection TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    embedding BLOB NOT NULL,
+    payload TEXT NOT NULL DEFAULT '{}',
+    PRIMARY KEY (collection, id)
+)";
+
+/// Embedded [`VectorStore`] backed by SQLite and sqlite-vec
+#[derive(Clone)]
+pub struct SqliteVectorStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteVectorStore {
+    /// Open or create the store at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+        register_sqlite_vec();
+        let conn = Connection::open(path)
+            .with_context(|| format!("opening vector store {}", path.display()))?;
+        // Agents built for other sessions open the same file
+        conn.busy_timeout(Duration::from_secs(5))?;
+        Self::init(conn)
+    }
+
+    /// A store that lives in memory, for tests and throwaway sessions
+    pub fn in_memory() -> Result<Self> {
+        register_sqlite_vec();
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("creating the vectors table")?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = Arc::clone(&self.conn);
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn
+                .lock()
+                .map_err(|_| anyhow!("vector store connection poisoned"))?;
+            f(&mut conn)
+        })
+        .await
+        .context("task join error")?
+    }
+}
+
+/// Load sqlite-vec into every connection opened from now on
+fn register_sqlite_vec() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| unsafe {
+        // sqlite3_vec_init has the signature SQLite expects of an extension
+        // entry point; the binding just declares it without arguments
+        #[allow(clippy::missing_transmute_annotations)]
+        rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+            sqlite_vec::sqlite3_vec_init as *const (),
+        )));
+    });
+}
+
+fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn filter_value(value: &FilterValue) -> SqlValue {
+    match value {
+        // json_extract reports JSON booleans as 0 and 1
+        FilterValue::Bool(value) => SqlValue::Integer(i64::from(*value)),
+        FilterValue::Integer(value) => SqlValue::Integer(*value),
+        FilterValue::Text(value) => SqlValue::Text(value.clone()),
+    }
+}
+
+#[async_trait]
+impl VectorStore for SqliteVectorStore {
+    fn backend(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+        if points.is_empty() {
+            return Ok(());
+        }
+        let collection = collection.to_string();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT OR REPLACE INTO vectors (collection, id, embedding, payload)
+                     VALUES (?1, ?2, ?3, ?4)",
+                )?;
+                for point in points {
+                    if point.vector.is_empty() {
+                        return Err(anyhow!("point {} has an empty vector", point.id));
+                    }
+                    stmt.execute(params![
+                        collection,
+                        point.id as i64,
+                        to_blob(&point.vector),
+                        serde_json::Value::Object(point.payload).to_string(),
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, collection: &str, ids: &[u64]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let collection = collection.to_string();
+        let ids = ids.to_vec();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt =
+                    tx.prepare("DELETE FROM vectors WHERE collection = ?1 AND id = ?2")?;
+                for id in ids {
+                    stmt.execute(params![collection, id as i64])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn knn(
+        &self,
+        collection: &str,
+        query: &[f32],
+        k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        if k == 0 || query.iter().all(|value| *value == 0.0) {
+            return Ok(Vec::new());
+        }
+
+        let mut sql = String::from(
+            "SELECT id, payload, vec_distance_cosine(embedding, ?1) AS distance
+             FROM vectors
+             WHERE collection = ?2 AND vec_length(embedding) = ?3",
+        );
+        let mut values = vec![
+            SqlValue::Blob(to_blob(query)),
+            SqlValue::Text(collection.to_string()),
+            SqlValue::Integer(query.len() as i64),
+        ];
+        for (key, value) in &filter.must {
+            sql.push_str(&format!(
+                " AND json_extract(payload, ?{}) = ?{}",
+                values.len() + 1,
+                values.len() + 2
+            ));
+            values.push(SqlValue::Text(format!(
+                "$.\"{}\"",
+                key.replace('"', "\\\"")
+            )));
+            values.push(filter_value(value));
+        }
+        sql.push_str(&format!(" ORDER BY distance LIMIT ?{}", values.len() + 1));
+        values.push(SqlValue::Integer(k as i64));
+
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(params_from_iter(values), |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                ))
+            })?;
+
+            let mut matches = Vec::new();
+            for row in rows {
+                let (id, payload, distance) = row?;
+                // Zero vectors have no direction and no distance
+                let Some(distance) = distance else {
+                    continue;
+                };
+                let payload = match serde_json::from_str(&payload)? {
+                    serde_json::Value::Object(payload) => payload,
+                    _ => serde_json::Map::new(),
+                };
+                matches.push(VectorMatch {
+                    id: id as u64,
+                    score: 1.0 - distance as f32,
+                    payload,
+                });
+            }
+            Ok(matches)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(id: u64, vector: &[f32], session: &str) -> VectorPoint {
+        VectorPoint::new(id, vector.to_vec()).with_field("session_id", session)
+    }
+
+    #[tokio::test]
+    async fn knn_returns_closest_points_first() {
+        let store = SqliteVectorStore::in_memory().unwrap();
+        store
+            .upsert(
+                "memory",
+                vec![
+                    point(1, &[1.0, 0.0, 0.0], "s1"),
+                    point(2, &[0.7, 0.7, 0.0], "s1"),
+                    point(3, &[0.0, 0.0, 1.0], "s1"),
+                    point(4, &[1.0, 0.0], "s1"),
+                ],
+            )
+            .await
+            .unwrap();
+        store
+            .upsert("graph_nodes", vec![point(1, &[0.0, 0.0, 1.0], "s1")])
+            .await
+            .unwrap();
+
+        let matches = store
+            .knn("memory", &[1.0, 0.1, 0.0], 2, &VectorFilter::none())
+            .await
+            .unwrap();
+        let ids: Vec<u64> = matches.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert!(matches[0].score > matches[1].score);
+        assert!(matches[0].score <= 1.0 && matches[0].score > 0.9);
+        assert_eq!(matches[0].payload["session_id"], "s1");
+
+        // Points of another dimension are never compared
+        let matches = store
+            .knn("memory", &[1.0, 0.0], 10, &VectorFilter::none())
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].id, 4);
+    }
+
+    #[tokio::test]
+    async fn upserts_replace_and_deletes_remove_points() {
+        let store = SqliteVectorStore::in_memory().unwrap();
+        store
+            .upsert("memory", vec![point(1, &[1.0, 0.0], "s1")])
+            .await
+            .unwrap();
+        store
+            .upsert("memory", vec![point(1, &[0.0, 1.0], "s2")])
+            .await
+            .unwrap();
+
+        let matches = store
+            .knn("memory", &[0.0, 1.0], 5, &VectorFilter::none())
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].payload["session_id"], "s2");
+
+        store.delete("memory", &[1, 99]).await.unwrap();
+        assert!(store
+            .knn("memory", &[0.0, 1.0], 5, &VectorFilter::none())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn filters_restrict_matches_to_payload_values() {
+        let store = SqliteVectorStore::in_memory().unwrap();
+        store
+            .upsert(
+                "memory",
+                vec![
+                    point(1, &[1.0, 0.0], "s1").with_field("message_id", 10),
+                    point(2, &[1.0, 0.1], "s2").with_field("message_id", 20),
+                    point(3, &[0.9, 0.1], "s1").with_field("pinned", true),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let only = |filter: VectorFilter| {
+            let store = store.clone();
+            async move {
+                store
+                    .knn("memory", &[1.0, 0.0], 10, &filter)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|m| m.id)
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(
+            only(VectorFilter::none().eq("session_id", "s1")).await,
+            [1, 3]
+        );
+        assert_eq!(only(VectorFilter::none().eq("message_id", 20)).await, [2]);
+        assert_eq!(only(VectorFilter::none().eq("pinned", true)).await, [3]);
+        assert!(only(VectorFilter::none().eq("pinned", false))
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn zero_vectors_are_stored_but_never_matched() {
+        let store = SqliteVectorStore::in_memory().unwrap();
+        store
+            .upsert(
+                "memory",
+                vec![point(1, &[0.0, 0.0], "s1"), point(2, &[1.0, 0.0], "s1")],
+            )
+            .await
+            .unwrap();
+
+        let matches = store
+            .knn("memory", &[1.0, 0.0], 5, &VectorFilter::none())
+            .await
+            .unwrap();
+        assert_eq!(matches.iter().map(|m| m.id).collect::<Vec<_>>(), [2]);
+        assert!(store
+            .knn("memory", &[0.0, 0.0], 5, &VectorFilter::none())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn points_persist_across_reopens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("vectors.sqlite");
+        SqliteVectorStore::open(&path)
+            .unwrap()
+            .upsert("memory", vec![point(5, &[0.5, 0.5], "s1")])
+            .await
+            .unwrap();
+
+        let reopened = SqliteVectorStore::open(&path).unwrap();
+        let matches = reopened
+            .knn("memory", &[0.5, 0.5], 1, &VectorFilter::none())
+            .await
+            .unwrap();
+        assert_eq!(matches[0].id, 5);
+    }
+}
@@ -0,0 +1,552 @@
+//! Filesystem watches started by agents
+//!
+//! `watch_path` asks the [`WatchRegistry`] to follow a file or directory.
+//! Changes are collected until the path has been quiet for the watch's
+//! debounce period and then broadcast as one [`ChangeBatch`], so saving a
+//! dozen files or a build rewriting a directory makes a single event. Front
+//! ends forward batches to the UI and to the agent's inbox, which lets the
+//! agent react to edits, for example by running the tests again.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::agent::AgentEvent;
+use crate::broadcast::{Broadcaster, DropPolicy, Subscriber};
+
+/// Directories whose changes are never reported: version control metadata
+/// and build output, which would otherwise wake the agent on every build
+pub const DEFAULT_IGNORED: &[&str] = &[".git", "target", "node_modules"];
+
+/// Shortest debounce period accepted
+pub const MIN_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Longest debounce period accepted
+pub const MAX_DEBOUNCE: Duration = Duration::from_secs(60);
+
+/// Changed paths listed in one batch; the rest are only counted
+const MAX_BATCH_PATHS: usize = 50;
+
+/// Changed paths named in the summary given to the agent
+const MAX_SUMMARY_PATHS: usize = 10;
+
+/// Events buffered for a slow subscriber before its oldest are dropped
+const EVENT_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Removed => "removed",
+        }
+    }
+
+    /// The kind of a raw notification, or None for ones that change nothing
+    /// such as reads
+    fn of(kind: &EventKind) -> Option<Self> {
+        match kind {
+            EventKind::Create(_) => Some(ChangeKind::Created),
+            EventKind::Modify(_) | EventKind::Any | EventKind::Other => Some(ChangeKind::Modified),
+            EventKind::Remove(_) => Some(ChangeKind::Removed),
+            EventKind::Access(_) => None,
+        }
+    }
+
+    /// What a path that changed as `self` and then as `next` amounts to
+    fn then(self, next: ChangeKind) -> ChangeKind {
+        match (self, next) {
+            // Still new to whoever last looked at the directory
+            (ChangeKind::Created, ChangeKind::Modified) => ChangeKind::Created,
+            // Replaced in place, as editors that save through a rename do
+            (ChangeKind::Removed, ChangeKind::Created) => ChangeKind::Modified,
+            (_, next) => next,
+        }
+    }
+}
+
+/// One path that changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileChange {
+    /// Path relative to the watched one, or the watched path itself
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+/// Changes seen by a watch between two quiet periods
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangeBatch {
+    pub watch_id: String,
+    /// The watched path
+    pub root: String,
+    /// Changed paths in path order, at most a few dozen
+    pub changes: Vec<FileChange>,
+    /// Changed paths left out of `changes`
+    pub omitted: usize,
+    pub at: DateTime<Utc>,
+}
+
+impl ChangeBatch {
+    /// One-line description, such as `modified src/lib.rs, created src/new.rs`
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = self
+            .changes
+            .iter()
+            .take(MAX_SUMMARY_PATHS)
+            .map(|change| format!("{} {}", change.kind.as_str(), change.path))
+            .collect();
+        let more = self.changes.len().saturating_sub(MAX_SUMMARY_PATHS) + self.omitted;
+        if more > 0 {
+            parts.push(format!("and {} more", more));
+        }
+        format!("under {}: {}", self.root, parts.join(", "))
+    }
+
+    /// The batch as an event for the agent's inbox
+    pub fn to_agent_event(&self) -> AgentEvent {
+        AgentEvent::new(self.watch_id.clone(), self.summary())
+    }
+}
+
+/// What is known about a watch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WatchInfo {
+    pub id: String,
+    pub path: String,
+    pub recursive: bool,
+    pub debounce_ms: u64,
+    /// File and directory names whose changes are not reported
+    pub ignore: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    /// Batches delivered so far
+    pub batches: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_change_at: Option<DateTime<Utc>>,
+}
+
+/// Change to the registry, as broadcast to subscribers
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    /// A watch started or delivered a batch
+    Updated(WatchInfo),
+    /// A watch was removed
+    Removed(String),
+    /// A watched path changed
+    Changed(ChangeBatch),
+}
+
+/// How a new watch behaves
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    pub recursive: bool,
+    pub debounce: Duration,
+    /// Names ignored on top of [`DEFAULT_IGNORED`]
+    pub ignore: Vec<String>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            recursive: true,
+            debounce: Duration::from_millis(500),
+            ignore: Vec::new(),
+        }
+    }
+}
+
+struct Entry {
+    info: WatchInfo,
+    /// Dropping the watcher ends the watch and its debounce task
+    _watcher: RecommendedWatcher,
+}
+
+/// Watches started in this process, by id
+pub struct WatchRegistry {
+    watches: Mutex<HashMap<String, Entry>>,
+    next_id: AtomicU64,
+    events: Broadcaster<WatchEvent>,
+}
+
+static GLOBAL: OnceLock<Arc<WatchRegistry>> = OnceLock::new();
+
+impl WatchRegistry {
+    pub fn new() -> Self {
+        Self {
+            watches: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            events: Broadcaster::new(EVENT_CAPACITY, DropPolicy::DropOldest),
+        }
+    }
+
+    /// Registry shared by the watch tools and the UI of this process
+    pub fn global() -> Arc<WatchRegistry> {
+        GLOBAL.get_or_init(|| Arc::new(Self::new())).clone()
+    }
+
+    /// Receive every change from now on
+    pub fn subscribe(&self) -> Subscriber<WatchEvent> {
+        self.events.subscribe()
+    }
+
+    /// Start watching `path`, which must exist
+    ///
+    /// Must be called within a Tokio runtime, which runs the debouncing.
+    pub fn watch(self: &Arc<Self>, path: &Path, options: WatchOptions) -> Result<WatchInfo> {
+        if options.debounce < MIN_DEBOUNCE || options.debounce > MAX_DEBOUNCE {
+            bail!(
+                "debounce must be between {}ms and {}ms",
+                MIN_DEBOUNCE.as_millis(),
+                MAX_DEBOUNCE.as_millis()
+            );
+        }
+        let root = path
+            .canonicalize()
+            .with_context(|| format!("Cannot watch '{}'", path.display()))?;
+
+        let (raw_tx, raw_rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                // Errors such as a full inotify queue lose changes, which the
+                // next batch will not show; there is nobody to report them to
+                match event {
+                    Ok(event) => {
+                        let _ = raw_tx.send(event);
+                    }
+                    Err(err) => tracing::warn!("File watch error: {}", err),
+                }
+            })
+            .context("Failed to create a file watcher")?;
+        let mode = if options.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(&root, mode)
+            .with_context(|| format!("Cannot watch '{}'", root.display()))?;
+
+        let id = format!("watch-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        let info = WatchInfo {
+            id: id.clone(),
+            path: root.display().to_string(),
+            recursive: options.recursive,
+            debounce_ms: options.debounce.as_millis() as u64,
+            ignore: options.ignore.clone(),
+            created_at: Utc::now(),
+            batches: 0,
+            last_change_at: None,
+        };
+        self.lock().insert(
+            id.clone(),
+            Entry {
+                info: info.clone(),
+                _watcher: watcher,
+            },
+        );
+        self.events.send(WatchEvent::Updated(info.clone()));
+
+        let filter = PathFilter::new(root, &options.ignore);
+        tokio::spawn(self.clone().debounce(id, filter, options.debounce, raw_rx));
+        Ok(info)
+    }
+
+    /// Stop a watch, returning what it was
+    pub fn unwatch(&self, id: &str) -> Result<WatchInfo> {
+        let entry = self
+            .lock()
+            .remove(id)
+            .ok_or_else(|| anyhow!("Unknown watch '{}'", id))?;
+        self.events.send(WatchEvent::Removed(id.to_string()));
+        Ok(entry.info)
+    }
+
+    pub fn status(&self, id: &str) -> Option<WatchInfo> {
+        self.lock().get(id).map(|entry| entry.info.clone())
+    }
+
+    /// Every watch, oldest first
+    pub fn list(&self) -> Vec<WatchInfo> {
+        let mut watches: Vec<WatchInfo> = self
+            .lock()
+            .values()
+            .map(|entry| entry.info.clone())
+            .collect();
+        watches.sort_by_key(|watch| (watch.created_at, watch_number(&watch.id)));
+        watches
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.watches.lock().expect("watch registry mutex poisoned")
+    }
+
+    /// Collect raw notifications into batches, delivering each once the
+    /// path has been quiet for `debounce`
+    async fn debounce(
+        self: Arc<Self>,
+        id: String,
+        filter: PathFilter,
+        debounce: Duration,
+        mut raw: mpsc::UnboundedReceiver<notify::Event>,
+    ) {
+        let mut pending = Pending::default();
+        loop {
+            let event = if pending.is_empty() {
+                match raw.recv().await {
+                    Some(event) => event,
+                    None => break,
+                }
+            } else {
+                match tokio::time::timeout(debounce, raw.recv()).await {
+                    Ok(Some(event)) => event,
+                    // The watch was removed
+                    Ok(None) => break,
+                    // Quiet for long enough
+                    Err(_) => {
+                        self.deliver(&id, &filter, std::mem::take(&mut pending));
+                        continue;
+                    }
+                }
+            };
+            pending.add(&filter, &event);
+        }
+    }
+
+    fn deliver(&self, id: &str, filter: &PathFilter, pending: Pending) {
+        let batch = {
+            let mut watches = self.lock();
+            let Some(entry) = watches.get_mut(id) else {
+                return;
+            };
+            let batch = pending.into_batch(id, &filter.root);
+            entry.info.batches += 1;
+            entry.info.last_change_at = Some(batch.at);
+            self.events.send(WatchEvent::Updated(entry.info.clone()));
+            batch
+        };
+        tracing::debug!(watch = %id, "{}", batch.summary());
+        self.events.send(WatchEvent::Changed(batch));
+    }
+}
+
+impl Default for WatchRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number in a `watch-<n>` id, so watch-10 sorts after watch-9
+fn watch_number(id: &str) -> u64 {
+    id.trim_start_matches("watch-").parse().unwrap_or_default()
+}
+
+/// Decides which changed paths are reported, and how they are named
+struct PathFilter {
+    root: PathBuf,
+    ignored: Vec<String>,
+}
+
+impl PathFilter {
+    fn new(root: PathBuf, ignore: &[String]) -> Self {
+        let ignored = DEFAULT_IGNORED
+            .iter()
+            .map(|name| name.to_string())
+            .chain(ignore.iter().cloned())
+            .collect();
+        Self { root, ignored }
+    }
+
+    /// Name to report a change to `path` under, or None when it is ignored
+    fn name(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let ignored = relative.components().any(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            self.ignored.iter().any(|name| *name == component)
+        });
+        if ignored {
+            return None;
+        }
+        if relative.as_os_str().is_empty() {
+            Some(self.root.display().to_string())
+        } else {
+            Some(relative.display().to_string())
+        }
+    }
+}
+
+/// Changes collected since the last batch
+#[derive(Default)]
+struct Pending {
+    changes: BTreeMap<String, ChangeKind>,
+}
+
+impl Pending {
+    fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn add(&mut self, filter: &PathFilter, event: &notify::Event) {
+        let Some(kind) = ChangeKind::of(&event.kind) else {
+            return;
+        };
+        for path in &event.paths {
+            let Some(name) = filter.name(path) else {
+                continue;
+            };
+            self.changes
+                .entry(name)
+                .and_modify(|existing| *existing = existing.then(kind))
+                .or_insert(kind);
+        }
+    }
+
+    fn into_batch(self, watch_id: &str, root: &Path) -> ChangeBatch {
+        let total = self.changes.len();
+        let changes: Vec<FileChange> = self
+            .changes
+            .into_iter()
+            .take(MAX_BATCH_PATHS)
+            .map(|(path, kind)| FileChange { path, kind })
+            .collect();
+        ChangeBatch {
+            watch_id: watch_id.to_string(),
+            root: root.display().to_string(),
+            omitted: total - changes.len(),
+            changes,
+            at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, ModifyKind, RemoveKind};
+
+    fn raw(kind: EventKind, path: &Path) -> notify::Event {
+        notify::Event::new(kind).add_path(path.to_path_buf())
+    }
+
+    #[test]
+    fn changes_are_merged_and_filtered() {
+        let root = PathBuf::from("/repo");
+        let filter = PathFilter::new(root.clone(), &["dist".to_string()]);
+        let mut pending = Pending::default();
+        let created = EventKind::Create(CreateKind::File);
+        let modified = EventKind::Modify(ModifyKind::Any);
+        let removed = EventKind::Remove(RemoveKind::File);
+
+        pending.add(&filter, &raw(created, &root.join("src/new.rs")));
+        pending.add(&filter, &raw(modified, &root.join("src/new.rs")));
+        pending.add(&filter, &raw(removed, &root.join("src/lib.rs")));
+        pending.add(&filter, &raw(created, &root.join("src/lib.rs")));
+        pending.add(&filter, &raw(modified, &root.join("target/debug/app")));
+        pending.add(&filter, &raw(modified, &root.join("dist/app.js")));
+        pending.add(
+            &filter,
+            &raw(
+                EventKind::Access(notify::event::AccessKind::Any),
+                &root.join("README.md"),
+            ),
+        );
+
+        let batch = pending.into_batch("watch-1", &root);
+        assert_eq!(
+            batch.changes,
+            vec![
+                FileChange {
+                    path: "src/lib.rs".to_string(),
+                    kind: ChangeKind::Modified,
+                },
+                FileChange {
+                    path: "src/new.rs".to_string(),
+                    kind: ChangeKind::Created,
+                },
+            ]
+        );
+        assert_eq!(
+            batch.summary(),
+            "under /repo: modified src/lib.rs, created src/new.rs"
+        );
+    }
+
+    #[test]
+    fn large_batches_are_cut_short() {
+        let root = PathBuf::from("/repo");
+        let filter = PathFilter::new(root.clone(), &[]);
+        let mut pending = Pending::default();
+        for n in 0..MAX_BATCH_PATHS + 5 {
+            pending.add(
+                &filter,
+                &raw(
+                    EventKind::Modify(ModifyKind::Any),
+                    &root.join(format!("f{:03}", n)),
+                ),
+            );
+        }
+        let batch = pending.into_batch("watch-1", &root);
+        assert_eq!(batch.changes.len(), MAX_BATCH_PATHS);
+        assert_eq!(batch.omitted, 5);
+        assert!(batch.summary().ends_with("and 45 more"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn writes_are_delivered_as_one_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = Arc::new(WatchRegistry::new());
+        let mut events = registry.subscribe();
+        let info = registry
+            .watch(
+                dir.path(),
+                WatchOptions {
+                    debounce: Duration::from_millis(200),
+                    ..WatchOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(registry
+            .watch(
+                dir.path(),
+                WatchOptions {
+                    debounce: Duration::ZERO,
+                    ..WatchOptions::default()
+                }
+            )
+            .is_err());
+
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "b").unwrap();
+
+        let batch = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Ok(WatchEvent::Changed(batch)) = events.recv().await {
+                    return batch;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(batch.watch_id, info.id);
+        let paths: Vec<&str> = batch.changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt", "b.txt"]);
+        assert_eq!(registry.status(&info.id).unwrap().batches, 1);
+
+        registry.unwatch(&info.id).unwrap();
+        assert!(registry.list().is_empty());
+        assert!(registry.unwatch(&info.id).is_err());
+    }
+}
@@ -45,6 +45,13 @@ impl<P: SyncPersistence> SyncEngine<P> {
         }
     }
 
+    /// Correct the peer's timestamps by how far its clock runs ahead of ours
+    /// when resolving conflicts.
+    pub fn with_peer_clock_offset(mut self, offset: chrono::Duration) -> Self {
+        self.resolver = self.resolver.with_clock_offset(offset);
+        self
+    }
+
     /// Get a reference to the persistence layer.
     pub fn persistence(&self) -> &P {
         &self.persistence
@@ -2,7 +2,7 @@
 
 use crate::protocol::{SyncedEdge, SyncedNode};
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde_json::{json, Value as JsonValue};
 use spec_ai_knowledge_graph::{ClockOrder, VectorClock};
 use tracing::{debug, info, warn};
@@ -37,6 +37,8 @@ pub enum ConflictResolution {
 /// Conflict resolution strategies for graph synchronization
 pub struct ConflictResolver {
     instance_id: String,
+    /// How far the peer's clock runs ahead of ours
+    clock_offset: Duration,
     conflict_log: std::sync::Arc<std::sync::Mutex<Vec<ConflictRecord>>>,
 }
 
@@ -44,10 +46,23 @@ impl ConflictResolver {
     pub fn new(instance_id: String) -> Self {
         Self {
             instance_id,
+            clock_offset: Duration::zero(),
             conflict_log: std::sync::Arc::new(std::sync::Mutex::new(Vec::new())),
         }
     }
 
+    /// Correct the peer's timestamps by how far its clock runs ahead of
+    /// ours before comparing them with local ones
+    pub fn with_clock_offset(mut self, offset: Duration) -> Self {
+        self.clock_offset = offset;
+        self
+    }
+
+    /// `timestamp` from the peer's clock, on ours
+    fn remote_time(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        timestamp - self.clock_offset
+    }
+
     /// Resolve a node conflict using vector clock merge and property reconciliation
     pub fn resolve_node_conflict(
         &self,
@@ -106,7 +121,7 @@ impl ConflictResolver {
 
                     // Get timestamps (already DateTime<Utc>)
                     let local_ts = local_node.updated_at;
-                    let remote_ts = incoming.updated_at;
+                    let remote_ts = self.remote_time(incoming.updated_at);
 
                     // Apply type-specific merge strategy
                     let merged_properties = if incoming.node_type == local_node.node_type {
@@ -234,7 +249,7 @@ impl ConflictResolver {
                 if let Some(local_edge) = our_edge {
                     // Get timestamps
                     let local_ts = local_edge.created_at; // Edges don't have updated_at
-                    let remote_ts = incoming.created_at;
+                    let remote_ts = self.remote_time(incoming.created_at);
 
                     // Merge properties based on timestamps
                     let empty_props = serde_json::json!({});
@@ -574,6 +589,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn peer_timestamps_are_corrected_for_clock_skew() {
+        let node = |label: &str, updated_at, instance: &str| {
+            let mut vector_clock = VectorClock::new();
+            vector_clock.increment(instance);
+            SyncedNode {
+                id: 7,
+                session_id: "s".to_string(),
+                node_type: spec_ai_knowledge_graph::NodeType::Entity,
+                label: label.to_string(),
+                properties: json!({}),
+                embedding_id: None,
+                created_at: updated_at,
+                updated_at,
+                vector_clock,
+                last_modified_by: Some(instance.to_string()),
+                is_deleted: false,
+                sync_enabled: true,
+            }
+        };
+        let now = chrono::Utc::now();
+        let local = node("local", now, "us");
+        // Stamped 10s after ours by a clock that runs 30s fast
+        let remote = node("remote", now + Duration::seconds(10), "peer");
+
+        let merged_label = |resolver: ConflictResolver| {
+            let mut clock = local.vector_clock.clone();
+            match resolver
+                .resolve_node_conflict(&remote, Some(&local), &mut clock)
+                .unwrap()
+            {
+                ConflictResolution::Merged(merged) => merged["label"].clone(),
+                other => panic!("expected a merge, got {:?}", other),
+            }
+        };
+        assert_eq!(
+            merged_label(ConflictResolver::new("us".to_string())),
+            "remote"
+        );
+        assert_eq!(
+            merged_label(
+                ConflictResolver::new("us".to_string()).with_clock_offset(Duration::seconds(30))
+            ),
+            "local"
+        );
+    }
+
     #[test]
     fn test_preserve_keys() {
         let resolver = ConflictResolver::new("test-instance".to_string());
@@ -0,0 +1,300 @@
+//! Encryption at rest for graph namespaces
+//!
+//! Node and edge properties in an encrypted namespace are stored as an
+//! AES-256-GCM envelope instead of plain JSON. Ids, session ids, types,
+//! labels, predicates, weights and timestamps stay readable, so encrypted
+//! graphs can still be listed, counted and traversed.
+//!
+//! A namespace can have several keys. The first encrypts new writes; the
+//! rest only decrypt, which lets a key be rotated by putting the new key
+//! first and re-encrypting the namespace with
+//! [`KnowledgeGraphStore::reencrypt_namespace`](crate::KnowledgeGraphStore::reencrypt_namespace).
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Key of the object that replaces encrypted properties
+pub const ENVELOPE_FIELD: &str = "$encrypted";
+
+/// Length of a namespace key in bytes
+pub const KEY_LEN: usize = 32;
+
+/// An AES-256 key for one namespace
+#[derive(Clone)]
+pub struct NamespaceKey {
+    /// Short fingerprint stored next to the ciphertext to find the key again
+    id: String,
+    key: Arc<LessSafeKey>,
+}
+
+impl NamespaceKey {
+    /// Key from 32 raw bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != KEY_LEN {
+            bail!(
+                "Graph encryption keys must be {} bytes, got {}",
+                KEY_LEN,
+                bytes.len()
+            );
+        }
+        let unbound = UnboundKey::new(&AES_256_GCM, bytes)
+            .map_err(|_| anyhow!("Invalid graph encryption key"))?;
+        let fingerprint = digest(&SHA256, bytes);
+        let id = fingerprint.as_ref()[..8]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Ok(Self {
+            id,
+            key: Arc::new(LessSafeKey::new(unbound)),
+        })
+    }
+
+    /// Key from base64, as produced by `openssl rand -base64 32`
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .context("Graph encryption keys must be base64 encoded")?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Fingerprint recorded in envelopes written with this key
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl fmt::Debug for NamespaceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamespaceKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Keys for the namespaces whose properties are encrypted
+///
+/// Namespaces without keys are stored in plain text.
+#[derive(Debug, Clone, Default)]
+pub struct GraphEncryption {
+    namespaces: HashMap<String, Vec<NamespaceKey>>,
+}
+
+impl GraphEncryption {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encrypt `namespace` with the first of `keys`, keeping the others for
+    /// data written before a rotation
+    pub fn with_namespace(
+        mut self,
+        namespace: impl Into<String>,
+        keys: Vec<NamespaceKey>,
+    ) -> Result<Self> {
+        let namespace = namespace.into();
+        if keys.is_empty() {
+            bail!("Encrypted namespace '{}' has no keys", namespace);
+        }
+        self.namespaces.insert(namespace, keys);
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.namespaces.is_empty()
+    }
+
+    pub fn is_encrypted(&self, namespace: &str) -> bool {
+        self.namespaces.contains_key(namespace)
+    }
+
+    /// Names of the encrypted namespaces, sorted
+    pub fn namespaces(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.namespaces.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Properties as they should be stored for `namespace`
+    ///
+    /// Values that are already envelopes are returned as they are.
+    pub fn encrypt(&self, namespace: &str, value: &JsonValue) -> Result<JsonValue> {
+        let Some(current) = self.namespaces.get(namespace).and_then(|keys| keys.first()) else {
+            return Ok(value.clone());
+        };
+        if is_envelope(value) {
+            return Ok(value.clone());
+        }
+
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("Failed to generate a nonce"))?;
+        let mut data = serde_json::to_vec(value)?;
+        current
+            .key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(namespace.as_bytes()),
+                &mut data,
+            )
+            .map_err(|_| anyhow!("Failed to encrypt properties for '{}'", namespace))?;
+
+        Ok(json!({
+            ENVELOPE_FIELD: {
+                "kid": current.id,
+                "nonce": STANDARD.encode(nonce),
+                "data": STANDARD.encode(data),
+            }
+        }))
+    }
+
+    /// Stored properties in plain text
+    ///
+    /// Values that are not envelopes are returned as they are, so data
+    /// written before a namespace was encrypted stays readable.
+    pub fn decrypt(&self, namespace: &str, value: JsonValue) -> Result<JsonValue> {
+        let Some(envelope) = envelope(&value) else {
+            return Ok(value);
+        };
+        let kid = envelope
+            .get("kid")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| anyhow!("Encrypted properties in '{}' have no key id", namespace))?;
+        let key = self
+            .namespaces
+            .get(namespace)
+            .and_then(|keys| keys.iter().find(|key| key.id == kid))
+            .ok_or_else(|| {
+                anyhow!(
+                    "No key '{}' configured for encrypted namespace '{}'",
+                    kid,
+                    namespace
+                )
+            })?;
+
+        let field = |name: &str| -> Result<Vec<u8>> {
+            let encoded = envelope
+                .get(name)
+                .and_then(JsonValue::as_str)
+                .ok_or_else(|| anyhow!("Encrypted properties have no '{}'", name))?;
+            Ok(STANDARD.decode(encoded)?)
+        };
+        let nonce = Nonce::try_assume_unique_for_key(&field("nonce")?)
+            .map_err(|_| anyhow!("Encrypted properties have an invalid nonce"))?;
+        let mut data = field("data")?;
+        let plain = key
+            .key
+            .open_in_place(nonce, Aad::from(namespace.as_bytes()), &mut data)
+            .map_err(|_| {
+                anyhow!(
+                    "Failed to decrypt properties in '{}' with key '{}'",
+                    namespace,
+                    kid
+                )
+            })?;
+        Ok(serde_json::from_slice(plain)?)
+    }
+
+    /// Whether stored properties should be rewritten with the current key,
+    /// either because they are in plain text or use an older key
+    pub fn needs_reencryption(&self, namespace: &str, value: &JsonValue) -> bool {
+        let Some(current) = self.namespaces.get(namespace).and_then(|keys| keys.first()) else {
+            return false;
+        };
+        match envelope(value) {
+            Some(envelope) => envelope.get("kid").and_then(JsonValue::as_str) != Some(&current.id),
+            None => true,
+        }
+    }
+}
+
+/// Whether stored properties are encrypted
+pub fn is_envelope(value: &JsonValue) -> bool {
+    envelope(value).is_some()
+}
+
+fn envelope(value: &JsonValue) -> Option<&JsonValue> {
+    let object = value.as_object()?;
+    if object.len() != 1 {
+        return None;
+    }
+    object.get(ENVELOPE_FIELD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> NamespaceKey {
+        NamespaceKey::from_bytes(&[byte; KEY_LEN]).unwrap()
+    }
+
+    #[test]
+    fn properties_round_trip_in_encrypted_namespaces_only() {
+        let encryption = GraphEncryption::new()
+            .with_namespace("personal", vec![key(1)])
+            .unwrap();
+        let properties = json!({"name": "Ada", "notes": ["private"]});
+
+        let stored = encryption.encrypt("personal", &properties).unwrap();
+        assert!(is_envelope(&stored));
+        assert!(!stored.to_string().contains("Ada"));
+        assert_eq!(
+            encryption.decrypt("personal", stored.clone()).unwrap(),
+            properties
+        );
+
+        // Already encrypted values are not wrapped twice
+        assert_eq!(encryption.encrypt("personal", &stored).unwrap(), stored);
+        // Other namespaces are untouched
+        assert_eq!(encryption.encrypt("work", &properties).unwrap(), properties);
+    }
+
+    #[test]
+    fn ciphertext_is_bound_to_its_namespace() {
+        let encryption = GraphEncryption::new()
+            .with_namespace("personal", vec![key(1)])
+            .unwrap()
+            .with_namespace("other", vec![key(1)])
+            .unwrap();
+        let stored = encryption.encrypt("personal", &json!({"a": 1})).unwrap();
+
+        assert!(encryption.decrypt("other", stored).is_err());
+    }
+
+    #[test]
+    fn old_keys_decrypt_after_rotation() {
+        let before = GraphEncryption::new()
+            .with_namespace("personal", vec![key(1)])
+            .unwrap();
+        let stored = before.encrypt("personal", &json!({"a": 1})).unwrap();
+
+        let after = GraphEncryption::new()
+            .with_namespace("personal", vec![key(2), key(1)])
+            .unwrap();
+        assert!(after.needs_reencryption("personal", &stored));
+        assert_eq!(after.decrypt("personal", stored).unwrap(), json!({"a": 1}));
+
+        let rotated = after.encrypt("personal", &json!({"a": 1})).unwrap();
+        assert!(!after.needs_reencryption("personal", &rotated));
+        assert!(before.decrypt("personal", rotated).is_err());
+    }
+
+    #[test]
+    fn keys_must_be_32_bytes_of_base64() {
+        assert!(NamespaceKey::from_base64(&STANDARD.encode([7u8; KEY_LEN])).is_ok());
+        assert!(NamespaceKey::from_base64(&STANDARD.encode([7u8; 16])).is_err());
+        assert!(NamespaceKey::from_base64("not base64!").is_err());
+        assert!(GraphEncryption::new()
+            .with_namespace("personal", Vec::new())
+            .is_err());
+    }
+}
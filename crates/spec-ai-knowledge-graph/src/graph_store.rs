@@ -1,3 +1,4 @@
+use crate::encryption::GraphEncryption;
 use crate::types::{EdgeType, GraphEdge, GraphNode, GraphPath, NodeType, TraversalDirection};
 use crate::vector_clock::VectorClock;
 use anyhow::Result;
@@ -11,6 +12,7 @@ use std::sync::{Arc, Mutex, MutexGuard};
 pub struct KnowledgeGraphStore {
     conn: Arc<Mutex<Connection>>,
     instance_id: String,
+    encryption: Arc<GraphEncryption>,
 }
 
 impl KnowledgeGraphStore {
@@ -18,6 +20,7 @@ impl KnowledgeGraphStore {
         Self {
             conn,
             instance_id: instance_id.into(),
+            encryption: Arc::default(),
         }
     }
 
@@ -25,14 +28,25 @@ impl KnowledgeGraphStore {
         Self {
             conn: Arc::new(Mutex::new(conn)),
             instance_id: instance_id.into(),
+            encryption: Arc::default(),
         }
     }
 
+    /// Encrypt node and edge properties of the namespaces in `encryption`
+    pub fn with_encryption(mut self, encryption: GraphEncryption) -> Self {
+        self.encryption = Arc::new(encryption);
+        self
+    }
+
+    pub fn encryption(&self) -> &GraphEncryption {
+        &self.encryption
+    }
+
     pub fn instance_id(&self) -> &str {
         &self.instance_id
     }
 
-    fn conn(&self) -> MutexGuard<'_, Connection> {
+    pub(crate) fn conn(&self) -> MutexGuard<'_, Connection> {
         self.conn.lock().expect("database connection poisoned")
     }
 
@@ -53,6 +67,7 @@ impl KnowledgeGraphStore {
         let mut vector_clock = VectorClock::new();
         vector_clock.increment(&self.instance_id);
         let vc_json = vector_clock.to_json()?;
+        let properties = &self.encryption.encrypt(session_id, properties)?;
 
         let conn = self.conn();
 
@@ -108,7 +123,7 @@ impl KnowledgeGraphStore {
         )?;
         let mut rows = stmt.query(params![node_id])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(Self::row_to_graph_node(row)?))
+            Ok(Some(self.row_to_graph_node(row)?))
         } else {
             Ok(None)
         }
@@ -130,7 +145,7 @@ impl KnowledgeGraphStore {
                  ORDER BY id DESC LIMIT ?",
             )?;
             let query = stmt.query(params![session_id, nt.as_str(), limit.unwrap_or(100)])?;
-            Self::collect_graph_nodes(query)?
+            self.collect_graph_nodes(query)?
         } else {
             let mut stmt = conn.prepare(
                 "SELECT id, session_id, node_type, label, properties, embedding_id,
@@ -139,7 +154,7 @@ impl KnowledgeGraphStore {
                  ORDER BY id DESC LIMIT ?",
             )?;
             let query = stmt.query(params![session_id, limit.unwrap_or(100)])?;
-            Self::collect_graph_nodes(query)?
+            self.collect_graph_nodes(query)?
         };
 
         Ok(nodes)
@@ -183,6 +198,7 @@ impl KnowledgeGraphStore {
         };
         vector_clock.increment(&self.instance_id);
         let vc_json = vector_clock.to_json()?;
+        let properties = &self.encryption.encrypt(&session_id, properties)?;
 
         conn.execute(
             "UPDATE graph_nodes
@@ -298,6 +314,10 @@ impl KnowledgeGraphStore {
         let mut vector_clock = VectorClock::new();
         vector_clock.increment(&self.instance_id);
         let vc_json = vector_clock.to_json()?;
+        let properties = properties
+            .map(|p| self.encryption.encrypt(session_id, p))
+            .transpose()?;
+        let properties = properties.as_ref();
 
         let conn = self.conn();
 
@@ -358,7 +378,7 @@ impl KnowledgeGraphStore {
         )?;
         let mut rows = stmt.query(params![edge_id])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(Self::row_to_graph_edge(row)?))
+            Ok(Some(self.row_to_graph_edge(row)?))
         } else {
             Ok(None)
         }
@@ -380,7 +400,7 @@ impl KnowledgeGraphStore {
                      FROM graph_edges WHERE session_id = ? AND source_id = ? AND target_id = ?",
                 )?;
                 let query = stmt.query(params![session_id, src, tgt])?;
-                Self::collect_graph_edges(query)?
+                self.collect_graph_edges(query)?
             }
             (Some(src), None) => {
                 let mut stmt = conn.prepare(
@@ -389,7 +409,7 @@ impl KnowledgeGraphStore {
                      FROM graph_edges WHERE session_id = ? AND source_id = ?",
                 )?;
                 let query = stmt.query(params![session_id, src])?;
-                Self::collect_graph_edges(query)?
+                self.collect_graph_edges(query)?
             }
             (None, Some(tgt)) => {
                 let mut stmt = conn.prepare(
@@ -398,7 +418,7 @@ impl KnowledgeGraphStore {
                      FROM graph_edges WHERE session_id = ? AND target_id = ?",
                 )?;
                 let query = stmt.query(params![session_id, tgt])?;
-                Self::collect_graph_edges(query)?
+                self.collect_graph_edges(query)?
             }
             (None, None) => {
                 let mut stmt = conn.prepare(
@@ -407,7 +427,7 @@ impl KnowledgeGraphStore {
                      FROM graph_edges WHERE session_id = ?",
                 )?;
                 let query = stmt.query(params![session_id])?;
-                Self::collect_graph_edges(query)?
+                self.collect_graph_edges(query)?
             }
         };
 
@@ -609,7 +629,7 @@ impl KnowledgeGraphStore {
         Ok(result)
     }
 
-    fn row_to_graph_node(row: &duckdb::Row) -> Result<GraphNode> {
+    fn row_to_graph_node(&self, row: &duckdb::Row) -> Result<GraphNode> {
         let id: i64 = row.get(0)?;
         let session_id: String = row.get(1)?;
         let node_type: String = row.get(2)?;
@@ -619,19 +639,22 @@ impl KnowledgeGraphStore {
         let created_at: String = row.get(6)?;
         let updated_at: String = row.get(7)?;
 
+        let properties = serde_json::from_str(&properties).unwrap_or(JsonValue::Null);
+        let properties = self.encryption.decrypt(&session_id, properties)?;
+
         Ok(GraphNode {
             id,
             session_id,
             node_type: NodeType::from_str(&node_type),
             label,
-            properties: serde_json::from_str(&properties).unwrap_or(JsonValue::Null),
+            properties,
             embedding_id,
             created_at: created_at.parse().unwrap_or_else(|_| Utc::now()),
             updated_at: updated_at.parse().unwrap_or_else(|_| Utc::now()),
         })
     }
 
-    fn row_to_graph_edge(row: &duckdb::Row) -> Result<GraphEdge> {
+    fn row_to_graph_edge(&self, row: &duckdb::Row) -> Result<GraphEdge> {
         let id: i64 = row.get(0)?;
         let session_id: String = row.get(1)?;
         let source_id: i64 = row.get(2)?;
@@ -643,6 +666,10 @@ impl KnowledgeGraphStore {
         let temporal_start: Option<String> = row.get(8)?;
         let temporal_end: Option<String> = row.get(9)?;
         let created_at: String = row.get(10)?;
+        let properties = properties
+            .and_then(|p| serde_json::from_str(&p).ok())
+            .map(|p| self.encryption.decrypt(&session_id, p))
+            .transpose()?;
 
         Ok(GraphEdge {
             id,
@@ -651,7 +678,7 @@ impl KnowledgeGraphStore {
             target_id,
             edge_type: EdgeType::from_str(&edge_type),
             predicate,
-            properties: properties.and_then(|p| serde_json::from_str(&p).ok()),
+            properties,
             weight,
             temporal_start: temporal_start.and_then(|s| s.parse().ok()),
             temporal_end: temporal_end.and_then(|s| s.parse().ok()),
@@ -659,18 +686,18 @@ impl KnowledgeGraphStore {
         })
     }
 
-    fn collect_graph_nodes(mut rows: duckdb::Rows) -> Result<Vec<GraphNode>> {
+    fn collect_graph_nodes(&self, mut rows: duckdb::Rows) -> Result<Vec<GraphNode>> {
         let mut nodes = Vec::new();
         while let Some(row) = rows.next()? {
-            nodes.push(Self::row_to_graph_node(row)?);
+            nodes.push(self.row_to_graph_node(row)?);
         }
         Ok(nodes)
     }
 
-    fn collect_graph_edges(mut rows: duckdb::Rows) -> Result<Vec<GraphEdge>> {
+    fn collect_graph_edges(&self, mut rows: duckdb::Rows) -> Result<Vec<GraphEdge>> {
         let mut edges = Vec::new();
         while let Some(row) = rows.next()? {
-            edges.push(Self::row_to_graph_edge(row)?);
+            edges.push(self.row_to_graph_edge(row)?);
         }
         Ok(edges)
     }
@@ -715,6 +742,63 @@ impl KnowledgeGraphStore {
         })
     }
 
+    // ---------- Encryption ----------
+
+    /// Rewrite the properties of `session_id` with its current key
+    ///
+    /// Encrypts properties stored before the namespace was encrypted and
+    /// moves data written with older keys to the current one, after which
+    /// the older keys can be dropped. Vector clocks are left alone since the
+    /// content does not change. Returns the number of nodes and edges
+    /// rewritten.
+    pub fn reencrypt_namespace(&self, session_id: &str) -> Result<usize> {
+        if !self.encryption.is_encrypted(session_id) {
+            anyhow::bail!("Namespace '{}' has no encryption keys", session_id);
+        }
+
+        let conn = self.conn();
+        let mut rewritten = 0;
+        for table in ["graph_nodes", "graph_edges"] {
+            let stored: Vec<(i64, String)> = {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT id, properties FROM {} WHERE session_id = ? AND properties IS NOT NULL",
+                    table
+                ))?;
+                let rows =
+                    stmt.query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<Result<_, _>>()?
+            };
+
+            for (id, properties) in stored {
+                let properties: JsonValue = serde_json::from_str(&properties)?;
+                if !self.encryption.needs_reencryption(session_id, &properties) {
+                    continue;
+                }
+                let plain = self.encryption.decrypt(session_id, properties)?;
+                let sealed = self.encryption.encrypt(session_id, &plain)?;
+                conn.execute(
+                    &format!("UPDATE {} SET properties = ? WHERE id = ?", table),
+                    params![sealed.to_string(), id],
+                )?;
+                rewritten += 1;
+            }
+        }
+        Ok(rewritten)
+    }
+
+    fn open_synced_node(&self, mut node: SyncedNodeRecord) -> Result<SyncedNodeRecord> {
+        node.properties = self.encryption.decrypt(&node.session_id, node.properties)?;
+        Ok(node)
+    }
+
+    fn open_synced_edge(&self, mut edge: SyncedEdgeRecord) -> Result<SyncedEdgeRecord> {
+        edge.properties = edge
+            .properties
+            .map(|p| self.encryption.decrypt(&edge.session_id, p))
+            .transpose()?;
+        Ok(edge)
+    }
+
     // ===== Graph Synchronization Methods =====
 
     pub fn graph_changelog_append(
@@ -1085,7 +1169,7 @@ impl KnowledgeGraphStore {
             SyncedNodeRecord::from_row,
         );
         match result {
-            Ok(node) => Ok(Some(node)),
+            Ok(node) => Ok(Some(self.open_synced_node(node)?)),
             Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
         }
@@ -1117,7 +1201,7 @@ impl KnowledgeGraphStore {
         let mut rows = stmt.query(params![session_id])?;
         let mut nodes = Vec::new();
         while let Some(row) = rows.next()? {
-            nodes.push(SyncedNodeRecord::from_row(row)?);
+            nodes.push(self.open_synced_node(SyncedNodeRecord::from_row(row)?)?);
         }
         Ok(nodes)
     }
@@ -1133,7 +1217,7 @@ impl KnowledgeGraphStore {
             SyncedEdgeRecord::from_row,
         );
         match result {
-            Ok(edge) => Ok(Some(edge)),
+            Ok(edge) => Ok(Some(self.open_synced_edge(edge)?)),
             Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
         }
@@ -1165,7 +1249,7 @@ impl KnowledgeGraphStore {
         let mut rows = stmt.query(params![session_id])?;
         let mut edges = Vec::new();
         while let Some(row) = rows.next()? {
-            edges.push(SyncedEdgeRecord::from_row(row)?);
+            edges.push(self.open_synced_edge(SyncedEdgeRecord::from_row(row)?)?);
         }
         Ok(edges)
     }
@@ -1407,12 +1491,12 @@ impl SyncedEdgeRecord {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use anyhow::Result;
     use serde_json::json;
 
-    fn setup_store() -> KnowledgeGraphStore {
+    pub(crate) fn setup_store() -> KnowledgeGraphStore {
         setup_store_with(|_| {})
     }
 
@@ -1688,4 +1772,83 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn encrypted_namespaces_store_ciphertext_and_rotate() -> Result<()> {
+        use crate::encryption::{is_envelope, NamespaceKey};
+
+        let old_key = NamespaceKey::from_bytes(&[1; 32])?;
+        let new_key = NamespaceKey::from_bytes(&[2; 32])?;
+        let store = setup_store();
+        let plain_id =
+            store.insert_graph_node("personal", NodeType::Fact, "Plain", &json!({"a": 1}), None)?;
+
+        let store = store.with_encryption(
+            GraphEncryption::new().with_namespace("personal", vec![old_key.clone()])?,
+        );
+        let props = json!({ "birthday": "1815-12-10" });
+        let a = store.insert_graph_node("personal", NodeType::Fact, "Ada", &props, None)?;
+        let b = store.insert_graph_node("work", NodeType::Entity, "Job", &props, None)?;
+        store.insert_graph_edge(
+            "personal",
+            a,
+            b,
+            EdgeType::RelatesTo,
+            None,
+            Some(&props),
+            1.0,
+        )?;
+
+        let raw = |table: &str, session: &str| -> Vec<JsonValue> {
+            let conn = store.conn();
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT properties FROM {} WHERE session_id = ? ORDER BY id",
+                    table
+                ))
+                .unwrap();
+            let values = stmt
+                .query_map(params![session], |row| row.get::<_, String>(0))
+                .unwrap()
+                .map(|p| serde_json::from_str(&p.unwrap()).unwrap())
+                .collect();
+            values
+        };
+        let stored = raw("graph_nodes", "personal");
+        assert!(!is_envelope(&stored[0]));
+        assert!(is_envelope(&stored[1]));
+        assert!(is_envelope(&raw("graph_edges", "personal")[0]));
+        assert_eq!(raw("graph_nodes", "work")[0], props);
+
+        // Reads see plain text while labels stay queryable
+        let node = store.get_graph_node(a)?.expect("node exists");
+        assert_eq!((node.label.as_str(), &node.properties), ("Ada", &props));
+        let edges = store.list_graph_edges("personal", None, None)?;
+        assert_eq!(edges[0].properties.as_ref(), Some(&props));
+        let synced = store.graph_list_nodes_with_sync("personal", false, false)?;
+        assert!(synced.iter().all(|n| !is_envelope(&n.properties)));
+
+        // Rotate: new key first, old key kept until the namespace is rewritten
+        let store = store.with_encryption(
+            GraphEncryption::new().with_namespace("personal", vec![new_key.clone(), old_key])?,
+        );
+        assert_eq!(store.reencrypt_namespace("personal")?, 3);
+        assert_eq!(store.reencrypt_namespace("personal")?, 0);
+        assert!(store.reencrypt_namespace("work").is_err());
+
+        let store = store
+            .with_encryption(GraphEncryption::new().with_namespace("personal", vec![new_key])?);
+        assert_eq!(
+            store.get_graph_node(a)?.expect("node exists").properties,
+            props
+        );
+        assert_eq!(
+            store
+                .get_graph_node(plain_id)?
+                .expect("node exists")
+                .properties,
+            json!({"a": 1})
+        );
+        Ok(())
+    }
 }
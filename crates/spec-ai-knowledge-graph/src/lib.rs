@@ -1,11 +1,15 @@
+pub mod encryption;
 pub mod graph_store;
+pub mod maintenance;
 pub mod types;
 pub mod vector_clock;
 
+pub use encryption::{GraphEncryption, NamespaceKey};
 pub use graph_store::{
     ChangelogEntry, GraphSyncConfig, KnowledgeGraphStore, SyncStateRecord, SyncedEdgeRecord,
     SyncedNodeRecord,
 };
+pub use maintenance::{EntityMerge, TombstonePurge, GRAPH_SUMMARY_LABEL};
 pub use types::{
     EdgeType, GraphEdge, GraphNode, GraphPath, GraphQuery, GraphQueryResult, GraphQueryReturnType,
     NodeType, TraversalDirection,
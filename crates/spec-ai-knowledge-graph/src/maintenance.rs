@@ -0,0 +1,407 @@
+//! Housekeeping for long-lived graphs
+//!
+//! Graphs collect rows soft-deleted by graph sync, duplicate entities
+//! extracted from different messages and indexes fragmented by churn. The
+//! operations here back the built-in graph maintenance workflows.
+
+use crate::graph_store::KnowledgeGraphStore;
+use crate::types::NodeType;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection};
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::BTreeMap;
+
+/// Label of the node summarization keeps in every session
+pub const GRAPH_SUMMARY_LABEL: &str = "graph summary";
+
+/// Indexes over the graph tables, as created by the migrations
+const GRAPH_INDEXES: &[(&str, &str, &str)] = &[
+    ("idx_graph_nodes_session", "graph_nodes", "session_id"),
+    ("idx_graph_nodes_type", "graph_nodes", "node_type"),
+    ("idx_graph_nodes_label", "graph_nodes", "label"),
+    ("idx_graph_nodes_embedding", "graph_nodes", "embedding_id"),
+    (
+        "idx_graph_nodes_sync",
+        "graph_nodes",
+        "sync_enabled, session_id",
+    ),
+    ("idx_graph_nodes_deleted", "graph_nodes", "is_deleted"),
+    (
+        "idx_graph_nodes_modified",
+        "graph_nodes",
+        "last_modified_by",
+    ),
+    ("idx_graph_edges_session", "graph_edges", "session_id"),
+    ("idx_graph_edges_source", "graph_edges", "source_id"),
+    ("idx_graph_edges_target", "graph_edges", "target_id"),
+    ("idx_graph_edges_type", "graph_edges", "edge_type"),
+    (
+        "idx_graph_edges_temporal",
+        "graph_edges",
+        "temporal_start, temporal_end",
+    ),
+    (
+        "idx_graph_edges_sync",
+        "graph_edges",
+        "sync_enabled, session_id",
+    ),
+    ("idx_graph_edges_deleted", "graph_edges", "is_deleted"),
+    (
+        "idx_graph_edges_modified",
+        "graph_edges",
+        "last_modified_by",
+    ),
+];
+
+/// Rows removed by [`KnowledgeGraphStore::purge_tombstones`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TombstonePurge {
+    pub nodes: usize,
+    pub edges: usize,
+}
+
+/// Outcome of [`KnowledgeGraphStore::resolve_entities`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EntityMerge {
+    /// Duplicate nodes merged away
+    pub nodes: usize,
+    /// Edges moved to the surviving nodes
+    pub edges: usize,
+}
+
+impl KnowledgeGraphStore {
+    /// Remove nodes and edges soft-deleted by graph sync before `older_than`
+    ///
+    /// Edges record no deletion time, so their age counts from creation.
+    /// Edges still attached to a purged node go with it.
+    pub fn purge_tombstones(&self, older_than: DateTime<Utc>) -> Result<TombstonePurge> {
+        let conn = self.conn();
+        let cutoff = older_than.to_rfc3339();
+        let edges = conn.execute(
+            "DELETE FROM graph_edges
+             WHERE (is_deleted AND created_at < ?)
+                OR source_id IN (SELECT id FROM graph_nodes WHERE is_deleted AND updated_at < ?)
+                OR target_id IN (SELECT id FROM graph_nodes WHERE is_deleted AND updated_at < ?)",
+            params![cutoff, cutoff, cutoff],
+        )?;
+        let nodes = conn.execute(
+            "DELETE FROM graph_nodes WHERE is_deleted AND updated_at < ?",
+            params![cutoff],
+        )?;
+        Ok(TombstonePurge { nodes, edges })
+    }
+
+    /// Drop and recreate the graph indexes; returns how many were rebuilt
+    pub fn rebuild_indexes(&self) -> Result<usize> {
+        let conn = self.conn();
+        for (name, table, columns) in GRAPH_INDEXES {
+            conn.execute_batch(&format!(
+                "DROP INDEX IF EXISTS {name};
+                 CREATE INDEX {name} ON {table}({columns});"
+            ))?;
+        }
+        Ok(GRAPH_INDEXES.len())
+    }
+
+    /// Merge entity nodes of a session whose labels match ignoring case and
+    /// spacing into the oldest of them
+    ///
+    /// Edges move to the surviving node, which also gains the properties it
+    /// lacked. Nodes shared through graph sync are left alone, since peers
+    /// would not learn of the merge.
+    pub fn resolve_entities(&self) -> Result<EntityMerge> {
+        let conn = self.conn();
+        let nodes: Vec<(i64, String, String, String)> = {
+            let mut stmt = conn.prepare(
+                "SELECT id, session_id, label, properties FROM graph_nodes
+                 WHERE node_type = ?
+                   AND NOT COALESCE(is_deleted, FALSE)
+                   AND NOT COALESCE(sync_enabled, FALSE)
+                 ORDER BY id",
+            )?;
+            let rows = stmt.query_map(params![NodeType::Entity.as_str()], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<Result<_, _>>()?
+        };
+
+        let mut groups: BTreeMap<(String, String), Vec<(i64, String)>> = BTreeMap::new();
+        for (id, session_id, label, properties) in nodes {
+            groups
+                .entry((session_id, normalize_label(&label)))
+                .or_default()
+                .push((id, properties));
+        }
+
+        let mut merge = EntityMerge::default();
+        for ((session_id, _), mut group) in groups {
+            if group.len() < 2 {
+                continue;
+            }
+            let (keeper, properties) = group.remove(0);
+            let mut merged = self.open_properties(&session_id, &properties)?;
+            for (duplicate, properties) in group {
+                for (key, value) in self.open_properties(&session_id, &properties)? {
+                    merged.entry(key).or_insert(value);
+                }
+                merge.edges += move_edges(&conn, duplicate, keeper)?;
+                conn.execute("DELETE FROM graph_nodes WHERE id = ?", params![duplicate])?;
+                merge.nodes += 1;
+            }
+            let sealed = self
+                .encryption()
+                .encrypt(&session_id, &JsonValue::Object(merged))?;
+            conn.execute(
+                "UPDATE graph_nodes SET properties = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                params![sealed.to_string(), keeper],
+            )?;
+        }
+        Ok(merge)
+    }
+
+    /// Keep a node labelled [`GRAPH_SUMMARY_LABEL`] in every session with its
+    /// node counts by type, its edge count and its `top` best connected
+    /// entities; returns the number of summaries written
+    ///
+    /// Summaries that would not change are left untouched.
+    pub fn summarize_sessions(&self, top: usize) -> Result<usize> {
+        let summaries = {
+            let conn = self.conn();
+            let sessions: Vec<String> = {
+                let mut stmt = conn.prepare(
+                    "SELECT DISTINCT session_id FROM graph_nodes
+                     WHERE NOT COALESCE(is_deleted, FALSE) ORDER BY session_id",
+                )?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                rows.collect::<Result<_, _>>()?
+            };
+            sessions
+                .into_iter()
+                .map(|session_id| {
+                    let summary = session_summary(&conn, &session_id, top)?;
+                    let existing = summary_node_id(&conn, &session_id)?;
+                    Ok((session_id, summary, existing))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let mut written = 0;
+        for (session_id, summary, existing) in summaries {
+            match existing {
+                Some(node_id) => {
+                    let unchanged = self
+                        .get_graph_node(node_id)?
+                        .is_some_and(|node| node.properties == summary);
+                    if unchanged {
+                        continue;
+                    }
+                    self.update_graph_node(node_id, &summary)?;
+                }
+                None => {
+                    self.insert_graph_node(
+                        &session_id,
+                        NodeType::Concept,
+                        GRAPH_SUMMARY_LABEL,
+                        &summary,
+                        None,
+                    )?;
+                }
+            }
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    fn open_properties(&self, session_id: &str, stored: &str) -> Result<Map<String, JsonValue>> {
+        let properties = self
+            .encryption()
+            .decrypt(session_id, serde_json::from_str(stored)?)?;
+        Ok(match properties {
+            JsonValue::Object(map) => map,
+            _ => Map::new(),
+        })
+    }
+}
+
+/// Lowercase `label` and collapse its whitespace
+fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Point the edges of `from` at `to`, dropping those between the two that
+/// would become self-loops; returns the number of edges moved
+fn move_edges(conn: &Connection, from: i64, to: i64) -> Result<usize> {
+    let moved = conn.execute(
+        "INSERT INTO graph_edges
+             (session_id, source_id, target_id, edge_type, predicate, properties, weight,
+              temporal_start, temporal_end, created_at, vector_clock, last_modified_by,
+              is_deleted, sync_enabled)
+         SELECT session_id,
+                CASE WHEN source_id = ? THEN ? ELSE source_id END,
+                CASE WHEN target_id = ? THEN ? ELSE target_id END,
+                edge_type, predicate, properties, weight, temporal_start, temporal_end,
+                created_at, vector_clock, last_modified_by, is_deleted, sync_enabled
+         FROM graph_edges
+         WHERE (source_id = ? OR target_id = ?)
+           AND NOT (source_id IN (?, ?) AND target_id IN (?, ?))",
+        params![from, to, from, to, from, from, from, to, from, to],
+    )?;
+    conn.execute(
+        "DELETE FROM graph_edges WHERE source_id = ? OR target_id = ?",
+        params![from, from],
+    )?;
+    Ok(moved)
+}
+
+/// Node holding the summary of `session_id`, if one was written before
+fn summary_node_id(conn: &Connection, session_id: &str) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM graph_nodes
+         WHERE session_id = ? AND node_type = ? AND label = ?
+         ORDER BY id LIMIT 1",
+    )?;
+    let mut rows = stmt.query(params![
+        session_id,
+        NodeType::Concept.as_str(),
+        GRAPH_SUMMARY_LABEL
+    ])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Counts and best connected entities of one session, leaving out its
+/// summary node
+fn session_summary(conn: &Connection, session_id: &str, top: usize) -> Result<JsonValue> {
+    let mut nodes = Map::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT node_type, COUNT(*) FROM graph_nodes
+             WHERE session_id = ? AND NOT COALESCE(is_deleted, FALSE)
+               AND NOT (node_type = ? AND label = ?)
+             GROUP BY node_type ORDER BY node_type",
+        )?;
+        let rows = stmt.query_map(
+            params![session_id, NodeType::Concept.as_str(), GRAPH_SUMMARY_LABEL],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        )?;
+        for row in rows {
+            let (node_type, count) = row?;
+            nodes.insert(node_type, json!(count));
+        }
+    }
+
+    let edges: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM graph_edges
+         WHERE session_id = ? AND NOT COALESCE(is_deleted, FALSE)",
+        params![session_id],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT n.label, COUNT(e.id) AS degree
+         FROM graph_nodes n
+         JOIN graph_edges e
+           ON (e.source_id = n.id OR e.target_id = n.id) AND NOT COALESCE(e.is_deleted, FALSE)
+         WHERE n.session_id = ? AND n.node_type = ? AND NOT COALESCE(n.is_deleted, FALSE)
+         GROUP BY n.id, n.label
+         ORDER BY degree DESC, n.id
+         LIMIT ?",
+    )?;
+    let rows = stmt.query_map(
+        params![session_id, NodeType::Entity.as_str(), top as i64],
+        |row| {
+            Ok(json!({
+                "label": row.get::<_, String>(0)?,
+                "degree": row.get::<_, i64>(1)?,
+            }))
+        },
+    )?;
+    let top_entities = rows.collect::<Result<Vec<_>, _>>()?;
+
+    Ok(json!({
+        "nodes": nodes,
+        "edges": edges,
+        "top_entities": top_entities,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_store::tests::setup_store;
+    use crate::types::EdgeType;
+    use chrono::Duration;
+
+    #[test]
+    fn labels_match_ignoring_case_and_spacing() {
+        assert_eq!(normalize_label("  Ada   Lovelace "), "ada lovelace");
+        assert_ne!(normalize_label("Ada"), normalize_label("Ada Lovelace"));
+    }
+
+    #[test]
+    fn tombstones_are_purged_with_their_edges() -> Result<()> {
+        let store = setup_store();
+        let kept = store.insert_graph_node("s", NodeType::Entity, "kept", &json!({}), None)?;
+        let gone = store.insert_graph_node("s", NodeType::Entity, "gone", &json!({}), None)?;
+        store.insert_graph_edge("s", kept, gone, EdgeType::RelatesTo, None, None, 1.0)?;
+        store.graph_mark_node_deleted(gone, "{}", "peer")?;
+
+        let purge = store.purge_tombstones(Utc::now() - Duration::days(1))?;
+        assert_eq!(purge, TombstonePurge::default());
+
+        let purge = store.purge_tombstones(Utc::now() + Duration::minutes(1))?;
+        assert_eq!(purge, TombstonePurge { nodes: 1, edges: 1 });
+        assert!(store.get_graph_node(gone)?.is_none());
+        assert!(store.get_graph_node(kept)?.is_some());
+        assert_eq!(store.rebuild_indexes()?, GRAPH_INDEXES.len());
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_entities_merge_into_the_oldest() -> Result<()> {
+        let store = setup_store();
+        let ada = store.insert_graph_node("s", NodeType::Entity, "Ada", &json!({"a": 1}), None)?;
+        let twin = store.insert_graph_node(
+            "s",
+            NodeType::Entity,
+            " ada ",
+            &json!({"a": 2, "b": 3}),
+            None,
+        )?;
+        let other = store.insert_graph_node("s", NodeType::Entity, "Bob", &json!({}), None)?;
+        let elsewhere = store.insert_graph_node("t", NodeType::Entity, "Ada", &json!({}), None)?;
+        store.insert_graph_edge("s", twin, other, EdgeType::RelatesTo, None, None, 1.0)?;
+        store.insert_graph_edge("s", ada, twin, EdgeType::RelatesTo, None, None, 1.0)?;
+
+        let merge = store.resolve_entities()?;
+        assert_eq!(merge, EntityMerge { nodes: 1, edges: 1 });
+        assert!(store.get_graph_node(twin)?.is_none());
+        assert!(store.get_graph_node(elsewhere)?.is_some());
+        let ada_node = store.get_graph_node(ada)?.expect("keeper exists");
+        assert_eq!(ada_node.properties, json!({"a": 1, "b": 3}));
+        let edges = store.list_graph_edges("s", None, None)?;
+        assert_eq!(edges.len(), 1);
+        assert_eq!((edges[0].source_id, edges[0].target_id), (ada, other));
+
+        assert_eq!(store.summarize_sessions(5)?, 2);
+        assert_eq!(store.summarize_sessions(5)?, 0);
+        let summary = store
+            .list_graph_nodes("s", Some(NodeType::Concept), None)?
+            .pop()
+            .expect("summary written");
+        assert_eq!(summary.label, GRAPH_SUMMARY_LABEL);
+        assert_eq!(summary.properties["nodes"], json!({"entity": 2}));
+        assert_eq!(summary.properties["edges"], 1);
+        assert_eq!(summary.properties["top_entities"][0]["label"], "Ada");
+        Ok(())
+    }
+}
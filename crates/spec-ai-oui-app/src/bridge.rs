@@ -0,0 +1,482 @@
+//! Bidirectional message bus between the OUI and an agent backend
+//!
+//! Telemetry flows one way (receiver -> UI). The agent bridge uses the same
+//! unbounded channel architecture in both directions: the backend publishes
+//! [`AgentEvent`]s (responses, tool progress, alerts) and the UI sends back
+//! [`AgentCommand`]s (acknowledge an alert, trigger a query).
+//!
+//! [`api_agent_bridge`] connects to the agent of a running spec-ai API
+//! server; [`mock_agent_bridge`] simulates one for demos.
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+/// Severity of an agent alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertLevel {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl AlertLevel {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            AlertLevel::Info => "ℹ",
+            AlertLevel::Warning => "⚠",
+            AlertLevel::Critical => "‼",
+        }
+    }
+}
+
+/// Lifecycle of a tool invocation reported by the agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolState {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl ToolState {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ToolState::Running => "◌",
+            ToolState::Succeeded => "✓",
+            ToolState::Failed => "✗",
+        }
+    }
+}
+
+/// Events published by the agent backend
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    /// Assistant response to a query
+    Response {
+        query: Option<String>,
+        text: String,
+        timestamp: SystemTime,
+    },
+    /// Progress update for a tool invocation
+    ToolProgress {
+        call_id: String,
+        tool: String,
+        state: ToolState,
+        /// Completion ratio in `0.0..=1.0`, if the tool reports one
+        progress: Option<f32>,
+        message: Option<String>,
+    },
+    /// Alert that stays visible until acknowledged
+    Alert {
+        id: u64,
+        level: AlertLevel,
+        message: String,
+        timestamp: SystemTime,
+    },
+}
+
+/// Commands sent from the OUI to the agent backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentCommand {
+    /// Mark an alert as seen
+    Acknowledge { alert_id: u64 },
+    /// Ask the agent a question
+    TriggerQuery { query: String },
+}
+
+/// UI side of the bridge
+pub struct AgentBridge {
+    pub events_rx: mpsc::UnboundedReceiver<AgentEvent>,
+    pub commands_tx: mpsc::UnboundedSender<AgentCommand>,
+}
+
+/// Backend side of the bridge
+pub struct AgentEndpoint {
+    pub events_tx: mpsc::UnboundedSender<AgentEvent>,
+    pub commands_rx: mpsc::UnboundedReceiver<AgentCommand>,
+}
+
+/// Create a connected bridge/endpoint pair
+pub fn agent_channel() -> (AgentBridge, AgentEndpoint) {
+    let (events_tx, events_rx) = mpsc::unbounded_channel();
+    let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+    (
+        AgentBridge {
+            events_rx,
+            commands_tx,
+        },
+        AgentEndpoint {
+            events_tx,
+            commands_rx,
+        },
+    )
+}
+
+/// Create a mock agent backend for demo/testing purposes
+///
+/// Answers queries after a short simulated tool run and raises a periodic alert.
+pub fn mock_agent_bridge() -> AgentBridge {
+    let (bridge, endpoint) = agent_channel();
+    let AgentEndpoint {
+        events_tx: tx,
+        mut commands_rx,
+    } = endpoint;
+
+    tokio::spawn(async move {
+        let mut alert_counter = 0u64;
+        let mut call_counter = 0u64;
+        let mut alert_interval = tokio::time::interval(Duration::from_secs(15));
+
+        loop {
+            tokio::select! {
+                _ = alert_interval.tick() => {
+                    alert_counter += 1;
+                    let level = if alert_counter.is_multiple_of(3) {
+                        AlertLevel::Critical
+                    } else {
+                        AlertLevel::Warning
+                    };
+                    let sent = tx.send(AgentEvent::Alert {
+                        id: alert_counter,
+                        level,
+                        message: format!("Error rate above threshold (#{})", alert_counter),
+                        timestamp: SystemTime::now(),
+                    });
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+                command = commands_rx.recv() => {
+                    let Some(command) = command else { break };
+                    if let AgentCommand::TriggerQuery { query } = command {
+                        call_counter += 1;
+                        let call_id = format!("call-{}", call_counter);
+                        for step in 0..=4u8 {
+                            let _ = tx.send(AgentEvent::ToolProgress {
+                                call_id: call_id.clone(),
+                                tool: "search_traces".to_string(),
+                                state: if step == 4 { ToolState::Succeeded } else { ToolState::Running },
+                                progress: Some(step as f32 / 4.0),
+                                message: None,
+                            });
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                        }
+                        let _ = tx.send(AgentEvent::Response {
+                            text: format!("No anomalies found for \"{}\"", query),
+                            query: Some(query),
+                            timestamp: SystemTime::now(),
+                        });
+                    }
+                }
+            }
+        }
+    });
+
+    bridge
+}
+
+/// spec-ai API server an [`api_agent_bridge`] talks to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiAgentConfig {
+    /// Base URL of the server, such as `http://127.0.0.1:3000`
+    pub url: String,
+    /// Bearer token, when the server has auth enabled
+    pub token: Option<String>,
+    /// Agent profile to ask; the server's default agent when unset
+    pub agent: Option<String>,
+}
+
+impl ApiAgentConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            token: None,
+            agent: None,
+        }
+    }
+}
+
+/// Connect to the agent of a running spec-ai API server
+///
+/// Each query is sent to `POST /stream`, one at a time and in one session so
+/// follow-up questions keep their context. The agent's tool calls are shown
+/// as tool progress and its answer as a response; failed requests, failed
+/// runs and questions the agent asks back are raised as alerts. Alerts only
+/// exist on the HUD, so acknowledging one sends nothing to the server.
+pub fn api_agent_bridge(config: ApiAgentConfig) -> AgentBridge {
+    let (bridge, endpoint) = agent_channel();
+    let AgentEndpoint {
+        events_tx: tx,
+        mut commands_rx,
+    } = endpoint;
+
+    tokio::spawn(async move {
+        let http = reqwest::Client::new();
+        let mut session = ApiSession::default();
+        while let Some(command) = commands_rx.recv().await {
+            let AgentCommand::TriggerQuery { query } = command else {
+                continue;
+            };
+            let result = ask(&http, &config, &mut session, &query, &tx).await;
+            let alert = match result {
+                Ok(()) if session.running => Some("Agent stream ended early".to_string()),
+                Ok(()) => None,
+                Err(err) => Some(format!("Agent request failed: {:#}", err)),
+            };
+            session.running = false;
+            if let Some(message) = alert {
+                if tx
+                    .send(session.alert(AlertLevel::Critical, message))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+    });
+
+    bridge
+}
+
+/// Send `query` to `POST /stream` and forward what the agent does
+async fn ask(
+    http: &reqwest::Client,
+    config: &ApiAgentConfig,
+    session: &mut ApiSession,
+    query: &str,
+    tx: &mpsc::UnboundedSender<AgentEvent>,
+) -> Result<()> {
+    let mut request = http
+        .post(format!("{}/stream", config.url.trim_end_matches('/')))
+        .json(&json!({
+            "message": query,
+            "session_id": session.session_id,
+            "agent": config.agent,
+        }));
+    if let Some(token) = &config.token {
+        request = request.bearer_auth(token);
+    }
+
+    let mut response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body: Value = response.json().await.unwrap_or_default();
+        bail!("{} {}", status, body["error"].as_str().unwrap_or_default());
+    }
+
+    let mut events = EventData::default();
+    while let Some(chunk) = response.chunk().await? {
+        for data in events.push(&chunk) {
+            let Ok(event) = serde_json::from_str::<Value>(&data) else {
+                continue;
+            };
+            for event in session.handle(query, &event) {
+                if tx.send(event).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// State of an [`api_agent_bridge`] across queries
+#[derive(Debug, Default)]
+struct ApiSession {
+    /// Session the server opened for the first query
+    session_id: Option<String>,
+    alerts: u64,
+    /// Whether a stream has started and not yet ended
+    running: bool,
+    run_id: String,
+    tool_calls: u64,
+    /// Answer to the query in progress
+    text: String,
+}
+
+impl ApiSession {
+    /// HUD events for one `/stream` event answering `query`
+    fn handle(&mut self, query: &str, event: &Value) -> Vec<AgentEvent> {
+        let field = |name: &str| event[name].as_str().unwrap_or_default().to_string();
+        match event["type"].as_str() {
+            Some("start") => {
+                self.session_id = Some(field("session_id"));
+                self.run_id = field("run_id");
+                self.running = true;
+                self.text.clear();
+                Vec::new()
+            }
+            Some("chunk") => {
+                self.text.push_str(&field("text"));
+                Vec::new()
+            }
+            // Every tool call is followed by its result
+            Some("tool_call") => {
+                self.tool_calls += 1;
+                vec![self.tool_progress(field("name"), ToolState::Running, None)]
+            }
+            Some("tool_result") => {
+                let result = &event["result"];
+                let state = if result["success"].as_bool() == Some(true) {
+                    ToolState::Succeeded
+                } else {
+                    ToolState::Failed
+                };
+                let message = result["error"].as_str().map(str::to_string);
+                vec![self.tool_progress(field("name"), state, message)]
+            }
+            Some("question") => {
+                let question = event["question"]["question"].as_str().unwrap_or_default();
+                vec![self.alert(
+                    AlertLevel::Warning,
+                    format!(
+                        "Agent asks: {} (answer with POST /runs/{}/answer)",
+                        question, self.run_id
+                    ),
+                )]
+            }
+            Some("end") => {
+                self.running = false;
+                vec![AgentEvent::Response {
+                    query: Some(query.to_string()),
+                    text: std::mem::take(&mut self.text),
+                    timestamp: SystemTime::now(),
+                }]
+            }
+            Some("error") => {
+                self.running = false;
+                vec![self.alert(
+                    AlertLevel::Critical,
+                    format!("Agent failed: {}", field("message")),
+                )]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn tool_progress(&self, tool: String, state: ToolState, message: Option<String>) -> AgentEvent {
+        AgentEvent::ToolProgress {
+            call_id: format!("{}-{}", self.run_id, self.tool_calls),
+            tool,
+            state,
+            progress: None,
+            message,
+        }
+    }
+
+    fn alert(&mut self, level: AlertLevel, message: String) -> AgentEvent {
+        self.alerts += 1;
+        AgentEvent::Alert {
+            id: self.alerts,
+            level,
+            message,
+            timestamp: SystemTime::now(),
+        }
+    }
+}
+
+/// Collects the `data` of server-sent events split across chunks
+#[derive(Debug, Default)]
+struct EventData {
+    buffer: Vec<u8>,
+}
+
+impl EventData {
+    /// Add received bytes, returning the data of the events they complete
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer
+            .extend(bytes.iter().filter(|&&byte| byte != b'\r'));
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|pair| pair == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let block = String::from_utf8_lossy(&block);
+            let data: Vec<&str> = block
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|value| value.strip_prefix(' ').unwrap_or(value))
+                .collect();
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_channel_carries_both_directions() {
+        let (mut bridge, mut endpoint) = agent_channel();
+
+        bridge
+            .commands_tx
+            .send(AgentCommand::Acknowledge { alert_id: 7 })
+            .unwrap();
+        assert_eq!(
+            endpoint.commands_rx.recv().await,
+            Some(AgentCommand::Acknowledge { alert_id: 7 })
+        );
+
+        endpoint
+            .events_tx
+            .send(AgentEvent::Response {
+                query: None,
+                text: "ok".to_string(),
+                timestamp: SystemTime::now(),
+            })
+            .unwrap();
+        assert!(matches!(
+            bridge.events_rx.recv().await,
+            Some(AgentEvent::Response { text, .. }) if text == "ok"
+        ));
+    }
+
+    #[test]
+    fn test_event_data_is_split_across_chunks() {
+        let mut events = EventData::default();
+        assert!(events.push(b"data: {\"type\":\"start\"}\r").is_empty());
+        assert_eq!(
+            events.push(b"\n\r\n: keep-alive\n\ndata: a\ndata: b\n\ndata: c"),
+            ["{\"type\":\"start\"}", "a\nb"]
+        );
+        assert_eq!(events.push(b"\n\n"), ["c"]);
+    }
+
+    #[test]
+    fn test_stream_events_become_hud_events() {
+        let mut session = ApiSession::default();
+        let mut handle = |event: Value| session.handle("why slow?", &event);
+
+        assert!(handle(
+            json!({"type": "start", "session_id": "s1", "agent": "ops", "run_id": "r1"})
+        )
+        .is_empty());
+        assert!(handle(json!({"type": "chunk", "text": "The database is locked"})).is_empty());
+        assert!(matches!(
+            &handle(json!({"type": "tool_call", "name": "sql", "arguments": {}}))[..],
+            [AgentEvent::ToolProgress { call_id, state: ToolState::Running, .. }] if call_id == "r1-1"
+        ));
+        assert!(matches!(
+            &handle(json!({"type": "tool_result", "name": "sql", "result": {"success": false, "error": "locked"}}))[..],
+            [AgentEvent::ToolProgress { call_id, state: ToolState::Failed, message: Some(message), .. }]
+                if call_id == "r1-1" && message == "locked"
+        ));
+        assert!(matches!(
+            &handle(json!({"type": "end", "metadata": {}}))[..],
+            [AgentEvent::Response { query: Some(query), text, .. }]
+                if query == "why slow?" && text == "The database is locked"
+        ));
+        assert!(matches!(
+            &handle(json!({"type": "error", "message": "rate limited", "code": "rate_limited"}))[..],
+            [AgentEvent::Alert { id: 1, level: AlertLevel::Critical, message, .. }]
+                if message == "Agent failed: rate limited"
+        ));
+        assert_eq!(session.session_id.as_deref(), Some("s1"));
+        assert!(!session.running);
+    }
+}
@@ -0,0 +1,204 @@
+//! Sampling control channel to telemetry senders
+//!
+//! A minimal OpAMP-like protocol of newline-delimited JSON over TCP. Each
+//! sender connects, reports its service name and sample ratio, and reports
+//! again whenever the ratio changes. The app pushes new ratios down the
+//! same connection when the operator dials a service up or down:
+//!
+//! ```text
+//! sender → app  {"service":"spec-ai","sample_ratio":1.0}
+//! app → sender  {"sample_ratio":0.25}
+//! sender → app  {"service":"spec-ai","sample_ratio":0.25}
+//! ```
+//!
+//! spec-ai speaks this protocol when `telemetry.control_endpoint` is set.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+/// Sample ratios the operator steps through, lowest first
+pub const SAMPLE_RATIOS: [f64; 7] = [0.0, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// A sender's report of the ratio it applies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SenderReport {
+    pub service: String,
+    pub sample_ratio: f64,
+}
+
+/// A request for a sender to apply a new ratio
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SamplingCommand {
+    pub sample_ratio: f64,
+}
+
+/// Change in the senders connected to the control channel
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlEvent {
+    /// A sender connected or reported a new ratio
+    Reported {
+        connection: u64,
+        service: String,
+        sample_ratio: f64,
+    },
+    /// A sender hung up
+    Disconnected { connection: u64, service: String },
+}
+
+type Senders = Arc<Mutex<HashMap<u64, (String, mpsc::UnboundedSender<SamplingCommand>)>>>;
+
+/// Handle for the running control channel
+pub struct ControlHandle {
+    pub events_rx: mpsc::UnboundedReceiver<ControlEvent>,
+    pub local_addr: SocketAddr,
+    senders: Senders,
+}
+
+impl ControlHandle {
+    /// Ask every sender of `service` to sample `sample_ratio` of its traces
+    ///
+    /// Returns how many senders the request went to.
+    pub fn set_sample_ratio(&self, service: &str, sample_ratio: f64) -> usize {
+        let senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders
+            .values()
+            .filter(|(name, _)| name == service)
+            .filter(|(_, tx)| tx.send(SamplingCommand { sample_ratio }).is_ok())
+            .count()
+    }
+}
+
+/// Listen for senders on `addr`
+pub async fn start_control(addr: SocketAddr) -> anyhow::Result<ControlHandle> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    let (events_tx, events_rx) = mpsc::unbounded_channel();
+    let senders: Senders = Arc::default();
+
+    let registry = senders.clone();
+    tokio::spawn(async move {
+        let mut connection = 0;
+        while let Ok((stream, _)) = listener.accept().await {
+            connection += 1;
+            tokio::spawn(serve_sender(
+                connection,
+                stream,
+                registry.clone(),
+                events_tx.clone(),
+            ));
+        }
+    });
+
+    Ok(ControlHandle {
+        events_rx,
+        local_addr,
+        senders,
+    })
+}
+
+/// Relay one sender's reports and the commands for it until it hangs up
+async fn serve_sender(
+    connection: u64,
+    stream: TcpStream,
+    senders: Senders,
+    events: mpsc::UnboundedSender<ControlEvent>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let (commands_tx, mut commands_rx) = mpsc::unbounded_channel::<SamplingCommand>();
+    let mut service = None;
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Ok(Some(line)) = line else { break };
+                // Senders must open with a report; anything else ends the connection
+                let Ok(report) = serde_json::from_str::<SenderReport>(&line) else {
+                    if service.is_none() {
+                        break;
+                    }
+                    continue;
+                };
+                if service.as_ref() != Some(&report.service) {
+                    senders
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(connection, (report.service.clone(), commands_tx.clone()));
+                    service = Some(report.service.clone());
+                }
+                let _ = events.send(ControlEvent::Reported {
+                    connection,
+                    service: report.service,
+                    sample_ratio: report.sample_ratio,
+                });
+            }
+            Some(command) = commands_rx.recv() => {
+                let Ok(mut line) = serde_json::to_string(&command) else { continue };
+                line.push('\n');
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    senders
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&connection);
+    if let Some(service) = service {
+        let _ = events.send(ControlEvent::Disconnected {
+            connection,
+            service,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_senders_report_and_receive_ratios() {
+        let mut control = start_control("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let stream = TcpStream::connect(control.local_addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        writer
+            .write_all(b"{\"service\":\"checkout\",\"sample_ratio\":1.0}\n")
+            .await
+            .unwrap();
+        let reported = control.events_rx.recv().await.unwrap();
+        assert_eq!(
+            reported,
+            ControlEvent::Reported {
+                connection: 1,
+                service: "checkout".to_string(),
+                sample_ratio: 1.0,
+            }
+        );
+
+        assert_eq!(control.set_sample_ratio("payments", 0.5), 0);
+        assert_eq!(control.set_sample_ratio("checkout", 0.1), 1);
+        let command = lines.next_line().await.unwrap().unwrap();
+        assert_eq!(command, r#"{"sample_ratio":0.1}"#);
+
+        drop(writer);
+        drop(lines);
+        assert_eq!(
+            control.events_rx.recv().await.unwrap(),
+            ControlEvent::Disconnected {
+                connection: 1,
+                service: "checkout".to_string(),
+            }
+        );
+        assert_eq!(control.set_sample_ratio("checkout", 0.1), 0);
+    }
+}
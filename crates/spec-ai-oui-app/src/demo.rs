@@ -0,0 +1,583 @@
+//! Scripted demo walkthroughs
+//!
+//! A demo script is a TOML file of timed steps. Each step either feeds a
+//! telemetry event into the UI or replays a user input, so a walkthrough
+//! plays back identically every time:
+//!
+//! ```toml
+//! title = "Checkout errors"
+//!
+//! [[step]]
+//! at_ms = 0
+//! type = "span"
+//! service = "api-gateway"
+//! name = "POST /checkout"
+//! trace_id = "0af7651916cd43dd8448eb211c80319c"
+//! span_id = "b7ad6b7169203331"
+//! status = "error"
+//! duration_ms = 320
+//!
+//! [[step]]
+//! at_ms = 1500
+//! type = "voice"
+//! command = "filter errors"
+//!
+//! [[step]]
+//! at_ms = 3000
+//! type = "key"
+//! key = "tab"
+//! ```
+//!
+//! Scripts are played with `oui-demo --demo <file>`. Running with
+//! `--record <file>` captures a live session (telemetry from the OTLP
+//! receiver or mock stream, plus every key press) in the same format.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::telemetry::{
+    Annotation, LogRecord, MetricData, MetricValue, Severity, SpanData, SpanKind, SpanStatus,
+    TelemetryEvent,
+};
+
+/// Error loading or saving a demo script
+#[derive(Debug, Clone)]
+pub enum DemoError {
+    /// The script file could not be read or written
+    Io(String),
+    /// The script is not valid TOML or has unknown fields
+    Parse(String),
+    /// A step names a key that cannot be replayed
+    InvalidKey(String),
+}
+
+impl fmt::Display for DemoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DemoError::Io(msg) => write!(f, "Demo read error: {}", msg),
+            DemoError::Parse(msg) => write!(f, "Demo parse error: {}", msg),
+            DemoError::InvalidKey(key) => write!(f, "Unknown key in demo: {}", key),
+        }
+    }
+}
+
+impl std::error::Error for DemoError {}
+
+/// A span in a demo script
+///
+/// Timestamps are assigned at playback: a finished span ends when its step
+/// plays and started `duration_ms` earlier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoSpan {
+    pub service: String,
+    pub name: String,
+    pub trace_id: String,
+    pub span_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_span_id: Option<String>,
+    #[serde(default)]
+    pub kind: SpanKind,
+    #[serde(default)]
+    pub status: SpanStatus,
+    /// Span length; a span without one is still running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// A log record in a demo script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoLog {
+    pub service: String,
+    #[serde(default)]
+    pub severity: Severity,
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// A metric data point in a demo script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoMetric {
+    pub service: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub unit: String,
+    pub value: MetricValue,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// A timeline annotation in a demo script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoAnnotation {
+    pub service: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// What a demo step does when it plays
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DemoAction {
+    /// A span that starts (no duration) or finishes
+    Span(DemoSpan),
+    Log(DemoLog),
+    Metric(DemoMetric),
+    /// A labeled marker on the traces timeline
+    Annotation(DemoAnnotation),
+    /// A key press, e.g. `"j"`, `"tab"`, `"ctrl+q"`
+    Key {
+        key: String,
+    },
+    /// A recognized voice command
+    Voice {
+        command: String,
+    },
+}
+
+impl DemoAction {
+    /// Capture a telemetry event as a demo action
+    pub fn from_telemetry(event: &TelemetryEvent) -> Self {
+        match event {
+            TelemetryEvent::SpanStarted(span) | TelemetryEvent::SpanEnded(span) => {
+                DemoAction::Span(DemoSpan {
+                    service: span.service_name.clone(),
+                    name: span.name.clone(),
+                    trace_id: span.trace_id.clone(),
+                    span_id: span.span_id.clone(),
+                    parent_span_id: span.parent_span_id.clone(),
+                    kind: span.kind,
+                    status: span.status,
+                    duration_ms: span.duration().map(|d| d.as_millis() as u64),
+                    attributes: sorted(&span.attributes),
+                })
+            }
+            TelemetryEvent::Log(log) => DemoAction::Log(DemoLog {
+                service: log.service_name.clone(),
+                severity: log.severity,
+                body: log.body.clone(),
+                trace_id: log.trace_id.clone(),
+                span_id: log.span_id.clone(),
+                attributes: sorted(&log.attributes),
+            }),
+            TelemetryEvent::Metric(metric) => DemoAction::Metric(DemoMetric {
+                service: metric.service_name.clone(),
+                name: metric.name.clone(),
+                description: metric.description.clone(),
+                unit: metric.unit.clone(),
+                value: metric.value.clone(),
+                attributes: sorted(&metric.attributes),
+            }),
+            TelemetryEvent::Annotation(annotation) => DemoAction::Annotation(DemoAnnotation {
+                service: annotation.service_name.clone(),
+                label: annotation.label.clone(),
+                detail: annotation.detail.clone(),
+                severity: annotation.severity,
+            }),
+        }
+    }
+
+    /// Telemetry event for this action, timestamped at `now`
+    ///
+    /// Returns `None` for input actions.
+    pub fn telemetry(&self, now: SystemTime) -> Option<TelemetryEvent> {
+        let event = match self {
+            DemoAction::Span(span) => {
+                let duration = span.duration_ms.map(Duration::from_millis);
+                let data = SpanData {
+                    trace_id: span.trace_id.clone(),
+                    span_id: span.span_id.clone(),
+                    parent_span_id: span.parent_span_id.clone(),
+                    name: span.name.clone(),
+                    kind: span.kind,
+                    start_time: duration.map_or(now, |d| now - d),
+                    end_time: duration.map(|_| now),
+                    status: span.status,
+                    attributes: unsorted(&span.attributes),
+                    service_name: span.service.clone(),
+                };
+                if duration.is_some() {
+                    TelemetryEvent::SpanEnded(data)
+                } else {
+                    TelemetryEvent::SpanStarted(data)
+                }
+            }
+            DemoAction::Log(log) => TelemetryEvent::Log(LogRecord {
+                timestamp: now,
+                severity: log.severity,
+                body: log.body.clone(),
+                trace_id: log.trace_id.clone(),
+                span_id: log.span_id.clone(),
+                attributes: unsorted(&log.attributes),
+                service_name: log.service.clone(),
+            }),
+            DemoAction::Metric(metric) => TelemetryEvent::Metric(MetricData {
+                name: metric.name.clone(),
+                description: metric.description.clone(),
+                unit: metric.unit.clone(),
+                value: metric.value.clone(),
+                attributes: unsorted(&metric.attributes),
+                timestamp: now,
+                service_name: metric.service.clone(),
+            }),
+            DemoAction::Annotation(annotation) => TelemetryEvent::Annotation(Annotation {
+                label: annotation.label.clone(),
+                detail: annotation.detail.clone(),
+                severity: annotation.severity,
+                timestamp: now,
+                service_name: annotation.service.clone(),
+            }),
+            DemoAction::Key { .. } | DemoAction::Voice { .. } => return None,
+        };
+        Some(event)
+    }
+}
+
+fn sorted(attributes: &HashMap<String, String>) -> BTreeMap<String, String> {
+    attributes
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+fn unsorted(attributes: &BTreeMap<String, String>) -> HashMap<String, String> {
+    attributes
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+/// One timed step of a demo script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DemoStep {
+    /// Milliseconds from the start of playback
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub action: DemoAction,
+}
+
+/// A demo walkthrough: telemetry and inputs on a shared timeline
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoScript {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, rename = "step")]
+    pub steps: Vec<DemoStep>,
+}
+
+impl DemoScript {
+    /// Parse and validate a script from TOML
+    pub fn from_toml(source: &str) -> Result<Self, DemoError> {
+        let mut script: DemoScript =
+            toml::from_str(source).map_err(|e| DemoError::Parse(e.to_string()))?;
+        for step in &script.steps {
+            if let DemoAction::Key { key } = &step.action {
+                parse_key(key).ok_or_else(|| DemoError::InvalidKey(key.clone()))?;
+            }
+        }
+        // Steps may be written out of order; playback walks them by time
+        script.steps.sort_by_key(|step| step.at_ms);
+        Ok(script)
+    }
+
+    /// Load a script file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DemoError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| DemoError::Io(format!("{}: {}", path.display(), e)))?;
+        Self::from_toml(&source)
+    }
+
+    /// Serialize the script to TOML
+    pub fn to_toml(&self) -> Result<String, DemoError> {
+        toml::to_string(self).map_err(|e| DemoError::Parse(e.to_string()))
+    }
+
+    /// Write the script to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DemoError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_toml()?)
+            .map_err(|e| DemoError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Time of the last step
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.steps.last().map_or(0, |step| step.at_ms))
+    }
+}
+
+/// Plays a demo script against the wall clock
+#[derive(Debug)]
+pub struct DemoPlayer {
+    script: DemoScript,
+    started: Instant,
+    next: usize,
+}
+
+impl DemoPlayer {
+    /// Start playing a script now
+    pub fn new(script: DemoScript) -> Self {
+        Self::starting_at(script, Instant::now())
+    }
+
+    /// Start playing a script at `started`
+    pub fn starting_at(script: DemoScript, started: Instant) -> Self {
+        Self {
+            script,
+            started,
+            next: 0,
+        }
+    }
+
+    /// Actions that are due, in order
+    pub fn poll(&mut self) -> Vec<DemoAction> {
+        self.poll_at(Instant::now())
+    }
+
+    /// Actions due by `now`, in order
+    pub fn poll_at(&mut self, now: Instant) -> Vec<DemoAction> {
+        self.advance(now.saturating_duration_since(self.started))
+    }
+
+    /// Actions due by `elapsed` since playback started
+    pub fn advance(&mut self, elapsed: Duration) -> Vec<DemoAction> {
+        let elapsed_ms = elapsed.as_millis() as u64;
+        let due = self.script.steps[self.next..]
+            .iter()
+            .take_while(|step| step.at_ms <= elapsed_ms)
+            .map(|step| step.action.clone())
+            .collect::<Vec<_>>();
+        self.next += due.len();
+        due
+    }
+
+    /// Whether every step has played
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.script.steps.len()
+    }
+}
+
+/// Records a live session as a demo script
+#[derive(Debug)]
+pub struct DemoRecorder {
+    started: Instant,
+    steps: Vec<DemoStep>,
+}
+
+impl Default for DemoRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DemoRecorder {
+    /// Start recording now
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Record a telemetry event
+    pub fn record_telemetry(&mut self, event: &TelemetryEvent) {
+        self.push(DemoAction::from_telemetry(event));
+    }
+
+    /// Record a key press; keys that cannot be replayed are skipped
+    pub fn record_key(&mut self, key: &KeyEvent) {
+        if let Some(key) = key_name(key) {
+            self.push(DemoAction::Key { key });
+        }
+    }
+
+    fn push(&mut self, action: DemoAction) {
+        self.steps.push(DemoStep {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            action,
+        });
+    }
+
+    /// Finish recording
+    pub fn finish(self, title: Option<String>) -> DemoScript {
+        DemoScript {
+            title,
+            steps: self.steps,
+        }
+    }
+}
+
+/// Parse a key name such as `"j"`, `"enter"` or `"ctrl+q"`
+pub fn parse_key(name: &str) -> Option<KeyEvent> {
+    let (modifiers, key) = match name.strip_prefix("ctrl+") {
+        Some(key) => (KeyModifiers::CONTROL, key),
+        None => (KeyModifiers::NONE, name),
+    };
+    let code = match key {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "space" => KeyCode::Char(' '),
+        _ => {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// Name of a key press in demo scripts, the inverse of [`parse_key`]
+pub fn key_name(key: &KeyEvent) -> Option<String> {
+    let name = match key.code {
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::BackTab => "backtab".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        _ => return None,
+    };
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        Some(format!("ctrl+{}", name))
+    } else {
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_demo_parses() {
+        let script = DemoScript::from_toml(include_str!("../demos/checkout-errors.toml")).unwrap();
+        assert!(script.title.is_some());
+        assert!(script
+            .steps
+            .iter()
+            .any(|step| matches!(step.action, DemoAction::Voice { .. })));
+        assert!(script
+            .steps
+            .iter()
+            .any(|step| matches!(step.action, DemoAction::Annotation(_))));
+        assert!(script
+            .steps
+            .windows(2)
+            .all(|pair| pair[0].at_ms <= pair[1].at_ms));
+    }
+
+    #[test]
+    fn test_recorded_session_round_trips() {
+        let now = SystemTime::now();
+        let span = TelemetryEvent::SpanEnded(SpanData {
+            trace_id: "t1".into(),
+            span_id: "s1".into(),
+            parent_span_id: None,
+            name: "GET /users".into(),
+            kind: SpanKind::Server,
+            start_time: now - Duration::from_millis(42),
+            end_time: Some(now),
+            status: SpanStatus::Error,
+            attributes: HashMap::from([("http.status_code".into(), "500".into())]),
+            service_name: "api-gateway".into(),
+        });
+
+        let mut recorder = DemoRecorder::new();
+        recorder.record_telemetry(&span);
+        recorder.record_key(&KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        recorder.record_key(&KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL));
+        recorder.record_key(&KeyEvent::new(KeyCode::F(1), KeyModifiers::NONE));
+        let script = recorder.finish(Some("recorded".into()));
+        assert_eq!(script.steps.len(), 3);
+
+        let parsed = DemoScript::from_toml(&script.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, script);
+
+        let replayed = parsed.steps[0].action.telemetry(now).unwrap();
+        let TelemetryEvent::SpanEnded(replayed) = replayed else {
+            panic!("expected a finished span");
+        };
+        assert_eq!(replayed.duration(), Some(Duration::from_millis(42)));
+        assert_eq!(replayed.attributes["http.status_code"], "500");
+        assert_eq!(
+            parsed.steps[2].action,
+            DemoAction::Key {
+                key: "ctrl+q".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_player_releases_due_steps_in_order() {
+        let script = DemoScript::from_toml(
+            r#"
+            [[step]]
+            at_ms = 500
+            type = "key"
+            key = "j"
+
+            [[step]]
+            at_ms = 0
+            type = "voice"
+            command = "show traces"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(script.duration(), Duration::from_millis(500));
+
+        let mut player = DemoPlayer::new(script);
+        let first = player.advance(Duration::from_millis(100));
+        assert_eq!(
+            first,
+            vec![DemoAction::Voice {
+                command: "show traces".into()
+            }]
+        );
+        assert!(!player.is_finished());
+        assert_eq!(player.advance(Duration::from_millis(600)).len(), 1);
+        assert!(player.is_finished());
+        assert!(player.advance(Duration::from_secs(5)).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let err = DemoScript::from_toml("[[step]]\nat_ms = 0\ntype = \"key\"\nkey = \"hyper\"\n")
+            .unwrap_err();
+        assert!(matches!(err, DemoError::InvalidKey(key) if key == "hyper"));
+    }
+}
@@ -5,11 +5,17 @@
 //! - Enter/Select: Activate current selection
 //! - Tab: Toggle focus between menu and content
 //! - Esc: Back to default view
+//! - X: Acknowledge the oldest agent alert
+//! - R: Ask the agent about the selected feed event
+//! - V: Dictate a voice command
+//! - B: Mark the current service stats as the baseline, or clear it
+//! - +/-: Sample more or less of the selected service's traces
 
-use crate::state::AppState;
-use crossterm::event::{KeyCode, KeyModifiers};
+use crate::state::{AppState, View};
+use crate::voice::{registry, VoiceAction};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use spec_ai_oui::{
-    input::{GestureType, SwipeDirection},
+    input::{GestureType, SwipeDirection, VoiceCommand},
     OpticalEvent,
 };
 
@@ -36,7 +42,10 @@ pub fn handle_event(event: OpticalEvent, state: &mut AppState) -> bool {
             }
             _ => true,
         },
-        OpticalEvent::Voice { command, .. } => handle_voice(&command, state),
+        OpticalEvent::Voice {
+            command,
+            confidence,
+        } => handle_voice(&command, confidence, state),
         _ => true,
     }
 }
@@ -74,30 +83,205 @@ fn handle_key(key: crossterm::event::KeyEvent, state: &mut AppState) -> bool {
             state.back();
         }
 
+        // Agent commands
+        KeyCode::Char('x') | KeyCode::Char('X') => {
+            state.acknowledge_alert();
+        }
+        KeyCode::Char('r') | KeyCode::Char('R') => {
+            if let Some(event) = state.selected_event() {
+                let query = format!("Explain {} ({})", event.title, event.detail);
+                state.trigger_query(&query);
+            }
+        }
+
+        // Baseline for the services view
+        KeyCode::Char('b') | KeyCode::Char('B') => {
+            if state.baseline.is_some() {
+                state.clear_baseline();
+            } else {
+                state.mark_baseline();
+            }
+        }
+
+        // Sampling of the selected service's senders
+        KeyCode::Char('+') | KeyCode::Char('=') => {
+            state.step_sampling(true);
+        }
+        KeyCode::Char('-') => {
+            state.step_sampling(false);
+        }
+
+        // Display mode
+        KeyCode::Char('m') | KeyCode::Char('M') => {
+            state.cycle_mode();
+        }
+
+        // Voice dictation
+        KeyCode::Char('v') | KeyCode::Char('V') => {
+            state.dictation = Some(String::new());
+        }
+
         _ => {}
     }
     true
 }
 
-fn handle_voice(cmd: &str, state: &mut AppState) -> bool {
-    let c = cmd.to_lowercase();
-    if c.contains("trace") {
-        state.menu_index = 0;
-        state.select();
-    } else if c.contains("span") {
-        state.menu_index = 1;
-        state.select();
-    } else if c.contains("service") {
-        state.menu_index = 2;
-        state.select();
-    } else if c.contains("back") || c.contains("home") || c.contains("feed") {
-        state.back();
-    } else if c.contains("up") || c.contains("previous") {
-        state.scroll_up();
-    } else if c.contains("down") || c.contains("next") {
-        state.scroll_down();
-    } else if c.contains("select") || c.contains("enter") {
-        state.select();
+/// Handle a key while dictating; Enter delivers the text as a voice event
+pub fn handle_dictation(key: KeyEvent, state: &mut AppState) -> bool {
+    if key.kind != KeyEventKind::Press {
+        return true;
     }
+    let Some(text) = state.dictation.as_mut() else {
+        return true;
+    };
+    match key.code {
+        KeyCode::Char(c) => text.push(c),
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        KeyCode::Esc => state.dictation = None,
+        KeyCode::Enter => {
+            let command = state.dictation.take().unwrap_or_default();
+            if !command.trim().is_empty() {
+                return handle_event(
+                    OpticalEvent::Voice {
+                        command,
+                        confidence: 1.0,
+                    },
+                    state,
+                );
+            }
+        }
+        _ => {}
+    }
+    true
+}
+
+fn handle_voice(text: &str, confidence: f32, state: &mut AppState) -> bool {
+    let command = VoiceCommand::new(text, confidence);
+    let Some(matched) = registry().resolve_command(&command) else {
+        state.set_transcript(text, "Not recognized", false);
+        return true;
+    };
+
+    let argument = matched.argument.unwrap_or_default();
+    let response = match matched.action {
+        VoiceAction::ShowTraces => {
+            state.show_view(View::Traces);
+            "Showing traces".to_string()
+        }
+        VoiceAction::ShowSpans => {
+            state.show_view(View::Spans);
+            "Showing spans".to_string()
+        }
+        VoiceAction::ShowServices => {
+            state.show_view(View::Services);
+            "Showing services".to_string()
+        }
+        VoiceAction::ShowFeed => {
+            state.back();
+            "Showing feed".to_string()
+        }
+        VoiceAction::FilterErrors => {
+            state.filter_errors();
+            "Showing errors only".to_string()
+        }
+        VoiceAction::ClearFilter => {
+            state.clear_filter();
+            "Filter cleared".to_string()
+        }
+        VoiceAction::SelectService => match state.filter_service(&argument) {
+            Some(service) => format!("Showing {}", service),
+            None => {
+                state.set_transcript(text, format!("Unknown service '{}'", argument), false);
+                return true;
+            }
+        },
+        VoiceAction::PauseFeed => {
+            state.pause_feed();
+            "Feed paused".to_string()
+        }
+        VoiceAction::ResumeFeed => {
+            state.resume_feed();
+            "Feed resumed".to_string()
+        }
+        VoiceAction::ScrollUp => {
+            state.scroll_up();
+            "Up".to_string()
+        }
+        VoiceAction::ScrollDown => {
+            state.scroll_down();
+            "Down".to_string()
+        }
+        VoiceAction::Select => {
+            state.select();
+            "Selected".to_string()
+        }
+        VoiceAction::Acknowledge => {
+            state.acknowledge_alert();
+            "Alert acknowledged".to_string()
+        }
+        VoiceAction::Ask => {
+            state.trigger_query(&argument);
+            "Asking agent".to_string()
+        }
+        VoiceAction::MarkBaseline => {
+            state.mark_baseline();
+            state.show_view(View::Services);
+            "Baseline marked".to_string()
+        }
+        VoiceAction::ClearBaseline => {
+            state.clear_baseline();
+            "Baseline cleared".to_string()
+        }
+        VoiceAction::SampleMore | VoiceAction::SampleLess => {
+            let up = matched.action == VoiceAction::SampleMore;
+            match state.step_sampling(up) {
+                Some((service, ratio)) => format!("Sampling {:.0}% of {}", ratio * 100.0, service),
+                None => {
+                    state.set_transcript(text, "No sampling control for this service", false);
+                    return true;
+                }
+            }
+        }
+        VoiceAction::CycleMode => {
+            state.cycle_mode();
+            format!("{} mode", state.mode.name())
+        }
+    };
+    state.set_transcript(text, response, true);
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dictated_voice_command_runs_end_to_end() {
+        let mut state = AppState::new();
+        state.dictation = Some(String::new());
+        for c in "show traces".chars() {
+            handle_dictation(KeyEvent::from(KeyCode::Char(c)), &mut state);
+        }
+        handle_dictation(KeyEvent::from(KeyCode::Enter), &mut state);
+
+        assert!(state.dictation.is_none());
+        assert_eq!(state.view, View::Traces);
+        let transcript = state.transcript.unwrap();
+        assert!(transcript.recognized);
+        assert_eq!(transcript.response, "Showing traces");
+    }
+
+    #[test]
+    fn test_unrecognized_and_low_confidence_commands() {
+        let mut state = AppState::new();
+        handle_voice("make coffee", 1.0, &mut state);
+        assert!(!state.transcript.as_ref().unwrap().recognized);
+
+        handle_voice("pause feed", 0.2, &mut state);
+        assert!(!state.paused);
+        handle_voice("pause feed", 0.9, &mut state);
+        assert!(state.paused);
+    }
+}
@@ -3,42 +3,96 @@
 //! A minimal optical interface that displays OpenTelemetry data streams.
 //! The UI state is derived from incoming telemetry (spans, logs, metrics).
 //!
-//! Two-panel interface:
-//! - Left: Menu (Traces, Spans, Services)
-//! - Right: Event feed (default) or filtered views
+//! Panels:
+//! - Menu (Traces, Spans, Services); traces show a timeline with agent
+//!   annotations as labeled markers
+//! - Event feed (default) or filtered views
+//! - Agent activity, alerts, voice line and stats
 //!
 //! Ring-style controls:
 //! - Up/Down or j/k: Navigate
 //! - Tab or Left/Right: Switch panel focus
 //! - Enter or Space: Select
 //! - Esc or Backspace: Back
+//! - X: Acknowledge agent alert
+//! - R: Ask the agent about the selected event
+//! - V: Dictate a voice command ("show traces", "filter errors",
+//!   "select service api-gateway", "pause feed", "mark baseline", ...)
+//! - B: Mark a baseline (e.g. before a deploy), or clear it; the Services
+//!   view then shows latency and error rate since the baseline against
+//!   before it, red for regressions and green for improvements
+//! - M: Cycle display mode
 //! - Q: Quit
+//!
+//! The HUD is described by a TOML layout (`layouts/default.toml`, or any
+//! file passed as [`AppConfig::layout_path`]) so panels can be rearranged
+//! without recompiling. Layout and redaction files are [`reload`]ed when
+//! they change, keeping the telemetry received so far.
+//!
+//! Walkthroughs can be scripted: a [`demo::DemoScript`] combines timed
+//! telemetry events and user inputs in one TOML file, played back with
+//! [`AppConfig::demo_path`] and captured from a live session with
+//! [`AppConfig::record_path`].
+//!
+//! Sensitive telemetry values are masked as they arrive by the
+//! [`redaction`] rules in [`AppConfig::redaction_path`], or built-in rules
+//! for credentials, URL query strings and email addresses.
+//!
+//! [`AppConfig::span_limit`] caps the spans each service adds to the feed
+//! per interval; spans beyond it are folded into one summary entry and the
+//! service is flagged as noisy in the Services view.
+//!
+//! With [`AppConfig::control_port`] set, senders connect to a [`control`]
+//! channel and report their sample ratio, which the Services view shows;
+//! `+` and `-` dial the selected service's sampling up or down.
+//!
+//! An optional [`bridge::AgentBridge`] connects the HUD to an agent backend:
+//! agent responses, tool progress and alerts are displayed alongside
+//! telemetry, and commands are sent back over the same bridge. With
+//! [`AppConfig::agent_api`] set it talks to a running spec-ai API server.
 
+pub mod bridge;
+pub mod control;
+pub mod demo;
 mod handlers;
 pub mod receiver;
+pub mod redaction;
+pub mod reload;
 pub mod state;
 pub mod telemetry;
 pub mod ui;
+mod voice;
 
 use std::io;
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use crossterm::{
-    event::{self, Event as CrosstermEvent, KeyCode, KeyModifiers},
-    execute,
-    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+use crossterm::event::{
+    self, Event as CrosstermEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
 };
 
+use crate::bridge::{api_agent_bridge, mock_agent_bridge, AgentBridge, ApiAgentConfig};
+use crate::demo::{parse_key, DemoAction, DemoPlayer, DemoRecorder, DemoScript};
 use crate::receiver::mock_telemetry_stream;
-use crate::state::AppState;
-use handlers::handle_event;
+use crate::redaction::{load_redaction, Redactor};
+use crate::reload::{Reload, Reloader};
+use crate::state::{AppState, SpanLimit};
+use crate::telemetry::TelemetryEvent;
+use handlers::{handle_dictation, handle_event};
 use spec_ai_oui::{
+    clock::{Clock, SystemClock, Ticker},
     context::DisplayContext,
     input::InputSimulator,
-    renderer::{terminal::TerminalBackend, RenderBackend},
+    layout::HudLayout,
+    renderer::{
+        terminal::{ScreenGuard, TerminalBackend},
+        RenderBackend,
+    },
     OpticalEvent,
 };
-use ui::render_app;
+use tokio::sync::mpsc;
+use ui::{load_layout, render_app};
 
 /// Configuration for the OUI app
 #[derive(Debug, Clone)]
@@ -49,6 +103,23 @@ pub struct AppConfig {
     pub otlp_port: u16,
     /// Use mock telemetry data for demo
     pub use_mock_data: bool,
+    /// HUD layout file (TOML); the built-in layout is used when unset
+    pub layout_path: Option<PathBuf>,
+    /// Demo script to play instead of live telemetry
+    pub demo_path: Option<PathBuf>,
+    /// File to save the session to as a demo script on exit
+    pub record_path: Option<PathBuf>,
+    /// Telemetry redaction rules (TOML); the built-in rules are used when unset
+    pub redaction_path: Option<PathBuf>,
+    /// Per-service span cap for the feed; unlimited when unset
+    pub span_limit: Option<SpanLimit>,
+    /// Port of the sampling control channel for senders; off when unset
+    pub control_port: Option<u16>,
+    /// spec-ai API server whose agent the HUD talks to; without one, mock
+    /// mode simulates an agent and live mode has none
+    pub agent_api: Option<ApiAgentConfig>,
+    /// Time source for ticks, demo playback, span caps and reload checks
+    pub clock: Arc<dyn Clock>,
 }
 
 impl Default for AppConfig {
@@ -57,14 +128,51 @@ impl Default for AppConfig {
             tick_rate: Duration::from_millis(100),
             otlp_port: 4317,
             use_mock_data: true, // Default to mock data for demo
+            layout_path: None,
+            demo_path: None,
+            record_path: None,
+            redaction_path: None,
+            span_limit: None,
+            control_port: None,
+            agent_api: None,
+            clock: Arc::new(SystemClock),
         }
     }
 }
 
 /// Run the OpenTelemetry visualization app
+///
+/// The agent backend is the API server in [`AppConfig::agent_api`], or a
+/// simulated one in mock mode.
 pub async fn run_app(config: AppConfig) -> io::Result<()> {
-    // Set up telemetry stream
-    let mut telemetry_rx = if config.use_mock_data {
+    let agent = match &config.agent_api {
+        Some(api) => Some(api_agent_bridge(api.clone())),
+        None => config.use_mock_data.then(mock_agent_bridge),
+    };
+    run_app_with_agent(config, agent).await
+}
+
+/// Run the visualization app connected to an agent backend
+pub async fn run_app_with_agent(config: AppConfig, agent: Option<AgentBridge>) -> io::Result<()> {
+    // Load the HUD layout before taking over the terminal so errors are visible
+    let mut layout = load_layout(config.layout_path.as_deref())?;
+    let mut redactor = load_redaction(config.redaction_path.as_deref())?;
+    let mut reloader = Reloader::new(
+        config.layout_path.as_deref(),
+        config.redaction_path.as_deref(),
+    );
+    let demo = match &config.demo_path {
+        Some(path) => Some(
+            DemoScript::load(path)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?,
+        ),
+        None => None,
+    };
+
+    // Set up telemetry stream; a demo script supplies its own telemetry
+    let mut telemetry_rx = if demo.is_some() {
+        mpsc::unbounded_channel().1
+    } else if config.use_mock_data {
         mock_telemetry_stream()
     } else {
         let receiver_config = receiver::ReceiverConfig {
@@ -76,55 +184,121 @@ pub async fn run_app(config: AppConfig) -> io::Result<()> {
         handle.events_rx
     };
 
+    // Sampling control channel for senders, when enabled
+    let mut control = match config.control_port {
+        Some(port) => Some(
+            control::start_control(format!("127.0.0.1:{}", port).parse().unwrap())
+                .await
+                .map_err(|e| io::Error::other(e.to_string()))?,
+        ),
+        None => None,
+    };
+
     // Initialize terminal
     let mut backend = TerminalBackend::new().map_err(|e| io::Error::other(e.to_string()))?;
     let mut input_simulator = InputSimulator::new();
     let mut context = DisplayContext::default();
 
-    terminal::enable_raw_mode()?;
-    execute!(io::stdout(), EnterAlternateScreen)?;
+    let screen = ScreenGuard::enter()?;
 
     // Initialize state
     let mut state = AppState::new();
-    let mut last_tick = Instant::now();
+    state.span_limit = config.span_limit;
+    state.agent.connected = agent.is_some();
+    let (mut agent_rx, agent_tx) = match agent {
+        Some(bridge) => (Some(bridge.events_rx), Some(bridge.commands_tx)),
+        None => (None, None),
+    };
+    let clock = config.clock.clone();
+    let mut player = demo.map(|script| DemoPlayer::starting_at(script, clock.now()));
+    let mut recorder = config.record_path.is_some().then(DemoRecorder::new);
+    let mut ticker = Ticker::new(clock.clone(), config.tick_rate);
     let mut running = true;
 
     // Main loop
     while running {
         // Poll for telemetry events (non-blocking)
         while let Ok(event) = telemetry_rx.try_recv() {
-            state.process_telemetry(event);
+            ingest(event, clock.now(), &redactor, &mut state, &mut recorder);
         }
 
-        // Poll for input events
-        let timeout = config
-            .tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or_default();
-
-        if event::poll(timeout)? {
-            if let CrosstermEvent::Key(key) = event::read()? {
-                // Check for quit
-                if key.code == KeyCode::Char('q') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                    running = false;
+        // Play demo steps that are due
+        if let Some(player) = player.as_mut() {
+            for action in player.poll_at(clock.now()) {
+                if !running {
+                    break;
+                }
+                if let Some(event) = action.telemetry(SystemTime::now()) {
+                    ingest(event, clock.now(), &redactor, &mut state, &mut recorder);
                     continue;
                 }
+                running = match action {
+                    DemoAction::Key { key } => match parse_key(&key) {
+                        Some(key) => {
+                            handle_key_press(key, &mut state, &mut input_simulator, &mut recorder)
+                        }
+                        None => true,
+                    },
+                    DemoAction::Voice { command } => handle_event(
+                        OpticalEvent::Voice {
+                            command,
+                            confidence: 1.0,
+                        },
+                        &mut state,
+                    ),
+                    _ => true,
+                };
+            }
+        }
+
+        // Poll for agent events (non-blocking)
+        if let Some(rx) = agent_rx.as_mut() {
+            while let Ok(event) = rx.try_recv() {
+                state.process_agent_event(event);
+            }
+        }
+
+        // Poll for senders joining, leaving or reporting on the control channel
+        if let Some(control) = control.as_mut() {
+            while let Ok(event) = control.events_rx.try_recv() {
+                state.process_control_event(event);
+            }
+        }
+
+        // Swap in layout and redaction files edited since the last check
+        for (path, reload) in reloader.poll(clock.now()) {
+            apply_reload(&path, reload, &mut layout, &mut redactor, &mut state);
+        }
+
+        // Poll for input events
+        if running && event::poll(ticker.timeout())? {
+            if let CrosstermEvent::Key(key) = event::read()? {
+                running = handle_key_press(key, &mut state, &mut input_simulator, &mut recorder);
+            }
+        }
 
-                // Convert to optical events
-                let events = input_simulator.process_key(key);
-                for event in events {
-                    if !handle_event(event, &mut state) {
-                        running = false;
-                        break;
-                    }
+        // Forward queued commands to the agent backend
+        for command in state.pending_commands.drain(..) {
+            if let Some(tx) = &agent_tx {
+                if tx.send(command).is_err() {
+                    state.agent.connected = false;
                 }
             }
         }
 
+        // Forward sampling changes to the senders
+        for (service, ratio) in state.pending_sampling.drain(..) {
+            if let Some(control) = &control {
+                control.set_sample_ratio(&service, ratio);
+            }
+        }
+
         // Check for tick
-        if last_tick.elapsed() >= config.tick_rate {
+        if let Some(dt) = ticker.tick() {
             // Update context
-            context.update(last_tick.elapsed());
+            context.update(dt);
+            context.mode = state.mode;
+            context.density = state.density;
 
             // Update tick counter
             state.tick = state.tick.wrapping_add(1);
@@ -137,7 +311,7 @@ pub async fn run_app(config: AppConfig) -> io::Result<()> {
                 .begin_frame()
                 .map_err(|e| io::Error::other(e.to_string()))?;
 
-            render_app(&state, &mut backend);
+            render_app(&state, &context, &layout, &mut backend);
 
             backend
                 .end_frame()
@@ -145,31 +319,114 @@ pub async fn run_app(config: AppConfig) -> io::Result<()> {
 
             // Send tick event
             handle_event(OpticalEvent::Tick, &mut state);
-
-            last_tick = Instant::now();
         }
     }
 
     // Cleanup
-    terminal::disable_raw_mode()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
+    drop(screen);
+
+    if let (Some(recorder), Some(path)) = (recorder, &config.record_path) {
+        recorder
+            .finish(Some("Recorded session".to_string()))
+            .save(path)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        eprintln!("Saved demo script to {}", path.display());
+    }
 
     Ok(())
 }
 
+/// Mask an incoming telemetry event, then record and display it
+fn ingest(
+    event: TelemetryEvent,
+    now: Instant,
+    redactor: &Redactor,
+    state: &mut AppState,
+    recorder: &mut Option<DemoRecorder>,
+) {
+    let event = redactor.redact(event);
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.record_telemetry(&event);
+    }
+    state.process_telemetry_at(event, now);
+}
+
+/// Use a reloaded file if it is valid, and say how it went on the voice line
+fn apply_reload(
+    path: &Path,
+    reload: Reload,
+    layout: &mut HudLayout,
+    redactor: &mut Redactor,
+    state: &mut AppState,
+) {
+    let kind = reload.kind();
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let result = match reload {
+        Reload::Layout(loaded) => loaded.map(|loaded| *layout = loaded),
+        // Events already received stay as they were masked
+        Reload::Redaction(loaded) => loaded.map(|loaded| *redactor = loaded),
+    };
+    match result {
+        Ok(()) => state.set_notice(format!("Reloaded {} from {}", kind, name), true),
+        Err(err) => state.set_notice(format!("Kept previous {}: {}", kind, err), false),
+    }
+}
+
+/// Handle a key press from the terminal or a demo script
+///
+/// Returns false when the key quits the app.
+fn handle_key_press(
+    key: KeyEvent,
+    state: &mut AppState,
+    input_simulator: &mut InputSimulator,
+    recorder: &mut Option<DemoRecorder>,
+) -> bool {
+    // Windows also reports key releases; act on presses only
+    if key.kind == KeyEventKind::Release {
+        return true;
+    }
+
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.record_key(&key);
+    }
+
+    // Check for quit
+    if key.code == KeyCode::Char('q') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return false;
+    }
+
+    // Typed dictation bypasses the simulator and arrives as voice
+    if state.dictation.is_some() {
+        handle_dictation(key, state);
+        return true;
+    }
+
+    // Convert to optical events
+    input_simulator
+        .process_key(key)
+        .into_iter()
+        .all(|event| handle_event(event, state))
+}
+
+/// Run the application with the given config on a new runtime
+pub fn run_with_config(config: AppConfig) -> io::Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(run_app(config))
+}
+
 /// Run the demo application with default config
 pub fn run_demo() -> io::Result<()> {
-    let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(run_app(AppConfig::default()))
+    run_with_config(AppConfig::default())
 }
 
 /// Run with OTLP receiver enabled (no mock data)
 pub fn run_with_otlp(port: u16) -> io::Result<()> {
-    let config = AppConfig {
+    run_with_config(AppConfig {
         use_mock_data: false,
         otlp_port: port,
         ..Default::default()
-    };
-    let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(run_app(config))
+    })
 }
@@ -8,24 +8,82 @@
 //! - Tab or Left/Right: Switch focus between menu and content
 //! - Enter or Space: Select current item
 //! - Esc or Backspace: Back to feed view
+//! - X: Acknowledge agent alert
+//! - R: Ask the agent about the selected event
+//! - V: Dictate a voice command
+//! - B: Mark or clear the services baseline
+//! - +/-: Sample more or less of the selected service's traces
+//! - M: Cycle display mode
 //! - Q: Quit
 //!
 //! Usage:
 //!   oui-demo              # Run with mock telemetry data
 //!   oui-demo --otlp 4317  # Run with OTLP receiver on port 4317
+//!   oui-demo --layout hud.toml  # Use a custom HUD layout
+//!   oui-demo --demo demos/checkout-errors.toml  # Play a demo script
+//!   oui-demo --otlp 4317 --record demo.toml     # Record a session as a demo
+//!   oui-demo --otlp 4317 --redact redact.toml   # Mask telemetry with custom rules
+//!   oui-demo --otlp 4317 --span-cap 20          # At most 20 spans per service per second
+//!   oui-demo --span-cap 100 --span-interval 10  # ... or per 10 seconds
+//!   oui-demo --otlp 4317 --control 4320         # Let senders follow sampling changes
+//!   oui-demo --otlp 4317 --agent http://127.0.0.1:3000  # Talk to a spec-ai API server's agent
+//!
+//! With `--agent`, `--agent-profile NAME` picks the agent to ask and a bearer
+//! token is read from `SPEC_AI_API_TOKEN`.
 
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use spec_ai_oui_app::bridge::ApiAgentConfig;
+use spec_ai_oui_app::state::SpanLimit;
+use spec_ai_oui_app::AppConfig;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut config = AppConfig::default();
+    let mut span_cap = None;
+    let mut span_interval = Duration::from_secs(1);
+    let mut agent_profile = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--otlp" => {
+                config.use_mock_data = false;
+                config.otlp_port = args.next().and_then(|p| p.parse().ok()).unwrap_or(4317);
+                eprintln!("Starting OTLP receiver on port {}...", config.otlp_port);
+            }
+            "--layout" => config.layout_path = args.next().map(PathBuf::from),
+            "--demo" => config.demo_path = args.next().map(PathBuf::from),
+            "--record" => config.record_path = args.next().map(PathBuf::from),
+            "--redact" => config.redaction_path = args.next().map(PathBuf::from),
+            "--control" => {
+                config.control_port = args.next().and_then(|p| p.parse().ok());
+                if let Some(port) = config.control_port {
+                    eprintln!("Starting sampling control on port {}...", port);
+                }
+            }
+            "--agent" => config.agent_api = args.next().map(ApiAgentConfig::new),
+            "--agent-profile" => agent_profile = args.next(),
+            "--span-cap" => span_cap = args.next().and_then(|n| n.parse().ok()),
+            "--span-interval" => {
+                if let Some(secs) = args.next().and_then(|s| s.parse().ok()) {
+                    span_interval = Duration::from_secs(secs);
+                }
+            }
+            other => eprintln!("Ignoring unknown argument: {}", other),
+        }
+    }
+    if let Some(api) = config.agent_api.as_mut() {
+        api.agent = agent_profile;
+        api.token = env::var("SPEC_AI_API_TOKEN").ok();
+        eprintln!("Connecting the agent panel to {}...", api.url);
+    }
+    config.span_limit = span_cap.map(|max_spans| SpanLimit {
+        max_spans,
+        interval: span_interval,
+    });
 
-    let result = if args.len() > 2 && args[1] == "--otlp" {
-        let port: u16 = args[2].parse().unwrap_or(4317);
-        eprintln!("Starting OTLP receiver on port {}...", port);
-        spec_ai_oui_app::run_with_otlp(port)
-    } else {
-        spec_ai_oui_app::run_demo()
-    };
+    let result = spec_ai_oui_app::run_with_config(config);
 
     if let Err(e) = result {
         eprintln!("Error: {}", e);
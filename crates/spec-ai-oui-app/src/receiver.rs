@@ -2,6 +2,9 @@
 //!
 //! This module implements an OTLP receiver that accepts telemetry data
 //! via gRPC and converts it to our UI-friendly data model.
+//!
+//! Spans carrying [`ANNOTATION`], as exported by spec-ai's
+//! `annotate_timeline` tool, arrive as [`Annotation`]s instead of spans.
 
 use std::collections::HashMap;
 use std::net::SocketAddr;
@@ -11,16 +14,36 @@ use opentelemetry_proto::tonic::collector::trace::v1::{
     trace_service_server::{TraceService, TraceServiceServer},
     ExportTraceServiceRequest, ExportTraceServiceResponse,
 };
+use opentelemetry_proto::tonic::common::v1::any_value::Value as AnyValueKind;
+use opentelemetry_proto::tonic::resource::v1::Resource;
 use opentelemetry_proto::tonic::trace::v1::span::SpanKind as ProtoSpanKind;
 use opentelemetry_proto::tonic::trace::v1::Status as ProtoStatus;
 use tokio::sync::mpsc;
 use tonic::{transport::Server, Request, Response, Status};
 
-use crate::telemetry::{SpanData, SpanKind, SpanStatus, TelemetryEvent};
+use crate::telemetry::{Annotation, Severity, SpanData, SpanKind, SpanStatus, TelemetryEvent};
+
+/// Attribute holding an annotation's label; its presence marks a span as one
+pub const ANNOTATION: &str = "spec_ai.annotation";
+/// Attribute holding an annotation's longer description
+pub const ANNOTATION_DETAIL: &str = "spec_ai.annotation.detail";
+/// Attribute holding an annotation's severity: `info`, `warn` or `error`
+pub const ANNOTATION_SEVERITY: &str = "spec_ai.annotation.severity";
+
+/// Longest attribute key or value, span name or service name kept, in
+/// characters; longer ones are cut and marked with "..."
+pub const MAX_TEXT_CHARS: usize = 1024;
+/// Most attributes kept per span; exporters may send any number
+pub const MAX_ATTRIBUTES: usize = 128;
+/// Longest trace or span id kept, in bytes; OTLP ids are 16 and 8 bytes
+const MAX_ID_BYTES: usize = 32;
 
 /// Convert protobuf timestamp (nanos since epoch) to SystemTime
 fn proto_time_to_system_time(time_unix_nano: u64) -> SystemTime {
-    UNIX_EPOCH + Duration::from_nanos(time_unix_nano)
+    // Not every platform can represent the whole u64 range
+    UNIX_EPOCH
+        .checked_add(Duration::from_nanos(time_unix_nano))
+        .unwrap_or(UNIX_EPOCH)
 }
 
 /// Convert protobuf span kind to our SpanKind
@@ -47,9 +70,131 @@ fn convert_status(status: Option<ProtoStatus>) -> SpanStatus {
     }
 }
 
+/// Annotation marked by a span's attributes, if it is one
+fn convert_annotation(
+    attributes: &HashMap<String, String>,
+    time: SystemTime,
+    service_name: &str,
+) -> Option<Annotation> {
+    let label = attributes.get(ANNOTATION)?;
+    let severity = match attributes.get(ANNOTATION_SEVERITY).map(String::as_str) {
+        Some("warn") => Severity::Warn,
+        Some("error") => Severity::Error,
+        _ => Severity::Info,
+    };
+    Some(Annotation {
+        label: label.clone(),
+        detail: attributes.get(ANNOTATION_DETAIL).cloned(),
+        severity,
+        timestamp: time,
+        service_name: service_name.to_string(),
+    })
+}
+
 /// Convert bytes to hex string
 fn bytes_to_hex(bytes: &[u8]) -> String {
-    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    bytes
+        .iter()
+        .take(MAX_ID_BYTES)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// `text` cut to [`MAX_TEXT_CHARS`]
+fn clip(text: &str) -> String {
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Attribute value as displayed; nested values are only described
+fn convert_value(value: &AnyValueKind) -> String {
+    match value {
+        AnyValueKind::StringValue(s) => clip(s),
+        AnyValueKind::IntValue(i) => i.to_string(),
+        AnyValueKind::DoubleValue(d) => d.to_string(),
+        AnyValueKind::BoolValue(b) => b.to_string(),
+        AnyValueKind::ArrayValue(array) => format!("[{} values]", array.values.len()),
+        AnyValueKind::KvlistValue(list) => format!("{{{} entries}}", list.values.len()),
+        AnyValueKind::BytesValue(bytes) => format!("<{} bytes>", bytes.len()),
+    }
+}
+
+/// Service name from a resource's `service.name` attribute
+fn service_name(resource: Option<&Resource>) -> String {
+    resource
+        .and_then(|r| r.attributes.iter().find(|a| a.key == "service.name"))
+        .and_then(|a| a.value.as_ref())
+        .and_then(|v| v.value.as_ref())
+        .and_then(|v| match v {
+            AnyValueKind::StringValue(s) => Some(clip(s)),
+            _ => None,
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Turn an export request into UI events
+///
+/// Exporters are not trusted: ids, text and attribute counts are bounded,
+/// and out-of-range kinds, status codes and timestamps fall back to
+/// defaults, so a malformed request never panics the visualizer.
+pub fn convert_request(req: ExportTraceServiceRequest) -> Vec<TelemetryEvent> {
+    let mut events = Vec::new();
+    for resource_spans in req.resource_spans {
+        let service_name = service_name(resource_spans.resource.as_ref());
+
+        for scope_spans in resource_spans.scope_spans {
+            for span in scope_spans.spans {
+                let trace_id = bytes_to_hex(&span.trace_id);
+                let span_id = bytes_to_hex(&span.span_id);
+                let parent_span_id = if span.parent_span_id.is_empty() {
+                    None
+                } else {
+                    Some(bytes_to_hex(&span.parent_span_id))
+                };
+
+                // Convert attributes
+                let attributes: HashMap<String, String> = span
+                    .attributes
+                    .iter()
+                    .filter_map(|a| {
+                        let value = a.value.as_ref()?.value.as_ref()?;
+                        Some((clip(&a.key), convert_value(value)))
+                    })
+                    .take(MAX_ATTRIBUTES)
+                    .collect();
+
+                let start_time = proto_time_to_system_time(span.start_time_unix_nano);
+                if let Some(annotation) = convert_annotation(&attributes, start_time, &service_name)
+                {
+                    events.push(TelemetryEvent::Annotation(annotation));
+                    continue;
+                }
+
+                let span_data = SpanData {
+                    trace_id,
+                    span_id,
+                    parent_span_id,
+                    name: clip(&span.name),
+                    kind: convert_span_kind(span.kind),
+                    start_time,
+                    end_time: if span.end_time_unix_nano > 0 {
+                        Some(proto_time_to_system_time(span.end_time_unix_nano))
+                    } else {
+                        None
+                    },
+                    status: convert_status(span.status),
+                    attributes,
+                    service_name: service_name.clone(),
+                };
+
+                // OTLP typically sends completed spans
+                events.push(TelemetryEvent::SpanEnded(span_data));
+            }
+        }
+    }
+    events
 }
 
 /// OTLP Trace service implementation
@@ -69,76 +214,8 @@ impl TraceService for OtlpTraceReceiver {
         &self,
         request: Request<ExportTraceServiceRequest>,
     ) -> Result<Response<ExportTraceServiceResponse>, Status> {
-        let req = request.into_inner();
-
-        for resource_spans in req.resource_spans {
-            // Extract service name from resource attributes
-            let service_name = resource_spans
-                .resource
-                .as_ref()
-                .map(|r| {
-                    r.attributes
-                        .iter()
-                        .find(|a| a.key == "service.name")
-                        .and_then(|a| a.value.as_ref())
-                        .and_then(|v| v.value.as_ref())
-                        .map(|v| match v {
-                            opentelemetry_proto::tonic::common::v1::any_value::Value::StringValue(s) => s.clone(),
-                            _ => "unknown".to_string(),
-                        })
-                        .unwrap_or_else(|| "unknown".to_string())
-                })
-                .unwrap_or_else(|| "unknown".to_string());
-
-            for scope_spans in resource_spans.scope_spans {
-                for span in scope_spans.spans {
-                    let trace_id = bytes_to_hex(&span.trace_id);
-                    let span_id = bytes_to_hex(&span.span_id);
-                    let parent_span_id = if span.parent_span_id.is_empty() {
-                        None
-                    } else {
-                        Some(bytes_to_hex(&span.parent_span_id))
-                    };
-
-                    // Convert attributes
-                    let attributes: HashMap<String, String> = span
-                        .attributes
-                        .iter()
-                        .filter_map(|a| {
-                            a.value.as_ref().and_then(|v| v.value.as_ref()).map(|v| {
-                                let val = match v {
-                                    opentelemetry_proto::tonic::common::v1::any_value::Value::StringValue(s) => s.clone(),
-                                    opentelemetry_proto::tonic::common::v1::any_value::Value::IntValue(i) => i.to_string(),
-                                    opentelemetry_proto::tonic::common::v1::any_value::Value::DoubleValue(d) => d.to_string(),
-                                    opentelemetry_proto::tonic::common::v1::any_value::Value::BoolValue(b) => b.to_string(),
-                                    _ => "...".to_string(),
-                                };
-                                (a.key.clone(), val)
-                            })
-                        })
-                        .collect();
-
-                    let span_data = SpanData {
-                        trace_id,
-                        span_id,
-                        parent_span_id,
-                        name: span.name.clone(),
-                        kind: convert_span_kind(span.kind),
-                        start_time: proto_time_to_system_time(span.start_time_unix_nano),
-                        end_time: if span.end_time_unix_nano > 0 {
-                            Some(proto_time_to_system_time(span.end_time_unix_nano))
-                        } else {
-                            None
-                        },
-                        status: convert_status(span.status),
-                        attributes,
-                        service_name: service_name.clone(),
-                    };
-
-                    // Send SpanEnded event (OTLP typically sends completed spans)
-                    let _ = self.tx.send(TelemetryEvent::SpanEnded(span_data));
-                }
-            }
+        for event in convert_request(request.into_inner()) {
+            let _ = self.tx.send(event);
         }
 
         Ok(Response::new(ExportTraceServiceResponse {
@@ -273,3 +350,289 @@ fn rand_delay() -> u64 {
         .subsec_nanos();
     (nanos % 500) as u64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AppState, FeedEvent};
+    use opentelemetry_proto::tonic::common::v1::{AnyValue, ArrayValue, KeyValue, KeyValueList};
+    use opentelemetry_proto::tonic::trace::v1::{ResourceSpans, ScopeSpans, Span};
+    use proptest::prelude::*;
+    use prost::Message;
+    use std::path::Path;
+
+    /// Seed requests replayed on every run; see `corpus/otlp/README.md`
+    const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/corpus/otlp");
+
+    /// Feed `events` through everything that builds UI state from them
+    fn display(events: Vec<TelemetryEvent>) {
+        let mut state = AppState::new();
+        for (id, event) in events.into_iter().enumerate() {
+            let _ = event.summary();
+            let _ = FeedEvent::from_telemetry(id, event.clone());
+            state.process_telemetry(event);
+        }
+    }
+
+    fn check_bounds(events: &[TelemetryEvent]) {
+        let max_len = MAX_TEXT_CHARS * 4 + 3;
+        for event in events {
+            assert!(event.service_name().len() <= max_len);
+            match event {
+                TelemetryEvent::SpanEnded(span) => {
+                    assert!(span.name.len() <= max_len);
+                    assert!(span.trace_id.len() <= MAX_ID_BYTES * 2);
+                    assert!(span.span_id.len() <= MAX_ID_BYTES * 2);
+                    assert!(span.attributes.len() <= MAX_ATTRIBUTES);
+                    for (key, value) in &span.attributes {
+                        assert!(key.len() <= max_len && value.len() <= max_len);
+                    }
+                }
+                TelemetryEvent::Annotation(annotation) => {
+                    assert!(annotation.label.len() <= max_len);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn any_value() -> impl Strategy<Value = Option<AnyValue>> {
+        let leaf = prop_oneof![
+            any::<String>().prop_map(AnyValueKind::StringValue),
+            "\\PC{2000,3000}".prop_map(AnyValueKind::StringValue),
+            any::<i64>().prop_map(AnyValueKind::IntValue),
+            any::<f64>().prop_map(AnyValueKind::DoubleValue),
+            any::<bool>().prop_map(AnyValueKind::BoolValue),
+            prop::collection::vec(any::<u8>(), 0..64).prop_map(AnyValueKind::BytesValue),
+        ];
+        let value = leaf.prop_recursive(3, 16, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(|values| {
+                    AnyValueKind::ArrayValue(ArrayValue {
+                        values: values
+                            .into_iter()
+                            .map(|v| AnyValue { value: Some(v) })
+                            .collect(),
+                    })
+                }),
+                prop::collection::vec(("\\PC{0,8}", inner), 0..4).prop_map(|values| {
+                    AnyValueKind::KvlistValue(KeyValueList {
+                        values: values
+                            .into_iter()
+                            .map(|(key, v)| KeyValue {
+                                key,
+                                value: Some(AnyValue { value: Some(v) }),
+                            })
+                            .collect(),
+                    })
+                }),
+            ]
+        });
+        prop::option::of(prop::option::of(value).prop_map(|value| AnyValue { value }))
+    }
+
+    fn key() -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just(ANNOTATION.to_string()),
+            Just(ANNOTATION_DETAIL.to_string()),
+            Just(ANNOTATION_SEVERITY.to_string()),
+            Just("service.name".to_string()),
+            any::<String>(),
+        ]
+    }
+
+    fn attributes(max: usize) -> impl Strategy<Value = Vec<KeyValue>> {
+        prop::collection::vec(
+            (key(), any_value()).prop_map(|(key, value)| KeyValue { key, value }),
+            0..max,
+        )
+    }
+
+    fn span() -> impl Strategy<Value = Span> {
+        (
+            prop::collection::vec(any::<u8>(), 0..40),
+            prop::collection::vec(any::<u8>(), 0..40),
+            prop::collection::vec(any::<u8>(), 0..40),
+            any::<String>(),
+            any::<i32>(),
+            any::<u64>(),
+            any::<u64>(),
+            attributes(MAX_ATTRIBUTES + 8),
+            prop::option::of((any::<i32>(), any::<String>())),
+        )
+            .prop_map(
+                |(
+                    trace_id,
+                    span_id,
+                    parent_span_id,
+                    name,
+                    kind,
+                    start,
+                    end,
+                    attributes,
+                    status,
+                )| {
+                    Span {
+                        trace_id,
+                        span_id,
+                        parent_span_id,
+                        name,
+                        kind,
+                        start_time_unix_nano: start,
+                        end_time_unix_nano: end,
+                        attributes,
+                        status: status.map(|(code, message)| ProtoStatus { code, message }),
+                        ..Default::default()
+                    }
+                },
+            )
+    }
+
+    fn request() -> impl Strategy<Value = ExportTraceServiceRequest> {
+        let resource_spans = (
+            prop::option::of(attributes(4).prop_map(|attributes| Resource {
+                attributes,
+                ..Default::default()
+            })),
+            prop::collection::vec(prop::collection::vec(span(), 0..4), 0..3),
+        )
+            .prop_map(|(resource, scopes)| ResourceSpans {
+                resource,
+                scope_spans: scopes
+                    .into_iter()
+                    .map(|spans| ScopeSpans {
+                        spans,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            });
+        prop::collection::vec(resource_spans, 0..3)
+            .prop_map(|resource_spans| ExportTraceServiceRequest { resource_spans })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_any_request_converts_within_bounds(req in request()) {
+            let spans: usize = req
+                .resource_spans
+                .iter()
+                .flat_map(|r| &r.scope_spans)
+                .map(|s| s.spans.len())
+                .sum();
+            let events = convert_request(req);
+            prop_assert_eq!(events.len(), spans);
+            check_bounds(&events);
+            display(events);
+        }
+
+        #[test]
+        fn prop_arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+            if let Ok(req) = ExportTraceServiceRequest::decode(bytes.as_slice()) {
+                let events = convert_request(req);
+                check_bounds(&events);
+                display(events);
+            }
+        }
+
+        #[test]
+        fn prop_corrupted_requests_never_panic(
+            req in request(),
+            flips in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+        ) {
+            let mut bytes = req.encode_to_vec();
+            if !bytes.is_empty() {
+                for (index, byte) in flips {
+                    let at = index.index(bytes.len());
+                    bytes[at] = byte;
+                }
+            }
+            if let Ok(req) = ExportTraceServiceRequest::decode(bytes.as_slice()) {
+                let events = convert_request(req);
+                check_bounds(&events);
+                display(events);
+            }
+        }
+    }
+
+    #[test]
+    fn test_corpus_seeds_convert() {
+        let mut seeds: Vec<_> = std::fs::read_dir(Path::new(CORPUS))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
+            .collect();
+        seeds.sort();
+        assert!(!seeds.is_empty());
+        for seed in seeds {
+            let bytes = std::fs::read(&seed).unwrap();
+            // Some seeds are deliberately not valid protobuf
+            if let Ok(req) = ExportTraceServiceRequest::decode(bytes.as_slice()) {
+                let events = convert_request(req);
+                check_bounds(&events);
+                display(events);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hostile_values_are_bounded() {
+        let huge = "é".repeat(MAX_TEXT_CHARS * 3);
+        let req = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: None,
+                scope_spans: vec![ScopeSpans {
+                    spans: vec![Span {
+                        trace_id: vec![0xab; 1000],
+                        name: huge.clone(),
+                        kind: 99,
+                        start_time_unix_nano: u64::MAX,
+                        end_time_unix_nano: 1,
+                        attributes: (0..MAX_ATTRIBUTES * 2)
+                            .map(|n| KeyValue {
+                                key: format!("k{}", n),
+                                value: Some(AnyValue {
+                                    value: Some(AnyValueKind::StringValue(huge.clone())),
+                                }),
+                            })
+                            .collect(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+
+        let events = convert_request(req);
+        let TelemetryEvent::SpanEnded(span) = &events[0] else {
+            panic!("expected a span");
+        };
+        assert_eq!(span.service_name, "unknown");
+        assert_eq!(span.kind, SpanKind::Internal);
+        assert_eq!(span.trace_id.len(), MAX_ID_BYTES * 2);
+        assert_eq!(span.name.chars().count(), MAX_TEXT_CHARS + 3);
+        assert_eq!(span.attributes.len(), MAX_ATTRIBUTES);
+        // Ends before it starts
+        assert_eq!(span.duration(), Some(Duration::ZERO));
+        display(events);
+    }
+
+    #[test]
+    fn test_annotation_spans_become_annotations() {
+        let time = UNIX_EPOCH + Duration::from_secs(60);
+        let attributes = HashMap::from([
+            (ANNOTATION.to_string(), "deployment".to_string()),
+            (ANNOTATION_SEVERITY.to_string(), "warn".to_string()),
+        ]);
+
+        let annotation = convert_annotation(&attributes, time, "spec-ai").unwrap();
+        assert_eq!(annotation.label, "deployment");
+        assert_eq!(annotation.severity, Severity::Warn);
+        assert_eq!(annotation.detail, None);
+        assert_eq!(annotation.timestamp, time);
+
+        let span = HashMap::from([("http.method".to_string(), "GET".to_string())]);
+        assert!(convert_annotation(&span, time, "spec-ai").is_none());
+    }
+}
@@ -0,0 +1,301 @@
+//! Masking of sensitive telemetry values
+//!
+//! Telemetry is redacted as it is ingested, before it reaches the UI state,
+//! so masked values never show on a shared or recorded screen and are not
+//! written to recorded demo scripts. Rules come from a TOML file:
+//!
+//! ```toml
+//! # Attributes whose values are hidden entirely
+//! keys = ["http.request.header.authorization"]
+//!
+//! # Query strings, only in these attributes
+//! [[pattern]]
+//! regex = '\?.*'
+//! keys = ["http.url"]
+//! replacement = "?***"
+//!
+//! # Email addresses in every attribute, span name, log body and annotation
+//! [[pattern]]
+//! regex = '[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}'
+//! ```
+//!
+//! Files are passed with `oui-demo --redact <file>`; without one the
+//! built-in [`DEFAULT_REDACTION`] rules apply.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::telemetry::TelemetryEvent;
+
+/// Rules used when no redaction file is given
+pub const DEFAULT_REDACTION: &str = include_str!("../redaction/default.toml");
+
+/// What a value, or the matched part of one, is replaced with by default
+pub const MASK: &str = "***";
+
+/// Error loading redaction rules
+#[derive(Debug, Clone)]
+pub enum RedactionError {
+    /// The rules file could not be read
+    Io(String),
+    /// The rules are not valid TOML or have unknown fields
+    Parse(String),
+    /// A pattern is not a valid regular expression
+    InvalidRegex(String),
+}
+
+impl fmt::Display for RedactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedactionError::Io(msg) => write!(f, "Redaction read error: {}", msg),
+            RedactionError::Parse(msg) => write!(f, "Redaction parse error: {}", msg),
+            RedactionError::InvalidRegex(msg) => write!(f, "Invalid redaction pattern: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RedactionError {}
+
+/// Redaction rules as written in the TOML file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedactionConfig {
+    /// Attribute keys whose values are replaced with [`MASK`]
+    #[serde(default)]
+    pub keys: Vec<String>,
+    /// Patterns masked inside values
+    #[serde(default, rename = "pattern")]
+    pub patterns: Vec<PatternConfig>,
+}
+
+/// A regular expression whose matches are masked
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternConfig {
+    pub regex: String,
+    /// Attributes the pattern applies to; when empty it applies to every
+    /// attribute as well as span names, log bodies and annotations
+    #[serde(default)]
+    pub keys: Vec<String>,
+    /// Text put in place of each match; `$1` refers to a capture group
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+}
+
+fn default_replacement() -> String {
+    MASK.to_string()
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    regex: Regex,
+    keys: HashSet<String>,
+    replacement: String,
+}
+
+impl Pattern {
+    fn applies_to(&self, key: Option<&str>) -> bool {
+        self.keys.is_empty() || key.is_some_and(|key| self.keys.contains(key))
+    }
+}
+
+/// Compiled redaction rules applied to incoming telemetry
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    keys: HashSet<String>,
+    patterns: Vec<Pattern>,
+}
+
+impl Redactor {
+    pub fn from_config(config: &RedactionConfig) -> Result<Self, RedactionError> {
+        let patterns = config
+            .patterns
+            .iter()
+            .map(|pattern| {
+                let regex = Regex::new(&pattern.regex).map_err(|e| {
+                    RedactionError::InvalidRegex(format!("'{}': {}", pattern.regex, e))
+                })?;
+                Ok(Pattern {
+                    regex,
+                    keys: pattern.keys.iter().cloned().collect(),
+                    replacement: pattern.replacement.clone(),
+                })
+            })
+            .collect::<Result<_, RedactionError>>()?;
+
+        Ok(Self {
+            keys: config.keys.iter().cloned().collect(),
+            patterns,
+        })
+    }
+
+    /// Parse and compile rules from TOML
+    pub fn from_toml(source: &str) -> Result<Self, RedactionError> {
+        let config: RedactionConfig =
+            toml::from_str(source).map_err(|e| RedactionError::Parse(e.to_string()))?;
+        Self::from_config(&config)
+    }
+
+    /// Load a rules file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RedactionError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| RedactionError::Io(format!("{}: {}", path.display(), e)))?;
+        Self::from_toml(&source)
+    }
+
+    /// Whether no rules are configured, so events pass through unchanged
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.patterns.is_empty()
+    }
+
+    /// The event with sensitive values masked
+    pub fn redact(&self, mut event: TelemetryEvent) -> TelemetryEvent {
+        if self.is_empty() {
+            return event;
+        }
+        match &mut event {
+            TelemetryEvent::SpanStarted(span) | TelemetryEvent::SpanEnded(span) => {
+                self.redact_text(&mut span.name);
+                self.redact_attributes(&mut span.attributes);
+            }
+            TelemetryEvent::Log(log) => {
+                self.redact_text(&mut log.body);
+                self.redact_attributes(&mut log.attributes);
+            }
+            TelemetryEvent::Metric(metric) => self.redact_attributes(&mut metric.attributes),
+            TelemetryEvent::Annotation(annotation) => {
+                self.redact_text(&mut annotation.label);
+                if let Some(detail) = annotation.detail.as_mut() {
+                    self.redact_text(detail);
+                }
+            }
+        }
+        event
+    }
+
+    fn redact_attributes(&self, attributes: &mut HashMap<String, String>) {
+        for (key, value) in attributes.iter_mut() {
+            if self.keys.contains(key) {
+                *value = MASK.to_string();
+            } else {
+                self.mask(Some(key), value);
+            }
+        }
+    }
+
+    fn redact_text(&self, text: &mut String) {
+        self.mask(None, text);
+    }
+
+    fn mask(&self, key: Option<&str>, value: &mut String) {
+        for pattern in self.patterns.iter().filter(|p| p.applies_to(key)) {
+            if pattern.regex.is_match(value) {
+                *value = pattern
+                    .regex
+                    .replace_all(value, pattern.replacement.as_str())
+                    .into_owned();
+            }
+        }
+    }
+}
+
+/// Load a redaction file, or the built-in rules when no path is given
+pub fn load_redaction(path: Option<&Path>) -> io::Result<Redactor> {
+    match path {
+        Some(path) => Redactor::load(path),
+        None => Redactor::from_toml(DEFAULT_REDACTION),
+    }
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::{Annotation, Severity, SpanData, SpanKind, SpanStatus};
+    use std::time::SystemTime;
+
+    fn span(name: &str, attributes: &[(&str, &str)]) -> TelemetryEvent {
+        TelemetryEvent::SpanEnded(SpanData {
+            trace_id: "t".to_string(),
+            span_id: "s".to_string(),
+            parent_span_id: None,
+            name: name.to_string(),
+            kind: SpanKind::Server,
+            start_time: SystemTime::now(),
+            end_time: None,
+            status: SpanStatus::Ok,
+            attributes: attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            service_name: "api".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_default_rules_mask_secrets_query_strings_and_emails() {
+        let redactor = load_redaction(None).unwrap();
+        let event = redactor.redact(span(
+            "notify ada@example.com",
+            &[
+                ("http.url", "https://api.example.com/users?token=abc&page=2"),
+                ("http.request.header.authorization", "Bearer abc"),
+                ("user.note", "contact ada@example.co.uk today"),
+                ("http.route", "/users?id"),
+            ],
+        ));
+
+        let TelemetryEvent::SpanEnded(span) = event else {
+            panic!("expected a span");
+        };
+        assert_eq!(span.name, "notify ***");
+        assert_eq!(
+            span.attributes["http.url"],
+            "https://api.example.com/users?***"
+        );
+        assert_eq!(span.attributes["http.request.header.authorization"], MASK);
+        assert_eq!(span.attributes["user.note"], "contact *** today");
+        // Query patterns only apply to the attributes they name
+        assert_eq!(span.attributes["http.route"], "/users?id");
+    }
+
+    #[test]
+    fn test_custom_rules_and_empty_files() {
+        let redactor = Redactor::from_toml(
+            r#"
+            [[pattern]]
+            regex = 'card=(\d{4})\d+'
+            replacement = "card=$1…"
+            "#,
+        )
+        .unwrap();
+        let event = redactor.redact(TelemetryEvent::Annotation(Annotation {
+            label: "refund card=4242424242".to_string(),
+            detail: Some("card=5555444433331111 declined".to_string()),
+            severity: Severity::Info,
+            timestamp: SystemTime::now(),
+            service_name: "billing".to_string(),
+        }));
+        let TelemetryEvent::Annotation(annotation) = event else {
+            panic!("expected an annotation");
+        };
+        assert_eq!(annotation.label, "refund card=4242…");
+        assert_eq!(annotation.detail.as_deref(), Some("card=5555… declined"));
+
+        assert!(Redactor::from_toml("").unwrap().is_empty());
+        assert!(matches!(
+            Redactor::from_toml("[[pattern]]\nregex = '('"),
+            Err(RedactionError::InvalidRegex(_))
+        ));
+        assert!(matches!(
+            Redactor::from_toml("mask = []"),
+            Err(RedactionError::Parse(_))
+        ));
+    }
+}
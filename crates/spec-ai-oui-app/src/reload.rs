@@ -0,0 +1,172 @@
+//! Live Reload of Layout and Redaction Files
+//!
+//! The layout and redaction files given on the command line are checked for
+//! changes a couple of times a second. A changed file is parsed again and
+//! swapped in if it is valid; telemetry, traces and baselines received so
+//! far are kept. An invalid file leaves the previous version in place, so a
+//! half-saved edit never blanks the HUD. Either way the outcome is shown on
+//! the voice line.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use spec_ai_oui::layout::HudLayout;
+
+use crate::redaction::{load_redaction, Redactor};
+use crate::ui::load_layout;
+
+/// How often watched files are checked
+pub const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A file whose modification time and size are compared between checks
+#[derive(Debug)]
+pub struct WatchedFile {
+    path: PathBuf,
+    stamp: Option<(SystemTime, u64)>,
+}
+
+impl WatchedFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let stamp = stamp(&path);
+        Self { path, stamp }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file changed since the last check
+    ///
+    /// A missing file doesn't count as a change, as editors often replace a
+    /// file by removing it first; it counts once it is back.
+    pub fn changed(&mut self) -> bool {
+        match stamp(&self.path) {
+            Some(stamp) if self.stamp != Some(stamp) => {
+                self.stamp = Some(stamp);
+                true
+            }
+            Some(_) => false,
+            None => {
+                self.stamp = None;
+                false
+            }
+        }
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// A watched file that changed, parsed again
+pub enum Reload {
+    Layout(io::Result<HudLayout>),
+    Redaction(io::Result<Redactor>),
+}
+
+impl Reload {
+    /// What the file is, for notices
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Reload::Layout(_) => "layout",
+            Reload::Redaction(_) => "redaction rules",
+        }
+    }
+}
+
+/// Watches the files the app was started with
+#[derive(Debug)]
+pub struct Reloader {
+    layout: Option<WatchedFile>,
+    redaction: Option<WatchedFile>,
+    last_check: Instant,
+}
+
+impl Reloader {
+    /// Watch the given layout and redaction files; built-in defaults are
+    /// not files, so there is nothing to watch when a path is unset
+    pub fn new(layout: Option<&Path>, redaction: Option<&Path>) -> Self {
+        Self {
+            layout: layout.map(WatchedFile::new),
+            redaction: redaction.map(WatchedFile::new),
+            last_check: Instant::now(),
+        }
+    }
+
+    /// Files that changed since the last check, parsed again; checks at
+    /// most once per [`CHECK_INTERVAL`]
+    pub fn poll(&mut self, now: Instant) -> Vec<(PathBuf, Reload)> {
+        if now.saturating_duration_since(self.last_check) < CHECK_INTERVAL {
+            return Vec::new();
+        }
+        self.last_check = now;
+
+        let mut reloads = Vec::new();
+        if let Some(file) = self.layout.as_mut() {
+            if file.changed() {
+                let layout = load_layout(Some(file.path()));
+                reloads.push((file.path().to_path_buf(), Reload::Layout(layout)));
+            }
+        }
+        if let Some(file) = self.redaction.as_mut() {
+            if file.changed() {
+                let redactor = load_redaction(Some(file.path()));
+                reloads.push((file.path().to_path_buf(), Reload::Redaction(redactor)));
+            }
+        }
+        reloads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &str = "[[widget]]\nid = \"content\"\nbind = \"telemetry.content\"\n";
+
+    #[test]
+    fn test_changed_files_are_parsed_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hud.toml");
+        std::fs::write(&path, LAYOUT).unwrap();
+        let mut reloader = Reloader::new(Some(&path), None);
+        let mut now = Instant::now();
+        let mut tick = || {
+            now += CHECK_INTERVAL;
+            now
+        };
+        assert!(reloader.poll(tick()).is_empty());
+
+        // Sizes differ, so the change is seen even within the clock's resolution
+        std::fs::write(&path, "[[widget]]\nid = \"x\"\nbind = \"weather\"\n").unwrap();
+        let reloads = reloader.poll(tick());
+        assert_eq!(reloads.len(), 1);
+        assert_eq!(reloads[0].0, path);
+        assert!(matches!(&reloads[0].1, Reload::Layout(Err(_))));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(reloader.poll(tick()).is_empty());
+
+        std::fs::write(&path, LAYOUT).unwrap();
+        let reloads = reloader.poll(tick());
+        assert!(matches!(&reloads[0].1, Reload::Layout(Ok(layout)) if layout.widgets.len() == 1));
+        assert!(reloader.poll(tick()).is_empty());
+    }
+
+    #[test]
+    fn test_checks_wait_for_the_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("redaction.toml");
+        std::fs::write(&path, "").unwrap();
+        let mut reloader = Reloader::new(None, Some(&path));
+        let start = Instant::now();
+
+        std::fs::write(&path, "# no rules\n").unwrap();
+        assert!(reloader.poll(start).is_empty());
+        let reloads = reloader.poll(start + CHECK_INTERVAL);
+        assert_eq!(reloads[0].1.kind(), "redaction rules");
+    }
+}
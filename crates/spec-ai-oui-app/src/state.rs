@@ -5,11 +5,14 @@
 //! from the telemetry stream.
 
 use std::collections::{HashMap, VecDeque};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
+use spec_ai_oui::context::{DisplayMode, InformationDensity};
 use spec_ai_oui::renderer::Color;
 
-use crate::telemetry::{SpanData, SpanStatus, TelemetryEvent, TelemetryStats, Trace};
+use crate::bridge::{AgentCommand, AgentEvent, AlertLevel, ToolState};
+use crate::control::{ControlEvent, SAMPLE_RATIOS};
+use crate::telemetry::{Annotation, SpanData, SpanStatus, TelemetryEvent, TelemetryStats, Trace};
 
 /// Menu items on the left
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,6 +27,14 @@ impl MenuItem {
         &[MenuItem::Traces, MenuItem::Spans, MenuItem::Services]
     }
 
+    pub fn view(&self) -> View {
+        match self {
+            MenuItem::Traces => View::Traces,
+            MenuItem::Spans => View::Spans,
+            MenuItem::Services => View::Services,
+        }
+    }
+
     pub fn label(&self) -> &'static str {
         match self {
             MenuItem::Traces => "Traces",
@@ -63,6 +74,9 @@ pub struct FeedEvent {
     pub timestamp: String,
     pub priority: EventPriority,
     pub source: TelemetryEvent,
+    /// Spans this entry stands for when it summarizes a noisy service;
+    /// `source` is the latest of them
+    pub summarized: usize,
 }
 
 impl FeedEvent {
@@ -98,19 +112,17 @@ impl FeedEvent {
                 format!("[{}] {}", log.severity.symbol(), truncate(&log.body, 30)),
                 log.service_name.clone(),
             ),
-            TelemetryEvent::Metric(metric) => {
-                let val = match &metric.value {
-                    crate::telemetry::MetricValue::Gauge(v) => format!("{:.2}", v),
-                    crate::telemetry::MetricValue::Counter(v) => format!("{}", v),
-                    crate::telemetry::MetricValue::Histogram { sum, count, .. } => {
-                        format!("avg={:.2}", sum / *count as f64)
-                    }
-                };
-                (
-                    format!("📊 {}: {}", metric.name, val),
-                    metric.service_name.clone(),
-                )
-            }
+            TelemetryEvent::Metric(metric) => (
+                format!("📊 {}: {}", metric.name, metric.value.display()),
+                metric.service_name.clone(),
+            ),
+            TelemetryEvent::Annotation(annotation) => (
+                format!("{} {}", annotation.symbol(), annotation.label),
+                annotation
+                    .detail
+                    .clone()
+                    .unwrap_or_else(|| annotation.service_name.clone()),
+            ),
         };
 
         Self {
@@ -120,19 +132,40 @@ impl FeedEvent {
             timestamp,
             priority,
             source: event,
+            summarized: 0,
         }
     }
+
+    /// Entry standing for the spans a service sent over its cap in the
+    /// current interval, `errors` of which failed
+    pub fn summary(id: usize, latest: TelemetryEvent, count: usize, errors: usize) -> Self {
+        let mut event = Self::from_telemetry(id, latest);
+        let service = event.source.service_name();
+        event.title = format!("≋ {} spans over cap", count);
+        event.detail = if errors > 0 {
+            format!("{} | {} err", service, errors)
+        } else {
+            format!("{} | noisy", service)
+        };
+        event.priority = if errors > 0 {
+            EventPriority::High
+        } else {
+            EventPriority::Low
+        };
+        event.summarized = count;
+        event
+    }
 }
 
+/// First `max` characters of `s`, marked with "..." when cut
 fn truncate(s: &str, max: usize) -> String {
-    if s.len() > max {
-        format!("{}...", &s[..max])
-    } else {
-        s.to_string()
+    match s.char_indices().nth(max) {
+        Some((end, _)) => format!("{}...", &s[..end]),
+        None => s.to_string(),
     }
 }
 
-fn format_time(time: SystemTime) -> String {
+pub(crate) fn format_time(time: SystemTime) -> String {
     use std::time::UNIX_EPOCH;
     let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
     let secs = duration.as_secs();
@@ -167,6 +200,60 @@ impl EventPriority {
     }
 }
 
+/// Filter applied to the event feed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedFilter {
+    /// Only show high-priority (error) events
+    pub errors_only: bool,
+    /// Only show events from this service
+    pub service: Option<String>,
+}
+
+impl FeedFilter {
+    pub fn matches(&self, event: &FeedEvent) -> bool {
+        (!self.errors_only || event.priority == EventPriority::High)
+            && self
+                .service
+                .as_ref()
+                .is_none_or(|s| event.source.service_name() == s)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.errors_only || self.service.is_some()
+    }
+
+    /// Short description for the content title
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if self.errors_only {
+            parts.push("errors".to_string());
+        }
+        if let Some(service) = &self.service {
+            parts.push(service.clone());
+        }
+        parts.join(", ")
+    }
+}
+
+/// Last voice command heard and how it was handled
+#[derive(Debug, Clone)]
+pub struct VoiceTranscript {
+    pub heard: String,
+    pub response: String,
+    pub recognized: bool,
+    /// Tick at which the command was heard
+    pub tick: u64,
+}
+
+/// Outcome of reloading a file, shown on the voice line
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub text: String,
+    pub ok: bool,
+    /// Tick at which the notice was raised
+    pub tick: u64,
+}
+
 /// Which panel has focus
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Focus {
@@ -175,6 +262,42 @@ pub enum Focus {
     Content,
 }
 
+/// Annotations kept for the traces timeline
+const MAX_ANNOTATIONS: usize = 50;
+
+/// Relative latency change treated as noise when comparing to a baseline
+const LATENCY_TOLERANCE: f64 = 0.1;
+
+/// Error rate change (in absolute terms) treated as noise when comparing to
+/// a baseline
+const ERROR_RATE_TOLERANCE: f64 = 0.01;
+
+/// Most spans one service adds to the feed per interval
+///
+/// Spans beyond the cap still count toward traces and stats, but are folded
+/// into one summary entry per service and interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanLimit {
+    pub max_spans: usize,
+    pub interval: Duration,
+}
+
+/// A service's senders on the sampling control channel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sampling {
+    /// Ratio each connected sender reported last, by connection
+    pub senders: HashMap<u64, f64>,
+    /// Ratio asked for that not every sender has reported yet
+    pub requested: Option<f64>,
+}
+
+impl Sampling {
+    /// The ratio the senders apply, the lowest if they differ
+    pub fn ratio(&self) -> Option<f64> {
+        self.senders.values().copied().reduce(f64::min)
+    }
+}
+
 /// Main application state - derived from telemetry stream
 #[derive(Debug, Clone)]
 pub struct AppState {
@@ -188,17 +311,96 @@ pub struct AppState {
     pub content_index: usize,
     pub scroll_offset: usize,
 
+    // Display
+    pub mode: DisplayMode,
+    pub density: InformationDensity,
+
     // Telemetry data (derived from stream)
     pub feed_events: VecDeque<FeedEvent>,
     pub traces: HashMap<String, Trace>,
+    /// Markers for the traces timeline, newest first
+    pub annotations: VecDeque<Annotation>,
     pub services: HashMap<String, ServiceStats>,
     pub stats: TelemetryStats,
+    /// Service aggregates to compare against, e.g. from before a deploy
+    pub baseline: Option<Baseline>,
+    /// Senders on the sampling control channel, by service
+    pub sampling: HashMap<String, Sampling>,
+
+    // Feed controls
+    pub feed_filter: FeedFilter,
+    pub paused: bool,
+    /// Events received while the feed is paused, newest first
+    pub held_events: VecDeque<FeedEvent>,
+
+    // Voice
+    pub transcript: Option<VoiceTranscript>,
+    /// Dictated text being typed (simulates speech in the terminal)
+    pub dictation: Option<String>,
+    /// Last reload of a layout or redaction file
+    pub notice: Option<Notice>,
+
+    // Agent data (derived from the agent bridge)
+    pub agent: AgentPanel,
+    /// Commands waiting to be sent to the agent backend
+    pub pending_commands: Vec<AgentCommand>,
+    /// Sample ratios waiting to be sent to a service's senders
+    pub pending_sampling: Vec<(String, f64)>,
 
     // Configuration
     pub max_feed_events: usize,
+    /// Per-service span cap; unlimited when unset
+    pub span_limit: Option<SpanLimit>,
     pub event_counter: usize,
 }
 
+/// A tool invocation as last reported by the agent
+#[derive(Debug, Clone)]
+pub struct ToolActivity {
+    pub call_id: String,
+    pub tool: String,
+    pub state: ToolState,
+    pub progress: Option<f32>,
+    pub message: Option<String>,
+}
+
+/// An alert raised by the agent
+#[derive(Debug, Clone)]
+pub struct AgentAlert {
+    pub id: u64,
+    pub level: AlertLevel,
+    pub message: String,
+    pub timestamp: String,
+    pub acknowledged: bool,
+}
+
+/// Agent HUD data
+#[derive(Debug, Clone, Default)]
+pub struct AgentPanel {
+    /// Whether an agent backend is attached
+    pub connected: bool,
+    pub last_query: Option<String>,
+    pub last_response: Option<String>,
+    /// Query sent but not yet answered
+    pub awaiting_response: bool,
+    pub tools: VecDeque<ToolActivity>,
+    pub alerts: VecDeque<AgentAlert>,
+}
+
+impl AgentPanel {
+    const MAX_TOOLS: usize = 5;
+    const MAX_ALERTS: usize = 20;
+
+    /// Oldest alert that has not been acknowledged
+    pub fn pending_alert(&self) -> Option<&AgentAlert> {
+        self.alerts.iter().rev().find(|a| !a.acknowledged)
+    }
+
+    pub fn unacknowledged(&self) -> usize {
+        self.alerts.iter().filter(|a| !a.acknowledged).count()
+    }
+}
+
 /// Stats per service
 #[derive(Debug, Clone, Default)]
 pub struct ServiceStats {
@@ -206,6 +408,139 @@ pub struct ServiceStats {
     pub span_count: usize,
     pub error_count: usize,
     pub last_seen: Option<SystemTime>,
+    /// Total duration of the ended spans
+    pub latency_total: Duration,
+    /// Ended spans with a duration
+    pub timed_spans: usize,
+    /// Spans folded into summary entries over the whole session
+    pub summarized_spans: usize,
+    /// Went over the span cap in the current or previous interval
+    pub noisy: bool,
+    pub window: IngestWindow,
+}
+
+/// A service's aggregates at one point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServiceSnapshot {
+    pub span_count: usize,
+    pub error_count: usize,
+    pub latency_total: Duration,
+    pub timed_spans: usize,
+}
+
+impl ServiceSnapshot {
+    /// Aggregates of what happened after `earlier`
+    pub fn since(&self, earlier: &ServiceSnapshot) -> ServiceSnapshot {
+        ServiceSnapshot {
+            span_count: self.span_count.saturating_sub(earlier.span_count),
+            error_count: self.error_count.saturating_sub(earlier.error_count),
+            latency_total: self.latency_total.saturating_sub(earlier.latency_total),
+            timed_spans: self.timed_spans.saturating_sub(earlier.timed_spans),
+        }
+    }
+
+    pub fn error_rate(&self) -> Option<f64> {
+        (self.span_count > 0).then(|| self.error_count as f64 / self.span_count as f64)
+    }
+
+    pub fn avg_latency(&self) -> Option<Duration> {
+        (self.timed_spans > 0).then(|| self.latency_total / self.timed_spans as u32)
+    }
+}
+
+/// Service aggregates marked as the point to compare against
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    pub taken_at: SystemTime,
+    pub services: HashMap<String, ServiceSnapshot>,
+}
+
+/// Whether a metric got better or worse than its baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Improved,
+    Unchanged,
+    Regressed,
+}
+
+impl Trend {
+    /// Classify a change, where higher is worse and changes within
+    /// `tolerance` don't count
+    fn of(change: f64, tolerance: f64) -> Self {
+        if change > tolerance {
+            Trend::Regressed
+        } else if change < -tolerance {
+            Trend::Improved
+        } else {
+            Trend::Unchanged
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Trend::Improved => Color::Green,
+            Trend::Unchanged => Color::DarkGrey,
+            Trend::Regressed => Color::Red,
+        }
+    }
+}
+
+/// How a service has done since the baseline compared to before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BaselineDelta {
+    /// Change in mean latency, in milliseconds
+    pub latency_ms: Option<(f64, Trend)>,
+    /// Change in error rate, as a fraction
+    pub error_rate: Option<(f64, Trend)>,
+}
+
+/// Spans a service sent in the current cap interval
+#[derive(Debug, Clone, Default)]
+pub struct IngestWindow {
+    pub start: Option<Instant>,
+    pub spans: usize,
+    pub summarized: usize,
+    pub summarized_errors: usize,
+    /// Feed entry summarizing this interval's spans over the cap
+    pub summary_id: Option<usize>,
+}
+
+impl ServiceStats {
+    /// Aggregates as of now
+    pub fn snapshot(&self) -> ServiceSnapshot {
+        ServiceSnapshot {
+            span_count: self.span_count,
+            error_count: self.error_count,
+            latency_total: self.latency_total,
+            timed_spans: self.timed_spans,
+        }
+    }
+
+    /// Count a span against the cap, returning whether it may go to the feed
+    fn admit(&mut self, limit: SpanLimit, is_error: bool, now: Instant) -> bool {
+        let elapsed = self.window.start.map(|start| now.duration_since(start));
+        if elapsed.is_none_or(|elapsed| elapsed >= limit.interval) {
+            // A service stays flagged for the interval after it was capped
+            self.noisy = self.window.summarized > 0
+                && elapsed.is_some_and(|elapsed| elapsed < limit.interval * 2);
+            self.window = IngestWindow {
+                start: Some(now),
+                ..Default::default()
+            };
+        }
+
+        self.window.spans += 1;
+        if self.window.spans <= limit.max_spans {
+            return true;
+        }
+        self.window.summarized += 1;
+        if is_error {
+            self.window.summarized_errors += 1;
+        }
+        self.summarized_spans += 1;
+        self.noisy = true;
+        false
+    }
 }
 
 impl AppState {
@@ -217,17 +552,40 @@ impl AppState {
             menu_index: 0,
             content_index: 0,
             scroll_offset: 0,
+            mode: DisplayMode::Research,
+            density: DisplayMode::Research.default_density(),
             feed_events: VecDeque::new(),
             traces: HashMap::new(),
+            annotations: VecDeque::new(),
             services: HashMap::new(),
             stats: TelemetryStats::default(),
+            baseline: None,
+            sampling: HashMap::new(),
+            feed_filter: FeedFilter::default(),
+            paused: false,
+            held_events: VecDeque::new(),
+            transcript: None,
+            dictation: None,
+            notice: None,
+            agent: AgentPanel::default(),
+            pending_commands: Vec::new(),
+            pending_sampling: Vec::new(),
             max_feed_events: 100,
+            span_limit: None,
             event_counter: 0,
         }
     }
 
     /// Process an incoming telemetry event
     pub fn process_telemetry(&mut self, event: TelemetryEvent) {
+        self.process_telemetry_at(event, Instant::now());
+    }
+
+    /// Process a telemetry event arriving at `now`, which span caps count
+    /// intervals by
+    pub fn process_telemetry_at(&mut self, event: TelemetryEvent, now: Instant) {
+        let mut over_cap = false;
+
         // Update stats
         match &event {
             TelemetryEvent::SpanStarted(span) | TelemetryEvent::SpanEnded(span) => {
@@ -259,6 +617,13 @@ impl AppState {
                     service.error_count += 1;
                 }
                 service.last_seen = Some(span.start_time);
+                if let (TelemetryEvent::SpanEnded(_), Some(duration)) = (&event, span.duration()) {
+                    service.latency_total += duration;
+                    service.timed_spans += 1;
+                }
+                if let Some(limit) = self.span_limit {
+                    over_cap = !service.admit(limit, span.status == SpanStatus::Error, now);
+                }
             }
             TelemetryEvent::Log(log) => {
                 self.stats.total_logs += 1;
@@ -269,14 +634,65 @@ impl AppState {
                 }
             }
             TelemetryEvent::Metric(_) => {}
+            TelemetryEvent::Annotation(annotation) => {
+                // Annotations may mark earlier times, so keep them in order
+                let index = self
+                    .annotations
+                    .iter()
+                    .position(|a| a.timestamp <= annotation.timestamp)
+                    .unwrap_or(self.annotations.len());
+                self.annotations.insert(index, annotation.clone());
+                self.annotations.truncate(MAX_ANNOTATIONS);
+            }
         }
 
         // Update services list
         self.stats.services = self.services.keys().cloned().collect();
 
+        if over_cap {
+            self.summarize(event);
+            return;
+        }
+
         // Add to feed
         self.event_counter += 1;
         let feed_event = FeedEvent::from_telemetry(self.event_counter, event);
+        self.push_feed(feed_event);
+    }
+
+    /// Fold a span over its service's cap into the service's summary entry,
+    /// moving the entry to the top of the feed
+    fn summarize(&mut self, span: TelemetryEvent) {
+        let Some(service) = self.services.get_mut(span.service_name()) else {
+            return;
+        };
+        let window = &mut service.window;
+        let existing = window.summary_id.and_then(|id| {
+            [&mut self.feed_events, &mut self.held_events]
+                .into_iter()
+                .find_map(|events| {
+                    let index = events.iter().position(|e| e.id == id)?;
+                    events.remove(index)
+                })
+        });
+        let id = match existing {
+            Some(entry) => entry.id,
+            None => {
+                self.event_counter += 1;
+                window.summary_id = Some(self.event_counter);
+                self.event_counter
+            }
+        };
+        let summary = FeedEvent::summary(id, span, window.summarized, window.summarized_errors);
+        self.push_feed(summary);
+    }
+
+    fn push_feed(&mut self, feed_event: FeedEvent) {
+        if self.paused {
+            self.held_events.push_front(feed_event);
+            self.held_events.truncate(self.max_feed_events);
+            return;
+        }
         self.feed_events.push_front(feed_event);
 
         // Trim feed to max size
@@ -285,12 +701,261 @@ impl AppState {
         }
     }
 
+    /// Feed events that pass the current filter, newest first
+    pub fn visible_feed(&self) -> impl Iterator<Item = &FeedEvent> {
+        self.feed_events
+            .iter()
+            .filter(|e| self.feed_filter.matches(e))
+    }
+
+    /// Stop adding events to the feed; they are held until resumed
+    pub fn pause_feed(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume the feed, adding any events held while paused
+    pub fn resume_feed(&mut self) {
+        self.paused = false;
+        while let Some(event) = self.held_events.pop_back() {
+            self.feed_events.push_front(event);
+        }
+        self.feed_events.truncate(self.max_feed_events);
+    }
+
+    /// Mark the current service aggregates as the baseline to compare against
+    pub fn mark_baseline(&mut self) {
+        self.baseline = Some(Baseline {
+            taken_at: SystemTime::now(),
+            services: self
+                .services
+                .iter()
+                .map(|(name, service)| (name.clone(), service.snapshot()))
+                .collect(),
+        });
+    }
+
+    pub fn clear_baseline(&mut self) {
+        self.baseline = None;
+    }
+
+    /// Compare a service's spans since the baseline with those before it
+    ///
+    /// `None` without a baseline, or for a service that had no spans before
+    /// the baseline.
+    pub fn baseline_delta(&self, service: &ServiceStats) -> Option<BaselineDelta> {
+        let before = self.baseline.as_ref()?.services.get(&service.name)?;
+        let after = service.snapshot().since(before);
+        let latency_ms = before
+            .avg_latency()
+            .zip(after.avg_latency())
+            .map(|(before, after)| {
+                let before = before.as_secs_f64() * 1000.0;
+                let change = after.as_secs_f64() * 1000.0 - before;
+                let relative = if before > 0.0 { change / before } else { 0.0 };
+                (change, Trend::of(relative, LATENCY_TOLERANCE))
+            });
+        let error_rate = before
+            .error_rate()
+            .zip(after.error_rate())
+            .map(|(before, after)| {
+                let change = after - before;
+                (change, Trend::of(change, ERROR_RATE_TOLERANCE))
+            });
+        Some(BaselineDelta {
+            latency_ms,
+            error_rate,
+        })
+    }
+
+    /// Track the senders connected to the sampling control channel
+    pub fn process_control_event(&mut self, event: ControlEvent) {
+        match event {
+            ControlEvent::Reported {
+                connection,
+                service,
+                sample_ratio,
+            } => {
+                let sampling = self.sampling.entry(service).or_default();
+                sampling.senders.insert(connection, sample_ratio);
+                if let Some(requested) = sampling.requested {
+                    if sampling.senders.values().all(|ratio| *ratio == requested) {
+                        sampling.requested = None;
+                    }
+                }
+            }
+            ControlEvent::Disconnected {
+                connection,
+                service,
+            } => {
+                if let Some(sampling) = self.sampling.get_mut(&service) {
+                    sampling.senders.remove(&connection);
+                    if sampling.senders.is_empty() {
+                        self.sampling.remove(&service);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Service selected in the Services view
+    pub fn selected_service(&self) -> Option<&ServiceStats> {
+        if self.view != View::Services {
+            return None;
+        }
+        self.services.values().nth(self.content_index)
+    }
+
+    /// Ask the selected service's senders for the next higher (or lower)
+    /// ratio in [`SAMPLE_RATIOS`], queueing the change for the control channel
+    ///
+    /// Returns the service and the ratio asked for, or `None` when the
+    /// service has no sender on the channel or is at the end of the range.
+    pub fn step_sampling(&mut self, up: bool) -> Option<(String, f64)> {
+        let service = self.selected_service()?.name.clone();
+        let sampling = self.sampling.get_mut(&service)?;
+        let current = sampling.requested.or(sampling.ratio())?;
+        let next = if up {
+            SAMPLE_RATIOS.iter().find(|ratio| **ratio > current)
+        } else {
+            SAMPLE_RATIOS.iter().rev().find(|ratio| **ratio < current)
+        }
+        .copied()?;
+        sampling.requested = Some(next);
+        self.pending_sampling.push((service.clone(), next));
+        Some((service, next))
+    }
+
+    /// Switch to a view with the content panel focused
+    pub fn show_view(&mut self, view: View) {
+        self.view = view;
+        self.focus = Focus::Content;
+        self.content_index = 0;
+        self.scroll_offset = 0;
+        if let Some(index) = MenuItem::all().iter().position(|m| m.view() == view) {
+            self.menu_index = index;
+        }
+    }
+
+    /// Show only error events in the feed
+    pub fn filter_errors(&mut self) {
+        self.feed_filter.errors_only = true;
+        self.show_view(View::Feed);
+    }
+
+    /// Show only events from the named service (case-insensitive)
+    ///
+    /// Returns the matched service name, or `None` if no such service was seen.
+    pub fn filter_service(&mut self, name: &str) -> Option<String> {
+        let wanted = name.to_lowercase().replace(' ', "-");
+        let service = self
+            .services
+            .keys()
+            .find(|s| s.to_lowercase() == wanted)
+            .cloned()?;
+        self.feed_filter.service = Some(service.clone());
+        self.show_view(View::Feed);
+        Some(service)
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.feed_filter = FeedFilter::default();
+        self.content_index = 0;
+        self.scroll_offset = 0;
+    }
+
+    /// Record the transcript line for a voice command
+    pub fn set_transcript(&mut self, heard: &str, response: impl Into<String>, recognized: bool) {
+        self.transcript = Some(VoiceTranscript {
+            heard: heard.to_string(),
+            response: response.into(),
+            recognized,
+            tick: self.tick,
+        });
+    }
+
+    /// Show `text` on the voice line, as a confirmation or an error
+    pub fn set_notice(&mut self, text: impl Into<String>, ok: bool) {
+        self.notice = Some(Notice {
+            text: text.into(),
+            ok,
+            tick: self.tick,
+        });
+    }
+
+    /// Process an incoming agent event
+    pub fn process_agent_event(&mut self, event: AgentEvent) {
+        let agent = &mut self.agent;
+        match event {
+            AgentEvent::Response { query, text, .. } => {
+                if query.is_some() {
+                    agent.last_query = query;
+                }
+                agent.last_response = Some(text);
+                agent.awaiting_response = false;
+            }
+            AgentEvent::ToolProgress {
+                call_id,
+                tool,
+                state,
+                progress,
+                message,
+            } => {
+                agent.tools.retain(|t| t.call_id != call_id);
+                agent.tools.push_front(ToolActivity {
+                    call_id,
+                    tool,
+                    state,
+                    progress,
+                    message,
+                });
+                agent.tools.truncate(AgentPanel::MAX_TOOLS);
+            }
+            AgentEvent::Alert {
+                id,
+                level,
+                message,
+                timestamp,
+            } => {
+                agent.alerts.push_front(AgentAlert {
+                    id,
+                    level,
+                    message,
+                    timestamp: format_time(timestamp),
+                    acknowledged: false,
+                });
+                agent.alerts.truncate(AgentPanel::MAX_ALERTS);
+            }
+        }
+    }
+
+    /// Acknowledge the oldest pending alert, queueing the command for the backend
+    pub fn acknowledge_alert(&mut self) {
+        let Some(alert) = self.agent.alerts.iter_mut().rev().find(|a| !a.acknowledged) else {
+            return;
+        };
+        alert.acknowledged = true;
+        self.pending_commands
+            .push(AgentCommand::Acknowledge { alert_id: alert.id });
+    }
+
+    /// Ask the agent a question, queueing the command for the backend
+    pub fn trigger_query(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() || !self.agent.connected {
+            return;
+        }
+        self.agent.last_query = Some(query.to_string());
+        self.agent.awaiting_response = true;
+        self.pending_commands.push(AgentCommand::TriggerQuery {
+            query: query.to_string(),
+        });
+    }
+
     /// Get the list of items for the current view
     pub fn content_items(&self) -> Vec<ContentItem> {
         match self.view {
             View::Feed => self
-                .feed_events
-                .iter()
+                .visible_feed()
                 .map(|e| ContentItem::Event(e.clone()))
                 .collect(),
             View::Traces => self
@@ -302,7 +967,9 @@ impl AppState {
                 .feed_events
                 .iter()
                 .filter_map(|e| match &e.source {
-                    TelemetryEvent::SpanEnded(s) => Some(ContentItem::Span(s.clone())),
+                    TelemetryEvent::SpanEnded(s) if e.summarized == 0 => {
+                        Some(ContentItem::Span(s.clone()))
+                    }
                     _ => None,
                 })
                 .collect(),
@@ -317,12 +984,12 @@ impl AppState {
 
     pub fn content_len(&self) -> usize {
         match self.view {
-            View::Feed => self.feed_events.len(),
+            View::Feed => self.visible_feed().count(),
             View::Traces => self.traces.len(),
             View::Spans => self
                 .feed_events
                 .iter()
-                .filter(|e| matches!(e.source, TelemetryEvent::SpanEnded(_)))
+                .filter(|e| e.summarized == 0 && matches!(e.source, TelemetryEvent::SpanEnded(_)))
                 .count(),
             View::Services => self.services.len(),
         }
@@ -370,6 +1037,23 @@ impl AppState {
         }
     }
 
+    /// Switch to the next display mode, resetting density to its default
+    pub fn cycle_mode(&mut self) {
+        const MODES: [DisplayMode; 8] = [
+            DisplayMode::Research,
+            DisplayMode::Meeting,
+            DisplayMode::Navigation,
+            DisplayMode::Social,
+            DisplayMode::Recording,
+            DisplayMode::Private,
+            DisplayMode::Ambient,
+            DisplayMode::Focus,
+        ];
+        let index = MODES.iter().position(|m| *m == self.mode).unwrap_or(0);
+        self.mode = MODES[(index + 1) % MODES.len()];
+        self.density = self.mode.default_density();
+    }
+
     /// Toggle focus between menu and content
     pub fn toggle_focus(&mut self) {
         self.focus = match self.focus {
@@ -382,12 +1066,7 @@ impl AppState {
     pub fn select(&mut self) {
         match self.focus {
             Focus::Menu => {
-                let item = MenuItem::all()[self.menu_index];
-                self.view = match item {
-                    MenuItem::Traces => View::Traces,
-                    MenuItem::Spans => View::Spans,
-                    MenuItem::Services => View::Services,
-                };
+                self.view = MenuItem::all()[self.menu_index].view();
                 self.focus = Focus::Content;
                 self.content_index = 0;
                 self.scroll_offset = 0;
@@ -408,7 +1087,7 @@ impl AppState {
     }
 
     pub fn selected_event(&self) -> Option<&FeedEvent> {
-        self.feed_events.get(self.content_index)
+        self.visible_feed().nth(self.content_index)
     }
 }
 
@@ -426,3 +1105,198 @@ pub enum ContentItem {
     Span(SpanData),
     Service(ServiceStats),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(service: &str, status: SpanStatus) -> TelemetryEvent {
+        TelemetryEvent::SpanEnded(SpanData {
+            trace_id: "t".to_string(),
+            span_id: "s".to_string(),
+            parent_span_id: None,
+            name: "op".to_string(),
+            kind: crate::telemetry::SpanKind::Internal,
+            start_time: SystemTime::now(),
+            end_time: Some(SystemTime::now()),
+            status,
+            attributes: HashMap::new(),
+            service_name: service.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_feed_filters_and_pause() {
+        let mut state = AppState::new();
+        state.process_telemetry(span("api", SpanStatus::Ok));
+        state.process_telemetry(span("db", SpanStatus::Error));
+
+        state.filter_errors();
+        assert_eq!(state.content_len(), 1);
+        assert_eq!(state.filter_service("DB").as_deref(), Some("db"));
+        assert_eq!(state.content_len(), 1);
+        assert!(state.filter_service("cache").is_none());
+        state.clear_filter();
+        assert_eq!(state.content_len(), 2);
+
+        state.pause_feed();
+        state.process_telemetry(span("api", SpanStatus::Ok));
+        assert_eq!(state.feed_events.len(), 2);
+        assert_eq!(state.stats.total_spans, 3);
+        state.resume_feed();
+        assert_eq!(state.feed_events.len(), 3);
+        assert_eq!(state.feed_events[0].id, 3);
+    }
+
+    #[test]
+    fn test_spans_over_cap_are_summarized() {
+        let mut state = AppState::new();
+        state.span_limit = Some(SpanLimit {
+            max_spans: 2,
+            interval: Duration::from_secs(1),
+        });
+        let start = Instant::now();
+        for status in [
+            SpanStatus::Ok,
+            SpanStatus::Ok,
+            SpanStatus::Ok,
+            SpanStatus::Error,
+            SpanStatus::Ok,
+        ] {
+            state.process_telemetry_at(span("chatty", status), start);
+        }
+        state.process_telemetry_at(span("quiet", SpanStatus::Ok), start);
+
+        assert_eq!(state.stats.total_spans, 6);
+        assert_eq!(state.feed_events.len(), 4);
+        let summary = &state.feed_events[1];
+        assert_eq!(summary.summarized, 3);
+        assert_eq!(summary.priority, EventPriority::High);
+        assert_eq!(summary.detail, "chatty | 1 err");
+        assert!(state.services["chatty"].noisy);
+        assert!(!state.services["quiet"].noisy);
+        state.show_view(View::Spans);
+        assert_eq!(state.content_len(), 3);
+
+        // Flagged through the next interval, cleared once a full one passes
+        let later = start + Duration::from_millis(1500);
+        state.process_telemetry_at(span("chatty", SpanStatus::Ok), later);
+        assert!(state.services["chatty"].noisy);
+        assert_eq!(state.feed_events.len(), 5);
+        let quiet_again = later + Duration::from_secs(1);
+        state.process_telemetry_at(span("chatty", SpanStatus::Ok), quiet_again);
+        assert!(!state.services["chatty"].noisy);
+        assert_eq!(state.services["chatty"].summarized_spans, 3);
+    }
+
+    fn timed_span(service: &str, status: SpanStatus, millis: u64) -> TelemetryEvent {
+        let TelemetryEvent::SpanEnded(mut data) = span(service, status) else {
+            unreachable!()
+        };
+        data.end_time = Some(data.start_time + Duration::from_millis(millis));
+        TelemetryEvent::SpanEnded(data)
+    }
+
+    #[test]
+    fn test_services_compared_to_baseline() {
+        let mut state = AppState::new();
+        for _ in 0..4 {
+            state.process_telemetry(timed_span("api", SpanStatus::Ok, 100));
+            state.process_telemetry(timed_span("db", SpanStatus::Ok, 10));
+        }
+        state.mark_baseline();
+        state.process_telemetry(timed_span("cache", SpanStatus::Ok, 5));
+        for status in [SpanStatus::Ok, SpanStatus::Error] {
+            state.process_telemetry(timed_span("api", status, 150));
+            state.process_telemetry(timed_span("db", SpanStatus::Ok, 5));
+        }
+
+        let api = state.baseline_delta(&state.services["api"]).unwrap();
+        let (latency, trend) = api.latency_ms.unwrap();
+        assert!((latency - 50.0).abs() < 1e-6);
+        assert_eq!(trend, Trend::Regressed);
+        let (error_rate, trend) = api.error_rate.unwrap();
+        assert!((error_rate - 0.5).abs() < 1e-9);
+        assert_eq!(trend, Trend::Regressed);
+
+        let db = state.baseline_delta(&state.services["db"]).unwrap();
+        assert_eq!(db.latency_ms.unwrap().1, Trend::Improved);
+        assert_eq!(db.error_rate.unwrap().1, Trend::Unchanged);
+
+        // New since the baseline, so nothing to compare with
+        assert!(state.baseline_delta(&state.services["cache"]).is_none());
+        state.clear_baseline();
+        assert!(state.baseline_delta(&state.services["api"]).is_none());
+    }
+
+    #[test]
+    fn test_sampling_stepped_for_selected_service() {
+        let mut state = AppState::new();
+        state.process_telemetry(timed_span("api", SpanStatus::Ok, 100));
+        state.show_view(View::Services);
+        assert_eq!(state.step_sampling(false), None);
+
+        for connection in [1, 2] {
+            state.process_control_event(ControlEvent::Reported {
+                connection,
+                service: "api".to_string(),
+                sample_ratio: 1.0,
+            });
+        }
+        assert_eq!(state.step_sampling(true), None);
+        assert_eq!(state.step_sampling(false), Some(("api".to_string(), 0.5)));
+        assert_eq!(state.step_sampling(false), Some(("api".to_string(), 0.25)));
+        assert_eq!(
+            state.pending_sampling,
+            [("api".to_string(), 0.5), ("api".to_string(), 0.25)]
+        );
+
+        // The request stands until every sender reports it
+        for connection in [1, 2] {
+            state.process_control_event(ControlEvent::Reported {
+                connection,
+                service: "api".to_string(),
+                sample_ratio: 0.25,
+            });
+            let sampling = &state.sampling["api"];
+            assert_eq!(sampling.requested.is_none(), connection == 2);
+        }
+        assert_eq!(state.sampling["api"].ratio(), Some(0.25));
+
+        for connection in [1, 2] {
+            state.process_control_event(ControlEvent::Disconnected {
+                connection,
+                service: "api".to_string(),
+            });
+        }
+        assert!(state.sampling.is_empty());
+    }
+
+    #[test]
+    fn test_alerts_acknowledged_oldest_first() {
+        let mut state = AppState::new();
+        state.agent.connected = true;
+        for id in 1..=2 {
+            state.process_agent_event(AgentEvent::Alert {
+                id,
+                level: AlertLevel::Warning,
+                message: format!("alert {}", id),
+                timestamp: SystemTime::now(),
+            });
+        }
+
+        state.acknowledge_alert();
+        assert_eq!(state.agent.pending_alert().map(|a| a.id), Some(2));
+        state.trigger_query("why?");
+        assert!(state.agent.awaiting_response);
+        assert_eq!(
+            state.pending_commands,
+            vec![
+                AgentCommand::Acknowledge { alert_id: 1 },
+                AgentCommand::TriggerQuery {
+                    query: "why?".to_string()
+                },
+            ]
+        );
+    }
+}
@@ -6,8 +6,11 @@
 use std::collections::HashMap;
 use std::time::{Duration, SystemTime};
 
+use serde::{Deserialize, Serialize};
+
 /// Status of a span
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SpanStatus {
     Unset,
     Ok,
@@ -56,7 +59,8 @@ impl SpanData {
 }
 
 /// Kind of span
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SpanKind {
     #[default]
     Internal,
@@ -79,7 +83,8 @@ impl SpanKind {
 }
 
 /// Severity level for log records
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Severity {
     Trace,
     Debug,
@@ -133,7 +138,8 @@ pub struct MetricData {
 }
 
 /// Metric value types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MetricValue {
     Gauge(f64),
     Counter(u64),
@@ -144,6 +150,39 @@ pub enum MetricValue {
     },
 }
 
+impl MetricValue {
+    /// Short rendering of the value; an empty histogram has no average
+    pub fn display(&self) -> String {
+        match self {
+            MetricValue::Gauge(v) => format!("{:.2}", v),
+            MetricValue::Counter(v) => format!("{}", v),
+            MetricValue::Histogram { count: 0, .. } => "avg=-".to_string(),
+            MetricValue::Histogram { sum, count, .. } => {
+                format!("avg={:.2}", sum / *count as f64)
+            }
+        }
+    }
+}
+
+/// A labeled point in time, such as a deployment, pushed by an agent
+///
+/// Agents export annotations as zero-length spans carrying a
+/// `spec_ai.annotation` attribute; the receiver turns those into this type.
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    pub label: String,
+    pub detail: Option<String>,
+    pub severity: Severity,
+    pub timestamp: SystemTime,
+    pub service_name: String,
+}
+
+impl Annotation {
+    pub fn symbol(&self) -> &'static str {
+        "⚑"
+    }
+}
+
 /// A telemetry event that can be displayed in the UI
 #[derive(Debug, Clone)]
 pub enum TelemetryEvent {
@@ -151,6 +190,7 @@ pub enum TelemetryEvent {
     SpanEnded(SpanData),
     Log(LogRecord),
     Metric(MetricData),
+    Annotation(Annotation),
 }
 
 impl TelemetryEvent {
@@ -161,6 +201,7 @@ impl TelemetryEvent {
             TelemetryEvent::SpanEnded(span) => span.end_time.unwrap_or(span.start_time),
             TelemetryEvent::Log(log) => log.timestamp,
             TelemetryEvent::Metric(metric) => metric.timestamp,
+            TelemetryEvent::Annotation(annotation) => annotation.timestamp,
         }
     }
 
@@ -172,6 +213,7 @@ impl TelemetryEvent {
             }
             TelemetryEvent::Log(log) => &log.service_name,
             TelemetryEvent::Metric(metric) => &metric.service_name,
+            TelemetryEvent::Annotation(annotation) => &annotation.service_name,
         }
     }
 
@@ -192,22 +234,17 @@ impl TelemetryEvent {
                 format!("{} {}{}", status, span.name, dur)
             }
             TelemetryEvent::Log(log) => {
-                let body = if log.body.len() > 40 {
-                    format!("{}...", &log.body[..40])
-                } else {
-                    log.body.clone()
+                let body = match log.body.char_indices().nth(40) {
+                    Some((end, _)) => format!("{}...", &log.body[..end]),
+                    None => log.body.clone(),
                 };
                 format!("[{}] {}", log.severity.symbol(), body)
             }
             TelemetryEvent::Metric(metric) => {
-                let val = match &metric.value {
-                    MetricValue::Gauge(v) => format!("{:.2}", v),
-                    MetricValue::Counter(v) => format!("{}", v),
-                    MetricValue::Histogram { sum, count, .. } => {
-                        format!("avg={:.2}", sum / *count as f64)
-                    }
-                };
-                format!("📊 {}: {}", metric.name, val)
+                format!("📊 {}: {}", metric.name, metric.value.display())
+            }
+            TelemetryEvent::Annotation(annotation) => {
+                format!("{} {}", annotation.symbol(), annotation.label)
             }
         }
     }
@@ -227,6 +264,11 @@ impl TelemetryEvent {
                 _ => 1,
             },
             TelemetryEvent::Metric(_) => 1,
+            // Annotations are pushed to be seen, so they stand out at least a little
+            TelemetryEvent::Annotation(annotation) => match annotation.severity {
+                Severity::Fatal | Severity::Error => 3,
+                _ => 2,
+            },
         }
     }
 }
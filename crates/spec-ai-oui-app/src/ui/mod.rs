@@ -1,36 +1,152 @@
 //! OpenTelemetry visualization UI
 //!
-//! Layout:
-//! - Upper left: Menu (Traces, Spans, Services)
-//! - Upper right: Event feed or filtered views
-//! - Bottom: Stats bar
-
-use crate::state::{AppState, ContentItem, Focus, MenuItem, View};
-use crate::telemetry::SpanStatus;
+//! Panels are described by a [`HudLayout`] (see `layouts/default.toml`),
+//! which binds each widget to one of the panels below by name and gives it a
+//! priority. Panels are then placed by attention zone rather than fixed
+//! coordinates: critical agent alerts take the foveal zone, the content
+//! panel and voice line sit in the parafoveal zone, and the menu, stats and
+//! help move to the periphery, following the current display mode and
+//! information density.
+
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::bridge::{AlertLevel, ToolState};
+use crate::state::{format_time, AppState, ContentItem, Focus, MenuItem, Notice, View};
+use crate::telemetry::{Severity, SpanStatus};
+use spec_ai_oui::context::DisplayContext;
+use spec_ai_oui::layout::{HudLayout, ZoneRect};
 use spec_ai_oui::renderer::{Color, RenderBackend};
 
+/// Layout used when no layout file is given
+pub const DEFAULT_LAYOUT: &str = include_str!("../../layouts/default.toml");
+
+/// How long (in ticks) the voice transcript stays visible
+const TRANSCRIPT_TICKS: u64 = 50;
+
+/// List rows the traces timeline takes
+const TIMELINE_ROWS: usize = 2;
+
+/// Longest annotation label shown on the timeline
+const MAX_TIMELINE_LABEL: usize = 24;
+
+/// Panels a layout widget can bind to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    CriticalAlert,
+    Alert,
+    Content,
+    Voice,
+    Agent,
+    Menu,
+    Stats,
+    Help,
+}
+
+impl Panel {
+    const ALL: [Panel; 8] = [
+        Panel::CriticalAlert,
+        Panel::Alert,
+        Panel::Content,
+        Panel::Voice,
+        Panel::Agent,
+        Panel::Menu,
+        Panel::Stats,
+        Panel::Help,
+    ];
+
+    /// Binding name used in layout files
+    fn binding(&self) -> &'static str {
+        match self {
+            Panel::CriticalAlert => "agent.alert.critical",
+            Panel::Alert => "agent.alert",
+            Panel::Content => "telemetry.content",
+            Panel::Voice => "voice",
+            Panel::Agent => "agent.activity",
+            Panel::Menu => "menu",
+            Panel::Stats => "telemetry.stats",
+            Panel::Help => "help",
+        }
+    }
+
+    fn from_binding(name: &str) -> Option<Panel> {
+        Panel::ALL.into_iter().find(|p| p.binding() == name)
+    }
+
+    /// Whether the panel has anything to show this frame
+    fn is_active(&self, state: &AppState) -> bool {
+        let alert_level = state.agent.pending_alert().map(|a| a.level);
+        match self {
+            Panel::CriticalAlert => alert_level == Some(AlertLevel::Critical),
+            Panel::Alert => alert_level.is_some_and(|l| l != AlertLevel::Critical),
+            Panel::Voice => voice_active(state),
+            Panel::Agent => state.agent.connected,
+            Panel::Help => state.tick < 300,
+            Panel::Content | Panel::Menu | Panel::Stats => true,
+        }
+    }
+}
+
+/// Load a HUD layout file, or the default layout when no path is given
+///
+/// Fails if the file cannot be parsed or binds to an unknown panel.
+pub fn load_layout(path: Option<&Path>) -> io::Result<HudLayout> {
+    let layout = match path {
+        Some(path) => HudLayout::load(path),
+        None => HudLayout::from_toml(DEFAULT_LAYOUT),
+    }
+    .and_then(|layout| {
+        let bindings = Panel::ALL.map(|p| p.binding());
+        layout.check_bindings(&bindings).map(|_| layout)
+    });
+    layout.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
 /// Render the OUI app
-pub fn render_app(state: &AppState, backend: &mut dyn RenderBackend) {
+pub fn render_app(
+    state: &AppState,
+    context: &DisplayContext,
+    layout: &HudLayout,
+    backend: &mut dyn RenderBackend,
+) {
     // Dark background
     backend.clear(Color::Rgb(8, 10, 14));
 
-    // Render menu on upper left
-    render_menu(state, backend);
-
-    // Render main content on upper right
-    render_content(state, backend);
+    let placements = layout.place(context.mode, context.density, |widget| {
+        Panel::from_binding(&widget.bind).is_some_and(|p| p.is_active(state))
+    });
+    for placement in placements {
+        let Some(panel) = Panel::from_binding(&placement.key.bind) else {
+            continue;
+        };
+        let rect = placement.rect;
+        match panel {
+            Panel::CriticalAlert | Panel::Alert => render_alert(state, backend, rect),
+            Panel::Content => render_content(state, backend, rect),
+            Panel::Voice => render_voice(state, backend, rect),
+            Panel::Agent => render_agent(state, backend, rect),
+            Panel::Menu => render_menu(state, backend, rect),
+            Panel::Stats => render_stats(state, context, backend, rect),
+            Panel::Help => render_help(backend, rect),
+        }
+    }
+}
 
-    // Stats bar at bottom
-    render_stats(state, backend);
+/// Number of text columns that fit in a rect
+fn columns(backend: &dyn RenderBackend, rect: ZoneRect) -> usize {
+    (rect.width * backend.capabilities().width as f32) as usize
+}
 
-    // Help hint
-    render_help(state, backend);
+/// Number of list rows of `row_height` that fit below a panel header
+fn visible_rows(rect: ZoneRect, row_height: f32) -> usize {
+    (((rect.height - 0.12) / row_height) as usize).max(1)
 }
 
-/// Upper left: menu panel
-fn render_menu(state: &AppState, backend: &mut dyn RenderBackend) {
-    let x = 0.02;
-    let y = 0.04;
+/// Menu panel
+fn render_menu(state: &AppState, backend: &mut dyn RenderBackend, rect: ZoneRect) {
+    let x = rect.x;
+    let y = rect.y + 0.02;
 
     let focused = state.focus == Focus::Menu;
     let border_color = if focused {
@@ -68,12 +184,10 @@ fn render_menu(state: &AppState, backend: &mut dyn RenderBackend) {
     backend.draw_hud_text(x, bottom_y, "└──────────┘", border_color);
 }
 
-/// Upper right: content panel
-fn render_content(state: &AppState, backend: &mut dyn RenderBackend) {
-    let x = 0.55;
-    let y = 0.04;
-    let width = 0.43;
-    let height = 0.45;
+/// Content panel: event feed or filtered views
+fn render_content(state: &AppState, backend: &mut dyn RenderBackend, rect: ZoneRect) {
+    let x = rect.x + 0.01;
+    let y = rect.y + 0.02;
 
     let focused = state.focus == Focus::Content;
     let border_color = if focused {
@@ -84,38 +198,64 @@ fn render_content(state: &AppState, backend: &mut dyn RenderBackend) {
 
     // Draw box background
     backend.draw_hud_rect(
-        x - 0.01,
-        y - 0.02,
-        width + 0.02,
-        height,
+        rect.x,
+        rect.y,
+        rect.width,
+        rect.height,
         Color::Rgb(12, 14, 18),
     );
 
-    // Title with count
+    // Title with count, filter and pause state
     let count = state.content_len();
-    let title = format!("{} ({})", state.view.label(), count);
+    let mut title = format!("{} ({})", state.view.label(), count);
+    if state.view == View::Feed {
+        if state.feed_filter.is_active() {
+            title.push_str(&format!(" [{}]", state.feed_filter.label()));
+        }
+        if state.paused {
+            title.push_str(&format!(" ⏸ +{}", state.held_events.len()));
+        }
+    }
+    if let (View::Services, Some(baseline)) = (state.view, &state.baseline) {
+        title.push_str(&format!(" vs {}", format_time(baseline.taken_at)));
+    }
     backend.draw_hud_text(x, y, &title, border_color);
 
+    let rows = visible_rows(rect, 0.05);
+    let y = y + 0.04;
     match state.view {
-        View::Feed => render_feed(state, backend, x, y + 0.04, focused),
-        View::Traces => render_traces(state, backend, x, y + 0.04, focused),
-        View::Spans => render_spans(state, backend, x, y + 0.04, focused),
-        View::Services => render_services(state, backend, x, y + 0.04, focused),
+        View::Feed => render_feed(state, backend, x, y, rows, focused),
+        View::Traces => {
+            let width = columns(backend, rect).saturating_sub(2);
+            render_traces(state, backend, x, y, rows, focused, width)
+        }
+        View::Spans => render_spans(state, backend, x, y, rows, focused),
+        View::Services => render_services(state, backend, x, y, rows, focused),
     }
 }
 
 /// Render the event feed
-fn render_feed(state: &AppState, backend: &mut dyn RenderBackend, x: f32, y: f32, focused: bool) {
-    let visible_count = 6;
-
-    if state.feed_events.is_empty() {
-        backend.draw_hud_text(x, y, "Waiting for telemetry...", Color::DarkGrey);
+fn render_feed(
+    state: &AppState,
+    backend: &mut dyn RenderBackend,
+    x: f32,
+    y: f32,
+    visible_count: usize,
+    focused: bool,
+) {
+    let total = state.content_len();
+    if total == 0 {
+        let message = if state.feed_filter.is_active() {
+            "No matching events"
+        } else {
+            "Waiting for telemetry..."
+        };
+        backend.draw_hud_text(x, y, message, Color::DarkGrey);
         return;
     }
 
     for (i, event) in state
-        .feed_events
-        .iter()
+        .visible_feed()
         .skip(state.scroll_offset)
         .take(visible_count)
         .enumerate()
@@ -151,21 +291,37 @@ fn render_feed(state: &AppState, backend: &mut dyn RenderBackend, x: f32, y: f32
     }
 
     // Scroll indicator
-    if state.feed_events.len() > visible_count {
+    if total > visible_count {
         let scroll_y = y + (visible_count as f32 * 0.05);
         let shown = format!(
             "{}-{}/{}",
             state.scroll_offset + 1,
-            (state.scroll_offset + visible_count).min(state.feed_events.len()),
-            state.feed_events.len()
+            (state.scroll_offset + visible_count).min(total),
+            total
         );
         backend.draw_hud_text(x + 0.30, scroll_y, &shown, Color::DarkGrey);
     }
 }
 
-/// Render traces view
-fn render_traces(state: &AppState, backend: &mut dyn RenderBackend, x: f32, y: f32, focused: bool) {
-    let visible_count = 6;
+/// Render traces view, with the timeline below the list
+fn render_traces(
+    state: &AppState,
+    backend: &mut dyn RenderBackend,
+    x: f32,
+    y: f32,
+    visible_count: usize,
+    focused: bool,
+    width: usize,
+) {
+    let visible_count = match Timeline::build(state, width) {
+        Some(timeline) => {
+            let rows = visible_count.saturating_sub(TIMELINE_ROWS).max(1);
+            render_timeline(&timeline, backend, x, y + rows as f32 * 0.05, width);
+            rows
+        }
+        None => visible_count,
+    };
+
     let traces: Vec<_> = state.traces.values().collect();
 
     if traces.is_empty() {
@@ -224,9 +380,135 @@ fn render_traces(state: &AppState, backend: &mut dyn RenderBackend, x: f32, y: f
     }
 }
 
+/// A trace start or annotation on the timeline
+#[derive(Debug, Clone, PartialEq)]
+struct TimelineMarker {
+    column: usize,
+    symbol: &'static str,
+    color: Color,
+    /// Label and the column it starts at, if there was room for it
+    label: Option<(usize, String)>,
+}
+
+/// Trace starts and annotations placed on a time axis
+#[derive(Debug, Clone, PartialEq)]
+struct Timeline {
+    markers: Vec<TimelineMarker>,
+    start: SystemTime,
+    end: SystemTime,
+}
+
+impl Timeline {
+    /// Place everything on an axis `width` columns wide; `None` if empty
+    fn build(state: &AppState, width: usize) -> Option<Self> {
+        if width == 0 {
+            return None;
+        }
+        let traces: Vec<(SystemTime, bool)> = state
+            .traces
+            .values()
+            .filter_map(|trace| {
+                let start = trace.spans.values().map(|s| s.start_time).min()?;
+                let failed = trace.spans.values().any(|s| s.status == SpanStatus::Error);
+                Some((start, failed))
+            })
+            .collect();
+        let times = traces
+            .iter()
+            .map(|(time, _)| *time)
+            .chain(state.annotations.iter().map(|a| a.timestamp));
+        let start = times.clone().min()?;
+        let end = times.max()?;
+
+        let range = end.duration_since(start).unwrap_or_default().as_secs_f64();
+        let column = |time: SystemTime| {
+            if range == 0.0 {
+                return 0;
+            }
+            let offset = time.duration_since(start).unwrap_or_default().as_secs_f64();
+            ((offset / range) * (width - 1) as f64).round() as usize
+        };
+
+        let mut markers: Vec<_> = traces
+            .iter()
+            .map(|(time, failed)| TimelineMarker {
+                column: column(*time),
+                symbol: if *failed { "✗" } else { "·" },
+                color: if *failed { Color::Red } else { Color::DarkGrey },
+                label: None,
+            })
+            .collect();
+
+        // Annotations go on top, labeled left to right while labels fit
+        let mut free = 0;
+        for annotation in state.annotations.iter().rev() {
+            let column = column(annotation.timestamp);
+            let text = truncate(&annotation.label, MAX_TIMELINE_LABEL.min(width));
+            let at = column.min(width.saturating_sub(text.chars().count()));
+            let label = (at >= free).then(|| {
+                free = at + text.chars().count() + 1;
+                (at, text)
+            });
+            markers.push(TimelineMarker {
+                column,
+                symbol: annotation.symbol(),
+                color: severity_color(annotation.severity),
+                label,
+            });
+        }
+
+        Some(Self {
+            markers,
+            start,
+            end,
+        })
+    }
+}
+
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Fatal | Severity::Error => Color::Red,
+        Severity::Warn => Color::Yellow,
+        _ => Color::HUD_CYAN,
+    }
+}
+
+/// Draw the timeline: axis with markers, labels, then the time range
+fn render_timeline(
+    timeline: &Timeline,
+    backend: &mut dyn RenderBackend,
+    x: f32,
+    y: f32,
+    width: usize,
+) {
+    let cell = 1.0 / backend.capabilities().width as f32;
+    backend.draw_hud_text(x, y, &"─".repeat(width), Color::Rgb(40, 45, 50));
+    for marker in &timeline.markers {
+        let mx = x + marker.column as f32 * cell;
+        backend.draw_hud_text(mx, y, marker.symbol, marker.color);
+        if let Some((column, label)) = &marker.label {
+            backend.draw_hud_text(x + *column as f32 * cell, y + 0.025, label, marker.color);
+        }
+    }
+
+    let start = format_time(timeline.start);
+    let end = format_time(timeline.end);
+    backend.draw_hud_text(x, y + 0.05, &start, Color::DarkGrey);
+    if end != start {
+        let end_x = x + width.saturating_sub(end.len()) as f32 * cell;
+        backend.draw_hud_text(end_x, y + 0.05, &end, Color::DarkGrey);
+    }
+}
+
 /// Render spans view
-fn render_spans(state: &AppState, backend: &mut dyn RenderBackend, x: f32, y: f32, focused: bool) {
-    let visible_count = 6;
+fn render_spans(
+    state: &AppState,
+    backend: &mut dyn RenderBackend,
+    x: f32,
+    y: f32,
+    visible_count: usize,
+    focused: bool,
+) {
     let items = state.content_items();
     let spans: Vec<_> = items
         .iter()
@@ -301,9 +583,9 @@ fn render_services(
     backend: &mut dyn RenderBackend,
     x: f32,
     y: f32,
+    visible_count: usize,
     focused: bool,
 ) {
-    let visible_count = 6;
     let services: Vec<_> = state.services.values().collect();
 
     if services.is_empty() {
@@ -357,12 +639,199 @@ fn render_services(
             let errors = format!("{} err", service.error_count);
             backend.draw_hud_text(x + 0.34, sy, &errors, Color::Red);
         }
+
+        // Latency and error rate since the baseline against before it
+        let mut nx = x + 0.42;
+        if let Some(delta) = state.baseline_delta(service) {
+            if let Some((change, trend)) = delta.latency_ms {
+                let latency = format!("{:+.1}ms", change);
+                backend.draw_hud_text(nx, sy, &latency, trend.color());
+            }
+            if let Some((change, trend)) = delta.error_rate {
+                let errors = format!("{:+.1}%", change * 100.0);
+                backend.draw_hud_text(nx + 0.10, sy, &errors, trend.color());
+            }
+            nx += 0.20;
+        }
+
+        // Sample ratio of the service's senders on the control channel, and
+        // the one asked for while they catch up
+        if let Some(sampling) = state.sampling.get(&service.name) {
+            if let Some(ratio) = sampling.ratio() {
+                let mut label = format!("◐ {:.0}%", ratio * 100.0);
+                if let Some(requested) = sampling.requested {
+                    label.push_str(&format!("→{:.0}%", requested * 100.0));
+                }
+                backend.draw_hud_text(nx, sy, &label, Color::HUD_CYAN);
+                nx += 0.12;
+            }
+        }
+
+        // Flag services whose spans are being summarized
+        if service.noisy {
+            let noisy = format!("≋ noisy ({})", service.summarized_spans);
+            backend.draw_hud_text(nx, sy, &noisy, Color::Yellow);
+        }
+    }
+}
+
+/// Foveal alert line for the oldest unacknowledged agent alert
+fn render_alert(state: &AppState, backend: &mut dyn RenderBackend, rect: ZoneRect) {
+    let Some(alert) = state.agent.pending_alert() else {
+        return;
+    };
+    let color = match alert.level {
+        AlertLevel::Info => Color::Grey,
+        AlertLevel::Warning => Color::Yellow,
+        AlertLevel::Critical => Color::Red,
+    };
+    backend.draw_hud_rect(
+        rect.x,
+        rect.y,
+        rect.width,
+        rect.height,
+        Color::Rgb(30, 12, 12),
+    );
+    let line = format!(
+        "{} {} {}  [X: ack]",
+        alert.level.symbol(),
+        alert.timestamp,
+        alert.message
+    );
+    let line = truncate(&line, columns(backend, rect));
+    backend.draw_hud_text(rect.x + 0.01, rect.y, &line, color);
+}
+
+/// Agent panel: latest response and tool progress
+fn render_agent(state: &AppState, backend: &mut dyn RenderBackend, rect: ZoneRect) {
+    let x = rect.x;
+    let mut y = rect.y;
+    let width = columns(backend, rect);
+    let agent = &state.agent;
+
+    let title = format!("Agent ({} alerts)", agent.unacknowledged());
+    backend.draw_hud_text(x, y, &title, Color::HUD_CYAN);
+    y += 0.04;
+
+    if let Some(query) = &agent.last_query {
+        let query = truncate(&format!("? {}", query), width);
+        backend.draw_hud_text(x, y, &query, Color::Grey);
+        y += 0.03;
+    }
+    if agent.awaiting_response {
+        backend.draw_hud_text(x, y, "… thinking", Color::DarkGrey);
+        y += 0.04;
+    } else if let Some(response) = &agent.last_response {
+        backend.draw_hud_text(x, y, &truncate(response, width), Color::White);
+        y += 0.04;
+    }
+
+    for tool in agent.tools.iter().take(3) {
+        let color = match tool.state {
+            ToolState::Running => Color::HUD_CYAN,
+            ToolState::Succeeded => Color::Green,
+            ToolState::Failed => Color::Red,
+        };
+        let progress = tool
+            .progress
+            .map(|p| format!(" {:>3.0}%", p.clamp(0.0, 1.0) * 100.0))
+            .unwrap_or_default();
+        let message = tool
+            .message
+            .as_deref()
+            .map(|m| format!(" {}", truncate(m, 30)))
+            .unwrap_or_default();
+        let line = format!(
+            "{} {}{}{}",
+            tool.state.symbol(),
+            tool.tool,
+            progress,
+            message
+        );
+        backend.draw_hud_text(x, y, &truncate(&line, width), color);
+        y += 0.03;
+        if y >= rect.bottom() {
+            break;
+        }
+    }
+}
+
+/// Whether the voice line has anything to show
+fn voice_active(state: &AppState) -> bool {
+    state.dictation.is_some()
+        || state
+            .transcript
+            .as_ref()
+            .is_some_and(|t| state.tick.saturating_sub(t.tick) <= TRANSCRIPT_TICKS)
+        || current_notice(state).is_some()
+}
+
+/// The reload notice, while it is recent and newer than the voice transcript
+fn current_notice(state: &AppState) -> Option<&Notice> {
+    let notice = state.notice.as_ref()?;
+    let recent = state.tick.saturating_sub(notice.tick) <= TRANSCRIPT_TICKS;
+    let newest = state
+        .transcript
+        .as_ref()
+        .is_none_or(|t| t.tick <= notice.tick);
+    (recent && newest).then_some(notice)
+}
+
+/// Voice line: the dictation prompt, a reload notice, or the last command
+/// and its ack
+fn render_voice(state: &AppState, backend: &mut dyn RenderBackend, rect: ZoneRect) {
+    let (x, y) = (rect.x, rect.y);
+    let width = columns(backend, rect);
+
+    if let Some(text) = &state.dictation {
+        let cursor = if (state.tick / 5).is_multiple_of(2) {
+            "▏"
+        } else {
+            " "
+        };
+        let line = format!("🎤 {}{}", text, cursor);
+        backend.draw_hud_text(x, y, &truncate(&line, width), Color::HUD_CYAN);
+        return;
+    }
+
+    if let Some(notice) = current_notice(state) {
+        let (mark, color) = if notice.ok {
+            ("↻", Color::Green)
+        } else {
+            ("✗", Color::Red)
+        };
+        let line = format!("{} {}", mark, notice.text);
+        backend.draw_hud_text(x, y, &truncate(&line, width), color);
+        return;
     }
+
+    let Some(transcript) = &state.transcript else {
+        return;
+    };
+    let heard = format!("🎤 \"{}\"", truncate(&transcript.heard, width / 2));
+    backend.draw_hud_text(x, y, &heard, Color::Grey);
+    let (mark, color) = if transcript.recognized {
+        ("✓", Color::Green)
+    } else {
+        ("✗", Color::Yellow)
+    };
+    let response = format!("{} {}", mark, transcript.response);
+    backend.draw_hud_text(
+        x + rect.width / 2.0,
+        y,
+        &truncate(&response, width / 2),
+        color,
+    );
 }
 
-/// Stats bar at bottom
-fn render_stats(state: &AppState, backend: &mut dyn RenderBackend) {
-    let y = 0.90;
+/// Stats line: telemetry counts, display mode and connection status
+fn render_stats(
+    state: &AppState,
+    context: &DisplayContext,
+    backend: &mut dyn RenderBackend,
+    rect: ZoneRect,
+) {
+    let (x, y, w) = (rect.x, rect.y, rect.width);
 
     // Stats summary
     let stats = &state.stats;
@@ -370,34 +839,178 @@ fn render_stats(state: &AppState, backend: &mut dyn RenderBackend) {
         "Spans: {} ({} active, {} err)",
         stats.total_spans, stats.active_spans, stats.error_spans
     );
-    backend.draw_hud_text(0.02, y, &spans_str, Color::Grey);
+    backend.draw_hud_text(x, y, &spans_str, Color::Grey);
 
     // Traces count
     let traces_str = format!("Traces: {}", state.traces.len());
-    backend.draw_hud_text(0.45, y, &traces_str, Color::Grey);
+    backend.draw_hud_text(x + w * 0.40, y, &traces_str, Color::Grey);
 
     // Services count
     let services_str = format!("Services: {}", state.services.len());
-    backend.draw_hud_text(0.65, y, &services_str, Color::Grey);
+    backend.draw_hud_text(x + w * 0.52, y, &services_str, Color::Grey);
 
-    // OTLP status indicator
-    backend.draw_hud_text(0.85, y, "OTLP ●", Color::Green);
+    // Display mode
+    let mode = format!("{} {}", context.mode.icon(), context.mode.name());
+    backend.draw_hud_text(x + w * 0.66, y, &mode, context.mode.theme_color());
+
+    // OTLP and agent status indicators
+    backend.draw_hud_text(x + w * 0.80, y, "OTLP ●", Color::Green);
+    if state.agent.connected {
+        backend.draw_hud_text(x + w * 0.89, y, "Agent ●", Color::Green);
+    }
 }
 
 /// Help hint
-fn render_help(state: &AppState, backend: &mut dyn RenderBackend) {
-    let help = if state.tick < 300 {
-        "j/k: Navigate  Tab: Switch panel  Enter: Select  Esc: Back  Q: Quit"
-    } else {
-        ""
-    };
-    backend.draw_hud_text(0.02, 0.96, help, Color::Rgb(45, 50, 55));
+fn render_help(backend: &mut dyn RenderBackend, rect: ZoneRect) {
+    let help = "j/k: Navigate  Tab: Switch panel  Enter: Select  Esc: Back  V: Voice  M: Mode  R: Ask  X: Ack  Q: Quit";
+    let help = truncate(help, columns(backend, rect));
+    backend.draw_hud_text(rect.x, rect.y, &help, Color::Rgb(45, 50, 55));
 }
 
 fn truncate(s: &str, max: usize) -> String {
     if s.chars().count() > max {
-        s.chars().take(max - 2).collect::<String>() + ".."
+        s.chars().take(max.saturating_sub(2)).collect::<String>() + ".."
     } else {
         s.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::AgentEvent;
+    use spec_ai_oui::context::DisplayMode;
+    use spec_ai_oui::layout::AttentionZone;
+    use std::io::Write;
+    use std::time::SystemTime;
+
+    fn zones(state: &AppState) -> Vec<(Panel, AttentionZone)> {
+        let layout = load_layout(None).unwrap();
+        layout
+            .place(state.mode, state.density, |w| {
+                Panel::from_binding(&w.bind).is_some_and(|p| p.is_active(state))
+            })
+            .into_iter()
+            .map(|p| (Panel::from_binding(&p.key.bind).unwrap(), p.zone))
+            .collect()
+    }
+
+    #[test]
+    fn test_critical_alert_takes_foveal_zone_in_every_mode() {
+        let mut state = AppState::new();
+        state.agent.connected = true;
+        state.process_agent_event(AgentEvent::Alert {
+            id: 1,
+            level: AlertLevel::Critical,
+            message: "error rate".to_string(),
+            timestamp: SystemTime::now(),
+        });
+
+        assert!(zones(&state).contains(&(Panel::Stats, AttentionZone::Peripheral)));
+        loop {
+            assert!(zones(&state).contains(&(Panel::CriticalAlert, AttentionZone::Foveal)));
+            state.cycle_mode();
+            if state.mode == DisplayMode::Research {
+                break;
+            }
+        }
+
+        // Minimal density shows only the alert
+        while state.mode != DisplayMode::Focus {
+            state.cycle_mode();
+        }
+        assert_eq!(
+            zones(&state),
+            vec![(Panel::CriticalAlert, AttentionZone::Foveal)]
+        );
+    }
+
+    #[test]
+    fn test_timeline_places_traces_and_labels_annotations() {
+        use crate::telemetry::{Annotation, SpanData, SpanKind, TelemetryEvent};
+        use std::collections::HashMap;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut state = AppState::new();
+        assert!(Timeline::build(&state, 40).is_none());
+
+        for (trace, secs, status) in [("a", 0, SpanStatus::Ok), ("b", 100, SpanStatus::Error)] {
+            state.process_telemetry(TelemetryEvent::SpanEnded(SpanData {
+                trace_id: trace.to_string(),
+                span_id: trace.to_string(),
+                parent_span_id: None,
+                name: "op".to_string(),
+                kind: SpanKind::Server,
+                start_time: at(secs),
+                end_time: Some(at(secs + 1)),
+                status,
+                attributes: HashMap::new(),
+                service_name: "api".to_string(),
+            }));
+        }
+        for (label, secs) in [("deploy", 50), ("rollback", 52), ("incident", 100)] {
+            state.process_telemetry(TelemetryEvent::Annotation(Annotation {
+                label: label.to_string(),
+                detail: None,
+                severity: Severity::Error,
+                timestamp: at(secs),
+                service_name: "spec-ai".to_string(),
+            }));
+        }
+
+        let timeline = Timeline::build(&state, 41).unwrap();
+        assert_eq!((timeline.start, timeline.end), (at(0), at(100)));
+        let mut traces: Vec<_> = timeline
+            .markers
+            .iter()
+            .filter(|m| m.symbol != "⚑")
+            .map(|m| (m.column, m.symbol))
+            .collect();
+        traces.sort();
+        assert_eq!(traces, vec![(0, "·"), (40, "✗")]);
+
+        let annotations: Vec<_> = timeline
+            .markers
+            .iter()
+            .filter(|m| m.symbol == "⚑")
+            .map(|m| (m.column, m.label.clone()))
+            .collect();
+        assert_eq!(
+            annotations,
+            vec![
+                (20, Some((20, "deploy".to_string()))),
+                // Overlaps the label before it
+                (21, None),
+                // Kept inside the axis
+                (40, Some((33, "incident".to_string()))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reload_notice_shows_until_a_newer_voice_command() {
+        let mut state = AppState::new();
+        assert!(!Panel::Voice.is_active(&state));
+
+        state.set_notice("Reloaded layout from hud.toml", true);
+        assert!(Panel::Voice.is_active(&state));
+        assert!(current_notice(&state).is_some());
+
+        state.tick += 1;
+        state.set_transcript("show traces", "Showing traces", true);
+        assert!(current_notice(&state).is_none());
+
+        state.set_notice("Kept previous layout: bad", false);
+        state.tick += TRANSCRIPT_TICKS + 1;
+        assert!(!Panel::Voice.is_active(&state));
+    }
+
+    #[test]
+    fn test_layout_file_with_unknown_binding_is_rejected() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "[[widget]]\nid = \"x\"\nbind = \"weather\"").unwrap();
+        let err = load_layout(Some(file.path())).unwrap_err();
+        assert!(err.to_string().contains("weather"));
+    }
+}
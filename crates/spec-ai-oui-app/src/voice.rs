@@ -0,0 +1,121 @@
+//! Voice commands for the telemetry HUD
+//!
+//! Spoken phrases are resolved through a [`VoiceRegistry`] to the same
+//! actions the ring controls use.
+
+use std::sync::OnceLock;
+
+use spec_ai_oui::input::VoiceRegistry;
+
+/// Actions reachable by voice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceAction {
+    ShowTraces,
+    ShowSpans,
+    ShowServices,
+    ShowFeed,
+    FilterErrors,
+    ClearFilter,
+    SelectService,
+    PauseFeed,
+    ResumeFeed,
+    ScrollUp,
+    ScrollDown,
+    Select,
+    Acknowledge,
+    Ask,
+    MarkBaseline,
+    ClearBaseline,
+    SampleMore,
+    SampleLess,
+    CycleMode,
+}
+
+/// Minimum recognition confidence for a command to be acted on
+const MIN_CONFIDENCE: f32 = 0.6;
+
+/// The app's voice command registry
+pub fn registry() -> &'static VoiceRegistry<VoiceAction> {
+    static REGISTRY: OnceLock<VoiceRegistry<VoiceAction>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        VoiceRegistry::new()
+            .min_confidence(MIN_CONFIDENCE)
+            .register(&["show traces", "traces", "trace"], VoiceAction::ShowTraces)
+            .register(&["show spans", "spans", "span"], VoiceAction::ShowSpans)
+            .register(
+                &["show services", "services", "service"],
+                VoiceAction::ShowServices,
+            )
+            .register(
+                &["show feed", "feed", "back", "home"],
+                VoiceAction::ShowFeed,
+            )
+            .register(
+                &["filter errors", "show errors", "errors only"],
+                VoiceAction::FilterErrors,
+            )
+            .register(
+                &["clear filter", "clear filters", "show all"],
+                VoiceAction::ClearFilter,
+            )
+            .register_with_argument("select service", VoiceAction::SelectService)
+            .register_with_argument("filter service", VoiceAction::SelectService)
+            .register(&["pause feed", "pause", "freeze"], VoiceAction::PauseFeed)
+            .register(
+                &["resume feed", "resume", "unpause", "play"],
+                VoiceAction::ResumeFeed,
+            )
+            .register(&["up", "previous", "scroll up"], VoiceAction::ScrollUp)
+            .register(&["down", "next", "scroll down"], VoiceAction::ScrollDown)
+            .register(&["select", "enter", "open"], VoiceAction::Select)
+            .register(
+                &["acknowledge", "dismiss", "acknowledge alert"],
+                VoiceAction::Acknowledge,
+            )
+            .register_with_argument("ask", VoiceAction::Ask)
+            .register(
+                &["mark baseline", "set baseline", "baseline"],
+                VoiceAction::MarkBaseline,
+            )
+            .register(
+                &["clear baseline", "drop baseline"],
+                VoiceAction::ClearBaseline,
+            )
+            .register(
+                &["sample more", "more traffic", "dial up"],
+                VoiceAction::SampleMore,
+            )
+            .register(
+                &["sample less", "less traffic", "dial down"],
+                VoiceAction::SampleLess,
+            )
+            .register(
+                &["next mode", "change mode", "switch mode"],
+                VoiceAction::CycleMode,
+            )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phrases_resolve_to_actions() {
+        let registry = registry();
+        let action = |text: &str| registry.resolve(text).map(|m| m.action);
+
+        assert_eq!(action("show traces"), Some(VoiceAction::ShowTraces));
+        assert_eq!(action("filter errors"), Some(VoiceAction::FilterErrors));
+        assert_eq!(action("pause feed"), Some(VoiceAction::PauseFeed));
+        assert_eq!(action("go back"), Some(VoiceAction::ShowFeed));
+        assert_eq!(action("next mode"), Some(VoiceAction::CycleMode));
+        assert_eq!(action("mark baseline"), Some(VoiceAction::MarkBaseline));
+        assert_eq!(action("clear baseline"), Some(VoiceAction::ClearBaseline));
+        assert_eq!(action("dial down"), Some(VoiceAction::SampleLess));
+
+        let m = registry.resolve("select service user-service").unwrap();
+        assert_eq!(m.action, VoiceAction::SelectService);
+        assert_eq!(m.argument.as_deref(), Some("user-service"));
+    }
+}
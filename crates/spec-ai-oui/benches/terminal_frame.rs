@@ -0,0 +1,126 @@
+//! Per-frame output of the terminal backend for a dense telemetry HUD
+//!
+//! Renders a scrolling feed of span rows (the shape of the telemetry app's
+//! feed panel) and reports the terminal commands and bytes written per
+//! frame, against a per-glyph encoder that moves the cursor and sets both
+//! colors for every changed cell.
+//!
+//! Run with `cargo bench -p spec-ai-oui --bench terminal_frame`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crossterm::{
+    cursor, queue,
+    style::{Print, SetBackgroundColor, SetForegroundColor},
+};
+use spec_ai_oui::renderer::terminal::{FrameStats, TerminalBackend};
+use spec_ai_oui::{Color, RenderBackend};
+
+const WIDTH: u16 = 160;
+const HEIGHT: u16 = 48;
+const FRAMES: usize = 600;
+
+const SERVICES: [&str; 5] = [
+    "api-gateway",
+    "user-service",
+    "order-service",
+    "payment-service",
+    "inventory",
+];
+const OPERATIONS: [&str; 4] = ["GET /users", "POST /orders", "db.query", "cache.get"];
+
+/// Draw one frame of the feed, scrolled by `frame` rows
+fn draw_feed(backend: &mut TerminalBackend, frame: usize) {
+    let rows = HEIGHT as usize - 4;
+    backend.draw_hud_rect(0.0, 0.0, 1.0, 1.0, Color::HUD_CYAN);
+    backend.draw_hud_text(0.02, 0.02, "TELEMETRY FEED", Color::HUD_CYAN);
+    for row in 0..rows {
+        let n = frame + row;
+        let error = n.is_multiple_of(7);
+        let color = if error { Color::Red } else { Color::Green };
+        let line = format!(
+            "{:>6} {} {:<16} {:<14} {:>5}ms {}",
+            n,
+            if error { "✗" } else { "✓" },
+            SERVICES[n % SERVICES.len()],
+            OPERATIONS[n % OPERATIONS.len()],
+            (n * 37) % 900,
+            "▮".repeat(n % 24),
+        );
+        let y = (row + 2) as f32 / HEIGHT as f32;
+        backend.draw_hud_text(0.02, y, &line, color);
+    }
+    let stats = format!("spans {}  errors {}", frame * 3, frame / 7);
+    backend.draw_hud_text(
+        0.02,
+        (HEIGHT - 1) as f32 / HEIGHT as f32,
+        &stats,
+        Color::Grey,
+    );
+}
+
+/// Bytes a per-glyph encoder writes for `cells` changed cells
+fn per_glyph_bytes(cells: usize) -> usize {
+    let mut out = Vec::new();
+    for i in 0..cells {
+        let (x, y) = ((i % WIDTH as usize) as u16, (i / WIDTH as usize) as u16);
+        queue!(
+            out,
+            cursor::MoveTo(x, y),
+            SetForegroundColor(Color::Green.to_crossterm()),
+            SetBackgroundColor(Color::Rgb(5, 7, 12).to_crossterm()),
+            Print("x"),
+        )
+        .unwrap();
+    }
+    out.len()
+}
+
+fn main() {
+    let mut backend = TerminalBackend::with_size(WIDTH, HEIGHT);
+    let mut sink = Vec::new();
+    let mut total = FrameStats::default();
+    let mut elapsed = Duration::ZERO;
+
+    for frame in 0..FRAMES {
+        backend.begin_frame().unwrap();
+        draw_feed(&mut backend, frame);
+        sink.clear();
+        let start = Instant::now();
+        let stats = backend.render_to(&mut sink).unwrap();
+        elapsed += start.elapsed();
+        black_box(&sink);
+
+        total.cells_changed += stats.cells_changed;
+        total.cursor_moves += stats.cursor_moves;
+        total.color_changes += stats.color_changes;
+        total.prints += stats.prints;
+        total.bytes += stats.bytes;
+    }
+
+    let per_frame = |n: usize| n as f64 / FRAMES as f64;
+    let cells = per_frame(total.cells_changed);
+    println!("terminal_frame: {WIDTH}x{HEIGHT} telemetry feed, {FRAMES} frames");
+    println!("  changed cells      {cells:>10.1} / frame");
+    println!(
+        "  batched commands   {:>10.1} / frame ({:.1} moves, {:.1} colors, {:.1} prints)",
+        per_frame(total.commands()),
+        per_frame(total.cursor_moves),
+        per_frame(total.color_changes),
+        per_frame(total.prints),
+    );
+    println!("  per-glyph commands {:>10.1} / frame", cells * 4.0);
+    println!(
+        "  batched bytes      {:>10.1} / frame",
+        per_frame(total.bytes)
+    );
+    println!(
+        "  per-glyph bytes    {:>10.1} / frame",
+        per_frame(per_glyph_bytes(total.cells_changed))
+    );
+    println!(
+        "  encode time        {:>10.1} µs / frame",
+        elapsed.as_secs_f64() * 1e6 / FRAMES as f64
+    );
+}
@@ -1,7 +1,30 @@
 //! Animation system for optical UI
+//!
+//! Animations advance by wall-clock delta time, never by frame count, so they
+//! play at the same speed at any frame rate. Simulations that are sensitive
+//! to step size run on a [`FixedTimestep`].
 
 mod easing;
+mod timestep;
 mod tween;
 
 pub use easing::Easing;
+pub use timestep::{FixedTimestep, Spring};
 pub use tween::Tween;
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::time::Duration;
+
+    /// Call `update` once per frame at `hz` for `total` wall-clock time
+    ///
+    /// Frame deltas are taken from a clock, as a real loop would measure
+    /// them, so they sum to exactly `total`.
+    pub fn run_at(hz: u32, total: Duration, mut update: impl FnMut(Duration)) {
+        let frames = (total.as_secs_f64() * hz as f64).round() as u32;
+        let clock = |frame: u32| total * frame / frames.max(1);
+        for frame in 0..frames {
+            update(clock(frame + 1) - clock(frame));
+        }
+    }
+}
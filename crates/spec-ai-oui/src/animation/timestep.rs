@@ -0,0 +1,174 @@
+//! Fixed-timestep simulation
+
+use std::time::Duration;
+
+/// Converts variable frame times into a whole number of fixed steps
+///
+/// Simulations whose result depends on step size (springs, physics) call
+/// [`advance`](Self::advance) with each frame's delta and run one step per
+/// returned count. Leftover time carries into the next frame, and
+/// [`alpha`](Self::alpha) gives how far into the next step the frame is, for
+/// interpolating between the previous and current state.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    /// Create a timestep of `step` (zero is bumped to one millisecond)
+    pub fn new(step: Duration) -> Self {
+        Self {
+            step: step.max(Duration::from_millis(1)),
+            accumulator: Duration::ZERO,
+            max_steps: 240,
+        }
+    }
+
+    /// Create a timestep running `hz` steps per second
+    pub fn from_hz(hz: u32) -> Self {
+        Self::new(Duration::from_secs(1) / hz.max(1))
+    }
+
+    /// Cap the steps run in one frame, dropping time after a long stall
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// The fixed step length
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Add a frame's delta and return how many steps to simulate
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        self.accumulator += dt;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+            if steps == self.max_steps {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+        }
+        steps
+    }
+
+    /// Fraction of a step left over after the last advance (0-1)
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+/// A damped spring pulling a value towards a target
+///
+/// Integrated at a fixed 240 Hz so its motion is the same at any frame rate.
+#[derive(Debug, Clone)]
+pub struct Spring {
+    /// Current value
+    pub value: f32,
+    /// Current velocity (units per second)
+    pub velocity: f32,
+    /// Value the spring pulls towards
+    pub target: f32,
+    /// Spring stiffness
+    pub stiffness: f32,
+    /// Velocity damping
+    pub damping: f32,
+    previous: f32,
+    timestep: FixedTimestep,
+}
+
+impl Spring {
+    /// Create a spring at rest at `value`
+    pub fn new(value: f32) -> Self {
+        Self {
+            value,
+            velocity: 0.0,
+            target: value,
+            stiffness: 170.0,
+            damping: 26.0,
+            previous: value,
+            timestep: FixedTimestep::from_hz(240),
+        }
+    }
+
+    /// Set stiffness and damping
+    pub fn with_params(mut self, stiffness: f32, damping: f32) -> Self {
+        self.stiffness = stiffness;
+        self.damping = damping;
+        self
+    }
+
+    /// Set the value to move towards
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+    }
+
+    /// Advance the simulation by the wall-clock time since the last update
+    pub fn update(&mut self, dt: Duration) {
+        let h = self.timestep.step().as_secs_f32();
+        for _ in 0..self.timestep.advance(dt) {
+            self.previous = self.value;
+            // Semi-implicit Euler
+            let force = self.stiffness * (self.target - self.value) - self.damping * self.velocity;
+            self.velocity += force * h;
+            self.value += self.velocity * h;
+        }
+    }
+
+    /// Value interpolated between the last two simulation steps
+    pub fn current(&self) -> f32 {
+        let alpha = self.timestep.alpha();
+        self.previous + (self.value - self.previous) * alpha
+    }
+
+    /// Whether the spring has come to rest at its target
+    pub fn is_settled(&self) -> bool {
+        (self.target - self.value).abs() < 1e-3 && self.velocity.abs() < 1e-3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::testing::run_at;
+
+    #[test]
+    fn test_fixed_steps_carry_remainder() {
+        let mut timestep = FixedTimestep::new(Duration::from_millis(10));
+        assert_eq!(timestep.advance(Duration::from_millis(25)), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+        assert_eq!(timestep.advance(Duration::from_millis(5)), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+
+        let mut capped = FixedTimestep::new(Duration::from_millis(10)).with_max_steps(3);
+        assert_eq!(capped.advance(Duration::from_secs(5)), 3);
+        assert_eq!(capped.alpha(), 0.0);
+    }
+
+    #[test]
+    fn test_spring_same_motion_at_30_and_120_hz() {
+        let spring = || {
+            let mut spring = Spring::new(0.0);
+            spring.set_target(1.0);
+            spring
+        };
+
+        for elapsed in [Duration::from_millis(100), Duration::from_millis(300)] {
+            let mut slow = spring();
+            let mut fast = spring();
+            run_at(30, elapsed, |dt| slow.update(dt));
+            run_at(120, elapsed, |dt| fast.update(dt));
+            assert_eq!(slow.value, fast.value);
+            assert!((slow.current() - fast.current()).abs() < 1e-3);
+        }
+
+        let mut settled = spring();
+        run_at(60, Duration::from_secs(3), |dt| settled.update(dt));
+        assert!(settled.is_settled());
+    }
+}
@@ -4,6 +4,10 @@ use super::Easing;
 use std::time::Duration;
 
 /// A tween animation
+///
+/// Progress is derived from the total elapsed time rather than accumulated
+/// per frame, so a tween reaches the same value at the same wall-clock time
+/// whatever the frame rate.
 #[derive(Debug, Clone)]
 pub struct Tween {
     /// Start value
@@ -18,6 +22,8 @@ pub struct Tween {
     pub easing: Easing,
     /// Whether the tween is complete
     pub complete: bool,
+    /// Time elapsed since the tween started
+    elapsed: Duration,
 }
 
 impl Tween {
@@ -30,6 +36,7 @@ impl Tween {
             progress: 0.0,
             easing: Easing::Linear,
             complete: false,
+            elapsed: Duration::ZERO,
         }
     }
 
@@ -39,14 +46,19 @@ impl Tween {
         self
     }
 
-    /// Update the tween
+    /// Advance the tween by the wall-clock time since the last update
     pub fn update(&mut self, dt: Duration) {
         if self.complete {
             return;
         }
 
-        self.progress += dt.as_secs_f32() / self.duration.as_secs_f32();
-        if self.progress >= 1.0 {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        self.progress = if self.duration.is_zero() {
+            1.0
+        } else {
+            self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+        };
+        if self.elapsed >= self.duration {
             self.progress = 1.0;
             self.complete = true;
         }
@@ -61,13 +73,50 @@ impl Tween {
     /// Reset the tween
     pub fn reset(&mut self) {
         self.progress = 0.0;
+        self.elapsed = Duration::ZERO;
         self.complete = false;
     }
 
     /// Reverse the tween
     pub fn reverse(&mut self) {
         std::mem::swap(&mut self.start, &mut self.end);
+        self.elapsed = self.duration.saturating_sub(self.elapsed);
         self.progress = 1.0 - self.progress;
         self.complete = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::testing::run_at;
+
+    #[test]
+    fn test_same_value_at_30_and_120_hz() {
+        let tween =
+            || Tween::new(0.0, 100.0, Duration::from_millis(500)).with_easing(Easing::EaseOutCubic);
+
+        for elapsed in [Duration::from_millis(100), Duration::from_millis(300)] {
+            let mut slow = tween();
+            let mut fast = tween();
+            run_at(30, elapsed, |dt| slow.update(dt));
+            run_at(120, elapsed, |dt| fast.update(dt));
+            assert!((slow.value() - fast.value()).abs() < 1e-3);
+        }
+
+        let mut slow = tween();
+        let mut fast = tween();
+        run_at(30, Duration::from_secs(1), |dt| slow.update(dt));
+        run_at(120, Duration::from_secs(1), |dt| fast.update(dt));
+        assert!(slow.complete && fast.complete);
+        assert_eq!(slow.value(), fast.value());
+    }
+
+    #[test]
+    fn test_zero_duration_completes_immediately() {
+        let mut tween = Tween::new(1.0, 2.0, Duration::ZERO);
+        tween.update(Duration::from_millis(1));
+        assert!(tween.complete);
+        assert_eq!(tween.value(), 2.0);
+    }
+}
@@ -1,17 +1,18 @@
 //! Optical application framework
 
 use std::io;
-use std::time::{Duration, Instant};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crossterm::{
-    event::{self, Event as CrosstermEvent, KeyCode, KeyModifiers},
-    execute,
-    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use crossterm::event::{self, Event as CrosstermEvent, KeyCode, KeyEventKind, KeyModifiers};
 
+use crate::clock::{Clock, SystemClock, Ticker};
 use crate::context::DisplayContext;
 use crate::input::{InputSimulator, OpticalEvent};
-use crate::renderer::{terminal::TerminalBackend, RenderBackend};
+use crate::layout::HudLayout;
+use crate::renderer::terminal::{ScreenGuard, TerminalBackend};
+use crate::renderer::RenderBackend;
 
 /// Optical application trait
 pub trait OpticalApp {
@@ -32,6 +33,9 @@ pub trait OpticalApp {
 
     /// Called each tick (for animations)
     fn on_tick(&mut self, _state: &mut Self::State) {}
+
+    /// Receive the HUD layout loaded at startup, before `init`
+    fn set_layout(&mut self, _layout: HudLayout) {}
 }
 
 /// Application runner for optical apps
@@ -41,6 +45,8 @@ pub struct OpticalAppRunner<A: OpticalApp> {
     input_simulator: InputSimulator,
     context: DisplayContext,
     tick_rate: Duration,
+    clock: Arc<dyn Clock>,
+    layout: Option<HudLayout>,
     running: bool,
 }
 
@@ -55,6 +61,8 @@ impl<A: OpticalApp> OpticalAppRunner<A> {
             input_simulator: InputSimulator::new(),
             context: DisplayContext::default(),
             tick_rate: Duration::from_millis(100),
+            clock: Arc::new(SystemClock),
+            layout: None,
             running: true,
         })
     }
@@ -65,26 +73,46 @@ impl<A: OpticalApp> OpticalAppRunner<A> {
         self
     }
 
+    /// Take frame times from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Use a HUD layout, handed to the app when it starts
+    pub fn with_layout(mut self, layout: HudLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Load the HUD layout from a TOML file
+    pub fn with_layout_file(self, path: impl AsRef<Path>) -> io::Result<Self> {
+        let layout = HudLayout::load(path).map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(self.with_layout(layout))
+    }
+
     /// Run the application
     pub fn run(&mut self) -> io::Result<()> {
-        // Enter alternate screen and raw mode
-        terminal::enable_raw_mode()?;
-        execute!(io::stdout(), EnterAlternateScreen)?;
+        // Enter alternate screen and raw mode, restored when the guard drops
+        let _screen = ScreenGuard::enter()?;
 
         // Initialize state
+        if let Some(layout) = self.layout.take() {
+            self.app.set_layout(layout);
+        }
         let mut state = self.app.init();
-        let mut last_tick = Instant::now();
+        let mut ticker = Ticker::new(self.clock.clone(), self.tick_rate);
 
         // Main loop
         while self.running {
             // Poll for events
-            let timeout = self
-                .tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_default();
-
-            if event::poll(timeout)? {
+            if event::poll(ticker.timeout())? {
+                // Windows also reports key releases; act on presses only
                 if let CrosstermEvent::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Release {
+                        continue;
+                    }
+
                     // Check for quit
                     if key.code == KeyCode::Char('q')
                         && key.modifiers.contains(KeyModifiers::CONTROL)
@@ -105,9 +133,9 @@ impl<A: OpticalApp> OpticalAppRunner<A> {
             }
 
             // Check for tick
-            if last_tick.elapsed() >= self.tick_rate {
+            if let Some(dt) = ticker.tick() {
                 // Update context
-                self.context.update(last_tick.elapsed());
+                self.context.update(dt);
 
                 // Update app
                 self.app.update(&mut state, &self.context);
@@ -130,15 +158,9 @@ impl<A: OpticalApp> OpticalAppRunner<A> {
 
                 // Send tick event
                 self.app.handle_event(OpticalEvent::Tick, &mut state);
-
-                last_tick = Instant::now();
             }
         }
 
-        // Cleanup
-        terminal::disable_raw_mode()?;
-        execute!(io::stdout(), LeaveAlternateScreen)?;
-
         Ok(())
     }
 }
@@ -0,0 +1,133 @@
+//! Injectable time source
+//!
+//! Everything in the framework that reads the time takes it from a
+//! [`Clock`], so tests can run time-dependent behavior (ticks, dwell times,
+//! animations) on a [`ManualClock`] they advance themselves instead of
+//! sleeping. [`SystemClock`] is the real one.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to
+///
+/// Clones share the same time, so a test can keep one and hand another to
+/// the code under test.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    /// Create a clock stopped at the current time
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Decides when the next frame is due at a fixed tick rate
+///
+/// Shared by the app runners so the frame timing they feed
+/// [`DisplayContext::update`](crate::DisplayContext::update) comes from
+/// their clock.
+#[derive(Debug)]
+pub struct Ticker {
+    clock: Arc<dyn Clock>,
+    rate: Duration,
+    last: Instant,
+}
+
+impl Ticker {
+    /// Start ticking every `rate`, the first tick one `rate` from now
+    pub fn new(clock: Arc<dyn Clock>, rate: Duration) -> Self {
+        let last = clock.now();
+        Self { clock, rate, last }
+    }
+
+    /// Time left until the next tick is due
+    pub fn timeout(&self) -> Duration {
+        self.rate.saturating_sub(self.elapsed())
+    }
+
+    /// If a tick is due, start the next one and return the time since the
+    /// last, to use as the frame's delta
+    pub fn tick(&mut self) -> Option<Duration> {
+        let now = self.clock.now();
+        let elapsed = now.saturating_duration_since(self.last);
+        if elapsed < self.rate {
+            return None;
+        }
+        self.last = now;
+        Some(elapsed)
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_clones_share_time() {
+        let clock = ManualClock::new();
+        let shared = clock.clone();
+        let start = clock.now();
+
+        shared.advance(Duration::from_millis(250));
+        assert_eq!(clock.now() - start, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_ticks_follow_the_clock() {
+        let clock = ManualClock::new();
+        let mut ticker = Ticker::new(Arc::new(clock.clone()), Duration::from_millis(100));
+
+        assert_eq!(ticker.tick(), None);
+        clock.advance(Duration::from_millis(60));
+        assert_eq!(ticker.timeout(), Duration::from_millis(40));
+        assert_eq!(ticker.tick(), None);
+
+        // A late frame reports the whole delta and restarts the interval
+        clock.advance(Duration::from_millis(90));
+        assert_eq!(ticker.tick(), Some(Duration::from_millis(150)));
+        assert_eq!(ticker.timeout(), Duration::from_millis(100));
+        assert_eq!(ticker.tick(), None);
+    }
+}
@@ -34,7 +34,16 @@ impl Default for AttentionState {
 impl AttentionState {
     /// Update attention state with new gaze data
     pub fn update(&mut self, gaze_point: Point3D, target: Option<String>) {
-        let now = Instant::now();
+        self.update_at(gaze_point, target, Instant::now());
+    }
+
+    /// Update attention state with gaze data sampled at `now`
+    pub fn update_at(&mut self, gaze_point: Point3D, target: Option<String>, now: Instant) {
+        let dt = self
+            .gaze_history
+            .back()
+            .map(|(_, at)| now.duration_since(*at))
+            .unwrap_or_default();
 
         // Update gaze history
         self.gaze_history.push_back((gaze_point, now));
@@ -45,8 +54,8 @@ impl AttentionState {
         // Update target and dwell time
         match (&self.gaze_target, &target) {
             (Some(old), Some(new)) if old == new => {
-                // Same target, increase dwell time
-                self.dwell_time += Duration::from_millis(16); // Assume ~60fps
+                // Same target, increase dwell time by the real time elapsed
+                self.dwell_time += dt;
             }
             _ => {
                 // New target or no target
@@ -111,6 +120,7 @@ impl AttentionState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{Clock, ManualClock};
 
     #[test]
     fn test_attention_state_default() {
@@ -121,23 +131,29 @@ mod tests {
 
     #[test]
     fn test_dwell_time_accumulation() {
+        let clock = ManualClock::new();
         let mut state = AttentionState::default();
-        state.update(Point3D::ORIGIN, Some("button1".to_string()));
-        state.update(Point3D::ORIGIN, Some("button1".to_string()));
-        state.update(Point3D::ORIGIN, Some("button1".to_string()));
+        for _ in 0..3 {
+            state.update_at(Point3D::ORIGIN, Some("button1".to_string()), clock.now());
+            clock.advance(Duration::from_millis(400));
+        }
 
-        assert!(state.dwell_time > Duration::ZERO);
+        assert_eq!(state.dwell_time, Duration::from_millis(800));
         assert!(state.is_focused_on("button1"));
+        assert_eq!(state.attention_score("button1"), 0.4);
     }
 
     #[test]
     fn test_target_change_resets_dwell() {
+        let clock = ManualClock::new();
         let mut state = AttentionState::default();
-        state.update(Point3D::ORIGIN, Some("button1".to_string()));
-        state.update(Point3D::ORIGIN, Some("button1".to_string()));
+        state.update_at(Point3D::ORIGIN, Some("button1".to_string()), clock.now());
+        clock.advance(Duration::from_millis(100));
+        state.update_at(Point3D::ORIGIN, Some("button1".to_string()), clock.now());
         let dwell1 = state.dwell_time;
 
-        state.update(Point3D::ORIGIN, Some("button2".to_string()));
+        clock.advance(Duration::from_millis(100));
+        state.update_at(Point3D::ORIGIN, Some("button2".to_string()), clock.now());
         assert!(state.dwell_time < dwell1);
     }
 }
@@ -30,7 +30,8 @@ pub struct DisplayContext {
     pub time: Duration,
     /// Time since last frame
     pub delta_time: Duration,
-    /// Current tick count
+    /// Current tick count (frames rendered; animate with `time` and
+    /// `delta_time` instead so speed does not depend on frame rate)
     pub tick: u64,
 }
 
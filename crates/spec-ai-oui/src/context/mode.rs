@@ -1,9 +1,12 @@
 //! Display modes for the super OUI
 
+use serde::Deserialize;
+
 use super::InformationDensity;
 
 /// Display modes combining all use cases
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DisplayMode {
     /// Ambient - minimal HUD, passive monitoring
     Ambient,
@@ -1,9 +1,12 @@
 //! Priority levels for content display
 
+use serde::Deserialize;
+
 use super::InformationDensity;
 
 /// Content priority levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Priority {
     /// Always visible (alerts, critical warnings)
     Critical = 0,
@@ -32,15 +32,25 @@ pub struct GazeTarget {
 
 impl GazeTarget {
     pub fn new(id: impl Into<String>) -> Self {
+        Self::new_at(id, Instant::now())
+    }
+
+    /// A target the gaze entered at `now`
+    pub fn new_at(id: impl Into<String>, now: Instant) -> Self {
         Self {
             id: id.into(),
-            entered_at: Instant::now(),
+            entered_at: now,
             dwell_time: Duration::ZERO,
         }
     }
 
     pub fn update(&mut self) {
-        self.dwell_time = self.entered_at.elapsed();
+        self.update_at(Instant::now());
+    }
+
+    /// Update the dwell time as of `now`
+    pub fn update_at(&mut self, now: Instant) {
+        self.dwell_time = now.saturating_duration_since(self.entered_at);
     }
 }
 
@@ -59,42 +69,60 @@ impl Default for GazeState {
 impl GazeState {
     /// Update gaze with new position
     pub fn update(&mut self, point: Point3D, screen_pos: (f32, f32)) {
-        // Calculate velocity
-        let dt = 1.0 / 60.0; // Assume 60fps
-        self.velocity = Point3D::new(
-            (point.x - self.point.x) / dt,
-            (point.y - self.point.y) / dt,
-            (point.z - self.point.z) / dt,
-        );
+        self.update_at(point, screen_pos, Instant::now());
+    }
+
+    /// Update gaze with a position sampled at `now`
+    pub fn update_at(&mut self, point: Point3D, screen_pos: (f32, f32), now: Instant) {
+        // Calculate velocity over the real time since the last sample
+        let dt = self
+            .history
+            .back()
+            .map(|(_, at)| now.duration_since(*at).as_secs_f32())
+            .unwrap_or_default();
+        self.velocity = if dt > 0.0 {
+            Point3D::new(
+                (point.x - self.point.x) / dt,
+                (point.y - self.point.y) / dt,
+                (point.z - self.point.z) / dt,
+            )
+        } else {
+            Point3D::ORIGIN
+        };
 
         // Update position
         self.point = point;
         self.screen_pos = screen_pos;
 
         // Add to history
-        self.history.push_back((point, Instant::now()));
+        self.history.push_back((point, now));
         while self.history.len() > 60 {
             self.history.pop_front();
         }
 
         // Update target dwell time
         if let Some(ref mut target) = self.target {
-            target.update();
+            target.update_at(now);
         }
     }
 
     /// Set the current gaze target
     pub fn set_target(&mut self, id: Option<String>) {
+        self.set_target_at(id, Instant::now());
+    }
+
+    /// Set the current gaze target as of `now`
+    pub fn set_target_at(&mut self, id: Option<String>, now: Instant) {
         match (id, &self.target) {
             (Some(new_id), Some(current)) if new_id == current.id => {
                 // Same target, just update dwell time
                 if let Some(ref mut target) = self.target {
-                    target.update();
+                    target.update_at(now);
                 }
             }
             (Some(new_id), _) => {
                 // New target
-                self.target = Some(GazeTarget::new(new_id));
+                self.target = Some(GazeTarget::new_at(new_id, now));
             }
             (None, _) => {
                 self.target = None;
@@ -125,13 +153,31 @@ impl GazeState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{Clock, ManualClock};
 
     #[test]
     fn test_gaze_target() {
-        let mut target = GazeTarget::new("button1");
-        std::thread::sleep(Duration::from_millis(10));
-        target.update();
-        assert!(target.dwell_time >= Duration::from_millis(10));
+        let start = Instant::now();
+        let mut target = GazeTarget::new_at("button1", start);
+        target.update_at(start + Duration::from_millis(10));
+        assert_eq!(target.dwell_time, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_dwell_and_velocity_follow_sample_times() {
+        let clock = ManualClock::new();
+        let mut state = GazeState::default();
+        state.update_at(Point3D::new(0.0, 0.0, 1.0), (0.5, 0.5), clock.now());
+        state.set_target_at(Some("menu".to_string()), clock.now());
+
+        clock.advance(Duration::from_millis(500));
+        state.update_at(Point3D::new(0.5, 0.0, 1.0), (0.6, 0.5), clock.now());
+        assert_eq!(state.velocity.x, 1.0);
+        assert!(!state.is_dwelling(Duration::from_millis(501)));
+        assert_eq!(
+            state.dwelling_target(Duration::from_millis(500)),
+            Some("menu")
+        );
     }
 
     #[test]
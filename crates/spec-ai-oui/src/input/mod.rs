@@ -19,4 +19,4 @@ pub use gaze::{GazeState, GazeTarget};
 pub use gesture::{GestureEvent, GestureType, Hand, SwipeDirection};
 pub use head::{HeadGestureType, HeadPose};
 pub use simulator::InputSimulator;
-pub use voice::VoiceCommand;
+pub use voice::{VoiceCommand, VoiceMatch, VoiceRegistry};
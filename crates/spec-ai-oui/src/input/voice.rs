@@ -1,4 +1,4 @@
-//! Voice command recognition types
+//! Voice command recognition types and phrase registry
 
 /// A recognized voice command
 #[derive(Debug, Clone)]
@@ -107,10 +107,221 @@ impl VoiceCommand {
     }
 }
 
+/// Normalize an utterance: lowercase words without punctuation
+fn normalize(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Position of `phrase` as a contiguous word sequence within `words`
+fn find_phrase(words: &[String], phrase: &[String]) -> Option<usize> {
+    if phrase.is_empty() || phrase.len() > words.len() {
+        return None;
+    }
+    words
+        .windows(phrase.len())
+        .position(|window| window == phrase)
+}
+
+/// A phrase registered with a [`VoiceRegistry`]
+#[derive(Debug, Clone)]
+struct VoiceRegistration<A> {
+    phrase: Vec<String>,
+    action: A,
+    takes_argument: bool,
+}
+
+/// Result of resolving an utterance against a [`VoiceRegistry`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceMatch<A> {
+    /// Registered action
+    pub action: A,
+    /// Words following the phrase, for commands that take an argument
+    pub argument: Option<String>,
+    /// The phrase that matched
+    pub phrase: String,
+}
+
+/// Registry mapping spoken phrases to application actions
+///
+/// A phrase matches when its words appear contiguously in the utterance, so
+/// "show traces" also matches "please show traces now". When several phrases
+/// match, the longest wins, then the one spoken first. Commands registered with an argument capture the
+/// words that follow the phrase ("select service api gateway").
+#[derive(Debug, Clone)]
+pub struct VoiceRegistry<A> {
+    commands: Vec<VoiceRegistration<A>>,
+    min_confidence: f32,
+}
+
+impl<A: Clone> VoiceRegistry<A> {
+    /// Create an empty registry accepting commands with confidence >= 0.5
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+            min_confidence: 0.5,
+        }
+    }
+
+    /// Set the minimum recognition confidence
+    pub fn min_confidence(mut self, confidence: f32) -> Self {
+        self.min_confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Register phrases that trigger `action`
+    pub fn register(mut self, phrases: &[&str], action: A) -> Self {
+        for phrase in phrases {
+            self.commands.push(VoiceRegistration {
+                phrase: normalize(phrase),
+                action: action.clone(),
+                takes_argument: false,
+            });
+        }
+        self
+    }
+
+    /// Register a phrase followed by a free-form argument
+    pub fn register_with_argument(mut self, phrase: &str, action: A) -> Self {
+        self.commands.push(VoiceRegistration {
+            phrase: normalize(phrase),
+            action,
+            takes_argument: true,
+        });
+        self
+    }
+
+    /// Registered phrases, in registration order
+    pub fn phrases(&self) -> impl Iterator<Item = String> + '_ {
+        self.commands.iter().map(|c| {
+            let phrase = c.phrase.join(" ");
+            if c.takes_argument {
+                format!("{} …", phrase)
+            } else {
+                phrase
+            }
+        })
+    }
+
+    /// Resolve an utterance to an action
+    pub fn resolve(&self, text: &str) -> Option<VoiceMatch<A>> {
+        let words = normalize(text);
+        // Best match so far: (registration, start position, argument)
+        let mut best: Option<(&VoiceRegistration<A>, usize, Option<String>)> = None;
+
+        for command in &self.commands {
+            let Some(start) = find_phrase(&words, &command.phrase) else {
+                continue;
+            };
+            let rest = &words[start + command.phrase.len()..];
+            let argument = if command.takes_argument {
+                if rest.is_empty() {
+                    continue;
+                }
+                Some(rest.join(" "))
+            } else {
+                None
+            };
+            let better = best.as_ref().is_none_or(|(b, b_start, _)| {
+                command.phrase.len() > b.phrase.len()
+                    || (command.phrase.len() == b.phrase.len() && start < *b_start)
+            });
+            if better {
+                best = Some((command, start, argument));
+            }
+        }
+
+        best.map(|(command, _, argument)| VoiceMatch {
+            action: command.action.clone(),
+            argument,
+            phrase: command.phrase.join(" "),
+        })
+    }
+
+    /// Resolve a recognized command, ignoring interim or low-confidence results
+    pub fn resolve_command(&self, command: &VoiceCommand) -> Option<VoiceMatch<A>> {
+        if !command.is_final || command.confidence < self.min_confidence {
+            return None;
+        }
+        self.resolve(&command.text)
+    }
+}
+
+impl<A: Clone> Default for VoiceRegistry<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[derive(Debug, Clone, PartialEq)]
+    enum Action {
+        Traces,
+        Errors,
+        ShowErrors,
+        Service,
+    }
+
+    fn registry() -> VoiceRegistry<Action> {
+        VoiceRegistry::new()
+            .register(&["traces", "show traces"], Action::Traces)
+            .register(&["errors"], Action::Errors)
+            .register(&["filter errors"], Action::ShowErrors)
+            .register_with_argument("select service", Action::Service)
+    }
+
+    #[test]
+    fn test_registry_matches_phrases_in_utterance() {
+        let registry = registry();
+        let m = registry.resolve("Please, show traces!").unwrap();
+        assert_eq!(m.action, Action::Traces);
+        assert_eq!(m.phrase, "show traces");
+
+        // Longest phrase wins
+        let m = registry.resolve("filter errors").unwrap();
+        assert_eq!(m.action, Action::ShowErrors);
+
+        // Equal length: the phrase spoken first
+        let m = registry.resolve("errors in traces").unwrap();
+        assert_eq!(m.action, Action::Errors);
+
+        // Whole words only
+        assert!(registry.resolve("tracestack").is_none());
+    }
+
+    #[test]
+    fn test_registry_captures_arguments() {
+        let registry = registry();
+        let m = registry.resolve("select service Auth Service").unwrap();
+        assert_eq!(m.action, Action::Service);
+        assert_eq!(m.argument.as_deref(), Some("auth service"));
+
+        // Missing argument does not match
+        assert!(registry.resolve("select service").is_none());
+    }
+
+    #[test]
+    fn test_registry_ignores_uncertain_commands() {
+        let registry = registry().min_confidence(0.8);
+        assert!(registry
+            .resolve_command(&VoiceCommand::new("traces", 0.5))
+            .is_none());
+        assert!(registry
+            .resolve_command(&VoiceCommand::new("traces", 0.9).interim())
+            .is_none());
+        assert!(registry
+            .resolve_command(&VoiceCommand::new("traces", 0.9))
+            .is_some());
+    }
+
     #[test]
     fn test_voice_command() {
         let cmd = VoiceCommand::new("select target", 0.95).with_parsed_intent();
@@ -1,11 +1,17 @@
 //! Spatial layout system for optical UI
 //!
-//! Provides constraint-based layout for 3D/2D positioning.
+//! Provides constraint-based layout for 3D/2D positioning, placement of
+//! HUD panels into attention zones, and declarative HUD layouts loaded from
+//! TOML.
 
+mod placement;
 mod screen_space;
 mod spatial;
+mod spec;
 mod zone;
 
+pub use placement::{PanelRequest, PanelShape, Placement, ZoneLayout, ZoneRect};
 pub use screen_space::ScreenLayout;
 pub use spatial::SpatialConstraint;
+pub use spec::{HudLayout, LayoutError, WidgetSpec};
 pub use zone::AttentionZone;
@@ -0,0 +1,268 @@
+//! Attention-zone aware placement of HUD panels
+//!
+//! Panels declare a priority and shape; the layout assigns each one a
+//! screen region in the attention zone matching its priority, the current
+//! display mode, and information density. Panels that do not fit their zone
+//! spill outward (foveal -> parafoveal -> peripheral); panels hidden at the
+//! current density are not placed.
+
+use serde::Deserialize;
+
+use super::AttentionZone;
+use crate::context::{DisplayMode, InformationDensity, Priority};
+
+/// A normalized screen rectangle (0-1 on both axes)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ZoneRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ZoneRect {
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+}
+
+/// Shape of the space a panel needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanelShape {
+    /// Multi-line panel
+    #[default]
+    Panel,
+    /// Single line of text
+    Line,
+}
+
+/// A panel asking to be placed
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanelRequest<K> {
+    /// Caller's identifier for the panel
+    pub key: K,
+    pub priority: Priority,
+    pub shape: PanelShape,
+    /// Zone to place in instead of the one the priority maps to
+    pub zone: Option<AttentionZone>,
+    /// Fixed screen rectangle, bypassing zone slots
+    pub rect: Option<ZoneRect>,
+}
+
+impl<K> PanelRequest<K> {
+    pub fn new(key: K, priority: Priority, shape: PanelShape) -> Self {
+        Self {
+            key,
+            priority,
+            shape,
+            zone: None,
+            rect: None,
+        }
+    }
+
+    /// Pin the panel to a zone (it still spills outward when the zone is full)
+    pub fn in_zone(mut self, zone: AttentionZone) -> Self {
+        self.zone = Some(zone);
+        self
+    }
+
+    /// Place the panel at a fixed rectangle
+    pub fn at(mut self, rect: ZoneRect) -> Self {
+        self.rect = Some(rect);
+        self
+    }
+}
+
+/// Where a panel was placed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement<K> {
+    pub key: K,
+    pub zone: AttentionZone,
+    pub rect: ZoneRect,
+}
+
+/// Screen slots available in each zone, in fill order
+fn slots(zone: AttentionZone, shape: PanelShape) -> &'static [ZoneRect] {
+    const FOVEAL_PANEL: [ZoneRect; 1] = [ZoneRect::new(0.25, 0.30, 0.50, 0.30)];
+    const FOVEAL_LINE: [ZoneRect; 1] = [ZoneRect::new(0.25, 0.46, 0.50, 0.04)];
+    const PARAFOVEAL_PANEL: [ZoneRect; 2] = [
+        ZoneRect::new(0.30, 0.04, 0.68, 0.45),
+        ZoneRect::new(0.30, 0.52, 0.68, 0.32),
+    ];
+    const PARAFOVEAL_LINE: [ZoneRect; 1] = [ZoneRect::new(0.30, 0.86, 0.68, 0.03)];
+    const PERIPHERAL_PANEL: [ZoneRect; 2] = [
+        ZoneRect::new(0.02, 0.04, 0.26, 0.40),
+        ZoneRect::new(0.02, 0.48, 0.26, 0.36),
+    ];
+    const PERIPHERAL_LINE: [ZoneRect; 2] = [
+        ZoneRect::new(0.02, 0.90, 0.96, 0.03),
+        ZoneRect::new(0.02, 0.95, 0.96, 0.03),
+    ];
+
+    match (zone, shape) {
+        (AttentionZone::Foveal, PanelShape::Panel) => &FOVEAL_PANEL,
+        (AttentionZone::Foveal, PanelShape::Line) => &FOVEAL_LINE,
+        (AttentionZone::ParaFoveal, PanelShape::Panel) => &PARAFOVEAL_PANEL,
+        (AttentionZone::ParaFoveal, PanelShape::Line) => &PARAFOVEAL_LINE,
+        (AttentionZone::Peripheral, PanelShape::Panel) => &PERIPHERAL_PANEL,
+        (AttentionZone::Peripheral, PanelShape::Line) => &PERIPHERAL_LINE,
+        (AttentionZone::OffScreen, _) => &[],
+    }
+}
+
+/// Assigns panels to attention zones for a display mode and density
+#[derive(Debug, Clone, Copy)]
+pub struct ZoneLayout {
+    mode: DisplayMode,
+    density: InformationDensity,
+}
+
+impl ZoneLayout {
+    pub fn new(mode: DisplayMode, density: InformationDensity) -> Self {
+        Self { mode, density }
+    }
+
+    /// Zone a priority belongs in under this layout's mode and density
+    pub fn zone_for(&self, priority: Priority) -> AttentionZone {
+        if !priority.is_visible_at(self.density) {
+            return AttentionZone::OffScreen;
+        }
+        match priority {
+            Priority::Critical => AttentionZone::Foveal,
+            Priority::High => AttentionZone::ParaFoveal,
+            // Information-heavy modes keep normal content near the center
+            Priority::Normal => match self.mode {
+                DisplayMode::Research | DisplayMode::Meeting => AttentionZone::ParaFoveal,
+                _ => AttentionZone::Peripheral,
+            },
+            Priority::Low | Priority::Optional => AttentionZone::Peripheral,
+        }
+    }
+
+    /// Place panels, most important first
+    ///
+    /// Returns placements in drawing order: peripheral first, foveal last, so
+    /// that central panels draw over anything they overlap. Panels that are
+    /// hidden or find no free slot are omitted. Panels with a fixed rectangle
+    /// are placed there without taking a slot.
+    pub fn place<K: Clone>(&self, requests: &[PanelRequest<K>]) -> Vec<Placement<K>> {
+        let mut order: Vec<&PanelRequest<K>> = requests.iter().collect();
+        // Stable sort keeps request order within a priority
+        order.sort_by_key(|r| r.priority);
+
+        let mut used: Vec<(AttentionZone, PanelShape)> = Vec::new();
+        let mut placements = Vec::new();
+        for request in order {
+            let mut zone = self.zone_for(request.priority);
+            if zone == AttentionZone::OffScreen {
+                continue;
+            }
+            zone = request.zone.unwrap_or(zone);
+            if let Some(rect) = request.rect {
+                placements.push(Placement {
+                    key: request.key.clone(),
+                    zone,
+                    rect,
+                });
+                continue;
+            }
+            while zone != AttentionZone::OffScreen {
+                let taken = used
+                    .iter()
+                    .filter(|(z, s)| *z == zone && *s == request.shape)
+                    .count();
+                if let Some(rect) = slots(zone, request.shape).get(taken) {
+                    used.push((zone, request.shape));
+                    placements.push(Placement {
+                        key: request.key.clone(),
+                        zone,
+                        rect: *rect,
+                    });
+                    break;
+                }
+                zone = zone.outer();
+            }
+        }
+
+        placements.sort_by_key(|p| std::cmp::Reverse(p.zone as u8));
+        placements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requests() -> Vec<PanelRequest<&'static str>> {
+        vec![
+            PanelRequest::new("stats", Priority::Low, PanelShape::Line),
+            PanelRequest::new("content", Priority::High, PanelShape::Panel),
+            PanelRequest::new("agent", Priority::Normal, PanelShape::Panel),
+            PanelRequest::new("menu", Priority::Normal, PanelShape::Panel),
+            PanelRequest::new("alert", Priority::Critical, PanelShape::Line),
+        ]
+    }
+
+    fn zone_of(placements: &[Placement<&str>], key: &str) -> Option<AttentionZone> {
+        placements.iter().find(|p| p.key == key).map(|p| p.zone)
+    }
+
+    #[test]
+    fn test_critical_panels_take_the_foveal_zone() {
+        let layout = ZoneLayout::new(DisplayMode::Research, InformationDensity::Maximum);
+        let placements = layout.place(&requests());
+
+        assert_eq!(zone_of(&placements, "alert"), Some(AttentionZone::Foveal));
+        assert_eq!(
+            zone_of(&placements, "content"),
+            Some(AttentionZone::ParaFoveal)
+        );
+        assert_eq!(
+            zone_of(&placements, "agent"),
+            Some(AttentionZone::ParaFoveal)
+        );
+        // Parafoveal panels are full, so the menu spills outward
+        assert_eq!(
+            zone_of(&placements, "menu"),
+            Some(AttentionZone::Peripheral)
+        );
+        assert_eq!(
+            zone_of(&placements, "stats"),
+            Some(AttentionZone::Peripheral)
+        );
+
+        // Foveal placements draw last
+        assert_eq!(placements.last().unwrap().key, "alert");
+    }
+
+    #[test]
+    fn test_mode_and_density_move_panels() {
+        let layout = ZoneLayout::new(DisplayMode::Navigation, InformationDensity::Normal);
+        let placements = layout.place(&requests());
+        assert_eq!(
+            zone_of(&placements, "agent"),
+            Some(AttentionZone::Peripheral)
+        );
+        // Low priority is hidden at normal density
+        assert_eq!(zone_of(&placements, "stats"), None);
+
+        let layout = ZoneLayout::new(DisplayMode::Focus, InformationDensity::Minimal);
+        let placements = layout.place(&requests());
+        assert_eq!(placements.len(), 1);
+        assert_eq!(placements[0].key, "alert");
+    }
+}
@@ -0,0 +1,292 @@
+//! Declarative HUD layouts loaded from TOML
+//!
+//! A layout lists the HUD's widgets, each with a data binding name, a
+//! priority, and optionally a pinned zone or fixed anchor rectangle:
+//!
+//! ```toml
+//! [[widget]]
+//! id = "alert"
+//! bind = "agent.alert"
+//! priority = "critical"
+//! shape = "line"
+//!
+//! [[widget]]
+//! id = "stats"
+//! bind = "telemetry.stats"
+//! priority = "low"
+//! shape = "line"
+//! zone = "peripheral"
+//! modes = ["research", "meeting"]
+//!
+//! [[widget]]
+//! id = "clock"
+//! bind = "clock"
+//! priority = "optional"
+//! anchor = { x = 0.85, y = 0.02, width = 0.13, height = 0.03 }
+//! ```
+//!
+//! Apps resolve each widget's `bind` name to the data and renderer it
+//! shows, so widgets can be moved, reprioritized or dropped without
+//! recompiling.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{AttentionZone, PanelRequest, PanelShape, Placement, ZoneLayout, ZoneRect};
+use crate::context::{DisplayMode, InformationDensity, Priority};
+
+/// Error loading or validating a HUD layout
+#[derive(Debug, Clone)]
+pub enum LayoutError {
+    /// The layout file could not be read
+    Io(String),
+    /// The layout is not valid TOML or has unknown fields
+    Parse(String),
+    /// The layout parsed but is inconsistent
+    Invalid(String),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Io(msg) => write!(f, "Layout read error: {}", msg),
+            LayoutError::Parse(msg) => write!(f, "Layout parse error: {}", msg),
+            LayoutError::Invalid(msg) => write!(f, "Invalid layout: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// One widget in a HUD layout
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WidgetSpec {
+    /// Unique widget identifier
+    pub id: String,
+    /// Name of the data the widget shows, resolved by the app
+    pub bind: String,
+    /// Visibility priority; decides the zone and which densities show it
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub shape: PanelShape,
+    /// Zone to place in instead of the priority's default
+    #[serde(default)]
+    pub zone: Option<AttentionZone>,
+    /// Fixed screen rectangle, bypassing zone placement
+    #[serde(default)]
+    pub anchor: Option<ZoneRect>,
+    /// Display modes the widget appears in (all when empty)
+    #[serde(default)]
+    pub modes: Vec<DisplayMode>,
+}
+
+impl WidgetSpec {
+    /// Whether the widget appears in a display mode
+    pub fn shown_in(&self, mode: DisplayMode) -> bool {
+        self.modes.is_empty() || self.modes.contains(&mode)
+    }
+}
+
+/// A HUD layout: the widgets to show and where
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HudLayout {
+    #[serde(default, rename = "widget")]
+    pub widgets: Vec<WidgetSpec>,
+}
+
+impl HudLayout {
+    /// Parse and validate a layout from TOML
+    pub fn from_toml(source: &str) -> Result<Self, LayoutError> {
+        let layout: HudLayout =
+            toml::from_str(source).map_err(|e| LayoutError::Parse(e.to_string()))?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// Load a layout file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LayoutError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| LayoutError::Io(format!("{}: {}", path.display(), e)))?;
+        Self::from_toml(&source)
+    }
+
+    /// Check ids are unique and anchors lie on screen
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        let mut ids = HashSet::new();
+        for widget in &self.widgets {
+            if !ids.insert(widget.id.as_str()) {
+                return Err(LayoutError::Invalid(format!(
+                    "duplicate widget id '{}'",
+                    widget.id
+                )));
+            }
+            if let Some(rect) = widget.anchor {
+                let on_screen = rect.x >= 0.0
+                    && rect.y >= 0.0
+                    && rect.width > 0.0
+                    && rect.height > 0.0
+                    && rect.right() <= 1.0
+                    && rect.bottom() <= 1.0;
+                if !on_screen {
+                    return Err(LayoutError::Invalid(format!(
+                        "widget '{}' anchor is outside the screen",
+                        widget.id
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every binding is one the app knows how to show
+    pub fn check_bindings(&self, known: &[&str]) -> Result<(), LayoutError> {
+        let unknown: Vec<&str> = self
+            .widgets
+            .iter()
+            .map(|w| w.bind.as_str())
+            .filter(|bind| !known.contains(bind))
+            .collect();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(LayoutError::Invalid(format!(
+                "unknown bindings: {}",
+                unknown.join(", ")
+            )))
+        }
+    }
+
+    /// Place the widgets that have data to show
+    ///
+    /// `active` reports whether a widget's binding currently has anything
+    /// to display; inactive widgets and widgets not shown in `mode` are
+    /// skipped. Placements come back in drawing order.
+    pub fn place<F>(
+        &self,
+        mode: DisplayMode,
+        density: InformationDensity,
+        active: F,
+    ) -> Vec<Placement<&WidgetSpec>>
+    where
+        F: Fn(&WidgetSpec) -> bool,
+    {
+        let requests: Vec<PanelRequest<&WidgetSpec>> = self
+            .widgets
+            .iter()
+            .filter(|w| w.shown_in(mode) && active(w))
+            .map(|w| {
+                let mut request = PanelRequest::new(w, w.priority, w.shape);
+                request.zone = w.zone;
+                request.rect = w.anchor;
+                request
+            })
+            .collect();
+        ZoneLayout::new(mode, density).place(&requests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &str = r#"
+        [[widget]]
+        id = "alert"
+        bind = "agent.alert"
+        priority = "critical"
+        shape = "line"
+
+        [[widget]]
+        id = "feed"
+        bind = "telemetry.feed"
+        priority = "high"
+
+        [[widget]]
+        id = "stats"
+        bind = "telemetry.stats"
+        priority = "low"
+        shape = "line"
+        modes = ["research"]
+
+        [[widget]]
+        id = "clock"
+        bind = "clock"
+        priority = "normal"
+        zone = "foveal"
+        anchor = { x = 0.85, y = 0.02, width = 0.13, height = 0.03 }
+    "#;
+
+    fn ids(placements: &[Placement<&WidgetSpec>]) -> Vec<String> {
+        let mut ids: Vec<String> = placements.iter().map(|p| p.key.id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_parse_and_place() {
+        let layout = HudLayout::from_toml(LAYOUT).unwrap();
+        assert_eq!(layout.widgets.len(), 4);
+        assert_eq!(layout.widgets[1].shape, PanelShape::Panel);
+
+        let placements = layout.place(DisplayMode::Research, InformationDensity::Maximum, |w| {
+            w.id != "alert"
+        });
+        assert_eq!(ids(&placements), vec!["clock", "feed", "stats"]);
+        let clock = placements.iter().find(|p| p.key.id == "clock").unwrap();
+        assert_eq!(clock.rect, ZoneRect::new(0.85, 0.02, 0.13, 0.03));
+        assert_eq!(clock.zone, AttentionZone::Foveal);
+
+        // Stats are limited to research mode
+        let placements = layout.place(DisplayMode::Meeting, InformationDensity::Maximum, |_| true);
+        assert_eq!(ids(&placements), vec!["alert", "clock", "feed"]);
+    }
+
+    #[test]
+    fn test_invalid_layouts_are_rejected() {
+        let unknown_field = "[[widget]]\nid = \"a\"\nbind = \"x\"\ncolour = \"red\"";
+        assert!(matches!(
+            HudLayout::from_toml(unknown_field),
+            Err(LayoutError::Parse(_))
+        ));
+
+        let duplicate =
+            "[[widget]]\nid = \"a\"\nbind = \"x\"\n[[widget]]\nid = \"a\"\nbind = \"y\"";
+        assert!(matches!(
+            HudLayout::from_toml(duplicate),
+            Err(LayoutError::Invalid(_))
+        ));
+
+        let off_screen = "[[widget]]\nid = \"a\"\nbind = \"x\"\nanchor = { x = 0.9, y = 0.0, width = 0.2, height = 0.1 }";
+        assert!(matches!(
+            HudLayout::from_toml(off_screen),
+            Err(LayoutError::Invalid(_))
+        ));
+
+        let layout = HudLayout::from_toml(LAYOUT).unwrap();
+        assert!(layout
+            .check_bindings(&["agent.alert", "telemetry.feed"])
+            .is_err());
+        assert!(layout
+            .check_bindings(&["agent.alert", "telemetry.feed", "telemetry.stats", "clock"])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_load_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hud.toml");
+        std::fs::write(&path, LAYOUT).unwrap();
+        assert_eq!(HudLayout::load(&path).unwrap().widgets.len(), 4);
+        assert!(matches!(
+            HudLayout::load(dir.path().join("missing.toml")),
+            Err(LayoutError::Io(_))
+        ));
+    }
+}
@@ -1,7 +1,10 @@
 //! Attention zones for context-aware layout
 
+use serde::Deserialize;
+
 /// Attention zones based on visual field
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AttentionZone {
     /// Center of vision (high detail, small area)
     Foveal,
@@ -34,6 +37,15 @@ impl AttentionZone {
         }
     }
 
+    /// The next zone further from the center of vision
+    pub fn outer(&self) -> Self {
+        match self {
+            AttentionZone::Foveal => AttentionZone::ParaFoveal,
+            AttentionZone::ParaFoveal => AttentionZone::Peripheral,
+            AttentionZone::Peripheral | AttentionZone::OffScreen => AttentionZone::OffScreen,
+        }
+    }
+
     /// Determine zone from angle (degrees from center)
     pub fn from_angle(angle: f32) -> Self {
         if angle <= 5.0 {
@@ -7,10 +7,12 @@
 //! - Context-aware HUD with dynamic information density
 //! - Optical widgets optimized for AR/glasses displays
 //! - Audio feedback integration
+//! - An injectable clock for deterministic time-dependent tests
 
 pub mod animation;
 pub mod app;
 pub mod audio;
+pub mod clock;
 pub mod context;
 pub mod input;
 pub mod layout;
@@ -21,6 +23,7 @@ pub mod widget;
 
 // Re-export commonly used types
 pub use app::OpticalApp;
+pub use clock::{Clock, ManualClock, SystemClock};
 pub use context::{DisplayContext, DisplayMode, InformationDensity, Priority};
 pub use input::{GestureEvent, GestureType, OpticalEvent};
 pub use layout::{AttentionZone, SpatialConstraint};
@@ -117,6 +117,15 @@ impl Color {
         }
     }
 
+    /// Fade toward black by visibility (1.0 = unchanged, 0.0 = black)
+    pub fn faded(&self, visibility: f32) -> Color {
+        if visibility >= 1.0 {
+            *self
+        } else {
+            Color::Black.blend(self, visibility.max(0.0))
+        }
+    }
+
     /// Convert to RGB tuple if possible
     pub fn to_rgb(&self) -> Option<(u8, u8, u8)> {
         match self {
@@ -6,29 +6,191 @@ use crossterm::{
     terminal::{self},
 };
 use std::io::{self, Write};
+use unicode_width::UnicodeWidthChar;
 
 use super::Projection;
 use crate::renderer::{Color, RenderBackend, RenderError, RenderGlyph, SurfaceCapabilities};
 use crate::spatial::{Point3D, Transform};
 
+/// Unchanged cells between two changed cells are reprinted rather than
+/// skipped with a cursor move when the gap is at most this wide and needs no
+/// color change (a move costs more bytes than a few characters)
+const MAX_BRIDGED_GAP: usize = 4;
+
 /// Cell in the terminal buffer
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 struct Cell {
-    symbol: String,
+    symbol: char,
     fg: Color,
     bg: Color,
     depth: f32,
 }
 
-impl Default for Cell {
-    fn default() -> Self {
+impl Cell {
+    fn blank(bg: Color) -> Self {
         Self {
-            symbol: " ".to_string(),
+            symbol: ' ',
             fg: Color::White,
-            bg: Color::Black,
+            bg,
             depth: f32::MAX,
         }
     }
+
+    fn same_content(&self, other: &Cell) -> bool {
+        self.symbol == other.symbol && self.fg == other.fg && self.bg == other.bg
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::blank(Color::Black)
+    }
+}
+
+/// Counts of what one frame wrote to the terminal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Cells that differed from the previous frame
+    pub cells_changed: usize,
+    /// Cursor move commands
+    pub cursor_moves: usize,
+    /// Foreground/background color commands
+    pub color_changes: usize,
+    /// Print commands (one per run of same-colored cells)
+    pub prints: usize,
+    /// Bytes written
+    pub bytes: usize,
+}
+
+impl FrameStats {
+    /// Total terminal commands written
+    pub fn commands(&self) -> usize {
+        self.cursor_moves + self.color_changes + self.prints
+    }
+}
+
+/// Builds one frame's output, batching characters into runs
+///
+/// Characters accumulate in `run` while the cursor advances naturally and
+/// colors stay the same; a cursor move or color change flushes the run as a
+/// single print.
+struct FrameEncoder<'a> {
+    out: &'a mut Vec<u8>,
+    run: &'a mut String,
+    stats: FrameStats,
+    cursor: Option<(usize, usize)>,
+    fg: Option<Color>,
+    bg: Option<Color>,
+}
+
+impl FrameEncoder<'_> {
+    fn flush_run(&mut self) -> io::Result<()> {
+        if !self.run.is_empty() {
+            queue!(self.out, Print(&*self.run))?;
+            self.stats.prints += 1;
+            self.run.clear();
+        }
+        Ok(())
+    }
+
+    fn put(&mut self, x: usize, y: usize, cell: &Cell) -> io::Result<()> {
+        if self.cursor != Some((x, y)) {
+            self.flush_run()?;
+            queue!(self.out, cursor::MoveTo(x as u16, y as u16))?;
+            self.stats.cursor_moves += 1;
+        }
+        if self.fg != Some(cell.fg) {
+            self.flush_run()?;
+            queue!(self.out, SetForegroundColor(cell.fg.to_crossterm()))?;
+            self.stats.color_changes += 1;
+            self.fg = Some(cell.fg);
+        }
+        if self.bg != Some(cell.bg) {
+            self.flush_run()?;
+            queue!(self.out, SetBackgroundColor(cell.bg.to_crossterm()))?;
+            self.stats.color_changes += 1;
+            self.bg = Some(cell.bg);
+        }
+        self.run.push(cell.symbol);
+        // Wide characters advance the terminal cursor by more than a cell
+        let advance = cell.symbol.width().unwrap_or(1);
+        self.cursor = Some((x + advance, y));
+        Ok(())
+    }
+
+    /// Whether an unchanged cell is cheaper to reprint than to skip
+    fn bridges(&self, x: usize, y: usize, cell: &Cell) -> bool {
+        self.cursor == Some((x, y))
+            && self.fg == Some(cell.fg)
+            && self.bg == Some(cell.bg)
+            && cell.symbol.width() == Some(1)
+    }
+}
+
+/// Write the cells of `current` that differ from `previous` to `out`
+fn encode_diff(
+    previous: &[Cell],
+    current: &[Cell],
+    width: usize,
+    out: &mut Vec<u8>,
+    run: &mut String,
+) -> io::Result<FrameStats> {
+    let start = out.len();
+    let mut encoder = FrameEncoder {
+        out,
+        run,
+        stats: FrameStats::default(),
+        cursor: None,
+        fg: None,
+        bg: None,
+    };
+
+    queue!(encoder.out, cursor::Hide)?;
+    for (y, (row, prev_row)) in current
+        .chunks(width.max(1))
+        .zip(previous.chunks(width.max(1)))
+        .enumerate()
+    {
+        let changed = |x: usize| !row[x].same_content(&prev_row[x]);
+        let mut x = 0;
+        while x < row.len() {
+            if changed(x) {
+                encoder.put(x, y, &row[x])?;
+                encoder.stats.cells_changed += 1;
+                x += 1;
+                continue;
+            }
+
+            // Reprint a short unchanged gap if more changes follow on this row
+            let gap_end = (x..row.len()).find(|&i| changed(i));
+            let bridge = gap_end.is_some_and(|end| {
+                end - x <= MAX_BRIDGED_GAP
+                    && (x..end).all(|i| {
+                        let cell = &row[i];
+                        encoder.fg == Some(cell.fg)
+                            && encoder.bg == Some(cell.bg)
+                            && cell.symbol.width() == Some(1)
+                    })
+                    && encoder.bridges(x, y, &row[x])
+            });
+            match gap_end {
+                Some(end) if bridge => {
+                    for (i, cell) in row.iter().enumerate().take(end).skip(x) {
+                        encoder.put(i, y, cell)?;
+                    }
+                    x = end;
+                }
+                Some(end) => x = end,
+                None => break,
+            }
+        }
+        encoder.flush_run()?;
+    }
+    queue!(encoder.out, cursor::Show)?;
+
+    let mut stats = encoder.stats;
+    stats.bytes = out.len() - start;
+    Ok(stats)
 }
 
 /// Terminal rendering backend with depth buffer
@@ -47,6 +209,14 @@ pub struct TerminalBackend {
     camera: Transform,
     /// Clear color
     clear_color: Color,
+    /// Whether the size was fixed with `with_size` (no terminal queries)
+    fixed_size: bool,
+    /// Reused output buffer for a frame
+    output: Vec<u8>,
+    /// Reused buffer for a run of characters
+    run: String,
+    /// Stats for the last frame written
+    last_frame: FrameStats,
 }
 
 impl TerminalBackend {
@@ -66,11 +236,63 @@ impl TerminalBackend {
             projection: Projection::perspective(70.0, aspect),
             camera: Transform::identity(),
             clear_color: Color::Rgb(5, 7, 12), // Dark blue-black for HUD feel
+            fixed_size: false,
+            output: Vec::new(),
+            run: String::new(),
+            last_frame: FrameStats::default(),
         })
     }
 
+    /// Create a backend of a fixed size that never queries the terminal
+    ///
+    /// Useful for tests and benchmarks: render with [`Self::render_to`]
+    /// instead of `end_frame`.
+    pub fn with_size(width: u16, height: u16) -> Self {
+        let size = (width as usize) * (height as usize);
+        Self {
+            width: width as u32,
+            height: height as u32,
+            buffer: vec![Cell::default(); size],
+            prev_buffer: vec![Cell::default(); size],
+            projection: Projection::perspective(70.0, width as f32 / height.max(1) as f32),
+            camera: Transform::identity(),
+            clear_color: Color::Rgb(5, 7, 12),
+            fixed_size: true,
+            output: Vec::new(),
+            run: String::new(),
+            last_frame: FrameStats::default(),
+        }
+    }
+
+    /// Stats for the last frame written
+    pub fn last_frame_stats(&self) -> FrameStats {
+        self.last_frame
+    }
+
+    /// Write the changes since the previous frame to `writer`
+    pub fn render_to(&mut self, writer: &mut impl Write) -> Result<FrameStats, RenderError> {
+        self.output.clear();
+        let stats = encode_diff(
+            &self.prev_buffer,
+            &self.buffer,
+            self.width as usize,
+            &mut self.output,
+            &mut self.run,
+        )
+        .map_err(|e| RenderError::FrameError(e.to_string()))?;
+        writer
+            .write_all(&self.output)
+            .and_then(|_| writer.flush())
+            .map_err(|e| RenderError::FrameError(e.to_string()))?;
+        self.last_frame = stats;
+        Ok(stats)
+    }
+
     /// Refresh terminal size
     pub fn refresh_size(&mut self) -> Result<(), RenderError> {
+        if self.fixed_size {
+            return Ok(());
+        }
         let (width, height) =
             terminal::size().map_err(|e| RenderError::TerminalError(e.to_string()))?;
 
@@ -96,7 +318,7 @@ impl TerminalBackend {
     }
 
     /// Set a cell in the buffer with depth test
-    fn set_cell(&mut self, x: u16, y: u16, symbol: String, fg: Color, depth: f32) {
+    fn set_cell(&mut self, x: u16, y: u16, symbol: char, fg: Color, depth: f32) {
         if let Some(idx) = self.index(x, y) {
             let cell = &mut self.buffer[idx];
             if depth < cell.depth {
@@ -108,7 +330,7 @@ impl TerminalBackend {
     }
 
     /// Set a cell without depth test (for HUD elements)
-    fn set_cell_hud(&mut self, x: u16, y: u16, symbol: String, fg: Color) {
+    fn set_cell_hud(&mut self, x: u16, y: u16, symbol: char, fg: Color) {
         if let Some(idx) = self.index(x, y) {
             let cell = &mut self.buffer[idx];
             cell.symbol = symbol;
@@ -143,52 +365,15 @@ impl RenderBackend for TerminalBackend {
         std::mem::swap(&mut self.buffer, &mut self.prev_buffer);
 
         // Clear buffer
-        for cell in &mut self.buffer {
-            *cell = Cell {
-                symbol: " ".to_string(),
-                fg: Color::White,
-                bg: self.clear_color,
-                depth: f32::MAX,
-            };
-        }
+        self.buffer.fill(Cell::blank(self.clear_color));
 
         Ok(())
     }
 
     fn end_frame(&mut self) -> Result<(), RenderError> {
-        let mut stdout = io::stdout();
-
-        // Hide cursor during rendering
-        queue!(stdout, cursor::Hide).map_err(|e| RenderError::FrameError(e.to_string()))?;
-
-        // Diff render - only update changed cells
-        for y in 0..self.height as u16 {
-            for x in 0..self.width as u16 {
-                if let Some(idx) = self.index(x, y) {
-                    let cell = &self.buffer[idx];
-                    let prev = &self.prev_buffer[idx];
-
-                    // Only update if changed
-                    if cell.symbol != prev.symbol || cell.fg != prev.fg || cell.bg != prev.bg {
-                        queue!(
-                            stdout,
-                            cursor::MoveTo(x, y),
-                            SetForegroundColor(cell.fg.to_crossterm()),
-                            SetBackgroundColor(cell.bg.to_crossterm()),
-                            Print(&cell.symbol)
-                        )
-                        .map_err(|e| RenderError::FrameError(e.to_string()))?;
-                    }
-                }
-            }
-        }
-
-        // Show cursor and flush
-        queue!(stdout, cursor::Show).map_err(|e| RenderError::FrameError(e.to_string()))?;
-        stdout
-            .flush()
-            .map_err(|e| RenderError::FrameError(e.to_string()))?;
-
+        // Diff render: changed cells are batched into runs and written to
+        // the terminal in a single write
+        self.render_to(&mut io::stdout().lock())?;
         Ok(())
     }
 
@@ -196,7 +381,7 @@ impl RenderBackend for TerminalBackend {
         self.clear_color = color;
         for cell in &mut self.buffer {
             cell.bg = color;
-            cell.symbol = " ".to_string();
+            cell.symbol = ' ';
             cell.depth = f32::MAX;
         }
     }
@@ -222,7 +407,8 @@ impl RenderBackend for TerminalBackend {
                     glyph.color
                 };
 
-                self.set_cell(x, y, glyph.symbol.clone(), color, depth);
+                let symbol = glyph.symbol.chars().next().unwrap_or(' ');
+                self.set_cell(x, y, symbol, color, depth);
             }
         }
     }
@@ -266,13 +452,13 @@ impl RenderBackend for TerminalBackend {
 
                 if x >= 0 && x < self.width as i32 && y >= 0 && y < self.height as i32 {
                     let symbol = if dx > -dy {
-                        "─"
+                        '─'
                     } else if -dy > dx {
-                        "│"
+                        '│'
                     } else {
-                        "·"
+                        '·'
                     };
-                    self.set_cell(x as u16, y as u16, symbol.to_string(), color, depth);
+                    self.set_cell(x as u16, y as u16, symbol, color, depth);
                 }
 
                 if x == x2 as i32 && y == y2 as i32 {
@@ -311,25 +497,25 @@ impl RenderBackend for TerminalBackend {
                     if dx == 0 || dx == sw - 1 {
                         if dy == 0 {
                             if dx == 0 {
-                                "┌"
+                                '┌'
                             } else {
-                                "┐"
+                                '┐'
                             }
                         } else if dx == 0 {
-                            "└"
+                            '└'
                         } else {
-                            "┘"
+                            '┘'
                         }
                     } else {
-                        "─"
+                        '─'
                     }
                 } else if dx == 0 || dx == sw - 1 {
-                    "│"
+                    '│'
                 } else {
-                    " "
+                    ' '
                 };
 
-                self.set_cell_hud(sx + dx, sy + dy, symbol.to_string(), color);
+                self.set_cell_hud(sx + dx, sy + dy, symbol, color);
             }
         }
     }
@@ -341,7 +527,7 @@ impl RenderBackend for TerminalBackend {
         for (i, ch) in text.chars().enumerate() {
             let char_x = sx + i as u16;
             if char_x < self.width as u16 {
-                self.set_cell_hud(char_x, sy, ch.to_string(), color);
+                self.set_cell_hud(char_x, sy, ch, color);
             }
         }
     }
@@ -369,10 +555,54 @@ impl RenderBackend for TerminalBackend {
 mod tests {
     use super::*;
 
+    fn frame(backend: &mut TerminalBackend, draw: impl FnOnce(&mut TerminalBackend)) -> FrameStats {
+        backend.begin_frame().unwrap();
+        draw(backend);
+        backend.render_to(&mut io::sink()).unwrap()
+    }
+
+    #[test]
+    fn test_same_colored_text_is_one_run() {
+        let mut backend = TerminalBackend::with_size(40, 4);
+        frame(&mut backend, |_| {});
+
+        let stats = frame(&mut backend, |b| {
+            b.draw_hud_text(0.0, 0.25, "hello world", Color::Green)
+        });
+        assert_eq!(stats.cells_changed, 11);
+        assert_eq!(stats.cursor_moves, 1);
+        assert_eq!(stats.color_changes, 2);
+        assert_eq!(stats.prints, 1);
+    }
+
+    #[test]
+    fn test_unchanged_frame_writes_no_cells() {
+        let mut backend = TerminalBackend::with_size(20, 2);
+        let draw = |b: &mut TerminalBackend| b.draw_hud_text(0.0, 0.0, "static", Color::White);
+        frame(&mut backend, draw);
+        let stats = frame(&mut backend, draw);
+        assert_eq!(stats.commands(), 0);
+    }
+
     #[test]
-    fn test_index_calculation() {
-        // Note: Can't easily test terminal backend without a terminal
-        // This is more of a compile-time check
-        let _ = Cell::default();
+    fn test_small_gaps_are_bridged() {
+        let mut backend = TerminalBackend::with_size(20, 1);
+        frame(&mut backend, |b| {
+            b.draw_hud_text(0.0, 0.0, "a-b-c", Color::White)
+        });
+
+        // Only a, b and c change; the dashes between are reprinted
+        let stats = frame(&mut backend, |b| {
+            b.draw_hud_text(0.0, 0.0, "x-y-z", Color::White)
+        });
+        assert_eq!(stats.cells_changed, 3);
+        assert_eq!(stats.cursor_moves, 1);
+        assert_eq!(stats.prints, 1);
+
+        let mut out = Vec::new();
+        backend.begin_frame().unwrap();
+        backend.draw_hud_text(0.0, 0.0, "a-b-c", Color::White);
+        backend.render_to(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("a-b-c"));
     }
 }
@@ -2,6 +2,8 @@
 
 mod backend;
 mod projection;
+mod screen;
 
-pub use backend::TerminalBackend;
+pub use backend::{FrameStats, TerminalBackend};
 pub use projection::Projection;
+pub use screen::ScreenGuard;
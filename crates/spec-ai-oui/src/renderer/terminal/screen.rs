@@ -0,0 +1,54 @@
+//! RAII guard for the full-screen terminal session
+
+use crossterm::{
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io::{self, stdout};
+use std::sync::Once;
+
+/// Raw mode and alternate screen, restored when dropped
+///
+/// Restoring on drop covers early returns through `?` as well as normal
+/// exits. The first guard also installs a panic hook that restores the
+/// terminal before the panic message is printed, so the message is not lost
+/// on the alternate screen.
+pub struct ScreenGuard {
+    _private: (),
+}
+
+impl ScreenGuard {
+    /// Enable raw mode and switch to the alternate screen
+    pub fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        install_panic_hook();
+        // Created before entering the alternate screen so raw mode is
+        // disabled again if that fails
+        let guard = Self { _private: () };
+        execute!(stdout(), EnterAlternateScreen)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for ScreenGuard {
+    fn drop(&mut self) {
+        // Best effort cleanup - ignore errors during drop
+        let _ = restore();
+    }
+}
+
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = restore();
+            previous(info);
+        }));
+    });
+}
+
+fn restore() -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(stdout(), LeaveAlternateScreen)
+}
@@ -1,6 +1,6 @@
 //! Quaternion representation for 3D rotations
 
-use super::Vector3D;
+use super::{Point3D, Vector3D};
 use std::ops::Mul;
 
 /// A quaternion for representing 3D rotations
@@ -43,6 +43,9 @@ impl Quaternion {
 
     /// Create a quaternion from Euler angles (in radians)
     /// Order: yaw (Y), pitch (X), roll (Z)
+    ///
+    /// Positive yaw turns forward toward +X, positive pitch tilts forward
+    /// down toward -Y.
     pub fn from_euler(yaw: f32, pitch: f32, roll: f32) -> Self {
         let cy = (yaw * 0.5).cos();
         let sy = (yaw * 0.5).sin();
@@ -51,14 +54,100 @@ impl Quaternion {
         let cr = (roll * 0.5).cos();
         let sr = (roll * 0.5).sin();
 
+        // yaw * pitch * roll
         Self {
-            w: cr * cp * cy + sr * sp * sy,
-            x: sr * cp * cy - cr * sp * sy,
-            y: cr * sp * cy + sr * cp * sy,
-            z: cr * cp * sy - sr * sp * cy,
+            w: cy * cp * cr + sy * sp * sr,
+            x: cy * sp * cr + sy * cp * sr,
+            y: sy * cp * cr - cy * sp * sr,
+            z: cy * cp * sr - sy * sp * cr,
         }
     }
 
+    /// Convert to Euler angles `(yaw, pitch, roll)` in radians
+    ///
+    /// Inverse of [`Quaternion::from_euler`]. Pitch is in [-π/2, π/2]; at
+    /// ±π/2 yaw and roll are indistinguishable and roll is reported as zero.
+    pub fn to_euler(&self) -> (f32, f32, f32) {
+        let q = self.normalize();
+        let (x, y, z, w) = (q.x, q.y, q.z, q.w);
+
+        // Rotation matrix entries used by the Y-X-Z decomposition
+        let m02 = 2.0 * (x * z + w * y);
+        let m22 = 1.0 - 2.0 * (x * x + y * y);
+        let m12 = 2.0 * (y * z - w * x);
+        let m10 = 2.0 * (x * y + w * z);
+        let m11 = 1.0 - 2.0 * (x * x + z * z);
+
+        let pitch = (-m12).clamp(-1.0, 1.0).asin();
+        if m12.abs() > 0.9999 {
+            let m00 = 1.0 - 2.0 * (y * y + z * z);
+            let m20 = 2.0 * (x * z - w * y);
+            return ((-m20).atan2(m00), pitch, 0.0);
+        }
+        (m02.atan2(m22), pitch, m10.atan2(m11))
+    }
+
+    /// Rotation that points forward (+Z) along `forward` with up as close to
+    /// `up` as possible
+    ///
+    /// Returns the identity for a zero `forward`. When `forward` is parallel
+    /// to `up`, a perpendicular up is chosen.
+    pub fn look_rotation(forward: Vector3D, up: Vector3D) -> Self {
+        let forward = forward.normalize();
+        if forward.magnitude_squared() < 0.0001 {
+            return Self::IDENTITY;
+        }
+        let mut right = up.cross(&forward);
+        if right.magnitude_squared() < 0.0001 {
+            // Looking straight along `up`: any perpendicular will do
+            let fallback = if forward.x.abs() < 0.9 {
+                Vector3D::RIGHT
+            } else {
+                Vector3D::FORWARD
+            };
+            right = forward.cross(&fallback).cross(&forward);
+        }
+        let right = right.normalize();
+        let up = forward.cross(&right);
+
+        // Rotation matrix with columns right, up, forward
+        let (m00, m01, m02) = (right.x, up.x, forward.x);
+        let (m10, m11, m12) = (right.y, up.y, forward.y);
+        let (m20, m21, m22) = (right.z, up.z, forward.z);
+
+        let trace = m00 + m11 + m22;
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new((m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s, 0.25 * s)
+        } else if m00 > m11 && m00 > m22 {
+            let s = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+            Self::new(0.25 * s, (m01 + m10) / s, (m02 + m20) / s, (m21 - m12) / s)
+        } else if m11 > m22 {
+            let s = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+            Self::new((m01 + m10) / s, 0.25 * s, (m12 + m21) / s, (m02 - m20) / s)
+        } else {
+            let s = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+            Self::new((m02 + m20) / s, (m12 + m21) / s, 0.25 * s, (m10 - m01) / s)
+        };
+        q.normalize()
+    }
+
+    /// Rotation from `from` to look at `to`, keeping up near `up`
+    pub fn look_at(from: Point3D, to: Point3D, up: Vector3D) -> Self {
+        Self::look_rotation(to - from, up)
+    }
+
+    /// Dot product of the two quaternions as 4D vectors
+    pub fn dot(&self, other: &Quaternion) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    /// Angle in radians of the rotation taking this orientation to `other`
+    pub fn angle_to(&self, other: &Quaternion) -> f32 {
+        let dot = self.normalize().dot(&other.normalize()).abs().min(1.0);
+        2.0 * dot.acos()
+    }
+
     /// Get the magnitude of the quaternion
     pub fn magnitude(&self) -> f32 {
         (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt()
@@ -113,9 +202,27 @@ impl Quaternion {
         v + (uv * self.w + uuv) * 2.0
     }
 
+    /// Normalized linear interpolation along the shorter path
+    ///
+    /// Cheaper than [`Quaternion::slerp`] but does not move at constant
+    /// angular speed; fine for small steps such as per-frame smoothing.
+    pub fn nlerp(&self, other: &Quaternion, t: f32) -> Self {
+        let sign = if self.dot(other) < 0.0 { -1.0 } else { 1.0 };
+        Quaternion::new(
+            self.x + t * (sign * other.x - self.x),
+            self.y + t * (sign * other.y - self.y),
+            self.z + t * (sign * other.z - self.z),
+            self.w + t * (sign * other.w - self.w),
+        )
+        .normalize()
+    }
+
     /// Spherical linear interpolation between two quaternions
+    ///
+    /// Rotates at constant angular speed along the shorter path; `t` of 0
+    /// gives `self` and 1 gives `other` (or its equivalent negation).
     pub fn slerp(&self, other: &Quaternion, t: f32) -> Self {
-        let dot = self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w;
+        let dot = self.dot(other);
 
         // If the dot product is negative, negate one quaternion to take the shorter path
         let (other, dot) = if dot < 0.0 {
@@ -129,21 +236,15 @@ impl Quaternion {
 
         // If quaternions are very close, use linear interpolation
         if dot > 0.9995 {
-            return Quaternion::new(
-                self.x + t * (other.x - self.x),
-                self.y + t * (other.y - self.y),
-                self.z + t * (other.z - self.z),
-                self.w + t * (other.w - self.w),
-            )
-            .normalize();
+            return self.nlerp(&other, t);
         }
 
-        let theta_0 = dot.acos();
+        let theta_0 = dot.min(1.0).acos();
         let theta = theta_0 * t;
         let sin_theta = theta.sin();
         let sin_theta_0 = theta_0.sin();
 
-        let s0 = (theta_0 - theta).cos() - dot * sin_theta / sin_theta_0;
+        let s0 = theta.cos() - dot * sin_theta / sin_theta_0;
         let s1 = sin_theta / sin_theta_0;
 
         Quaternion::new(
@@ -223,6 +324,164 @@ mod tests {
         assert!((n.magnitude() - 1.0).abs() < 0.0001);
     }
 
+    fn assert_vec_eq(a: Vector3D, b: Vector3D) {
+        assert!(
+            (a - b).magnitude() < 0.001,
+            "expected {:?} to equal {:?}",
+            a,
+            b
+        );
+    }
+
+    fn assert_same_rotation(a: Quaternion, b: Quaternion) {
+        assert!(a.angle_to(&b) < 0.001, "expected {:?} to equal {:?}", a, b);
+    }
+
+    #[test]
+    fn test_slerp_endpoints_and_midpoint() {
+        let a = Quaternion::IDENTITY;
+        let b = Quaternion::from_axis_angle(Vector3D::UP, PI / 2.0);
+
+        assert_same_rotation(a.slerp(&b, 0.0), a);
+        assert_same_rotation(a.slerp(&b, 1.0), b);
+        assert_same_rotation(
+            a.slerp(&b, 0.5),
+            Quaternion::from_axis_angle(Vector3D::UP, PI / 4.0),
+        );
+        assert!((a.slerp(&b, 0.3).magnitude() - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_slerp_constant_angular_speed() {
+        let a = Quaternion::from_axis_angle(Vector3D::RIGHT, -0.4);
+        let b = Quaternion::from_axis_angle(Vector3D::new(1.0, 1.0, 0.0), 2.0);
+        let total = a.angle_to(&b);
+        for step in 1..10 {
+            let t = step as f32 / 10.0;
+            let q = a.slerp(&b, t);
+            assert!((a.angle_to(&q) - total * t).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn test_slerp_takes_shorter_path() {
+        let a = Quaternion::from_axis_angle(Vector3D::UP, 0.1);
+        // Same rotation as a 0.3 rad turn, but in the opposite hemisphere
+        let b = Quaternion::from_axis_angle(Vector3D::UP, 0.3);
+        let b_negated = Quaternion::new(-b.x, -b.y, -b.z, -b.w);
+
+        assert_same_rotation(
+            a.slerp(&b_negated, 0.5),
+            Quaternion::from_axis_angle(Vector3D::UP, 0.2),
+        );
+        assert_same_rotation(
+            a.nlerp(&b_negated, 0.5),
+            Quaternion::from_axis_angle(Vector3D::UP, 0.2),
+        );
+    }
+
+    #[test]
+    fn test_nlerp_endpoints() {
+        let a = Quaternion::from_axis_angle(Vector3D::FORWARD, 0.5);
+        let b = Quaternion::from_axis_angle(Vector3D::UP, -1.0);
+        assert_same_rotation(a.nlerp(&b, 0.0), a);
+        assert_same_rotation(a.nlerp(&b, 1.0), b);
+        assert!((a.nlerp(&b, 0.5).magnitude() - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_from_euler_axes() {
+        // Yaw turns forward toward +X
+        let yaw = Quaternion::from_euler(PI / 2.0, 0.0, 0.0);
+        assert_vec_eq(yaw.forward(), Vector3D::RIGHT);
+        assert_vec_eq(yaw.up(), Vector3D::UP);
+
+        // Pitch tilts forward down
+        let pitch = Quaternion::from_euler(0.0, PI / 2.0, 0.0);
+        assert_vec_eq(pitch.forward(), Vector3D::DOWN);
+
+        // Roll keeps forward and turns up toward -X
+        let roll = Quaternion::from_euler(0.0, 0.0, PI / 2.0);
+        assert_vec_eq(roll.forward(), Vector3D::FORWARD);
+        assert_vec_eq(roll.up(), Vector3D::LEFT);
+
+        // Composition order is yaw * pitch * roll
+        let combined = Quaternion::from_euler(0.3, -0.2, 0.1);
+        let composed = Quaternion::from_axis_angle(Vector3D::UP, 0.3)
+            * Quaternion::from_axis_angle(Vector3D::RIGHT, -0.2)
+            * Quaternion::from_axis_angle(Vector3D::FORWARD, 0.1);
+        assert_same_rotation(combined, composed);
+    }
+
+    #[test]
+    fn test_euler_round_trip() {
+        for &(yaw, pitch, roll) in &[
+            (0.0, 0.0, 0.0),
+            (0.5, 0.2, -0.3),
+            (-2.5, -1.2, 1.0),
+            (3.0, 0.7, -3.0),
+        ] {
+            let (y, p, r) = Quaternion::from_euler(yaw, pitch, roll).to_euler();
+            assert!((y - yaw).abs() < 0.001, "yaw {} != {}", y, yaw);
+            assert!((p - pitch).abs() < 0.001, "pitch {} != {}", p, pitch);
+            assert!((r - roll).abs() < 0.001, "roll {} != {}", r, roll);
+        }
+    }
+
+    #[test]
+    fn test_euler_gimbal_lock() {
+        let q = Quaternion::from_euler(0.4, PI / 2.0, 0.0);
+        let (yaw, pitch, roll) = q.to_euler();
+        assert!((pitch - PI / 2.0).abs() < 0.01);
+        assert_eq!(roll, 0.0);
+        assert_same_rotation(Quaternion::from_euler(yaw, pitch, roll), q);
+    }
+
+    #[test]
+    fn test_look_rotation() {
+        let directions = [
+            Vector3D::FORWARD,
+            Vector3D::BACK,
+            Vector3D::RIGHT,
+            Vector3D::new(1.0, 2.0, -3.0),
+            Vector3D::new(-0.2, -1.0, 0.1),
+        ];
+        for direction in directions {
+            let q = Quaternion::look_rotation(direction, Vector3D::UP);
+            assert_vec_eq(q.forward(), direction.normalize());
+            // Up stays on the world-up side and perpendicular to forward
+            assert!(q.up().y > 0.0);
+            assert!(q.up().dot(&q.forward()).abs() < 0.001);
+        }
+
+        assert_same_rotation(
+            Quaternion::look_rotation(Vector3D::FORWARD, Vector3D::UP),
+            Quaternion::IDENTITY,
+        );
+        assert_eq!(
+            Quaternion::look_rotation(Vector3D::ZERO, Vector3D::UP),
+            Quaternion::IDENTITY
+        );
+    }
+
+    #[test]
+    fn test_look_rotation_parallel_to_up() {
+        let q = Quaternion::look_rotation(Vector3D::UP, Vector3D::UP);
+        assert_vec_eq(q.forward(), Vector3D::UP);
+        assert!((q.magnitude() - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_look_at_points() {
+        let q = Quaternion::look_at(
+            Point3D::new(1.0, 0.0, 0.0),
+            Point3D::new(1.0, 0.0, -5.0),
+            Vector3D::UP,
+        );
+        assert_vec_eq(q.forward(), Vector3D::BACK);
+        assert_vec_eq(q.up(), Vector3D::UP);
+    }
+
     #[test]
     fn test_inverse() {
         let q = Quaternion::from_axis_angle(Vector3D::UP, PI / 4.0);
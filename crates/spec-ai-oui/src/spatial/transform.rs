@@ -38,6 +38,24 @@ impl Transform {
         }
     }
 
+    /// Create a transform at `position` facing `target` with world up (+Y)
+    pub fn looking_at(position: Point3D, target: Point3D) -> Self {
+        Self::from_position_rotation(
+            position,
+            Quaternion::look_at(position, target, Vector3D::UP),
+        )
+    }
+
+    /// Get the rotation as Euler angles `(yaw, pitch, roll)` in radians
+    pub fn euler_angles(&self) -> (f32, f32, f32) {
+        self.rotation.to_euler()
+    }
+
+    /// Set the rotation from Euler angles in radians
+    pub fn set_euler_angles(&mut self, yaw: f32, pitch: f32, roll: f32) {
+        self.rotation = Quaternion::from_euler(yaw, pitch, roll);
+    }
+
     /// Get the forward direction of this transform
     pub fn forward(&self) -> Vector3D {
         self.rotation.forward()
@@ -53,18 +71,18 @@ impl Transform {
         self.rotation.up()
     }
 
-    /// Rotate the transform to look at a target point
+    /// Rotate the transform to look at a target point, keeping world up
     pub fn look_at(&mut self, target: Point3D) {
-        let direction = (target - self.position).normalize();
+        self.look_at_with_up(target, Vector3D::UP);
+    }
+
+    /// Rotate the transform to look at a target point with a custom up
+    pub fn look_at_with_up(&mut self, target: Point3D, up: Vector3D) {
+        let direction = target - self.position;
         if direction.magnitude_squared() < 0.0001 {
             return;
         }
-
-        // Calculate yaw and pitch from direction
-        let yaw = direction.x.atan2(direction.z);
-        let pitch = (-direction.y).asin();
-
-        self.rotation = Quaternion::from_euler(yaw, pitch, 0.0);
+        self.rotation = Quaternion::look_rotation(direction, up);
     }
 
     /// Transform a point from local space to world space
@@ -95,7 +113,7 @@ impl Transform {
         )
     }
 
-    /// Linearly interpolate between two transforms
+    /// Linearly interpolate between two transforms (rotation uses slerp)
     pub fn lerp(&self, other: &Transform, t: f32) -> Self {
         Self {
             position: self.position.lerp(&other.position, t),
@@ -154,6 +172,58 @@ mod tests {
         assert!((forward.z - 1.0).abs() < 0.0001);
     }
 
+    #[test]
+    fn test_look_at_off_axis() {
+        let mut t = Transform::from_position(Point3D::new(1.0, 1.0, 1.0));
+        t.look_at(Point3D::new(4.0, 5.0, 1.0));
+
+        let forward = t.forward();
+        assert!((forward.x - 0.6).abs() < 0.0001);
+        assert!((forward.y - 0.8).abs() < 0.0001);
+        assert!(forward.z.abs() < 0.0001);
+        // No roll: right stays horizontal
+        assert!(t.right().y.abs() < 0.0001);
+
+        // Looking at its own position leaves the rotation alone
+        let before = t.rotation;
+        t.look_at(t.position);
+        assert_eq!(t.rotation, before);
+    }
+
+    #[test]
+    fn test_looking_at_and_euler_angles() {
+        let t = Transform::looking_at(Point3D::ORIGIN, Point3D::new(-3.0, 0.0, 0.0));
+        let (yaw, pitch, roll) = t.euler_angles();
+        assert!((yaw + std::f32::consts::FRAC_PI_2).abs() < 0.0001);
+        assert!(pitch.abs() < 0.0001);
+        assert!(roll.abs() < 0.0001);
+
+        let mut t = Transform::identity();
+        t.set_euler_angles(0.3, -0.2, 0.1);
+        let (yaw, pitch, roll) = t.euler_angles();
+        assert!((yaw - 0.3).abs() < 0.0001);
+        assert!((pitch + 0.2).abs() < 0.0001);
+        assert!((roll - 0.1).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_lerp_rotation_endpoints() {
+        let a = Transform::looking_at(Point3D::ORIGIN, Point3D::new(0.0, 0.0, 1.0));
+        let b = Transform::looking_at(Point3D::new(2.0, 0.0, 0.0), Point3D::new(3.0, 0.0, 0.0));
+
+        let start = a.lerp(&b, 0.0);
+        assert!(start.rotation.angle_to(&a.rotation) < 0.001);
+        let end = a.lerp(&b, 1.0);
+        assert!(end.rotation.angle_to(&b.rotation) < 0.001);
+
+        let mid = a.lerp(&b, 0.5);
+        assert!((mid.position.x - 1.0).abs() < 0.0001);
+        let forward = mid.forward();
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((forward.x - diagonal).abs() < 0.001);
+        assert!((forward.z - diagonal).abs() < 0.001);
+    }
+
     #[test]
     fn test_inverse_transform() {
         let mut t = Transform::identity();
@@ -12,11 +12,16 @@ pub enum FadeState {
 }
 
 /// Fade transition effect
+///
+/// Tracks how much of the fade has elapsed as a duration, so it finishes at
+/// the same wall-clock time at any frame rate.
 #[derive(Debug, Clone)]
 pub struct FadeTransition {
     pub state: FadeState,
     pub progress: f32,
     pub duration: Duration,
+    /// Time into the fade (0 = hidden, `duration` = visible)
+    elapsed: Duration,
 }
 
 impl Default for FadeTransition {
@@ -25,6 +30,7 @@ impl Default for FadeTransition {
             state: FadeState::Hidden,
             progress: 0.0,
             duration: Duration::from_millis(300),
+            elapsed: Duration::ZERO,
         }
     }
 }
@@ -37,6 +43,36 @@ impl FadeTransition {
         }
     }
 
+    /// Create a fade at `alpha` (0 = hidden, 1 = visible)
+    ///
+    /// Partially visible fades resume fading in; call [`Self::fade_to`] to
+    /// pick a direction.
+    pub fn from_alpha(alpha: f32, duration: Duration) -> Self {
+        let progress = alpha.clamp(0.0, 1.0);
+        let state = if progress >= 1.0 {
+            FadeState::Visible
+        } else if progress <= 0.0 {
+            FadeState::Hidden
+        } else {
+            FadeState::FadingIn
+        };
+        Self {
+            state,
+            progress,
+            duration,
+            elapsed: duration.mul_f64(progress as f64),
+        }
+    }
+
+    /// Fade toward visible or hidden, unless already there
+    pub fn fade_to(&mut self, visible: bool) {
+        match (visible, self.state) {
+            (true, FadeState::Visible) | (false, FadeState::Hidden) => {}
+            (true, _) => self.fade_in(),
+            (false, _) => self.fade_out(),
+        }
+    }
+
     pub fn fade_in(&mut self) {
         self.state = FadeState::FadingIn;
     }
@@ -45,24 +81,33 @@ impl FadeTransition {
         self.state = FadeState::FadingOut;
     }
 
+    /// Advance the fade by the wall-clock time since the last update
     pub fn update(&mut self, dt: Duration) {
-        let delta = dt.as_secs_f32() / self.duration.as_secs_f32();
-
         match self.state {
             FadeState::FadingIn => {
-                self.progress = (self.progress + delta).min(1.0);
-                if self.progress >= 1.0 {
+                self.elapsed = (self.elapsed + dt).min(self.duration);
+                if self.elapsed >= self.duration {
                     self.state = FadeState::Visible;
                 }
             }
             FadeState::FadingOut => {
-                self.progress = (self.progress - delta).max(0.0);
-                if self.progress <= 0.0 {
+                self.elapsed = self.elapsed.saturating_sub(dt);
+                if self.elapsed.is_zero() {
                     self.state = FadeState::Hidden;
                 }
             }
-            _ => {}
+            _ => return,
         }
+
+        self.progress = if self.duration.is_zero() {
+            if self.state == FadeState::Visible {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+        };
     }
 
     pub fn alpha(&self) -> f32 {
@@ -73,3 +118,43 @@ impl FadeTransition {
         self.progress > 0.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::testing::run_at;
+
+    #[test]
+    fn test_fade_same_alpha_at_30_and_120_hz() {
+        let mut slow = FadeTransition::new(Duration::from_millis(300));
+        let mut fast = slow.clone();
+        slow.fade_in();
+        fast.fade_in();
+
+        run_at(30, Duration::from_millis(100), |dt| slow.update(dt));
+        run_at(120, Duration::from_millis(100), |dt| fast.update(dt));
+        assert!((slow.alpha() - fast.alpha()).abs() < 1e-3);
+
+        run_at(30, Duration::from_millis(200), |dt| slow.update(dt));
+        run_at(120, Duration::from_millis(200), |dt| fast.update(dt));
+        assert_eq!(slow.state, FadeState::Visible);
+        assert_eq!(fast.state, FadeState::Visible);
+    }
+
+    #[test]
+    fn test_fade_resumes_from_alpha() {
+        let mut fade = FadeTransition::from_alpha(0.5, Duration::from_millis(300));
+        fade.fade_to(false);
+        fade.update(Duration::from_millis(75));
+        assert!((fade.alpha() - 0.25).abs() < 1e-3);
+
+        fade.update(Duration::from_millis(100));
+        assert_eq!(fade.state, FadeState::Hidden);
+        assert_eq!(fade.alpha(), 0.0);
+
+        // Already at the target, so nothing changes
+        let mut visible = FadeTransition::from_alpha(1.0, Duration::from_millis(300));
+        visible.fade_to(true);
+        assert_eq!(visible.state, FadeState::Visible);
+    }
+}
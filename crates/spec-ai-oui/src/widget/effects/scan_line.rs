@@ -1,6 +1,10 @@
 //! Scan line effect (placeholder)
 
+use std::time::Duration;
+
 /// Scan line animation effect
+///
+/// `speed` is in screen heights per second.
 #[derive(Debug, Clone)]
 pub struct ScanLineEffect {
     pub speed: f32,
@@ -19,7 +23,8 @@ impl Default for ScanLineEffect {
 }
 
 impl ScanLineEffect {
-    pub fn update(&mut self, dt: f32) {
-        self.position = (self.position + self.speed * dt) % 1.0;
+    /// Advance the scan line by the wall-clock time since the last update
+    pub fn update(&mut self, dt: Duration) {
+        self.position = (self.position + self.speed * dt.as_secs_f32()).rem_euclid(1.0);
     }
 }
@@ -168,38 +168,41 @@ impl OpticalWidget for InfoCard {
             }
         }
 
+        // Colors fade with visibility during density transitions
+        let fade = |color: Color| color.faded(self.visibility);
+
         // Draw card border
-        backend.draw_hud_rect(x, y, self.width, height, self.border_color);
+        backend.draw_hud_rect(x, y, self.width, height, fade(self.border_color));
 
         // Draw title
-        backend.draw_hud_text(x + 0.01, y + 0.01, &self.title, Color::GOLD);
+        backend.draw_hud_text(x + 0.01, y + 0.01, &self.title, fade(Color::GOLD));
 
         if !self.expanded {
-            backend.draw_hud_text(x + self.width - 0.02, y + 0.01, "▶", Color::Grey);
+            backend.draw_hud_text(x + self.width - 0.02, y + 0.01, "▶", fade(Color::Grey));
             return;
         }
 
-        backend.draw_hud_text(x + self.width - 0.02, y + 0.01, "▼", Color::Grey);
+        backend.draw_hud_text(x + self.width - 0.02, y + 0.01, "▼", fade(Color::Grey));
 
         // Draw sections
         let mut current_y = y + 0.04;
         for section in &self.sections {
             // Section header
             if let Some(ref header) = section.header {
-                backend.draw_hud_text(x + 0.01, current_y, header, Color::HUD_CYAN);
+                backend.draw_hud_text(x + 0.01, current_y, header, fade(Color::HUD_CYAN));
                 current_y += 0.02;
             }
 
             // Section content
             match &section.content {
                 CardContent::Text(text) => {
-                    backend.draw_hud_text(x + 0.01, current_y, text, Color::White);
+                    backend.draw_hud_text(x + 0.01, current_y, text, fade(Color::White));
                     current_y += 0.03;
                 }
                 CardContent::KeyValue(pairs) => {
                     for (key, value) in pairs {
                         let line = format!("{}: {}", key, value);
-                        backend.draw_hud_text(x + 0.01, current_y, &line, Color::White);
+                        backend.draw_hud_text(x + 0.01, current_y, &line, fade(Color::White));
                         current_y += 0.025;
                     }
                 }
@@ -208,13 +211,13 @@ impl OpticalWidget for InfoCard {
                     let filled = (pct * 20.0) as usize;
                     let bar = "█".repeat(filled) + &"░".repeat(20 - filled);
                     let text = format!("{} [{}]", label, bar);
-                    backend.draw_hud_text(x + 0.01, current_y, &text, Color::STATUS_GREEN);
+                    backend.draw_hud_text(x + 0.01, current_y, &text, fade(Color::STATUS_GREEN));
                     current_y += 0.03;
                 }
                 CardContent::List(items) => {
                     for item in items {
                         let line = format!("• {}", item);
-                        backend.draw_hud_text(x + 0.01, current_y, &line, Color::White);
+                        backend.draw_hud_text(x + 0.01, current_y, &line, fade(Color::White));
                         current_y += 0.02;
                     }
                 }
@@ -98,4 +98,8 @@ impl OpticalWidget for Tooltip {
     fn priority(&self) -> Priority {
         Priority::Low
     }
+
+    fn update_visibility(&mut self, _dt: Duration, _ctx: &DisplayContext) {
+        // Shown and hidden explicitly with `show` and `hide`
+    }
 }
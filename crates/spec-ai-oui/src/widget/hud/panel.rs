@@ -113,20 +113,22 @@ impl OpticalWidget for HudPanel {
         }
 
         if let Some((x, y)) = self.anchor.screen_coords() {
+            let fade = |color: Color| color.faded(self.visibility);
+
             // Draw panel border
-            backend.draw_hud_rect(x, y, self.width, self.height, self.border_color);
+            backend.draw_hud_rect(x, y, self.width, self.height, fade(self.border_color));
 
             // Draw title if present
             let mut current_y = y + 0.01;
             if let Some(ref title) = self.title {
                 let title_x = x + 0.01;
-                backend.draw_hud_text(title_x, current_y, title, Color::GOLD);
+                backend.draw_hud_text(title_x, current_y, title, fade(Color::GOLD));
                 current_y += 0.03;
             }
 
             // Draw content lines
             for (line, color) in &self.content_lines {
-                backend.draw_hud_text(x + 0.01, current_y, line, *color);
+                backend.draw_hud_text(x + 0.01, current_y, line, fade(*color));
                 current_y += 0.025;
             }
         }
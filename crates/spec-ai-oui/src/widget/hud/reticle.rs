@@ -23,6 +23,9 @@ pub enum ReticleStyle {
     Dot,
 }
 
+/// How long each frame of the scanner animation is shown
+const SCANNER_FRAME: Duration = Duration::from_millis(250);
+
 /// Target lock information
 #[derive(Debug, Clone)]
 pub struct TargetLock {
@@ -45,7 +48,8 @@ pub struct Reticle {
     locked_color: Color,
     target: Option<TargetLock>,
     visibility: f32,
-    animation_tick: u64,
+    /// Wall-clock time the reticle has been animating
+    animation_time: Duration,
 }
 
 impl Reticle {
@@ -59,7 +63,7 @@ impl Reticle {
             locked_color: Color::ALERT_RED,
             target: None,
             visibility: 1.0,
-            animation_tick: 0,
+            animation_time: Duration::ZERO,
         }
     }
 
@@ -86,6 +90,13 @@ impl Reticle {
         self.target = target;
     }
 
+    /// Current frame of the scanner animation
+    fn scanner_symbol(&self) -> &'static str {
+        const SYMBOLS: [&str; 4] = ["◴", "◷", "◶", "◵"];
+        let frame = self.animation_time.as_millis() / SCANNER_FRAME.as_millis();
+        SYMBOLS[(frame % SYMBOLS.len() as u128) as usize]
+    }
+
     /// Update lock progress
     pub fn update_lock_progress(&mut self, progress: f32) {
         if let Some(ref mut target) = self.target {
@@ -108,8 +119,8 @@ impl OpticalWidget for Reticle {
         &self.anchor
     }
 
-    fn update(&mut self, _dt: Duration, _ctx: &DisplayContext) {
-        self.animation_tick = self.animation_tick.wrapping_add(1);
+    fn update(&mut self, dt: Duration, _ctx: &DisplayContext) {
+        self.animation_time += dt;
     }
 
     fn handle_event(&mut self, _event: &OpticalEvent) -> bool {
@@ -148,9 +159,7 @@ impl OpticalWidget for Reticle {
             }
             ReticleStyle::Scanner => {
                 // Animated scanning effect
-                let frame = (self.animation_tick / 10) % 4;
-                let symbols = ["◴", "◷", "◶", "◵"];
-                backend.draw_hud_text(x, y, symbols[frame as usize], color);
+                backend.draw_hud_text(x, y, self.scanner_symbol(), color);
             }
             ReticleStyle::Dot => {
                 backend.draw_hud_text(x, y, "●", color);
@@ -186,3 +195,21 @@ impl OpticalWidget for Reticle {
         Priority::High
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::testing::run_at;
+
+    #[test]
+    fn test_scanner_frame_independent_of_frame_rate() {
+        let ctx = DisplayContext::default();
+        let mut slow = Reticle::new("slow").style(ReticleStyle::Scanner);
+        let mut fast = Reticle::new("fast").style(ReticleStyle::Scanner);
+
+        run_at(30, Duration::from_millis(600), |dt| slow.update(dt, &ctx));
+        run_at(120, Duration::from_millis(600), |dt| fast.update(dt, &ctx));
+        assert_eq!(slow.scanner_symbol(), fast.scanner_symbol());
+        assert_eq!(fast.scanner_symbol(), "◶");
+    }
+}
@@ -12,4 +12,4 @@ pub mod floating;
 pub mod hud;
 mod traits;
 
-pub use traits::{OpticalWidget, StatefulOpticalWidget};
+pub use traits::{OpticalWidget, StatefulOpticalWidget, DENSITY_FADE_DURATION};
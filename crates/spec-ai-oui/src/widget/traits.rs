@@ -6,6 +6,10 @@ use crate::context::DisplayContext;
 use crate::input::OpticalEvent;
 use crate::renderer::RenderBackend;
 use crate::spatial::{Bounds, SpatialAnchor, Transform};
+use crate::widget::effects::FadeTransition;
+
+/// How long a widget takes to fade when density hides or reveals it
+pub const DENSITY_FADE_DURATION: Duration = Duration::from_millis(300);
 
 /// Core trait for optical widgets
 pub trait OpticalWidget: Send + Sync {
@@ -47,6 +51,33 @@ pub trait OpticalWidget: Send + Sync {
     fn is_enabled(&self) -> bool {
         true
     }
+
+    /// How long density changes take to fade the widget in or out
+    ///
+    /// Return `None` to show and hide the widget immediately instead.
+    fn density_fade(&self) -> Option<Duration> {
+        Some(DENSITY_FADE_DURATION)
+    }
+
+    /// Move visibility toward what the widget's priority allows at the
+    /// context's information density
+    fn update_visibility(&mut self, dt: Duration, ctx: &DisplayContext) {
+        let visible = self.priority().is_visible_at(ctx.density);
+        let Some(duration) = self.density_fade() else {
+            self.set_visibility(if visible { 1.0 } else { 0.0 });
+            return;
+        };
+        let mut fade = FadeTransition::from_alpha(self.visibility(), duration);
+        fade.fade_to(visible);
+        fade.update(dt);
+        self.set_visibility(fade.alpha());
+    }
+
+    /// Advance the widget one frame: fade for the current density, then update
+    fn tick(&mut self, dt: Duration, ctx: &DisplayContext) {
+        self.update_visibility(dt, ctx);
+        self.update(dt, ctx);
+    }
 }
 
 /// Stateful optical widget with external state
@@ -77,3 +108,85 @@ pub trait StatefulOpticalWidget: Send + Sync {
     /// Set visibility
     fn set_visibility(&self, state: &mut Self::State, visibility: f32);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{InformationDensity, Priority};
+    use crate::widget::hud::HudPanel;
+
+    /// Panel that pops in and out instead of fading
+    struct InstantPanel(HudPanel);
+
+    impl OpticalWidget for InstantPanel {
+        fn id(&self) -> &str {
+            self.0.id()
+        }
+        fn bounds(&self) -> Bounds {
+            self.0.bounds()
+        }
+        fn anchor(&self) -> &SpatialAnchor {
+            self.0.anchor()
+        }
+        fn update(&mut self, dt: Duration, ctx: &DisplayContext) {
+            self.0.update(dt, ctx)
+        }
+        fn handle_event(&mut self, event: &OpticalEvent) -> bool {
+            self.0.handle_event(event)
+        }
+        fn render(&self, backend: &mut dyn RenderBackend, camera: &Transform) {
+            self.0.render(backend, camera)
+        }
+        fn visibility(&self) -> f32 {
+            self.0.visibility()
+        }
+        fn set_visibility(&mut self, visibility: f32) {
+            self.0.set_visibility(visibility)
+        }
+        fn priority(&self) -> Priority {
+            OpticalWidget::priority(&self.0)
+        }
+        fn density_fade(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    fn context(density: InformationDensity) -> DisplayContext {
+        DisplayContext {
+            density,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_density_change_fades_widget() {
+        let mut panel = HudPanel::new("stats").priority(Priority::Low);
+        let step = DENSITY_FADE_DURATION / 2;
+
+        // Low priority is hidden at normal density
+        panel.tick(step, &context(InformationDensity::Normal));
+        assert!((panel.visibility() - 0.5).abs() < 1e-3);
+        panel.tick(step, &context(InformationDensity::Normal));
+        assert_eq!(panel.visibility(), 0.0);
+
+        panel.tick(step, &context(InformationDensity::High));
+        assert!((panel.visibility() - 0.5).abs() < 1e-3);
+        panel.tick(step, &context(InformationDensity::High));
+        assert_eq!(panel.visibility(), 1.0);
+    }
+
+    #[test]
+    fn test_density_fade_opt_out() {
+        let mut panel = InstantPanel(HudPanel::new("stats").priority(Priority::Low));
+        panel.tick(
+            Duration::from_millis(16),
+            &context(InformationDensity::Normal),
+        );
+        assert_eq!(panel.visibility(), 0.0);
+        panel.tick(
+            Duration::from_millis(16),
+            &context(InformationDensity::Maximum),
+        );
+        assert_eq!(panel.visibility(), 1.0);
+    }
+}
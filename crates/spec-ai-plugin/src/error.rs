@@ -37,4 +37,54 @@ pub enum PluginError {
     /// Plugin execution failed
     #[error("Plugin execution failed: {0}")]
     ExecutionFailed(String),
+
+    /// Plugin name cannot be used as a crate name
+    #[error(
+        "Invalid plugin name '{0}': use ASCII letters, digits, '-' or '_', starting with a letter"
+    )]
+    InvalidName(String),
+
+    /// Plugin kind has no loadable ABI
+    #[error("Plugin kind '{0}' is not supported yet: the host can only load tool plugins")]
+    UnsupportedKind(String),
+
+    /// Scaffolding target already exists
+    #[error("Refusing to overwrite existing path: {0}")]
+    AlreadyExists(PathBuf),
+
+    /// Plugin manifest or index is malformed
+    #[error("Invalid plugin manifest: {0}")]
+    InvalidManifest(String),
+
+    /// No plugin of that name (and version) in the index
+    #[error("Plugin '{0}' not found in the index")]
+    NotFound(String),
+
+    /// Plugin has no artifact the host can load
+    #[error("Plugin '{name}' has no artifact for {target}")]
+    NoArtifact { name: String, target: String },
+
+    /// Downloaded artifact does not match the manifest checksum
+    #[error("Checksum mismatch for plugin '{name}': expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// Artifact signature is missing or no trusted key verifies it
+    #[error("Signature check failed for plugin '{name}': {message}")]
+    SignatureInvalid { name: String, message: String },
+
+    /// Trusted key is neither a base64 Ed25519 key nor a minisign public key
+    #[error("Invalid trusted key '{0}': expected a base64 Ed25519 or minisign public key")]
+    InvalidKey(String),
+
+    /// Filesystem error while writing plugin files
+    #[error("Failed to write {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
 }
@@ -60,14 +60,22 @@
 //!
 //! # For Host Applications
 //!
-//! Use the [`loader::PluginLoader`] to discover and load plugins from a directory:
+//! Use the [`loader::PluginLoader`] to discover and load plugins from a directory.
+//! Every library is checked against a [`trust::TrustStore`] before it is opened,
+//! and `allow` decides what happens to libraries that are not verified:
 //!
 //! ```rust,ignore
 //! use spec_ai_plugin::loader::{PluginLoader, expand_tilde};
+//! use spec_ai_plugin::trust::TrustStore;
 //! use std::path::Path;
 //!
+//! let trust = TrustStore::new(&["<base64 Ed25519 or minisign public key>".to_string()])?;
 //! let mut loader = PluginLoader::new();
-//! let stats = loader.load_directory(&expand_tilde(Path::new("~/.spec-ai/tools")))?;
+//! let stats = loader.load_directory_trusted(
+//!     &expand_tilde(Path::new("~/.spec-ai/tools")),
+//!     &trust,
+//!     |_path, trust| trust.is_verified(),
+//! )?;
 //!
 //! println!("Loaded {} plugins with {} tools", stats.loaded, stats.tools_loaded);
 //!
@@ -80,6 +88,9 @@
 pub mod abi;
 pub mod error;
 pub mod loader;
+pub mod manifest;
+pub mod scaffold;
+pub mod trust;
 
 // Re-export commonly used types
 pub use abi::{
@@ -87,4 +98,9 @@ pub use abi::{
     PLUGIN_API_VERSION,
 };
 pub use error::PluginError;
-pub use loader::{expand_tilde, LoadStats, LoadedPlugin, PluginLoader};
+pub use loader::{expand_tilde, shutdown_plugins, LoadStats, LoadedPlugin, PluginLoader};
+pub use manifest::{
+    host_target, InstalledPlugin, PluginArtifact, PluginIndex, PluginInstaller, PluginManifest,
+};
+pub use scaffold::{render_plugin, scaffold_plugin, PluginKind, ScaffoldFile};
+pub use trust::{Trust, TrustStore};
@@ -2,11 +2,20 @@
 
 use crate::abi::{PluginModuleRef, PluginToolRef, PLUGIN_API_VERSION};
 use crate::error::PluginError;
+use crate::trust::{Trust, TrustStore};
 use abi_stable::library::RootModule;
 use anyhow::Result;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
+/// Shutdown hooks of loaded plugins, keyed by library path
+///
+/// Plugin libraries stay loaded for the life of the process, so their
+/// hooks are tracked globally rather than per [`PluginLoader`].
+static SHUTDOWN_HOOKS: Mutex<Vec<(PathBuf, String, extern "C" fn())>> = Mutex::new(Vec::new());
+
 /// Statistics from loading plugins
 #[derive(Debug, Default, Clone)]
 pub struct LoadStats {
@@ -16,6 +25,8 @@ pub struct LoadStats {
     pub loaded: usize,
     /// Failed to load plugins
     pub failed: usize,
+    /// Plugins refused by the trust check, never opened
+    pub rejected: usize,
     /// Total tools loaded across all plugins
     pub tools_loaded: usize,
 }
@@ -53,17 +64,23 @@ impl PluginLoader {
         }
     }
 
-    /// Load all plugins from a directory
-    ///
-    /// Scans the directory for dynamic library files (.dylib on macOS, .so on Linux,
-    /// .dll on Windows) and attempts to load each one as a plugin.
+    /// Load the plugins in a directory that pass a trust check
     ///
-    /// # Arguments
-    /// * `dir` - Directory to scan for plugins
+    /// Scans the directory for dynamic library files (.dylib on macOS, .so on
+    /// Linux, .dll on Windows). Every library is checked against `trust` before
+    /// it is opened, since opening it runs its code. `allow` is given the
+    /// outcome and decides whether the library loads; refused libraries count
+    /// as rejected.
     ///
-    /// # Returns
-    /// Statistics about the loading process
-    pub fn load_directory(&mut self, dir: &Path) -> Result<LoadStats> {
+    /// Each library is read once: the bytes that were checked are copied to a
+    /// private temporary file and opened from there, so replacing the file in
+    /// `dir` after the check has no effect.
+    pub fn load_directory_trusted(
+        &mut self,
+        dir: &Path,
+        trust: &TrustStore,
+        mut allow: impl FnMut(&Path, &Trust) -> bool,
+    ) -> Result<LoadStats> {
         let mut stats = LoadStats::default();
 
         if !dir.exists() {
@@ -90,7 +107,21 @@ impl PluginLoader {
 
             stats.total += 1;
 
-            match self.load_plugin(path) {
+            let bytes = match fs::read(path) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    stats.failed += 1;
+                    error!("Failed to read plugin {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            if !allow(path, &trust.check_bytes(path, &bytes)) {
+                stats.rejected += 1;
+                continue;
+            }
+
+            match self.load_plugin(path, &bytes) {
                 Ok(tool_count) => {
                     stats.loaded += 1;
                     stats.tools_loaded += tool_count;
@@ -106,13 +137,28 @@ impl PluginLoader {
         Ok(stats)
     }
 
-    /// Load a single plugin from a file
-    fn load_plugin(&mut self, path: &Path) -> Result<usize> {
+    /// Load a single plugin from the checked `bytes` of the library at `path`
+    fn load_plugin(&mut self, path: &Path, bytes: &[u8]) -> Result<usize> {
         debug!("Loading plugin from: {}", path.display());
 
+        // Only this process can write to the copy. It is removed again once
+        // loaded, where the platform allows removing an open library.
+        let copy = tempfile::Builder::new()
+            .prefix("spec-ai-plugin-")
+            .tempdir()
+            .map_err(|source| PluginError::Io {
+                path: std::env::temp_dir(),
+                source,
+            })?;
+        let library = copy.path().join(path.file_name().unwrap_or_default());
+        fs::write(&library, bytes).map_err(|source| PluginError::Io {
+            path: library.clone(),
+            source,
+        })?;
+
         // Load the root module using abi_stable
         let module =
-            PluginModuleRef::load_from_file(path).map_err(|e| PluginError::LoadFailed {
+            PluginModuleRef::load_from_file(&library).map_err(|e| PluginError::LoadFailed {
                 path: path.to_path_buf(),
                 message: e.to_string(),
             })?;
@@ -156,6 +202,14 @@ impl PluginLoader {
             }
         }
 
+        if let Some(shutdown) = module.shutdown() {
+            let mut hooks = SHUTDOWN_HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+            // The same library may be loaded again when an agent is rebuilt
+            if !hooks.iter().any(|(hook_path, _, _)| hook_path == path) {
+                hooks.push((path.to_path_buf(), plugin_name.clone(), shutdown));
+            }
+        }
+
         self.plugins.push(LoadedPlugin {
             path: path.to_path_buf(),
             name: plugin_name,
@@ -219,6 +273,21 @@ impl Default for PluginLoader {
     }
 }
 
+/// Call the shutdown hook of every plugin loaded so far, once each
+///
+/// Returns the names of the plugins whose hooks ran.
+pub fn shutdown_plugins() -> Vec<String> {
+    let hooks = std::mem::take(&mut *SHUTDOWN_HOOKS.lock().unwrap_or_else(|e| e.into_inner()));
+    hooks
+        .into_iter()
+        .map(|(_, name, shutdown)| {
+            debug!("Shutting down plugin '{}'", name);
+            shutdown();
+            name
+        })
+        .collect()
+}
+
 /// Expand tilde (~) in paths to the home directory
 pub fn expand_tilde(path: &Path) -> PathBuf {
     if let Ok(path_str) = path.to_str().ok_or(()) {
@@ -268,6 +337,37 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn test_trusted_loading_checks_before_opening() {
+        let dir = std::env::temp_dir().join(format!("spec-ai-loader-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        #[cfg(target_os = "macos")]
+        let name = "libfake.dylib";
+        #[cfg(target_os = "windows")]
+        let name = "fake.dll";
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        let name = "libfake.so";
+        fs::write(dir.join(name), b"not really a shared library").unwrap();
+
+        let mut loader = PluginLoader::new();
+        let mut seen = Vec::new();
+        let stats = loader
+            .load_directory_trusted(&dir, &TrustStore::default(), |_, trust| {
+                seen.push(trust.clone());
+                trust.is_verified()
+            })
+            .unwrap();
+        assert_eq!(seen, vec![Trust::Unsigned]);
+        assert_eq!((stats.total, stats.rejected, stats.loaded), (1, 1, 0));
+
+        // Admitted libraries are opened from a copy; this one is not a library
+        let stats = loader
+            .load_directory_trusted(&dir, &TrustStore::default(), |_, _| true)
+            .unwrap();
+        assert_eq!((stats.failed, stats.loaded), (1, 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_expand_tilde() {
         let home = dirs_home().unwrap_or_else(|| PathBuf::from("/home/user"));
@@ -286,6 +386,7 @@ mod tests {
         assert_eq!(stats.total, 0);
         assert_eq!(stats.loaded, 0);
         assert_eq!(stats.failed, 0);
+        assert_eq!(stats.rejected, 0);
         assert_eq!(stats.tools_loaded, 0);
     }
 }
@@ -0,0 +1,463 @@
+//! Plugin manifests, indexes and installation
+//!
+//! A manifest describes one published version of a plugin: its name, the
+//! plugin API it targets, the capabilities it needs, and one artifact per
+//! platform with a SHA-256 checksum and an optional Ed25519 signature. An
+//! index is a list of manifests. [`PluginInstaller`] verifies a downloaded
+//! artifact against its manifest and writes it into the plugin directory,
+//! next to a `<name>.plugin.json` record of what was installed.
+
+use crate::abi::PLUGIN_API_VERSION;
+use crate::error::PluginError;
+use crate::scaffold::validate_name;
+use crate::trust::{sha256_hex, TrustStore};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Artifact target of WebAssembly modules, which no host can load yet
+pub const WASM_TARGET: &str = "wasm32";
+
+/// One published version of a plugin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginManifest {
+    /// Plugin name, also used for the installed file names
+    pub name: String,
+    /// Version of this release, e.g. "1.2.0"
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Plugin ABI version the artifacts were built against
+    pub api_version: u32,
+    /// What the plugin needs from the host, e.g. "network" or "filesystem"
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Builds of this version, one per target
+    pub artifacts: Vec<PluginArtifact>,
+}
+
+/// A build of a plugin for one target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginArtifact {
+    /// `<os>-<arch>` as in "linux-x86_64" or "macos-aarch64", or "wasm32"
+    pub target: String,
+    /// Download URL, relative to the manifest or index when it has no scheme
+    pub url: String,
+    /// Hex SHA-256 of the artifact
+    pub sha256: String,
+    /// Base64 Ed25519 signature of the artifact bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// A list of published plugins, as served by a marketplace
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginIndex {
+    pub plugins: Vec<PluginManifest>,
+}
+
+/// Record of an installed plugin, kept as `<name>.plugin.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledPlugin {
+    pub manifest: PluginManifest,
+    /// Target of the installed artifact
+    pub target: String,
+    /// Path of the installed library
+    pub path: PathBuf,
+    /// Whether a trusted key verified the artifact
+    pub signed: bool,
+}
+
+/// Target name of the running host, as used in [`PluginArtifact::target`]
+pub fn host_target() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Order dotted versions numerically, falling back to text for other parts
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut left = a.split(['.', '-', '+']);
+    let mut right = b.split(['.', '-', '+']);
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(l), Some(r)) => match (l.parse::<u64>(), r.parse::<u64>()) {
+                (Ok(l), Ok(r)) => l.cmp(&r),
+                _ => l.cmp(r),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> PluginError {
+    PluginError::InvalidManifest(message.into())
+}
+
+impl PluginManifest {
+    /// Parse and validate a manifest from JSON
+    pub fn from_json(bytes: &[u8]) -> Result<Self, PluginError> {
+        let manifest: Self = serde_json::from_slice(bytes).map_err(|e| invalid(e.to_string()))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Check the name, version and every artifact's checksum and URL
+    pub fn validate(&self) -> Result<(), PluginError> {
+        validate_name(&self.name)?;
+        if self.version.trim().is_empty() {
+            return Err(invalid(format!("'{}' has no version", self.name)));
+        }
+        if self.artifacts.is_empty() {
+            return Err(invalid(format!("'{}' has no artifacts", self.name)));
+        }
+        for artifact in &self.artifacts {
+            let checksum = &artifact.sha256;
+            if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid(format!(
+                    "'{}' artifact for {} has an invalid sha256",
+                    self.name, artifact.target
+                )));
+            }
+            if artifact.url.trim().is_empty() {
+                return Err(invalid(format!(
+                    "'{}' artifact for {} has no url",
+                    self.name, artifact.target
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The artifact the host can load: the one built for [`host_target`]
+    pub fn artifact_for_host(&self) -> Result<&PluginArtifact, PluginError> {
+        if self.api_version != PLUGIN_API_VERSION {
+            return Err(PluginError::VersionMismatch {
+                expected: PLUGIN_API_VERSION,
+                found: self.api_version,
+                path: PathBuf::from(&self.name),
+            });
+        }
+        let target = host_target();
+        if let Some(artifact) = self.artifacts.iter().find(|a| a.target == target) {
+            return Ok(artifact);
+        }
+        if self.artifacts.iter().any(|a| a.target == WASM_TARGET) {
+            return Err(PluginError::UnsupportedKind("wasm".to_string()));
+        }
+        Err(PluginError::NoArtifact {
+            name: self.name.clone(),
+            target,
+        })
+    }
+}
+
+impl PluginIndex {
+    /// Parse and validate an index from JSON
+    pub fn from_json(bytes: &[u8]) -> Result<Self, PluginError> {
+        let index: Self = serde_json::from_slice(bytes).map_err(|e| invalid(e.to_string()))?;
+        for manifest in &index.plugins {
+            manifest.validate()?;
+        }
+        Ok(index)
+    }
+
+    /// The named plugin at `version`, or its newest version
+    pub fn find(&self, name: &str, version: Option<&str>) -> Result<&PluginManifest, PluginError> {
+        self.plugins
+            .iter()
+            .filter(|manifest| manifest.name == name)
+            .filter(|manifest| version.is_none_or(|version| manifest.version == version))
+            .max_by(|a, b| compare_versions(&a.version, &b.version))
+            .ok_or_else(|| {
+                PluginError::NotFound(match version {
+                    Some(version) => format!("{}@{}", name, version),
+                    None => name.to_string(),
+                })
+            })
+    }
+}
+
+/// Verifies plugin artifacts and installs them into a plugin directory
+#[derive(Debug, Clone)]
+pub struct PluginInstaller {
+    dir: PathBuf,
+    trusted_keys: Vec<String>,
+    require_signatures: bool,
+}
+
+impl PluginInstaller {
+    /// Install into `dir`, requiring signed artifacts
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            trusted_keys: Vec::new(),
+            require_signatures: true,
+        }
+    }
+
+    /// Base64 Ed25519 or minisign public keys whose signatures are accepted
+    pub fn trusted_keys(mut self, keys: Vec<String>) -> Self {
+        self.trusted_keys = keys;
+        self
+    }
+
+    /// Refuse unsigned artifacts (default: true)
+    pub fn require_signatures(mut self, require: bool) -> Self {
+        self.require_signatures = require;
+        self
+    }
+
+    /// Directory plugins are installed into
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The record of an installed plugin, if there is one
+    pub fn installed(&self, name: &str) -> Option<InstalledPlugin> {
+        let record = fs::read(self.record_path(name)).ok()?;
+        serde_json::from_slice(&record).ok()
+    }
+
+    /// Check `bytes` against the artifact's checksum and signature
+    ///
+    /// Returns whether a trusted key verified the signature. An artifact
+    /// without a signature passes only when signatures are not required; a
+    /// signature no trusted key verifies never does.
+    pub fn verify(
+        &self,
+        manifest: &PluginManifest,
+        artifact: &PluginArtifact,
+        bytes: &[u8],
+    ) -> Result<bool, PluginError> {
+        let actual = sha256_hex(bytes);
+        if !actual.eq_ignore_ascii_case(&artifact.sha256) {
+            return Err(PluginError::ChecksumMismatch {
+                name: manifest.name.clone(),
+                expected: artifact.sha256.to_lowercase(),
+                actual,
+            });
+        }
+
+        let signature_error = |message: &str| PluginError::SignatureInvalid {
+            name: manifest.name.clone(),
+            message: message.to_string(),
+        };
+        let Some(signature) = &artifact.signature else {
+            return if self.require_signatures {
+                Err(signature_error("the artifact is not signed"))
+            } else {
+                Ok(false)
+            };
+        };
+        let signature = STANDARD
+            .decode(signature.trim())
+            .map_err(|_| signature_error("the signature is not base64"))?;
+        let trust = TrustStore::new(&self.trusted_keys)?;
+        if trust.is_empty() {
+            return Err(signature_error(
+                "no trusted keys are configured under [plugins] trusted_keys",
+            ));
+        }
+        match trust.verify(bytes, &signature) {
+            Some(_) => Ok(true),
+            None => Err(signature_error("no trusted key verifies the signature")),
+        }
+    }
+
+    /// Verify `bytes` and write them into the plugin directory
+    ///
+    /// The library is written under a temporary name and renamed into place,
+    /// so a failed install never leaves a partial library for the loader.
+    pub fn install(
+        &self,
+        manifest: &PluginManifest,
+        artifact: &PluginArtifact,
+        bytes: &[u8],
+    ) -> Result<InstalledPlugin, PluginError> {
+        manifest.validate()?;
+        let signed = self.verify(manifest, artifact, bytes)?;
+
+        fs::create_dir_all(&self.dir).map_err(|source| PluginError::Io {
+            path: self.dir.clone(),
+            source,
+        })?;
+        let path = self.dir.join(format!(
+            "{}{}{}",
+            std::env::consts::DLL_PREFIX,
+            manifest.name.replace('-', "_"),
+            std::env::consts::DLL_SUFFIX
+        ));
+        let partial = self.dir.join(format!(".{}.partial", manifest.name));
+        fs::write(&partial, bytes).map_err(|source| PluginError::Io {
+            path: partial.clone(),
+            source,
+        })?;
+        fs::rename(&partial, &path).map_err(|source| PluginError::Io {
+            path: path.clone(),
+            source,
+        })?;
+
+        let installed = InstalledPlugin {
+            manifest: manifest.clone(),
+            target: artifact.target.clone(),
+            path,
+            signed,
+        };
+        let record_path = self.record_path(&manifest.name);
+        let record = serde_json::to_vec_pretty(&installed).map_err(|e| invalid(e.to_string()))?;
+        fs::write(&record_path, record).map_err(|source| PluginError::Io {
+            path: record_path,
+            source,
+        })?;
+        Ok(installed)
+    }
+
+    fn record_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.plugin.json", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const LIBRARY: &[u8] = b"not really a shared library";
+
+    fn manifest(version: &str, target: &str) -> PluginManifest {
+        PluginManifest {
+            name: "weather".to_string(),
+            version: version.to_string(),
+            description: None,
+            api_version: PLUGIN_API_VERSION,
+            capabilities: vec!["network".to_string()],
+            artifacts: vec![PluginArtifact {
+                target: target.to_string(),
+                url: "libweather.so".to_string(),
+                sha256: sha256_hex(LIBRARY),
+                signature: None,
+            }],
+        }
+    }
+
+    fn key_pair() -> (Ed25519KeyPair, String) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public = STANDARD.encode(pair.public_key().as_ref());
+        (pair, public)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("spec-ai-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_index_finds_newest_or_exact_version() {
+        let index = PluginIndex {
+            plugins: vec![
+                manifest("1.9.0", "linux-x86_64"),
+                manifest("1.10.0", "linux-x86_64"),
+                manifest("1.2.0", "linux-x86_64"),
+            ],
+        };
+        let json = serde_json::to_vec(&index).unwrap();
+        let index = PluginIndex::from_json(&json).unwrap();
+
+        assert_eq!(index.find("weather", None).unwrap().version, "1.10.0");
+        assert_eq!(
+            index.find("weather", Some("1.2.0")).unwrap().version,
+            "1.2.0"
+        );
+        assert!(matches!(
+            index.find("weather", Some("2.0.0")),
+            Err(PluginError::NotFound(name)) if name == "weather@2.0.0"
+        ));
+    }
+
+    #[test]
+    fn test_manifest_validation_and_artifact_selection() {
+        let mut bad = manifest("1.0.0", &host_target());
+        bad.artifacts[0].sha256 = "abc".to_string();
+        assert!(matches!(
+            PluginManifest::from_json(&serde_json::to_vec(&bad).unwrap()),
+            Err(PluginError::InvalidManifest(_))
+        ));
+
+        let native = manifest("1.0.0", &host_target());
+        assert_eq!(native.artifact_for_host().unwrap().target, host_target());
+
+        let wasm = manifest("1.0.0", WASM_TARGET);
+        assert!(matches!(
+            wasm.artifact_for_host(),
+            Err(PluginError::UnsupportedKind(_))
+        ));
+
+        let mut old = native.clone();
+        old.api_version = PLUGIN_API_VERSION + 1;
+        assert!(matches!(
+            old.artifact_for_host(),
+            Err(PluginError::VersionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_checks_checksum_and_signature() {
+        let (pair, public) = key_pair();
+        let (_, stranger) = key_pair();
+        let mut signed = manifest("1.0.0", &host_target());
+        signed.artifacts[0].signature = Some(STANDARD.encode(pair.sign(LIBRARY).as_ref()));
+        let artifact = &signed.artifacts[0];
+
+        let installer = PluginInstaller::new(temp_dir("verify")).trusted_keys(vec![public]);
+        assert!(installer.verify(&signed, artifact, LIBRARY).unwrap());
+        assert!(matches!(
+            installer.verify(&signed, artifact, b"tampered"),
+            Err(PluginError::ChecksumMismatch { .. })
+        ));
+
+        let untrusted = PluginInstaller::new(temp_dir("verify")).trusted_keys(vec![stranger]);
+        assert!(matches!(
+            untrusted.verify(&signed, artifact, LIBRARY),
+            Err(PluginError::SignatureInvalid { .. })
+        ));
+
+        let unsigned = manifest("1.0.0", &host_target());
+        assert!(matches!(
+            installer.verify(&unsigned, &unsigned.artifacts[0], LIBRARY),
+            Err(PluginError::SignatureInvalid { .. })
+        ));
+        let lenient = installer.clone().require_signatures(false);
+        assert!(!lenient
+            .verify(&unsigned, &unsigned.artifacts[0], LIBRARY)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_install_writes_library_and_record() {
+        let dir = temp_dir("install");
+        let installer = PluginInstaller::new(&dir).require_signatures(false);
+        let manifest = manifest("1.0.0", &host_target());
+
+        let installed = installer
+            .install(&manifest, &manifest.artifacts[0], LIBRARY)
+            .unwrap();
+        assert_eq!(fs::read(&installed.path).unwrap(), LIBRARY);
+        assert!(installed
+            .path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .ends_with(std::env::consts::DLL_SUFFIX));
+        assert!(!installed.signed);
+        assert_eq!(installer.installed("weather"), Some(installed));
+        assert!(installer.installed("other").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,351 @@
+//! Project scaffolding for new plugins
+//!
+//! Generates a crate skeleton that builds against the current plugin ABI:
+//! a `cdylib` manifest, tool metadata, the exported root module, and unit tests.
+
+use crate::abi::PLUGIN_API_VERSION;
+use crate::error::PluginError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Kind of plugin to scaffold
+///
+/// Only kinds the host can load have templates; providers and WebAssembly
+/// modules have no plugin ABI yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginKind {
+    /// Custom tool loaded from a dynamic library
+    Tool,
+}
+
+impl PluginKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluginKind::Tool => "tool",
+        }
+    }
+}
+
+/// A file produced by the scaffolder, relative to the crate root
+#[derive(Debug, Clone)]
+pub struct ScaffoldFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+const CARGO_TOML_TEMPLATE: &str = r#"[package]
+name = "{{name}}"
+version = "0.1.0"
+edition = "2021"
+description = "spec-ai tool plugin"
+
+[lib]
+crate-type = ["cdylib"]
+
+[dependencies]
+abi_stable = "0.11"
+serde = { version = "1.0", features = ["derive"] }
+serde_json = "1.0"
+spec-ai-plugin = { version = "{{host_version}}", features = ["plugin-api"] }
+"#;
+
+const LIB_RS_TEMPLATE: &str = r##"//! {{name}}: a custom tool plugin for spec-ai
+//!
+//! Build with `cargo build --release` and copy the library from
+//! `target/release/` into your plugins directory (e.g. `~/.spec-ai/tools`).
+
+use abi_stable::std_types::{RStr, RString, RVec};
+use serde::Deserialize;
+use spec_ai_plugin::{
+    PluginModule, PluginModuleRef, PluginTool, PluginToolInfo, PluginToolRef, PluginToolResult,
+    PLUGIN_API_VERSION,
+};
+
+/// Arguments accepted by the tool
+#[derive(Debug, Deserialize)]
+struct Args {
+    /// Text to process
+    input: String,
+}
+
+/// Tool metadata: name, description and JSON Schema for the arguments
+extern "C" fn tool_info() -> PluginToolInfo {
+    PluginToolInfo::new(
+        "{{tool_name}}",
+        "Describe what {{tool_name}} does",
+        r#"{
+            "type": "object",
+            "properties": {
+                "input": {
+                    "type": "string",
+                    "description": "Text to process"
+                }
+            },
+            "required": ["input"]
+        }"#,
+    )
+}
+
+/// Execute the tool with JSON-encoded arguments
+extern "C" fn tool_execute(args_json: RStr<'_>) -> PluginToolResult {
+    let args: Args = match serde_json::from_str(args_json.as_str()) {
+        Ok(args) => args,
+        Err(e) => return PluginToolResult::failure(format!("Invalid arguments: {}", e)),
+    };
+
+    PluginToolResult::success(args.input)
+}
+
+static TOOL: PluginTool = PluginTool {
+    info: tool_info,
+    execute: tool_execute,
+    initialize: None,
+};
+
+extern "C" fn api_version() -> u32 {
+    PLUGIN_API_VERSION
+}
+
+extern "C" fn plugin_name() -> RString {
+    RString::from("{{name}}")
+}
+
+extern "C" fn get_tools() -> RVec<PluginToolRef> {
+    RVec::from(vec![&TOOL])
+}
+
+/// Entry point spec-ai uses to load the plugin
+#[abi_stable::export_root_module]
+fn get_library() -> PluginModuleRef {
+    PluginModuleRef::from_prefix(PluginModule {
+        api_version,
+        plugin_name,
+        get_tools,
+        shutdown: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_matches_plugin() {
+        let info = tool_info();
+        assert_eq!(info.name.as_str(), "{{tool_name}}");
+        assert!(serde_json::from_str::<serde_json::Value>(info.parameters_json.as_str()).is_ok());
+        assert_eq!(api_version(), {{api_version}});
+    }
+
+    #[test]
+    fn test_execute_echoes_input() {
+        let result = tool_execute(r#"{"input": "hello"}"#.into());
+        assert!(result.success);
+        assert_eq!(result.output.as_str(), "hello");
+    }
+
+    #[test]
+    fn test_execute_rejects_invalid_args() {
+        let result = tool_execute(r#"{"unexpected": true}"#.into());
+        assert!(!result.success);
+        assert!(result.error.is_some());
+    }
+}
+"##;
+
+const README_TEMPLATE: &str = r#"# {{name}}
+
+A custom tool plugin for spec-ai, exposing the `{{tool_name}}` tool.
+
+## Building
+
+```bash
+cargo test
+cargo build --release
+```
+
+## Installation
+
+Copy the built library (`.dylib` on macOS, `.so` on Linux, `.dll` on Windows)
+from `target/release/` into your plugins directory, then enable plugins:
+
+```toml
+[plugins]
+enabled = true
+custom_tools_dir = "~/.spec-ai/tools"
+```
+
+## Signing
+
+spec-ai checks a library's signature before loading it. Sign the release
+build with [minisign](https://jedisct1.github.io/minisign/), which writes
+`lib{{tool_name}}.so.minisig` next to the library; copy both into the plugins
+directory:
+
+```bash
+minisign -G
+minisign -S -m target/release/lib{{tool_name}}.so
+```
+
+Or sign with a raw Ed25519 key and save the signature as
+`lib{{tool_name}}.so.sig`:
+
+```bash
+openssl genpkey -algorithm ed25519 -out plugin-key.pem
+openssl pkeyutl -sign -rawin -inkey plugin-key.pem -in target/release/lib{{tool_name}}.so \
+  | openssl base64 -A > target/release/lib{{tool_name}}.so.sig
+openssl pkey -in plugin-key.pem -pubout -outform DER | tail -c 32 | base64
+```
+
+Users add the public key (`minisign.pub`, or the last command's output) to
+`trusted_keys`:
+
+```toml
+[plugins]
+trusted_keys = ["<public key>"]
+```
+
+An unsigned library, or one no trusted key verifies, loads only where a policy
+rule allows the `plugin_load` action on its path:
+
+```json
+{"agent": "*", "action": "plugin_load", "resource": "/home/me/.spec-ai/tools/lib{{tool_name}}.so", "effect": "allow"}
+```
+"#;
+
+const GITIGNORE_TEMPLATE: &str = "/target\nCargo.lock\n";
+
+/// Check that `name` is usable as a crate name
+pub(crate) fn validate_name(name: &str) -> Result<(), PluginError> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(PluginError::InvalidName(name.to_string()))
+    }
+}
+
+fn render(template: &str, name: &str) -> String {
+    template
+        .replace("{{name}}", name)
+        .replace("{{tool_name}}", &name.replace('-', "_"))
+        .replace("{{host_version}}", env!("CARGO_PKG_VERSION"))
+        .replace("{{api_version}}", &PLUGIN_API_VERSION.to_string())
+}
+
+/// Render the files for a new plugin crate without touching the filesystem
+pub fn render_plugin(name: &str, kind: PluginKind) -> Result<Vec<ScaffoldFile>, PluginError> {
+    validate_name(name)?;
+
+    let templates = match kind {
+        PluginKind::Tool => [
+            ("Cargo.toml", CARGO_TOML_TEMPLATE),
+            ("src/lib.rs", LIB_RS_TEMPLATE),
+            ("README.md", README_TEMPLATE),
+            (".gitignore", GITIGNORE_TEMPLATE),
+        ],
+    };
+
+    Ok(templates
+        .into_iter()
+        .map(|(path, template)| ScaffoldFile {
+            path: PathBuf::from(path),
+            contents: render(template, name),
+        })
+        .collect())
+}
+
+/// Create a new plugin crate named `name` inside `parent`
+///
+/// Returns the path of the created crate. Fails if the target directory already exists.
+pub fn scaffold_plugin(
+    name: &str,
+    kind: PluginKind,
+    parent: &Path,
+) -> Result<PathBuf, PluginError> {
+    let files = render_plugin(name, kind)?;
+    let root = parent.join(name);
+    if root.exists() {
+        return Err(PluginError::AlreadyExists(root));
+    }
+
+    for file in files {
+        let path = root.join(&file.path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|source| PluginError::Io {
+                path: dir.to_path_buf(),
+                source,
+            })?;
+        }
+        fs::write(&path, file.contents).map_err(|source| PluginError::Io { path, source })?;
+    }
+
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_tool_plugin() {
+        let files = render_plugin("word-count", PluginKind::Tool).unwrap();
+        let manifest = files
+            .iter()
+            .find(|f| f.path == Path::new("Cargo.toml"))
+            .unwrap();
+        assert!(manifest.contents.contains(r#"name = "word-count""#));
+        assert!(manifest
+            .contents
+            .contains(&format!(r#"version = "{}""#, env!("CARGO_PKG_VERSION"))));
+
+        let lib = files
+            .iter()
+            .find(|f| f.path == Path::new("src/lib.rs"))
+            .unwrap();
+        assert!(lib.contents.contains(r#""word_count""#));
+        assert!(lib.contents.contains("#[abi_stable::export_root_module]"));
+        assert!(!lib.contents.contains("{{"));
+    }
+
+    #[test]
+    fn test_render_readme_documents_signing() {
+        let files = render_plugin("word-count", PluginKind::Tool).unwrap();
+        let readme = files
+            .iter()
+            .find(|f| f.path == Path::new("README.md"))
+            .unwrap();
+        let signing = &readme.contents[readme.contents.find("## Signing").unwrap()..];
+        assert!(signing.contains("minisign -S -m target/release/libword_count.so"));
+        assert!(signing.contains("openssl pkeyutl -sign"));
+        assert!(signing.contains("trusted_keys"));
+        assert!(signing.contains(r#""action": "plugin_load""#));
+        assert!(!readme.contents.contains("{{"));
+    }
+
+    #[test]
+    fn test_render_rejects_invalid_names() {
+        for name in ["", "1tool", "my tool", "../escape"] {
+            assert!(matches!(
+                render_plugin(name, PluginKind::Tool),
+                Err(PluginError::InvalidName(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_scaffold_writes_files_and_refuses_overwrite() {
+        let parent = std::env::temp_dir().join(format!("spec-ai-scaffold-{}", std::process::id()));
+        let root = scaffold_plugin("demo-tool", PluginKind::Tool, &parent).unwrap();
+        assert!(root.join("Cargo.toml").is_file());
+        assert!(root.join("src/lib.rs").is_file());
+
+        assert!(matches!(
+            scaffold_plugin("demo-tool", PluginKind::Tool, &parent),
+            Err(PluginError::AlreadyExists(_))
+        ));
+        fs::remove_dir_all(&parent).unwrap();
+    }
+}
@@ -0,0 +1,470 @@
+//! Publisher keys and the signature check done before a plugin loads
+//!
+//! A plugin library is trusted when a key in the [`TrustStore`] verifies a
+//! signature found next to it. Signatures are looked up, in this order, as a
+//! [minisign](https://jedisct1.github.io/minisign/) `<library>.minisig` file,
+//! a detached `<library>.sig` file holding a base64 Ed25519 signature, and the
+//! `<name>.plugin.json` record written by [`PluginInstaller`], whose checksum
+//! must still match the library. Checking happens before the library is
+//! opened, since opening it already runs its code, and covers the bytes the
+//! loader then opens rather than the file, which could change in between.
+//!
+//! [`PluginInstaller`]: crate::manifest::PluginInstaller
+
+use crate::error::PluginError;
+use crate::manifest::InstalledPlugin;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use blake2::{Blake2b512, Digest};
+use ring::digest::{digest, SHA256};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Leading bytes of minisign keys and of signatures over the raw file
+const MINISIGN_ED: &[u8; 2] = b"Ed";
+/// Leading bytes of minisign signatures over the BLAKE2b-512 hash of the file
+const MINISIGN_PREHASHED: &[u8; 2] = b"ED";
+
+/// How far a plugin library can be trusted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trust {
+    /// A trusted key verified the library's signature
+    Verified {
+        /// Base64 Ed25519 public key that verified it
+        key: String,
+    },
+    /// No signature was found for the library
+    Unsigned,
+    /// The library is signed, but no trusted key verifies it or it changed
+    /// after it was installed
+    Untrusted(String),
+}
+
+impl Trust {
+    /// `verified`, `unsigned` or `untrusted`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Trust::Verified { .. } => "verified",
+            Trust::Unsigned => "unsigned",
+            Trust::Untrusted(_) => "untrusted",
+        }
+    }
+
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Trust::Verified { .. })
+    }
+}
+
+impl fmt::Display for Trust {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trust::Verified { key } => write!(f, "signed by trusted key {}", key),
+            Trust::Unsigned => write!(f, "not signed"),
+            Trust::Untrusted(reason) => write!(f, "untrusted: {}", reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TrustedKey {
+    /// Minisign key ID, when the key was given in minisign format
+    id: Option<[u8; 8]>,
+    key: [u8; 32],
+}
+
+impl TrustedKey {
+    fn parse(text: &str) -> Result<Self, PluginError> {
+        let invalid = || PluginError::InvalidKey(text.trim().to_string());
+        // A whole minisign .pub file may be pasted in, comment line included
+        let encoded = text
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+            .ok_or_else(invalid)?;
+        let bytes = STANDARD.decode(encoded).map_err(|_| invalid())?;
+        match bytes.len() {
+            32 => Ok(Self {
+                id: None,
+                key: bytes.try_into().map_err(|_| invalid())?,
+            }),
+            42 if bytes.starts_with(MINISIGN_ED) => Ok(Self {
+                id: Some(bytes[2..10].try_into().map_err(|_| invalid())?),
+                key: bytes[10..].try_into().map_err(|_| invalid())?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        UnparsedPublicKey::new(&ED25519, &self.key)
+            .verify(message, signature)
+            .is_ok()
+    }
+
+    fn encoded(&self) -> String {
+        STANDARD.encode(self.key)
+    }
+}
+
+/// Public keys of the plugin publishers whose signatures are accepted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustStore {
+    keys: Vec<TrustedKey>,
+}
+
+impl TrustStore {
+    /// Parse keys given as base64 Ed25519 public keys or minisign public keys
+    pub fn new(keys: &[String]) -> Result<Self, PluginError> {
+        let keys = keys
+            .iter()
+            .map(|key| TrustedKey::parse(key))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The key that verifies a raw Ed25519 `signature` of `message`
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Option<String> {
+        self.keys
+            .iter()
+            .find(|key| key.verify(message, signature))
+            .map(TrustedKey::encoded)
+    }
+
+    /// The key that verifies a minisign signature file of `message`
+    ///
+    /// Both the legacy signatures over the file and the default prehashed
+    /// ones are accepted; the trusted comment must be signed too.
+    pub fn verify_minisign(&self, message: &[u8], minisig: &str) -> Result<String, String> {
+        let mut lines = minisig
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty());
+        let mut next = || lines.next().ok_or("the minisign signature is truncated");
+        if !next()?.starts_with("untrusted comment:") {
+            return Err("the minisign signature has no untrusted comment".to_string());
+        }
+        let signature = STANDARD
+            .decode(next()?)
+            .ok()
+            .filter(|bytes| bytes.len() == 74)
+            .ok_or("the minisign signature is malformed")?;
+        let comment = next()?
+            .strip_prefix("trusted comment: ")
+            .ok_or("the minisign signature has no trusted comment")?
+            .to_string();
+        let global = STANDARD
+            .decode(next()?)
+            .map_err(|_| "the minisign trusted comment signature is malformed")?;
+
+        let (algorithm, rest) = signature.split_at(2);
+        let (id, signature) = rest.split_at(8);
+        let hash;
+        let signed: &[u8] = match algorithm {
+            a if a == MINISIGN_ED => message,
+            a if a == MINISIGN_PREHASHED => {
+                hash = Blake2b512::digest(message);
+                &hash
+            }
+            _ => return Err("the minisign signature algorithm is unknown".to_string()),
+        };
+
+        let key = self
+            .keys
+            .iter()
+            .filter(|key| key.id.is_none_or(|key_id| key_id == id))
+            .find(|key| key.verify(signed, signature))
+            .ok_or_else(|| {
+                format!(
+                    "no trusted key verifies the minisign signature (key ID {})",
+                    key_id_hex(id)
+                )
+            })?;
+        let mut trusted = signature.to_vec();
+        trusted.extend_from_slice(comment.as_bytes());
+        if !key.verify(&trusted, &global) {
+            return Err("the minisign trusted comment was tampered with".to_string());
+        }
+        Ok(key.encoded())
+    }
+
+    /// Decide how far the library at `path` can be trusted
+    pub fn check(&self, path: &Path) -> Trust {
+        match fs::read(path) {
+            Ok(bytes) => self.check_bytes(path, &bytes),
+            Err(e) => Trust::Untrusted(format!("cannot read the library: {}", e)),
+        }
+    }
+
+    /// Decide how far `bytes`, read from the library at `path`, can be
+    /// trusted; signatures and install records are looked up next to `path`
+    pub fn check_bytes(&self, path: &Path, bytes: &[u8]) -> Trust {
+        let mut failure = None;
+
+        if let Ok(minisig) = fs::read_to_string(with_suffix(path, ".minisig")) {
+            match self.verify_minisign(bytes, &minisig) {
+                Ok(key) => return Trust::Verified { key },
+                Err(reason) => failure = Some(reason),
+            }
+        }
+
+        if let Ok(signature) = fs::read_to_string(with_suffix(path, ".sig")) {
+            match STANDARD.decode(signature.trim()) {
+                Ok(signature) => match self.verify(bytes, &signature) {
+                    Some(key) => return Trust::Verified { key },
+                    None => {
+                        failure.get_or_insert_with(|| {
+                            "no trusted key verifies the .sig signature".to_string()
+                        });
+                    }
+                },
+                Err(_) => {
+                    failure.get_or_insert_with(|| "the .sig file is not base64".to_string());
+                }
+            }
+        }
+
+        if let Some(record) = installed_record(path) {
+            let artifact = record
+                .manifest
+                .artifacts
+                .iter()
+                .find(|artifact| artifact.target == record.target);
+            if let Some(artifact) = artifact {
+                if !artifact.sha256.eq_ignore_ascii_case(&sha256_hex(bytes)) {
+                    return Trust::Untrusted(format!(
+                        "the library no longer matches the checksum of '{}' {}",
+                        record.manifest.name, record.manifest.version
+                    ));
+                }
+                if let Some(signature) = &artifact.signature {
+                    let key = STANDARD
+                        .decode(signature.trim())
+                        .ok()
+                        .and_then(|signature| self.verify(bytes, &signature));
+                    match key {
+                        Some(key) => return Trust::Verified { key },
+                        None => {
+                            failure.get_or_insert_with(|| {
+                                format!(
+                                    "no trusted key verifies the signature of '{}' {}",
+                                    record.manifest.name, record.manifest.version
+                                )
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        match failure {
+            Some(reason) => Trust::Untrusted(reason),
+            None => Trust::Unsigned,
+        }
+    }
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    digest(&SHA256, bytes)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// The install record next to `path` that describes it
+fn installed_record(path: &Path) -> Option<InstalledPlugin> {
+    let dir = path.parent()?;
+    let file_name = path.file_name()?;
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(".plugin.json")
+        })
+        .filter_map(|entry| fs::read(entry.path()).ok())
+        .filter_map(|record| serde_json::from_slice::<InstalledPlugin>(&record).ok())
+        .find(|record| record.path.file_name() == Some(file_name))
+}
+
+/// Minisign shows key IDs as the hex of a little-endian integer
+fn key_id_hex(id: &[u8]) -> String {
+    id.iter()
+        .rev()
+        .map(|byte| format!("{:02X}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::{PluginArtifact, PluginInstaller, PluginManifest};
+    use crate::PLUGIN_API_VERSION;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const LIBRARY: &[u8] = b"not really a shared library";
+
+    fn key_pair() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn minisign_key(pair: &Ed25519KeyPair, id: [u8; 8]) -> String {
+        let mut key = b"Ed".to_vec();
+        key.extend_from_slice(&id);
+        key.extend_from_slice(pair.public_key().as_ref());
+        format!(
+            "untrusted comment: minisign public key\n{}\n",
+            STANDARD.encode(key)
+        )
+    }
+
+    fn minisign(pair: &Ed25519KeyPair, id: [u8; 8], message: &[u8], comment: &str) -> String {
+        let signature = pair.sign(&Blake2b512::digest(message));
+        let mut line = b"ED".to_vec();
+        line.extend_from_slice(&id);
+        line.extend_from_slice(signature.as_ref());
+        let mut global = signature.as_ref().to_vec();
+        global.extend_from_slice(comment.as_bytes());
+        format!(
+            "untrusted comment: signature from minisign secret key\n{}\ntrusted comment: {}\n{}\n",
+            STANDARD.encode(line),
+            comment,
+            STANDARD.encode(pair.sign(&global))
+        )
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("spec-ai-trust-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_prehashed_minisign_signatures_cover_whole_blocks() {
+        let pair = key_pair();
+        let id = [8, 7, 6, 5, 4, 3, 2, 1];
+        let store = TrustStore::new(&[minisign_key(&pair, id)]).unwrap();
+
+        // BLAKE2b works in 128-byte blocks; sign an exact block and several
+        let exact: Vec<u8> = (0..128u8).collect();
+        let several: Vec<u8> = (0..300).map(|i| (i % 256) as u8).collect();
+        assert_eq!(
+            hex(&Blake2b512::digest(&exact)),
+            "2319e3789c47e2daa5fe807f61bec2a1a6537fa03f19ff32e87eecbfd64b7e0e\
+             8ccff439ac333b040f19b0c4ddd11a61e24ac1fe0f10a039806c5dcc0da3d115"
+        );
+        assert_eq!(
+            hex(&Blake2b512::digest(&several)),
+            "d9cf5983dc6b34c0fa1f0226926855ad3eccd2bcdcd8f8053b9a80664d33b5af\
+             cc32fd21c70ea14f4ef50ca97c3203c4d1803159f0e01bb6cb1d1c83db52b63c"
+        );
+        for message in [&exact, &several] {
+            let signature = minisign(&pair, id, message, "timestamp:1");
+            assert!(store.verify_minisign(message, &signature).is_ok());
+            let mut changed = message.clone();
+            changed[127] ^= 1;
+            assert!(store.verify_minisign(&changed, &signature).is_err());
+        }
+    }
+
+    #[test]
+    fn test_minisign_signatures_and_key_formats() {
+        let pair = key_pair();
+        let id = [1, 2, 3, 4, 5, 6, 7, 8];
+        let store = TrustStore::new(&[minisign_key(&pair, id)]).unwrap();
+        let raw = TrustStore::new(&[STANDARD.encode(pair.public_key().as_ref())]).unwrap();
+        let signature = minisign(&pair, id, LIBRARY, "timestamp:1 file:libweather.so");
+
+        let key = store.verify_minisign(LIBRARY, &signature).unwrap();
+        assert_eq!(key, STANDARD.encode(pair.public_key().as_ref()));
+        assert!(raw.verify_minisign(LIBRARY, &signature).is_ok());
+        assert!(store.verify_minisign(b"tampered", &signature).is_err());
+
+        let forged = signature.replace("file:libweather.so", "file:other.so");
+        assert!(store
+            .verify_minisign(LIBRARY, &forged)
+            .unwrap_err()
+            .contains("tampered"));
+
+        let other = TrustStore::new(&[minisign_key(&key_pair(), id)]).unwrap();
+        assert!(other.verify_minisign(LIBRARY, &signature).is_err());
+        assert!(matches!(
+            TrustStore::new(&["not a key".to_string()]),
+            Err(PluginError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_finds_detached_and_installed_signatures() {
+        let pair = key_pair();
+        let store = TrustStore::new(&[STANDARD.encode(pair.public_key().as_ref())]).unwrap();
+        let dir = temp_dir("check");
+
+        let unsigned = dir.join("libunsigned.so");
+        fs::write(&unsigned, LIBRARY).unwrap();
+        assert_eq!(store.check(&unsigned), Trust::Unsigned);
+
+        let detached = dir.join("libdetached.so");
+        fs::write(&detached, LIBRARY).unwrap();
+        fs::write(
+            with_suffix(&detached, ".sig"),
+            STANDARD.encode(pair.sign(LIBRARY)),
+        )
+        .unwrap();
+        assert!(store.check(&detached).is_verified());
+        assert!(matches!(
+            TrustStore::default().check(&detached),
+            Trust::Untrusted(_)
+        ));
+
+        let manifest = PluginManifest {
+            name: "weather".to_string(),
+            version: "1.0.0".to_string(),
+            description: None,
+            api_version: PLUGIN_API_VERSION,
+            capabilities: Vec::new(),
+            artifacts: vec![PluginArtifact {
+                target: crate::host_target(),
+                url: "libweather.so".to_string(),
+                sha256: sha256_hex(LIBRARY),
+                signature: Some(STANDARD.encode(pair.sign(LIBRARY))),
+            }],
+        };
+        let installed = PluginInstaller::new(&dir)
+            .trusted_keys(vec![STANDARD.encode(pair.public_key().as_ref())])
+            .install(&manifest, &manifest.artifacts[0], LIBRARY)
+            .unwrap();
+        assert!(store.check(&installed.path).is_verified());
+
+        // Only the bytes that were checked count, not what is on disk now
+        assert!(store.check_bytes(&installed.path, LIBRARY).is_verified());
+        fs::write(&installed.path, b"replaced after install").unwrap();
+        assert!(store.check_bytes(&installed.path, LIBRARY).is_verified());
+        assert!(matches!(
+            store.check(&installed.path),
+            Trust::Untrusted(reason) if reason.contains("checksum")
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
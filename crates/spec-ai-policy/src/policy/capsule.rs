@@ -0,0 +1,253 @@
+//! Policy capsules for delegated work
+//!
+//! When an agent delegates a task it attaches a capsule describing the most
+//! the task may do: which tools it may call, which paths those tools may
+//! touch and how much it may spend. The receiving agent's policy engine
+//! intersects the capsule with its own rules, so delegated work can never
+//! exceed what the delegator was allowed to do. Re-delegating from inside a
+//! capsule intersects again, which only ever narrows it.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+use super::wildcard_match;
+
+/// Spending limits of a delegated task
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapsuleBudget {
+    /// Most tool calls the task may make
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<u32>,
+    /// Most model tokens the task may use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+}
+
+impl CapsuleBudget {
+    /// The tighter of two budgets, limit by limit
+    pub fn intersect(&self, other: &CapsuleBudget) -> CapsuleBudget {
+        CapsuleBudget {
+            max_tool_calls: min_limit(self.max_tool_calls, other.max_tool_calls),
+            max_tokens: min_limit(self.max_tokens, other.max_tokens),
+        }
+    }
+}
+
+/// Restrictions a delegator places on the work it hands to a peer
+///
+/// `None` leaves a dimension unrestricted; an empty list allows nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyCapsule {
+    /// Agent that issued the capsule
+    pub issuer: String,
+    /// Tools the task may call (supports wildcards: "*")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<String>>,
+    /// Directories and files the task's tools may touch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paths: Option<Vec<String>>,
+    #[serde(default)]
+    pub budget: CapsuleBudget,
+}
+
+impl PolicyCapsule {
+    /// An unrestricted capsule issued by `issuer`
+    pub fn new(issuer: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Limit the capsule to `tools`
+    pub fn with_tools(mut self, tools: Vec<String>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Limit the capsule to paths under `paths`
+    pub fn with_paths(mut self, paths: Vec<String>) -> Self {
+        self.paths = Some(paths);
+        self
+    }
+
+    pub fn with_budget(mut self, budget: CapsuleBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Whether the capsule lets the task call `tool`
+    pub fn permits_tool(&self, tool: &str) -> bool {
+        match &self.tools {
+            Some(tools) => tools.iter().any(|pattern| wildcard_match(pattern, tool)),
+            None => true,
+        }
+    }
+
+    /// Whether the capsule lets the task touch `path`
+    ///
+    /// Relative paths, in the capsule and in `path`, are resolved against the
+    /// working directory of the process checking them.
+    pub fn permits_path(&self, path: &str) -> bool {
+        match &self.paths {
+            Some(scopes) => {
+                let path = normalize(path);
+                scopes
+                    .iter()
+                    .any(|scope| path.starts_with(normalize(scope)))
+            }
+            None => true,
+        }
+    }
+
+    /// A capsule allowing only what both capsules allow, issued by `self`
+    pub fn intersect(&self, other: &PolicyCapsule) -> PolicyCapsule {
+        let tools = match (&self.tools, &other.tools) {
+            (Some(ours), Some(theirs)) => {
+                let mut tools: Vec<String> = ours
+                    .iter()
+                    .filter(|tool| other.permits_tool(tool))
+                    .chain(theirs.iter().filter(|tool| self.permits_tool(tool)))
+                    .cloned()
+                    .collect();
+                tools.sort();
+                tools.dedup();
+                Some(tools)
+            }
+            (tools, None) | (None, tools) => tools.clone(),
+        };
+
+        let paths = match (&self.paths, &other.paths) {
+            (Some(ours), Some(theirs)) => {
+                let mut paths = Vec::new();
+                for a in ours {
+                    for b in theirs {
+                        let (na, nb) = (normalize(a), normalize(b));
+                        if na.starts_with(&nb) {
+                            paths.push(a.clone());
+                        } else if nb.starts_with(&na) {
+                            paths.push(b.clone());
+                        }
+                    }
+                }
+                paths.sort();
+                paths.dedup();
+                Some(paths)
+            }
+            (paths, None) | (None, paths) => paths.clone(),
+        };
+
+        PolicyCapsule {
+            issuer: self.issuer.clone(),
+            tools,
+            paths,
+            budget: self.budget.intersect(&other.budget),
+        }
+    }
+}
+
+fn min_limit<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (limit, None) | (None, limit) => limit,
+    }
+}
+
+/// Absolute form of `path` with `.` and `..` resolved, without touching the
+/// file system so paths that do not exist yet can be checked too
+fn normalize(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("/"))
+            .join(path)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn tools_and_paths_are_checked_against_the_capsule() {
+        let capsule = PolicyCapsule::new("planner")
+            .with_tools(tools(&["file_*", "echo"]))
+            .with_paths(tools(&["/work/project"]));
+
+        assert!(capsule.permits_tool("file_read"));
+        assert!(capsule.permits_tool("echo"));
+        assert!(!capsule.permits_tool("bash"));
+
+        assert!(capsule.permits_path("/work/project"));
+        assert!(capsule.permits_path("/work/project/src/main.rs"));
+        assert!(!capsule.permits_path("/work/project-other/secrets"));
+        assert!(!capsule.permits_path("/work/project/../../etc/passwd"));
+
+        let open = PolicyCapsule::new("planner");
+        assert!(open.permits_tool("bash"));
+        assert!(open.permits_path("/etc/passwd"));
+    }
+
+    #[test]
+    fn intersections_only_narrow() {
+        let delegator = PolicyCapsule::new("planner")
+            .with_tools(tools(&["file_read", "file_write", "echo"]))
+            .with_paths(tools(&["/work"]))
+            .with_budget(CapsuleBudget {
+                max_tool_calls: Some(10),
+                max_tokens: None,
+            });
+        let requested = PolicyCapsule::new("worker")
+            .with_tools(tools(&["file_*", "bash"]))
+            .with_paths(tools(&["/work/docs", "/etc"]))
+            .with_budget(CapsuleBudget {
+                max_tool_calls: Some(20),
+                max_tokens: Some(5_000),
+            });
+
+        let capsule = requested.intersect(&delegator);
+        assert_eq!(capsule.issuer, "worker");
+        assert!(capsule.permits_tool("file_read"));
+        assert!(capsule.permits_tool("file_write"));
+        assert!(!capsule.permits_tool("echo"));
+        assert!(!capsule.permits_tool("bash"));
+        assert_eq!(capsule.paths, Some(tools(&["/work/docs"])));
+        assert_eq!(capsule.budget.max_tool_calls, Some(10));
+        assert_eq!(capsule.budget.max_tokens, Some(5_000));
+
+        // Unrestricted dimensions take the other side's limits
+        let open = PolicyCapsule::new("worker").intersect(&delegator);
+        assert_eq!(open.tools, delegator.tools);
+        assert_eq!(open.paths, delegator.paths);
+    }
+
+    #[test]
+    fn capsules_round_trip_through_json() {
+        let capsule = PolicyCapsule::new("planner").with_tools(tools(&["echo"]));
+        let value = serde_json::to_value(&capsule).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"issuer": "planner", "tools": ["echo"], "budget": {}})
+        );
+        let parsed: PolicyCapsule = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, capsule);
+    }
+}
@@ -3,6 +3,10 @@ use serde::{Deserialize, Serialize};
 
 use spec_ai_config::persistence::Persistence;
 
+pub mod capsule;
+
+pub use capsule::{CapsuleBudget, PolicyCapsule};
+
 /// Represents the effect of a policy rule
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -52,19 +56,39 @@ pub enum PolicyDecision {
 #[derive(Debug, Clone)]
 pub struct PolicyEngine {
     policy_set: PolicySet,
+    /// Restrictions of the delegated task being executed, if any
+    capsule: Option<PolicyCapsule>,
 }
 
 impl PolicyEngine {
     /// Create a new policy engine with an empty policy set
     pub fn new() -> Self {
-        Self {
-            policy_set: PolicySet::default(),
-        }
+        Self::with_policy_set(PolicySet::default())
     }
 
     /// Create a policy engine with the given policy set
     pub fn with_policy_set(policy_set: PolicySet) -> Self {
-        Self { policy_set }
+        Self {
+            policy_set,
+            capsule: None,
+        }
+    }
+
+    /// Confine the engine to a delegated policy capsule
+    ///
+    /// A capsule already in place is intersected with the new one, so
+    /// capsules can only narrow what the engine allows.
+    pub fn with_capsule(mut self, capsule: PolicyCapsule) -> Self {
+        self.capsule = Some(match self.capsule.take() {
+            Some(current) => capsule.intersect(&current),
+            None => capsule,
+        });
+        self
+    }
+
+    /// Capsule the engine is confined to, if any
+    pub fn capsule(&self) -> Option<&PolicyCapsule> {
+        self.capsule.as_ref()
     }
 
     /// Load policies from persistence layer
@@ -100,7 +124,16 @@ impl PolicyEngine {
     /// Evaluate a policy decision for the given agent, action, and resource
     /// Rules are evaluated in order, and the first matching rule determines the decision
     /// If no rules match, the default is to deny with a reason
+    /// Allowed actions must also fit the capsule, if any: "tool_call" resources
+    /// must be among its tools and other resources are checked as paths
     pub fn check(&self, agent: &str, action: &str, resource: &str) -> PolicyDecision {
+        match self.check_rules(agent, action, resource) {
+            PolicyDecision::Allow => self.check_capsule(action, resource),
+            deny => deny,
+        }
+    }
+
+    fn check_rules(&self, agent: &str, action: &str, resource: &str) -> PolicyDecision {
         for rule in &self.policy_set.rules {
             if rule.matches(agent, action, resource) {
                 return match rule.effect {
@@ -120,6 +153,57 @@ impl PolicyEngine {
         ))
     }
 
+    fn check_capsule(&self, action: &str, resource: &str) -> PolicyDecision {
+        let Some(capsule) = &self.capsule else {
+            return PolicyDecision::Allow;
+        };
+        let permitted = if action == "tool_call" {
+            capsule.permits_tool(resource)
+        } else {
+            capsule.permits_path(resource)
+        };
+        if permitted {
+            PolicyDecision::Allow
+        } else {
+            PolicyDecision::Deny(format!(
+                "Capsule delegated by '{}' does not allow action {} on resource {}",
+                capsule.issuer, action, resource
+            ))
+        }
+    }
+
+    /// Paths the rules allow `agent` to perform `action` on, such as the
+    /// directories a sandbox lets it write for `file_write`
+    ///
+    /// Resources are read as path prefixes: a trailing `*` or `/*` is
+    /// dropped, and rules with other wildcards are skipped because they name
+    /// no single path. A path is only returned when the rules, read in
+    /// order, actually allow it, so an earlier deny rule for the same path
+    /// still wins; deny rules for paths inside it are not reflected.
+    pub fn allowed_paths(&self, agent: &str, action: &str) -> Vec<String> {
+        let mut paths: Vec<String> = Vec::new();
+        for rule in &self.policy_set.rules {
+            if rule.effect != PolicyEffect::Allow
+                || !wildcard_match(&rule.agent, agent)
+                || !wildcard_match(&rule.action, action)
+            {
+                continue;
+            }
+            let prefix = rule.resource.trim_end_matches('*');
+            let path = match prefix.trim_end_matches('/') {
+                "" if prefix.starts_with('/') => "/",
+                path => path,
+            };
+            if path.is_empty() || path.contains('*') || paths.iter().any(|p| p == path) {
+                continue;
+            }
+            if self.check(agent, action, prefix) == PolicyDecision::Allow {
+                paths.push(path.to_string());
+            }
+        }
+        paths
+    }
+
     /// Get the number of rules in the policy set
     pub fn rule_count(&self) -> usize {
         self.policy_set.rules.len()
@@ -469,6 +553,86 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_policy_engine_capsule_narrows_rules() {
+        let mut engine = PolicyEngine::new();
+        engine.add_rule(PolicyRule {
+            agent: "*".to_string(),
+            action: "*".to_string(),
+            resource: "*".to_string(),
+            effect: PolicyEffect::Allow,
+        });
+        engine.add_rule(PolicyRule {
+            agent: "*".to_string(),
+            action: "tool_call".to_string(),
+            resource: "bash".to_string(),
+            effect: PolicyEffect::Deny,
+        });
+        let engine = engine.with_capsule(
+            PolicyCapsule::new("planner")
+                .with_tools(vec!["echo".to_string(), "bash".to_string()])
+                .with_paths(vec!["/work".to_string()]),
+        );
+
+        assert_eq!(
+            engine.check("worker", "tool_call", "echo"),
+            PolicyDecision::Allow
+        );
+        assert!(matches!(
+            engine.check("worker", "tool_call", "file_write"),
+            PolicyDecision::Deny(reason) if reason.contains("planner")
+        ));
+        assert_eq!(
+            engine.check("worker", "file_write", "/work/notes.md"),
+            PolicyDecision::Allow
+        );
+        assert!(matches!(
+            engine.check("worker", "file_write", "/etc/hosts"),
+            PolicyDecision::Deny(_)
+        ));
+
+        // The capsule never widens the engine's own rules
+        let engine = engine.with_capsule(PolicyCapsule::new("worker"));
+        assert!(matches!(
+            engine.check("worker", "tool_call", "bash"),
+            PolicyDecision::Deny(_)
+        ));
+        assert_eq!(engine.capsule().unwrap().tools.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_allowed_paths() {
+        let rule = |agent: &str, action: &str, resource: &str, effect| PolicyRule {
+            agent: agent.to_string(),
+            action: action.to_string(),
+            resource: resource.to_string(),
+            effect,
+        };
+        let engine = PolicyEngine::with_policy_set(PolicySet {
+            rules: vec![
+                rule("*", "file_write", "/srv/secrets/*", PolicyEffect::Deny),
+                rule("coder", "file_write", "/work/*", PolicyEffect::Allow),
+                rule("*", "file_write", "/tmp/cache", PolicyEffect::Allow),
+                rule("*", "file_write", "/srv/*", PolicyEffect::Allow),
+                rule("*", "file_write", "/srv/secrets/*", PolicyEffect::Allow),
+                rule("*", "file_write", "*.log", PolicyEffect::Allow),
+                rule("*", "file_write", "/var/*/cache", PolicyEffect::Allow),
+                rule("*", "file_read", "/etc/*", PolicyEffect::Allow),
+            ],
+        });
+
+        assert_eq!(
+            engine.allowed_paths("coder", "file_write"),
+            ["/work", "/tmp/cache", "/srv"]
+        );
+        assert_eq!(
+            engine.allowed_paths("reviewer", "file_write"),
+            ["/tmp/cache", "/srv"]
+        );
+        assert_eq!(engine.allowed_paths("coder", "file_read"), ["/etc"]);
+        assert!(engine.allowed_paths("coder", "bash").is_empty());
+    }
+
     #[test]
     fn test_load_empty_persistence() {
         use spec_ai_config::test_utils::create_test_db;
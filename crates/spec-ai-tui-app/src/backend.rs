@@ -1,14 +1,78 @@
+use crate::keymap::{Keymap, Profile};
+use crate::mentions;
 use anyhow::Result;
 use futures::StreamExt;
-use spec_ai_core::cli::{formatting, parse_command, CliState, Command};
-use spec_ai_core::types::Message;
+use spec_ai_core::agent::{
+    tool_approval_channel, user_question_channel, AgentInbox, ModelListing, PolicyViolation,
+    RunInterrupt, SessionUsage, StreamDelta, StreamedResponse, ToolApprovalRequest,
+    UserQuestionRequest,
+};
+use spec_ai_core::cli::{
+    formatting, parse_command, BranchCommand, BudgetCommand, CliState, Command, PresetCommand,
+};
+use spec_ai_core::error::{code_of, ErrorCode};
+use spec_ai_core::mesh::{self, MeshClient, MeshInstance};
+use spec_ai_core::persistence::{MeshMessageRecord, SessionRecord};
+use spec_ai_core::presence::{Activity, Participant, PresenceAnnouncement, PRESENCE_INTERVAL};
+use spec_ai_core::shutdown::CancellationToken;
+use spec_ai_core::tasks::{TaskEvent, TaskRegistry};
+use spec_ai_core::types::{Message, ToolLog};
+use spec_ai_core::watch::{WatchEvent, WatchRegistry};
+use spec_ai_tui::app::MirrorFrame;
+use spec_ai_tui::event::Attachment;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// Number of persisted inputs loaded into the editor history
+const INPUT_HISTORY_LIMIT: i64 = 500;
+
+/// Number of persisted messages loaded when a session opens
+const MESSAGE_HISTORY_LIMIT: i64 = 200;
+
+/// Number of persisted tool calls shown when a session is resumed
+const TOOL_CALL_LIMIT: i64 = 200;
+
+/// Number of past sessions listed in the history overlay
+const SESSION_LIST_LIMIT: i64 = 50;
+
+/// Number of recent mesh messages counted per instance in the mesh view
+const MESH_MESSAGE_LIMIT: usize = 500;
+
+/// How often the mesh view is refreshed while it is open
+const MESH_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a mesh refresh waits for the registry before giving up
+const MESH_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Turns run for watch events in a row before further events wait for the
+/// user, so an agent whose edits keep waking it cannot loop forever
+const MAX_UNATTENDED_TURNS: usize = 10;
 
 /// Requests sent from the UI to the backend worker.
 #[derive(Debug)]
 pub enum BackendRequest {
-    Submit(String),
+    /// Run a line of input; attachments go along with plain messages
+    Submit {
+        input: String,
+        attachments: Vec<Attachment>,
+        /// Earlier user message this input replaces on a new branch
+        edit: Option<i64>,
+    },
+    /// List past sessions for the history overlay
+    ListSessions,
+    /// Reopen a past session and continue it
+    ResumeSession(String),
+    /// Start or stop periodic mesh status updates for the mesh view
+    WatchMesh(bool),
+    /// Query the configured providers for the model picker
+    ListModels,
+    /// List workspace files for the `@` mention picker
+    IndexFiles,
 }
 
 /// Events emitted by the backend worker to drive the UI.
@@ -16,9 +80,96 @@ pub enum BackendRequest {
 pub enum BackendEvent {
     Initialized {
         agent: Option<String>,
+        session_id: String,
         messages: Vec<Message>,
         reasoning: Vec<String>,
         status: String,
+        /// Input history for the active session, oldest first
+        history: Vec<String>,
+    },
+    /// Actions refused or escalated by policy while handling the last request
+    PolicyViolations {
+        violations: Vec<PolicyViolation>,
+    },
+    /// Input history reloaded after the active session changed
+    HistoryLoaded {
+        entries: Vec<String>,
+    },
+    /// Past sessions, most recently active first
+    SessionsLoaded {
+        sessions: Vec<SessionRecord>,
+    },
+    /// A past session was reopened
+    SessionResumed {
+        session_id: String,
+        messages: Vec<Message>,
+        tool_calls: Vec<ToolLog>,
+        /// Input history for the resumed session, oldest first
+        history: Vec<String>,
+        status: String,
+    },
+    /// The conversation continues on a new branch; the parent is left as it was
+    Branched {
+        session_id: String,
+        parent_session_id: String,
+        /// The user message the resubmitted input replaces
+        edited: i64,
+    },
+    /// Models offered by each configured provider
+    ModelsLoaded {
+        listings: Vec<ModelListing>,
+    },
+    /// Another agent profile is now active
+    AgentChanged {
+        agent: Option<String>,
+    },
+    /// The agent is now running on this provider and model
+    ModelChanged {
+        provider: String,
+        model: Option<String>,
+    },
+    /// Key bindings from the `[ui.keymap]` config
+    KeymapLoaded {
+        keymap: Keymap,
+    },
+    /// Whether the `[ui] screen_reader` config asks for linear output
+    ScreenReaderLoaded {
+        enabled: bool,
+    },
+    /// Token usage for the current session, per model
+    UsageUpdated {
+        usage: SessionUsage,
+    },
+    /// Workspace files available to `@` mentions, relative to the working directory
+    FilesIndexed {
+        files: Vec<String>,
+    },
+    /// A tool call blocked by policy is waiting for the user's decision
+    ToolApproval(ToolApprovalRequest),
+    /// The agent asked the user a question and is waiting for the answer
+    UserQuestion(UserQuestionRequest),
+    /// Other people in the session, from the API server when `[ui] presence` is set
+    Presence {
+        participants: Vec<Participant>,
+        /// Messages queued or being answered by the server in the session
+        pending_turns: u64,
+    },
+    /// Mesh registry snapshot for the mesh view
+    MeshStatus {
+        instances: Vec<MeshInstance>,
+        leader_id: Option<String>,
+        /// Recent messages sent or received, keyed by instance id
+        message_counts: HashMap<String, usize>,
+        /// Why the registry could not be reached, if it could not
+        error: Option<String>,
+    },
+    /// A background task started, finished or wrote output
+    Task(TaskEvent),
+    /// A file watch started, stopped or saw changes
+    Watch(WatchEvent),
+    /// Pending events are being run as a turn with this input
+    EventsDelivered {
+        input: String,
     },
     CommandResult {
         response: Option<String>,
@@ -32,6 +183,10 @@ pub enum BackendEvent {
     StreamDelta {
         content: String,
     },
+    /// A tool call the model is generating, as `name(arguments)` so far
+    ToolCallStreaming {
+        preview: String,
+    },
     /// Signals the end of a streaming response
     StreamEnd {
         new_messages: Vec<Message>,
@@ -41,64 +196,302 @@ pub enum BackendEvent {
     Error {
         context: String,
         message: String,
+        code: ErrorCode,
     },
     Quit,
 }
 
+impl BackendEvent {
+    /// Error event for a failure while handling `context`
+    fn error(context: impl Into<String>, err: &anyhow::Error) -> Self {
+        BackendEvent::Error {
+            context: context.into(),
+            message: err.to_string(),
+            code: code_of(err),
+        }
+    }
+}
+
 /// Handle containing the channels used by the TUI to talk to the backend worker.
 pub struct BackendHandle {
     pub request_tx: UnboundedSender<BackendRequest>,
     pub event_rx: UnboundedReceiver<BackendEvent>,
+    /// The worker task, which exits once the shutdown token is cancelled
+    pub task: JoinHandle<Result<()>>,
+    /// Interrupts the agent step the worker is running
+    pub interrupt: RunInterrupt,
 }
 
 /// Spawn the backend worker that owns CliState and performs all agent operations.
-pub fn spawn_backend(config_path: Option<PathBuf>) -> Result<BackendHandle> {
+///
+/// Frames rendered by the UI arrive on `frames`; they are mirrored through
+/// the API server when `[ui] mirror` is set and dropped otherwise.
+pub fn spawn_backend(
+    config_path: Option<PathBuf>,
+    agent: Option<String>,
+    frames: UnboundedReceiver<MirrorFrame>,
+    shutdown: CancellationToken,
+) -> Result<BackendHandle> {
     let (request_tx, mut request_rx) = unbounded_channel();
     let (event_tx, event_rx) = unbounded_channel();
 
+    let interrupt = RunInterrupt::new();
+
     let config_path = config_path.clone();
-    tokio::spawn(async move {
-        if let Err(err) = run_backend_loop(&mut request_rx, &event_tx, config_path).await {
-            let _ = event_tx.send(BackendEvent::Error {
-                context: "startup".to_string(),
-                message: err.to_string(),
-            });
+    let worker_interrupt = interrupt.clone();
+    let task = tokio::spawn(async move {
+        let result = run_backend_loop(
+            &mut request_rx,
+            &event_tx,
+            config_path,
+            agent,
+            frames,
+            shutdown,
+            worker_interrupt,
+        )
+        .await;
+        if let Err(err) = &result {
+            let _ = event_tx.send(BackendEvent::error("startup", &err));
         }
+        result
     });
 
     Ok(BackendHandle {
         request_tx,
         event_rx,
+        task,
+        interrupt,
     })
 }
 
+/// Forward background task changes to the process panel
+///
+/// A subscriber that falls behind loses its oldest events, so the panel then
+/// also gets the current state of every task.
+fn spawn_task_events(event_tx: UnboundedSender<BackendEvent>) {
+    let tasks = TaskRegistry::global();
+    let mut events = tasks.subscribe();
+    tokio::spawn(async move {
+        let mut missed = 0;
+        while let Ok(event) = events.recv().await {
+            let mut forwarded = event_tx.send(BackendEvent::Task(event)).is_ok();
+            let dropped = events.stats().dropped;
+            if dropped > missed {
+                missed = dropped;
+                forwarded &= tasks.list().into_iter().all(|info| {
+                    event_tx
+                        .send(BackendEvent::Task(TaskEvent::Updated(info)))
+                        .is_ok()
+                });
+            }
+            if !forwarded {
+                break;
+            }
+        }
+    });
+}
+
+/// Forward file watch changes to the UI and to the agent's inbox
+///
+/// As with tasks, a subscriber that falls behind also sends the current state
+/// of every watch.
+fn spawn_watch_events(event_tx: UnboundedSender<BackendEvent>, inbox: AgentInbox) {
+    let watches = WatchRegistry::global();
+    let mut events = watches.subscribe();
+    tokio::spawn(async move {
+        let mut missed = 0;
+        while let Ok(event) = events.recv().await {
+            if let WatchEvent::Changed(batch) = &event {
+                inbox.push(batch.to_agent_event());
+            }
+            let mut forwarded = event_tx.send(BackendEvent::Watch(event)).is_ok();
+            let dropped = events.stats().dropped;
+            if dropped > missed {
+                missed = dropped;
+                forwarded &= watches.list().into_iter().all(|info| {
+                    event_tx
+                        .send(BackendEvent::Watch(WatchEvent::Updated(info)))
+                        .is_ok()
+                });
+            }
+            if !forwarded {
+                break;
+            }
+        }
+    });
+}
+
 async fn run_backend_loop(
     request_rx: &mut UnboundedReceiver<BackendRequest>,
     event_tx: &UnboundedSender<BackendEvent>,
     config_path: Option<PathBuf>,
+    agent: Option<String>,
+    frames: UnboundedReceiver<MirrorFrame>,
+    shutdown: CancellationToken,
+    interrupt: RunInterrupt,
 ) -> Result<()> {
     // Force plain text output so we can render cleanly in our own UI.
     formatting::set_plain_text_mode(true);
 
-    let mut cli_state = initialize_cli_state(config_path)?;
-    let _ = cli_state.agent.load_history(200);
+    // Startup blocks on the database and joins its worker threads, so keep it
+    // off the runtime that is drawing the first frame
+    let mut cli_state =
+        tokio::task::spawn_blocking(move || initialize_cli_state(config_path)).await??;
+    if let Some(agent) = agent {
+        cli_state.switch_agent(&agent)?;
+    }
+
+    // Approvals and questions are forwarded by separate tasks because this
+    // loop is blocked inside the agent step while it waits for the answer
+    let (approval_tx, mut approval_rx) = tool_approval_channel();
+    cli_state.set_tool_approvals(Some(approval_tx));
+    let approval_events = event_tx.clone();
+    tokio::spawn(async move {
+        while let Some(request) = approval_rx.recv().await {
+            if approval_events
+                .send(BackendEvent::ToolApproval(request))
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+    let (question_tx, mut question_rx) = user_question_channel();
+    cli_state.set_user_questions(Some(question_tx));
+    let question_events = event_tx.clone();
+    tokio::spawn(async move {
+        while let Some(request) = question_rx.recv().await {
+            if question_events
+                .send(BackendEvent::UserQuestion(request))
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+    spawn_task_events(event_tx.clone());
+    // Watch events wait in an inbox that outlives agents rebuilt by /model
+    let inbox = AgentInbox::new();
+    cli_state.agent.set_inbox(inbox.clone());
+    spawn_watch_events(event_tx.clone(), inbox.clone());
+    let _ = cli_state.agent.load_history(MESSAGE_HISTORY_LIMIT);
+    record_session(&cli_state);
+
+    // User messages are stored under the name of whoever runs the TUI, and
+    // other clients of the session see it in the presence list
+    let author = local_author();
+    let presence = if cli_state.config.ui.presence {
+        spawn_presence(&cli_state, &author, event_tx)
+    } else {
+        None
+    };
 
     let agent_name = cli_state.registry.active_name();
     let initial_messages = cli_state.agent.conversation_history().to_vec();
     cli_state.status_message = "Status: awaiting input".to_string();
+    if cli_state.config.ui.mirror {
+        if let Some(name) = spawn_mirror(&cli_state, frames, event_tx) {
+            cli_state.status_message = format!("Status: awaiting input; mirroring as '{}'", name);
+        }
+    } else {
+        // The UI stops encoding frames once nobody receives them
+        drop(frames);
+    }
 
     let _ = event_tx.send(BackendEvent::Initialized {
         agent: agent_name,
+        session_id: cli_state.agent.session_id().to_string(),
         messages: initial_messages,
         reasoning: cli_state.reasoning_messages.clone(),
         status: cli_state.status_message.clone(),
+        history: load_input_history(&cli_state),
+    });
+    let _ = event_tx.send(model_changed(&cli_state));
+    let _ = event_tx.send(usage_updated(&cli_state));
+    send_keymap(&cli_state, &event_tx);
+    let _ = event_tx.send(BackendEvent::ScreenReaderLoaded {
+        enabled: cli_state.config.ui.screen_reader,
     });
 
-    while let Some(request) = request_rx.recv().await {
+    let mut mesh_refresh = tokio::time::interval(MESH_REFRESH_INTERVAL);
+    // Don't burst missed refreshes after a long-running request
+    mesh_refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut watching_mesh = false;
+    let mut unattended_turns = 0;
+
+    loop {
+        report_presence(&presence, cli_state.agent.session_id(), Activity::Idle);
+        // Whether the request runs pending events rather than user input
+        let mut injected = false;
+        let request = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            request = request_rx.recv() => match request {
+                Some(request) => request,
+                None => break,
+            },
+            _ = mesh_refresh.tick(), if watching_mesh => {
+                let _ = event_tx.send(mesh_status(&cli_state).await);
+                continue;
+            }
+            _ = inbox.wait(), if unattended_turns < MAX_UNATTENDED_TURNS => {
+                cli_state.agent.set_inbox(inbox.clone());
+                let Some(input) = cli_state.agent.take_events_input() else {
+                    continue;
+                };
+                injected = true;
+                unattended_turns += 1;
+                let _ = event_tx.send(BackendEvent::EventsDelivered {
+                    input: input.clone(),
+                });
+                BackendRequest::Submit {
+                    input,
+                    attachments: Vec::new(),
+                    edit: None,
+                }
+            }
+        };
+
         match request {
-            BackendRequest::Submit(input) => {
+            BackendRequest::Submit {
+                input,
+                attachments,
+                edit,
+            } => {
+                // Editing an earlier message forks the conversation just before it
+                if let Some(message_id) = edit {
+                    match cli_state.agent.branch_before(message_id) {
+                        Ok(branch) => {
+                            record_session(&cli_state);
+                            let _ = event_tx.send(BackendEvent::Branched {
+                                session_id: branch.session_id,
+                                parent_session_id: branch.parent_session_id,
+                                edited: message_id,
+                            });
+                        }
+                        Err(err) => {
+                            cli_state.status_message = "Status: error".to_string();
+                            let _ = event_tx.send(BackendEvent::error("edit", &err));
+                            continue;
+                        }
+                    }
+                }
+
+                let session_id = cli_state.agent.session_id().to_string();
+                if !injected {
+                    unattended_turns = 0;
+                    // History is best-effort; a failed write should not block the command
+                    let _ = cli_state
+                        .persistence
+                        .append_input_history(&session_id, &input);
+                }
+                record_session(&cli_state);
+
                 let command = parse_command(&input);
                 cli_state.status_message = status_message_for_command(&command);
+                let agent = cli_state.registry.active_name();
+                let model = cli_state.active_model();
+                let keymap = cli_state.config.ui.keymap.clone();
+                let screen_reader = cli_state.config.ui.screen_reader;
 
                 // Use streaming for Message commands
                 if let Command::Message(text) = command {
@@ -107,25 +500,53 @@ async fn run_backend_loop(
                     // Signal stream start
                     let _ = event_tx.send(BackendEvent::StreamStart);
 
+                    // `@path` mentions and attachments carry file contents along with the query
+                    let attached = match save_attachments(&attachments) {
+                        Ok(paths) => paths,
+                        Err(err) => {
+                            let _ = event_tx.send(BackendEvent::error("Attachments", &err));
+                            Vec::new()
+                        }
+                    };
+                    let query = mentions::attach_files(&text, &workspace_root(), &attached);
+
+                    // Commands such as /model rebuild the agent, so hand the
+                    // TUI's interrupt, inbox and author to whichever agent runs this step
+                    cli_state.agent.set_interrupt(interrupt.clone());
+                    cli_state.agent.set_inbox(inbox.clone());
+                    cli_state.agent.set_author(Some(author.clone()));
+                    report_presence(&presence, &session_id, Activity::Running);
+
                     // Start streaming
-                    match cli_state.agent.run_step_streaming(&text).await {
+                    match cli_state.agent.run_step_streaming_deltas(&query).await {
                         Ok(mut stream) => {
-                            let mut accumulated_content = String::new();
-
-                            // Stream chunks to the UI
-                            while let Some(chunk_result) = stream.next().await {
-                                match chunk_result {
-                                    Ok(chunk) => {
-                                        accumulated_content.push_str(&chunk);
-                                        let _ = event_tx
-                                            .send(BackendEvent::StreamDelta { content: chunk });
+                            let mut streamed = StreamedResponse::new();
+
+                            // Stream text and tool calls to the UI as they are generated
+                            while let Some(delta_result) = stream.next().await {
+                                match delta_result {
+                                    Ok(delta) => {
+                                        streamed.push(&delta);
+                                        let event = match delta {
+                                            StreamDelta::Text(content) => {
+                                                BackendEvent::StreamDelta { content }
+                                            }
+                                            StreamDelta::ToolCallStart { index, .. }
+                                            | StreamDelta::ToolCallArguments { index, .. } => {
+                                                BackendEvent::ToolCallStreaming {
+                                                    preview: streamed
+                                                        .tool_call_preview(index)
+                                                        .unwrap_or_default(),
+                                                }
+                                            }
+                                            StreamDelta::Usage(_) => continue,
+                                        };
+                                        let _ = event_tx.send(event);
                                     }
                                     Err(err) => {
                                         cli_state.status_message = "Status: error".to_string();
-                                        let _ = event_tx.send(BackendEvent::Error {
-                                            context: text.clone(),
-                                            message: err.to_string(),
-                                        });
+                                        let _ =
+                                            event_tx.send(BackendEvent::error(text.clone(), &err));
                                         break;
                                     }
                                 }
@@ -134,14 +555,11 @@ async fn run_backend_loop(
                             // Finalize the streaming step
                             if let Err(err) = cli_state
                                 .agent
-                                .finalize_streaming_step(&accumulated_content)
+                                .finalize_streaming_step(&streamed.content)
                                 .await
                             {
                                 cli_state.status_message = "Status: error".to_string();
-                                let _ = event_tx.send(BackendEvent::Error {
-                                    context: text,
-                                    message: err.to_string(),
-                                });
+                                let _ = event_tx.send(BackendEvent::error(text, &err));
                                 continue;
                             }
 
@@ -150,7 +568,11 @@ async fn run_backend_loop(
                             let new_messages: Vec<Message> =
                                 history.into_iter().skip(start_len).collect();
 
-                            cli_state.status_message = "Status: awaiting input".to_string();
+                            cli_state.status_message = if interrupt.is_interrupted() {
+                                "Status: interrupted".to_string()
+                            } else {
+                                "Status: awaiting input".to_string()
+                            };
 
                             let _ = event_tx.send(BackendEvent::StreamEnd {
                                 new_messages,
@@ -160,10 +582,7 @@ async fn run_backend_loop(
                         }
                         Err(err) => {
                             cli_state.status_message = "Status: error".to_string();
-                            let _ = event_tx.send(BackendEvent::Error {
-                                context: text,
-                                message: err.to_string(),
-                            });
+                            let _ = event_tx.send(BackendEvent::error(text, &err));
                         }
                     }
                 } else {
@@ -183,6 +602,42 @@ async fn run_backend_loop(
                             // Return to idle after handling the command
                             cli_state.status_message = "Status: awaiting input".to_string();
 
+                            // `/session`, `/branch` and `/rollback` move to another transcript
+                            if cli_state.agent.session_id() != session_id {
+                                match session_resumed(&mut cli_state) {
+                                    Ok(event) => {
+                                        let _ = event_tx.send(event);
+                                    }
+                                    Err(err) => {
+                                        let _ = event_tx.send(BackendEvent::error("session", &err));
+                                    }
+                                }
+                            }
+
+                            let active_agent = cli_state.registry.active_name();
+                            if active_agent != agent {
+                                record_session(&cli_state);
+                                let _ = event_tx.send(BackendEvent::AgentChanged {
+                                    agent: active_agent,
+                                });
+                            }
+                            // `/model`, `/switch` and `/config reload` can change the running model
+                            let active_model = cli_state.active_model();
+                            if active_model.provider != model.provider
+                                || active_model.model_name != model.model_name
+                            {
+                                record_session(&cli_state);
+                                let _ = event_tx.send(model_changed(&cli_state));
+                            }
+                            if cli_state.config.ui.keymap != keymap {
+                                send_keymap(&cli_state, &event_tx);
+                            }
+                            if cli_state.config.ui.screen_reader != screen_reader {
+                                let _ = event_tx.send(BackendEvent::ScreenReaderLoaded {
+                                    enabled: cli_state.config.ui.screen_reader,
+                                });
+                            }
+
                             let _ = event_tx.send(BackendEvent::CommandResult {
                                 response: output,
                                 new_messages,
@@ -192,18 +647,418 @@ async fn run_backend_loop(
                         }
                         Err(err) => {
                             cli_state.status_message = "Status: error".to_string();
-                            let _ = event_tx.send(BackendEvent::Error {
-                                context: input,
-                                message: err.to_string(),
-                            });
+                            let _ = event_tx.send(BackendEvent::error(input, &err));
                         }
                     }
                 }
+
+                if !cli_state.policy_violations.is_empty() {
+                    let _ = event_tx.send(BackendEvent::PolicyViolations {
+                        violations: std::mem::take(&mut cli_state.policy_violations),
+                    });
+                }
+                let _ = event_tx.send(usage_updated(&cli_state));
+            }
+            BackendRequest::ListSessions => {
+                match cli_state
+                    .persistence
+                    .list_session_summaries(SESSION_LIST_LIMIT)
+                {
+                    Ok(sessions) => {
+                        let _ = event_tx.send(BackendEvent::SessionsLoaded { sessions });
+                    }
+                    Err(err) => {
+                        let _ = event_tx.send(BackendEvent::error("session history", &err));
+                    }
+                }
+            }
+            BackendRequest::ResumeSession(session_id) => {
+                match resume_session(&mut cli_state, &session_id).await {
+                    Ok(event) => {
+                        let _ = event_tx.send(event);
+                        let _ = event_tx.send(usage_updated(&cli_state));
+                    }
+                    Err(err) => {
+                        cli_state.status_message = "Status: error".to_string();
+                        let _ = event_tx
+                            .send(BackendEvent::error(format!("resume {}", session_id), &err));
+                    }
+                }
+            }
+            BackendRequest::WatchMesh(watch) => {
+                watching_mesh = watch;
+                // Restarting the interval makes the first tick fire immediately
+                mesh_refresh.reset_immediately();
+            }
+            BackendRequest::ListModels => {
+                let listings = cli_state.list_models().await;
+                let _ = event_tx.send(BackendEvent::ModelsLoaded { listings });
             }
+            BackendRequest::IndexFiles => {
+                // Walking a large tree must not hold up the request loop
+                let event_tx = event_tx.clone();
+                let shutdown = shutdown.clone();
+                tokio::task::spawn_blocking(move || {
+                    let files = mentions::index_workspace(&workspace_root(), &shutdown);
+                    let _ = event_tx.send(BackendEvent::FilesIndexed { files });
+                });
+            }
+        }
+    }
+
+    // Write out the WAL so an exit mid-session leaves a clean database
+    cli_state.persistence.checkpoint()
+}
+
+/// Directory `@` mentions are resolved against
+fn workspace_root() -> PathBuf {
+    std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
+}
+
+/// Paths of `attachments` on disk, saving pasted images to a temporary directory.
+fn save_attachments(attachments: &[Attachment]) -> Result<Vec<PathBuf>> {
+    let dir = std::env::temp_dir().join("spec-ai-attachments");
+    attachments
+        .iter()
+        .map(|attachment| {
+            attachment
+                .save_to(&dir)
+                .map_err(|e| anyhow::anyhow!("Failed to save {}: {}", attachment.label(), e))
+        })
+        .collect()
+}
+
+/// Switch the agent to a past session and load its transcript.
+async fn resume_session(cli_state: &mut CliState, session_id: &str) -> Result<BackendEvent> {
+    cli_state
+        .handle_line(&format!("/session switch {}", session_id))
+        .await?;
+    cli_state.status_message = format!("Status: resumed session '{}'", session_id);
+    session_resumed(cli_state)
+}
+
+/// Load the transcript of the session the agent is now on.
+fn session_resumed(cli_state: &mut CliState) -> Result<BackendEvent> {
+    cli_state.agent.load_history(MESSAGE_HISTORY_LIMIT)?;
+    record_session(cli_state);
+
+    let session_id = cli_state.agent.session_id().to_string();
+    let tool_calls = cli_state
+        .persistence
+        .list_tool_calls(&session_id, TOOL_CALL_LIMIT)
+        .unwrap_or_default();
+
+    Ok(BackendEvent::SessionResumed {
+        session_id,
+        messages: cli_state.agent.conversation_history().to_vec(),
+        tool_calls,
+        history: load_input_history(cli_state),
+        status: cli_state.status_message.clone(),
+    })
+}
+
+/// The provider and model the agent is running on.
+fn model_changed(cli_state: &CliState) -> BackendEvent {
+    let model = cli_state.active_model();
+    BackendEvent::ModelChanged {
+        provider: model.provider,
+        model: model.model_name,
+    }
+}
+
+/// Send the configured key bindings, falling back to the bare profile when
+/// an override is invalid.
+fn send_keymap(cli_state: &CliState, event_tx: &UnboundedSender<BackendEvent>) {
+    let config = &cli_state.config.ui.keymap;
+    let keymap = match Keymap::from_config(config) {
+        Ok(keymap) => keymap,
+        Err(err) => {
+            let _ = event_tx.send(BackendEvent::Error {
+                context: "[ui.keymap]".to_string(),
+                message: format!("{:#}", err),
+                code: ErrorCode::Config,
+            });
+            Keymap::new(Profile::parse(&config.profile).unwrap_or_default())
         }
+    };
+    let _ = event_tx.send(BackendEvent::KeymapLoaded { keymap });
+}
+
+/// The session's token usage so far.
+fn usage_updated(cli_state: &CliState) -> BackendEvent {
+    BackendEvent::UsageUpdated {
+        usage: cli_state.agent.session_usage().clone(),
+    }
+}
+
+/// Address of the mesh registry, which is the API server
+fn registry_address(cli_state: &CliState) -> String {
+    std::env::var("SPEC_AI_TUI_MESH_REGISTRY")
+        .unwrap_or_else(|_| format!("127.0.0.1:{}", cli_state.config.mesh.registry_port))
+}
+
+/// Client for a `host:port` registry address
+fn registry_client(registry: &str) -> Result<MeshClient> {
+    match registry
+        .rsplit_once(':')
+        .map(|(h, p)| (h, p.parse::<u16>()))
+    {
+        Some((host, Ok(port))) => Ok(MeshClient::new(host, port)),
+        _ => anyhow::bail!("Invalid mesh registry address '{}'", registry),
     }
+}
+
+/// Post the UI's frames to the API server, named after the session.
+///
+/// Returns the mirror's name, or `None` if the server address is invalid.
+fn spawn_mirror(
+    cli_state: &CliState,
+    mut frames: UnboundedReceiver<MirrorFrame>,
+    event_tx: &UnboundedSender<BackendEvent>,
+) -> Option<String> {
+    let client = match registry_client(&registry_address(cli_state)) {
+        Ok(client) => client,
+        Err(err) => {
+            let _ = event_tx.send(BackendEvent::error("mirror", &err));
+            return None;
+        }
+    };
+    let name = cli_state.agent.session_id().to_string();
+    let events = event_tx.clone();
+    let mirror = name.clone();
+    tokio::spawn(async move {
+        let mut reported = false;
+        while let Some(frame) = frames.recv().await {
+            let frame = mesh::MirrorFrame {
+                seq: frame.seq,
+                keyframe: frame.keyframe,
+                width: frame.width,
+                height: frame.height,
+                ansi: frame.ansi,
+            };
+            let result = match tokio::time::timeout(
+                MESH_REQUEST_TIMEOUT,
+                client.publish_frame(&mirror, &frame),
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!("timed out")),
+            };
+            // Keep publishing so viewers recover at the next keyframe, but
+            // only tell the user once
+            if let Err(err) = result {
+                if !reported {
+                    let _ = events.send(BackendEvent::error("mirror", &err));
+                    reported = true;
+                }
+            }
+        }
+        // The UI has exited; disconnect the viewers
+        let _ = client.end_mirror(&mirror).await;
+    });
+    Some(name)
+}
 
-    Ok(())
+/// Login name of whoever runs the TUI, recorded as the author of their messages
+fn local_author() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "tui".to_string())
+}
+
+/// Tell the presence task which session the TUI is in and what it is doing
+fn report_presence(
+    presence: &Option<watch::Sender<(String, Activity)>>,
+    session_id: &str,
+    activity: Activity,
+) {
+    if let Some(presence) = presence {
+        presence.send_if_modified(|current| {
+            let changed = current.0 != session_id || current.1 != activity;
+            *current = (session_id.to_string(), activity);
+            changed
+        });
+    }
+}
+
+/// Announce the TUI in its session on the API server and report who else is there.
+///
+/// Announces again on every change and every [`PRESENCE_INTERVAL`], and leaves
+/// the session once the backend drops the returned sender. Returns `None` if
+/// the server address is invalid.
+fn spawn_presence(
+    cli_state: &CliState,
+    author: &str,
+    event_tx: &UnboundedSender<BackendEvent>,
+) -> Option<watch::Sender<(String, Activity)>> {
+    let client = match registry_client(&registry_address(cli_state)) {
+        Ok(client) => client,
+        Err(err) => {
+            let _ = event_tx.send(BackendEvent::error("presence", &err));
+            return None;
+        }
+    };
+    let (presence_tx, mut presence_rx) =
+        watch::channel((cli_state.agent.session_id().to_string(), Activity::Idle));
+    let events = event_tx.clone();
+    let name = author.to_string();
+    let announcement = move |activity, leaving| PresenceAnnouncement {
+        name: name.clone(),
+        client: "tui".to_string(),
+        activity,
+        leaving,
+    };
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(PRESENCE_INTERVAL);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut announced: Option<String> = None;
+        let mut reported = false;
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => {}
+                changed = presence_rx.changed() => if changed.is_err() {
+                    break;
+                },
+            }
+            let (session_id, activity) = presence_rx.borrow_and_update().clone();
+            if let Some(previous) = announced.as_ref().filter(|id| **id != session_id) {
+                let _ = client
+                    .announce_presence(previous, &announcement(Activity::Idle, true))
+                    .await;
+            }
+            let result = match tokio::time::timeout(
+                MESH_REQUEST_TIMEOUT,
+                client.announce_presence(&session_id, &announcement(activity, false)),
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!("timed out")),
+            };
+            announced = Some(session_id);
+            match result {
+                Ok(snapshot) => {
+                    reported = false;
+                    let mine = announcement(activity, false);
+                    let participants = snapshot
+                        .participants
+                        .into_iter()
+                        .filter(|p| !(p.name == mine.name && p.client == mine.client))
+                        .collect();
+                    let event = BackendEvent::Presence {
+                        participants,
+                        pending_turns: snapshot.pending_turns,
+                    };
+                    if events.send(event).is_err() {
+                        break;
+                    }
+                }
+                // Keep announcing in case the server comes back, but only
+                // tell the user once
+                Err(err) if !reported => {
+                    let _ = events.send(BackendEvent::error("presence", &err));
+                    reported = true;
+                }
+                Err(_) => {}
+            }
+        }
+        // The backend has exited; leave without waiting to expire
+        if let Some(session_id) = announced {
+            let _ = client
+                .announce_presence(&session_id, &announcement(Activity::Idle, true))
+                .await;
+        }
+    });
+    Some(presence_tx)
+}
+
+/// Query the mesh registry and count recent messages per instance.
+async fn mesh_status(cli_state: &CliState) -> BackendEvent {
+    let registry = registry_address(cli_state);
+    let client = match registry_client(&registry) {
+        Ok(client) => client,
+        Err(err) => {
+            return BackendEvent::MeshStatus {
+                instances: Vec::new(),
+                leader_id: None,
+                message_counts: HashMap::new(),
+                error: Some(err.to_string()),
+            }
+        }
+    };
+
+    // The backend loop waits on this, so an unresponsive registry must not stall it
+    let response = match tokio::time::timeout(MESH_REQUEST_TIMEOUT, client.list_instances()).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("timed out")),
+    };
+
+    match response {
+        Ok(mut response) => {
+            let records = cli_state
+                .persistence
+                .mesh_message_get_history(None, MESH_MESSAGE_LIMIT)
+                .unwrap_or_default();
+            // Heartbeat times are on the registry's clock; move them onto
+            // ours so their age does not include the clocks' difference
+            if let Some(registry_time) = response.registry_time {
+                let offset = registry_time - chrono::Utc::now();
+                for instance in &mut response.instances {
+                    instance.last_heartbeat -= offset;
+                }
+            }
+            BackendEvent::MeshStatus {
+                message_counts: count_mesh_messages(&records),
+                instances: response.instances,
+                leader_id: response.leader_id,
+                error: None,
+            }
+        }
+        Err(err) => BackendEvent::MeshStatus {
+            instances: Vec::new(),
+            leader_id: None,
+            message_counts: HashMap::new(),
+            error: Some(format!(
+                "Mesh registry at {} unavailable: {}",
+                registry, err
+            )),
+        },
+    }
+}
+
+/// Count messages each instance sent or received.
+fn count_mesh_messages(records: &[MeshMessageRecord]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for record in records {
+        *counts.entry(record.source_instance.clone()).or_insert(0) += 1;
+        if let Some(target) = &record.target_instance {
+            if *target != record.source_instance {
+                *counts.entry(target.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Record the current session's agent and model selection and mark it active.
+fn record_session(cli_state: &CliState) {
+    let model = cli_state.active_model();
+    // Metadata is best-effort; messages are persisted regardless
+    let _ = cli_state.persistence.upsert_session(
+        cli_state.agent.session_id(),
+        cli_state.agent.agent_name(),
+        &model.provider,
+        model.model_name.as_deref(),
+    );
+}
+
+/// Load the persisted input history for the agent's current session.
+fn load_input_history(cli_state: &CliState) -> Vec<String> {
+    cli_state
+        .persistence
+        .list_input_history(cli_state.agent.session_id(), INPUT_HISTORY_LIMIT)
+        .unwrap_or_default()
 }
 
 fn initialize_cli_state(config_path: Option<PathBuf>) -> Result<CliState> {
@@ -243,6 +1098,14 @@ fn status_message_for_command(command: &Command) -> String {
         Command::PolicyReload => "Status: reloading policies".to_string(),
         Command::SwitchAgent(name) => format!("Status: switching to agent '{}'", name),
         Command::ListAgents => "Status: listing agents".to_string(),
+        Command::ListModels => "Status: listing models".to_string(),
+        Command::SwitchModel { model, .. } => format!("Status: switching to model '{}'", model),
+        Command::Usage => "Status: showing token usage".to_string(),
+        Command::Budget(BudgetCommand::Show) => "Status: showing step budget".to_string(),
+        Command::Budget(_) => "Status: changing step budget".to_string(),
+        Command::Preset(PresetCommand::Show) => "Status: listing presets".to_string(),
+        Command::Preset(PresetCommand::Use(_)) => "Status: changing preset".to_string(),
+        Command::Preset(PresetCommand::Once { .. }) => "Status: running agent step".to_string(),
         Command::MemoryShow(Some(limit)) => {
             format!("Status: showing last {} messages", limit)
         }
@@ -251,6 +1114,14 @@ fn status_message_for_command(command: &Command) -> String {
         Command::SessionNew(None) => "Status: starting new session".to_string(),
         Command::SessionList => "Status: listing sessions".to_string(),
         Command::SessionSwitch(id) => format!("Status: switching to session '{}'", id),
+        Command::Branch(BranchCommand::List) => "Status: listing branches".to_string(),
+        Command::Branch(BranchCommand::New) => "Status: starting a branch".to_string(),
+        Command::Branch(BranchCommand::Switch(id)) => {
+            format!("Status: switching to branch '{}'", id)
+        }
+        Command::Checkpoint(None) => "Status: listing checkpoints".to_string(),
+        Command::Checkpoint(Some(name)) => format!("Status: saving checkpoint '{}'", name),
+        Command::Rollback(name) => format!("Status: rolling back to '{}'", name),
         Command::GraphEnable => "Status: showing graph enable instructions".to_string(),
         Command::GraphDisable => "Status: showing graph disable instructions".to_string(),
         Command::GraphStatus => "Status: showing graph status".to_string(),
@@ -258,6 +1129,9 @@ fn status_message_for_command(command: &Command) -> String {
             format!("Status: inspecting graph (limit {})", limit)
         }
         Command::GraphShow(None) => "Status: inspecting graph".to_string(),
+        Command::GraphExport(export) => {
+            format!("Status: exporting graph as {}", export.format.as_str())
+        }
         Command::GraphClear => "Status: clearing session graph".to_string(),
         Command::SyncList => "Status: listing sync-enabled graphs".to_string(),
         Command::Init(_) => "Status: bootstrapping repository graph".to_string(),
@@ -346,6 +1220,12 @@ mod tests {
         assert!(status.contains("listing agents"));
     }
 
+    #[test]
+    fn status_message_usage() {
+        let status = status_message_for_command(&Command::Usage);
+        assert!(status.contains("token usage"));
+    }
+
     #[test]
     fn status_message_memory_show_with_limit() {
         let status = status_message_for_command(&Command::MemoryShow(Some(10)));
@@ -416,6 +1296,12 @@ mod tests {
         assert!(status.contains("inspecting graph"));
     }
 
+    #[test]
+    fn status_message_graph_export() {
+        let status = status_message_for_command(&parse_command("/graph export mermaid 7"));
+        assert!(status.contains("exporting graph as mermaid"));
+    }
+
     #[test]
     fn status_message_graph_clear() {
         let status = status_message_for_command(&Command::GraphClear);
@@ -520,17 +1406,23 @@ mod tests {
     fn backend_event_initialized_fields() {
         let event = BackendEvent::Initialized {
             agent: Some("test".to_string()),
+            session_id: "s1".to_string(),
             messages: vec![],
             reasoning: vec!["reasoning".to_string()],
             status: "ready".to_string(),
+            history: vec!["previous input".to_string()],
         };
         match event {
             BackendEvent::Initialized {
                 agent,
+                session_id,
                 messages,
                 reasoning,
                 status,
+                history,
             } => {
+                assert_eq!(session_id, "s1");
+                assert_eq!(history, vec!["previous input".to_string()]);
                 assert_eq!(agent, Some("test".to_string()));
                 assert!(messages.is_empty());
                 assert_eq!(reasoning.len(), 1);
@@ -545,23 +1437,65 @@ mod tests {
         let event = BackendEvent::Error {
             context: "ctx".to_string(),
             message: "msg".to_string(),
+            code: ErrorCode::Internal,
         };
         match event {
-            BackendEvent::Error { context, message } => {
+            BackendEvent::Error {
+                context,
+                message,
+                code,
+            } => {
                 assert_eq!(context, "ctx");
                 assert_eq!(message, "msg");
+                assert_eq!(code, ErrorCode::Internal);
             }
             _ => panic!("Wrong event type"),
         }
     }
 
+    #[test]
+    fn mesh_messages_counted_for_source_and_target() {
+        let record = |source: &str, target: Option<&str>| MeshMessageRecord {
+            id: 0,
+            source_instance: source.to_string(),
+            target_instance: target.map(str::to_string),
+            message_type: "query".to_string(),
+            payload: serde_json::json!({}),
+            status: "delivered".to_string(),
+            created_at: chrono::Utc::now(),
+            delivered_at: None,
+        };
+        let counts = count_mesh_messages(&[
+            record("a", Some("b")),
+            record("b", Some("a")),
+            record("a", None),
+            record("c", Some("c")),
+        ]);
+
+        assert_eq!(counts["a"], 3);
+        assert_eq!(counts["b"], 2);
+        // Messages to self count once
+        assert_eq!(counts["c"], 1);
+    }
+
     #[test]
     fn backend_request_submit_contains_text() {
-        let request = BackendRequest::Submit("test input".to_string());
+        let request = BackendRequest::Submit {
+            input: "test input".to_string(),
+            attachments: vec![],
+            edit: None,
+        };
         match request {
-            BackendRequest::Submit(text) => {
-                assert_eq!(text, "test input");
+            BackendRequest::Submit {
+                input,
+                attachments,
+                edit,
+            } => {
+                assert_eq!(input, "test input");
+                assert!(attachments.is_empty());
+                assert_eq!(edit, None);
             }
+            _ => panic!("Wrong request type"),
         }
     }
 }
@@ -1,7 +1,9 @@
 use crate::backend::BackendRequest;
+use crate::keymap::{Action, Context, EditMode};
 use crate::models::ChatMessage;
-use crate::state::{AppState, PanelFocus};
-use spec_ai_tui::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crate::state::{AppState, PanelFocus, APPROVAL_CHOICES};
+use spec_ai_core::agent::{AnswerForm, ApprovalDecision};
+use spec_ai_tui::event::{Attachment, Event, KeyCode, KeyEvent, KeyModifiers};
 use spec_ai_tui::widget::builtin::{EditorAction, Selection, SlashCommand};
 use tokio::sync::mpsc::UnboundedSender;
 
@@ -22,19 +24,119 @@ pub fn handle_event(
                 return false;
             }
 
+            // The agent is blocked until the approval card is answered
+            if state.pending_approval.is_some() {
+                handle_approval_key(key, state);
+                return true;
+            }
+
+            if state.pending_question.is_some() {
+                handle_question_key(key, state);
+                return true;
+            }
+
+            if !state.pending_attachments.is_empty() {
+                handle_attachment_key(key, state);
+                return true;
+            }
+
+            // Esc stops the reply; the partial answer stays in the conversation
+            if key.code == KeyCode::Esc && state.is_streaming() {
+                state.interrupt.interrupt();
+                state.status = "Interrupting...".to_string();
+                return true;
+            }
+
+            let global = state.keymap.action(Context::Global, key);
+            if global == Some(Action::ToggleViolations) {
+                state.toggle_violations();
+                return true;
+            }
+
+            // The violations overlay is modal until dismissed
+            if state.show_violations {
+                if state.keymap.action(Context::Overlay, key) == Some(Action::Close) {
+                    state.show_violations = false;
+                }
+                return true;
+            }
+
+            if global == Some(Action::ToggleSessions) {
+                if state.toggle_sessions() && backend_tx.send(BackendRequest::ListSessions).is_err()
+                {
+                    state.error = Some("Backend channel closed".to_string());
+                }
+                return true;
+            }
+
+            if state.show_sessions {
+                handle_sessions_key(key, state, backend_tx);
+                return true;
+            }
+
+            if global == Some(Action::ToggleMesh) {
+                let watch = state.toggle_mesh();
+                if backend_tx.send(BackendRequest::WatchMesh(watch)).is_err() {
+                    state.error = Some("Backend channel closed".to_string());
+                }
+                return true;
+            }
+
+            // The mesh overlay is modal until dismissed
+            if state.show_mesh {
+                if state.keymap.action(Context::Overlay, key) == Some(Action::Close) {
+                    state.show_mesh = false;
+                    let _ = backend_tx.send(BackendRequest::WatchMesh(false));
+                }
+                return true;
+            }
+
+            if global == Some(Action::ToggleTasks) {
+                state.toggle_tasks();
+                return true;
+            }
+
+            // The task panel is modal until dismissed
+            if state.show_tasks {
+                match state.keymap.action(Context::Overlay, key) {
+                    Some(Action::Close) => state.show_tasks = false,
+                    Some(Action::Previous) => state.select_task(-1),
+                    Some(Action::Next) => state.select_task(1),
+                    _ => {}
+                }
+                return true;
+            }
+
+            if state.show_models {
+                handle_models_key(key, state, backend_tx);
+                return true;
+            }
+
             match state.focus {
                 PanelFocus::Input => handle_input_key(&event, key, state, backend_tx),
                 PanelFocus::Chat => handle_chat_key(key, state),
             }
         }
+        Event::Paste(text) if state.pending_question.is_some() => {
+            if let Some(prompt) = state.pending_question.as_mut() {
+                if prompt.is_typing() {
+                    prompt.input.push_str(text);
+                }
+            }
+        }
         Event::Paste(_) => {
             if state.focus == PanelFocus::Input {
                 let was_showing = state.editor.show_slash_menu;
+                let was_mentioning = state.editor.show_mention_menu;
                 if let EditorAction::Handled = state.editor.handle_event(&event) {
                     sync_slash_menu_visibility(state, was_showing);
+                    sync_mention_menu_visibility(state, backend_tx, was_mentioning);
                 }
             }
         }
+        Event::Attach(attachments) => {
+            state.offer_attachments(attachments.clone());
+        }
         Event::Tick => {
             on_tick(state);
         }
@@ -50,55 +152,239 @@ pub fn handle_event(
 pub fn on_tick(state: &mut AppState) {
     state.tick = state.tick.saturating_add(1);
     state.drain_backend_events();
+    state.drop_abandoned_question();
 }
 
-fn handle_chat_key(key: &KeyEvent, state: &mut AppState) {
+/// Keys for the tool approval card
+fn handle_approval_key(key: &KeyEvent, state: &mut AppState) {
+    match key.code {
+        KeyCode::Left | KeyCode::BackTab => state.select_approval(-1),
+        KeyCode::Right | KeyCode::Tab => state.select_approval(1),
+        KeyCode::Enter => state.resolve_approval(APPROVAL_CHOICES[state.approval_choice]),
+        KeyCode::Char('y') => state.resolve_approval(ApprovalDecision::AllowOnce),
+        KeyCode::Char('a') => state.resolve_approval(ApprovalDecision::AllowAlways),
+        KeyCode::Char('n') | KeyCode::Esc => state.resolve_approval(ApprovalDecision::Deny),
+        _ => {}
+    }
+}
+
+/// Keys for the question card
+fn handle_question_key(key: &KeyEvent, state: &mut AppState) {
+    let Some(prompt) = state.pending_question.as_mut() else {
+        return;
+    };
+    let multiline = matches!(
+        prompt.request.question.form,
+        AnswerForm::Text {
+            multiline: true,
+            ..
+        }
+    );
+    let multiple = matches!(
+        prompt.request.question.form,
+        AnswerForm::Choice { multiple: true, .. }
+    );
+    match key.code {
+        KeyCode::Esc => state.decline_question(),
+        KeyCode::Enter if multiline && key.modifiers.contains(KeyModifiers::SHIFT) => {
+            prompt.input.push('\n');
+        }
+        KeyCode::Enter => state.answer_question(),
+        KeyCode::Up | KeyCode::BackTab => prompt.select(-1),
+        KeyCode::Down | KeyCode::Tab => prompt.select(1),
+        KeyCode::Char(' ') if multiple && !prompt.is_typing() => prompt.toggle(),
+        KeyCode::Backspace if prompt.is_typing() => {
+            prompt.input.pop();
+        }
+        KeyCode::Char(ch) if prompt.is_typing() => prompt.input.push(ch),
+        _ => {}
+    }
+}
+
+/// Keys for the attachment confirmation, which is modal while open
+fn handle_attachment_key(key: &KeyEvent, state: &mut AppState) {
+    match key.code {
+        KeyCode::Enter | KeyCode::Char('y') => state.confirm_attachments(),
+        KeyCode::Char('p') => {
+            // Fall back to what the terminal pasted: the paths themselves
+            let paths: Vec<String> = state
+                .decline_attachments()
+                .iter()
+                .filter_map(|attachment| match attachment {
+                    Attachment::File(path) => Some(path.display().to_string()),
+                    Attachment::Image { .. } => None,
+                })
+                .collect();
+            if !paths.is_empty() {
+                state.focus = PanelFocus::Input;
+                state.editor.handle_event(&Event::Paste(paths.join(" ")));
+            }
+        }
+        KeyCode::Char('n') | KeyCode::Esc => {
+            state.decline_attachments();
+            state.status = "Attachments discarded".to_string();
+        }
+        _ => {}
+    }
+}
+
+/// Keys for the session history overlay, which is modal while open
+fn handle_sessions_key(
+    key: &KeyEvent,
+    state: &mut AppState,
+    backend_tx: &UnboundedSender<BackendRequest>,
+) {
+    match state.keymap.action(Context::Overlay, key) {
+        Some(Action::Close) => state.show_sessions = false,
+        Some(Action::Previous) => state.select_session(-1),
+        Some(Action::Next) => state.select_session(1),
+        Some(Action::Select) => {
+            state.show_sessions = false;
+            let Some(session_id) = state.session_to_resume().map(|s| s.session_id.clone()) else {
+                return;
+            };
+            state.busy = true;
+            state.status = format!("Resuming session '{}'...", session_id);
+            if backend_tx
+                .send(BackendRequest::ResumeSession(session_id))
+                .is_err()
+            {
+                state.busy = false;
+                state.status = "Backend unavailable".to_string();
+                state.error = Some("Backend channel closed".to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Keys for the model picker; typing filters the list
+fn handle_models_key(
+    key: &KeyEvent,
+    state: &mut AppState,
+    backend_tx: &UnboundedSender<BackendRequest>,
+) {
+    // Typed characters always go to the search, even when bound to an action
     match key.code {
-        KeyCode::Down | KeyCode::Char('j') => {
+        KeyCode::Backspace => {
+            let mut query = state.model_query.clone();
+            query.pop();
+            state.set_model_query(query);
+            return;
+        }
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            let query = format!("{}{}", state.model_query, c);
+            state.set_model_query(query);
+            return;
+        }
+        _ => {}
+    }
+
+    match state.keymap.action(Context::Overlay, key) {
+        Some(Action::Close) => state.show_models = false,
+        Some(Action::Previous) => state.select_model(-1),
+        Some(Action::Next) => state.select_model(1),
+        Some(Action::Select) => {
+            state.show_models = false;
+            // Switching goes through /model so it shows up in the transcript
+            if let Some(choice) = state.model_to_switch().cloned() {
+                let command = format!("/model {} {}", choice.provider, choice.model);
+                submit_text(state, backend_tx, command);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_chat_key(key: &KeyEvent, state: &mut AppState) {
+    match state.keymap.action(Context::Chat, key) {
+        Some(Action::ScrollDown) => {
             if state.scroll_offset > 0 {
                 state.scroll_offset = state.scroll_offset.saturating_sub(1);
             } else {
-                state.focus = PanelFocus::Input;
-                state.editor.focused = true;
+                focus_input(state);
             }
         }
-        KeyCode::Up | KeyCode::Char('k') => {
+        Some(Action::ScrollUp) => {
             state.scroll_offset = state.scroll_offset.saturating_add(1);
         }
-        KeyCode::PageUp => {
+        Some(Action::PageUp) => {
             state.scroll_offset = state.scroll_offset.saturating_add(8);
         }
-        KeyCode::PageDown => {
+        Some(Action::PageDown) => {
             state.scroll_offset = state.scroll_offset.saturating_sub(8);
         }
-        KeyCode::Tab => {
-            state.focus = PanelFocus::Input;
-            state.editor.focused = true;
+        Some(Action::ScrollBottom) => {
+            state.scroll_offset = 0;
         }
+        Some(Action::EditMessage) => {
+            if state.edit_previous_message() {
+                focus_input(state);
+                state.status =
+                    "Editing an earlier message (Enter resubmits on a new branch, Esc cancels)"
+                        .to_string();
+            } else {
+                state.status = "No earlier message to edit".to_string();
+            }
+        }
+        Some(Action::FocusInput) => focus_input(state),
         _ => {}
     }
 }
 
+fn focus_input(state: &mut AppState) {
+    state.focus = PanelFocus::Input;
+    state.editor.focused = true;
+    state.edit_mode = EditMode::Insert;
+}
+
+fn focus_chat(state: &mut AppState) {
+    state.focus = PanelFocus::Chat;
+    state.editor.focused = false;
+}
+
 fn handle_input_key(
     event: &Event,
     key: &KeyEvent,
     state: &mut AppState,
     backend_tx: &UnboundedSender<BackendRequest>,
 ) {
-    // Global shortcuts while focused on input
-    if key.modifiers.contains(KeyModifiers::CONTROL) {
-        if let KeyCode::Char('l') = key.code {
-            state.messages.clear();
-            state.status = "Chat cleared".to_string();
-            state.scroll_offset = 0;
+    // Open menus and history search keep their own keys
+    let menu_open = state.editor.show_slash_menu
+        || state.editor.show_mention_menu
+        || state.editor.history.is_searching();
+    if !menu_open {
+        let context = state.input_context();
+        // Backspace on an empty line takes back the last attachment
+        if context == Context::Input
+            && key.code == KeyCode::Backspace
+            && state.editor.text.is_empty()
+        {
+            if let Some(attachment) = state.attachments.pop() {
+                state.status = format!("Removed {}", attachment.label());
+                return;
+            }
+        }
+        if let Some(action) = state.keymap.action(context, key) {
+            let was_showing = state.editor.show_slash_menu;
+            let was_mentioning = state.editor.show_mention_menu;
+            apply_editor_action(action, state);
+            sync_slash_menu_visibility(state, was_showing);
+            sync_mention_menu_visibility(state, backend_tx, was_mentioning);
+            return;
+        }
+        // Normal mode ignores unbound keys instead of typing them; Enter still submits
+        if context == Context::Normal && key.code != KeyCode::Enter {
             return;
         }
     }
 
     let was_showing = state.editor.show_slash_menu;
+    let was_mentioning = state.editor.show_mention_menu;
     match state.editor.handle_event(event) {
         EditorAction::Handled => {
             sync_slash_menu_visibility(state, was_showing);
+            sync_mention_menu_visibility(state, backend_tx, was_mentioning);
         }
         EditorAction::Submit(text) => {
             submit_text(state, backend_tx, text);
@@ -117,16 +403,33 @@ fn handle_input_key(
             let count = filtered_command_count(state);
             state.slash_menu.prev(count);
         }
+        EditorAction::MentionSelect(_) => {
+            complete_mention(state);
+        }
+        EditorAction::MentionMenuNext => {
+            let count = state.mention_matches().len();
+            state.mention_menu.next(count);
+        }
+        EditorAction::MentionMenuPrev => {
+            let count = state.mention_matches().len();
+            state.mention_menu.prev(count);
+        }
         EditorAction::Escape => {
+            state.cancel_edit();
             state.editor.show_slash_menu = false;
             state.editor.slash_query.clear();
             state.slash_menu.hide();
         }
         EditorAction::Ignored => match key.code {
-            KeyCode::Up if !state.editor.show_slash_menu => {
-                state.focus = PanelFocus::Chat;
-                state.editor.focused = false;
+            KeyCode::Up if state.editor.show_mention_menu => {
+                let count = state.mention_matches().len();
+                state.mention_menu.prev(count);
             }
+            KeyCode::Down if state.editor.show_mention_menu => {
+                let count = state.mention_matches().len();
+                state.mention_menu.next(count);
+            }
+            KeyCode::Up if !state.editor.show_slash_menu => focus_chat(state),
             KeyCode::Up if state.editor.show_slash_menu => {
                 let count = filtered_command_count(state);
                 state.slash_menu.prev(count);
@@ -141,36 +444,139 @@ fn handle_input_key(
             KeyCode::PageDown => {
                 state.scroll_offset = state.scroll_offset.saturating_sub(5);
             }
-            KeyCode::Tab => {
-                state.focus = PanelFocus::Chat;
-                state.editor.focused = false;
-            }
+            KeyCode::Tab => focus_chat(state),
             _ => {}
         },
     }
 }
 
+/// Run a bound editor command, including the vim mode switches
+fn apply_editor_action(action: Action, state: &mut AppState) {
+    let editor = &mut state.editor;
+    match action {
+        Action::FocusChat => focus_chat(state),
+        Action::ClearChat => {
+            state.messages.clear();
+            state.status = "Chat cleared".to_string();
+            state.scroll_offset = 0;
+        }
+        Action::PasteImage => match Attachment::from_clipboard() {
+            Some(image) => state.offer_attachments(vec![image]),
+            None => state.status = "No image on the clipboard".to_string(),
+        },
+        Action::Left => editor.move_left(false),
+        Action::Right => editor.move_right(false),
+        Action::WordLeft => editor.move_word_left(false),
+        Action::WordRight => editor.move_word_right(false),
+        Action::LineStart => editor.move_home(false),
+        Action::LineEnd => editor.move_end(false),
+        Action::DeleteChar => editor.delete(),
+        Action::DeleteBackward => editor.backspace(),
+        Action::DeleteWordBackward => editor.delete_word_backward(),
+        Action::DeleteWordForward => editor.delete_word_forward(),
+        Action::DeleteToStart => editor.delete_to_start(),
+        Action::DeleteToEnd => editor.delete_to_end(),
+        Action::HistoryPrevious => {
+            // Past the oldest entry, keep going up into the transcript
+            if !editor.history_previous() {
+                focus_chat(state);
+            }
+        }
+        Action::HistoryNext => {
+            editor.history_next();
+        }
+        Action::Undo => editor.undo(),
+        Action::Redo => editor.redo(),
+        Action::NormalMode => {
+            // Like vim, the cursor steps back onto the last character typed
+            editor.move_left(false);
+            state.edit_mode = EditMode::Normal;
+        }
+        Action::Insert => state.edit_mode = EditMode::Insert,
+        Action::Append => {
+            editor.move_right(false);
+            state.edit_mode = EditMode::Insert;
+        }
+        Action::InsertLineStart => {
+            editor.move_home(false);
+            state.edit_mode = EditMode::Insert;
+        }
+        Action::AppendLineEnd => {
+            editor.move_end(false);
+            state.edit_mode = EditMode::Insert;
+        }
+        Action::ChangeLine => {
+            editor.clear();
+            state.edit_mode = EditMode::Insert;
+        }
+        Action::ChangeToEnd => {
+            editor.delete_to_end();
+            state.edit_mode = EditMode::Insert;
+        }
+        _ => {}
+    }
+}
+
 fn submit_text(state: &mut AppState, backend_tx: &UnboundedSender<BackendRequest>, text: String) {
     let trimmed = text.trim();
     if trimmed.is_empty() {
+        state.cancel_edit();
         return;
     }
 
-    state.messages.push(ChatMessage::user(trimmed));
+    // A bare /model opens the picker instead of printing the model list
+    if matches!(trimmed, "/model" | "/models") {
+        state.editor.clear();
+        state.editor.show_slash_menu = false;
+        state.editor.slash_query.clear();
+        state.slash_menu.hide();
+        state.open_models();
+        if backend_tx.send(BackendRequest::ListModels).is_err() {
+            state.models_loading = false;
+            state.error = Some("Backend channel closed".to_string());
+        }
+        return;
+    }
+
+    // Slash commands leave attachments for the next message
+    let attachments = if trimmed.starts_with('/') {
+        Vec::new()
+    } else {
+        std::mem::take(&mut state.attachments)
+    };
+    let shown = if attachments.is_empty() {
+        trimmed.to_string()
+    } else {
+        let labels: Vec<String> = attachments.iter().map(|a| a.label()).collect();
+        format!("{}\n\nAttached: {}", trimmed, labels.join(", "))
+    };
+
+    // Slash commands run on the current branch and leave the edit open
+    let edit = if trimmed.starts_with('/') {
+        None
+    } else {
+        state.editing.take()
+    };
+
+    state.messages.push(ChatMessage::user(shown));
     state.scroll_offset = 0;
     state.busy = true;
     state.status = "Running command...".to_string();
     state.last_submitted_text = Some(trimmed.to_string());
 
+    // Slash menu selections bypass the editor's own Enter handling
+    state.editor.history.push(trimmed);
     state.editor.clear();
     state.editor.show_slash_menu = false;
     state.editor.slash_query.clear();
     state.slash_menu.hide();
 
-    if backend_tx
-        .send(BackendRequest::Submit(trimmed.to_string()))
-        .is_err()
-    {
+    let request = BackendRequest::Submit {
+        input: trimmed.to_string(),
+        attachments,
+        edit,
+    };
+    if backend_tx.send(request).is_err() {
         state.busy = false;
         state.status = "Backend unavailable".to_string();
         state.error = Some("Backend channel closed".to_string());
@@ -185,6 +591,47 @@ fn sync_slash_menu_visibility(state: &mut AppState, was_showing: bool) {
     }
 }
 
+/// Show the mention menu when an `@` is typed and refresh the file list
+fn sync_mention_menu_visibility(
+    state: &mut AppState,
+    backend_tx: &UnboundedSender<BackendRequest>,
+    was_showing: bool,
+) {
+    if state.editor.show_mention_menu && !was_showing {
+        state.mention_menu.show();
+        // Re-index on every open so new files show up
+        if !state.files_indexing && backend_tx.send(BackendRequest::IndexFiles).is_ok() {
+            state.files_indexing = true;
+        }
+    } else if !state.editor.show_mention_menu && was_showing {
+        state.mention_menu.hide();
+    } else if state.editor.show_mention_menu {
+        // The matches changed with the query, so start from the best one
+        state.mention_menu.selected = 0;
+    }
+}
+
+/// Insert the highlighted file into the editor as an `@path` mention
+pub fn complete_mention(state: &mut AppState) -> bool {
+    let matches = state.mention_matches();
+    let index = state
+        .mention_menu
+        .selected_index()
+        .min(matches.len().saturating_sub(1));
+    let Some(path) = matches.get(index).map(|path| path.to_string()) else {
+        state.editor.close_mention_menu();
+        state.mention_menu.hide();
+        return false;
+    };
+    state.editor.complete_mention(&path);
+    state.mention_menu.hide();
+    state.status = format!(
+        "Mentioned {} (its contents are sent with the message)",
+        path
+    );
+    true
+}
+
 fn filtered_command_count(state: &AppState) -> usize {
     state
         .slash_commands
@@ -479,4 +926,435 @@ mod tests {
         handle_event(Event::Tick, &mut state, &backend_tx);
         assert_eq!(state.tick, 1);
     }
+
+    #[test]
+    fn handle_event_ctrl_h_lists_and_resumes_sessions() {
+        let mut state = create_test_state();
+        state.session_id = Some("current".to_string());
+        let (backend_tx, mut backend_rx) = tokio::sync::mpsc::unbounded_channel();
+        let ctrl_h = Event::Key(KeyEvent::new(KeyCode::Char('h'), KeyModifiers::CONTROL));
+
+        handle_event(ctrl_h, &mut state, &backend_tx);
+        assert!(state.show_sessions);
+        assert!(matches!(
+            backend_rx.try_recv(),
+            Ok(BackendRequest::ListSessions)
+        ));
+
+        let now = chrono::Utc::now();
+        state.sessions = ["current", "older"]
+            .iter()
+            .map(|id| spec_ai_core::persistence::SessionRecord {
+                session_id: id.to_string(),
+                agent: None,
+                provider: None,
+                model: None,
+                title: None,
+                message_count: 0,
+                tool_call_count: 0,
+                created_at: now,
+                updated_at: now,
+            })
+            .collect();
+
+        let down = Event::Key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+        handle_event(down, &mut state, &backend_tx);
+        let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        handle_event(enter, &mut state, &backend_tx);
+
+        assert!(!state.show_sessions);
+        assert!(state.busy);
+        match backend_rx.try_recv() {
+            Ok(BackendRequest::ResumeSession(id)) => assert_eq!(id, "older"),
+            other => panic!("expected resume request, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn handle_event_esc_interrupts_streaming_reply() {
+        let (events_tx, events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut state = AppState::new(events_rx);
+        let backend_tx = create_backend_channel();
+        let interrupt = state.interrupt.clone();
+        let esc = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        // Nothing to interrupt before the reply starts
+        handle_event(esc.clone(), &mut state, &backend_tx);
+        assert!(!interrupt.is_interrupted());
+
+        events_tx
+            .send(crate::backend::BackendEvent::StreamStart)
+            .unwrap();
+        handle_event(esc, &mut state, &backend_tx);
+        assert!(interrupt.is_interrupted());
+        assert_eq!(state.status, "Interrupting...");
+    }
+
+    #[test]
+    fn handle_event_ctrl_p_toggles_violations_overlay() {
+        let mut state = create_test_state();
+        let backend_tx = create_backend_channel();
+        let ctrl_p = Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+
+        handle_event(ctrl_p.clone(), &mut state, &backend_tx);
+        assert!(state.show_violations);
+
+        // Typing is swallowed while the overlay is open
+        let typed = Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        handle_event(typed, &mut state, &backend_tx);
+        assert!(state.editor.text.is_empty());
+
+        let esc = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        handle_event(esc, &mut state, &backend_tx);
+        assert!(!state.show_violations);
+    }
+
+    #[test]
+    fn handle_event_ctrl_t_watches_mesh_until_closed() {
+        let mut state = create_test_state();
+        let (backend_tx, mut backend_rx) = tokio::sync::mpsc::unbounded_channel();
+        let ctrl_t = Event::Key(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL));
+
+        handle_event(ctrl_t, &mut state, &backend_tx);
+        assert!(state.show_mesh);
+        assert!(matches!(
+            backend_rx.try_recv(),
+            Ok(BackendRequest::WatchMesh(true))
+        ));
+
+        let esc = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        handle_event(esc, &mut state, &backend_tx);
+        assert!(!state.show_mesh);
+        assert!(matches!(
+            backend_rx.try_recv(),
+            Ok(BackendRequest::WatchMesh(false))
+        ));
+    }
+
+    #[test]
+    fn handle_event_ctrl_o_opens_task_panel() {
+        let mut state = create_test_state();
+        let backend_tx = create_backend_channel();
+        let ctrl_o = Event::Key(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL));
+
+        handle_event(ctrl_o.clone(), &mut state, &backend_tx);
+        assert!(state.show_tasks);
+
+        // Typing is swallowed while the panel is open
+        let typed = Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        handle_event(typed, &mut state, &backend_tx);
+        assert!(state.editor.text.is_empty());
+
+        handle_event(ctrl_o, &mut state, &backend_tx);
+        assert!(!state.show_tasks);
+    }
+
+    #[test]
+    fn handle_event_approval_card_answers_agent() {
+        let mut state = create_test_state();
+        let backend_tx = create_backend_channel();
+        let (request, mut decision) = spec_ai_core::agent::ToolApprovalRequest::new(
+            "bash",
+            "Run shell commands",
+            serde_json::json!({"command": "ls"}),
+            "tool 'bash' is not permitted",
+        );
+        state.pending_approval = Some(request);
+
+        // Typing does not reach the editor while the card is open
+        let typed = Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        handle_event(typed, &mut state, &backend_tx);
+        assert!(state.editor.text.is_empty());
+        assert!(state.pending_approval.is_some());
+
+        let right = Event::Key(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE));
+        handle_event(right, &mut state, &backend_tx);
+        let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        handle_event(enter, &mut state, &backend_tx);
+
+        assert!(state.pending_approval.is_none());
+        assert_eq!(decision.try_recv(), Ok(ApprovalDecision::AllowAlways));
+        assert!(state.status.contains("for this session"));
+    }
+
+    #[test]
+    fn handle_event_question_card_answers_agent() {
+        use spec_ai_core::agent::{UserAnswer, UserQuestion, UserQuestionRequest};
+
+        let mut state = create_test_state();
+        let backend_tx = create_backend_channel();
+        let question = UserQuestion::from_args(&serde_json::json!({
+            "question": "Which database?",
+            "choices": ["duckdb", "sqlite"],
+            "multiple": true,
+            "allow_other": true
+        }))
+        .unwrap();
+        let (request, mut answer) = UserQuestionRequest::new(question);
+        state.pending_question = Some(crate::state::QuestionPrompt::new(request));
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+
+        // Nothing ticked is not an answer, so the card stays open
+        handle_event(key(KeyCode::Enter), &mut state, &backend_tx);
+        let prompt = state.pending_question.as_ref().unwrap();
+        assert_eq!(prompt.problem.as_deref(), Some("select an option"));
+
+        handle_event(key(KeyCode::Down), &mut state, &backend_tx);
+        handle_event(key(KeyCode::Char(' ')), &mut state, &backend_tx);
+        handle_event(key(KeyCode::Down), &mut state, &backend_tx);
+        handle_event(key(KeyCode::Char('p')), &mut state, &backend_tx);
+        handle_event(key(KeyCode::Char('g')), &mut state, &backend_tx);
+        assert!(state.editor.text.is_empty());
+        handle_event(key(KeyCode::Enter), &mut state, &backend_tx);
+
+        assert!(state.pending_question.is_none());
+        assert_eq!(
+            answer.try_recv(),
+            Ok(UserAnswer::Choice {
+                selected: vec!["sqlite".into(), "pg".into()]
+            })
+        );
+    }
+
+    #[test]
+    fn handle_event_question_card_declines_on_esc() {
+        use spec_ai_core::agent::{UserAnswer, UserQuestion, UserQuestionRequest};
+
+        let mut state = create_test_state();
+        let backend_tx = create_backend_channel();
+        let question = UserQuestion::from_args(&serde_json::json!({"question": "Why?"})).unwrap();
+        let (request, mut answer) = UserQuestionRequest::new(question);
+        state.pending_question = Some(crate::state::QuestionPrompt::new(request));
+
+        let esc = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        handle_event(esc, &mut state, &backend_tx);
+
+        assert!(state.pending_question.is_none());
+        assert_eq!(answer.try_recv(), Ok(UserAnswer::Declined));
+    }
+
+    #[test]
+    fn dropped_files_are_confirmed_and_sent_with_the_next_message() {
+        let mut state = create_test_state();
+        let (backend_tx, mut backend_rx) = tokio::sync::mpsc::unbounded_channel();
+        let notes = Attachment::File("/tmp/notes.md".into());
+
+        handle_event(Event::Attach(vec![notes.clone()]), &mut state, &backend_tx);
+        assert_eq!(state.pending_attachments.len(), 1);
+        // Typing does not reach the editor until the drop is answered
+        let typed = Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        handle_event(typed, &mut state, &backend_tx);
+        assert!(state.editor.text.is_empty());
+
+        let yes = Event::Key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE));
+        handle_event(yes, &mut state, &backend_tx);
+        assert_eq!(state.attachments, vec![notes.clone()]);
+
+        // Commands leave the attachment in place
+        submit_text(&mut state, &backend_tx, "/help".to_string());
+        assert!(matches!(
+            backend_rx.try_recv(),
+            Ok(BackendRequest::Submit { attachments, .. }) if attachments.is_empty()
+        ));
+
+        submit_text(&mut state, &backend_tx, "summarize".to_string());
+        match backend_rx.try_recv() {
+            Ok(BackendRequest::Submit {
+                input,
+                attachments,
+                edit: None,
+            }) => {
+                assert_eq!(input, "summarize");
+                assert_eq!(attachments, vec![notes]);
+            }
+            other => panic!("expected submit, got {:?}", other),
+        }
+        assert!(state.attachments.is_empty());
+        assert!(state
+            .messages
+            .last()
+            .unwrap()
+            .content
+            .ends_with("Attached: notes.md"));
+    }
+
+    #[test]
+    fn dropped_files_can_be_inserted_as_text_or_removed() {
+        let mut state = create_test_state();
+        let backend_tx = create_backend_channel();
+        let drop = || Event::Attach(vec![Attachment::File("/tmp/a.rs".into())]);
+
+        handle_event(drop(), &mut state, &backend_tx);
+        let p = Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE));
+        handle_event(p, &mut state, &backend_tx);
+        assert!(state.pending_attachments.is_empty());
+        assert!(state.attachments.is_empty());
+        assert_eq!(state.editor.text, "/tmp/a.rs");
+
+        state.editor.clear();
+        handle_event(drop(), &mut state, &backend_tx);
+        let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        handle_event(enter, &mut state, &backend_tx);
+        assert_eq!(state.attachments.len(), 1);
+
+        let backspace = Event::Key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        handle_event(backspace, &mut state, &backend_tx);
+        assert!(state.attachments.is_empty());
+        assert_eq!(state.status, "Removed a.rs");
+    }
+
+    #[test]
+    fn model_command_opens_picker_and_switches() {
+        let mut state = create_test_state();
+        let (backend_tx, mut backend_rx) = tokio::sync::mpsc::unbounded_channel();
+        submit_text(&mut state, &backend_tx, "/model".to_string());
+        assert!(state.show_models);
+        assert!(state.messages.is_empty());
+        assert!(matches!(
+            backend_rx.try_recv(),
+            Ok(BackendRequest::ListModels)
+        ));
+
+        state.model_choices = ["llama3:latest", "mistral:7b"]
+            .iter()
+            .map(|model| crate::state::ModelChoice {
+                provider: "ollama".to_string(),
+                model: model.to_string(),
+            })
+            .collect();
+        for c in "mis".chars() {
+            let typed = Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            handle_event(typed, &mut state, &backend_tx);
+        }
+        assert_eq!(state.model_query, "mis");
+        assert!(state.editor.text.is_empty());
+
+        let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        handle_event(enter, &mut state, &backend_tx);
+        assert!(!state.show_models);
+        match backend_rx.try_recv() {
+            Ok(BackendRequest::Submit { input, .. }) => {
+                assert_eq!(input, "/model ollama mistral:7b")
+            }
+            other => panic!("expected model switch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mention_menu_indexes_files_and_completes_path() {
+        let mut state = create_test_state();
+        let (backend_tx, mut backend_rx) = tokio::sync::mpsc::unbounded_channel();
+        for c in "explain @edi".chars() {
+            let typed = Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            handle_event(typed, &mut state, &backend_tx);
+        }
+        assert!(state.editor.show_mention_menu);
+        assert!(state.mention_menu.visible);
+        assert!(state.files_indexing);
+        assert!(matches!(
+            backend_rx.try_recv(),
+            Ok(BackendRequest::IndexFiles)
+        ));
+        // Typing more does not queue another index
+        assert!(backend_rx.try_recv().is_err());
+
+        state.apply_backend_event(crate::backend::BackendEvent::FilesIndexed {
+            files: vec!["README.md".to_string(), "src/editor.rs".to_string()],
+        });
+        assert_eq!(state.mention_matches(), vec!["src/editor.rs"]);
+
+        let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        handle_event(enter, &mut state, &backend_tx);
+        assert_eq!(state.editor.text, "explain @src/editor.rs ");
+        assert!(!state.editor.show_mention_menu);
+        assert!(state.messages.is_empty());
+    }
+
+    fn press(state: &mut AppState, backend_tx: &UnboundedSender<BackendRequest>, keys: &str) {
+        for c in keys.chars() {
+            let typed = Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            handle_event(typed, state, backend_tx);
+        }
+    }
+
+    #[test]
+    fn vim_profile_switches_between_insert_and_normal_mode() {
+        let mut state = create_test_state();
+        let backend_tx = create_backend_channel();
+        state.keymap = crate::keymap::Keymap::new(crate::keymap::Profile::Vim);
+
+        press(&mut state, &backend_tx, "helo");
+        assert_eq!(state.edit_mode, EditMode::Insert);
+        let esc = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        handle_event(esc, &mut state, &backend_tx);
+        assert_eq!(state.edit_mode, EditMode::Normal);
+
+        // Normal mode keys edit instead of typing
+        press(&mut state, &backend_tx, "hal");
+        assert_eq!(state.editor.text, "hello");
+        assert_eq!(state.edit_mode, EditMode::Insert);
+
+        let esc = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        handle_event(esc, &mut state, &backend_tx);
+        press(&mut state, &backend_tx, "0xq");
+        assert_eq!(state.editor.text, "ello");
+        press(&mut state, &backend_tx, "A!");
+        assert_eq!(state.editor.text, "ello!");
+    }
+
+    #[test]
+    fn emacs_profile_edits_with_readline_keys() {
+        let mut state = create_test_state();
+        let backend_tx = create_backend_channel();
+        state.keymap = crate::keymap::Keymap::new(crate::keymap::Profile::Emacs);
+
+        press(&mut state, &backend_tx, "world");
+        let ctrl_a = Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL));
+        handle_event(ctrl_a, &mut state, &backend_tx);
+        press(&mut state, &backend_tx, "hello ");
+        assert_eq!(state.editor.text, "hello world");
+
+        let ctrl_k = Event::Key(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL));
+        handle_event(ctrl_k, &mut state, &backend_tx);
+        assert_eq!(state.editor.text, "hello ");
+
+        // Ctrl+P walks history, so it no longer opens the violations overlay
+        let ctrl_p = Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        handle_event(ctrl_p, &mut state, &backend_tx);
+        assert!(!state.show_violations);
+        let alt_p = Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::ALT));
+        handle_event(alt_p, &mut state, &backend_tx);
+        assert!(state.show_violations);
+    }
+
+    #[test]
+    fn edit_key_resubmits_an_earlier_message() {
+        let mut state = create_test_state();
+        let (backend_tx, mut backend_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut first = ChatMessage::user("first");
+        first.id = Some(7);
+        state.messages.push(first);
+        state.focus = PanelFocus::Chat;
+
+        let e = Event::Key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE));
+        handle_event(e, &mut state, &backend_tx);
+        assert_eq!(state.focus, PanelFocus::Input);
+        assert_eq!(state.editing, Some(7));
+        assert_eq!(state.editor.text, "first");
+
+        // Commands leave the edit open
+        submit_text(&mut state, &backend_tx, "/usage".to_string());
+        assert!(matches!(
+            backend_rx.try_recv(),
+            Ok(BackendRequest::Submit { edit: None, .. })
+        ));
+        assert_eq!(state.editing, Some(7));
+
+        submit_text(&mut state, &backend_tx, "first, reworded".to_string());
+        assert!(matches!(
+            backend_rx.try_recv(),
+            Ok(BackendRequest::Submit { edit: Some(7), .. })
+        ));
+        assert!(state.editing.is_none());
+    }
 }
@@ -0,0 +1,704 @@
+//! Key binding profiles: default, vim (modal editor) and emacs.
+//!
+//! A [`Keymap`] maps a key pressed in a [`Context`] to an [`Action`]. It starts
+//! from one of the built-in profiles and applies the `[ui.keymap.bindings]`
+//! overrides from the config, each replacing the keys of one action.
+
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use spec_ai_core::config::KeymapConfig;
+use spec_ai_tui::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A built-in set of bindings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Profile {
+    #[default]
+    Default,
+    /// Normal and insert modes in the editor, hjkl navigation elsewhere
+    Vim,
+    /// Readline editing keys and Ctrl+P/N navigation
+    Emacs,
+}
+
+impl Profile {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "vim" => Some(Self::Vim),
+            "emacs" => Some(Self::Emacs),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Vim => "vim",
+            Self::Emacs => "emacs",
+        }
+    }
+
+    fn bindings(self) -> &'static [Entry] {
+        match self {
+            Self::Default => DEFAULT_BINDINGS,
+            Self::Vim => VIM_BINDINGS,
+            Self::Emacs => EMACS_BINDINGS,
+        }
+    }
+}
+
+/// Editor mode in the vim profile; other profiles are always inserting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EditMode {
+    #[default]
+    Insert,
+    Normal,
+}
+
+/// Where a key is pressed; each context has its own bindings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    /// Shortcuts that work outside the approval card
+    Global,
+    /// The transcript, while it has focus
+    Chat,
+    /// The editor, or insert mode in the vim profile
+    Input,
+    /// Normal mode in the vim profile
+    Normal,
+    /// Session, model, mesh and policy overlays
+    Overlay,
+}
+
+impl Context {
+    const ALL: [Context; 5] = [
+        Self::Global,
+        Self::Chat,
+        Self::Input,
+        Self::Normal,
+        Self::Overlay,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Global => "global",
+            Self::Chat => "chat",
+            Self::Input => "input",
+            Self::Normal => "normal",
+            Self::Overlay => "overlay",
+        }
+    }
+}
+
+/// Something a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    ToggleViolations,
+    ToggleSessions,
+    ToggleMesh,
+    ToggleTasks,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    ScrollBottom,
+    EditMessage,
+    FocusInput,
+    FocusChat,
+    ClearChat,
+    PasteImage,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    DeleteChar,
+    DeleteBackward,
+    DeleteWordBackward,
+    DeleteWordForward,
+    DeleteToStart,
+    DeleteToEnd,
+    HistoryPrevious,
+    HistoryNext,
+    Undo,
+    Redo,
+    NormalMode,
+    Insert,
+    Append,
+    InsertLineStart,
+    AppendLineEnd,
+    ChangeLine,
+    ChangeToEnd,
+    Previous,
+    Next,
+    Select,
+    Close,
+}
+
+impl Action {
+    const ALL: [Action; 41] = [
+        Self::ToggleViolations,
+        Self::ToggleSessions,
+        Self::ToggleMesh,
+        Self::ToggleTasks,
+        Self::ScrollUp,
+        Self::ScrollDown,
+        Self::PageUp,
+        Self::PageDown,
+        Self::ScrollBottom,
+        Self::EditMessage,
+        Self::FocusInput,
+        Self::FocusChat,
+        Self::ClearChat,
+        Self::PasteImage,
+        Self::Left,
+        Self::Right,
+        Self::WordLeft,
+        Self::WordRight,
+        Self::LineStart,
+        Self::LineEnd,
+        Self::DeleteChar,
+        Self::DeleteBackward,
+        Self::DeleteWordBackward,
+        Self::DeleteWordForward,
+        Self::DeleteToStart,
+        Self::DeleteToEnd,
+        Self::HistoryPrevious,
+        Self::HistoryNext,
+        Self::Undo,
+        Self::Redo,
+        Self::NormalMode,
+        Self::Insert,
+        Self::Append,
+        Self::InsertLineStart,
+        Self::AppendLineEnd,
+        Self::ChangeLine,
+        Self::ChangeToEnd,
+        Self::Previous,
+        Self::Next,
+        Self::Select,
+        Self::Close,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ToggleViolations => "toggle_violations",
+            Self::ToggleSessions => "toggle_sessions",
+            Self::ToggleMesh => "toggle_mesh",
+            Self::ToggleTasks => "toggle_tasks",
+            Self::ScrollUp => "scroll_up",
+            Self::ScrollDown => "scroll_down",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::ScrollBottom => "scroll_bottom",
+            Self::EditMessage => "edit_message",
+            Self::FocusInput => "focus_input",
+            Self::FocusChat => "focus_chat",
+            Self::ClearChat => "clear_chat",
+            Self::PasteImage => "paste_image",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::WordLeft => "word_left",
+            Self::WordRight => "word_right",
+            Self::LineStart => "line_start",
+            Self::LineEnd => "line_end",
+            Self::DeleteChar => "delete_char",
+            Self::DeleteBackward => "delete_backward",
+            Self::DeleteWordBackward => "delete_word_backward",
+            Self::DeleteWordForward => "delete_word_forward",
+            Self::DeleteToStart => "delete_to_start",
+            Self::DeleteToEnd => "delete_to_end",
+            Self::HistoryPrevious => "history_previous",
+            Self::HistoryNext => "history_next",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::NormalMode => "normal_mode",
+            Self::Insert => "insert",
+            Self::Append => "append",
+            Self::InsertLineStart => "insert_line_start",
+            Self::AppendLineEnd => "append_line_end",
+            Self::ChangeLine => "change_line",
+            Self::ChangeToEnd => "change_to_end",
+            Self::Previous => "previous",
+            Self::Next => "next",
+            Self::Select => "select",
+            Self::Close => "close",
+        }
+    }
+}
+
+/// A key with its modifiers, as written in the config (e.g. "ctrl+p", "G", "pageup")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (modifier_names, key) = if spec == "+" {
+            ("", "+")
+        } else if let Some(modifiers) = spec.strip_suffix("++") {
+            (modifiers, "+")
+        } else {
+            spec.rsplit_once('+').unwrap_or(("", spec))
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match name.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "option" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier '{}' in key '{}'", name, spec),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "space" => KeyCode::Char(' '),
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => bail!("unknown key '{}'", spec),
+                },
+            },
+        };
+        Ok(Self::normalize(code, modifiers))
+    }
+
+    /// Whether `key` is this binding
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        *self == Self::normalize(key.code, key.modifiers)
+    }
+
+    /// Fold Shift into the key itself, as terminals report "G" and Shift+Tab
+    fn normalize(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(if shift { c.to_ascii_uppercase() } else { c })
+            }
+            KeyCode::Tab if shift => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chorded = self
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if chorded => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+type Entry = (Context, Action, &'static [&'static str]);
+
+const DEFAULT_BINDINGS: &[Entry] = &[
+    (Context::Global, Action::ToggleViolations, &["ctrl+p"]),
+    (Context::Global, Action::ToggleSessions, &["ctrl+h"]),
+    // Ctrl+M arrives as Enter in most terminals
+    (Context::Global, Action::ToggleMesh, &["ctrl+t"]),
+    (Context::Global, Action::ToggleTasks, &["ctrl+o"]),
+    (Context::Chat, Action::ScrollUp, &["up", "k"]),
+    (Context::Chat, Action::ScrollDown, &["down", "j"]),
+    (Context::Chat, Action::PageUp, &["pageup"]),
+    (Context::Chat, Action::PageDown, &["pagedown"]),
+    (Context::Chat, Action::ScrollBottom, &["end"]),
+    (Context::Chat, Action::EditMessage, &["e"]),
+    (Context::Chat, Action::FocusInput, &["tab"]),
+    (Context::Input, Action::FocusChat, &["tab"]),
+    (Context::Input, Action::ClearChat, &["ctrl+l"]),
+    (Context::Input, Action::PasteImage, &["ctrl+v", "alt+v"]),
+    (Context::Overlay, Action::Previous, &["up", "k"]),
+    (Context::Overlay, Action::Next, &["down", "j"]),
+    (Context::Overlay, Action::Select, &["enter"]),
+    (Context::Overlay, Action::Close, &["esc"]),
+];
+
+const VIM_BINDINGS: &[Entry] = &[
+    (Context::Global, Action::ToggleViolations, &["ctrl+p"]),
+    (Context::Global, Action::ToggleSessions, &["ctrl+h"]),
+    (Context::Global, Action::ToggleMesh, &["ctrl+t"]),
+    (Context::Global, Action::ToggleTasks, &["ctrl+o"]),
+    (Context::Chat, Action::ScrollUp, &["k", "up", "ctrl+y"]),
+    (Context::Chat, Action::ScrollDown, &["j", "down", "ctrl+e"]),
+    (
+        Context::Chat,
+        Action::PageUp,
+        &["ctrl+u", "ctrl+b", "pageup"],
+    ),
+    (
+        Context::Chat,
+        Action::PageDown,
+        &["ctrl+d", "ctrl+f", "pagedown"],
+    ),
+    (Context::Chat, Action::ScrollBottom, &["G", "end"]),
+    (Context::Chat, Action::EditMessage, &["e"]),
+    (Context::Chat, Action::FocusInput, &["i", "tab"]),
+    (Context::Input, Action::NormalMode, &["esc"]),
+    (Context::Input, Action::FocusChat, &["tab"]),
+    (Context::Input, Action::ClearChat, &["ctrl+l"]),
+    (Context::Input, Action::PasteImage, &["ctrl+v", "alt+v"]),
+    (Context::Input, Action::DeleteWordBackward, &["ctrl+w"]),
+    (Context::Input, Action::DeleteToStart, &["ctrl+u"]),
+    (Context::Normal, Action::Insert, &["i"]),
+    (Context::Normal, Action::Append, &["a"]),
+    (Context::Normal, Action::InsertLineStart, &["I"]),
+    (Context::Normal, Action::AppendLineEnd, &["A"]),
+    (Context::Normal, Action::ChangeLine, &["S"]),
+    (Context::Normal, Action::ChangeToEnd, &["C"]),
+    (Context::Normal, Action::Left, &["h", "left", "backspace"]),
+    (Context::Normal, Action::Right, &["l", "right", "space"]),
+    (Context::Normal, Action::WordLeft, &["b"]),
+    (Context::Normal, Action::WordRight, &["w", "e"]),
+    (Context::Normal, Action::LineStart, &["0", "^", "home"]),
+    (Context::Normal, Action::LineEnd, &["$", "end"]),
+    (Context::Normal, Action::DeleteChar, &["x", "delete"]),
+    (Context::Normal, Action::DeleteBackward, &["X"]),
+    (Context::Normal, Action::DeleteToEnd, &["D"]),
+    (Context::Normal, Action::HistoryPrevious, &["k", "up"]),
+    (Context::Normal, Action::HistoryNext, &["j", "down"]),
+    (Context::Normal, Action::Undo, &["u"]),
+    (Context::Normal, Action::Redo, &["ctrl+r"]),
+    (Context::Normal, Action::FocusChat, &["tab"]),
+    (Context::Normal, Action::ClearChat, &["ctrl+l"]),
+    (Context::Overlay, Action::Previous, &["up", "k", "ctrl+p"]),
+    (Context::Overlay, Action::Next, &["down", "j", "ctrl+n"]),
+    (Context::Overlay, Action::Select, &["enter"]),
+    (Context::Overlay, Action::Close, &["esc", "q"]),
+];
+
+const EMACS_BINDINGS: &[Entry] = &[
+    // Ctrl+P and Ctrl+N walk the input history
+    (Context::Global, Action::ToggleViolations, &["alt+p"]),
+    (Context::Global, Action::ToggleSessions, &["ctrl+h"]),
+    (Context::Global, Action::ToggleMesh, &["ctrl+t"]),
+    (Context::Global, Action::ToggleTasks, &["ctrl+o"]),
+    (Context::Chat, Action::ScrollUp, &["ctrl+p", "up"]),
+    (Context::Chat, Action::ScrollDown, &["ctrl+n", "down"]),
+    (Context::Chat, Action::PageUp, &["alt+v", "pageup"]),
+    (Context::Chat, Action::PageDown, &["ctrl+v", "pagedown"]),
+    (Context::Chat, Action::ScrollBottom, &["alt+>", "end"]),
+    (Context::Chat, Action::EditMessage, &["e"]),
+    (Context::Chat, Action::FocusInput, &["tab", "ctrl+g"]),
+    (Context::Input, Action::FocusChat, &["tab"]),
+    (Context::Input, Action::ClearChat, &["ctrl+l"]),
+    (Context::Input, Action::PasteImage, &["ctrl+v", "alt+v"]),
+    (Context::Input, Action::Left, &["ctrl+b"]),
+    (Context::Input, Action::Right, &["ctrl+f"]),
+    (Context::Input, Action::WordLeft, &["alt+b"]),
+    (Context::Input, Action::WordRight, &["alt+f"]),
+    (Context::Input, Action::LineStart, &["ctrl+a"]),
+    (Context::Input, Action::LineEnd, &["ctrl+e"]),
+    (Context::Input, Action::DeleteChar, &["ctrl+d"]),
+    (
+        Context::Input,
+        Action::DeleteWordBackward,
+        &["ctrl+w", "alt+backspace"],
+    ),
+    (Context::Input, Action::DeleteWordForward, &["alt+d"]),
+    (Context::Input, Action::DeleteToStart, &["ctrl+u"]),
+    (Context::Input, Action::DeleteToEnd, &["ctrl+k"]),
+    (Context::Input, Action::HistoryPrevious, &["ctrl+p"]),
+    (Context::Input, Action::HistoryNext, &["ctrl+n"]),
+    (Context::Input, Action::Undo, &["ctrl+_", "ctrl+/"]),
+    (Context::Overlay, Action::Previous, &["up", "ctrl+p"]),
+    (Context::Overlay, Action::Next, &["down", "ctrl+n"]),
+    (Context::Overlay, Action::Select, &["enter"]),
+    (Context::Overlay, Action::Close, &["esc", "ctrl+g"]),
+];
+
+#[derive(Debug, Clone)]
+struct Binding {
+    context: Context,
+    action: Action,
+    keys: Vec<KeyBinding>,
+}
+
+/// Key bindings in effect, looked up per context
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    profile: Profile,
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(Profile::Default)
+    }
+}
+
+impl Keymap {
+    /// The built-in bindings of `profile`
+    pub fn new(profile: Profile) -> Self {
+        let bindings = profile
+            .bindings()
+            .iter()
+            .map(|(context, action, keys)| Binding {
+                context: *context,
+                action: *action,
+                keys: keys
+                    .iter()
+                    .map(|key| KeyBinding::parse(key).expect("built-in key binding is valid"))
+                    .collect(),
+            })
+            .collect();
+        Self { profile, bindings }
+    }
+
+    /// The configured profile with the `[ui.keymap.bindings]` overrides applied
+    pub fn from_config(config: &KeymapConfig) -> Result<Self> {
+        let profile = Profile::parse(&config.profile)
+            .ok_or_else(|| anyhow!("unknown keymap profile '{}'", config.profile))?;
+        let mut keymap = Self::new(profile);
+        let mut problems = Vec::new();
+        for (name, keys) in &config.bindings {
+            if let Err(err) = keymap.rebind(name, keys) {
+                problems.push(format!("{}: {:#}", name, err));
+            }
+        }
+        if !problems.is_empty() {
+            bail!("invalid key bindings: {}", problems.join("; "));
+        }
+        Ok(keymap)
+    }
+
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Whether the editor has normal and insert modes
+    pub fn is_modal(&self) -> bool {
+        self.profile == Profile::Vim
+    }
+
+    /// Action bound to `key` in `context`
+    pub fn action(&self, context: Context, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|b| b.context == context && b.keys.iter().any(|k| k.matches(key)))
+            .map(|b| b.action)
+    }
+
+    /// Keys bound to `action` in `context`, in the order they are listed
+    pub fn keys(&self, context: Context, action: Action) -> &[KeyBinding] {
+        self.bindings
+            .iter()
+            .find(|b| b.context == context && b.action == action)
+            .map_or(&[], |b| b.keys.as_slice())
+    }
+
+    /// First key bound to `action`, for help text
+    pub fn hint(&self, context: Context, action: Action) -> String {
+        self.keys(context, action)
+            .first()
+            .map_or_else(|| "unbound".to_string(), |key| key.to_string())
+    }
+
+    /// Replace the keys of the action named "<context>.<action>"
+    ///
+    /// The keys are taken away from any other action in the same context.
+    fn rebind(&mut self, name: &str, keys: &[String]) -> Result<()> {
+        let (context_name, action_name) = name
+            .split_once('.')
+            .ok_or_else(|| anyhow!("expected \"<context>.<action>\""))?;
+        let context = Context::ALL
+            .into_iter()
+            .find(|c| c.name() == context_name)
+            .ok_or_else(|| anyhow!("unknown context '{}'", context_name))?;
+        let action = Action::ALL
+            .into_iter()
+            .find(|a| a.name() == action_name)
+            .ok_or_else(|| anyhow!("unknown action '{}'", action_name))?;
+        let keys = keys
+            .iter()
+            .map(|key| KeyBinding::parse(key))
+            .collect::<Result<Vec<_>>>()?;
+
+        for binding in self.bindings.iter_mut().filter(|b| b.context == context) {
+            binding.keys.retain(|key| !keys.contains(key));
+        }
+        match self
+            .bindings
+            .iter_mut()
+            .find(|b| b.context == context && b.action == action)
+        {
+            Some(binding) => binding.keys = keys,
+            None => self.bindings.push(Binding {
+                context,
+                action,
+                keys,
+            }),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn built_in_profiles_parse() {
+        for profile in [Profile::Default, Profile::Vim, Profile::Emacs] {
+            let keymap = Keymap::new(profile);
+            assert_eq!(Profile::parse(profile.name()), Some(profile));
+            assert!(!keymap.keys(Context::Overlay, Action::Close).is_empty());
+            assert!(!keymap.keys(Context::Global, Action::ToggleTasks).is_empty());
+        }
+        assert_eq!(Profile::parse("nano"), None);
+    }
+
+    #[test]
+    fn key_specs_match_terminal_events() {
+        let ctrl_p = KeyBinding::parse("ctrl+p").unwrap();
+        assert!(ctrl_p.matches(&key(KeyCode::Char('p'), KeyModifiers::CONTROL)));
+        assert!(!ctrl_p.matches(&key(KeyCode::Char('p'), KeyModifiers::NONE)));
+
+        // Terminals report an uppercase letter with Shift held
+        let big_g = KeyBinding::parse("G").unwrap();
+        assert!(big_g.matches(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+        assert_eq!(KeyBinding::parse("shift+g").unwrap(), big_g);
+        assert!(KeyBinding::parse("shift+tab")
+            .unwrap()
+            .matches(&key(KeyCode::BackTab, KeyModifiers::SHIFT)));
+        assert!(KeyBinding::parse("alt++")
+            .unwrap()
+            .matches(&key(KeyCode::Char('+'), KeyModifiers::ALT)));
+
+        assert_eq!(ctrl_p.to_string(), "Ctrl+P");
+        assert_eq!(KeyBinding::parse("pageup").unwrap().to_string(), "PgUp");
+        assert!(KeyBinding::parse("hyper+x").is_err());
+        assert!(KeyBinding::parse("pgup").is_err());
+    }
+
+    #[test]
+    fn profiles_bind_keys_per_context() {
+        let j = key(KeyCode::Char('j'), KeyModifiers::NONE);
+        let ctrl_p = key(KeyCode::Char('p'), KeyModifiers::CONTROL);
+
+        let default = Keymap::default();
+        assert_eq!(default.action(Context::Chat, &j), Some(Action::ScrollDown));
+        assert_eq!(default.action(Context::Input, &j), None);
+        assert!(!default.is_modal());
+
+        let vim = Keymap::new(Profile::Vim);
+        assert_eq!(vim.action(Context::Normal, &j), Some(Action::HistoryNext));
+        assert_eq!(vim.action(Context::Input, &j), None);
+        assert!(vim.is_modal());
+
+        let emacs = Keymap::new(Profile::Emacs);
+        assert_eq!(
+            emacs.action(Context::Input, &ctrl_p),
+            Some(Action::HistoryPrevious)
+        );
+        assert_eq!(emacs.action(Context::Global, &ctrl_p), None);
+        assert_eq!(
+            emacs.hint(Context::Global, Action::ToggleViolations),
+            "Alt+P"
+        );
+    }
+
+    #[test]
+    fn config_overrides_replace_profile_keys() {
+        let mut bindings = BTreeMap::new();
+        bindings.insert("chat.scroll_up".to_string(), vec!["ctrl+y".to_string()]);
+        bindings.insert("chat.focus_input".to_string(), vec!["k".to_string()]);
+        bindings.insert("global.toggle_mesh".to_string(), Vec::new());
+        let config = KeymapConfig {
+            profile: "default".to_string(),
+            bindings,
+        };
+
+        let keymap = Keymap::from_config(&config).unwrap();
+        let k = key(KeyCode::Char('k'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(Context::Chat, &k), Some(Action::FocusInput));
+        assert_eq!(
+            keymap.keys(Context::Chat, Action::ScrollUp),
+            [KeyBinding::parse("ctrl+y").unwrap()]
+        );
+        assert_eq!(keymap.hint(Context::Global, Action::ToggleMesh), "unbound");
+
+        let mut config = config;
+        config
+            .bindings
+            .insert("chat.fly".to_string(), vec!["f".to_string()]);
+        config
+            .bindings
+            .insert("editor.undo".to_string(), vec!["u".to_string()]);
+        let err = Keymap::from_config(&config).unwrap_err().to_string();
+        assert!(err.contains("chat.fly: unknown action 'fly'"), "{}", err);
+        assert!(
+            err.contains("editor.undo: unknown context 'editor'"),
+            "{}",
+            err
+        );
+    }
+}
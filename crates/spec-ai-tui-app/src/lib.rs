@@ -1,5 +1,7 @@
 mod backend;
 mod handlers;
+mod keymap;
+mod mentions;
 mod models;
 mod state;
 mod ui;
@@ -7,26 +9,36 @@ mod ui;
 use anyhow::Result;
 use backend::{spawn_backend, BackendEvent, BackendHandle, BackendRequest};
 use handlers::{handle_event, on_tick};
+use spec_ai_core::agent::RunInterrupt;
+use spec_ai_core::shutdown::{ShutdownCoordinator, DEFAULT_STOP_TIMEOUT};
 use spec_ai_tui::{
-    app::{App, AppRunner},
+    app::{App, AppRunner, MirrorFrame, PaneCache, RenderMode, Transcript},
     buffer::Buffer,
     event::Event,
     geometry::Rect,
 };
 use state::AppState;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 struct SpecAiTuiApp {
     backend_tx: tokio::sync::mpsc::UnboundedSender<BackendRequest>,
     backend_rx: Mutex<Option<tokio::sync::mpsc::UnboundedReceiver<BackendEvent>>>,
+    interrupt: RunInterrupt,
+    panes: Arc<PaneCache>,
 }
 
 impl SpecAiTuiApp {
-    fn new(handle: BackendHandle) -> Self {
+    fn new(
+        backend_tx: tokio::sync::mpsc::UnboundedSender<BackendRequest>,
+        backend_rx: tokio::sync::mpsc::UnboundedReceiver<BackendEvent>,
+        interrupt: RunInterrupt,
+    ) -> Self {
         Self {
-            backend_tx: handle.request_tx,
-            backend_rx: Mutex::new(Some(handle.event_rx)),
+            backend_tx,
+            backend_rx: Mutex::new(Some(backend_rx)),
+            interrupt,
+            panes: Arc::new(ui::pane_cache()),
         }
     }
 }
@@ -41,7 +53,9 @@ impl App for SpecAiTuiApp {
             .expect("backend receiver poisoned")
             .take()
             .expect("backend receiver already taken");
-        AppState::new(rx)
+        let mut state = AppState::new(rx);
+        state.interrupt = self.interrupt.clone();
+        state
     }
 
     fn handle_event(&mut self, event: Event, state: &mut Self::State) -> bool {
@@ -53,15 +67,59 @@ impl App for SpecAiTuiApp {
     }
 
     fn render(&self, state: &Self::State, area: Rect, buf: &mut Buffer) {
-        ui::render(state, area, buf);
+        ui::render(state, &self.panes, area, buf);
     }
+
+    fn render_mode(&self, state: &Self::State) -> Option<RenderMode> {
+        Some(if state.screen_reader {
+            RenderMode::Linear
+        } else {
+            RenderMode::Screen
+        })
+    }
+
+    fn transcript(&self, state: &Self::State) -> Option<Transcript> {
+        Some(ui::transcript(state))
+    }
+}
+
+/// Run the spec-ai TUI app, optionally providing an explicit config path
+/// and the agent profile to start with.
+pub async fn run_tui(config_path: Option<PathBuf>, agent: Option<String>) -> Result<()> {
+    let mut shutdown = ShutdownCoordinator::new();
+    let (frame_tx, frame_rx) = tokio::sync::mpsc::unbounded_channel();
+    let BackendHandle {
+        request_tx,
+        event_rx,
+        task,
+        interrupt,
+    } = spawn_backend(config_path, agent, frame_rx, shutdown.token())?;
+    // The backend is aborted if it is stuck inside an agent step
+    shutdown.register_task("agent backend", DEFAULT_STOP_TIMEOUT, task);
+    shutdown.stop_tasks();
+    shutdown.stop_plugins();
+
+    let result = run_app(SpecAiTuiApp::new(request_tx, event_rx, interrupt), frame_tx).await;
+
+    // The terminal is restored by now, so failures can be printed
+    let report = shutdown.shutdown().await;
+    if !report.is_clean() {
+        eprintln!("spec-ai did not shut down cleanly: {}", report);
+    }
+    result
 }
 
-/// Run the spec-ai TUI app, optionally providing an explicit config path.
-pub async fn run_tui(config_path: Option<PathBuf>) -> Result<()> {
-    let backend = spawn_backend(config_path)?;
-    let app = SpecAiTuiApp::new(backend);
-    let mut runner = AppRunner::new(app)?;
+async fn run_app(
+    app: SpecAiTuiApp,
+    frames: tokio::sync::mpsc::UnboundedSender<MirrorFrame>,
+) -> Result<()> {
+    let panes = app.panes.clone();
+    // The backend drops the receiver unless `[ui] mirror` is set
+    let mut runner = AppRunner::new(app)?.mirror(frames);
     runner.run().await?;
+    // Shows how much drawing the pane cache saved, once the screen is back
+    if std::env::var_os("SPEC_AI_TUI_RENDER_STATS").is_some() {
+        eprintln!("Pane renders: {}", panes.stats());
+    }
     Ok(())
 }
@@ -0,0 +1,343 @@
+//! `@` file mentions: workspace indexing, fuzzy ranking and attachment.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use spec_ai_core::shutdown::CancellationToken;
+
+/// Most files indexed, so huge trees don't stall the picker
+pub const MAX_INDEXED_FILES: usize = 20_000;
+
+/// Most candidates shown in the picker
+pub const MAX_MATCHES: usize = 50;
+
+/// Largest slice of a single file attached to a query
+const MAX_FILE_BYTES: usize = 64 * 1024;
+
+/// Attachment budget across every file mentioned in one query
+const MAX_TOTAL_BYTES: usize = 256 * 1024;
+
+/// Directories that are never worth mentioning
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// List files under `root` as `/`-separated relative paths, sorted.
+///
+/// Hidden entries and build output are skipped. The walk stops early, with
+/// the files found so far, once `cancel` is cancelled.
+pub fn index_workspace(root: &Path, cancel: &CancellationToken) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                if !SKIPPED_DIRS.contains(&name.as_ref()) {
+                    pending.push(path);
+                }
+            } else if file_type.is_file() {
+                if let Some(relative) = relative_path(root, &path) {
+                    files.push(relative);
+                }
+                if files.len() >= MAX_INDEXED_FILES {
+                    files.sort();
+                    return files;
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// Score `path` against `query` as a case-insensitive subsequence match.
+///
+/// Higher is better; `None` means the query does not match. Consecutive
+/// characters, segment starts and hits in the file name score extra.
+pub fn fuzzy_score(query: &str, path: &str) -> Option<i64> {
+    let path_chars: Vec<char> = path.chars().collect();
+    let file_start = path
+        .rfind('/')
+        .map_or(0, |idx| path[..idx].chars().count() + 1);
+    let mut score = 0i64;
+    let mut next = 0usize;
+    let mut previous: Option<usize> = None;
+
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        let found = (next..path_chars.len())
+            .find(|&idx| path_chars[idx].to_lowercase().eq(std::iter::once(wanted)))?;
+        score += 1;
+        if previous.is_some_and(|prev| prev + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(path_chars[found - 1], '/' | '_' | '-' | '.') {
+            score += 3;
+        }
+        if found >= file_start {
+            score += 2;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+
+    // Prefer shorter paths among otherwise equal matches
+    Some(score * 100 - path_chars.len() as i64)
+}
+
+/// Best matches for `query`, best first, at most `limit` of them.
+pub fn rank_files<'a>(files: &'a [String], query: &str, limit: usize) -> Vec<&'a str> {
+    let mut scored: Vec<(i64, &str)> = files
+        .iter()
+        .filter_map(|path| fuzzy_score(query, path).map(|score| (score, path.as_str())))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// Paths written as `@path` in `text`, in order of first mention.
+fn mentioned_paths(text: &str) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    text.split_whitespace()
+        .filter_map(|token| token.strip_prefix('@'))
+        .map(|path| path.trim_end_matches([',', ';', ':', ')', '!', '?', '"', '\'']))
+        .filter(|path| !path.is_empty() && seen.insert(*path))
+        .collect()
+}
+
+/// Resolve a mention to a file inside `root`, tolerating trailing sentence punctuation.
+fn resolve(root: &Path, mention: &str) -> Option<(String, PathBuf)> {
+    let canonical_root = root.canonicalize().ok()?;
+    let mut candidate = mention;
+    loop {
+        if let Ok(path) = root.join(candidate).canonicalize() {
+            if path.is_file() && path.starts_with(&canonical_root) {
+                return Some((candidate.to_string(), path));
+            }
+        }
+        candidate = candidate.strip_suffix('.')?;
+    }
+}
+
+/// Append the contents of every `@path` mentioned in `text` to the query.
+///
+/// Files are truncated to keep the query bounded; binary files are noted but
+/// not attached. Text without resolvable mentions is returned unchanged.
+pub fn attach_mentions(text: &str, root: &Path) -> String {
+    attach_files(text, root, &[])
+}
+
+/// [`attach_mentions`], followed by the files in `attached`.
+///
+/// Attached files were dropped or pasted by the user, so unlike mentions they
+/// may live outside `root`. A file both mentioned and attached is included once.
+pub fn attach_files(text: &str, root: &Path, attached: &[PathBuf]) -> String {
+    let mut files: Vec<(String, PathBuf)> = mentioned_paths(text)
+        .into_iter()
+        .filter_map(|mention| resolve(root, mention))
+        .collect();
+    for path in attached {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if !files.iter().any(|(_, known)| *known == canonical) {
+            files.push((path.display().to_string(), canonical));
+        }
+    }
+
+    let mut sections = Vec::new();
+    let mut budget = MAX_TOTAL_BYTES;
+
+    for (display, path) in files {
+        if budget == 0 {
+            sections.push(format!(
+                "<file path=\"{}\">\n[omitted: attachment limit reached]\n</file>",
+                display
+            ));
+            continue;
+        }
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                sections.push(format!(
+                    "<file path=\"{}\">\n[unreadable: {}]\n</file>",
+                    display, err
+                ));
+                continue;
+            }
+        };
+        let Ok(content) = String::from_utf8(bytes) else {
+            let note = if is_image(&path) {
+                "image, read it from the path"
+            } else {
+                "omitted: binary file"
+            };
+            sections.push(format!("<file path=\"{}\">\n[{}]\n</file>", display, note));
+            continue;
+        };
+
+        let limit = budget.min(MAX_FILE_BYTES);
+        let (content, truncated) = truncate(&content, limit);
+        budget -= content.len();
+        let marker = if truncated {
+            format!("\n[truncated to {} bytes]", content.len())
+        } else {
+            String::new()
+        };
+        sections.push(format!(
+            "<file path=\"{}\">\n{}{}\n</file>",
+            display, content, marker
+        ));
+    }
+
+    if sections.is_empty() {
+        return text.to_string();
+    }
+    format!("{}\n\nAttached files:\n{}", text, sections.join("\n"))
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["png", "jpg", "jpeg", "gif", "webp", "bmp"]
+                .contains(&ext.to_ascii_lowercase().as_str())
+        })
+}
+
+/// Cut `content` to at most `limit` bytes on a character boundary.
+fn truncate(content: &str, limit: usize) -> (&str, bool) {
+    if content.len() <= limit {
+        return (content, false);
+    }
+    let mut end = limit;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&content[..end], true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("spec-ai-mentions-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/widget")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/widget/editor.rs"), "pub struct Editor;\n").unwrap();
+        fs::write(root.join("target/debug/app"), "binary").unwrap();
+        fs::write(root.join(".git/HEAD"), "ref").unwrap();
+        root
+    }
+
+    #[test]
+    fn index_skips_hidden_and_build_dirs() {
+        let root = workspace("index");
+        assert_eq!(
+            index_workspace(&root, &CancellationToken::new()),
+            vec!["Cargo.toml", "src/main.rs", "src/widget/editor.rs"]
+        );
+
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        assert!(index_workspace(&root, &cancelled).is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn ranking_prefers_tight_file_name_matches() {
+        let files: Vec<String> = [
+            "src/widget/editor.rs",
+            "docs/engineering-design-report.md",
+            "src/main.rs",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(fuzzy_score("xyz", "src/main.rs"), None);
+        assert_eq!(
+            rank_files(&files, "edit", 10),
+            vec!["src/widget/editor.rs", "docs/engineering-design-report.md"]
+        );
+        assert_eq!(rank_files(&files, "", 2).len(), 2);
+    }
+
+    #[test]
+    fn attach_mentions_inlines_file_contents() {
+        let root = workspace("attach");
+        fs::write(root.join("big.txt"), "é".repeat(MAX_FILE_BYTES)).unwrap();
+        fs::write(root.join("blob.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let query = attach_mentions("explain @src/main.rs.", &root);
+        assert!(query.starts_with("explain @src/main.rs."));
+        assert!(query.contains("<file path=\"src/main.rs\">\nfn main() {}\n\n</file>"));
+
+        let query = attach_mentions("@big.txt @blob.bin @missing.rs @../outside", &root);
+        assert!(query.contains("[truncated to 65536 bytes]"));
+        assert!(query.contains("<file path=\"blob.bin\">\n[omitted: binary file]"));
+        assert!(!query.contains("missing.rs\">"));
+
+        assert_eq!(attach_mentions("mail me @ noon", &root), "mail me @ noon");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn attach_files_includes_dropped_files_outside_the_workspace() {
+        let root = workspace("dropped");
+        let outside = workspace("dropped-outside");
+        let notes = outside.join("notes.md");
+        let screenshot = outside.join("shot.png");
+        fs::write(&notes, "remember the milk\n").unwrap();
+        fs::write(&screenshot, b"\x89PNG\r\n\x1a\n").unwrap();
+
+        let query = attach_files(
+            "summarize @src/main.rs",
+            &root,
+            &[notes.clone(), screenshot.clone(), root.join("src/main.rs")],
+        );
+        assert!(query.contains(&format!(
+            "<file path=\"{}\">\nremember the milk\n",
+            notes.display()
+        )));
+        assert!(query.contains(&format!(
+            "<file path=\"{}\">\n[image, read it from the path]",
+            screenshot.display()
+        )));
+        // Mentioned and dropped, attached once
+        assert_eq!(query.matches("fn main()").count(), 1);
+
+        fs::remove_dir_all(root).unwrap();
+        fs::remove_dir_all(outside).unwrap();
+    }
+}
@@ -1,12 +1,14 @@
 use chrono::{DateTime, Local, Utc};
-use spec_ai_core::types::{Message, MessageRole};
+use spec_ai_core::types::{Message, MessageRole, ToolLog};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ChatRole {
     User,
     Assistant,
     System,
     Agent(String),
+    /// A tool call, with the tool's name
+    Tool(String),
 }
 
 impl ChatRole {
@@ -16,15 +18,18 @@ impl ChatRole {
             ChatRole::Assistant => "Assistant".to_string(),
             ChatRole::System => "System".to_string(),
             ChatRole::Agent(id) => format!("Agent {id}"),
+            ChatRole::Tool(name) => format!("Tool {name}"),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct ChatMessage {
     pub role: ChatRole,
     pub content: String,
     pub timestamp: String,
+    /// Id of the persisted message this shows, once the backend has stored it
+    pub id: Option<i64>,
 }
 
 impl ChatMessage {
@@ -33,6 +38,7 @@ impl ChatMessage {
             role: ChatRole::System,
             content: content.into(),
             timestamp: Local::now().format("%H:%M:%S").to_string(),
+            id: None,
         }
     }
 
@@ -41,6 +47,7 @@ impl ChatMessage {
             role: ChatRole::User,
             content: content.into(),
             timestamp: Local::now().format("%H:%M:%S").to_string(),
+            id: None,
         }
     }
 
@@ -49,6 +56,7 @@ impl ChatMessage {
             role: ChatRole::Assistant,
             content: content.into(),
             timestamp: Local::now().format("%H:%M:%S").to_string(),
+            id: None,
         }
     }
 
@@ -64,10 +72,44 @@ impl ChatMessage {
             role,
             content: message.content.clone(),
             timestamp: format_timestamp(message.created_at),
+            id: Some(message.id),
+        }
+    }
+
+    /// A persisted tool call: its arguments, then the error if it failed
+    pub fn from_tool_call(call: &ToolLog) -> Self {
+        let mut content = format!("`{}`", call.arguments);
+        match &call.error {
+            Some(error) if !call.success => content.push_str(&format!("\n\nFailed: {}", error)),
+            _ if !call.success => content.push_str("\n\nFailed"),
+            _ => {}
+        }
+
+        Self {
+            role: ChatRole::Tool(call.tool_name.clone()),
+            content,
+            timestamp: format_timestamp(call.created_at),
+            id: None,
         }
     }
 }
 
+/// A session's messages and tool calls interleaved in the order they happened
+pub fn transcript(messages: &[Message], tool_calls: &[ToolLog]) -> Vec<ChatMessage> {
+    let mut entries: Vec<(DateTime<Utc>, ChatMessage)> = messages
+        .iter()
+        .map(|m| (m.created_at, ChatMessage::from_backend(m)))
+        .chain(
+            tool_calls
+                .iter()
+                .map(|c| (c.created_at, ChatMessage::from_tool_call(c))),
+        )
+        .collect();
+    // Stable sort keeps each source's own order for equal timestamps
+    entries.sort_by_key(|(at, _)| *at);
+    entries.into_iter().map(|(_, message)| message).collect()
+}
+
 fn format_timestamp(timestamp: DateTime<Utc>) -> String {
     timestamp
         .with_timezone(&Local)
@@ -148,6 +190,7 @@ mod tests {
             role,
             content: content.to_string(),
             created_at: Utc::now(),
+            author: None,
         }
     }
 
@@ -194,6 +237,54 @@ mod tests {
         assert!(formatted.chars().nth(5) == Some(':'));
     }
 
+    #[test]
+    fn chat_message_from_tool_call_shows_failure() {
+        let call = make_test_tool_call("FileTool", Utc::now(), Some("not found"));
+        let chat_msg = ChatMessage::from_tool_call(&call);
+        assert_eq!(chat_msg.role, ChatRole::Tool("FileTool".to_string()));
+        assert_eq!(chat_msg.role.label(), "Tool FileTool");
+        assert!(chat_msg.content.contains(r#"{"path":"a.txt"}"#));
+        assert!(chat_msg.content.contains("Failed: not found"));
+    }
+
+    #[test]
+    fn transcript_interleaves_tool_calls_by_time() {
+        let start = Utc::now();
+        let mut question = make_test_message(MessageRole::User, "read a.txt");
+        question.created_at = start;
+        let mut answer = make_test_message(MessageRole::Assistant, "done");
+        answer.created_at = start + chrono::Duration::seconds(2);
+        let call = make_test_tool_call("FileTool", start + chrono::Duration::seconds(1), None);
+
+        let roles: Vec<ChatRole> = transcript(&[question, answer], &[call])
+            .into_iter()
+            .map(|m| m.role)
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                ChatRole::User,
+                ChatRole::Tool("FileTool".to_string()),
+                ChatRole::Assistant
+            ]
+        );
+    }
+
+    fn make_test_tool_call(name: &str, at: DateTime<Utc>, error: Option<&str>) -> ToolLog {
+        ToolLog {
+            id: 0,
+            session_id: "test-session".to_string(),
+            agent: "tester".to_string(),
+            run_id: "run-1".to_string(),
+            tool_name: name.to_string(),
+            arguments: serde_json::json!({"path": "a.txt"}),
+            result: serde_json::Value::Null,
+            success: error.is_none(),
+            error: error.map(str::to_string),
+            created_at: at,
+        }
+    }
+
     #[test]
     fn chat_role_equality() {
         assert_eq!(ChatRole::User, ChatRole::User);
@@ -1,15 +1,153 @@
 use crate::backend::BackendEvent;
-use crate::models::ChatMessage;
+use crate::keymap::{Action, Context, EditMode, Keymap};
+use crate::mentions;
+use crate::models::{transcript, ChatMessage, ChatRole};
+use crate::ui;
+use chrono::{DateTime, Utc};
+use spec_ai_core::agent::{
+    AnswerForm, ApprovalDecision, PolicyViolation, RunInterrupt, SessionUsage, ToolApprovalRequest,
+    UserAnswer, UserQuestionRequest,
+};
+use spec_ai_core::error::ErrorCode;
+use spec_ai_core::mesh::MeshInstance;
+use spec_ai_core::persistence::SessionRecord;
+use spec_ai_core::presence::Participant;
+use spec_ai_core::tasks::{LogLine, TaskEvent, TaskInfo};
 use spec_ai_core::types::{Message, MessageRole};
+use spec_ai_core::watch::{WatchEvent, WatchInfo};
+use spec_ai_tui::event::Attachment;
+use spec_ai_tui::style::{truncate, StreamingMarkdown};
 use spec_ai_tui::widget::builtin::{EditorState, SlashCommand, SlashMenuState};
+use std::collections::{HashMap, VecDeque};
 use tokio::sync::mpsc::UnboundedReceiver;
 
+/// Maximum number of policy violations kept for the violations overlay
+const MAX_POLICY_VIOLATIONS: usize = 100;
+
+/// Output lines kept per background task for the process panel
+const TASK_LOG_LINES: usize = 500;
+
+/// Widest tool call shown in the status bar while it streams
+const TOOL_PREVIEW_WIDTH: usize = 60;
+
+/// Choices on the tool approval card, left to right
+pub const APPROVAL_CHOICES: [ApprovalDecision; 3] = [
+    ApprovalDecision::AllowOnce,
+    ApprovalDecision::AllowAlways,
+    ApprovalDecision::Deny,
+];
+
+/// A model offered by a provider, as listed in the model picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelChoice {
+    pub provider: String,
+    pub model: String,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PanelFocus {
     Input,
     Chat,
 }
 
+/// A question from the agent and the answer being filled in for it
+#[derive(Debug)]
+pub struct QuestionPrompt {
+    pub request: UserQuestionRequest,
+    /// Highlighted row of a choice question; the row after the options is
+    /// the typed answer when other answers are allowed
+    pub cursor: usize,
+    /// Options ticked so far, for choice questions with several answers
+    pub checked: Vec<bool>,
+    /// Typed text: the answer, a file path, or an answer not among the options
+    pub input: String,
+    /// Why the last answer was not accepted
+    pub problem: Option<String>,
+}
+
+impl QuestionPrompt {
+    pub fn new(request: UserQuestionRequest) -> Self {
+        let checked = match &request.question.form {
+            AnswerForm::Choice { options, .. } => vec![false; options.len()],
+            _ => Vec::new(),
+        };
+        Self {
+            request,
+            cursor: 0,
+            checked,
+            input: String::new(),
+            problem: None,
+        }
+    }
+
+    /// Rows the cursor moves over, counting the typed answer row
+    pub fn rows(&self) -> usize {
+        match &self.request.question.form {
+            AnswerForm::Choice {
+                options,
+                allow_other,
+                ..
+            } => options.len() + usize::from(*allow_other),
+            _ => 0,
+        }
+    }
+
+    /// Whether typed keys go into [`Self::input`]
+    pub fn is_typing(&self) -> bool {
+        match &self.request.question.form {
+            AnswerForm::Choice { options, .. } => self.cursor >= options.len(),
+            _ => true,
+        }
+    }
+
+    /// Move the highlight by `delta` rows, wrapping around
+    pub fn select(&mut self, delta: isize) {
+        let rows = self.rows() as isize;
+        if rows > 0 {
+            self.cursor = (self.cursor as isize + delta).rem_euclid(rows) as usize;
+        }
+    }
+
+    /// Tick or untick the highlighted option of a question with several answers
+    pub fn toggle(&mut self) {
+        if let Some(checked) = self.checked.get_mut(self.cursor) {
+            *checked = !*checked;
+        }
+    }
+
+    /// The answer as it stands
+    pub fn answer(&self) -> UserAnswer {
+        match &self.request.question.form {
+            AnswerForm::Text { .. } => UserAnswer::Text {
+                text: self.input.clone(),
+            },
+            AnswerForm::File { .. } => UserAnswer::File {
+                path: self.input.trim().into(),
+            },
+            AnswerForm::Choice {
+                options, multiple, ..
+            } => {
+                let other = self.input.trim();
+                let mut selected: Vec<String> = if *multiple {
+                    options
+                        .iter()
+                        .zip(&self.checked)
+                        .filter(|(_, checked)| **checked)
+                        .map(|(option, _)| option.clone())
+                        .collect()
+                } else {
+                    options.get(self.cursor).cloned().into_iter().collect()
+                };
+                if self.is_typing() || (*multiple && !other.is_empty()) {
+                    selected.push(other.to_string());
+                }
+                selected.retain(|choice| !choice.is_empty());
+                UserAnswer::Choice { selected }
+            }
+        }
+    }
+}
+
 pub struct AppState {
     pub editor: EditorState,
     pub slash_menu: SlashMenuState,
@@ -24,10 +162,92 @@ pub struct AppState {
     pub tick: u64,
     pub active_agent: Option<String>,
     pub error: Option<String>,
+    /// Code of the error in `error`
+    pub error_code: Option<ErrorCode>,
     pub backend_rx: UnboundedReceiver<BackendEvent>,
     pub last_submitted_text: Option<String>,
+    /// Policy violations reported by the backend, oldest first
+    pub policy_violations: Vec<PolicyViolation>,
+    /// Whether the policy violations overlay is open
+    pub show_violations: bool,
+    /// Session the backend agent is using
+    pub session_id: Option<String>,
+    /// Past sessions for the history overlay, most recent first
+    pub sessions: Vec<SessionRecord>,
+    /// Whether the session history overlay is open
+    pub show_sessions: bool,
+    /// Highlighted row in the session history overlay
+    pub selected_session: usize,
+    /// Blocked tool call the agent is waiting on
+    pub pending_approval: Option<ToolApprovalRequest>,
+    /// Highlighted choice on the approval card, an index into [`APPROVAL_CHOICES`]
+    pub approval_choice: usize,
+    /// Question the agent is waiting on
+    pub pending_question: Option<QuestionPrompt>,
+    /// Whether the mesh status overlay is open
+    pub show_mesh: bool,
+    /// Mesh instances from the registry, leader first
+    pub mesh_instances: Vec<MeshInstance>,
+    /// Recent mesh messages sent or received, keyed by instance id
+    pub mesh_message_counts: HashMap<String, usize>,
+    /// Why the last mesh refresh failed, if it did
+    pub mesh_error: Option<String>,
+    /// When the mesh status was last received
+    pub mesh_updated_at: Option<DateTime<Utc>>,
+    /// Other people in the session, when `[ui] presence` is set
+    pub participants: Vec<Participant>,
+    /// Messages the API server is answering or holding in the session
+    pub pending_turns: u64,
+    /// Whether the background task panel is open
+    pub show_tasks: bool,
+    /// Background tasks started by the agent, oldest first
+    pub tasks: Vec<TaskInfo>,
+    /// Newest output lines of each task, keyed by task id
+    pub task_logs: HashMap<String, VecDeque<LogLine>>,
+    /// Highlighted row in the task panel
+    pub selected_task: usize,
+    /// File watches started by the agent, oldest first
+    pub watches: Vec<WatchInfo>,
+    /// Whether the model picker overlay is open
+    pub show_models: bool,
+    /// Whether the picker is waiting for the providers to answer
+    pub models_loading: bool,
+    /// Models from every configured provider, in provider order
+    pub model_choices: Vec<ModelChoice>,
+    /// Providers that could not be listed, as "provider: reason"
+    pub model_errors: Vec<String>,
+    /// Search text typed into the model picker
+    pub model_query: String,
+    /// Highlighted row among the models matching the query
+    pub selected_model: usize,
+    /// Provider and model the agent is running on
+    pub current_model: Option<ModelChoice>,
+    /// Token usage for the session, shown in the status bar
+    pub session_usage: SessionUsage,
+    /// Selection in the `@` file mention menu
+    pub mention_menu: SlashMenuState,
+    /// Workspace files offered by the mention menu, relative paths
+    pub workspace_files: Vec<String>,
+    /// Whether the backend is still listing workspace files
+    pub files_indexing: bool,
+    /// Key bindings from the `[ui.keymap]` config
+    pub keymap: Keymap,
+    /// Editor mode when the keymap is modal
+    pub edit_mode: EditMode,
+    /// Whether to render linear output for screen readers
+    pub screen_reader: bool,
+    /// Dropped files or pasted images waiting for the user to confirm
+    pub pending_attachments: Vec<Attachment>,
+    /// Attachments sent along with the next message
+    pub attachments: Vec<Attachment>,
+    /// Interrupts the agent step the backend is running
+    pub interrupt: RunInterrupt,
+    /// Earlier user message the editor text will replace on a new branch
+    pub editing: Option<i64>,
     /// Index of the currently streaming assistant message, if any
     streaming_message_idx: Option<usize>,
+    /// Incremental layout of the streaming message's markdown
+    streaming_markdown: StreamingMarkdown,
 }
 
 impl AppState {
@@ -46,9 +266,59 @@ impl AppState {
             tick: 0,
             active_agent: None,
             error: None,
+            error_code: None,
             backend_rx,
             last_submitted_text: None,
+            policy_violations: Vec::new(),
+            show_violations: false,
+            session_id: None,
+            sessions: Vec::new(),
+            show_sessions: false,
+            selected_session: 0,
+            pending_approval: None,
+            approval_choice: 0,
+            pending_question: None,
+            show_mesh: false,
+            mesh_instances: Vec::new(),
+            mesh_message_counts: HashMap::new(),
+            mesh_error: None,
+            mesh_updated_at: None,
+            participants: Vec::new(),
+            pending_turns: 0,
+            show_tasks: false,
+            tasks: Vec::new(),
+            task_logs: HashMap::new(),
+            selected_task: 0,
+            watches: Vec::new(),
+            show_models: false,
+            models_loading: false,
+            model_choices: Vec::new(),
+            model_errors: Vec::new(),
+            model_query: String::new(),
+            selected_model: 0,
+            current_model: None,
+            session_usage: SessionUsage::default(),
+            mention_menu: SlashMenuState::new(),
+            workspace_files: Vec::new(),
+            files_indexing: false,
+            keymap: Keymap::default(),
+            edit_mode: EditMode::Insert,
+            screen_reader: false,
+            pending_attachments: Vec::new(),
+            attachments: Vec::new(),
+            interrupt: RunInterrupt::new(),
+            editing: None,
             streaming_message_idx: None,
+            streaming_markdown: StreamingMarkdown::new(ui::conversation_markdown()),
+        }
+    }
+
+    /// Binding context for keys typed into the editor
+    pub fn input_context(&self) -> Context {
+        if self.keymap.is_modal() && self.edit_mode == EditMode::Normal {
+            Context::Normal
+        } else {
+            Context::Input
         }
     }
 
@@ -62,11 +332,15 @@ impl AppState {
         match event {
             BackendEvent::Initialized {
                 agent,
+                session_id,
                 messages,
                 reasoning,
                 status,
+                history,
             } => {
                 self.active_agent = agent;
+                self.session_id = Some(session_id);
+                self.editor.history.set_entries(history);
                 self.messages = messages
                     .iter()
                     .map(ChatMessage::from_backend)
@@ -79,7 +353,176 @@ impl AppState {
                 self.status = status;
                 self.busy = false;
                 self.error = None;
+                self.error_code = None;
+                self.scroll_offset = 0;
+            }
+            BackendEvent::SessionsLoaded { sessions } => {
+                self.sessions = sessions;
+                // Start on the active session when it is listed
+                self.selected_session = self
+                    .sessions
+                    .iter()
+                    .position(|s| Some(&s.session_id) == self.session_id.as_ref())
+                    .unwrap_or(0);
+            }
+            BackendEvent::SessionResumed {
+                session_id,
+                messages,
+                tool_calls,
+                history,
+                status,
+            } => {
+                self.session_id = Some(session_id);
+                self.messages = transcript(&messages, &tool_calls);
+                self.editor.history.set_entries(history);
+                self.status = status;
+                self.busy = false;
+                self.error = None;
+                self.error_code = None;
+                self.scroll_offset = 0;
+                self.streaming_message_idx = None;
+                self.last_submitted_text = None;
+                self.editing = None;
+            }
+            BackendEvent::Branched {
+                session_id,
+                parent_session_id,
+                edited,
+            } => {
+                // Replace the edited message and what followed it, keeping the resubmitted text
+                if let Some(start) = self.messages.iter().position(|m| m.id == Some(edited)) {
+                    let end = self.messages.len().saturating_sub(1).max(start);
+                    self.messages.drain(start..end);
+                    self.messages.insert(
+                        start,
+                        ChatMessage::system(format!(
+                            "Branched '{}' from '{}'; the original thread is unchanged",
+                            session_id, parent_session_id
+                        )),
+                    );
+                }
+                self.session_id = Some(session_id);
+            }
+            BackendEvent::ModelsLoaded { listings } => {
+                self.models_loading = false;
+                self.model_choices = listings
+                    .iter()
+                    .flat_map(|listing| {
+                        listing.models.iter().map(|model| ModelChoice {
+                            provider: listing.provider.clone(),
+                            model: model.clone(),
+                        })
+                    })
+                    .collect();
+                self.model_errors = listings
+                    .into_iter()
+                    .filter_map(|listing| {
+                        listing
+                            .error
+                            .map(|err| format!("{}: {}", listing.provider, err))
+                    })
+                    .collect();
+                // Start on the running model when it is listed
+                self.selected_model = self
+                    .filtered_models()
+                    .iter()
+                    .position(|choice| Some(*choice) == self.current_model.as_ref())
+                    .unwrap_or(0);
+            }
+            BackendEvent::AgentChanged { agent } => {
+                self.active_agent = agent;
+            }
+            BackendEvent::ModelChanged { provider, model } => {
+                self.current_model = Some(ModelChoice {
+                    provider,
+                    model: model.unwrap_or_default(),
+                });
+            }
+            BackendEvent::KeymapLoaded { keymap } => {
+                self.keymap = keymap;
+                self.edit_mode = EditMode::Insert;
+            }
+            BackendEvent::ScreenReaderLoaded { enabled } => {
+                self.screen_reader = enabled;
+            }
+            BackendEvent::UsageUpdated { usage } => {
+                self.session_usage = usage;
+            }
+            BackendEvent::FilesIndexed { files } => {
+                self.files_indexing = false;
+                self.workspace_files = files;
+            }
+            BackendEvent::ToolApproval(request) => {
+                self.status = format!("Approval needed for tool '{}'", request.tool_name);
+                self.pending_approval = Some(request);
+                self.approval_choice = 0;
+            }
+            BackendEvent::UserQuestion(request) => {
+                self.status = "The agent has a question".to_string();
+                self.pending_question = Some(QuestionPrompt::new(request));
+            }
+            BackendEvent::Task(TaskEvent::Updated(info)) => {
+                match self.tasks.iter_mut().find(|task| task.id == info.id) {
+                    Some(task) => *task = info,
+                    None => self.tasks.push(info),
+                }
+            }
+            BackendEvent::Task(TaskEvent::Output { id, line }) => {
+                let logs = self.task_logs.entry(id).or_default();
+                logs.push_back(line);
+                if logs.len() > TASK_LOG_LINES {
+                    logs.pop_front();
+                }
+            }
+            BackendEvent::Watch(WatchEvent::Updated(info)) => {
+                match self.watches.iter_mut().find(|watch| watch.id == info.id) {
+                    Some(watch) => *watch = info,
+                    None => self.watches.push(info),
+                }
+            }
+            BackendEvent::Watch(WatchEvent::Removed(id)) => {
+                self.watches.retain(|watch| watch.id != id);
+            }
+            BackendEvent::Watch(WatchEvent::Changed(batch)) => {
+                if !self.busy {
+                    self.status = format!("Changes {}", batch.summary());
+                }
+            }
+            BackendEvent::EventsDelivered { input } => {
+                // Shown like a message the user sent, which it is stored as
+                self.messages.push(ChatMessage::user(input));
                 self.scroll_offset = 0;
+                self.busy = true;
+                self.status = "Status: reacting to events...".to_string();
+            }
+            BackendEvent::Presence {
+                participants,
+                pending_turns,
+            } => {
+                self.participants = participants;
+                self.pending_turns = pending_turns;
+            }
+            BackendEvent::MeshStatus {
+                mut instances,
+                leader_id,
+                message_counts,
+                error,
+            } => {
+                for instance in &mut instances {
+                    // The registry's leader id wins over stale per-instance flags
+                    if let Some(leader) = &leader_id {
+                        instance.is_leader = instance.instance_id == *leader;
+                    }
+                }
+                instances.sort_by(|a, b| {
+                    b.is_leader
+                        .cmp(&a.is_leader)
+                        .then_with(|| a.instance_id.cmp(&b.instance_id))
+                });
+                self.mesh_instances = instances;
+                self.mesh_message_counts = message_counts;
+                self.mesh_error = error;
+                self.mesh_updated_at = Some(Utc::now());
             }
             BackendEvent::CommandResult {
                 response,
@@ -89,6 +532,7 @@ impl AppState {
             } => {
                 self.busy = false;
                 self.error = None;
+                self.error_code = None;
                 if !reasoning.is_empty() {
                     self.reasoning = reasoning;
                 }
@@ -107,6 +551,7 @@ impl AppState {
             BackendEvent::StreamStart => {
                 // Create a new streaming assistant message
                 self.streaming_message_idx = Some(self.messages.len());
+                self.streaming_markdown.clear();
                 self.messages.push(ChatMessage::assistant(""));
                 self.scroll_offset = 0;
                 self.status = "Status: streaming response...".to_string();
@@ -116,13 +561,17 @@ impl AppState {
                 if let Some(idx) = self.streaming_message_idx {
                     if let Some(msg) = self.messages.get_mut(idx) {
                         msg.content.push_str(&content);
+                        self.streaming_markdown.push(&content);
                     }
                 }
                 // Keep scroll at bottom while streaming
                 self.scroll_offset = 0;
             }
+            BackendEvent::ToolCallStreaming { preview } => {
+                self.status = format!("Calling {}", truncate(&preview, TOOL_PREVIEW_WIDTH));
+            }
             BackendEvent::StreamEnd {
-                new_messages: _,
+                new_messages,
                 reasoning,
                 status,
             } => {
@@ -130,6 +579,7 @@ impl AppState {
                 self.streaming_message_idx = None;
                 self.busy = false;
                 self.error = None;
+                self.error_code = None;
                 if !reasoning.is_empty() {
                     self.reasoning = reasoning;
                 }
@@ -137,25 +587,270 @@ impl AppState {
                 // The streaming message already contains the final content,
                 // but we may want to skip adding duplicate messages from new_messages
                 // For now, we don't re-add since the streaming message should match
+                self.adopt_user_ids(&new_messages);
                 self.last_submitted_text = None;
                 self.scroll_offset = 0;
             }
-            BackendEvent::Error { context, message } => {
+            BackendEvent::Error {
+                context,
+                message,
+                code,
+            } => {
                 self.streaming_message_idx = None;
                 self.busy = false;
                 self.error = Some(message.clone());
+                self.error_code = Some(code);
                 self.status = format!("Error while handling '{}'", context);
-                self.messages
-                    .push(ChatMessage::system(format!("Error: {}", message)));
+                let hint = if code.is_retryable() {
+                    " - this may succeed if you try again"
+                } else {
+                    ""
+                };
+                self.messages.push(ChatMessage::system(format!(
+                    "Error [{}]: {}{}",
+                    code, message, hint
+                )));
                 self.scroll_offset = 0;
                 self.last_submitted_text = None;
             }
+            BackendEvent::PolicyViolations { violations } => {
+                for violation in &violations {
+                    let summary = violation.summary();
+                    if !self.reasoning.contains(&summary) {
+                        self.reasoning.insert(0, summary);
+                    }
+                }
+                if let Some(latest) = violations.last() {
+                    self.status = format!(
+                        "Policy {} {} ({} for details)",
+                        latest.outcome.as_str(),
+                        latest.resource,
+                        self.keymap.hint(Context::Global, Action::ToggleViolations)
+                    );
+                }
+                self.policy_violations.extend(violations);
+                if self.policy_violations.len() > MAX_POLICY_VIOLATIONS {
+                    let excess = self.policy_violations.len() - MAX_POLICY_VIOLATIONS;
+                    self.policy_violations.drain(..excess);
+                }
+            }
             BackendEvent::Quit => {
                 self.quit = true;
             }
         }
     }
 
+    /// Open or close the policy violations overlay
+    pub fn toggle_violations(&mut self) {
+        self.show_violations = !self.show_violations;
+    }
+
+    /// Open or close the session history overlay, returning whether it opened
+    pub fn toggle_sessions(&mut self) -> bool {
+        self.show_sessions = !self.show_sessions;
+        self.show_sessions
+    }
+
+    /// Move the approval card highlight by `delta` choices, wrapping around
+    pub fn select_approval(&mut self, delta: isize) {
+        let len = APPROVAL_CHOICES.len() as isize;
+        self.approval_choice = (self.approval_choice as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// Answer the pending approval request so the agent can continue
+    pub fn resolve_approval(&mut self, decision: ApprovalDecision) {
+        let Some(request) = self.pending_approval.take() else {
+            return;
+        };
+        let tool = request.tool_name.clone();
+        self.status = if !request.respond(decision) {
+            format!("Approval for tool '{}' is no longer needed", tool)
+        } else {
+            match decision {
+                ApprovalDecision::AllowOnce => format!("Allowed tool '{}' once", tool),
+                ApprovalDecision::AllowAlways => {
+                    format!("Allowed tool '{}' for this session", tool)
+                }
+                ApprovalDecision::Deny => format!("Denied tool '{}'", tool),
+            }
+        };
+    }
+
+    /// Send the answer being filled in, or keep the question open with the
+    /// reason it was not accepted
+    pub fn answer_question(&mut self) {
+        let Some(prompt) = self.pending_question.take() else {
+            return;
+        };
+        let answer = prompt.answer();
+        let QuestionPrompt {
+            request,
+            cursor,
+            checked,
+            input,
+            ..
+        } = prompt;
+        match request.respond(answer) {
+            Ok(true) => self.status = "Answer sent".to_string(),
+            Ok(false) => self.status = "The question is no longer open".to_string(),
+            Err((request, reason)) => {
+                self.pending_question = Some(QuestionPrompt {
+                    request,
+                    cursor,
+                    checked,
+                    input,
+                    problem: Some(reason),
+                });
+            }
+        }
+    }
+
+    /// Tell the agent the user will not answer
+    pub fn decline_question(&mut self) {
+        if let Some(prompt) = self.pending_question.take() {
+            let _ = prompt.request.respond(UserAnswer::Declined);
+            self.status = "Declined to answer".to_string();
+        }
+    }
+
+    /// Close the question if the agent stopped waiting, e.g. when the run
+    /// was interrupted
+    pub fn drop_abandoned_question(&mut self) {
+        if self
+            .pending_question
+            .as_ref()
+            .is_some_and(|prompt| prompt.request.is_abandoned())
+        {
+            self.pending_question = None;
+            self.status = "The question is no longer open".to_string();
+        }
+    }
+
+    /// Ask the user whether to attach `attachments` to the next message
+    pub fn offer_attachments(&mut self, attachments: Vec<Attachment>) {
+        for attachment in attachments {
+            if !self.pending_attachments.contains(&attachment)
+                && !self.attachments.contains(&attachment)
+            {
+                self.pending_attachments.push(attachment);
+            }
+        }
+    }
+
+    /// Bind the offered attachments to the next message
+    pub fn confirm_attachments(&mut self) {
+        let count = self.pending_attachments.len();
+        self.attachments.append(&mut self.pending_attachments);
+        self.status = match count {
+            1 => format!(
+                "Attached {}",
+                self.attachments[self.attachments.len() - 1].label()
+            ),
+            n => format!("Attached {} items", n),
+        };
+    }
+
+    /// Drop the offered attachments, returning them
+    pub fn decline_attachments(&mut self) -> Vec<Attachment> {
+        std::mem::take(&mut self.pending_attachments)
+    }
+
+    /// Open or close the mesh status overlay, returning whether it opened
+    pub fn toggle_mesh(&mut self) -> bool {
+        self.show_mesh = !self.show_mesh;
+        self.show_mesh
+    }
+
+    /// Open or close the background task panel, highlighting the newest task
+    /// when it opens
+    pub fn toggle_tasks(&mut self) {
+        self.show_tasks = !self.show_tasks;
+        if self.show_tasks {
+            self.selected_task = self.tasks.len().saturating_sub(1);
+        }
+    }
+
+    /// Move the task panel highlight by `delta` rows
+    pub fn select_task(&mut self, delta: isize) {
+        let max = self.tasks.len().saturating_sub(1);
+        self.selected_task = self.selected_task.saturating_add_signed(delta).min(max);
+    }
+
+    /// The highlighted task in the task panel
+    pub fn highlighted_task(&self) -> Option<&TaskInfo> {
+        self.tasks.get(self.selected_task)
+    }
+
+    /// Number of background tasks still running
+    pub fn running_tasks(&self) -> usize {
+        self.tasks
+            .iter()
+            .filter(|task| task.state.is_running())
+            .count()
+    }
+
+    /// Open the model picker with an empty search while models load
+    pub fn open_models(&mut self) {
+        self.show_models = true;
+        self.models_loading = true;
+        self.model_query.clear();
+        self.selected_model = 0;
+    }
+
+    /// Models whose provider or name contain every word of the search
+    pub fn filtered_models(&self) -> Vec<&ModelChoice> {
+        let query = self.model_query.to_lowercase();
+        self.model_choices
+            .iter()
+            .filter(|choice| {
+                let text = format!("{} {}", choice.provider, choice.model).to_lowercase();
+                query.split_whitespace().all(|word| text.contains(word))
+            })
+            .collect()
+    }
+
+    /// Workspace files matching the `@` mention being typed, best first
+    pub fn mention_matches(&self) -> Vec<&str> {
+        mentions::rank_files(
+            &self.workspace_files,
+            &self.editor.mention_query,
+            mentions::MAX_MATCHES,
+        )
+    }
+
+    /// Change the model search, keeping the selection on a matching row
+    pub fn set_model_query(&mut self, query: String) {
+        self.model_query = query;
+        self.selected_model = 0;
+    }
+
+    /// Move the model picker selection by `delta` rows
+    pub fn select_model(&mut self, delta: isize) {
+        let max = self.filtered_models().len().saturating_sub(1);
+        self.selected_model = self.selected_model.saturating_add_signed(delta).min(max);
+    }
+
+    /// The highlighted model, unless the agent is already running it
+    pub fn model_to_switch(&self) -> Option<&ModelChoice> {
+        self.filtered_models()
+            .get(self.selected_model)
+            .copied()
+            .filter(|choice| Some(*choice) != self.current_model.as_ref())
+    }
+
+    /// Move the history overlay selection by `delta` rows
+    pub fn select_session(&mut self, delta: isize) {
+        let max = self.sessions.len().saturating_sub(1);
+        self.selected_session = self.selected_session.saturating_add_signed(delta).min(max);
+    }
+
+    /// The highlighted session, unless it is already the active one
+    pub fn session_to_resume(&self) -> Option<&SessionRecord> {
+        self.sessions
+            .get(self.selected_session)
+            .filter(|s| Some(&s.session_id) != self.session_id.as_ref())
+    }
+
     fn append_messages(&mut self, incoming: &[Message]) {
         let mut skipped_user = false;
 
@@ -164,6 +859,7 @@ impl AppState {
                 if let Some(pending) = &self.last_submitted_text {
                     if !skipped_user && message.content.trim() == pending.trim() {
                         skipped_user = true;
+                        self.adopt_user_ids(std::slice::from_ref(message));
                         continue;
                     }
                 }
@@ -180,10 +876,74 @@ impl AppState {
         }
     }
 
+    /// Give locally echoed user messages the ids the backend stored them under
+    fn adopt_user_ids(&mut self, incoming: &[Message]) {
+        let ids = incoming
+            .iter()
+            .filter(|m| m.role == MessageRole::User)
+            .map(|m| m.id);
+        let echoed = self
+            .messages
+            .iter_mut()
+            .rev()
+            .filter(|m| m.role == ChatRole::User && m.id.is_none());
+        for (message, id) in echoed.zip(ids.rev()) {
+            message.id = Some(id);
+        }
+    }
+
+    /// Load the stored user message before the one being edited, or the latest,
+    /// into the editor; returns false when there is none
+    pub fn edit_previous_message(&mut self) -> bool {
+        let before = self
+            .editing
+            .and_then(|id| self.messages.iter().position(|m| m.id == Some(id)))
+            .unwrap_or(self.messages.len());
+        let Some(message) = self.messages[..before]
+            .iter()
+            .rev()
+            .find(|m| m.role == ChatRole::User && m.id.is_some())
+        else {
+            return false;
+        };
+
+        self.editing = message.id;
+        let text = message.content.clone();
+        self.editor.clear();
+        self.editor.insert_str(&text);
+        true
+    }
+
+    /// Stop editing an earlier message, clearing the editor
+    pub fn cancel_edit(&mut self) -> bool {
+        if self.editing.take().is_none() {
+            return false;
+        }
+        self.editor.clear();
+        self.status = "Edit cancelled".to_string();
+        true
+    }
+
+    /// Whether a reply is streaming, so Esc interrupts the agent
+    pub fn is_streaming(&self) -> bool {
+        self.streaming_message_idx.is_some()
+    }
+
+    /// Index of the message being streamed, if any
+    pub fn streaming_index(&self) -> Option<usize> {
+        self.streaming_message_idx
+    }
+
     /// Returns true if the message at the given index is currently being streamed
     pub fn is_streaming_message(&self, index: usize) -> bool {
         self.streaming_message_idx == Some(index)
     }
+
+    /// Markdown of the message at `index` while it is still streaming
+    pub fn streaming_markdown(&self, index: usize) -> Option<&StreamingMarkdown> {
+        self.is_streaming_message(index)
+            .then_some(&self.streaming_markdown)
+    }
 }
 
 fn default_reasoning() -> Vec<String> {
@@ -201,9 +961,26 @@ fn default_slash_commands() -> Vec<SlashCommand> {
         SlashCommand::new("policy", "Reload policies"),
         SlashCommand::new("agents", "List configured agents"),
         SlashCommand::new("switch", "Switch active agent (/switch <name>)"),
+        SlashCommand::new("profile", "Switch agent profile (/profile <name>)"),
+        SlashCommand::new("model", "Pick a model from the configured providers"),
+        SlashCommand::new("usage", "Show token usage and cost for this session"),
+        SlashCommand::new(
+            "budget",
+            "Show or raise the step budget (/budget extend|tokens|cost|tools)",
+        ),
+        SlashCommand::new(
+            "preset",
+            "Show or pick a generation preset (/preset [name|off] [message])",
+        ),
         SlashCommand::new("memory", "Show recent memory (/memory show [n])"),
         SlashCommand::new("session", "Session actions (/session new|list|switch)"),
-        SlashCommand::new("graph", "Graph tools (/graph status|show|clear)"),
+        SlashCommand::new("branch", "Conversation branches (/branch list|new|switch)"),
+        SlashCommand::new(
+            "checkpoint",
+            "Save or list checkpoints (/checkpoint [name])",
+        ),
+        SlashCommand::new("rollback", "Branch from a checkpoint (/rollback <name>)"),
+        SlashCommand::new("graph", "Graph tools (/graph status|show|export|clear)"),
         SlashCommand::new("sync", "List sync-enabled graphs"),
         SlashCommand::new("init", "Bootstrap knowledge graph (first command only)"),
         SlashCommand::new("refresh", "Refresh knowledge graph cache"),
@@ -226,6 +1003,8 @@ fn clean_text(text: &str) -> String {
 mod tests {
     use super::*;
     use chrono::Utc;
+    use spec_ai_core::agent::{ModelListing, PolicyOutcome};
+    use spec_ai_core::tasks::{LogStream, TaskState};
 
     fn create_test_state() -> AppState {
         let (_tx, rx) = tokio::sync::mpsc::unbounded_channel();
@@ -364,9 +1143,11 @@ mod tests {
         state.apply_backend_event(BackendEvent::Error {
             context: "test context".to_string(),
             message: "test error".to_string(),
+            code: ErrorCode::Internal,
         });
         assert!(!state.busy);
         assert_eq!(state.error, Some("test error".to_string()));
+        assert_eq!(state.error_code, Some(ErrorCode::Internal));
         assert!(state.status.contains("test context"));
     }
 
@@ -376,9 +1157,12 @@ mod tests {
         state.apply_backend_event(BackendEvent::Error {
             context: "ctx".to_string(),
             message: "error msg".to_string(),
+            code: ErrorCode::RateLimited,
         });
         assert_eq!(state.messages.len(), 1);
         assert!(state.messages[0].content.contains("error msg"));
+        assert!(state.messages[0].content.contains("[rate_limited]"));
+        assert!(state.messages[0].content.contains("try again"));
     }
 
     #[test]
@@ -386,22 +1170,35 @@ mod tests {
         let mut state = create_test_state();
         state.apply_backend_event(BackendEvent::Initialized {
             agent: Some("test-agent".to_string()),
+            session_id: "s1".to_string(),
             messages: vec![],
             reasoning: vec![],
             status: "Ready".to_string(),
+            history: vec![],
         });
         assert_eq!(state.active_agent, Some("test-agent".to_string()));
         assert!(!state.busy);
     }
 
+    #[test]
+    fn apply_backend_event_agent_changed_updates_agent() {
+        let mut state = create_test_state();
+        state.apply_backend_event(BackendEvent::AgentChanged {
+            agent: Some("researcher".to_string()),
+        });
+        assert_eq!(state.active_agent, Some("researcher".to_string()));
+    }
+
     #[test]
     fn apply_backend_event_initialized_with_empty_reasoning_uses_default() {
         let mut state = create_test_state();
         state.apply_backend_event(BackendEvent::Initialized {
             agent: None,
+            session_id: "s1".to_string(),
             messages: vec![],
             reasoning: vec![],
             status: "Ready".to_string(),
+            history: vec![],
         });
         // Should use default reasoning when empty
         assert_eq!(state.reasoning.len(), 3);
@@ -413,13 +1210,40 @@ mod tests {
         let custom_reasoning = vec!["Custom line".to_string()];
         state.apply_backend_event(BackendEvent::Initialized {
             agent: None,
+            session_id: "s1".to_string(),
             messages: vec![],
             reasoning: custom_reasoning.clone(),
             status: "Ready".to_string(),
+            history: vec![],
         });
         assert_eq!(state.reasoning, custom_reasoning);
     }
 
+    #[test]
+    fn apply_backend_event_policy_violations_updates_reasoning_and_log() {
+        let mut state = create_test_state();
+        let violation = PolicyViolation {
+            rule: "default deny".to_string(),
+            action: "tool_call".to_string(),
+            resource: "bash".to_string(),
+            outcome: PolicyOutcome::Denied,
+            timestamp: Utc::now(),
+        };
+        state.apply_backend_event(BackendEvent::PolicyViolations {
+            violations: vec![violation.clone()],
+        });
+        assert_eq!(state.policy_violations.len(), 1);
+        assert_eq!(state.reasoning[0], violation.summary());
+        assert!(state.status.contains("denied bash"));
+
+        // Already-present summaries are not duplicated
+        state.apply_backend_event(BackendEvent::PolicyViolations {
+            violations: vec![violation],
+        });
+        assert_eq!(state.policy_violations.len(), 2);
+        assert_eq!(state.reasoning.len(), 4);
+    }
+
     #[test]
     fn apply_backend_event_command_result_clears_busy() {
         let mut state = create_test_state();
@@ -471,6 +1295,260 @@ mod tests {
         assert!(state.messages.is_empty());
     }
 
+    #[test]
+    fn apply_backend_event_tool_call_streaming_shows_call_in_status() {
+        let mut state = create_test_state();
+        state.apply_backend_event(BackendEvent::StreamStart);
+        state.apply_backend_event(BackendEvent::ToolCallStreaming {
+            preview: r#"code_search({"query": "pars"#.to_string(),
+        });
+        assert_eq!(state.status, r#"Calling code_search({"query": "pars"#);
+
+        state.apply_backend_event(BackendEvent::ToolCallStreaming {
+            preview: format!("code_search({})", "x".repeat(100)),
+        });
+        assert_eq!(
+            state.status.chars().count(),
+            "Calling ".len() + TOOL_PREVIEW_WIDTH
+        );
+        assert!(state.status.ends_with("..."));
+    }
+
+    fn make_test_session(session_id: &str) -> SessionRecord {
+        SessionRecord {
+            session_id: session_id.to_string(),
+            agent: Some("default".to_string()),
+            provider: Some("mock".to_string()),
+            model: None,
+            title: Some("hello".to_string()),
+            message_count: 2,
+            tool_call_count: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn sessions_loaded_selects_active_session() {
+        let mut state = create_test_state();
+        state.session_id = Some("s2".to_string());
+        state.apply_backend_event(BackendEvent::SessionsLoaded {
+            sessions: vec![make_test_session("s1"), make_test_session("s2")],
+        });
+        assert_eq!(state.selected_session, 1);
+        // The active session is not resumed again
+        assert!(state.session_to_resume().is_none());
+
+        state.select_session(-1);
+        assert_eq!(state.session_to_resume().unwrap().session_id, "s1");
+        state.select_session(5);
+        assert_eq!(state.selected_session, 1);
+    }
+
+    #[test]
+    fn session_resumed_replaces_transcript() {
+        let mut state = create_test_state();
+        state.messages.push(ChatMessage::user("current session"));
+        state.scroll_offset = 4;
+        state.apply_backend_event(BackendEvent::SessionResumed {
+            session_id: "s1".to_string(),
+            messages: vec![
+                make_test_message(MessageRole::User, "old question"),
+                make_test_message(MessageRole::Assistant, "old answer"),
+            ],
+            tool_calls: vec![],
+            history: vec!["old question".to_string()],
+            status: "Status: resumed session 's1'".to_string(),
+        });
+        assert_eq!(state.session_id.as_deref(), Some("s1"));
+        assert_eq!(state.messages.len(), 2);
+        assert_eq!(state.messages[0].content, "old question");
+        assert_eq!(state.scroll_offset, 0);
+        assert!(!state.busy);
+    }
+
+    #[test]
+    fn offered_attachments_are_confirmed_once() {
+        let mut state = create_test_state();
+        let notes = Attachment::File("/tmp/notes.md".into());
+        state.offer_attachments(vec![notes.clone(), notes.clone()]);
+        assert_eq!(state.pending_attachments.len(), 1);
+
+        state.confirm_attachments();
+        assert_eq!(state.attachments, vec![notes.clone()]);
+        assert!(state.pending_attachments.is_empty());
+        assert_eq!(state.status, "Attached notes.md");
+
+        // Already attached, so not offered again
+        state.offer_attachments(vec![notes]);
+        assert!(state.pending_attachments.is_empty());
+    }
+
+    fn make_test_instance(instance_id: &str, is_leader: bool) -> MeshInstance {
+        MeshInstance {
+            instance_id: instance_id.to_string(),
+            hostname: "localhost".to_string(),
+            port: 3000,
+            capabilities: vec!["query".to_string()],
+            is_leader,
+            last_heartbeat: Utc::now(),
+            created_at: Utc::now(),
+            agent_profiles: vec![],
+            clock: None,
+        }
+    }
+
+    #[test]
+    fn mesh_status_lists_leader_first() {
+        let mut state = create_test_state();
+        assert!(state.toggle_mesh());
+        state.apply_backend_event(BackendEvent::MeshStatus {
+            instances: vec![
+                make_test_instance("node-a", true),
+                make_test_instance("node-c", false),
+                make_test_instance("node-b", false),
+            ],
+            leader_id: Some("node-b".to_string()),
+            message_counts: HashMap::from([("node-b".to_string(), 3)]),
+            error: None,
+        });
+
+        let ids: Vec<_> = state
+            .mesh_instances
+            .iter()
+            .map(|i| (i.instance_id.as_str(), i.is_leader))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![("node-b", true), ("node-a", false), ("node-c", false)]
+        );
+        assert_eq!(state.mesh_message_counts["node-b"], 3);
+        assert!(state.mesh_updated_at.is_some());
+        assert!(!state.toggle_mesh());
+    }
+
+    fn make_test_task(id: &str, state: TaskState) -> TaskInfo {
+        TaskInfo {
+            id: id.to_string(),
+            name: "cargo test".to_string(),
+            command: "cargo test".to_string(),
+            working_dir: None,
+            state,
+            exit_code: None,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+            lines: 0,
+        }
+    }
+
+    #[test]
+    fn task_events_update_the_panel() {
+        let mut state = create_test_state();
+        for id in ["task-1", "task-2"] {
+            state.apply_backend_event(BackendEvent::Task(TaskEvent::Updated(make_test_task(
+                id,
+                TaskState::Running,
+            ))));
+        }
+        state.apply_backend_event(BackendEvent::Task(TaskEvent::Updated(make_test_task(
+            "task-1",
+            TaskState::Succeeded,
+        ))));
+        for seq in 0..TASK_LOG_LINES as u64 + 5 {
+            state.apply_backend_event(BackendEvent::Task(TaskEvent::Output {
+                id: "task-2".to_string(),
+                line: LogLine {
+                    seq,
+                    stream: LogStream::Stdout,
+                    text: format!("line {}", seq),
+                },
+            }));
+        }
+
+        assert_eq!(state.tasks.len(), 2);
+        assert_eq!(state.running_tasks(), 1);
+        let logs = &state.task_logs["task-2"];
+        assert_eq!(logs.len(), TASK_LOG_LINES);
+        assert_eq!(logs.front().unwrap().seq, 5);
+
+        state.toggle_tasks();
+        assert!(state.show_tasks);
+        assert_eq!(state.highlighted_task().unwrap().id, "task-2");
+        state.select_task(-5);
+        assert_eq!(state.highlighted_task().unwrap().id, "task-1");
+    }
+
+    #[test]
+    fn watch_events_track_watches_and_delivered_turns() {
+        let mut state = create_test_state();
+        let watch = WatchInfo {
+            id: "watch-1".to_string(),
+            path: "/repo".to_string(),
+            recursive: true,
+            debounce_ms: 500,
+            ignore: vec![],
+            created_at: Utc::now(),
+            batches: 0,
+            last_change_at: None,
+        };
+        state.apply_backend_event(BackendEvent::Watch(WatchEvent::Updated(watch.clone())));
+        state.apply_backend_event(BackendEvent::Watch(WatchEvent::Updated(WatchInfo {
+            batches: 1,
+            ..watch
+        })));
+        assert_eq!(state.watches.len(), 1);
+        assert_eq!(state.watches[0].batches, 1);
+
+        state.apply_backend_event(BackendEvent::EventsDelivered {
+            input: "[events]\n- [watch-1]: under /repo: modified src/lib.rs".to_string(),
+        });
+        assert!(state.busy);
+        assert_eq!(state.messages.last().unwrap().role, ChatRole::User);
+
+        state.apply_backend_event(BackendEvent::Watch(WatchEvent::Removed(
+            "watch-1".to_string(),
+        )));
+        assert!(state.watches.is_empty());
+    }
+
+    #[test]
+    fn model_picker_filters_and_skips_running_model() {
+        let mut state = create_test_state();
+        state.apply_backend_event(BackendEvent::ModelChanged {
+            provider: "ollama".to_string(),
+            model: Some("qwen2.5:7b".to_string()),
+        });
+        state.open_models();
+        state.apply_backend_event(BackendEvent::ModelsLoaded {
+            listings: vec![
+                ModelListing {
+                    provider: "ollama".to_string(),
+                    models: vec!["llama3:latest".to_string(), "qwen2.5:7b".to_string()],
+                    error: None,
+                },
+                ModelListing {
+                    provider: "openai".to_string(),
+                    models: Vec::new(),
+                    error: Some("OPENAI_API_KEY not set".to_string()),
+                },
+            ],
+        });
+
+        assert!(!state.models_loading);
+        assert_eq!(state.model_choices.len(), 2);
+        assert_eq!(state.model_errors, vec!["openai: OPENAI_API_KEY not set"]);
+        // The running model is highlighted but is not a switch target
+        assert_eq!(state.selected_model, 1);
+        assert!(state.model_to_switch().is_none());
+
+        state.set_model_query("OLLAMA llama".to_string());
+        assert_eq!(state.filtered_models().len(), 1);
+        assert_eq!(state.model_to_switch().unwrap().model, "llama3:latest");
+        state.select_model(5);
+        assert_eq!(state.selected_model, 0);
+    }
+
     fn make_test_message(role: MessageRole, content: &str) -> Message {
         Message {
             id: 0,
@@ -478,6 +1556,7 @@ mod tests {
             role,
             content: content.to_string(),
             created_at: Utc::now(),
+            author: None,
         }
     }
 
@@ -538,4 +1617,63 @@ mod tests {
         // First "Hello" is skipped, second one should be added
         assert_eq!(state.messages.len(), 1);
     }
+
+    #[test]
+    fn editing_an_earlier_message_resubmits_it_on_a_branch() {
+        let mut state = create_test_state();
+        state.session_id = Some("main".to_string());
+        let stored = |id, role, content| Message {
+            id,
+            ..make_test_message(role, content)
+        };
+
+        state.messages.push(ChatMessage::user("first"));
+        state.apply_backend_event(BackendEvent::StreamEnd {
+            new_messages: vec![
+                stored(1, MessageRole::User, "first"),
+                stored(2, MessageRole::Assistant, "one"),
+            ],
+            reasoning: Vec::new(),
+            status: String::new(),
+        });
+        state.messages.push(ChatMessage::assistant("one"));
+        state.messages.push(ChatMessage::user("second"));
+        state.apply_backend_event(BackendEvent::StreamEnd {
+            new_messages: vec![stored(3, MessageRole::User, "second")],
+            reasoning: Vec::new(),
+            status: String::new(),
+        });
+        assert_eq!(state.messages[0].id, Some(1));
+        assert_eq!(state.messages[2].id, Some(3));
+
+        // Each press steps back to an older user message
+        assert!(state.edit_previous_message());
+        assert_eq!(
+            (state.editing, state.editor.text.as_str()),
+            (Some(3), "second")
+        );
+        assert!(state.edit_previous_message());
+        assert_eq!(
+            (state.editing, state.editor.text.as_str()),
+            (Some(1), "first")
+        );
+        assert!(!state.edit_previous_message());
+
+        state.messages.push(ChatMessage::user("first, reworded"));
+        state.apply_backend_event(BackendEvent::Branched {
+            session_id: "main.1".to_string(),
+            parent_session_id: "main".to_string(),
+            edited: 1,
+        });
+        assert_eq!(state.session_id.as_deref(), Some("main.1"));
+        assert_eq!(state.messages.len(), 2);
+        assert!(state.messages[0]
+            .content
+            .contains("Branched 'main.1' from 'main'"));
+        assert_eq!(state.messages[1].content, "first, reworded");
+
+        assert!(state.cancel_edit());
+        assert!(state.editing.is_none());
+        assert!(!state.cancel_edit());
+    }
 }
@@ -1,17 +1,49 @@
+use crate::keymap::{Action, Context, EditMode};
 use crate::models::ChatRole;
-use crate::state::{AppState, PanelFocus};
+use crate::state::{AppState, ModelChoice, PanelFocus, QuestionPrompt, APPROVAL_CHOICES};
+use chrono::{DateTime, Local, Utc};
+use spec_ai_core::agent::{
+    AnswerForm, ApprovalDecision, PolicyOutcome, SessionUsage, ToolApprovalRequest,
+};
+use spec_ai_core::mesh::MeshInstance;
+use spec_ai_core::persistence::SessionRecord;
+use spec_ai_core::presence::{Activity, Participant};
+use spec_ai_core::tasks::{LogStream, TaskInfo, TaskState};
 use spec_ai_tui::{
+    app::{pane_key, PaneCache, Transcript},
     buffer::Buffer,
+    event::Attachment,
     geometry::Rect,
     layout::{Constraint, Layout},
     style::{parse_markdown, Color, Line, MarkdownConfig, Span, Style},
     widget::{
-        builtin::{Block, Editor, SlashCommand, SlashMenu, StatusBar, StatusSection},
+        builtin::{
+            spinner_frame, Block, Editor, Overlay, SlashCommand, SlashMenu, StatusBar,
+            StatusSection,
+        },
         StatefulWidget, Widget,
     },
 };
+use std::time::Duration;
+
+/// Pane showing the conversation
+pub const CHAT_PANE: &str = "chat";
+
+/// Pane showing the agent's reasoning
+pub const REASONING_PANE: &str = "reasoning";
+
+/// Panes cached between frames
+///
+/// The conversation redraws at most every [`CHAT_REFRESH`] while a reply
+/// streams in; typing and ticks leave both panes as they were.
+pub fn pane_cache() -> PaneCache {
+    PaneCache::new().refresh_interval(CHAT_PANE, CHAT_REFRESH)
+}
+
+/// How often a changing conversation is laid out again
+const CHAT_REFRESH: Duration = Duration::from_millis(33);
 
-pub fn render(state: &AppState, area: Rect, buf: &mut Buffer) {
+pub fn render(state: &AppState, panes: &PaneCache, area: Rect, buf: &mut Buffer) {
     let layout = Layout::vertical()
         .constraints([
             Constraint::Fill(1),
@@ -21,10 +53,109 @@ pub fn render(state: &AppState, area: Rect, buf: &mut Buffer) {
         ])
         .split(area);
 
-    render_chat(state, layout[0], buf);
+    panes.render(CHAT_PANE, chat_key(state), layout[0], buf, |area, buf| {
+        render_chat(state, area, buf)
+    });
     render_input(state, layout[1], buf);
-    render_reasoning(state, layout[2], buf);
+    panes.render(
+        REASONING_PANE,
+        reasoning_key(state),
+        layout[2],
+        buf,
+        |area, buf| render_reasoning(state, area, buf),
+    );
     render_status(state, layout[3], buf);
+
+    if state.show_sessions {
+        render_sessions(state, area, buf);
+    }
+    if state.show_mesh {
+        render_mesh(state, area, buf);
+    }
+    if state.show_tasks {
+        render_tasks(state, area, buf);
+    }
+    if state.show_models {
+        render_models(state, area, buf);
+    }
+    if state.show_violations {
+        render_violations(state, area, buf);
+    }
+    if !state.pending_attachments.is_empty() {
+        render_attachments(state, area, buf);
+    }
+    if let Some(request) = &state.pending_approval {
+        render_approval(request, state.approval_choice, area, buf);
+    }
+    if let Some(prompt) = &state.pending_question {
+        render_question(prompt, area, buf);
+    }
+}
+
+/// The conversation as plain text for the screen reader render mode
+///
+/// A streaming reply is left out until it finishes, so it is read once.
+pub fn transcript(state: &AppState) -> Transcript {
+    let mut lines = Vec::new();
+    for (idx, message) in state.messages.iter().enumerate() {
+        if state.is_streaming_message(idx) {
+            continue;
+        }
+        let mut content = message.content.lines();
+        lines.push(format!(
+            "{}: {}",
+            message.role.label(),
+            content.next().unwrap_or_default()
+        ));
+        lines.extend(content.map(str::to_string));
+    }
+
+    let status = if let Some(request) = &state.pending_approval {
+        format!(
+            "Tool {} needs approval ({}): y allows once, a always, n denies",
+            request.tool_name, request.rule
+        )
+    } else if let Some(prompt) = &state.pending_question {
+        format!(
+            "The agent asks: {} ({}): Enter answers, Esc declines",
+            prompt.request.question.question,
+            question_hint(&prompt.request.question.form)
+        )
+    } else if let Some(err) = &state.error {
+        format!("Error: {}", err)
+    } else if state.is_streaming() {
+        "Assistant is responding".to_string()
+    } else {
+        state.status.clone()
+    };
+
+    Transcript {
+        lines,
+        status: Some(status),
+        prompt: Some(format!("> {}", state.editor.text.replace('\n', " "))),
+    }
+}
+
+/// Markdown styling for conversation messages, before the width is known
+pub fn conversation_markdown() -> MarkdownConfig {
+    MarkdownConfig::new().wrap_prefix("  ")
+}
+
+/// Everything [`render_chat`] reads
+fn chat_key(state: &AppState) -> u64 {
+    pane_key(&(
+        &state.messages,
+        state.streaming_index(),
+        state.focus == PanelFocus::Chat,
+        &state.active_agent,
+        state.scroll_offset,
+    ))
+}
+
+/// Everything [`render_reasoning`] reads
+fn reasoning_key(state: &AppState) -> u64 {
+    let spinner = state.busy.then_some(state.tick / 2);
+    pane_key(&(&state.reasoning, spinner))
 }
 
 fn render_chat(state: &AppState, area: Rect, buf: &mut Buffer) {
@@ -50,9 +181,8 @@ fn render_chat(state: &AppState, area: Rect, buf: &mut Buffer) {
     let content_width = inner.width.saturating_sub(1) as usize;
     let mut lines: Vec<Line> = Vec::new();
 
-    let md_config = MarkdownConfig::new()
-        .max_width(content_width.saturating_sub(2))
-        .wrap_prefix("  ");
+    let markdown_width = content_width.saturating_sub(2);
+    let md_config = conversation_markdown().max_width(markdown_width);
 
     for (idx, message) in state.messages.iter().enumerate() {
         // Check if this is a streaming message that hasn't received content yet
@@ -72,8 +202,12 @@ fn render_chat(state: &AppState, area: Rect, buf: &mut Buffer) {
             Span::styled(label.to_string(), style),
         ]));
 
-        // Parse markdown and add prefix to each line
-        let parsed = parse_markdown(&message.content, &md_config);
+        // Parse markdown and add prefix to each line; a streaming message
+        // only re-lays-out the lines that changed
+        let parsed = match state.streaming_markdown(idx) {
+            Some(markdown) => markdown.render(markdown_width),
+            None => parse_markdown(&message.content, &md_config),
+        };
         for md_line in parsed.lines {
             // Add indent prefix
             let mut prefixed_spans = vec![Span::raw("  ".to_string())];
@@ -129,7 +263,12 @@ fn render_input(state: &AppState, area: Rect, buf: &mut Buffer) {
         Style::new().fg(Color::DarkGrey)
     };
 
-    let block = Block::bordered().title("Input").border_style(border_style);
+    let title = match (state.keymap.is_modal(), state.edit_mode) {
+        (false, _) => "Input",
+        (true, EditMode::Insert) => "Input -- INSERT --",
+        (true, EditMode::Normal) => "Input -- NORMAL --",
+    };
+    let block = Block::bordered().title(title).border_style(border_style);
     Widget::render(&block, area, buf);
 
     let inner = block.inner(area);
@@ -138,14 +277,26 @@ fn render_input(state: &AppState, area: Rect, buf: &mut Buffer) {
     }
 
     let help_text = if state.editor.show_slash_menu {
-        "Tab: autocomplete | ↑/↓: select | Enter: run"
+        "Tab: autocomplete | ↑/↓: select | Enter: run".to_string()
+    } else if state.editor.show_mention_menu {
+        if state.files_indexing && state.workspace_files.is_empty() {
+            "Indexing workspace files...".to_string()
+        } else {
+            "↑/↓: select file | Enter: insert | Esc: close".to_string()
+        }
+    } else if !state.attachments.is_empty() {
+        let labels: Vec<String> = state.attachments.iter().map(|a| a.label()).collect();
+        format!(
+            "Attached: {} | Backspace on empty input: remove",
+            labels.join(", ")
+        )
     } else {
-        "Ctrl+C: quit | Ctrl+L: clear | / commands | Alt+b/f: word nav"
+        input_help(state)
     };
     buf.set_string(
         inner.x,
         inner.y,
-        help_text,
+        &help_text,
         Style::new().fg(Color::DarkGrey),
     );
 
@@ -189,6 +340,27 @@ fn render_input(state: &AppState, area: Rect, buf: &mut Buffer) {
             menu.render(menu_area, buf, &mut menu_state);
         }
     }
+
+    if state.editor.show_mention_menu {
+        let files: Vec<SlashCommand> = state
+            .mention_matches()
+            .into_iter()
+            .map(|path| SlashCommand::new(path, ""))
+            .collect();
+
+        if !files.is_empty() {
+            let menu = SlashMenu::new().prefix("@").commands(files);
+            let menu_area = Rect::new(
+                inner.x + 2,
+                area.y,
+                inner.width.saturating_sub(2).min(60),
+                area.height,
+            );
+
+            let mut menu_state = state.mention_menu.clone();
+            menu.render(menu_area, buf, &mut menu_state);
+        }
+    }
 }
 
 fn render_reasoning(state: &AppState, area: Rect, buf: &mut Buffer) {
@@ -200,9 +372,8 @@ fn render_reasoning(state: &AppState, area: Rect, buf: &mut Buffer) {
         return;
     }
 
-    let spinner_frames = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
     let spinner = if state.busy {
-        spinner_frames[(state.tick / 2) as usize % spinner_frames.len()]
+        spinner_frame(state.tick / 2)
     } else {
         '◇'
     };
@@ -229,11 +400,58 @@ fn render_reasoning(state: &AppState, area: Rect, buf: &mut Buffer) {
     }
 }
 
+/// Editor shortcuts from the active keymap
+fn input_help(state: &AppState) -> String {
+    let keymap = &state.keymap;
+    let normal = |action| keymap.hint(Context::Normal, action);
+    match state.input_context() {
+        Context::Normal => format!(
+            "{}/{}: insert | {}/{}: move | {}/{}: word | {}: delete | {}: undo | Enter: send",
+            normal(Action::Insert),
+            normal(Action::Append),
+            normal(Action::Left),
+            normal(Action::Right),
+            normal(Action::WordLeft),
+            normal(Action::WordRight),
+            normal(Action::DeleteChar),
+            normal(Action::Undo),
+        ),
+        _ if keymap.is_modal() => format!(
+            "Ctrl+C: quit | {}: normal mode | {}: clear | / commands | @ files",
+            keymap.hint(Context::Input, Action::NormalMode),
+            keymap.hint(Context::Input, Action::ClearChat),
+        ),
+        _ => format!(
+            "Ctrl+C: quit | {}: clear | / commands | @ files | {}: paste image | Alt+b/f: word nav",
+            keymap.hint(Context::Input, Action::ClearChat),
+            keymap.hint(Context::Input, Action::PasteImage),
+        ),
+    }
+}
+
+/// Help line for an overlay that `toggle` also closes
+fn overlay_close_help(state: &AppState, toggle: Action) -> String {
+    format!(
+        "{}/{}: close",
+        state.keymap.hint(Context::Overlay, Action::Close),
+        state.keymap.hint(Context::Global, toggle)
+    )
+}
+
 fn render_status(state: &AppState, area: Rect, buf: &mut Buffer) {
     let mut left_sections = vec![StatusSection::new(&state.status)];
     if let Some(err) = &state.error {
-        left_sections
-            .push(StatusSection::new(format!("Error: {}", err)).style(Style::new().fg(Color::Red)));
+        let label = match state.error_code {
+            Some(code) => format!("Error [{}]: {}", code, err),
+            None => format!("Error: {}", err),
+        };
+        // Transient failures are shown as warnings rather than hard errors
+        let color = if state.error_code.is_some_and(|code| code.is_retryable()) {
+            Color::Yellow
+        } else {
+            Color::Red
+        };
+        left_sections.push(StatusSection::new(label).style(Style::new().fg(color)));
     }
 
     let center_sections = if state.busy {
@@ -242,10 +460,77 @@ fn render_status(state: &AppState, area: Rect, buf: &mut Buffer) {
         vec![StatusSection::new("Idle").style(Style::new().fg(Color::Green))]
     };
 
-    let right_sections = vec![
-        StatusSection::new("Tab: scroll/chat"),
+    let keymap = &state.keymap;
+    let mut right_sections = vec![
+        StatusSection::new(format!(
+            "{}: scroll/chat",
+            keymap.hint(Context::Input, Action::FocusChat)
+        )),
+        StatusSection::new("/model: switch"),
+        StatusSection::new(format!(
+            "{}: sessions",
+            keymap.hint(Context::Global, Action::ToggleSessions)
+        )),
+        StatusSection::new(format!(
+            "{}: mesh",
+            keymap.hint(Context::Global, Action::ToggleMesh)
+        )),
+        StatusSection::new(format!(
+            "{}: tasks",
+            keymap.hint(Context::Global, Action::ToggleTasks)
+        )),
         StatusSection::new("Ctrl+C: quit"),
     ];
+    if !state.watches.is_empty() {
+        right_sections.insert(
+            0,
+            StatusSection::new(format!(
+                "watching {} path{}",
+                state.watches.len(),
+                if state.watches.len() == 1 { "" } else { "s" }
+            ))
+            .style(Style::new().fg(Color::Cyan)),
+        );
+    }
+    let running = state.running_tasks();
+    if running > 0 {
+        right_sections.insert(
+            0,
+            StatusSection::new(format!(
+                "{} task{} running",
+                running,
+                if running == 1 { "" } else { "s" }
+            ))
+            .style(Style::new().fg(Color::Yellow)),
+        );
+    }
+    if !state.session_usage.is_empty() {
+        right_sections.insert(0, StatusSection::new(usage_label(&state.session_usage)));
+    }
+    if !state.participants.is_empty() {
+        right_sections.insert(
+            0,
+            StatusSection::new(presence_label(&state.participants, state.pending_turns))
+                .style(Style::new().fg(Color::Magenta)),
+        );
+    }
+    if let Some(current) = &state.current_model {
+        right_sections.insert(
+            0,
+            StatusSection::new(model_label(current)).style(Style::new().fg(Color::Cyan)),
+        );
+    }
+    if !state.policy_violations.is_empty() {
+        right_sections.insert(
+            0,
+            StatusSection::new(format!(
+                "{}: policy ({})",
+                keymap.hint(Context::Global, Action::ToggleViolations),
+                state.policy_violations.len()
+            ))
+            .style(Style::new().fg(Color::Red)),
+        );
+    }
 
     let bar = StatusBar::new()
         .left(left_sections)
@@ -256,18 +541,868 @@ fn render_status(state: &AppState, area: Rect, buf: &mut Buffer) {
     Widget::render(&bar, area, buf);
 }
 
+fn render_violations(state: &AppState, area: Rect, buf: &mut Buffer) {
+    let overlay = Overlay::new()
+        .title("Policy Violations")
+        .border_color(Color::Red)
+        .help_text(overlay_close_help(state, Action::ToggleViolations))
+        .dimensions(0.8, 0.6);
+    let inner = overlay.render_frame(area, buf);
+    if inner.is_empty() {
+        return;
+    }
+
+    if state.policy_violations.is_empty() {
+        buf.set_string(
+            inner.x,
+            inner.y,
+            "No policy violations this session",
+            Style::new().fg(Color::DarkGrey),
+        );
+        return;
+    }
+
+    // Newest first, two lines per violation
+    let mut y = inner.y;
+    for violation in state.policy_violations.iter().rev() {
+        if y + 1 >= inner.bottom() {
+            break;
+        }
+        let outcome_style = match violation.outcome {
+            PolicyOutcome::Denied => Style::new().fg(Color::Red).bold(),
+            PolicyOutcome::Escalated => Style::new().fg(Color::Yellow).bold(),
+            PolicyOutcome::Audited => Style::new().fg(Color::Cyan).bold(),
+        };
+        let header = Line::from_spans([
+            Span::styled(
+                format!(
+                    "[{}] ",
+                    violation.timestamp.with_timezone(&Local).format("%H:%M:%S")
+                ),
+                Style::new().fg(Color::DarkGrey),
+            ),
+            Span::styled(violation.outcome.as_str().to_uppercase(), outcome_style),
+            Span::raw(format!(" {} · {}", violation.action, violation.resource)),
+        ]);
+        buf.set_line(inner.x, y, &header);
+        buf.set_string(
+            inner.x + 2,
+            y + 1,
+            &violation.rule,
+            Style::new().fg(Color::White),
+        );
+        y += 2;
+    }
+}
+
+fn render_sessions(state: &AppState, area: Rect, buf: &mut Buffer) {
+    let overlay = Overlay::new()
+        .title("Session History")
+        .border_color(Color::Magenta)
+        .help_text(format!(
+            "{}/{}: select | {}: resume | {}",
+            state.keymap.hint(Context::Overlay, Action::Previous),
+            state.keymap.hint(Context::Overlay, Action::Next),
+            state.keymap.hint(Context::Overlay, Action::Select),
+            overlay_close_help(state, Action::ToggleSessions)
+        ))
+        .dimensions(0.7, 0.6);
+    let inner = overlay.render_frame(area, buf);
+    if inner.is_empty() {
+        return;
+    }
+
+    if state.sessions.is_empty() {
+        buf.set_string(
+            inner.x,
+            inner.y,
+            "No saved sessions yet",
+            Style::new().fg(Color::DarkGrey),
+        );
+        return;
+    }
+
+    // Two lines per session; scroll so the selection stays visible
+    let visible = (inner.height as usize / 2).max(1);
+    let first = state.selected_session.saturating_sub(visible - 1);
+    let width = inner.width as usize;
+    let mut y = inner.y;
+    for (idx, session) in state.sessions.iter().enumerate().skip(first) {
+        if y + 1 >= inner.bottom() {
+            break;
+        }
+        let is_current = Some(&session.session_id) == state.session_id.as_ref();
+        let is_selected = idx == state.selected_session;
+        if is_selected {
+            for row in [y, y + 1] {
+                for x in inner.x..inner.right() {
+                    if let Some(cell) = buf.get_mut(x, row) {
+                        cell.bg = Color::Rgb(40, 40, 60);
+                    }
+                }
+            }
+        }
+
+        let (marker, marker_style) = if is_current {
+            ("● ", Style::new().fg(Color::Green))
+        } else {
+            ("○ ", Style::new().fg(Color::DarkGrey))
+        };
+        let title_style = if is_selected {
+            Style::new().fg(Color::White).bold()
+        } else {
+            Style::new().fg(Color::White)
+        };
+        let updated = session
+            .updated_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string();
+        let title = session_title(session, width.saturating_sub(updated.len() + 3));
+        buf.set_line(
+            inner.x,
+            y,
+            &Line::from_spans([
+                Span::styled(marker, marker_style),
+                Span::styled(title, title_style),
+            ]),
+        );
+        buf.set_string(
+            inner.right().saturating_sub(updated.len() as u16),
+            y,
+            &updated,
+            Style::new().fg(Color::DarkGrey),
+        );
+
+        let details: String = session_details(session)
+            .chars()
+            .take(width.saturating_sub(2))
+            .collect();
+        buf.set_string(
+            inner.x + 2,
+            y + 1,
+            &details,
+            Style::new().fg(Color::DarkGrey),
+        );
+        y += 2;
+    }
+}
+
+/// First line of the session's opening prompt, or its id, cut to `max` chars
+fn session_title(session: &SessionRecord, max: usize) -> String {
+    let title = session
+        .title
+        .as_deref()
+        .and_then(|t| t.lines().next())
+        .unwrap_or(&session.session_id);
+    if title.chars().count() > max {
+        let mut cut: String = title.chars().take(max.saturating_sub(1)).collect();
+        cut.push('…');
+        cut
+    } else {
+        title.to_string()
+    }
+}
+
+/// Session id, agent, model and activity counts
+fn session_details(session: &SessionRecord) -> String {
+    let mut parts = vec![session.session_id.clone()];
+    if let Some(agent) = &session.agent {
+        parts.push(agent.clone());
+    }
+    match (&session.provider, &session.model) {
+        (Some(provider), Some(model)) => parts.push(format!("{}/{}", provider, model)),
+        (Some(provider), None) => parts.push(provider.clone()),
+        (None, Some(model)) => parts.push(model.clone()),
+        (None, None) => {}
+    }
+    parts.push(format!("{} messages", session.message_count));
+    if session.tool_call_count > 0 {
+        parts.push(format!("{} tool calls", session.tool_call_count));
+    }
+    parts.join(" · ")
+}
+
+fn render_attachments(state: &AppState, area: Rect, buf: &mut Buffer) {
+    let overlay = Overlay::new()
+        .title("Attach to Next Message?")
+        .border_color(Color::Cyan)
+        .help_text("y/Enter: attach | p: insert paths as text | n/Esc: discard")
+        .dimensions(0.6, 0.5);
+    let inner = overlay.render_frame(area, buf);
+    if inner.height < 2 {
+        return;
+    }
+
+    let width = inner.width as usize;
+    let clip = |text: &str| -> String { text.chars().take(width).collect() };
+    let rows = inner.height as usize;
+    let pending = &state.pending_attachments;
+    let shown = if pending.len() > rows {
+        rows - 1
+    } else {
+        pending.len()
+    };
+    for (row, attachment) in pending.iter().take(shown).enumerate() {
+        let text = match attachment {
+            Attachment::File(path) => path.display().to_string(),
+            Attachment::Image { .. } => format!("{} from the clipboard", attachment.label()),
+        };
+        buf.set_string(
+            inner.x,
+            inner.y + row as u16,
+            &clip(&text),
+            Style::new().fg(Color::White),
+        );
+    }
+    if shown < pending.len() {
+        buf.set_string(
+            inner.x,
+            inner.y + shown as u16,
+            &clip(&format!("... and {} more", pending.len() - shown)),
+            Style::new().fg(Color::DarkGrey),
+        );
+    }
+}
+
+fn render_approval(request: &ToolApprovalRequest, choice: usize, area: Rect, buf: &mut Buffer) {
+    let overlay = Overlay::new()
+        .title("Tool Approval")
+        .border_color(Color::Yellow)
+        .help_text("←/→: choose | Enter: confirm | y: once | a: always | n/Esc: deny")
+        .dimensions(0.75, 0.7);
+    let inner = overlay.render_frame(area, buf);
+    if inner.height < 4 {
+        return;
+    }
+
+    let width = inner.width as usize;
+    let clip = |text: &str| -> String { text.chars().take(width).collect() };
+    let label = Style::new().fg(Color::DarkGrey);
+    buf.set_line(
+        inner.x,
+        inner.y,
+        &Line::from_spans([
+            Span::styled("Tool ", label),
+            Span::styled(
+                clip(&request.tool_name),
+                Style::new().fg(Color::Yellow).bold(),
+            ),
+        ]),
+    );
+    buf.set_line(
+        inner.x,
+        inner.y + 1,
+        &Line::from_spans([
+            Span::styled("Blocked by ", label),
+            Span::styled(clip(&request.rule), Style::new().fg(Color::Red)),
+        ]),
+    );
+    buf.set_string(inner.x, inner.y + 2, &clip(&request.description), label);
+
+    // Arguments, then the diff preview, above the choice row
+    let arguments = serde_json::to_string_pretty(&request.arguments)
+        .unwrap_or_else(|_| request.arguments.to_string());
+    let mut body: Vec<(String, Style)> = arguments
+        .lines()
+        .map(|line| (line.to_string(), Style::new().fg(Color::White)))
+        .collect();
+    if let Some(preview) = &request.preview {
+        body.push((String::new(), Style::default()));
+        body.extend(preview.lines().map(|line| {
+            let style = match line.chars().next() {
+                Some('+') => Style::new().fg(Color::Green),
+                Some('-') => Style::new().fg(Color::Red),
+                Some('@') => Style::new().fg(Color::Cyan),
+                _ => Style::new().fg(Color::DarkGrey),
+            };
+            (line.to_string(), style)
+        }));
+    }
+    let body_rows = inner.height.saturating_sub(6) as usize;
+    for (row, (text, style)) in body.iter().take(body_rows).enumerate() {
+        buf.set_string(inner.x, inner.y + 4 + row as u16, &clip(text), *style);
+    }
+
+    let mut spans = Vec::new();
+    for (idx, decision) in APPROVAL_CHOICES.iter().enumerate() {
+        let text = format!(" {} ", approval_label(*decision));
+        let style = if idx == choice {
+            Style::new().fg(Color::Black).bg(Color::Yellow).bold()
+        } else {
+            Style::new().fg(Color::White)
+        };
+        spans.push(Span::styled(text, style));
+        spans.push(Span::raw("  "));
+    }
+    buf.set_line(inner.x, inner.bottom() - 1, &Line::from_spans(spans));
+}
+
+fn approval_label(decision: ApprovalDecision) -> &'static str {
+    match decision {
+        ApprovalDecision::AllowOnce => "Allow once",
+        ApprovalDecision::AllowAlways => "Allow always",
+        ApprovalDecision::Deny => "Deny",
+    }
+}
+
+fn render_question(prompt: &QuestionPrompt, area: Rect, buf: &mut Buffer) {
+    let question = &prompt.request.question;
+    let help = match &question.form {
+        AnswerForm::Choice { multiple: true, .. } => {
+            "↑/↓: move | Space: tick | Enter: answer | Esc: decline"
+        }
+        AnswerForm::Choice { .. } => "↑/↓: move | Enter: answer | Esc: decline",
+        AnswerForm::Text {
+            multiline: true, ..
+        } => "Shift+Enter: new line | Enter: answer | Esc: decline",
+        _ => "Enter: answer | Esc: decline",
+    };
+    let overlay = Overlay::new()
+        .title("Question")
+        .border_color(Color::Cyan)
+        .help_text(help)
+        .dimensions(0.7, 0.6);
+    let inner = overlay.render_frame(area, buf);
+    if inner.height < 4 {
+        return;
+    }
+
+    let width = inner.width as usize;
+    let clip = |text: &str| -> String { text.chars().take(width).collect() };
+    let label = Style::new().fg(Color::DarkGrey);
+    let mut lines: Vec<Line> = vec![Line::styled(
+        clip(&question.question),
+        Style::new().fg(Color::White).bold(),
+    )];
+    if let Some(context) = &question.context {
+        lines.push(Line::styled(clip(context), label));
+    }
+    lines.push(Line::styled(clip(question_hint(&question.form)), label));
+    lines.push(Line::raw(""));
+
+    let typed = |prefix: &str| -> Line {
+        let mut text = prompt.input.replace('\n', " ⏎ ");
+        text.push('▏');
+        let shown = width.saturating_sub(prefix.chars().count());
+        // Keep the end of long input, where the cursor is, in view
+        let skip = text.chars().count().saturating_sub(shown);
+        Line::from_spans([
+            Span::styled(prefix.to_string(), label),
+            Span::styled(
+                text.chars().skip(skip).collect::<String>(),
+                Style::new().fg(Color::White),
+            ),
+        ])
+    };
+    match &question.form {
+        AnswerForm::Choice {
+            options,
+            multiple,
+            allow_other,
+        } => {
+            for (idx, option) in options.iter().enumerate() {
+                let mark = match (multiple, prompt.checked.get(idx)) {
+                    (true, Some(true)) => "[x] ",
+                    (true, _) => "[ ] ",
+                    (false, _) => "",
+                };
+                let style = if idx == prompt.cursor {
+                    Style::new().fg(Color::Black).bg(Color::Cyan).bold()
+                } else {
+                    Style::new().fg(Color::White)
+                };
+                lines.push(Line::styled(clip(&format!(" {}{} ", mark, option)), style));
+            }
+            if *allow_other {
+                if prompt.is_typing() {
+                    lines.push(typed(" Other: "));
+                } else if prompt.input.is_empty() {
+                    lines.push(Line::styled(" Other...", label));
+                } else {
+                    lines.push(Line::styled(
+                        clip(&format!(" Other: {}", prompt.input)),
+                        label,
+                    ));
+                }
+            }
+        }
+        AnswerForm::Text { placeholder, .. } => match placeholder {
+            Some(placeholder) if prompt.input.is_empty() => {
+                lines.push(Line::styled(clip(&format!("> {}", placeholder)), label));
+            }
+            _ => lines.push(typed("> ")),
+        },
+        AnswerForm::File { .. } => lines.push(typed("Path: ")),
+    }
+
+    let body_rows = inner.height.saturating_sub(1) as usize;
+    for (row, line) in lines.iter().take(body_rows).enumerate() {
+        buf.set_line(inner.x, inner.y + row as u16, line);
+    }
+    if let Some(problem) = &prompt.problem {
+        buf.set_string(
+            inner.x,
+            inner.bottom() - 1,
+            &clip(problem),
+            Style::new().fg(Color::Red),
+        );
+    }
+}
+
+/// What kind of answer a question takes, in words
+fn question_hint(form: &AnswerForm) -> &'static str {
+    match form {
+        AnswerForm::Text { .. } => "Type an answer",
+        AnswerForm::Choice {
+            multiple: true,
+            allow_other: true,
+            ..
+        } => "Tick any options, or type another answer",
+        AnswerForm::Choice { multiple: true, .. } => "Tick any options",
+        AnswerForm::Choice {
+            allow_other: true, ..
+        } => "Pick an option, or type another answer",
+        AnswerForm::Choice { .. } => "Pick an option",
+        AnswerForm::File { extensions } if extensions.is_empty() => "Enter the path of a file",
+        AnswerForm::File { .. } => "Enter the path of a file of an allowed type",
+    }
+}
+
+/// Heartbeats older than this are shown as late
+const MESH_HEARTBEAT_LATE_SECS: i64 = 15;
+
+/// Heartbeats older than this are shown as stale
+const MESH_HEARTBEAT_STALE_SECS: i64 = 60;
+
+/// Clocks further than this from the registry's are shown as skewed
+const MESH_CLOCK_SKEW_MS: u64 = 1000;
+
+fn render_mesh(state: &AppState, area: Rect, buf: &mut Buffer) {
+    let overlay = Overlay::new()
+        .title("Mesh Status")
+        .border_color(Color::Cyan)
+        .help_text(overlay_close_help(state, Action::ToggleMesh))
+        .dimensions(0.7, 0.6);
+    let inner = overlay.render_frame(area, buf);
+    if inner.is_empty() {
+        return;
+    }
+
+    let Some(updated_at) = state.mesh_updated_at else {
+        buf.set_string(
+            inner.x,
+            inner.y,
+            "Querying mesh registry...",
+            Style::new().fg(Color::DarkGrey),
+        );
+        return;
+    };
+
+    let width = inner.width as usize;
+    let (summary, summary_style) = match &state.mesh_error {
+        Some(err) => (err.clone(), Style::new().fg(Color::Red)),
+        None => (
+            format!(
+                "{} instance{} · updated {}",
+                state.mesh_instances.len(),
+                if state.mesh_instances.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                },
+                updated_at.with_timezone(&Local).format("%H:%M:%S")
+            ),
+            Style::new().fg(Color::DarkGrey),
+        ),
+    };
+    let summary: String = summary.chars().take(width).collect();
+    buf.set_string(inner.x, inner.y, &summary, summary_style);
+
+    let now = Utc::now();
+    let mut y = inner.y + 2;
+    for instance in &state.mesh_instances {
+        if y + 1 >= inner.bottom() {
+            break;
+        }
+
+        let (marker, marker_style) = if instance.is_leader {
+            ("★ ", Style::new().fg(Color::Yellow).bold())
+        } else {
+            ("○ ", Style::new().fg(Color::DarkGrey))
+        };
+        let age = (now - instance.last_heartbeat).num_seconds().max(0);
+        let age_style = match age {
+            a if a < MESH_HEARTBEAT_LATE_SECS => Style::new().fg(Color::Green),
+            a if a < MESH_HEARTBEAT_STALE_SECS => Style::new().fg(Color::Yellow),
+            _ => Style::new().fg(Color::Red),
+        };
+        let heartbeat = format!("♥ {} ago", format_age(age));
+        let name: String = format!(
+            "{} {}:{}",
+            instance.instance_id, instance.hostname, instance.port
+        )
+        .chars()
+        .take(width.saturating_sub(heartbeat.chars().count() + 3))
+        .collect();
+        buf.set_line(
+            inner.x,
+            y,
+            &Line::from_spans([
+                Span::styled(marker, marker_style),
+                Span::styled(name, Style::new().fg(Color::White)),
+            ]),
+        );
+        buf.set_string(
+            inner
+                .right()
+                .saturating_sub(heartbeat.chars().count() as u16),
+            y,
+            &heartbeat,
+            age_style,
+        );
+
+        let messages = state
+            .mesh_message_counts
+            .get(&instance.instance_id)
+            .copied()
+            .unwrap_or(0);
+        let details: String = mesh_details(instance, messages)
+            .chars()
+            .take(width.saturating_sub(2))
+            .collect();
+        buf.set_string(
+            inner.x + 2,
+            y + 1,
+            &details,
+            Style::new().fg(Color::DarkGrey),
+        );
+        y += 2;
+    }
+}
+
+/// Background tasks with the output of the highlighted one below them
+fn render_tasks(state: &AppState, area: Rect, buf: &mut Buffer) {
+    let overlay = Overlay::new()
+        .title("Background Tasks")
+        .border_color(Color::Yellow)
+        .help_text(format!(
+            "{}/{}: select | {}",
+            state.keymap.hint(Context::Overlay, Action::Previous),
+            state.keymap.hint(Context::Overlay, Action::Next),
+            overlay_close_help(state, Action::ToggleTasks)
+        ))
+        .dimensions(0.8, 0.7);
+    let inner = overlay.render_frame(area, buf);
+    if inner.is_empty() {
+        return;
+    }
+
+    if state.tasks.is_empty() {
+        buf.set_string(
+            inner.x,
+            inner.y,
+            "No background tasks; the agent starts them with task_start",
+            Style::new().fg(Color::DarkGrey),
+        );
+        return;
+    }
+
+    // A third of the panel lists tasks; scroll so the selection stays visible
+    let width = inner.width as usize;
+    let visible = (inner.height as usize / 3).clamp(1, state.tasks.len());
+    let first = state.selected_task.saturating_sub(visible - 1);
+    let now = Utc::now();
+    let mut y = inner.y;
+    for (idx, task) in state.tasks.iter().enumerate().skip(first).take(visible) {
+        let is_selected = idx == state.selected_task;
+        if is_selected {
+            for x in inner.x..inner.right() {
+                if let Some(cell) = buf.get_mut(x, y) {
+                    cell.bg = Color::Rgb(40, 40, 60);
+                }
+            }
+        }
+        let (marker, marker_color) = match task.state {
+            TaskState::Running => ("● ", Color::Yellow),
+            TaskState::Succeeded => ("✓ ", Color::Green),
+            TaskState::Failed => ("✗ ", Color::Red),
+            TaskState::Stopped => ("■ ", Color::DarkGrey),
+        };
+        let summary = task_summary(task, now);
+        let title: String = format!("{} {}", task.id, task.name.replace('\n', " "))
+            .chars()
+            .take(width.saturating_sub(summary.chars().count() + 3))
+            .collect();
+        let title_style = if is_selected {
+            Style::new().fg(Color::White).bold()
+        } else {
+            Style::new().fg(Color::White)
+        };
+        buf.set_line(
+            inner.x,
+            y,
+            &Line::from_spans([
+                Span::styled(marker, Style::new().fg(marker_color)),
+                Span::styled(title, title_style),
+            ]),
+        );
+        buf.set_string(
+            inner.right().saturating_sub(summary.chars().count() as u16),
+            y,
+            &summary,
+            Style::new().fg(Color::DarkGrey),
+        );
+        y += 1;
+    }
+
+    let Some(task) = state.highlighted_task() else {
+        return;
+    };
+    y += 1;
+    if y >= inner.bottom() {
+        return;
+    }
+    let command: String = format!("$ {}", task.command.replace('\n', " "))
+        .chars()
+        .take(width)
+        .collect();
+    buf.set_string(inner.x, y, &command, Style::new().fg(Color::Cyan));
+    y += 1;
+
+    // Newest lines at the bottom, as in a terminal
+    let rows = inner.bottom().saturating_sub(y) as usize;
+    let Some(logs) = state.task_logs.get(&task.id) else {
+        return;
+    };
+    for line in logs.iter().skip(logs.len().saturating_sub(rows)) {
+        let style = match line.stream {
+            LogStream::Stdout => Style::new().fg(Color::White),
+            LogStream::Stderr => Style::new().fg(Color::Yellow),
+        };
+        let text: String = line.text.chars().take(width).collect();
+        buf.set_string(inner.x, y, &text, style);
+        y += 1;
+    }
+}
+
+/// State and running time of a task, such as `running 42s` or `exit 1 · 3m`
+fn task_summary(task: &TaskInfo, now: DateTime<Utc>) -> String {
+    let elapsed = (task.finished_at.unwrap_or(now) - task.started_at)
+        .num_seconds()
+        .max(0);
+    let state = match (task.state, task.exit_code) {
+        (TaskState::Failed, Some(code)) => format!("exit {}", code),
+        (state, _) => state.as_str().to_string(),
+    };
+    format!("{} · {}", state, format_age(elapsed))
+}
+
+fn render_models(state: &AppState, area: Rect, buf: &mut Buffer) {
+    let overlay = Overlay::new()
+        .title("Switch Model")
+        .border_color(Color::Blue)
+        .help_text(format!(
+            "type to search | {}/{}: select | {}: switch | {}: close",
+            state.keymap.hint(Context::Overlay, Action::Previous),
+            state.keymap.hint(Context::Overlay, Action::Next),
+            state.keymap.hint(Context::Overlay, Action::Select),
+            state.keymap.hint(Context::Overlay, Action::Close)
+        ))
+        .dimensions(0.6, 0.6);
+    let inner = overlay.render_frame(area, buf);
+    if inner.is_empty() {
+        return;
+    }
+
+    let width = inner.width as usize;
+    buf.set_line(
+        inner.x,
+        inner.y,
+        &Line::from_spans([
+            Span::styled("Search: ", Style::new().fg(Color::DarkGrey)),
+            Span::styled(
+                format!("{}▏", state.model_query),
+                Style::new().fg(Color::White),
+            ),
+        ]),
+    );
+
+    if state.models_loading {
+        buf.set_string(
+            inner.x,
+            inner.y + 2,
+            &format!("{} Querying providers...", spinner_frame(state.tick / 2)),
+            Style::new().fg(Color::DarkGrey),
+        );
+        return;
+    }
+
+    // Providers that failed are listed below the models
+    let errors_height = state.model_errors.len().min(3) as u16;
+    let list_top = inner.y + 2;
+    let list_bottom = inner.bottom().saturating_sub(errors_height);
+    let models = state.filtered_models();
+    if models.is_empty() {
+        let message = if state.model_choices.is_empty() {
+            "No models available"
+        } else {
+            "No models match the search"
+        };
+        buf.set_string(inner.x, list_top, message, Style::new().fg(Color::DarkGrey));
+    }
+
+    // Scroll so the selection stays visible
+    let visible = (list_bottom.saturating_sub(list_top) as usize).max(1);
+    let first = state.selected_model.saturating_sub(visible - 1);
+    for (row, (idx, choice)) in models.iter().enumerate().skip(first).enumerate() {
+        let y = list_top + row as u16;
+        if y >= list_bottom {
+            break;
+        }
+        let is_current = Some(*choice) == state.current_model.as_ref();
+        let is_selected = idx == state.selected_model;
+        if is_selected {
+            for x in inner.x..inner.right() {
+                if let Some(cell) = buf.get_mut(x, y) {
+                    cell.bg = Color::Rgb(40, 40, 60);
+                }
+            }
+        }
+        let (marker, marker_style) = if is_current {
+            ("● ", Style::new().fg(Color::Green))
+        } else {
+            ("○ ", Style::new().fg(Color::DarkGrey))
+        };
+        let name_style = if is_selected {
+            Style::new().fg(Color::White).bold()
+        } else {
+            Style::new().fg(Color::White)
+        };
+        let provider = format!("{} ", choice.provider);
+        let name: String = choice
+            .model
+            .chars()
+            .take(width.saturating_sub(provider.chars().count() + 2))
+            .collect();
+        buf.set_line(
+            inner.x,
+            y,
+            &Line::from_spans([
+                Span::styled(marker, marker_style),
+                Span::styled(provider, Style::new().fg(Color::DarkGrey)),
+                Span::styled(name, name_style),
+            ]),
+        );
+    }
+
+    for (row, error) in state.model_errors.iter().take(3).enumerate() {
+        let error: String = error.chars().take(width).collect();
+        buf.set_string(
+            inner.x,
+            list_bottom + row as u16,
+            &error,
+            Style::new().fg(Color::Red),
+        );
+    }
+}
+
+/// Provider and model as shown in the status bar
+fn model_label(choice: &ModelChoice) -> String {
+    if choice.model.is_empty() {
+        choice.provider.clone()
+    } else {
+        format!("{}/{}", choice.provider, choice.model)
+    }
+}
+
+/// Session token total, with the estimated cost when every model is priced
+fn usage_label(usage: &SessionUsage) -> String {
+    let tokens = usage.total_tokens();
+    let tokens = if tokens >= 1_000_000 {
+        format!("{:.1}M", tokens as f64 / 1_000_000.0)
+    } else if tokens >= 1_000 {
+        format!("{:.1}k", tokens as f64 / 1_000.0)
+    } else {
+        tokens.to_string()
+    };
+    match usage.cost() {
+        Some(cost) => format!("{} tok · {:.4}", tokens, cost),
+        None => format!("{} tok", tokens),
+    }
+}
+
+/// Others in the session and what they are doing, with the server's pending messages
+fn presence_label(participants: &[Participant], pending_turns: u64) -> String {
+    let names: Vec<String> = participants
+        .iter()
+        .map(|p| match p.activity {
+            Activity::Idle => p.name.clone(),
+            activity => format!("{} ({})", p.name, activity.as_str()),
+        })
+        .collect();
+    let mut label = format!("with {}", names.join(", "));
+    if pending_turns > 0 {
+        label.push_str(&format!(" · {} pending", pending_turns));
+    }
+    label
+}
+
+/// Capabilities, agent profiles, recent message count and clock skew
+fn mesh_details(instance: &MeshInstance, messages: usize) -> String {
+    let mut parts = Vec::new();
+    if instance.capabilities.is_empty() {
+        parts.push("no capabilities".to_string());
+    } else {
+        parts.push(instance.capabilities.join(", "));
+    }
+    if !instance.agent_profiles.is_empty() {
+        parts.push(format!("agents: {}", instance.agent_profiles.join(", ")));
+    }
+    parts.push(format!(
+        "{} recent message{}",
+        messages,
+        if messages == 1 { "" } else { "s" }
+    ));
+    if let Some(clock) = instance.clock.filter(|c| c.exceeds(MESH_CLOCK_SKEW_MS)) {
+        parts.push(format!(
+            "clock {:.1}s {}",
+            clock.offset_ms.unsigned_abs() as f64 / 1000.0,
+            if clock.offset_ms > 0 {
+                "ahead"
+            } else {
+                "behind"
+            }
+        ));
+    }
+    parts.join(" · ")
+}
+
+/// Compact duration such as `42s`, `5m` or `3h`
+fn format_age(seconds: i64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86_400),
+    }
+}
+
 fn role_style(role: &ChatRole) -> (Style, String) {
     match role {
         ChatRole::User => (Style::new().fg(Color::Green).bold(), role.label()),
         ChatRole::Assistant => (Style::new().fg(Color::Cyan).bold(), role.label()),
         ChatRole::System => (Style::new().fg(Color::Yellow).bold(), role.label()),
         ChatRole::Agent(_) => (Style::new().fg(Color::Magenta).bold(), role.label()),
+        ChatRole::Tool(_) => (Style::new().fg(Color::Blue).bold(), role.label()),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::ChatMessage;
+    use spec_ai_core::mesh::ClockSample;
     use spec_ai_tui::style::Modifier;
 
     #[test]
@@ -298,6 +1433,152 @@ mod tests {
         assert_eq!(label, "Agent test");
     }
 
+    #[test]
+    fn task_summary_shows_exit_code_and_duration() {
+        let now = Utc::now();
+        let mut task = TaskInfo {
+            id: "task-1".to_string(),
+            name: "tests".to_string(),
+            command: "cargo test".to_string(),
+            working_dir: None,
+            state: TaskState::Running,
+            exit_code: None,
+            error: None,
+            started_at: now - chrono::Duration::seconds(42),
+            finished_at: None,
+            lines: 0,
+        };
+        assert_eq!(task_summary(&task, now), "running · 42s");
+
+        task.state = TaskState::Failed;
+        task.exit_code = Some(101);
+        task.finished_at = Some(now - chrono::Duration::seconds(2));
+        assert_eq!(task_summary(&task, now), "exit 101 · 40s");
+    }
+
+    #[test]
+    fn session_details_summarizes_metadata() {
+        let session = SessionRecord {
+            session_id: "session-1".to_string(),
+            agent: Some("coder".to_string()),
+            provider: Some("openai".to_string()),
+            model: Some("gpt-4o".to_string()),
+            title: Some("Fix the build\nwith details".to_string()),
+            message_count: 4,
+            tool_call_count: 2,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        assert_eq!(
+            session_details(&session),
+            "session-1 · coder · openai/gpt-4o · 4 messages · 2 tool calls"
+        );
+        assert_eq!(session_title(&session, 40), "Fix the build");
+        assert_eq!(session_title(&session, 5), "Fix …");
+    }
+
+    #[test]
+    fn mesh_details_summarizes_instance() {
+        let instance = MeshInstance {
+            instance_id: "node-a".to_string(),
+            hostname: "localhost".to_string(),
+            port: 3000,
+            capabilities: vec!["query".to_string(), "graph".to_string()],
+            is_leader: true,
+            last_heartbeat: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+            agent_profiles: vec!["coder".to_string()],
+            clock: None,
+        };
+        assert_eq!(
+            mesh_details(&instance, 1),
+            "query, graph · agents: coder · 1 recent message"
+        );
+        let skewed = MeshInstance {
+            clock: Some(ClockSample {
+                offset_ms: -2_500,
+                rtt_ms: 40,
+            }),
+            ..instance
+        };
+        assert_eq!(
+            mesh_details(&skewed, 0),
+            "query, graph · agents: coder · 0 recent messages · clock 2.5s behind"
+        );
+        assert_eq!(format_age(42), "42s");
+        assert_eq!(format_age(300), "5m");
+        assert_eq!(format_age(7200), "2h");
+    }
+
+    #[test]
+    fn usage_label_abbreviates_tokens_and_shows_priced_cost() {
+        use spec_ai_core::agent::model::TokenUsage;
+
+        let mut usage = SessionUsage::default();
+        usage.record("openai", "gpt-4.1", &TokenUsage::new(900, 300), Some(0.012));
+        assert_eq!(usage_label(&usage), "1.2k tok · 0.0120");
+
+        usage.record("ollama", "llama3", &TokenUsage::new(40, 10), None);
+        assert_eq!(usage_label(&usage), "1.2k tok");
+    }
+
+    #[test]
+    fn presence_label_names_others_and_pending_messages() {
+        let participant = |name: &str, activity| Participant {
+            name: name.to_string(),
+            client: "api".to_string(),
+            activity,
+            last_seen: Utc::now(),
+        };
+        let participants = vec![
+            participant("alice", Activity::Running),
+            participant("bob", Activity::Idle),
+        ];
+        assert_eq!(
+            presence_label(&participants, 2),
+            "with alice (running), bob · 2 pending"
+        );
+        assert_eq!(presence_label(&participants[1..], 0), "with bob");
+    }
+
+    #[test]
+    fn transcript_reads_messages_status_and_prompt() {
+        let (_tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut state = AppState::new(rx);
+        state.messages = vec![
+            ChatMessage::user("hi"),
+            ChatMessage::assistant("Two\nlines"),
+        ];
+        state.status = "Status: awaiting input".to_string();
+        state.editor.insert_str("next\nquestion");
+
+        let text = transcript(&state);
+        assert_eq!(text.lines, vec!["User: hi", "Assistant: Two", "lines"]);
+        assert_eq!(text.status.as_deref(), Some("Status: awaiting input"));
+        assert_eq!(text.prompt.as_deref(), Some("> next question"));
+
+        state.error = Some("offline".to_string());
+        assert_eq!(transcript(&state).status.as_deref(), Some("Error: offline"));
+    }
+
+    #[test]
+    fn typing_reuses_the_conversation_pane() {
+        let (_tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut state = AppState::new(rx);
+        state.messages = vec![ChatMessage::assistant("A **long** reply")];
+        let panes = PaneCache::new();
+        let area = Rect::new(0, 0, 80, 24);
+
+        render(&state, &panes, area, &mut Buffer::new(area));
+        state.editor.insert_str("typed");
+        render(&state, &panes, area, &mut Buffer::new(area));
+        assert_eq!((panes.stats().drawn, panes.stats().reused), (2, 2));
+
+        state.messages.push(ChatMessage::user("next"));
+        render(&state, &panes, area, &mut Buffer::new(area));
+        assert_eq!((panes.stats().drawn, panes.stats().reused), (3, 3));
+    }
+
     #[test]
     fn role_style_all_are_bold() {
         let roles = [
@@ -305,6 +1586,7 @@ mod tests {
             ChatRole::Assistant,
             ChatRole::System,
             ChatRole::Agent("x".to_string()),
+            ChatRole::Tool("x".to_string()),
         ];
         for role in &roles {
             let (style, _) = role_style(role);
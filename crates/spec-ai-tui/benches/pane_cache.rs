@@ -0,0 +1,107 @@
+//! Frame render time of a chat layout while typing, with and without panes
+//! cached
+//!
+//! Lays out a long markdown conversation above an input line, the shape of
+//! the spec-ai TUI, on a large terminal, then types a message one character
+//! per frame. Without a [`PaneCache`] every keystroke lays out the whole
+//! conversation again; with one only the input is drawn.
+//!
+//! Run with `cargo bench -p spec-ai-tui --bench pane_cache`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use spec_ai_tui::app::{pane_key, PaneCache};
+use spec_ai_tui::widget::builtin::Block;
+use spec_ai_tui::{
+    parse_markdown, Buffer, Constraint, Layout, Line, MarkdownConfig, Rect, Span, Style, Widget,
+};
+
+const WIDTH: u16 = 240;
+const HEIGHT: u16 = 70;
+const MESSAGES: usize = 200;
+const TYPED: &str = "Could you summarize the failing tests and suggest a fix for each of them?";
+
+fn conversation() -> Vec<String> {
+    (0..MESSAGES)
+        .map(|n| {
+            format!(
+                "## Step {n}\n\nRan `cargo test` on **crate {n}** and found *{} failures*:\n\n\
+                 - `parser::tokens` expected {n} tokens\n- `render::wrap` overflowed by {} columns\n\n\
+                 ```rust\nassert_eq!(tokens.len(), {n});\n```",
+                n % 5,
+                n % 13
+            )
+        })
+        .collect()
+}
+
+fn draw_chat(messages: &[String], area: Rect, buf: &mut Buffer) {
+    let block = Block::bordered().title("Conversation");
+    Widget::render(&block, area, buf);
+    let inner = block.inner(area);
+    let config = MarkdownConfig::new().max_width(inner.width as usize - 2);
+
+    let mut lines: Vec<Line> = Vec::new();
+    for message in messages {
+        lines.push(Line::from_spans([Span::styled(
+            "Assistant".to_string(),
+            Style::default(),
+        )]));
+        lines.extend(parse_markdown(message, &config).lines);
+        lines.push(Line::empty());
+    }
+    let start = lines.len().saturating_sub(inner.height as usize);
+    for (i, line) in lines[start..].iter().enumerate() {
+        buf.set_line(inner.x, inner.y + i as u16, line);
+    }
+}
+
+fn draw_input(text: &str, area: Rect, buf: &mut Buffer) {
+    let block = Block::bordered().title("Input");
+    Widget::render(&block, area, buf);
+    let inner = block.inner(area);
+    buf.set_string(inner.x, inner.y, text, Style::default());
+}
+
+/// Average time to render a frame while typing `TYPED`
+fn typing(messages: &[String], cache: Option<&PaneCache>) -> Duration {
+    let area = Rect::new(0, 0, WIDTH, HEIGHT);
+    let layout = Layout::vertical()
+        .constraints([Constraint::Fill(1), Constraint::Fixed(3)])
+        .split(area);
+    let chat_key = pane_key(&messages);
+
+    let started = Instant::now();
+    for typed in 1..=TYPED.len() {
+        let mut buf = Buffer::new(area);
+        match cache {
+            Some(cache) => cache.render("chat", chat_key, layout[0], &mut buf, |area, buf| {
+                draw_chat(messages, area, buf)
+            }),
+            None => draw_chat(messages, layout[0], &mut buf),
+        }
+        draw_input(&TYPED[..typed], layout[1], &mut buf);
+        black_box(&buf);
+    }
+    started.elapsed() / TYPED.len() as u32
+}
+
+fn main() {
+    let messages = conversation();
+    let uncached = typing(&messages, None);
+    let cache = PaneCache::new();
+    let cached = typing(&messages, Some(&cache));
+
+    println!(
+        "pane_cache: {WIDTH}x{HEIGHT} chat of {MESSAGES} messages, {} keystrokes",
+        TYPED.len()
+    );
+    println!("  uncached      {:>10.1?} / frame", uncached);
+    println!("  cached        {:>10.1?} / frame", cached);
+    println!(
+        "  speedup       {:>10.1}x",
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+    println!("  chat pane     {}", cache.stats());
+}
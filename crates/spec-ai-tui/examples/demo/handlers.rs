@@ -2,9 +2,10 @@
 
 use crate::models::{ChatMessage, ProcessStatus, ToolExecution, ToolStatus};
 use crate::state::{DemoState, OnboardingStep, Panel};
+use crate::ui::chat_markdown;
 use spec_ai_tui::{
     event::{Event, KeyCode, KeyModifiers},
-    style::truncate,
+    style::{truncate, StreamingMarkdown},
     widget::builtin::{EditorAction, Selection},
 };
 
@@ -177,12 +178,12 @@ pub fn apply_actions(state: &mut DemoState, actions: Vec<Action>) {
             }
 
             Action::StartStreaming => {
-                state.streaming = Some(String::new());
+                state.streaming = Some(StreamingMarkdown::new(chat_markdown()));
                 state.stream_index = 0;
             }
             Action::ProgressStreaming(chunk) => {
                 if let Some(ref mut s) = state.streaming {
-                    s.push_str(&chunk);
+                    s.push(&chunk);
                 }
                 state.stream_index += 1;
             }
@@ -824,7 +825,7 @@ pub fn handle_event(event: Event, state: &mut DemoState) -> bool {
                     KeyCode::Char('s') => {
                         // Start streaming simulation
                         if state.streaming.is_none() {
-                            state.streaming = Some(String::new());
+                            state.streaming = Some(StreamingMarkdown::new(chat_markdown()));
                             state.stream_index = 0;
                             state.status = "Streaming...".to_string();
                         }
@@ -922,7 +923,7 @@ pub fn handle_event(event: Event, state: &mut DemoState) -> bool {
                                     .push(ChatMessage::new("user", &text, &timestamp));
 
                                 // Start streaming response
-                                state.streaming = Some(String::new());
+                                state.streaming = Some(StreamingMarkdown::new(chat_markdown()));
                                 state.stream_index = 0;
                                 state.status = "Generating response...".to_string();
                             }
@@ -941,6 +942,11 @@ pub fn handle_event(event: Event, state: &mut DemoState) -> bool {
                             let count = filtered_command_count(state);
                             state.slash_menu.prev(count);
                         }
+                        EditorAction::MentionSelect(_) => {
+                            // The demo has no file index, so mentions stay as typed
+                            state.editor.close_mention_menu();
+                        }
+                        EditorAction::MentionMenuNext | EditorAction::MentionMenuPrev => {}
                         EditorAction::Escape => {
                             // Do nothing, slash menu already closed
                         }
@@ -1307,11 +1313,11 @@ pub fn on_tick(state: &mut DemoState) {
     // Simulate streaming
     if let Some(ref mut streaming) = state.streaming {
         if state.stream_index < state.stream_buffer.len() {
-            streaming.push_str(state.stream_buffer[state.stream_index]);
+            streaming.push(state.stream_buffer[state.stream_index]);
             state.stream_index += 1;
 
             // Update reasoning during streaming
-            let tokens_out = streaming.split_whitespace().count();
+            let tokens_out = streaming.source().split_whitespace().count();
             state.reasoning[0] = format!("{} Generating response...", spin_char);
             state.reasoning[1] = format!("  Tokens: ~{} output", tokens_out);
             state.reasoning[2] = format!(
@@ -1329,7 +1335,7 @@ pub fn on_tick(state: &mut DemoState) {
             );
             state
                 .messages
-                .push(ChatMessage::new("assistant", &response, &timestamp));
+                .push(ChatMessage::new("assistant", response.source(), &timestamp));
             state.status = "Ready".to_string();
             state.stream_index = 0;
 
@@ -1788,7 +1794,7 @@ mod tests {
             &mut state2,
             vec![Action::ProgressStreaming("hello ".to_string())],
         );
-        assert_eq!(state2.streaming.as_ref().unwrap(), "hello ");
+        assert_eq!(state2.streaming.as_ref().unwrap().source(), "hello ");
         assert_eq!(state2.stream_index, 1);
     }
 
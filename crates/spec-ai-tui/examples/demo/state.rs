@@ -1,6 +1,7 @@
 //! Demo application state and defaults.
 
 use crate::models::{AgentProcess, ChatMessage, Session, ToolExecution, ToolStatus};
+use spec_ai_tui::style::StreamingMarkdown;
 use spec_ai_tui::widget::builtin::{EditorState, SlashCommand, SlashMenuState};
 use std::collections::BTreeMap;
 
@@ -369,7 +370,7 @@ pub struct DemoState {
     /// Chat messages
     pub messages: Vec<ChatMessage>,
     /// Current streaming response (simulated)
-    pub streaming: Option<String>,
+    pub streaming: Option<StreamingMarkdown>,
     /// Scroll offset for chat
     pub scroll_offset: u16,
     /// Status message
@@ -545,6 +546,23 @@ impl Default for DemoState {
                 "actual ",
                 "LLM ",
                 "response!",
+                "\n\n",
+                "**Markdown** ",
+                "is ",
+                "laid ",
+                "out ",
+                "as ",
+                "it ",
+                "arrives:\n",
+                "- partial **bo",
+                "ld** ",
+                "text\n",
+                "- `inline ",
+                "code`\n",
+                "```rust\n",
+                "fn main() ",
+                "{}\n",
+                "```",
             ],
             stream_index: 0,
             listening: false,
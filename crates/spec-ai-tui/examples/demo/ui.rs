@@ -6,7 +6,7 @@ use spec_ai_tui::{
     buffer::Buffer,
     geometry::Rect,
     layout::{Constraint, Layout},
-    style::{truncate, wrap_text, Color, Line, Span, Style},
+    style::{parse_markdown, truncate, wrap_text, Color, Line, MarkdownConfig, Span, Style},
     widget::{
         builtin::{Block, Editor, Overlay, SlashCommand, SlashMenu, StatusBar, StatusSection},
         StatefulWidget, Widget,
@@ -56,6 +56,19 @@ pub fn render(state: &DemoState, area: Rect, buf: &mut Buffer) {
     }
 }
 
+/// Markdown styling for chat messages, shared by finished and streaming ones
+pub fn chat_markdown() -> MarkdownConfig {
+    MarkdownConfig::new().wrap_prefix("  ")
+}
+
+fn push_indented(lines: &mut Vec<Line>, content: Vec<Line>, indent: &str) {
+    for line in content {
+        let mut spans = vec![Span::raw(indent.to_string())];
+        spans.extend(line.spans);
+        lines.push(Line::from_spans(spans));
+    }
+}
+
 fn render_chat(state: &DemoState, area: Rect, buf: &mut Buffer) {
     // Draw border
     let border_style = if state.focus == Panel::Agent {
@@ -77,6 +90,8 @@ fn render_chat(state: &DemoState, area: Rect, buf: &mut Buffer) {
 
     // Build chat content with word wrapping
     let mut lines: Vec<Line> = Vec::new();
+    let markdown_width = content_width.saturating_sub(2);
+    let md_config = chat_markdown().max_width(markdown_width);
 
     for msg in &state.messages {
         // Role header
@@ -127,22 +142,17 @@ fn render_chat(state: &DemoState, area: Rect, buf: &mut Buffer) {
         };
         let prefix = if is_tool { "  │ " } else { "  " };
 
-        let content_to_render = if is_tool {
-            condensed_tool_summary(&msg.content, content_width)
-        } else {
-            msg.content.clone()
-        };
-
-        for content_line in content_to_render.lines() {
-            let prefixed = format!("{}{}", prefix, content_line);
-            let wrapped = wrap_text(&prefixed, content_width, prefix);
-            for wrapped_line in wrapped {
-                if is_tool {
+        if is_tool {
+            let summary = condensed_tool_summary(&msg.content, content_width);
+            for content_line in summary.lines() {
+                let prefixed = format!("{}{}", prefix, content_line);
+                for wrapped_line in wrap_text(&prefixed, content_width, prefix) {
                     lines.push(Line::styled(wrapped_line, content_style));
-                } else {
-                    lines.push(Line::raw(wrapped_line));
                 }
             }
+        } else {
+            let parsed = parse_markdown(&msg.content, &md_config);
+            push_indented(&mut lines, parsed.lines, prefix);
         }
 
         lines.push(Line::empty());
@@ -156,13 +166,8 @@ fn render_chat(state: &DemoState, area: Rect, buf: &mut Buffer) {
             Span::styled(" (streaming...)", Style::new().fg(Color::DarkGrey).italic()),
         ]));
 
-        for content_line in streaming.lines() {
-            let prefixed = format!("  {}", content_line);
-            let wrapped = wrap_text(&prefixed, content_width, "  ");
-            for wrapped_line in wrapped {
-                lines.push(Line::raw(wrapped_line));
-            }
-        }
+        // Only the lines touched by new chunks are laid out again
+        push_indented(&mut lines, streaming.render(markdown_width).lines, "  ");
 
         // Blinking cursor - always include line to prevent bobbing
         let cursor_char = if state.tick % 10 < 5 { "█" } else { " " };
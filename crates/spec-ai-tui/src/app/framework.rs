@@ -1,11 +1,19 @@
 //! Application framework with App trait and runner
 
+use super::linear::{LinearRenderer, RenderMode, Transcript};
+use super::mirror::{Mirror, MirrorFrame};
+use super::toast::{toast_channel, Toast, ToastSender, Toasts};
+use super::windows::{window_channel, WindowCommand, WindowController, WindowLauncher, Windows};
 use crate::buffer::Buffer;
+use crate::clock::{Clock, SystemClock};
 use crate::event::{Event, EventLoop};
 use crate::geometry::Rect;
+use crate::style::GlyphSet;
 use crate::terminal::Terminal;
 use std::io;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// Application trait for implementing TUI apps
 ///
@@ -28,28 +36,86 @@ pub trait App {
 
     /// Called before each render (optional)
     fn on_tick(&mut self, _state: &mut Self::State) {}
+
+    /// Receive the clock to read the time from, before `init` (optional)
+    ///
+    /// Reading the time from it instead of `Instant::now` keeps timed
+    /// behavior deterministic under [`TestBackend`](crate::testing::TestBackend).
+    fn set_clock(&mut self, _clock: Arc<dyn Clock>) {}
+
+    /// Render mode the state asks for (optional)
+    ///
+    /// Checked before each render, so apps can switch modes at runtime;
+    /// `None` keeps the runner's current mode.
+    fn render_mode(&self, _state: &Self::State) -> Option<RenderMode> {
+        None
+    }
+
+    /// The state as plain text for [`RenderMode::Linear`] (optional)
+    ///
+    /// Defaults to the rendered buffer read row by row without borders.
+    fn transcript(&self, _state: &Self::State) -> Option<Transcript> {
+        None
+    }
 }
 
 /// Application runner that manages the terminal and event loop
+///
+/// The runner also owns a toast queue: background tasks holding a
+/// [`ToastSender`] can show notifications without going through app state.
+/// In [`RenderMode::Linear`] toasts are announced as lines instead.
+///
+/// Likewise a [`WindowController`] opens auxiliary terminal windows, such
+/// as detached log viewers, which the runner closes when it exits.
 pub struct AppRunner<A: App> {
     app: A,
     terminal: Terminal,
     event_loop: EventLoop,
     tick_rate: Duration,
+    clock: Arc<dyn Clock>,
+    toasts: Toasts,
+    toast_tx: ToastSender,
+    toast_rx: mpsc::UnboundedReceiver<Toast>,
+    mode: RenderMode,
+    linear: LinearRenderer,
+    /// Toasts waiting to be announced in the linear mode
+    announcements: Vec<String>,
+    /// Where screen-mode frames are mirrored, if anywhere
+    mirror: Option<Mirror>,
+    windows: Windows,
+    window_tx: WindowController,
+    window_rx: mpsc::UnboundedReceiver<WindowCommand>,
 }
 
 impl<A: App> AppRunner<A> {
     /// Create a new app runner
+    ///
+    /// Also selects the [`GlyphSet`] for the terminal (see
+    /// [`GlyphSet::detect`]).
     pub fn new(app: A) -> io::Result<Self> {
         let terminal = Terminal::new()?;
+        GlyphSet::detect().set_current();
         let tick_rate = Duration::from_millis(100);
         let event_loop = EventLoop::new(tick_rate);
+        let (toast_tx, toast_rx) = toast_channel();
+        let (window_tx, window_rx) = window_channel();
 
         Ok(Self {
             app,
             terminal,
             event_loop,
             tick_rate,
+            clock: Arc::new(SystemClock),
+            toasts: Toasts::new(),
+            toast_tx,
+            toast_rx,
+            mode: RenderMode::default(),
+            linear: LinearRenderer::new(),
+            announcements: Vec::new(),
+            mirror: None,
+            windows: Windows::new(WindowLauncher::detect()),
+            window_tx,
+            window_rx,
         })
     }
 
@@ -60,17 +126,64 @@ impl<A: App> AppRunner<A> {
         self
     }
 
+    /// Set the clock the app and toasts read the time from
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
     /// Get a sender for custom events
     pub fn event_sender(&self) -> tokio::sync::mpsc::UnboundedSender<Event> {
         self.event_loop.sender()
     }
 
+    /// Set how toasts are laid out
+    pub fn toasts(mut self, toasts: Toasts) -> Self {
+        self.toasts = toasts;
+        self
+    }
+
+    /// Get a sender for toast notifications
+    pub fn toast_sender(&self) -> ToastSender {
+        self.toast_tx.clone()
+    }
+
+    /// Set the render mode to start in
+    pub fn render_mode(mut self, mode: RenderMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Mirror every screen-mode frame to a channel (see [`Mirror`])
+    ///
+    /// Mirroring stops once the receiver is dropped.
+    pub fn mirror(mut self, frames: mpsc::UnboundedSender<MirrorFrame>) -> Self {
+        self.mirror = Some(Mirror::new(frames));
+        self
+    }
+
+    /// Set how auxiliary windows are opened (see [`WindowLauncher::detect`]
+    /// for the default)
+    pub fn windows(mut self, windows: Windows) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    /// Get a controller for auxiliary windows
+    pub fn window_controller(&self) -> WindowController {
+        self.window_tx.clone()
+    }
+
     /// Run the application
     pub async fn run(&mut self) -> io::Result<()> {
         // Enter raw mode
-        let _raw_guard = self.terminal.enter_raw_mode()?;
+        let _raw_guard = match self.mode {
+            RenderMode::Screen => self.terminal.enter_raw_mode()?,
+            RenderMode::Linear => self.terminal.enter_raw_mode_inline()?,
+        };
 
         // Initialize state
+        self.app.set_clock(self.clock.clone());
         let mut state = self.app.init();
 
         // Initial render
@@ -78,41 +191,111 @@ impl<A: App> AppRunner<A> {
 
         // Main event loop
         loop {
-            if let Some(event) = self.event_loop.next().await {
-                // Handle resize
-                if let Event::Resize { .. } = &event {
-                    self.terminal.refresh_size()?;
-                    self.terminal.invalidate();
-                }
-
-                // Handle tick
-                if matches!(event, Event::Tick) {
-                    self.app.on_tick(&mut state);
+            let event = tokio::select! {
+                event = self.event_loop.next() => event,
+                Some(toast) = self.toast_rx.recv() => {
+                    self.notify(toast);
+                    self.render(&state)?;
+                    continue;
                 }
-
-                // Let app handle the event
-                if !self.app.handle_event(event, &mut state) {
-                    break;
+                Some(command) = self.window_rx.recv() => {
+                    if let Err(e) = self.windows.apply(command) {
+                        self.notify(Toast::error(e.to_string()));
+                        self.render(&state)?;
+                    }
+                    continue;
                 }
+            };
 
-                // Render after each event
-                self.render(&state)?;
-            } else {
+            let Some(event) = event else {
                 // Event stream ended
                 break;
+            };
+
+            // Handle resize
+            if let Event::Resize { .. } = &event {
+                self.terminal.refresh_size()?;
+                self.terminal.invalidate();
+            }
+
+            // Handle tick
+            if matches!(event, Event::Tick) {
+                self.app.on_tick(&mut state);
+                self.toasts.prune_at(self.clock.now());
             }
+
+            // Let app handle the event
+            if !self.app.handle_event(event, &mut state) {
+                break;
+            }
+
+            // Render after each event
+            self.render(&state)?;
         }
 
+        self.windows.close_all();
         Ok(())
     }
 
+    /// Show a toast, or queue it for announcement in the linear mode
+    fn notify(&mut self, toast: Toast) {
+        match self.mode {
+            RenderMode::Screen => self.toasts.push_at(toast, self.clock.now()),
+            RenderMode::Linear => {
+                self.announcements
+                    .push(format!("{}: {}", toast.level.label(), toast.message))
+            }
+        }
+    }
+
     /// Render the current state
     fn render(&mut self, state: &A::State) -> io::Result<()> {
+        if let Some(mode) = self.app.render_mode(state) {
+            self.switch_mode(mode)?;
+        }
         let area = self.terminal.full_rect();
-        let mut buf = Buffer::new(area);
 
+        if self.mode == RenderMode::Linear {
+            let transcript = self.app.transcript(state).unwrap_or_else(|| {
+                let mut buf = Buffer::new(area);
+                self.app.render(state, area, &mut buf);
+                Transcript::from_buffer(&buf)
+            });
+            let text = self.linear.render(&transcript, &self.announcements);
+            self.announcements.clear();
+            // Unchanged frames write nothing, so ticks cause no churn
+            if text.is_empty() {
+                return Ok(());
+            }
+            return self.terminal.write_text(&text);
+        }
+
+        let mut buf = Buffer::new(area);
         self.app.render(state, area, &mut buf);
-        self.terminal.draw(&buf)
+        self.toasts.render(area, &mut buf);
+        self.terminal.draw(&buf)?;
+        if let Some(mirror) = &mut self.mirror {
+            if !mirror.publish(&buf) {
+                self.mirror = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the terminal to `mode`'s screen
+    fn switch_mode(&mut self, mode: RenderMode) -> io::Result<()> {
+        if mode == self.mode {
+            return Ok(());
+        }
+        self.mode = mode;
+        match mode {
+            RenderMode::Screen => self.terminal.set_alternate_screen(true),
+            RenderMode::Linear => {
+                // The normal screen has none of the transcript yet
+                self.linear.reset();
+                self.terminal.set_alternate_screen(false)
+            }
+        }
     }
 }
 
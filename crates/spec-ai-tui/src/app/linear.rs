@@ -0,0 +1,207 @@
+//! Linear render mode for screen readers
+//!
+//! Screen readers follow a terminal as a stream of text, which full-screen
+//! apps defeat: borders are read out glyph by glyph and every redraw looks
+//! like new output. In [`RenderMode::Linear`] the runner stays on the normal
+//! screen and instead appends a [`Transcript`] of the state, writing only
+//! lines it has not written before, announcing status changes and toasts as
+//! lines of their own, and echoing edits to the prompt in place.
+
+use crate::buffer::Buffer;
+
+/// How the runner presents frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RenderMode {
+    /// Full-screen on the alternate screen, redrawing changed cells
+    #[default]
+    Screen,
+    /// Plain lines appended to the normal screen, for screen readers
+    Linear,
+}
+
+/// The state as plain text in reading order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    /// Finished output; each line is written once
+    pub lines: Vec<String>,
+    /// What the app is doing, announced whenever it changes
+    pub status: Option<String>,
+    /// Line being edited, rewritten in place below the output
+    pub prompt: Option<String>,
+}
+
+impl Transcript {
+    /// Read a rendered buffer row by row, without borders or blank rows
+    pub fn from_buffer(buf: &Buffer) -> Self {
+        let area = buf.area();
+        let lines = (area.y..area.bottom())
+            .filter_map(|y| {
+                let row: String = (area.x..area.right())
+                    .filter_map(|x| buf.get(x, y))
+                    .map(|cell| cell.symbol.as_str())
+                    .collect();
+                let text = plain_text(&row);
+                (!text.is_empty()).then_some(text)
+            })
+            .collect();
+        Self {
+            lines,
+            ..Self::default()
+        }
+    }
+}
+
+/// `text` without box-drawing, block or braille glyphs and with runs of
+/// whitespace collapsed
+pub fn plain_text(text: &str) -> String {
+    text.chars()
+        .map(|c| if is_decoration(c) { ' ' } else { c })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Glyphs that only draw borders, bars or spinners
+fn is_decoration(c: char) -> bool {
+    matches!(c, '\u{2500}'..='\u{259F}' | '\u{2800}'..='\u{28FF}')
+}
+
+/// Turns successive transcripts into the text to append to the terminal
+#[derive(Debug, Default)]
+pub struct LinearRenderer {
+    /// Lines of the transcript already written
+    written: Vec<String>,
+    /// Last status announced
+    status: Option<String>,
+    /// Prompt on the terminal's last line
+    prompt: Option<String>,
+}
+
+impl LinearRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget what was written, so the next transcript is written in full
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Text to write for `transcript`, preceded by `announcements`
+    ///
+    /// Empty when nothing changed. Lines end in `\r\n` since the terminal is
+    /// in raw mode.
+    pub fn render(&mut self, transcript: &Transcript, announcements: &[String]) -> String {
+        let kept = self
+            .written
+            .iter()
+            .zip(&transcript.lines)
+            .take_while(|(written, line)| written == line)
+            .count();
+        let status = transcript
+            .status
+            .as_ref()
+            .filter(|status| self.status.as_ref() != Some(*status));
+
+        let mut output: Vec<String> = announcements.to_vec();
+        output.extend(transcript.lines[kept..].iter().cloned());
+        output.extend(status.cloned());
+        self.written = transcript.lines.clone();
+        self.status = transcript.status.clone();
+
+        let mut text = String::new();
+        if output.is_empty() {
+            // Typing usually appends to the prompt, so only echo what is new
+            if transcript.prompt != self.prompt {
+                let shown = self.prompt.as_deref().unwrap_or_default();
+                let prompt = transcript.prompt.as_deref().unwrap_or_default();
+                match prompt.strip_prefix(shown) {
+                    Some(typed) => text.push_str(typed),
+                    None => {
+                        text.push_str("\r\x1b[2K");
+                        text.push_str(prompt);
+                    }
+                }
+            }
+        } else {
+            if self.prompt.is_some() {
+                text.push_str("\r\x1b[2K");
+            }
+            for line in &output {
+                text.push_str(line);
+                text.push_str("\r\n");
+            }
+            text.push_str(transcript.prompt.as_deref().unwrap_or_default());
+        }
+        self.prompt = transcript.prompt.clone();
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+    use crate::style::Style;
+    use crate::widget::builtin::Block;
+    use crate::widget::Widget;
+
+    fn transcript(lines: &[&str], status: &str, prompt: &str) -> Transcript {
+        Transcript {
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+            status: Some(status.to_string()),
+            prompt: Some(prompt.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_buffer_transcript_drops_borders() {
+        let area = Rect::new(0, 0, 20, 4);
+        let mut buf = Buffer::new(area);
+        Block::bordered().title("Chat").render(area, &mut buf);
+        buf.set_string(2, 1, "Hello   there", Style::default());
+
+        let transcript = Transcript::from_buffer(&buf);
+        assert_eq!(transcript.lines, vec!["Chat", "Hello there"]);
+        assert_eq!(plain_text("⠋ Thinking ▌▌"), "Thinking");
+    }
+
+    #[test]
+    fn test_only_new_output_is_written() {
+        let mut renderer = LinearRenderer::new();
+        assert_eq!(
+            renderer.render(&transcript(&["User: hi"], "Status: ready", "> "), &[]),
+            "User: hi\r\nStatus: ready\r\n> "
+        );
+        assert_eq!(
+            renderer.render(&transcript(&["User: hi"], "Status: ready", "> "), &[]),
+            ""
+        );
+
+        // Typing echoes only the new characters
+        assert_eq!(
+            renderer.render(&transcript(&["User: hi"], "Status: ready", "> ok"), &[]),
+            "ok"
+        );
+        assert_eq!(
+            renderer.render(&transcript(&["User: hi"], "Status: ready", "> o"), &[]),
+            "\r\x1b[2K> o"
+        );
+
+        // New output clears the prompt and writes it again below
+        assert_eq!(
+            renderer.render(
+                &transcript(&["User: hi", "Assistant: hello"], "Status: working", "> "),
+                &["Error: offline".to_string()]
+            ),
+            "\r\x1b[2KError: offline\r\nAssistant: hello\r\nStatus: working\r\n> "
+        );
+
+        renderer.reset();
+        assert_eq!(
+            renderer.render(&transcript(&["User: hi"], "Status: working", ""), &[]),
+            "User: hi\r\nStatus: working\r\n"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Read-only mirroring of rendered frames to other viewers
+//!
+//! The runner hands every screen-mode buffer to a [`Mirror`], which encodes
+//! it as ANSI text: a keyframe repaints the whole screen, later frames only
+//! repaint the cells that changed, using the same buffer diff as the
+//! terminal. Viewers that join late start from the latest keyframe, which is
+//! re-sent periodically so they never replay a long run of diffs. Inline
+//! images are not mirrored.
+
+use std::time::{Duration, Instant};
+
+use crossterm::{
+    queue,
+    style::ResetColor,
+    terminal::{Clear, ClearType},
+};
+use tokio::sync::mpsc;
+
+use crate::buffer::Buffer;
+use crate::terminal::{queue_buffer, queue_cell};
+
+/// How often a changed screen is sent whole instead of as a diff
+pub const DEFAULT_KEYFRAME_INTERVAL: Duration = Duration::from_secs(5);
+
+/// One encoded frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirrorFrame {
+    /// Position in the stream, starting at 0
+    pub seq: u64,
+    /// Whether the frame repaints the whole screen
+    pub keyframe: bool,
+    pub width: u16,
+    pub height: u16,
+    /// Escape sequences that bring a viewer's screen up to date
+    pub ansi: String,
+}
+
+/// Turns successive buffers into keyframes and diffs
+pub struct FrameEncoder {
+    prev: Option<Buffer>,
+    seq: u64,
+    last_keyframe: Option<Instant>,
+    keyframe_interval: Duration,
+}
+
+impl FrameEncoder {
+    pub fn new() -> Self {
+        Self {
+            prev: None,
+            seq: 0,
+            last_keyframe: None,
+            keyframe_interval: DEFAULT_KEYFRAME_INTERVAL,
+        }
+    }
+
+    /// Set how often a changed screen is sent whole
+    pub fn keyframe_interval(mut self, interval: Duration) -> Self {
+        self.keyframe_interval = interval;
+        self
+    }
+
+    /// Force the next frame to be a keyframe
+    pub fn reset(&mut self) {
+        self.prev = None;
+    }
+
+    /// Encode a buffer; `None` if nothing changed since the last frame
+    pub fn encode(&mut self, buffer: &Buffer) -> std::io::Result<Option<MirrorFrame>> {
+        self.encode_at(buffer, Instant::now())
+    }
+
+    fn encode_at(&mut self, buffer: &Buffer, now: Instant) -> std::io::Result<Option<MirrorFrame>> {
+        let mut out = Vec::new();
+        let keyframe = match &self.prev {
+            Some(prev) if prev.area() == buffer.area() => {
+                let mut changes = buffer.diff(prev).peekable();
+                if changes.peek().is_none() {
+                    return Ok(None);
+                }
+                let due = self
+                    .last_keyframe
+                    .is_none_or(|at| now.duration_since(at) >= self.keyframe_interval);
+                if due {
+                    true
+                } else {
+                    for (x, y, cell) in changes {
+                        queue_cell(&mut out, x, y, cell)?;
+                    }
+                    queue!(out, ResetColor)?;
+                    false
+                }
+            }
+            // First frame or resized
+            _ => true,
+        };
+
+        if keyframe {
+            queue!(out, ResetColor, Clear(ClearType::All))?;
+            queue_buffer(&mut out, buffer)?;
+            queue!(out, ResetColor)?;
+            self.last_keyframe = Some(now);
+        }
+
+        let area = buffer.area();
+        let frame = MirrorFrame {
+            seq: self.seq,
+            keyframe,
+            width: area.width,
+            height: area.height,
+            ansi: String::from_utf8_lossy(&out).into_owned(),
+        };
+        self.seq += 1;
+        self.prev = Some(buffer.clone());
+        Ok(Some(frame))
+    }
+}
+
+impl Default for FrameEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sends the frames the runner renders to a channel
+pub struct Mirror {
+    encoder: FrameEncoder,
+    frames: mpsc::UnboundedSender<MirrorFrame>,
+}
+
+impl Mirror {
+    pub fn new(frames: mpsc::UnboundedSender<MirrorFrame>) -> Self {
+        Self {
+            encoder: FrameEncoder::new(),
+            frames,
+        }
+    }
+
+    /// Set the encoder, e.g. to change its keyframe interval
+    pub fn encoder(mut self, encoder: FrameEncoder) -> Self {
+        self.encoder = encoder;
+        self
+    }
+
+    /// Encode and send a buffer; false once nobody receives the frames
+    pub fn publish(&mut self, buffer: &Buffer) -> bool {
+        if self.frames.is_closed() {
+            return false;
+        }
+        match self.encoder.encode(buffer) {
+            Ok(Some(frame)) => self.frames.send(frame).is_ok(),
+            Ok(None) => true,
+            // Writing to a Vec only fails on formatting errors; skip the frame
+            Err(_) => {
+                self.encoder.reset();
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+    use crate::style::Style;
+
+    #[test]
+    fn test_encoder_sends_keyframes_then_diffs() {
+        let start = Instant::now();
+        let mut encoder = FrameEncoder::new();
+        let mut buf = Buffer::new(Rect::new(0, 0, 10, 2));
+        buf.set_string(0, 0, "hello", Style::default());
+
+        let first = encoder.encode_at(&buf, start).unwrap().unwrap();
+        assert!(first.keyframe);
+        assert_eq!((first.seq, first.width, first.height), (0, 10, 2));
+        assert!(first.ansi.contains("\x1b[2J"));
+        assert!(first.ansi.contains('h'));
+
+        assert!(encoder.encode_at(&buf, start).unwrap().is_none());
+
+        buf.set_string(0, 1, "x", Style::default());
+        let diff = encoder.encode_at(&buf, start).unwrap().unwrap();
+        assert!(!diff.keyframe);
+        assert_eq!(diff.seq, 1);
+        assert!(diff.ansi.contains('x'));
+        assert!(!diff.ansi.contains('h'));
+
+        buf.set_string(1, 1, "y", Style::default());
+        let due = start + DEFAULT_KEYFRAME_INTERVAL;
+        assert!(encoder.encode_at(&buf, due).unwrap().unwrap().keyframe);
+
+        let resized = Buffer::new(Rect::new(0, 0, 12, 2));
+        let frame = encoder.encode_at(&resized, due).unwrap().unwrap();
+        assert!(frame.keyframe);
+        assert_eq!((frame.seq, frame.width), (3, 12));
+
+        encoder.reset();
+        assert!(encoder.encode_at(&resized, due).unwrap().unwrap().keyframe);
+    }
+
+    #[test]
+    fn test_publish_stops_when_receiver_is_dropped() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut mirror = Mirror::new(tx);
+        let buf = Buffer::new(Rect::new(0, 0, 4, 1));
+
+        assert!(mirror.publish(&buf));
+        assert!(mirror.publish(&buf));
+        assert!(rx.try_recv().unwrap().keyframe);
+        assert!(rx.try_recv().is_err());
+
+        drop(rx);
+        assert!(!mirror.publish(&buf));
+    }
+}
@@ -1,5 +1,17 @@
 //! Application framework for building TUI apps
 
 mod framework;
+mod linear;
+mod mirror;
+mod panes;
+mod toast;
+mod windows;
 
 pub use framework::{App, AppRunner};
+pub use linear::{plain_text, LinearRenderer, RenderMode, Transcript};
+pub use mirror::{FrameEncoder, Mirror, MirrorFrame, DEFAULT_KEYFRAME_INTERVAL};
+pub use panes::{pane_key, PaneCache, PaneStats};
+pub use toast::{toast_channel, Toast, ToastLevel, ToastSender, Toasts, DEFAULT_TOAST_TIMEOUT};
+pub use windows::{
+    window_channel, WindowCommand, WindowController, WindowId, WindowLauncher, Windows,
+};
@@ -0,0 +1,234 @@
+//! Per-pane render caching
+//!
+//! Most frames change one part of the screen: typing redraws the input but
+//! leaves a long conversation as it was. A [`PaneCache`] keeps the cells each
+//! pane drew last along with a key summarizing what the pane showed, and
+//! copies them back instead of drawing again while the key and area stay the
+//! same. A pane can also be given a refresh interval, capping how often it
+//! redraws while its key changes quickly, such as a reply streaming in.
+
+use crate::buffer::Buffer;
+use crate::geometry::Rect;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Key of a pane drawn from `value`
+///
+/// Hashing the state a pane reads is far cheaper than laying it out again.
+pub fn pane_key(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// How often panes were drawn or reused
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PaneStats {
+    /// Times a pane was drawn
+    pub drawn: u64,
+    /// Times a pane's last cells were reused because its key was unchanged
+    pub reused: u64,
+    /// Times a changed pane kept its last cells until its refresh interval
+    /// passed
+    pub deferred: u64,
+    /// Time spent drawing panes
+    pub draw_time: Duration,
+}
+
+impl PaneStats {
+    /// Fraction of pane renders that skipped drawing
+    pub fn skip_ratio(&self) -> f64 {
+        let skipped = self.reused + self.deferred;
+        let total = self.drawn + skipped;
+        if total == 0 {
+            0.0
+        } else {
+            skipped as f64 / total as f64
+        }
+    }
+}
+
+impl fmt::Display for PaneStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} drawn, {} reused, {} deferred ({:.0}% skipped), {:.1?} drawing",
+            self.drawn,
+            self.reused,
+            self.deferred,
+            self.skip_ratio() * 100.0,
+            self.draw_time
+        )
+    }
+}
+
+struct CachedPane {
+    key: u64,
+    cells: Buffer,
+    drawn_at: Instant,
+}
+
+#[derive(Default)]
+struct CacheState {
+    panes: HashMap<&'static str, CachedPane>,
+    intervals: HashMap<&'static str, Duration>,
+    stats: PaneStats,
+}
+
+/// Cells of each pane from its last draw, reused while the pane is unchanged
+///
+/// Render methods take `&self`, so the cache locks internally and can live
+/// in the app alongside them.
+#[derive(Default)]
+pub struct PaneCache {
+    state: Mutex<CacheState>,
+}
+
+impl PaneCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redraw pane `id` at most once per `interval`
+    ///
+    /// In between, a changed pane shows its last cells. The runner renders on
+    /// every tick, so the pane catches up once the interval has passed.
+    pub fn refresh_interval(self, id: &'static str, interval: Duration) -> Self {
+        self.lock().intervals.insert(id, interval);
+        self
+    }
+
+    /// Render pane `id` over `area`, calling `draw` only when needed
+    ///
+    /// `key` must change whenever anything `draw` reads changes; see
+    /// [`pane_key`]. `draw` should stay within `area`, since only those
+    /// cells are kept.
+    pub fn render(
+        &self,
+        id: &'static str,
+        key: u64,
+        area: Rect,
+        buf: &mut Buffer,
+        draw: impl FnOnce(Rect, &mut Buffer),
+    ) {
+        {
+            let mut state = self.lock();
+            let interval = state.intervals.get(id).copied();
+            let reuse = state.panes.get(id).and_then(|pane| {
+                if pane.cells.area() != area {
+                    None
+                } else if pane.key == key {
+                    Some(true)
+                } else if interval.is_some_and(|interval| pane.drawn_at.elapsed() < interval) {
+                    Some(false)
+                } else {
+                    None
+                }
+            });
+            if let Some(unchanged) = reuse {
+                buf.merge(&state.panes[id].cells);
+                if unchanged {
+                    state.stats.reused += 1;
+                } else {
+                    state.stats.deferred += 1;
+                }
+                return;
+            }
+        }
+
+        // Not locked while drawing, so panes may nest
+        let started = Instant::now();
+        draw(area, buf);
+        let elapsed = started.elapsed();
+
+        let mut state = self.lock();
+        state.stats.drawn += 1;
+        state.stats.draw_time += elapsed;
+        state.panes.insert(
+            id,
+            CachedPane {
+                key,
+                cells: buf.region(area),
+                drawn_at: started,
+            },
+        );
+    }
+
+    /// Forget every pane, so each is drawn on its next render
+    pub fn invalidate(&self) {
+        self.lock().panes.clear();
+    }
+
+    /// Counts since the cache was created
+    pub fn stats(&self) -> PaneStats {
+        self.lock().stats
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::Style;
+
+    fn render(cache: &PaneCache, text: &str, area: Rect, buf: &mut Buffer) -> bool {
+        let mut drew = false;
+        cache.render("pane", pane_key(&text), area, buf, |area, buf| {
+            drew = true;
+            buf.set_string(area.x, area.y, text, Style::default());
+        });
+        drew
+    }
+
+    #[test]
+    fn test_unchanged_panes_are_copied_not_drawn() {
+        let cache = PaneCache::new();
+        let area = Rect::new(2, 1, 10, 2);
+
+        let mut first = Buffer::new(Rect::new(0, 0, 20, 4));
+        assert!(render(&cache, "hello", area, &mut first));
+        let mut second = Buffer::new(Rect::new(0, 0, 20, 4));
+        assert!(!render(&cache, "hello", area, &mut second));
+        assert_eq!(second.get(2, 1).unwrap().symbol, "h");
+        assert_eq!(first.diff(&second).count(), 0);
+
+        assert!(render(&cache, "changed", area, &mut second));
+        assert!(render(
+            &cache,
+            "changed",
+            Rect::new(0, 0, 10, 2),
+            &mut second
+        ));
+        cache.invalidate();
+        assert!(render(
+            &cache,
+            "changed",
+            Rect::new(0, 0, 10, 2),
+            &mut second
+        ));
+
+        let stats = cache.stats();
+        assert_eq!((stats.drawn, stats.reused, stats.deferred), (4, 1, 0));
+    }
+
+    #[test]
+    fn test_refresh_interval_defers_changes() {
+        let cache = PaneCache::new().refresh_interval("pane", Duration::from_secs(60));
+        let area = Rect::new(0, 0, 10, 1);
+        let mut buf = Buffer::new(area);
+
+        assert!(render(&cache, "one", area, &mut buf));
+        assert!(!render(&cache, "two", area, &mut buf));
+        assert_eq!(buf.get(0, 0).unwrap().symbol, "o");
+        assert_eq!(cache.stats().deferred, 1);
+    }
+}
@@ -0,0 +1,290 @@
+//! Transient toast notifications rendered by the app runner
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+
+use crate::buffer::{Buffer, Cell};
+use crate::geometry::Rect;
+use crate::style::{truncate, Color, Style};
+use crate::widget::builtin::{Block, BorderType};
+use crate::widget::Widget;
+
+/// How long a toast stays on screen unless overridden
+pub const DEFAULT_TOAST_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Severity of a toast, which picks its color and icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToastLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl ToastLevel {
+    /// Border and icon color
+    pub fn color(&self) -> Color {
+        match self {
+            ToastLevel::Info => Color::Cyan,
+            ToastLevel::Success => Color::Green,
+            ToastLevel::Warning => Color::Yellow,
+            ToastLevel::Error => Color::Red,
+        }
+    }
+
+    /// Icon shown before the message
+    pub fn icon(&self) -> &'static str {
+        match self {
+            ToastLevel::Info => "ℹ",
+            ToastLevel::Success => "✓",
+            ToastLevel::Warning => "⚠",
+            ToastLevel::Error => "✗",
+        }
+    }
+
+    /// Word read out before the message in the linear render mode
+    pub fn label(&self) -> &'static str {
+        match self {
+            ToastLevel::Info => "Info",
+            ToastLevel::Success => "Success",
+            ToastLevel::Warning => "Warning",
+            ToastLevel::Error => "Error",
+        }
+    }
+}
+
+/// A short message shown in the corner of the screen for a while
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toast {
+    pub message: String,
+    pub level: ToastLevel,
+    pub timeout: Duration,
+}
+
+impl Toast {
+    /// Create a toast with the default timeout
+    pub fn new(level: ToastLevel, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            level,
+            timeout: DEFAULT_TOAST_TIMEOUT,
+        }
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self::new(ToastLevel::Info, message)
+    }
+
+    pub fn success(message: impl Into<String>) -> Self {
+        Self::new(ToastLevel::Success, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(ToastLevel::Warning, message)
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(ToastLevel::Error, message)
+    }
+
+    /// Set how long the toast stays visible
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Cloneable handle for enqueueing toasts from any task
+///
+/// Obtained from `AppRunner::toast_sender`. Sending never blocks; toasts
+/// sent after the runner exits are dropped.
+#[derive(Debug, Clone)]
+pub struct ToastSender {
+    tx: mpsc::UnboundedSender<Toast>,
+}
+
+impl ToastSender {
+    /// Enqueue a toast, returning `false` if the runner has gone away
+    pub fn send(&self, toast: Toast) -> bool {
+        self.tx.send(toast).is_ok()
+    }
+
+    pub fn info(&self, message: impl Into<String>) -> bool {
+        self.send(Toast::info(message))
+    }
+
+    pub fn success(&self, message: impl Into<String>) -> bool {
+        self.send(Toast::success(message))
+    }
+
+    pub fn warning(&self, message: impl Into<String>) -> bool {
+        self.send(Toast::warning(message))
+    }
+
+    pub fn error(&self, message: impl Into<String>) -> bool {
+        self.send(Toast::error(message))
+    }
+}
+
+/// Create a connected toast sender and receiver
+pub fn toast_channel() -> (ToastSender, mpsc::UnboundedReceiver<Toast>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (ToastSender { tx }, rx)
+}
+
+/// Active toasts, stacked in the top-right corner, newest on top
+#[derive(Debug, Clone)]
+pub struct Toasts {
+    active: VecDeque<(Toast, Instant)>,
+    max_visible: usize,
+    max_width: u16,
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        Self {
+            active: VecDeque::new(),
+            max_visible: 4,
+            max_width: 48,
+        }
+    }
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many toasts are shown at once (older ones are dropped)
+    pub fn max_visible(mut self, max_visible: usize) -> Self {
+        self.max_visible = max_visible.max(1);
+        self
+    }
+
+    /// Set the maximum toast width in columns, borders included
+    pub fn max_width(mut self, max_width: u16) -> Self {
+        self.max_width = max_width.max(8);
+        self
+    }
+
+    /// Show a toast starting now
+    pub fn push(&mut self, toast: Toast) {
+        self.push_at(toast, Instant::now());
+    }
+
+    /// Show a toast starting at `now`
+    pub fn push_at(&mut self, toast: Toast, now: Instant) {
+        self.active.push_front((toast, now));
+        self.active.truncate(self.max_visible);
+    }
+
+    /// Drop expired toasts, returning whether any were removed
+    pub fn prune(&mut self) -> bool {
+        self.prune_at(Instant::now())
+    }
+
+    /// Drop toasts expired at `now`, returning whether any were removed
+    pub fn prune_at(&mut self, now: Instant) -> bool {
+        let before = self.active.len();
+        self.active
+            .retain(|(toast, shown)| now.duration_since(*shown) < toast.timeout);
+        self.active.len() != before
+    }
+
+    /// Toasts currently shown, newest first
+    pub fn iter(&self) -> impl Iterator<Item = &Toast> {
+        self.active.iter().map(|(toast, _)| toast)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    /// Draw the toasts over whatever is already in the buffer
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let width = self.max_width.min(area.width);
+        if width < 8 {
+            return;
+        }
+
+        let mut y = area.y;
+        for toast in self.iter() {
+            if y + 3 > area.bottom() {
+                break;
+            }
+            let text = truncate(
+                &format!("{} {}", toast.level.icon(), toast.message),
+                width as usize - 4,
+            );
+            let toast_width =
+                (unicode_width::UnicodeWidthStr::width(text.as_str()) as u16 + 4).min(width);
+            let rect = Rect::new(area.right() - toast_width, y, toast_width, 3);
+
+            let color = toast.level.color();
+            buf.fill(rect, Cell::new(" ").bg(Color::Rgb(20, 20, 30)));
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .border_style(Style::new().fg(color))
+                .render(rect, buf);
+            buf.set_string(
+                rect.x + 2,
+                y + 1,
+                &text,
+                Style::new().fg(color).bg(Color::Rgb(20, 20, 30)),
+            );
+            y += 3;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::buffer_lines;
+
+    #[test]
+    fn test_toasts_expire_and_cap() {
+        let start = Instant::now();
+        let mut toasts = Toasts::new().max_visible(2);
+        toasts.push_at(Toast::info("one").timeout(Duration::from_secs(1)), start);
+        toasts.push_at(Toast::info("two"), start);
+        toasts.push_at(Toast::error("three"), start);
+
+        let messages: Vec<_> = toasts.iter().map(|t| t.message.as_str()).collect();
+        assert_eq!(messages, vec!["three", "two"]);
+
+        assert!(!toasts.prune_at(start + Duration::from_secs(1)));
+        assert!(toasts.prune_at(start + DEFAULT_TOAST_TIMEOUT));
+        assert!(toasts.is_empty());
+    }
+
+    #[test]
+    fn test_render_stacks_in_top_right() {
+        let area = Rect::new(0, 0, 20, 7);
+        let mut buf = Buffer::new(area);
+        let mut toasts = Toasts::new();
+        toasts.push(Toast::success("saved"));
+        toasts.push(Toast::warning("slow"));
+        toasts.render(area, &mut buf);
+
+        let lines = buffer_lines(&buf);
+        assert_eq!(lines[1], "          │ ⚠ slow │");
+        assert_eq!(lines[4], "         │ ✓ saved │");
+        assert_eq!(lines[6], "");
+        assert_eq!(buf.get(19, 0).unwrap().fg, Color::Yellow);
+    }
+
+    #[tokio::test]
+    async fn test_sender_delivers_from_other_tasks() {
+        let (sender, mut rx) = toast_channel();
+        let task_sender = sender.clone();
+        tokio::spawn(async move { task_sender.error("backend down") })
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await, Some(Toast::error("backend down")));
+        drop(rx);
+        assert!(!sender.info("late"));
+    }
+}
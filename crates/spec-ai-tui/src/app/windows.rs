@@ -0,0 +1,418 @@
+//! Auxiliary terminal windows managed by the app runner
+//!
+//! An auxiliary window is a second terminal (a tmux pane, a Terminal.app
+//! window or a new terminal emulator) that follows a log file the runner
+//! appends to, so an app can keep a detached log viewer or a secondary view
+//! next to its main screen. Removing the file ends the viewer, which closes
+//! the window; the runner does that when a window is closed or the app exits.
+//!
+//! Apps drive windows through a cloneable [`WindowController`], the same way
+//! they show toasts through a `ToastSender`.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+
+use super::mirror::FrameEncoder;
+use crate::buffer::Buffer;
+
+/// Viewer run in the window: follows the file until it is removed
+const VIEWER_SCRIPT: &str =
+    r#"tail -n +1 -f "$0" & t=$!; while [ -e "$0" ]; do sleep 0.2; done; kill $t"#;
+
+/// Identifies an auxiliary window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowId(u64);
+
+/// How auxiliary windows are opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowLauncher {
+    /// A pane split off the current tmux window, leaving focus where it is
+    Tmux,
+    /// A new Terminal.app window (macOS)
+    TerminalApp,
+    /// A terminal emulator that runs the command following its arguments,
+    /// e.g. `["xterm", "-e"]`
+    Emulator(Vec<String>),
+    /// No window is opened; output only goes to the file
+    Headless,
+}
+
+impl WindowLauncher {
+    /// Pick a launcher for the current environment
+    ///
+    /// Prefers tmux when running inside it, then Terminal.app on macOS, then
+    /// `$TERMINAL` or `x-terminal-emulator` elsewhere.
+    pub fn detect() -> Self {
+        if std::env::var_os("TMUX").is_some() {
+            return WindowLauncher::Tmux;
+        }
+        if cfg!(target_os = "macos") {
+            return WindowLauncher::TerminalApp;
+        }
+        let terminal = std::env::var("TERMINAL")
+            .ok()
+            .filter(|terminal| !terminal.trim().is_empty())
+            .unwrap_or_else(|| "x-terminal-emulator".to_string());
+        WindowLauncher::Emulator(vec![terminal, "-e".to_string()])
+    }
+
+    /// Command that opens a window following `path`, if this launcher opens one
+    pub fn command(&self, path: &Path) -> Option<Command> {
+        let path = path.to_string_lossy();
+        match self {
+            WindowLauncher::Tmux => {
+                let mut command = Command::new("tmux");
+                command.args(["split-window", "-d", "-h", "sh", "-c", VIEWER_SCRIPT, &path]);
+                Some(command)
+            }
+            WindowLauncher::TerminalApp => {
+                let shell = format!(
+                    "sh -c {} {}",
+                    shell_quote(VIEWER_SCRIPT),
+                    shell_quote(&path)
+                );
+                let script = format!(
+                    r#"tell application "Terminal" to do script "{}""#,
+                    shell.replace('\\', "\\\\").replace('"', "\\\"")
+                );
+                let mut command = Command::new("osascript");
+                command.args(["-e", &script]);
+                Some(command)
+            }
+            WindowLauncher::Emulator(args) => {
+                let (program, args) = args.split_first()?;
+                let mut command = Command::new(program);
+                command.args(args).args(["sh", "-c", VIEWER_SCRIPT, &path]);
+                Some(command)
+            }
+            WindowLauncher::Headless => None,
+        }
+    }
+}
+
+impl Default for WindowLauncher {
+    fn default() -> Self {
+        Self::detect()
+    }
+}
+
+/// Quote `text` as a single POSIX shell word
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Request from a [`WindowController`] to the runner
+#[derive(Debug, Clone)]
+pub enum WindowCommand {
+    Open { id: WindowId, title: String },
+    Write { id: WindowId, text: String },
+    Draw { id: WindowId, buffer: Buffer },
+    Close { id: WindowId },
+}
+
+/// Cloneable handle for opening, writing to and closing auxiliary windows
+///
+/// Obtained from `AppRunner::window_controller`. Requests are carried out
+/// by the runner between events; a window that fails to open is reported
+/// as an error toast. Requests sent after the runner exits are dropped.
+#[derive(Debug, Clone)]
+pub struct WindowController {
+    tx: mpsc::UnboundedSender<WindowCommand>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl WindowController {
+    /// Open a window headed by `title`
+    pub fn open(&self, title: impl Into<String>) -> WindowId {
+        let id = WindowId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let _ = self.tx.send(WindowCommand::Open {
+            id,
+            title: title.into(),
+        });
+        id
+    }
+
+    /// Append text to a window, returning `false` if the runner has gone away
+    pub fn write(&self, id: WindowId, text: impl Into<String>) -> bool {
+        self.tx
+            .send(WindowCommand::Write {
+                id,
+                text: text.into(),
+            })
+            .is_ok()
+    }
+
+    /// Append a line to a window
+    pub fn writeln(&self, id: WindowId, line: impl AsRef<str>) -> bool {
+        self.write(id, format!("{}\n", line.as_ref()))
+    }
+
+    /// Show a rendered buffer in a window, repainting only what changed
+    /// since the last one
+    pub fn draw(&self, id: WindowId, buffer: Buffer) -> bool {
+        self.tx.send(WindowCommand::Draw { id, buffer }).is_ok()
+    }
+
+    /// Close a window
+    pub fn close(&self, id: WindowId) -> bool {
+        self.tx.send(WindowCommand::Close { id }).is_ok()
+    }
+}
+
+/// Create a connected window controller and receiver
+pub fn window_channel() -> (WindowController, mpsc::UnboundedReceiver<WindowCommand>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let controller = WindowController {
+        tx,
+        next_id: Arc::new(AtomicU64::new(1)),
+    };
+    (controller, rx)
+}
+
+struct AuxWindow {
+    path: PathBuf,
+    file: File,
+    encoder: FrameEncoder,
+}
+
+/// The auxiliary windows that are open
+///
+/// Dropping it closes them all.
+pub struct Windows {
+    launcher: WindowLauncher,
+    dir: PathBuf,
+    open: HashMap<WindowId, AuxWindow>,
+}
+
+impl Windows {
+    /// Open windows with `launcher`, keeping their files in the temp directory
+    pub fn new(launcher: WindowLauncher) -> Self {
+        Self {
+            launcher,
+            dir: std::env::temp_dir(),
+            open: HashMap::new(),
+        }
+    }
+
+    /// Keep the files the windows follow in `dir`
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    pub fn launcher(&self) -> &WindowLauncher {
+        &self.launcher
+    }
+
+    /// Number of open windows
+    pub fn len(&self) -> usize {
+        self.open.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.open.is_empty()
+    }
+
+    /// File the window follows, while it is open
+    pub fn path(&self, id: WindowId) -> Option<&Path> {
+        self.open.get(&id).map(|window| window.path.as_path())
+    }
+
+    /// Carry out a controller request
+    ///
+    /// Writes to windows that are not open are ignored.
+    pub fn apply(&mut self, command: WindowCommand) -> io::Result<()> {
+        match command {
+            WindowCommand::Open { id, title } => self.open(id, &title),
+            WindowCommand::Write { id, text } => match self.open.get_mut(&id) {
+                Some(window) => window.file.write_all(text.as_bytes()),
+                None => Ok(()),
+            },
+            WindowCommand::Draw { id, buffer } => match self.open.get_mut(&id) {
+                Some(window) => match window.encoder.encode(&buffer)? {
+                    Some(frame) => window.file.write_all(frame.ansi.as_bytes()),
+                    None => Ok(()),
+                },
+                None => Ok(()),
+            },
+            WindowCommand::Close { id } => {
+                self.close(id);
+                Ok(())
+            }
+        }
+    }
+
+    fn open(&mut self, id: WindowId, title: &str) -> io::Result<()> {
+        let path = self
+            .dir
+            .join(format!("spec-ai-tui-{}-{}.log", std::process::id(), id.0));
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(&path)?;
+        writeln!(file, "── {} ──", title)?;
+
+        if let Some(mut command) = self.launcher.command(&path) {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            if let Err(e) = launch(&self.launcher, command) {
+                let _ = fs::remove_file(&path);
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("could not open window '{}': {}", title, e),
+                ));
+            }
+        }
+
+        self.open.insert(
+            id,
+            AuxWindow {
+                path,
+                file,
+                encoder: FrameEncoder::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Close a window by removing the file its viewer follows
+    pub fn close(&mut self, id: WindowId) {
+        if let Some(window) = self.open.remove(&id) {
+            let _ = fs::remove_file(&window.path);
+        }
+    }
+
+    /// Close every window
+    pub fn close_all(&mut self) {
+        let ids: Vec<WindowId> = self.open.keys().copied().collect();
+        for id in ids {
+            self.close(id);
+        }
+    }
+}
+
+/// Run a launcher command
+///
+/// tmux and osascript return once the window is open, so their exit status
+/// is checked; a terminal emulator runs as long as its window, so it is left
+/// running and reaped in the background.
+fn launch(launcher: &WindowLauncher, mut command: Command) -> io::Result<()> {
+    if let WindowLauncher::Emulator(_) = launcher {
+        let mut child = command.spawn()?;
+        std::thread::spawn(move || child.wait());
+        return Ok(());
+    }
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("launcher exited with {}", status)));
+    }
+    Ok(())
+}
+
+impl Drop for Windows {
+    fn drop(&mut self) {
+        self.close_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+    use crate::style::Style;
+
+    #[test]
+    fn test_headless_windows_follow_writes_until_closed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut windows = Windows::new(WindowLauncher::Headless).dir(dir.path());
+        let (controller, mut rx) = window_channel();
+
+        let logs = controller.open("Logs");
+        assert!(controller.writeln(logs, "started"));
+        let view = controller.open("View");
+        let mut buf = Buffer::new(Rect::new(0, 0, 5, 1));
+        buf.set_string(0, 0, "hi", Style::default());
+        assert!(controller.draw(view, buf));
+        while let Ok(command) = rx.try_recv() {
+            windows.apply(command).unwrap();
+        }
+        assert_ne!(logs, view);
+        assert_eq!(windows.len(), 2);
+
+        let log_path = windows.path(logs).unwrap().to_path_buf();
+        assert_eq!(
+            fs::read_to_string(&log_path).unwrap(),
+            "── Logs ──\nstarted\n"
+        );
+        let drawn = fs::read_to_string(windows.path(view).unwrap()).unwrap();
+        assert!(drawn.starts_with("── View ──\n\x1b["));
+        assert!(drawn.contains('h'));
+
+        windows.apply(WindowCommand::Close { id: logs }).unwrap();
+        assert!(!log_path.exists());
+        assert!(windows.path(logs).is_none());
+        // Writes to closed windows are dropped
+        windows
+            .apply(WindowCommand::Write {
+                id: logs,
+                text: "late".to_string(),
+            })
+            .unwrap();
+        assert!(!log_path.exists());
+
+        let view_path = windows.path(view).unwrap().to_path_buf();
+        drop(windows);
+        assert!(!view_path.exists());
+    }
+
+    #[test]
+    fn test_failed_launch_leaves_no_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let launcher = WindowLauncher::Emulator(vec!["spec-ai-tui-no-such-terminal".to_string()]);
+        let mut windows = Windows::new(launcher).dir(dir.path());
+        let (controller, mut rx) = window_channel();
+
+        controller.open("Logs");
+        let err = windows.apply(rx.try_recv().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("could not open window 'Logs'"));
+        assert!(windows.is_empty());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_launcher_commands() {
+        let path = Path::new("/tmp/it's.log");
+
+        let tmux = WindowLauncher::Tmux.command(path).unwrap();
+        assert_eq!(tmux.get_program(), "tmux");
+        let args: Vec<_> = tmux.get_args().collect();
+        assert_eq!(&args[..4], ["split-window", "-d", "-h", "sh"]);
+        assert_eq!(args.last().unwrap(), &"/tmp/it's.log");
+
+        let xterm = WindowLauncher::Emulator(vec!["xterm".to_string(), "-e".to_string()])
+            .command(path)
+            .unwrap();
+        assert_eq!(xterm.get_program(), "xterm");
+        let args: Vec<_> = xterm.get_args().collect();
+        assert_eq!(&args[..3], ["-e", "sh", "-c"]);
+
+        let osascript = WindowLauncher::TerminalApp.command(path).unwrap();
+        let script = osascript.get_args().nth(1).unwrap().to_str().unwrap();
+        assert!(script.starts_with(r#"tell application "Terminal" to do script "sh -c 'tail"#));
+        assert!(script.ends_with(r#"'/tmp/it'\\''s.log'""#));
+        assert!(script.contains(r#"\"$0\""#));
+
+        assert!(WindowLauncher::Headless.command(path).is_none());
+        assert!(WindowLauncher::Emulator(Vec::new()).command(path).is_none());
+    }
+}
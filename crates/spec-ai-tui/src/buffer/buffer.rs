@@ -3,6 +3,16 @@
 use super::Cell;
 use crate::geometry::Rect;
 use crate::style::{Line, Span, Style};
+use std::sync::Arc;
+
+/// An image drawn over a region of the buffer via a graphics protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePlacement {
+    /// Cells covered by the image
+    pub area: Rect,
+    /// Escape sequence that draws the image at the area's top-left corner
+    pub sequence: Arc<str>,
+}
 
 /// 2D buffer of cells for rendering
 #[derive(Debug, Clone)]
@@ -11,6 +21,8 @@ pub struct Buffer {
     area: Rect,
     /// Flat array of cells (row-major order)
     cells: Vec<Cell>,
+    /// Images emitted after the cells are drawn
+    images: Vec<ImagePlacement>,
 }
 
 impl Buffer {
@@ -20,6 +32,7 @@ impl Buffer {
         Self {
             area,
             cells: vec![Cell::empty(); size],
+            images: Vec::new(),
         }
     }
 
@@ -29,6 +42,7 @@ impl Buffer {
         Self {
             area,
             cells: vec![cell; size],
+            images: Vec::new(),
         }
     }
 
@@ -137,11 +151,30 @@ impl Buffer {
         }
     }
 
+    /// Place an image over `area`, replacing any image already placed there
+    ///
+    /// The covered cells are left untouched; widgets should blank them.
+    pub fn place_image(&mut self, area: Rect, sequence: Arc<str>) {
+        let area = self.area.intersect(&area);
+        if area.is_empty() {
+            return;
+        }
+        self.images
+            .retain(|image| image.area.intersect(&area).is_empty());
+        self.images.push(ImagePlacement { area, sequence });
+    }
+
+    /// Images placed in this buffer
+    pub fn images(&self) -> &[ImagePlacement] {
+        &self.images
+    }
+
     /// Clear the entire buffer (reset all cells to empty)
     pub fn clear(&mut self) {
         for cell in &mut self.cells {
             cell.reset();
         }
+        self.images.clear();
     }
 
     /// Clear an area within the buffer
@@ -164,10 +197,33 @@ impl Buffer {
             .filter(move |(x, y, cell)| other.get(*x, *y).map(|c| c != *cell).unwrap_or(true))
     }
 
+    /// Copy of the cells and images within `area`
+    pub fn region(&self, area: Rect) -> Buffer {
+        let area = self.area.intersect(&area);
+        let mut region = Buffer::new(area);
+        for (x, y) in area.positions() {
+            if let Some(cell) = self.get(x, y) {
+                region.set(x, y, cell.clone());
+            }
+        }
+        region.images = self
+            .images
+            .iter()
+            .filter(|image| area.intersect(&image.area) == image.area)
+            .cloned()
+            .collect();
+        region
+    }
+
     /// Merge another buffer into this one at its position
     pub fn merge(&mut self, other: &Buffer) {
         for (x, y, cell) in other.iter() {
-            self.set(x, y, cell.clone());
+            if let Some(i) = self.index(x, y) {
+                self.cells[i].clone_from(cell);
+            }
+        }
+        for image in &other.images {
+            self.place_image(image.area, image.sequence.clone());
         }
     }
 
@@ -193,6 +249,8 @@ impl Buffer {
 
         self.area = area;
         self.cells = new_cells;
+        self.images
+            .retain(|image| area.intersect(&image.area) == image.area);
     }
 }
 
@@ -275,6 +333,25 @@ mod tests {
         assert_eq!(diffs.len(), 2);
     }
 
+    #[test]
+    fn test_buffer_image_placements() {
+        let area = Rect::new(0, 0, 10, 5);
+        let mut buf = Buffer::new(area);
+
+        buf.place_image(Rect::new(0, 0, 4, 2), Arc::from("a"));
+        buf.place_image(Rect::new(2, 1, 4, 2), Arc::from("b"));
+        buf.place_image(Rect::new(8, 3, 4, 4), Arc::from("c"));
+
+        // Overlapping placement replaced, out-of-bounds one clipped
+        let images = buf.images();
+        assert_eq!(images.len(), 2);
+        assert_eq!(&*images[0].sequence, "b");
+        assert_eq!(images[1].area, Rect::new(8, 3, 2, 2));
+
+        buf.clear();
+        assert!(buf.images().is_empty());
+    }
+
     #[test]
     fn test_buffer_clear() {
         let area = Rect::new(0, 0, 5, 5);
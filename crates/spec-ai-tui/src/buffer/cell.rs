@@ -3,7 +3,7 @@
 use crate::style::{Color, Modifier, Style};
 
 /// A single terminal cell with content and style
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Cell {
     /// The character(s) displayed (supports Unicode grapheme clusters)
     pub symbol: String,
@@ -15,6 +15,25 @@ pub struct Cell {
     pub modifier: Modifier,
 }
 
+impl Clone for Cell {
+    fn clone(&self) -> Self {
+        Self {
+            symbol: self.symbol.clone(),
+            fg: self.fg,
+            bg: self.bg,
+            modifier: self.modifier,
+        }
+    }
+
+    /// Reuses the symbol's allocation, which makes copying buffers cheap
+    fn clone_from(&mut self, source: &Self) {
+        self.symbol.clone_from(&source.symbol);
+        self.fg = source.fg;
+        self.bg = source.bg;
+        self.modifier = source.modifier;
+    }
+}
+
 impl Cell {
     /// Create a new empty cell (space character, default style)
     pub fn empty() -> Self {
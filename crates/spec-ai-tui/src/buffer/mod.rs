@@ -3,5 +3,5 @@
 mod buffer;
 mod cell;
 
-pub use buffer::Buffer;
+pub use buffer::{Buffer, ImagePlacement};
 pub use cell::Cell;
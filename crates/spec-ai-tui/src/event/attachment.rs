@@ -0,0 +1,406 @@
+//! Files and images pasted into the terminal
+//!
+//! Terminals deliver a drag-and-drop of files as a bracketed paste of their
+//! paths: quoted or backslash-escaped on macOS and Linux, quoted on Windows,
+//! sometimes as `file://` URIs. [`Attachment::from_paste`] recognizes such a
+//! paste so the application can offer to attach the files instead of
+//! inserting the paths as text. Terminals cannot paste images at all, so
+//! [`Attachment::from_clipboard`] reads one from the system clipboard when
+//! the user asks for it.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Most files accepted from one paste
+const MAX_DROPPED_FILES: usize = 32;
+
+/// Longer pastes are always treated as text
+const MAX_DROP_BYTES: usize = 16 * 1024;
+
+/// Signature at the start of every PNG file
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// A file or image the user wants to add to the conversation
+#[derive(Clone, PartialEq, Eq)]
+pub enum Attachment {
+    /// A file on disk, by absolute path
+    File(PathBuf),
+    /// Image data that has no file yet
+    Image {
+        /// MIME type such as `image/png`
+        mime_type: String,
+        /// Encoded image bytes
+        data: Vec<u8>,
+    },
+}
+
+impl Attachment {
+    /// Attachments in a paste, if it consists only of paths to existing
+    /// files or an image `data:` URI
+    pub fn from_paste(text: &str) -> Option<Vec<Attachment>> {
+        if let Some(image) = image_data_uri(text.trim()) {
+            return Some(vec![image]);
+        }
+        dropped_files(text, cfg!(windows), Path::is_file)
+            .map(|paths| paths.into_iter().map(Attachment::File).collect())
+    }
+
+    /// Image currently on the system clipboard, as PNG
+    ///
+    /// Uses `osascript` on macOS, PowerShell on Windows and `wl-paste` or
+    /// `xclip` elsewhere. Returns `None` when the clipboard holds no image or
+    /// no clipboard tool is installed.
+    pub fn from_clipboard() -> Option<Attachment> {
+        let data = clipboard_png()?;
+        data.starts_with(PNG_SIGNATURE).then(|| Attachment::Image {
+            mime_type: "image/png".to_string(),
+            data,
+        })
+    }
+
+    /// Short description for display, such as `report.pdf` or
+    /// `image/png (12.3 KB)`
+    pub fn label(&self) -> String {
+        match self {
+            Attachment::File(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+            Attachment::Image { mime_type, data } => {
+                format!("{} ({:.1} KB)", mime_type, data.len() as f64 / 1024.0)
+            }
+        }
+    }
+
+    /// Path of the attachment on disk, writing image data into `dir` first
+    pub fn save_to(&self, dir: &Path) -> io::Result<PathBuf> {
+        match self {
+            Attachment::File(path) => Ok(path.clone()),
+            Attachment::Image { mime_type, data } => {
+                std::fs::create_dir_all(dir)?;
+                let extension = mime_type.strip_prefix("image/").unwrap_or("bin");
+                let stamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis())
+                    .unwrap_or_default();
+                let mut path = dir.join(format!("pasted-{}.{}", stamp, extension));
+                let mut n = 1;
+                while path.exists() {
+                    path = dir.join(format!("pasted-{}-{}.{}", stamp, n, extension));
+                    n += 1;
+                }
+                std::fs::write(&path, data)?;
+                Ok(path)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Attachment {
+    // Image bytes are summarized rather than dumped
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attachment::File(path) => f.debug_tuple("File").field(path).finish(),
+            Attachment::Image { mime_type, data } => f
+                .debug_struct("Image")
+                .field("mime_type", mime_type)
+                .field("bytes", &data.len())
+                .finish(),
+        }
+    }
+}
+
+/// Paths in a paste, if every word of it is an absolute path accepted by
+/// `is_file`
+fn dropped_files(
+    text: &str,
+    windows: bool,
+    is_file: impl Fn(&Path) -> bool,
+) -> Option<Vec<PathBuf>> {
+    let text = text.trim();
+    if text.is_empty() || text.len() > MAX_DROP_BYTES {
+        return None;
+    }
+    // Windows paths use backslashes as separators, not escapes
+    let words = split_words(text, !windows)?;
+    if words.len() > MAX_DROPPED_FILES {
+        return None;
+    }
+    words
+        .into_iter()
+        .map(|word| {
+            let path = match word.strip_prefix("file://") {
+                Some(rest) => file_uri_path(rest, windows)?,
+                None => PathBuf::from(word),
+            };
+            (is_absolute(&path, windows) && is_file(&path)).then_some(path)
+        })
+        .collect()
+}
+
+/// Split on whitespace, honouring quotes and, if `escapes`, backslashes
+fn split_words(text: &str, escapes: bool) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c == '\\' && escapes => {
+                word.push(chars.next()?);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            None => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    if in_word {
+        words.push(word);
+    }
+    Some(words)
+}
+
+/// Local path of a `file://` URI, given the part after the scheme
+fn file_uri_path(rest: &str, windows: bool) -> Option<PathBuf> {
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !path.starts_with('/') {
+        return None;
+    }
+    let path = percent_decode(path)?;
+    // file:///C:/Users/... names C:/Users/...
+    let path = match path.strip_prefix('/') {
+        Some(rest) if windows && has_drive_letter(rest) => rest.to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Absolute path check that does not depend on the host platform
+fn is_absolute(path: &Path, windows: bool) -> bool {
+    let path = path.to_string_lossy();
+    if windows {
+        (has_drive_letter(&path) && path[2..].starts_with(['\\', '/'])) || path.starts_with(r"\\")
+    } else {
+        path.starts_with('/')
+    }
+}
+
+fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// Image in a `data:image/...;base64,` URI
+fn image_data_uri(text: &str) -> Option<Attachment> {
+    let (header, payload) = text.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    if !mime_type.starts_with("image/") {
+        return None;
+    }
+    let data = STANDARD.decode(payload.trim()).ok()?;
+    Some(Attachment::Image {
+        mime_type: mime_type.to_string(),
+        data,
+    })
+}
+
+fn clipboard_png() -> Option<Vec<u8>> {
+    if cfg!(target_os = "macos") {
+        let output = run("osascript", &["-e", "the clipboard as «class PNGf»"])?;
+        parse_applescript_data(&String::from_utf8_lossy(&output))
+    } else if cfg!(windows) {
+        let script = "Add-Type -AssemblyName System.Windows.Forms; \
+            $image = [System.Windows.Forms.Clipboard]::GetImage(); \
+            if ($image) { $stream = New-Object System.IO.MemoryStream; \
+            $image.Save($stream, [System.Drawing.Imaging.ImageFormat]::Png); \
+            [Convert]::ToBase64String($stream.ToArray()) }";
+        let output = run("powershell", &["-NoProfile", "-STA", "-Command", script])?;
+        STANDARD
+            .decode(String::from_utf8_lossy(&output).trim())
+            .ok()
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        run("wl-paste", &["--no-newline", "--type", "image/png"])
+    } else {
+        run(
+            "xclip",
+            &["-selection", "clipboard", "-target", "image/png", "-out"],
+        )
+    }
+}
+
+/// Standard output of a successful command
+fn run(program: &str, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    (output.status.success() && !output.stdout.is_empty()).then_some(output.stdout)
+}
+
+/// Bytes of AppleScript data such as `«data PNGf89504E47...»`
+fn parse_applescript_data(text: &str) -> Option<Vec<u8>> {
+    let hex = text.trim().strip_prefix("«data ")?.strip_suffix('»')?;
+    let hex = hex.get(4..)?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(text: &str, windows: bool) -> Option<Vec<PathBuf>> {
+        dropped_files(text, windows, |_| true)
+    }
+
+    #[test]
+    fn test_dropped_unix_paths() {
+        assert_eq!(
+            files("/tmp/a.txt", false),
+            Some(vec![PathBuf::from("/tmp/a.txt")])
+        );
+        assert_eq!(
+            files(r"/Users/me/My\ Notes.md '/tmp/it'\''s.png'", false),
+            Some(vec![
+                PathBuf::from("/Users/me/My Notes.md"),
+                PathBuf::from("/tmp/it's.png"),
+            ])
+        );
+        assert_eq!(
+            files("\"/home/me/a b.rs\"\n/home/me/c.rs\n", false),
+            Some(vec![
+                PathBuf::from("/home/me/a b.rs"),
+                PathBuf::from("/home/me/c.rs"),
+            ])
+        );
+        assert_eq!(
+            files(
+                "file:///home/me/My%20File.png file://localhost/tmp/x",
+                false
+            ),
+            Some(vec![
+                PathBuf::from("/home/me/My File.png"),
+                PathBuf::from("/tmp/x"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_dropped_windows_paths() {
+        assert_eq!(
+            files(r#""C:\Users\me\My File.txt" D:\data\b.csv"#, true),
+            Some(vec![
+                PathBuf::from(r"C:\Users\me\My File.txt"),
+                PathBuf::from(r"D:\data\b.csv"),
+            ])
+        );
+        assert_eq!(
+            files("file:///C:/Users/me/a%20b.txt", true),
+            Some(vec![PathBuf::from("C:/Users/me/a b.txt")])
+        );
+        assert_eq!(files(r"C:relative.txt", true), None);
+    }
+
+    #[test]
+    fn test_text_is_not_a_drop() {
+        assert_eq!(files("", false), None);
+        assert_eq!(files("see /tmp/a.txt", false), None);
+        assert_eq!(files("src/main.rs", false), None);
+        assert_eq!(files("'/tmp/unterminated", false), None);
+        assert_eq!(files("https://example.com/a.png", false), None);
+        assert_eq!(dropped_files("/tmp/missing", false, |_| false), None);
+        assert_eq!(files(&"/tmp/a ".repeat(MAX_DROPPED_FILES + 1), false), None);
+    }
+
+    #[test]
+    fn test_paste_of_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "hi").unwrap();
+
+        let pasted = Attachment::from_paste(&format!("'{}'", path.display())).unwrap();
+        assert_eq!(pasted, vec![Attachment::File(path)]);
+        assert_eq!(pasted[0].label(), "notes.txt");
+        assert!(Attachment::from_paste(&dir.path().display().to_string()).is_none());
+    }
+
+    #[test]
+    fn test_image_data_uri() {
+        let uri = format!("data:image/png;base64,{}", STANDARD.encode(PNG_SIGNATURE));
+        let image = Attachment::from_paste(&uri).unwrap().remove(0);
+        assert_eq!(
+            image,
+            Attachment::Image {
+                mime_type: "image/png".to_string(),
+                data: PNG_SIGNATURE.to_vec(),
+            }
+        );
+        assert_eq!(
+            format!("{:?}", image),
+            r#"Image { mime_type: "image/png", bytes: 8 }"#
+        );
+        assert!(image_data_uri("data:text/plain;base64,aGk=").is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let saved = image.save_to(dir.path()).unwrap();
+        assert_eq!(saved.extension().unwrap(), "png");
+        assert_eq!(std::fs::read(&saved).unwrap(), PNG_SIGNATURE);
+        assert_ne!(image.save_to(dir.path()).unwrap(), saved);
+    }
+
+    #[test]
+    fn test_applescript_data() {
+        assert_eq!(
+            parse_applescript_data("«data PNGf89504E47»\n"),
+            Some(vec![0x89, 0x50, 0x4E, 0x47])
+        );
+        assert_eq!(parse_applescript_data("missing value"), None);
+    }
+}
@@ -14,6 +14,11 @@ pub struct EventLoop {
     custom_rx: mpsc::UnboundedReceiver<Event>,
     /// Sender for custom events (cloneable)
     custom_tx: mpsc::UnboundedSender<Event>,
+    /// Terminal event stream, created on first use
+    ///
+    /// Kept across calls so events already read from the terminal are not
+    /// lost when a tick or custom event wins the race.
+    event_stream: Option<EventStream>,
 }
 
 impl EventLoop {
@@ -24,6 +29,7 @@ impl EventLoop {
             tick_rate,
             custom_rx,
             custom_tx,
+            event_stream: None,
         }
     }
 
@@ -47,29 +53,36 @@ impl EventLoop {
     /// - Custom events sent via the sender
     /// - Tick events at the configured rate
     ///
+    /// Key releases (reported by Windows consoles) are skipped.
+    ///
     /// Returns None if the event stream ends.
     pub async fn next(&mut self) -> Option<Event> {
         let tick_delay = tokio::time::sleep(self.tick_rate);
         tokio::pin!(tick_delay);
 
-        let mut event_stream = EventStream::new();
+        let event_stream = self.event_stream.get_or_insert_with(EventStream::new);
 
-        tokio::select! {
-            // Crossterm terminal events
-            maybe_event = event_stream.next() => {
-                match maybe_event {
-                    Some(Ok(event)) => Some(event.into()),
-                    Some(Err(_)) => None,
-                    None => None,
+        loop {
+            tokio::select! {
+                // Crossterm terminal events
+                maybe_event = event_stream.next() => {
+                    match maybe_event {
+                        Some(Ok(event)) => match Event::from_terminal(event) {
+                            Some(event) => return Some(event),
+                            None => continue,
+                        },
+                        Some(Err(_)) => return None,
+                        None => return None,
+                    }
+                }
+                // Custom events from other tasks
+                Some(event) = self.custom_rx.recv() => {
+                    return Some(event);
+                }
+                // Periodic tick
+                _ = &mut tick_delay => {
+                    return Some(Event::Tick);
                 }
-            }
-            // Custom events from other tasks
-            Some(event) = self.custom_rx.recv() => {
-                Some(event)
-            }
-            // Periodic tick
-            _ = &mut tick_delay => {
-                Some(Event::Tick)
             }
         }
     }
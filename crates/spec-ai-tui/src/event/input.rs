@@ -1,7 +1,9 @@
 //! Input event types
 
+use super::Attachment;
+
 // Re-export crossterm types with cleaner names
-pub use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent};
+pub use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent};
 
 /// Unified input event type
 #[derive(Debug, Clone)]
@@ -25,9 +27,28 @@ pub enum Event {
     FocusLost,
     /// Paste event (bracketed paste mode)
     Paste(String),
+    /// Files dropped onto the terminal or an image pasted into it
+    Attach(Vec<Attachment>),
 }
 
 impl Event {
+    /// Convert a terminal event, dropping key releases
+    ///
+    /// Windows consoles report both the press and the release of every key,
+    /// while other platforms only report presses (and repeats). Handlers
+    /// only expect presses, so releases are filtered out here. Pastes that
+    /// consist of file paths become [`Event::Attach`].
+    pub fn from_terminal(event: crossterm::event::Event) -> Option<Self> {
+        match event {
+            crossterm::event::Event::Key(key) if key.kind == KeyEventKind::Release => None,
+            crossterm::event::Event::Paste(text) => Some(match Attachment::from_paste(&text) {
+                Some(attachments) => Event::Attach(attachments),
+                None => Event::Paste(text),
+            }),
+            event => Some(event.into()),
+        }
+    }
+
     /// Check if this is a quit event (Ctrl+C or Ctrl+Q)
     pub fn is_quit(&self) -> bool {
         matches!(
@@ -153,6 +174,40 @@ mod tests {
         assert!(!just_c.is_quit());
     }
 
+    #[test]
+    fn test_from_terminal_drops_key_releases() {
+        use crossterm::event::{Event as CEvent, KeyEventState};
+
+        let key = |kind| {
+            CEvent::Key(KeyEvent::new_with_kind_and_state(
+                KeyCode::Char('a'),
+                KeyModifiers::NONE,
+                kind,
+                KeyEventState::NONE,
+            ))
+        };
+
+        assert!(Event::from_terminal(key(KeyEventKind::Press)).is_some());
+        assert!(Event::from_terminal(key(KeyEventKind::Repeat)).is_some());
+        assert!(Event::from_terminal(key(KeyEventKind::Release)).is_none());
+        assert!(Event::from_terminal(CEvent::Resize(80, 24)).is_some_and(|e| e.is_resize()));
+    }
+
+    #[test]
+    fn test_from_terminal_turns_file_pastes_into_attachments() {
+        use crossterm::event::Event as CEvent;
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let dropped = CEvent::Paste(file.path().display().to_string());
+        assert!(matches!(
+            Event::from_terminal(dropped),
+            Some(Event::Attach(attachments)) if attachments == vec![Attachment::File(file.path().to_path_buf())]
+        ));
+
+        let text = CEvent::Paste("hello world".to_string());
+        assert!(matches!(Event::from_terminal(text), Some(Event::Paste(_))));
+    }
+
     #[test]
     fn test_is_enter() {
         let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
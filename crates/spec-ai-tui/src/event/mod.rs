@@ -1,10 +1,12 @@
 //! Event handling system
 
+mod attachment;
 mod event_loop;
 mod input;
 
+pub use attachment::Attachment;
 pub use event_loop::EventLoop;
-pub use input::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent};
+pub use input::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent};
 
 /// Result of handling an event
 #[derive(Debug, Clone)]
@@ -0,0 +1,258 @@
+//! Escape sequence encoders for each graphics protocol
+
+use super::image::{ImageData, ImageFormat};
+use super::GraphicsProtocol;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Maximum base64 payload per kitty escape sequence
+const KITTY_CHUNK: usize = 4096;
+
+/// Levels per channel of the sixel palette (6 x 6 x 6 colour cube)
+const SIXEL_LEVELS: u32 = 6;
+
+/// Largest cell footprint (cols, rows) that fits the image in the given bounds
+///
+/// The aspect ratio is preserved, accounting for non-square cells.
+pub fn fit_cells(
+    dimensions: (u32, u32),
+    max_cols: u16,
+    max_rows: u16,
+    cell_size: (u16, u16),
+) -> (u16, u16) {
+    let (width, height) = dimensions;
+    let (cell_w, cell_h) = (cell_size.0.max(1) as u64, cell_size.1.max(1) as u64);
+    if width == 0 || height == 0 || max_cols == 0 || max_rows == 0 {
+        return (0, 0);
+    }
+
+    // Fill the width first, then shrink if too tall
+    let cols = max_cols as u64;
+    let rows = (height as u64 * cols * cell_w).div_ceil(width as u64 * cell_h);
+    if rows <= max_rows as u64 {
+        return (cols as u16, rows.max(1) as u16);
+    }
+    let rows = max_rows as u64;
+    let cols = (width as u64 * rows * cell_h) / (height as u64 * cell_w);
+    (cols.clamp(1, max_cols as u64) as u16, rows as u16)
+}
+
+/// Encode an image to be displayed over `cols` x `rows` cells
+///
+/// Returns `None` when the protocol cannot display this image format; callers
+/// should then fall back to drawing cells.
+pub fn encode(
+    image: &ImageData,
+    protocol: GraphicsProtocol,
+    cols: u16,
+    rows: u16,
+    cell_size: (u16, u16),
+) -> Option<Arc<str>> {
+    if cols == 0 || rows == 0 {
+        return None;
+    }
+    image.cached((protocol, cols, rows, cell_size), || {
+        match (protocol, image.format()) {
+            (GraphicsProtocol::Kitty, _) => Some(kitty(image, cols, rows)),
+            (GraphicsProtocol::Iterm2, ImageFormat::Png) => Some(iterm2(image, cols, rows)),
+            // iTerm2 only accepts image files; it also understands sixel
+            (GraphicsProtocol::Iterm2 | GraphicsProtocol::Sixel, ImageFormat::Rgba) => Some(sixel(
+                image,
+                cols as u32 * cell_size.0 as u32,
+                rows as u32 * cell_size.1 as u32,
+            )),
+            _ => None,
+        }
+    })
+}
+
+/// Stable non-zero image id so re-sent images replace their previous placement
+fn image_id(image: &ImageData) -> u32 {
+    // FNV-1a
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in image.bytes() {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash.max(1)
+}
+
+fn kitty(image: &ImageData, cols: u16, rows: u16) -> String {
+    let (width, height) = image.dimensions();
+    let format = match image.format() {
+        ImageFormat::Rgba => 32,
+        ImageFormat::Png => 100,
+    };
+    let payload = STANDARD.encode(image.bytes());
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+
+    let mut out = String::with_capacity(payload.len() + chunks.len() * 16 + 64);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        // Chunks are base64, so always valid UTF-8
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            let _ = write!(
+                out,
+                "\x1b_Ga=T,f={},s={},v={},c={},r={},i={},p=1,C=1,q=2,m={};{}\x1b\\",
+                format,
+                width,
+                height,
+                cols,
+                rows,
+                image_id(image),
+                more,
+                chunk
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    out
+}
+
+fn iterm2(image: &ImageData, cols: u16, rows: u16) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        image.bytes().len(),
+        cols,
+        rows,
+        STANDARD.encode(image.bytes())
+    )
+}
+
+/// Palette index of the nearest colour in the 6x6x6 cube
+fn sixel_color(pixel: [u8; 4]) -> usize {
+    let level = |c: u8| (c as u32 * (SIXEL_LEVELS - 1) + 127) / 255;
+    (level(pixel[0]) * SIXEL_LEVELS * SIXEL_LEVELS
+        + level(pixel[1]) * SIXEL_LEVELS
+        + level(pixel[2])) as usize
+}
+
+/// Append one sixel run, using the repeat introducer for long runs
+fn push_run(out: &mut String, bits: u8, count: u32) {
+    let ch = (63 + bits) as char;
+    if count > 3 {
+        let _ = write!(out, "!{}{}", count, ch);
+    } else {
+        for _ in 0..count {
+            out.push(ch);
+        }
+    }
+}
+
+/// Encode RGBA pixels as sixel, scaled (nearest neighbour) to `width` x `height`
+fn sixel(image: &ImageData, width: u32, height: u32) -> String {
+    let (src_w, src_h) = image.dimensions();
+    let sample = |x: u32, y: u32| {
+        image
+            .pixel(x * src_w / width, y * src_h / height)
+            .unwrap_or([0, 0, 0, 0])
+    };
+
+    // P2=1: transparent pixels keep the background
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    let colors = (SIXEL_LEVELS * SIXEL_LEVELS * SIXEL_LEVELS) as usize;
+    for index in 0..colors {
+        let n = SIXEL_LEVELS as usize;
+        let (r, g, b) = (index / (n * n), (index / n) % n, index % n);
+        let pct = |level: usize| level * 100 / (n - 1);
+        let _ = write!(out, "#{};2;{};{};{}", index, pct(r), pct(g), pct(b));
+    }
+
+    // Each band covers six pixel rows; one pass per colour used in the band
+    let mut band_colors = vec![0u8; width as usize];
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let mut used: Vec<usize> = Vec::new();
+        let mut pixels = vec![None; (width * rows) as usize];
+        for dy in 0..rows {
+            for x in 0..width {
+                let pixel = sample(x, band + dy);
+                if pixel[3] >= 128 {
+                    let color = sixel_color(pixel);
+                    pixels[(dy * width + x) as usize] = Some(color);
+                    if !used.contains(&color) {
+                        used.push(color);
+                    }
+                }
+            }
+        }
+
+        for (pass, &color) in used.iter().enumerate() {
+            if pass > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{}", color);
+            for (x, bits) in band_colors.iter_mut().enumerate() {
+                *bits = (0..rows)
+                    .filter(|dy| pixels[(dy * width) as usize + x] == Some(color))
+                    .fold(0, |acc, dy| acc | (1 << dy));
+            }
+
+            let mut run = (band_colors[0], 0u32);
+            for &bits in &band_colors {
+                if bits == run.0 {
+                    run.1 += 1;
+                } else {
+                    push_run(&mut out, run.0, run.1);
+                    run = (bits, 1);
+                }
+            }
+            push_run(&mut out, run.0, run.1);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, pixel: [u8; 4]) -> ImageData {
+        let pixels = pixel.repeat((width * height) as usize);
+        ImageData::rgba(width, height, pixels).unwrap()
+    }
+
+    #[test]
+    fn test_fit_preserves_aspect_ratio() {
+        // Square image in 10x20 cells: twice as many columns as rows
+        assert_eq!(fit_cells((100, 100), 40, 40, (10, 20)), (40, 20));
+        assert_eq!(fit_cells((100, 100), 40, 10, (10, 20)), (20, 10));
+        assert_eq!(fit_cells((100, 100), 0, 10, (10, 20)), (0, 0));
+    }
+
+    #[test]
+    fn test_kitty_chunks_payload() {
+        let image = solid(64, 64, [255, 0, 0, 255]);
+        let sequence = kitty(&image, 8, 4);
+        assert!(sequence.starts_with("\x1b_Ga=T,f=32,s=64,v=64,c=8,r=4,"));
+        // 16 KiB of pixels is more than one chunk; only the last has m=0
+        assert!(sequence.matches("\x1b_G").count() > 1);
+        assert_eq!(sequence.matches("m=0;").count(), 1);
+        assert!(sequence.ends_with("\x1b\\"));
+    }
+
+    #[test]
+    fn test_sixel_encodes_runs() {
+        let image = solid(2, 2, [255, 255, 255, 255]);
+        let sequence = sixel(&image, 8, 6);
+        assert!(sequence.starts_with("\x1bP0;1;0q\"1;1;8;6"));
+        // White is the last cube entry; one band of eight full columns
+        assert!(sequence.ends_with("#215!8~-\x1b\\"));
+    }
+
+    #[test]
+    fn test_encode_falls_back_when_unsupported() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1]);
+        let png = ImageData::png(png).unwrap();
+        assert!(encode(&png, GraphicsProtocol::Sixel, 4, 2, (10, 20)).is_none());
+        assert!(encode(&png, GraphicsProtocol::None, 4, 2, (10, 20)).is_none());
+        assert!(encode(&png, GraphicsProtocol::Iterm2, 4, 2, (10, 20)).is_some());
+    }
+}
@@ -0,0 +1,157 @@
+//! Image data accepted by the graphics encoders
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Encoding of the image bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Raw 8-bit RGBA pixels, row-major
+    Rgba,
+    /// PNG file contents
+    Png,
+}
+
+/// Key identifying a cached escape sequence
+pub(crate) type EncodeKey = (super::GraphicsProtocol, u16, u16, (u16, u16));
+
+/// An image to display, with its pixel dimensions
+///
+/// Encoded escape sequences are cached so that redrawing the same image at
+/// the same size does not re-encode it every frame.
+pub struct ImageData {
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    bytes: Arc<[u8]>,
+    cache: Mutex<Option<(EncodeKey, Arc<str>)>>,
+}
+
+impl ImageData {
+    /// Create an image from raw RGBA pixels
+    ///
+    /// Returns `None` if `pixels` does not hold exactly `width * height` pixels.
+    pub fn rgba(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        let expected = (width as usize)
+            .checked_mul(height as usize)?
+            .checked_mul(4)?;
+        if width == 0 || height == 0 || pixels.len() != expected {
+            return None;
+        }
+        Some(Self::from_parts(ImageFormat::Rgba, width, height, pixels))
+    }
+
+    /// Create an image from PNG file contents
+    ///
+    /// Only the header is read (for the dimensions); returns `None` if the
+    /// bytes are not a PNG.
+    pub fn png(bytes: Vec<u8>) -> Option<Self> {
+        const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+        if bytes.len() < 24 || !bytes.starts_with(SIGNATURE) || &bytes[12..16] != b"IHDR" {
+            return None;
+        }
+        let width = u32::from_be_bytes(bytes[16..20].try_into().ok()?);
+        let height = u32::from_be_bytes(bytes[20..24].try_into().ok()?);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(Self::from_parts(ImageFormat::Png, width, height, bytes))
+    }
+
+    fn from_parts(format: ImageFormat, width: u32, height: u32, bytes: Vec<u8>) -> Self {
+        Self {
+            format,
+            width,
+            height,
+            bytes: bytes.into(),
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Encoding of the image bytes
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// Pixel dimensions (width, height)
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Raw image bytes (RGBA pixels or PNG contents)
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// RGBA value of the pixel at (x, y), if the pixels are available
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if self.format != ImageFormat::Rgba || x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = ((y * self.width + x) * 4) as usize;
+        self.bytes[i..i + 4].try_into().ok()
+    }
+
+    /// Return the cached sequence for `key`, encoding it on a miss
+    pub(crate) fn cached<F>(&self, key: EncodeKey, encode: F) -> Option<Arc<str>>
+    where
+        F: FnOnce() -> Option<String>,
+    {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((cached_key, sequence)) = cache.as_ref() {
+            if *cached_key == key {
+                return Some(sequence.clone());
+            }
+        }
+        let sequence: Arc<str> = encode()?.into();
+        *cache = Some((key, sequence.clone()));
+        Some(sequence)
+    }
+}
+
+impl Clone for ImageData {
+    fn clone(&self) -> Self {
+        Self {
+            format: self.format,
+            width: self.width,
+            height: self.height,
+            bytes: self.bytes.clone(),
+            cache: Mutex::new(None),
+        }
+    }
+}
+
+impl fmt::Debug for ImageData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageData")
+            .field("format", &self.format)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("len", &self.bytes.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_requires_matching_length() {
+        assert!(ImageData::rgba(2, 2, vec![0; 16]).is_some());
+        assert!(ImageData::rgba(2, 2, vec![0; 15]).is_none());
+        assert!(ImageData::rgba(0, 2, Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_png_reads_dimensions_from_header() {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        bytes.extend_from_slice(&640u32.to_be_bytes());
+        bytes.extend_from_slice(&480u32.to_be_bytes());
+        let image = ImageData::png(bytes).unwrap();
+        assert_eq!(image.dimensions(), (640, 480));
+        assert_eq!(image.format(), ImageFormat::Png);
+
+        assert!(ImageData::png(b"GIF89a".to_vec()).is_none());
+    }
+}
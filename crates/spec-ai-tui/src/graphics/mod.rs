@@ -0,0 +1,16 @@
+//! Inline image support via terminal graphics protocols
+//!
+//! Images are emitted as escape sequences after the cell buffer is drawn.
+//! Supported protocols are detected from the environment; terminals without
+//! graphics support fall back to half-block rendering in ordinary cells.
+
+mod encode;
+mod image;
+mod protocol;
+
+pub use encode::{encode, fit_cells};
+pub use image::{ImageData, ImageFormat};
+pub use protocol::GraphicsProtocol;
+
+/// Pixel size of a terminal cell assumed when the terminal does not report one
+pub const DEFAULT_CELL_SIZE: (u16, u16) = (10, 20);
@@ -0,0 +1,88 @@
+//! Graphics protocol capability detection
+
+use std::env;
+
+/// Terminal graphics protocol used to display images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GraphicsProtocol {
+    /// Kitty graphics protocol (kitty, Ghostty)
+    Kitty,
+    /// iTerm2 inline images (iTerm2, WezTerm)
+    Iterm2,
+    /// DEC sixel graphics (foot, mlterm, xterm with sixel)
+    Sixel,
+    /// No graphics support: images are drawn with half-block characters
+    #[default]
+    None,
+}
+
+impl GraphicsProtocol {
+    /// Detect the protocol supported by the current terminal
+    pub fn detect() -> Self {
+        Self::detect_from(|key| env::var(key).ok())
+    }
+
+    /// Detect the protocol from an environment lookup
+    pub fn detect_from<F>(lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        // Multiplexers swallow graphics sequences unless passthrough is configured
+        if lookup("TMUX").is_some() {
+            return GraphicsProtocol::None;
+        }
+
+        let term = lookup("TERM").unwrap_or_default();
+        let program = lookup("TERM_PROGRAM").unwrap_or_default();
+
+        if lookup("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || program == "ghostty" {
+            GraphicsProtocol::Kitty
+        } else if program == "iTerm.app" || program == "WezTerm" {
+            GraphicsProtocol::Iterm2
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            GraphicsProtocol::Sixel
+        } else {
+            GraphicsProtocol::None
+        }
+    }
+
+    /// Whether images are emitted as escape sequences rather than cells
+    pub fn is_supported(&self) -> bool {
+        *self != GraphicsProtocol::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)]) -> GraphicsProtocol {
+        GraphicsProtocol::detect_from(|key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn test_detect_protocols() {
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), GraphicsProtocol::Kitty);
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "iTerm.app")]),
+            GraphicsProtocol::Iterm2
+        );
+        assert_eq!(detect(&[("TERM", "foot")]), GraphicsProtocol::Sixel);
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color")]),
+            GraphicsProtocol::None
+        );
+    }
+
+    #[test]
+    fn test_tmux_disables_graphics() {
+        assert_eq!(
+            detect(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux-0/default")]),
+            GraphicsProtocol::None
+        );
+    }
+}
@@ -3,29 +3,38 @@
 //! This crate provides a complete TUI framework with:
 //! - Geometry primitives (`Rect`, `Point`, `Size`)
 //! - Cell-based buffer system with diff rendering
+//! - Inline images via Kitty, iTerm2 and sixel graphics protocols
 //! - Terminal abstraction over crossterm
 //! - Constraint-based layout engine
 //! - Widget system with stateful and interactive traits
 //! - Async event loop integrated with tokio
 //! - Application framework with Elm-inspired architecture
+//! - Headless test backend with scripted input and snapshot assertions
+//! - An injectable clock so time-dependent behavior is deterministic in tests
 
 pub mod app;
 pub mod buffer;
+pub mod clock;
 pub mod event;
 pub mod geometry;
+pub mod graphics;
 pub mod layout;
 pub mod style;
 pub mod terminal;
+pub mod testing;
 pub mod widget;
 
 // Re-export commonly used types
-pub use app::App;
+pub use app::{App, MirrorFrame};
 pub use buffer::{Buffer, Cell};
+pub use clock::{Clock, ManualClock, SystemClock};
 pub use event::Event;
 pub use geometry::{Point, Rect, Size};
+pub use graphics::{GraphicsProtocol, ImageData};
 pub use layout::{Constraint, Direction, Layout};
 pub use style::{
-    parse_markdown, truncate, wrap_text, Color, Line, MarkdownConfig, Modifier, Span, Style, Text,
+    parse_markdown, truncate, wrap_text, Color, GlyphSet, Line, MarkdownConfig, Modifier, Span,
+    StreamingMarkdown, Style, Text,
 };
 pub use terminal::Terminal;
 pub use widget::Widget;
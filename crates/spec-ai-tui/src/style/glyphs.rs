@@ -0,0 +1,174 @@
+//! Glyph sets for terminals with limited fonts
+//!
+//! Widgets draw rounded and heavy borders, braille spinners and eighth
+//! blocks, which the legacy Windows console host and the Linux virtual
+//! console cannot display. [`GlyphSet::Basic`] swaps them for characters of
+//! code page 437, which every terminal font covers.
+
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Braille spinner frames
+const UNICODE_SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// ASCII spinner frames
+const BASIC_SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Blocks filling 1/8 to 7/8 of a cell from the left
+const LEFT_EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// Blocks filling 1/8 to 7/8 of a cell from the bottom
+const LOWER_EIGHTHS: [char; 7] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇'];
+
+/// Glyph set selected for the running process
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// Characters available to widgets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GlyphSet {
+    /// Full Unicode: rounded and heavy borders, braille, eighth blocks
+    #[default]
+    Unicode,
+    /// Code page 437 only: single and double borders, half blocks, shades
+    Basic,
+}
+
+impl GlyphSet {
+    /// Detect the glyph set supported by the current terminal
+    pub fn detect() -> Self {
+        Self::detect_from(cfg!(windows), |key| env::var(key).ok())
+    }
+
+    /// Detect the glyph set from an environment lookup
+    ///
+    /// `SPEC_AI_GLYPHS=unicode` or `SPEC_AI_GLYPHS=basic` overrides detection.
+    /// On Windows, Unicode is only assumed inside terminals that announce
+    /// themselves (Windows Terminal, VS Code, ConEmu, mintty), since the
+    /// console host's default fonts lack braille and eighth blocks.
+    pub fn detect_from<F>(windows: bool, lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        match lookup("SPEC_AI_GLYPHS").as_deref().map(str::trim) {
+            Some(value) if value.eq_ignore_ascii_case("unicode") => return GlyphSet::Unicode,
+            Some(value) if value.eq_ignore_ascii_case("basic") => return GlyphSet::Basic,
+            _ => {}
+        }
+
+        if lookup("TERM").as_deref() == Some("linux") {
+            return GlyphSet::Basic;
+        }
+
+        if windows {
+            let modern = lookup("WT_SESSION").is_some()
+                || lookup("TERM_PROGRAM").is_some()
+                || lookup("ConEmuANSI").as_deref() == Some("ON")
+                || lookup("TERM").is_some();
+            if !modern {
+                return GlyphSet::Basic;
+            }
+        }
+
+        GlyphSet::Unicode
+    }
+
+    /// Glyph set used by widgets in this process
+    pub fn current() -> Self {
+        match CURRENT.load(Ordering::Relaxed) {
+            0 => GlyphSet::Unicode,
+            _ => GlyphSet::Basic,
+        }
+    }
+
+    /// Make this the glyph set used by widgets in this process
+    pub fn set_current(self) {
+        CURRENT.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// Frames of the indeterminate progress spinner
+    pub fn spinner(&self) -> &'static [char] {
+        match self {
+            GlyphSet::Unicode => &UNICODE_SPINNER,
+            GlyphSet::Basic => &BASIC_SPINNER,
+        }
+    }
+
+    /// Block filling `eighths` (1..=8) of a cell from the left
+    ///
+    /// The basic set rounds up to a half or full block, so small non-zero
+    /// values stay visible.
+    pub fn left_block(&self, eighths: usize) -> char {
+        match (self, eighths) {
+            (_, 8..) => '█',
+            (GlyphSet::Unicode, 1..) => LEFT_EIGHTHS[eighths - 1],
+            (GlyphSet::Basic, 5..) => '█',
+            (GlyphSet::Basic, 1..) => '▌',
+            (_, 0) => ' ',
+        }
+    }
+
+    /// Block filling `eighths` (1..=8) of a cell from the bottom
+    ///
+    /// The basic set rounds up to a half or full block, so small non-zero
+    /// values stay visible.
+    pub fn lower_block(&self, eighths: usize) -> char {
+        match (self, eighths) {
+            (_, 8..) => '█',
+            (GlyphSet::Unicode, 1..) => LOWER_EIGHTHS[eighths - 1],
+            (GlyphSet::Basic, 5..) => '█',
+            (GlyphSet::Basic, 1..) => '▄',
+            (_, 0) => ' ',
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(windows: bool, vars: &[(&str, &str)]) -> GlyphSet {
+        GlyphSet::detect_from(windows, |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn test_detects_legacy_windows_console() {
+        assert_eq!(detect(true, &[]), GlyphSet::Basic);
+        assert_eq!(detect(true, &[("WT_SESSION", "1")]), GlyphSet::Unicode);
+        assert_eq!(
+            detect(true, &[("TERM_PROGRAM", "vscode")]),
+            GlyphSet::Unicode
+        );
+        assert_eq!(detect(true, &[("ConEmuANSI", "ON")]), GlyphSet::Unicode);
+        assert_eq!(detect(true, &[("ConEmuANSI", "OFF")]), GlyphSet::Basic);
+        assert_eq!(detect(false, &[]), GlyphSet::Unicode);
+        assert_eq!(detect(false, &[("TERM", "linux")]), GlyphSet::Basic);
+    }
+
+    #[test]
+    fn test_override_wins() {
+        assert_eq!(
+            detect(true, &[("SPEC_AI_GLYPHS", "Unicode")]),
+            GlyphSet::Unicode
+        );
+        assert_eq!(
+            detect(false, &[("SPEC_AI_GLYPHS", "basic"), ("WT_SESSION", "1")]),
+            GlyphSet::Basic
+        );
+    }
+
+    #[test]
+    fn test_basic_blocks_round_up_to_halves() {
+        let basic = GlyphSet::Basic;
+        assert_eq!(basic.left_block(1), '▌');
+        assert_eq!(basic.left_block(5), '█');
+        assert_eq!(basic.lower_block(4), '▄');
+        assert_eq!(basic.lower_block(8), '█');
+        assert_eq!(GlyphSet::Unicode.left_block(1), '▏');
+        assert_eq!(GlyphSet::Unicode.lower_block(7), '▇');
+        assert!(basic.spinner().iter().all(char::is_ascii));
+    }
+}
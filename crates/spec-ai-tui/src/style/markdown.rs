@@ -74,53 +74,258 @@ impl MarkdownConfig {
 
 /// Parse markdown text and return styled Text
 pub fn parse_markdown(input: &str, config: &MarkdownConfig) -> Text {
-    let mut lines = Vec::new();
-    let mut in_code_block = false;
-    let mut code_block_lang = String::new();
-
+    let mut blocks = BlockState::default();
+    let mut rendered = Vec::new();
     for line in input.lines() {
+        blocks.push_line(line, config, &mut rendered);
+    }
+    Text::from_lines(layout(&rendered, config, config.max_width))
+}
+
+/// A rendered source line before wrapping
+#[derive(Debug, Clone)]
+struct RenderedLine {
+    line: Line,
+    /// Code block lines keep their layout
+    wrap: bool,
+}
+
+/// Block-level state carried from one source line to the next
+#[derive(Debug, Clone, Default)]
+struct BlockState {
+    in_code_block: bool,
+}
+
+impl BlockState {
+    /// Render one complete source line
+    fn push_line(&mut self, line: &str, config: &MarkdownConfig, out: &mut Vec<RenderedLine>) {
         if line.starts_with("```") {
-            if in_code_block {
+            if self.in_code_block {
                 // End of code block
-                in_code_block = false;
-                code_block_lang.clear();
+                self.in_code_block = false;
             } else {
                 // Start of code block
-                in_code_block = true;
-                code_block_lang = line.trim_start_matches('`').trim().to_string();
-                if !code_block_lang.is_empty() {
-                    lines.push(Line::from_spans([Span::styled(
-                        format!("  {}", code_block_lang),
-                        config.code_lang_style,
-                    )]));
+                self.in_code_block = true;
+                let lang = line.trim_start_matches('`').trim();
+                if !lang.is_empty() {
+                    out.push(RenderedLine {
+                        line: Line::from_spans([Span::styled(
+                            format!("  {}", lang),
+                            config.code_lang_style,
+                        )]),
+                        wrap: false,
+                    });
                 }
             }
-            continue;
+            return;
         }
 
-        if in_code_block {
+        if self.in_code_block {
             // Code block content - preserve as-is with code style
-            lines.push(Line::from_spans([Span::styled(
-                format!("  {}", line),
-                config.code_block_style,
-            )]));
+            out.push(RenderedLine {
+                line: Line::from_spans([Span::styled(
+                    format!("  {}", line),
+                    config.code_block_style,
+                )]),
+                wrap: false,
+            });
         } else {
             // Parse the line for markdown elements
-            let parsed_lines = parse_line(line, config);
-            lines.extend(parsed_lines);
+            out.push(RenderedLine {
+                line: parse_line(line, config),
+                wrap: true,
+            });
+        }
+    }
+}
+
+/// Wrap rendered lines to `max_width`
+fn layout(rendered: &[RenderedLine], config: &MarkdownConfig, max_width: usize) -> Vec<Line> {
+    let mut lines = Vec::with_capacity(rendered.len());
+    for rendered in rendered {
+        if rendered.wrap {
+            lines.extend(wrap_line(rendered.line.clone(), config, max_width));
+        } else {
+            lines.push(rendered.line.clone());
+        }
+    }
+    lines
+}
+
+/// Markdown that arrives in chunks, such as a streamed model response
+///
+/// Complete lines are parsed once and cached, so each chunk only re-lays-out
+/// the trailing partial line. That line is drawn the way it will look once
+/// finished: open inline delimiters are closed and a half-typed fence, header
+/// or list marker is held back, so text doesn't jump as the rest arrives.
+#[derive(Debug, Clone)]
+pub struct StreamingMarkdown {
+    config: MarkdownConfig,
+    source: String,
+    /// Bytes of `source` already parsed into `rendered`
+    committed: usize,
+    rendered: Vec<RenderedLine>,
+    blocks: BlockState,
+}
+
+impl StreamingMarkdown {
+    /// Create an empty stream; `max_width` is given per render instead
+    pub fn new(config: MarkdownConfig) -> Self {
+        Self {
+            config,
+            source: String::new(),
+            committed: 0,
+            rendered: Vec::new(),
+            blocks: BlockState::default(),
+        }
+    }
+
+    /// Append a chunk, parsing any lines it completes
+    pub fn push(&mut self, chunk: &str) {
+        self.source.push_str(chunk);
+        while let Some(len) = self.source[self.committed..].find('\n') {
+            let end = self.committed + len;
+            let line = self.source[self.committed..end].trim_end_matches('\r');
+            self.blocks
+                .push_line(line, &self.config, &mut self.rendered);
+            self.committed = end + 1;
+        }
+    }
+
+    /// Markdown received so far
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+
+    /// Forget everything received so far
+    pub fn clear(&mut self) {
+        self.source.clear();
+        self.committed = 0;
+        self.rendered.clear();
+        self.blocks = BlockState::default();
+    }
+
+    /// Lay out everything received so far, wrapped to `max_width` (0 = no wrapping)
+    pub fn render(&self, max_width: usize) -> Text {
+        let mut lines = layout(&self.rendered, &self.config, max_width);
+        let tail = &self.source[self.committed..];
+        if !tail.is_empty() && !is_pending_marker(tail, self.blocks.in_code_block) {
+            let mut blocks = self.blocks.clone();
+            let mut rendered = Vec::new();
+            if blocks.in_code_block {
+                blocks.push_line(tail, &self.config, &mut rendered);
+            } else {
+                blocks.push_line(&close_inline(tail), &self.config, &mut rendered);
+            }
+            lines.extend(layout(&rendered, &self.config, max_width));
         }
+        Text::from_lines(lines)
     }
+}
 
-    Text::from_lines(lines)
+/// Whether a partial line is so far only the start of block syntax
+fn is_pending_marker(tail: &str, in_code_block: bool) -> bool {
+    if "```".starts_with(tail) {
+        return true;
+    }
+    if in_code_block {
+        return false;
+    }
+    let marker = tail.trim();
+    if marker.is_empty() {
+        return false;
+    }
+    marker.chars().all(|c| c == '#')
+        || marker == "-"
+        || marker == "*"
+        || marker
+            .strip_suffix('.')
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Close inline delimiters left open at the end of a partial line
+///
+/// Openers with nothing after them yet are dropped rather than closed, so
+/// they don't show up as literal `****` or ``` `` ```.
+fn close_inline(text: &str) -> String {
+    // (closer, byte offset of the opener, byte offset after it)
+    let mut open: Vec<(&'static str, usize, usize)> = Vec::new();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+        let next = chars.get(i + 1).map(|&(_, n)| n);
+        let top = open.last().map(|&(closer, _, _)| closer);
+        let mut width = c.len_utf8();
+        match c {
+            '`' => toggle(&mut open, "`", pos, width),
+            _ if top == Some("`") => {}
+            '*' | '_' if next == Some(c) => {
+                width *= 2;
+                toggle(&mut open, if c == '*' { "**" } else { "__" }, pos, width);
+                i += 1;
+            }
+            '*' | '_' => {
+                let delim = if c == '*' { "*" } else { "_" };
+                let word_start = i == 0 || !chars[i - 1].1.is_alphanumeric();
+                if top == Some(delim) {
+                    open.pop();
+                } else if next.is_some_and(|n| !n.is_whitespace()) && (c == '*' || word_start) {
+                    open.push((delim, pos, pos + width));
+                }
+            }
+            '[' => open.push(("]()", pos, pos + width)),
+            ']' if top == Some("]()") => {
+                open.pop();
+                if next == Some('(') {
+                    // An empty target still renders as a link
+                    open.push((")", pos, pos + width));
+                    i += 1;
+                }
+            }
+            ')' if top == Some(")") => {
+                open.pop();
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let mut closed = text.to_string();
+    while let Some((closer, start, after)) = open.pop() {
+        if after >= closed.len() && closer != ")" {
+            closed.truncate(start);
+        } else {
+            closed.push_str(closer);
+        }
+    }
+    closed
+}
+
+fn toggle(
+    open: &mut Vec<(&'static str, usize, usize)>,
+    delim: &'static str,
+    pos: usize,
+    width: usize,
+) {
+    if open.last().is_some_and(|&(closer, _, _)| closer == delim) {
+        open.pop();
+    } else {
+        open.push((delim, pos, pos + width));
+    }
 }
 
 /// Parse a single line of markdown
-fn parse_line(line: &str, config: &MarkdownConfig) -> Vec<Line> {
+fn parse_line(line: &str, config: &MarkdownConfig) -> Line {
     let trimmed = line.trim_start();
 
     // Check for headers
     if let Some(header_line) = parse_header(trimmed, config) {
-        return wrap_line(header_line, config);
+        return header_line;
     }
 
     // Check for list items
@@ -132,16 +337,15 @@ fn parse_line(line: &str, config: &MarkdownConfig) -> Vec<Line> {
             config.bullet_style,
         )];
         spans.extend(parse_inline(rest, config));
-        return wrap_line(Line::from_spans(spans), config);
+        return Line::from_spans(spans);
     }
 
     // Regular paragraph
     if trimmed.is_empty() {
-        return vec![Line::empty()];
+        return Line::empty();
     }
 
-    let spans = parse_inline(trimmed, config);
-    wrap_line(Line::from_spans(spans), config)
+    Line::from_spans(parse_inline(trimmed, config))
 }
 
 /// Parse header syntax (# ## ###)
@@ -392,8 +596,8 @@ fn find_link(text: &str) -> Option<(usize, &str, &str, &str)> {
 }
 
 /// Wrap a line if it exceeds max_width
-fn wrap_line(line: Line, config: &MarkdownConfig) -> Vec<Line> {
-    if config.max_width == 0 || line.width() <= config.max_width {
+fn wrap_line(line: Line, config: &MarkdownConfig, max_width: usize) -> Vec<Line> {
+    if max_width == 0 || line.width() <= max_width {
         return vec![line];
     }
 
@@ -401,7 +605,7 @@ fn wrap_line(line: Line, config: &MarkdownConfig) -> Vec<Line> {
     let mut current_spans: Vec<Span> = Vec::new();
     let mut current_width = 0;
     let prefix_width = UnicodeWidthStr::width(config.wrap_prefix.as_str());
-    let effective_width = config.max_width;
+    let effective_width = max_width;
     let mut is_first_line = true;
 
     for span in line.spans {
@@ -555,4 +759,67 @@ mod tests {
         let text = parse_markdown("This is a longer line that should wrap", &config);
         assert!(text.lines.len() > 1);
     }
+
+    fn rendered_text(text: &Text) -> Vec<String> {
+        text.lines
+            .iter()
+            .map(|line| line.spans.iter().map(|s| s.content.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_streaming_matches_full_parse() {
+        let source = "# Plan\n\nSome **bold** and `code` with [a link](http://x).\n\n\
+                      - first item\n- second *item*\n1. numbered\n\n\
+                      ```rust\nfn main() {}\n```\nDone.";
+        let config = MarkdownConfig::new().wrap_prefix("  ");
+        let mut stream = StreamingMarkdown::new(config.clone());
+        for c in source.chars() {
+            stream.push(&c.to_string());
+            for line in rendered_text(&stream.render(30)) {
+                assert!(!line.contains("**"), "raw delimiter in {:?}", line);
+            }
+        }
+
+        assert_eq!(stream.source(), source);
+        assert_eq!(
+            stream.render(30),
+            parse_markdown(source, &config.clone().max_width(30))
+        );
+        assert_eq!(stream.render(0), parse_markdown(source, &config));
+    }
+
+    #[test]
+    fn test_streaming_partial_lines() {
+        let mut stream = StreamingMarkdown::new(MarkdownConfig::default());
+        stream.push("Hello **wor");
+        let text = stream.render(0);
+        assert_eq!(rendered_text(&text), vec!["Hello wor"]);
+        assert!(text.lines[0].spans[1]
+            .style
+            .modifier
+            .contains(Modifier::BOLD));
+
+        stream.push("ld**\n##");
+        assert_eq!(rendered_text(&stream.render(0)), vec!["Hello world"]);
+
+        stream.push(" Title\n``");
+        assert_eq!(
+            rendered_text(&stream.render(0)),
+            vec!["Hello world", "## Title"]
+        );
+
+        stream.push("`rust\nfn ma");
+        let text = stream.render(0);
+        assert_eq!(
+            rendered_text(&text)[2..],
+            ["  rust".to_string(), "  fn ma".to_string()]
+        );
+        assert_eq!(text.lines[3].spans[0].style.fg, Color::Yellow);
+
+        stream.clear();
+        assert!(stream.is_empty());
+        stream.push("see `");
+        assert_eq!(rendered_text(&stream.render(0)), vec!["see "]);
+    }
 }
@@ -1,6 +1,7 @@
 //! Styling system for terminal text
 
 mod color;
+mod glyphs;
 pub mod markdown;
 mod modifier;
 mod style;
@@ -8,7 +9,8 @@ mod styled;
 pub mod text_utils;
 
 pub use color::Color;
-pub use markdown::{parse_markdown, MarkdownConfig};
+pub use glyphs::GlyphSet;
+pub use markdown::{parse_markdown, MarkdownConfig, StreamingMarkdown};
 pub use modifier::Modifier;
 pub use style::Style;
 pub use styled::{Line, Span, Text};
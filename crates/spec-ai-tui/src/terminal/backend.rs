@@ -1,6 +1,6 @@
 //! Terminal backend wrapping crossterm operations
 
-use crate::buffer::{Buffer, Cell};
+use crate::buffer::{Buffer, Cell, ImagePlacement};
 use crate::geometry::{Rect, Size};
 use crate::style::Color;
 use crossterm::{
@@ -8,11 +8,16 @@ use crossterm::{
     event::EnableBracketedPaste,
     execute, queue,
     style::{Attribute, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
-    terminal::{self, enable_raw_mode, Clear, ClearType, EnterAlternateScreen},
+    terminal::{
+        self, enable_raw_mode, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+    },
 };
 use std::io::{self, Stdout, Write};
 
-use super::RawModeGuard;
+use super::raw_mode::{self, RawModeGuard};
+
+/// Kitty graphics command deleting all visible placements
+const KITTY_DELETE_ALL: &str = "\x1b_Ga=d,q=2\x1b\\";
 
 /// Terminal backend wrapping crossterm operations
 pub struct Terminal {
@@ -21,6 +26,8 @@ pub struct Terminal {
     size: Size,
     /// Previous buffer for diff rendering
     prev_buffer: Option<Buffer>,
+    /// Whether kitty image placements may be on screen
+    kitty_images: bool,
 }
 
 impl Terminal {
@@ -32,6 +39,7 @@ impl Terminal {
             stdout,
             size: Size::new(width, height),
             prev_buffer: None,
+            kitty_images: false,
         })
     }
 
@@ -41,18 +49,52 @@ impl Terminal {
     /// 1. Enable raw mode
     /// 2. Enter alternate screen
     /// 3. Hide the cursor
-    /// 4. Enable bracketed paste mode
+    /// 4. Enable bracketed paste mode, where supported
     ///
     /// The returned guard will cleanup when dropped.
     pub fn enter_raw_mode(&mut self) -> io::Result<RawModeGuard> {
         enable_raw_mode()?;
-        execute!(
-            self.stdout,
-            EnterAlternateScreen,
-            Hide,
-            EnableBracketedPaste
-        )?;
-        Ok(RawModeGuard::new())
+        // Created before the remaining setup so a failure part way through
+        // still restores the terminal
+        let guard = RawModeGuard::new();
+        execute!(self.stdout, EnterAlternateScreen, Hide)?;
+        raw_mode::set_alternate_screen(true);
+        // Legacy Windows consoles without ANSI support reject bracketed
+        // paste; pastes then arrive as key presses instead
+        let _ = execute!(self.stdout, EnableBracketedPaste);
+        Ok(guard)
+    }
+
+    /// Enter raw mode but stay on the normal screen with the cursor shown
+    ///
+    /// Used by the linear render mode, whose output should remain in the
+    /// terminal's scrollback.
+    pub fn enter_raw_mode_inline(&mut self) -> io::Result<RawModeGuard> {
+        enable_raw_mode()?;
+        let guard = RawModeGuard::new();
+        let _ = execute!(self.stdout, EnableBracketedPaste);
+        Ok(guard)
+    }
+
+    /// Switch to or from the alternate screen while in raw mode
+    ///
+    /// The cursor is hidden on the alternate screen and shown otherwise, and
+    /// the next draw is a full redraw.
+    pub fn set_alternate_screen(&mut self, alternate: bool) -> io::Result<()> {
+        if alternate {
+            execute!(self.stdout, EnterAlternateScreen, Hide)?;
+        } else {
+            execute!(self.stdout, ResetColor, LeaveAlternateScreen, Show)?;
+        }
+        raw_mode::set_alternate_screen(alternate);
+        self.invalidate();
+        Ok(())
+    }
+
+    /// Write text as is at the cursor and flush it
+    pub fn write_text(&mut self, text: &str) -> io::Result<()> {
+        queue!(self.stdout, Print(text))?;
+        self.flush()
     }
 
     /// Get current terminal size
@@ -67,6 +109,15 @@ impl Terminal {
         Ok(())
     }
 
+    /// Pixel size of a cell, if the terminal reports its pixel dimensions
+    pub fn cell_pixel_size(&self) -> Option<(u16, u16)> {
+        let size = terminal::window_size().ok()?;
+        if size.width == 0 || size.height == 0 || size.columns == 0 || size.rows == 0 {
+            return None;
+        }
+        Some((size.width / size.columns, size.height / size.rows))
+    }
+
     /// Get a full-screen rect
     pub fn full_rect(&self) -> Rect {
         Rect::sized(self.size.width, self.size.height)
@@ -103,27 +154,25 @@ impl Terminal {
 
     /// Draw a single cell at position
     pub fn draw_cell(&mut self, x: u16, y: u16, cell: &Cell) -> io::Result<()> {
-        queue!(self.stdout, MoveTo(x, y))?;
-
-        // Set colors
-        queue!(self.stdout, SetForegroundColor(cell.fg.into()))?;
-        queue!(self.stdout, SetBackgroundColor(cell.bg.into()))?;
-
-        // Set attributes
-        if !cell.modifier.is_empty() {
-            for attr in cell.modifier.attributes() {
-                queue!(self.stdout, SetAttribute(attr))?;
-            }
-        }
+        queue_cell(&mut self.stdout, x, y, cell)
+    }
 
-        // Draw the symbol
-        queue!(self.stdout, Print(&cell.symbol))?;
+    /// Emit an image's escape sequence at its top-left cell
+    fn draw_image(&mut self, image: &ImagePlacement) -> io::Result<()> {
+        self.kitty_images |= image.sequence.starts_with("\x1b_G");
+        queue!(
+            self.stdout,
+            MoveTo(image.area.x, image.area.y),
+            Print(&*image.sequence)
+        )
+    }
 
-        // Reset attributes if we set any
-        if !cell.modifier.is_empty() {
-            queue!(self.stdout, SetAttribute(Attribute::Reset))?;
+    /// Remove kitty placements, which persist until explicitly deleted
+    fn clear_kitty_images(&mut self) -> io::Result<()> {
+        if self.kitty_images {
+            queue!(self.stdout, Print(KITTY_DELETE_ALL))?;
+            self.kitty_images = false;
         }
-
         Ok(())
     }
 
@@ -149,8 +198,37 @@ impl Terminal {
                 .map(|(x, y, cell)| (x, y, cell.clone()))
                 .collect();
 
-            for (x, y, cell) in changes {
-                self.draw_cell(x, y, &cell)?;
+            let images_changed = buffer.images() != prev.images();
+            let stale: Vec<Rect> = if images_changed {
+                prev.images().iter().map(|image| image.area).collect()
+            } else {
+                Vec::new()
+            };
+
+            for (x, y, cell) in &changes {
+                self.draw_cell(*x, *y, cell)?;
+            }
+
+            if images_changed {
+                self.clear_kitty_images()?;
+                // Repaint cells under removed images to erase their pixels
+                for area in stale {
+                    for y in area.y..area.bottom() {
+                        for x in area.x..area.right() {
+                            if let Some(cell) = buffer.get(x, y) {
+                                self.draw_cell(x, y, cell)?;
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Re-emit images that are new or had cells drawn over them
+            for image in buffer.images() {
+                let overdrawn = changes.iter().any(|(x, y, _)| image.area.contains(*x, *y));
+                if images_changed || overdrawn {
+                    self.draw_image(image)?;
+                }
             }
         }
 
@@ -161,36 +239,12 @@ impl Terminal {
 
     /// Force a full redraw of the buffer (no diff)
     pub fn draw_full(&mut self, buffer: &Buffer) -> io::Result<()> {
-        // Reset terminal state
-        queue!(self.stdout, ResetColor)?;
+        self.clear_kitty_images()?;
 
-        let mut last_style = (Color::Reset, Color::Reset);
+        queue_buffer(&mut self.stdout, buffer)?;
 
-        for (x, y, cell) in buffer.iter() {
-            queue!(self.stdout, MoveTo(x, y))?;
-
-            // Only change colors if needed
-            let current_style = (cell.fg, cell.bg);
-            if current_style != last_style {
-                queue!(self.stdout, SetForegroundColor(cell.fg.into()))?;
-                queue!(self.stdout, SetBackgroundColor(cell.bg.into()))?;
-                last_style = current_style;
-            }
-
-            // Handle modifiers
-            if !cell.modifier.is_empty() {
-                for attr in cell.modifier.attributes() {
-                    queue!(self.stdout, SetAttribute(attr))?;
-                }
-            }
-
-            queue!(self.stdout, Print(&cell.symbol))?;
-
-            // Reset if we had modifiers
-            if !cell.modifier.is_empty() {
-                queue!(self.stdout, SetAttribute(Attribute::Reset))?;
-                last_style = (Color::Reset, Color::Reset); // Force color reset next time
-            }
+        for image in buffer.images() {
+            self.draw_image(image)?;
         }
 
         // Store for future diffs
@@ -204,6 +258,69 @@ impl Terminal {
     }
 }
 
+/// Queue one cell with its colors and attributes at a position
+pub(crate) fn queue_cell(out: &mut impl Write, x: u16, y: u16, cell: &Cell) -> io::Result<()> {
+    queue!(out, MoveTo(x, y))?;
+
+    // Set colors
+    queue!(out, SetForegroundColor(cell.fg.into()))?;
+    queue!(out, SetBackgroundColor(cell.bg.into()))?;
+
+    // Set attributes
+    if !cell.modifier.is_empty() {
+        for attr in cell.modifier.attributes() {
+            queue!(out, SetAttribute(attr))?;
+        }
+    }
+
+    // Draw the symbol
+    queue!(out, Print(&cell.symbol))?;
+
+    // Reset attributes if we set any
+    if !cell.modifier.is_empty() {
+        queue!(out, SetAttribute(Attribute::Reset))?;
+    }
+
+    Ok(())
+}
+
+/// Queue every cell of a buffer, only switching colors when they change
+pub(crate) fn queue_buffer(out: &mut impl Write, buffer: &Buffer) -> io::Result<()> {
+    // Reset terminal state
+    queue!(out, ResetColor)?;
+
+    let mut last_style = (Color::Reset, Color::Reset);
+
+    for (x, y, cell) in buffer.iter() {
+        queue!(out, MoveTo(x, y))?;
+
+        // Only change colors if needed
+        let current_style = (cell.fg, cell.bg);
+        if current_style != last_style {
+            queue!(out, SetForegroundColor(cell.fg.into()))?;
+            queue!(out, SetBackgroundColor(cell.bg.into()))?;
+            last_style = current_style;
+        }
+
+        // Handle modifiers
+        if !cell.modifier.is_empty() {
+            for attr in cell.modifier.attributes() {
+                queue!(out, SetAttribute(attr))?;
+            }
+        }
+
+        queue!(out, Print(&cell.symbol))?;
+
+        // Reset if we had modifiers
+        if !cell.modifier.is_empty() {
+            queue!(out, SetAttribute(Attribute::Reset))?;
+            last_style = (Color::Reset, Color::Reset); // Force color reset next time
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,4 +4,5 @@ mod backend;
 mod raw_mode;
 
 pub use backend::Terminal;
+pub(crate) use backend::{queue_buffer, queue_cell};
 pub use raw_mode::RawModeGuard;
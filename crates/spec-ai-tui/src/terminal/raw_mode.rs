@@ -7,15 +7,29 @@ use crossterm::{
     terminal::{disable_raw_mode, LeaveAlternateScreen},
 };
 use std::io::{self, stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+/// Whether the terminal is showing the alternate screen
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+
+/// Record whether the alternate screen is shown, so cleanup only leaves it
+/// when it was entered
+pub(crate) fn set_alternate_screen(active: bool) {
+    ALTERNATE_SCREEN.store(active, Ordering::Relaxed);
+}
 
 /// RAII guard for raw terminal mode
 ///
 /// When this guard is dropped, it will:
 /// 1. Disable raw mode
-/// 2. Leave alternate screen
+/// 2. Leave alternate screen, if it was entered
 /// 3. Show the cursor
 ///
 /// This ensures the terminal is properly restored even if the program panics.
+/// The first guard also installs a panic hook that restores the terminal
+/// before the panic message is printed, so the message is not lost on the
+/// alternate screen.
 pub struct RawModeGuard {
     // Private field to prevent construction outside this module
     _private: (),
@@ -26,6 +40,7 @@ impl RawModeGuard {
     ///
     /// This should only be called by Terminal::enter_raw_mode()
     pub(crate) fn new() -> Self {
+        install_panic_hook();
         Self { _private: () }
     }
 }
@@ -37,10 +52,28 @@ impl Drop for RawModeGuard {
     }
 }
 
+/// Restore the terminal before the default panic output runs
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = cleanup_terminal();
+            previous(info);
+        }));
+    });
+}
+
 /// Cleanup the terminal state
 fn cleanup_terminal() -> io::Result<()> {
     disable_raw_mode()?;
-    execute!(stdout(), DisableBracketedPaste, LeaveAlternateScreen, Show)?;
+    // Bracketed paste is not supported by legacy Windows consoles and fails
+    // there, so it must not stop the alternate screen from being left
+    let _ = execute!(stdout(), DisableBracketedPaste);
+    if ALTERNATE_SCREEN.swap(false, Ordering::Relaxed) {
+        execute!(stdout(), LeaveAlternateScreen)?;
+    }
+    execute!(stdout(), Show)?;
     Ok(())
 }
 
@@ -0,0 +1,124 @@
+//! In-memory backend for driving apps without a terminal
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::app::App;
+use crate::buffer::Buffer;
+use crate::clock::ManualClock;
+use crate::event::Event;
+use crate::geometry::Rect;
+
+use super::EventScript;
+
+/// Runs an [`App`] headlessly, rendering into an in-memory buffer
+///
+/// Mirrors `AppRunner`: ticks call `on_tick`, resizes change the render
+/// area, and every handled event is followed by a render.
+///
+/// The app gets a [`ManualClock`] that stands still between events and
+/// moves forward by the tick rate (100ms, like `AppRunner`) on each tick.
+pub struct TestBackend<A: App> {
+    app: A,
+    state: A::State,
+    area: Rect,
+    buffer: Buffer,
+    running: bool,
+    clock: ManualClock,
+    tick_rate: Duration,
+}
+
+impl<A: App> TestBackend<A> {
+    /// Initialize the app and render its first frame
+    pub fn new(mut app: A, width: u16, height: u16) -> Self {
+        let clock = ManualClock::new();
+        app.set_clock(Arc::new(clock.clone()));
+        let state = app.init();
+        let area = Rect::new(0, 0, width, height);
+        let mut backend = Self {
+            app,
+            state,
+            area,
+            buffer: Buffer::new(area),
+            running: true,
+            clock,
+            tick_rate: Duration::from_millis(100),
+        };
+        backend.render();
+        backend
+    }
+
+    /// Set how far the clock moves on each tick
+    pub fn tick_rate(mut self, rate: Duration) -> Self {
+        self.tick_rate = rate;
+        self
+    }
+
+    /// The app's clock, for moving time forward between events
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    /// Re-render the current state
+    pub fn render(&mut self) {
+        self.buffer = Buffer::new(self.area);
+        self.app.render(&self.state, self.area, &mut self.buffer);
+    }
+
+    /// Deliver an event and re-render
+    ///
+    /// Returns `false` once the app has asked to quit; later events are
+    /// ignored.
+    pub fn send(&mut self, event: Event) -> bool {
+        if !self.running {
+            return false;
+        }
+
+        if let Event::Resize { width, height } = event {
+            self.area = Rect::new(0, 0, width, height);
+        }
+        if matches!(event, Event::Tick) {
+            self.clock.advance(self.tick_rate);
+            self.app.on_tick(&mut self.state);
+        }
+
+        self.running = self.app.handle_event(event, &mut self.state);
+        self.render();
+        self.running
+    }
+
+    /// Deliver every event in a script, stopping if the app quits
+    pub fn run(&mut self, script: &EventScript) -> bool {
+        for event in script.events() {
+            if !self.send(event.clone()) {
+                break;
+            }
+        }
+        self.running
+    }
+
+    /// The most recently rendered frame
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Current app state
+    pub fn state(&self) -> &A::State {
+        &self.state
+    }
+
+    /// Mutable app state, for setting up fixtures (call `render` afterwards)
+    pub fn state_mut(&mut self) -> &mut A::State {
+        &mut self.state
+    }
+
+    /// The app under test
+    pub fn app(&self) -> &A {
+        &self.app
+    }
+
+    /// Whether the app is still running
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+}
@@ -0,0 +1,214 @@
+//! Headless testing utilities
+//!
+//! [`TestBackend`] runs an [`App`](crate::app::App) against an in-memory
+//! [`Buffer`](crate::buffer::Buffer) instead of a terminal, [`EventScript`]
+//! feeds it scripted input, and the assertion helpers compare the rendered
+//! cells with expected lines or snapshot files.
+//!
+//! ```ignore
+//! let mut backend = TestBackend::new(MyApp, 40, 10);
+//! backend.run(&EventScript::new().keys("hello").enter());
+//! assert_buffer_lines(backend.buffer(), &["> hello", ""]);
+//! assert_snapshot!("prompt_after_enter", backend.buffer());
+//! ```
+
+mod backend;
+mod script;
+mod snapshot;
+
+pub use backend::TestBackend;
+pub use script::EventScript;
+pub use snapshot::{
+    assert_buffer_lines, assert_cell, assert_snapshot, buffer_lines, UPDATE_SNAPSHOTS_ENV,
+};
+
+/// Assert a buffer against `tests/snapshots/<name>.snap` in the calling crate
+///
+/// The snapshot is created when missing and rewritten when the
+/// `SPEC_AI_UPDATE_SNAPSHOTS` environment variable is set.
+#[macro_export]
+macro_rules! assert_snapshot {
+    ($name:literal, $buffer:expr) => {
+        $crate::testing::assert_snapshot(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/snapshots/",
+                $name,
+                ".snap"
+            ),
+            $buffer,
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::App;
+    use crate::buffer::Buffer;
+    use crate::clock::Clock;
+    use crate::event::{Event, KeyCode};
+    use crate::geometry::Rect;
+    use crate::style::{Color, Style};
+    use crate::widget::builtin::{Block, Paragraph};
+    use crate::widget::Widget;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// Echoes typed text; Enter submits it, Ctrl+Q quits
+    struct EchoApp;
+
+    #[derive(Default)]
+    struct EchoState {
+        input: String,
+        submitted: Vec<String>,
+        ticks: u32,
+    }
+
+    impl App for EchoApp {
+        type State = EchoState;
+
+        fn init(&self) -> Self::State {
+            EchoState::default()
+        }
+
+        fn handle_event(&mut self, event: Event, state: &mut Self::State) -> bool {
+            if event.is_quit() {
+                return false;
+            }
+            if let Event::Key(key) = event {
+                match key.code {
+                    KeyCode::Char(c) => state.input.push(c),
+                    KeyCode::Enter => state.submitted.push(std::mem::take(&mut state.input)),
+                    _ => {}
+                }
+            }
+            true
+        }
+
+        fn render(&self, state: &Self::State, area: Rect, buf: &mut Buffer) {
+            let header = format!("{}x{} ticks:{}", area.width, area.height, state.ticks);
+            buf.set_string(0, 0, &header, Style::new().fg(Color::Cyan));
+            for (i, line) in state.submitted.iter().enumerate() {
+                buf.set_string(0, 1 + i as u16, line, Style::default());
+            }
+            let prompt = format!("> {}", state.input);
+            buf.set_string(0, area.height - 1, &prompt, Style::default());
+        }
+
+        fn on_tick(&mut self, state: &mut Self::State) {
+            state.ticks += 1;
+        }
+    }
+
+    #[test]
+    fn test_scripted_events_drive_app() {
+        let mut backend = TestBackend::new(EchoApp, 20, 4);
+        assert_buffer_lines(backend.buffer(), &["20x4 ticks:0", "", "", ">"]);
+
+        let script = EventScript::new()
+            .keys("hi\n")
+            .keys("yo")
+            .ticks(2)
+            .resize(16, 5);
+        assert!(backend.run(&script));
+
+        assert_eq!(backend.state().submitted, vec!["hi"]);
+        assert_buffer_lines(backend.buffer(), &["16x5 ticks:2", "hi", "", "", "> yo"]);
+        assert_cell(backend.buffer(), 0, 0, "1", Style::new().fg(Color::Cyan));
+    }
+
+    /// Shows how long ago it started, by the clock it was given
+    #[derive(Default)]
+    struct StopwatchApp {
+        clock: Option<Arc<dyn Clock>>,
+    }
+
+    impl App for StopwatchApp {
+        type State = Instant;
+
+        fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+            self.clock = Some(clock);
+        }
+
+        fn init(&self) -> Self::State {
+            self.clock.as_ref().unwrap().now()
+        }
+
+        fn handle_event(&mut self, _event: Event, _state: &mut Self::State) -> bool {
+            true
+        }
+
+        fn render(&self, state: &Self::State, _area: Rect, buf: &mut Buffer) {
+            let elapsed = self.clock.as_ref().unwrap().now() - *state;
+            buf.set_string(
+                0,
+                0,
+                &format!("{}ms", elapsed.as_millis()),
+                Style::default(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_ticks_advance_the_clock() {
+        let mut backend =
+            TestBackend::new(StopwatchApp::default(), 10, 1).tick_rate(Duration::from_millis(250));
+        backend.run(&EventScript::new().ticks(3).keys("x"));
+        assert_buffer_lines(backend.buffer(), &["750ms"]);
+
+        backend.clock().advance(Duration::from_secs(1));
+        backend.render();
+        assert_buffer_lines(backend.buffer(), &["1750ms"]);
+    }
+
+    #[test]
+    fn test_quit_stops_script() {
+        let mut backend = TestBackend::new(EchoApp, 20, 2);
+        assert!(!backend.run(&EventScript::new().keys("a").ctrl('q').keys("b")));
+        assert!(!backend.is_running());
+        assert_eq!(backend.state().input, "a");
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer does not match")]
+    fn test_buffer_lines_mismatch_panics() {
+        let backend = TestBackend::new(EchoApp, 20, 2);
+        assert_buffer_lines(backend.buffer(), &["wrong"]);
+    }
+
+    #[test]
+    fn test_wide_characters_render_once() {
+        let area = Rect::new(0, 0, 6, 1);
+        let mut buf = Buffer::new(area);
+        buf.set_string(0, 0, "日本x", Style::default());
+        assert_eq!(buffer_lines(&buf), vec!["日本x"]);
+    }
+
+    #[test]
+    fn test_snapshot_file_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("echo.snap");
+        let mut backend = TestBackend::new(EchoApp, 12, 2);
+
+        assert_snapshot(&path, backend.buffer());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "12x2 ticks:0\n>\n");
+        assert_snapshot(&path, backend.buffer());
+
+        backend.send(Event::Tick);
+        let result = std::panic::catch_unwind(|| assert_snapshot(&path, backend.buffer()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_block_paragraph_snapshot() {
+        let area = Rect::new(0, 0, 24, 4);
+        let mut buf = Buffer::new(area);
+        let block = Block::bordered().title("Notes");
+        let inner = block.inner(area);
+        block.render(area, &mut buf);
+        Paragraph::raw("headless render").render(inner, &mut buf);
+
+        crate::assert_snapshot!("block_paragraph", &buf);
+    }
+}
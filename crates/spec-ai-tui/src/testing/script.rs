@@ -0,0 +1,91 @@
+//! Scripted input for headless tests
+
+use crate::event::{Attachment, Event, KeyCode, KeyEvent, KeyModifiers};
+
+/// A sequence of events to inject into a [`TestBackend`](super::TestBackend)
+#[derive(Debug, Clone, Default)]
+pub struct EventScript {
+    events: Vec<Event>,
+}
+
+impl EventScript {
+    /// Create an empty script
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an arbitrary event
+    pub fn event(mut self, event: Event) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Append a key press
+    pub fn key(self, code: KeyCode) -> Self {
+        self.key_with(code, KeyModifiers::NONE)
+    }
+
+    /// Append a key press with modifiers
+    pub fn key_with(self, code: KeyCode, modifiers: KeyModifiers) -> Self {
+        self.event(Event::Key(KeyEvent::new(code, modifiers)))
+    }
+
+    /// Append a Ctrl+key press
+    pub fn ctrl(self, c: char) -> Self {
+        self.key_with(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    /// Append one key press per character, as if typed
+    pub fn keys(self, text: &str) -> Self {
+        text.chars().fold(self, |script, c| match c {
+            '\n' => script.key(KeyCode::Enter),
+            '\t' => script.key(KeyCode::Tab),
+            c if c.is_uppercase() => script.key_with(KeyCode::Char(c), KeyModifiers::SHIFT),
+            c => script.key(KeyCode::Char(c)),
+        })
+    }
+
+    /// Append an Enter key press
+    pub fn enter(self) -> Self {
+        self.key(KeyCode::Enter)
+    }
+
+    /// Append an Escape key press
+    pub fn esc(self) -> Self {
+        self.key(KeyCode::Esc)
+    }
+
+    /// Append a paste
+    pub fn paste(self, text: impl Into<String>) -> Self {
+        self.event(Event::Paste(text.into()))
+    }
+
+    /// Append a drop of files onto the terminal
+    pub fn drop_files<P: Into<std::path::PathBuf>>(
+        self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.event(Event::Attach(
+            paths
+                .into_iter()
+                .map(|p| Attachment::File(p.into()))
+                .collect(),
+        ))
+    }
+
+    /// Append `count` ticks
+    pub fn ticks(mut self, count: usize) -> Self {
+        self.events.extend(std::iter::repeat_n(Event::Tick, count));
+        self
+    }
+
+    /// Append a terminal resize
+    pub fn resize(self, width: u16, height: u16) -> Self {
+        self.event(Event::Resize { width, height })
+    }
+
+    /// The scripted events, in order
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+}
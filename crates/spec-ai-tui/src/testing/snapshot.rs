@@ -0,0 +1,109 @@
+//! Buffer assertions and snapshot files
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::buffer::Buffer;
+use crate::style::Style;
+
+/// Environment variable that makes [`assert_snapshot`] rewrite snapshots
+pub const UPDATE_SNAPSHOTS_ENV: &str = "SPEC_AI_UPDATE_SNAPSHOTS";
+
+/// Render a buffer's symbols as text, one string per row
+///
+/// Trailing spaces are trimmed and the cells covered by wide characters are
+/// skipped, so rows read the way they appear on screen.
+pub fn buffer_lines(buffer: &Buffer) -> Vec<String> {
+    let area = buffer.area();
+    (area.y..area.bottom())
+        .map(|y| {
+            let mut line = String::new();
+            let mut x = area.x;
+            while x < area.right() {
+                let symbol = buffer.get(x, y).map_or(" ", |c| c.symbol.as_str());
+                line.push_str(symbol);
+                x = x.saturating_add(symbol.width().max(1) as u16);
+            }
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Assert that a buffer's rows match `expected`
+///
+/// Rows missing from `expected` must be blank; trailing spaces are ignored.
+#[track_caller]
+pub fn assert_buffer_lines(buffer: &Buffer, expected: &[&str]) {
+    let actual = buffer_lines(buffer);
+    let mut expected: Vec<String> = expected.iter().map(|l| l.trim_end().to_string()).collect();
+    expected.resize(actual.len().max(expected.len()), String::new());
+
+    if actual != expected {
+        panic!(
+            "buffer does not match expected lines\n{}",
+            diff(&expected, &actual)
+        );
+    }
+}
+
+/// Assert the symbol and style of a single cell
+#[track_caller]
+pub fn assert_cell(buffer: &Buffer, x: u16, y: u16, symbol: &str, style: Style) {
+    let Some(cell) = buffer.get(x, y) else {
+        panic!("cell ({}, {}) is outside {:?}", x, y, buffer.area());
+    };
+    assert_eq!(cell.symbol, symbol, "symbol at ({}, {})", x, y);
+    assert_eq!(cell.get_style(), style, "style at ({}, {})", x, y);
+}
+
+/// Assert a buffer against a snapshot file
+///
+/// A missing snapshot is written and the assertion passes. Setting
+/// [`UPDATE_SNAPSHOTS_ENV`] rewrites existing snapshots instead of
+/// comparing. See also the [`assert_snapshot!`](crate::assert_snapshot) macro.
+#[track_caller]
+pub fn assert_snapshot(path: impl AsRef<Path>, buffer: &Buffer) {
+    let path = path.as_ref();
+    let actual = buffer_lines(buffer);
+    let update = std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some();
+
+    if update || !path.exists() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("create snapshot directory");
+        }
+        let mut contents = actual.join("\n");
+        contents.push('\n');
+        fs::write(path, contents).expect("write snapshot");
+        return;
+    }
+
+    let contents = fs::read_to_string(path).expect("read snapshot");
+    let expected: Vec<String> = contents.lines().map(str::to_string).collect();
+    if actual != expected {
+        panic!(
+            "snapshot {} does not match (set {}=1 to update)\n{}",
+            path.display(),
+            UPDATE_SNAPSHOTS_ENV,
+            diff(&expected, &actual)
+        );
+    }
+}
+
+/// Line-by-line comparison, marking rows that differ
+fn diff(expected: &[String], actual: &[String]) -> String {
+    let mut out = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        let e = expected.get(i).map_or("", String::as_str);
+        let a = actual.get(i).map_or("", String::as_str);
+        if e == a {
+            let _ = writeln!(out, "  {:>3} |{}", i, a);
+        } else {
+            let _ = writeln!(out, "- {:>3} |{}", i, e);
+            let _ = writeln!(out, "+ {:>3} |{}", i, a);
+        }
+    }
+    out
+}
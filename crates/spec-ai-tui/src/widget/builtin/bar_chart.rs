@@ -0,0 +1,330 @@
+//! Bar chart widget with horizontal and vertical layouts
+
+use crate::buffer::Buffer;
+use crate::geometry::Rect;
+use crate::style::{Color, GlyphSet, Style};
+use crate::widget::Widget;
+
+/// Orientation of the bars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BarDirection {
+    /// One bar per row, growing to the right
+    #[default]
+    Horizontal,
+    /// One bar per column group, growing upwards
+    Vertical,
+}
+
+/// A single labelled bar
+#[derive(Debug, Clone)]
+pub struct Bar {
+    /// Label shown beside (horizontal) or below (vertical) the bar
+    pub label: String,
+    /// Bar value
+    pub value: u64,
+    /// Style override for this bar
+    pub style: Option<Style>,
+}
+
+impl Bar {
+    /// Create a new bar
+    pub fn new(label: impl Into<String>, value: u64) -> Self {
+        Self {
+            label: label.into(),
+            value,
+            style: None,
+        }
+    }
+
+    /// Set a style for this bar only
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+}
+
+/// Bar chart with labels and values
+#[derive(Debug, Clone)]
+pub struct BarChart {
+    /// Bars in display order
+    bars: Vec<Bar>,
+    /// Orientation
+    direction: BarDirection,
+    /// Value mapped to the full bar length (defaults to the largest value)
+    max: Option<u64>,
+    /// Width of each bar in vertical mode
+    bar_width: u16,
+    /// Gap between bars in vertical mode
+    gap: u16,
+    /// Whether to print values next to (or above) the bars
+    show_values: bool,
+    /// Default bar style
+    bar_style: Style,
+    /// Style for labels
+    label_style: Style,
+    /// Style for values
+    value_style: Style,
+}
+
+impl Default for BarChart {
+    fn default() -> Self {
+        Self {
+            bars: Vec::new(),
+            direction: BarDirection::Horizontal,
+            max: None,
+            bar_width: 3,
+            gap: 1,
+            show_values: true,
+            bar_style: Style::new().fg(Color::Cyan),
+            label_style: Style::new().fg(Color::DarkGrey),
+            value_style: Style::new().fg(Color::White),
+        }
+    }
+}
+
+impl BarChart {
+    /// Create an empty horizontal bar chart
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the bars
+    pub fn bars<I: IntoIterator<Item = Bar>>(mut self, bars: I) -> Self {
+        self.bars = bars.into_iter().collect();
+        self
+    }
+
+    /// Set the orientation
+    pub fn direction(mut self, direction: BarDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the value mapped to the full bar length
+    pub fn max(mut self, max: u64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Set the width of each bar in vertical mode
+    pub fn bar_width(mut self, width: u16) -> Self {
+        self.bar_width = width.max(1);
+        self
+    }
+
+    /// Set the gap between bars in vertical mode
+    pub fn gap(mut self, gap: u16) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Show or hide values
+    pub fn show_values(mut self, show: bool) -> Self {
+        self.show_values = show;
+        self
+    }
+
+    /// Set the default bar style
+    pub fn bar_style(mut self, style: Style) -> Self {
+        self.bar_style = style;
+        self
+    }
+
+    /// Set the label style
+    pub fn label_style(mut self, style: Style) -> Self {
+        self.label_style = style;
+        self
+    }
+
+    /// Set the value style
+    pub fn value_style(mut self, style: Style) -> Self {
+        self.value_style = style;
+        self
+    }
+
+    fn max_value(&self) -> u64 {
+        self.max
+            .unwrap_or_else(|| self.bars.iter().map(|b| b.value).max().unwrap_or(0))
+    }
+
+    /// Length of a bar in eighths of a cell
+    fn scaled(value: u64, max: u64, cells: u16) -> u64 {
+        if max == 0 {
+            return 0;
+        }
+        let total = cells as u64 * 8;
+        (value.min(max) * total + max / 2) / max
+    }
+
+    fn render_horizontal(&self, area: Rect, buf: &mut Buffer) {
+        let max = self.max_value();
+        let label_width = self
+            .bars
+            .iter()
+            .map(|b| text_width(&b.label))
+            .max()
+            .unwrap_or(0);
+        let value_width = if self.show_values {
+            self.bars
+                .iter()
+                .map(|b| text_width(&b.value.to_string()))
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        let bar_x = area.x + label_width + u16::from(label_width > 0);
+        let bar_cells = area
+            .right()
+            .saturating_sub(bar_x)
+            .saturating_sub(value_width + u16::from(value_width > 0));
+
+        for (i, bar) in self.bars.iter().enumerate() {
+            let y = area.y + i as u16;
+            if y >= area.bottom() {
+                break;
+            }
+            buf.set_string(area.x, y, &bar.label, self.label_style);
+
+            let style = bar.style.unwrap_or(self.bar_style);
+            let eighths = Self::scaled(bar.value, max, bar_cells);
+            let full = (eighths / 8) as u16;
+            for offset in 0..full {
+                buf.set_string(bar_x + offset, y, "█", style);
+            }
+            let partial = (eighths % 8) as usize;
+            if partial > 0 {
+                buf.set_string(
+                    bar_x + full,
+                    y,
+                    &GlyphSet::current().left_block(partial).to_string(),
+                    style,
+                );
+            }
+
+            if self.show_values {
+                let end = bar_x + full + u16::from(partial > 0);
+                buf.set_string(end + 1, y, &bar.value.to_string(), self.value_style);
+            }
+        }
+    }
+
+    fn render_vertical(&self, area: Rect, buf: &mut Buffer) {
+        let max = self.max_value();
+        // Bottom row holds labels, the top row holds values when shown
+        let label_row = area.bottom() - 1;
+        let top = area.y + u16::from(self.show_values);
+        let bar_rows = label_row.saturating_sub(top);
+
+        for (i, bar) in self.bars.iter().enumerate() {
+            let x = area.x + i as u16 * (self.bar_width + self.gap);
+            if x >= area.right() {
+                break;
+            }
+            let width = self.bar_width.min(area.right() - x);
+            let style = bar.style.unwrap_or(self.bar_style);
+
+            let eighths = Self::scaled(bar.value, max, bar_rows);
+            let full = (eighths / 8) as u16;
+            let partial = (eighths % 8) as usize;
+            for col in x..x + width {
+                for row in 0..full {
+                    buf.set_string(col, label_row - 1 - row, "█", style);
+                }
+                if partial > 0 {
+                    buf.set_string(
+                        col,
+                        label_row - 1 - full,
+                        &GlyphSet::current().lower_block(partial).to_string(),
+                        style,
+                    );
+                }
+            }
+
+            let label: String = bar.label.chars().take(width as usize).collect();
+            buf.set_string(x, label_row, &label, self.label_style);
+
+            if self.show_values {
+                let value: String = bar.value.to_string().chars().take(width as usize).collect();
+                let value_y = label_row
+                    .saturating_sub(1 + full + u16::from(partial > 0))
+                    .max(area.y);
+                buf.set_string(x, value_y, &value, self.value_style);
+            }
+        }
+    }
+}
+
+fn text_width(s: &str) -> u16 {
+    unicode_width::UnicodeWidthStr::width(s) as u16
+}
+
+impl Widget for BarChart {
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() || self.bars.is_empty() {
+            return;
+        }
+
+        match self.direction {
+            BarDirection::Horizontal => self.render_horizontal(area, buf),
+            BarDirection::Vertical => self.render_vertical(area, buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(buf: &Buffer, y: u16, width: u16) -> String {
+        (0..width)
+            .map(|x| buf.get(x, y).unwrap().symbol.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_horizontal_bars_scale_and_show_values() {
+        let chart = BarChart::new().bars([Bar::new("a", 4), Bar::new("bb", 2)]);
+        let area = Rect::new(0, 0, 10, 2);
+        let mut buf = Buffer::new(area);
+        chart.render(area, &mut buf);
+
+        // Labels padded to 2 cells, one value column: 10 - 3 - 2 = 5 bar cells
+        assert_eq!(row(&buf, 0, 10), "a  █████ 4");
+        assert_eq!(row(&buf, 1, 10), "bb ██▌ 2  ");
+    }
+
+    #[test]
+    fn test_vertical_bars_grow_upwards() {
+        let chart = BarChart::new()
+            .direction(BarDirection::Vertical)
+            .bar_width(1)
+            .show_values(false)
+            .bars([Bar::new("x", 2), Bar::new("y", 1)]);
+        let area = Rect::new(0, 0, 3, 3);
+        let mut buf = Buffer::new(area);
+        chart.render(area, &mut buf);
+
+        assert_eq!(row(&buf, 0, 3), "█  ");
+        assert_eq!(row(&buf, 1, 3), "█ █");
+        assert_eq!(row(&buf, 2, 3), "x y");
+    }
+
+    #[test]
+    fn test_vertical_value_sits_above_bar() {
+        let chart = BarChart::new()
+            .direction(BarDirection::Vertical)
+            .bar_width(2)
+            .bars([Bar::new("ok", 1)])
+            .max(4);
+        let area = Rect::new(0, 0, 2, 6);
+        let mut buf = Buffer::new(area);
+        chart.render(area, &mut buf);
+
+        // Four bar rows, value 1/4 fills exactly one row
+        assert_eq!(row(&buf, 4, 2), "██");
+        assert_eq!(row(&buf, 3, 2), "1 ");
+        assert_eq!(row(&buf, 5, 2), "ok");
+    }
+}
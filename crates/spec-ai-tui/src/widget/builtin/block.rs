@@ -2,7 +2,7 @@
 
 use crate::buffer::Buffer;
 use crate::geometry::Rect;
-use crate::style::Style;
+use crate::style::{GlyphSet, Style};
 use crate::widget::Widget;
 
 /// Border type for blocks
@@ -23,8 +23,14 @@ pub enum BorderType {
 
 impl BorderType {
     /// Get the border characters for this type
-    fn chars(&self) -> BorderChars {
-        match self {
+    fn chars(&self, glyphs: GlyphSet) -> BorderChars {
+        // Rounded and heavy borders are missing from code page 437
+        let border_type = match (self, glyphs) {
+            (BorderType::Rounded, GlyphSet::Basic) => BorderType::Single,
+            (BorderType::Heavy, GlyphSet::Basic) => BorderType::Double,
+            (border_type, _) => *border_type,
+        };
+        match border_type {
             BorderType::None => BorderChars {
                 top: ' ',
                 bottom: ' ',
@@ -183,7 +189,7 @@ impl Widget for Block {
             return;
         }
 
-        let chars = self.border_type.chars();
+        let chars = self.border_type.chars(GlyphSet::current());
 
         // Draw corners
         buf.set_string(
@@ -304,4 +310,13 @@ mod tests {
         assert_eq!(buf.get(0, 4).unwrap().symbol, "└");
         assert_eq!(buf.get(9, 4).unwrap().symbol, "┘");
     }
+
+    #[test]
+    fn test_basic_glyphs_fall_back_to_cp437_borders() {
+        let rounded = BorderType::Rounded.chars(GlyphSet::Basic);
+        assert_eq!(rounded.top_left, '┌');
+        let heavy = BorderType::Heavy.chars(GlyphSet::Basic);
+        assert_eq!(heavy.top, '═');
+        assert_eq!(BorderType::Heavy.chars(GlyphSet::Unicode).top, '━');
+    }
 }
@@ -5,12 +5,14 @@
 //! - Clipboard operations (copy, cut, paste)
 //! - Undo/redo history
 //! - Word-level navigation and deletion
+//! - Input history recall (Up/Down) and reverse search (Ctrl+R)
 //! - Platform-aware modifier keys (Cmd on macOS, Ctrl elsewhere)
 
 use crate::buffer::Buffer;
 use crate::event::{Event, KeyCode, KeyModifiers};
 use crate::geometry::Rect;
 use crate::style::{Color, Style};
+use crate::widget::builtin::history::InputHistory;
 use crate::widget::StatefulWidget;
 use std::collections::VecDeque;
 
@@ -109,6 +111,12 @@ pub struct EditorState {
     pub show_slash_menu: bool,
     /// Slash command being typed (after /)
     pub slash_query: String,
+    /// Whether the file mention menu should be shown
+    pub show_mention_menu: bool,
+    /// Path being typed after @
+    pub mention_query: String,
+    /// Previously submitted inputs
+    pub history: InputHistory,
 }
 
 impl Default for EditorState {
@@ -131,6 +139,9 @@ impl EditorState {
             clipboard: String::new(),
             show_slash_menu: false,
             slash_query: String::new(),
+            show_mention_menu: false,
+            mention_query: String::new(),
+            history: InputHistory::new(),
         }
     }
 
@@ -253,7 +264,7 @@ impl EditorState {
             let end = self.selection.end();
             self.text.drain(start..end);
             self.selection = Selection::cursor(start);
-            self.update_slash_state();
+            self.update_menus();
         }
     }
 
@@ -275,7 +286,7 @@ impl EditorState {
         self.text.insert(pos, c);
         self.selection = Selection::cursor(pos + c.len_utf8());
 
-        self.update_slash_state();
+        self.update_menus();
     }
 
     /// Insert a string at cursor (replaces selection if any)
@@ -293,7 +304,7 @@ impl EditorState {
         self.text.insert_str(pos, s);
         self.selection = Selection::cursor(pos + s.len());
 
-        self.update_slash_state();
+        self.update_menus();
     }
 
     /// Delete character before cursor (backspace)
@@ -305,7 +316,7 @@ impl EditorState {
             let prev = self.prev_char_boundary(self.selection.cursor);
             self.text.drain(prev..self.selection.cursor);
             self.selection = Selection::cursor(prev);
-            self.update_slash_state();
+            self.update_menus();
         }
     }
 
@@ -317,7 +328,7 @@ impl EditorState {
             self.save_undo();
             let next = self.next_char_boundary(self.selection.cursor);
             self.text.drain(self.selection.cursor..next);
-            self.update_slash_state();
+            self.update_menus();
         }
     }
 
@@ -330,7 +341,7 @@ impl EditorState {
             let start = self.find_word_start(self.selection.cursor);
             self.text.drain(start..self.selection.cursor);
             self.selection = Selection::cursor(start);
-            self.update_slash_state();
+            self.update_menus();
         }
     }
 
@@ -342,7 +353,7 @@ impl EditorState {
             self.save_undo();
             let end = self.find_word_end(self.selection.cursor);
             self.text.drain(self.selection.cursor..end);
-            self.update_slash_state();
+            self.update_menus();
         }
     }
 
@@ -352,7 +363,7 @@ impl EditorState {
             self.save_undo();
             self.text.drain(0..self.selection.cursor);
             self.selection = Selection::cursor(0);
-            self.update_slash_state();
+            self.update_menus();
         }
     }
 
@@ -361,7 +372,7 @@ impl EditorState {
         if self.selection.cursor < self.text.len() {
             self.save_undo();
             self.text.drain(self.selection.cursor..);
-            self.update_slash_state();
+            self.update_menus();
         }
     }
 
@@ -372,7 +383,7 @@ impl EditorState {
             self.text.clear();
             self.selection = Selection::cursor(0);
             self.scroll = 0;
-            self.update_slash_state();
+            self.update_menus();
         }
     }
 
@@ -383,9 +394,43 @@ impl EditorState {
         self.scroll = 0;
         self.show_slash_menu = false;
         self.slash_query.clear();
+        self.close_mention_menu();
         text
     }
 
+    /// Replace the text with a recalled history entry, cursor at the end
+    fn recall(&mut self, text: String) {
+        self.save_undo();
+        self.selection = Selection::cursor(text.len());
+        self.text = text;
+        // Recalled slash commands should not reopen the menu mid-navigation
+        self.close_slash_menu();
+        self.close_mention_menu();
+    }
+
+    /// Recall the previous history entry matching the typed prefix
+    pub fn history_previous(&mut self) -> bool {
+        match self.history.older(&self.text) {
+            Some(entry) => {
+                let entry = entry.to_string();
+                self.recall(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Recall the next newer history entry, restoring the draft at the end
+    pub fn history_next(&mut self) -> bool {
+        match self.history.newer() {
+            Some(entry) => {
+                self.recall(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
     // ========== Movement ==========
 
     /// Move cursor left
@@ -458,6 +503,11 @@ impl EditorState {
 
     // ========== Slash Command ==========
 
+    fn update_menus(&mut self) {
+        self.update_slash_state();
+        self.update_mention_state();
+    }
+
     fn update_slash_state(&mut self) {
         // Find if we're in a slash command context
         if let Some(slash_pos) = self.text[..self.selection.cursor].rfind('/') {
@@ -483,6 +533,53 @@ impl EditorState {
         self.slash_query.clear();
     }
 
+    // ========== File Mentions ==========
+
+    /// Start of the `@` mention the cursor is in, if any
+    fn mention_start(&self) -> Option<usize> {
+        let before_cursor = &self.text[..self.selection.cursor];
+        let at_pos = before_cursor.rfind('@')?;
+        // Only at the start or after whitespace, so e-mail addresses don't trigger it
+        let before_at = &self.text[..at_pos];
+        let after_at = &before_cursor[at_pos + 1..];
+        if (before_at.is_empty() || before_at.ends_with(char::is_whitespace))
+            && !after_at.contains(char::is_whitespace)
+        {
+            Some(at_pos)
+        } else {
+            None
+        }
+    }
+
+    fn update_mention_state(&mut self) {
+        match self.mention_start() {
+            Some(at_pos) => {
+                self.show_mention_menu = true;
+                self.mention_query = self.text[at_pos + 1..self.selection.cursor].to_string();
+            }
+            None => self.close_mention_menu(),
+        }
+    }
+
+    /// Close the file mention menu
+    pub fn close_mention_menu(&mut self) {
+        self.show_mention_menu = false;
+        self.mention_query.clear();
+    }
+
+    /// Replace the `@` mention being typed with `@path` and a trailing space
+    pub fn complete_mention(&mut self, path: &str) {
+        let Some(at_pos) = self.mention_start() else {
+            return;
+        };
+        self.save_undo();
+        let completion = format!("@{} ", path);
+        self.text
+            .replace_range(at_pos..self.selection.cursor, &completion);
+        self.selection = Selection::cursor(at_pos + completion.len());
+        self.close_mention_menu();
+    }
+
     // ========== Helper Methods ==========
 
     fn prev_char_boundary(&self, pos: usize) -> usize {
@@ -586,11 +683,35 @@ impl EditorState {
 
     /// Handle a key event (internal)
     fn handle_key_inner(&mut self, key: &crate::event::KeyEvent) -> EditorAction {
+        if self.history.is_searching() {
+            return self.handle_search_key(key);
+        }
+
         let shift = key.modifiers.contains(KeyModifiers::SHIFT);
         let cmd = has_cmd_modifier(key.modifiers);
         let word = has_word_modifier(key.modifiers);
 
         match key.code {
+            // History recall (menu navigation takes precedence)
+            KeyCode::Up if !self.show_slash_menu && !self.show_mention_menu => {
+                if self.history_previous() {
+                    EditorAction::Handled
+                } else {
+                    EditorAction::Ignored
+                }
+            }
+            KeyCode::Down if !self.show_slash_menu && !self.show_mention_menu => {
+                if self.history_next() {
+                    EditorAction::Handled
+                } else {
+                    EditorAction::Ignored
+                }
+            }
+            KeyCode::Char('r') if cmd => {
+                self.history.start_search(&self.text);
+                EditorAction::Handled
+            }
+
             // Navigation
             KeyCode::Left => {
                 if word {
@@ -699,8 +820,11 @@ impl EditorState {
             KeyCode::Enter => {
                 if self.show_slash_menu {
                     EditorAction::SlashCommand(self.slash_query.clone())
+                } else if self.show_mention_menu {
+                    EditorAction::MentionSelect(self.mention_query.clone())
                 } else {
                     let text = self.take();
+                    self.history.push(text.clone());
                     EditorAction::Submit(text)
                 }
             }
@@ -710,6 +834,9 @@ impl EditorState {
                 if self.show_slash_menu {
                     self.close_slash_menu();
                     EditorAction::Handled
+                } else if self.show_mention_menu {
+                    self.close_mention_menu();
+                    EditorAction::Handled
                 } else if self.has_selection() {
                     self.collapse_selection();
                     EditorAction::Handled
@@ -722,6 +849,8 @@ impl EditorState {
             KeyCode::Tab => {
                 if self.show_slash_menu {
                     EditorAction::SlashMenuNext
+                } else if self.show_mention_menu {
+                    EditorAction::MentionMenuNext
                 } else {
                     EditorAction::Ignored
                 }
@@ -729,6 +858,8 @@ impl EditorState {
             KeyCode::BackTab => {
                 if self.show_slash_menu {
                     EditorAction::SlashMenuPrev
+                } else if self.show_mention_menu {
+                    EditorAction::MentionMenuPrev
                 } else {
                     EditorAction::Ignored
                 }
@@ -739,6 +870,54 @@ impl EditorState {
     }
 }
 
+impl EditorState {
+    /// Handle a key while reverse history search is active
+    fn handle_search_key(&mut self, key: &crate::event::KeyEvent) -> EditorAction {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('r') if ctrl => {
+                self.history.search_older();
+                EditorAction::Handled
+            }
+            KeyCode::Char('g') if ctrl => {
+                if let Some(original) = self.history.cancel_search() {
+                    self.recall(original);
+                }
+                EditorAction::Handled
+            }
+            KeyCode::Char(c) if !ctrl && !key.modifiers.contains(KeyModifiers::ALT) => {
+                self.history.search_push(c);
+                EditorAction::Handled
+            }
+            KeyCode::Backspace => {
+                self.history.search_backspace();
+                EditorAction::Handled
+            }
+            KeyCode::Esc => {
+                if let Some(original) = self.history.cancel_search() {
+                    self.recall(original);
+                }
+                EditorAction::Handled
+            }
+            KeyCode::Enter => {
+                if let Some(entry) = self.history.accept_search() {
+                    self.recall(entry);
+                }
+                let text = self.take();
+                self.history.push(text.clone());
+                EditorAction::Submit(text)
+            }
+            // Any other key accepts the match into the editor for further editing
+            _ => {
+                if let Some(entry) = self.history.accept_search() {
+                    self.recall(entry);
+                }
+                EditorAction::Handled
+            }
+        }
+    }
+}
+
 /// Result of handling a key in the editor
 #[derive(Debug, Clone, PartialEq)]
 pub enum EditorAction {
@@ -756,6 +935,12 @@ pub enum EditorAction {
     SlashMenuNext,
     /// Navigate to previous item in slash menu
     SlashMenuPrev,
+    /// User picked from the file mention menu; carries the typed query
+    MentionSelect(String),
+    /// Navigate to next item in the file mention menu
+    MentionMenuNext,
+    /// Navigate to previous item in the file mention menu
+    MentionMenuPrev,
 }
 
 /// Text editor widget
@@ -835,6 +1020,20 @@ impl StatefulWidget for Editor {
         let width = area.width as usize;
         let height = area.height as usize;
 
+        // Reverse history search replaces the text with a readline-style prompt
+        if let Some(query) = state.history.search_query() {
+            let prompt = format!("(reverse-i-search)`{}': ", query);
+            let prompt: String = prompt.chars().take(width).collect();
+            buf.set_string(area.x, area.y, &prompt, self.placeholder_style);
+            let end = area.x + unicode_width::UnicodeWidthStr::width(prompt.as_str()) as u16;
+            if let Some(matched) = state.history.search_match() {
+                let remaining = area.right().saturating_sub(end) as usize;
+                let display: String = matched.chars().take(remaining).collect();
+                buf.set_string(end, area.y, &display, self.style);
+            }
+            return;
+        }
+
         // Show placeholder if empty
         if state.text.is_empty() {
             if let Some(ref placeholder) = self.placeholder {
@@ -1054,4 +1253,83 @@ mod tests {
         state.insert(' ');
         assert!(!state.show_slash_menu);
     }
+
+    #[test]
+    fn test_mention_menu() {
+        let mut state = EditorState::with_value("mail me@host.com");
+        state.insert(' ');
+        assert!(!state.show_mention_menu);
+
+        state.insert_str("see @src/ma");
+        assert!(state.show_mention_menu);
+        assert_eq!(state.mention_query, "src/ma");
+        assert!(!state.show_slash_menu);
+
+        let action = state.handle_event(&key(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(action, EditorAction::MentionSelect("src/ma".to_string()));
+
+        state.complete_mention("src/main.rs");
+        assert_eq!(state.value(), "mail me@host.com see @src/main.rs ");
+        assert!(!state.show_mention_menu);
+
+        state.insert('@');
+        assert!(state.show_mention_menu);
+        state.handle_event(&key(KeyCode::Esc, KeyModifiers::NONE));
+        assert!(!state.show_mention_menu);
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(crate::event::KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn test_history_recall_with_up_and_down() {
+        let mut state = EditorState::new();
+        state.history.set_entries(["first", "/help", "second"]);
+
+        assert_eq!(
+            state.handle_event(&key(KeyCode::Up, KeyModifiers::NONE)),
+            EditorAction::Handled
+        );
+        assert_eq!(state.value(), "second");
+        state.handle_event(&key(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(state.value(), "/help");
+        assert!(!state.show_slash_menu);
+        state.handle_event(&key(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(state.value(), "first");
+        // Oldest entry reached: Up is left for the app to handle
+        assert_eq!(
+            state.handle_event(&key(KeyCode::Up, KeyModifiers::NONE)),
+            EditorAction::Ignored
+        );
+
+        state.handle_event(&key(KeyCode::Down, KeyModifiers::NONE));
+        state.handle_event(&key(KeyCode::Down, KeyModifiers::NONE));
+        state.handle_event(&key(KeyCode::Down, KeyModifiers::NONE));
+        assert_eq!(state.value(), "");
+    }
+
+    #[test]
+    fn test_submit_records_history() {
+        let mut state = EditorState::with_value("hello");
+        let action = state.handle_event(&key(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(action, EditorAction::Submit("hello".to_string()));
+        assert_eq!(state.history.entries(), &["hello".to_string()]);
+    }
+
+    #[test]
+    fn test_reverse_search_submits_match() {
+        let mut state = EditorState::new();
+        state.history.set_entries(["deploy staging", "run tests"]);
+
+        state.handle_event(&key(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        assert!(state.history.is_searching());
+        for c in "dep".chars() {
+            state.handle_event(&key(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        assert_eq!(state.history.search_match(), Some("deploy staging"));
+        let action = state.handle_event(&key(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(action, EditorAction::Submit("deploy staging".to_string()));
+        assert!(!state.history.is_searching());
+    }
 }
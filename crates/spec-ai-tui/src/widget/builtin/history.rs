@@ -0,0 +1,328 @@
+//! Input history for chat-style editors
+//!
+//! Tracks previous submissions and supports:
+//! - Up/Down recall filtered by the prefix typed before navigation started
+//! - Reverse incremental search (Ctrl+R) by substring, newest first
+//!
+//! Persistence is left to the application: seed the history with
+//! [`InputHistory::set_entries`] and store submissions as they happen.
+
+/// Default number of entries kept in memory
+const DEFAULT_MAX_ENTRIES: usize = 500;
+
+/// State of an in-progress reverse incremental search
+#[derive(Debug, Clone)]
+struct HistorySearch {
+    /// Substring being searched for
+    query: String,
+    /// Index of the current match, if any
+    matched: Option<usize>,
+    /// Editor text before the search started
+    original: String,
+}
+
+/// Ordered history of submitted inputs (oldest first)
+#[derive(Debug, Clone)]
+pub struct InputHistory {
+    entries: Vec<String>,
+    max_entries: usize,
+    /// Index of the recalled entry while navigating with Up/Down
+    position: Option<usize>,
+    /// Text being edited when navigation started, used as the prefix filter
+    draft: String,
+    search: Option<HistorySearch>,
+}
+
+impl Default for InputHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputHistory {
+    /// Create an empty history
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            position: None,
+            draft: String::new(),
+            search: None,
+        }
+    }
+
+    /// Set the maximum number of entries retained
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self.truncate();
+        self
+    }
+
+    /// Replace all entries (oldest first), e.g. when switching sessions
+    pub fn set_entries<I, S>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.entries.clear();
+        for entry in entries {
+            self.push(entry);
+        }
+        self.reset_navigation();
+        self.search = None;
+    }
+
+    /// All entries, oldest first
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the history is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record a submission. Blank input and repeats of the latest entry are skipped.
+    pub fn push<S: Into<String>>(&mut self, entry: S) {
+        let entry = entry.into();
+        self.reset_navigation();
+        if entry.trim().is_empty() || self.entries.last() == Some(&entry) {
+            return;
+        }
+        self.entries.push(entry);
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        if self.entries.len() > self.max_entries {
+            let excess = self.entries.len() - self.max_entries;
+            self.entries.drain(..excess);
+        }
+    }
+
+    // ========== Up/Down Navigation ==========
+
+    /// Check if an entry is currently recalled
+    pub fn is_navigating(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Stop navigating and forget the saved draft
+    pub fn reset_navigation(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    /// Recall the previous entry matching the draft prefix.
+    ///
+    /// `current` is the editor text; it becomes the draft when navigation starts.
+    /// Returns `None` when there is no older match.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let start = match self.position {
+            Some(pos) => pos,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len()
+            }
+        };
+
+        let found = self.entries[..start]
+            .iter()
+            .rposition(|entry| entry.starts_with(&self.draft))?;
+        self.position = Some(found);
+        Some(&self.entries[found])
+    }
+
+    /// Recall the next newer entry matching the draft prefix.
+    ///
+    /// Past the newest match the draft is restored and navigation ends.
+    /// Returns `None` when not navigating.
+    pub fn newer(&mut self) -> Option<String> {
+        let pos = self.position?;
+        let newer = self.entries[pos + 1..]
+            .iter()
+            .position(|entry| entry.starts_with(&self.draft))
+            .map(|offset| pos + 1 + offset);
+
+        match newer {
+            Some(found) => {
+                self.position = Some(found);
+                Some(self.entries[found].clone())
+            }
+            None => {
+                let draft = std::mem::take(&mut self.draft);
+                self.position = None;
+                Some(draft)
+            }
+        }
+    }
+
+    // ========== Reverse Incremental Search ==========
+
+    /// Begin a reverse search, remembering the current editor text
+    pub fn start_search(&mut self, current: &str) {
+        self.reset_navigation();
+        self.search = Some(HistorySearch {
+            query: String::new(),
+            matched: None,
+            original: current.to_string(),
+        });
+    }
+
+    /// Check if a reverse search is active
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    /// The active search query
+    pub fn search_query(&self) -> Option<&str> {
+        self.search.as_ref().map(|s| s.query.as_str())
+    }
+
+    /// The entry matching the active search
+    pub fn search_match(&self) -> Option<&str> {
+        let idx = self.search.as_ref()?.matched?;
+        self.entries.get(idx).map(String::as_str)
+    }
+
+    /// Extend the search query and re-match from the current match
+    pub fn search_push(&mut self, c: char) {
+        if let Some(search) = self.search.as_mut() {
+            search.query.push(c);
+            let from = search.matched.map(|m| m + 1).unwrap_or(self.entries.len());
+            search.matched = find_older(&self.entries, &search.query, from);
+        }
+    }
+
+    /// Remove the last query character and re-match from the newest entry
+    pub fn search_backspace(&mut self) {
+        if let Some(search) = self.search.as_mut() {
+            search.query.pop();
+            search.matched = if search.query.is_empty() {
+                None
+            } else {
+                find_older(&self.entries, &search.query, self.entries.len())
+            };
+        }
+    }
+
+    /// Jump to the next older match for the same query
+    pub fn search_older(&mut self) {
+        if let Some(search) = self.search.as_mut() {
+            if search.query.is_empty() {
+                return;
+            }
+            let from = search.matched.unwrap_or(self.entries.len());
+            if let Some(found) = find_older(&self.entries, &search.query, from) {
+                search.matched = Some(found);
+            }
+        }
+    }
+
+    /// End the search, returning the matched entry (or the original text without a match)
+    pub fn accept_search(&mut self) -> Option<String> {
+        let search = self.search.take()?;
+        Some(
+            search
+                .matched
+                .and_then(|idx| self.entries.get(idx).cloned())
+                .unwrap_or(search.original),
+        )
+    }
+
+    /// Abort the search, returning the text from before it started
+    pub fn cancel_search(&mut self) -> Option<String> {
+        self.search.take().map(|s| s.original)
+    }
+}
+
+/// Find the newest entry before `before` that contains `query`
+fn find_older(entries: &[String], query: &str, before: usize) -> Option<usize> {
+    if query.is_empty() {
+        return None;
+    }
+    entries[..before.min(entries.len())]
+        .iter()
+        .rposition(|entry| entry.contains(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> InputHistory {
+        let mut history = InputHistory::new();
+        history.set_entries(["git status", "ls -la", "git commit", "echo hi"]);
+        history
+    }
+
+    #[test]
+    fn test_push_skips_blank_and_repeated_entries() {
+        let mut history = InputHistory::new();
+        history.push("one");
+        history.push("one");
+        history.push("   ");
+        history.push("two");
+        assert_eq!(history.entries(), &["one".to_string(), "two".to_string()]);
+    }
+
+    #[test]
+    fn test_max_entries_drops_oldest() {
+        let mut history = InputHistory::new().max_entries(2);
+        history.push("a");
+        history.push("b");
+        history.push("c");
+        assert_eq!(history.entries(), &["b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn test_older_and_newer_cycle_and_restore_draft() {
+        let mut history = history();
+        assert_eq!(history.older(""), Some("echo hi"));
+        assert_eq!(history.older("echo hi"), Some("git commit"));
+        assert_eq!(history.newer(), Some("echo hi".to_string()));
+        assert_eq!(history.newer(), Some(String::new()));
+        assert!(!history.is_navigating());
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn test_older_filters_by_prefix() {
+        let mut history = history();
+        assert_eq!(history.older("git"), Some("git commit"));
+        assert_eq!(history.older("git commit"), Some("git status"));
+        assert_eq!(history.older("git status"), None);
+        assert_eq!(history.newer(), Some("git commit".to_string()));
+        assert_eq!(history.newer(), Some("git".to_string()));
+    }
+
+    #[test]
+    fn test_reverse_search() {
+        let mut history = history();
+        history.start_search("draft");
+        assert!(history.is_searching());
+        history.search_push('g');
+        history.search_push('i');
+        assert_eq!(history.search_match(), Some("git commit"));
+        history.search_older();
+        assert_eq!(history.search_match(), Some("git status"));
+        history.search_older();
+        assert_eq!(history.search_match(), Some("git status"));
+        assert_eq!(history.accept_search(), Some("git status".to_string()));
+        assert!(!history.is_searching());
+    }
+
+    #[test]
+    fn test_cancel_search_restores_original() {
+        let mut history = history();
+        history.start_search("draft");
+        history.search_push('z');
+        assert_eq!(history.search_match(), None);
+        assert_eq!(history.cancel_search(), Some("draft".to_string()));
+    }
+}
@@ -0,0 +1,183 @@
+//! Image widget using terminal graphics protocols
+
+use crate::buffer::{Buffer, Cell};
+use crate::geometry::Rect;
+use crate::graphics::{encode, fit_cells, GraphicsProtocol, ImageData, DEFAULT_CELL_SIZE};
+use crate::style::{Color, Style};
+use crate::widget::Widget;
+
+/// An inline image, scaled to fit its area with the aspect ratio preserved
+///
+/// With a graphics protocol the image is emitted as an escape sequence after
+/// the cells are drawn. Without one, RGBA images are drawn with half blocks
+/// and other formats show their alt text.
+#[derive(Debug, Clone)]
+pub struct Image<'a> {
+    /// Image to display
+    data: &'a ImageData,
+    /// Protocol used to emit the image
+    protocol: GraphicsProtocol,
+    /// Pixel size of a terminal cell (width, height)
+    cell_size: (u16, u16),
+    /// Text shown when the image cannot be displayed
+    alt: String,
+    /// Style for the alt text
+    alt_style: Style,
+}
+
+impl<'a> Image<'a> {
+    /// Create an image widget drawn with half blocks
+    pub fn new(data: &'a ImageData) -> Self {
+        Self {
+            data,
+            protocol: GraphicsProtocol::None,
+            cell_size: DEFAULT_CELL_SIZE,
+            alt: "image".to_string(),
+            alt_style: Style::new().fg(Color::DarkGrey),
+        }
+    }
+
+    /// Set the graphics protocol (see [`GraphicsProtocol::detect`])
+    pub fn protocol(mut self, protocol: GraphicsProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Set the pixel size of a cell (see `Terminal::cell_pixel_size`)
+    pub fn cell_size(mut self, width: u16, height: u16) -> Self {
+        self.cell_size = (width.max(1), height.max(1));
+        self
+    }
+
+    /// Set the alt text
+    pub fn alt(mut self, alt: impl Into<String>) -> Self {
+        self.alt = alt.into();
+        self
+    }
+
+    /// Set the alt text style
+    pub fn alt_style(mut self, style: Style) -> Self {
+        self.alt_style = style;
+        self
+    }
+
+    /// Draw RGBA pixels two per cell using the upper half block
+    fn render_half_blocks(&self, area: Rect, buf: &mut Buffer) -> bool {
+        // Each cell holds two square pixels stacked vertically
+        let (cols, rows) = fit_cells(self.data.dimensions(), area.width, area.height, (1, 2));
+        let (width, height) = self.data.dimensions();
+        let color = |x: u16, y: u32| {
+            let px = x as u32 * width / cols as u32;
+            let py = y * height / (rows as u32 * 2);
+            match self.data.pixel(px, py) {
+                Some([r, g, b, a]) if a >= 128 => Some(Color::Rgb(r, g, b)),
+                Some(_) => Some(Color::Reset),
+                None => None,
+            }
+        };
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let (Some(top), Some(bottom)) =
+                    (color(col, row as u32 * 2), color(col, row as u32 * 2 + 1))
+                else {
+                    return false;
+                };
+                buf.set(
+                    area.x + col,
+                    area.y + row,
+                    Cell::new("▀").fg(top).bg(bottom),
+                );
+            }
+        }
+        true
+    }
+}
+
+impl Widget for Image<'_> {
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+
+        let (cols, rows) = fit_cells(
+            self.data.dimensions(),
+            area.width,
+            area.height,
+            self.cell_size,
+        );
+        if self.protocol.is_supported() {
+            if let Some(sequence) = encode(self.data, self.protocol, cols, rows, self.cell_size) {
+                let target = Rect::new(area.x, area.y, cols, rows);
+                buf.clear_area(target);
+                buf.place_image(target, sequence);
+                return;
+            }
+        }
+
+        if !self.render_half_blocks(area, buf) {
+            let (width, height) = self.data.dimensions();
+            let alt = format!("[{} {}x{}]", self.alt, width, height);
+            let alt: String = alt.chars().take(area.width as usize).collect();
+            buf.set_string(area.x, area.y, &alt, self.alt_style);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_tone() -> ImageData {
+        // 1x2 image: red over blue
+        ImageData::rgba(1, 2, vec![255, 0, 0, 255, 0, 0, 255, 255]).unwrap()
+    }
+
+    #[test]
+    fn test_half_block_fallback() {
+        let data = two_tone();
+        let area = Rect::new(0, 0, 1, 4);
+        let mut buf = Buffer::new(area);
+        Image::new(&data).render(area, &mut buf);
+
+        let cell = buf.get(0, 0).unwrap();
+        assert_eq!(cell.symbol, "▀");
+        assert_eq!(cell.fg, Color::Rgb(255, 0, 0));
+        assert_eq!(cell.bg, Color::Rgb(0, 0, 255));
+        assert!(buf.images().is_empty());
+    }
+
+    #[test]
+    fn test_protocol_places_image() {
+        let data = two_tone();
+        let area = Rect::new(0, 0, 4, 4);
+        let mut buf = Buffer::new(area);
+        buf.set_string(0, 0, "xx", Style::default());
+        Image::new(&data)
+            .protocol(GraphicsProtocol::Kitty)
+            .render(area, &mut buf);
+
+        let images = buf.images();
+        assert_eq!(images.len(), 1);
+        assert!(images[0].sequence.starts_with("\x1b_G"));
+        assert_eq!(buf.get(0, 0).unwrap().symbol, " ");
+    }
+
+    #[test]
+    fn test_alt_text_when_undisplayable() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 8, 0, 0, 0, 4]);
+        let data = ImageData::png(png).unwrap();
+        let area = Rect::new(0, 0, 20, 2);
+        let mut buf = Buffer::new(area);
+        Image::new(&data)
+            .protocol(GraphicsProtocol::Sixel)
+            .alt("graph")
+            .render(area, &mut buf);
+
+        let text: String = (0..15)
+            .map(|x| buf.get(x, 0).unwrap().symbol.clone())
+            .collect();
+        assert_eq!(text, "[graph 8x4]    ");
+    }
+}
@@ -1,17 +1,27 @@
 //! Built-in widgets
 
+mod bar_chart;
 mod block;
 mod editor;
+mod history;
+mod image;
 mod input;
 mod overlay;
 mod paragraph;
+mod progress;
 mod slash_menu;
+mod sparkline;
 mod status;
 
+pub use bar_chart::{Bar, BarChart, BarDirection};
 pub use block::{Block, BorderType};
 pub use editor::{Editor, EditorAction, EditorState, Selection};
+pub use history::InputHistory;
+pub use image::Image;
 pub use input::{Input, InputState};
 pub use overlay::Overlay;
 pub use paragraph::{Alignment, Paragraph, Wrap};
+pub use progress::{spinner_frame, Gauge, ProgressBar, SPINNER_FRAMES};
 pub use slash_menu::{SlashCommand, SlashMenu, SlashMenuState};
+pub use sparkline::Sparkline;
 pub use status::{StatusBar, StatusSection};
@@ -0,0 +1,470 @@
+//! Progress bar and gauge widgets
+//!
+//! Both widgets have two modes:
+//! - Determinate: a ratio between 0.0 and 1.0, rendered with a percentage
+//! - Indeterminate: driven by a tick counter, rendered with a spinner
+
+use crate::buffer::Buffer;
+use crate::geometry::Rect;
+use crate::style::{Color, GlyphSet, Style};
+use crate::widget::Widget;
+
+/// Braille spinner frames used for indeterminate progress
+pub const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Spinner frame for the given tick
+///
+/// Uses [`SPINNER_FRAMES`] unless the current [`GlyphSet`] lacks braille.
+pub fn spinner_frame(tick: u64) -> char {
+    let frames = GlyphSet::current().spinner();
+    frames[(tick % frames.len() as u64) as usize]
+}
+
+/// Clamp a ratio into 0.0..=1.0, treating NaN and infinities as zero
+fn clamp_ratio(ratio: f64) -> f64 {
+    if ratio.is_finite() {
+        ratio.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Ratio formatted as a whole percentage
+fn percent_label(ratio: f64) -> String {
+    format!("{}%", (ratio * 100.0).round() as u16)
+}
+
+fn text_width(s: &str) -> u16 {
+    unicode_width::UnicodeWidthStr::width(s) as u16
+}
+
+/// Single-line progress bar: `label ████▌░░░░ 42% 1.2 MB/s`
+///
+/// Without a ratio the bar is indeterminate and renders `⠹ label 1.2 MB/s`.
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    /// Completion ratio (None for indeterminate)
+    ratio: Option<f64>,
+    /// Text shown before the bar
+    label: Option<String>,
+    /// Throughput or rate text shown after the percentage
+    throughput: Option<String>,
+    /// Whether to show the percentage in determinate mode
+    show_percent: bool,
+    /// Animation tick for the spinner
+    tick: u64,
+    /// Style for the filled portion and spinner
+    filled_style: Style,
+    /// Style for the unfilled portion
+    empty_style: Style,
+    /// Style for the label, percentage and throughput
+    label_style: Style,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self {
+            ratio: None,
+            label: None,
+            throughput: None,
+            show_percent: true,
+            tick: 0,
+            filled_style: Style::new().fg(Color::Cyan),
+            empty_style: Style::new().fg(Color::DarkGrey),
+            label_style: Style::new().fg(Color::White),
+        }
+    }
+}
+
+impl ProgressBar {
+    /// Create an indeterminate progress bar
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the completion ratio (0.0 to 1.0), switching to determinate mode
+    pub fn ratio(mut self, ratio: f64) -> Self {
+        self.ratio = Some(clamp_ratio(ratio));
+        self
+    }
+
+    /// Set progress from completed and total counts; a zero total is indeterminate
+    pub fn progress(mut self, done: u64, total: u64) -> Self {
+        self.ratio = if total == 0 {
+            None
+        } else {
+            Some(clamp_ratio(done as f64 / total as f64))
+        };
+        self
+    }
+
+    /// Switch to indeterminate mode
+    pub fn indeterminate(mut self) -> Self {
+        self.ratio = None;
+        self
+    }
+
+    /// Set the label shown before the bar
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the throughput text (e.g. "1.2 MB/s")
+    pub fn throughput(mut self, throughput: impl Into<String>) -> Self {
+        self.throughput = Some(throughput.into());
+        self
+    }
+
+    /// Show or hide the percentage
+    pub fn show_percent(mut self, show: bool) -> Self {
+        self.show_percent = show;
+        self
+    }
+
+    /// Set the animation tick for the spinner
+    pub fn tick(mut self, tick: u64) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Set the style for the filled portion and spinner
+    pub fn filled_style(mut self, style: Style) -> Self {
+        self.filled_style = style;
+        self
+    }
+
+    /// Set the style for the unfilled portion
+    pub fn empty_style(mut self, style: Style) -> Self {
+        self.empty_style = style;
+        self
+    }
+
+    /// Set the style for the label, percentage and throughput
+    pub fn label_style(mut self, style: Style) -> Self {
+        self.label_style = style;
+        self
+    }
+
+    /// Check if the bar is in indeterminate mode
+    pub fn is_indeterminate(&self) -> bool {
+        self.ratio.is_none()
+    }
+
+    fn suffix(&self, ratio: f64) -> String {
+        let mut parts = Vec::new();
+        if self.show_percent {
+            parts.push(percent_label(ratio));
+        }
+        if let Some(throughput) = &self.throughput {
+            parts.push(throughput.clone());
+        }
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", parts.join(" "))
+        }
+    }
+
+    fn render_indeterminate(&self, area: Rect, buf: &mut Buffer) {
+        let mut x = area.x;
+        buf.set_string(
+            x,
+            area.y,
+            &spinner_frame(self.tick).to_string(),
+            self.filled_style,
+        );
+        x = x.saturating_add(2);
+
+        for text in [&self.label, &self.throughput].into_iter().flatten() {
+            if x >= area.right() {
+                break;
+            }
+            buf.set_string(x, area.y, text, self.label_style);
+            x = x.saturating_add(text_width(text) + 1);
+        }
+    }
+
+    fn render_bar(&self, ratio: f64, x: u16, y: u16, width: u16, buf: &mut Buffer) {
+        let eighths = (ratio * width as f64 * 8.0).round() as usize;
+        let full = eighths / 8;
+        let partial = eighths % 8;
+        let glyphs = GlyphSet::current();
+
+        for i in 0..width as usize {
+            let (symbol, style) = if i < full {
+                ('█', self.filled_style)
+            } else if i == full && partial > 0 {
+                (glyphs.left_block(partial), self.filled_style)
+            } else {
+                ('░', self.empty_style)
+            };
+            buf.set_string(x + i as u16, y, &symbol.to_string(), style);
+        }
+    }
+}
+
+impl Widget for ProgressBar {
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+
+        let Some(ratio) = self.ratio else {
+            self.render_indeterminate(area, buf);
+            return;
+        };
+
+        let mut x = area.x;
+        if let Some(label) = &self.label {
+            buf.set_string(x, area.y, label, self.label_style);
+            x = x.saturating_add(text_width(label) + 1);
+        }
+
+        let suffix = self.suffix(ratio);
+        let bar_width = area
+            .right()
+            .saturating_sub(x)
+            .saturating_sub(text_width(&suffix));
+        if bar_width > 0 {
+            self.render_bar(ratio, x, area.y, bar_width, buf);
+        }
+        buf.set_string(x + bar_width, area.y, &suffix, self.label_style);
+    }
+}
+
+/// Block gauge filling its whole area, with a centered label
+///
+/// Without a ratio the gauge is indeterminate: a segment bounces across the
+/// area and the label is prefixed with a spinner.
+#[derive(Debug, Clone)]
+pub struct Gauge {
+    /// Completion ratio (None for indeterminate)
+    ratio: Option<f64>,
+    /// Label shown alongside the percentage
+    label: Option<String>,
+    /// Animation tick for indeterminate mode
+    tick: u64,
+    /// Background color of the filled portion
+    fill_color: Color,
+    /// Background color of the unfilled portion
+    empty_color: Color,
+    /// Style for the centered label
+    label_style: Style,
+}
+
+impl Default for Gauge {
+    fn default() -> Self {
+        Self {
+            ratio: None,
+            label: None,
+            tick: 0,
+            fill_color: Color::Cyan,
+            empty_color: Color::DarkGrey,
+            label_style: Style::new().fg(Color::White).bold(),
+        }
+    }
+}
+
+impl Gauge {
+    /// Create an indeterminate gauge
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the completion ratio (0.0 to 1.0), switching to determinate mode
+    pub fn ratio(mut self, ratio: f64) -> Self {
+        self.ratio = Some(clamp_ratio(ratio));
+        self
+    }
+
+    /// Set the completion as a percentage (0 to 100)
+    pub fn percent(mut self, percent: u16) -> Self {
+        self.ratio = Some(clamp_ratio(percent as f64 / 100.0));
+        self
+    }
+
+    /// Switch to indeterminate mode
+    pub fn indeterminate(mut self) -> Self {
+        self.ratio = None;
+        self
+    }
+
+    /// Set the label shown with the percentage
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the animation tick for indeterminate mode
+    pub fn tick(mut self, tick: u64) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Set the fill color
+    pub fn fill_color(mut self, color: Color) -> Self {
+        self.fill_color = color;
+        self
+    }
+
+    /// Set the unfilled color
+    pub fn empty_color(mut self, color: Color) -> Self {
+        self.empty_color = color;
+        self
+    }
+
+    /// Set the label style
+    pub fn label_style(mut self, style: Style) -> Self {
+        self.label_style = style;
+        self
+    }
+
+    /// Text rendered in the middle of the gauge
+    fn display_label(&self) -> String {
+        match (self.ratio, &self.label) {
+            (Some(ratio), Some(label)) => format!("{} {}", label, percent_label(ratio)),
+            (Some(ratio), None) => percent_label(ratio),
+            (None, Some(label)) => format!("{} {}", spinner_frame(self.tick), label),
+            (None, None) => spinner_frame(self.tick).to_string(),
+        }
+    }
+
+    /// Filled column range relative to the area's left edge
+    fn filled_columns(&self, width: u16) -> (u16, u16) {
+        match self.ratio {
+            Some(ratio) => (0, (ratio * width as f64).round() as u16),
+            None => {
+                // Bounce a quarter-width segment back and forth
+                let segment = (width / 4).max(1);
+                let travel = width.saturating_sub(segment) as u64;
+                if travel == 0 {
+                    return (0, width);
+                }
+                let step = self.tick % (travel * 2);
+                let start = if step <= travel {
+                    step
+                } else {
+                    travel * 2 - step
+                };
+                (start as u16, start as u16 + segment)
+            }
+        }
+    }
+}
+
+impl Widget for Gauge {
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+
+        let (fill_start, fill_end) = self.filled_columns(area.width);
+        for y in area.y..area.bottom() {
+            for offset in 0..area.width {
+                if let Some(cell) = buf.get_mut(area.x + offset, y) {
+                    cell.symbol = " ".to_string();
+                    cell.bg = if offset >= fill_start && offset < fill_end {
+                        self.fill_color
+                    } else {
+                        self.empty_color
+                    };
+                }
+            }
+        }
+
+        // Label keeps the underlying background so it reads across the fill edge
+        let label = self.display_label();
+        let label_x = area.x + area.width.saturating_sub(text_width(&label)) / 2;
+        let label_y = area.y + area.height / 2;
+        let mut x = label_x;
+        for c in label.chars() {
+            if x >= area.right() {
+                break;
+            }
+            if let Some(cell) = buf.get_mut(x, label_y) {
+                cell.symbol = c.to_string();
+                cell.fg = self.label_style.fg;
+                cell.modifier = self.label_style.modifier;
+            }
+            x = x.saturating_add(unicode_width::UnicodeWidthChar::width(c).unwrap_or(1) as u16);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(buf: &Buffer, y: u16, width: u16) -> String {
+        (0..width)
+            .map(|x| buf.get(x, y).unwrap().symbol.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_spinner_frame_wraps() {
+        assert_eq!(spinner_frame(0), '⠋');
+        assert_eq!(spinner_frame(SPINNER_FRAMES.len() as u64 + 1), '⠙');
+    }
+
+    #[test]
+    fn test_progress_bar_determinate() {
+        let bar = ProgressBar::new()
+            .label("dl")
+            .ratio(0.5)
+            .throughput("2 MB/s");
+        let area = Rect::new(0, 0, 20, 1);
+        let mut buf = Buffer::new(area);
+        bar.render(area, &mut buf);
+
+        // "dl " + 6-cell bar + " 50% 2 MB/s"
+        assert_eq!(row(&buf, 0, 20), "dl ███░░░ 50% 2 MB/s");
+    }
+
+    #[test]
+    fn test_progress_bar_partial_block() {
+        let bar = ProgressBar::new().ratio(0.3).show_percent(false);
+        let area = Rect::new(0, 0, 5, 1);
+        let mut buf = Buffer::new(area);
+        bar.render(area, &mut buf);
+
+        // 0.3 * 5 cells = 1.5 cells -> one full block and a half block
+        assert_eq!(row(&buf, 0, 5), "█▌░░░");
+    }
+
+    #[test]
+    fn test_progress_bar_indeterminate_and_counts() {
+        let bar = ProgressBar::new().label("syncing").tick(1);
+        assert!(bar.is_indeterminate());
+        let area = Rect::new(0, 0, 12, 1);
+        let mut buf = Buffer::new(area);
+        bar.render(area, &mut buf);
+        assert_eq!(row(&buf, 0, 10), "⠙ syncing ");
+
+        assert!(ProgressBar::new().progress(1, 0).is_indeterminate());
+        assert!(!ProgressBar::new().progress(3, 4).is_indeterminate());
+    }
+
+    #[test]
+    fn test_gauge_fill_and_label() {
+        let gauge = Gauge::new().percent(50);
+        let area = Rect::new(0, 0, 10, 3);
+        let mut buf = Buffer::new(area);
+        gauge.render(area, &mut buf);
+
+        assert_eq!(buf.get(0, 0).unwrap().bg, Color::Cyan);
+        assert_eq!(buf.get(4, 0).unwrap().bg, Color::Cyan);
+        assert_eq!(buf.get(5, 0).unwrap().bg, Color::DarkGrey);
+        // "50%" centered on the middle row
+        assert_eq!(row(&buf, 1, 10), "   50%    ");
+    }
+
+    #[test]
+    fn test_gauge_indeterminate_segment_bounces() {
+        let gauge = Gauge::new();
+        assert_eq!(gauge.clone().tick(0).filled_columns(8), (0, 2));
+        assert_eq!(gauge.clone().tick(6).filled_columns(8), (6, 8));
+        assert_eq!(gauge.tick(8).filled_columns(8), (4, 6));
+    }
+}
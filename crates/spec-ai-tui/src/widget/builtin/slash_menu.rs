@@ -113,6 +113,8 @@ pub struct SlashMenu {
     shortcut_style: Style,
     /// Maximum visible items
     max_visible: usize,
+    /// Trigger shown before each item name
+    prefix: String,
 }
 
 impl Default for SlashMenu {
@@ -134,6 +136,7 @@ impl SlashMenu {
             desc_style: Style::new().fg(Color::Grey),
             shortcut_style: Style::new().fg(Color::DarkGrey),
             max_visible: 8,
+            prefix: "/".to_string(),
         }
     }
 
@@ -173,6 +176,12 @@ impl SlashMenu {
         self
     }
 
+    /// Set the trigger shown before each item name (defaults to "/")
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
     /// Get filtered commands based on query
     pub fn filtered_commands(&self) -> Vec<&SlashCommand> {
         self.commands
@@ -311,7 +320,7 @@ impl StatefulWidget for SlashMenu {
             }
 
             // Draw command name
-            let name = format!("/{}", cmd.name);
+            let name = format!("{}{}", self.prefix, cmd.name);
             let name_style = if is_selected {
                 self.selected_style
             } else {
@@ -0,0 +1,132 @@
+//! Sparkline widget for compact time series
+
+use crate::buffer::Buffer;
+use crate::geometry::Rect;
+use crate::style::{Color, GlyphSet, Style};
+use crate::widget::Widget;
+
+/// A sparkline plotting one value per column
+///
+/// When there are more values than columns, the most recent values are shown.
+/// Taller areas stack blocks to give eight levels of resolution per row.
+#[derive(Debug, Clone)]
+pub struct Sparkline {
+    /// Data points, oldest first
+    data: Vec<u64>,
+    /// Value mapped to the full height (defaults to the visible maximum)
+    max: Option<u64>,
+    /// Style for the bars
+    style: Style,
+}
+
+impl Default for Sparkline {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            max: None,
+            style: Style::new().fg(Color::Cyan),
+        }
+    }
+}
+
+impl Sparkline {
+    /// Create an empty sparkline
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the data points (oldest first)
+    pub fn data<I: IntoIterator<Item = u64>>(mut self, data: I) -> Self {
+        self.data = data.into_iter().collect();
+        self
+    }
+
+    /// Set the value mapped to the full height
+    pub fn max(mut self, max: u64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Set the bar style
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Values that fit in the given width, most recent last
+    fn visible(&self, width: u16) -> &[u64] {
+        let start = self.data.len().saturating_sub(width as usize);
+        &self.data[start..]
+    }
+}
+
+impl Widget for Sparkline {
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+
+        let visible = self.visible(area.width);
+        let max = self
+            .max
+            .unwrap_or_else(|| visible.iter().copied().max().unwrap_or(0));
+        if max == 0 {
+            return;
+        }
+
+        let glyphs = GlyphSet::current();
+        let total_levels = area.height as u64 * 8;
+        for (i, &value) in visible.iter().enumerate() {
+            let x = area.x + i as u16;
+            let mut remaining = (value.min(max) * total_levels + max / 2) / max;
+            // Fill from the bottom row upwards
+            for row in (area.y..area.bottom()).rev() {
+                if remaining == 0 {
+                    break;
+                }
+                let level = remaining.min(8) as usize;
+                buf.set_string(x, row, &glyphs.lower_block(level).to_string(), self.style);
+                remaining -= level as u64;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(buf: &Buffer, y: u16, width: u16) -> String {
+        (0..width)
+            .map(|x| buf.get(x, y).unwrap().symbol.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_sparkline_scales_to_max() {
+        let spark = Sparkline::new().data([0, 1, 2, 4, 8]);
+        let area = Rect::new(0, 0, 5, 1);
+        let mut buf = Buffer::new(area);
+        spark.render(area, &mut buf);
+        assert_eq!(row(&buf, 0, 5), " ▁▂▄█");
+    }
+
+    #[test]
+    fn test_sparkline_shows_most_recent_values() {
+        let spark = Sparkline::new().data([8, 8, 8, 0, 8]).max(8);
+        let area = Rect::new(0, 0, 2, 1);
+        let mut buf = Buffer::new(area);
+        spark.render(area, &mut buf);
+        assert_eq!(row(&buf, 0, 2), " █");
+    }
+
+    #[test]
+    fn test_sparkline_stacks_rows() {
+        let spark = Sparkline::new().data([12]).max(16);
+        let area = Rect::new(0, 0, 1, 2);
+        let mut buf = Buffer::new(area);
+        spark.render(area, &mut buf);
+        assert_eq!(buf.get(0, 1).unwrap().symbol, "█");
+        assert_eq!(buf.get(0, 0).unwrap().symbol, "▄");
+    }
+}
@@ -1,8 +1,12 @@
-use anyhow::Result;
-
 #[cfg(feature = "cli")]
-fn main() -> Result<()> {
-    spec_ai_cli::run()
+fn main() -> std::process::ExitCode {
+    match spec_ai_cli::run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            spec_ai_cli::exit_code(&err)
+        }
+    }
 }
 
 #[cfg(not(feature = "cli"))]
@@ -128,3 +128,115 @@ fn policy_upsert_and_get() {
     let got2 = p.policy_get("policies").unwrap().expect("exists");
     assert_eq!(got2.value, v2);
 }
+
+#[test]
+fn usage_records_aggregate_by_day_provider_and_model() {
+    let path = temp_db_path();
+    let p = Persistence::new(&path).unwrap();
+
+    p.record_usage(
+        "s1",
+        Some("coder"),
+        "run-1",
+        "openai",
+        "gpt-4.1",
+        100,
+        50,
+        150,
+    )
+    .unwrap();
+    p.record_usage("s2", None, "run-2", "openai", "gpt-4.1", 200, 25, 225)
+        .unwrap();
+    p.record_usage(
+        "s1",
+        Some("coder"),
+        "run-3",
+        "mock",
+        "mock-model",
+        10,
+        5,
+        15,
+    )
+    .unwrap();
+
+    let summary = p.usage_summary(None, None).unwrap();
+    assert_eq!(summary.len(), 2);
+    let openai = summary.iter().find(|r| r.provider == "openai").unwrap();
+    assert_eq!(openai.calls, 2);
+    assert_eq!(openai.prompt_tokens, 300);
+    assert_eq!(openai.completion_tokens, 75);
+    assert_eq!(openai.total_tokens, 375);
+
+    let tomorrow = chrono::Utc::now().date_naive() + chrono::Duration::days(1);
+    assert!(p.usage_summary(Some(tomorrow), None).unwrap().is_empty());
+}
+
+#[test]
+fn input_history_is_namespaced_per_session() {
+    let path = temp_db_path();
+    let p = Persistence::new(&path).unwrap();
+
+    p.append_input_history("s1", "first").unwrap();
+    p.append_input_history("s2", "other").unwrap();
+    p.append_input_history("s1", "second").unwrap();
+    p.append_input_history("s1", "third").unwrap();
+
+    assert_eq!(
+        p.list_input_history("s1", 10).unwrap(),
+        vec!["first", "second", "third"]
+    );
+    assert_eq!(
+        p.list_input_history("s1", 2).unwrap(),
+        vec!["second", "third"]
+    );
+    assert_eq!(p.list_input_history("s2", 10).unwrap(), vec!["other"]);
+}
+
+#[test]
+fn session_summaries_include_metadata_and_counts() {
+    let path = temp_db_path();
+    let p = Persistence::new(&path).unwrap();
+
+    p.insert_message("legacy", MessageRole::User, "old question")
+        .unwrap();
+    p.upsert_session("s1", Some("coder"), "openai", Some("gpt-4o"))
+        .unwrap();
+    p.insert_message("s1", MessageRole::User, "first question")
+        .unwrap();
+    p.insert_message("s1", MessageRole::Assistant, "answer")
+        .unwrap();
+    p.log_tool(
+        "s1",
+        "coder",
+        "run-1",
+        "FileTool",
+        &json!({"path": "a.txt"}),
+        &json!({"ok": true}),
+        true,
+        None,
+    )
+    .unwrap();
+    // Re-recording keeps one row and updates the selection
+    p.upsert_session("s1", Some("coder"), "anthropic", None)
+        .unwrap();
+
+    let sessions = p.list_session_summaries(10).unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].session_id, "s1");
+    assert_eq!(sessions[0].provider.as_deref(), Some("anthropic"));
+    assert_eq!(sessions[0].model, None);
+    assert_eq!(sessions[0].title.as_deref(), Some("first question"));
+    assert_eq!(sessions[0].message_count, 2);
+    assert_eq!(sessions[0].tool_call_count, 1);
+
+    // Sessions without metadata still appear
+    assert_eq!(sessions[1].session_id, "legacy");
+    assert_eq!(sessions[1].agent, None);
+    assert_eq!(sessions[1].message_count, 1);
+
+    let calls = p.list_tool_calls("s1", 10).unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].tool_name, "FileTool");
+    assert_eq!(calls[0].arguments, json!({"path": "a.txt"}));
+    assert!(calls[0].error.is_none());
+}